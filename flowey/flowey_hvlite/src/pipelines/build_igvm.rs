@@ -128,6 +128,14 @@ pub struct BuildIgvmCliCustomizations {
     #[clap(long)]
     pub with_mi_secure: bool,
 
+    /// Build openvmm_hcl with the on-demand profiler worker, so that VTL2
+    /// CPU profiles can be captured with `ohcldiag-dev profile`.
+    ///
+    /// The profiler binary itself is not part of this repo, and must be
+    /// added to the initrd, e.g. with `--custom-layer`.
+    #[clap(long)]
+    pub with_profiler: bool,
+
     /// Disable secure AVIC support for SNP. This adds the
     /// `disable_secure_avic` cargo feature and sets `secure_avic` to
     /// `disabled` in the IGVM manifest.
@@ -303,6 +311,7 @@ fn into_pipeline(self, backend_hint: PipelineBackendHint) -> anyhow::Result<Pipe
                     with_perf_tools,
                     with_debuginfo,
                     with_mi_secure,
+                    with_profiler,
                     disable_secure_avic,
                     custom_openvmm_hcl,
                     custom_openhcl_boot,
@@ -438,6 +447,7 @@ fn into_pipeline(self, backend_hint: PipelineBackendHint) -> anyhow::Result<Pipe
                 with_perf_tools,
                 with_debuginfo,
                 with_mi_secure,
+                with_profiler,
                 disable_secure_avic,
                 override_kernel_pkg: override_kernel_pkg.map(|p| match p {
                     KernelPackageKindCli::Main => OpenhclKernelPackage::Main,
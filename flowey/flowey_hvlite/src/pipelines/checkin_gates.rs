@@ -57,6 +57,11 @@ pub struct CheckinGatesCli {
     /// Set custom path to search for / download VMM tests disk-images
     #[clap(long)]
     vmm_tests_disk_cache_dir: Option<PathBuf>,
+
+    /// Prune least-recently-used VMM tests disk-images so that the cache does
+    /// not exceed this size, in GiB
+    #[clap(long)]
+    vmm_tests_disk_cache_quota_gb: Option<u64>,
 }
 
 impl IntoPipeline for CheckinGatesCli {
@@ -65,6 +70,7 @@ fn into_pipeline(self, backend_hint: PipelineBackendHint) -> anyhow::Result<Pipe
             config,
             local_run_args,
             vmm_tests_disk_cache_dir,
+            vmm_tests_disk_cache_quota_gb,
         } = self;
 
         let release = match config {
@@ -1577,10 +1583,11 @@ struct VmmTestJobParams<'a> {
                 }
             });
 
-            if let Some(vmm_tests_disk_cache_dir) = vmm_tests_disk_cache_dir.clone() {
+            if vmm_tests_disk_cache_dir.is_some() || vmm_tests_disk_cache_quota_gb.is_some() {
                 vmm_tests_run_job = vmm_tests_run_job.config(
                     flowey_lib_hvlite::download_openvmm_vmm_tests_artifacts::Config {
-                        custom_cache_dir: Some(vmm_tests_disk_cache_dir),
+                        custom_cache_dir: vmm_tests_disk_cache_dir.clone(),
+                        cache_quota_bytes: vmm_tests_disk_cache_quota_gb.map(|gb| gb << 30),
                         ..Default::default()
                     },
                 );
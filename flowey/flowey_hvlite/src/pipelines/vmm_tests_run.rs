@@ -84,6 +84,11 @@ pub struct VmmTestsRunCli {
     #[clap(long)]
     no_lazy_fetch: bool,
 
+    /// Prune least-recently-used disk images so that the image cache does not
+    /// exceed this size, in GiB
+    #[clap(long)]
+    disk_cache_quota_gb: Option<u64>,
+
     /// Optional: custom kernel modules
     #[clap(long)]
     custom_kernel_modules: Option<PathBuf>,
@@ -156,6 +161,7 @@ fn into_pipeline(self, backend_hint: PipelineBackendHint) -> anyhow::Result<Pipe
             copy_extras,
             skip_vhd_prompt,
             no_lazy_fetch,
+            disk_cache_quota_gb,
             custom_kernel_modules,
             custom_kernel,
             custom_uefi_firmware,
@@ -343,6 +349,7 @@ fn into_pipeline(self, backend_hint: PipelineBackendHint) -> anyhow::Result<Pipe
                     custom_kernel_modules,
                     custom_kernel,
                     skip_vhd_prompt,
+                    disk_cache_quota_bytes: disk_cache_quota_gb.map(|gb| gb << 30),
                     nextest_profile: if ci_profile {
                         flowey_lib_hvlite::run_cargo_nextest_run::NextestProfile::Ci
                     } else {
@@ -657,7 +664,8 @@ fn resolve_artifact(&mut self, id: &str) -> anyhow::Result<()> {
 
             // Guest test UEFI
             petri_artifacts_vmm_test::artifacts::test_vhd::GUEST_TEST_UEFI_X64::GLOBAL_UNIQUE_ID
-            | petri_artifacts_vmm_test::artifacts::test_vhd::GUEST_TEST_UEFI_AARCH64 ::GLOBAL_UNIQUE_ID => {
+            | petri_artifacts_vmm_test::artifacts::test_vhd::GUEST_TEST_UEFI_AARCH64 ::GLOBAL_UNIQUE_ID
+            | petri_artifacts_vmm_test::artifacts::test_vhd::GUEST_TEST_UEFI_EFI_X64::GLOBAL_UNIQUE_ID => {
                 self.build.guest_test_uefi = true;
             }
 
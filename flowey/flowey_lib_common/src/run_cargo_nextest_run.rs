@@ -352,6 +352,8 @@ fn emit(
                         let final_xml = std::env::current_dir()?.join("junit.xml");
                         // copy locally to avoid trashing the output between test runs
                         fs_err::rename(emitted_xml, &final_xml)?;
+                        let xml = fs_err::read_to_string(&final_xml)?;
+                        fs_err::write(&final_xml, add_junit_properties(&xml)?)?;
                         Some(final_xml.absolute()?)
                     } else {
                         None
@@ -387,6 +389,56 @@ fn emit(
     }
 }
 
+/// Adds the `[[PROPERTY|name|value]]` lines that tests print to their output
+/// as JUnit properties of their test cases, since nextest has no other way to
+/// emit them.
+fn add_junit_properties(xml: &str) -> anyhow::Result<String> {
+    let doc = roxmltree::Document::parse(xml)?;
+    let mut insertions = Vec::new();
+    for testcase in doc.descendants().filter(|n| n.has_tag_name("testcase")) {
+        let properties = testcase
+            .children()
+            .filter(|n| n.has_tag_name("system-out"))
+            .filter_map(|n| n.text())
+            .flat_map(str::lines)
+            .filter_map(|line| {
+                line.trim()
+                    .strip_prefix("[[PROPERTY|")?
+                    .strip_suffix("]]")?
+                    .split_once('|')
+            })
+            .map(|(name, value)| {
+                format!(
+                    r#"<property name="{}" value="{}"/>"#,
+                    xml_escape(name),
+                    xml_escape(value)
+                )
+            })
+            .collect::<String>();
+        // Test cases with output always have children, so the properties
+        // become the first child.
+        if let Some(first_child) = testcase.first_child().filter(|_| !properties.is_empty()) {
+            insertions.push((
+                first_child.range().start,
+                format!("<properties>{properties}</properties>"),
+            ));
+        }
+    }
+
+    let mut xml = xml.to_owned();
+    for (offset, properties) in insertions.into_iter().rev() {
+        xml.insert_str(offset, &properties);
+    }
+    Ok(xml)
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
 // FUTURE: this seems like something a proc-macro can help with...
 impl build_params::NextestBuildParams {
     pub fn claim(self, ctx: &mut StepCtx<'_>) -> build_params::NextestBuildParams<VarClaimed> {
@@ -79,6 +79,8 @@ pub struct Params {
 
         /// Skip the interactive VHD download prompt
         pub skip_vhd_prompt: bool,
+        /// Optional: prune cached disk images down to this many bytes
+        pub disk_cache_quota_bytes: Option<u64>,
 
         pub nextest_profile: crate::run_cargo_nextest_run::NextestProfile,
 
@@ -133,6 +135,7 @@ fn process_request(request: Self::Request, ctx: &mut NodeCtx<'_>) -> anyhow::Res
             custom_kernel_modules,
             custom_kernel,
             skip_vhd_prompt,
+            disk_cache_quota_bytes,
             nextest_profile,
             reuse_prepped_vhds,
             disable_secure_avic,
@@ -618,6 +621,7 @@ fn process_request(request: Self::Request, ctx: &mut NodeCtx<'_>) -> anyhow::Res
         ctx.config(crate::download_openvmm_vmm_tests_artifacts::Config {
             custom_cache_dir: Some(vmm_test_artifacts_dir),
             skip_prompt: Some(skip_vhd_prompt),
+            cache_quota_bytes: disk_cache_quota_bytes,
             ..Default::default()
         });
 
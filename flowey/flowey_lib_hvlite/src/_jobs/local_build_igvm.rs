@@ -42,6 +42,7 @@ pub struct Customizations {
     pub with_debuginfo: bool,
     pub with_mi_secure: bool,
     pub with_perf_tools: bool,
+    pub with_profiler: bool,
     pub with_sidecar: bool,
 }
 
@@ -99,6 +100,7 @@ fn process_request(request: Self::Request, ctx: &mut NodeCtx<'_>) -> anyhow::Res
             with_debuginfo,
             with_mi_secure,
             with_perf_tools,
+            with_profiler,
             with_sidecar,
             custom_extra_rootfs,
         } = customizations;
@@ -190,6 +192,10 @@ fn process_request(request: Self::Request, ctx: &mut NodeCtx<'_>) -> anyhow::Res
                 openvmm_hcl_features.insert(OpenvmmHclFeature::MiSecure);
             }
 
+            if with_profiler {
+                openvmm_hcl_features.insert(OpenvmmHclFeature::Profiler);
+            }
+
             if let Some(arch) = override_arch {
                 *target = match arch {
                     CommonArch::X86_64 => CommonTriple::X86_64_LINUX_MUSL,
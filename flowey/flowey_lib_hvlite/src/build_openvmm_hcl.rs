@@ -14,6 +14,7 @@
 pub enum OpenvmmHclFeature {
     Gdb,
     MiSecure,
+    Profiler,
     Tpm,
     LocalOnlyCustom(String),
 }
@@ -130,6 +131,7 @@ fn emit(requests: Vec<Self::Request>, ctx: &mut NodeCtx<'_>) -> anyhow::Result<(
                 .map(|f| match f {
                     OpenvmmHclFeature::Gdb => "gdb".into(),
                     OpenvmmHclFeature::MiSecure => "mi-secure".into(),
+                    OpenvmmHclFeature::Profiler => "profiler".into(),
                     OpenvmmHclFeature::Tpm => "tpm".into(),
                     OpenvmmHclFeature::LocalOnlyCustom(s) => s,
                 })
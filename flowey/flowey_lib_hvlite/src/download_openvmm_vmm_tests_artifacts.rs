@@ -32,6 +32,9 @@ pub struct Config {
         /// Specify a custom cache directory. By default, VHDs are cloned
         /// into a job-local temp directory.
         pub custom_cache_dir: Option<PathBuf>,
+        /// If set, least-recently-used cached disk images are deleted before
+        /// downloading, to keep the cache within this many bytes.
+        pub cache_quota_bytes: Option<u64>,
     }
 }
 
@@ -53,6 +56,7 @@ impl FlowNodeWithConfig for Node {
     fn imports(ctx: &mut ImportCtx<'_>) {
         ctx.import::<flowey_lib_common::download_azcopy::Node>();
         ctx.import::<flowey_lib_common::install_azure_cli::Node>();
+        ctx.import::<crate::prune_vmm_tests_artifacts::Node>();
     }
 
     fn emit(
@@ -82,6 +86,7 @@ fn emit(
         };
         let custom_disk_policy = config.custom_disk_policy;
         let custom_cache_dir = config.custom_cache_dir;
+        let cache_quota_bytes = config.cache_quota_bytes;
 
         let persistent_dir = ctx.persistent_dir();
 
@@ -90,6 +95,15 @@ fn emit(
         let (files_to_download, write_files_to_download) = ctx.new_var::<Vec<(String, u64)>>();
         let (output_folder, write_output_folder) = ctx.new_var();
 
+        let did_prune = cache_quota_bytes.map(|quota_bytes| {
+            ctx.reqv(|done| crate::prune_vmm_tests_artifacts::Request {
+                folder: output_folder.clone(),
+                in_use: test_artifacts.iter().copied().collect(),
+                quota_bytes,
+                done,
+            })
+        });
+
         ctx.emit_rust_step("calculating required VMM tests disk images", |ctx| {
             let persistent_dir = persistent_dir.clone().claim(ctx);
             let test_artifacts = test_artifacts.into_iter().collect::<Vec<_>>();
@@ -266,6 +280,7 @@ fn emit(
         });
 
         let did_download = ctx.emit_rust_step("downloading VMM test disk images", |ctx| {
+            did_prune.claim(ctx);
             let azcopy_bin = azcopy_bin.claim(ctx);
             let files_to_download = files_to_download.claim(ctx);
             let output_folder = output_folder.clone().claim(ctx);
@@ -317,11 +317,9 @@ fn process_request(request: Self::Request, ctx: &mut NodeCtx<'_>) -> anyhow::Res
                 }
 
                 if let Some(guest_test_uefi) = guest_test_uefi {
-                    let crate::build_guest_test_uefi::GuestTestUefiOutput {
-                        efi: _,
-                        pdb: _,
-                        img,
-                    } = rt.read(guest_test_uefi);
+                    let crate::build_guest_test_uefi::GuestTestUefiOutput { efi, pdb: _, img } =
+                        rt.read(guest_test_uefi);
+                    fs_err::copy(efi, test_content_dir.join("guest_test_uefi.efi"))?;
                     fs_err::copy(img, test_content_dir.join("guest_test_uefi.img"))?;
                 }
 
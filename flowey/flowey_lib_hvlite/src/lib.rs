@@ -53,6 +53,7 @@
 pub mod install_git_credential_manager;
 pub mod install_openvmm_rust_build_essential;
 pub mod install_vmm_tests_deps;
+pub mod prune_vmm_tests_artifacts;
 pub mod resolve_openhcl_kernel_package;
 pub mod resolve_openvmm_deps;
 pub mod resolve_openvmm_test_initrd;
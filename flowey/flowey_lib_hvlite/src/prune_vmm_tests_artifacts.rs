@@ -0,0 +1,206 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Prune least-recently-used VMM test artifacts from a cache folder.
+//!
+//! Each time the node runs, the requested artifacts are recorded as used in a
+//! small JSON file alongside the cached images. Cached artifacts that are not
+//! in use are then deleted, oldest first, until the cache fits in the quota.
+//! Files that are not known test artifacts are never touched.
+
+use flowey::node::prelude::*;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::time::SystemTime;
+use vmm_test_images::KnownTestArtifacts;
+
+/// The name of the file recording when each artifact was last used, as
+/// seconds since the Unix epoch.
+const USAGE_FILE: &str = "vmm_tests_artifacts_usage.json";
+
+flowey_request! {
+    pub struct Request {
+        /// Folder containing the cached artifacts
+        pub folder: ReadVar<PathBuf>,
+        /// Artifacts about to be used. These are never pruned, and count
+        /// against the quota even if they have not been downloaded yet.
+        pub in_use: Vec<KnownTestArtifacts>,
+        /// Maximum total size of the cached artifacts, in bytes
+        pub quota_bytes: u64,
+        /// Completion indicator
+        pub done: WriteVar<SideEffect>,
+    }
+}
+
+new_simple_flow_node!(struct Node);
+
+impl SimpleFlowNode for Node {
+    type Request = Request;
+
+    fn imports(_ctx: &mut ImportCtx<'_>) {}
+
+    fn process_request(request: Self::Request, ctx: &mut NodeCtx<'_>) -> anyhow::Result<()> {
+        let Request {
+            folder,
+            in_use,
+            quota_bytes,
+            done,
+        } = request;
+
+        ctx.emit_rust_step("pruning VMM test disk image cache", |ctx| {
+            let folder = folder.claim(ctx);
+            done.claim(ctx);
+            move |rt| {
+                let folder = rt.read(folder);
+                prune(&folder, &in_use.into_iter().collect(), quota_bytes)
+            }
+        });
+
+        Ok(())
+    }
+}
+
+fn prune(
+    folder: &Path,
+    in_use: &BTreeSet<KnownTestArtifacts>,
+    quota_bytes: u64,
+) -> anyhow::Result<()> {
+    let usage_path = folder.join(USAGE_FILE);
+    let mut usage: BTreeMap<String, u64> = match fs_err::read(&usage_path) {
+        Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|err| {
+            log::warn!("ignoring invalid {}: {err}", usage_path.display());
+            BTreeMap::new()
+        }),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+        Err(err) => return Err(err.into()),
+    };
+
+    let secs = |time: SystemTime| {
+        time.duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+    };
+    let now = secs(SystemTime::now());
+    for artifact in in_use {
+        usage.insert(artifact.filename().to_string(), now);
+    }
+
+    // In-use artifacts are counted at their expected size, since they will be
+    // (re-)downloaded if they are missing or inconsistent.
+    let mut total_bytes: u64 = in_use.iter().map(|a| a.file_size()).sum();
+    let mut candidates = Vec::new();
+    for e in fs_err::read_dir(folder)? {
+        let e = e?;
+        if !e.file_type()?.is_file() {
+            continue;
+        }
+        let filename = e.file_name();
+        let Some(filename) = filename.to_str() else {
+            continue;
+        };
+        let Some(artifact) = KnownTestArtifacts::from_filename(filename) else {
+            continue;
+        };
+        if in_use.contains(&artifact) {
+            continue;
+        }
+
+        let metadata = e.metadata()?;
+        // Fall back to the modification time for artifacts that were
+        // downloaded before usage was tracked.
+        let last_used = match usage.get(filename) {
+            Some(&last_used) => last_used,
+            None => secs(metadata.modified()?),
+        };
+        total_bytes += metadata.len();
+        candidates.push((last_used, filename.to_owned(), metadata.len()));
+    }
+
+    candidates.sort();
+    for (_, filename, size) in candidates {
+        if total_bytes <= quota_bytes {
+            break;
+        }
+        log::info!("pruning {filename} ({size} bytes) from VMM test disk image cache");
+        fs_err::remove_file(folder.join(&filename))?;
+        usage.remove(&filename);
+        total_bytes -= size;
+    }
+
+    if total_bytes > quota_bytes {
+        log::warn!(
+            "VMM test disk images in use ({total_bytes} bytes) exceed the cache quota ({quota_bytes} bytes)"
+        );
+    }
+
+    usage.retain(|filename, _| {
+        KnownTestArtifacts::from_filename(filename).is_some_and(|a| in_use.contains(&a))
+            || folder.join(filename).exists()
+    });
+    fs_err::write(&usage_path, serde_json::to_vec_pretty(&usage)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create(folder: &Path, filename: &str, len: usize) {
+        fs_err::write(folder.join(filename), vec![0; len]).unwrap();
+    }
+
+    fn read_usage(folder: &Path) -> BTreeMap<String, u64> {
+        serde_json::from_slice(&fs_err::read(folder.join(USAGE_FILE)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn prunes_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path();
+        let [a, b, c] = [
+            KnownTestArtifacts::Alpine323X64Vhd,
+            KnownTestArtifacts::Alpine323Aarch64Vhd,
+            KnownTestArtifacts::FreeBsd13_2X64Vhd,
+        ]
+        .map(|artifact| artifact.filename());
+        for filename in [a, b, c] {
+            create(folder, filename, 10);
+        }
+        create(folder, "unknown.vhd", 1000);
+        fs_err::write(
+            folder.join(USAGE_FILE),
+            serde_json::to_vec(&BTreeMap::from([(a, 100), (b, 300), (c, 200)])).unwrap(),
+        )
+        .unwrap();
+
+        prune(folder, &BTreeSet::new(), 20).unwrap();
+
+        assert!(!folder.join(a).exists());
+        assert!(folder.join(b).exists());
+        assert!(folder.join(c).exists());
+        assert!(folder.join("unknown.vhd").exists());
+        assert_eq!(
+            read_usage(folder),
+            BTreeMap::from([(b.to_string(), 300), (c.to_string(), 200)])
+        );
+    }
+
+    #[test]
+    fn keeps_in_use_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path();
+        let in_use = KnownTestArtifacts::Alpine323X64Vhd;
+        let unused = KnownTestArtifacts::FreeBsd13_2X64Vhd;
+        create(folder, in_use.filename(), 10);
+        create(folder, unused.filename(), 10);
+
+        // The in-use artifact alone exceeds the quota at its expected size, so
+        // everything else is pruned, but the in-use artifact is kept.
+        prune(folder, &BTreeSet::from([in_use]), 1).unwrap();
+
+        assert!(folder.join(in_use.filename()).exists());
+        assert!(!folder.join(unused.filename()).exists());
+        let usage = read_usage(folder);
+        assert_eq!(usage.keys().collect::<Vec<_>>(), [in_use.filename()]);
+        assert!(usage[in_use.filename()] > 0);
+    }
+}
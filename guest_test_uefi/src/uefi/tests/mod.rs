@@ -3,37 +3,77 @@
 
 use crate::uefi::Splashes;
 use crate::uefi::splash;
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::num::NonZeroU8;
 use uefi::boot;
 use uefi::cstr16;
+use uefi::mem::memory_map::MemoryMap;
+use uefi::mem::memory_map::MemoryType;
 use uefi::println;
+use uefi::proto::loaded_image::LoadedImage;
 use uefi::runtime;
+use uefi::runtime::VariableAttributes;
 use uefi::runtime::VariableVendor;
 
-// TODO: add runtime config for which tests to run (e.g: skipping watchdog)
+/// The tests, in the order they run.
+const TESTS: &[(&str, fn())] = &[
+    ("global_alloc", test_global_alloc),
+    ("memory_map", test_memory_map),
+    ("dbdefault", test_dbdefault),
+    ("os_indications", test_os_indications),
+    // TODO: re-enable when UEFI handles dbDefault correctly
+    //("readonly", test_readonly),
+    // leave the watchdog test for last, since it blows away the VM
+    ("watchdog", test_watchdog),
+];
+
+/// Runs the tests selected on the image's command line.
+///
+/// The command line may contain `tests=<name>[,<name>...]` to run only the
+/// named tests. Otherwise, all tests are run. Results are reported on the
+/// console, one line per event, as `>>>>>> [TEST]: <event> '<name>'`. A
+/// failing test panics, so a test without a `passed` line has failed.
 pub fn run_tests() {
+    let selected = selected_tests();
+    if let Some(selected) = &selected {
+        for name in selected {
+            if !TESTS.iter().any(|&(test, _)| test == name) {
+                panic!("unknown test '{name}'");
+            }
+        }
+    }
+
     println!("running tests...");
     let mut splash_seq = 2u8;
-
-    macro_rules! do_test {
-        ($test_fn:ident) => {{
-            let name = stringify!($test_fn);
-            println!(">>>>>> [TEST]: running '{}'", name);
-            $test_fn();
-            splash_seq = splash_seq.wrapping_shl(1);
-            splash::draw_splash(Splashes(NonZeroU8::new(splash_seq).unwrap()));
-            boot::stall(1000000); // stall for 1 seconds
-        }};
+    for &(name, test_fn) in TESTS {
+        if selected
+            .as_ref()
+            .is_some_and(|selected| !selected.iter().any(|s| s == name))
+        {
+            println!(">>>>>> [TEST]: skipped '{}'", name);
+            continue;
+        }
+        println!(">>>>>> [TEST]: running '{}'", name);
+        test_fn();
+        println!(">>>>>> [TEST]: passed '{}'", name);
+        splash_seq = splash_seq.wrapping_shl(1);
+        if let Some(seq) = NonZeroU8::new(splash_seq) {
+            splash::draw_splash(Splashes(seq));
+        }
+        boot::stall(1000000); // stall for 1 seconds
     }
+    println!(">>>>>> [TEST]: all tests passed");
+}
 
-    do_test!(test_global_alloc);
-
-    do_test!(test_dbdefault);
-    // TODO: re-enable when UEFI handles dbDefault correctly
-    //do_test!(test_readonly);
-
-    // leave the watchdog test for last, since it blows away the VM
-    do_test!(test_watchdog);
+/// Returns the tests named by `tests=` on the image's command line, if any.
+fn selected_tests() -> Option<Vec<String>> {
+    let image = boot::open_protocol_exclusive::<LoadedImage>(boot::image_handle()).ok()?;
+    let options = String::from(image.load_options_as_cstr16().ok()?);
+    let tests = options
+        .split_whitespace()
+        .find_map(|arg| arg.strip_prefix("tests="))?;
+    Some(tests.split(',').map(String::from).collect())
 }
 
 fn test_global_alloc() {
@@ -41,6 +81,33 @@ fn test_global_alloc() {
     println!("look 'ma, i'm outputting a heap allocated string: {}", s);
 }
 
+fn test_memory_map() {
+    let memory_map = boot::memory_map(MemoryType::LOADER_DATA).expect("failed to get memory map");
+    let mut entries: Vec<_> = memory_map.entries().collect();
+    assert!(!entries.is_empty(), "memory map is empty");
+
+    // Each descriptor must describe a non-empty, page-aligned range, and no
+    // two ranges may overlap.
+    entries.sort_by_key(|desc| desc.phys_start);
+    let mut prev_end = 0;
+    for desc in entries {
+        assert!(desc.page_count != 0, "empty memory descriptor: {desc:?}");
+        assert_eq!(
+            desc.phys_start % 4096,
+            0,
+            "unaligned memory descriptor: {desc:?}"
+        );
+        assert!(
+            desc.phys_start >= prev_end,
+            "overlapping memory descriptor: {desc:?}"
+        );
+        prev_end = desc
+            .phys_start
+            .checked_add(desc.page_count * 4096)
+            .expect("memory descriptor overflows");
+    }
+}
+
 fn test_watchdog() {
     boot::set_watchdog_timer(5, 0xdeadbeef, None).unwrap();
     boot::stall(1000000 * 6); // stall for 6 seconds
@@ -58,6 +125,32 @@ fn test_dbdefault() {
     assert_eq!(db_data, dbdefault_data);
 }
 
+fn test_os_indications() {
+    // Write OsIndications without requesting any action, to check that the
+    // variable is accepted with its spec-defined attributes. Requesting
+    // BOOT_TO_FW_UI here would send the watchdog reset into setup.
+    let attributes = VariableAttributes::NON_VOLATILE
+        | VariableAttributes::BOOTSERVICE_ACCESS
+        | VariableAttributes::RUNTIME_ACCESS;
+    let value = 0u64.to_le_bytes();
+    runtime::set_variable(
+        cstr16!("OsIndications"),
+        &VariableVendor::GLOBAL_VARIABLE,
+        attributes,
+        &value,
+    )
+    .expect("failed to set OsIndications");
+
+    let (data, read_attributes) =
+        runtime::get_variable_boxed(cstr16!("OsIndications"), &VariableVendor::GLOBAL_VARIABLE)
+            .expect("OsIndications not found");
+    assert_eq!(*data, value);
+    assert_eq!(read_attributes, attributes);
+
+    runtime::delete_variable(cstr16!("OsIndications"), &VariableVendor::GLOBAL_VARIABLE)
+        .expect("failed to delete OsIndications");
+}
+
 /* TODO: re-enable when UEFI handles dbDefault correctly
 fn test_readonly(rt: &RuntimeServices) {
     match rt.set_variable(
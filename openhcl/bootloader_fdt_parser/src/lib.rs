@@ -696,6 +696,10 @@ fn new_from_raw(raw: &[u8]) -> anyhow::Result<Self> {
 pub struct BootTimes {
     /// Kernel start time.
     pub start: Option<u64>,
+    /// Time VTL2 memory and page table setup finished.
+    pub page_tables_end: Option<u64>,
+    /// Time the bootloader started preparing to enter the kernel.
+    pub kernel_load_start: Option<u64>,
     /// Kernel end time.
     pub end: Option<u64>,
     /// Sidecar start time.
@@ -717,6 +721,8 @@ pub fn new() -> anyhow::Result<Self> {
 
     fn new_from_raw(raw: &[u8]) -> anyhow::Result<Self> {
         let mut start = None;
+        let mut page_tables_end = None;
+        let mut kernel_load_start = None;
         let mut end = None;
         let mut sidecar_start = None;
         let mut sidecar_end = None;
@@ -733,6 +739,14 @@ fn new_from_raw(raw: &[u8]) -> anyhow::Result<Self> {
             start = Some(prop.read_u64(0).map_err(err_to_owned)?);
         }
 
+        if let Some(prop) = try_find_property(&root, "reftime_page_tables_end") {
+            page_tables_end = Some(prop.read_u64(0).map_err(err_to_owned)?);
+        }
+
+        if let Some(prop) = try_find_property(&root, "reftime_kernel_load_start") {
+            kernel_load_start = Some(prop.read_u64(0).map_err(err_to_owned)?);
+        }
+
         if let Some(prop) = try_find_property(&root, "reftime_boot_end") {
             end = Some(prop.read_u64(0).map_err(err_to_owned)?);
         }
@@ -747,6 +761,8 @@ fn new_from_raw(raw: &[u8]) -> anyhow::Result<Self> {
 
         Ok(Self {
             start,
+            page_tables_end,
+            kernel_load_start,
             end,
             sidecar_start,
             sidecar_end,
@@ -1119,6 +1135,8 @@ fn build_boottime_dt(boot_times: BootTimes) -> anyhow::Result<Vec<u8>> {
         let p_size_cells = builder.add_string("#size-cells")?;
         let p_reftime_boot_start = builder.add_string("reftime_boot_start")?;
         let p_reftime_boot_end = builder.add_string("reftime_boot_end")?;
+        let p_reftime_page_tables_end = builder.add_string("reftime_page_tables_end")?;
+        let p_reftime_kernel_load_start = builder.add_string("reftime_kernel_load_start")?;
         let p_reftime_sidecar_start = builder.add_string("reftime_sidecar_start")?;
         let p_reftime_sidecar_end = builder.add_string("reftime_sidecar_end")?;
 
@@ -1131,6 +1149,14 @@ fn build_boottime_dt(boot_times: BootTimes) -> anyhow::Result<Vec<u8>> {
             root_builder = root_builder.add_u64(p_reftime_boot_start, start)?;
         }
 
+        if let Some(time) = boot_times.page_tables_end {
+            root_builder = root_builder.add_u64(p_reftime_page_tables_end, time)?;
+        }
+
+        if let Some(time) = boot_times.kernel_load_start {
+            root_builder = root_builder.add_u64(p_reftime_kernel_load_start, time)?;
+        }
+
         if let Some(end) = boot_times.end {
             root_builder = root_builder.add_u64(p_reftime_boot_end, end)?;
         }
@@ -1152,6 +1178,8 @@ fn build_boottime_dt(boot_times: BootTimes) -> anyhow::Result<Vec<u8>> {
     fn test_basic_boottime() {
         let orig_info = BootTimes {
             start: Some(0x1000),
+            page_tables_end: Some(0x1400),
+            kernel_load_start: Some(0x1800),
             end: Some(0x2000),
             sidecar_start: Some(0x3000),
             sidecar_end: Some(0x4000),
@@ -1165,6 +1193,8 @@ fn test_basic_boottime() {
         // test no boot times.
         let orig_info = BootTimes {
             start: None,
+            page_tables_end: None,
+            kernel_load_start: None,
             end: None,
             sidecar_start: None,
             sidecar_end: None,
@@ -588,11 +588,17 @@ pub async fn kmsg(&self, follow: bool) -> anyhow::Result<KmsgStream> {
         Ok(KmsgStream::new(socket))
     }
 
-    /// Gets the contents of the file
+    /// Gets the contents of the file, stopping after `max_size` bytes if
+    /// specified.
+    ///
+    /// The stream ends silently at the limit. To detect that the file was
+    /// truncated, request one more byte than needed and check whether it
+    /// arrives.
     pub async fn read_file(
         &self,
         follow: bool,
         file_path: String,
+        max_size: Option<u64>,
     ) -> anyhow::Result<PolledSocket<socket2::Socket>> {
         let (conn, socket) = self.connect_data().await?;
 
@@ -604,6 +610,7 @@ pub async fn read_file(
                     follow,
                     conn,
                     file_path,
+                    max_size: max_size.unwrap_or(0),
                 },
             )
             .await
@@ -743,6 +750,45 @@ pub async fn memory_profile_trace(&self, pid: i32) -> anyhow::Result<Vec<u8>> {
         Ok(state.data)
     }
 
+    /// Runs the VTL2 profiler for `duration` and writes the profile it
+    /// streams back to `writer`.
+    ///
+    /// `profiler_args` are passed through to the profiler binary.
+    pub async fn profile(
+        &self,
+        duration: Duration,
+        profiler_args: Vec<String>,
+        mut writer: impl AsyncWrite + Unpin,
+    ) -> anyhow::Result<()> {
+        let (conn, socket) = self.connect_data().await?;
+
+        // The call does not complete until the profile has been taken, so
+        // don't time it out.
+        let call = async {
+            self.ttrpc
+                .call()
+                .timeout(None)
+                .start(
+                    azure_profiler_proto::AzureProfiler::Profile,
+                    azure_profiler_proto::ProfileRequest {
+                        conn,
+                        duration: duration.as_secs(),
+                        profiler_args,
+                    },
+                )
+                .await
+                .map_err(grpc_status)
+        };
+        let copy = async {
+            futures::io::copy(socket, &mut writer)
+                .await
+                .context("failed to read profile")
+        };
+        futures::try_join!(call, copy)?;
+        writer.flush().await?;
+        Ok(())
+    }
+
     /// Restarts the Underhill worker.
     pub async fn restart(&self) -> anyhow::Result<()> {
         self.ttrpc
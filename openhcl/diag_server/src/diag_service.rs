@@ -561,7 +561,7 @@ async fn handle_kmsg(
         driver: &(impl Driver + Spawn + Clone),
         request: &KmsgRequest,
     ) -> anyhow::Result<()> {
-        self.handle_read_file_request(driver, request.conn, request.follow, "/dev/kmsg")
+        self.handle_read_file_request(driver, request.conn, request.follow, "/dev/kmsg", None)
             .await
     }
 
@@ -570,8 +570,14 @@ async fn handle_read_file(
         driver: &(impl Driver + Spawn + Clone),
         request: &FileRequest,
     ) -> anyhow::Result<()> {
-        self.handle_read_file_request(driver, request.conn, request.follow, &request.file_path)
-            .await
+        self.handle_read_file_request(
+            driver,
+            request.conn,
+            request.follow,
+            &request.file_path,
+            (request.max_size != 0).then_some(request.max_size),
+        )
+        .await
     }
 
     async fn handle_packet_capture(
@@ -656,6 +662,7 @@ async fn handle_read_file_request(
         conn: u64,
         follow: bool,
         file_path: &str,
+        max_size: Option<u64>,
     ) -> anyhow::Result<()> {
         let mut conn = self.take_connection(conn).await?;
         let file = fs_err::File::open(file_path).context("failed to open file")?;
@@ -668,7 +675,7 @@ async fn handle_read_file_request(
 
             driver
                 .spawn("read file relay", async move {
-                    if let Err(err) = relay_read_file(file, conn, follow).await {
+                    if let Err(err) = relay_read_file(file, conn, follow, max_size).await {
                         tracing::warn!(
                             error = &*err as &dyn std::error::Error,
                             "read file relay failed"
@@ -685,9 +692,8 @@ async fn handle_read_file_request(
                     //
                     // (If this becomes a problem, we can spawn a thread to do
                     // this, or use io-uring.)
-                    if let Err(err) =
-                        futures::io::copy(AllowStdIo::new(File::from(file)), &mut conn).await
-                    {
+                    let file = AllowStdIo::new(File::from(file)).take(max_size.unwrap_or(u64::MAX));
+                    if let Err(err) = futures::io::copy(file, &mut conn).await {
                         tracing::warn!(
                             error = &err as &dyn std::error::Error,
                             "read file relay failed"
@@ -802,8 +808,10 @@ async fn relay_read_file(
     mut file: PolledPipe,
     mut conn: PolledSocket<Socket>,
     follow: bool,
+    max_size: Option<u64>,
 ) -> anyhow::Result<()> {
     let mut buffer = [0; FILE_LINE_MAX];
+    let mut remaining = max_size.unwrap_or(u64::MAX);
     loop {
         let n = if follow {
             futures::select! { // race semantics
@@ -840,10 +848,16 @@ async fn relay_read_file(
         );
         // Add a null terminator.
         buffer[n] = 0;
-        // Write the message followed by a null terminator.
-        conn.write_all(&buffer[..n + 1])
+        // Write the message followed by a null terminator, cut off at the size
+        // limit so that the client can tell whether anything was dropped.
+        let len = (n as u64 + 1).min(remaining) as usize;
+        conn.write_all(&buffer[..len])
             .await
             .context("socket write failed")?;
+        remaining -= len as u64;
+        if remaining == 0 {
+            break;
+        }
     }
     Ok(())
 }
@@ -4,6 +4,7 @@
 //! Interface to `mshv_vtl` driver.
 
 mod deferred;
+mod page_pool;
 pub mod register;
 
 pub mod aarch64;
@@ -14,6 +15,7 @@
 
 use self::deferred::DeferredActionSlots;
 use self::ioctls::*;
+pub use self::page_pool::HvcallPagePoolStats;
 use crate::GuestVtl;
 use crate::ioctl::deferred::DeferredAction;
 use crate::ioctl::register::GetRegError;
@@ -324,6 +326,7 @@ struct PinUnpinError {
 }
 
 /// Result of translate gva hypercall from [`Hcl`]
+#[derive(Debug, Clone)]
 pub struct TranslateResult {
     /// The GPA that the GVA translated to.
     pub gpa_page: u64,
@@ -1118,15 +1121,13 @@ unsafe fn hvcall_rep<InputHeader, InputRep, O>(
         InputRep: IntoBytes + Sized + Immutable + KnownLayout,
         O: IntoBytes + FromBytes + Sized + Immutable + KnownLayout,
     {
-        // Construct input buffer.
-        let (input, count) = match input_rep {
-            HvcallRepInput::Elements(e) => {
-                ([input_header.as_bytes(), e.as_bytes()].concat(), e.len())
-            }
-            HvcallRepInput::Count(c) => (input_header.as_bytes().to_vec(), c.into()),
+        // Gather the input, to be assembled in a pooled input page.
+        let (input, count): ([&[u8]; 2], _) = match input_rep {
+            HvcallRepInput::Elements(e) => ([input_header.as_bytes(), e.as_bytes()], e.len()),
+            HvcallRepInput::Count(c) => ([input_header.as_bytes(), &[]], c.into()),
         };
 
-        if input.len() > HV_PAGE_SIZE as usize {
+        if input.iter().map(|x| x.len()).sum::<usize>() > HV_PAGE_SIZE as usize {
             return Err(HvcallError::InputParametersTooLarge);
         }
 
@@ -1152,17 +1153,20 @@ unsafe fn hvcall_rep<InputHeader, InputRep, O>(
             .with_code(code.0)
             .with_rep_count(count);
 
-        let call_object = protocol::hcl_hvcall {
-            control,
-            input_data: input.as_ptr().cast(),
-            input_size: input.len(),
-            status: HypercallOutput::new(),
-            output_data,
-            output_size,
-        };
+        page_pool::with_input(&input, |input| {
+            let call_object = protocol::hcl_hvcall {
+                control,
+                input_data: input.as_ptr().cast(),
+                input_size: input.len(),
+                status: HypercallOutput::new(),
+                output_data,
+                output_size,
+            };
 
-        // SAFETY: The data referenced in the call lives as long as `self` does.
-        unsafe { self.invoke_hvcall_ioctl(call_object) }
+            // SAFETY: The data referenced in the call lives for the duration
+            // of the call.
+            unsafe { self.invoke_hvcall_ioctl(call_object) }
+        })
     }
 
     /// Issues a non-rep hypercall with variable input to the hypervisor via the direct hypercall kernel interface.
@@ -1207,8 +1211,8 @@ const fn assert_size<I, O>()
         assert_size::<I, O>();
         assert!(variable_input.len().is_multiple_of(8));
 
-        let input = [input.as_bytes(), variable_input].concat();
-        if input.len() > HV_PAGE_SIZE as usize {
+        let input = [input.as_bytes(), variable_input];
+        if input.iter().map(|x| x.len()).sum::<usize>() > HV_PAGE_SIZE as usize {
             return Err(HvcallError::InputParametersTooLarge);
         }
 
@@ -1216,17 +1220,20 @@ const fn assert_size<I, O>()
             .with_code(code.0)
             .with_variable_header_size(variable_input.len() / 8);
 
-        let call_object = protocol::hcl_hvcall {
-            control,
-            input_data: input.as_bytes().as_ptr().cast(),
-            input_size: input.len(),
-            status: FromZeros::new_zeroed(),
-            output_data: output.as_bytes().as_ptr().cast(),
-            output_size: size_of::<O>(),
-        };
+        page_pool::with_input(&input, |input| {
+            let call_object = protocol::hcl_hvcall {
+                control,
+                input_data: input.as_ptr().cast(),
+                input_size: input.len(),
+                status: FromZeros::new_zeroed(),
+                output_data: output.as_bytes().as_ptr().cast(),
+                output_size: size_of::<O>(),
+            };
 
-        // SAFETY: The data referenced in the call lives as long as `self` does.
-        unsafe { self.invoke_hvcall_ioctl(call_object) }
+            // SAFETY: The data referenced in the call lives for the duration
+            // of the call.
+            unsafe { self.invoke_hvcall_ioctl(call_object) }
+        })
     }
 
     /// Sets the VTL protection mask for the specified memory range.
@@ -1978,6 +1985,12 @@ pub fn sidecar_enabled(&self) -> bool {
         self.sidecar.is_some()
     }
 
+    /// Returns usage statistics for the pooled hypercall input pages used by
+    /// rep and variable-size hypercalls.
+    pub fn hvcall_page_pool_stats(&self) -> HvcallPagePoolStats {
+        page_pool::stats()
+    }
+
     /// Create a VP runner for the given partition.
     pub fn runner<'a, T: Backing<'a>>(
         &'a self,
@@ -131,6 +131,12 @@ pub fn translate_gva_to_gpa(
     }
 }
 
+/// Returns true if `name` is one of the SIMD/FP registers (Q0-Q31), which are
+/// stored in the CPU context.
+fn is_simd_reg(name: HvArm64RegisterName) -> bool {
+    (HvArm64RegisterName::Q0.0..=HvArm64RegisterName::Q31.0).contains(&name.0)
+}
+
 impl<'a> super::BackingPrivate<'a> for MshvArm64<'a> {
     fn new(vp: &'a HclVp, sidecar: Option<&SidecarVp<'_>>, _hcl: &Hcl) -> Result<Self, NoRunner> {
         assert!(sidecar.is_none());
@@ -193,6 +199,11 @@ fn try_set_reg(
                 runner.cpu_context_mut().x[18] = value.as_u64();
                 false
             }
+            reg if is_simd_reg(reg) => {
+                runner.cpu_context_mut().q[(reg.0 - HvArm64RegisterName::Q0.0) as usize] =
+                    value.as_u128();
+                true
+            }
             _ => false,
         };
         if set {
@@ -232,8 +243,12 @@ fn try_set_reg(
         false
     }
 
-    fn must_flush_regs_on(_runner: &ProcessorRunner<'a, Self>, _name: HvRegisterName) -> bool {
-        false
+    fn must_flush_regs_on(runner: &ProcessorRunner<'a, Self>, name: HvRegisterName) -> bool {
+        // Updating PSTATE must be ordered with other registers in a batch,
+        // since its interrupt mask bits may affect the validity of other
+        // interrupt-related registers.
+        matches!(HvArm64RegisterName::from(name), HvArm64RegisterName::Cpsr)
+            && runner.reg_page().is_some()
     }
 
     fn try_get_reg(
@@ -276,6 +291,9 @@ fn try_get_reg(
             | HvArm64RegisterName::XLr => {
                 Some(runner.cpu_context().x[(name.0 - HvArm64RegisterName::X0.0) as usize].into())
             }
+            reg if is_simd_reg(reg) => {
+                Some(runner.cpu_context().q[(reg.0 - HvArm64RegisterName::Q0.0) as usize].into())
+            }
             _ => None,
         };
         if value.is_some() {
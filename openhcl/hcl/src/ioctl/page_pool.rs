@@ -0,0 +1,75 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Pooled hypercall input pages.
+//!
+//! Rep and variable-size hypercalls need their input header and elements
+//! assembled into one contiguous buffer. Instead of allocating a new buffer for
+//! every call, each thread keeps a page-sized buffer that is reused across
+//! calls. Since each VP runs on its own thread, this is effectively a per-VP
+//! pool, and it requires no synchronization.
+
+use hvdef::HV_PAGE_SIZE;
+use inspect::Inspect;
+use std::cell::Cell;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+#[repr(C, align(4096))]
+struct InputPage([u8; HV_PAGE_SIZE as usize]);
+
+thread_local! {
+    static INPUT_PAGE: Cell<Option<Box<InputPage>>> = const { Cell::new(None) };
+}
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static REUSES: AtomicU64 = AtomicU64::new(0);
+
+/// Usage statistics for the pooled hypercall input pages, across all threads.
+#[derive(Debug, Copy, Clone, Inspect)]
+pub struct HvcallPagePoolStats {
+    /// The number of input pages that had to be allocated.
+    pub allocations: u64,
+    /// The number of hypercalls that reused an existing input page.
+    pub reuses: u64,
+}
+
+/// Returns the current pool statistics.
+pub(super) fn stats() -> HvcallPagePoolStats {
+    HvcallPagePoolStats {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        reuses: REUSES.load(Ordering::Relaxed),
+    }
+}
+
+/// Concatenates `parts` into this thread's input page and calls `f` with the
+/// assembled input.
+///
+/// Panics if the combined length of `parts` exceeds a page; callers must
+/// check this first.
+pub(super) fn with_input<R>(parts: &[&[u8]], f: impl FnOnce(&[u8]) -> R) -> R {
+    // Take the page out of the slot so that a nested call (which should not
+    // happen, but is not unsafe) just allocates a page of its own.
+    let mut page = match INPUT_PAGE.take() {
+        Some(page) => {
+            REUSES.fetch_add(1, Ordering::Relaxed);
+            page
+        }
+        None => {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            Box::new(InputPage([0; HV_PAGE_SIZE as usize]))
+        }
+    };
+
+    // Only the assembled bytes are passed to the hypervisor, so there is no
+    // need to zero the rest of the page.
+    let mut len = 0;
+    for part in parts {
+        page.0[len..len + part.len()].copy_from_slice(part);
+        len += part.len();
+    }
+
+    let r = f(&page.0[..len]);
+    INPUT_PAGE.set(Some(page));
+    r
+}
@@ -14,6 +14,7 @@
 use super::ioctls::mshv_vp_registers;
 use crate::GuestVtl;
 use arrayvec::ArrayVec;
+use hvdef::HV_PAGE_SIZE;
 use hvdef::HV_PARTITION_ID_SELF;
 use hvdef::HV_VP_INDEX_SELF;
 use hvdef::HvError;
@@ -31,6 +32,28 @@
 #[cfg(guest_arch = "aarch64")]
 type HvArchRegisterName = hvdef::HvArm64RegisterName;
 
+/// The number of registers batched into a single hypercall by the regular
+/// get/set paths, which keep their staging buffers small since they are used
+/// on the exit fast path.
+const MAX_REGS_PER_HVCALL: usize = 32;
+
+/// The maximum number of registers that can be retrieved by a single
+/// HvCallGetVpRegisters hypercall, limited by the size of the hypercall
+/// input and output pages.
+const MAX_GET_REGS_PER_PAGE: usize = {
+    let input = (HV_PAGE_SIZE as usize - size_of::<hvdef::hypercall::GetSetVpRegisters>())
+        / size_of::<HvArchRegisterName>();
+    let output = HV_PAGE_SIZE as usize / size_of::<HvRegisterValue>();
+    if input < output { input } else { output }
+};
+
+/// The maximum number of registers that can be set by a single
+/// HvCallSetVpRegisters hypercall, limited by the size of the hypercall input
+/// page.
+const MAX_SET_REGS_PER_PAGE: usize = (HV_PAGE_SIZE as usize
+    - size_of::<hvdef::hypercall::GetSetVpRegisters>())
+    / size_of::<HvRegisterAssoc>();
+
 #[derive(Error, Debug)]
 #[expect(missing_docs)]
 pub enum GetRegError {
@@ -61,7 +84,7 @@ pub fn get_vp_register(
         name: HvArchRegisterName,
     ) -> Result<HvRegisterValue, GetRegError> {
         let mut value = [FromZeros::new_zeroed(); 1];
-        self.get_regs(vtl.into(), &[name], &mut value)?;
+        self.get_regs::<MAX_REGS_PER_HVCALL>(vtl.into(), &[name], &mut value)?;
         Ok(value[0])
     }
 
@@ -85,7 +108,27 @@ pub fn get_vp_registers(
         names: &[HvArchRegisterName],
         values: &mut [HvRegisterValue],
     ) -> Result<(), GetRegError> {
-        self.get_regs(vtl.into(), names, values)
+        self.get_regs::<MAX_REGS_PER_HVCALL>(vtl.into(), names, values)
+    }
+
+    /// Get an arbitrarily long list of registers on the current VP for the
+    /// given VTL.
+    ///
+    /// Registers that are not available via the register page or CPU context
+    /// and are not managed by the kernel are coalesced into as few
+    /// HvCallGetVpRegisters hypercalls as possible, splitting only when the
+    /// hypercall page is full. Prefer this over [`Self::get_vp_registers`]
+    /// for large register lists, such as when saving VP state.
+    ///
+    /// # Panics
+    /// Panics if `names.len() != values.len()`.
+    pub fn get_vp_registers_batch(
+        &mut self,
+        vtl: GuestVtl,
+        names: &[HvArchRegisterName],
+        values: &mut [HvRegisterValue],
+    ) -> Result<(), GetRegError> {
+        self.get_regs::<MAX_GET_REGS_PER_PAGE>(vtl.into(), names, values)
     }
 
     /// Get the given register on the VP for VTL 2 via hypercall.
@@ -102,7 +145,7 @@ pub fn get_vp_vtl2_register(
         // Go through get_regs to ensure proper sidecar handling, even though
         // we know this will never end up calling the ioctl.
         let mut value = [FromZeros::new_zeroed(); 1];
-        self.get_regs(Vtl::Vtl2, &[name], &mut value)?;
+        self.get_regs::<MAX_REGS_PER_HVCALL>(Vtl::Vtl2, &[name], &mut value)?;
         Ok(value[0])
     }
 
@@ -115,9 +158,29 @@ pub fn set_vp_registers<I>(&mut self, vtl: GuestVtl, regs: I) -> Result<(), SetR
         self.set_regs(vtl.into(), regs)
     }
 
+    /// Set an arbitrarily long list of registers on the current VP for the
+    /// given VTL.
+    ///
+    /// Registers that cannot be set via the register page or CPU context and
+    /// are not managed by the kernel are coalesced into as few
+    /// HvCallSetVpRegisters hypercalls as possible, splitting only when the
+    /// hypercall page is full. Prefer this over [`Self::set_vp_registers`]
+    /// for large register lists, such as when restoring VP state.
+    pub fn set_vp_registers_batch<I>(&mut self, vtl: GuestVtl, regs: I) -> Result<(), SetRegError>
+    where
+        I: IntoIterator,
+        I::Item: Into<HvRegisterAssoc>,
+    {
+        self.set_regs_nongeneric::<MAX_SET_REGS_PER_PAGE>(
+            vtl.into(),
+            &mut regs.into_iter().map(Into::into),
+        )
+    }
+
     /// Get the given registers on the current VP for the given VTL via
-    /// ioctl/hypercall, as appropriate.
-    fn get_regs(
+    /// ioctl/hypercall, as appropriate, issuing at most `MAX_PER_HVCALL`
+    /// registers per hypercall.
+    fn get_regs<const MAX_PER_HVCALL: usize>(
         &mut self,
         vtl: Vtl,
         names: &[HvArchRegisterName],
@@ -131,13 +194,13 @@ fn get_regs(
                 .map_err(GetRegError::Sidecar);
         }
 
-        const MAX_REGS_PER_HVCALL: usize = 32;
-        let mut hv_names: ArrayVec<_, MAX_REGS_PER_HVCALL> = ArrayVec::new();
-        let mut hv_values: ArrayVec<_, MAX_REGS_PER_HVCALL> = ArrayVec::new();
+        const { assert!(MAX_PER_HVCALL <= MAX_GET_REGS_PER_PAGE) };
+        let mut hv_names: ArrayVec<_, MAX_PER_HVCALL> = ArrayVec::new();
+        let mut hv_values: ArrayVec<_, MAX_PER_HVCALL> = ArrayVec::new();
 
         let do_hvcall =
             |hv_names: &mut ArrayVec<_, _>, hv_values: &mut ArrayVec<&mut HvRegisterValue, _>| {
-                let mut values: ArrayVec<_, MAX_REGS_PER_HVCALL> = ArrayVec::from_iter(
+                let mut values: ArrayVec<_, MAX_PER_HVCALL> = ArrayVec::from_iter(
                     std::iter::repeat_n(FromZeros::new_zeroed(), hv_names.len()),
                 );
                 self.hcl
@@ -206,12 +269,13 @@ fn set_regs<I>(&mut self, vtl: Vtl, regs: I) -> Result<(), SetRegError>
         I: IntoIterator,
         I::Item: Into<HvRegisterAssoc>,
     {
-        self.set_regs_nongeneric(vtl, &mut regs.into_iter().map(Into::into))
+        self.set_regs_nongeneric::<MAX_REGS_PER_HVCALL>(vtl, &mut regs.into_iter().map(Into::into))
     }
 
     /// Set the given registers on the current VP for the given VTL via
-    /// ioctl/hypercall, as appropriate.
-    fn set_regs_nongeneric(
+    /// ioctl/hypercall, as appropriate, issuing at most `MAX_PER_HVCALL`
+    /// registers per hypercall.
+    fn set_regs_nongeneric<const MAX_PER_HVCALL: usize>(
         &mut self,
         vtl: Vtl,
         regs: &mut dyn Iterator<Item = HvRegisterAssoc>,
@@ -224,8 +288,8 @@ fn set_regs_nongeneric(
                 .map_err(SetRegError::Sidecar);
         }
 
-        const MAX_REGS_PER_HVCALL: usize = 32;
-        let mut hv_regs: ArrayVec<_, MAX_REGS_PER_HVCALL> = ArrayVec::new();
+        const { assert!(MAX_PER_HVCALL <= MAX_SET_REGS_PER_PAGE) };
+        let mut hv_regs: ArrayVec<_, MAX_PER_HVCALL> = ArrayVec::new();
 
         let do_hvcall = |hv_regs: &mut ArrayVec<_, _>| {
             self.hcl
@@ -22,6 +22,7 @@
 use memory_range::MemoryRange;
 use sidecar_client::SidecarVp;
 use std::cell::UnsafeCell;
+use std::ops::RangeInclusive;
 use std::os::fd::AsRawFd;
 use tdcall::Tdcall;
 use tdcall::tdcall_vp_invgla;
@@ -352,25 +353,42 @@ pub fn read_vmcs16(&self, vtl: GuestVtl, field: VmcsField) -> u16 {
     ///
     /// Panics if there is an error in the TDX module when writing the bit.
     pub fn set_msr_bit(&self, vtl: GuestVtl, msr_index: u32, write: bool, intercept: bool) {
-        let mut word_index = (msr_index & 0xFFFF) / 64;
+        self.set_msr_bits(vtl, msr_index..=msr_index, write, intercept);
+    }
 
-        if msr_index & 0x80000000 == 0x80000000 {
-            assert!((0xC0000000..=0xC0001FFF).contains(&msr_index));
-            word_index += 0x80;
-        } else {
-            assert!(msr_index <= 0x00001FFF);
+    /// Sets the MSR bitmap intercept bits for the MSR indexes in `msrs`.
+    ///
+    /// The bitmap is per VP and per VTL, so this only changes whether
+    /// accesses by this VP in `vtl` exit to VTL2. It can be used at runtime to
+    /// temporarily intercept accesses to MSRs that are otherwise passed
+    /// through.
+    ///
+    /// Panics if the range spans MSRs that are not covered by the bitmap, or
+    /// if there is an error in the TDX module when writing the bits.
+    pub fn set_msr_bits(
+        &self,
+        vtl: GuestVtl,
+        msrs: RangeInclusive<u32>,
+        write: bool,
+        intercept: bool,
+    ) {
+        let word = if intercept { !0 } else { 0 };
+        let mut pending: Option<(u32, u64)> = None;
+        for msr_index in msrs {
+            let word_index = msr_bitmap_word_index(msr_index, write);
+            let bit = 1 << (msr_index as u64 & 0x3F);
+            match &mut pending {
+                Some((index, mask)) if *index == word_index => *mask |= bit,
+                _ => {
+                    if let Some((index, mask)) = pending.replace((word_index, bit)) {
+                        self.write_msr_bitmap(vtl, index, mask, word);
+                    }
+                }
+            }
         }
-
-        if write {
-            word_index += 0x100;
+        if let Some((index, mask)) = pending {
+            self.write_msr_bitmap(vtl, index, mask, word);
         }
-
-        self.write_msr_bitmap(
-            vtl,
-            word_index,
-            1 << (msr_index as u64 & 0x3F),
-            if intercept { !0 } else { 0 },
-        );
     }
 
     /// Writes 64-bit word with index `i` of the MSR bitmap.
@@ -619,3 +637,35 @@ fn tdcall(&mut self, input: tdcall::TdcallInput) -> tdcall::TdcallOutput {
         }
     }
 }
+
+/// Returns the index of the MSR bitmap word containing the read or write
+/// intercept bit for `msr_index`.
+fn msr_bitmap_word_index(msr_index: u32, write: bool) -> u32 {
+    let mut word_index = (msr_index & 0xFFFF) / 64;
+
+    if msr_index & 0x80000000 == 0x80000000 {
+        assert!((0xC0000000..=0xC0001FFF).contains(&msr_index));
+        word_index += 0x80;
+    } else {
+        assert!(msr_index <= 0x00001FFF);
+    }
+
+    if write {
+        word_index += 0x100;
+    }
+    word_index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::msr_bitmap_word_index;
+
+    #[test]
+    fn msr_bitmap_layout() {
+        // Low MSR reads, high MSR reads, low MSR writes, high MSR writes.
+        assert_eq!(msr_bitmap_word_index(0x6a4, false), 0x1a);
+        assert_eq!(msr_bitmap_word_index(0xc000_0080, false), 0x82);
+        assert_eq!(msr_bitmap_word_index(0x6a4, true), 0x11a);
+        assert_eq!(msr_bitmap_word_index(0xc000_0080, true), 0x182);
+    }
+}
@@ -27,7 +27,7 @@
 
 /// Result when the translate gva hypercall returns a code indicating
 /// the translation was unsuccessful.
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 #[error("translate gva to gpa returned non-successful code {code:?}")]
 pub struct TranslateErrorX64 {
     /// The code returned by the translate gva hypercall.
@@ -111,13 +111,69 @@ pub fn translate_gva_to_gpa(
         gva: u64,
         control_flags: hvdef::hypercall::TranslateGvaControlFlagsX64,
     ) -> Result<Result<TranslateResult, TranslateErrorX64>, TranslateGvaToGpaError> {
-        use hvdef::hypercall;
+        assert!(
+            control_flags.input_vtl().use_target_vtl(),
+            "did not specify a target VTL"
+        );
 
+        let output = self.translate_gvn(gva, control_flags)?;
+        Ok(translate_output_to_result(&output))
+    }
+
+    /// Translate a batch of gvas to gpa pages in the context of the current
+    /// VP, returning one result per input gva in the same order.
+    ///
+    /// Each distinct page in the batch is translated once, and gvas on the
+    /// same page share its result. HvCallTranslateVirtualAddressEx has no rep
+    /// form, so each distinct page still costs one hypercall (or sidecar
+    /// request).
+    ///
+    /// Fails the entire batch if the hypervisor or sidecar returns an error
+    /// for any entry. Per-entry translation failures are reported in the
+    /// returned vector.
+    ///
+    /// The caller must ensure `control_flags.input_vtl()` is set to a specific
+    /// VTL.
+    pub fn translate_gvas_to_gpas(
+        &mut self,
+        gvas: &[u64],
+        control_flags: hvdef::hypercall::TranslateGvaControlFlagsX64,
+    ) -> Result<Vec<Result<TranslateResult, TranslateErrorX64>>, TranslateGvaToGpaError> {
         assert!(
             control_flags.input_vtl().use_target_vtl(),
             "did not specify a target VTL"
         );
 
+        let mut gvns: Vec<u64> = gvas.iter().map(|gva| gva >> hvdef::HV_PAGE_SHIFT).collect();
+        gvns.sort_unstable();
+        gvns.dedup();
+
+        let translations = gvns
+            .iter()
+            .map(|&gvn| {
+                let output = self.translate_gvn(gvn << hvdef::HV_PAGE_SHIFT, control_flags)?;
+                Ok(translate_output_to_result(&output))
+            })
+            .collect::<Result<Vec<_>, TranslateGvaToGpaError>>()?;
+
+        Ok(gvas
+            .iter()
+            .map(|gva| {
+                let i = gvns.binary_search(&(gva >> hvdef::HV_PAGE_SHIFT)).unwrap();
+                translations[i].clone()
+            })
+            .collect())
+    }
+
+    /// Issues a single translation request for the page containing `gva`,
+    /// via the sidecar if this VP is remote.
+    fn translate_gvn(
+        &mut self,
+        gva: u64,
+        control_flags: hvdef::hypercall::TranslateGvaControlFlagsX64,
+    ) -> Result<hvdef::hypercall::TranslateVirtualAddressExOutputX64, TranslateGvaToGpaError> {
+        use hvdef::hypercall;
+
         let gvn = gva >> hvdef::HV_PAGE_SHIFT;
         let output = if let Some(sidecar) = &mut self.sidecar {
             sidecar
@@ -153,19 +209,24 @@ pub fn translate_gva_to_gpa(
 
             output
         };
+        Ok(output)
+    }
+}
 
-        // Note: WHP doesn't currently support TranslateVirtualAddressEx, so overlay_page, cache_type,
-        // event_info aren't trustworthy values if the results came from WHP.
-        match output.translation_result.result.result_code() {
-            c if c == hypercall::TranslateGvaResultCode::SUCCESS.0 => Ok(Ok(TranslateResult {
-                gpa_page: output.gpa_page,
-                overlay_page: output.translation_result.result.overlay_page(),
-            })),
-            x => Ok(Err(TranslateErrorX64 {
-                code: x,
-                event_info: output.translation_result.event_info,
-            })),
-        }
+fn translate_output_to_result(
+    output: &hvdef::hypercall::TranslateVirtualAddressExOutputX64,
+) -> Result<TranslateResult, TranslateErrorX64> {
+    // Note: WHP doesn't currently support TranslateVirtualAddressEx, so overlay_page, cache_type,
+    // event_info aren't trustworthy values if the results came from WHP.
+    match output.translation_result.result.result_code() {
+        c if c == hvdef::hypercall::TranslateGvaResultCode::SUCCESS.0 => Ok(TranslateResult {
+            gpa_page: output.gpa_page,
+            overlay_page: output.translation_result.result.overlay_page(),
+        }),
+        x => Err(TranslateErrorX64 {
+            code: x,
+            event_info: output.translation_result.event_info,
+        }),
     }
 }
 
@@ -16,6 +16,9 @@
 use clap::Subcommand;
 use diag_client::DiagClient;
 use diag_client::PacketCaptureOperation;
+use futures::AsyncRead;
+use futures::AsyncReadExt;
+use futures::AsyncWrite;
 use futures::StreamExt;
 use futures::io::AllowStdIo;
 use futures_concurrency::future::Race;
@@ -158,13 +161,22 @@ enum Command {
         #[clap(long, requires = "serial")]
         pipe_path: Option<String>,
     },
-    /// Writes the contents of the file.
+    /// Writes the contents of a file from the VTL2 filesystem.
+    ///
+    /// This can be used to pull files such as `/proc` snapshots, core files,
+    /// or collected traces out of VTL2.
     File {
         /// Keep waiting for and writing new data as its logged.
         #[clap(short, long)]
         follow: bool,
         #[clap(short('p'), long)]
         file_path: String,
+        /// The output file. Defaults to stdout.
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+        /// The maximum number of bytes to transfer.
+        #[clap(long)]
+        max_size: Option<u64>,
     },
     /// Starts GDB server on stdio.
     ///
@@ -264,6 +276,8 @@ enum Command {
     Pause,
     /// Resume the VM
     Resume,
+    /// Shows when each VTL2 boot phase was reached, as a waterfall.
+    BootTimes,
     /// Dumps the VM's VTL2 state without servicing or tearing down Underhill.
     DumpSavedState {
         /// The output file. Defaults to stdout.
@@ -294,6 +308,20 @@ enum Command {
         #[clap(short)]
         output: Option<PathBuf>,
     },
+    /// Captures a CPU sampling profile of VTL2.
+    ///
+    /// This requires a paravisor built with profiler support.
+    Profile {
+        /// Number of seconds to profile for.
+        #[clap(short, long, default_value = "10", value_parser = |arg: &str| -> Result<Duration, std::num::ParseIntError> {Ok(Duration::from_secs(arg.parse()?))})]
+        duration: Duration,
+        /// Additional argument to pass to the profiler. May be repeated.
+        #[clap(long = "arg")]
+        profiler_args: Vec<String>,
+        /// Destination file path. If omitted, the data is written to the standard
+        /// output unless it is a terminal. In that case, an error is returned.
+        dst: Option<PathBuf>,
+    },
     /// Processes EFI diagnostics from guest memory and outputs the logs.
     ///
     /// The log level filter controls which UEFI log entries are emitted.
@@ -727,12 +755,20 @@ pub fn main() -> anyhow::Result<()> {
                     break;
                 }
             }
-            Command::File { follow, file_path } => {
+            Command::File {
+                follow,
+                file_path,
+                output,
+                max_size,
+            } => {
                 let client = new_client(driver.clone(), &vm)?;
-                let stream = client.read_file(follow, file_path).await?;
-                futures::io::copy(stream, &mut AllowStdIo::new(term::raw_stdout()))
-                    .await
-                    .context("failed to copy trace file")?;
+                let file = create_or_stderr(&output)?;
+                // Request an extra byte to find out whether the file was
+                // truncated.
+                let stream = client
+                    .read_file(follow, file_path, max_size.map(|n| n.saturating_add(1)))
+                    .await?;
+                copy_limited(stream, AllowStdIo::new(file), max_size).await?;
             }
             Command::Gdbserver { multi, pid } => {
                 let client = new_client(driver.clone(), &vm)?;
@@ -867,7 +903,7 @@ pub fn main() -> anyhow::Result<()> {
 
                 let file = create_or_stderr(&output)?;
                 let stream = client
-                    .read_file(false, "underhill.perfetto".to_owned())
+                    .read_file(false, "underhill.perfetto".to_owned(), None)
                     .await
                     .context("failed to read trace file")?;
 
@@ -939,6 +975,15 @@ pub fn main() -> anyhow::Result<()> {
                 let client = new_client(driver.clone(), &vm)?;
                 client.resume().await?;
             }
+            Command::BootTimes => {
+                let client = new_client(driver.clone(), &vm)?;
+                let node = client
+                    .inspect("vm/boot_times", Some(0), Some(Duration::from_secs(1)))
+                    .await?;
+                for line in format_boot_times(node)? {
+                    println!("{line}");
+                }
+            }
             Command::DumpSavedState { output } => {
                 ensure_not_terminal(&output)?;
                 let client = new_client(driver.clone(), &vm)?;
@@ -960,6 +1005,18 @@ pub fn main() -> anyhow::Result<()> {
                 let mut file = create_or_stderr(&output)?;
                 file.write_all(&client.memory_profile_trace(pid).await?)?;
             }
+            Command::Profile {
+                duration,
+                profiler_args,
+                dst,
+            } => {
+                ensure_not_terminal(&dst)?;
+                let client = new_client(driver.clone(), &vm)?;
+                let file = create_or_stderr(&dst)?;
+                client
+                    .profile(duration, profiler_args, AllowStdIo::new(file))
+                    .await?;
+            }
             Command::EfiDiagnostics { log_level, output } => {
                 let client = new_client(driver.clone(), &vm)?;
                 let arg = format!(
@@ -981,6 +1038,67 @@ pub fn main() -> anyhow::Result<()> {
     })
 }
 
+/// Copies `stream` to `out`, failing if `stream` has more than `max_size` bytes.
+///
+/// At most `max_size` bytes are written to `out` either way.
+async fn copy_limited(
+    mut stream: impl AsyncRead + Unpin,
+    mut out: impl AsyncWrite + Unpin,
+    max_size: Option<u64>,
+) -> anyhow::Result<()> {
+    futures::io::copy((&mut stream).take(max_size.unwrap_or(u64::MAX)), &mut out)
+        .await
+        .context("failed to copy file")?;
+    if let Some(max_size) = max_size {
+        if stream.read(&mut [0]).await.context("failed to read file")? != 0 {
+            anyhow::bail!("file is larger than {max_size} bytes, output was truncated");
+        }
+    }
+    Ok(())
+}
+
+/// Formats the boot phases in `node`, each a reference time in 100ns units, as
+/// the lines of a waterfall.
+fn format_boot_times(node: inspect::Node) -> anyhow::Result<Vec<String>> {
+    const BAR_WIDTH: u64 = 50;
+
+    let inspect::Node::Dir(entries) = node else {
+        anyhow::bail!("unexpected boot times: {node}");
+    };
+    let mut phases = entries
+        .into_iter()
+        .filter_map(|entry| match entry.node {
+            inspect::Node::Value(inspect::Value {
+                kind: inspect::ValueKind::Unsigned(time),
+                ..
+            }) => Some((entry.name, time)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    phases.sort_by_key(|&(_, time)| time);
+    let Some(&(_, last)) = phases.last() else {
+        anyhow::bail!("no boot times available");
+    };
+
+    let name_width = phases.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let column = |time: u64| (time * BAR_WIDTH / last.max(1)) as usize;
+    let ms = |time: u64| time as f64 / 10_000.0;
+    let mut prev = 0;
+    let mut lines = Vec::new();
+    for (name, time) in &phases {
+        let (start, end) = (column(prev), column(*time));
+        lines.push(format!(
+            "{name:<name_width$} {:>10.3}ms {:>+10.3}ms |{}{}",
+            ms(*time),
+            ms(time - prev),
+            " ".repeat(start),
+            "#".repeat((end - start).max(1)),
+        ));
+        prev = *time;
+    }
+    Ok(lines)
+}
+
 fn ensure_not_terminal(path: &Option<PathBuf>) -> anyhow::Result<()> {
     if path.is_none() && std::io::stdout().is_terminal() {
         anyhow::bail!("cannot write to terminal");
@@ -1085,3 +1203,71 @@ enum Event {
     }
     println!("All done.");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::copy_limited;
+    use super::format_boot_times;
+    use pal_async::async_test;
+
+    #[test]
+    fn boot_times_waterfall() {
+        let node = inspect::inspect(
+            "",
+            inspect::adhoc(|req| {
+                req.respond()
+                    .field("vm_start", 1_000_000u64)
+                    .field("boot_shim_start", 0u64)
+                    .field("boot_shim_end", 500_000u64)
+                    .field("other", "not a time");
+            }),
+        )
+        .results();
+
+        let lines = format_boot_times(node).unwrap();
+        assert_eq!(
+            lines,
+            [
+                format!(
+                    "boot_shim_start      0.000ms     +0.000ms |{}",
+                    "#".repeat(1)
+                ),
+                format!(
+                    "boot_shim_end       50.000ms    +50.000ms |{}",
+                    "#".repeat(25)
+                ),
+                format!(
+                    "vm_start           100.000ms    +50.000ms |{}{}",
+                    " ".repeat(25),
+                    "#".repeat(25)
+                ),
+            ]
+        );
+    }
+
+    #[async_test]
+    async fn copy_limited_reports_truncation() {
+        let data = b"0123456789";
+
+        let mut out = Vec::new();
+        copy_limited(&data[..], &mut out, None).await.unwrap();
+        assert_eq!(out, data);
+
+        let mut out = Vec::new();
+        copy_limited(&data[..], &mut out, Some(10)).await.unwrap();
+        assert_eq!(out, data);
+
+        let mut out = Vec::new();
+        let err = copy_limited(&data[..], &mut out, Some(4))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("truncated"), "{err}");
+        assert_eq!(out, b"0123");
+    }
+
+    #[test]
+    fn boot_times_empty() {
+        let node = inspect::inspect("", inspect::adhoc(|req| drop(req.respond()))).results();
+        assert!(format_boot_times(node).is_err());
+    }
+}
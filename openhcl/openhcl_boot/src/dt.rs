@@ -83,6 +83,10 @@ macro_rules! format_fixed {
 
 pub struct BootTimes {
     pub start: u64,
+    /// When VTL2 memory acceptance and page table setup finished.
+    pub page_tables_end: u64,
+    /// When the shim started preparing to enter the kernel.
+    pub kernel_load_start: u64,
     pub end: u64,
 }
 
@@ -206,6 +210,8 @@ pub fn write_dt(
     let p_numa_node_id = builder.add_string("numa-node-id")?;
     let p_reftime_boot_start = builder.add_string("reftime_boot_start")?;
     let p_reftime_boot_end = builder.add_string("reftime_boot_end")?;
+    let p_reftime_page_tables_end = builder.add_string("reftime_page_tables_end")?;
+    let p_reftime_kernel_load_start = builder.add_string("reftime_kernel_load_start")?;
     let p_reftime_sidecar_start = builder.add_string("reftime_sidecar_start")?;
     let p_reftime_sidecar_end = builder.add_string("reftime_sidecar_end")?;
     let p_vtl = builder.add_string(igvm_defs::dt::IGVM_DT_VTL_PROPERTY)?;
@@ -233,9 +239,16 @@ pub fn write_dt(
         .add_str(p_compatible, "microsoft,openvmm")?;
 
     if let Some(boot_times) = boot_times {
-        let BootTimes { start, end } = boot_times;
+        let BootTimes {
+            start,
+            page_tables_end,
+            kernel_load_start,
+            end,
+        } = boot_times;
         root_builder = root_builder
             .add_u64(p_reftime_boot_start, start)?
+            .add_u64(p_reftime_page_tables_end, page_tables_end)?
+            .add_u64(p_reftime_kernel_load_start, kernel_load_start)?
             .add_u64(p_reftime_boot_end, end)?;
     }
 
@@ -652,6 +652,7 @@ fn shim_main(shim_params_raw_offset: isize) -> ! {
 
     setup_vtl2_memory(&p, partition_info, address_space);
     setup_vtl2_vp(partition_info);
+    let page_tables_end_reftime = get_ref_time(p.isolation_type);
 
     verify_imported_regions_hash(&p);
 
@@ -704,6 +705,7 @@ fn shim_main(shim_params_raw_offset: isize) -> ! {
         setup_data_tail = &mut cc_data.header;
     }
 
+    let kernel_load_start_reftime = get_ref_time(p.isolation_type);
     let initrd = p.initrd_base..p.initrd_base + p.initrd_size;
 
     // Validate the initrd crc matches what was put at file generation time.
@@ -727,6 +729,8 @@ fn shim_main(shim_params_raw_offset: isize) -> ! {
 
     let boot_times = boot_reftime.map(|start| BootTimes {
         start,
+        page_tables_end: page_tables_end_reftime.unwrap_or(0),
+        kernel_load_start: kernel_load_start_reftime.unwrap_or(0),
         end: get_ref_time(p.isolation_type).unwrap_or(0),
     });
 
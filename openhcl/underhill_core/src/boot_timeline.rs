@@ -0,0 +1,88 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Timestamps of VTL2 boot phases, for diagnosing boot latency.
+//!
+//! All timestamps are in hypervisor reference time, the same clock the boot
+//! shim uses to report its own start and end times, so that phases from the
+//! boot shim, the kernel, and the worker can be shown on a single timeline.
+
+use crate::reference_time::ReferenceTime;
+use bootloader_fdt_parser::BootTimes;
+use cvm_tracing::CVM_ALLOWED;
+use inspect::Inspect;
+
+/// The boot phases reached so far, in order.
+///
+/// Rendered as a waterfall by `ohcldiag-dev boot-times`.
+#[derive(Debug, Default)]
+pub(crate) struct BootTimeline {
+    phases: Vec<(&'static str, ReferenceTime)>,
+}
+
+impl BootTimeline {
+    /// Returns a timeline with the phases reported by the boot shim, plus the
+    /// start of init given its time since kernel boot in nanoseconds.
+    pub fn new(init_start_ns: Option<u64>) -> Self {
+        let mut this = Self::default();
+        let BootTimes {
+            start,
+            page_tables_end,
+            kernel_load_start,
+            end,
+            sidecar_start,
+            sidecar_end,
+        } = match BootTimes::new() {
+            Ok(times) => times,
+            Err(err) => {
+                tracing::warn!(
+                    CVM_ALLOWED,
+                    error = err.as_ref() as &dyn std::error::Error,
+                    "failed to read boot shim times"
+                );
+                return this;
+            }
+        };
+        for (phase, time) in [
+            ("boot_shim_start", start),
+            ("page_tables_end", page_tables_end),
+            ("sidecar_start", sidecar_start),
+            ("sidecar_end", sidecar_end),
+            ("kernel_load_start", kernel_load_start),
+            ("boot_shim_end", end),
+        ] {
+            if let Some(time) = time {
+                this.phases.push((phase, ReferenceTime::new(time)));
+            }
+        }
+        // The kernel's boot clock starts shortly after the boot shim hands off
+        // to it, so this is approximate.
+        if let (Some(end), Some(init_start_ns)) = (end, init_start_ns) {
+            this.phases
+                .push(("init_start", ReferenceTime::new(end + init_start_ns / 100)));
+        }
+        this
+    }
+
+    /// Records that `phase` was reached at reference time `now`.
+    pub fn mark(&mut self, phase: &'static str, now: u64) {
+        let now = ReferenceTime::new(now);
+        tracing::info!(
+            CVM_ALLOWED,
+            phase,
+            reference_time = now.as_100ns(),
+            since_boot = now.since(ReferenceTime::new(0)).map(tracing::field::debug),
+            "boot phase reached"
+        );
+        self.phases.push((phase, now));
+    }
+}
+
+impl Inspect for BootTimeline {
+    fn inspect(&self, req: inspect::Request<'_>) {
+        let mut resp = req.respond();
+        for &(phase, time) in &self.phases {
+            resp.field(phase, time.as_100ns());
+        }
+    }
+}
@@ -8,6 +8,7 @@
 
 use self::vtl2_settings_worker::DeviceInterfaces;
 use crate::ControlRequest;
+use crate::boot_timeline::BootTimeline;
 use crate::emuplat::EmuplatServicing;
 use crate::emuplat::netvsp::RuntimeSavedState;
 use crate::nvme_manager::manager::NvmeManager;
@@ -194,6 +195,7 @@ pub(crate) struct LoadedVm {
     pub control_send: Arc<Mutex<Option<mesh::Sender<ControlRequest>>>>,
 
     pub _periodic_telemetry_task: Task<()>,
+    pub _liveness_watchdog: Option<crate::liveness::LivenessWatchdog>,
 
     pub nvme_keep_alive: KeepAliveConfig,
     pub mana_keep_alive: KeepAliveConfig,
@@ -201,6 +203,7 @@ pub(crate) struct LoadedVm {
     pub dma_manager: OpenhclDmaManager,
     pub config_timeout_in_seconds: u64,
     pub servicing_timeout_dump_collection_in_ms: u64,
+    pub boot_timeline: BootTimeline,
     #[cfg(feature = "mem-profile-tracing")]
     pub profiler: mem_profile_tracing::HeapProfiler,
 }
@@ -346,6 +349,7 @@ enum Event<T> {
                             req.respond().field("dps", &self.device_platform_settings);
                         });
                         resp.field("runtime_params", &self.runtime_params);
+                        resp.field("boot_times", &self.boot_timeline);
                         resp.field("get", &self.get_client);
                         resp.field("vmgs", self.vmgs.as_ref().map(|x| &x.0));
                         resp.field("network", &self.network_settings);
@@ -379,6 +383,23 @@ enum Event<T> {
                                 Ok(self.nvme_keep_alive.as_str())
                             },
                         );
+                        // Writing a VP index here moves that VP out of the
+                        // sidecar kernel and into VTL2 Linux.
+                        resp.field_mut_with(
+                            "promote_sidecar_vp",
+                            |value| -> anyhow::Result<inspect::ValueKind> {
+                                let Some(value) = value else {
+                                    return Ok("".into());
+                                };
+                                let vp_index: u32 = value.parse().context("expected a VP index")?;
+                                crate::vp::promote_sidecar_vp(
+                                    threadpool,
+                                    &self.partition,
+                                    vp_index,
+                                )?;
+                                Ok(vp_index.into())
+                            },
+                        );
                     }),
                 },
                 Event::Vtl2ConfigNicRpc(message) => {
@@ -848,6 +869,8 @@ async fn start(&mut self, correlation_id: Option<Guid>) {
                 "resuming VM"
             );
         } else {
+            self.boot_timeline
+                .mark("vm_start", reference_time.as_100ns());
             // Assume we started at reference time 0.
             let boot_time = reference_time.since(ReferenceTime::new(0));
             tracing::info!(
@@ -958,6 +981,25 @@ async fn save(
             None
         };
 
+        let mut features = 0;
+        if nvme_keepalive_mode.is_enabled() && nvme_state.is_some() {
+            features |= servicing::feature::NVME_KEEPALIVE;
+        }
+        if mana_state.is_some() {
+            features |= servicing::feature::MANA_KEEPALIVE;
+        }
+        // Memory kept alive across servicing lives in the private pool, so
+        // the new paravisor needs a pool at least as large.
+        let private_pool_bytes = if dma_manager_state.is_some() {
+            self.runtime_params
+                .private_pool_ranges()
+                .iter()
+                .map(|r| r.range.len())
+                .sum()
+        } else {
+            0
+        };
+
         let mut state = ServicingState {
             init_state: servicing::ServicingInitState {
                 firmware_type: self.firmware_type.into(),
@@ -971,6 +1013,11 @@ async fn save(
                 dma_manager_state,
                 vmbus_client,
                 mana_state,
+                saved_by: Some(servicing::SavedByBuild::current()),
+                requirements: Some(servicing::ServicingRequirements::current(
+                    features,
+                    private_pool_bytes,
+                )),
             },
             units,
         };
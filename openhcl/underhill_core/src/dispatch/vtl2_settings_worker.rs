@@ -12,6 +12,8 @@
 use disk_backend::resolve::ResolveDiskParameters;
 use disk_backend_resources::AutoFormattedDiskHandle;
 use disk_backend_resources::BlockDeviceDiskHandle;
+use disk_backend_resources::DiskTelemetry;
+use disk_backend_resources::IoTraceLevel;
 use futures::StreamExt;
 use guest_emulation_transport::api::platform_settings::DevicePlatformSettings;
 use guid::Guid;
@@ -25,6 +27,7 @@
 use mesh::rpc::RpcSend;
 use nvme_resources::NamespaceDefinition;
 use nvme_resources::NvmeControllerHandle;
+use nvme_resources::NvmeControllerRequest;
 use scsidisk_resources::SimpleScsiDiskHandle;
 use scsidisk_resources::SimpleScsiDvdHandle;
 use scsidisk_resources::SimpleScsiDvdRequest;
@@ -102,6 +105,12 @@ enum Error<'a> {
     StorageRemoveDiskFailed(u8, #[source] anyhow::Error),
     #[error("failed to change media at lun {0}")]
     StorageChangeMediaFailed(u8, #[source] anyhow::Error),
+    #[error("failed to set telemetry at lun {0}")]
+    StorageSetTelemetryFailed(u8, #[source] anyhow::Error),
+    #[error("no NVMe controller {0}")]
+    StorageNvmeControllerNotFound(Guid),
+    #[error("failed to set telemetry on namespace {0}")]
+    StorageSetNamespaceTelemetryFailed(u32, #[source] anyhow::Error),
     #[error("failed to modify networking instance {0}")]
     NetworkingModifyNicFailed(Guid, #[source] anyhow::Error),
     #[error("failed to add network interface {0}")]
@@ -183,6 +192,9 @@ fn code(&self) -> Vtl2SettingsErrorCode {
             Error::StorageChangeMediaFailed { .. } => {
                 Vtl2SettingsErrorCode::StorageChangeMediaFailed
             }
+            Error::StorageSetTelemetryFailed(..) => Vtl2SettingsErrorCode::InternalFailure,
+            Error::StorageNvmeControllerNotFound(_) => Vtl2SettingsErrorCode::InternalFailure,
+            Error::StorageSetNamespaceTelemetryFailed(..) => Vtl2SettingsErrorCode::InternalFailure,
         }
     }
 }
@@ -213,6 +225,8 @@ pub enum Vtl2ConfigAcquireResource {
     AddDisk(Guid, underhill_config::ScsiDisk),
     RmDisk(Guid, underhill_config::ScsiDisk),
     ChangeMedia(Guid, StorageDisk),
+    SetDiskTelemetry(Guid, underhill_config::ScsiDisk),
+    SetNamespaceTelemetry(Guid, underhill_config::NvmeNamespace),
     ModifyNic((Guid, Option<Guid>)),
     AddNic((Guid, Option<Guid>, Option<u16>)),
     RemoveNic(Guid),
@@ -226,6 +240,8 @@ pub enum Vtl2ConfigCommit {
     ),
     RmDisk(Guid, storvsp_resources::ScsiPath),
     ChangeMedia(Guid, StorageDevicePath, Option<Resource<DiskHandleKind>>),
+    SetDiskTelemetry(Guid, storvsp_resources::ScsiPath, DiskTelemetry),
+    SetNamespaceTelemetry(Guid, u32, DiskTelemetry),
     ModifyNic((Guid, Option<Guid>)),
     AddNic((Guid, Option<Guid>, Option<u16>)),
     RemoveNic(Guid),
@@ -243,6 +259,7 @@ pub struct Vtl2SettingsWorker {
 pub struct DeviceInterfaces {
     scsi_dvds: HashMap<StorageDevicePath, mesh::Sender<SimpleScsiDvdRequest>>,
     scsi_request: HashMap<Guid, mesh::Sender<ScsiControllerRequest>>,
+    nvme_request: HashMap<Guid, mesh::Sender<NvmeControllerRequest>>,
     use_nvme_vfio: bool,
 }
 
@@ -402,12 +419,35 @@ async fn acquire_configuration_resources(
                         false,
                     )
                     .await?;
+                    let scsi_path = disk_cfg.path;
                     to_commits.push(Vtl2ConfigCommit::AddDisk(guid, disk_cfg, dvd));
+                    if disk.telemetry != Default::default() {
+                        to_commits.push(Vtl2ConfigCommit::SetDiskTelemetry(
+                            guid,
+                            scsi_path,
+                            telemetry_from_config(&disk.telemetry),
+                        ));
+                    }
                 }
                 Vtl2ConfigAcquireResource::RmDisk(guid, disk) => {
                     let scsi_path = scsi_path_from_config(&disk)?;
                     to_commits.push(Vtl2ConfigCommit::RmDisk(guid, scsi_path));
                 }
+                Vtl2ConfigAcquireResource::SetDiskTelemetry(guid, disk) => {
+                    let scsi_path = scsi_path_from_config(&disk)?;
+                    to_commits.push(Vtl2ConfigCommit::SetDiskTelemetry(
+                        guid,
+                        scsi_path,
+                        telemetry_from_config(&disk.telemetry),
+                    ));
+                }
+                Vtl2ConfigAcquireResource::SetNamespaceTelemetry(guid, namespace) => {
+                    to_commits.push(Vtl2ConfigCommit::SetNamespaceTelemetry(
+                        guid,
+                        namespace.nsid,
+                        telemetry_from_config(&namespace.telemetry),
+                    ));
+                }
                 Vtl2ConfigAcquireResource::ChangeMedia(guid, disk) => {
                     let path = storage_path_from_config(&disk)?;
                     let disk_type = make_disk_type(
@@ -509,6 +549,28 @@ async fn commit_configuration_changes(
                     .await
                     .map_err(|e| Error::StorageChangeMediaFailed(lun, e))?;
                 }
+                Vtl2ConfigCommit::SetDiskTelemetry(controller_id, scsi_path, telemetry) => {
+                    self.interfaces
+                        .scsi_request
+                        .get(&controller_id)
+                        .ok_or(Error::StorageScsiControllerNotFound(controller_id))?
+                        .call_failable(ScsiControllerRequest::SetTelemetry, (scsi_path, telemetry))
+                        .await
+                        .map_err(|err| {
+                            Error::StorageSetTelemetryFailed(scsi_path.lun, err.into())
+                        })?;
+                }
+                Vtl2ConfigCommit::SetNamespaceTelemetry(controller_id, nsid, telemetry) => {
+                    self.interfaces
+                        .nvme_request
+                        .get(&controller_id)
+                        .ok_or(Error::StorageNvmeControllerNotFound(controller_id))?
+                        .call_failable(NvmeControllerRequest::SetTelemetry, (nsid, telemetry))
+                        .await
+                        .map_err(|err| {
+                            Error::StorageSetNamespaceTelemetryFailed(nsid, err.into())
+                        })?;
+                }
                 Vtl2ConfigCommit::ModifyNic(nic_settings) => {
                     let instance_id = nic_settings.0;
                     self.device_config_send
@@ -601,6 +663,7 @@ fn modify_storage_configuration(
     if let Err(e) = modify_scsi_configuration(old_settings, new_settings, todos) {
         errors.push(e);
     }
+    modify_nvme_configuration(old_settings, new_settings, todos);
 }
 
 fn modify_ide_configuration(
@@ -654,6 +717,42 @@ fn modify_ide_configuration(
     Ok(())
 }
 
+fn modify_nvme_configuration(
+    old_settings: &Vtl2SettingsDynamic,
+    new_settings: &Vtl2SettingsDynamic,
+    todos: &mut Vec<Vtl2ConfigAcquireResource>,
+) {
+    // Only telemetry can be changed on an NVMe namespace at runtime.
+    for new_controller in &new_settings.nvme_controllers {
+        let Some(old_controller) = old_settings
+            .nvme_controllers
+            .iter()
+            .find(|c| c.instance_id == new_controller.instance_id)
+        else {
+            continue;
+        };
+        for new_namespace in &new_controller.namespaces {
+            let Some(old_namespace) = old_controller
+                .namespaces
+                .iter()
+                .find(|n| n.nsid == new_namespace.nsid)
+            else {
+                continue;
+            };
+            let with_new_telemetry = underhill_config::NvmeNamespace {
+                telemetry: new_namespace.telemetry,
+                ..old_namespace.clone()
+            };
+            if old_namespace != new_namespace && with_new_telemetry == *new_namespace {
+                todos.push(Vtl2ConfigAcquireResource::SetNamespaceTelemetry(
+                    new_controller.instance_id,
+                    new_namespace.clone(),
+                ));
+            }
+        }
+    }
+}
+
 fn modify_scsi_configuration(
     old_settings: &Vtl2SettingsDynamic,
     new_settings: &Vtl2SettingsDynamic,
@@ -684,6 +783,19 @@ fn modify_scsi_disks_configuration(
     let mut change_media_disks = Vec::new();
 
     for (old_config, new_config, controller_id) in modify_disks {
+        // Telemetry changes are applied in place, without disturbing the disk.
+        let with_new_telemetry = underhill_config::ScsiDisk {
+            telemetry: new_config.telemetry,
+            ..old_config.clone()
+        };
+        if with_new_telemetry == *new_config {
+            todos.push(Vtl2ConfigAcquireResource::SetDiskTelemetry(
+                controller_id,
+                new_config.clone(),
+            ));
+            continue;
+        }
+
         if (old_config.physical_devices.is_striping()) || new_config.physical_devices.is_striping()
         {
             return Err(Error::StripStorageCannotChangeControllerAtRuntime.into());
@@ -860,6 +972,22 @@ fn scsi_path_from_config(
     })
 }
 
+fn telemetry_from_config(telemetry: &underhill_config::DiskTelemetry) -> DiskTelemetry {
+    let underhill_config::DiskTelemetry {
+        latency_histogram,
+        trace_level,
+    } = *telemetry;
+    DiskTelemetry {
+        latency_histogram,
+        trace_level: match trace_level {
+            underhill_config::IoTraceLevel::Off => IoTraceLevel::Off,
+            underhill_config::IoTraceLevel::Info => IoTraceLevel::Info,
+            underhill_config::IoTraceLevel::Debug => IoTraceLevel::Debug,
+            underhill_config::IoTraceLevel::Trace => IoTraceLevel::Trace,
+        },
+    }
+}
+
 fn modify_network_configuration(
     old_settings: &Vtl2SettingsDynamic,
     new_settings: &Vtl2SettingsDynamic,
@@ -916,6 +1044,11 @@ pub struct UhVpciDeviceConfig {
     pub resource: Resource<PciDeviceHandleKind>,
 }
 
+pub struct UhNvmeControllerConfig {
+    pub config: UhVpciDeviceConfig,
+    pub request: mesh::Sender<NvmeControllerRequest>,
+}
+
 #[derive(Debug, Eq, PartialEq, Hash)]
 pub enum StorageDevicePath {
     Ide(IdePath),
@@ -1394,12 +1527,16 @@ async fn make_scsi_controller_config(
     let mut scsi_disks = Vec::new();
 
     let mut dvds = Vec::new();
+    let mut telemetry = Vec::new();
     for disk in &controller.disks {
         let (disk_cfg, dvd) =
             make_scsi_disk_config(ctx, storage_context, disk, is_restoring).await?;
         if let Some(dvd) = dvd {
             dvds.push((disk_cfg.path, dvd));
         }
+        if disk.telemetry != Default::default() {
+            telemetry.push((disk_cfg.path, telemetry_from_config(&disk.telemetry)));
+        }
         scsi_disks.push(disk_cfg);
     }
 
@@ -1420,6 +1557,12 @@ async fn make_scsi_controller_config(
     );
 
     let (send, recv) = mesh::channel();
+    // Queue the initial telemetry settings, to be applied once the controller
+    // starts handling requests. The disks are attached by then, so these
+    // cannot fail.
+    for settings in telemetry {
+        send.send(ScsiControllerRequest::SetTelemetry(Rpc::detached(settings)));
+    }
     // The choice of max 256 scsi subchannels is somewhat arbitrary. But
     // for now, it provides a decent trade off between unbounded number of
     // channels that cause perf issues vs delivering some reasonable perf.
@@ -1431,6 +1574,7 @@ async fn make_scsi_controller_config(
             io_queue_depth: Some(controller.io_queue_depth.unwrap_or(default_io_queue_depth)),
             requests: Some(recv),
             poll_mode_queue_depth,
+            max_protocol_version: None,
         },
         request: send,
         dvds,
@@ -1443,23 +1587,37 @@ async fn make_nvme_controller_config(
     storage_context: &StorageContext<'_>,
     controller: &underhill_config::NvmeController,
     is_restoring: bool,
-) -> Result<UhVpciDeviceConfig, Vtl2SettingsErrorInfo> {
+) -> Result<UhNvmeControllerConfig, Vtl2SettingsErrorInfo> {
     let mut namespaces = Vec::new();
+    let mut telemetry = Vec::new();
     for namespace in &controller.namespaces {
         namespaces
             .push(make_nvme_disk_config(ctx, storage_context, namespace, is_restoring).await?);
+        if namespace.telemetry != Default::default() {
+            telemetry.push((namespace.nsid, telemetry_from_config(&namespace.telemetry)));
+        }
     }
 
-    Ok(UhVpciDeviceConfig {
-        instance_id: controller.instance_id,
-        resource: NvmeControllerHandle {
-            subsystem_id: controller.instance_id,
-            namespaces,
-            max_io_queues: 64,
-            msix_count: 64,
-            requests: None,
-        }
-        .into_resource(),
+    let (send, recv) = mesh::channel();
+    // Queue the initial telemetry settings, to be applied once the controller
+    // starts handling requests.
+    for settings in telemetry {
+        send.send(NvmeControllerRequest::SetTelemetry(Rpc::detached(settings)));
+    }
+    Ok(UhNvmeControllerConfig {
+        config: UhVpciDeviceConfig {
+            instance_id: controller.instance_id,
+            resource: NvmeControllerHandle {
+                subsystem_id: controller.instance_id,
+                namespaces,
+                max_io_queues: 64,
+                msix_count: 64,
+                requests: Some(recv),
+                boot_partitions: None,
+            }
+            .into_resource(),
+        },
+        request: send,
     })
 }
 
@@ -1475,7 +1633,7 @@ pub async fn create_storage_controllers_from_vtl2_settings(
     (
         Option<UhIdeControllerConfig>,
         Vec<UhScsiControllerConfig>,
-        Vec<UhVpciDeviceConfig>,
+        Vec<UhNvmeControllerConfig>,
     ),
     Vtl2SettingsErrorInfo,
 > {
@@ -1848,7 +2006,7 @@ pub async fn new(
         let fixed = vtl2_settings.map_or_else(Default::default, |s| s.fixed.clone());
         let dynamic = vtl2_settings.map(|s| &s.dynamic);
 
-        let (ide_controller, scsi_controllers, vpci_devices) = if let Some(dynamic) = &dynamic {
+        let (ide_controller, scsi_controllers, nvme_controllers) = if let Some(dynamic) = &dynamic {
             create_storage_controllers_from_vtl2_settings(
                 &mut context,
                 uevent_listener,
@@ -1875,6 +2033,7 @@ pub async fn new(
 
         let mut scsi_dvds = HashMap::new();
         let mut scsi_request = HashMap::new();
+        let mut nvme_request = HashMap::new();
 
         let ide_controller = ide_controller.map(|c| {
             scsi_dvds.extend(
@@ -1898,6 +2057,14 @@ pub async fn new(
             })
             .collect();
 
+        let vpci_devices = nvme_controllers
+            .into_iter()
+            .map(|c| {
+                nvme_request.insert(c.config.instance_id, c.request);
+                c.config
+            })
+            .collect();
+
         let cfg = InitialControllers {
             ide_controller,
             vmbus_devices,
@@ -1906,6 +2073,7 @@ pub async fn new(
             device_interfaces: DeviceInterfaces {
                 scsi_dvds,
                 scsi_request,
+                nvme_request,
                 use_nvme_vfio,
             },
         };
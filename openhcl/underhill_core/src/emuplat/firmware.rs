@@ -33,6 +33,17 @@ fn log_event(&self, event: UefiEvent) {
                 }
             }
             UefiEvent::NoBootDevice => EventLogId::NO_BOOT_DEVICE,
+            // The host event log has no IDs for boot phases.
+            UefiEvent::BdsStarted | UefiEvent::ExitBootServices => {
+                tracing::debug!(CVM_ALLOWED, ?event, "uefi boot phase");
+                return;
+            }
+            // The host loads the firmware, so whether the frontpage is
+            // available is up to the host configuration.
+            UefiEvent::BootToFirmwareUi => {
+                tracing::info!(CVM_ALLOWED, "guest requested boot to firmware ui");
+                return;
+            }
         };
         self.get.event_log(log_event_id);
     }
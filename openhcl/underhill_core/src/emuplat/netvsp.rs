@@ -1268,7 +1268,31 @@ enum NextWorkItem {
             match next_work_item {
                 NextWorkItem::Continue => continue,
                 NextWorkItem::ManagerMessage(HclNetworkVfManagerMessage::Inspect(deferred)) => {
-                    deferred.inspect(&self)
+                    // Writing `vtl0_vf_hidden` hides or restores the VTL0 VF,
+                    // which switches the guest's data path. This is used to
+                    // test failover while traffic is flowing.
+                    let mut hide_vtl0 = None;
+                    deferred.respond(|resp| {
+                        resp.merge(&self).field_mut_with("vtl0_vf_hidden", |v| {
+                            let hidden = match v {
+                                Some(v) => *hide_vtl0.insert(v.parse::<bool>()?),
+                                None => matches!(
+                                    self.vtl0_bus_control,
+                                    Vtl0Bus::HiddenPresent(_) | Vtl0Bus::HiddenNotPresent
+                                ),
+                            };
+                            Ok::<_, std::str::ParseBoolError>(hidden)
+                        });
+                    });
+                    if let Some(hide_vtl0) = hide_vtl0 {
+                        if self.is_shutdown_active {
+                            continue;
+                        }
+                        let vtl0_vfid = vtl0_vfid_from_bus_control(&self.vtl0_bus_control);
+                        self.hide_vtl0_vf(Rpc::detached(hide_vtl0), &vtl2_device_state)
+                            .instrument(tracing::info_span!("hide vtl0 vf", vtl2_vfid, vtl0_vfid))
+                            .await;
+                    }
                 }
                 NextWorkItem::ManagerMessage(HclNetworkVfManagerMessage::AddGuestVFManager(
                     rpc,
@@ -8,6 +8,7 @@
 #![expect(missing_docs)]
 #![forbid(unsafe_code)]
 
+mod boot_timeline;
 mod diag;
 mod dispatch;
 mod emuplat;
@@ -15,6 +16,7 @@
 mod inspect_internal;
 mod inspect_proc;
 mod livedump;
+mod liveness;
 mod loader;
 mod nvme_manager;
 mod options;
@@ -244,6 +246,8 @@ fn diff(start: Option<u64>, end: Option<u64>) -> Option<tracing::field::DebugVal
     // Read boot times provided by the bootloader.
     let BootTimes {
         start,
+        page_tables_end,
+        kernel_load_start,
         end,
         sidecar_start,
         sidecar_end,
@@ -251,6 +255,8 @@ fn diff(start: Option<u64>, end: Option<u64>) -> Option<tracing::field::DebugVal
     tracing::info!(
         CVM_ALLOWED,
         start,
+        page_tables_end,
+        kernel_load_start,
         end,
         sidecar_start,
         sidecar_end,
@@ -346,6 +352,8 @@ async fn launch_workers(
         disable_lower_vtl_timer_virt: opt.disable_lower_vtl_timer_virt,
         config_timeout_in_seconds: opt.config_timeout_in_seconds,
         servicing_timeout_dump_collection_in_ms: opt.servicing_timeout_dump_collection_in_ms,
+        liveness_timeout_in_seconds: opt.liveness_timeout_in_seconds,
+        vmbus_relay_block: opt.vmbus_relay_block,
     };
 
     let (mut remote_console_cfg, framebuffer_access) =
@@ -0,0 +1,101 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! A watchdog that detects when the VM worker stops making progress.
+//!
+//! A heartbeat task runs on the worker's threadpool and periodically records
+//! the current time. A monitor task on its own thread checks the heartbeat,
+//! and if it has gone stale, logs an error and takes a live dump, which is
+//! forwarded to the host. This surfaces a hung paravisor without waiting for
+//! symptoms in the guest. The worker is left running, so a transient stall is
+//! just logged when the heartbeat resumes.
+
+use cvm_tracing::CVM_ALLOWED;
+use pal_async::DefaultPool;
+use pal_async::task::Spawn;
+use pal_async::task::Task;
+use pal_async::timer::PolledTimer;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
+use vmcore::vm_task::VmTaskDriver;
+
+/// The running watchdog. Dropping this stops both the heartbeat and the
+/// monitor, and waits for the monitor thread to exit.
+pub(crate) struct LivenessWatchdog {
+    _heartbeat: Task<()>,
+    monitor: Option<Task<()>>,
+    monitor_thread: Option<JoinHandle<()>>,
+}
+
+impl LivenessWatchdog {
+    /// Starts the heartbeat on `spawn` and the monitor on a new thread.
+    ///
+    /// A heartbeat older than `timeout` is treated as a hang.
+    pub fn new(spawn: &impl Spawn, driver: VmTaskDriver, timeout: Duration) -> Self {
+        let start = Instant::now();
+        let last_beat_ms = Arc::new(AtomicU64::new(0));
+        let interval = timeout / 4;
+
+        let heartbeat = spawn.spawn("liveness-heartbeat", {
+            let last_beat_ms = last_beat_ms.clone();
+            async move {
+                let mut timer = PolledTimer::new(&driver);
+                loop {
+                    last_beat_ms.store(start.elapsed().as_millis() as u64, Ordering::Relaxed);
+                    timer.sleep(interval).await;
+                }
+            }
+        });
+
+        // Run the monitor on its own executor so that it is not stalled by
+        // whatever is stalling the threadpool.
+        let (monitor_thread, monitor_driver) =
+            DefaultPool::spawn_on_thread("liveness-watchdog-executor");
+        let monitor = monitor_driver
+            .clone()
+            .spawn("liveness-watchdog", async move {
+                let mut timer = PolledTimer::new(&monitor_driver);
+                let mut hung = false;
+                loop {
+                    timer.sleep(interval).await;
+                    let last_beat = Duration::from_millis(last_beat_ms.load(Ordering::Relaxed));
+                    let stale = start.elapsed().saturating_sub(last_beat);
+                    if stale > timeout {
+                        if !hung {
+                            hung = true;
+                            tracing::error!(
+                                CVM_ALLOWED,
+                                stale_ms = stale.as_millis() as u64,
+                                "worker heartbeat missed, taking live dump"
+                            );
+                            crate::livedump::livedump().await;
+                        }
+                    } else if hung {
+                        hung = false;
+                        tracing::warn!(CVM_ALLOWED, "worker heartbeat resumed");
+                    }
+                }
+            });
+
+        Self {
+            _heartbeat: heartbeat,
+            monitor: Some(monitor),
+            monitor_thread: Some(monitor_thread),
+        }
+    }
+}
+
+impl Drop for LivenessWatchdog {
+    fn drop(&mut self) {
+        // Cancelling the monitor drops the last reference to its executor's
+        // driver, so the executor runs out of tasks and the thread exits.
+        drop(self.monitor.take());
+        if let Some(thread) = self.monitor_thread.take() {
+            thread.join().unwrap();
+        }
+    }
+}
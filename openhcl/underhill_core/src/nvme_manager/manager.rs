@@ -5,7 +5,6 @@
 use crate::nvme_manager::device::NvmeDriverManager;
 use crate::nvme_manager::device::NvmeDriverManagerClient;
 use crate::nvme_manager::device::NvmeDriverShutdownOptions;
-use crate::nvme_manager::is_nvme_keepalive_compatible;
 use crate::nvme_manager::save_restore::NvmeManagerSavedState;
 use crate::nvme_manager::save_restore::NvmeSavedDiskConfig;
 use crate::servicing::NvmeSavedState;
@@ -23,10 +22,12 @@
 use pal_async::task::Task;
 use parking_lot::RwLock;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::collections::hash_map;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
 use tracing::Instrument;
+use underhill_config::NvmeNamespaceFilter;
 use vm_resource::AsyncResolveResource;
 use vm_resource::ResourceId;
 use vm_resource::ResourceResolver;
@@ -68,6 +69,8 @@ pub fn new(
         save_restore_supported: bool,
         saved_state: Option<NvmeSavedState>,
         nvme_driver_spawner: Arc<dyn CreateNvmeDriver>,
+        namespace_filters: Vec<NvmeNamespaceFilter>,
+        keepalive_disabled: Vec<String>,
     ) -> Self {
         tracing::info!(
             vp_count,
@@ -86,6 +89,12 @@ pub fn new(
                 driver_source: driver_source.clone(),
                 devices: Arc::new(RwLock::new(HashMap::new())),
                 nvme_driver_spawner: nvme_driver_spawner.clone(),
+                namespace_filters: namespace_filters
+                    .into_iter()
+                    .map(|filter| (filter.pci_id.clone(), filter))
+                    .collect::<HashMap<_, _>>()
+                    .into(),
+                keepalive_disabled: Arc::new(keepalive_disabled.into_iter().collect()),
             },
         };
         let task = driver.spawn("nvme-manager", async move {
@@ -212,6 +221,25 @@ struct NvmeWorkerContext {
     devices: Arc<RwLock<HashMap<String, NvmeDriverManager>>>,
     #[inspect(skip)]
     nvme_driver_spawner: Arc<dyn CreateNvmeDriver>,
+    /// Namespace allow-lists from the VTL2 settings, by PCI ID.
+    #[inspect(iter_by_key)]
+    namespace_filters: Arc<HashMap<String, NvmeNamespaceFilter>>,
+    /// PCI IDs of devices that the host asked not to keep alive, from the
+    /// VTL2 settings.
+    #[inspect(with = "|x| inspect::iter_by_index(x.iter())")]
+    keepalive_disabled: Arc<HashSet<String>>,
+}
+
+impl NvmeWorkerContext {
+    /// Returns whether the device may be kept alive across servicing: it must
+    /// be compatible with keepalive and not excluded by the host.
+    fn keepalive_compatible(&self, pci_id: &str) -> bool {
+        if self.keepalive_disabled.contains(pci_id) {
+            tracing::info!(%pci_id, "nvme keepalive disabled for device by vtl2 settings");
+            return false;
+        }
+        self.nvme_driver_spawner.keepalive_compatible(pci_id)
+    }
 }
 
 #[derive(Inspect)]
@@ -344,7 +372,7 @@ async fn load_driver(pci_id: String, context: NvmeWorkerContext) -> anyhow::Resu
         // Note: `client` exists outside of the devices write lock. This is safe:
         // the mesh client will fail appropriately if shutdown comes in between inserting
         // this entry and the call to `load_driver()`.
-        let keepalive_compatible = is_nvme_keepalive_compatible(&pci_id);
+        let keepalive_compatible = context.keepalive_compatible(&pci_id);
         let client = {
             let mut guard = context.devices.write();
 
@@ -391,6 +419,15 @@ async fn get_namespace(
         nsid: u32,
         context: NvmeWorkerContext,
     ) -> anyhow::Result<nvme_driver::NamespaceHandle> {
+        let filter = context.namespace_filters.get(&pci_id);
+        // Without any allowed NGUIDs, the namespace ID alone decides, so reject
+        // before loading the driver.
+        if let Some(filter) = filter {
+            if filter.allowed_nguids.is_empty() && !filter.allowed_nsids.contains(&nsid) {
+                anyhow::bail!("nvme namespace {nsid} on {pci_id} is not allowed by vtl2 settings");
+            }
+        }
+
         // If the driver is already created, use it.
         let mut client: Option<NvmeDriverManagerClient> = None;
         {
@@ -414,14 +451,21 @@ async fn get_namespace(
             }
         }
 
-        match client {
-            Some(client) => client.get_namespace(nsid).await,
-            None => Err(anyhow::anyhow!(
+        let namespace = match client {
+            Some(client) => client.get_namespace(nsid).await?,
+            None => anyhow::bail!(
                 "nvme device manager worker is shut down, can't get namespace {} for {}",
                 nsid,
                 pci_id
-            )),
+            ),
+        };
+
+        if let Some(filter) = filter {
+            if !filter.allows(nsid, &namespace.nguid()) {
+                anyhow::bail!("nvme namespace {nsid} on {pci_id} is not allowed by vtl2 settings");
+            }
         }
+        Ok(namespace)
     }
 
     /// Saves NVMe device's states into buffer during servicing.
@@ -475,7 +519,11 @@ pub async fn restore(
             // cannot rely on saved state to determine keepalive compatibility
             // because previous versions might save devices that are no longer
             // considered keepalive compatible.
-            let keepalive_compatible = is_nvme_keepalive_compatible(&pci_id);
+            //
+            // A device the host has since excluded from keepalive is still
+            // restored from its saved state, since it was not reset, but it
+            // will be reset at the next servicing.
+            let keepalive_compatible = self.context.keepalive_compatible(&pci_id);
 
             let nvme_driver = self
                 .context
@@ -587,6 +635,7 @@ mod tests {
     use inspect::Inspect;
     use inspect::InspectionBuilder;
     use nvme_driver::save_restore::NvmeDriverSavedState;
+    use nvme_driver::save_restore::NvmeDriverWorkerSavedState;
     use pal_async::DefaultDriver;
     use pal_async::async_test;
     use std::sync::atomic::AtomicU32;
@@ -596,6 +645,7 @@ mod tests {
     use test_with_tracing::test;
     use vmcore::vm_task::VmTaskDriverSource;
     use vmcore::vm_task::thread::ThreadDriverBackend;
+    use zerocopy::FromZeros;
 
     /// Mock NVMe driver for testing that simulates realistic delays and tracks call patterns
     #[derive(Inspect, Clone)]
@@ -662,6 +712,10 @@ fn shutdown_call_count(&self) -> u32 {
         fn set_fail_namespace(&self, fail: bool) {
             self.fail_namespace.store(fail, Ordering::SeqCst);
         }
+
+        fn set_success_mode(&self, success: bool) {
+            self.success_mode.store(success, Ordering::SeqCst);
+        }
     }
 
     #[async_trait]
@@ -711,6 +765,21 @@ async fn save(&mut self) -> anyhow::Result<NvmeDriverSavedState> {
             let mut timer = pal_async::timer::PolledTimer::new(&self.driver_source.simple());
             timer.sleep(Duration::from_millis(10)).await;
 
+            if self.success_mode.load(Ordering::SeqCst) {
+                return Ok(NvmeDriverSavedState {
+                    identify_ctrl: nvme_spec::IdentifyController::new_zeroed(),
+                    device_id: self.pci_id.clone(),
+                    namespaces: Vec::new(),
+                    worker_data: NvmeDriverWorkerSavedState {
+                        admin: None,
+                        io: Vec::new(),
+                        qsize: 0,
+                        max_io_queues: 0,
+                        allow_lazy_restore: None,
+                    },
+                });
+            }
+
             anyhow::bail!("MOCK_SUCCESS: save operation completed for {}", self.pci_id);
         }
 
@@ -743,6 +812,8 @@ struct MockNvmeDriverSpawner {
         created_drivers: Arc<RwLock<Vec<Arc<MockNvmeDriver>>>>,
         /// Allow injection of creation failures
         fail_create: Arc<AtomicBool>,
+        /// PCI IDs of drivers created from saved state
+        restored_drivers: Arc<RwLock<Vec<String>>>,
     }
 
     impl MockNvmeDriverSpawner {
@@ -752,9 +823,14 @@ fn new(namespace_delay: Duration, shutdown_delay: Duration) -> Self {
                 shutdown_delay,
                 created_drivers: Arc::new(RwLock::new(Vec::new())),
                 fail_create: Arc::new(AtomicBool::new(false)),
+                restored_drivers: Arc::new(RwLock::new(Vec::new())),
             }
         }
 
+        fn was_restored(&self, pci_id: &str) -> bool {
+            self.restored_drivers.read().iter().any(|id| id == pci_id)
+        }
+
         fn get_driver(&self, pci_id: &str) -> Option<Arc<MockNvmeDriver>> {
             let drivers = self.created_drivers.read();
             drivers.iter().find(|d| d.pci_id == pci_id).cloned()
@@ -777,7 +853,7 @@ async fn create_driver(
             pci_id: &str,
             _vp_count: u32,
             _save_restore_supported: bool,
-            _saved_state: Option<&NvmeDriverSavedState>,
+            saved_state: Option<&NvmeDriverSavedState>,
         ) -> Result<Box<dyn NvmeDevice>, NvmeSpawnerError> {
             if self.fail_create.load(Ordering::SeqCst) {
                 return Err(NvmeSpawnerError::MockDriverCreationFailed(anyhow::anyhow!(
@@ -798,9 +874,17 @@ async fn create_driver(
                 let mut drivers = self.created_drivers.write();
                 drivers.push(driver.clone());
             }
+            if saved_state.is_some() {
+                self.restored_drivers.write().push(pci_id.to_string());
+            }
 
             Ok(Box::new((*driver).clone()))
         }
+
+        fn keepalive_compatible(&self, _pci_id: &str) -> bool {
+            // There is no sysfs entry for mock devices.
+            true
+        }
     }
 
     // Helper to create test VmTaskDriverSource
@@ -825,6 +909,8 @@ async fn test_concurrent_get_namespace_calls(driver: DefaultDriver) {
             false, // save_restore_supported
             None,  // no saved state
             spawner.clone(),
+            Vec::new(),
+            Vec::new(),
         );
 
         let client = manager.client().clone();
@@ -867,7 +953,15 @@ async fn test_concurrent_shutdown(driver: DefaultDriver) {
             Duration::from_millis(100), // shutdown delay - this is what we're testing
         ));
 
-        let manager = NvmeManager::new(&driver_source, 4, false, None, spawner.clone());
+        let manager = NvmeManager::new(
+            &driver_source,
+            4,
+            false,
+            None,
+            spawner.clone(),
+            Vec::new(),
+            Vec::new(),
+        );
 
         let client = manager.client().clone();
 
@@ -911,7 +1005,15 @@ async fn test_same_device_namespace_serialization(driver: DefaultDriver) {
             Duration::from_millis(10),
         ));
 
-        let manager = NvmeManager::new(&driver_source, 4, false, None, spawner.clone());
+        let manager = NvmeManager::new(
+            &driver_source,
+            4,
+            false,
+            None,
+            spawner.clone(),
+            Vec::new(),
+            Vec::new(),
+        );
         let client = manager.client().clone();
 
         let pci_id = "test-device-same".to_string();
@@ -948,7 +1050,15 @@ async fn test_error_handling(driver: DefaultDriver) {
             Duration::from_millis(10),
         ));
 
-        let manager = NvmeManager::new(&driver_source, 4, false, None, spawner.clone());
+        let manager = NvmeManager::new(
+            &driver_source,
+            4,
+            false,
+            None,
+            spawner.clone(),
+            Vec::new(),
+            Vec::new(),
+        );
         let client = manager.client().clone();
 
         // Test spawner creation failure
@@ -980,7 +1090,15 @@ async fn test_shutdown_before_operations(driver: DefaultDriver) {
             Duration::from_millis(10),
         ));
 
-        let manager = NvmeManager::new(&driver_source, 4, false, None, spawner.clone());
+        let manager = NvmeManager::new(
+            &driver_source,
+            4,
+            false,
+            None,
+            spawner.clone(),
+            Vec::new(),
+            Vec::new(),
+        );
         let client = manager.client().clone();
 
         // Shutdown immediately
@@ -1007,7 +1125,15 @@ async fn test_concurrent_namespace_timing(driver: DefaultDriver) {
             Duration::from_millis(50),
         ));
 
-        let manager = NvmeManager::new(&driver_source, 4, false, None, spawner.clone());
+        let manager = NvmeManager::new(
+            &driver_source,
+            4,
+            false,
+            None,
+            spawner.clone(),
+            Vec::new(),
+            Vec::new(),
+        );
         let client = manager.client().clone();
 
         // Test concurrent calls to different devices
@@ -1048,6 +1174,76 @@ async fn test_concurrent_namespace_timing(driver: DefaultDriver) {
         manager.shutdown(false).await;
     }
 
+    #[async_test]
+    async fn test_keepalive_disabled_device_reset_on_servicing(driver: DefaultDriver) {
+        // Test that a device the host excluded from keepalive is not saved and
+        // is re-initialized on restore, while the others are kept alive.
+        let driver_source = create_test_driver_source(driver);
+        let keepalive_disabled = vec!["disabled-device".to_string()];
+
+        let spawner = Arc::new(MockNvmeDriverSpawner::new(
+            Duration::from_millis(10),
+            Duration::from_millis(10),
+        ));
+        let manager = NvmeManager::new(
+            &driver_source,
+            4,
+            true,
+            None,
+            spawner.clone(),
+            Vec::new(),
+            keepalive_disabled.clone(),
+        );
+        let client = manager.client().clone();
+        for pci_id in ["kept-device", "disabled-device"] {
+            let _ = client.get_namespace(pci_id.into(), 1).await; // Ignore mock "error"
+            spawner.get_driver(pci_id).unwrap().set_success_mode(true);
+        }
+
+        let saved_state = manager.save(true).await.unwrap();
+        let saved_ids: Vec<_> = saved_state
+            .nvme_disks
+            .iter()
+            .map(|disk| disk.pci_id.as_str())
+            .collect();
+        assert_eq!(saved_ids, ["kept-device"]);
+
+        // Only the excluded device is shut down; the other is left running.
+        manager.shutdown(true).await;
+        let kept = spawner.get_driver("kept-device").unwrap();
+        let disabled = spawner.get_driver("disabled-device").unwrap();
+        assert_eq!(kept.shutdown_call_count(), 0);
+        assert_eq!(disabled.shutdown_call_count(), 1);
+
+        let spawner = Arc::new(MockNvmeDriverSpawner::new(
+            Duration::from_millis(10),
+            Duration::from_millis(10),
+        ));
+        let manager = NvmeManager::new(
+            &driver_source,
+            4,
+            true,
+            Some(NvmeSavedState {
+                nvme_state: saved_state,
+            }),
+            spawner.clone(),
+            Vec::new(),
+            keepalive_disabled,
+        );
+        let client = manager.client().clone();
+        for pci_id in ["kept-device", "disabled-device"] {
+            let _ = client.get_namespace(pci_id.into(), 1).await; // Ignore mock "error"
+        }
+
+        // The kept device is restored from its saved state, and the excluded
+        // device is initialized from scratch.
+        assert_eq!(spawner.driver_count(), 2);
+        assert!(spawner.was_restored("kept-device"));
+        assert!(!spawner.was_restored("disabled-device"));
+
+        manager.shutdown(false).await;
+    }
+
     #[async_test]
     async fn test_nvme_manager_inspect(driver: DefaultDriver) {
         // Test that NvmeManager's Inspect implementation provides access to device information
@@ -1058,7 +1254,15 @@ async fn test_nvme_manager_inspect(driver: DefaultDriver) {
             Duration::from_millis(10),
         ));
 
-        let manager = NvmeManager::new(&driver_source, 4, false, None, spawner.clone());
+        let manager = NvmeManager::new(
+            &driver_source,
+            4,
+            false,
+            None,
+            spawner.clone(),
+            Vec::new(),
+            Vec::new(),
+        );
         let client = manager.client().clone();
 
         // Create some devices by calling GetNamespace
@@ -1159,7 +1363,15 @@ async fn test_shutdown_flag_errors_during_load(driver: DefaultDriver) {
             Duration::from_millis(10),
         ));
 
-        let manager = NvmeManager::new(&driver_source, 4, false, None, spawner);
+        let manager = NvmeManager::new(
+            &driver_source,
+            4,
+            false,
+            None,
+            spawner,
+            Vec::new(),
+            Vec::new(),
+        );
 
         let client = manager.client().clone();
 
@@ -1176,6 +1388,47 @@ async fn test_shutdown_flag_errors_during_load(driver: DefaultDriver) {
         );
     }
 
+    #[async_test]
+    async fn test_namespace_filter_rejects_nsid(driver: DefaultDriver) {
+        // A namespace that is not on the allow-list is rejected without
+        // loading the driver.
+        let driver_source = create_test_driver_source(driver);
+        let spawner = Arc::new(MockNvmeDriverSpawner::new(
+            Duration::from_millis(10),
+            Duration::from_millis(10),
+        ));
+
+        let pci_id = "0000:00:04.0".to_string();
+        let manager = NvmeManager::new(
+            &driver_source,
+            4,
+            false,
+            None,
+            spawner.clone(),
+            vec![NvmeNamespaceFilter {
+                pci_id: pci_id.clone(),
+                allowed_nsids: vec![1],
+                allowed_nguids: Vec::new(),
+            }],
+            Vec::new(),
+        );
+        let client = manager.client().clone();
+
+        let error_msg = client
+            .get_namespace(pci_id.clone(), 2)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error_msg.contains("not allowed by vtl2 settings"));
+        assert_eq!(spawner.driver_count(), 0);
+
+        // An allowed namespace goes on to the driver (which fails in the mock).
+        let _ = client.get_namespace(pci_id.clone(), 1).await;
+        assert_eq!(spawner.driver_count(), 1);
+
+        manager.shutdown(false).await;
+    }
+
     #[async_test]
     async fn test_driver_manager_shutdown_during_operations(driver: DefaultDriver) {
         // Test multiple concurrent operations when driver manager is shut down
@@ -1270,7 +1523,15 @@ async fn test_get_namespace_no_client_after_failed_load(driver: DefaultDriver) {
         // Set spawner to fail creation
         spawner.set_fail_create(true);
 
-        let manager = NvmeManager::new(&driver_source, 4, false, None, spawner);
+        let manager = NvmeManager::new(
+            &driver_source,
+            4,
+            false,
+            None,
+            spawner,
+            Vec::new(),
+            Vec::new(),
+        );
 
         let client = manager.client().clone();
 
@@ -1298,7 +1559,15 @@ async fn test_multiple_shutdown_scenarios(driver: DefaultDriver) {
 
         // Test 1: Shutdown before any operations
         {
-            let manager = NvmeManager::new(&driver_source, 4, false, None, spawner.clone());
+            let manager = NvmeManager::new(
+                &driver_source,
+                4,
+                false,
+                None,
+                spawner.clone(),
+                Vec::new(),
+                Vec::new(),
+            );
             let client = manager.client().clone();
 
             manager.shutdown(false).await;
@@ -1310,7 +1579,15 @@ async fn test_multiple_shutdown_scenarios(driver: DefaultDriver) {
 
         // Test 2: Shutdown after successful operations
         {
-            let manager = NvmeManager::new(&driver_source, 4, false, None, spawner.clone());
+            let manager = NvmeManager::new(
+                &driver_source,
+                4,
+                false,
+                None,
+                spawner.clone(),
+                Vec::new(),
+                Vec::new(),
+            );
             let client = manager.client().clone();
 
             // This will fail due to mock, but should create the driver manager
@@ -118,6 +118,11 @@ async fn create_driver(
         save_restore_supported: bool,
         saved_state: Option<&nvme_driver::save_restore::NvmeDriverSavedState>,
     ) -> Result<Box<dyn NvmeDevice>, NvmeSpawnerError>;
+
+    /// Returns whether the given PCI device is compatible with NVMe keepalive.
+    fn keepalive_compatible(&self, pci_id: &str) -> bool {
+        is_nvme_keepalive_compatible(pci_id)
+    }
 }
 
 /// Returns whether the given PCI device is compatible with NVMe keepalive.
@@ -314,6 +314,16 @@ pub struct Options {
     /// The default time to wait in milliseconds for dump collection during a
     /// panic in servicing.
     pub servicing_timeout_dump_collection_in_ms: u64,
+
+    /// (OPENHCL_LIVENESS_TIMEOUT_IN_SECONDS=\<number\>) (default: disabled)
+    /// Take a live dump if the worker stops making progress for this many
+    /// seconds.
+    pub liveness_timeout_in_seconds: Option<u64>,
+
+    /// (OPENHCL_VMBUS_RELAY_BLOCK=\<guid\>,\<guid\>,...) (default: none)
+    /// Don't relay host vmbus channels whose interface (device class) or
+    /// instance ID is in this list, hiding them from the guest.
+    pub vmbus_relay_block: Vec<guid::Guid>,
 }
 
 impl Options {
@@ -500,6 +510,19 @@ fn parse_number(value: Option<&OsString>) -> anyhow::Result<Option<u64>> {
             parse_legacy_env_number("OPENHCL_CONFIG_TIMEOUT_IN_SECONDS")?.unwrap_or(5);
         let servicing_timeout_dump_collection_in_ms =
             parse_env_number("OPENHCL_SERVICING_TIMEOUT_DUMP_COLLECTION_IN_MS")?.unwrap_or(500);
+        let liveness_timeout_in_seconds =
+            parse_env_number("OPENHCL_LIVENESS_TIMEOUT_IN_SECONDS")?.filter(|&x| x != 0);
+        let vmbus_relay_block = read_env("OPENHCL_VMBUS_RELAY_BLOCK")
+            .map(|x| {
+                x.to_string_lossy()
+                    .split(',')
+                    .filter(|s| !s.trim().is_empty())
+                    .map(|s| s.trim().parse())
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()
+            .context("parsing OPENHCL_VMBUS_RELAY_BLOCK")?
+            .unwrap_or_default();
 
         let mut args = std::env::args().chain(extra_args);
         // Skip our own filename.
@@ -567,6 +590,8 @@ fn parse_number(value: Option<&OsString>) -> anyhow::Result<Option<u64>> {
             disable_lower_vtl_timer_virt,
             config_timeout_in_seconds,
             servicing_timeout_dump_collection_in_ms,
+            liveness_timeout_in_seconds,
+            vmbus_relay_block,
         })
     }
 
@@ -7,6 +7,7 @@
 
 use crate::worker::FirmwareType;
 use anyhow::Context as _;
+use thiserror::Error;
 use vmcore::save_restore::SavedStateBlob;
 
 mod state {
@@ -92,6 +93,56 @@ pub struct ServicingInitState {
         pub vmbus_client: Option<vmbus_client::SavedState>,
         #[mesh(10003)]
         pub mana_state: Option<Vec<ManaSavedState>>,
+        /// The build of the paravisor that saved this state.
+        #[mesh(10004)]
+        pub saved_by: Option<SavedByBuild>,
+        /// What restoring this state requires of the paravisor. Older
+        /// paravisors do not record this.
+        #[mesh(10005)]
+        pub requirements: Option<ServicingRequirements>,
+    }
+
+    /// What servicing state requires of the paravisor that restores it.
+    ///
+    /// This is checked before any state is restored, so that an incompatible
+    /// paravisor rejects the state with a specific reason rather than failing
+    /// part way through the restore.
+    #[derive(Protobuf, Debug, Clone)]
+    #[mesh(package = "underhill")]
+    pub struct ServicingRequirements {
+        /// The servicing state format version.
+        #[mesh(1)]
+        pub version: u32,
+        /// The features the state depends on, as [`feature`](super::feature)
+        /// bits.
+        #[mesh(2)]
+        pub features: u64,
+        /// The size of the VTL2 private pool holding memory that was kept
+        /// alive across servicing.
+        #[mesh(3)]
+        pub private_pool_bytes: u64,
+    }
+
+    /// Identifies the paravisor build that saved the servicing state, so that
+    /// restore failures can be attributed to a specific source version.
+    #[derive(Protobuf)]
+    #[mesh(package = "underhill")]
+    pub struct SavedByBuild {
+        #[mesh(1)]
+        pub scm_revision: String,
+        #[mesh(2)]
+        pub openhcl_version: String,
+    }
+
+    impl SavedByBuild {
+        /// Returns the build information for the running paravisor.
+        pub fn current() -> Self {
+            let info = build_info::get();
+            Self {
+                scm_revision: info.scm_revision().to_owned(),
+                openhcl_version: info.openhcl_version().to_owned(),
+            }
+        }
     }
 
     #[derive(Protobuf)]
@@ -168,6 +219,74 @@ pub fn fix_post_restore(&mut self) -> anyhow::Result<()> {
     }
 }
 
+/// The servicing state format version written by this build. Increment this
+/// when changing the servicing state in a way that older builds cannot
+/// restore.
+const SERVICING_STATE_VERSION: u32 = 1;
+
+/// The oldest servicing state format version this build can restore.
+const MIN_SERVICING_STATE_VERSION: u32 = 1;
+
+/// Features that servicing state can depend on.
+pub mod feature {
+    /// NVMe devices were kept alive across servicing.
+    pub const NVME_KEEPALIVE: u64 = 1 << 0;
+    /// MANA devices were kept alive across servicing.
+    pub const MANA_KEEPALIVE: u64 = 1 << 1;
+
+    /// The features this build can restore.
+    pub(super) const SUPPORTED: u64 = NVME_KEEPALIVE | MANA_KEEPALIVE;
+}
+
+/// The reason servicing state cannot be restored by this paravisor.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ServicingIncompatibility {
+    #[error(
+        "servicing state version {version} is not in the supported range {MIN_SERVICING_STATE_VERSION}..={SERVICING_STATE_VERSION}"
+    )]
+    Version { version: u32 },
+    #[error("servicing state requires unsupported features {features:#x}")]
+    Features { features: u64 },
+    #[error(
+        "servicing state requires a {required} byte VTL2 private pool, but only {available} bytes are available"
+    )]
+    PrivatePool { required: u64, available: u64 },
+}
+
+impl ServicingRequirements {
+    /// Returns the requirements for state saved by this build.
+    pub fn current(features: u64, private_pool_bytes: u64) -> Self {
+        Self {
+            version: SERVICING_STATE_VERSION,
+            features,
+            private_pool_bytes,
+        }
+    }
+
+    /// Checks that this build can restore the state, given the size of its
+    /// VTL2 private pool.
+    pub fn check(&self, private_pool_bytes: u64) -> Result<(), ServicingIncompatibility> {
+        if !(MIN_SERVICING_STATE_VERSION..=SERVICING_STATE_VERSION).contains(&self.version) {
+            return Err(ServicingIncompatibility::Version {
+                version: self.version,
+            });
+        }
+        let unsupported = self.features & !feature::SUPPORTED;
+        if unsupported != 0 {
+            return Err(ServicingIncompatibility::Features {
+                features: unsupported,
+            });
+        }
+        if self.private_pool_bytes > private_pool_bytes {
+            return Err(ServicingIncompatibility::PrivatePool {
+                required: self.private_pool_bytes,
+                available: private_pool_bytes,
+            });
+        }
+        Ok(())
+    }
+}
+
 impl From<FirmwareType> for Firmware {
     fn from(value: FirmwareType) -> Self {
         match value {
@@ -246,6 +365,8 @@ fn from(state: Option<ServicingInitState>) -> Self {
                     mana_state,
                     dma_manager_state,
                     vmbus_client,
+                    saved_by: _saved_by,
+                    requirements: _requirements,
                 } = state;
 
                 OptionServicingInitState {
@@ -271,3 +392,42 @@ fn from(state: Option<ServicingInitState>) -> Self {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_requirements() {
+        let requirements = ServicingRequirements::current(feature::NVME_KEEPALIVE, 0x1000);
+        requirements.check(0x1000).unwrap();
+
+        assert_eq!(
+            requirements.check(0),
+            Err(ServicingIncompatibility::PrivatePool {
+                required: 0x1000,
+                available: 0
+            })
+        );
+
+        let newer = ServicingRequirements {
+            version: SERVICING_STATE_VERSION + 1,
+            ..requirements.clone()
+        };
+        assert_eq!(
+            newer.check(0x1000),
+            Err(ServicingIncompatibility::Version {
+                version: SERVICING_STATE_VERSION + 1
+            })
+        );
+
+        let unknown_feature = ServicingRequirements {
+            features: feature::MANA_KEEPALIVE | 1 << 63,
+            ..requirements
+        };
+        assert_eq!(
+            unknown_feature.check(0x1000),
+            Err(ServicingIncompatibility::Features { features: 1 << 63 })
+        );
+    }
+}
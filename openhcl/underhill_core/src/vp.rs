@@ -42,6 +42,36 @@ pub(crate) async fn spawn_vps(
     Ok(())
 }
 
+/// Requests that a VP currently running in the sidecar kernel be moved to
+/// VTL2 Linux, as if it had hit an exit that required VTL2 processing.
+///
+/// This only starts the promotion; the CPU is onlined and the VP restarted
+/// asynchronously. Promotion is one way; there is no support for returning
+/// a VP to the sidecar kernel once its CPU has been onlined.
+pub(crate) fn promote_sidecar_vp(
+    tp: &AffinitizedThreadpool,
+    partition: &virt_mshv_vtl::UhPartition,
+    vp_index: u32,
+) -> anyhow::Result<()> {
+    if partition
+        .sidecar_base_cpu(virt::VpIndex::new(vp_index))
+        .is_none()
+    {
+        anyhow::bail!("vp {vp_index} is not managed by sidecar");
+    }
+    // VP index is used directly as the Linux CPU number. See `spawn_vps`.
+    let cpu = vp_index;
+    if underhill_threadpool::is_cpu_online(cpu)? {
+        anyhow::bail!("vp {vp_index} is already running in VTL2 Linux");
+    }
+    tracing::info!(CVM_ALLOWED, cpu, "promoting sidecar VP on request");
+    // Spawning any task on the target CPU's thread runs the spawn notifier
+    // registered by `spawn_sidecar_vp`, which onlines the CPU and migrates the
+    // VP.
+    tp.driver(cpu).spawn("promote-sidecar", async {}).detach();
+    Ok(())
+}
+
 /// An object to spawn and run a VP.
 struct VpSpawner {
     vp: virt_mshv_vtl::UhProcessorBox,
@@ -17,6 +17,7 @@
 }
 
 use crate::ControlRequest;
+use crate::boot_timeline::BootTimeline;
 use crate::dispatch::LoadedVm;
 use crate::dispatch::LoadedVmNetworkSettings;
 use crate::dispatch::vtl2_settings_worker::InitialControllers;
@@ -325,6 +326,10 @@ pub struct UnderhillEnvCfg {
     pub config_timeout_in_seconds: u64,
     /// The timeout in milliseconds for dump collection during a panic in servicing.
     pub servicing_timeout_dump_collection_in_ms: u64,
+    /// The timeout in seconds after which a stalled worker is live dumped.
+    pub liveness_timeout_in_seconds: Option<u64>,
+    /// Interface and instance IDs of host vmbus channels not to relay.
+    pub vmbus_relay_block: Vec<Guid>,
 }
 
 /// Bundle of config + runtime objects for hooking into the underhill remote
@@ -546,10 +551,23 @@ async fn new_or_restart(
         }
 
         if let Some(state) = &mut servicing_state {
+            // Older paravisors do not record their build.
+            if let Some(saved_by) = &state.init_state.saved_by {
+                tracing::info!(
+                    CVM_ALLOWED,
+                    scm_revision = %saved_by.scm_revision,
+                    openhcl_version = %saved_by.openhcl_version,
+                    "servicing state saved by"
+                );
+            }
             state
                 .fix_post_restore()
                 .context("failed to fix up servicing state on restore")?;
         }
+        let saved_by = servicing_state
+            .as_ref()
+            .and_then(|s| s.init_state.saved_by.as_ref())
+            .map(|saved_by| saved_by.openhcl_version.clone());
 
         let is_post_servicing = servicing_state.is_some();
         let correlation_id = (servicing_state.as_ref()).and_then(|s| s.init_state.correlation_id);
@@ -559,7 +577,7 @@ async fn new_or_restart(
         };
 
         // Build the VM.
-        let mut vm = new_underhill_vm(
+        let vm = new_underhill_vm(
             get_spawner,
             &threadpool,
             early_init_driver,
@@ -579,7 +597,20 @@ async fn new_or_restart(
             CVM_ALLOWED,
             correlation_id = correlation_id.map(tracing::field::display)
         ))
-        .await?;
+        .await;
+
+        // The host is waiting for the result of restoring its saved state, so
+        // report a failure to build the VM, such as when the state is
+        // incompatible, so that the host can roll back.
+        let mut vm = match vm {
+            Ok(vm) => vm,
+            Err(err) => {
+                if is_post_servicing && saved_state_from_host {
+                    get_client.report_restore_result_to_host(false).await;
+                }
+                return Err(err);
+            }
+        };
 
         LOADED_VM.store(&vm);
 
@@ -595,14 +626,25 @@ async fn new_or_restart(
                 .await;
 
             // If we received saved state from the host then notify the host
-            // that servicing was successful.
-            //
-            // TODO: send error string to host.
+            // whether servicing was successful. The notification only carries
+            // success or failure, so log the error first to get it to the
+            // host via the trace channel.
             if saved_state_from_host {
+                if let Err(err) = &r {
+                    tracing::error!(
+                        CVM_ALLOWED,
+                        error = err.as_ref() as &dyn std::error::Error,
+                        saved_by = saved_by.as_deref(),
+                        "servicing restore failed"
+                    );
+                }
                 get_client.report_restore_result_to_host(r.is_ok()).await;
             }
 
-            r.context("failed to restore")?;
+            r.with_context(|| match &saved_by {
+                Some(version) => format!("failed to restore state saved by version {version}"),
+                None => "failed to restore".to_owned(),
+            })?;
         }
 
         Ok(Self {
@@ -1471,11 +1513,13 @@ async fn new_underhill_vm(
         control_send,
     } = params;
 
-    if let Ok(kernel_boot_time) = std::env::var("KERNEL_BOOT_TIME") {
-        if let Ok(kernel_boot_time_ns) = kernel_boot_time.parse::<u64>() {
-            tracing::info!(CVM_ALLOWED, kernel_boot_time_ns, "kernel boot time");
-        }
+    let kernel_boot_time_ns = std::env::var("KERNEL_BOOT_TIME")
+        .ok()
+        .and_then(|t| t.parse::<u64>().ok());
+    if let Some(kernel_boot_time_ns) = kernel_boot_time_ns {
+        tracing::info!(CVM_ALLOWED, kernel_boot_time_ns, "kernel boot time");
     }
+    let mut boot_timeline = BootTimeline::new(kernel_boot_time_ns);
 
     // Read the initial configuration from the IGVM parameters.
     let (runtime_params, measured_vtl2_info) =
@@ -1597,6 +1641,28 @@ async fn new_underhill_vm(
 
     let driver_source = VmTaskDriverSource::new(ThreadpoolBackend::new(tp.clone()));
 
+    // Reject servicing state that this paravisor cannot restore before
+    // restoring any of it.
+    if let Some(requirements) = servicing_state
+        .as_ref()
+        .and_then(|s| s.requirements.as_ref())
+    {
+        let private_pool_bytes = runtime_params
+            .private_pool_ranges()
+            .iter()
+            .map(|r| r.range.len())
+            .sum();
+        if let Err(err) = requirements.check(private_pool_bytes) {
+            tracing::error!(
+                CVM_ALLOWED,
+                error = &err as &dyn std::error::Error,
+                ?requirements,
+                "servicing state is incompatible with this paravisor"
+            );
+            return Err(err).context("servicing state is incompatible with this paravisor");
+        }
+    }
+
     let is_restoring = servicing_state.is_some();
     let servicing_state = OptionServicingInitState::from(servicing_state);
 
@@ -2229,6 +2295,7 @@ async fn new_underhill_vm(
         .context("failed to create partition")?;
 
     let partition = Arc::new(partition);
+    boot_timeline.mark("partition_created", partition.reference_time());
 
     // By default, scale the max QD by the number of VPs to save memory
     // on smaller VMs, up to a QD of 256.
@@ -2251,7 +2318,12 @@ async fn new_underhill_vm(
     let (halt_vps, halt_request_recv) = Halt::new();
     let halt_vps = Arc::new(halt_vps);
 
-    resolver.add_resolver(vmm_core::platform_resolvers::HaltResolver(halt_vps.clone()));
+    // OpenHCL reports guest crashes to the host with a crash notification
+    // instead of halting.
+    resolver.add_resolver(vmm_core::platform_resolvers::HaltResolver {
+        halt: halt_vps.clone(),
+        halt_on_guest_crash: false,
+    });
     #[cfg(guest_arch = "x86_64")]
     resolver.add_resolver(vmm_core::platform_resolvers::IoApicRoutingResolver(
         partition.ioapic_routing(),
@@ -2297,6 +2369,14 @@ async fn new_underhill_vm(
         crate::inspect_proc::periodic_telemetry_task(driver_source.simple()),
     );
 
+    let liveness_watchdog = env_cfg.liveness_timeout_in_seconds.map(|timeout| {
+        crate::liveness::LivenessWatchdog::new(
+            tp,
+            driver_source.simple(),
+            Duration::from_secs(timeout),
+        )
+    });
+
     let nvme_manager = if env_cfg.nvme_vfio {
         // TODO: reevaluate enablement of nvme save restore when private pool
         // save restore to bootshim is available.
@@ -2313,6 +2393,16 @@ async fn new_underhill_vm(
                 is_isolated: isolation.is_isolated(),
                 dma_client_spawner: dma_manager.client_spawner(),
             }),
+            dps.general
+                .vtl2_settings
+                .as_ref()
+                .map(|settings| settings.fixed.nvme_namespace_filters.clone())
+                .unwrap_or_default(),
+            dps.general
+                .vtl2_settings
+                .as_ref()
+                .map(|settings| settings.fixed.nvme_keepalive_disabled.clone())
+                .unwrap_or_default(),
         );
 
         tracing::debug!(
@@ -2578,6 +2668,7 @@ async fn new_underhill_vm(
                 mem_layout: &mem_layout,
                 cache_topology: None,
                 pcie_host_bridges: &vec![],
+                extra_tables: &[],
                 arch: vmm_core::acpi_builder::AcpiArchConfig::X86 {
                     with_ioapic: capabilities.with_ioapic,
                     with_pic: capabilities.with_pic,
@@ -3177,6 +3268,7 @@ async fn new_underhill_vm(
                     .await
                     .context("failed to connect to vmbus")?
             };
+            boot_timeline.mark("vmbus_connected", partition.reference_time());
 
             let mut filter = vmbus_client::filter::ClientFilterBuilder::new();
 
@@ -3288,6 +3380,7 @@ async fn new_underhill_vm(
                 client.access().clone(),
                 connection,
                 intercept_list,
+                env_cfg.vmbus_relay_block.iter().copied().collect(),
             )
             .await
             .context("failed to create host vmbus transport")?;
@@ -3520,6 +3613,7 @@ async fn new_underhill_vm(
     let control_send = Arc::new(Mutex::new(Some(control_send)));
 
     let (chipset, devices) = chipset_builder.build()?;
+    boot_timeline.mark("devices_initialized", partition.reference_time());
     let (fatal_error_send, fatal_error_recv) = mesh::channel();
     let control_send_clone = control_send.clone();
     let fatal_error_policy = if env_cfg.halt_on_guest_halt {
@@ -3647,12 +3741,14 @@ async fn new_underhill_vm(
         control_send,
 
         _periodic_telemetry_task: periodic_telemetry_task,
+        _liveness_watchdog: liveness_watchdog,
         nvme_keep_alive: env_cfg.nvme_keep_alive,
         mana_keep_alive: env_cfg.mana_keep_alive,
         test_configuration: env_cfg.test_configuration,
         dma_manager,
         config_timeout_in_seconds: env_cfg.config_timeout_in_seconds,
         servicing_timeout_dump_collection_in_ms: env_cfg.servicing_timeout_dump_collection_in_ms,
+        boot_timeline,
         #[cfg(feature = "mem-profile-tracing")]
         profiler: mem_profile_tracing::HeapProfiler::new(),
     };
@@ -3828,6 +3924,12 @@ enum HaltRequest {
                 tracing::info!(CVM_ALLOWED, vp, "hardware breakpoint");
                 continue;
             }
+            // OpenHCL reports guest crashes to the host from the crash MSR
+            // handler, and its partition never halts for them.
+            HaltReason::GuestCrash { vp, .. } => {
+                tracing::warn!(CVM_ALLOWED, vp, "unexpected guest crash halt");
+                continue;
+            }
         };
 
         if halt_on_guest_halt {
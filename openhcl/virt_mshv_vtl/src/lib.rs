@@ -88,6 +88,7 @@
 use hvdef::hypercall::HvInterceptType;
 use inspect::Inspect;
 use inspect::InspectMut;
+use inspect_counters::SharedCounter;
 use memory_range::MemoryRange;
 use pal::unix::affinity;
 use pal::unix::affinity::CpuSet;
@@ -121,6 +122,9 @@
 use vm_topology::memory::MemoryLayout;
 use vm_topology::processor::ProcessorTopology;
 use vm_topology::processor::TargetVpInfo;
+use vmcore::interrupt_latency::InterruptLatency;
+#[cfg(guest_arch = "x86_64")]
+use vmcore::interrupt_latency::Stage;
 use vmcore::monitor::MonitorPage;
 use vmcore::reference_time::GetReferenceTime;
 use vmcore::reference_time::ReferenceTimeResult;
@@ -253,6 +257,65 @@ struct UhPartitionInner {
     device_vector_table: RwLock<IrrBitmap>,
     vmbus_relay: bool,
     synic_ports: virt::synic::SynicPortMap,
+    msi_stats: MsiStats,
+    /// Latency of device interrupts from request to injection, tracked under
+    /// [`latency_vector`].
+    interrupt_latency: InterruptLatency,
+}
+
+/// Returns the key under which interrupt latency is tracked for `vector` in
+/// `vtl`.
+#[cfg(guest_arch = "x86_64")]
+fn latency_vector(vtl: GuestVtl, vector: u8) -> u32 {
+    (u32::from(u8::from(vtl)) << 8) | u32::from(vector)
+}
+
+/// Counts of device interrupts by how they were delivered.
+#[derive(Inspect, Default)]
+struct MsiStats {
+    /// Handed directly to the hypervisor for delivery.
+    hypervisor: SharedCounter,
+    /// Latched in the emulated APIC of the VP that runs on the requesting
+    /// thread, which picks it up without being woken.
+    apic_local: SharedCounter,
+    /// Latched in the emulated APIC, waking the target VP.
+    apic_wake: SharedCounter,
+    /// Latched in the emulated APIC without waking a VP, because the vector
+    /// was already pending in the target.
+    apic_coalesced: SharedCounter,
+    /// Dropped by the emulated APIC, because there was no target APIC or
+    /// the targets were disabled.
+    apic_undeliverable: SharedCounter,
+}
+
+/// Where a VTL's device interrupts are delivered.
+#[cfg(guest_arch = "x86_64")]
+#[derive(Debug, PartialEq, Eq)]
+enum MsiTarget<T> {
+    /// The VTL's emulated APIC.
+    Apic(T),
+    /// The hypervisor, via [`Hcl::request_interrupt`].
+    Hypervisor,
+}
+
+#[cfg(guest_arch = "x86_64")]
+impl<T> MsiTarget<T> {
+    /// Returns the target for a VTL with the given emulated APIC, if any.
+    ///
+    /// Hardware-isolated partitions cannot ask the hypervisor to request
+    /// interrupts, so their VTLs must always have an emulated APIC.
+    fn new(isolation: IsolationType, lapic: Option<T>) -> Self {
+        match lapic {
+            Some(lapic) => Self::Apic(lapic),
+            None => {
+                assert!(
+                    !isolation.is_hardware_isolated(),
+                    "hardware-isolated partitions must deliver msis through the emulated apic"
+                );
+                Self::Hypervisor
+            }
+        }
+    }
 }
 
 #[derive(Inspect)]
@@ -874,6 +937,14 @@ pub fn reference_time(&self) -> u64 {
                 .expect("should not fail to get the reference time")
         }
     }
+
+    /// Returns the base CPU that manages the given VP when sidecar is enabled.
+    ///
+    /// Returns `None` if sidecar is disabled or `vp_index` is out of range.
+    pub fn sidecar_base_cpu(&self, vp_index: VpIndex) -> Option<u32> {
+        self.inner.vp(vp_index)?;
+        self.inner.hcl.sidecar_base_cpu(vp_index.index())
+    }
 }
 
 impl virt::Partition for UhPartition {
@@ -992,6 +1063,7 @@ fn inspect_extra(&self, resp: &mut inspect::Response<'_>) {
                 }
             }),
         );
+        resp.field("hvcall_page_pool", self.hcl.hvcall_page_pool_stats());
 
         // Wake VPs to propagate updates.
         if wake_vps {
@@ -1349,13 +1421,39 @@ fn signal_msi(&self, _devid: Option<u32>, address: u64, data: u32) {
 impl UhPartitionInner {
     #[cfg(guest_arch = "x86_64")]
     fn request_msi(&self, vtl: GuestVtl, request: MsiRequest) {
-        if let Some(lapic) = self.lapic(vtl) {
+        let (address, data) = request.as_x86();
+        let latency_vector = latency_vector(vtl, data.vector());
+        self.interrupt_latency.signal(latency_vector);
+        if let MsiTarget::Apic(lapic) = MsiTarget::new(self.isolation, self.lapic(vtl)) {
             tracing::trace!(?request, "interrupt");
-            lapic.request_interrupt(request.address, request.data, |vp_index| {
-                self.vp(vp_index).unwrap().wake(vtl, WakeReason::INTCON)
+            let (mut local, mut woke) = (false, false);
+            let accepted = lapic.request_interrupt(request.address, request.data, |vp_index| {
+                let vp = self.vp(vp_index).unwrap();
+                // Fast path: if the target runs on this thread, it is not in
+                // the guest and will scan its APIC before it next enters it,
+                // so skip the waker.
+                if vp.is_current_thread() {
+                    local = true;
+                    vp.wake_local(vtl, WakeReason::INTCON);
+                } else {
+                    woke = true;
+                    vp.wake(vtl, WakeReason::INTCON);
+                }
             });
+            // The injection is recorded when the VP scans its APIC.
+            self.interrupt_latency.record(latency_vector, Stage::Queued);
+            let counter = if !accepted {
+                &self.msi_stats.apic_undeliverable
+            } else if woke {
+                &self.msi_stats.apic_wake
+            } else if local {
+                &self.msi_stats.apic_local
+            } else {
+                &self.msi_stats.apic_coalesced
+            };
+            counter.increment();
         } else {
-            let (address, data) = request.as_x86();
+            self.msi_stats.hypervisor.increment();
             if let Err(err) = self.hcl.request_interrupt(
                 request.hv_x86_interrupt_control(),
                 address.virt_destination().into(),
@@ -1370,6 +1468,12 @@ fn request_msi(&self, vtl: GuestVtl, request: MsiRequest) {
                     "failed to request msi"
                 );
             }
+            // The hypervisor injects the interrupt, so this is as far as it
+            // can be tracked. Discard the pending signal so that the next one
+            // starts afresh.
+            self.interrupt_latency
+                .record(latency_vector, Stage::Delivered);
+            self.interrupt_latency.discard(latency_vector);
         }
     }
 
@@ -2010,6 +2114,8 @@ pub async fn build(
             intercept_debug_exceptions: params.intercept_debug_exceptions,
             vmbus_relay: late_params.vmbus_relay,
             synic_ports: Default::default(),
+            msi_stats: Default::default(),
+            interrupt_latency: InterruptLatency::new(),
         });
 
         if cfg!(guest_arch = "x86_64") {
@@ -2620,3 +2726,29 @@ fn validate_vtl_gpa_flags(
 
     true
 }
+
+#[cfg(all(test, guest_arch = "x86_64"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_msi_target_hardware_isolated() {
+        for isolation in [IsolationType::Snp, IsolationType::Tdx] {
+            assert_eq!(MsiTarget::new(isolation, Some(())), MsiTarget::Apic(()));
+        }
+    }
+
+    #[test]
+    fn test_msi_target_hypervisor() {
+        for isolation in [IsolationType::None, IsolationType::Vbs] {
+            assert_eq!(MsiTarget::new(isolation, Some(())), MsiTarget::Apic(()));
+            assert_eq!(MsiTarget::<()>::new(isolation, None), MsiTarget::Hypervisor);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_msi_target_hardware_isolated_without_apic() {
+        let _ = MsiTarget::<()>::new(IsolationType::Snp, None);
+    }
+}
@@ -420,6 +420,8 @@ fn hv_mut(&mut self, _vtl: GuestVtl) -> Option<&mut ProcessorVtlHv> {
         None
     }
 
+    fn reset_stats(&mut self) {}
+
     fn handle_vp_start_enable_vtl_wake(_this: &mut UhProcessor<'_, Self>, _vtl: GuestVtl) {
         todo!()
     }
@@ -2,6 +2,7 @@
 // Licensed under the MIT License.
 
 use crate::UhProcessor;
+use crate::latency_vector;
 use crate::processor::HardwareIsolatedBacking;
 use cvm_tracing::CVM_ALLOWED;
 use hcl::GuestVtl;
@@ -9,6 +10,7 @@
 use virt::vp::MpState;
 use virt::x86::SegmentRegister;
 use virt_support_apic::ApicWork;
+use vmcore::interrupt_latency::Stage;
 
 pub(crate) trait ApicBacking<'b, B: HardwareIsolatedBacking> {
     fn vp(&mut self) -> &mut UhProcessor<'b, B>;
@@ -100,6 +102,11 @@ pub(crate) fn poll_apic_core<'b, B: HardwareIsolatedBacking, T: ApicBacking<'b,
 
         if let Some(vector) = interrupt {
             apic_backing.handle_interrupt(vtl, vector);
+            apic_backing
+                .vp()
+                .partition
+                .interrupt_latency
+                .record(latency_vector(vtl, vector), Stage::Injected);
         }
 
         if extint {
@@ -4,6 +4,7 @@
 //! Common processor support for hardware-isolated partitions.
 
 pub mod apic;
+pub mod save_restore;
 pub mod tlb_lock;
 
 use super::UhEmulationState;
@@ -22,6 +23,7 @@
 use cvm_tracing::CVM_ALLOWED;
 use guestmem::GuestMemory;
 use guestmem::GuestMemoryErrorKind;
+use hcl::protocol::hcl_intr_offload_flags;
 use hv1_emulator::RequestInterrupt;
 use hv1_hypercall::HvRepResult;
 use hv1_structs::ProcessorSet;
@@ -54,6 +56,7 @@
 use virt::io::CpuIo;
 use virt::irqcon::MsiRequest;
 use virt::vp::AccessVpState;
+use virt::vp::MpState;
 use virt::x86::MsrError;
 use virt::x86::MsrErrorExt;
 use virt_support_x86emu::emulate::TranslateGvaSupport;
@@ -2129,6 +2132,72 @@ pub(crate) fn cvm_deliver_exit_pending_event(&mut self) {
         }
     }
 
+    /// Passes the halt state of `vtl` to the kernel through the run page
+    /// before running it.
+    ///
+    /// While the VP is halted, the kernel parks the VP thread until it is
+    /// signaled instead of running the VP. If interrupt offload is enabled
+    /// and the VP is halted due to HLT or idle, the kernel can also unhalt
+    /// the VP and inject an interrupt itself, without returning to user mode.
+    ///
+    /// Returns whether the kernel knows why the VP is halted, and so may
+    /// unhalt it.
+    pub(crate) fn set_kernel_halt_hints(
+        &mut self,
+        vtl: GuestVtl,
+        tlb_halt: bool,
+        offload_enabled: bool,
+    ) -> bool {
+        let lapic = &mut self.backing.cvm_state_mut().lapics[vtl];
+        let activity = lapic.activity;
+        let kernel_known_state =
+            matches!(activity, MpState::Running | MpState::Halted | MpState::Idle);
+        let halted = activity != MpState::Running || tlb_halt;
+        let stats = &mut lapic.halt_stats;
+        match (stats.halted, halted) {
+            (false, true) => stats.parked.increment(),
+            (true, false) => stats.unparked.increment(),
+            _ => {}
+        }
+        stats.halted = halted;
+        let x2apic_enabled = lapic.lapic.x2apic_enabled();
+
+        self.runner.set_halted(halted);
+        *self.runner.offload_flags_mut() = hcl_intr_offload_flags::new()
+            .with_offload_intr_inject(offload_enabled)
+            .with_offload_x2apic(offload_enabled && x2apic_enabled)
+            .with_halted_other(tlb_halt || !kernel_known_state)
+            .with_halted_hlt(activity == MpState::Halted)
+            .with_halted_idle(activity == MpState::Idle);
+
+        kernel_known_state
+    }
+
+    /// Updates the activity state of `vtl` from the halt state left in the
+    /// run page by the kernel, after running the VP with halt hints set by
+    /// [`Self::set_kernel_halt_hints`].
+    pub(crate) fn sync_kernel_halt_state(&mut self, vtl: GuestVtl) {
+        let offload_flags = *self.runner.offload_flags_mut();
+        let lapic = &mut self.backing.cvm_state_mut().lapics[vtl];
+        let activity = lapic.activity;
+        lapic.activity = match (offload_flags.halted_hlt(), offload_flags.halted_idle()) {
+            (false, false) => MpState::Running,
+            (true, false) => MpState::Halted,
+            (false, true) => MpState::Idle,
+            (true, true) => {
+                tracelimit::warn_ratelimited!(
+                    CVM_ALLOWED,
+                    "Kernel indicates VP is both halted and idle!"
+                );
+                activity
+            }
+        };
+        if activity != MpState::Running && lapic.activity == MpState::Running {
+            lapic.halt_stats.kernel_unparked.increment();
+            lapic.halt_stats.halted = false;
+        }
+    }
+
     pub(crate) fn hcvm_vtl1_inspectable(&self) -> bool {
         self.backing.cvm_state().vtl1.is_some()
     }
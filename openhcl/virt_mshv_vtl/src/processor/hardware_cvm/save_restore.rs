@@ -0,0 +1,65 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Save/restore of the emulated hypervisor state of hardware-isolated
+//! partitions.
+//!
+//! Guest register state lives in hardware-protected pages and is not saved
+//! here. Servicing of isolated VMs is still rejected by the caller until the
+//! rest of the emulated state, such as the APICs, is saved too.
+
+use crate::UhProcessor;
+use crate::processor::HardwareIsolatedBacking;
+use hcl::GuestVtl;
+use vmcore::save_restore::RestoreError;
+
+pub mod state {
+    use mesh::payload::Protobuf;
+    use vmcore::save_restore::SavedStateRoot;
+
+    #[derive(Protobuf, SavedStateRoot)]
+    #[mesh(package = "underhill.partition.cvm")]
+    pub struct ProcessorSavedState {
+        #[mesh(1)]
+        pub(super) per_vtl: Vec<ProcessorVtlSavedState>,
+    }
+
+    #[derive(Protobuf)]
+    #[mesh(package = "underhill.partition.cvm")]
+    pub struct ProcessorVtlSavedState {
+        #[mesh(1)]
+        pub(super) synic_timers: virt::vp::SynicTimers,
+    }
+}
+
+impl<B: HardwareIsolatedBacking> UhProcessor<'_, B> {
+    pub(crate) fn cvm_save(&mut self) -> state::ProcessorSavedState {
+        let per_vtl = [GuestVtl::Vtl0, GuestVtl::Vtl1]
+            .map(|vtl| {
+                let hv = &self.backing.cvm_state().hv[vtl];
+                state::ProcessorVtlSavedState {
+                    synic_timers: hv.synic.timers(hv.ref_time_now()),
+                }
+            })
+            .into();
+        state::ProcessorSavedState { per_vtl }
+    }
+
+    pub(crate) fn cvm_restore(
+        &mut self,
+        state: state::ProcessorSavedState,
+    ) -> Result<(), RestoreError> {
+        let state::ProcessorSavedState { per_vtl } = state;
+        if per_vtl.len() > 2 {
+            return Err(RestoreError::InvalidSavedState(anyhow::anyhow!(
+                "too many vtls"
+            )));
+        }
+        for (per, vtl) in per_vtl.into_iter().zip([GuestVtl::Vtl0, GuestVtl::Vtl1]) {
+            let hv = &mut self.backing.cvm_state_mut().hv[vtl];
+            let ref_time_now = hv.ref_time_now();
+            hv.synic.set_timers(&per.synic_timers, ref_time_now);
+        }
+        Ok(())
+    }
+}
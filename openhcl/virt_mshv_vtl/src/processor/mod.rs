@@ -67,6 +67,7 @@
 use pal_async::timer::PollTimer;
 use pal_uring::IdleControl;
 use private::BackingPrivate;
+use std::cell::Cell;
 use std::convert::Infallible;
 use std::future::poll_fn;
 use std::marker::PhantomData;
@@ -165,6 +166,7 @@ pub(crate) struct LapicState {
     lapic: LocalApic,
     activity: MpState,
     nmi_pending: bool,
+    halt_stats: HaltStats,
 }
 
 #[cfg(guest_arch = "x86_64")]
@@ -174,10 +176,32 @@ pub fn new(lapic: LocalApic, activity: MpState) -> Self {
             lapic,
             activity,
             nmi_pending: false,
+            halt_stats: Default::default(),
         }
     }
 }
 
+/// Counts of how often the VP thread parked in the kernel while the VP was
+/// halted, and of what woke it.
+#[cfg(guest_arch = "x86_64")]
+#[derive(Inspect, Default)]
+struct HaltStats {
+    /// The VP halted, parking the thread in the kernel until it is
+    /// signaled.
+    parked: inspect_counters::Counter,
+    /// Unhalted in user mode, after the kernel returned for a signal or an
+    /// exit.
+    unparked: inspect_counters::Counter,
+    /// Unhalted by the kernel to inject an interrupt, without returning to
+    /// user mode.
+    kernel_unparked: inspect_counters::Counter,
+    /// Whether the VP was halted the last time it was run, so that a VP that
+    /// returns to user mode and is run again while still halted is not
+    /// counted as parking again.
+    #[inspect(skip)]
+    halted: bool,
+}
+
 struct BackingParams<'a, 'b, T: Backing> {
     partition: &'a UhPartitionInner,
     vp_info: &'a TargetVpInfo,
@@ -260,6 +284,9 @@ fn process_interrupts(
 
         fn inspect_extra(_this: &mut UhProcessor<'_, Self>, _resp: &mut inspect::Response<'_>) {}
 
+        /// Resets the backing-specific exit statistics.
+        fn reset_stats(&mut self);
+
         fn hv(&self, vtl: GuestVtl) -> Option<&ProcessorVtlHv>;
         fn hv_mut(&mut self, vtl: GuestVtl) -> Option<&mut ProcessorVtlHv>;
 
@@ -542,6 +569,27 @@ pub(crate) enum SidecarRemoveExit {
     },
 }
 
+thread_local! {
+    /// The VP whose run loop is the idle task of the current thread, if any.
+    static IDLE_TASK_VP: Cell<Option<VpIndex>> = const { Cell::new(None) };
+}
+
+/// Clears [`IDLE_TASK_VP`] when the VP's run loop exits.
+struct IdleTaskVpGuard;
+
+impl IdleTaskVpGuard {
+    fn new(vp_index: VpIndex) -> Self {
+        IDLE_TASK_VP.set(Some(vp_index));
+        Self
+    }
+}
+
+impl Drop for IdleTaskVpGuard {
+    fn drop(&mut self) {
+        IDLE_TASK_VP.set(None);
+    }
+}
+
 impl UhVpInner {
     // Create a new vp's state.
     pub fn new(cpu_index: u32, vp_info: TargetVpInfo) -> Self {
@@ -571,6 +619,25 @@ pub fn wake(&self, vtl: GuestVtl, reason: WakeReason) {
         }
     }
 
+    /// Returns true if this VP's run loop is the idle task of the current
+    /// thread.
+    ///
+    /// The thread pool polls the idle task before the thread blocks, so while
+    /// the current thread is running other work, the VP is not in the guest and
+    /// will process any wake reasons before it next enters it.
+    pub fn is_current_thread(&self) -> bool {
+        IDLE_TASK_VP.get() == Some(self.vp_info.base.vp_index)
+    }
+
+    /// Sets a wake reason without notifying the VP's waker.
+    ///
+    /// Only valid when [`Self::is_current_thread`] returns true.
+    pub fn wake_local(&self, vtl: GuestVtl, reason: WakeReason) {
+        debug_assert!(self.is_current_thread());
+        let reason = u64::from(reason.0) << (vtl as u8 * 32);
+        self.wake_reasons.fetch_or(reason, Ordering::Release);
+    }
+
     pub fn wake_vtl2(&self) {
         if let Some(waker) = &*self.waker.read() {
             waker.wake_by_ref();
@@ -610,7 +677,14 @@ fn inspect_extra(&mut self, resp: &mut inspect::Response<'_>) {
         .field(
             "sidecar_base_cpu",
             self.partition.hcl.sidecar_base_cpu(self.vp_index().index()),
-        );
+        )
+        .field_mut_with("reset_stats", |v| {
+            let v = v.map_or(Ok(false), |v| v.parse())?;
+            if v {
+                self.backing.reset_stats();
+            }
+            Result::<_, std::str::ParseBoolError>::Ok(v)
+        });
 
         T::inspect_extra(self, resp);
     }
@@ -748,6 +822,13 @@ async fn run_vp(
             nice::nice(1);
         }
 
+        // Let interrupt requests from other tasks on this thread take the
+        // local wake path.
+        let _idle_task_vp = self
+            .idle_control
+            .is_some()
+            .then(|| IdleTaskVpGuard::new(self.inner.vp_info.base.vp_index));
+
         let mut last_waker = None;
 
         // Force deliverability notifications to be reevaluated.
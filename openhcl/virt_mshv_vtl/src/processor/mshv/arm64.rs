@@ -290,6 +290,10 @@ fn hv_mut(&mut self, _vtl: GuestVtl) -> Option<&mut ProcessorVtlHv> {
         None
     }
 
+    fn reset_stats(&mut self) {
+        self.stats = Default::default();
+    }
+
     fn handle_vp_start_enable_vtl_wake(_this: &mut UhProcessor<'_, Self>, _vtl: GuestVtl) {
         unimplemented!()
     }
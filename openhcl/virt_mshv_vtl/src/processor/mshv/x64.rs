@@ -380,6 +380,10 @@ fn hv_mut(&mut self, _vtl: GuestVtl) -> Option<&mut ProcessorVtlHv> {
         None
     }
 
+    fn reset_stats(&mut self) {
+        self.stats = Default::default();
+    }
+
     fn handle_vp_start_enable_vtl_wake(_this: &mut UhProcessor<'_, Self>, _vtl: GuestVtl) {
         unimplemented!()
     }
@@ -1060,6 +1064,63 @@ fn from_seg(reg: hvdef::HvX64SegmentRegister) -> SegmentRegister {
     }
 }
 
+/// Returns the control flags for translating a GVA for the emulator.
+fn translate_control_flags(
+    mode: virt_support_x86emu::emulate::TranslateMode,
+    vtl: GuestVtl,
+) -> hypercall::TranslateGvaControlFlagsX64 {
+    let mut control_flags = hypercall::TranslateGvaControlFlagsX64::new();
+    match mode {
+        virt_support_x86emu::emulate::TranslateMode::Read => control_flags.set_validate_read(true),
+        virt_support_x86emu::emulate::TranslateMode::Write => {
+            control_flags.set_validate_read(true);
+            control_flags.set_validate_write(true);
+        }
+        virt_support_x86emu::emulate::TranslateMode::Execute => {
+            control_flags.set_validate_execute(true)
+        }
+    };
+
+    // The translation will be used, so set the appropriate page table bits
+    // (the access/dirty bit).
+    //
+    // Prevent flushes in order to make sure that translation of this GVA
+    // remains usable until the VP is resumed back to direct execution.
+    control_flags.set_set_page_table_bits(true);
+    control_flags.set_tlb_flush_inhibit(true);
+
+    // In case we're not running ring 0, check privileges against VP state
+    // as of when the original intercept came in - since the emulator
+    // doesn't support instructions that change ring level, the ring level
+    // will remain the same as it was in the VP state as of when the
+    // original intercept came in. The privilege exempt flag should
+    // not be set.
+    assert!(!control_flags.privilege_exempt());
+
+    // Do the translation using the current VTL.
+    control_flags.set_input_vtl(vtl.into());
+    control_flags
+}
+
+fn translate_result(
+    gva: u64,
+    result: Result<ioctl::TranslateResult, ioctl::x64::TranslateErrorX64>,
+) -> Result<EmuTranslateResult, EmuTranslateError> {
+    match result {
+        Ok(ioctl::TranslateResult {
+            gpa_page,
+            overlay_page,
+        }) => Ok(EmuTranslateResult {
+            gpa: (gpa_page << hvdef::HV_PAGE_SHIFT) + (gva & (HV_PAGE_SIZE - 1)),
+            overlay_page: Some(overlay_page),
+        }),
+        Err(ioctl::x64::TranslateErrorX64 { code, event_info }) => Err(EmuTranslateError {
+            code: hypercall::TranslateGvaResultCode(code),
+            event_info: Some(event_info),
+        }),
+    }
+}
+
 impl<T: CpuIo> EmulatorSupport for UhEmulationState<'_, '_, T, HypervisorBackedX86> {
     fn flush(&mut self) {
         self.vp
@@ -1105,6 +1166,16 @@ fn set_xmm(&mut self, index: usize, v: u128) {
         self.vp.runner.cpu_context_mut().fx_state.xmm[index] = v.to_le_bytes();
     }
 
+    fn ymm_upper(&mut self, _index: usize) -> Option<u128> {
+        // The run page only holds the legacy FX state, so 256-bit moves are
+        // reported as unsupported.
+        None
+    }
+
+    fn set_ymm_upper(&mut self, _index: usize, _v: u128) -> Option<()> {
+        None
+    }
+
     fn rip(&mut self) -> u64 {
         self.cache.rip
     }
@@ -1286,60 +1357,36 @@ fn translate_gva(
         gva: u64,
         mode: virt_support_x86emu::emulate::TranslateMode,
     ) -> Result<EmuTranslateResult, EmuTranslateError> {
-        let mut control_flags = hypercall::TranslateGvaControlFlagsX64::new();
-        match mode {
-            virt_support_x86emu::emulate::TranslateMode::Read => {
-                control_flags.set_validate_read(true)
-            }
-            virt_support_x86emu::emulate::TranslateMode::Write => {
-                control_flags.set_validate_read(true);
-                control_flags.set_validate_write(true);
-            }
-            virt_support_x86emu::emulate::TranslateMode::Execute => {
-                control_flags.set_validate_execute(true)
-            }
-        };
-
-        // The translation will be used, so set the appropriate page table bits
-        // (the access/dirty bit).
-        //
-        // Prevent flushes in order to make sure that translation of this GVA
-        // remains usable until the VP is resumed back to direct execution.
-        control_flags.set_set_page_table_bits(true);
-        control_flags.set_tlb_flush_inhibit(true);
-
-        // In case we're not running ring 0, check privileges against VP state
-        // as of when the original intercept came in - since the emulator
-        // doesn't support instructions that change ring level, the ring level
-        // will remain the same as it was in the VP state as of when the
-        // original intercept came in. The privilege exempt flag should
-        // not be set.
-        assert!(!control_flags.privilege_exempt());
-
-        // Do the translation using the current VTL.
-        control_flags.set_input_vtl(self.vtl.into());
-
-        match self
+        let control_flags = translate_control_flags(mode, self.vtl);
+        let result = self
             .vp
             .runner
             .translate_gva_to_gpa(gva, control_flags)
-            .unwrap()
-        {
-            Ok(ioctl::TranslateResult {
-                gpa_page,
-                overlay_page,
-            }) => {
-                self.vp.mark_tlb_locked(Vtl::Vtl2, self.vtl);
-                Ok(EmuTranslateResult {
-                    gpa: (gpa_page << hvdef::HV_PAGE_SHIFT) + (gva & (HV_PAGE_SIZE - 1)),
-                    overlay_page: Some(overlay_page),
-                })
-            }
-            Err(ioctl::x64::TranslateErrorX64 { code, event_info }) => Err(EmuTranslateError {
-                code: hypercall::TranslateGvaResultCode(code),
-                event_info: Some(event_info),
-            }),
+            .unwrap();
+        if result.is_ok() {
+            self.vp.mark_tlb_locked(Vtl::Vtl2, self.vtl);
+        }
+        translate_result(gva, result)
+    }
+
+    fn translate_gvas(
+        &mut self,
+        gvas: &[u64],
+        mode: virt_support_x86emu::emulate::TranslateMode,
+    ) -> Vec<Result<EmuTranslateResult, EmuTranslateError>> {
+        let control_flags = translate_control_flags(mode, self.vtl);
+        let results = self
+            .vp
+            .runner
+            .translate_gvas_to_gpas(gvas, control_flags)
+            .unwrap();
+        if results.iter().any(|result| result.is_ok()) {
+            self.vp.mark_tlb_locked(Vtl::Vtl2, self.vtl);
         }
+        gvas.iter()
+            .zip(results)
+            .map(|(&gva, result)| translate_result(gva, result))
+            .collect()
     }
 
     fn inject_pending_event(&mut self, event_info: HvX64PendingEvent) {
@@ -1451,7 +1498,7 @@ fn set_register_state<T, R: ToVpRegisterName, const N: usize>(
         regs.get_values(values.iter_mut());
         self.vp
             .runner
-            .set_vp_registers(self.vtl, names.iter().copied().zip(values))
+            .set_vp_registers_batch(self.vtl, names.iter().copied().zip(values))
             .map_err(vp_state::Error::SetRegisters)?;
         Ok(())
     }
@@ -1467,7 +1514,7 @@ fn get_register_state<T, R: ToVpRegisterName, const N: usize>(
         let mut values = [HvRegisterValue::new_zeroed(); N];
         self.vp
             .runner
-            .get_vp_registers(self.vtl, &names, &mut values)
+            .get_vp_registers_batch(self.vtl, &names, &mut values)
             .map_err(vp_state::Error::GetRegisters)?;
 
         regs.set_values(values.into_iter());
@@ -29,7 +29,6 @@
 use crate::processor::hardware_cvm::apic::ApicBacking;
 use cvm_tracing::CVM_ALLOWED;
 use cvm_tracing::CVM_CONFIDENTIAL;
-use hcl::protocol::hcl_intr_offload_flags;
 use hcl::vmsa::VmsaWrapper;
 use hv1_emulator::hv::ProcessorVtlHv;
 use hv1_emulator::synic::ProcessorSynic;
@@ -138,6 +137,8 @@ struct ExitStats {
     ioio: Counter,
     msr_read: Counter,
     msr_write: Counter,
+    monitor: Counter,
+    mwait: Counter,
     npf: Counter,
     npf_no_intercept: Counter,
     npf_spurious: Counter,
@@ -374,6 +375,10 @@ fn is_interrupt_pending(
         if vmsa.v_intr_cntrl().nmi() {
             return true;
         }
+        // So does an NMI waiting for another event to be injected first.
+        if this.backing.cvm.lapics[vtl].nmi_pending {
+            return true;
+        }
 
         let vmsa_priority = vmsa.v_intr_cntrl().priority() as u32;
         let lapic = &mut this.backing.cvm.lapics[vtl].lapic;
@@ -753,6 +758,11 @@ fn hv_mut(&mut self, vtl: GuestVtl) -> Option<&mut ProcessorVtlHv> {
         Some(&mut self.cvm.hv[vtl])
     }
 
+    fn reset_stats(&mut self) {
+        self.general_stats = VtlArray::from_fn(|_| Default::default());
+        self.exit_stats = VtlArray::from_fn(|_| Default::default());
+    }
+
     fn handle_vp_start_enable_vtl_wake(this: &mut UhProcessor<'_, Self>, vtl: GuestVtl) {
         this.hcvm_handle_vp_start_enable_vtl(vtl)
     }
@@ -1103,14 +1113,10 @@ fn handle_nmi(&mut self, vtl: GuestVtl) {
             }
         } else {
             let mut vmsa = self.runner.vmsa_mut(vtl);
-            // TODO GUEST VSM: Don't inject the NMI if there's already an event
-            // pending.
-            vmsa.set_event_inject(
-                SevEventInjectInfo::new()
-                    .with_interruption_type(x86defs::snp::SEV_INTR_TYPE_NMI)
-                    .with_vector(2)
-                    .with_valid(true),
-            );
+            let Some(event) = nmi_event_inject(vmsa.event_inject()) else {
+                return;
+            };
+            vmsa.set_event_inject(event);
         }
         self.backing.cvm.lapics[vtl].nmi_pending = false;
         self.backing.cvm.lapics[vtl].activity = MpState::Running;
@@ -1124,6 +1130,24 @@ fn handle_sipi(&mut self, vtl: GuestVtl, cs: SegmentRegister) {
     }
 }
 
+/// Returns the event to inject for an NMI, given the event currently pending
+/// injection.
+///
+/// Returns `None` if an event is already pending. The NMI is then left pending
+/// so that it is retried after that event has been delivered, rather than
+/// overwriting it.
+fn nmi_event_inject(pending: SevEventInjectInfo) -> Option<SevEventInjectInfo> {
+    if pending.valid() {
+        return None;
+    }
+    Some(
+        SevEventInjectInfo::new()
+            .with_interruption_type(x86defs::snp::SEV_INTR_TYPE_NMI)
+            .with_vector(2)
+            .with_valid(true),
+    )
+}
+
 impl UhProcessor<'_, SnpBacked> {
     fn handle_synic_deliverable_exit(&mut self) {
         let message = self
@@ -1492,30 +1516,17 @@ async fn run_vp_snp(&mut self, dev: &impl CpuIo) -> Result<(), VpHaltReason> {
         let tlb_halt = self.should_halt_for_tlb_unlock(next_vtl);
         let halt = self.backing.cvm.lapics[next_vtl].activity != MpState::Running || tlb_halt;
 
-        // If we are halted in the kernel due to hlt or idle, and we receive an interrupt
-        // we'd like to unhalt, inject the interrupt, and resume vtl0 without returning to
-        // user-mode.
-        let activity = self.backing.cvm.lapics[next_vtl].activity;
-        let kernel_known_state =
-            matches!(activity, MpState::Running | MpState::Halted | MpState::Idle);
-        let halted_other = tlb_halt || !kernel_known_state;
-
-        self.runner.set_halted(halt);
         self.runner.set_exit_vtl(next_vtl);
 
         if halt && next_vtl == GuestVtl::Vtl1 && !tlb_halt {
             tracelimit::warn_ratelimited!(CVM_ALLOWED, "halting VTL 1, which might halt the guest");
         }
 
-        let x2apic_enabled = self.backing.cvm.lapics[next_vtl].lapic.x2apic_enabled();
+        // If we are halted in the kernel due to hlt or idle, and we receive an interrupt
+        // we'd like to unhalt, inject the interrupt, and resume vtl0 without returning to
+        // user-mode.
         let offload_enabled = self.backing.cvm.lapics[next_vtl].lapic.can_offload_irr();
-        let offload_flags = hcl_intr_offload_flags::new()
-            .with_offload_intr_inject(offload_enabled)
-            .with_offload_x2apic(offload_enabled && x2apic_enabled)
-            .with_halted_other(halted_other)
-            .with_halted_hlt(activity == MpState::Halted)
-            .with_halted_idle(activity == MpState::Idle);
-        *self.runner.offload_flags_mut() = offload_flags;
+        let kernel_known_state = self.set_kernel_halt_hints(next_vtl, tlb_halt, offload_enabled);
 
         // Set the lazy EOI bit just before running.
         let lazy_eoi = self.sync_lazy_eoi(next_vtl);
@@ -1526,6 +1537,12 @@ async fn run_vp_snp(&mut self, dev: &impl CpuIo) -> Result<(), VpHaltReason> {
             .map_err(|e| dev.fatal_error(SnpRunVpError(e).into()))?;
 
         let entered_from_vtl = next_vtl;
+
+        // Kernel offload may have set or cleared the halt/idle states
+        if offload_enabled && kernel_known_state {
+            self.sync_kernel_halt_state(entered_from_vtl);
+        }
+
         let (avic_page, mut vmsa) = self.runner.secure_avic_page_vmsa_mut(entered_from_vtl);
 
         // TODO SNP: The guest busy bit needs to be tested and set atomically.
@@ -1781,6 +1798,23 @@ async fn run_vp_snp(&mut self, dev: &impl CpuIo) -> Result<(), VpHaltReason> {
                 &mut self.backing.exit_stats[entered_from_vtl].hlt
             }
 
+            SevExitCode::MWAIT | SevExitCode::MWAIT_CONDITIONAL => {
+                // MONITOR/MWAIT are not enumerated to the guest, so it cannot
+                // rely on a write to the monitored range to wake it. Treat
+                // MWAIT as a hint to idle until the next interrupt, which
+                // parks the VP thread in the kernel instead of returning to
+                // the guest to spin.
+                self.backing.cvm.lapics[entered_from_vtl].activity = MpState::Idle;
+                advance_to_next_instruction(&mut vmsa);
+                vmsa.v_intr_cntrl_mut().set_intr_shadow(false);
+                &mut self.backing.exit_stats[entered_from_vtl].mwait
+            }
+
+            SevExitCode::MONITOR => {
+                advance_to_next_instruction(&mut vmsa);
+                &mut self.backing.exit_stats[entered_from_vtl].monitor
+            }
+
             SevExitCode::INVALID_VMCB => {
                 return Err(dev.fatal_error(InvalidVmcb.into()));
             }
@@ -2234,6 +2268,19 @@ fn set_xmm(&mut self, index: usize, v: u128) {
             .set_xmm_registers(index, v);
     }
 
+    fn ymm_upper(&mut self, index: usize) -> Option<u128> {
+        // The VMSA holds the upper halves of the YMM registers.
+        Some(self.vp.runner.vmsa(self.vtl).ymm_registers(index))
+    }
+
+    fn set_ymm_upper(&mut self, index: usize, v: u128) -> Option<()> {
+        self.vp
+            .runner
+            .vmsa_mut(self.vtl)
+            .set_ymm_registers(index, v);
+        Some(())
+    }
+
     fn rip(&mut self) -> u64 {
         let vmsa = self.vp.runner.vmsa(self.vtl);
         vmsa.rip()
@@ -2267,6 +2314,11 @@ fn cr0(&mut self) -> u64 {
         vmsa.cr0()
     }
 
+    fn cr3(&mut self) -> Option<u64> {
+        let vmsa = self.vp.runner.vmsa(self.vtl);
+        Some(vmsa.cr3())
+    }
+
     fn rflags(&mut self) -> RFlags {
         let vmsa = self.vp.runner.vmsa(self.vtl);
         vmsa.rflags().into()
@@ -2813,11 +2865,15 @@ fn set_synic_message_queues(
     }
 
     fn synic_timers(&mut self) -> Result<vp::SynicTimers, Self::Error> {
-        Err(vp_state::Error::Unimplemented("synic_timers"))
+        let hv = &self.vp.backing.cvm_state().hv[self.vtl];
+        Ok(hv.synic.timers(hv.ref_time_now()))
     }
 
-    fn set_synic_timers(&mut self, _value: &vp::SynicTimers) -> Result<(), Self::Error> {
-        Err(vp_state::Error::Unimplemented("synic_timers"))
+    fn set_synic_timers(&mut self, value: &vp::SynicTimers) -> Result<(), Self::Error> {
+        let hv = &mut self.vp.backing.cvm_state_mut().hv[self.vtl];
+        let ref_time_now = hv.ref_time_now();
+        hv.synic.set_timers(value, ref_time_now);
+        Ok(())
     }
 }
 
@@ -3216,20 +3272,42 @@ fn set_wait_for_tlb_locks(&mut self, vtl: GuestVtl) {
 mod save_restore {
     use super::SnpBacked;
     use super::UhProcessor;
+    use crate::processor::hardware_cvm::save_restore::state::ProcessorSavedState;
     use vmcore::save_restore::RestoreError;
     use vmcore::save_restore::SaveError;
     use vmcore::save_restore::SaveRestore;
-    use vmcore::save_restore::SavedStateNotSupported;
 
     impl SaveRestore for UhProcessor<'_, SnpBacked> {
-        type SavedState = SavedStateNotSupported;
+        type SavedState = ProcessorSavedState;
 
         fn save(&mut self) -> Result<Self::SavedState, SaveError> {
-            Err(SaveError::NotSupported)
+            Ok(self.cvm_save())
         }
 
         fn restore(&mut self, state: Self::SavedState) -> Result<(), RestoreError> {
-            match state {}
+            self.cvm_restore(state)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::nmi_event_inject;
+    use x86defs::snp::SevEventInjectInfo;
+
+    #[test]
+    fn nmi_does_not_overwrite_pending_event() {
+        let nmi = nmi_event_inject(SevEventInjectInfo::new()).unwrap();
+        assert!(nmi.valid());
+        assert_eq!(nmi.interruption_type(), x86defs::snp::SEV_INTR_TYPE_NMI);
+        assert_eq!(nmi.vector(), 2);
+
+        // Neither another event nor a previously injected NMI is replaced.
+        let exception = SevEventInjectInfo::new()
+            .with_interruption_type(x86defs::snp::SEV_INTR_TYPE_EXCEPT)
+            .with_vector(14)
+            .with_valid(true);
+        assert_eq!(nmi_event_inject(exception), None);
+        assert_eq!(nmi_event_inject(nmi), None);
+    }
+}
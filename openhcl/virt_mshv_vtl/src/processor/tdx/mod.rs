@@ -30,7 +30,6 @@
 use hcl::ioctl::ProcessorRunner;
 use hcl::ioctl::tdx::Tdx;
 use hcl::ioctl::tdx::TdxPrivateRegs;
-use hcl::protocol::hcl_intr_offload_flags;
 use hcl::protocol::tdx_tdg_vp_enter_exit_info;
 use hv1_emulator::hv::ProcessorVtlHv;
 use hv1_emulator::synic::GlobalSynic;
@@ -55,6 +54,7 @@
 use std::sync::atomic::Ordering;
 use thiserror::Error;
 use tlb_flush::FLUSH_GVA_LIST_SIZE;
+use tlb_flush::TdxFlushKickBatch;
 use tlb_flush::TdxFlushState;
 use tlb_flush::TdxPartitionFlushState;
 use virt::EmulatorMonitorSupport;
@@ -587,6 +587,8 @@ struct ExitStats {
     hw_interrupt: Counter,
     tdcall: Counter,
     hlt: Counter,
+    mwait: Counter,
+    monitor: Counter,
     pause: Counter,
     needs_interrupt_reinject: Counter,
     exception: Counter,
@@ -785,33 +787,11 @@ fn cr_intercept_registration(
             true,
             intercept_control.msr_scet_write(),
         );
-        this.runner.set_msr_bit(
-            vtl,
-            x86defs::X86X_MSR_PL0_SSP,
-            true,
-            intercept_control.msr_pls_ssp_write(),
-        );
-        this.runner.set_msr_bit(
+        // The PLx_SSP MSRs and the interrupt SSP table address are
+        // contiguous.
+        this.runner.set_msr_bits(
             vtl,
-            x86defs::X86X_MSR_PL1_SSP,
-            true,
-            intercept_control.msr_pls_ssp_write(),
-        );
-        this.runner.set_msr_bit(
-            vtl,
-            x86defs::X86X_MSR_PL2_SSP,
-            true,
-            intercept_control.msr_pls_ssp_write(),
-        );
-        this.runner.set_msr_bit(
-            vtl,
-            x86defs::X86X_MSR_PL3_SSP,
-            true,
-            intercept_control.msr_pls_ssp_write(),
-        );
-        this.runner.set_msr_bit(
-            vtl,
-            x86defs::X86X_MSR_INTERRUPT_SSP_TABLE_ADDR,
+            x86defs::X86X_MSR_PL0_SSP..=x86defs::X86X_MSR_INTERRUPT_SSP_TABLE_ADDR,
             true,
             intercept_control.msr_pls_ssp_write(),
         );
@@ -899,6 +879,7 @@ pub struct TdxBackedShared {
     /// hypervisor via an architecture-specific interface.
     pub(crate) untrusted_synic: Option<GlobalSynic>,
     flush_state: VtlArray<TdxPartitionFlushState, 2>,
+    flush_kicks: TdxFlushKickBatch,
     #[inspect(iter_by_index)]
     active_vtl: Vec<AtomicU8>,
     /// CR4 bits that the guest is allowed to set to 1.
@@ -951,6 +932,7 @@ pub(crate) fn new(
         Ok(Self {
             untrusted_synic,
             flush_state: VtlArray::from_fn(|_| TdxPartitionFlushState::new()),
+            flush_kicks: TdxFlushKickBatch::new(partition_params.topology.vp_count() as usize),
             cvm,
             // VPs start in VTL 2.
             active_vtl: std::iter::repeat_n(2, partition_params.topology.vp_count() as usize)
@@ -1316,6 +1298,13 @@ fn hv_mut(&mut self, vtl: GuestVtl) -> Option<&mut ProcessorVtlHv> {
         Some(&mut self.cvm.hv[vtl])
     }
 
+    fn reset_stats(&mut self) {
+        for vtl in self.vtls.iter_mut() {
+            vtl.enter_stats = Default::default();
+            vtl.exit_stats = Default::default();
+        }
+    }
+
     fn handle_vp_start_enable_vtl_wake(this: &mut UhProcessor<'_, Self>, vtl: GuestVtl) {
         this.hcvm_handle_vp_start_enable_vtl(vtl)
     }
@@ -1693,17 +1682,6 @@ async fn run_vp_tdx(&mut self, dev: &impl CpuIo) -> Result<(), VpHaltReason> {
         self.unlock_tlb_lock(Vtl::Vtl2);
         let tlb_halt = self.should_halt_for_tlb_unlock(next_vtl);
 
-        // If we are halted in the kernel due to hlt or idle, and we receive an interrupt
-        // we'd like to unhalt, inject the interrupt, and resume vtl0 without returning to
-        // user-mode. To enable this, the kernel must know why are are halted
-        let activity = self.backing.cvm.lapics[next_vtl].activity;
-        let kernel_known_state =
-            matches!(activity, MpState::Running | MpState::Halted | MpState::Idle);
-        let halted_other = tlb_halt || !kernel_known_state;
-
-        self.runner
-            .set_halted(activity != MpState::Running || tlb_halt);
-
         // Turn on kernel interrupt handling if possible. This will cause the
         // kernel to handle some exits internally, without returning to user
         // mode, to improve performance.
@@ -1722,16 +1700,11 @@ async fn run_vp_tdx(&mut self, dev: &impl CpuIo) -> Result<(), VpHaltReason> {
                 .vp_entry_flags
                 .invd_translations()
                 == 0;
-        let x2apic_enabled = self.backing.cvm.lapics[next_vtl].lapic.x2apic_enabled();
-
-        let offload_flags = hcl_intr_offload_flags::new()
-            .with_offload_intr_inject(offload_enabled)
-            .with_offload_x2apic(offload_enabled && x2apic_enabled)
-            .with_halted_other(halted_other)
-            .with_halted_hlt(activity == MpState::Halted)
-            .with_halted_idle(activity == MpState::Idle);
 
-        *self.runner.offload_flags_mut() = offload_flags;
+        // If we are halted in the kernel due to hlt or idle, and we receive an interrupt
+        // we'd like to unhalt, inject the interrupt, and resume vtl0 without returning to
+        // user-mode. To enable this, the kernel must know why are are halted
+        let kernel_known_state = self.set_kernel_halt_hints(next_vtl, tlb_halt, offload_enabled);
 
         self.runner
             .write_private_regs(&self.backing.vtls[next_vtl].private_regs);
@@ -1755,21 +1728,7 @@ async fn run_vp_tdx(&mut self, dev: &impl CpuIo) -> Result<(), VpHaltReason> {
 
         // Kernel offload may have set or cleared the halt/idle states
         if offload_enabled && kernel_known_state {
-            let offload_flags = self.runner.offload_flags_mut();
-
-            self.backing.cvm.lapics[entered_from_vtl].activity =
-                match (offload_flags.halted_hlt(), offload_flags.halted_idle()) {
-                    (false, false) => MpState::Running,
-                    (true, false) => MpState::Halted,
-                    (false, true) => MpState::Idle,
-                    (true, true) => {
-                        tracelimit::warn_ratelimited!(
-                            CVM_ALLOWED,
-                            "Kernel indicates VP is both halted and idle!"
-                        );
-                        activity
-                    }
-                };
+            self.sync_kernel_halt_state(entered_from_vtl);
         }
 
         if !has_intercept {
@@ -2128,6 +2087,21 @@ async fn handle_vmx_exit(
                 self.advance_to_next_instruction(intercepted_vtl);
                 &mut self.backing.vtls[intercepted_vtl].exit_stats.hlt
             }
+            VmxExitBasic::MWAIT_INSTRUCTION => {
+                // MONITOR/MWAIT are not enumerated to the guest, so it cannot
+                // rely on a write to the monitored range to wake it. Treat
+                // MWAIT as a hint to idle until the next interrupt, which
+                // parks the VP thread in the kernel instead of returning to
+                // the guest to spin.
+                self.backing.cvm.lapics[intercepted_vtl].activity = MpState::Idle;
+                self.clear_interrupt_shadow(intercepted_vtl);
+                self.advance_to_next_instruction(intercepted_vtl);
+                &mut self.backing.vtls[intercepted_vtl].exit_stats.mwait
+            }
+            VmxExitBasic::MONITOR_INSTRUCTION => {
+                self.advance_to_next_instruction(intercepted_vtl);
+                &mut self.backing.vtls[intercepted_vtl].exit_stats.monitor
+            }
             VmxExitBasic::CR_ACCESS => {
                 let qual = CrAccessQualification::from(exit_info.qualification());
                 let cr;
@@ -2992,6 +2966,16 @@ fn set_xmm(&mut self, index: usize, v: u128) {
         self.vp.runner.fx_state_mut().xmm[index] = v.to_ne_bytes();
     }
 
+    fn ymm_upper(&mut self, _index: usize) -> Option<u128> {
+        // The run page only holds the legacy FX state, so 256-bit moves are
+        // reported as unsupported.
+        None
+    }
+
+    fn set_ymm_upper(&mut self, _index: usize, _v: u128) -> Option<()> {
+        None
+    }
+
     fn rip(&mut self) -> u64 {
         self.vp.backing.vtls[self.vtl].private_regs.rip
     }
@@ -3030,6 +3014,14 @@ fn cr0(&mut self) -> u64 {
         *reg
     }
 
+    fn cr3(&mut self) -> Option<u64> {
+        Some(
+            self.vp
+                .runner
+                .read_vmcs64(self.vtl, VmcsField::VMX_VMCS_GUEST_CR3),
+        )
+    }
+
     fn rflags(&mut self) -> RFlags {
         self.vp.backing.vtls[self.vtl].private_regs.rflags.into()
     }
@@ -4248,11 +4240,15 @@ fn set_synic_message_queues(
     }
 
     fn synic_timers(&mut self) -> Result<vp::SynicTimers, Self::Error> {
-        Err(vp_state::Error::Unimplemented("synic_timers"))
+        let hv = &self.vp.backing.cvm_state().hv[self.vtl];
+        Ok(hv.synic.timers(hv.ref_time_now()))
     }
 
-    fn set_synic_timers(&mut self, _value: &vp::SynicTimers) -> Result<(), Self::Error> {
-        Err(vp_state::Error::Unimplemented("synic_timers"))
+    fn set_synic_timers(&mut self, value: &vp::SynicTimers) -> Result<(), Self::Error> {
+        let hv = &mut self.vp.backing.cvm_state_mut().hv[self.vtl];
+        let ref_time_now = hv.ref_time_now();
+        hv.synic.set_timers(value, ref_time_now);
+        Ok(())
     }
 }
 
@@ -4535,13 +4531,16 @@ fn wake_processors_for_tlb_flush_inner(
         // We use a single fence to avoid having to take a SeqCst load
         // for each VP.
         std::sync::atomic::fence(Ordering::SeqCst);
-        self.partition.hcl.kick_cpus(
-            processors.into_iter().filter(|&vp| {
-                self.shared.active_vtl[vp as usize].load(Ordering::Relaxed) == target_vtl as u8
-            }),
-            true,
-            true,
-        );
+        // VPs that are not currently in the target VTL, such as those halted
+        // in VTL 2, will pick up the flush from the partition flush state the
+        // next time they enter it, so only the rest need a kick. Batch the
+        // kick with those of concurrent flushes from other VPs.
+        let cpus = processors.into_iter().filter(|&vp| {
+            self.shared.active_vtl[vp as usize].load(Ordering::Relaxed) == target_vtl as u8
+        });
+        self.shared
+            .flush_kicks
+            .kick(cpus, |cpus| self.partition.hcl.kick_cpus(cpus, true, true));
     }
 }
 
@@ -4587,20 +4586,20 @@ fn set_wait_for_tlb_locks(&mut self, vtl: GuestVtl) {
 mod save_restore {
     use super::TdxBacked;
     use super::UhProcessor;
+    use crate::processor::hardware_cvm::save_restore::state::ProcessorSavedState;
     use vmcore::save_restore::RestoreError;
     use vmcore::save_restore::SaveError;
     use vmcore::save_restore::SaveRestore;
-    use vmcore::save_restore::SavedStateNotSupported;
 
     impl SaveRestore for UhProcessor<'_, TdxBacked> {
-        type SavedState = SavedStateNotSupported;
+        type SavedState = ProcessorSavedState;
 
         fn save(&mut self) -> Result<Self::SavedState, SaveError> {
-            Err(SaveError::NotSupported)
+            Ok(self.cvm_save())
         }
 
         fn restore(&mut self, state: Self::SavedState) -> Result<(), RestoreError> {
-            match state {}
+            self.cvm_restore(state)
         }
     }
 }
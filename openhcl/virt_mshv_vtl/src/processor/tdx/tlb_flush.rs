@@ -13,7 +13,9 @@
 use inspect::Inspect;
 use safeatomic::AtomicSliceOps;
 use std::num::Wrapping;
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicU32;
+use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use x86defs::tdx::TdGlaVmAndFlags;
 use x86defs::tdx::TdxGlaListInfo;
@@ -42,6 +44,116 @@ pub(super) fn new() -> Self {
     }
 }
 
+/// Coalesces the kicks that TLB flush requests from multiple VPs send to the
+/// flush targets.
+///
+/// Each requester adds its targets to a shared processor mask. One requester
+/// at a time drains the mask and kicks all the targets with a single ioctl,
+/// while the others wait for a kick that includes their targets instead of
+/// issuing their own. Under a burst of flushes, this turns one ioctl per
+/// request into one per round.
+#[derive(Debug, Inspect)]
+pub(super) struct TdxFlushKickBatch {
+    /// The VPs waiting to be kicked, one bit per VP.
+    #[inspect(skip)]
+    pending: Vec<AtomicU64>,
+    /// Whether a requester is currently draining `pending`.
+    kicking: AtomicBool,
+    /// The number of kick rounds that have started.
+    started: AtomicU64,
+    /// The number of kick rounds that have completed.
+    completed: AtomicU64,
+    /// The round that waiting requesters need to complete.
+    requested: AtomicU64,
+}
+
+impl TdxFlushKickBatch {
+    pub(super) fn new(vp_count: usize) -> Self {
+        Self {
+            pending: (0..vp_count.div_ceil(64))
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+            kicking: AtomicBool::new(false),
+            started: AtomicU64::new(0),
+            completed: AtomicU64::new(0),
+            requested: AtomicU64::new(0),
+        }
+    }
+
+    /// Kicks `cpus`, batching them with the targets of any concurrent
+    /// requests. `kick` issues the kick for a batch of targets.
+    ///
+    /// Returns once a kick that includes all of `cpus` has completed.
+    pub(super) fn kick(
+        &self,
+        cpus: impl IntoIterator<Item = u32>,
+        mut kick: impl FnMut(&mut dyn Iterator<Item = u32>),
+    ) {
+        let mut any = false;
+        for cpu in cpus {
+            self.pending[cpu as usize / 64].fetch_or(1 << (cpu % 64), Ordering::SeqCst);
+            any = true;
+        }
+        if !any {
+            return;
+        }
+
+        // Any round that starts after this point drains our targets, so the
+        // next one is the round we need.
+        let round = self.started.load(Ordering::SeqCst) + 1;
+        self.requested.fetch_max(round, Ordering::SeqCst);
+        loop {
+            if !self.kicking.load(Ordering::Relaxed) && !self.kicking.swap(true, Ordering::SeqCst) {
+                self.drain(&mut kick);
+            }
+            if self.completed.load(Ordering::SeqCst) >= round {
+                break;
+            }
+            std::hint::spin_loop();
+        }
+    }
+
+    /// Runs kick rounds until no requester is waiting on one, then releases
+    /// the batch.
+    fn drain(&self, kick: &mut dyn FnMut(&mut dyn Iterator<Item = u32>)) {
+        loop {
+            while self.completed.load(Ordering::SeqCst) < self.requested.load(Ordering::SeqCst) {
+                self.started.fetch_add(1, Ordering::SeqCst);
+                let mut cpus = self
+                    .pending
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, word)| {
+                        let mut bits = word.swap(0, Ordering::SeqCst);
+                        std::iter::from_fn(move || {
+                            (bits != 0).then(|| {
+                                let bit = bits.trailing_zeros();
+                                bits &= bits - 1;
+                                i as u32 * 64 + bit
+                            })
+                        })
+                    })
+                    .peekable();
+                // Our targets may already have been drained by an earlier
+                // round, but the round still has to complete to release the
+                // requesters waiting on it.
+                if cpus.peek().is_some() {
+                    kick(&mut cpus);
+                }
+                self.completed.fetch_add(1, Ordering::SeqCst);
+            }
+            self.kicking.store(false, Ordering::SeqCst);
+            // A requester may have asked for another round after the last
+            // check but before the release, and then seen the batch as taken.
+            if self.completed.load(Ordering::SeqCst) >= self.requested.load(Ordering::SeqCst)
+                || self.kicking.swap(true, Ordering::SeqCst)
+            {
+                break;
+            }
+        }
+    }
+}
+
 #[derive(Debug, Inspect)]
 pub(super) struct TdxFlushState {
     /// The last observed value of the partition's counter.
@@ -191,3 +303,45 @@ fn set_flush_entire(global: bool, vp_flags: &mut TdxVmFlags) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::TdxFlushKickBatch;
+    use std::sync::Mutex;
+
+    #[test]
+    fn kick_batch() {
+        let batch = TdxFlushKickBatch::new(130);
+        let mut kicks = Vec::new();
+        batch.kick([1, 65, 129], |cpus| kicks.push(cpus.collect::<Vec<_>>()));
+        assert_eq!(kicks, [vec![1, 65, 129]]);
+
+        // No targets, no kick.
+        batch.kick([], |_| panic!("unexpected kick"));
+    }
+
+    #[test]
+    fn kick_batch_concurrent() {
+        let batch = TdxFlushKickBatch::new(16);
+        let kicks = Mutex::new([0; 16]);
+        std::thread::scope(|s| {
+            for vp in 0..8 {
+                let (batch, kicks) = (&batch, &kicks);
+                s.spawn(move || {
+                    for i in 1..=100 {
+                        batch.kick([vp, vp + 8], |cpus| {
+                            let mut kicks = kicks.lock().unwrap();
+                            for cpu in cpus {
+                                kicks[cpu as usize] += 1;
+                            }
+                        });
+                        // The targets must have been kicked by the time the
+                        // request returns, whoever sent the kick.
+                        let kicks = kicks.lock().unwrap();
+                        assert!(kicks[vp as usize] >= i && kicks[vp as usize + 8] >= i);
+                    }
+                });
+            }
+        });
+    }
+}
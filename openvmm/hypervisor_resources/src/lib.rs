@@ -115,6 +115,12 @@ pub trait HypervisorProbe: Send + Sync + 'static {
     /// passed as `(key, "true")`. Backends should return an error for
     /// unrecognized keys.
     fn new_resource(&self, params: &[(&str, &str)]) -> anyhow::Result<Resource<HypervisorKind>>;
+
+    /// Returns guidance on making this backend available, reported to the
+    /// user when no backend could be selected.
+    fn remediation(&self) -> Option<&str> {
+        None
+    }
 }
 
 /// Private module for linkme infrastructure.
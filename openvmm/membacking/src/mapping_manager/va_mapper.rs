@@ -330,6 +330,11 @@ fn is_private(&self, addr: u64) -> bool {
         self.private_ranges.iter().any(|r| r.contains_addr(addr))
     }
 
+    /// Returns true if `range` falls entirely within a private range.
+    pub fn is_private_range(&self, range: MemoryRange) -> bool {
+        self.private_ranges.iter().any(|r| r.contains(&range))
+    }
+
     /// Ensures a mapping has been established for the given range.
     pub async fn ensure_mapped(&self, range: MemoryRange) -> Result<(), NoMapping> {
         self.inner.request_mapping(range, false).await
@@ -399,12 +404,9 @@ pub(crate) fn madvise_hugepage(&self, offset: usize, len: usize) -> Result<(), s
     ///
     /// The caller must ensure this is only called on ranges backed by
     /// private anonymous memory (allocated via [`alloc_range`](Self::alloc_range)).
-    #[expect(dead_code)] // Will be used by ballooning / memory hot-remove.
     pub fn decommit(&self, offset: usize, len: usize) -> Result<(), std::io::Error> {
         assert!(
-            self.private_ranges
-                .iter()
-                .any(|r| r.contains(&MemoryRange::new(offset as u64..offset as u64 + len as u64))),
+            self.is_private_range(MemoryRange::new(offset as u64..offset as u64 + len as u64)),
             "decommit called on non-private range"
         );
         self.inner.mapping.decommit(offset, len)
@@ -17,12 +17,17 @@
 use crate::region_manager::MapParams;
 use crate::region_manager::RegionHandle;
 use crate::region_manager::RegionManager;
+use crate::region_manager::RegionManagerClient;
+use futures::executor::block_on;
+use guestmem::DiscardRam;
 use guestmem::GuestMemory;
+use guestmem::HotAddRam;
 use hvdef::Vtl;
 use inspect::Inspect;
 use memory_range::MemoryRange;
 use mesh::MeshPayload;
 use pal_async::DefaultPool;
+use parking_lot::Mutex;
 use sparse_mmap::SparseMapping;
 use std::io;
 use std::sync::Arc;
@@ -49,6 +54,9 @@ pub struct GuestMemoryManager {
     #[inspect(skip)]
     va_mapper: Arc<VaMapper>,
 
+    #[inspect(skip)]
+    hot_add: Option<Arc<PrivateRamHotAdd>>,
+
     #[inspect(skip)]
     _thread: JoinHandle<()>,
 
@@ -128,6 +136,12 @@ pub enum MemoryBuildError {
     /// Private memory is incompatible with an existing memory backing.
     #[error("private memory is incompatible with an existing memory backing")]
     PrivateMemoryWithExistingBacking,
+    /// The hot-add range overlaps RAM or is outside the address space.
+    #[error("hot-add range {0} overlaps RAM or is beyond the end of the address space")]
+    InvalidHotAddRange(MemoryRange),
+    /// Hot-add is incompatible with x86 legacy support.
+    #[error("memory hot-add is incompatible with x86 legacy support")]
+    HotAddWithLegacy,
     /// Failed to allocate private RAM range.
     #[error("failed to allocate private RAM range {1}")]
     PrivateRamAlloc(#[source] io::Error, MemoryRange),
@@ -197,6 +211,7 @@ pub struct RamBackingRequest {
     transparent_hugepages: bool,
     hugepages: bool,
     hugepage_size: Option<u64>,
+    hugepage_fallback: bool,
     existing_mappable: Option<Mappable>,
     host_numa_node: Option<u32>,
 }
@@ -214,6 +229,7 @@ pub fn new(ranges: Vec<MemoryRange>) -> Self {
             transparent_hugepages: false,
             hugepages: false,
             hugepage_size: None,
+            hugepage_fallback: false,
             existing_mappable: None,
             host_numa_node: None,
         }
@@ -245,6 +261,15 @@ pub fn hugepages(mut self, size: Option<u64>) -> Self {
         self
     }
 
+    /// Fall back to regular shared memory if the hugetlb pool cannot satisfy
+    /// the allocation, instead of failing the build. Other hugetlb allocation
+    /// errors still fail the build. Only meaningful with
+    /// [`hugepages`](Self::hugepages).
+    pub fn hugepage_fallback(mut self, enable: bool) -> Self {
+        self.hugepage_fallback = enable;
+        self
+    }
+
     /// Reuse an existing file-backed memory handle (restore path).
     /// When set, no new allocation is performed for this backing.
     pub fn existing_mappable(mut self, mappable: Mappable) -> Self {
@@ -264,6 +289,15 @@ pub fn host_numa_node(mut self, node: Option<u32>) -> Self {
     }
 }
 
+/// Returns whether a hugetlb allocation failed because the pool has too few
+/// free pages (ENOMEM or ENOSPC), as opposed to a configuration error.
+fn is_hugepage_pool_exhausted(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::OutOfMemory | io::ErrorKind::StorageFull
+    )
+}
+
 fn validate_hugepage_size(size: u64) -> Result<usize, MemoryBuildError> {
     if !size.is_power_of_two() || size < SparseMapping::page_size() as u64 {
         return Err(MemoryBuildError::InvalidHugepageSize(MemorySize(size)));
@@ -327,6 +361,7 @@ pub struct GuestMemoryBuilder {
     pin_mappings: bool,
     x86_legacy_support: bool,
     backing_requests: Vec<RamBackingRequest>,
+    hot_add_range: Option<MemoryRange>,
 }
 
 impl GuestMemoryBuilder {
@@ -337,6 +372,7 @@ pub fn new() -> Self {
             pin_mappings: false,
             x86_legacy_support: false,
             backing_requests: Vec::new(),
+            hot_add_range: None,
         }
     }
 
@@ -380,6 +416,17 @@ pub fn add_backing(mut self, request: RamBackingRequest) -> Self {
         self
     }
 
+    /// Reserves a range of the VM's physical address space for RAM that is
+    /// added while the VM is running, via [`GuestMemoryManager::ram_hot_add`].
+    ///
+    /// Hot-added RAM is backed by private anonymous memory, so, like
+    /// [`RamBackingRequest::private_memory`], it is incompatible with x86
+    /// legacy support.
+    pub fn hot_add_range(mut self, range: Option<MemoryRange>) -> Self {
+        self.hot_add_range = range;
+        self
+    }
+
     /// Builds the memory backing, allocating one memfd or anonymous region
     /// per backing request.
     ///
@@ -423,6 +470,20 @@ pub async fn build(self, max_addr: u64) -> Result<GuestMemoryManager, MemoryBuil
             }
         }
 
+        if let Some(range) = self.hot_add_range {
+            if self.x86_legacy_support {
+                return Err(MemoryBuildError::HotAddWithLegacy);
+            }
+            if range.end() > max_addr
+                || backing_requests
+                    .iter()
+                    .flat_map(|req| &req.ranges)
+                    .any(|r| r.overlaps(&range))
+            {
+                return Err(MemoryBuildError::InvalidHotAddRange(range));
+            }
+        }
+
         // Validate x86 legacy support: at least one backing must contain a
         // range starting at GPA 0 and covering at least 1MB.
         if self.x86_legacy_support {
@@ -485,18 +546,32 @@ pub async fn build(self, max_addr: u64) -> Result<GuestMemoryManager, MemoryBuil
                     let hugepage_size =
                         validate_hugepage_size(req.hugepage_size.unwrap_or(DEFAULT_HUGEPAGE_SIZE))?;
                     validate_hugepage_ram_alignment(size, &req.ranges, hugepage_size as u64)?;
-                    sparse_mmap::alloc_shared_memory_hugetlb(
+                    match sparse_mmap::alloc_shared_memory_hugetlb(
                         backing_size,
                         &name,
                         Some(hugepage_size),
-                    )
-                    .map_err(|error| MemoryBuildError::HugepageAllocationFailed {
-                        size: MemorySize(size),
-                        hugepage_size: MemorySize(hugepage_size as u64),
-                        page_count: backing_size / hugepage_size,
-                        error,
-                    })?
-                    .into()
+                    ) {
+                        Ok(fd) => fd.into(),
+                        Err(error) => {
+                            let exhausted = is_hugepage_pool_exhausted(&error);
+                            let error = MemoryBuildError::HugepageAllocationFailed {
+                                size: MemorySize(size),
+                                hugepage_size: MemorySize(hugepage_size as u64),
+                                page_count: backing_size / hugepage_size,
+                                error,
+                            };
+                            if !req.hugepage_fallback || !exhausted {
+                                return Err(error);
+                            }
+                            tracing::warn!(
+                                error = &error as &dyn std::error::Error,
+                                "falling back to regular pages for guest RAM"
+                            );
+                            sparse_mmap::alloc_shared_memory(backing_size, &name)
+                                .map_err(MemoryBuildError::AllocationFailed)?
+                                .into()
+                        }
+                    }
                 } else {
                     sparse_mmap::alloc_shared_memory(backing_size, &name)
                         .map_err(MemoryBuildError::AllocationFailed)?
@@ -527,6 +602,9 @@ pub async fn build(self, max_addr: u64) -> Result<GuestMemoryManager, MemoryBuil
             None
         };
 
+        // Hot-added RAM is allocated in the VA mapping as it is added.
+        private_ranges.extend(self.hot_add_range);
+
         let mapping_manager =
             MappingManager::new(&spawner, max_addr, private_ranges, max_hugepage_size);
 
@@ -628,8 +706,21 @@ pub async fn build(self, max_addr: u64) -> Result<GuestMemoryManager, MemoryBuil
             }
         }
 
+        let hot_add = self.hot_add_range.map(|range| {
+            Arc::new(PrivateRamHotAdd {
+                range,
+                va_mapper: va_mapper.clone(),
+                region_manager: region_manager.client().clone(),
+                state: Mutex::new(HotAddState {
+                    added: 0,
+                    regions: Vec::new(),
+                }),
+            })
+        });
+
         let gm = GuestMemoryManager {
             guest_ram: backings,
+            hot_add,
             _thread: thread,
             ram_regions: Arc::new(ram_regions),
             mapping_manager,
@@ -713,6 +804,26 @@ pub fn ram_visibility_control(&self) -> RamVisibilityControl {
         }
     }
 
+    /// Returns an object for releasing the backing of guest RAM, such as
+    /// for pages given up to a balloon.
+    ///
+    /// Only RAM allocated with [`RamBackingRequest::private_memory`] can be
+    /// discarded. Discarding other RAM fails with
+    /// [`io::ErrorKind::Unsupported`].
+    pub fn ram_discard(&self) -> Arc<dyn DiscardRam> {
+        Arc::new(PrivateRamDiscard {
+            va_mapper: self.va_mapper.clone(),
+        })
+    }
+
+    /// Returns an object for adding RAM to the VM, if a range was reserved
+    /// with [`GuestMemoryBuilder::hot_add_range`].
+    pub fn ram_hot_add(&self) -> Option<Arc<dyn HotAddRam>> {
+        self.hot_add
+            .clone()
+            .map(|hot_add| hot_add as Arc<dyn HotAddRam>)
+    }
+
     /// Returns the shared memory resources that can be used to reconstruct the
     /// memory backing.
     ///
@@ -783,6 +894,92 @@ pub async fn attach_partition(
     }
 }
 
+/// Discards private RAM by decommitting it in the VA mapping.
+struct PrivateRamDiscard {
+    va_mapper: Arc<VaMapper>,
+}
+
+impl DiscardRam for PrivateRamDiscard {
+    fn discard_ram(&self, gpa: u64, len: u64) -> io::Result<()> {
+        let range = gpa
+            .checked_add(len)
+            .and_then(|end| MemoryRange::try_new(gpa..end).ok())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid RAM range {gpa:#x}+{len:#x}"),
+                )
+            })?;
+        if !self.va_mapper.is_private_range(range) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{range} is not private RAM"),
+            ));
+        }
+        self.va_mapper
+            .decommit(range.start() as usize, range.len() as usize)
+    }
+}
+
+/// Adds private RAM from the reserved hot-add range, in order.
+#[derive(Debug)]
+struct PrivateRamHotAdd {
+    range: MemoryRange,
+    va_mapper: Arc<VaMapper>,
+    region_manager: RegionManagerClient,
+    state: Mutex<HotAddState>,
+}
+
+#[derive(Debug)]
+struct HotAddState {
+    /// The number of bytes added so far.
+    added: u64,
+    regions: Vec<RegionHandle>,
+}
+
+impl HotAddRam for PrivateRamHotAdd {
+    fn hot_add_ram(&self, len: u64) -> io::Result<u64> {
+        let mut state = self.state.lock();
+        let start = self.range.start() + state.added;
+        if len == 0 || !len.is_multiple_of(SparseMapping::page_size() as u64) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid hot-add size {len:#x}"),
+            ));
+        }
+        if len > self.range.end() - start {
+            return Err(io::Error::new(
+                io::ErrorKind::OutOfMemory,
+                format!(
+                    "hot-add range {} has only {:#x} bytes left",
+                    self.range,
+                    self.range.end() - start
+                ),
+            ));
+        }
+        let range = MemoryRange::new(start..start + len);
+        self.va_mapper
+            .alloc_range(start as usize, len as usize, None)?;
+        self.va_mapper
+            .set_range_name(start as usize, len as usize, "guest-ram-hot-add");
+        let region = block_on(self.region_manager.new_region(
+            "ram-hot-add".into(),
+            range,
+            RAM_PRIORITY,
+            true,
+        ))
+        .map_err(io::Error::other)?;
+        block_on(region.map(MapParams {
+            writable: true,
+            executable: true,
+            prefetch: false,
+        }));
+        state.regions.push(region);
+        state.added += len;
+        Ok(start)
+    }
+}
+
 /// A client to the [`GuestMemoryManager`] used to control the visibility of
 /// RAM regions.
 pub struct RamVisibilityControl {
@@ -882,6 +1079,22 @@ fn test_validate_hugepage_size() {
         ));
     }
 
+    #[test]
+    fn test_hugepage_pool_exhausted() {
+        assert!(is_hugepage_pool_exhausted(&io::Error::from(
+            io::ErrorKind::OutOfMemory
+        )));
+        assert!(is_hugepage_pool_exhausted(&io::Error::from(
+            io::ErrorKind::StorageFull
+        )));
+        assert!(!is_hugepage_pool_exhausted(&io::Error::from(
+            io::ErrorKind::InvalidInput
+        )));
+        assert!(!is_hugepage_pool_exhausted(&io::Error::from(
+            io::ErrorKind::Unsupported
+        )));
+    }
+
     #[test]
     fn test_validate_hugepage_ram_alignment() {
         const HUGEPAGE_SIZE: u64 = 2 * 1024 * 1024;
@@ -1061,4 +1274,108 @@ fn test_two_backings_with_gap() {
             assert_eq!(buf, pattern_b);
         });
     }
+
+    #[test]
+    fn test_hot_add_ram() {
+        DefaultPool::run_with(|_| async {
+            let page = SparseMapping::page_size() as u64;
+            let ram = MemoryRange::new(0..2 * page);
+            let hot_add = MemoryRange::new(4 * page..7 * page);
+
+            let mgr = GuestMemoryBuilder::new()
+                .add_backing(RamBackingRequest::new(vec![ram]))
+                .hot_add_range(Some(hot_add))
+                .build(hot_add.end())
+                .await
+                .unwrap();
+            let gm = mgr.client().guest_memory().await.unwrap();
+            let hot_add_ram = mgr.ram_hot_add().unwrap();
+
+            // RAM is added in order from the start of the range.
+            assert_eq!(hot_add_ram.hot_add_ram(page).unwrap(), hot_add.start());
+            assert_eq!(
+                hot_add_ram.hot_add_ram(page).unwrap(),
+                hot_add.start() + page
+            );
+
+            let pattern = vec![0xAB; 2 * page as usize];
+            gm.write_at(hot_add.start(), &pattern).unwrap();
+            let mut buf = vec![0u8; 2 * page as usize];
+            gm.read_at(hot_add.start(), &mut buf).unwrap();
+            assert_eq!(buf, pattern);
+
+            // Added RAM is private, so it can be discarded.
+            mgr.ram_discard()
+                .discard_ram(hot_add.start(), page)
+                .unwrap();
+
+            let err = hot_add_ram.hot_add_ram(2 * page).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::OutOfMemory);
+            let err = hot_add_ram.hot_add_ram(1).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            hot_add_ram.hot_add_ram(page).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_hot_add_range_overlap() {
+        DefaultPool::run_with(|_| async {
+            let page = SparseMapping::page_size() as u64;
+            let ram = MemoryRange::new(0..2 * page);
+            let result = GuestMemoryBuilder::new()
+                .add_backing(RamBackingRequest::new(vec![ram]))
+                .hot_add_range(Some(MemoryRange::new(page..3 * page)))
+                .build(3 * page)
+                .await;
+            assert!(matches!(
+                result,
+                Err(MemoryBuildError::InvalidHotAddRange(_))
+            ));
+
+            let mgr = GuestMemoryBuilder::new()
+                .add_backing(RamBackingRequest::new(vec![ram]))
+                .build(ram.end())
+                .await
+                .unwrap();
+            assert!(mgr.ram_hot_add().is_none());
+        });
+    }
+
+    #[test]
+    fn test_discard_private_ram() {
+        DefaultPool::run_with(|_| async {
+            let page = SparseMapping::page_size() as u64;
+            let private = MemoryRange::new(0..2 * page);
+            let shared = MemoryRange::new(2 * page..4 * page);
+
+            let mgr = GuestMemoryBuilder::new()
+                .add_backing(RamBackingRequest::new(vec![private]).private_memory(true))
+                .add_backing(RamBackingRequest::new(vec![shared]))
+                .build(shared.end())
+                .await
+                .unwrap();
+            let gm = mgr.client().guest_memory().await.unwrap();
+            let discard = mgr.ram_discard();
+
+            let pattern = vec![0xEE; page as usize];
+            gm.write_at(page, &pattern).unwrap();
+            discard.discard_ram(page, page).unwrap();
+
+            // Discarded RAM reads as zeros and is usable again.
+            let mut buf = vec![0u8; page as usize];
+            gm.read_at(page, &mut buf).unwrap();
+            assert_eq!(buf, vec![0u8; page as usize]);
+            gm.write_at(page, &pattern).unwrap();
+            gm.read_at(page, &mut buf).unwrap();
+            assert_eq!(buf, pattern);
+
+            // File-backed RAM and ranges spanning backings can't be discarded.
+            let err = discard.discard_ram(shared.start(), page).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+            let err = discard.discard_ram(page, 2 * page).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+            let err = discard.discard_ram(1, page).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        });
+    }
 }
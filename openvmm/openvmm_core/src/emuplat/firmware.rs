@@ -10,6 +10,9 @@
 use firmware_uefi_resources::platform::UefiEvent;
 use firmware_uefi_resources::platform::UefiLogger;
 use get_resources::ged::FirmwareEvent;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use vm_resource::PlatformResource;
 use vm_resource::ResolveResource;
 
@@ -17,11 +20,15 @@
 #[derive(Debug)]
 pub struct MeshLogger {
     sender: Option<mesh::Sender<FirmwareEvent>>,
+    boot_to_fw_ui: Option<Arc<AtomicBool>>,
 }
 
 impl MeshLogger {
     pub fn new(sender: Option<mesh::Sender<FirmwareEvent>>) -> Self {
-        Self { sender }
+        Self {
+            sender,
+            boot_to_fw_ui: None,
+        }
     }
 
     fn send(&self, event: FirmwareEvent) {
@@ -37,6 +44,16 @@ fn log_event(&self, event: UefiEvent) {
             UefiEvent::BootSuccess(_) => FirmwareEvent::BootSuccess,
             UefiEvent::BootFailure(_) => FirmwareEvent::BootFailed,
             UefiEvent::NoBootDevice => FirmwareEvent::NoBootDevice,
+            UefiEvent::BdsStarted => FirmwareEvent::BdsStarted,
+            UefiEvent::ExitBootServices => FirmwareEvent::OsLoaderHandoff,
+            UefiEvent::BootToFirmwareUi => {
+                // Not surfaced to the host. The frontpage is enabled when the
+                // firmware is reloaded for the next boot.
+                if let Some(boot_to_fw_ui) = &self.boot_to_fw_ui {
+                    boot_to_fw_ui.store(true, Ordering::Relaxed);
+                }
+                return;
+            }
         };
         self.send(event);
     }
@@ -55,11 +72,20 @@ fn log_event(&self, event: PcatEvent) {
 // TODO: PCAT resolving
 pub struct MeshLoggerResolver {
     sender: Option<mesh::Sender<FirmwareEvent>>,
+    boot_to_fw_ui: Arc<AtomicBool>,
 }
 
 impl MeshLoggerResolver {
-    pub fn new(sender: Option<mesh::Sender<FirmwareEvent>>) -> Self {
-        Self { sender }
+    /// `boot_to_fw_ui` is set when the guest asks for the next boot to enter
+    /// the firmware UI.
+    pub fn new(
+        sender: Option<mesh::Sender<FirmwareEvent>>,
+        boot_to_fw_ui: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            sender,
+            boot_to_fw_ui,
+        }
     }
 }
 
@@ -72,8 +98,9 @@ fn resolve(
         _resource: PlatformResource,
         _input: (),
     ) -> Result<Self::Output, Self::Error> {
-        Ok(ResolvedUefiLogger(Box::new(MeshLogger::new(
-            self.sender.clone(),
-        ))))
+        Ok(ResolvedUefiLogger(Box::new(MeshLogger {
+            sender: self.sender.clone(),
+            boot_to_fw_ui: Some(self.boot_to_fw_ui.clone()),
+        })))
     }
 }
@@ -34,6 +34,8 @@
 use futures::future::try_join_all;
 use futures_concurrency::prelude::*;
 use guestmem::GuestMemory;
+use guestmem::tracking::AccessKind;
+use guestmem::tracking::AccessTracker;
 use hvdef::HV_PAGE_SIZE;
 use hvdef::Vtl;
 use hypervisor_resources::HypervisorKind;
@@ -76,6 +78,9 @@
 use openvmm_defs::config::Vtl2Config;
 use openvmm_defs::config::X2ApicConfig;
 use openvmm_defs::config::X86TopologyConfig;
+use openvmm_defs::rpc::DeviceMemoryAccess;
+use openvmm_defs::rpc::DeviceMemoryAccessKind;
+use openvmm_defs::rpc::DeviceMemoryAccesses;
 use openvmm_defs::rpc::PulseSaveRestoreError;
 use openvmm_defs::rpc::VmRpc;
 use openvmm_defs::worker::VM_WORKER;
@@ -101,6 +106,8 @@
 use state_unit::StateUnits;
 use std::fs::File;
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::thread;
 use std::thread::JoinHandle;
 use storvsp::ScsiControllerDisk;
@@ -129,6 +136,7 @@
 use vm_topology::processor::aarch64::GicVersion;
 use vm_topology::processor::x86::X86Topology;
 use vmbus_channel::channel::VmbusDevice;
+use vmbus_channel::resources::ResolveVmbusDeviceHandleParams;
 use vmbus_server::HvsockRelayChannel;
 use vmbus_server::VmbusServer;
 use vmbus_server::hvsock::HvsockRelay;
@@ -149,7 +157,7 @@
 use vmm_core::vmbus_unit::ChannelUnit;
 use vmm_core::vmbus_unit::VmbusServerHandle;
 use vmm_core::vmbus_unit::offer_channel_unit;
-use vmm_core::vmbus_unit::offer_vmbus_device_handle_unit;
+use vmm_core::vmbus_unit::offer_vmbus_device_unit;
 use vmm_core_defs::HaltReason;
 use vmotherboard::BaseChipsetBuilder;
 use vmotherboard::BaseChipsetBuilderOutput;
@@ -215,11 +223,13 @@ fn from_config(config: Config) -> Self {
             layout: config.layout,
             rtc_delta_milliseconds: config.rtc_delta_milliseconds,
             automatic_guest_reset: config.automatic_guest_reset,
+            extra_acpi_tables: config.extra_acpi_tables,
             efi_diagnostics_log_level: match config.efi_diagnostics_log_level {
                 EfiDiagnosticsLogLevelType::Default => LogLevel::make_default(),
                 EfiDiagnosticsLogLevelType::Info => LogLevel::make_info(),
                 EfiDiagnosticsLogLevelType::Full => LogLevel::make_full(),
             },
+            track_device_memory: config.track_device_memory,
         }
     }
 }
@@ -265,7 +275,9 @@ pub struct Manifest {
     layout: vmm_core_defs::LayoutConfig,
     rtc_delta_milliseconds: i64,
     automatic_guest_reset: bool,
+    extra_acpi_tables: Vec<Vec<u8>>,
     efi_diagnostics_log_level: LogLevel,
+    track_device_memory: bool,
 }
 
 #[derive(Protobuf, SavedStateRoot)]
@@ -708,7 +720,12 @@ struct LoadedVmInner {
     processor_topology: ProcessorTopology,
     hypervisor_cfg: HypervisorConfig,
     vmbus_redirect: bool,
-    vmbus_devices: Vec<SpawnedUnit<ChannelUnit<dyn VmbusDevice>>>,
+    vmbus_devices: Vec<OfferedVmbusDevice>,
+    vpci_buses: Vec<(
+        DeviceVtl,
+        guid::Guid,
+        Arc<closeable_mutex::CloseableMutex<VpciBus>>,
+    )>,
 
     input_distributor: SpawnedUnit<InputDistributor>,
     vtl2_framebuffer_gpa_base: Option<u64>,
@@ -725,10 +742,14 @@ struct LoadedVmInner {
     #[cfg_attr(not(guest_arch = "x86_64"), expect(dead_code))]
     pci_legacy_interrupts: Vec<((u8, Option<u8>), u32)>,
     firmware_event_send: Option<mesh::Sender<get_resources::ged::FirmwareEvent>>,
+    /// Set by the UEFI device on reset when the guest has asked to boot into
+    /// the firmware UI, so that the frontpage is enabled for the next boot.
+    boot_to_fw_ui: Arc<AtomicBool>,
 
     load_mode: LoadMode,
     igvm_file: Option<IgvmFile>,
     next_igvm_file: Option<IgvmFile>,
+    previous_igvm_file: Option<IgvmFile>,
     _vmgs_task: Option<Task<()>>,
     vmgs_client_inspect_handle: Option<vmgs_broker::VmgsClient>,
 
@@ -746,6 +767,10 @@ struct LoadedVmInner {
     automatic_guest_reset: bool,
     chipset: Arc<vmotherboard::Chipset>,
     pcie_host_bridges: Vec<PcieHostBridge>,
+    /// Additional raw ACPI tables to expose to the guest.
+    extra_acpi_tables: Vec<Vec<u8>>,
+    /// Records PCI device accesses to guest memory, if enabled for tests.
+    device_memory_tracker: Option<AccessTracker>,
     pcie_root_complexes: Vec<Arc<closeable_mutex::CloseableMutex<GenericPcieRootComplex>>>,
     /// SMMU configurations, one per instance.
     #[cfg(guest_arch = "aarch64")]
@@ -761,6 +786,43 @@ struct LoadedVmInner {
     )>,
 }
 
+/// A vmbus device offered as a state unit, identified so that it can be
+/// revoked on request.
+struct OfferedVmbusDevice {
+    vtl: DeviceVtl,
+    instance_id: guid::Guid,
+    unit: SpawnedUnit<ChannelUnit<dyn VmbusDevice>>,
+}
+
+async fn offer_vmbus_device(
+    driver_source: &VmTaskDriverSource,
+    state_units: &StateUnits,
+    vmbus: &VmbusServerHandle,
+    vtl: DeviceVtl,
+    device: Box<dyn VmbusDevice>,
+) -> anyhow::Result<OfferedVmbusDevice> {
+    let instance_id = device.offer().instance_id;
+    let unit = offer_vmbus_device_unit(driver_source, state_units, vmbus, device).await?;
+    Ok(OfferedVmbusDevice {
+        vtl,
+        instance_id,
+        unit,
+    })
+}
+
+/// Returns the guest memory for the PCI device `name`, which records the
+/// device's accesses if `tracker` is set.
+fn device_guest_memory(
+    gm: &GuestMemory,
+    tracker: Option<&AccessTracker>,
+    name: impl Into<Arc<str>>,
+) -> GuestMemory {
+    match tracker {
+        Some(tracker) => tracker.guest_memory(name),
+        None => gm.clone(),
+    }
+}
+
 fn convert_vtl2_config(
     vtl2_cfg: Option<&Vtl2Config>,
     load_mode: &LoadMode,
@@ -846,6 +908,7 @@ fn build_root_port_settings(rp_cfg: &PcieRootPortConfig) -> PciePortSettings {
                 .with_cache_capable(true)
                 .with_mem_capable(true),
         ),
+        aer: false,
     }
 }
 
@@ -881,7 +944,7 @@ pub(crate) async fn new_with_hypervisor<P, H>(
         hypervisor: &mut H,
         #[cfg_attr(not(guest_arch = "aarch64"), expect(unused_variables))]
         platform_info: virt::PlatformInfo,
-        cfg: Manifest,
+        mut cfg: Manifest,
         shared_memory: Option<SharedMemoryBacking>,
     ) -> anyhow::Result<Self>
     where
@@ -897,6 +960,15 @@ pub(crate) async fn new_with_hypervisor<P, H>(
             .await
             .unwrap();
 
+        if !cfg.extra_acpi_tables.is_empty()
+            && matches!(cfg.load_mode, LoadMode::Pcat { .. } | LoadMode::Igvm { .. })
+        {
+            anyhow::bail!("extra ACPI tables are only supported with linux direct or UEFI boot");
+        }
+        for table in &mut cfg.extra_acpi_tables {
+            acpi::builder::finalize_raw_table(table).context("invalid extra ACPI table")?;
+        }
+
         // Pre-parse the igvm file early.
         let igvm_file = if let LoadMode::Igvm { file, .. } = &cfg.load_mode {
             let igvm_file = super::vm_loaders::igvm::read_igvm_file(file)
@@ -922,6 +994,7 @@ pub(crate) async fn new_with_hypervisor<P, H>(
                     &cfg.load_mode,
                     igvm_file.as_ref(),
                 )?,
+                halt_on_guest_crash: cfg.hypervisor.halt_on_guest_crash,
             })
         } else {
             None
@@ -1078,7 +1151,9 @@ pub(crate) async fn new_with_hypervisor<P, H>(
             .private_memory(cfg.memory.private_memory)
             .transparent_hugepages(cfg.memory.transparent_hugepages);
         if cfg.memory.hugepages {
-            backing = backing.hugepages(cfg.memory.hugepage_size);
+            backing = backing
+                .hugepages(cfg.memory.hugepage_size)
+                .hugepage_fallback(cfg.memory.hugepage_fallback);
         }
         if let Some(smb) = shared_memory {
             backing = backing.existing_mappable(smb.into_mappable());
@@ -1116,7 +1191,27 @@ pub(crate) async fn new_with_hypervisor<P, H>(
             .end_of_layout()
             .max(mem_layout.vtl2_range().map_or(0, |r| r.end()));
 
+        // Reserve space for hot-added RAM above the rest of the layout, in
+        // units of the memory block size that guests add memory in.
+        const HOT_ADD_ALIGNMENT: u64 = 128 * 1024 * 1024;
+        let hot_add_range = cfg
+            .memory
+            .hot_add_size
+            .map(|size| {
+                let start = max_addr.next_multiple_of(HOT_ADD_ALIGNMENT);
+                start
+                    .checked_add(size.next_multiple_of(HOT_ADD_ALIGNMENT))
+                    .filter(|&end| end <= 1 << physical_address_size)
+                    .and_then(|end| MemoryRange::try_new(start..end).ok())
+                    .with_context(|| {
+                        format!("hot-add size {size:#x} does not fit in the address space")
+                    })
+            })
+            .transpose()?;
+        let max_addr = hot_add_range.map_or(max_addr, |r| r.end());
+
         let mut memory_manager = memory_builder
+            .hot_add_range(hot_add_range)
             .build(max_addr)
             .await
             .context("failed to build guest memory")?;
@@ -1126,6 +1221,9 @@ pub(crate) async fn new_with_hypervisor<P, H>(
             .guest_memory()
             .await
             .context("failed to get guest memory")?;
+        let device_memory_tracker = cfg
+            .track_device_memory
+            .then(|| AccessTracker::new(gm.clone()));
         let mut cpuid = Vec::new();
 
         // Add in Hyper-V VMM CPUID leaves.
@@ -1139,11 +1237,26 @@ pub(crate) async fn new_with_hypervisor<P, H>(
             ));
         }
 
+        // The backend applies user overrides after the leaves above and after
+        // its own leaves.
+        let cpuid_overrides = cfg
+            .hypervisor
+            .cpuid_overrides
+            .iter()
+            .map(|o| virt::CpuidLeaf {
+                function: o.function,
+                index: o.index,
+                result: o.result,
+                mask: o.mask,
+            })
+            .collect::<Vec<_>>();
+
         let (partition, vps) = proto
             .build(virt::PartitionConfig {
                 mem_layout: &mem_layout,
                 guest_memory: &gm,
                 cpuid: &cpuid,
+                cpuid_overrides: &cpuid_overrides,
                 vtl0_alias_map,
             })
             .context("failed to create the partition")?;
@@ -1300,7 +1413,10 @@ async fn load(
         let (halt_vps, halt_request_recv) = Halt::new();
         let halt_vps = Arc::new(halt_vps);
 
-        resolver.add_resolver(vmm_core::platform_resolvers::HaltResolver(halt_vps.clone()));
+        resolver.add_resolver(vmm_core::platform_resolvers::HaltResolver {
+            halt: halt_vps.clone(),
+            halt_on_guest_crash: cfg.hypervisor.halt_on_guest_crash,
+        });
         #[cfg(guest_arch = "x86_64")]
         resolver.add_resolver(vmm_core::platform_resolvers::IoApicRoutingResolver(
             partition.clone().ioapic_routing(),
@@ -1310,6 +1426,7 @@ async fn load(
 
         #[cfg_attr(not(guest_arch = "x86_64"), expect(unused_mut))]
         let mut deps_hyperv_firmware_pcat = None;
+        let boot_to_fw_ui = Arc::new(AtomicBool::new(false));
         match &cfg.load_mode {
             LoadMode::Uefi { .. } => {
                 use emuplat::uefi::*;
@@ -1317,6 +1434,7 @@ async fn load(
                 // device.
                 resolver.add_resolver(emuplat::firmware::MeshLoggerResolver::new(
                     cfg.firmware_event_send.clone(),
+                    boot_to_fw_ui.clone(),
                 ));
                 resolver.add_async_resolver(OpenvmmUefiWatchdogPlatformResolver::new(
                     partition.clone(),
@@ -1346,6 +1464,7 @@ async fn load(
                             mem_layout: &mem_layout,
                             cache_topology: None,
                             pcie_host_bridges: &Vec::new(),
+                            extra_tables: &[],
                             arch: vmm_core::acpi_builder::AcpiArchConfig::X86 {
                                 with_ioapic: cfg.chipset_capabilities.with_ioapic,
                                 with_pic: cfg.chipset_capabilities.with_pic,
@@ -1801,6 +1920,7 @@ async fn load(
         let mut vtl2_vmbus_server = None;
         let mut vtl2_hvsock_relay = None;
         let mut vmbus_redirect = false;
+        let mut vpci_buses = Vec::new();
 
         // PCI Express topology
 
@@ -2002,6 +2122,7 @@ struct PortInfo {
                                 .acs_capabilities_supported
                                 .unwrap_or(DEFAULT_ACS_CAP_MASK),
                             cxl_flex_bus_port_capability: None,
+                            aer: false,
                         },
                     };
                     GenericPcieSwitch::new(definition)
@@ -2093,6 +2214,7 @@ struct PortInfo {
             let driver_source = &driver_source;
             let resolver = &resolver;
             let gm = &gm;
+            let device_memory_tracker = device_memory_tracker.as_ref();
             let partition = &partition;
             let mapper = &mapper;
             let port_info = &port_info;
@@ -2109,11 +2231,12 @@ struct PortInfo {
                 })?;
 
                 let msi_conn = pci_core::msi::MsiConnection::new(pi.bus_range.clone(), 0);
+                let device_gm = device_guest_memory(gm, device_memory_tracker, port_name.clone());
 
                 let pcie_ctx =
                     pcie_wiring::build_pcie_msi_context(&pcie_wiring::PcieWiringParams {
                         partition: partition.as_ref(),
-                        guest_memory: gm,
+                        guest_memory: &device_gm,
                         #[cfg(guest_arch = "aarch64")]
                         bus_range: &pi.bus_range,
                         segment: pi.segment,
@@ -2315,6 +2438,7 @@ fn make_ids(
 
             if partition.supports_virtual_devices() {
                 for dev_cfg in cfg.vpci_devices {
+                    let instance_id = dev_cfg.instance_id;
                     let vmbus = match dev_cfg.vtl {
                         DeviceVtl::Vtl0 => vmbus_server.as_ref().context("vmbus not enabled")?,
                         DeviceVtl::Vtl1 => anyhow::bail!("not supported"),
@@ -2329,11 +2453,16 @@ fn make_ids(
                         DeviceVtl::Vtl2 => Vtl::Vtl2,
                     };
 
-                    vmm_core::device_builder::build_vpci_device(
+                    let device_gm = device_guest_memory(
+                        &gm,
+                        device_memory_tracker.as_ref(),
+                        format!("vpci:{instance_id}"),
+                    );
+                    let bus = vmm_core::device_builder::build_vpci_device(
                         vmm_core::device_builder::PciDeviceResolveContext {
                             driver_source: &driver_source,
                             resolver: &resolver,
-                            guest_memory: &gm,
+                            guest_memory: &device_gm,
                             resource: dev_cfg.resource,
                             doorbell_registration: partition
                                 .clone()
@@ -2361,6 +2490,7 @@ fn make_ids(
                         None,
                     )
                     .await?;
+                    vpci_buses.push((dev_cfg.vtl, instance_id, bus));
                 }
 
                 #[cfg(all(windows, feature = "virt_whp"))]
@@ -2425,15 +2555,18 @@ fn make_ids(
                     .context("failed to find vmbus for vtl2"),
             }
             .with_context(|| format!("failed to resolve vmbus resource {}", resource.id()))?;
-            vmbus_devices.push(
-                offer_vmbus_device_handle_unit(
-                    &driver_source,
-                    &state_units,
-                    vmbus,
-                    &resolver,
+            let device = resolver
+                .resolve(
                     resource,
+                    ResolveVmbusDeviceHandleParams {
+                        driver_source: &driver_source,
+                        discard_ram: Some(memory_manager.ram_discard()),
+                        hot_add_ram: memory_manager.ram_hot_add(),
+                    },
                 )
-                .await?,
+                .await?;
+            vmbus_devices.push(
+                offer_vmbus_device(&driver_source, &state_units, vmbus, vtl, device.0).await?,
             );
         }
 
@@ -2464,6 +2597,7 @@ fn make_ids(
                     device,
                     VirtioResolveInput {
                         driver_source: &driver_source,
+                        discard_ram: Some(memory_manager.ram_discard()),
                     },
                 )
                 .await?;
@@ -2614,9 +2748,11 @@ fn make_ids(
                 #[cfg(windows)]
                 _kernel_vmnics: kernel_vmnics,
                 vmbus_devices,
+                vpci_buses,
                 chipset_cfg: cfg.chipset,
                 chipset_capabilities: cfg.chipset_capabilities,
                 firmware_event_send: cfg.firmware_event_send,
+                boot_to_fw_ui,
                 load_mode: cfg.load_mode,
                 virtio_mmio_region,
                 virtio_mmio_irq,
@@ -2624,6 +2760,7 @@ fn make_ids(
                 pci_legacy_interrupts,
                 igvm_file,
                 next_igvm_file: None,
+                previous_igvm_file: None,
                 _vmgs_task: vmgs_task,
                 vmgs_client_inspect_handle,
                 #[cfg(target_os = "linux")]
@@ -2635,6 +2772,8 @@ fn make_ids(
                 automatic_guest_reset: cfg.automatic_guest_reset,
                 chipset: chipset.chipset.clone(),
                 pcie_host_bridges,
+                extra_acpi_tables: cfg.extra_acpi_tables,
+                device_memory_tracker,
                 pcie_root_complexes,
                 pcie_hotplug_devices: Vec::new(),
                 #[cfg(guest_arch = "aarch64")]
@@ -2658,6 +2797,15 @@ fn make_ids(
 }
 
 impl LoadedVmInner {
+    fn vmbus_server_for(&self, vtl: DeviceVtl) -> anyhow::Result<&VmbusServerHandle> {
+        match vtl {
+            DeviceVtl::Vtl0 => self.vmbus_server.as_ref(),
+            DeviceVtl::Vtl1 => None,
+            DeviceVtl::Vtl2 => self.vtl2_vmbus_server.as_ref(),
+        }
+        .context("no vmbus available")
+    }
+
     async fn load_firmware(&mut self, vtl2_only: bool) -> anyhow::Result<()> {
         let cache_topology = if cfg!(guest_arch = "aarch64") {
             Some(
@@ -2672,6 +2820,7 @@ async fn load_firmware(&mut self, vtl2_only: bool) -> anyhow::Result<()> {
             mem_layout: &self.mem_layout,
             cache_topology: cache_topology.as_ref(),
             pcie_host_bridges: &self.pcie_host_bridges,
+            extra_tables: &self.extra_acpi_tables,
             #[cfg(guest_arch = "x86_64")]
             arch: vmm_core::acpi_builder::AcpiArchConfig::X86 {
                 with_ioapic: self.chipset_capabilities.with_ioapic,
@@ -2813,7 +2962,10 @@ async fn load_firmware(&mut self, vtl2_only: bool) -> anyhow::Result<()> {
                 let load_settings = super::vm_loaders::uefi::UefiLoadSettings {
                     debugging: enable_debugging,
                     memory_protections: enable_memory_protections,
-                    frontpage: !disable_frontpage,
+                    // Honor a guest request to boot into the firmware UI
+                    // even if the frontpage is otherwise disabled.
+                    frontpage: !disable_frontpage
+                        || self.boot_to_fw_ui.swap(false, Ordering::Relaxed),
                     tpm: enable_tpm,
                     battery: enable_battery,
                     guest_watchdog: self.chipset_capabilities.with_guest_watchdog,
@@ -2835,6 +2987,7 @@ async fn load_firmware(&mut self, vtl2_only: bool) -> anyhow::Result<()> {
                     &srat,
                     mcfg.as_deref(),
                     pptt.as_deref(),
+                    &self.extra_acpi_tables,
                 )?;
 
                 (regs, Vec::new())
@@ -2930,6 +3083,23 @@ async fn load_firmware(&mut self, vtl2_only: bool) -> anyhow::Result<()> {
 }
 
 impl LoadedVm {
+    /// Revokes the channel of a vmbus device and removes its state unit,
+    /// returning the device.
+    async fn revoke_vmbus_device(
+        &mut self,
+        vtl: DeviceVtl,
+        instance_id: guid::Guid,
+    ) -> anyhow::Result<Box<dyn VmbusDevice>> {
+        let index = self
+            .inner
+            .vmbus_devices
+            .iter()
+            .position(|d| d.vtl == vtl && d.instance_id == instance_id)
+            .with_context(|| format!("no vmbus device {instance_id} in {vtl:?}"))?;
+        let device = self.inner.vmbus_devices.remove(index);
+        Ok(device.unit.remove().await.revoke().await)
+    }
+
     async fn resume(&mut self) -> bool {
         if self.running {
             return false;
@@ -3088,45 +3258,70 @@ enum Event {
                         rpc.handle_failable(async |()| self.save().await.map(ProtobufMessage::new))
                             .await
                     }
-                    VmRpc::Nmi(rpc) => rpc.handle_sync(|vpindex| {
-                        if vpindex < self.inner.processor_topology.vp_count() {
-                            // Send an NMI MSI to the processor. We could raise
-                            // LINT1 instead, which would allow the guest to
-                            // reconfigure the LINT to do something other than
-                            // an NMI. Since this is for diagnostics, that
-                            // doesn't seem like what we want.
-                            //
-                            // AARCH64-TODO: is there an equivalent?
-                            #[cfg(guest_arch = "x86_64")]
-                            self.inner.partition.request_msi(
-                                Vtl::Vtl0,
-                                virt::irqcon::MsiRequest::new_x86(
-                                    virt::irqcon::DeliveryMode::NMI,
-                                    self.inner
-                                        .processor_topology
-                                        .vp_arch(VpIndex::new(vpindex))
-                                        .apic_id,
-                                    false,
-                                    0,
-                                    false,
-                                ),
-                            );
+                    VmRpc::Nmi(rpc) => rpc.handle_failable_sync(|vpindex| {
+                        if vpindex >= self.inner.processor_topology.vp_count() {
+                            anyhow::bail!("invalid vp index {vpindex}");
                         }
+                        // AARCH64-TODO: is there an equivalent?
+                        if cfg!(guest_arch = "aarch64") {
+                            anyhow::bail!("NMI injection is not supported on aarch64");
+                        }
+                        // Send an NMI MSI to the processor. We could raise
+                        // LINT1 instead, which would allow the guest to
+                        // reconfigure the LINT to do something other than an
+                        // NMI. Since this is for diagnostics, that doesn't
+                        // seem like what we want.
+                        #[cfg(guest_arch = "x86_64")]
+                        self.inner.partition.request_msi(
+                            Vtl::Vtl0,
+                            virt::irqcon::MsiRequest::new_x86(
+                                virt::irqcon::DeliveryMode::NMI,
+                                self.inner
+                                    .processor_topology
+                                    .vp_arch(VpIndex::new(vpindex))
+                                    .apic_id,
+                                false,
+                                0,
+                                false,
+                            ),
+                        );
+                        Ok(())
                     }),
+                    VmRpc::MachineCheck(rpc) => {
+                        rpc.handle_failable(async |vpindex| {
+                            if vpindex >= self.inner.processor_topology.vp_count() {
+                                anyhow::bail!("invalid vp index {vpindex}");
+                            }
+                            self.inner
+                                .partition_unit
+                                .inject_machine_check(VpIndex::new(vpindex))
+                                .await
+                        })
+                        .await
+                    }
                     VmRpc::AddVmbusDevice(rpc) => {
                         rpc.handle_failable(async |(vtl, resource)| {
-                            let vmbus = match vtl {
-                                DeviceVtl::Vtl0 => self.inner.vmbus_server.as_ref(),
-                                DeviceVtl::Vtl1 => None,
-                                DeviceVtl::Vtl2 => self.inner.vtl2_vmbus_server.as_ref(),
-                            }
-                            .context("no vmbus available")?;
-                            let device = offer_vmbus_device_handle_unit(
+                            let vmbus = self.inner.vmbus_server_for(vtl)?;
+                            let device = self
+                                .inner
+                                .resolver
+                                .resolve(
+                                    resource,
+                                    ResolveVmbusDeviceHandleParams {
+                                        driver_source: &self.inner.driver_source,
+                                        discard_ram: Some(
+                                            self.inner.memory_manager.ram_discard(),
+                                        ),
+                                        hot_add_ram: self.inner.memory_manager.ram_hot_add(),
+                                    },
+                                )
+                                .await?;
+                            let device = offer_vmbus_device(
                                 &self.inner.driver_source,
                                 &self.state_units,
                                 vmbus,
-                                &self.inner.resolver,
-                                resource,
+                                vtl,
+                                device.0,
                             )
                             .await?;
                             self.inner.vmbus_devices.push(device);
@@ -3135,6 +3330,52 @@ enum Event {
                         })
                         .await
                     }
+                    VmRpc::RemoveVmbusDevice(rpc) => {
+                        rpc.handle_failable(async |(vtl, instance_id)| {
+                            self.revoke_vmbus_device(vtl, instance_id).await?;
+                            anyhow::Ok(())
+                        })
+                        .await
+                    }
+                    VmRpc::ReofferVmbusDevice(rpc) => {
+                        rpc.handle_failable(async |(vtl, instance_id)| {
+                            let device = self.revoke_vmbus_device(vtl, instance_id).await?;
+                            let vmbus = self.inner.vmbus_server_for(vtl)?;
+                            let device = offer_vmbus_device(
+                                &self.inner.driver_source,
+                                &self.state_units,
+                                vmbus,
+                                vtl,
+                                device,
+                            )
+                            .await?;
+                            self.inner.vmbus_devices.push(device);
+                            self.state_units.start_stopped_units().await;
+                            anyhow::Ok(())
+                        })
+                        .await
+                    }
+                    VmRpc::ReofferVpciDevice(rpc) => {
+                        rpc.handle_failable(async |(vtl, instance_id)| {
+                            let (_, _, bus) = self
+                                .inner
+                                .vpci_buses
+                                .iter()
+                                .find(|(bus_vtl, id, _)| *bus_vtl == vtl && *id == instance_id)
+                                .with_context(|| {
+                                    format!("no vpci device {instance_id} in {vtl:?}")
+                                })?;
+                            let vmbus = self.inner.vmbus_server_for(vtl)?;
+                            VpciBus::reoffer(
+                                bus,
+                                &self.inner.driver_source,
+                                vmbus.control().as_ref(),
+                            )
+                            .await?;
+                            anyhow::Ok(())
+                        })
+                        .await
+                    }
                     VmRpc::ConnectHvsock(rpc) => {
                         let ((mut ctx, service_id, vtl), response) = rpc.split();
                         if let Some(relay) = self.hvsock_relay(vtl) {
@@ -3174,6 +3415,10 @@ enum Event {
                         })
                         .await
                     }
+                    VmRpc::RollbackReloadIgvm(rpc) => {
+                        rpc.handle_failable(async |()| self.rollback_reload_igvm().await)
+                            .await
+                    }
                     VmRpc::ReadMemory(rpc) => {
                         rpc.handle_failable_sync(|(gpa, size)| {
                             let mut bytes = vec![0u8; size];
@@ -3218,10 +3463,15 @@ enum Event {
                             let segment = self.inner.pcie_host_bridges[rc_idx].segment;
                             let msi_conn = pci_core::msi::MsiConnection::new(bus_range.clone(), 0);
 
+                            let device_gm = device_guest_memory(
+                                &self.inner.gm,
+                                self.inner.device_memory_tracker.as_ref(),
+                                port_name.as_str(),
+                            );
                             let pcie_ctx = pcie_wiring::build_pcie_msi_context(
                                 &pcie_wiring::PcieWiringParams {
                                     partition: self.inner.partition.as_ref(),
-                                    guest_memory: &self.inner.gm,
+                                    guest_memory: &device_gm,
                                     #[cfg(guest_arch = "aarch64")]
                                     bus_range: &bus_range,
                                     segment,
@@ -3321,6 +3571,63 @@ enum Event {
                         rpc.handle_failable(async |file| self.dump_state(file).await)
                             .await
                     }
+                    VmRpc::RequestPcieDeviceRemoval(rpc) => {
+                        rpc.handle_failable_sync(|port_name: String| {
+                            let rc = self.inner.pcie_root_complexes.iter()
+                                .find(|rc| {
+                                    rc.lock().downstream_ports().iter().any(|p| p.name.as_ref() == port_name.as_str())
+                                })
+                                .ok_or_else(|| anyhow::anyhow!("port '{}' not found in any root complex", port_name))?;
+                            rc.lock().hotplug_request_removal(&port_name)
+                        })
+                    }
+                    VmRpc::PcieDeviceReleased(rpc) => {
+                        rpc.handle_failable_sync(|port_name: String| {
+                            let rc = self.inner.pcie_root_complexes.iter()
+                                .find(|rc| {
+                                    rc.lock().downstream_ports().iter().any(|p| p.name.as_ref() == port_name.as_str())
+                                })
+                                .ok_or_else(|| anyhow::anyhow!("port '{}' not found in any root complex", port_name))?;
+                            rc.lock().hotplug_device_released(&port_name)
+                        })
+                    }
+                    VmRpc::QueryDeviceMemoryAccesses(rpc) => {
+                        rpc.handle_failable_sync(|(start, end)| {
+                            let tracker = self
+                                .inner
+                                .device_memory_tracker
+                                .as_ref()
+                                .context("device memory tracking not enabled")?;
+                            let accesses = tracker
+                                .accesses_in(start..end)
+                                .into_iter()
+                                .map(|access| DeviceMemoryAccess {
+                                    device: access.device.to_string(),
+                                    gpa: access.gpa,
+                                    len: access.len,
+                                    kind: match access.kind {
+                                        AccessKind::Read => DeviceMemoryAccessKind::Read,
+                                        AccessKind::Write => DeviceMemoryAccessKind::Write,
+                                        AccessKind::Lock => DeviceMemoryAccessKind::Lock,
+                                    },
+                                })
+                                .collect();
+                            anyhow::Ok(DeviceMemoryAccesses {
+                                touched: tracker.is_touched(start..end),
+                                accesses,
+                            })
+                        })
+                    }
+                    VmRpc::ClearDeviceMemoryAccesses(rpc) => {
+                        rpc.handle_failable_sync(|()| {
+                            self.inner
+                                .device_memory_tracker
+                                .as_ref()
+                                .context("device memory tracking not enabled")?
+                                .clear();
+                            anyhow::Ok(())
+                        })
+                    }
                 },
                 Event::Halt(Err(_)) => break,
                 Event::Halt(Ok(reason)) => {
@@ -3398,7 +3705,10 @@ async fn complete_reload_igvm(&mut self, complete: bool) -> anyhow::Result<()> {
         //
         // When the initial registers are set, this will implicitly reset VTL2
         // state as well.
-        let _old_igvm_file = self.inner.igvm_file.replace(next_igvm_file);
+        //
+        // Keep the old file so that a failed servicing operation can be
+        // rolled back.
+        self.inner.previous_igvm_file = self.inner.igvm_file.replace(next_igvm_file);
         self.inner
             .load_firmware(true)
             .await
@@ -3409,6 +3719,16 @@ async fn complete_reload_igvm(&mut self, complete: bool) -> anyhow::Result<()> {
         Ok(())
     }
 
+    async fn rollback_reload_igvm(&mut self) -> anyhow::Result<()> {
+        let previous_igvm_file = self
+            .inner
+            .previous_igvm_file
+            .take()
+            .context("no previous igvm file")?;
+        self.inner.next_igvm_file = Some(previous_igvm_file);
+        self.complete_reload_igvm(true).await
+    }
+
     /// Get the associated hvsock relay for a given vtl, if any.
     fn hvsock_relay(&self, vtl: DeviceVtl) -> Option<&HvsockRelay> {
         match vtl {
@@ -3495,7 +3815,9 @@ async fn serialize(
             }, // TODO
             rtc_delta_milliseconds: 0, // TODO
             automatic_guest_reset: self.inner.automatic_guest_reset,
+            extra_acpi_tables: self.inner.extra_acpi_tables,
             efi_diagnostics_log_level: Default::default(),
+            track_device_memory: self.inner.device_memory_tracker.is_some(),
         };
         #[expect(unreachable_code, reason = "TODO")]
         RestartState {
@@ -1365,3 +1365,60 @@ fn flush<R: GuestArch>(&mut self, loader: &mut dyn ImageLoad<R>) -> Result<(), E
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use igvmfilegen::ImageResources;
+    use igvmfilegen_config::Config;
+    use igvmfilegen_config::ConfigIsolationType;
+    use igvmfilegen_config::GuestArch;
+    use igvmfilegen_config::GuestConfig;
+    use igvmfilegen_config::Image;
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    /// Builds an IGVM file with no images, and so no VTL2 memory range.
+    fn build_empty_igvm_file() -> Vec<u8> {
+        let config = Config {
+            guest_arch: GuestArch::X64,
+            guest_configs: vec![GuestConfig {
+                guest_svn: 0,
+                max_vtl: 0,
+                isolation_type: ConfigIsolationType::None,
+                image: Image::None,
+            }],
+        };
+        igvmfilegen::build_igvm_file(config, ImageResources::Memory(&HashMap::new()), false)
+            .unwrap()
+            .binary
+    }
+
+    fn read(binary: &[u8]) -> Result<IgvmFile, Error> {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(binary).unwrap();
+        read_igvm_file(&file)
+    }
+
+    #[test]
+    fn missing_vtl2_memory_source() {
+        let igvm_file = read(&build_empty_igvm_file()).unwrap();
+        assert!(!supports_relocations(&igvm_file));
+        assert!(matches!(
+            vtl2_memory_info(&igvm_file),
+            Err(Error::Vtl2MemorySource)
+        ));
+        assert!(matches!(
+            vtl2_memory_layout_request(&igvm_file, None),
+            Err(Error::Vtl2MemorySource)
+        ));
+    }
+
+    #[test]
+    fn corrupted_header() {
+        let mut binary = build_empty_igvm_file();
+        // Corrupt the magic number in the fixed header.
+        binary[0] ^= 0xff;
+        assert!(matches!(read(&binary), Err(Error::InvalidIgvmFile(_))));
+    }
+}
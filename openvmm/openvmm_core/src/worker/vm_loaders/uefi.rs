@@ -60,6 +60,7 @@ pub fn load_uefi(
     srat: &[u8],
     mcfg: Option<&[u8]>,
     pptt: Option<&[u8]>,
+    extra_acpi_tables: &[Vec<u8>],
 ) -> Result<Vec<Register>, Error> {
     let mut loaded_image;
     let image = {
@@ -182,6 +183,10 @@ pub fn load_uefi(
         }
     }
 
+    for table in extra_acpi_tables {
+        cfg.add_raw(config::BlobStructureType::AcpiTable, table);
+    }
+
     if !pcie_host_bridges.is_empty() {
         let entries: Vec<config::PcieBarApertureEntry> = pcie_host_bridges
             .iter()
@@ -63,7 +63,17 @@ pub struct Config {
     pub rtc_delta_milliseconds: i64,
     /// allow the guest to reset without notifying the client
     pub automatic_guest_reset: bool,
+    /// Additional raw ACPI tables (such as SSDTs) to expose to the guest.
+    ///
+    /// The table checksums are recomputed and the tables are added to the
+    /// XSDT. Only supported with Linux direct and UEFI boot.
+    pub extra_acpi_tables: Vec<Vec<u8>>,
     pub efi_diagnostics_log_level: EfiDiagnosticsLogLevelType,
+    /// Record the guest memory accesses made by PCI devices, so that tests can
+    /// query them with [`VmRpc::QueryDeviceMemoryAccesses`](crate::rpc::VmRpc::QueryDeviceMemoryAccesses).
+    ///
+    /// Every device access takes a slow path, so this is only for tests.
+    pub track_device_memory: bool,
 }
 
 pub const DEFAULT_GIC_DISTRIBUTOR_BASE: u64 = 0xFFFF_0000;
@@ -376,10 +386,17 @@ pub struct MemoryConfig {
     pub transparent_hugepages: bool,
     pub hugepages: bool,
     pub hugepage_size: Option<u64>,
+    /// Fall back to regular pages if the hugetlb pool is too small for
+    /// `hugepages`.
+    pub hugepage_fallback: bool,
     /// Test only: per-NUMA-node memory sizes. When set, RAM is distributed
     /// across vNUMA nodes according to these sizes instead of assigning all RAM
     /// to node 0. The sum must equal `mem_size`.
     pub numa_mem_sizes: Option<Vec<u64>>,
+    /// The size of the address space to reserve, above the rest of the
+    /// memory layout, for RAM hot-added at runtime through the dynamic memory
+    /// device.
+    pub hot_add_size: Option<u64>,
 }
 
 #[derive(Debug, MeshPayload, Default)]
@@ -397,6 +414,32 @@ pub struct HypervisorConfig {
     pub with_hv: bool,
     pub with_vtl2: Option<Vtl2Config>,
     pub with_isolation: Option<IsolationType>,
+    /// User-specified CPUID overrides, applied on top of the leaves computed
+    /// by the VMM and the hypervisor backend.
+    pub cpuid_overrides: Vec<CpuidOverride>,
+    /// Halt the VM when the guest reports a crash through the guest crash
+    /// MSRs, either directly or through OpenHCL. Otherwise the crash is only
+    /// logged.
+    pub halt_on_guest_crash: bool,
+}
+
+/// A user-specified CPUID override.
+///
+/// Each backend applies overrides after all other CPUID leaves, including the
+/// leaves it computes itself (such as topology leaves and KVM's PSFD fixup),
+/// so that the guest sees the same value for the overridden bits on every
+/// backend.
+#[derive(Debug, Clone, Protobuf)]
+pub struct CpuidOverride {
+    /// The CPUID function/leaf, provided in eax.
+    pub function: u32,
+    /// The CPUID index/subleaf, provided in ecx. If `None`, the override
+    /// applies to all subleaves.
+    pub index: Option<u32>,
+    /// The eax, ebx, ecx, and edx values.
+    pub result: [u32; 4],
+    /// The bits of `result` to override.
+    pub mask: [u32; 4],
 }
 
 #[derive(Debug, MeshPayload)]
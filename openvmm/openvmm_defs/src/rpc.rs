@@ -24,12 +24,28 @@ pub enum VmRpc {
     Pause(Rpc<(), bool>),
     ClearHalt(Rpc<(), bool>),
     Reset(FailableRpc<(), ()>),
-    Nmi(Rpc<u32, ()>),
+    /// Injects an NMI into the given VTL0 processor.
+    Nmi(FailableRpc<u32, ()>),
+    /// Injects a machine check exception into the given VTL0 processor.
+    MachineCheck(FailableRpc<u32, ()>),
     AddVmbusDevice(FailableRpc<(DeviceVtl, Resource<VmbusDeviceHandleKind>), ()>),
+    /// Revokes the channel of the vmbus device with the given instance ID and
+    /// removes the device.
+    RemoveVmbusDevice(FailableRpc<(DeviceVtl, Guid), ()>),
+    /// Revokes the channel of the vmbus device with the given instance ID and
+    /// then offers the same device again, to exercise channel teardown in the
+    /// guest and the device.
+    ReofferVmbusDevice(FailableRpc<(DeviceVtl, Guid), ()>),
+    /// Revokes the channel of the VPCI bus with the given instance ID and then
+    /// offers it again, as when the host hot-removes and re-adds the device.
+    ReofferVpciDevice(FailableRpc<(DeviceVtl, Guid), ()>),
     ConnectHvsock(FailableRpc<(CancelContext, Guid, DeviceVtl), unix_socket::UnixStream>),
     PulseSaveRestore(Rpc<(), Result<(), PulseSaveRestoreError>>),
     StartReloadIgvm(FailableRpc<File, ()>),
     CompleteReloadIgvm(FailableRpc<bool, ()>),
+    /// Reloads the IGVM file that was replaced by the last
+    /// `CompleteReloadIgvm`, to roll back a failed servicing operation.
+    RollbackReloadIgvm(FailableRpc<(), ()>),
     ReadMemory(FailableRpc<(u64, usize), Vec<u8>>),
     WriteMemory(FailableRpc<(u64, Vec<u8>), ()>),
     /// Updates the command line parameters that will be passed to the boot shim
@@ -47,6 +63,56 @@ pub enum VmRpc {
     /// handle to write to (typically a temporary file that gets renamed
     /// into place on success).
     DumpState(FailableRpc<File, ()>),
+    /// Request that the guest release the PCIe device in a named port, by
+    /// pressing the slot's attention button.
+    ///
+    /// Poll [`VmRpc::PcieDeviceReleased`] to find out when the guest is done,
+    /// then send [`VmRpc::RemovePcieDevice`].
+    RequestPcieDeviceRemoval(FailableRpc<String, ()>),
+    /// Returns whether the guest has released the PCIe device in a named
+    /// port, as indicated by it turning off the slot's power and power
+    /// indicator.
+    PcieDeviceReleased(FailableRpc<String, bool>),
+    /// Returns the PCI device accesses to the guest memory range `(start,
+    /// end)` recorded since the VM was created or the last
+    /// [`VmRpc::ClearDeviceMemoryAccesses`].
+    ///
+    /// Fails unless the VM was configured with
+    /// [`Config::track_device_memory`](crate::config::Config::track_device_memory).
+    QueryDeviceMemoryAccesses(FailableRpc<(u64, u64), DeviceMemoryAccesses>),
+    /// Forgets the recorded PCI device accesses to guest memory.
+    ClearDeviceMemoryAccesses(FailableRpc<(), ()>),
+}
+
+/// The device accesses to a range of guest memory.
+#[derive(Debug, MeshPayload)]
+pub struct DeviceMemoryAccesses {
+    /// Whether any device accessed the range or still holds a lock on it.
+    pub touched: bool,
+    /// The recorded accesses that overlap the range, oldest first.
+    ///
+    /// If the worker's journal overflowed, older accesses are missing, but
+    /// `touched` is still accurate.
+    pub accesses: Vec<DeviceMemoryAccess>,
+}
+
+/// A single device access to guest memory.
+#[derive(Debug, MeshPayload)]
+pub struct DeviceMemoryAccess {
+    /// The name of the device that made the access.
+    pub device: String,
+    pub gpa: u64,
+    pub len: u64,
+    pub kind: DeviceMemoryAccessKind,
+}
+
+/// The kind of a [`DeviceMemoryAccess`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, MeshPayload)]
+pub enum DeviceMemoryAccessKind {
+    Read,
+    Write,
+    /// The device locked the pages for direct access.
+    Lock,
 }
 
 #[derive(Debug, MeshPayload, thiserror::Error)]
@@ -72,17 +138,26 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             VmRpc::Pause(_) => "Pause",
             VmRpc::ClearHalt(_) => "ClearHalt",
             VmRpc::Nmi(_) => "Nmi",
+            VmRpc::MachineCheck(_) => "MachineCheck",
             VmRpc::AddVmbusDevice(_) => "AddVmbusDevice",
+            VmRpc::RemoveVmbusDevice(_) => "RemoveVmbusDevice",
+            VmRpc::ReofferVmbusDevice(_) => "ReofferVmbusDevice",
+            VmRpc::ReofferVpciDevice(_) => "ReofferVpciDevice",
             VmRpc::ConnectHvsock(_) => "ConnectHvsock",
             VmRpc::PulseSaveRestore(_) => "PulseSaveRestore",
             VmRpc::StartReloadIgvm(_) => "StartReloadIgvm",
             VmRpc::CompleteReloadIgvm(_) => "CompleteReloadIgvm",
+            VmRpc::RollbackReloadIgvm(_) => "RollbackReloadIgvm",
             VmRpc::ReadMemory(_) => "ReadMemory",
             VmRpc::WriteMemory(_) => "WriteMemory",
             VmRpc::UpdateCliParams(_) => "UpdateCliParams",
             VmRpc::AddPcieDevice(_) => "AddPcieDevice",
             VmRpc::RemovePcieDevice(_) => "RemovePcieDevice",
             VmRpc::DumpState(_) => "DumpState",
+            VmRpc::RequestPcieDeviceRemoval(_) => "RequestPcieDeviceRemoval",
+            VmRpc::PcieDeviceReleased(_) => "PcieDeviceReleased",
+            VmRpc::QueryDeviceMemoryAccesses(_) => "QueryDeviceMemoryAccesses",
+            VmRpc::ClearDeviceMemoryAccesses(_) => "ClearDeviceMemoryAccesses",
         };
         f.pad(s)
     }
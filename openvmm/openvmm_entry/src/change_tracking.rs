@@ -0,0 +1,117 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Changed block tracking for disks, queried and reset through the VM
+//! controller and saved with snapshots.
+
+use anyhow::Context;
+use disk_backend_resources::ChangeTrackingDiskHandle;
+use disk_backend_resources::ChangeTrackingRequest;
+use disk_backend_resources::ChangedBlocks;
+use mesh::rpc::RpcSend;
+use std::collections::BTreeMap;
+use std::path::Path;
+use vm_resource::IntoResource;
+use vm_resource::Resource;
+use vm_resource::kind::DiskHandleKind;
+
+/// The granularity at which changes are tracked.
+const BLOCK_SIZE: u32 = 0x10000;
+
+/// The name of the file in a snapshot directory holding the changed blocks.
+const SNAPSHOT_FILE: &str = "changed_blocks.bin";
+
+#[derive(mesh::MeshPayload)]
+struct SavedChangedBlocks {
+    disks: Vec<SavedDisk>,
+}
+
+#[derive(mesh::MeshPayload)]
+struct SavedDisk {
+    name: String,
+    changed: ChangedBlocks,
+}
+
+/// The change tracking disks of a VM, by name.
+#[derive(Default)]
+pub struct ChangeTrackers {
+    /// Changed blocks restored from a snapshot, not yet claimed by a disk.
+    restored: BTreeMap<String, ChangedBlocks>,
+    disks: BTreeMap<String, mesh::Sender<ChangeTrackingRequest>>,
+}
+
+impl ChangeTrackers {
+    /// Returns trackers that restore the changed blocks saved in the
+    /// snapshot directory `dir`, if any.
+    pub fn from_snapshot(dir: &Path) -> anyhow::Result<Self> {
+        let path = dir.join(SNAPSHOT_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let saved: SavedChangedBlocks = mesh::payload::decode(&fs_err::read(&path)?)
+            .with_context(|| format!("failed to decode {}", path.display()))?;
+        Ok(Self {
+            restored: saved
+                .disks
+                .into_iter()
+                .map(|disk| (disk.name, disk.changed))
+                .collect(),
+            disks: BTreeMap::new(),
+        })
+    }
+
+    /// Wraps `disk` in a change tracking disk named `name`.
+    pub fn add(
+        &mut self,
+        name: &str,
+        disk: Resource<DiskHandleKind>,
+    ) -> anyhow::Result<Resource<DiskHandleKind>> {
+        if self.disks.contains_key(name) {
+            anyhow::bail!("duplicate change tracking disk name {name}");
+        }
+        let (send, recv) = mesh::channel();
+        self.disks.insert(name.to_owned(), send);
+        Ok(ChangeTrackingDiskHandle {
+            disk,
+            block_size: BLOCK_SIZE,
+            requests: recv,
+            saved_state: self.restored.remove(name),
+        }
+        .into_resource())
+    }
+
+    /// Returns the blocks changed in the current epoch of disk `name`. If
+    /// `reset` is true, atomically starts a new epoch.
+    pub async fn changed_blocks(&self, name: &str, reset: bool) -> anyhow::Result<ChangedBlocks> {
+        let disk = self
+            .disks
+            .get(name)
+            .with_context(|| format!("no change tracking disk named {name}"))?;
+        let changed = if reset {
+            disk.call(ChangeTrackingRequest::Reset, ()).await
+        } else {
+            disk.call(ChangeTrackingRequest::Query, ()).await
+        };
+        changed.context("change tracking disk is gone")
+    }
+
+    /// Saves the changed blocks of all disks to the snapshot directory
+    /// `dir`. The VM must be paused.
+    pub async fn save_snapshot(&self, dir: &Path) -> anyhow::Result<()> {
+        if self.disks.is_empty() {
+            return Ok(());
+        }
+        let mut disks = Vec::new();
+        for name in self.disks.keys() {
+            disks.push(SavedDisk {
+                name: name.clone(),
+                changed: self.changed_blocks(name, false).await?,
+            });
+        }
+        fs_err::write(
+            dir.join(SNAPSHOT_FILE),
+            mesh::payload::encode(SavedChangedBlocks { disks }),
+        )?;
+        Ok(())
+    }
+}
@@ -22,6 +22,7 @@
 use clap::Parser;
 use clap::ValueEnum;
 use cxl_spec::spec::CfmwsWindowRestrictions;
+use openvmm_defs::config::CpuidOverride;
 use openvmm_defs::config::DEFAULT_PCAT_BOOT_ORDER;
 use openvmm_defs::config::DeviceVtl;
 use openvmm_defs::config::PcatBootDevice;
@@ -50,6 +51,8 @@ pub struct MemoryCli {
     pub hugepages: bool,
     /// Explicit hugetlb page size in bytes.
     pub hugepage_size: Option<u64>,
+    /// Whether to fall back to regular pages if the hugetlb pool is too small.
+    pub hugepage_fallback: bool,
     /// File used to back guest RAM.
     pub file: Option<PathBuf>,
 }
@@ -59,7 +62,18 @@ pub struct MemoryCli {
 /// This is not yet a stable interface and may change radically between
 /// versions.
 #[derive(Parser)]
+// Let a later occurrence of an option replace an earlier one, so that the
+// command line overrides the config file.
+#[clap(args_override_self = true)]
 pub struct Options {
+    /// read options from the specified TOML file.
+    ///
+    /// each key is the long name of an option, e.g. `processors = 4` or
+    /// `disk = ["file:os.vhdx"]`. single-valued options on the command line
+    /// override the ones in the file; repeatable ones are added to them.
+    #[clap(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
     /// processor count
     #[clap(short = 'p', long, value_name = "COUNT", default_value = "1")]
     pub processors: u32,
@@ -83,13 +97,17 @@ pub struct Options {
     shared=on|off            use shared file-backed RAM, default on
     prefetch=on|off          pre-populate shared RAM mappings
     thp=on|off               mark private RAM as THP-eligible; requires shared=off
-    hugepages=on|off         allocate RAM from Linux hugetlb pages
+    hugepages=on|off|fallback
+                             allocate RAM from Linux hugetlb pages; with
+                             fallback, use regular pages if the hugetlb pool
+                             is too small
     hugepage_size=<SIZE>     hugetlb page size, default 2MB; requires hugepages=on
     file=<PATH>              use an existing file as guest RAM backing
 
 Examples:
     --memory 4G
     --memory size=64GB,hugepages=on,hugepage_size=2MB
+    --memory size=64GB,hugepages=fallback,hugepage_size=1GB
     --memory size=4G,file=path/to/memory.bin
     --memory size=4G,shared=off,thp=on"#
     )]
@@ -160,6 +178,11 @@ pub struct Options {
     #[clap(long)]
     pub hv: bool,
 
+    /// halt the VM when the guest reports a crash through the Hyper-V guest
+    /// crash MSRs, instead of letting it write a crash dump
+    #[clap(long, requires("hv"))]
+    pub halt_on_guest_crash: bool,
+
     /// Use a full device tree instead of ACPI tables for ARM64 Linux direct
     /// boot. By default, ARM64 uses ACPI mode (stub DT + EFI + ACPI tables).
     /// This flag selects the legacy DT-only path. Rejected on x86.
@@ -213,9 +236,10 @@ pub struct Options {
         <len>: length of ramdisk, e.g.: `1G`
     `memdiff:<disk>`               memory backed diff disk
         <disk>: lower disk, e.g.: `file:base.img`
-    `file:<path>[;direct][;create=<len>]`   file-backed disk
+    `file:<path>[;direct][;rct][;create=<len>]`   file-backed disk
         <path>: path to file
         `;direct`: bypass the OS page cache
+        `;rct`: enable resilient change tracking (Windows, VHDX only)
     `sql:<path>[;create=<len>]`    SQLite-backed disk (dev/test)
     `sqldiff:<path>[;create]:<disk>` SQLite diff layer on a backing disk
     `autocache:<key>:<disk>`       auto-cached SQLite layer (use `autocache::<disk>` to omit key; needs OPENVMM_AUTO_CACHE_PATH)
@@ -224,6 +248,8 @@ pub struct Options {
     `crypt:<cipher>:<key_file>:<disk>` encrypted disk wrapper
         <cipher>: `xts-aes-256`
     `prwrap:<disk>`                persistent reservations wrapper
+    `track:<name>:<disk>`          changed block tracking wrapper
+        <name>: name for querying the changed blocks, e.g. with `changed-blocks`
 
 flags:
     `ro`                           open disk as read-only
@@ -249,9 +275,10 @@ pub struct Options {
         <len>: length of ramdisk, e.g.: `1G`
     `memdiff:<disk>`               memory backed diff disk
         <disk>: lower disk, e.g.: `file:base.img`
-    `file:<path>[;direct][;create=<len>]`   file-backed disk
+    `file:<path>[;direct][;rct][;create=<len>]`   file-backed disk
         <path>: path to file
         `;direct`: bypass the OS page cache
+        `;rct`: enable resilient change tracking (Windows, VHDX only)
     `sql:<path>[;create=<len>]`    SQLite-backed disk (dev/test)
     `sqldiff:<path>[;create]:<disk>` SQLite diff layer on a backing disk
     `autocache:<key>:<disk>`       auto-cached SQLite layer (use `autocache::<disk>` to omit key; needs OPENVMM_AUTO_CACHE_PATH)
@@ -260,6 +287,8 @@ pub struct Options {
     `crypt:<cipher>:<key_file>:<disk>` encrypted disk wrapper
         <cipher>: `xts-aes-256`
     `prwrap:<disk>`                persistent reservations wrapper
+    `track:<name>:<disk>`          changed block tracking wrapper
+        <name>: name for querying the changed blocks, e.g. with `changed-blocks`
 
 flags:
     `ro`                           open disk as read-only
@@ -273,6 +302,11 @@ pub struct Options {
     #[clap(long)]
     pub nvme: Vec<DiskCli>,
 
+    /// expose the contents of a file as the active boot partition of the VTL0
+    /// NVMe controller
+    #[clap(long, value_name = "FILE")]
+    pub nvme_boot_partition: Option<PathBuf>,
+
     /// attach a CXL Type-3 test endpoint on a PCIe root port
     #[clap(long = "cxl-test", value_name = "mem:<len>,pcie_port=<name>")]
     pub cxl_test: Vec<CxlTestDeviceCli>,
@@ -288,9 +322,10 @@ pub struct Options {
         <len>: length of ramdisk, e.g.: `1G`
     `memdiff:<disk>`               memory backed diff disk
         <disk>: lower disk, e.g.: `file:base.img`
-    `file:<path>[;direct]`                  file-backed disk
+    `file:<path>[;direct][;rct]`            file-backed disk
         <path>: path to file
         `;direct`: bypass the OS page cache
+        `;rct`: enable resilient change tracking (Windows, VHDX only)
 
 flags:
     `ro`                           open disk as read-only
@@ -337,7 +372,8 @@ pub struct Options {
     #[clap(long)]
     pub nic: bool,
 
-    /// expose a virtual NIC with the given backend (consomme | dio | tap | none)
+    /// expose a virtual NIC with the given backend (consomme | dio | tap |
+    /// af_xdp | none)
     ///
     /// Prefix with `uh:` to add this NIC via Mana emulation through OpenHCL,
     /// `vtl2:` to assign this NIC to VTL2, or `pcie_port=<port_name>:` to
@@ -348,6 +384,24 @@ pub struct Options {
     ///   --net consomme:hostfwd=tcp:127.0.0.1:8080-:80
     ///   --net consomme:hostfwd=tcp:\[::1\]:8080-:80
     ///   --net consomme:10.0.0.0/24,hostfwd=tcp::22-:22,hostfwd=udp::5000-:5000
+    ///
+    /// and forward guest DNS traffic to specific servers, instead of using the
+    /// built-in resolver, with `dns=`:
+    ///   --net consomme:dns=1.1.1.1,dns=8.8.8.8
+    ///
+    /// The guest is assigned the network's second address via DHCP unless one
+    /// is reserved with `guest_ip=`:
+    ///   --net consomme:10.0.0.0/24,guest_ip=10.0.0.100
+    ///
+    /// and network boot the guest from a host directory, served over TFTP,
+    /// with `tftp=` and `bootfile=`:
+    ///   --net consomme:tftp=/srv/tftp,bootfile=bootx64.efi
+    ///
+    /// For af_xdp (requires building with the `net_af_xdp` feature), attach
+    /// directly to the queues of a host interface, adding `:zerocopy` to fail
+    /// if the driver does not support zero-copy:
+    ///   --net af_xdp:eth1
+    ///   --net af_xdp:eth1:zerocopy
     #[clap(long)]
     pub net: Vec<NicConfigCli>,
 
@@ -391,6 +445,12 @@ pub struct Options {
     #[clap(long, default_value = "auto", value_parser = parse_x2apic)]
     pub x2apic: X2ApicConfig,
 
+    /// override CPUID result bits, consistently across hypervisor backends
+    /// (LEAF[/SUBLEAF]:REG=VALUE[/MASK], e.g. 7/0:ebx=0/0x20; repeatable)
+    #[cfg(guest_arch = "x86_64")]
+    #[clap(long, value_name = "OVERRIDE", value_parser = parse_cpuid_override)]
+    pub cpuid: Vec<CpuidOverride>,
+
     /// configure PCIe MSI controller for aarch64 (auto | its | v2m)
     #[cfg(guest_arch = "aarch64")]
     #[clap(long, default_value = "auto")]
@@ -527,6 +587,22 @@ pub struct Options {
     #[clap(long, value_name = "PORT", requires("virtio_rng"))]
     pub virtio_rng_pcie_port: Option<String>,
 
+    /// add a virtio memory balloon device, adjustable with the `balloon` command
+    #[clap(long)]
+    pub virtio_balloon: bool,
+
+    /// add the virtio-balloon device under either the PCI or MMIO bus, or whatever the hypervisor supports (pci | mmio | vpci | auto)
+    #[clap(long, value_name = "BUS", default_value = "auto")]
+    pub virtio_balloon_bus: VirtioBusCli,
+
+    /// add a Hyper-V dynamic memory device, adjustable with the `dynamic-memory` command
+    #[clap(long)]
+    pub dynamic_memory: bool,
+
+    /// reserve address space for up to SIZE of RAM to hot-add with the `dynamic-memory` command
+    #[clap(long, value_name = "SIZE", value_parser = parse_memory, requires("dynamic_memory"))]
+    pub hot_add_size: Option<u64>,
+
     /// virtio console device backed by a serial backend (/dev/hvc0 in guest)
     ///
     /// Accepts serial config (console | stderr | listen=\<path\> |
@@ -548,7 +624,9 @@ pub struct Options {
     ///
     /// Prefix with `uh:` to add this NIC via Mana emulation through OpenHCL,
     /// `vtl2:` to assign this NIC to VTL2, or `pcie_port=<port_name>:` to
-    /// expose the NIC over emulated PCIe at the specified port.
+    /// expose the NIC over emulated PCIe at the specified port. Prefix with
+    /// `busy_poll:` to poll the queues continuously, trading a busy host
+    /// thread per queue pair for lower latency.
     #[clap(long)]
     pub virtio_net: Vec<NicConfigCli>,
 
@@ -570,6 +648,23 @@ pub struct Options {
     #[clap(long, value_name = "SOCKETPATH", conflicts_with("ttrpc"))]
     pub grpc: Option<PathBuf>,
 
+    /// run as a grpc server over TLS on the specified TCP address
+    #[clap(
+        long,
+        value_name = "ADDR",
+        conflicts_with_all = ["ttrpc", "grpc"],
+        requires_all = ["grpc_tls_cert", "grpc_tls_key"]
+    )]
+    pub grpc_tcp: Option<SocketAddr>,
+
+    /// the PEM-encoded TLS certificate chain for --grpc-tcp
+    #[clap(long, value_name = "PATH", requires("grpc_tcp"))]
+    pub grpc_tls_cert: Option<PathBuf>,
+
+    /// the PEM-encoded PKCS #8 TLS private key for --grpc-tcp
+    #[clap(long, value_name = "PATH", requires("grpc_tcp"))]
+    pub grpc_tls_key: Option<PathBuf>,
+
     /// do not launch child processes
     #[clap(long)]
     pub single_process: bool,
@@ -579,7 +674,9 @@ pub struct Options {
     #[clap(long, value_name = "PATH")]
     pub device: Vec<String>,
 
-    /// instead of showing the frontpage the VM will shutdown instead
+    /// instead of showing the frontpage the VM will shutdown instead. The
+    /// frontpage is still shown when the guest asks to boot into firmware
+    /// setup
     #[clap(long, requires("uefi"))]
     pub disable_frontpage: bool,
 
@@ -695,6 +792,14 @@ pub struct Options {
     #[clap(long, value_name = "FILE", conflicts_with_all(&["uefi", "pcat", "igvm"]))]
     pub custom_dsdt: Option<PathBuf>,
 
+    /// add a raw ACPI table (e.g: an SSDT) to the guest's ACPI tables (can be
+    /// passed multiple times)
+    ///
+    /// The table's checksum is recomputed, so it does not need to be valid in
+    /// the file. Only supported with linux direct and UEFI boot.
+    #[clap(long, value_name = "FILE", conflicts_with_all(&["pcat", "igvm"]))]
+    pub acpi_table: Vec<PathBuf>,
+
     /// attach an ide drive (can be passed multiple times)
     ///
     /// Each ide controller has two channels. Each channel can have up to two
@@ -1178,6 +1283,7 @@ fn parse_memory_config(s: &str) -> anyhow::Result<MemoryCli> {
             transparent_hugepages: false,
             hugepages: false,
             hugepage_size: None,
+            hugepage_fallback: false,
             file: None,
         });
     }
@@ -1189,6 +1295,7 @@ fn parse_memory_config(s: &str) -> anyhow::Result<MemoryCli> {
     let mut transparent_hugepages = None;
     let mut hugepages = None;
     let mut hugepage_size = None;
+    let mut hugepage_fallback = false;
     let mut file = None;
 
     for part in s.split(',') {
@@ -1229,7 +1336,12 @@ fn parse_memory_config(s: &str) -> anyhow::Result<MemoryCli> {
                 if hugepages.is_some() {
                     anyhow::bail!("duplicate memory option 'hugepages'");
                 }
-                hugepages = Some(parse_memory_toggle(key, value)?);
+                if value == "fallback" {
+                    hugepages = Some(true);
+                    hugepage_fallback = true;
+                } else {
+                    hugepages = Some(parse_memory_toggle(key, value)?);
+                }
             }
             "hugepage_size" => {
                 if hugepage_size.is_some() {
@@ -1269,6 +1381,7 @@ fn parse_memory_config(s: &str) -> anyhow::Result<MemoryCli> {
         transparent_hugepages: transparent_hugepages.unwrap_or(false),
         hugepages: hugepages.unwrap_or(false),
         hugepage_size,
+        hugepage_fallback,
         file,
     })
 }
@@ -1306,11 +1419,17 @@ pub enum DiskCliKind {
     },
     // prwrap:<kind>
     PersistentReservationsWrapper(Box<DiskCliKind>),
-    // file:<path>[;direct][;create=<len>]
+    // track:<name>:<kind>
+    ChangeTracking {
+        name: String,
+        disk: Box<DiskCliKind>,
+    },
+    // file:<path>[;direct][;rct][;create=<len>]
     File {
         path: PathBuf,
         create_with_len: Option<u64>,
         direct: bool,
+        rct: bool,
     },
     // blob:<type>:<url>
     Blob {
@@ -1346,12 +1465,14 @@ struct FileOpts {
     path: PathBuf,
     create_with_len: Option<u64>,
     direct: bool,
+    rct: bool,
 }
 
 fn parse_file_opts(arg: &str) -> anyhow::Result<FileOpts> {
     let mut path = arg;
     let mut create_with_len = None;
     let mut direct = false;
+    let mut rct = false;
 
     // Parse semicolon-delimited options after the path.
     if let Some((p, rest)) = arg.split_once(';') {
@@ -1361,8 +1482,12 @@ fn parse_file_opts(arg: &str) -> anyhow::Result<FileOpts> {
                 create_with_len = Some(parse_memory(len)?);
             } else if opt == "direct" {
                 direct = true;
+            } else if opt == "rct" {
+                rct = true;
             } else {
-                anyhow::bail!("invalid file option '{opt}', expected 'create=<len>' or 'direct'");
+                anyhow::bail!(
+                    "invalid file option '{opt}', expected 'create=<len>', 'direct' or 'rct'"
+                );
             }
         }
     }
@@ -1371,6 +1496,7 @@ fn parse_file_opts(arg: &str) -> anyhow::Result<FileOpts> {
         path: path.into(),
         create_with_len,
         direct,
+        rct,
     })
 }
 
@@ -1402,11 +1528,13 @@ fn from_str(s: &str) -> anyhow::Result<Self> {
                     path,
                     create_with_len,
                     direct,
+                    rct,
                 } = parse_file_opts(s)?;
                 DiskCliKind::File {
                     path,
                     create_with_len,
                     direct,
+                    rct,
                 }
             }
             Some((kind, arg)) => match kind {
@@ -1417,10 +1545,14 @@ fn from_str(s: &str) -> anyhow::Result<Self> {
                         path,
                         create_with_len,
                         direct,
+                        rct,
                     } = parse_file_opts(arg)?;
                     if direct {
                         anyhow::bail!("'direct' is not supported for 'sql' disks");
                     }
+                    if rct {
+                        anyhow::bail!("'rct' is not supported for 'sql' disks");
+                    }
                     DiskCliKind::Sqlite {
                         path,
                         create_with_len,
@@ -1452,16 +1584,28 @@ fn from_str(s: &str) -> anyhow::Result<Self> {
                     Self::parse_autocache(arg, std::env::var("OPENVMM_AUTO_CACHE_PATH"))?
                 }
                 "prwrap" => DiskCliKind::PersistentReservationsWrapper(Box::new(arg.parse()?)),
+                "track" => {
+                    let (name, kind) = arg.split_once(':').context("expected name:kind")?;
+                    if name.is_empty() {
+                        anyhow::bail!("missing change tracking name");
+                    }
+                    DiskCliKind::ChangeTracking {
+                        name: name.to_owned(),
+                        disk: Box::new(kind.parse()?),
+                    }
+                }
                 "file" => {
                     let FileOpts {
                         path,
                         create_with_len,
                         direct,
+                        rct,
                     } = parse_file_opts(arg)?;
                     DiskCliKind::File {
                         path,
                         create_with_len,
                         direct,
+                        rct,
                     }
                 }
                 "blob" => {
@@ -1497,12 +1641,14 @@ fn from_str(s: &str) -> anyhow::Result<Self> {
                         path,
                         create_with_len,
                         direct,
+                        rct,
                     } = parse_file_opts(s)?;
                     if path.has_root() {
                         DiskCliKind::File {
                             path,
                             create_with_len,
                             direct,
+                            rct,
                         }
                     } else {
                         anyhow::bail!("invalid disk kind {kind}");
@@ -1875,7 +2021,11 @@ pub enum EndpointConfigCli {
     None,
     Consomme {
         cidr: Option<String>,
+        guest_ip: Option<std::net::Ipv4Addr>,
         host_fwd: Vec<HostPortConfigCli>,
+        dns: Vec<std::net::IpAddr>,
+        tftp_root: Option<String>,
+        boot_file: Option<String>,
     },
     Dio {
         id: Option<String>,
@@ -1883,6 +2033,10 @@ pub enum EndpointConfigCli {
     Tap {
         name: String,
     },
+    AfXdp {
+        interface: String,
+        require_zero_copy: bool,
+    },
 }
 
 /// Parsed host port forwarding configuration from the CLI.
@@ -1985,17 +2139,42 @@ fn from_str(s: &str) -> Result<Self, Self::Err> {
             ["consomme", rest @ ..] => {
                 let remaining = rest.join(":");
                 let mut cidr = None;
+                let mut guest_ip = None;
                 let mut host_fwd = Vec::new();
+                let mut dns = Vec::new();
+                let mut tftp_root = None;
+                let mut boot_file = None;
                 for opt in remaining.split(',').filter(|s| !s.is_empty()) {
                     if let Some(fwd) = opt.strip_prefix("hostfwd=") {
                         host_fwd.push(parse_hostfwd(fwd)?);
+                    } else if let Some(addr) = opt.strip_prefix("guest_ip=") {
+                        guest_ip = Some(
+                            addr.parse()
+                                .map_err(|e| format!("invalid guest address '{addr}': {e}"))?,
+                        );
+                    } else if let Some(addr) = opt.strip_prefix("dns=") {
+                        dns.push(
+                            addr.parse()
+                                .map_err(|e| format!("invalid dns address '{addr}': {e}"))?,
+                        );
+                    } else if let Some(path) = opt.strip_prefix("tftp=") {
+                        tftp_root = Some(path.to_owned());
+                    } else if let Some(name) = opt.strip_prefix("bootfile=") {
+                        boot_file = Some(name.to_owned());
                     } else if cidr.is_none() {
                         cidr = Some(opt.to_owned());
                     } else {
                         return Err(format!("unexpected consomme option '{opt}'"));
                     }
                 }
-                EndpointConfigCli::Consomme { cidr, host_fwd }
+                EndpointConfigCli::Consomme {
+                    cidr,
+                    guest_ip,
+                    host_fwd,
+                    dns,
+                    tftp_root,
+                    boot_file,
+                }
             }
             ["dio", s @ ..] => EndpointConfigCli::Dio {
                 id: s.first().map(|s| (*s).to_owned()),
@@ -2003,6 +2182,14 @@ fn from_str(s: &str) -> Result<Self, Self::Err> {
             ["tap", name] => EndpointConfigCli::Tap {
                 name: (*name).to_owned(),
             },
+            ["af_xdp", interface] => EndpointConfigCli::AfXdp {
+                interface: (*interface).to_owned(),
+                require_zero_copy: false,
+            },
+            ["af_xdp", interface, "zerocopy"] => EndpointConfigCli::AfXdp {
+                interface: (*interface).to_owned(),
+                require_zero_copy: true,
+            },
             _ => return Err("invalid network backend".into()),
         };
 
@@ -2017,6 +2204,7 @@ pub struct NicConfigCli {
     pub max_queues: Option<u16>,
     pub underhill: bool,
     pub pcie_port: Option<String>,
+    pub busy_poll: bool,
 }
 
 impl FromStr for NicConfigCli {
@@ -2027,6 +2215,7 @@ fn from_str(mut s: &str) -> Result<Self, Self::Err> {
         let mut max_queues = None;
         let mut underhill = false;
         let mut pcie_port = None;
+        let mut busy_poll = false;
         while let Some((opt, rest)) = s.split_once(':') {
             if let Some((opt, val)) = opt.split_once('=') {
                 match opt {
@@ -2047,6 +2236,7 @@ fn from_str(mut s: &str) -> Result<Self, Self::Err> {
                         vtl = DeviceVtl::Vtl2;
                     }
                     "uh" => underhill = true,
+                    "busy_poll" => busy_poll = true,
                     _ => break,
                 }
             }
@@ -2068,6 +2258,7 @@ fn from_str(mut s: &str) -> Result<Self, Self::Err> {
             max_queues,
             underhill,
             pcie_port,
+            busy_poll,
         })
     }
 }
@@ -2160,6 +2351,49 @@ fn parse_x2apic(s: &str) -> Result<X2ApicConfig, &'static str> {
     Ok(r)
 }
 
+/// Parses a CPUID override of the form `LEAF[/SUBLEAF]:REG=VALUE[/MASK]`,
+/// where `REG` is one of `eax`, `ebx`, `ecx`, or `edx`. If `MASK` is omitted,
+/// the entire register is overridden.
+#[cfg_attr(not(guest_arch = "x86_64"), expect(dead_code))]
+fn parse_cpuid_override(s: &str) -> anyhow::Result<CpuidOverride> {
+    let parse_u32 = |s: &str| -> anyhow::Result<u32> {
+        let n = parse_number(s).with_context(|| format!("invalid number '{s}'"))?;
+        u32::try_from(n).with_context(|| format!("'{s}' does not fit in 32 bits"))
+    };
+    let (leaf, reg) = s
+        .split_once(':')
+        .context("expected LEAF[/SUBLEAF]:REG=VALUE[/MASK]")?;
+    let (function, index) = match leaf.split_once('/') {
+        Some((function, index)) => (parse_u32(function)?, Some(parse_u32(index)?)),
+        None => (parse_u32(leaf)?, None),
+    };
+    let (reg, value) = reg.split_once('=').context("expected REG=VALUE")?;
+    let reg = match reg {
+        "eax" => 0,
+        "ebx" => 1,
+        "ecx" => 2,
+        "edx" => 3,
+        _ => anyhow::bail!("invalid register '{reg}', expected eax, ebx, ecx, or edx"),
+    };
+    let (value, mask) = match value.split_once('/') {
+        Some((value, mask)) => (parse_u32(value)?, parse_u32(mask)?),
+        None => (parse_u32(value)?, !0),
+    };
+    if value & !mask != 0 {
+        anyhow::bail!("value {value:#x} has bits outside of mask {mask:#x}");
+    }
+    let mut result = [0; 4];
+    let mut result_mask = [0; 4];
+    result[reg] = value;
+    result_mask[reg] = mask;
+    Ok(CpuidOverride {
+        function,
+        index,
+        result,
+        mask: result_mask,
+    })
+}
+
 #[derive(Debug, Copy, Clone, ValueEnum)]
 pub enum Vtl0LateMapPolicyCli {
     Off,
@@ -2863,7 +3097,7 @@ fn test_parse_file_opts() {
         let disk = DiskCliKind::from_str("file:test.vhd;create=1G").unwrap();
         assert!(matches!(
             &disk,
-            DiskCliKind::File { path, create_with_len: Some(len), direct: false }
+            DiskCliKind::File { path, create_with_len: Some(len), direct: false, rct: false }
                 if path == Path::new("test.vhd") && *len == 1024 * 1024 * 1024
         ));
 
@@ -2871,7 +3105,7 @@ fn test_parse_file_opts() {
         let disk = DiskCliKind::from_str("test.vhd;create=1G").unwrap();
         assert!(matches!(
             &disk,
-            DiskCliKind::File { path, create_with_len: Some(len), direct: false }
+            DiskCliKind::File { path, create_with_len: Some(len), direct: false, rct: false }
                 if path == Path::new("test.vhd") && *len == 1024 * 1024 * 1024
         ));
 
@@ -2879,7 +3113,7 @@ fn test_parse_file_opts() {
         let disk = DiskCliKind::from_str("file:/dev/sdb;direct").unwrap();
         assert!(matches!(
             &disk,
-            DiskCliKind::File { path, create_with_len: None, direct: true }
+            DiskCliKind::File { path, create_with_len: None, direct: true, rct: false }
                 if path == Path::new("/dev/sdb")
         ));
 
@@ -2887,14 +3121,14 @@ fn test_parse_file_opts() {
         let disk = DiskCliKind::from_str("file:disk.img;direct;create=1G").unwrap();
         assert!(matches!(
             &disk,
-            DiskCliKind::File { path, create_with_len: Some(len), direct: true }
+            DiskCliKind::File { path, create_with_len: Some(len), direct: true, rct: false }
                 if path == Path::new("disk.img") && *len == 1024 * 1024 * 1024
         ));
 
         let disk = DiskCliKind::from_str("file:disk.img;create=1G;direct").unwrap();
         assert!(matches!(
             &disk,
-            DiskCliKind::File { path, create_with_len: Some(len), direct: true }
+            DiskCliKind::File { path, create_with_len: Some(len), direct: true, rct: false }
                 if path == Path::new("disk.img") && *len == 1024 * 1024 * 1024
         ));
 
@@ -2902,7 +3136,7 @@ fn test_parse_file_opts() {
         let disk = DiskCliKind::from_str("file:disk.img").unwrap();
         assert!(matches!(
             &disk,
-            DiskCliKind::File { path, create_with_len: None, direct: false }
+            DiskCliKind::File { path, create_with_len: None, direct: false, rct: false }
                 if path == Path::new("disk.img")
         ));
 
@@ -2911,6 +3145,15 @@ fn test_parse_file_opts() {
 
         // direct rejected for sql disks
         assert!(DiskCliKind::from_str("sql:db.sqlite;direct").is_err());
+
+        // resilient change tracking
+        let disk = DiskCliKind::from_str("file:disk.vhdx;rct").unwrap();
+        assert!(matches!(
+            &disk,
+            DiskCliKind::File { path, create_with_len: None, direct: false, rct: true }
+                if path == Path::new("disk.vhdx")
+        ));
+        assert!(DiskCliKind::from_str("sql:db.sqlite;rct").is_err());
     }
 
     #[test]
@@ -2973,6 +3216,21 @@ fn test_parse_memory_diff_disk() {
         }
     }
 
+    #[test]
+    fn test_parse_change_tracking_disk() {
+        let disk = DiskCliKind::from_str("track:os:memdiff:file:base.img").unwrap();
+        match disk {
+            DiskCliKind::ChangeTracking { name, disk } => {
+                assert_eq!(name, "os");
+                assert!(matches!(*disk, DiskCliKind::MemoryDiff(_)));
+            }
+            _ => panic!("Expected ChangeTracking variant"),
+        }
+
+        assert!(DiskCliKind::from_str("track:os").is_err());
+        assert!(DiskCliKind::from_str("track::mem:1G").is_err());
+    }
+
     #[test]
     fn test_parse_sqlite_disk() {
         let s = "sql:db.sqlite;create=2G";
@@ -3241,6 +3499,7 @@ fn test_endpoint_config_from_str() {
             EndpointConfigCli::Consomme {
                 cidr: None,
                 host_fwd,
+                ..
             } => assert!(host_fwd.is_empty()),
             _ => panic!("Expected Consomme variant without cidr"),
         }
@@ -3250,6 +3509,7 @@ fn test_endpoint_config_from_str() {
             EndpointConfigCli::Consomme {
                 cidr: Some(cidr),
                 host_fwd,
+                ..
             } => {
                 assert_eq!(cidr, "192.168.0.0/24");
                 assert!(host_fwd.is_empty());
@@ -3259,7 +3519,7 @@ fn test_endpoint_config_from_str() {
 
         // Test consomme with hostfwd
         match EndpointConfigCli::from_str("consomme:hostfwd=udp:127.0.0.1:5000-:5000").unwrap() {
-            EndpointConfigCli::Consomme { cidr, host_fwd } => {
+            EndpointConfigCli::Consomme { cidr, host_fwd, .. } => {
                 assert!(cidr.is_none());
                 assert_eq!(host_fwd.len(), 1);
                 assert_eq!(host_fwd[0].protocol, HostPortProtocolCli::Udp);
@@ -3275,7 +3535,7 @@ fn test_endpoint_config_from_str() {
 
         // Test consomme with cidr and hostfwd
         match EndpointConfigCli::from_str("consomme:10.0.0.0/24,hostfwd=tcp::2222-:22").unwrap() {
-            EndpointConfigCli::Consomme { cidr, host_fwd } => {
+            EndpointConfigCli::Consomme { cidr, host_fwd, .. } => {
                 assert_eq!(cidr.as_deref(), Some("10.0.0.0/24"));
                 assert_eq!(host_fwd.len(), 1);
                 assert_eq!(host_fwd[0].protocol, HostPortProtocolCli::Tcp);
@@ -3289,7 +3549,7 @@ fn test_endpoint_config_from_str() {
         match EndpointConfigCli::from_str("consomme:hostfwd=tcp::2222-:22,hostfwd=tcp::3389-:3389")
             .unwrap()
         {
-            EndpointConfigCli::Consomme { cidr, host_fwd } => {
+            EndpointConfigCli::Consomme { cidr, host_fwd, .. } => {
                 assert!(cidr.is_none());
                 assert_eq!(host_fwd.len(), 2);
                 assert_eq!(host_fwd[0].host_port, 2222);
@@ -3302,7 +3562,7 @@ fn test_endpoint_config_from_str() {
 
         // Test consomme with different host and guest ports
         match EndpointConfigCli::from_str("consomme:hostfwd=tcp:127.0.0.1:8080-:80").unwrap() {
-            EndpointConfigCli::Consomme { cidr, host_fwd } => {
+            EndpointConfigCli::Consomme { cidr, host_fwd, .. } => {
                 assert!(cidr.is_none());
                 assert_eq!(host_fwd.len(), 1);
                 assert_eq!(host_fwd[0].protocol, HostPortProtocolCli::Tcp);
@@ -3318,7 +3578,7 @@ fn test_endpoint_config_from_str() {
 
         // Test consomme with guest address (accepted but ignored by backend)
         match EndpointConfigCli::from_str("consomme:hostfwd=tcp::8080-10.0.0.2:80").unwrap() {
-            EndpointConfigCli::Consomme { cidr, host_fwd } => {
+            EndpointConfigCli::Consomme { cidr, host_fwd, .. } => {
                 assert!(cidr.is_none());
                 assert_eq!(host_fwd[0].host_port, 8080);
                 assert_eq!(host_fwd[0].guest_port, 80);
@@ -3328,7 +3588,7 @@ fn test_endpoint_config_from_str() {
 
         // Test consomme with IPv6 host address (bracketed)
         match EndpointConfigCli::from_str("consomme:hostfwd=tcp:[::1]:8080-:80").unwrap() {
-            EndpointConfigCli::Consomme { cidr, host_fwd } => {
+            EndpointConfigCli::Consomme { cidr, host_fwd, .. } => {
                 assert!(cidr.is_none());
                 assert_eq!(host_fwd.len(), 1);
                 assert_eq!(host_fwd[0].protocol, HostPortProtocolCli::Tcp);
@@ -3344,7 +3604,7 @@ fn test_endpoint_config_from_str() {
 
         // Test consomme with IPv6 guest address (bracketed)
         match EndpointConfigCli::from_str("consomme:hostfwd=tcp::8080-[::1]:80").unwrap() {
-            EndpointConfigCli::Consomme { cidr, host_fwd } => {
+            EndpointConfigCli::Consomme { cidr, host_fwd, .. } => {
                 assert!(cidr.is_none());
                 assert_eq!(host_fwd[0].host_port, 8080);
                 assert_eq!(host_fwd[0].guest_port, 80);
@@ -3352,6 +3612,59 @@ fn test_endpoint_config_from_str() {
             _ => panic!("Expected Consomme variant with IPv6 guest address"),
         }
 
+        // Test consomme with DNS forwarders
+        match EndpointConfigCli::from_str("consomme:dns=1.1.1.1,dns=2001:4860:4860::8888").unwrap()
+        {
+            EndpointConfigCli::Consomme {
+                cidr,
+                guest_ip,
+                host_fwd,
+                dns,
+            } => {
+                assert!(cidr.is_none());
+                assert!(guest_ip.is_none());
+                assert!(host_fwd.is_empty());
+                assert_eq!(
+                    dns,
+                    [
+                        std::net::IpAddr::V4(std::net::Ipv4Addr::new(1, 1, 1, 1)),
+                        std::net::IpAddr::V6(std::net::Ipv6Addr::new(
+                            0x2001, 0x4860, 0x4860, 0, 0, 0, 0, 0x8888
+                        )),
+                    ]
+                );
+            }
+            _ => panic!("Expected Consomme variant with DNS forwarders"),
+        }
+        assert!(EndpointConfigCli::from_str("consomme:dns=not-an-ip").is_err());
+
+        // Test consomme with a guest address reservation
+        match EndpointConfigCli::from_str("consomme:10.0.0.0/24,guest_ip=10.0.0.100").unwrap() {
+            EndpointConfigCli::Consomme { cidr, guest_ip, .. } => {
+                assert_eq!(cidr.as_deref(), Some("10.0.0.0/24"));
+                assert_eq!(guest_ip, Some(std::net::Ipv4Addr::new(10, 0, 0, 100)));
+            }
+            _ => panic!("Expected Consomme variant with guest address"),
+        }
+        assert!(EndpointConfigCli::from_str("consomme:guest_ip=::1").is_err());
+
+        // Test consomme with network boot
+        match EndpointConfigCli::from_str("consomme:tftp=C:\\tftp,bootfile=efi/bootx64.efi")
+            .unwrap()
+        {
+            EndpointConfigCli::Consomme {
+                cidr,
+                tftp_root,
+                boot_file,
+                ..
+            } => {
+                assert_eq!(cidr, None);
+                assert_eq!(tftp_root.as_deref(), Some("C:\\tftp"));
+                assert_eq!(boot_file.as_deref(), Some("efi/bootx64.efi"));
+            }
+            _ => panic!("Expected Consomme variant with network boot"),
+        }
+
         // Test dio without id
         match EndpointConfigCli::from_str("dio").unwrap() {
             EndpointConfigCli::Dio { id: None } => (),
@@ -3374,6 +3687,23 @@ fn test_endpoint_config_from_str() {
             _ => panic!("Expected Tap variant"),
         }
 
+        // Test af_xdp
+        assert_eq!(
+            EndpointConfigCli::from_str("af_xdp:eth1").unwrap(),
+            EndpointConfigCli::AfXdp {
+                interface: "eth1".into(),
+                require_zero_copy: false,
+            }
+        );
+        assert_eq!(
+            EndpointConfigCli::from_str("af_xdp:eth1:zerocopy").unwrap(),
+            EndpointConfigCli::AfXdp {
+                interface: "eth1".into(),
+                require_zero_copy: true,
+            }
+        );
+        assert!(EndpointConfigCli::from_str("af_xdp:eth1:bogus").is_err());
+
         // Test error case
         assert!(EndpointConfigCli::from_str("invalid").is_err());
     }
@@ -3413,6 +3743,13 @@ fn test_nic_config_from_str() {
         assert_eq!(config.pcie_port.unwrap(), "rp0".to_string());
         assert!(matches!(config.endpoint, EndpointConfigCli::None));
 
+        // Test with busy polling
+        let config = NicConfigCli::from_str("busy_poll:queues=2:none").unwrap();
+        assert!(config.busy_poll);
+        assert_eq!(config.max_queues, Some(2));
+        assert!(matches!(config.endpoint, EndpointConfigCli::None));
+        assert!(!NicConfigCli::from_str("none").unwrap().busy_poll);
+
         // Test error cases
         assert!(NicConfigCli::from_str("queues=invalid:none").is_err());
         assert!(NicConfigCli::from_str("uh:vtl2:none").is_err()); // uh incompatible with vtl2
@@ -3529,6 +3866,26 @@ fn test_smt_config_from_str() {
         assert!(SmtConfigCli::from_str("").is_err());
     }
 
+    #[test]
+    fn test_parse_cpuid_override() {
+        let o = parse_cpuid_override("7/0:ebx=0/0x20").unwrap();
+        assert_eq!(o.function, 7);
+        assert_eq!(o.index, Some(0));
+        assert_eq!(o.result, [0; 4]);
+        assert_eq!(o.mask, [0, 0x20, 0, 0]);
+
+        let o = parse_cpuid_override("0x80000001:ecx=0x12345678").unwrap();
+        assert_eq!(o.function, 0x80000001);
+        assert_eq!(o.index, None);
+        assert_eq!(o.result, [0, 0, 0x12345678, 0]);
+        assert_eq!(o.mask, [0, 0, !0, 0]);
+
+        assert!(parse_cpuid_override("7").is_err());
+        assert!(parse_cpuid_override("7:esi=0").is_err());
+        assert!(parse_cpuid_override("7:eax=0x100000000").is_err());
+        assert!(parse_cpuid_override("7:eax=3/1").is_err());
+    }
+
     #[test]
     fn test_pcat_boot_order_from_str() {
         // Test single device
@@ -3984,6 +4341,7 @@ fn test_memory_config_size_only() {
                 transparent_hugepages: false,
                 hugepages: false,
                 hugepage_size: None,
+                hugepage_fallback: false,
                 file: None,
             }
         );
@@ -4000,6 +4358,7 @@ fn test_memory_config_key_value() {
                 transparent_hugepages: true,
                 hugepages: false,
                 hugepage_size: None,
+                hugepage_fallback: false,
                 file: None,
             }
         );
@@ -4013,6 +4372,21 @@ fn test_memory_config_key_value() {
                 transparent_hugepages: false,
                 hugepages: true,
                 hugepage_size: Some(2 * 1024 * 1024),
+                hugepage_fallback: false,
+                file: None,
+            }
+        );
+
+        assert_eq!(
+            parse_memory_config("size=4GB,hugepages=fallback,hugepage_size=1GB").unwrap(),
+            MemoryCli {
+                mem_size: 4 * 1024 * 1024 * 1024,
+                shared: None,
+                prefetch: false,
+                transparent_hugepages: false,
+                hugepages: true,
+                hugepage_size: Some(1024 * 1024 * 1024),
+                hugepage_fallback: true,
                 file: None,
             }
         );
@@ -4026,6 +4400,7 @@ fn test_memory_config_key_value() {
                 transparent_hugepages: false,
                 hugepages: false,
                 hugepage_size: None,
+                hugepage_fallback: false,
                 file: Some(PathBuf::from("/tmp/memory.bin")),
             }
         );
@@ -4037,6 +4412,7 @@ fn test_memory_config_rejects_invalid_combinations() {
         assert!(parse_memory_config("size=1G,size=2G").is_err());
         assert!(parse_memory_config("hugepage_size=2M").is_err());
         assert!(parse_memory_config("hugepages=on,shared=off").is_err());
+        assert!(parse_memory_config("hugepages=fallback,shared=off").is_err());
         assert!(parse_memory_config("hugepages=on,file=/tmp/memory.bin").is_err());
 
         // Semantic validation of the hugepage size happens in the memory
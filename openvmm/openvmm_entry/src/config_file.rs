@@ -0,0 +1,149 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Support for reading command line options from a TOML file.
+//!
+//! Each top-level key in the file is the long name of a command line option,
+//! and its value is the option's argument:
+//!
+//! ```toml
+//! processors = 4
+//! memory = "4GB"
+//! uefi = true
+//! disk = ["file:os.vhdx", "mem:1G"]
+//! ```
+//!
+//! `true` passes a flag and `false` omits it. An array passes the option once
+//! per element.
+
+use anyhow::Context;
+use std::ffi::OsString;
+use std::path::Path;
+use toml_edit::Item;
+use toml_edit::Value;
+
+/// Reads the options in the TOML file at `path` and returns them as command
+/// line arguments.
+///
+/// Each option is checked against `command`, and errors report the line in
+/// the file where the problem is.
+pub fn read_args(path: &Path, command: &clap::Command) -> anyhow::Result<Vec<OsString>> {
+    let contents = fs_err::read_to_string(path)?;
+    parse_args(&contents, command)
+        .with_context(|| format!("invalid config file {}", path.display()))
+}
+
+fn parse_args(contents: &str, command: &clap::Command) -> anyhow::Result<Vec<OsString>> {
+    let doc = toml_edit::Document::parse(contents)?;
+    let line = |span: Option<std::ops::Range<usize>>| {
+        span.map_or(0, |span| contents[..span.start].matches('\n').count() + 1)
+    };
+
+    let mut args = Vec::new();
+    for (key, item) in doc.as_table().iter() {
+        let key_line = line(doc.as_table().key(key).and_then(|k| k.span()));
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(key))
+            .filter(|arg| arg.get_id().as_str() != "config")
+            .with_context(|| format!("line {key_line}: unknown option `{key}`"))?;
+        let takes_value = arg.get_action().takes_values();
+
+        let values: Vec<&Value> = match item {
+            Item::Value(Value::Array(array)) => array.iter().collect(),
+            Item::Value(value) => vec![value],
+            _ => anyhow::bail!("line {key_line}: `{key}` must be a value"),
+        };
+        for value in values {
+            let value_line = line(value.span());
+            let flag = OsString::from(format!("--{key}"));
+            match value {
+                Value::Boolean(b) if !takes_value => {
+                    if *b.value() {
+                        args.push(flag);
+                    }
+                }
+                Value::String(s) if takes_value => args.extend([flag, s.value().into()]),
+                Value::Integer(i) if takes_value => {
+                    args.extend([flag, i.value().to_string().into()])
+                }
+                _ if takes_value => {
+                    anyhow::bail!("line {value_line}: `{key}` must be a string or integer")
+                }
+                _ => anyhow::bail!("line {value_line}: `{key}` must be true or false"),
+            }
+        }
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_args;
+    use crate::cli_args::Options;
+    use clap::CommandFactory;
+    use clap::Parser;
+
+    #[test]
+    fn test_parse_args() {
+        let command = Options::command();
+        let args = parse_args(
+            r#"
+processors = 4
+memory = "4GB"
+uefi = true
+pcat = false
+disk = ["mem:1G", "mem:2G"]
+"#,
+            &command,
+        )
+        .unwrap();
+        assert_eq!(
+            args,
+            [
+                "--processors",
+                "4",
+                "--memory",
+                "4GB",
+                "--uefi",
+                "--disk",
+                "mem:1G",
+                "--disk",
+                "mem:2G"
+            ]
+        );
+
+        let err = parse_args("uefi = true\nbogus = 1\n", &command).unwrap_err();
+        assert_eq!(err.to_string(), "line 2: unknown option `bogus`");
+
+        let err = parse_args("\n\nprocessors = true\n", &command).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 3: `processors` must be a string or integer"
+        );
+    }
+
+    #[test]
+    fn test_command_line_precedence() {
+        let file_args = parse_args(
+            r#"
+processors = 4
+uefi = true
+disk = ["mem:1G"]
+"#,
+            &Options::command(),
+        )
+        .unwrap();
+        // Insert the file's options the way `parse_options` does.
+        let mut args: Vec<std::ffi::OsString> =
+            ["openvmm", "-p", "2", "--uefi", "--disk", "mem:2G"]
+                .into_iter()
+                .map(Into::into)
+                .collect();
+        args.splice(1..1, file_args);
+        let opt = Options::try_parse_from(args).unwrap();
+        assert_eq!(opt.processors, 2);
+        assert!(opt.uefi);
+        assert_eq!(opt.disk.len(), 2);
+    }
+}
@@ -7,7 +7,9 @@
 #![expect(missing_docs)]
 #![forbid(unsafe_code)]
 
+mod change_tracking;
 mod cli_args;
+mod config_file;
 mod crash_dump;
 mod kvp;
 mod meshworker;
@@ -27,6 +29,7 @@
 use anyhow::Context;
 use anyhow::bail;
 use chipset_resources::battery::HostBatteryUpdate;
+use clap::CommandFactory;
 use clap::Parser;
 use cli_args::DiskCliKind;
 use cli_args::EfiDiagnosticsLogLevelCli;
@@ -64,6 +67,7 @@
 use mesh::rpc::RpcSend;
 use meshworker::VmmMesh;
 use net_backend_resources::mac_address::MacAddress;
+use nvme_resources::NvmeBootPartitions;
 use nvme_resources::NvmeControllerRequest;
 use openvmm_defs::config::Config;
 use openvmm_defs::config::DEFAULT_PCAT_BOOT_ORDER;
@@ -162,7 +166,7 @@ pub fn openvmm_main() {
 
     let mut pidfile_guard = PidfileGuard(None);
     let exit_code = match do_main(&mut pidfile_guard.0) {
-        Ok(_) => 0,
+        Ok(exit_code) => exit_code,
         Err(err) => {
             eprintln!("fatal error: {:?}", err);
             1
@@ -194,10 +198,13 @@ struct VmResources {
     framebuffer_access: Option<FramebufferAccess>,
     shutdown_ic: Option<mesh::Sender<hyperv_ic_resources::shutdown::ShutdownRpc>>,
     kvp_ic: Option<mesh::Sender<hyperv_ic_resources::kvp::KvpConnectRpc>>,
+    balloon: Option<mesh::Sender<virtio_resources::balloon::BalloonRpc>>,
+    dynamic_memory: Option<mesh::Sender<hyperv_dm_resources::DynamicMemoryRpc>>,
     scsi_rpc: Option<mesh::Sender<ScsiControllerRequest>>,
     nvme_vtl2_rpc: Option<mesh::Sender<NvmeControllerRequest>>,
     ged_rpc: Option<mesh::Sender<get_resources::ged::GuestEmulationRequest>>,
     vtl2_settings: Option<vtl2_settings_proto::Vtl2Settings>,
+    change_trackers: change_tracking::ChangeTrackers,
     #[cfg(windows)]
     switch_ports: Vec<vmswitch::kernel::SwitchPort>,
 }
@@ -450,7 +457,12 @@ async fn vm_config_from_command_line(
 
     let with_get = opt.get || (opt.vtl2 && !opt.no_get);
 
-    let mut storage = storage_builder::StorageBuilder::new(with_get.then_some(openhcl_vtl));
+    let change_trackers = match &opt.restore_snapshot {
+        Some(dir) => change_tracking::ChangeTrackers::from_snapshot(dir)?,
+        None => Default::default(),
+    };
+    let mut storage =
+        storage_builder::StorageBuilder::new(with_get.then_some(openhcl_vtl), change_trackers);
     for &cli_args::DiskCli {
         vtl,
         ref kind,
@@ -517,6 +529,14 @@ async fn vm_config_from_command_line(
             .await?;
     }
 
+    if let Some(path) = &opt.nvme_boot_partition {
+        let partition = fs_err::read(path).context("failed to read nvme boot partition")?;
+        storage.set_vtl0_nvme_boot_partitions(NvmeBootPartitions {
+            partitions: [partition, Vec::new()],
+            active: 0,
+        });
+    }
+
     for &cli_args::DiskCli {
         vtl,
         ref kind,
@@ -548,7 +568,7 @@ async fn vm_config_from_command_line(
             read_only,
         } = disk;
         floppy_disks.push(FloppyDiskConfig {
-            disk_type: disk_open(kind, read_only).await?,
+            disk_type: disk_open(kind, read_only, storage.change_trackers()).await?,
             read_only,
         });
     }
@@ -563,6 +583,9 @@ async fn vm_config_from_command_line(
         if cli_cfg.pcie_port.is_some() {
             anyhow::bail!("`--net` does not support PCIe");
         }
+        if cli_cfg.busy_poll {
+            anyhow::bail!("`--net` does not support busy polling");
+        }
         let vport = parse_endpoint(cli_cfg, &mut nic_index, &mut resources)?;
         if cli_cfg.underhill {
             if !opt.no_alias_map {
@@ -592,11 +615,16 @@ async fn vm_config_from_command_line(
                 vtl: DeviceVtl::Vtl0,
                 endpoint: EndpointConfigCli::Consomme {
                     cidr: None,
+                    guest_ip: None,
                     host_fwd: Vec::new(),
+                    dns: Vec::new(),
+                    tftp_root: None,
+                    boot_file: None,
                 },
                 max_queues: None,
                 underhill: false,
                 pcie_port: None,
+                busy_poll: false,
             },
             &mut nic_index,
             &mut resources,
@@ -1178,7 +1206,7 @@ async fn vm_config_from_command_line(
 
     let mut vmgs = Some(if let Some(VmgsCli { kind, provision }) = &opt.vmgs {
         let disk = VmgsDisk {
-            disk: disk_open(kind, false)
+            disk: disk_open(kind, false, storage.change_trackers())
                 .await
                 .context("failed to open vmgs disk")?,
             encryption_policy: if opt.test_gsp_by_id {
@@ -1286,6 +1314,7 @@ async fn vm_config_from_command_line(
                     enable_battery: opt.battery,
                     no_persistent_secrets: true,
                     igvm_attest_test_config: None,
+                    vtl2_settings_test_config: None,
                     test_gsp_by_id: opt.test_gsp_by_id,
                     efi_diagnostics_log_level: {
                         match opt.efi_diagnostics_log_level.unwrap_or_default() {
@@ -1433,6 +1462,10 @@ async fn vm_config_from_command_line(
             apic_id_offset: opt.apic_id_offset,
             x2apic: opt.x2apic,
         });
+    #[cfg(guest_arch = "x86_64")]
+    let cpuid_overrides = opt.cpuid.clone();
+    #[cfg(not(guest_arch = "x86_64"))]
+    let cpuid_overrides = Vec::new();
 
     let with_isolation = if let Some(isolation) = &opt.isolation {
         // TODO: For now, isolation is only supported with VTL2.
@@ -1470,6 +1503,15 @@ async fn vm_config_from_command_line(
         );
     }
 
+    if opt.dynamic_memory {
+        let (dm_send, dm_recv) = mesh::channel();
+        resources.dynamic_memory = Some(dm_send);
+        vmbus_devices.push((
+            DeviceVtl::Vtl0,
+            hyperv_dm_resources::DynamicMemoryHandle { recv: dm_recv }.into_resource(),
+        ));
+    }
+
     if let Some(hive_path) = &opt.imc {
         let file = fs_err::File::open(hive_path).context("failed to open imc hive")?;
         vmbus_devices.push((
@@ -1514,6 +1556,7 @@ async fn vm_config_from_command_line(
             max_queues: vport.max_queues,
             mac_address: vport.mac_address,
             endpoint: vport.endpoint,
+            busy_poll: cli_cfg.busy_poll,
         }
         .into_resource();
         if let Some(pcie_port) = &cli_cfg.pcie_port {
@@ -1608,6 +1651,19 @@ async fn vm_config_from_command_line(
         }
     }
 
+    if opt.virtio_balloon {
+        let (balloon_send, balloon_recv) = mesh::channel();
+        resources.balloon = Some(balloon_send);
+        add_virtio_device(
+            opt.virtio_balloon_bus,
+            virtio_resources::balloon::VirtioBalloonHandle {
+                target_pages: 0,
+                recv: balloon_recv,
+            }
+            .into_resource(),
+        );
+    }
+
     if let Some(backend) = virtio_console_backend {
         let resource: Resource<VirtioDeviceHandle> =
             virtio_resources::console::VirtioConsoleHandle { backend }.into_resource();
@@ -1689,6 +1745,15 @@ async fn vm_config_from_command_line(
         );
     }
 
+    let extra_acpi_tables = opt
+        .acpi_table
+        .iter()
+        .map(|path| {
+            fs_err::read(path)
+                .with_context(|| format!("failed to read acpi table {}", path.display()))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
     let mut cfg = Config {
         chipset,
         load_mode,
@@ -1719,7 +1784,9 @@ async fn vm_config_from_command_line(
             transparent_hugepages: opt.transparent_hugepages(),
             hugepages: opt.memory.hugepages,
             hugepage_size: opt.memory.hugepage_size,
+            hugepage_fallback: opt.memory.hugepage_fallback,
             numa_mem_sizes: opt.numa_memory.clone(),
+            hot_add_size: opt.hot_add_size,
         },
         processor_topology: ProcessorTopologyConfig {
             proc_count: opt.processors,
@@ -1745,6 +1812,8 @@ async fn vm_config_from_command_line(
                 },
             }),
             with_isolation,
+            cpuid_overrides,
+            halt_on_guest_crash: opt.halt_on_guest_crash,
         },
         #[cfg(windows)]
         kernel_vmnics,
@@ -1781,6 +1850,7 @@ async fn vm_config_from_command_line(
         debugger_rpc: None,
         rtc_delta_milliseconds: 0,
         automatic_guest_reset: !opt.halt_on_reset,
+        extra_acpi_tables,
         efi_diagnostics_log_level: {
             match opt.efi_diagnostics_log_level.unwrap_or_default() {
                 EfiDiagnosticsLogLevelCli::Default => EfiDiagnosticsLogLevelType::Default,
@@ -1788,6 +1858,7 @@ async fn vm_config_from_command_line(
                 EfiDiagnosticsLogLevelCli::Full => EfiDiagnosticsLogLevelType::Full,
             }
         },
+        track_device_memory: false,
     };
 
     storage.build_config(&mut cfg, &mut resources, opt.scsi_sub_channels)?;
@@ -1846,9 +1917,43 @@ fn parse_endpoint(
     index: &mut usize,
     resources: &mut VmResources,
 ) -> anyhow::Result<NicConfig> {
-    let _ = resources;
-    let endpoint = match &cli_cfg.endpoint {
-        EndpointConfigCli::Consomme { cidr, host_fwd } => {
+    let endpoint = endpoint_resource(&cli_cfg.endpoint, Some(resources))?;
+
+    // Pick a fixed instance ID based on the index.
+    const BASE_INSTANCE_ID: Guid = guid::guid!("00000000-da43-11ed-936a-00155d6db52f");
+    let instance_id = Guid {
+        data1: *index as u32,
+        ..BASE_INSTANCE_ID
+    };
+    *index += 1;
+
+    Ok(NicConfig {
+        vtl: cli_cfg.vtl,
+        instance_id,
+        endpoint,
+        mac_address: random_mac_address(),
+        max_queues: cli_cfg.max_queues,
+        pcie_port: cli_cfg.pcie_port.clone(),
+    })
+}
+
+/// Builds the network endpoint resource for `endpoint`.
+///
+/// `resources` holds any host resources the endpoint needs to keep alive. If
+/// it is `None`, endpoints that need such resources are rejected.
+fn endpoint_resource(
+    endpoint: &EndpointConfigCli,
+    #[cfg_attr(not(windows), expect(unused_variables))] resources: Option<&mut VmResources>,
+) -> anyhow::Result<Resource<NetEndpointHandleKind>> {
+    let endpoint = match endpoint {
+        EndpointConfigCli::Consomme {
+            cidr,
+            guest_ip,
+            host_fwd,
+            dns,
+            tftp_root,
+            boot_file,
+        } => {
             let ports = host_fwd
                 .iter()
                 .map(|fwd| {
@@ -1868,7 +1973,14 @@ fn parse_endpoint(
                 .collect();
             net_backend_resources::consomme::ConsommeHandle {
                 cidr: cidr.clone(),
+                guest_ip: *guest_ip,
                 ports,
+                nameservers: dns
+                    .iter()
+                    .map(|&addr| net_backend_resources::consomme::HostIpAddress::from(addr))
+                    .collect(),
+                tftp_root: tftp_root.clone(),
+                boot_file: boot_file.clone(),
             }
             .into_resource()
         }
@@ -1876,6 +1988,7 @@ fn parse_endpoint(
         EndpointConfigCli::Dio { id } => {
             #[cfg(windows)]
             {
+                let resources = resources.context("dio endpoints are not supported here")?;
                 let (port_id, port) = new_switch_port(id.as_deref().unwrap_or(DEFAULT_SWITCH))?;
                 resources.switch_ports.push(port);
                 net_backend_resources::dio::WindowsDirectIoHandle {
@@ -1907,28 +2020,34 @@ fn parse_endpoint(
                 bail!("TAP backend is only supported on Linux")
             }
         }
+        EndpointConfigCli::AfXdp {
+            interface,
+            require_zero_copy,
+        } => {
+            #[cfg(target_os = "linux")]
+            {
+                net_backend_resources::af_xdp::AfXdpHandle {
+                    interface: interface.clone(),
+                    require_zero_copy: *require_zero_copy,
+                }
+                .into_resource()
+            }
+
+            #[cfg(not(target_os = "linux"))]
+            {
+                let _ = (interface, require_zero_copy);
+                bail!("AF_XDP backend is only supported on Linux")
+            }
+        }
     };
+    Ok(endpoint)
+}
 
-    // Pick a random MAC address.
+/// Picks a random MAC address in the Hyper-V range.
+fn random_mac_address() -> MacAddress {
     let mut mac_address = [0x00, 0x15, 0x5D, 0, 0, 0];
     getrandom::fill(&mut mac_address[3..]).expect("rng failure");
-
-    // Pick a fixed instance ID based on the index.
-    const BASE_INSTANCE_ID: Guid = guid::guid!("00000000-da43-11ed-936a-00155d6db52f");
-    let instance_id = Guid {
-        data1: *index as u32,
-        ..BASE_INSTANCE_ID
-    };
-    *index += 1;
-
-    Ok(NicConfig {
-        vtl: cli_cfg.vtl,
-        instance_id,
-        endpoint,
-        mac_address: mac_address.into(),
-        max_queues: cli_cfg.max_queues,
-        pcie_port: cli_cfg.pcie_port.clone(),
-    })
+    mac_address.into()
 }
 
 #[derive(Debug)]
@@ -1964,9 +2083,10 @@ enum LayerOrDisk {
 async fn disk_open(
     disk_cli: &DiskCliKind,
     read_only: bool,
+    change_trackers: &mut change_tracking::ChangeTrackers,
 ) -> anyhow::Result<Resource<DiskHandleKind>> {
     let mut layers = Vec::new();
-    disk_open_inner(disk_cli, read_only, &mut layers).await?;
+    disk_open_inner(disk_cli, read_only, change_trackers, &mut layers).await?;
     if layers.len() == 1 && matches!(layers[0], LayerOrDisk::Disk(_)) {
         let LayerOrDisk::Disk(disk) = layers.pop().unwrap() else {
             unreachable!()
@@ -1992,6 +2112,7 @@ async fn disk_open(
 fn disk_open_inner<'a>(
     disk_cli: &'a DiskCliKind,
     read_only: bool,
+    change_trackers: &'a mut change_tracking::ChangeTrackers,
     layers: &'a mut Vec<LayerOrDisk>,
 ) -> futures::future::BoxFuture<'a, anyhow::Result<()>> {
     Box::pin(async move {
@@ -2012,6 +2133,7 @@ fn disk<T: IntoResource<DiskHandleKind>>(disk: T) -> LayerOrDisk {
                 path,
                 create_with_len,
                 direct,
+                rct,
             } => layers.push(LayerOrDisk::Disk(if let Some(size) = create_with_len {
                 create_disk_type(
                     path,
@@ -2019,6 +2141,7 @@ fn disk<T: IntoResource<DiskHandleKind>>(disk: T) -> LayerOrDisk {
                     OpenDiskOptions {
                         read_only: false,
                         direct: *direct,
+                        change_tracking: *rct,
                     },
                 )
                 .with_context(|| format!("failed to create {}", path.display()))?
@@ -2028,6 +2151,7 @@ fn disk<T: IntoResource<DiskHandleKind>>(disk: T) -> LayerOrDisk {
                     OpenDiskOptions {
                         read_only,
                         direct: *direct,
+                        change_tracking: *rct,
                     },
                 )
                 .await
@@ -2049,26 +2173,30 @@ fn disk<T: IntoResource<DiskHandleKind>>(disk: T) -> LayerOrDisk {
                     len: None,
                     sector_size: None,
                 }));
-                disk_open_inner(inner, true, layers).await?;
+                disk_open_inner(inner, true, change_trackers, layers).await?;
             }
             DiskCliKind::PersistentReservationsWrapper(inner) => {
                 layers.push(disk(disk_backend_resources::DiskWithReservationsHandle(
-                    disk_open(inner, read_only).await?,
+                    disk_open(inner, read_only, change_trackers).await?,
                 )))
             }
+            DiskCliKind::ChangeTracking { name, disk: inner } => {
+                let inner = disk_open(inner, read_only, change_trackers).await?;
+                layers.push(LayerOrDisk::Disk(change_trackers.add(name, inner)?));
+            }
             DiskCliKind::DelayDiskWrapper {
                 delay_ms,
                 disk: inner,
             } => layers.push(disk(DelayDiskHandle {
                 delay: CellUpdater::new(Duration::from_millis(*delay_ms)).cell(),
-                disk: disk_open(inner, read_only).await?,
+                disk: disk_open(inner, read_only, change_trackers).await?,
             })),
             DiskCliKind::Crypt {
                 disk: inner,
                 cipher,
                 key_file,
             } => layers.push(disk(disk_crypt_resources::DiskCryptHandle {
-                disk: disk_open(inner, read_only).await?,
+                disk: disk_open(inner, read_only, change_trackers).await?,
                 cipher: match cipher {
                     cli_args::DiskCipher::XtsAes256 => disk_crypt_resources::Cipher::XtsAes256,
                 },
@@ -2130,7 +2258,7 @@ fn disk<T: IntoResource<DiskHandleKind>>(disk: T) -> LayerOrDisk {
                         },
                     ),
                 }));
-                disk_open_inner(disk, true, layers).await?;
+                disk_open_inner(disk, true, change_trackers, layers).await?;
             }
             DiskCliKind::AutoCacheSqlite {
                 cache_path,
@@ -2146,7 +2274,7 @@ fn disk<T: IntoResource<DiskHandleKind>>(disk: T) -> LayerOrDisk {
                     }
                     .into_resource(),
                 }));
-                disk_open_inner(disk, read_only, layers).await?;
+                disk_open_inner(disk, read_only, change_trackers, layers).await?;
             }
         }
         Ok(())
@@ -2212,7 +2340,23 @@ fn prepare_snapshot_restore(
     Ok((shared_memory_fd, state_msg))
 }
 
-fn do_main(pidfile_path: &mut Option<PathBuf>) -> anyhow::Result<()> {
+/// Parses the command line, including any options from a `--config` file.
+fn parse_options() -> anyhow::Result<Options> {
+    let opt = Options::parse();
+    let Some(path) = &opt.config else {
+        return Ok(opt);
+    };
+
+    // Insert the file's options ahead of the command line's so that the
+    // command line takes precedence.
+    let mut args: Vec<_> = std::env::args_os().collect();
+    let file_args = config_file::read_args(path, &Options::command())?;
+    args.splice(1..1, file_args);
+    Ok(Options::parse_from(args))
+}
+
+/// Runs openvmm, returning the process exit code.
+fn do_main(pidfile_path: &mut Option<PathBuf>) -> anyhow::Result<i32> {
     #[cfg(windows)]
     pal::windows::disable_hard_error_dialog();
 
@@ -2223,12 +2367,12 @@ fn do_main(pidfile_path: &mut Option<PathBuf>) -> anyhow::Result<()> {
     // not return). Any worker host setup errors are return and bubbled up.
     meshworker::run_vmm_mesh_host()?;
 
-    let opt = Options::parse();
+    let opt = parse_options()?;
     if let Some(path) = &opt.write_saved_state_proto {
         mesh::payload::protofile::DescriptorWriter::new(vmcore::save_restore::saved_state_roots())
             .write_to_path(path)
             .context("failed to write protobuf descriptors")?;
-        return Ok(());
+        return Ok(0);
     }
 
     if let Some(ref path) = opt.pidfile {
@@ -2239,7 +2383,8 @@ fn do_main(pidfile_path: &mut Option<PathBuf>) -> anyhow::Result<()> {
 
     if let Some(path) = opt.relay_console_path {
         let console_title = opt.relay_console_title.unwrap_or_default();
-        return console_relay::relay_console(&path, console_title.as_str());
+        console_relay::relay_console(&path, console_title.as_str())?;
+        return Ok(0);
     }
 
     #[cfg(any(feature = "grpc", feature = "ttrpc"))]
@@ -2258,8 +2403,9 @@ fn do_main(pidfile_path: &mut Option<PathBuf>) -> anyhow::Result<()> {
             // This is a local launch
             let mut handle =
                 mesh_worker::launch_local_worker::<ttrpc::TtrpcWorker>(ttrpc::Parameters {
-                    listener,
+                    listener: ttrpc::RpcListener::Unix(listener),
                     transport,
+                    tls: None,
                 })
                 .await?;
 
@@ -2270,7 +2416,42 @@ fn do_main(pidfile_path: &mut Option<PathBuf>) -> anyhow::Result<()> {
 
             handle.join().await?;
 
-            Ok(())
+            Ok(0)
+        });
+    }
+
+    #[cfg(not(feature = "grpc_tls"))]
+    if opt.grpc_tcp.is_some() {
+        anyhow::bail!("--grpc-tcp requires openvmm to be built with the grpc_tls feature");
+    }
+
+    #[cfg(feature = "grpc_tls")]
+    if let Some(addr) = opt.grpc_tcp {
+        return block_on(async {
+            let tls = ttrpc::TlsIdentity {
+                cert_pem: fs_err::read(opt.grpc_tls_cert.as_ref().unwrap())?,
+                key_pem: fs_err::read(opt.grpc_tls_key.as_ref().unwrap())?,
+            };
+            let listener =
+                std::net::TcpListener::bind(addr).context("failed to bind to address")?;
+            let addr = listener.local_addr()?;
+
+            let mut handle =
+                mesh_worker::launch_local_worker::<ttrpc::TtrpcWorker>(ttrpc::Parameters {
+                    listener: ttrpc::RpcListener::Tcp(listener),
+                    transport: ttrpc::RpcTransport::Grpc,
+                    tls: Some(tls),
+                })
+                .await?;
+
+            tracing::info!(%addr, "listening for grpc over tls");
+
+            // Signal the the parent process that the server is ready.
+            pal::close_stdout().context("failed to close stdout")?;
+
+            handle.join().await?;
+
+            Ok(0)
         });
     }
 
@@ -2286,7 +2467,7 @@ fn new_hvsock_service_id(port: u32) -> Guid {
     }
 }
 
-async fn run_control(driver: &DefaultDriver, opt: Options) -> anyhow::Result<()> {
+async fn run_control(driver: &DefaultDriver, opt: Options) -> anyhow::Result<i32> {
     let mut mesh = Some(VmmMesh::new(&driver, opt.single_process)?);
     let result = run_control_inner(driver, &mut mesh, opt).await;
     // If setup failed before the mesh was handed to the controller, shut it
@@ -2301,7 +2482,7 @@ async fn run_control_inner(
     driver: &DefaultDriver,
     mesh_slot: &mut Option<VmmMesh>,
     opt: Options,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<i32> {
     let mesh = mesh_slot.as_ref().unwrap();
     let (mut vm_config, mut resources) = vm_config_from_command_line(driver, mesh, &opt).await?;
 
@@ -2447,6 +2628,7 @@ async fn run_control_inner(
         diag_inspector: Some(diag_inspector),
         vtl2_settings: resources.vtl2_settings,
         ged_rpc: resources.ged_rpc.clone(),
+        change_trackers: resources.change_trackers,
         vm_rpc: vm_rpc.clone(),
         paravisor_diag: Some(paravisor_diag),
         igvm_path: opt.igvm.clone(),
@@ -2473,6 +2655,8 @@ async fn run_control_inner(
             nvme_vtl2_rpc: resources.nvme_vtl2_rpc,
             shutdown_ic: resources.shutdown_ic,
             kvp_ic: resources.kvp_ic,
+            balloon: resources.balloon,
+            dynamic_memory: resources.dynamic_memory,
             console_in: resources.console_in,
             has_vtl2,
         },
@@ -2483,7 +2667,7 @@ async fn run_control_inner(
     // shuts down the mesh).
     controller_task.await;
 
-    repl_result
+    Ok(repl_result?.exit_code())
 }
 
 struct DiagDialer {
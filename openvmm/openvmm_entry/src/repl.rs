@@ -8,9 +8,11 @@
 //! directly. Commands that need exclusive resources (worker handles,
 //! DiagInspector, vtl2_settings) are dispatched via `Sender<VmControllerRpc>`.
 
+use crate::cli_args::NicConfigCli;
 use crate::kvp;
 use crate::storage_builder;
 use crate::vm_controller::AddVtl0ScsiDiskParams;
+use crate::vm_controller::ChangedBlocksParams;
 use crate::vm_controller::InspectTarget;
 use crate::vm_controller::RemoveVtl0ScsiDiskByNvmeNsidParams;
 use crate::vm_controller::RemoveVtl0ScsiDiskParams;
@@ -29,13 +31,17 @@
 use futures::StreamExt;
 use futures::executor::block_on;
 use futures_concurrency::stream::Merge;
+use get_resources::ged::ServicingFault;
+use guid::Guid;
 use inspect::InspectionBuilder;
 use mesh::CancelContext;
 use mesh::error::RemoteError;
 use mesh::rpc::Rpc;
 use mesh::rpc::RpcError;
 use mesh::rpc::RpcSend;
+use netvsp_resources::NetvspHandle;
 use nvme_resources::NamespaceDefinition;
+use nvme_resources::NvmeControllerHandle;
 use nvme_resources::NvmeControllerRequest;
 use openvmm_defs::config::DeviceVtl;
 use openvmm_defs::rpc::PulseSaveRestoreError;
@@ -57,12 +63,48 @@
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
+use std::time::Instant;
 use storvsp_resources::ScsiControllerRequest;
 use storvsp_resources::ScsiDeviceAndPath;
 use storvsp_resources::ScsiPath;
 use tracing_helpers::AnyhowValueExt;
 use vm_resource::IntoResource;
 use vm_resource::Resource;
+use vm_resource::kind::DiskHandleKind;
+
+/// Opens the backing disk for a hot-added device, from either a file or a new
+/// RAM disk of `ram` bytes.
+async fn open_hot_add_disk(
+    ram: Option<u64>,
+    file_path: Option<PathBuf>,
+    read_only: bool,
+) -> anyhow::Result<Resource<DiskHandleKind>> {
+    let disk = match (ram, file_path) {
+        (None, Some(path)) => openvmm_helpers::disk::open_disk_type(
+            path.as_ref(),
+            openvmm_helpers::disk::OpenDiskOptions {
+                read_only,
+                direct: false,
+                change_tracking: false,
+            },
+        )
+        .await
+        .with_context(|| format!("failed to open {}", path.display()))?,
+        (Some(size), None) => Resource::new(
+            disk_backend_resources::LayeredDiskHandle::single_layer(RamDiskLayerHandle {
+                len: Some(size),
+                sector_size: None,
+            }),
+        ),
+        (None, None) => {
+            anyhow::bail!("must specify either file path or --ram");
+        }
+        (Some(_), Some(_)) => {
+            anyhow::bail!("cannot specify both file path and --ram");
+        }
+    };
+    Ok(disk)
+}
 
 fn maybe_with_radix_u64(s: &str) -> Result<u64, String> {
     let (radix, prefix_len) = if s.starts_with("0x") || s.starts_with("0X") {
@@ -119,6 +161,15 @@ enum InteractiveCommand {
         path: PathBuf,
     },
 
+    /// Show the blocks changed on a change tracking disk (`track:<name>:...`).
+    ChangedBlocks {
+        /// The name of the change tracking disk.
+        name: String,
+        /// Start a new epoch after showing the changed blocks.
+        #[clap(long)]
+        reset: bool,
+    },
+
     /// Do a pulsed save restore (pause, save, reset, restore, resume) to the VM.
     #[clap(visible_alias = "psr")]
     PulseSaveRestore,
@@ -160,6 +211,47 @@ enum InteractiveCommand {
         lun: u8,
     },
 
+    /// Hot add a NIC to the VTL0 guest.
+    AddNic {
+        /// The NIC backend, using the same syntax as `--net` (e.g. `consomme`,
+        /// `tap:<name>`, or `none`).
+        nic: NicConfigCli,
+    },
+
+    /// Hot remove a NIC added with `add-nic` from the VTL0 guest.
+    RmNic {
+        /// The instance ID of the NIC, as printed by `add-nic`.
+        instance_id: Guid,
+    },
+
+    /// Hot add an NVMe disk on a PCIe port.
+    AddPcieDisk {
+        /// The PCIe port to add the disk's NVMe controller to.
+        #[clap(long)]
+        port: String,
+        #[clap(long = "ro")]
+        read_only: bool,
+        /// Create a RAM-backed disk of the specified size in bytes.
+        #[clap(long)]
+        ram: Option<u64>,
+        /// Path to a file to use as the backing store.
+        file_path: Option<PathBuf>,
+    },
+
+    /// Hot remove the device on a PCIe port.
+    RmPcieDevice {
+        /// Press the slot's attention button and wait for the guest to
+        /// release the device before removing it, instead of removing it by
+        /// surprise.
+        #[clap(long)]
+        graceful: bool,
+        /// How long to wait for the guest to release the device, in seconds.
+        #[clap(long, default_value = "30", requires = "graceful")]
+        timeout: u64,
+        /// The PCIe port of the device.
+        port: String,
+    },
+
     /// Manage VTL2 settings (storage controllers, NICs exposed to VTL0).
     #[clap(subcommand)]
     Vtl2Settings(Vtl2SettingsCommand),
@@ -192,6 +284,12 @@ enum InteractiveCommand {
         vtl0: bool,
     },
 
+    /// Write a per-command IO latency trace for the VTL2 NVMe controller.
+    NvmeIoTrace {
+        /// The file to write the trace to. Stops tracing if not specified.
+        path: Option<PathBuf>,
+    },
+
     /// Inspect program state.
     #[clap(visible_alias = "x")]
     Inspect {
@@ -256,6 +354,18 @@ enum InteractiveCommand {
         /// Tell the guest to force the power state transition.
         #[clap(long, short = 'f')]
         force: bool,
+        /// Wait for the guest to power off and then exit, powering off the
+        /// VM if the guest does not shut down in time.
+        ///
+        /// The process exit code reports which path was taken: 0 if the guest
+        /// shut down, 2 if the timeout expired, and 3 if the shutdown request
+        /// could not be delivered.
+        #[clap(long, conflicts_with_all = ["reboot", "hibernate"])]
+        graceful: bool,
+        /// The number of seconds to wait for a graceful shutdown before
+        /// powering off the VM.
+        #[clap(long, default_value = "60", requires = "graceful")]
+        timeout: u64,
     },
 
     /// Clears the current halt condition, resuming the VPs if the VM is
@@ -281,6 +391,9 @@ enum InteractiveCommand {
         /// Default is `false`.
         #[clap(long)]
         mana_keepalive: bool,
+        /// Simulate a host failure at one step of the servicing handshake.
+        #[clap(long, value_enum, conflicts_with("user_mode_only"))]
+        fault: Option<ServicingFaultCli>,
     },
 
     /// Read guest memory
@@ -315,6 +428,48 @@ enum InteractiveCommand {
 
     /// Use KVP to interact with the guest.
     Kvp(kvp::KvpCommand),
+
+    /// Show the virtio balloon status, optionally setting a new target.
+    Balloon {
+        /// The new balloon target, in MB of memory to take from the guest.
+        #[clap(long)]
+        target_mb: Option<u32>,
+    },
+
+    /// Show the dynamic memory status, optionally setting a new balloon
+    /// target or hot-adding memory.
+    DynamicMemory {
+        /// The new balloon target, in MB of memory to take from the guest.
+        #[clap(long)]
+        target_mb: Option<u32>,
+        /// The amount of memory to hot-add to the guest, in MB.
+        #[clap(long)]
+        hot_add_mb: Option<u32>,
+    },
+}
+
+/// A host failure to simulate while servicing VTL2.
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ServicingFaultCli {
+    /// Reject the saved state sent by VTL2.
+    RejectSave,
+    /// Corrupt the saved state before delivering it to the new VTL2.
+    CorruptSavedState,
+    /// Fail to activate the new IGVM file, so the new VTL2 never starts.
+    FailActivation,
+    /// Fail the new VTL2's request for its saved state.
+    FailRestore,
+}
+
+impl From<ServicingFaultCli> for ServicingFault {
+    fn from(fault: ServicingFaultCli) -> Self {
+        match fault {
+            ServicingFaultCli::RejectSave => ServicingFault::RejectSave,
+            ServicingFaultCli::CorruptSavedState => ServicingFault::CorruptSavedState,
+            ServicingFaultCli::FailActivation => ServicingFault::FailActivation,
+            ServicingFaultCli::FailRestore => ServicingFault::FailRestore,
+        }
+    }
 }
 
 /// Subcommands for managing VTL2 settings.
@@ -395,15 +550,43 @@ pub(crate) struct ReplResources {
     pub nvme_vtl2_rpc: Option<mesh::Sender<NvmeControllerRequest>>,
     pub shutdown_ic: Option<mesh::Sender<hyperv_ic_resources::shutdown::ShutdownRpc>>,
     pub kvp_ic: Option<mesh::Sender<hyperv_ic_resources::kvp::KvpConnectRpc>>,
+    pub balloon: Option<mesh::Sender<virtio_resources::balloon::BalloonRpc>>,
+    pub dynamic_memory: Option<mesh::Sender<hyperv_dm_resources::DynamicMemoryRpc>>,
     pub console_in: Option<Box<dyn AsyncWrite + Send + Unpin>>,
     pub has_vtl2: bool,
 }
 
+/// How the REPL exited.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum ReplExit {
+    /// The user quit or the VM worker stopped.
+    Quit,
+    /// The guest powered off in response to `shutdown --graceful`.
+    GracefulShutdown,
+    /// The guest did not power off before the `shutdown --graceful` timeout,
+    /// so the VM was powered off.
+    ShutdownTimedOut,
+    /// The `shutdown --graceful` request could not be delivered to the guest,
+    /// so the VM was powered off.
+    ShutdownFailed,
+}
+
+impl ReplExit {
+    /// Returns the process exit code for this exit path.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ReplExit::Quit | ReplExit::GracefulShutdown => 0,
+            ReplExit::ShutdownTimedOut => 2,
+            ReplExit::ShutdownFailed => 3,
+        }
+    }
+}
+
 /// Run the interactive REPL.
 pub(crate) async fn run_repl(
     driver: &DefaultDriver,
     resources: ReplResources,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<ReplExit> {
     let ReplResources {
         vm_rpc,
         vm_controller,
@@ -412,6 +595,8 @@ pub(crate) async fn run_repl(
         mut nvme_vtl2_rpc,
         shutdown_ic,
         kvp_ic,
+        balloon,
+        dynamic_memory,
         console_in,
         has_vtl2,
     } = resources;
@@ -557,6 +742,8 @@ pub(crate) async fn run_repl(
     let mut state_change_task = None::<Task<Result<StateChange, RpcError>>>;
     let mut pulse_save_restore_interval: Option<Duration> = None;
     let mut pending_shutdown = None;
+    let mut graceful_shutdown_deadline = None::<Instant>;
+    let mut exit = ReplExit::Quit;
     let mut snapshot_saved = false;
 
     enum StateChange {
@@ -576,9 +763,23 @@ enum Event {
         PulseSaveRestore,
         StateChange(Result<StateChange, RpcError>),
         ShutdownResult(Result<hyperv_ic_resources::shutdown::ShutdownResult, RpcError>),
+        GracefulShutdownTimeout,
         Controller(VmControllerEvent),
     }
 
+    /// Stops the VM worker, after which the REPL exits.
+    fn quit(
+        vm_controller: &mesh::Sender<VmControllerRpc>,
+        scsi_rpc: &mut Option<mesh::Sender<ScsiControllerRequest>>,
+        nvme_vtl2_rpc: &mut Option<mesh::Sender<NvmeControllerRequest>>,
+    ) {
+        // Work around the detached SCSI task holding up worker stop.
+        // TODO: Fix the underlying bug
+        drop(scsi_rpc.take());
+        drop(nvme_vtl2_rpc.take());
+        vm_controller.send(VmControllerRpc::Quit);
+    }
+
     let mut console_command_recv = console_command_recv
         .map(Event::Command)
         .chain(futures::stream::repeat_with(|| Event::Quit));
@@ -608,6 +809,17 @@ enum Event {
                     pending().await
                 }
             });
+            let graceful_shutdown_timeout = pin!(async {
+                match graceful_shutdown_deadline {
+                    Some(deadline) => {
+                        PolledTimer::new(driver)
+                            .sleep(deadline.saturating_duration_since(Instant::now()))
+                            .await;
+                        Event::GracefulShutdownTimeout
+                    }
+                    None => pending().await,
+                }
+            });
             let controller_events = (&mut vm_controller_events).map(Event::Controller);
 
             (
@@ -616,6 +828,7 @@ enum Event {
                 pulse_save_restore.into_stream(),
                 change,
                 shutdown.into_stream(),
+                graceful_shutdown_timeout.into_stream(),
                 controller_events,
             )
                 .merge()
@@ -705,6 +918,7 @@ enum Event {
                 continue;
             }
             Event::ShutdownResult(r) => {
+                let initiated = matches!(r, Ok(hyperv_ic_resources::shutdown::ShutdownResult::Ok));
                 match r {
                     Ok(r) => match r {
                         hyperv_ic_resources::shutdown::ShutdownResult::Ok => {
@@ -728,6 +942,18 @@ enum Event {
                     }
                 }
                 pending_shutdown = None;
+                if !initiated && graceful_shutdown_deadline.take().is_some() {
+                    tracing::warn!("graceful shutdown failed, powering off");
+                    exit = ReplExit::ShutdownFailed;
+                    quit(&vm_controller, &mut scsi_rpc, &mut nvme_vtl2_rpc);
+                }
+                continue;
+            }
+            Event::GracefulShutdownTimeout => {
+                tracing::warn!("graceful shutdown timed out, powering off");
+                graceful_shutdown_deadline = None;
+                exit = ReplExit::ShutdownTimedOut;
+                quit(&vm_controller, &mut scsi_rpc, &mut nvme_vtl2_rpc);
                 continue;
             }
             Event::Controller(event) => {
@@ -741,8 +967,13 @@ enum Event {
                     VmControllerEvent::VncWorkerStopped { .. } => {
                         // VNC stopped but VM is still running, continue.
                     }
-                    VmControllerEvent::GuestHalt(reason) => {
+                    VmControllerEvent::GuestHalt { reason, power_off } => {
                         tracing::info!(reason = reason.as_str(), "guest halted");
+                        if power_off && graceful_shutdown_deadline.take().is_some() {
+                            tracing::info!("graceful shutdown complete");
+                            exit = ReplExit::GracefulShutdown;
+                            quit(&vm_controller, &mut scsi_rpc, &mut nvme_vtl2_rpc);
+                        }
                     }
                 }
                 continue;
@@ -860,6 +1091,37 @@ fn state_change<U: 'static + Send>(
                     }
                 }
             }
+            InteractiveCommand::ChangedBlocks { name, reset } => {
+                match vm_controller
+                    .call(
+                        VmControllerRpc::ChangedBlocks,
+                        ChangedBlocksParams { name, reset },
+                    )
+                    .await
+                    .map_err(anyhow::Error::from)
+                    .and_then(|r| Ok(r?))
+                {
+                    Ok(changed) => {
+                        let blocks = changed
+                            .bitmap
+                            .iter()
+                            .enumerate()
+                            .flat_map(|(i, &word)| {
+                                (0..64)
+                                    .filter(move |bit| word & (1 << bit) != 0)
+                                    .map(move |bit| i as u64 * 64 + bit)
+                            })
+                            .collect::<Vec<_>>();
+                        println!(
+                            "epoch {}, block size {:#x}, changed blocks: {:?}",
+                            changed.epoch, changed.block_size, blocks
+                        );
+                    }
+                    Err(err) => {
+                        eprintln!("error: changed-blocks failed: {err:#}");
+                    }
+                }
+            }
             InteractiveCommand::PulseSaveRestore => {
                 state_change(
                     driver,
@@ -879,8 +1141,10 @@ fn state_change<U: 'static + Send>(
                 reboot,
                 hibernate,
                 force,
+                graceful,
+                timeout,
             } => {
-                if pending_shutdown.is_some() {
+                if pending_shutdown.is_some() || graceful_shutdown_deadline.is_some() {
                     println!("shutdown already in progress");
                 } else if let Some(ic) = &shutdown_ic {
                     let params = hyperv_ic_resources::shutdown::ShutdownParams {
@@ -895,12 +1159,22 @@ fn state_change<U: 'static + Send>(
                     };
                     pending_shutdown =
                         Some(ic.call(hyperv_ic_resources::shutdown::ShutdownRpc::Shutdown, params));
+                    if graceful {
+                        graceful_shutdown_deadline =
+                            Some(Instant::now() + Duration::from_secs(timeout));
+                    }
+                } else if graceful {
+                    println!("no shutdown ic configured, powering off");
+                    exit = ReplExit::ShutdownFailed;
+                    quit(&vm_controller, &mut scsi_rpc, &mut nvme_vtl2_rpc);
                 } else {
                     println!("no shutdown ic configured");
                 }
             }
             InteractiveCommand::Nmi => {
-                let _ = vm_rpc.call(VmRpc::Nmi, 0).await;
+                if let Err(err) = vm_rpc.call_failable(VmRpc::Nmi, 0).await {
+                    eprintln!("error: nmi failed: {err:#}");
+                }
             }
             InteractiveCommand::ClearHalt => {
                 vm_rpc.call(VmRpc::ClearHalt, ()).await.ok();
@@ -924,6 +1198,7 @@ fn state_change<U: 'static + Send>(
                                 openvmm_helpers::disk::OpenDiskOptions {
                                     read_only,
                                     direct: false,
+                                    change_tracking: false,
                                 },
                             )
                             .await
@@ -984,6 +1259,109 @@ fn state_change<U: 'static + Send>(
                     tracing::error!(error = error.as_error(), "error removing disk")
                 }
             }
+            InteractiveCommand::AddNic { nic } => {
+                let action = async {
+                    if nic.vtl != DeviceVtl::Vtl0 || nic.underhill || nic.pcie_port.is_some() {
+                        anyhow::bail!("only VTL0 vmbus NICs can be hot added");
+                    }
+                    let instance_id = Guid::new_random();
+                    let device = NetvspHandle {
+                        instance_id,
+                        mac_address: crate::random_mac_address(),
+                        endpoint: crate::endpoint_resource(&nic.endpoint, None)?,
+                        max_queues: nic.max_queues,
+                    }
+                    .into_resource();
+                    vm_rpc
+                        .call_failable(VmRpc::AddVmbusDevice, (DeviceVtl::Vtl0, device))
+                        .await?;
+                    println!("added nic {instance_id}");
+                    anyhow::Ok(())
+                };
+
+                if let Err(error) = action.await {
+                    tracing::error!(error = error.as_error(), "error adding nic")
+                }
+            }
+            InteractiveCommand::RmNic { instance_id } => {
+                let action = async {
+                    vm_rpc
+                        .call_failable(VmRpc::RemoveVmbusDevice, (DeviceVtl::Vtl0, instance_id))
+                        .await?;
+                    println!("removed nic {instance_id}");
+                    anyhow::Ok(())
+                };
+
+                if let Err(error) = action.await {
+                    tracing::error!(error = error.as_error(), "error removing nic")
+                }
+            }
+            InteractiveCommand::AddPcieDisk {
+                port,
+                read_only,
+                ram,
+                file_path,
+            } => {
+                let action = async {
+                    let disk = open_hot_add_disk(ram, file_path, read_only).await?;
+                    let device = NvmeControllerHandle {
+                        subsystem_id: Guid::new_random(),
+                        namespaces: vec![NamespaceDefinition {
+                            nsid: 1,
+                            read_only,
+                            disk,
+                        }],
+                        max_io_queues: 64,
+                        msix_count: 64,
+                        requests: None,
+                        boot_partitions: None,
+                    }
+                    .into_resource();
+                    vm_rpc
+                        .call_failable(VmRpc::AddPcieDevice, (port.clone(), device))
+                        .await?;
+                    println!("added nvme disk on port {port}");
+                    anyhow::Ok(())
+                };
+
+                if let Err(error) = action.await {
+                    tracing::error!(error = error.as_error(), "error adding pcie disk")
+                }
+            }
+            InteractiveCommand::RmPcieDevice {
+                graceful,
+                timeout,
+                port,
+            } => {
+                let action = async {
+                    if graceful {
+                        vm_rpc
+                            .call_failable(VmRpc::RequestPcieDeviceRemoval, port.clone())
+                            .await?;
+                        println!("waiting for the guest to release the device on port {port}");
+                        let deadline = Instant::now() + Duration::from_secs(timeout);
+                        let mut timer = PolledTimer::new(driver);
+                        while !vm_rpc
+                            .call_failable(VmRpc::PcieDeviceReleased, port.clone())
+                            .await?
+                        {
+                            if Instant::now() >= deadline {
+                                anyhow::bail!("guest did not release the device within {timeout}s");
+                            }
+                            timer.sleep(Duration::from_millis(100)).await;
+                        }
+                    }
+                    vm_rpc
+                        .call_failable(VmRpc::RemovePcieDevice, port.clone())
+                        .await?;
+                    println!("removed device on port {port}");
+                    anyhow::Ok(())
+                };
+
+                if let Err(error) = action.await {
+                    tracing::error!(error = error.as_error(), "error removing pcie device")
+                }
+            }
             InteractiveCommand::Vtl2Settings(cmd) => {
                 if !has_vtl2 {
                     eprintln!("error: no VTL2 settings (not running with VTL2?)");
@@ -1113,31 +1491,7 @@ fn state_change<U: 'static + Send>(
                 }
                 let action = async {
                     let nvme = nvme_vtl2_rpc.as_ref().context("no vtl2 nvme controller")?;
-                    let disk_type = match (ram, file_path) {
-                        (None, Some(path)) => openvmm_helpers::disk::open_disk_type(
-                            path.as_ref(),
-                            openvmm_helpers::disk::OpenDiskOptions {
-                                read_only,
-                                direct: false,
-                            },
-                        )
-                        .await
-                        .with_context(|| format!("failed to open {}", path.display()))?,
-                        (Some(size), None) => {
-                            Resource::new(disk_backend_resources::LayeredDiskHandle::single_layer(
-                                RamDiskLayerHandle {
-                                    len: Some(size),
-                                    sector_size: None,
-                                },
-                            ))
-                        }
-                        (None, None) => {
-                            anyhow::bail!("must specify either file path or --ram");
-                        }
-                        (Some(_), Some(_)) => {
-                            anyhow::bail!("cannot specify both file path and --ram");
-                        }
-                    };
+                    let disk_type = open_hot_add_disk(ram, file_path, read_only).await?;
 
                     let ns = NamespaceDefinition {
                         nsid,
@@ -1213,6 +1567,30 @@ fn state_change<U: 'static + Send>(
                     eprintln!("error removing nvme namespace: {}", error);
                 }
             }
+            InteractiveCommand::NvmeIoTrace { path } => {
+                if !has_vtl2 {
+                    eprintln!("error: nvme-io-trace requires --vtl2 mode");
+                    continue;
+                }
+                let action = async {
+                    let nvme = nvme_vtl2_rpc.as_ref().context("no vtl2 nvme controller")?;
+                    let file = path
+                        .as_ref()
+                        .map(|path| fs_err::File::create(path).map(|file| file.into_parts().0))
+                        .transpose()?;
+                    nvme.call_failable(NvmeControllerRequest::SetIoTrace, file)
+                        .await?;
+                    match &path {
+                        Some(path) => println!("Tracing NVMe IO to {}", path.display()),
+                        None => println!("Stopped tracing NVMe IO"),
+                    }
+                    anyhow::Ok(())
+                };
+
+                if let Err(error) = action.await {
+                    eprintln!("error setting nvme io trace: {:#}", error);
+                }
+            }
             InteractiveCommand::Inspect {
                 recursive,
                 limit,
@@ -1319,6 +1697,7 @@ fn state_change<U: 'static + Send>(
                 igvm,
                 mana_keepalive,
                 nvme_keepalive,
+                fault,
             } => {
                 let vm_controller = vm_controller.clone();
                 let r = async move {
@@ -1330,6 +1709,7 @@ fn state_change<U: 'static + Send>(
                                 igvm: igvm.map(|p| p.to_string_lossy().into_owned()),
                                 nvme_keepalive,
                                 mana_keepalive,
+                                fault: fault.map(Into::into),
                             },
                         )
                         .await??;
@@ -1344,11 +1724,7 @@ fn state_change<U: 'static + Send>(
             }
             InteractiveCommand::Quit => {
                 tracing::info!("quitting");
-                // Work around the detached SCSI task holding up worker stop.
-                // TODO: Fix the underlying bug
-                drop(scsi_rpc.take());
-                drop(nvme_vtl2_rpc.take());
-                vm_controller.send(VmControllerRpc::Quit);
+                quit(&vm_controller, &mut scsi_rpc, &mut nvme_vtl2_rpc);
             }
             InteractiveCommand::ReadMemory { gpa, size, file } => {
                 let size = size as usize;
@@ -1469,11 +1845,109 @@ fn state_change<U: 'static + Send>(
                     eprintln!("error: {err:#}");
                 }
             }
+            InteractiveCommand::Balloon { target_mb } => {
+                let Some(balloon) = &balloon else {
+                    eprintln!("error: no balloon configured");
+                    continue;
+                };
+                if let Some(target_mb) = target_mb {
+                    // The balloon target is in 4KB pages.
+                    if let Err(err) = balloon
+                        .call(
+                            virtio_resources::balloon::BalloonRpc::SetTarget,
+                            target_mb.saturating_mul(256),
+                        )
+                        .await
+                    {
+                        eprintln!("error: failed to set balloon target: {err:#}");
+                        continue;
+                    }
+                }
+                let status = match balloon
+                    .call(virtio_resources::balloon::BalloonRpc::Status, ())
+                    .await
+                {
+                    Ok(status) => status,
+                    Err(err) => {
+                        eprintln!("error: failed to get balloon status: {err:#}");
+                        continue;
+                    }
+                };
+                println!(
+                    "target: {} MB, actual: {} MB",
+                    status.target_pages / 256,
+                    status.actual_pages / 256
+                );
+                for stat in status.stats {
+                    println!("stat {}: {:#x}", stat.tag, stat.value);
+                }
+            }
+            InteractiveCommand::DynamicMemory {
+                target_mb,
+                hot_add_mb,
+            } => {
+                let Some(dynamic_memory) = &dynamic_memory else {
+                    eprintln!("error: no dynamic memory device configured");
+                    continue;
+                };
+                if let Some(target_mb) = target_mb {
+                    // The balloon target is in 4KB pages.
+                    if let Err(err) = dynamic_memory
+                        .call(
+                            hyperv_dm_resources::DynamicMemoryRpc::SetTarget,
+                            u64::from(target_mb) * 256,
+                        )
+                        .await
+                    {
+                        eprintln!("error: failed to set balloon target: {err:#}");
+                        continue;
+                    }
+                }
+                if let Some(hot_add_mb) = hot_add_mb {
+                    match dynamic_memory
+                        .call_failable(
+                            hyperv_dm_resources::DynamicMemoryRpc::HotAdd,
+                            u64::from(hot_add_mb) << 20,
+                        )
+                        .await
+                    {
+                        Ok(len) => println!("added {} MB", len >> 20),
+                        Err(err) => {
+                            eprintln!("error: failed to hot-add memory: {err:#}");
+                            continue;
+                        }
+                    }
+                }
+                let status = match dynamic_memory
+                    .call(hyperv_dm_resources::DynamicMemoryRpc::Status, ())
+                    .await
+                {
+                    Ok(status) => status,
+                    Err(err) => {
+                        eprintln!("error: failed to get dynamic memory status: {err:#}");
+                        continue;
+                    }
+                };
+                println!(
+                    "connected: {}, target: {} MB, ballooned: {} MB, hot-added: {} MB",
+                    status.connected,
+                    status.target_pages / 256,
+                    status.ballooned_pages / 256,
+                    status.hot_added_pages / 256
+                );
+                if let Some(guest) = status.guest {
+                    println!(
+                        "guest available: {} MB, committed: {} MB",
+                        guest.available_pages / 256,
+                        guest.committed_pages / 256
+                    );
+                }
+            }
             InteractiveCommand::Input { .. } | InteractiveCommand::InputMode => unreachable!(),
         }
     }
 
-    Ok(())
+    Ok(exit)
 }
 
 // -- Rustyline helpers --
@@ -4,6 +4,7 @@
 //! Code to build storage configuration from command line arguments.
 
 use crate::VmResources;
+use crate::change_tracking::ChangeTrackers;
 use crate::cli_args::DiskCliKind;
 use crate::cli_args::UnderhillDiskSource;
 use crate::disk_open;
@@ -13,6 +14,7 @@
 use ide_resources::IdeDeviceConfig;
 use ide_resources::IdePath;
 use nvme_resources::NamespaceDefinition;
+use nvme_resources::NvmeBootPartitions;
 use nvme_resources::NvmeControllerHandle;
 use openvmm_defs::config::Config;
 use openvmm_defs::config::DeviceVtl;
@@ -39,6 +41,7 @@ pub(super) struct StorageBuilder {
     vtl0_scsi_devices: Vec<ScsiDeviceAndPath>,
     vtl2_scsi_devices: Vec<ScsiDeviceAndPath>,
     vtl0_nvme_namespaces: Vec<NamespaceDefinition>,
+    vtl0_nvme_boot_partitions: Option<NvmeBootPartitions>,
     vtl2_nvme_namespaces: Vec<NamespaceDefinition>,
     pcie_nvme_controllers: BTreeMap<String, Vec<NamespaceDefinition>>,
     pcie_virtio_blk_disks: Vec<(String, VirtioBlkDisk)>,
@@ -46,6 +49,7 @@ pub(super) struct StorageBuilder {
     underhill_nvme_luns: Vec<Lun>,
     vtl0_virtio_blk_disks: Vec<VirtioBlkDisk>,
     openhcl_vtl: Option<DeviceVtl>,
+    change_trackers: ChangeTrackers,
 }
 
 struct VirtioBlkDisk {
@@ -111,12 +115,13 @@ fn from(value: UnderhillDiskSource) -> Self {
 const VIRTIO_BLK_INSTANCE_ID_TEMPLATE: Guid = guid::guid!("00000000-a4e7-4b53-b702-1f42d938647e");
 
 impl StorageBuilder {
-    pub fn new(openhcl_vtl: Option<DeviceVtl>) -> Self {
+    pub fn new(openhcl_vtl: Option<DeviceVtl>, change_trackers: ChangeTrackers) -> Self {
         Self {
             vtl0_ide_disks: Vec::new(),
             vtl0_scsi_devices: Vec::new(),
             vtl2_scsi_devices: Vec::new(),
             vtl0_nvme_namespaces: Vec::new(),
+            vtl0_nvme_boot_partitions: None,
             vtl2_nvme_namespaces: Vec::new(),
             pcie_nvme_controllers: BTreeMap::new(),
             pcie_virtio_blk_disks: Vec::new(),
@@ -124,9 +129,22 @@ pub fn new(openhcl_vtl: Option<DeviceVtl>) -> Self {
             underhill_nvme_luns: Vec::new(),
             vtl0_virtio_blk_disks: Vec::new(),
             openhcl_vtl,
+            change_trackers,
         }
     }
 
+    /// Returns the change tracking disks opened so far, for opening disks
+    /// that are not managed by the storage builder.
+    pub fn change_trackers(&mut self) -> &mut ChangeTrackers {
+        &mut self.change_trackers
+    }
+
+    /// Exposes boot partitions on the VTL0 NVMe controller, adding the
+    /// controller if there are no namespaces.
+    pub fn set_vtl0_nvme_boot_partitions(&mut self, boot_partitions: NvmeBootPartitions) {
+        self.vtl0_nvme_boot_partitions = Some(boot_partitions);
+    }
+
     pub fn has_vtl0_nvme(&self) -> bool {
         !self.vtl0_nvme_namespaces.is_empty() || !self.underhill_nvme_luns.is_empty()
     }
@@ -162,7 +180,7 @@ async fn add_inner(
         is_dvd: bool,
         read_only: bool,
     ) -> anyhow::Result<Option<u32>> {
-        let disk = disk_open(kind, read_only || is_dvd).await?;
+        let disk = disk_open(kind, read_only || is_dvd, &mut self.change_trackers).await?;
         let location = match target {
             DiskLocation::Ide(channel, device) => {
                 let guest_media = if is_dvd {
@@ -373,6 +391,7 @@ pub fn build_config(
         scsi_sub_channels: u16,
     ) -> anyhow::Result<()> {
         config.ide_disks.append(&mut self.vtl0_ide_disks);
+        resources.change_trackers = std::mem::take(&mut self.change_trackers);
 
         // Add an empty VTL0 SCSI controller even if there are no configured disks.
         if !self.vtl0_scsi_devices.is_empty() || config.vmbus.is_some() {
@@ -386,6 +405,7 @@ pub fn build_config(
                     io_queue_depth: None,
                     requests: Some(recv),
                     poll_mode_queue_depth: None,
+                    max_protocol_version: None,
                 }
                 .into_resource(),
             ));
@@ -410,12 +430,13 @@ pub fn build_config(
                     io_queue_depth: None,
                     requests: None,
                     poll_mode_queue_depth: None,
+                    max_protocol_version: None,
                 }
                 .into_resource(),
             ));
         }
 
-        if !self.vtl0_nvme_namespaces.is_empty() {
+        if !self.vtl0_nvme_namespaces.is_empty() || self.vtl0_nvme_boot_partitions.is_some() {
             config.vpci_devices.push(VpciDeviceConfig {
                 vtl: DeviceVtl::Vtl0,
                 instance_id: NVME_VTL0_INSTANCE_ID,
@@ -425,6 +446,7 @@ pub fn build_config(
                     max_io_queues: 64,
                     msix_count: 64,
                     requests: None,
+                    boot_partitions: self.vtl0_nvme_boot_partitions.take(),
                 }
                 .into_resource(),
             });
@@ -462,6 +484,7 @@ pub fn build_config(
                     max_io_queues: 64,
                     msix_count: 64,
                     requests: Some(recv),
+                    boot_partitions: None,
                 }
                 .into_resource(),
             });
@@ -487,6 +510,7 @@ pub fn build_config(
                     max_io_queues: 64,
                     msix_count: 64,
                     requests: None,
+                    boot_partitions: None,
                 }
                 .into_resource(),
             });
@@ -541,6 +565,7 @@ pub fn build_underhill(&self, vmbus_redirect: bool) -> Vec<StorageController> {
                 protocol: storage_controller::StorageProtocol::Scsi.into(),
                 luns: self.underhill_scsi_luns.clone(),
                 io_queue_depth: None,
+                telemetry: None,
             };
             storage_controllers.push(controller);
         }
@@ -551,6 +576,7 @@ pub fn build_underhill(&self, vmbus_redirect: bool) -> Vec<StorageController> {
                 protocol: storage_controller::StorageProtocol::Nvme.into(),
                 luns: self.underhill_nvme_luns.clone(),
                 io_queue_depth: None,
+                telemetry: None,
             };
             storage_controllers.push(controller);
         }
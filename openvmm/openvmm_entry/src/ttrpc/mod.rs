@@ -5,9 +5,11 @@
 
 #![cfg(any(feature = "ttrpc", feature = "grpc"))]
 
+use crate::change_tracking::ChangeTrackers;
 use crate::meshworker::VmmMesh;
 use crate::serial_io::bind_serial;
 use crate::serial_io::connect_serial;
+use crate::vm_controller::ChangedBlocksParams;
 use crate::vm_controller::InspectTarget;
 use crate::vm_controller::VmController;
 use crate::vm_controller::VmControllerEvent;
@@ -18,6 +20,11 @@
 use futures::FutureExt;
 use futures::StreamExt;
 use guid::Guid;
+use hyperv_ic_resources::shutdown::ShutdownIcHandle;
+use hyperv_ic_resources::shutdown::ShutdownParams;
+use hyperv_ic_resources::shutdown::ShutdownResult;
+use hyperv_ic_resources::shutdown::ShutdownRpc;
+use hyperv_ic_resources::shutdown::ShutdownType;
 use inspect::InspectionBuilder;
 use inspect_proto::InspectResponse2;
 use inspect_proto::InspectService;
@@ -49,11 +56,13 @@
 use openvmm_ttrpc_vmservice as vmservice;
 use pal_async::DefaultDriver;
 use pal_async::DefaultPool;
+use pal_async::socket::Listener;
 use pal_async::task::Spawn;
 use pal_async::task::Task;
 use scsidisk_resources::SimpleScsiDiskHandle;
 use std::fs::File;
 use std::future::Future;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use storvsp_resources::ScsiControllerHandle;
@@ -70,8 +79,25 @@
 
 #[derive(mesh::MeshPayload)]
 pub struct Parameters {
-    pub listener: UnixListener,
+    pub listener: RpcListener,
     pub transport: RpcTransport,
+    /// The TLS identity to serve with. Only supported for the gRPC transport.
+    pub tls: Option<TlsIdentity>,
+}
+
+#[derive(mesh::MeshPayload)]
+pub enum RpcListener {
+    Unix(UnixListener),
+    Tcp(std::net::TcpListener),
+}
+
+/// A TLS server identity.
+#[derive(mesh::MeshPayload)]
+pub struct TlsIdentity {
+    /// The PEM-encoded certificate chain.
+    pub cert_pem: Vec<u8>,
+    /// The PEM-encoded PKCS #8 private key.
+    pub key_pem: Vec<u8>,
 }
 
 #[derive(Copy, Clone, mesh::MeshPayload)]
@@ -89,16 +115,18 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 enum ResolvedTransport {
     #[cfg(feature = "ttrpc")]
     Ttrpc,
     #[cfg(feature = "grpc")]
     Grpc,
+    #[cfg(feature = "grpc_tls")]
+    GrpcTls(tokio_native_tls::native_tls::TlsAcceptor),
 }
 
 pub struct TtrpcWorker {
-    listener: UnixListener,
+    listener: RpcListener,
     transport: ResolvedTransport,
 }
 
@@ -112,14 +140,26 @@ impl Worker for TtrpcWorker {
     fn new(parameters: Self::Parameters) -> anyhow::Result<Self> {
         Ok(Self {
             listener: parameters.listener,
-            transport: match parameters.transport {
+            transport: match (parameters.transport, parameters.tls) {
                 #[cfg(feature = "ttrpc")]
-                RpcTransport::Ttrpc => ResolvedTransport::Ttrpc,
+                (RpcTransport::Ttrpc, None) => ResolvedTransport::Ttrpc,
                 #[cfg(feature = "grpc")]
-                RpcTransport::Grpc => ResolvedTransport::Grpc,
+                (RpcTransport::Grpc, None) => ResolvedTransport::Grpc,
+                #[cfg(feature = "grpc_tls")]
+                (RpcTransport::Grpc, Some(tls)) => {
+                    use tokio_native_tls::native_tls;
+                    let identity = native_tls::Identity::from_pkcs8(&tls.cert_pem, &tls.key_pem)
+                        .context("invalid tls identity")?;
+                    let acceptor = native_tls::TlsAcceptor::new(identity)
+                        .context("failed to create tls acceptor")?;
+                    ResolvedTransport::GrpcTls(acceptor)
+                }
+                #[expect(clippy::allow_attributes)]
+                #[allow(unreachable_patterns)]
+                (transport, Some(_)) => bail!("tls is not supported for transport {transport}"),
                 #[expect(clippy::allow_attributes)]
                 #[allow(unreachable_patterns)]
-                transport => bail!("unsupported transport {transport}"),
+                (transport, None) => bail!("unsupported transport {transport}"),
             },
         })
     }
@@ -130,6 +170,7 @@ fn restart(_state: Self::State) -> anyhow::Result<Self> {
 
     fn run(self, recv: mesh::Receiver<WorkerRpc<Self::State>>) -> anyhow::Result<()> {
         DefaultPool::run_with(async |driver| {
+            let (power_off_send, power_off_recv) = mesh::channel();
             let mut service = VmService {
                 driver,
                 vm: None,
@@ -138,10 +179,12 @@ fn run(self, recv: mesh::Receiver<WorkerRpc<Self::State>>) -> anyhow::Result<()>
                 controller_task: None,
                 wait_vm_response: None,
                 halted: false,
+                shutdown_waiter: None,
+                power_off_send,
                 rpc_tasks: Vec::new(),
                 transport: self.transport,
             };
-            service.run(self.listener, recv).await?;
+            service.run(self.listener, recv, power_off_recv).await?;
             Ok(())
         })
     }
@@ -150,24 +193,25 @@ fn run(self, recv: mesh::Receiver<WorkerRpc<Self::State>>) -> anyhow::Result<()>
 impl VmService {
     async fn run(
         &mut self,
-        listener: UnixListener,
+        listener: RpcListener,
         mut recv: mesh::Receiver<WorkerRpc<()>>,
+        mut power_off_recv: mesh::Receiver<mesh::OneshotSender<()>>,
     ) -> anyhow::Result<()> {
         let mut server = mesh_rpc::Server::new();
         let mut vm_service_recv = server.add_service::<vmservice::Vm>();
         let mut inspect_service_recv = server.add_service::<InspectService>();
 
-        let transport = self.transport;
+        let transport = self.transport.clone();
         let (cancel_send, cancel_recv) = mesh::oneshot();
         let server_task = self.driver.spawn("ttrpc-server", {
             let driver = self.driver.clone();
             async move {
-                let r = match transport {
-                    #[cfg(feature = "ttrpc")]
-                    ResolvedTransport::Ttrpc => server.run(&driver, listener, cancel_recv).await,
-                    #[cfg(feature = "grpc")]
-                    ResolvedTransport::Grpc => {
-                        server.run_grpc(&driver, listener, cancel_recv).await
+                let r = match listener {
+                    RpcListener::Unix(listener) => {
+                        run_server(&mut server, &driver, listener, transport, cancel_recv).await
+                    }
+                    RpcListener::Tcp(listener) => {
+                        run_server(&mut server, &driver, listener, transport, cancel_recv).await
                     }
                 };
                 match &r {
@@ -208,6 +252,7 @@ enum Action {
                 WorkerRpc(Result<WorkerRpc<()>, mesh::RecvError>),
                 ControllerEvent(Option<VmControllerEvent>),
                 WaitVmCancelled(CancelReason),
+                PowerOff(mesh::OneshotSender<()>),
             }
 
             let action = futures::select! { // merge semantics
@@ -216,6 +261,7 @@ enum Action {
                 r = recv.recv().fuse() => Action::WorkerRpc(r),
                 e = ctrl_fut.fuse() => Action::ControllerEvent(e),
                 reason = wait_cancel_fut.fuse() => Action::WaitVmCancelled(reason.unwrap()),
+                done = power_off_recv.select_next_some() => Action::PowerOff(done),
             };
 
             // Restore controller events (unless the channel closed).
@@ -268,6 +314,24 @@ enum Action {
                         response.send(Err(grpc_error(anyhow::Error::new(reason))));
                     }
                 }
+                Action::PowerOff(done) => {
+                    // A `ShutdownVm` call gave up on the guest, so tear the
+                    // VM down as if it had powered off.
+                    if self.vm.is_some() {
+                        tracing::info!("guest did not shut down, tearing down VM");
+                        self.halted = true;
+                        if let Some((_, response)) = self.wait_vm_response.take() {
+                            response.send(Ok(()));
+                        }
+                        if let Err(err) = self.teardown_vm().await {
+                            tracing::warn!(
+                                error = err.as_ref() as &dyn std::error::Error,
+                                "failed to tear down VM"
+                            );
+                        }
+                    }
+                    done.send(());
+                }
             }
         };
 
@@ -318,6 +382,7 @@ fn start_rpc<F, R>(
 struct Vm {
     worker_rpc: mesh::Sender<VmRpc>,
     scsi_rpc: Option<mesh::Sender<ScsiControllerRequest>>,
+    shutdown_ic: mesh::Sender<ShutdownRpc>,
 }
 
 struct VmService {
@@ -330,10 +395,36 @@ struct VmService {
     /// Set when the guest has halted, so that a later `WaitVm` completes
     /// immediately instead of blocking forever. Cleared on `CreateVm`.
     halted: bool,
+    /// Completed when the guest powers off during a `ShutdownVm` call.
+    shutdown_waiter: Option<mesh::OneshotSender<()>>,
+    /// Used by `ShutdownVm` calls to ask the service loop to tear down a VM
+    /// that did not power off.
+    power_off_send: mesh::Sender<mesh::OneshotSender<()>>,
     rpc_tasks: Vec<Task<()>>,
     transport: ResolvedTransport,
 }
 
+async fn run_server(
+    server: &mut mesh_rpc::Server,
+    driver: &DefaultDriver,
+    listener: impl Listener,
+    transport: ResolvedTransport,
+    cancel: mesh::OneshotReceiver<()>,
+) -> anyhow::Result<()> {
+    match transport {
+        #[cfg(feature = "ttrpc")]
+        ResolvedTransport::Ttrpc => server.run(driver, listener, cancel).await,
+        #[cfg(feature = "grpc")]
+        ResolvedTransport::Grpc => server.run_grpc(driver, listener, cancel).await,
+        #[cfg(feature = "grpc_tls")]
+        ResolvedTransport::GrpcTls(acceptor) => {
+            server
+                .run_grpc_tls(driver, listener, acceptor, cancel)
+                .await
+        }
+    }
+}
+
 fn grpc_error(err: anyhow::Error) -> Status {
     let root_cause = err.root_cause();
     let code = if let Some(code) = root_cause.downcast_ref::<Code>() {
@@ -372,6 +463,7 @@ async fn handle(&mut self, ctx: mesh::CancelContext, request: vmservice::Vm) ->
             vmservice::Vm::TeardownVm((), response) => {
                 response.send(map_grpc(self.teardown_vm().await))
             }
+            vmservice::Vm::CapabilitiesVm((), response) => response.send(Ok(capabilities())),
             vmservice::Vm::Quit((), response) => {
                 // Shut down the controller (which stops and joins the worker).
                 if let Some(controller) = self.vm_controller.take() {
@@ -420,14 +512,26 @@ async fn handle(&mut self, ctx: mesh::CancelContext, request: vmservice::Vm) ->
                         let r = self.modify_resource(&vm, request);
                         self.start_rpc(response, r);
                     }
+                    vmservice::Vm::SaveVm(request, response) => {
+                        let r = self.save_vm(request);
+                        self.start_rpc(response, r);
+                    }
+                    vmservice::Vm::ChangedBlocksVm(request, response) => {
+                        let r = self.changed_blocks_vm(request);
+                        self.start_rpc(response, r);
+                    }
+                    vmservice::Vm::ShutdownVm(request, response) => {
+                        let r = self.shutdown_vm(&vm, request);
+                        self.start_rpc(response, r);
+                    }
 
-                    r @ vmservice::Vm::CapabilitiesVm(_, _)
-                    | r @ vmservice::Vm::PropertiesVm(_, _) => {
+                    r @ vmservice::Vm::PropertiesVm(_, _) => {
                         r.fail(grpc_error(anyhow!("not supported")))
                     }
 
                     vmservice::Vm::CreateVm(_, _)
                     | vmservice::Vm::TeardownVm(_, _)
+                    | vmservice::Vm::CapabilitiesVm(_, _)
                     | vmservice::Vm::Quit(_, _) => unreachable!(),
                 };
             }
@@ -502,6 +606,7 @@ async fn create_vm(&mut self, request: vmservice::CreateVmRequest) -> anyhow::Re
 
         // Reset halt state for the new VM.
         self.halted = false;
+        self.shutdown_waiter = None;
 
         let load_mode = match req_config
             .boot_config
@@ -550,13 +655,22 @@ async fn create_vm(&mut self, request: vmservice::CreateVmRequest) -> anyhow::Re
             .context("failed to build vm configuration")?;
 
         // Extract memory and processor counts for the VmController.
-        let config_mem_size = req_config
+        let memory_config = req_config
             .memory_config
             .as_ref()
-            .context("missing memory configuration")?
+            .context("missing memory configuration")?;
+        let config_mem_size = memory_config
             .memory_mb
             .checked_mul(0x100000)
             .context("invalid memory configuration")?;
+        let memory_backing_file = (!memory_config.backing_file.is_empty())
+            .then(|| PathBuf::from(&memory_config.backing_file));
+        let shared_memory = memory_backing_file
+            .as_deref()
+            .map(|path| {
+                openvmm_helpers::shared_memory::open_memory_backing_file(path, config_mem_size)
+            })
+            .transpose()?;
         let config_proc_count = req_config
             .processor_config
             .as_ref()
@@ -579,7 +693,9 @@ async fn create_vm(&mut self, request: vmservice::CreateVmRequest) -> anyhow::Re
                 transparent_hugepages: false,
                 hugepages: false,
                 hugepage_size: None,
+                hugepage_fallback: false,
                 numa_mem_sizes: None,
+                hot_add_size: None,
             },
             chipset: chipset.chipset,
             processor_topology: ProcessorTopologyConfig {
@@ -616,15 +732,27 @@ async fn create_vm(&mut self, request: vmservice::CreateVmRequest) -> anyhow::Re
             layout: layout_config,
             rtc_delta_milliseconds: 0,
             automatic_guest_reset: true,
+            extra_acpi_tables: Vec::new(),
             efi_diagnostics_log_level: Default::default(),
+            track_device_memory: false,
         };
 
+        let (shutdown_ic, shutdown_recv) = mesh::channel();
+        config.vmbus_devices.push((
+            DeviceVtl::Vtl0,
+            ShutdownIcHandle {
+                recv: shutdown_recv,
+            }
+            .into_resource(),
+        ));
+
         let mut scsi_rpc = None;
+        let mut change_trackers = ChangeTrackers::default();
         if let Some(devices_config) = req_config.devices_config {
             if !devices_config.scsi_disks.is_empty() {
                 let mut devices = Vec::new();
                 for disk in devices_config.scsi_disks {
-                    devices.push(make_disk_config(disk).await?);
+                    devices.push(make_disk_config(disk, Some(&mut change_trackers)).await?);
                 }
                 let (send, recv) = mesh::channel();
                 config.vmbus_devices.push((
@@ -636,6 +764,7 @@ async fn create_vm(&mut self, request: vmservice::CreateVmRequest) -> anyhow::Re
                         io_queue_depth: None,
                         requests: Some(recv),
                         poll_mode_queue_depth: None,
+                        max_protocol_version: None,
                     }
                     .into_resource(),
                 ));
@@ -718,7 +847,7 @@ async fn create_vm(&mut self, request: vmservice::CreateVmRequest) -> anyhow::Re
                     hypervisor: openvmm_helpers::hypervisor::choose_hypervisor()?,
                     cfg: config,
                     saved_state: None,
-                    shared_memory: None,
+                    shared_memory,
                     rpc: recv,
                     notify: notify_send,
                 },
@@ -741,10 +870,11 @@ async fn create_vm(&mut self, request: vmservice::CreateVmRequest) -> anyhow::Re
             diag_inspector: None,
             vtl2_settings: None,
             ged_rpc: None,
+            change_trackers,
             vm_rpc: send.clone(),
             paravisor_diag: None,
             igvm_path: None,
-            memory_backing_file: None,
+            memory_backing_file,
             memory,
             processors,
             log_file: None,
@@ -762,6 +892,7 @@ async fn create_vm(&mut self, request: vmservice::CreateVmRequest) -> anyhow::Re
         self.vm = Some(Arc::new(Vm {
             scsi_rpc,
             worker_rpc: send,
+            shutdown_ic,
         }));
         Ok(())
     }
@@ -775,6 +906,7 @@ async fn teardown_vm(&mut self) -> anyhow::Result<()> {
         }
         self.vm.take();
         self.vm_controller_events.take();
+        self.shutdown_waiter.take();
         if let Some((_, response)) = self.wait_vm_response.take() {
             response.send(Err(grpc_error(anyhow!("VM torn down"))));
         }
@@ -791,11 +923,113 @@ fn resume_vm(&mut self, vm: &Vm) -> impl Future<Output = anyhow::Result<()>> + u
         async move { recv.await.map(drop).context("resume failed") }
     }
 
+    fn save_vm(
+        &mut self,
+        request: vmservice::SaveVmRequest,
+    ) -> anyhow::Result<impl Future<Output = anyhow::Result<()>> + use<>> {
+        if request.directory.is_empty() {
+            anyhow::bail!("missing snapshot directory");
+        }
+        let controller = self.vm_controller.as_ref().context("vm not created")?;
+        let recv = controller.call(VmControllerRpc::SaveSnapshot, request.directory);
+        Ok(async move {
+            recv.await
+                .context("vm controller gone")?
+                .context("save failed")
+        })
+    }
+
+    fn shutdown_vm(
+        &mut self,
+        vm: &Vm,
+        request: vmservice::ShutdownVmRequest,
+    ) -> anyhow::Result<impl Future<Output = anyhow::Result<vmservice::ShutdownVmResponse>> + use<>>
+    {
+        use vmservice::shutdown_vm_response::ShutdownResult as VmShutdownResult;
+
+        if self.shutdown_waiter.is_some() {
+            bail!("shutdown already in flight");
+        }
+        let (halt_send, halt_recv) = mesh::oneshot();
+        self.shutdown_waiter = Some(halt_send);
+        let shutdown = vm.shutdown_ic.call(
+            ShutdownRpc::Shutdown,
+            ShutdownParams {
+                shutdown_type: ShutdownType::PowerOff,
+                force: request.force,
+            },
+        );
+        let power_off = self.power_off_send.clone();
+        let mut ctx =
+            mesh::CancelContext::new().with_timeout(Duration::from_secs(request.timeout_secs));
+        Ok(async move {
+            let result = match ctx.until_cancelled(shutdown).await {
+                Ok(Ok(ShutdownResult::Ok)) => match ctx.until_cancelled(halt_recv).await {
+                    Ok(Ok(())) => VmShutdownResult::Graceful,
+                    Ok(Err(_)) => bail!("VM torn down during shutdown"),
+                    Err(_) => VmShutdownResult::TimedOut,
+                },
+                Ok(Ok(result)) => {
+                    tracing::warn!(?result, "guest rejected shutdown request");
+                    VmShutdownResult::Failed
+                }
+                Ok(Err(err)) => {
+                    tracing::warn!(
+                        error = &err as &dyn std::error::Error,
+                        "failed to send shutdown request"
+                    );
+                    VmShutdownResult::Failed
+                }
+                Err(_) => VmShutdownResult::TimedOut,
+            };
+            if !matches!(result, VmShutdownResult::Graceful) {
+                let (done_send, done_recv) = mesh::oneshot();
+                power_off.send(done_send);
+                done_recv.await.context("vm service gone")?;
+            }
+            Ok(vmservice::ShutdownVmResponse {
+                result: result as i32,
+            })
+        })
+    }
+
+    fn changed_blocks_vm(
+        &mut self,
+        request: vmservice::ChangedBlocksVmRequest,
+    ) -> anyhow::Result<
+        impl Future<Output = anyhow::Result<vmservice::ChangedBlocksVmResponse>> + use<>,
+    > {
+        let controller = self.vm_controller.as_ref().context("vm not created")?;
+        let recv = controller.call(
+            VmControllerRpc::ChangedBlocks,
+            ChangedBlocksParams {
+                name: request.name,
+                reset: request.reset,
+            },
+        );
+        Ok(async move {
+            let changed = recv
+                .await
+                .context("vm controller gone")?
+                .context("failed to get changed blocks")?;
+            Ok(vmservice::ChangedBlocksVmResponse {
+                epoch: changed.epoch,
+                block_size: changed.block_size,
+                bitmap: changed.bitmap,
+            })
+        })
+    }
+
     fn handle_controller_event(&mut self, event: VmControllerEvent) {
         match event {
-            VmControllerEvent::GuestHalt(reason) => {
+            VmControllerEvent::GuestHalt { reason, power_off } => {
                 tracing::info!(%reason, "guest halted (via controller)");
                 self.halted = true;
+                if power_off {
+                    if let Some(waiter) = self.shutdown_waiter.take() {
+                        waiter.send(());
+                    }
+                }
                 if let Some((_, response)) = self.wait_vm_response.take() {
                     response.send(Ok(()));
                 }
@@ -818,6 +1052,7 @@ fn handle_controller_event(&mut self, event: VmControllerEvent) {
                 // task will be awaited during final cleanup.
                 self.vm.take();
                 self.vm_controller.take();
+                self.shutdown_waiter.take();
             }
             VmControllerEvent::VncWorkerStopped { error } => {
                 if let Some(err) = &error {
@@ -847,7 +1082,7 @@ fn modify_resource(
                     }
                     let scsi_rpc = vm.scsi_rpc.as_ref().context("no scsi controller")?.clone();
                     Ok(async move {
-                        let config = make_disk_config(disk).await?;
+                        let config = make_disk_config(disk, None).await?;
                         scsi_rpc
                             .call_failable(ScsiControllerRequest::AddDevice, config)
                             .await
@@ -882,6 +1117,31 @@ fn modify_resource(
     }
 }
 
+/// Returns the guest OSes and runtime resource changes supported by
+/// [`VmService`]. Resources that cannot be changed at runtime are not listed.
+/// This must be kept in sync with `create_vm` and `modify_resource`.
+fn capabilities() -> vmservice::CapabilitiesVmResponse {
+    use vmservice::capabilities_vm_response::Resource;
+    use vmservice::capabilities_vm_response::SupportedGuestOs;
+    use vmservice::capabilities_vm_response::SupportedResource;
+
+    let resource = |resource: Resource, add, remove| SupportedResource {
+        add,
+        remove,
+        update: false,
+        resource: resource as i32,
+    };
+
+    vmservice::CapabilitiesVmResponse {
+        supported_resources: vec![
+            resource(Resource::Scsi, true, true),
+            resource(Resource::VmNic, true, false),
+        ],
+        // Only direct Linux boot is supported.
+        supported_guest_os: vec![SupportedGuestOs::Linux as i32],
+    }
+}
+
 /// Returns the appropriate serial backend open function and a human-readable
 /// action verb for error messages, based on whether we should connect to an
 /// existing socket or bind a new listener.
@@ -926,15 +1186,81 @@ fn parse_nic_config(
                 .with_context(|| format!("failed to open TAP device '{}'", tap.name))?;
             net_backend_resources::tap::TapHandle { fd }.into_resource()
         }
-        Backend::Consomme(consomme) => net_backend_resources::consomme::ConsommeHandle {
-            cidr: if consomme.cidr.is_empty() {
+        Backend::Consomme(consomme) => {
+            use net_backend_resources::consomme::HostIpAddress;
+            use net_backend_resources::consomme::HostPortConfig;
+            use net_backend_resources::consomme::HostPortProtocol;
+
+            let ports = consomme
+                .port_forwards
+                .iter()
+                .map(|fwd| {
+                    let protocol = if fwd.protocol == vmservice::PortForwardProtocol::Tcp as i32 {
+                        HostPortProtocol::Tcp
+                    } else if fwd.protocol == vmservice::PortForwardProtocol::Udp as i32 {
+                        HostPortProtocol::Udp
+                    } else {
+                        anyhow::bail!("unsupported port forward protocol {}", fwd.protocol);
+                    };
+                    let host_address = if fwd.host_address.is_empty() {
+                        None
+                    } else {
+                        Some(HostIpAddress::from(
+                            fwd.host_address
+                                .parse::<std::net::IpAddr>()
+                                .context("invalid port forward host address")?,
+                        ))
+                    };
+                    Ok(HostPortConfig {
+                        protocol,
+                        host_address,
+                        host_port: fwd
+                            .host_port
+                            .try_into()
+                            .context("invalid port forward host port")?,
+                        guest_port: fwd
+                            .guest_port
+                            .try_into()
+                            .context("invalid port forward guest port")?,
+                    })
+                })
+                .collect::<anyhow::Result<_>>()?;
+            let nameservers = consomme
+                .dns_servers
+                .iter()
+                .map(|addr| {
+                    Ok(HostIpAddress::from(
+                        addr.parse::<std::net::IpAddr>()
+                            .context("invalid dns server address")?,
+                    ))
+                })
+                .collect::<anyhow::Result<_>>()?;
+
+            let guest_ip = if consomme.guest_address.is_empty() {
                 None
             } else {
-                Some(consomme.cidr)
-            },
-            ports: Vec::new(),
+                Some(
+                    consomme
+                        .guest_address
+                        .parse()
+                        .context("invalid guest address")?,
+                )
+            };
+
+            net_backend_resources::consomme::ConsommeHandle {
+                cidr: if consomme.cidr.is_empty() {
+                    None
+                } else {
+                    Some(consomme.cidr)
+                },
+                guest_ip,
+                ports,
+                nameservers,
+                tftp_root: None,
+                boot_file: None,
+            }
+            .into_resource()
         }
-        .into_resource(),
         _ => anyhow::bail!("unsupported backend"),
     };
     let cfg = NetvspHandle {
@@ -949,7 +1275,27 @@ fn parse_nic_config(
     Ok((DeviceVtl::Vtl0, cfg.into_resource()))
 }
 
-async fn make_disk_config(disk: vmservice::ScsiDisk) -> anyhow::Result<ScsiDeviceAndPath> {
+/// Builds the SCSI device for `disk`. Change tracking is only available when
+/// `change_trackers` is provided, i.e. at VM creation.
+async fn make_disk_config(
+    disk: vmservice::ScsiDisk,
+    change_trackers: Option<&mut ChangeTrackers>,
+) -> anyhow::Result<ScsiDeviceAndPath> {
+    let mut resource = open_disk_type(
+        disk.host_path.as_ref(),
+        OpenDiskOptions {
+            read_only: disk.read_only,
+            direct: false,
+            change_tracking: false,
+        },
+    )
+    .await
+    .with_context(|| format!("failed to open {}", disk.host_path))?;
+    if !disk.change_tracking_name.is_empty() {
+        resource = change_trackers
+            .context("change tracking is only supported at VM creation")?
+            .add(&disk.change_tracking_name, resource)?;
+    }
     Ok(ScsiDeviceAndPath {
         path: storvsp_resources::ScsiPath {
             path: 0,
@@ -957,15 +1303,7 @@ async fn make_disk_config(disk: vmservice::ScsiDisk) -> anyhow::Result<ScsiDevic
             lun: disk.lun.try_into().ok().context("lun value out of range")?,
         },
         device: SimpleScsiDiskHandle {
-            disk: open_disk_type(
-                disk.host_path.as_ref(),
-                OpenDiskOptions {
-                    read_only: disk.read_only,
-                    direct: false,
-                },
-            )
-            .await
-            .with_context(|| format!("failed to open {}", disk.host_path))?,
+            disk: resource,
             read_only: disk.read_only,
             parameters: Default::default(),
         }
@@ -5,12 +5,16 @@
 //! DiagInspector, vtl2_settings) and exposes them to the REPL via mesh RPC.
 
 use crate::DiagInspector;
+use crate::change_tracking::ChangeTrackers;
 use crate::meshworker::VmmMesh;
 use anyhow::Context;
+use disk_backend_resources::ChangedBlocks;
 use futures::FutureExt;
 use futures::StreamExt;
 use futures_concurrency::stream::Merge;
+use get_resources::ged::GuestEmulationRequest;
 use get_resources::ged::GuestServicingFlags;
+use get_resources::ged::ServicingFault;
 use guid::Guid;
 use inspect::InspectMut;
 use mesh::rpc::Rpc;
@@ -59,6 +63,9 @@ pub enum VmControllerRpc {
     DumpState(Rpc<String, Result<(), mesh::error::RemoteError>>),
     /// Service (update) the VTL2 firmware.
     ServiceVtl2(Rpc<ServiceVtl2Params, Result<u64, mesh::error::RemoteError>>),
+    /// Query, and optionally reset, the changed blocks of a change tracking
+    /// disk.
+    ChangedBlocks(Rpc<ChangedBlocksParams, Result<ChangedBlocks, mesh::error::RemoteError>>),
     /// Stop the VM and quit.
     Quit,
 }
@@ -91,6 +98,16 @@ pub struct ServiceVtl2Params {
     pub igvm: Option<String>,
     pub nvme_keepalive: bool,
     pub mana_keepalive: bool,
+    /// A host failure to simulate during servicing.
+    pub fault: Option<ServicingFault>,
+}
+
+#[derive(mesh::MeshPayload)]
+pub struct ChangedBlocksParams {
+    /// The name of the change tracking disk.
+    pub name: String,
+    /// Whether to start a new epoch after returning the changed blocks.
+    pub reset: bool,
 }
 
 /// Events sent from the VmController to the REPL.
@@ -101,7 +118,11 @@ pub enum VmControllerEvent {
     /// The VNC worker stopped or failed.
     VncWorkerStopped { error: Option<String> },
     /// The guest halted.
-    GuestHalt(String),
+    GuestHalt {
+        reason: String,
+        /// Whether the guest powered off.
+        power_off: bool,
+    },
 }
 
 /// Owns exclusive VM resources and services RPCs from the REPL.
@@ -113,6 +134,7 @@ pub struct VmController {
     pub(crate) diag_inspector: Option<DiagInspector>,
     pub(crate) vtl2_settings: Option<vtl2_settings_proto::Vtl2Settings>,
     pub(crate) ged_rpc: Option<mesh::Sender<get_resources::ged::GuestEmulationRequest>>,
+    pub(crate) change_trackers: ChangeTrackers,
     pub(crate) vm_rpc: mesh::Sender<VmRpc>,
     pub(crate) paravisor_diag: Option<Arc<diag_client::DiagClient>>,
     pub(crate) igvm_path: Option<PathBuf>,
@@ -231,7 +253,10 @@ enum Event {
                 },
                 Event::Halt(reason) => {
                     tracing::info!(?reason, "guest halted");
-                    event_send.send(VmControllerEvent::GuestHalt(format!("{reason:?}")));
+                    event_send.send(VmControllerEvent::GuestHalt {
+                        reason: format!("{reason:?}"),
+                        power_off: matches!(reason, vmm_core_defs::HaltReason::PowerOff),
+                    });
                 }
             }
         }
@@ -318,6 +343,14 @@ async fn handle_rpc(&mut self, rpc: VmControllerRpc, quit: &mut bool) {
                 let result = self.handle_service_vtl2(params).await;
                 req.complete(result.map_err(mesh::error::RemoteError::new));
             }
+            VmControllerRpc::ChangedBlocks(req) => {
+                let (params, req) = req.split();
+                let result = self
+                    .change_trackers
+                    .changed_blocks(&params.name, params.reset)
+                    .await;
+                req.complete(result.map_err(mesh::error::RemoteError::new));
+            }
             VmControllerRpc::Quit => {
                 tracing::info!("quitting");
                 self.vm_worker.stop();
@@ -414,6 +447,10 @@ async fn handle_save_snapshot(&self, dir: &Path) -> anyhow::Result<()> {
             &saved_state_bytes,
             memory_file_path,
         )?;
+        self.change_trackers
+            .save_snapshot(dir)
+            .await
+            .context("failed to save changed blocks")?;
 
         // VM stays paused. Do NOT resume.
         Ok(())
@@ -463,6 +500,11 @@ async fn handle_service_vtl2(&self, params: ServiceVtl2Params) -> anyhow::Result
             let file = fs_err::File::open(igvm)?;
             start = Instant::now();
             let ged_rpc = self.ged_rpc.as_ref().context("no GED")?;
+            if let Some(fault) = params.fault {
+                ged_rpc
+                    .call(GuestEmulationRequest::InjectServicingFault, Some(fault))
+                    .await?;
+            }
             openvmm_helpers::underhill::save_underhill(
                 &self.vm_rpc,
                 ged_rpc,
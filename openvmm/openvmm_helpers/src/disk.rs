@@ -29,6 +29,9 @@ pub struct OpenDiskOptions {
     pub read_only: bool,
     /// Bypass the OS page cache for direct disk I/O.
     pub direct: bool,
+    /// Enable resilient change tracking (RCT). Only supported for VHDX files
+    /// opened on Windows.
+    pub change_tracking: bool,
 }
 
 /// Opens the resources needed for using a disk from a file at `path`.
@@ -47,6 +50,9 @@ pub async fn open_disk_type(
         };
         Ok(())
     };
+    if options.change_tracking && path.extension().and_then(|s| s.to_str()) != Some("vhdx") {
+        anyhow::bail!("change tracking is only supported for .vhdx files");
+    }
     Ok(match path.extension().and_then(|s| s.to_str()) {
         Some("vhd") => {
             let file = std::fs::OpenOptions::new()
@@ -82,10 +88,14 @@ pub async fn open_disk_type(
         Some("vhdx") => {
             #[cfg(windows)]
             {
+                let mut vhd_options = disk_vhdmp::VhdmpDisk::options()
+                    .read_only(read_only)
+                    .cached_io(!options.direct);
+                if options.change_tracking {
+                    vhd_options = vhd_options.change_tracking(true);
+                }
                 Resource::new(disk_vhdmp::OpenVhdmpDiskConfig(
-                    disk_vhdmp::VhdmpDisk::options()
-                        .read_only(read_only)
-                        .cached_io(!options.direct)
+                    vhd_options
                         .open(path)
                         .with_context(|| disk_open_error(path, "failed to open"))?,
                 ))
@@ -118,6 +128,9 @@ pub fn create_disk_type(
     size: u64,
     options: OpenDiskOptions,
 ) -> anyhow::Result<Resource<DiskHandleKind>> {
+    if options.change_tracking {
+        anyhow::bail!("change tracking is not supported for created disks");
+    }
     Ok(match path.extension().and_then(|s| s.to_str()) {
         Some("vhd") | Some("vmgs") => {
             if options.direct {
@@ -5,19 +5,40 @@
 //! points.
 
 use hypervisor_resources::HypervisorKind;
+use std::fmt::Write as _;
 use vm_resource::Resource;
 
 /// Returns a [`Resource<HypervisorKind>`] for the first available hypervisor
 /// backend.
 ///
-/// Backends are checked in registration order (highest priority first).
+/// Backends are checked in registration order (highest priority first). The
+/// selected backend is logged. If no backend is available, the error describes
+/// why each one was skipped and how to make it available.
 pub fn choose_hypervisor() -> anyhow::Result<Resource<HypervisorKind>> {
+    let mut skipped = String::new();
     for probe in hypervisor_resources::probes() {
-        if let Some(resource) = probe.try_new_resource()? {
-            return Ok(resource);
+        let reason = match probe.try_new_resource() {
+            Ok(Some(resource)) => {
+                tracing::info!(hypervisor = probe.name(), "selected hypervisor");
+                return Ok(resource);
+            }
+            Ok(None) => "not available".to_string(),
+            Err(err) => format!("{err:#}"),
+        };
+        tracing::debug!(
+            hypervisor = probe.name(),
+            reason = reason.as_str(),
+            "skipping hypervisor"
+        );
+        write!(skipped, "\n  {}: {reason}", probe.name()).unwrap();
+        if let Some(remediation) = probe.remediation() {
+            write!(skipped, "; to use it, {remediation}").unwrap();
         }
     }
-    anyhow::bail!("no hypervisor available");
+    if skipped.is_empty() {
+        anyhow::bail!("no hypervisor available: no hypervisor backends are enabled in this build");
+    }
+    anyhow::bail!("no hypervisor available:{skipped}");
 }
 
 /// Parses a hypervisor specifier of the form `name` or `name:key=val,key,...`.
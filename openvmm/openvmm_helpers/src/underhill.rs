@@ -6,6 +6,7 @@
 use anyhow::Context;
 use get_resources::ged::GuestEmulationRequest;
 use get_resources::ged::GuestServicingFlags;
+use get_resources::ged::ServicingFault;
 use mesh::rpc::RpcSend;
 use openvmm_defs::rpc::VmRpc;
 
@@ -47,20 +48,68 @@ pub async fn restore_underhill(
     vm_send: &mesh::Sender<VmRpc>,
     send: &mesh::Sender<GuestEmulationRequest>,
 ) -> anyhow::Result<()> {
+    if send
+        .call(
+            GuestEmulationRequest::TakeServicingFault,
+            ServicingFault::FailActivation,
+        )
+        .await
+        .context("failed to check for servicing fault")?
+    {
+        tracing::debug!("injecting fault, clearing staged IGVM file");
+        let _ = vm_send.call(VmRpc::CompleteReloadIgvm, false).await;
+        anyhow::bail!("injected fault: failed to activate the new IGVM file");
+    }
+
     // Reload the IGVM file and reset VTL2 state.
     tracing::debug!("reloading IGVM file");
-    vm_send
+    if let Err(err) = vm_send
         .call_failable(VmRpc::CompleteReloadIgvm, true)
         .await
-        .context("failed to reload VTL2 firmware")?;
+        .context("failed to reload VTL2 firmware")
+    {
+        // The new VTL2 never ran, so the saved state has not been consumed.
+        rollback_underhill(vm_send, send).await?;
+        return Err(err);
+    }
 
     // Wait for VTL0 to start.
     //
     // TODO: event driven, cancellable.
     tracing::debug!("waiting for VTL0 to start");
-    send.call_failable(GuestEmulationRequest::WaitForVtl0Start, ())
+    if let Err(err) = send
+        .call_failable(GuestEmulationRequest::WaitForVtl0Start, ())
         .await
-        .context("vtl0 start failed")?;
+        .context("vtl0 start failed")
+    {
+        // If the new VTL2 failed to restore the saved state, the previous
+        // VTL2 can restore it instead.
+        if send
+            .call(GuestEmulationRequest::RearmFailedRestore, ())
+            .await
+            .context("failed to check for a failed restore")?
+        {
+            rollback_underhill(vm_send, send).await?;
+        }
+        return Err(err);
+    }
+
+    Ok(())
+}
 
+/// Reloads the previous Underhill version after servicing failed, so that it
+/// restores the saved state and the guest resumes.
+async fn rollback_underhill(
+    vm_send: &mesh::Sender<VmRpc>,
+    send: &mesh::Sender<GuestEmulationRequest>,
+) -> anyhow::Result<()> {
+    tracing::warn!("servicing failed, rolling back to the previous IGVM file");
+    vm_send
+        .call_failable(VmRpc::RollbackReloadIgvm, ())
+        .await
+        .context("failed to roll back VTL2 firmware")?;
+    send.call_failable(GuestEmulationRequest::WaitForVtl0Start, ())
+        .await
+        .context("vtl0 start failed after rollback")?;
     Ok(())
 }
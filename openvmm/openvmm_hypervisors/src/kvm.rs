@@ -35,8 +35,21 @@ fn new_resource(&self, params: &[(&str, &str)]) -> anyhow::Result<Resource<Hyper
         let kvm = open_kvm().context("KVM is not available")?;
         Ok(KvmHandle { kvm: kvm.into() }.into_resource())
     }
+
+    fn remediation(&self) -> Option<&str> {
+        Some(REMEDIATION)
+    }
 }
 
+#[cfg(guest_arch = "x86_64")]
+const REMEDIATION: &str = "enable virtualization in the firmware settings, load the kvm_intel or \
+    kvm_amd kernel module, and make sure the current user can read and write /dev/kvm (e.g. by \
+    adding it to the kvm group)";
+
+#[cfg(guest_arch = "aarch64")]
+const REMEDIATION: &str = "boot the host kernel at EL2 with KVM enabled, and make sure the \
+    current user can read and write /dev/kvm (e.g. by adding it to the kvm group)";
+
 fn open_kvm() -> std::io::Result<fs_err::File> {
     fs_err::File::options()
         .read(true)
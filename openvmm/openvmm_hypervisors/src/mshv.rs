@@ -36,4 +36,10 @@ fn new_resource(&self, params: &[(&str, &str)]) -> anyhow::Result<Resource<Hyper
             mshv: fs_err::File::open("/dev/mshv")?.into(),
         }))
     }
+
+    fn remediation(&self) -> Option<&str> {
+        Some(
+            "run in a Linux root partition on the Microsoft hypervisor, with the mshv driver loaded",
+        )
+    }
 }
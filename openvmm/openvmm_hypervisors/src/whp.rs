@@ -45,6 +45,10 @@ fn new_resource(&self, params: &[(&str, &str)]) -> anyhow::Result<Resource<Hyper
         anyhow::ensure!(virt_whp::is_available()?, "WHP is not available");
         Ok(Resource::new(handle))
     }
+
+    fn remediation(&self) -> Option<&str> {
+        Some("enable the Windows Hypervisor Platform optional feature and reboot")
+    }
 }
 
 fn parse_bool_param(key: &str, val: &str) -> anyhow::Result<bool> {
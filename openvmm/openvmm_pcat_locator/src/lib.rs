@@ -97,11 +97,17 @@ fn translate_path(path: &Path) -> anyhow::Result<PathBuf> {
     let file_path = if cfg!(windows) {
         path.into()
     } else if cfg!(target_os = "linux") {
-        // WSL
-        let output = Command::new("wslpath")
-            .arg(path)
-            .output()
-            .context("Failed to translate path to windows. Are you not on WSL?")?;
+        // WSL. Native Linux hosts have no default location, so the firmware
+        // path must be specified explicitly.
+        let output = Command::new("wslpath").arg(path).output().context(
+            "No path specified for firmware, and the default Windows path can only be found under WSL",
+        )?;
+        anyhow::ensure!(
+            output.status.success(),
+            "Failed to translate {} with wslpath: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
 
         String::from_utf8_lossy(&output.stdout).trim().into()
     } else {
@@ -59,6 +59,8 @@
     net_consomme::resolver::ConsommeResolver,
     #[cfg(all(feature = "net_tap", target_os = "linux"))]
     net_tap::resolver::TapResolver,
+    #[cfg(all(feature = "net_af_xdp", target_os = "linux"))]
+    net_af_xdp::resolver::AfXdpResolver,
     #[cfg(windows)]
     net_dio::resolver::DioResolver,
 
@@ -71,6 +73,7 @@
     disk_blockdevice::resolver::StaticBlockDeviceResolver,
     disk_prwrap::DiskWithReservationsResolver,
     disk_delay::resolver::DelayDiskResolver,
+    disk_change_tracking::resolver::ChangeTrackingDiskResolver,
     disk_vhd1::Vhd1Resolver,
     #[cfg(windows)]
     disk_vhdmp::VhdmpDiskResolver,
@@ -93,6 +96,7 @@
     scsidisk::resolver::SimpleScsiResolver,
 
     // Virtio devices
+    virtio_balloon::resolver::VirtioBalloonResolver,
     virtio_blk::resolver::VirtioBlkResolver,
     virtio_console::resolver::VirtioConsoleResolver,
     #[cfg(any(windows, target_os = "linux"))]
@@ -110,6 +114,7 @@
     guest_crash_device::resolver::GuestCrashDeviceResolver,
     guest_emulation_device::resolver::GuestEmulationDeviceResolver,
     guest_emulation_log::resolver::GuestEmulationLogResolver,
+    hyperv_dm::resolver::DynamicMemoryResolver,
     hyperv_ic::resolver::KvpIcResolver,
     hyperv_ic::resolver::ShutdownIcResolver,
     hyperv_ic::resolver::TimesyncIcResolver,
@@ -109,6 +109,7 @@ pub fn main() -> anyhow::Result<()> {
                             Resource::new(virtio_handle),
                             VirtioResolveInput {
                                 driver_source: &driver_source,
+                                discard_ram: None,
                             },
                         )
                         .await
@@ -348,6 +348,7 @@ async fn make_disk_resource(
             openvmm_helpers::disk::OpenDiskOptions {
                 read_only: false,
                 direct: false,
+                change_tracking: false,
             },
         )
         .await
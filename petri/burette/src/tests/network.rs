@@ -515,6 +515,7 @@ fn add_virtio_tap_nic(config: &mut openvmm_defs::config::Config, tap_fd: std::os
                     max_queues: None,
                     mac_address: TAP_MAC_ADDRESS,
                     endpoint,
+                    busy_poll: false,
                 }
                 .into_resource(),
             )
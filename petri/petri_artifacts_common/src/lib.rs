@@ -85,6 +85,17 @@ pub enum InitialRebootCondition {
         WithTpm,
     }
 
+    impl InitialRebootCondition {
+        /// Returns whether the guest is expected to reboot automatically on
+        /// first boot, given whether the VM has a TPM.
+        pub fn applies(&self, tpm_enabled: bool) -> bool {
+            match self {
+                Self::Always => true,
+                Self::WithTpm => tpm_enabled,
+            }
+        }
+    }
+
     /// Quirks needed to boot a guest, allowing for differences based on backend
     #[derive(Default, Clone, Debug)]
     pub struct GuestQuirks {
@@ -21,6 +21,7 @@
 pub mod requirements;
 mod test;
 mod tracing;
+mod uefi_test_results;
 mod vm;
 mod worker;
 
@@ -37,13 +38,22 @@
 pub use petri_artifacts_core::TestArtifactRequirements;
 pub use petri_artifacts_core::TestArtifacts;
 pub use pipette_client as pipette;
+pub use test::ComparisonSide;
+pub use test::ComparisonTest;
+pub use test::MetricComparison;
+pub use test::MetricDirection;
 pub use test::PetriTestParams;
 pub use test::RunTest;
 pub use test::SimpleTest;
+pub use test::StressStats;
+pub use test::StressTest;
+pub use test::TestBudget;
 pub use test::TestCase;
 pub use test::test_macro_support;
 pub use test::test_main;
 pub use tracing::*;
+pub use uefi_test_results::UefiTestResults;
+pub use uefi_test_results::UefiTestStatus;
 pub use vm::*;
 
 use jiff::Timestamp;
@@ -33,6 +33,9 @@ pub mod test_macro_support {
 use petri_artifacts_core::RemoteAccess;
 use std::panic::AssertUnwindSafe;
 use std::panic::catch_unwind;
+use std::path::Path;
+use std::time::Duration;
+use std::time::Instant;
 use test_macro_support::TESTS;
 
 /// Defines a single test from a value that implements [`RunTest`].
@@ -71,6 +74,27 @@ macro_rules! unstable_test {
     };
 }
 
+/// Defines a test that runs `$f` repeatedly with [`StressTest`], up to
+/// `iterations` times and for at most `max_duration`.
+#[macro_export]
+macro_rules! stress_test {
+    ($f:ident, $req:expr, iterations = $iterations:expr, max_duration = $max_duration:expr) => {
+        $crate::multitest!(vec![
+            $crate::StressTest::new(
+                stringify!($f),
+                $iterations,
+                $req,
+                $f,
+                None,
+                false,
+                ::petri::RemoteAccess::LocalOnly
+            )
+            .with_max_duration($max_duration)
+            .into()
+        ]);
+    };
+}
+
 /// Defines a set of tests from a [`TestCase`].
 #[macro_export]
 macro_rules! multitest {
@@ -150,6 +174,7 @@ fn run(
         let logger = try_init_tracing(output_dir, tracing::level_filters::LevelFilter::DEBUG)
             .context("failed to initialize tracing")?;
         let mut post_test_hooks = Vec::new();
+        let start = Instant::now();
 
         // Catch test panics in order to cleanly log the panic result. Without
         // this, `libtest_mimic` will report the panic to stdout and fail the
@@ -179,6 +204,31 @@ fn run(
             };
             Err(err)
         });
+        let r = match self.test.0.budget() {
+            Some(budget) => {
+                let usage = ResourceUsage::measure(start.elapsed(), logger.output_dir());
+                usage.write_properties(&logger);
+                let violations = budget.violations(&usage);
+                if violations.is_empty() {
+                    r
+                } else {
+                    let violations = violations.join("; ");
+                    logger.write_property("budget.violations", &violations);
+                    match r {
+                        Ok(()) => Err(anyhow::anyhow!(
+                            "test exceeded its resource budget: {violations}"
+                        )),
+                        Err(err) => {
+                            // Keep the test's own failure as the result, but
+                            // don't lose the violation.
+                            tracing::error!(%violations, "test exceeded its resource budget");
+                            Err(err)
+                        }
+                    }
+                }
+            }
+            None => r,
+        };
         logger.log_test_result(&name, &r, self.test.0.unstable());
 
         for hook in post_test_hooks {
@@ -242,6 +292,10 @@ pub trait RunTest: Send {
     fn host_requirements(&self) -> Option<&TestCaseRequirements>;
     /// Whether this test is unstable
     fn unstable(&self) -> bool;
+    /// Returns the resource budget of the current test, if any.
+    fn budget(&self) -> Option<&TestBudget> {
+        None
+    }
 }
 
 trait DynRunTest: Send {
@@ -250,6 +304,7 @@ trait DynRunTest: Send {
     fn run(&self, params: PetriTestParams<'_>, artifacts: &TestArtifacts) -> anyhow::Result<()>;
     fn host_requirements(&self) -> Option<&TestCaseRequirements>;
     fn unstable(&self) -> bool;
+    fn budget(&self) -> Option<&TestBudget>;
 }
 
 impl<T: RunTest> DynRunTest for T {
@@ -277,6 +332,150 @@ fn host_requirements(&self) -> Option<&TestCaseRequirements> {
     fn unstable(&self) -> bool {
         self.unstable()
     }
+
+    fn budget(&self) -> Option<&TestBudget> {
+        self.budget()
+    }
+}
+
+/// Host resource limits for a test, enforced by the test harness.
+///
+/// The harness measures the test's usage once it completes. A test that
+/// exceeds any of its limits fails, even if the test itself passed, and the
+/// measured usage is added to the test's JUnit properties.
+#[derive(Debug, Clone, Default)]
+pub struct TestBudget {
+    /// The maximum peak resident memory of the test process and its child
+    /// processes (such as the VMM), in bytes.
+    ///
+    /// Only measured on Unix hosts.
+    pub max_host_memory_bytes: Option<u64>,
+    /// The maximum total size of the files the test leaves in its output
+    /// directory, in bytes.
+    pub max_scratch_disk_bytes: Option<u64>,
+    /// The maximum wall-clock duration of the test.
+    ///
+    /// The test is not interrupted when it runs over, but it fails once it
+    /// completes.
+    pub max_duration: Option<Duration>,
+}
+
+impl TestBudget {
+    /// Returns a description of each limit that `usage` exceeds.
+    fn violations(&self, usage: &ResourceUsage) -> Vec<String> {
+        let Self {
+            max_host_memory_bytes,
+            max_scratch_disk_bytes,
+            max_duration,
+        } = *self;
+        let mut violations = Vec::new();
+        if let Some(max) = max_host_memory_bytes {
+            match usage.peak_host_memory_bytes {
+                Some(peak) if peak > max => violations.push(format!(
+                    "peak host memory {peak} bytes exceeds budget of {max} bytes"
+                )),
+                Some(_) => {}
+                None => tracing::warn!("host memory budget cannot be measured on this host"),
+            }
+        }
+        if let Some(max) = max_scratch_disk_bytes {
+            match usage.scratch_disk_bytes {
+                Some(used) if used > max => violations.push(format!(
+                    "scratch disk usage {used} bytes exceeds budget of {max} bytes"
+                )),
+                Some(_) => {}
+                None => tracing::warn!("failed to measure scratch disk usage"),
+            }
+        }
+        if let Some(max) = max_duration {
+            if usage.duration > max {
+                violations.push(format!(
+                    "duration {:?} exceeds budget of {max:?}",
+                    usage.duration
+                ));
+            }
+        }
+        violations
+    }
+}
+
+/// The host resources used by a test run.
+#[derive(Debug)]
+struct ResourceUsage {
+    duration: Duration,
+    peak_host_memory_bytes: Option<u64>,
+    scratch_disk_bytes: Option<u64>,
+}
+
+impl ResourceUsage {
+    fn measure(duration: Duration, output_dir: &Path) -> Self {
+        let scratch_disk_bytes = dir_size(output_dir)
+            .inspect_err(|err| {
+                tracing::warn!(
+                    error = err as &dyn std::error::Error,
+                    "failed to measure output directory size"
+                )
+            })
+            .ok();
+        Self {
+            duration,
+            peak_host_memory_bytes: peak_host_memory_bytes(),
+            scratch_disk_bytes,
+        }
+    }
+
+    fn write_properties(&self, logger: &PetriLogSource) {
+        let Self {
+            duration,
+            peak_host_memory_bytes,
+            scratch_disk_bytes,
+        } = *self;
+        logger.write_property("budget.duration_secs", duration.as_secs_f64());
+        if let Some(peak) = peak_host_memory_bytes {
+            logger.write_property("budget.host_memory_bytes", peak);
+        }
+        if let Some(used) = scratch_disk_bytes {
+            logger.write_property("budget.scratch_disk_bytes", used);
+        }
+    }
+}
+
+/// Returns the peak resident memory of this process plus that of its largest
+/// reaped child process.
+///
+/// The kernel only tracks the largest child, so this is an estimate when a
+/// test launches several VMs.
+#[cfg(unix)]
+fn peak_host_memory_bytes() -> Option<u64> {
+    let max_rss = |who| {
+        // SAFETY: `rusage` is plain old data, so all zeroes is a valid value.
+        let mut usage = unsafe { std::mem::zeroed::<libc::rusage>() };
+        // SAFETY: `usage` is valid for writes.
+        let r = unsafe { libc::getrusage(who, &mut usage) };
+        (r == 0).then_some(usage.ru_maxrss as u64)
+    };
+    // `ru_maxrss` is in bytes on macOS and in KiB elsewhere.
+    let scale = if cfg!(target_os = "macos") { 1 } else { 1024 };
+    Some((max_rss(libc::RUSAGE_SELF)? + max_rss(libc::RUSAGE_CHILDREN)?) * scale)
+}
+
+#[cfg(not(unix))]
+fn peak_host_memory_bytes() -> Option<u64> {
+    None
+}
+
+/// Returns the total size of the files under `path`.
+fn dir_size(path: &Path) -> std::io::Result<u64> {
+    let mut size = 0;
+    for entry in fs_err::read_dir(path)? {
+        let entry = entry?;
+        size += if entry.file_type()?.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            entry.metadata()?.len()
+        };
+    }
+    Ok(size)
 }
 
 /// Parameters passed to a [`RunTest`] when it is run.
@@ -324,6 +523,7 @@ pub struct SimpleTest<A, F> {
     pub host_requirements: Option<TestCaseRequirements>,
     unstable: bool,
     remote_policy: RemoteAccess,
+    budget: Option<TestBudget>,
 }
 
 impl<A, AR, F, E> SimpleTest<A, F>
@@ -349,8 +549,15 @@ pub fn new(
             host_requirements,
             unstable,
             remote_policy,
+            budget: None,
         }
     }
+
+    /// Fails the test if it uses more host resources than `budget` allows.
+    pub fn with_budget(mut self, budget: TestBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
 }
 
 impl<A, AR, F, E> RunTest for SimpleTest<A, F>
@@ -381,6 +588,415 @@ fn host_requirements(&self) -> Option<&TestCaseRequirements> {
     fn unstable(&self) -> bool {
         self.unstable
     }
+
+    fn budget(&self) -> Option<&TestBudget> {
+        self.budget.as_ref()
+    }
+}
+
+/// Which set of artifacts a [`ComparisonTest`] is running against.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComparisonSide {
+    /// The reference artifacts, such as a previously released OpenHCL IGVM
+    /// file.
+    Baseline,
+    /// The artifacts being evaluated, usually the ones built from the current
+    /// tree.
+    Candidate,
+}
+
+/// Whether larger or smaller values of a [`ComparisonTest`] metric are better.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricDirection {
+    /// Larger values are better, as for throughput.
+    HigherIsBetter,
+    /// Smaller values are better, as for latency.
+    LowerIsBetter,
+}
+
+/// The result of a [`ComparisonTest`], written to the test output as
+/// `perf_comparison.json`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct MetricComparison {
+    /// The name of the metric.
+    pub metric: String,
+    /// Whether larger or smaller values are better.
+    pub direction: MetricDirection,
+    /// The metric measured with the baseline artifacts.
+    pub baseline: f64,
+    /// The metric measured with the candidate artifacts.
+    pub candidate: f64,
+    /// `candidate - baseline`.
+    pub delta: f64,
+    /// How much worse the candidate is than the baseline, as a percentage of
+    /// the baseline. Negative values are improvements.
+    pub regression_percent: f64,
+}
+
+impl MetricComparison {
+    fn new(
+        metric: &str,
+        direction: MetricDirection,
+        baseline: f64,
+        candidate: f64,
+    ) -> anyhow::Result<Self> {
+        // A zero baseline makes the relative regression infinite or NaN, and
+        // NaN compares false against any threshold, so would always pass.
+        if baseline == 0.0 || !baseline.is_finite() || !candidate.is_finite() {
+            anyhow::bail!("cannot compare {metric}: baseline {baseline}, candidate {candidate}");
+        }
+        let delta = candidate - baseline;
+        let worse_by = match direction {
+            MetricDirection::HigherIsBetter => -delta,
+            MetricDirection::LowerIsBetter => delta,
+        };
+        Ok(Self {
+            metric: metric.to_owned(),
+            direction,
+            baseline,
+            candidate,
+            delta,
+            regression_percent: worse_by / baseline.abs() * 100.0,
+        })
+    }
+}
+
+/// A test that runs the same body against a baseline and a candidate set of
+/// artifacts, comparing a metric measured by the body.
+///
+/// The body runs against the baseline first, then against the candidate. The
+/// comparison is logged, attached to the test results, and added to the
+/// test's JUnit properties, and the test fails if the candidate regresses by
+/// more than the configured threshold.
+pub struct ComparisonTest<A, F> {
+    leaf_name: &'static str,
+    metric: &'static str,
+    direction: MetricDirection,
+    resolve: A,
+    run: F,
+    /// Optional test requirements
+    pub host_requirements: Option<TestCaseRequirements>,
+    remote_policy: RemoteAccess,
+    max_regression_percent: Option<f64>,
+}
+
+impl<A, AR, F, E> ComparisonTest<A, F>
+where
+    A: 'static + Send + Fn(&ArtifactResolver<'_>, ComparisonSide) -> Option<AR>,
+    F: 'static + Send + Fn(PetriTestParams<'_>, AR) -> Result<f64, E>,
+    E: Into<anyhow::Error>,
+{
+    /// Returns a new test with the given `leaf_name`, measuring `metric`.
+    ///
+    /// `resolve` is called once for each [`ComparisonSide`] to get the
+    /// artifacts for that side, and `run` is called with each side's
+    /// artifacts and returns the measured metric.
+    pub fn new(
+        leaf_name: &'static str,
+        metric: &'static str,
+        direction: MetricDirection,
+        resolve: A,
+        run: F,
+        host_requirements: Option<TestCaseRequirements>,
+        remote_policy: RemoteAccess,
+    ) -> Self {
+        ComparisonTest {
+            leaf_name,
+            metric,
+            direction,
+            resolve,
+            run,
+            host_requirements,
+            remote_policy,
+            max_regression_percent: None,
+        }
+    }
+
+    /// Fails the test if the candidate is worse than the baseline by more
+    /// than `percent` percent.
+    pub fn with_max_regression_percent(mut self, percent: f64) -> Self {
+        self.max_regression_percent = Some(percent);
+        self
+    }
+}
+
+impl<A, AR, F, E> RunTest for ComparisonTest<A, F>
+where
+    A: 'static + Send + Fn(&ArtifactResolver<'_>, ComparisonSide) -> Option<AR>,
+    F: 'static + Send + Fn(PetriTestParams<'_>, AR) -> Result<f64, E>,
+    E: Into<anyhow::Error>,
+{
+    type Artifacts = (AR, AR);
+
+    fn leaf_name(&self) -> &str {
+        self.leaf_name
+    }
+
+    fn resolve(&self, mut resolver: ArtifactResolver<'_>) -> Option<Self::Artifacts> {
+        resolver.set_remote_policy(self.remote_policy);
+        Some((
+            (self.resolve)(&resolver, ComparisonSide::Baseline)?,
+            (self.resolve)(&resolver, ComparisonSide::Candidate)?,
+        ))
+    }
+
+    fn run(&self, params: PetriTestParams<'_>, artifacts: Self::Artifacts) -> anyhow::Result<()> {
+        let (baseline_artifacts, candidate_artifacts) = artifacts;
+        let mut measure = |side: ComparisonSide, artifacts: AR| {
+            tracing::info!(?side, metric = self.metric, "running comparison side");
+            let value = (self.run)(
+                PetriTestParams {
+                    test_name: params.test_name,
+                    logger: params.logger,
+                    post_test_hooks: &mut *params.post_test_hooks,
+                },
+                artifacts,
+            )
+            .map_err(Into::into)
+            .with_context(|| format!("{side:?} run failed"))?;
+            tracing::info!(?side, metric = self.metric, value, "measured metric");
+            anyhow::Ok(value)
+        };
+        let baseline = measure(ComparisonSide::Baseline, baseline_artifacts)?;
+        let candidate = measure(ComparisonSide::Candidate, candidate_artifacts)?;
+
+        let comparison = MetricComparison::new(self.metric, self.direction, baseline, candidate)?;
+        tracing::info!(
+            metric = self.metric,
+            baseline,
+            candidate,
+            delta = comparison.delta,
+            regression_percent = comparison.regression_percent,
+            "comparison complete"
+        );
+        params.logger.write_attachment(
+            "perf_comparison.json",
+            serde_json::to_vec_pretty(&comparison)?.as_slice(),
+        )?;
+        for (name, value) in [
+            ("baseline", baseline),
+            ("candidate", candidate),
+            ("delta", comparison.delta),
+            ("regression_percent", comparison.regression_percent),
+        ] {
+            params
+                .logger
+                .write_property(&format!("{}.{name}", self.metric), value);
+        }
+
+        if let Some(max) = self.max_regression_percent {
+            if comparison.regression_percent > max {
+                anyhow::bail!(
+                    "{} regressed by {:.2}% (baseline {baseline}, candidate {candidate}), more than the allowed {max}%",
+                    self.metric,
+                    comparison.regression_percent,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn host_requirements(&self) -> Option<&TestCaseRequirements> {
+        self.host_requirements.as_ref()
+    }
+
+    fn unstable(&self) -> bool {
+        false
+    }
+}
+
+/// The results of a [`StressTest`], written to the test output as
+/// `stress_stats.json`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct StressStats {
+    /// The number of iterations that ran.
+    pub iterations: u32,
+    /// The number of iterations that failed.
+    pub failures: u32,
+    /// Whether the test stopped early because it reached its maximum
+    /// duration.
+    pub time_limited: bool,
+    /// The duration of the fastest iteration, in seconds.
+    pub min_secs: f64,
+    /// The duration of the slowest iteration, in seconds.
+    pub max_secs: f64,
+    /// The mean duration of an iteration, in seconds.
+    pub mean_secs: f64,
+    /// The error from the first failing iteration, if any.
+    pub first_failure: Option<String>,
+}
+
+impl StressStats {
+    fn new(
+        durations: &[Duration],
+        failures: u32,
+        time_limited: bool,
+        first_failure: Option<String>,
+    ) -> Self {
+        let secs = || durations.iter().map(Duration::as_secs_f64);
+        let iterations = durations.len() as u32;
+        Self {
+            iterations,
+            failures,
+            time_limited,
+            min_secs: secs().fold(f64::INFINITY, f64::min),
+            max_secs: secs().fold(0.0, f64::max),
+            mean_secs: secs().sum::<f64>() / iterations as f64,
+            first_failure,
+        }
+    }
+}
+
+/// A test that runs the same body repeatedly, to soak race-prone scenarios
+/// and collect flake statistics.
+///
+/// Each iteration gets freshly resolved artifacts, so the body should create
+/// its own VM(s); to stress a single VM, loop within the body of a
+/// [`SimpleTest`] instead. All iterations run even if some fail, and the test
+/// fails at the end if any iteration failed.
+pub struct StressTest<A, F> {
+    leaf_name: &'static str,
+    resolve: A,
+    run: F,
+    /// Optional test requirements
+    pub host_requirements: Option<TestCaseRequirements>,
+    unstable: bool,
+    remote_policy: RemoteAccess,
+    iterations: u32,
+    max_duration: Option<Duration>,
+}
+
+impl<A, AR, F, E> StressTest<A, F>
+where
+    A: 'static + Send + Fn(&ArtifactResolver<'_>) -> Option<AR>,
+    F: 'static + Send + Fn(PetriTestParams<'_>, AR) -> Result<(), E>,
+    E: Into<anyhow::Error>,
+{
+    /// Returns a new test with the given `leaf_name`, `resolve`, and `run`
+    /// functions that runs `run` up to `iterations` times.
+    pub fn new(
+        leaf_name: &'static str,
+        iterations: u32,
+        resolve: A,
+        run: F,
+        host_requirements: Option<TestCaseRequirements>,
+        unstable: bool,
+        remote_policy: RemoteAccess,
+    ) -> Self {
+        assert!(iterations > 0, "stress tests must run at least once");
+        StressTest {
+            leaf_name,
+            resolve,
+            run,
+            host_requirements,
+            unstable,
+            remote_policy,
+            iterations,
+            max_duration: None,
+        }
+    }
+
+    /// Stops starting new iterations once the test has run for `duration`.
+    /// At least one iteration always runs.
+    pub fn with_max_duration(mut self, duration: Duration) -> Self {
+        self.max_duration = Some(duration);
+        self
+    }
+}
+
+impl<A, AR, F, E> RunTest for StressTest<A, F>
+where
+    A: 'static + Send + Fn(&ArtifactResolver<'_>) -> Option<AR>,
+    F: 'static + Send + Fn(PetriTestParams<'_>, AR) -> Result<(), E>,
+    E: Into<anyhow::Error>,
+{
+    type Artifacts = Vec<AR>;
+
+    fn leaf_name(&self) -> &str {
+        self.leaf_name
+    }
+
+    fn resolve(&self, mut resolver: ArtifactResolver<'_>) -> Option<Self::Artifacts> {
+        resolver.set_remote_policy(self.remote_policy);
+        (0..self.iterations)
+            .map(|_| (self.resolve)(&resolver))
+            .collect()
+    }
+
+    fn run(&self, params: PetriTestParams<'_>, artifacts: Self::Artifacts) -> anyhow::Result<()> {
+        let start = Instant::now();
+        let mut durations = Vec::new();
+        let mut failures = 0;
+        let mut first_failure = None;
+        let mut time_limited = false;
+        for (iteration, artifacts) in artifacts.into_iter().enumerate() {
+            if iteration > 0 && self.max_duration.is_some_and(|max| start.elapsed() >= max) {
+                time_limited = true;
+                break;
+            }
+            tracing::info!(iteration, "starting stress iteration");
+            let iteration_start = Instant::now();
+            let r = catch_unwind(AssertUnwindSafe(|| {
+                (self.run)(
+                    PetriTestParams {
+                        test_name: params.test_name,
+                        logger: params.logger,
+                        post_test_hooks: &mut *params.post_test_hooks,
+                    },
+                    artifacts,
+                )
+                .map_err(Into::into)
+            }))
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("iteration panicked")));
+            let elapsed = iteration_start.elapsed();
+            durations.push(elapsed);
+            match r {
+                Ok(()) => tracing::info!(iteration, ?elapsed, "stress iteration passed"),
+                Err(err) => {
+                    tracing::error!(
+                        iteration,
+                        ?elapsed,
+                        error = err.as_ref() as &dyn std::error::Error,
+                        "stress iteration failed"
+                    );
+                    failures += 1;
+                    first_failure.get_or_insert_with(|| format!("iteration {iteration}: {err:#}"));
+                }
+            }
+        }
+
+        let stats = StressStats::new(&durations, failures, time_limited, first_failure);
+        tracing::info!(
+            iterations = stats.iterations,
+            failures,
+            time_limited,
+            mean_secs = stats.mean_secs,
+            "stress test complete"
+        );
+        params.logger.write_attachment(
+            "stress_stats.json",
+            serde_json::to_vec_pretty(&stats)?.as_slice(),
+        )?;
+
+        if failures > 0 {
+            anyhow::bail!(
+                "{failures} of {} iterations failed, first failure: {}",
+                stats.iterations,
+                stats.first_failure.as_deref().unwrap_or_default()
+            );
+        }
+        Ok(())
+    }
+
+    fn host_requirements(&self) -> Option<&TestCaseRequirements> {
+        self.host_requirements.as_ref()
+    }
+
+    fn unstable(&self) -> bool {
+        self.unstable
+    }
 }
 
 #[derive(clap::Parser)]
@@ -489,3 +1105,94 @@ pub fn test_main(
 
     libtest_mimic::run(&args.inner, trials).exit();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::MetricComparison;
+    use super::MetricDirection;
+    use super::ResourceUsage;
+    use super::StressStats;
+    use super::TestBudget;
+    use super::dir_size;
+    use std::time::Duration;
+
+    #[test]
+    fn test_metric_comparison() {
+        let c = MetricComparison::new("throughput", MetricDirection::HigherIsBetter, 200.0, 150.0)
+            .unwrap();
+        assert_eq!(c.delta, -50.0);
+        assert_eq!(c.regression_percent, 25.0);
+
+        let c =
+            MetricComparison::new("latency", MetricDirection::LowerIsBetter, 200.0, 150.0).unwrap();
+        assert_eq!(c.delta, -50.0);
+        assert_eq!(c.regression_percent, -25.0);
+
+        assert!(
+            MetricComparison::new("latency", MetricDirection::LowerIsBetter, 0.0, 150.0).is_err()
+        );
+        assert!(
+            MetricComparison::new("latency", MetricDirection::LowerIsBetter, 200.0, f64::NAN)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_stress_stats() {
+        let durations = [1, 3, 2].map(Duration::from_secs);
+        let stats = StressStats::new(&durations, 1, true, Some("iteration 1: boom".into()));
+        assert_eq!(
+            stats,
+            StressStats {
+                iterations: 3,
+                failures: 1,
+                time_limited: true,
+                min_secs: 1.0,
+                max_secs: 3.0,
+                mean_secs: 2.0,
+                first_failure: Some("iteration 1: boom".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_budget_violations() {
+        let usage = ResourceUsage {
+            duration: Duration::from_secs(120),
+            peak_host_memory_bytes: Some(4 << 30),
+            scratch_disk_bytes: Some(1 << 20),
+        };
+        assert!(TestBudget::default().violations(&usage).is_empty());
+
+        let budget = TestBudget {
+            max_host_memory_bytes: Some(8 << 30),
+            max_scratch_disk_bytes: Some(1 << 20),
+            max_duration: Some(Duration::from_secs(120)),
+        };
+        assert!(budget.violations(&usage).is_empty());
+
+        let budget = TestBudget {
+            max_host_memory_bytes: Some(2 << 30),
+            max_scratch_disk_bytes: Some(1 << 10),
+            max_duration: Some(Duration::from_secs(60)),
+        };
+        assert_eq!(budget.violations(&usage).len(), 3);
+
+        // Unmeasured usage is not a violation.
+        let usage = ResourceUsage {
+            peak_host_memory_bytes: None,
+            scratch_disk_bytes: None,
+            ..usage
+        };
+        assert_eq!(budget.violations(&usage).len(), 1);
+    }
+
+    #[test]
+    fn test_dir_size() {
+        let dir = tempfile::tempdir().unwrap();
+        fs_err::write(dir.path().join("a"), [0; 100]).unwrap();
+        fs_err::create_dir(dir.path().join("sub")).unwrap();
+        fs_err::write(dir.path().join("sub/b"), [0; 28]).unwrap();
+        assert_eq!(dir_size(dir.path()).unwrap(), 128);
+    }
+}
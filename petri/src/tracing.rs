@@ -7,6 +7,7 @@
 use futures::AsyncRead;
 use futures::AsyncReadExt;
 use futures::StreamExt;
+use futures::channel::oneshot;
 use futures::io::BufReader;
 use jiff::Timestamp;
 use kmsg::KmsgParsedEntry;
@@ -16,7 +17,11 @@
 use std::io::Write as _;
 use std::path::Path;
 use std::path::PathBuf;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
 use tracing::Level;
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::filter::Targets;
@@ -123,6 +128,14 @@ pub fn copy_attachment(
         Ok(())
     }
 
+    /// Adds a named property to the test result.
+    ///
+    /// Like attachments, properties are written to stdout, from which CI adds
+    /// them to the test case's JUnit properties.
+    pub fn write_property(&self, name: &str, value: impl std::fmt::Display) {
+        println!("[[PROPERTY|{name}|{value}]]");
+    }
+
     fn trace_attachment(&self, path: &Path) {
         // Just write the relative path to the JSON log.
         self.0
@@ -361,6 +374,38 @@ pub async fn log_task(
     log_file: PetriLogFile,
     reader: impl AsyncRead + Unpin + Send + 'static,
     name: &str,
+) -> anyhow::Result<()> {
+    log_lines(reader, name, |line| write_log_line(&log_file, line)).await
+}
+
+/// Like [`log_task`], but also records any Linux kernel panic message seen in
+/// a guest's console output into `panic_message`.
+pub(crate) async fn guest_log_task(
+    log_file: PetriLogFile,
+    reader: impl AsyncRead + Unpin + Send + 'static,
+    name: &str,
+    panic_message: GuestPanicMessage,
+) -> anyhow::Result<()> {
+    log_lines(panic_message.track(reader), name, |line| {
+        panic_message.observe(line);
+        write_log_line(&log_file, line)
+    })
+    .await
+}
+
+fn write_log_line(log_file: &PetriLogFile, line: &str) {
+    if let Some(message) = kmsg::SyslogParsedEntry::new(line) {
+        let level = kernel_level_to_tracing_level(message.level);
+        log_file.write_entry_fmt(None, level, format_args!("{}", message.display(false)));
+    } else {
+        log_file.write_entry(line);
+    }
+}
+
+async fn log_lines(
+    reader: impl AsyncRead + Unpin,
+    name: &str,
+    mut on_line: impl FnMut(&str),
 ) -> anyhow::Result<()> {
     tracing::info!("connected to {name}");
     let mut buf = Vec::new();
@@ -380,14 +425,105 @@ pub async fn log_task(
         }
 
         let string_buf = String::from_utf8_lossy(&buf);
-        let string_buf_trimmed = string_buf.trim_end();
+        on_line(string_buf.trim_end());
+    }
+}
 
-        if let Some(message) = kmsg::SyslogParsedEntry::new(string_buf_trimmed) {
-            let level = kernel_level_to_tracing_level(message.level);
-            log_file.write_entry_fmt(None, level, format_args!("{}", message.display(false)));
-        } else {
-            log_file.write_entry(string_buf_trimmed);
+/// The most recent Linux kernel panic message seen in a guest's console
+/// output, used to report why the guest halted.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct GuestPanicMessage(Arc<Mutex<GuestPanicState>>);
+
+#[derive(Debug, Default)]
+struct GuestPanicState {
+    message: Option<String>,
+    /// Whether a console reader is currently attached.
+    connected: bool,
+    /// The waker from the reader's last pending read.
+    waker: Option<Waker>,
+    /// Callers waiting for the reader to catch up with the console output.
+    waiters: Vec<oneshot::Sender<()>>,
+}
+
+impl GuestPanicMessage {
+    const PREFIX: &str = "Kernel panic - not syncing: ";
+
+    fn observe(&self, line: &str) {
+        if let Some((_, message)) = line.split_once(Self::PREFIX) {
+            self.0.lock().message = Some(message.to_owned());
+        }
+    }
+
+    fn track<R: AsyncRead + Unpin>(&self, reader: R) -> PanicTrackingReader<R> {
+        self.0.lock().connected = true;
+        PanicTrackingReader {
+            reader,
+            state: self.0.clone(),
+        }
+    }
+
+    /// Takes the recorded panic message, if any.
+    ///
+    /// The guest writes its panic message before it halts, but the console
+    /// reader may not have processed it by the time the halt is reported. So
+    /// first wait for the reader to consume all the output that is already
+    /// available.
+    pub async fn take(&self) -> Option<String> {
+        let sync = {
+            let mut state = self.0.lock();
+            state.connected.then(|| {
+                let (send, recv) = oneshot::channel();
+                state.waiters.push(send);
+                (recv, state.waker.take())
+            })
+        };
+        if let Some((recv, waker)) = sync {
+            // Repoll the reader in case it is parked on a read that output
+            // has since arrived for.
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+            // An error means the reader was dropped, which is just as good.
+            let _ = recv.await;
+        }
+        self.0.lock().message.take()
+    }
+}
+
+/// A reader that notifies [`GuestPanicMessage::take`] callers each time it
+/// has consumed all the available input.
+struct PanicTrackingReader<R> {
+    reader: R,
+    state: Arc<Mutex<GuestPanicState>>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for PanicTrackingReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let r = Pin::new(&mut this.reader).poll_read(cx, buf);
+        if r.is_pending() {
+            // All the lines read so far have been observed, since the
+            // caller only reads more once it is done with the previous line.
+            let mut state = this.state.lock();
+            state.waker = Some(cx.waker().clone());
+            for waiter in state.waiters.drain(..) {
+                let _ = waiter.send(());
+            }
         }
+        r
+    }
+}
+
+impl<R> Drop for PanicTrackingReader<R> {
+    fn drop(&mut self) {
+        let mut state = self.state.lock();
+        state.connected = false;
+        state.waker = None;
+        state.waiters.clear();
     }
 }
 
@@ -457,4 +593,57 @@ fn test_kernel_level_to_tracing_level() {
         assert_eq!(kernel_level_to_tracing_level(8), Level::INFO);
         assert_eq!(kernel_level_to_tracing_level(255), Level::INFO);
     }
+
+    #[test]
+    fn test_guest_panic_message() {
+        let panic_message = GuestPanicMessage::default();
+        panic_message.observe("[    1.234567] Run /init as init process");
+        assert_eq!(futures::executor::block_on(panic_message.take()), None);
+
+        panic_message.observe(
+            "[    2.345678] Kernel panic - not syncing: Attempted to kill init! exitcode=0x00000100",
+        );
+        assert_eq!(
+            futures::executor::block_on(panic_message.take()).as_deref(),
+            Some("Attempted to kill init! exitcode=0x00000100")
+        );
+        assert_eq!(futures::executor::block_on(panic_message.take()), None);
+    }
+
+    #[test]
+    fn test_guest_panic_message_waits_for_reader() {
+        use futures::TryStreamExt;
+        use futures::task::LocalSpawnExt;
+
+        let mut pool = futures::executor::LocalPool::new();
+        let (send, recv) = futures::channel::mpsc::unbounded::<std::io::Result<Vec<u8>>>();
+        let panic_message = GuestPanicMessage::default();
+        let reader = panic_message.track(recv.into_async_read());
+        pool.spawner()
+            .spawn_local({
+                let panic_message = panic_message.clone();
+                async move {
+                    log_lines(reader, "test", |line| panic_message.observe(line))
+                        .await
+                        .unwrap();
+                }
+            })
+            .unwrap();
+
+        // The reader has not run yet when the message is taken, as when the
+        // halt is reported before the console output is processed.
+        send.unbounded_send(Ok(
+            b"[    2.345678] Kernel panic - not syncing: oops\n".to_vec()
+        ))
+        .unwrap();
+        assert_eq!(
+            pool.run_until(panic_message.take()).as_deref(),
+            Some("oops")
+        );
+
+        // Once the reader is gone, there is nothing to wait for.
+        drop(send);
+        pool.run_until_stalled();
+        assert_eq!(pool.run_until(panic_message.take()), None);
+    }
 }
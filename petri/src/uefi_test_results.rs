@@ -0,0 +1,145 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Parsing of the test results reported by the `guest_test_uefi` image.
+
+use crate::PetriLogSource;
+use anyhow::Context as _;
+
+/// The marker that prefixes each result line in the image's console output.
+const MARKER: &str = ">>>>>> [TEST]: ";
+
+/// The status of a single `guest_test_uefi` test.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UefiTestStatus {
+    /// The test started but did not report completion, either because it
+    /// failed or because it ended the VM.
+    Running,
+    /// The test passed.
+    Passed,
+    /// The test was not selected to run.
+    Skipped,
+}
+
+/// The results reported by a `guest_test_uefi` run, in the order the tests
+/// were reported.
+#[derive(Debug, Default)]
+pub struct UefiTestResults {
+    tests: Vec<(String, UefiTestStatus)>,
+    all_passed: bool,
+}
+
+impl UefiTestResults {
+    /// Parses the results from the UEFI console log of a torn down VM.
+    pub fn from_log(log_source: &PetriLogSource) -> anyhow::Result<Self> {
+        let path = log_source.output_dir().join("uefi.log");
+        let output = fs_err::read_to_string(&path).context("failed to read uefi console log")?;
+        Ok(Self::parse(&output))
+    }
+
+    /// Parses the results from the image's console output.
+    ///
+    /// If a test is reported more than once, for example because the guest
+    /// rebooted, its last status wins.
+    pub fn parse(output: &str) -> Self {
+        let mut results = Self::default();
+        for line in output.lines() {
+            let Some((_, event)) = line.split_once(MARKER) else {
+                continue;
+            };
+            let event = event.trim_end();
+            if event == "all tests passed" {
+                results.all_passed = true;
+                continue;
+            }
+            let Some((status, name)) = event.split_once(' ') else {
+                continue;
+            };
+            let status = match status {
+                "running" => UefiTestStatus::Running,
+                "passed" => UefiTestStatus::Passed,
+                "skipped" => UefiTestStatus::Skipped,
+                _ => continue,
+            };
+            let Some(name) = name.strip_prefix('\'').and_then(|n| n.strip_suffix('\'')) else {
+                continue;
+            };
+            match results.tests.iter_mut().find(|(n, _)| n == name) {
+                Some((_, s)) => *s = status,
+                None => results.tests.push((name.to_owned(), status)),
+            }
+        }
+        results
+    }
+
+    /// Returns the status of the named test, if it was reported.
+    pub fn status(&self, name: &str) -> Option<UefiTestStatus> {
+        self.tests
+            .iter()
+            .find_map(|(n, status)| (n == name).then_some(*status))
+    }
+
+    /// Returns the reported tests and their statuses.
+    pub fn tests(&self) -> impl Iterator<Item = (&str, UefiTestStatus)> {
+        self.tests
+            .iter()
+            .map(|(name, status)| (name.as_str(), *status))
+    }
+
+    /// Returns whether the image reported that all selected tests passed.
+    pub fn all_passed(&self) -> bool {
+        self.all_passed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let output = "\
+running tests...\r
+>>>>>> [TEST]: running 'global_alloc'\r
+look 'ma, i'm outputting a heap allocated string: hello world!\r
+>>>>>> [TEST]: passed 'global_alloc'\r
+>>>>>> [TEST]: skipped 'memory_map'\r
+>>>>>> [TEST]: running 'watchdog'\r
+";
+        let results = UefiTestResults::parse(output);
+        assert_eq!(
+            results.tests().collect::<Vec<_>>(),
+            [
+                ("global_alloc", UefiTestStatus::Passed),
+                ("memory_map", UefiTestStatus::Skipped),
+                ("watchdog", UefiTestStatus::Running),
+            ]
+        );
+        assert_eq!(results.status("dbdefault"), None);
+        assert!(!results.all_passed());
+    }
+
+    #[test]
+    fn test_parse_all_passed() {
+        let output = "\
+>>>>>> [TEST]: running 'global_alloc'
+>>>>>> [TEST]: passed 'global_alloc'
+>>>>>> [TEST]: all tests passed
+";
+        let results = UefiTestResults::parse(output);
+        assert_eq!(results.status("global_alloc"), Some(UefiTestStatus::Passed));
+        assert!(results.all_passed());
+    }
+
+    #[test]
+    fn test_parse_ignores_malformed_lines() {
+        let output = "\
+>>>>>> [TEST]: running global_alloc
+>>>>>> [TEST]: exploded 'global_alloc'
+>>>>>> [TEST]:
+";
+        let results = UefiTestResults::parse(output);
+        assert_eq!(results.tests().count(), 0);
+        assert!(!results.all_passed());
+    }
+}
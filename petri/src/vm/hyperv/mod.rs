@@ -11,6 +11,7 @@
 use crate::Disk;
 use crate::Drive;
 use crate::Firmware;
+use crate::GuestPanicMessage;
 use crate::IsolationType;
 use crate::ModifyFn;
 use crate::NoPetriVmInspector;
@@ -76,6 +77,7 @@ pub struct HyperVPetriRuntime {
     output_dir: PathBuf,
     driver: DefaultDriver,
     properties: PetriVmProperties,
+    guest_panic: GuestPanicMessage,
 }
 
 #[async_trait]
@@ -192,9 +194,14 @@ async fn run(
             secure_boot_enabled,
             default_boot_always_attempt,
             efi_diagnostics_log_level,
+            custom_secure_boot_vars,
             ..
         }) = config.firmware.uefi_config()
         {
+            if custom_secure_boot_vars.is_some() {
+                anyhow::bail!("custom secure boot variables are not supported on Hyper-V");
+            }
+
             // TODO: Disable frontpage for non-OpenHCL Hyper-V VMs
             if *disable_frontpage && properties.is_openhcl {
                 append_cmdline(
@@ -261,9 +268,13 @@ async fn run(
                 target_vtl,
                 controller_type,
                 drives,
+                scsi_max_protocol_version,
             },
         ) in config.vmbus_storage_controllers.iter()
         {
+            if scsi_max_protocol_version.is_some() {
+                anyhow::bail!("limiting the SCSI protocol version is not supported on Hyper-V");
+            }
             let mut hyperv_drives = HashMap::new();
             for (lun, Drive { disk, is_dvd }) in drives {
                 hyperv_drives.insert(
@@ -426,6 +437,7 @@ async fn run(
                         driver.clone(),
                         openhcl_serial_pipe_path,
                         openhcl_log_file,
+                        None,
                     ),
                 ));
             } else {
@@ -448,9 +460,15 @@ async fn run(
 
         let serial_pipe_path = vm.get_vm_com_port_path(1);
         let serial_log_file = log_source.log_file("guest")?;
+        let guest_panic = GuestPanicMessage::default();
         log_tasks.push(driver.spawn(
             "guest-log",
-            hyperv_serial_log_task(driver.clone(), serial_pipe_path, serial_log_file),
+            hyperv_serial_log_task(
+                driver.clone(),
+                serial_pipe_path,
+                serial_log_file,
+                Some(guest_panic.clone()),
+            ),
         ));
 
         vm.start().await?;
@@ -463,6 +481,7 @@ async fn run(
                 output_dir: log_source.output_dir().to_owned(),
                 driver: driver.clone(),
                 properties,
+                guest_panic,
             },
             config
                 .firmware
@@ -482,7 +501,8 @@ async fn teardown(mut self) -> anyhow::Result<()> {
     }
 
     async fn wait_for_halt(&mut self, allow_reset: bool) -> anyhow::Result<PetriHaltReasonDetail> {
-        self.vm.wait_for_halt(allow_reset).await
+        let halt = self.vm.wait_for_halt(allow_reset).await?;
+        Ok(halt.with_panic_message(self.guest_panic.take().await))
     }
 
     async fn wait_for_agent(&mut self, set_high_vtl: bool) -> anyhow::Result<PipetteClient> {
@@ -553,6 +573,10 @@ async fn wait_for_boot_event(&mut self) -> anyhow::Result<FirmwareEvent> {
         self.vm.wait_for_boot_event().await
     }
 
+    async fn wait_for_boot_phase(&mut self, _phase: FirmwareEvent) -> anyhow::Result<()> {
+        anyhow::bail!("boot phases are not reported on Hyper-V");
+    }
+
     async fn wait_for_enlightened_shutdown_ready(&mut self) -> anyhow::Result<()> {
         self.vm.wait_for_enlightened_shutdown_ready().await
     }
@@ -605,6 +629,21 @@ async fn reset(&mut self) -> anyhow::Result<()> {
         self.vm.reset().await
     }
 
+    async fn inject_nmi(&mut self, vp: u32) -> anyhow::Result<()> {
+        // Debug-VM cannot target a specific processor; Hyper-V delivers the
+        // NMI to the BSP.
+        if vp != 0 {
+            anyhow::bail!("Hyper-V only supports injecting NMIs into VP 0");
+        }
+        self.vm.inject_nmi().await
+    }
+
+    async fn inject_machine_check(&mut self, _vp: u32) -> anyhow::Result<()> {
+        // Debug-VM has no equivalent of -InjectNonMaskableInterrupt for
+        // machine checks.
+        anyhow::bail!("Hyper-V does not support injecting machine checks")
+    }
+
     async fn get_guest_state_file(&self) -> anyhow::Result<Option<PathBuf>> {
         Ok(Some(self.vm.get_guest_state_file().await?))
     }
@@ -656,10 +695,22 @@ fn acl_for_vm(path: &Path, id: Option<Guid>, write: bool) -> anyhow::Result<()>
     Ok(())
 }
 
+impl HyperVPetriRuntime {
+    /// Restart a Hyper-V host service while the VM is running, and wait for
+    /// the VM to be re-adopted by the service.
+    pub async fn restart_host_service(
+        &mut self,
+        service: powershell::HyperVHostService,
+    ) -> anyhow::Result<()> {
+        self.vm.restart_host_service(service).await
+    }
+}
+
 async fn hyperv_serial_log_task(
     driver: DefaultDriver,
     serial_pipe_path: String,
     log_file: crate::PetriLogFile,
+    panic_message: Option<GuestPanicMessage>,
 ) -> anyhow::Result<()> {
     let mut timer = None;
     loop {
@@ -673,7 +724,17 @@ async fn hyperv_serial_log_task(
             Ok(file) => {
                 let pipe = PolledPipe::new(&driver, file).expect("failed to create pipe");
                 // connect/disconnect messages logged internally
-                _ = crate::log_task(log_file.clone(), pipe, &serial_pipe_path).await;
+                _ = if let Some(panic_message) = &panic_message {
+                    crate::guest_log_task(
+                        log_file.clone(),
+                        pipe,
+                        &serial_pipe_path,
+                        panic_message.clone(),
+                    )
+                    .await
+                } else {
+                    crate::log_task(log_file.clone(), pipe, &serial_pipe_path).await
+                };
             }
             Err(err) => {
                 // Log the error if it isn't just that the VM is not running
@@ -229,6 +229,24 @@ fn as_val(&self) -> impl '_ + AsRef<OsStr> {
     }
 }
 
+/// A Hyper-V host service that can be restarted while VMs are running.
+#[derive(Clone, Copy, Debug)]
+pub enum HyperVHostService {
+    /// The Hyper-V Virtual Machine Management service
+    Vmms,
+    /// The Hyper-V Host Compute Service
+    HostCompute,
+}
+
+impl ps::AsVal for HyperVHostService {
+    fn as_val(&self) -> impl '_ + AsRef<OsStr> {
+        match self {
+            HyperVHostService::Vmms => "vmms",
+            HyperVHostService::HostCompute => "vmcompute",
+        }
+    }
+}
+
 /// Hyper-V Management VTL Feature Flags
 #[bitfield_struct::bitfield(u64)]
 pub struct HyperVManagementVtlFeatureFlags {
@@ -817,6 +835,21 @@ pub async fn run_remove_vm(vmid: &Guid) -> anyhow::Result<()> {
     .context("remove_vm")
 }
 
+/// Runs Restart-Service for a Hyper-V host service.
+pub async fn run_restart_host_service(service: HyperVHostService) -> anyhow::Result<()> {
+    run_host_cmd(
+        PowerShellBuilder::new()
+            .cmdlet("Restart-Service")
+            .arg("Name", service)
+            .flag("Force")
+            .finish()
+            .build(),
+    )
+    .await
+    .map(|_| ())
+    .context("restart_host_service")
+}
+
 /// Request a physical NVMe device via closed-source script
 pub async fn request_physical_nvme(
     namespace_size_mib: u64,
@@ -1378,11 +1411,13 @@ pub async fn run_restart_openhcl(
     ps_mod: &Path,
     flags: OpenHclServicingFlags,
 ) -> anyhow::Result<()> {
-    // No NVMe storage, so no keepalive. Prevent us from silently thinking that we're testing this feature.
-    // Tracked by #1649.
-    if flags.enable_nvme_keepalive {
+    // No assigned devices, so no keepalive. Prevent us from silently thinking
+    // that we're testing this feature. Tracked by #1649.
+    let keepalive = flags.keepalive_device_types();
+    if !keepalive.is_empty() {
         return Err(anyhow::anyhow!(
-            "enable_nvme_keepalive is not yet supported for HyperV VMs"
+            "keepalive for {} devices is not yet supported for HyperV VMs",
+            keepalive.join(" and ")
         ));
     }
     run_host_cmd(
@@ -2036,3 +2071,21 @@ pub async fn run_disable_vmtpm(vmid: &Guid) -> anyhow::Result<()> {
     .map(|_| ())
     .context("run_disable_vmtpm")
 }
+
+/// Runs Debug-VM to inject a non-maskable interrupt into the VM
+pub async fn run_inject_nmi(vmid: &Guid) -> anyhow::Result<()> {
+    run_host_cmd(
+        PowerShellBuilder::new()
+            .cmdlet("Get-VM")
+            .arg("Id", vmid)
+            .pipeline()
+            .cmdlet("Debug-VM")
+            .flag("InjectNonMaskableInterrupt")
+            .flag("Force")
+            .finish()
+            .build(),
+    )
+    .await
+    .map(|_| ())
+    .context("run_inject_nmi")
+}
@@ -8,6 +8,7 @@
 use super::powershell;
 use crate::CommandError;
 use crate::OpenHclServicingFlags;
+use crate::PetriGuestCrash;
 use crate::PetriHaltReason;
 use crate::PetriHaltReasonDetail;
 use crate::PetriLogFile;
@@ -436,6 +437,11 @@ async fn halt_event(&self) -> anyhow::Result<Option<(PetriHaltReasonDetail, Time
             .collect::<Vec<_>>()
             .join(", ");
 
+        let crash = events
+            .iter()
+            .filter(|e| e.id == powershell::MSVM_GUEST_CRASH_REPORT)
+            .find_map(|e| parse_guest_crash_report(&e.message));
+
         let reasons = events
             .into_iter()
             .map(|e| {
@@ -465,14 +471,18 @@ async fn halt_event(&self) -> anyhow::Result<Option<(PetriHaltReasonDetail, Time
             })
             .collect::<anyhow::Result<Vec<(PetriHaltReason, Timestamp)>>>()?;
 
-        Ok(if reasons.len() > 1 {
+        let halt = if reasons.len() > 1 {
             Some((
                 PetriHaltReason::Other.with_detail(detail),
                 reasons.last().unwrap().1,
             ))
         } else {
             reasons.first().map(|r| (r.0.with_detail(detail), r.1))
-        })
+        };
+        Ok(halt.map(|(mut detail, timestamp)| {
+            detail.crash = crash;
+            (detail, timestamp)
+        }))
     }
 
     /// Wait for the VM shutdown ic
@@ -534,6 +544,45 @@ pub(crate) async fn wait_for_off_or_internal<T>(
         }
     }
 
+    /// Restart a Hyper-V host service while the VM is running, and wait for
+    /// the service to manage the VM again.
+    ///
+    /// The VM's worker process is expected to survive the restart, so the VM
+    /// must be re-adopted in the same state it was in beforehand.
+    pub async fn restart_host_service(
+        &self,
+        service: powershell::HyperVHostService,
+    ) -> anyhow::Result<()> {
+        const READOPT_TIMEOUT: Duration = Duration::from_secs(60);
+
+        let expected = self.state().await?;
+        tracing::info!(?service, ?expected, "restarting hyper-v host service");
+        powershell::run_restart_host_service(service).await?;
+
+        // The service rediscovers running VMs asynchronously after it starts,
+        // and fails requests for the VM until it has done so.
+        let mut timer = PolledTimer::new(&self.driver);
+        let deadline = std::time::Instant::now() + READOPT_TIMEOUT;
+        loop {
+            let err = match self.state().await {
+                Ok(state) if state == expected => break,
+                Ok(state) => {
+                    anyhow::anyhow!("unexpected VM state {state:?}, should be {expected:?}")
+                }
+                Err(err) => err,
+            };
+            if std::time::Instant::now() > deadline {
+                return Err(err.context(format!(
+                    "VM was not re-adopted after restarting {service:?}"
+                )));
+            }
+            timer.sleep(Duration::from_secs(1)).await;
+        }
+
+        tracing::info!(?service, "VM re-adopted after host service restart");
+        Ok(())
+    }
+
     /// Remove the VM
     pub async fn remove(mut self) -> anyhow::Result<()> {
         self.remove_inner().await
@@ -625,6 +674,36 @@ pub async fn enable_tpm(&self) -> anyhow::Result<()> {
     pub async fn disable_tpm(&self) -> anyhow::Result<()> {
         powershell::run_disable_vmtpm(&self.vmid).await
     }
+
+    /// Inject a non-maskable interrupt into the VM
+    pub async fn inject_nmi(&self) -> anyhow::Result<()> {
+        powershell::run_inject_nmi(&self.vmid).await
+    }
+}
+
+/// Parses the bugcheck code and parameters from a guest crash report event,
+/// whose message lists them as hex values (e.g. "...failed with the following
+/// error codes: ErrorCode0: 0x7E, ErrorCode1: 0xFFFFFFFFC0000005, ...").
+fn parse_guest_crash_report(message: &str) -> Option<PetriGuestCrash> {
+    let (_, codes) = message.split_once("error codes")?;
+    let mut values = codes
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter_map(|word| {
+            let hex = word
+                .strip_prefix("0x")
+                .or_else(|| word.strip_prefix("0X"))?;
+            u64::from_str_radix(hex, 16).ok()
+        });
+    let bugcheck_code = values.next()?;
+    let mut parameters = [0; 4];
+    for (parameter, value) in parameters.iter_mut().zip(values) {
+        *parameter = value;
+    }
+    Some(PetriGuestCrash {
+        bugcheck_code: Some(bugcheck_code),
+        parameters,
+        panic_message: None,
+    })
 }
 
 impl Drop for HyperVVM {
@@ -670,3 +749,34 @@ async fn screenshot(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::parse_guest_crash_report;
+    use crate::PetriGuestCrash;
+
+    #[test]
+    fn test_parse_guest_crash_report() {
+        let crash = parse_guest_crash_report(
+            "'vm' has encountered a fatal error. The guest operating system reported that it \
+             failed with the following error codes: ErrorCode0: 0xEF, ErrorCode1: \
+             0xFFFFC3064F1D5080, ErrorCode2: 0x0, ErrorCode3: 0x0, ErrorCode4: 0x0.",
+        );
+        assert_eq!(
+            crash,
+            Some(PetriGuestCrash {
+                bugcheck_code: Some(0xef),
+                parameters: [0xffffc3064f1d5080, 0, 0, 0],
+                panic_message: None,
+            })
+        );
+
+        // Missing parameters are left as zero.
+        let crash = parse_guest_crash_report("error codes: ErrorCode0: 0x7e").unwrap();
+        assert_eq!(crash.bugcheck_code, Some(0x7e));
+        assert_eq!(crash.parameters, [0; 4]);
+
+        assert_eq!(parse_guest_crash_report("the VM was turned off"), None);
+        assert_eq!(parse_guest_crash_report("error codes: none"), None);
+    }
+}
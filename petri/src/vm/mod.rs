@@ -20,6 +20,7 @@
 use crate::vtl2_settings::Vtl2StorageBackingDeviceBuilder;
 use crate::vtl2_settings::Vtl2StorageControllerBuilder;
 use async_trait::async_trait;
+use firmware_uefi_custom_vars::delta::CustomVarsDelta;
 use get_resources::ged::FirmwareEvent;
 use guid::Guid;
 use mesh::CancelContext;
@@ -30,7 +31,6 @@
 use pal_async::timer::PolledTimer;
 use petri_artifacts_common::tags::GuestQuirks;
 use petri_artifacts_common::tags::GuestQuirksInner;
-use petri_artifacts_common::tags::InitialRebootCondition;
 use petri_artifacts_common::tags::IsOpenhclIgvm;
 use petri_artifacts_common::tags::IsTestVmgs;
 use petri_artifacts_common::tags::MachineArch;
@@ -51,6 +51,7 @@
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
+use storvsp_resources::ScsiProtocolVersion;
 use tempfile::TempPath;
 use vmgs_resources::GuestStateEncryptionPolicy;
 use vtl2_settings_proto::StorageController;
@@ -389,6 +390,7 @@ pub struct PetriVm<T: PetriVmmBackend> {
     vmm_quirks: VmmQuirks,
     expected_boot_event: Option<FirmwareEvent>,
     uses_pipette_as_init: bool,
+    reset_on_startup: bool,
 
     config: PetriVmRuntimeConfig,
 }
@@ -1004,6 +1006,7 @@ async fn run_core(mut self) -> anyhow::Result<PetriVm<T>> {
             vmm_quirks: self.vmm_quirks,
             expected_boot_event: self.expected_boot_event,
             uses_pipette_as_init,
+            reset_on_startup: expect_reset,
 
             config,
         };
@@ -1019,25 +1022,13 @@ async fn run_core(mut self) -> anyhow::Result<PetriVm<T>> {
 
     fn expect_reset(&self) -> bool {
         self.override_expect_reset
-            || matches!(
-                (
-                    self.guest_quirks.initial_reboot,
-                    self.expected_boot_event,
-                    &self.config.firmware,
-                    &self.config.tpm,
-                ),
-                (
-                    Some(InitialRebootCondition::Always),
-                    Some(FirmwareEvent::BootSuccess | FirmwareEvent::BootAttempt),
-                    _,
-                    _,
-                ) | (
-                    Some(InitialRebootCondition::WithTpm),
-                    Some(FirmwareEvent::BootSuccess | FirmwareEvent::BootAttempt),
-                    _,
-                    Some(_),
-                )
-            )
+            || (matches!(
+                self.expected_boot_event,
+                Some(FirmwareEvent::BootSuccess | FirmwareEvent::BootAttempt)
+            ) && self
+                .guest_quirks
+                .initial_reboot
+                .is_some_and(|c| c.applies(self.config.tpm.is_some())))
     }
 
     fn start_watchdog_tasks(
@@ -1214,6 +1205,22 @@ pub fn with_uefi_ca_secure_boot_template(mut self) -> Self {
         self
     }
 
+    /// Inject custom secure boot variables into the VM's UEFI, on top of the
+    /// secure boot template (if any).
+    ///
+    /// This can replace or append to the PK, KEK, db, and dbx variables, to
+    /// test custom-signed bootloaders or revocations. As with the templates,
+    /// the variables are only injected when the UEFI NVRAM is first
+    /// initialized, after which they persist in the VMGS.
+    pub fn with_custom_secure_boot_vars(mut self, vars: CustomVarsDelta) -> Self {
+        self.config
+            .firmware
+            .uefi_config_mut()
+            .expect("Secure boot is only supported for UEFI firmware.")
+            .custom_secure_boot_vars = Some(vars);
+        self
+    }
+
     /// Set the VM to use the specified processor topology.
     pub fn with_processor_topology(mut self, topology: ProcessorTopology) -> Self {
         self.config.proc_topology = topology;
@@ -1512,6 +1519,26 @@ pub fn add_vmbus_storage_controller(
         self
     }
 
+    /// Limit the protocol version negotiated by a VMBus SCSI controller, to
+    /// emulate an older host.
+    pub fn with_scsi_max_protocol_version(
+        mut self,
+        controller_id: &Guid,
+        version: ScsiProtocolVersion,
+    ) -> Self {
+        let controller = self
+            .config
+            .vmbus_storage_controllers
+            .get_mut(controller_id)
+            .unwrap_or_else(|| panic!("storage controller {controller_id} does not exist"));
+        assert!(
+            matches!(controller.controller_type, VmbusStorageType::Scsi),
+            "storage controller {controller_id} is not a SCSI controller"
+        );
+        controller.scsi_max_protocol_version = Some(version);
+        self
+    }
+
     /// Add a VMBus disk drive to the VM
     pub fn add_vmbus_drive(
         mut self,
@@ -1562,6 +1589,12 @@ pub fn is_openhcl(&self) -> bool {
         self.config.firmware.is_openhcl()
     }
 
+    /// Get the guest quirks that apply to the configured guest image on this
+    /// VMM backend.
+    pub fn guest_quirks(&self) -> &GuestQuirksInner {
+        &self.guest_quirks
+    }
+
     /// Get the isolation type of the VM
     pub fn isolation(&self) -> Option<IsolationType> {
         self.config.firmware.isolation()
@@ -1621,6 +1654,18 @@ pub async fn wait_for_clean_shutdown(&mut self) -> anyhow::Result<()> {
         Ok(())
     }
 
+    /// Wait for the VM to halt due to a guest crash, returning the crash
+    /// details.
+    ///
+    /// Fails if the VM halted without the guest reporting a crash.
+    pub async fn wait_for_crash(&mut self) -> anyhow::Result<PetriGuestCrash> {
+        let halt_reason = self.wait_for_halt().await?;
+        match halt_reason.crash {
+            Some(crash) => Ok(crash),
+            None => anyhow::bail!("Expected a guest crash, got {halt_reason:?}"),
+        }
+    }
+
     /// Wait for the VM to halt, returning the reason for the halt,
     /// and tear down the VM.
     pub async fn wait_for_teardown(mut self) -> anyhow::Result<PetriHaltReasonDetail> {
@@ -1698,6 +1743,24 @@ pub async fn inspect_update_openhcl(
             .await
     }
 
+    /// Hide or restore the VTL0 VF of an OpenHCL accelerated NIC.
+    ///
+    /// Hiding the VF revokes it from the guest, switching the guest's data
+    /// path to the synthetic NIC; restoring it offers the VF again so that
+    /// the guest can switch back.
+    pub async fn set_openhcl_vtl0_vf_hidden(
+        &self,
+        nic_instance_id: Guid,
+        hidden: bool,
+    ) -> anyhow::Result<()> {
+        self.inspect_update_openhcl(
+            format!("vm/network/vf_managers/{nic_instance_id}/vtl0_vf_hidden"),
+            hidden.to_string(),
+        )
+        .await?;
+        Ok(())
+    }
+
     /// Test that we are able to inspect OpenHCL.
     pub async fn test_inspect_openhcl(&mut self) -> anyhow::Result<()> {
         self.inspect_openhcl("", None, None).await.map(|_| ())
@@ -1807,6 +1870,18 @@ async fn wait_for_boot_event(&mut self) -> anyhow::Result<FirmwareEvent> {
         Ok(boot_event)
     }
 
+    /// Waits for the firmware to report that it has reached the given boot
+    /// phase, such as [`FirmwareEvent::BdsStarted`] or
+    /// [`FirmwareEvent::OsLoaderHandoff`].
+    ///
+    /// This allows a hang to be attributed to a specific part of the boot
+    /// sequence. Boot phases are only reported by UEFI when not using
+    /// OpenHCL, and are not reported on Hyper-V.
+    pub async fn wait_for_boot_phase(&mut self, phase: FirmwareEvent) -> anyhow::Result<()> {
+        tracing::info!(?phase, "Waiting for boot phase...");
+        self.runtime.wait_for_boot_phase(phase).await
+    }
+
     /// Wait for the Hyper-V shutdown IC to be ready and use it to instruct
     /// the guest to shutdown.
     pub async fn send_enlightened_shutdown(&mut self, kind: ShutdownKind) -> anyhow::Result<()> {
@@ -1869,6 +1944,18 @@ pub async fn remove_pcie_device(&mut self, port_name: String) -> anyhow::Result<
         self.runtime.remove_pcie_device(port_name).await
     }
 
+    /// Inject a non-maskable interrupt into the given VTL0 processor, e.g. to
+    /// trigger a guest crash dump. Hyper-V only supports VP 0.
+    pub async fn inject_nmi(&mut self, vp: u32) -> anyhow::Result<()> {
+        self.runtime.inject_nmi(vp).await
+    }
+
+    /// Inject a machine check exception into the given VTL0 processor, e.g.
+    /// to test the guest's machine check handling. Not supported on Hyper-V.
+    pub async fn inject_machine_check(&mut self, vp: u32) -> anyhow::Result<()> {
+        self.runtime.inject_machine_check(vp).await
+    }
+
     /// Instruct the OpenHCL to save the state of the VTL2 paravisor. Will fail if the VM
     /// is not running OpenHCL. Will also fail if the VM is not running or if this is called twice in succession
     pub async fn save_openhcl(
@@ -1890,6 +1977,22 @@ pub fn arch(&self) -> MachineArch {
         self.arch
     }
 
+    /// Get the guest quirks that apply to the running guest image.
+    pub fn guest_quirks(&self) -> &GuestQuirksInner {
+        &self.guest_quirks
+    }
+
+    /// Returns whether the VM was expected to reset once during startup,
+    /// either because of the guest's initial reboot quirk or
+    /// because the test requested it, and that reset has been consumed.
+    ///
+    /// Tests that need the guest to have rebooted once (for example, to
+    /// pick up state provisioned on first boot) can use this to decide
+    /// whether a manual reboot is still required.
+    pub fn reset_on_startup(&self) -> bool {
+        self.reset_on_startup
+    }
+
     /// Get the inner runtime backend to make backend-specific calls
     pub fn backend(&mut self) -> &mut T::VmRuntime {
         &mut self.runtime
@@ -1999,6 +2102,9 @@ pub trait PetriVmRuntime: Send + Sync + 'static {
     /// Waits for an event emitted by the firmware about its boot status, and
     /// returns that status.
     async fn wait_for_boot_event(&mut self) -> anyhow::Result<FirmwareEvent>;
+    /// Waits for the firmware to report that it has reached the given boot
+    /// phase (see [`FirmwareEvent::is_boot_phase`]).
+    async fn wait_for_boot_phase(&mut self, phase: FirmwareEvent) -> anyhow::Result<()>;
     /// Waits for the Hyper-V shutdown IC to be ready
     // TODO: return a receiver that will be closed when it is no longer ready.
     async fn wait_for_enlightened_shutdown_ready(&mut self) -> anyhow::Result<()>;
@@ -2036,6 +2142,10 @@ fn take_framebuffer_access(&mut self) -> Option<Self::VmFramebufferAccess> {
     }
     /// Issue a hard reset to the VM
     async fn reset(&mut self) -> anyhow::Result<()>;
+    /// Inject a non-maskable interrupt into the given VTL0 processor
+    async fn inject_nmi(&mut self, vp: u32) -> anyhow::Result<()>;
+    /// Inject a machine check exception into the given VTL0 processor
+    async fn inject_machine_check(&mut self, vp: u32) -> anyhow::Result<()>;
     /// Get the path to the VM's guest state file
     async fn get_guest_state_file(&self) -> anyhow::Result<Option<PathBuf>> {
         Ok(None)
@@ -2199,6 +2309,10 @@ pub struct UefiConfig {
     pub secure_boot_enabled: bool,
     /// Secure boot template
     pub secure_boot_template: Option<SecureBootTemplate>,
+    /// Custom secure boot variables (such as PK, KEK, db, and dbx), layered
+    /// on top of the secure boot template. Only supported for non-OpenHCL
+    /// UEFI on the OpenVMM backend.
+    pub custom_secure_boot_vars: Option<CustomVarsDelta>,
     /// Disable the UEFI frontpage which will cause the VM to shutdown instead when unable to boot.
     pub disable_frontpage: bool,
     /// Always attempt a default boot
@@ -2214,6 +2328,7 @@ fn default() -> Self {
         Self {
             secure_boot_enabled: false,
             secure_boot_template: None,
+            custom_secure_boot_vars: None,
             disable_frontpage: true,
             default_boot_always_attempt: false,
             enable_vpci_boot: false,
@@ -2989,6 +3104,26 @@ pub struct OpenHclServicingFlags {
     pub stop_timeout_hint_secs: Option<u16>,
 }
 
+impl OpenHclServicingFlags {
+    /// Returns the types of assigned devices for which keepalive is
+    /// requested.
+    pub fn keepalive_device_types(&self) -> Vec<&'static str> {
+        let Self {
+            enable_nvme_keepalive,
+            enable_mana_keepalive,
+            override_version_checks: _,
+            stop_timeout_hint_secs: _,
+        } = *self;
+        [
+            ("NVMe", enable_nvme_keepalive),
+            ("MANA", enable_mana_keepalive),
+        ]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect()
+    }
+}
+
 /// Where a disk image is located.
 #[derive(Debug, Clone)]
 pub enum DiskPath {
@@ -3162,6 +3297,7 @@ pub fn with_detail(self, detail: String) -> PetriHaltReasonDetail {
         PetriHaltReasonDetail {
             reason: self,
             detail,
+            crash: None,
         }
     }
 }
@@ -3173,6 +3309,33 @@ pub struct PetriHaltReasonDetail {
     pub reason: PetriHaltReason,
     /// More details about the halt
     pub detail: String,
+    /// Details of the guest crash that led to the halt, if the guest reported
+    /// one
+    pub crash: Option<PetriGuestCrash>,
+}
+
+impl PetriHaltReasonDetail {
+    /// Records the Linux kernel panic message seen before the halt, if any.
+    pub(crate) fn with_panic_message(mut self, message: Option<String>) -> Self {
+        if let Some(message) = message {
+            self.crash.get_or_insert_default().panic_message = Some(message);
+        }
+        self
+    }
+}
+
+/// Details of a guest crash, as reported by the guest before it halted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PetriGuestCrash {
+    /// The bugcheck code reported through the Hyper-V guest crash registers,
+    /// or `None` if the guest only reported a panic message on its console.
+    pub bugcheck_code: Option<u64>,
+    /// The bugcheck parameters reported through the Hyper-V guest crash
+    /// registers, or zeroes if there is no bugcheck code.
+    pub parameters: [u64; 4],
+    /// The Linux kernel panic message, if one was seen on the guest's
+    /// console.
+    pub panic_message: Option<String>,
 }
 
 fn append_cmdline(cmd: &mut Option<String>, add_cmd: impl AsRef<str>) {
@@ -3273,6 +3436,9 @@ pub struct VmbusStorageController {
     pub controller_type: VmbusStorageType,
     /// Drives (with any inserted disks) attached to this storage controller
     pub drives: HashMap<u32, Drive>,
+    /// For SCSI controllers, the newest protocol version to negotiate with
+    /// the guest, to test downlevel guest support. Only supported on OpenVMM.
+    pub scsi_max_protocol_version: Option<ScsiProtocolVersion>,
 }
 
 impl VmbusStorageController {
@@ -3282,6 +3448,7 @@ pub fn new(target_vtl: Vtl, controller_type: VmbusStorageType) -> Self {
             target_vtl,
             controller_type,
             drives: HashMap::new(),
+            scsi_max_protocol_version: None,
         }
     }
 
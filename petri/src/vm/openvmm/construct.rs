@@ -9,6 +9,7 @@
 use crate::Drive;
 use crate::EfiDiagnosticsLogLevel;
 use crate::Firmware;
+use crate::GuestPanicMessage;
 use crate::IsolationType;
 use crate::MemoryConfig;
 use crate::OpenHclConfig;
@@ -31,6 +32,7 @@
 use crate::vm::PetriVmProperties;
 use crate::vm::append_cmdline;
 use anyhow::Context;
+use firmware_uefi_custom_vars::CustomVars;
 use framebuffer::FRAMEBUFFER_SIZE;
 use framebuffer::Framebuffer;
 use framebuffer::FramebufferAccess;
@@ -182,6 +184,7 @@ pub async fn new(
             }
         }
 
+        let guest_panic = GuestPanicMessage::default();
         let (emulated_serial_config, log_stream_tasks, linux_direct_serial_agent) =
             if !properties.enable_serial {
                 // No emulated serial backends (OpenHCL VMBus serial stubs may still exist)
@@ -191,7 +194,7 @@ pub async fn new(
                     emulated_serial_config,
                     serial_tasks,
                     linux_direct_serial_agent,
-                } = setup.configure_serial(log_source)?;
+                } = setup.configure_serial(log_source, &guest_panic)?;
                 (
                     emulated_serial_config,
                     serial_tasks,
@@ -237,6 +240,7 @@ pub async fn new(
                         disk,
                     }],
                     requests: None,
+                    boot_partitions: None,
                 }
                 .into_resource(),
             });
@@ -358,24 +362,10 @@ pub async fn new(
         // OpenhclUefi uses BaseChipsetType::HclHost, so it does not need this.
         if matches!(firmware, Firmware::Uefi { .. }) {
             let uefi_cfg = firmware.uefi_config();
-            let custom_uefi_vars =
-                uefi_cfg.map_or_else(Default::default, |c| match (arch, c.secure_boot_template) {
-                    (MachineArch::X86_64, Some(SecureBootTemplate::MicrosoftWindows)) => {
-                        hyperv_secure_boot_templates::x64::microsoft_windows()
-                    }
-                    (
-                        MachineArch::X86_64,
-                        Some(SecureBootTemplate::MicrosoftUefiCertificateAuthority),
-                    ) => hyperv_secure_boot_templates::x64::microsoft_uefi_ca(),
-                    (MachineArch::Aarch64, Some(SecureBootTemplate::MicrosoftWindows)) => {
-                        hyperv_secure_boot_templates::aarch64::microsoft_windows()
-                    }
-                    (
-                        MachineArch::Aarch64,
-                        Some(SecureBootTemplate::MicrosoftUefiCertificateAuthority),
-                    ) => hyperv_secure_boot_templates::aarch64::microsoft_uefi_ca(),
-                    (_, None) => Default::default(),
-                });
+            let custom_uefi_vars = uefi_cfg
+                .map(|c| uefi_custom_vars(arch, c))
+                .transpose()?
+                .unwrap_or_default();
             let secure_boot = uefi_cfg.is_some_and(|c| c.secure_boot_enabled);
             let log_level = match uefi_cfg
                 .map(|c| c.efi_diagnostics_log_level)
@@ -437,7 +427,9 @@ pub async fn new(
                 transparent_hugepages: false,
                 hugepages: false,
                 hugepage_size: None,
+                hugepage_fallback: false,
                 numa_mem_sizes,
+                hot_add_size: None,
             }
         };
 
@@ -480,31 +472,10 @@ pub async fn new(
             }
         };
 
-        let (secure_boot_enabled, custom_uefi_vars) = firmware.uefi_config().map_or_else(
-            || (false, Default::default()),
-            |c| {
-                (
-                    c.secure_boot_enabled,
-                    match (arch, c.secure_boot_template) {
-                        (MachineArch::X86_64, Some(SecureBootTemplate::MicrosoftWindows)) => {
-                            hyperv_secure_boot_templates::x64::microsoft_windows()
-                        }
-                        (
-                            MachineArch::X86_64,
-                            Some(SecureBootTemplate::MicrosoftUefiCertificateAuthority),
-                        ) => hyperv_secure_boot_templates::x64::microsoft_uefi_ca(),
-                        (MachineArch::Aarch64, Some(SecureBootTemplate::MicrosoftWindows)) => {
-                            hyperv_secure_boot_templates::aarch64::microsoft_windows()
-                        }
-                        (
-                            MachineArch::Aarch64,
-                            Some(SecureBootTemplate::MicrosoftUefiCertificateAuthority),
-                        ) => hyperv_secure_boot_templates::aarch64::microsoft_uefi_ca(),
-                        (_, None) => Default::default(),
-                    },
-                )
-            },
-        );
+        let (secure_boot_enabled, custom_uefi_vars) = match firmware.uefi_config() {
+            Some(c) => (c.secure_boot_enabled, uefi_custom_vars(arch, c)?),
+            None => (false, Default::default()),
+        };
 
         let vmgs = if firmware.is_openhcl() {
             None
@@ -567,6 +538,10 @@ pub async fn new(
                     None => None,
                     _ => anyhow::bail!("unsupported isolation type"),
                 },
+                cpuid_overrides: Vec::new(),
+                // Halt on guest crashes so that tests can inspect the crash
+                // parameters via `wait_for_crash`.
+                halt_on_guest_crash: true,
             },
             vmbus: Some(VmbusConfig {
                 // If virtio vsock is enabled, the vsock_listener will have already been taken and
@@ -599,6 +574,10 @@ pub async fn new(
 
             // Don't automatically reset the guest by default
             automatic_guest_reset: false,
+            extra_acpi_tables: vec![],
+
+            // Enabled by tests that check for stray device accesses
+            track_device_memory: false,
 
             // Disabled for VMM tests by default
             #[cfg(windows)]
@@ -655,6 +634,7 @@ pub async fn new(
 
             resources: PetriVmResourcesOpenVmm {
                 log_stream_tasks,
+                guest_panic,
                 firmware_event_recv,
                 shutdown_ic_send,
                 kvp_ic_send,
@@ -666,7 +646,7 @@ pub async fn new(
                 output_dir: log_source.output_dir().to_owned(),
                 openvmm_path: openvmm_path.clone(),
                 vtl2_vsock_path,
-                _vsock_path: vsock_path,
+                vsock_path,
                 properties,
             },
 
@@ -706,7 +686,11 @@ enum VideoDevice {
 }
 
 impl PetriVmConfigSetupCore<'_> {
-    fn configure_serial(&self, logger: &PetriLogSource) -> anyhow::Result<SerialData> {
+    fn configure_serial(
+        &self,
+        logger: &PetriLogSource,
+        guest_panic: &GuestPanicMessage,
+    ) -> anyhow::Result<SerialData> {
         let mut serial_tasks = Vec::new();
 
         let serial0_log_file = logger.log_file(match self.firmware {
@@ -721,7 +705,12 @@ fn configure_serial(&self, logger: &PetriLogSource) -> anyhow::Result<SerialData
         let (serial0_read, serial0_write) = serial0_host.split();
         let serial0_task = self.driver.spawn(
             "serial0-console",
-            crate::log_task(serial0_log_file, serial0_read, "serial0-console"),
+            crate::guest_log_task(
+                serial0_log_file,
+                serial0_read,
+                "serial0-console",
+                guest_panic.clone(),
+            ),
         );
         serial_tasks.push(serial0_task);
 
@@ -846,8 +835,9 @@ fn load_firmware(&self) -> anyhow::Result<LoadMode> {
                     guest: _, // load_boot_disk
                     uefi_config:
                         UefiConfig {
-                            secure_boot_enabled: _,  // new
-                            secure_boot_template: _, // new
+                            secure_boot_enabled: _,     // new
+                            secure_boot_template: _,    // new
+                            custom_secure_boot_vars: _, // new
                             disable_frontpage,
                             default_boot_always_attempt,
                             enable_vpci_boot,
@@ -1008,6 +998,7 @@ async fn config_openhcl_vmbus_devices(
             UefiConfig {
                 secure_boot_enabled,
                 secure_boot_template,
+                custom_secure_boot_vars,
                 disable_frontpage,
                 default_boot_always_attempt,
                 enable_vpci_boot,
@@ -1025,6 +1016,9 @@ async fn config_openhcl_vmbus_devices(
             }
             _ => anyhow::bail!("not a supported openhcl firmware config"),
         };
+        if custom_secure_boot_vars.is_some() {
+            anyhow::bail!("custom secure boot variables are not supported with OpenHCL");
+        }
 
         let test_gsp_by_id = matches!(
             self.vmgs.encryption_policy(),
@@ -1063,6 +1057,7 @@ async fn config_openhcl_vmbus_devices(
             enable_battery: false,
             no_persistent_secrets: self.tpm_config.as_ref().is_some_and(|c| c.no_persistent_secrets),
             igvm_attest_test_config: None,
+            vtl2_settings_test_config: None,
             test_gsp_by_id,
             efi_diagnostics_log_level: match efi_diagnostics_log_level {
                 EfiDiagnosticsLogLevel::Default => {
@@ -1177,6 +1172,32 @@ async fn make_device_worker(&self, name: &str) -> anyhow::Result<mesh_worker::Wo
     }
 }
 
+/// Returns the custom UEFI variables for `config`: the secure boot template,
+/// with any custom secure boot variables applied on top.
+fn uefi_custom_vars(arch: MachineArch, config: &UefiConfig) -> anyhow::Result<CustomVars> {
+    let base_vars = match (arch, config.secure_boot_template) {
+        (MachineArch::X86_64, Some(SecureBootTemplate::MicrosoftWindows)) => {
+            hyperv_secure_boot_templates::x64::microsoft_windows()
+        }
+        (MachineArch::X86_64, Some(SecureBootTemplate::MicrosoftUefiCertificateAuthority)) => {
+            hyperv_secure_boot_templates::x64::microsoft_uefi_ca()
+        }
+        (MachineArch::Aarch64, Some(SecureBootTemplate::MicrosoftWindows)) => {
+            hyperv_secure_boot_templates::aarch64::microsoft_windows()
+        }
+        (MachineArch::Aarch64, Some(SecureBootTemplate::MicrosoftUefiCertificateAuthority)) => {
+            hyperv_secure_boot_templates::aarch64::microsoft_uefi_ca()
+        }
+        (_, None) => CustomVars::default(),
+    };
+    match &config.custom_secure_boot_vars {
+        Some(delta) => base_vars
+            .apply_delta(delta.clone())
+            .context("failed to apply custom secure boot variables"),
+        None => Ok(base_vars),
+    }
+}
+
 fn spawn_dump_handler(driver: &DefaultDriver, logger: &PetriLogSource) -> GuestCrashDeviceHandle {
     let (send, mut recv) = mesh::channel();
     let handle = GuestCrashDeviceHandle {
@@ -1306,6 +1327,7 @@ async fn vmbus_storage_controllers_to_openvmm(
                         devices,
                         requests: None,
                         poll_mode_queue_depth: None,
+                        max_protocol_version: controller.scsi_max_protocol_version,
                     }
                     .into_resource(),
                 ));
@@ -1333,6 +1355,7 @@ async fn vmbus_storage_controllers_to_openvmm(
                         msix_count: 64,
                         namespaces,
                         requests: None,
+                        boot_partitions: None,
                     }
                     .into_resource(),
                 });
@@ -19,6 +19,7 @@
 pub use hugetlb::HUGETLB_2MB_PAGE_SIZE;
 #[cfg(target_os = "linux")]
 pub use hugetlb::ensure_2mb_hugetlb_pages;
+pub use runtime::GuestVsockListener;
 pub use runtime::OpenVmmFramebufferAccess;
 pub use runtime::OpenVmmInspector;
 pub use runtime::PetriVmOpenVmm;
@@ -26,6 +27,7 @@
 use crate::Disk;
 use crate::DiskPath;
 use crate::Firmware;
+use crate::GuestPanicMessage;
 use crate::ModifyFn;
 use crate::OpenHclServicingFlags;
 use crate::OpenvmmLogConfig;
@@ -75,7 +77,7 @@
 use vmgs_resources::VmgsResource;
 
 /// The instance guid for the MANA nic automatically added when specifying `PetriVmConfigOpenVmm::with_nic`
-const MANA_INSTANCE: Guid = guid::guid!("f9641cf4-d915-4743-a7d8-efa75db7b85a");
+pub const MANA_INSTANCE: Guid = guid::guid!("f9641cf4-d915-4743-a7d8-efa75db7b85a");
 
 /// The MAC address used by the NIC assigned with [`PetriVmConfigOpenVmm::with_nic`].
 pub const NIC_MAC_ADDRESS: MacAddress = MacAddress::new([0x00, 0x15, 0x5D, 0x12, 0x12, 0x12]);
@@ -178,6 +180,7 @@ pub struct PetriVmConfigOpenVmm {
 /// Various channels and resources used to interact with the VM while it is running.
 struct PetriVmResourcesOpenVmm {
     log_stream_tasks: Vec<Task<anyhow::Result<()>>>,
+    guest_panic: GuestPanicMessage,
     firmware_event_recv: Receiver<FirmwareEvent>,
     shutdown_ic_send: Sender<ShutdownRpc>,
     kvp_ic_send: Sender<hyperv_ic_resources::kvp::KvpConnectRpc>,
@@ -193,7 +196,7 @@ struct PetriVmResourcesOpenVmm {
 
     // TempPaths that cannot be dropped until the end.
     vtl2_vsock_path: Option<TempPath>,
-    _vsock_path: TempPath,
+    vsock_path: TempPath,
 
     // properties needed at runtime
     properties: PetriVmProperties,
@@ -205,6 +208,7 @@ async fn memdiff_disk(path: &Path) -> anyhow::Result<Resource<DiskHandleKind>> {
         OpenDiskOptions {
             read_only: true,
             direct: false,
+            change_tracking: false,
         },
     )
     .await
@@ -312,6 +316,7 @@ async fn petri_disk_to_openvmm(disk: &Disk) -> anyhow::Result<Resource<DiskHandl
                 OpenDiskOptions {
                     read_only: false,
                     direct: false,
+                    change_tracking: false,
                 },
             )
             .await?
@@ -322,6 +327,7 @@ async fn petri_disk_to_openvmm(disk: &Disk) -> anyhow::Result<Resource<DiskHandl
                 OpenDiskOptions {
                     read_only: false,
                     direct: false,
+                    change_tracking: false,
                 },
             )
             .await?
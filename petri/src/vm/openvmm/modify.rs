@@ -17,12 +17,14 @@
 use gdma_resources::GdmaDeviceHandle;
 use gdma_resources::VportDefinition;
 use get_resources::ged::IgvmAttestTestConfig;
+use get_resources::ged::Vtl2SettingsTestConfig;
 use guid::Guid;
 use net_backend_resources::mac_address::MacAddress;
 use nvme_resources::NamespaceDefinition;
 use nvme_resources::NvmeControllerHandle;
 use openvmm_defs::config::Config;
 use openvmm_defs::config::DeviceVtl;
+use openvmm_defs::config::GicMsiConfig;
 use openvmm_defs::config::LoadMode;
 use openvmm_defs::config::PcieDeviceConfig;
 use openvmm_defs::config::PcieMmioRangeConfig;
@@ -32,6 +34,7 @@
 use openvmm_defs::config::SmmuInstanceConfig;
 use openvmm_defs::config::VpciDeviceConfig;
 use openvmm_defs::config::Vtl2BaseAddressType;
+use std::path::Path;
 use vm_resource::IntoResource;
 use vmotherboard::ChipsetDeviceHandle;
 
@@ -84,13 +87,48 @@ pub fn with_igvm_attest_test_config(mut self, config: IgvmAttestTestConfig) -> S
         self
     }
 
+    /// Set test config for faults the GED injects into the VTL2 settings
+    /// exchanges
+    pub fn with_vtl2_settings_test_config(mut self, config: Vtl2SettingsTestConfig) -> Self {
+        if !self.resources.properties.is_openhcl {
+            panic!("VTL2 settings test config is only supported for OpenHCL.")
+        };
+
+        let ged = self
+            .ged
+            .as_mut()
+            .expect("No GED to configure VTL2 settings");
+
+        ged.vtl2_settings_test_config = Some(config);
+
+        self
+    }
+
     /// Enable a synthnic for the VM.
     ///
     /// Uses a mana emulator and the paravisor if a paravisor is present.
-    pub fn with_nic(mut self) -> Self {
+    pub fn with_nic(self) -> Self {
+        self.with_consomme_nic(None, None)
+    }
+
+    /// Enable a synthnic for the VM, like [`Self::with_nic`], whose backend
+    /// serves `tftp_root` over TFTP and tells the guest to network boot
+    /// `boot_file` from it.
+    pub fn with_network_boot_nic(self, tftp_root: &Path, boot_file: &str) -> Self {
+        self.with_consomme_nic(
+            Some(tftp_root.to_string_lossy().into_owned()),
+            Some(boot_file.to_owned()),
+        )
+    }
+
+    fn with_consomme_nic(mut self, tftp_root: Option<String>, boot_file: Option<String>) -> Self {
         let endpoint = net_backend_resources::consomme::ConsommeHandle {
             cidr: None,
+            guest_ip: None,
             ports: Vec::new(),
+            nameservers: Vec::new(),
+            tftp_root,
+            boot_file,
         }
         .into_resource();
         if let Some(vtl2_settings) = self.runtime_config.vtl2_settings.as_mut() {
@@ -134,7 +172,11 @@ pub fn with_nic(mut self) -> Self {
     pub fn with_pcie_nic(mut self, port_name: &str, mac_address: MacAddress) -> Self {
         let endpoint = net_backend_resources::consomme::ConsommeHandle {
             cidr: None,
+            guest_ip: None,
             ports: Vec::new(),
+            nameservers: Vec::new(),
+            tftp_root: None,
+            boot_file: None,
         }
         .into_resource();
         self.config.pcie_devices.push(PcieDeviceConfig {
@@ -169,6 +211,7 @@ pub fn with_pcie_nvme(mut self, port_name: &str, subsystem_id: Guid) -> Self {
                     read_only: false,
                 }],
                 requests: None,
+                boot_partitions: None,
             }
             .into_resource(),
         });
@@ -183,7 +226,11 @@ pub fn with_pcie_nvme(mut self, port_name: &str, subsystem_id: Guid) -> Self {
     pub fn with_virtio_nic(mut self, port_name: &str) -> Self {
         let endpoint = net_backend_resources::consomme::ConsommeHandle {
             cidr: None,
+            guest_ip: None,
             ports: Vec::new(),
+            nameservers: Vec::new(),
+            tftp_root: None,
+            boot_file: None,
         }
         .into_resource();
 
@@ -194,6 +241,7 @@ pub fn with_virtio_nic(mut self, port_name: &str) -> Self {
                     max_queues: None,
                     mac_address: NIC_MAC_ADDRESS,
                     endpoint,
+                    busy_poll: false,
                 }
                 .into_resource(),
             )
@@ -332,6 +380,34 @@ pub fn with_smmu(mut self, rc_names: &[&str]) -> Self {
         self
     }
 
+    /// Select the GIC MSI controller used for PCIe MSIs (aarch64 only).
+    pub fn with_gic_msi(mut self, gic_msi: GicMsiConfig) -> Self {
+        let arch = self
+            .config
+            .processor_topology
+            .arch
+            .as_mut()
+            .expect("arch topology not set");
+
+        match arch {
+            openvmm_defs::config::ArchTopologyConfig::Aarch64(aarch64) => {
+                aarch64.gic_msi = gic_msi;
+            }
+            _ => panic!("GIC MSI controllers are only supported on aarch64"),
+        }
+        self
+    }
+
+    /// Record the guest memory accesses made by PCI devices, so that the test
+    /// can check them with
+    /// [`PetriVmOpenVmm::device_memory_accesses`](super::PetriVmOpenVmm::device_memory_accesses).
+    ///
+    /// Device accesses are much slower with tracking enabled.
+    pub fn with_device_memory_tracking(mut self) -> Self {
+        self.config.track_device_memory = true;
+        self
+    }
+
     /// This is intended for special one-off use cases. As soon as something
     /// is needed in multiple tests we should consider making it a supported
     /// pattern.
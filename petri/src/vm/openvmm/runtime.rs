@@ -5,6 +5,7 @@
 
 use super::PetriVmResourcesOpenVmm;
 use crate::OpenHclServicingFlags;
+use crate::PetriGuestCrash;
 use crate::PetriHaltReason;
 use crate::PetriHaltReasonDetail;
 use crate::PetriVmFramebufferAccess;
@@ -17,6 +18,8 @@
 use anyhow::Context;
 use async_trait::async_trait;
 use framebuffer::View;
+use futures::AsyncRead;
+use futures::AsyncWrite;
 use futures::FutureExt;
 use futures_concurrency::future::Race;
 use get_resources::ged::FirmwareEvent;
@@ -27,14 +30,22 @@
 use mesh::rpc::RpcError;
 use mesh::rpc::RpcSend;
 use mesh_process::Mesh;
+use openvmm_defs::config::DeviceVtl;
+use openvmm_defs::rpc::DeviceMemoryAccesses;
 use openvmm_defs::rpc::PulseSaveRestoreError;
+use pal_async::DefaultDriver;
 use pal_async::socket::PolledSocket;
 use petri_artifacts_core::ResolvedArtifact;
 use pipette_client::PipetteClient;
+use std::collections::VecDeque;
 use std::future::Future;
+use std::ops::Range;
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
+use tempfile::TempPath;
+use unix_socket::UnixListener;
+use unix_socket::UnixStream;
 use vmm_core_defs::HaltReason;
 use vtl2_settings_proto::Vtl2Settings;
 
@@ -89,6 +100,16 @@ async fn wait_for_halt(&mut self, allow_reset: bool) -> anyhow::Result<PetriHalt
             HaltReason::TripleFault { .. } => PetriHaltReason::TripleFault,
             _ => PetriHaltReason::Other,
         };
+        let crash = match halt_reason {
+            HaltReason::GuestCrash { parameters, .. } => Some(PetriGuestCrash {
+                bugcheck_code: Some(parameters[0]),
+                parameters: parameters[1..].try_into().unwrap(),
+                panic_message: None,
+            }),
+            _ => None,
+        };
+
+        let panic_message = self.inner.resources.guest_panic.take().await;
 
         if allow_reset && reason == PetriHaltReason::Reset {
             self.reset().await?
@@ -97,7 +118,9 @@ async fn wait_for_halt(&mut self, allow_reset: bool) -> anyhow::Result<PetriHalt
         Ok(PetriHaltReasonDetail {
             reason,
             detail: format!("{halt_reason:?}"),
-        })
+            crash,
+        }
+        .with_panic_message(panic_message))
     }
 
     async fn wait_for_agent(&mut self, set_high_vtl: bool) -> anyhow::Result<PipetteClient> {
@@ -117,6 +140,10 @@ async fn wait_for_boot_event(&mut self) -> anyhow::Result<FirmwareEvent> {
         Self::wait_for_boot_event(self).await
     }
 
+    async fn wait_for_boot_phase(&mut self, phase: FirmwareEvent) -> anyhow::Result<()> {
+        Self::wait_for_boot_phase(self, phase).await
+    }
+
     async fn wait_for_enlightened_shutdown_ready(&mut self) -> anyhow::Result<()> {
         Self::wait_for_enlightened_shutdown_ready(self)
             .await
@@ -169,6 +196,14 @@ async fn reset(&mut self) -> anyhow::Result<()> {
         Self::reset(self).await
     }
 
+    async fn inject_nmi(&mut self, vp: u32) -> anyhow::Result<()> {
+        Self::inject_nmi(self, vp).await
+    }
+
+    async fn inject_machine_check(&mut self, vp: u32) -> anyhow::Result<()> {
+        Self::inject_machine_check(self, vp).await
+    }
+
     async fn set_vtl2_settings(&mut self, settings: &Vtl2Settings) -> anyhow::Result<()> {
         Self::set_vtl2_settings(self, settings).await
     }
@@ -204,6 +239,10 @@ pub(super) struct PetriVmInner {
     /// Used to skip re-mounting after save/restore (where guest state is
     /// preserved) while still mounting after a full reset/reboot.
     pub(super) cidata_mounted: bool,
+    /// Boot result events received while waiting for a boot phase.
+    pub(super) pending_boot_events: VecDeque<FirmwareEvent>,
+    /// Boot phases reached since the VM was started or last reset.
+    pub(super) reached_boot_phases: Vec<FirmwareEvent>,
     pub(super) pid: i32,
 }
 
@@ -245,6 +284,40 @@ pub fn vtl2_vsock_path(&self) -> anyhow::Result<&Path> {
             .context("VM is not configured with OpenHCL")
     }
 
+    /// Get the path to the VTL 0 vsock socket.
+    ///
+    /// Connections to guest vsock ports are multiplexed over this socket
+    /// using the hybrid vsock protocol, whether the guest uses vmbus
+    /// hvsockets or virtio-vsock.
+    pub fn vsock_path(&self) -> &Path {
+        &self.inner.resources.vsock_path
+    }
+
+    /// Connects to a listener on the guest's vsock port `port`.
+    pub async fn connect_guest_vsock(
+        &self,
+        port: u32,
+    ) -> anyhow::Result<impl AsyncRead + AsyncWrite + Unpin + Send + use<>> {
+        diag_client::connect_hybrid_vsock(&self.inner.resources.driver, self.vsock_path(), port)
+            .await
+            .with_context(|| format!("failed to connect to guest vsock port {port}"))
+    }
+
+    /// Listens for guest connections to the host's vsock port `port`.
+    ///
+    /// Fails if something is already listening on the port, such as the
+    /// pipette listener.
+    pub fn listen_guest_vsock(&self, port: u32) -> anyhow::Result<GuestVsockListener> {
+        let path = format!("{}_{port}", self.vsock_path().display());
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("failed to listen on host vsock port {port}"))?;
+        Ok(GuestVsockListener {
+            listener: PolledSocket::new(&self.inner.resources.driver, listener)?,
+            driver: self.inner.resources.driver.clone(),
+            path: TempPath::from_path(path),
+        })
+    }
+
     /// Get the PID of the openvmm child process.
     pub fn pid(&self) -> i32 {
         self.inner.pid
@@ -255,6 +328,11 @@ pub fn pid(&self) -> i32 {
         /// returns that status.
         pub async fn wait_for_boot_event(&mut self) -> anyhow::Result<FirmwareEvent>
     );
+    petri_vm_fn!(
+        /// Waits for the firmware to report that it has reached the given boot
+        /// phase, returning immediately if it already has.
+        pub async fn wait_for_boot_phase(&mut self, phase: FirmwareEvent) -> anyhow::Result<()>
+    );
     petri_vm_fn!(
         /// Waits for the Hyper-V shutdown IC to be ready, returning a receiver
         /// that will be closed when it is no longer ready.
@@ -283,6 +361,20 @@ pub async fn restore_openhcl(
             &mut self
         ) -> anyhow::Result<()>
     );
+    petri_vm_fn!(
+        /// Arms a host failure to simulate during the next OpenHCL servicing
+        /// operation.
+        pub async fn inject_servicing_fault(
+            &mut self,
+            fault: get_resources::ged::ServicingFault
+        ) -> anyhow::Result<()>
+    );
+    petri_vm_fn!(
+        /// Disarms any servicing fault armed by `inject_servicing_fault`.
+        pub async fn clear_servicing_fault(
+            &mut self
+        ) -> anyhow::Result<()>
+    );
     petri_vm_fn!(
         /// Updates the command line parameters of the running VM.
         pub async fn update_command_line(
@@ -306,10 +398,61 @@ pub async fn remove_pcie_device(
             port_name: String
         ) -> anyhow::Result<()>
     );
+    petri_vm_fn!(
+        /// Revokes the channel of the VTL0 vmbus device with the given
+        /// instance ID and then offers it again, to test channel teardown.
+        pub async fn reoffer_vmbus_device(
+            &mut self,
+            instance_id: guid::Guid
+        ) -> anyhow::Result<()>
+    );
+    petri_vm_fn!(
+        /// Removes the OpenHCL VPCI device on the bus with the given instance
+        /// ID the way the host does when it revokes a VF: OpenHCL is told to
+        /// prepare for removal, and the bus is revoked and offered again once
+        /// OpenHCL reports the device unbound. Returns after OpenHCL reports
+        /// the device bound again.
+        pub async fn revoke_and_reoffer_openhcl_vf(
+            &mut self,
+            instance_id: guid::Guid
+        ) -> anyhow::Result<()>
+    );
     petri_vm_fn!(
         /// Resets the hardware state of the VM, simulating a power cycle.
         pub async fn reset(&mut self) -> anyhow::Result<()>
     );
+    petri_vm_fn!(
+        /// Injects a non-maskable interrupt into the given VTL0 processor.
+        pub async fn inject_nmi(&mut self, vp: u32) -> anyhow::Result<()>
+    );
+    petri_vm_fn!(
+        /// Injects a machine check exception into the given VTL0 processor.
+        pub async fn inject_machine_check(&mut self, vp: u32) -> anyhow::Result<()>
+    );
+    petri_vm_fn!(
+        /// Returns the PCI device accesses to the guest memory in `range`
+        /// recorded since the VM started or the last
+        /// [`clear_device_memory_accesses`](Self::clear_device_memory_accesses).
+        ///
+        /// Requires [`PetriVmConfigOpenVmm::with_device_memory_tracking`](super::PetriVmConfigOpenVmm::with_device_memory_tracking).
+        pub async fn device_memory_accesses(
+            &mut self,
+            range: Range<u64>
+        ) -> anyhow::Result<DeviceMemoryAccesses>
+    );
+    petri_vm_fn!(
+        /// Forgets the recorded PCI device accesses to guest memory.
+        pub async fn clear_device_memory_accesses(&mut self) -> anyhow::Result<()>
+    );
+    petri_vm_fn!(
+        /// Fails if any PCI device accessed the guest memory in `range`, or
+        /// still has it locked, since the VM started or the last
+        /// [`clear_device_memory_accesses`](Self::clear_device_memory_accesses).
+        pub async fn assert_device_memory_untouched(
+            &mut self,
+            range: Range<u64>
+        ) -> anyhow::Result<()>
+    );
     petri_vm_fn!(
         /// Wait for a connection from a pipette agent
         pub async fn wait_for_agent(&mut self, set_high_vtl: bool) -> anyhow::Result<PipetteClient>
@@ -413,11 +556,52 @@ enum Either<T> {
 
 impl PetriVmInner {
     async fn wait_for_boot_event(&mut self) -> anyhow::Result<FirmwareEvent> {
-        self.resources
+        if let Some(event) = self.pending_boot_events.pop_front() {
+            return Ok(event);
+        }
+        loop {
+            let event = self.next_firmware_event().await?;
+            if !event.is_boot_phase() {
+                break Ok(event);
+            }
+        }
+    }
+
+    async fn wait_for_boot_phase(&mut self, phase: FirmwareEvent) -> anyhow::Result<()> {
+        anyhow::ensure!(phase.is_boot_phase(), "{phase:?} is not a boot phase");
+        let properties = &self.resources.properties;
+        anyhow::ensure!(
+            !properties.is_openhcl && !properties.is_pcat && !properties.is_linux_direct,
+            "boot phases are only reported by UEFI without OpenHCL"
+        );
+        while !self.reached_boot_phases.contains(&phase) {
+            let event = self.next_firmware_event().await?;
+            if !event.is_boot_phase() {
+                self.pending_boot_events.push_back(event);
+            }
+        }
+        Ok(())
+    }
+
+    /// Receives the next firmware event, recording any boot phases so that
+    /// the last phase reached can be reported if the boot hangs.
+    async fn next_firmware_event(&mut self) -> anyhow::Result<FirmwareEvent> {
+        let event = self
+            .resources
             .firmware_event_recv
             .recv()
             .await
-            .context("Failed to get firmware boot event")
+            .with_context(|| {
+                format!(
+                    "Failed to get firmware boot event, boot phases reached: {:?}",
+                    self.reached_boot_phases
+                )
+            })?;
+        if event.is_boot_phase() {
+            tracing::info!(?event, "Reached boot phase");
+            self.reached_boot_phases.push(event);
+        }
+        Ok(event)
     }
 
     async fn wait_for_enlightened_shutdown_ready(
@@ -506,6 +690,82 @@ async fn remove_pcie_device(&mut self, port_name: String) -> anyhow::Result<()>
         self.worker.remove_pcie_device(port_name).await
     }
 
+    async fn reoffer_vmbus_device(&mut self, instance_id: guid::Guid) -> anyhow::Result<()> {
+        self.worker.reoffer_vmbus_device(instance_id).await
+    }
+
+    async fn revoke_and_reoffer_openhcl_vf(
+        &mut self,
+        instance_id: guid::Guid,
+    ) -> anyhow::Result<()> {
+        let ged_send = self
+            .resources
+            .ged_send
+            .as_ref()
+            .context("openhcl not configured")?;
+
+        tracing::info!(%instance_id, "Revoking OpenHCL VF");
+        ged_send
+            .call(
+                get_resources::ged::GuestEmulationRequest::PrepareVpciDeviceRemoval,
+                instance_id,
+            )
+            .await?;
+        ged_send
+            .call(
+                get_resources::ged::GuestEmulationRequest::WaitForVpciBindingState,
+                (instance_id, false),
+            )
+            .await?;
+
+        tracing::info!(%instance_id, "Offering OpenHCL VF again");
+        self.worker
+            .reoffer_vpci_device(DeviceVtl::Vtl2, instance_id)
+            .await?;
+        ged_send
+            .call(
+                get_resources::ged::GuestEmulationRequest::WaitForVpciBindingState,
+                (instance_id, true),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn inject_nmi(&mut self, vp: u32) -> anyhow::Result<()> {
+        tracing::info!(vp, "Injecting NMI");
+        self.worker.inject_nmi(vp).await
+    }
+
+    async fn inject_machine_check(&mut self, vp: u32) -> anyhow::Result<()> {
+        tracing::info!(vp, "Injecting machine check");
+        self.worker.inject_machine_check(vp).await
+    }
+
+    async fn device_memory_accesses(
+        &mut self,
+        range: Range<u64>,
+    ) -> anyhow::Result<DeviceMemoryAccesses> {
+        self.worker.device_memory_accesses(range).await
+    }
+
+    async fn clear_device_memory_accesses(&mut self) -> anyhow::Result<()> {
+        self.worker.clear_device_memory_accesses().await
+    }
+
+    async fn assert_device_memory_untouched(&mut self, range: Range<u64>) -> anyhow::Result<()> {
+        let accesses = self.worker.device_memory_accesses(range.clone()).await?;
+        if accesses.touched {
+            anyhow::bail!(
+                "devices touched guest memory {:#x}..{:#x}: {:#x?}",
+                range.start,
+                range.end,
+                accesses.accesses
+            );
+        }
+        Ok(())
+    }
+
     async fn restore_openhcl(&self) -> anyhow::Result<()> {
         let ged_send = self
             .resources
@@ -516,6 +776,45 @@ async fn restore_openhcl(&self) -> anyhow::Result<()> {
         self.worker.restore_openhcl(ged_send).await
     }
 
+    async fn inject_servicing_fault(
+        &mut self,
+        fault: get_resources::ged::ServicingFault,
+    ) -> anyhow::Result<()> {
+        let ged_send = self
+            .resources
+            .ged_send
+            .as_ref()
+            .context("openhcl not configured")?;
+
+        tracing::info!(?fault, "Injecting servicing fault");
+        ged_send
+            .call(
+                get_resources::ged::GuestEmulationRequest::InjectServicingFault,
+                Some(fault),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn clear_servicing_fault(&mut self) -> anyhow::Result<()> {
+        let ged_send = self
+            .resources
+            .ged_send
+            .as_ref()
+            .context("openhcl not configured")?;
+
+        tracing::info!("Clearing servicing fault");
+        ged_send
+            .call(
+                get_resources::ged::GuestEmulationRequest::InjectServicingFault,
+                None,
+            )
+            .await?;
+
+        Ok(())
+    }
+
     async fn set_vtl2_settings(&self, settings: &Vtl2Settings) -> anyhow::Result<()> {
         let ged_send = self
             .resources
@@ -538,6 +837,8 @@ async fn reset(&mut self) -> anyhow::Result<()> {
         self.worker.reset().await?;
         // Guest state is lost on reset, so CIDATA needs to be remounted.
         self.cidata_mounted = false;
+        self.pending_boot_events.clear();
+        self.reached_boot_phases.clear();
         // On linux direct, pipette won't auto-start unless it is the init
         // process. When it isn't, restart it over serial. (When pipette runs
         // as PID 1 via rdinit=/pipette, linux_direct_serial_agent is None, so
@@ -668,6 +969,33 @@ async fn launch_linux_direct_pipette(&mut self) -> anyhow::Result<()> {
     }
 }
 
+/// A listener for guest connections to a host vsock port, returned by
+/// [`PetriVmOpenVmm::listen_guest_vsock`].
+///
+/// The listener's socket file is removed when it is dropped.
+pub struct GuestVsockListener {
+    listener: PolledSocket<UnixListener>,
+    driver: DefaultDriver,
+    path: TempPath,
+}
+
+impl GuestVsockListener {
+    /// Waits for the next guest connection.
+    pub async fn accept(&mut self) -> anyhow::Result<PolledSocket<UnixStream>> {
+        let (conn, _) = self
+            .listener
+            .accept()
+            .await
+            .context("failed to accept guest vsock connection")?;
+        Ok(PolledSocket::new(&self.driver, conn)?)
+    }
+
+    /// The path to the listener's socket file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
 /// Interface for inspecting OpenVMM
 pub struct OpenVmmInspector {
     worker: Arc<Worker>,
@@ -116,6 +116,8 @@ async fn run_core(self) -> anyhow::Result<(PetriVmOpenVmm, PetriVmRuntimeConfig)
                 worker,
                 framebuffer_view,
                 cidata_mounted: false,
+                pending_boot_events: Default::default(),
+                reached_boot_phases: Vec::new(),
                 pid,
             },
             halt_notif,
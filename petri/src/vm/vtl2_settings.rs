@@ -248,6 +248,7 @@ pub fn build(mut self) -> vtl2_settings_proto::StorageController {
             protocol: protocol.into(),
             luns: self.luns.drain(..).map(|l| l.build()).collect(),
             io_queue_depth: self.io_queue_depth,
+            telemetry: None,
         }
     }
 }
@@ -8,6 +8,8 @@
 use mesh_worker::WorkerHandle;
 use mesh_worker::WorkerHost;
 use openvmm_defs::config::Config;
+use openvmm_defs::config::DeviceVtl;
+use openvmm_defs::rpc::DeviceMemoryAccesses;
 use openvmm_defs::rpc::PulseSaveRestoreError;
 use openvmm_defs::rpc::VmRpc;
 use openvmm_defs::worker::VM_WORKER;
@@ -65,6 +67,16 @@ pub(crate) async fn reset(&self) -> anyhow::Result<()> {
         Ok(())
     }
 
+    pub(crate) async fn inject_nmi(&self, vp: u32) -> anyhow::Result<()> {
+        self.rpc.call_failable(VmRpc::Nmi, vp).await?;
+        Ok(())
+    }
+
+    pub(crate) async fn inject_machine_check(&self, vp: u32) -> anyhow::Result<()> {
+        self.rpc.call_failable(VmRpc::MachineCheck, vp).await?;
+        Ok(())
+    }
+
     pub(crate) async fn pulse_save_restore(&self) -> Result<(), RpcError<PulseSaveRestoreError>> {
         self.rpc.call_failable(VmRpc::PulseSaveRestore, ()).await
     }
@@ -119,6 +131,41 @@ pub(crate) async fn remove_pcie_device(&self, port_name: String) -> anyhow::Resu
         Ok(())
     }
 
+    pub(crate) async fn reoffer_vmbus_device(&self, instance_id: guid::Guid) -> anyhow::Result<()> {
+        self.rpc
+            .call_failable(VmRpc::ReofferVmbusDevice, (DeviceVtl::Vtl0, instance_id))
+            .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn reoffer_vpci_device(
+        &self,
+        vtl: DeviceVtl,
+        instance_id: guid::Guid,
+    ) -> anyhow::Result<()> {
+        self.rpc
+            .call_failable(VmRpc::ReofferVpciDevice, (vtl, instance_id))
+            .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn device_memory_accesses(
+        &self,
+        range: std::ops::Range<u64>,
+    ) -> anyhow::Result<DeviceMemoryAccesses> {
+        Ok(self
+            .rpc
+            .call_failable(VmRpc::QueryDeviceMemoryAccesses, (range.start, range.end))
+            .await?)
+    }
+
+    pub(crate) async fn clear_device_memory_accesses(&self) -> anyhow::Result<()> {
+        self.rpc
+            .call_failable(VmRpc::ClearDeviceMemoryAccesses, ())
+            .await?;
+        Ok(())
+    }
+
     pub(crate) async fn inspect_all(&self) -> inspect::Node {
         let mut inspection = inspect::inspect("", &self.handle);
         inspection.resolve().await;
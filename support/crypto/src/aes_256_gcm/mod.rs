@@ -69,7 +69,19 @@ pub fn cipher(
         data: &[u8],
         tag: &mut [u8],
     ) -> Result<Vec<u8>, Aes256GcmError> {
-        self.0.cipher(iv, data, tag)
+        self.0.cipher(iv, &[], data, tag)
+    }
+
+    /// Encrypts `data` using the provided `iv`, authenticating `aad` as
+    /// additional data, and produces the authentication tag in `tag`.
+    pub fn cipher_with_aad(
+        &mut self,
+        iv: &[u8; IV_LEN],
+        aad: &[u8],
+        data: &[u8],
+        tag: &mut [u8],
+    ) -> Result<Vec<u8>, Aes256GcmError> {
+        self.0.cipher(iv, aad, data, tag)
     }
 }
 
@@ -85,7 +97,19 @@ pub fn cipher(
         data: &[u8],
         tag: &[u8],
     ) -> Result<Vec<u8>, Aes256GcmError> {
-        self.0.cipher(iv, data, tag)
+        self.0.cipher(iv, &[], data, tag)
+    }
+
+    /// Decrypts `data` using the provided `iv` and verifies the
+    /// authentication `tag` over it and the additional data `aad`.
+    pub fn cipher_with_aad(
+        &mut self,
+        iv: &[u8; IV_LEN],
+        aad: &[u8],
+        data: &[u8],
+        tag: &[u8],
+    ) -> Result<Vec<u8>, Aes256GcmError> {
+        self.0.cipher(iv, aad, data, tag)
     }
 }
 
@@ -125,4 +149,36 @@ fn test_aes_256_gcm() {
         let dec_plain = dec_ctx.cipher(&iv, &cipher, &tag).unwrap();
         assert_eq!(dec_plain, plain);
     }
+
+    #[test]
+    fn test_aes_256_gcm_aad() {
+        let aes = Aes256Gcm::new(&[0x42; KEY_LEN]).unwrap();
+        let iv = [7; IV_LEN];
+        let plain = b"plaintext";
+        let aad = b"header";
+
+        let mut tag = [0; 16];
+        let cipher = aes
+            .encrypt()
+            .unwrap()
+            .cipher_with_aad(&iv, aad, plain, &mut tag)
+            .unwrap();
+
+        let dec_plain = aes
+            .decrypt()
+            .unwrap()
+            .cipher_with_aad(&iv, aad, &cipher, &tag)
+            .unwrap();
+        assert_eq!(dec_plain, plain);
+
+        // The additional data is authenticated.
+        aes.decrypt()
+            .unwrap()
+            .cipher_with_aad(&iv, b"other", &cipher, &tag)
+            .unwrap_err();
+        aes.decrypt()
+            .unwrap()
+            .cipher(&iv, &cipher, &tag)
+            .unwrap_err();
+    }
 }
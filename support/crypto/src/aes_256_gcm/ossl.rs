@@ -57,6 +57,7 @@ impl Aes256GcmEncCtxInner<'_> {
     pub fn cipher(
         &mut self,
         iv: &[u8; IV_LEN],
+        aad: &[u8],
         data: &[u8],
         tag: &mut [u8],
     ) -> Result<Vec<u8>, Aes256GcmError> {
@@ -64,6 +65,11 @@ pub fn cipher(
         self.ctx
             .encrypt_init(None, None, Some(iv))
             .map_err(|e| err(e, "setting iv for encryption"))?;
+        if !aad.is_empty() {
+            self.ctx
+                .cipher_update(aad, None)
+                .map_err(|e| err(e, "encrypting additional data"))?;
+        }
         let count = self
             .ctx
             .cipher_update(data, Some(&mut output))
@@ -84,6 +90,7 @@ impl Aes256GcmDecCtxInner<'_> {
     pub fn cipher(
         &mut self,
         iv: &[u8; IV_LEN],
+        aad: &[u8],
         data: &[u8],
         tag: &[u8],
     ) -> Result<Vec<u8>, Aes256GcmError> {
@@ -91,6 +98,11 @@ pub fn cipher(
         self.ctx
             .decrypt_init(None, None, Some(iv))
             .map_err(|e| err(e, "setting iv for decryption"))?;
+        if !aad.is_empty() {
+            self.ctx
+                .cipher_update(aad, None)
+                .map_err(|e| err(e, "decrypting additional data"))?;
+        }
         let count = self
             .ctx
             .cipher_update(data, Some(&mut output))
@@ -331,6 +331,46 @@ fn from(status: http::StatusCode) -> Self {
         }
     }
 
+    /// Adapts a [`PolledSocket`] to the tokio I/O traits used by `h2`.
+    struct Wrap<T>(T);
+
+    impl<T: AsSockRef + Read> tokio::io::AsyncRead for Wrap<PolledSocket<T>> {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            let n =
+                ready!(Pin::new(&mut self.get_mut().0).poll_read(cx, buf.initialize_unfilled()))?;
+            buf.advance(n);
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    impl<T: AsSockRef + Write> tokio::io::AsyncWrite for Wrap<PolledSocket<T>> {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &[u8],
+        ) -> std::task::Poll<Result<usize, std::io::Error>> {
+            Pin::new(&mut self.get_mut().0).poll_write(cx, buf)
+        }
+
+        fn poll_flush(
+            self: Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<(), std::io::Error>> {
+            Pin::new(&mut self.get_mut().0).poll_flush(cx)
+        }
+
+        fn poll_shutdown(
+            self: Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<(), std::io::Error>> {
+            Pin::new(&mut self.get_mut().0).poll_close(cx)
+        }
+    }
+
     impl Server {
         /// Runs the server using the gRPC transport, listening on `listener` and servicing connections until
         /// `cancel`.
@@ -340,6 +380,46 @@ pub async fn run_grpc(
             listener: impl Listener,
             cancel: mesh::OneshotReceiver<()>,
         ) -> anyhow::Result<()> {
+            let this = &*self;
+            this.accept_grpc(driver, listener, cancel, |conn| this.serve_grpc(Wrap(conn)))
+                .await
+        }
+
+        /// Runs the server using the gRPC transport over TLS, listening on
+        /// `listener` and servicing connections until `cancel`.
+        ///
+        /// Each connection is authenticated with `acceptor` before any
+        /// requests are processed.
+        #[cfg(feature = "grpc_tls")]
+        pub async fn run_grpc_tls(
+            &mut self,
+            driver: &(impl Driver + ?Sized),
+            listener: impl Listener,
+            acceptor: tokio_native_tls::native_tls::TlsAcceptor,
+            cancel: mesh::OneshotReceiver<()>,
+        ) -> anyhow::Result<()> {
+            let this = &*self;
+            let acceptor = &tokio_native_tls::TlsAcceptor::from(acceptor);
+            this.accept_grpc(driver, listener, cancel, |conn| async move {
+                let stream = acceptor
+                    .accept(Wrap(conn))
+                    .await
+                    .context("failed tls handshake")?;
+                this.serve_grpc(stream).await
+            })
+            .await
+        }
+
+        async fn accept_grpc<L: Listener, F>(
+            &self,
+            driver: &(impl Driver + ?Sized),
+            listener: L,
+            cancel: mesh::OneshotReceiver<()>,
+            serve: impl Fn(PolledSocket<L::Socket>) -> F,
+        ) -> anyhow::Result<()>
+        where
+            F: Future<Output = anyhow::Result<()>>,
+        {
             let mut listener = PolledSocket::new(driver, listener)?;
             let mut tasks = FuturesUnordered::new();
             let mut cancel = cancel.fuse();
@@ -350,8 +430,9 @@ pub async fn run_grpc(
                     _ = cancel => break,
                 };
                 if let Ok(conn) = conn.and_then(|(conn, _)| PolledSocket::new(driver, conn)) {
+                    let conn = serve(conn);
                     tasks.push(async {
-                        let _ = self.serve_grpc(conn).await.map_err(|err| {
+                        let _ = conn.await.map_err(|err| {
                             tracing::error!(
                                 error = err.as_ref() as &dyn std::error::Error,
                                 "connection error"
@@ -365,49 +446,9 @@ pub async fn run_grpc(
 
         async fn serve_grpc(
             &self,
-            stream: PolledSocket<impl AsSockRef + Read + Write>,
+            stream: impl tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
         ) -> anyhow::Result<()> {
-            struct Wrap<T>(T);
-
-            impl<T: AsSockRef + Read> tokio::io::AsyncRead for Wrap<PolledSocket<T>> {
-                fn poll_read(
-                    self: Pin<&mut Self>,
-                    cx: &mut std::task::Context<'_>,
-                    buf: &mut tokio::io::ReadBuf<'_>,
-                ) -> std::task::Poll<std::io::Result<()>> {
-                    let n = ready!(
-                        Pin::new(&mut self.get_mut().0).poll_read(cx, buf.initialize_unfilled())
-                    )?;
-                    buf.advance(n);
-                    std::task::Poll::Ready(Ok(()))
-                }
-            }
-
-            impl<T: AsSockRef + Write> tokio::io::AsyncWrite for Wrap<PolledSocket<T>> {
-                fn poll_write(
-                    self: Pin<&mut Self>,
-                    cx: &mut std::task::Context<'_>,
-                    buf: &[u8],
-                ) -> std::task::Poll<Result<usize, std::io::Error>> {
-                    Pin::new(&mut self.get_mut().0).poll_write(cx, buf)
-                }
-
-                fn poll_flush(
-                    self: Pin<&mut Self>,
-                    cx: &mut std::task::Context<'_>,
-                ) -> std::task::Poll<Result<(), std::io::Error>> {
-                    Pin::new(&mut self.get_mut().0).poll_flush(cx)
-                }
-
-                fn poll_shutdown(
-                    self: Pin<&mut Self>,
-                    cx: &mut std::task::Context<'_>,
-                ) -> std::task::Poll<Result<(), std::io::Error>> {
-                    Pin::new(&mut self.get_mut().0).poll_close(cx)
-                }
-            }
-
-            let mut conn = h2::server::handshake(Wrap(stream))
+            let mut conn = h2::server::handshake(stream)
                 .await
                 .context("failed http2 handshake")?;
 
@@ -607,6 +648,76 @@ async fn invoke_rpc(
                 .unwrap_or_else(|err| Err(status_from_err(Code::Internal, err))))
         }
     }
+
+    #[cfg(all(test, feature = "grpc_tls"))]
+    mod tests {
+        use super::Wrap;
+        use crate::Server;
+        use futures::future::Either;
+        use pal_async::DefaultPool;
+        use pal_async::socket::PolledSocket;
+        use prost::bytes::Bytes;
+        use std::pin::pin;
+        use test_with_tracing::test;
+        use tokio_native_tls::native_tls;
+
+        const CERT: &[u8] = include_bytes!("../test_data/localhost.crt");
+        const KEY: &[u8] = include_bytes!("../test_data/localhost.key");
+
+        #[test]
+        fn grpc_tls() {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            let identity = native_tls::Identity::from_pkcs8(CERT, KEY).unwrap();
+            let acceptor = native_tls::TlsAcceptor::new(identity).unwrap();
+            let (cancel_send, cancel_recv) = mesh::oneshot();
+            let server_thread = std::thread::spawn(move || {
+                DefaultPool::run_with(async |driver| {
+                    Server::new()
+                        .run_grpc_tls(&driver, listener, acceptor, cancel_recv)
+                        .await
+                })
+            });
+
+            let status = DefaultPool::run_with(async |driver| {
+                let conn = std::net::TcpStream::connect(addr).unwrap();
+                let conn = PolledSocket::new(&driver, conn).unwrap();
+                let connector = native_tls::TlsConnector::builder()
+                    .add_root_certificate(native_tls::Certificate::from_pem(CERT).unwrap())
+                    .build()
+                    .unwrap();
+                let stream = tokio_native_tls::TlsConnector::from(connector)
+                    .connect("localhost", Wrap(conn))
+                    .await
+                    .unwrap();
+                let (mut client, conn) = h2::client::handshake(stream).await.unwrap();
+
+                let request = async {
+                    let request = http::Request::post("/unknown.Service/Method")
+                        .header("content-type", "application/grpc")
+                        .header("te", "trailers")
+                        .body(())
+                        .unwrap();
+                    let (response, mut send) = client.send_request(request, false).unwrap();
+                    send.send_data(Bytes::from_static(&[0; 5]), true).unwrap();
+                    let response = response.await.unwrap();
+                    assert_eq!(response.status(), http::StatusCode::OK);
+                    let trailers = response.into_body().trailers().await.unwrap().unwrap();
+                    trailers["grpc-status"].clone()
+                };
+                match futures::future::select(pin!(request), pin!(conn)).await {
+                    Either::Left((status, _)) => status,
+                    Either::Right((r, _)) => panic!("connection closed: {r:?}"),
+                }
+            });
+            // The request made it through TLS to the gRPC layer, which
+            // reported the service as unimplemented.
+            assert_eq!(status, "12");
+
+            cancel_send.send(());
+            server_thread.join().unwrap().unwrap();
+        }
+    }
 }
 
 #[cfg(test)]
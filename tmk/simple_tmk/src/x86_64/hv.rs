@@ -0,0 +1,238 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Hyper-V reference time and synthetic timer tests.
+//!
+//! These are skipped when the VMM does not expose the Hyper-V interface.
+
+use super::apic::ApicMode;
+use crate::prelude::*;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::Ordering::Relaxed;
+use hvdef::HvFeatures;
+use hvdef::HvSynicStimerConfig;
+
+/// The synthetic timer period, in 100ns units.
+const TIMER_PERIOD: u64 = 10_000;
+
+/// How long to wait for timer interrupts before failing, in 100ns units.
+const TIMER_TIMEOUT: u64 = 10_000_000;
+
+const TIMER_VECTOR: u8 = 0x41;
+
+/// Returns the Hyper-V features, or `None` if the Hyper-V interface is not
+/// present.
+fn hv_features() -> Option<HvFeatures> {
+    let cpuid = |leaf| {
+        let r = core::arch::x86_64::__cpuid(leaf);
+        [r.eax, r.ebx, r.ecx, r.edx]
+    };
+    let hypervisor_present = cpuid(1)[2] & (1 << 31) != 0;
+    if !hypervisor_present
+        || cpuid(hvdef::HV_CPUID_FUNCTION_HV_INTERFACE)[0] != u32::from_le_bytes(*b"Hv#1")
+    {
+        log!("skipping: no Hyper-V interface");
+        return None;
+    }
+    Some(HvFeatures::from_cpuid(cpuid(
+        hvdef::HV_CPUID_FUNCTION_MS_HV_FEATURES,
+    )))
+}
+
+fn ref_time(s: &mut Scope<'_, '_>) -> u64 {
+    s.read_msr(hvdef::HV_X64_MSR_TIME_REF_COUNT).unwrap()
+}
+
+#[repr(C, align(4096))]
+struct ReferenceTscPage(hvdef::HvReferenceTscPage);
+
+static mut REFERENCE_TSC_PAGE: ReferenceTscPage = ReferenceTscPage(hvdef::HvReferenceTscPage {
+    tsc_sequence: 0,
+    reserved1: 0,
+    tsc_scale: 0,
+    tsc_offset: 0,
+    timeline_bias: 0,
+    tsc_multiplier: 0,
+    reserved2: [0; 507],
+});
+
+#[tmk_test]
+fn reference_tsc(t: TestContext<'_>) {
+    let Some(features) = hv_features() else {
+        return;
+    };
+    if !features.privileges().access_partition_reference_tsc() {
+        log!("skipping: reference TSC not available");
+        return;
+    }
+
+    let page = &raw mut REFERENCE_TSC_PAGE;
+    t.scope
+        .write_msr(
+            hvdef::HV_X64_MSR_REFERENCE_TSC,
+            hvdef::HvRegisterReferenceTsc::new()
+                .with_enable(true)
+                .with_gpn(page as u64 >> 12)
+                .into(),
+        )
+        .unwrap();
+
+    // Compare the time computed from the page against the reference time
+    // MSR, retrying if the page is being updated.
+    for _ in 0..10 {
+        // SAFETY: the page is only written by the hypervisor, and the
+        // identity map makes its address the same as its GPA.
+        let read = || unsafe { (&raw const (*page).0).read_volatile() };
+        let before = ref_time(t.scope);
+        let tsc_page = read();
+        // SAFETY: the TSC is safe to read.
+        let tsc = unsafe { core::arch::x86_64::_rdtsc() };
+        let after = ref_time(t.scope);
+        if tsc_page.tsc_sequence == hvdef::HV_REFERENCE_TSC_SEQUENCE_INVALID {
+            log!("reference TSC page is invalid, guests must use the MSR");
+            break;
+        }
+        if read().tsc_sequence != tsc_page.tsc_sequence {
+            continue;
+        }
+
+        let computed = (((tsc as u128 * tsc_page.tsc_scale as u128) >> 64) as i64)
+            .wrapping_add(tsc_page.tsc_offset) as u64;
+        log!("reference time: msr {before}..{after}, page {computed}");
+        // Allow for rounding in the scale.
+        let slack = 10;
+        assert!(
+            computed + slack >= before && computed <= after + slack,
+            "reference TSC page time {computed} outside of {before}..{after}"
+        );
+        break;
+    }
+
+    t.scope
+        .write_msr(hvdef::HV_X64_MSR_REFERENCE_TSC, 0)
+        .unwrap();
+}
+
+/// Returns true if direct-mode synthetic timers are available.
+fn direct_timers_available() -> bool {
+    let Some(features) = hv_features() else {
+        return false;
+    };
+    if !features.privileges().access_synthetic_timer_msrs() || !features.direct_synthetic_timers() {
+        log!("skipping: direct synthetic timers not available");
+        return false;
+    }
+    true
+}
+
+#[tmk_test]
+fn stimer_periodic(t: TestContext<'_>) {
+    if !direct_timers_available() {
+        return;
+    }
+    let apic = ApicMode::X2Apic;
+    apic.init(t.scope);
+
+    let fired = AtomicU32::new(0);
+    let isr = |_: &mut IsrContext<'_>| {
+        fired.fetch_add(1, Relaxed);
+    };
+
+    const EXPIRATIONS: u32 = 10;
+    t.scope.subscope(|s| {
+        s.set_isr(TIMER_VECTOR, &isr);
+        s.enable_interrupts();
+
+        let start = ref_time(s);
+        s.write_msr(hvdef::HV_X64_MSR_STIMER0_COUNT, TIMER_PERIOD)
+            .unwrap();
+        s.write_msr(
+            hvdef::HV_X64_MSR_STIMER0_CONFIG,
+            HvSynicStimerConfig::new()
+                .with_enabled(true)
+                .with_periodic(true)
+                .with_direct_mode(true)
+                .with_apic_vector(TIMER_VECTOR)
+                .into(),
+        )
+        .unwrap();
+
+        // Direct-mode timer interrupts need an EOI before the next one can
+        // be delivered.
+        let mut seen = 0;
+        let mut now = start;
+        while seen < EXPIRATIONS && now - start < TIMER_TIMEOUT {
+            let count = fired.load(Relaxed);
+            if count != seen {
+                seen = count;
+                apic.write(s, x86defs::apic::ApicRegister::EOI, 0);
+            }
+            now = ref_time(s);
+        }
+
+        s.write_msr(hvdef::HV_X64_MSR_STIMER0_CONFIG, 0).unwrap();
+        let elapsed = now - start;
+        log!(
+            "{seen} expirations in {elapsed} (period {TIMER_PERIOD}, average {})",
+            elapsed / u64::from(seen.max(1))
+        );
+        assert_eq!(seen, EXPIRATIONS, "timer did not fire often enough");
+        // Expirations stay on the timer's schedule, so they can be late but
+        // never early.
+        assert!(
+            elapsed >= TIMER_PERIOD * u64::from(EXPIRATIONS),
+            "timer fired early"
+        );
+    });
+}
+
+#[tmk_test]
+fn stimer_one_shot(t: TestContext<'_>) {
+    if !direct_timers_available() {
+        return;
+    }
+    let apic = ApicMode::X2Apic;
+    apic.init(t.scope);
+
+    let fired = AtomicU32::new(0);
+    let isr = |_: &mut IsrContext<'_>| {
+        fired.fetch_add(1, Relaxed);
+    };
+
+    t.scope.subscope(|s| {
+        s.set_isr(TIMER_VECTOR, &isr);
+        s.enable_interrupts();
+
+        s.write_msr(
+            hvdef::HV_X64_MSR_STIMER0_CONFIG,
+            HvSynicStimerConfig::new()
+                .with_auto_enable(true)
+                .with_direct_mode(true)
+                .with_apic_vector(TIMER_VECTOR)
+                .into(),
+        )
+        .unwrap();
+        // One-shot timers expire at an absolute reference time, and writing
+        // the count enables the timer.
+        let start = ref_time(s);
+        let due = start + TIMER_PERIOD;
+        s.write_msr(hvdef::HV_X64_MSR_STIMER0_COUNT, due).unwrap();
+        let config =
+            HvSynicStimerConfig::from(s.read_msr(hvdef::HV_X64_MSR_STIMER0_CONFIG).unwrap());
+        assert!(config.enabled(), "auto-enable did not enable the timer");
+
+        let mut now = start;
+        while fired.load(Relaxed) == 0 && now - start < TIMER_TIMEOUT {
+            now = ref_time(s);
+        }
+        assert_eq!(fired.load(Relaxed), 1, "timer did not fire");
+        apic.write(s, x86defs::apic::ApicRegister::EOI, 0);
+        log!("timer due at {due} fired by {now}");
+        assert!(now >= due, "timer fired early");
+
+        // The timer is disabled on expiration.
+        let config =
+            HvSynicStimerConfig::from(s.read_msr(hvdef::HV_X64_MSR_STIMER0_CONFIG).unwrap());
+        assert!(!config.enabled(), "one-shot timer still enabled");
+    });
+}
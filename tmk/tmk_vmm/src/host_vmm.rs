@@ -54,6 +54,7 @@ pub async fn run_host_vmm<H: Hypervisor>(
                 mem_layout: &self.state.memory_layout,
                 guest_memory: &guest_memory,
                 cpuid: &[],
+                cpuid_overrides: &[],
                 vtl0_alias_map: None,
             })
             .context("failed to build partition")?;
@@ -2,6 +2,8 @@
 // Licensed under the MIT License.
 
 use std::num::Wrapping;
+use thiserror::Error;
+use zerocopy::FromBytes;
 use zerocopy::IntoBytes;
 
 #[derive(Copy, Clone)]
@@ -98,6 +100,48 @@ fn checksum(data: &[u8]) -> Wrapping<u8> {
     sum
 }
 
+/// Errors when validating a caller-provided raw ACPI table.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RawTableError {
+    /// The table is too short to contain an ACPI header.
+    #[error("table is too short ({0:#x} bytes) to contain an ACPI header")]
+    TooShort(usize),
+    /// The header length does not match the size of the table.
+    #[error("table header length {header:#x} does not match table size {actual:#x}")]
+    LengthMismatch { header: u32, actual: usize },
+    /// The table is one that the builder generates itself.
+    #[error("{} tables cannot be provided as raw tables", String::from_utf8_lossy(.0))]
+    ReservedSignature([u8; 4]),
+}
+
+/// Validates a caller-provided raw ACPI table (such as an SSDT) so that it
+/// can be passed to [`Builder::append_raw`], and recomputes its checksum.
+///
+/// Tables that describe the table hierarchy itself (RSDT, XSDT, FADT, FACS)
+/// and the DSDT are rejected, since those are generated by the builder.
+pub fn finalize_raw_table(data: &mut [u8]) -> Result<(), RawTableError> {
+    let actual = data.len();
+    let (header, _) =
+        acpi_spec::Header::read_from_prefix(data).map_err(|_| RawTableError::TooShort(actual))?;
+    if matches!(
+        &header.signature,
+        b"RSDT" | b"XSDT" | b"FACP" | b"FACS" | b"DSDT"
+    ) {
+        return Err(RawTableError::ReservedSignature(header.signature));
+    }
+    let length = header.length.get();
+    if length as usize != actual {
+        return Err(RawTableError::LengthMismatch {
+            header: length,
+            actual,
+        });
+    }
+    let offset = std::mem::offset_of!(acpi_spec::Header, checksum);
+    data[offset] = 0;
+    data[offset] = (-checksum(data)).0;
+    Ok(())
+}
+
 impl Builder {
     pub fn new(base_addr: u64, oem: OemInfo) -> Self {
         Builder {
@@ -167,3 +211,54 @@ pub fn build(mut self) -> (Vec<u8>, Vec<u8>) {
         (rsdp.as_bytes().to_vec(), self.v)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_table(signature: &[u8; 4], body_len: usize) -> Vec<u8> {
+        let len = size_of::<acpi_spec::Header>() + body_len;
+        let header = acpi_spec::Header {
+            signature: *signature,
+            length: (len as u32).into(),
+            revision: 2,
+            checksum: 0x55,
+            oem_id: *b"OEMID ",
+            oem_tableid: *b"TABLEID ",
+            oem_revision: 1.into(),
+            creator_id: 0.into(),
+            creator_revision: 0.into(),
+        };
+        let mut v = header.as_bytes().to_vec();
+        v.extend((0..body_len).map(|i| i as u8));
+        v
+    }
+
+    #[test]
+    fn finalize_raw_table_fixes_checksum() {
+        let mut table = raw_table(b"SSDT", 13);
+        finalize_raw_table(&mut table).unwrap();
+        assert_eq!(checksum(&table), Wrapping(0));
+    }
+
+    #[test]
+    fn finalize_raw_table_rejects_bad_tables() {
+        assert_eq!(
+            finalize_raw_table(&mut [0; 8]),
+            Err(RawTableError::TooShort(8))
+        );
+
+        let mut table = raw_table(b"SSDT", 4);
+        table.push(0);
+        assert!(matches!(
+            finalize_raw_table(&mut table),
+            Err(RawTableError::LengthMismatch { .. })
+        ));
+
+        let mut table = raw_table(b"DSDT", 4);
+        assert_eq!(
+            finalize_raw_table(&mut table),
+            Err(RawTableError::ReservedSignature(*b"DSDT"))
+        );
+    }
+}
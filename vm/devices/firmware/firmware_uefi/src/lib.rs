@@ -325,6 +325,9 @@ async fn reset(&mut self) {
 
         self.service.nvram.reset();
         self.service.event_log.reset();
+        if self.service.nvram.boot_to_fw_ui_requested().await {
+            self.service.event_log.report_boot_to_fw_ui();
+        }
         self.service.uefi_watchdog.watchdog.reset();
         self.service.generation_id.reset();
         self.service.diagnostics.reset();
@@ -34,15 +34,42 @@ pub enum EventLogError {
 pub struct EventLogServices {
     #[inspect(skip)]
     logger: Box<dyn UefiLogger>,
+    bds_started: bool,
 }
 
 impl EventLogServices {
     pub fn new(logger: Box<dyn UefiLogger>) -> EventLogServices {
-        EventLogServices { logger }
+        EventLogServices {
+            logger,
+            bds_started: false,
+        }
     }
 
     pub fn reset(&mut self) {
-        // Nothing to do.
+        self.bds_started = false;
+    }
+
+    /// Reports that the BDS phase has started, if it has not already been
+    /// reported since the last reset.
+    pub fn report_bds_started(&mut self) {
+        if !self.bds_started {
+            self.bds_started = true;
+            tracelimit::info_ratelimited!("uefi boot: bds started");
+            self.logger.log_event(UefiEvent::BdsStarted);
+        }
+    }
+
+    /// Reports that the guest has asked for the next boot to enter the
+    /// firmware UI.
+    pub fn report_boot_to_fw_ui(&mut self) {
+        tracing::info!("uefi boot: guest requested boot to firmware ui");
+        self.logger.log_event(UefiEvent::BootToFirmwareUi);
+    }
+
+    /// Reports that the OS loader has called `ExitBootServices`.
+    pub fn report_exit_boot_services(&mut self) {
+        tracelimit::info_ratelimited!("uefi boot: exit boot services");
+        self.logger.log_event(UefiEvent::ExitBootServices);
     }
 
     fn event_log_flush_inner(&mut self, gpa: u64, gm: &GuestMemory) -> Result<(), EventLogError> {
@@ -99,6 +99,25 @@ pub fn reset(&mut self) {
         self.services.prepare_for_boot();
     }
 
+    /// Returns whether the guest has asked for the next boot to enter the
+    /// firmware UI, by setting `EFI_OS_INDICATIONS_BOOT_TO_FW_UI` in
+    /// `OsIndications`.
+    ///
+    /// The bit is left set, since it is the firmware that consumes it.
+    pub async fn boot_to_fw_ui_requested(&mut self) -> bool {
+        use uefi_specs::uefi::nvram::OsIndications;
+        use uefi_specs::uefi::nvram::vars::OS_INDICATIONS;
+
+        let (vendor, name) = OS_INDICATIONS();
+        let Ok((_, data)) = self.services.get_variable_ucs2(vendor, name).await else {
+            return false;
+        };
+        let mut indications = [0; 8];
+        let len = data.len().min(indications.len());
+        indications[..len].copy_from_slice(&data[..len]);
+        OsIndications::from(u64::from_le_bytes(indications)).boot_to_fw_ui()
+    }
+
     /// Check if this is the VM's first boot, and if so, inject various
     /// hard-coded and custom UEFI vars.
     async fn inject_vars_on_first_boot(
@@ -451,6 +470,15 @@ async fn handle_nvram_command_inner(
                     None
                 };
 
+                // BDS reads BootOrder to decide what to boot. This is the
+                // earliest point at which the host can observe that DXE
+                // dispatch has completed.
+                if !self.service.nvram.services.exited_boot_services()
+                    && is_boot_order(command.vendor_guid, name.as_deref())
+                {
+                    self.service.event_log.report_bds_started();
+                }
+
                 let NvramResult(data, status, err) = self
                     .service
                     .nvram
@@ -570,6 +598,7 @@ async fn handle_nvram_command_inner(
                     }
                 }
                 self.service.nvram.services.exit_boot_services();
+                self.service.event_log.report_exit_boot_services();
 
                 (EfiStatus::SUCCESS, None)
             }
@@ -613,6 +642,11 @@ async fn handle_nvram_command_inner(
     }
 }
 
+fn is_boot_order(vendor: guid::Guid, name: Option<&[u8]>) -> bool {
+    name.and_then(|name| ucs2::Ucs2LeSlice::from_slice_with_nul(name).ok())
+        .is_some_and(|name| uefi_specs::uefi::nvram::vars::BOOT_ORDER() == (vendor, name))
+}
+
 mod save_restore {
     use super::*;
     use vmcore::save_restore::RestoreError;
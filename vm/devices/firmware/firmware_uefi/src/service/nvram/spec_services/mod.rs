@@ -291,6 +291,12 @@ pub fn exit_boot_services(&mut self) {
         self.runtime_state = RuntimeState::Runtime;
     }
 
+    /// Returns true if the guest has signaled that ExitBootServices has been
+    /// called.
+    pub fn exited_boot_services(&self) -> bool {
+        self.runtime_state.is_runtime()
+    }
+
     /// Called when the VM resets to return to the preboot state.
     pub fn reset(&mut self) {
         self.runtime_state = RuntimeState::PreBoot;
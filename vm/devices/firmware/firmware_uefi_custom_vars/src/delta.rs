@@ -8,7 +8,7 @@
 use super::Signature;
 
 /// Collection of custom UEFI nvram variables.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CustomVarsDelta {
     /// Secure Boot signature vars
     pub signatures: SignaturesDelta,
@@ -16,7 +16,7 @@ pub struct CustomVarsDelta {
     pub custom_vars: Vec<(String, CustomVar)>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum SignaturesDelta {
     /// Vars should append onto underlying template
     Append(SignaturesAppend),
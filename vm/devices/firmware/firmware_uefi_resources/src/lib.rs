@@ -46,6 +46,16 @@ pub enum UefiEvent {
         BootSuccess(BootInfo),
         BootFailure(BootInfo),
         NoBootDevice,
+        /// The boot device selection (BDS) phase started, meaning DXE driver
+        /// dispatch has completed.
+        BdsStarted,
+        /// The OS loader called `ExitBootServices`, handing off control of
+        /// the platform to the OS.
+        ExitBootServices,
+        /// The guest set `EFI_OS_INDICATIONS_BOOT_TO_FW_UI` before resetting,
+        /// asking for the next boot to enter the firmware UI. Reported when
+        /// the device is reset, before the firmware is reloaded.
+        BootToFirmwareUi,
     }
 
     /// Information about a boot attempt.
@@ -176,6 +176,23 @@ pub fn is_secure_boot_policy_var(vendor: Guid, name: &ucs2::Ucs2LeSlice) -> bool
     is_secure_boot_policy_var || vendor == vars::IMAGE_SECURITY_DATABASE_GUID
 }
 
+/// UEFI spec 8.5.4 - Exchanging information between the OS and Firmware
+///
+/// The bits of the `OsIndications` and `OsIndicationsSupported` variables.
+#[bitfield(u64)]
+pub struct OsIndications {
+    pub boot_to_fw_ui: bool,
+    pub timestamp_revocation: bool,
+    pub file_capsule_delivery_supported: bool,
+    pub fmp_capsule_supported: bool,
+    pub capsule_result_var_supported: bool,
+    pub start_os_recovery: bool,
+    pub start_platform_recovery: bool,
+    pub jsonconfig_data_refresh: bool,
+    #[bits(56)]
+    _reserved: u64,
+}
+
 /// UEFI spec 3.3 - Table 3-1
 ///
 /// Due to the Rust compiler not having built-in support for defining
@@ -194,6 +211,8 @@ pub mod vars {
 
     defn_nvram_var!(SECURE_BOOT = (EFI_GLOBAL_VARIABLE, "SecureBoot"));
     defn_nvram_var!(SETUP_MODE = (EFI_GLOBAL_VARIABLE, "SetupMode"));
+    defn_nvram_var!(BOOT_ORDER = (EFI_GLOBAL_VARIABLE, "BootOrder"));
+    defn_nvram_var!(OS_INDICATIONS = (EFI_GLOBAL_VARIABLE, "OsIndications"));
 
     defn_nvram_var!(PK = (EFI_GLOBAL_VARIABLE, "PK"));
     defn_nvram_var!(KEK = (EFI_GLOBAL_VARIABLE, "KEK"));
@@ -44,11 +44,13 @@ impl ResourceId<VmbusDeviceHandleKind> for GuestCrashDeviceHandle {
 
 /// Guest Emulation Device resources.
 pub mod ged {
+    use guid::Guid;
     use inspect::Inspect;
     use mesh::MeshPayload;
     use mesh::error::RemoteError;
     use mesh::payload::Protobuf;
     use mesh::rpc::Rpc;
+    use std::time::Duration;
     use thiserror::Error;
     use vm_resource::Resource;
     use vm_resource::ResourceId;
@@ -91,6 +93,8 @@ pub struct GuestEmulationDeviceHandle {
         pub no_persistent_secrets: bool,
         /// Test configuration for IGVM Attest message.
         pub igvm_attest_test_config: Option<IgvmAttestTestConfig>,
+        /// Test configuration for the VTL2 settings exchanges.
+        pub vtl2_settings_test_config: Option<Vtl2SettingsTestConfig>,
         /// Send the test seed for GspById requests
         pub test_gsp_by_id: bool,
         /// EFI diagnostics log level
@@ -196,6 +200,46 @@ pub enum GuestEmulationRequest {
         SaveGuestVtl2State(Rpc<GuestServicingFlags, Result<(), SaveRestoreError>>),
         /// Update the VTL2 settings.
         ModifyVtl2Settings(Rpc<Vec<u8>, Result<(), ModifyVtl2SettingsError>>),
+        /// Arm a fault to inject into the next servicing operation, or disarm
+        /// the armed fault if `None`.
+        InjectServicingFault(Rpc<Option<ServicingFault>, ()>),
+        /// Clear the armed servicing fault and return true if it is the given
+        /// fault. Used by the host to inject faults into servicing steps that
+        /// do not involve the GED.
+        TakeServicingFault(Rpc<ServicingFault, bool>),
+        /// Notify VTL2 that the VPCI device on the bus with the given instance
+        /// ID is about to be removed.
+        PrepareVpciDeviceRemoval(Rpc<Guid, ()>),
+        /// Wait for VTL2 to report that the VPCI device on the bus with the
+        /// given instance ID is bound (`true`) or unbound (`false`).
+        WaitForVpciBindingState(Rpc<(Guid, bool), ()>),
+        /// Offer the saved state from the last servicing operation again, if
+        /// VTL2 reported that it failed to restore it, so that it can be
+        /// restored by the previous VTL2 firmware. Returns whether the saved
+        /// state was offered again.
+        RearmFailedRestore(Rpc<(), bool>),
+    }
+
+    /// A host failure to simulate during servicing, to test how VTL2 handles
+    /// failures at each step of the servicing handshake.
+    ///
+    /// A fault applies only to the next servicing operation. It is cleared
+    /// when the step it applies to runs, and when the servicing operation
+    /// fails or completes, so it never carries over to a later operation.
+    #[derive(Debug, MeshPayload, Copy, Clone, PartialEq, Eq)]
+    pub enum ServicingFault {
+        /// Reject the saved state sent by the guest, as if the host failed to
+        /// store it.
+        RejectSave,
+        /// Corrupt the retained saved state before it is delivered back to
+        /// the guest.
+        CorruptSavedState,
+        /// Fail to activate the new IGVM file after the guest has saved its
+        /// state, as if the host could not load it. The new VTL2 is never
+        /// started.
+        FailActivation,
+        /// Fail the guest's request for its saved state on restore.
+        FailRestore,
     }
 
     /// An error waiting to start VTL0.
@@ -214,6 +258,8 @@ pub enum SaveRestoreError {
         Io(#[source] RemoteError),
         #[error("guest error")]
         GuestError,
+        #[error("injected host fault")]
+        InjectedFault,
     }
 
     /// An error that can occur during a VTL2 settings update.
@@ -242,6 +288,19 @@ pub enum FirmwareEvent {
         NoBootDevice,
         /// A boot attempt was made.
         BootAttempt,
+        /// The boot device selection phase started, after DXE driver
+        /// dispatch completed. Only reported by UEFI.
+        BdsStarted,
+        /// The OS loader called `ExitBootServices`. Only reported by UEFI.
+        OsLoaderHandoff,
+    }
+
+    impl FirmwareEvent {
+        /// Returns true if this event marks progress through the boot
+        /// sequence rather than the result of a boot attempt.
+        pub fn is_boot_phase(&self) -> bool {
+            matches!(self, Self::BdsStarted | Self::OsLoaderHandoff)
+        }
     }
 
     /// Configuration for the GED's IGVM Attest request handler in test
@@ -302,5 +361,24 @@ pub enum IgvmAttestTestConfig {
         /// unsealing using the hardware key protector saved on the
         /// previous successful boot.  The VM should boot normally.
         KeyReleaseFailure,
+        /// Config for testing AK cert retry after transient faults.
+        ///
+        /// Plan: a retryable failure, a truncated response, no response,
+        /// and then a success delayed by a few seconds.
+        AkCertRequestTransientFaults,
+    }
+
+    /// Configuration for faults that the GED injects into the VTL2 settings
+    /// exchanges in test scenarios, to test how VTL2 handles a slow or
+    /// unreliable host.
+    #[derive(Debug, MeshPayload, Copy, Clone)]
+    pub enum Vtl2SettingsTestConfig {
+        /// Delay the response to the device platform settings request, which
+        /// carries the initial VTL2 settings, and each VTL2 settings
+        /// modification by the given time.
+        Delay(Duration),
+        /// Drop the last byte of the first VTL2 settings modification, so
+        /// that VTL2 fails to parse it. Later modifications are sent intact.
+        TruncateFirstModify,
     }
 }
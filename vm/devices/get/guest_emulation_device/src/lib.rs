@@ -16,6 +16,8 @@
 #[cfg(feature = "test_utilities")]
 pub mod test_utilities;
 
+pub use test_igvm_agent_lib::AkCertIssuer;
+pub use test_igvm_agent_lib::FixedAkCertIssuer;
 pub use test_igvm_agent_lib::IgvmAgentAction;
 pub use test_igvm_agent_lib::IgvmAgentTestPlan;
 pub use test_igvm_agent_lib::IgvmAgentTestSetting;
@@ -50,7 +52,9 @@
 use get_resources::ged::GuestServicingFlags;
 use get_resources::ged::ModifyVtl2SettingsError;
 use get_resources::ged::SaveRestoreError;
+use get_resources::ged::ServicingFault;
 use get_resources::ged::Vtl0StartError;
+use get_resources::ged::Vtl2SettingsTestConfig;
 use guestmem::GuestMemory;
 use guid::Guid;
 use inspect::Inspect;
@@ -64,7 +68,10 @@
 use power_resources::PowerRequest;
 use power_resources::PowerRequestClient;
 use scsi_buffers::OwnedRequestBuffers;
+use std::collections::HashMap;
 use std::io::IoSlice;
+use std::sync::Arc;
+use std::time::Duration;
 use task_control::StopTask;
 use test_igvm_agent_lib::TestIgvmAgent;
 use thiserror::Error;
@@ -221,12 +228,26 @@ pub struct GuestEmulationDevice {
     guest_request_recv: mesh::Receiver<GuestEmulationRequest>,
     #[inspect(skip)]
     waiting_for_vtl0_start: Vec<Rpc<(), Result<(), Vtl0StartError>>>,
+    #[inspect(skip)]
+    waiting_for_vpci_binding: Vec<Rpc<(Guid, bool), ()>>,
+    /// The last binding state reported by the guest for each VPCI bus.
+    #[inspect(iter_by_key)]
+    vpci_bound: HashMap<Guid, bool>,
 
     vmgs: Option<VmgsState>,
 
     #[inspect(with = "Option::is_some")]
     save_restore_buf: Option<Vec<u8>>,
+    /// Saved state sent to VTL2, kept until VTL2 reports whether it restored
+    /// it.
+    #[inspect(with = "Option::is_some")]
+    sent_restore_buf: Option<Vec<u8>>,
+    /// Saved state that VTL2 failed to restore.
+    #[inspect(with = "Option::is_some")]
+    failed_restore_buf: Option<Vec<u8>>,
     last_save_restore_buf_len: usize,
+    #[inspect(debug)]
+    servicing_fault: Option<ServicingFault>,
 
     igvm_agent_setting: Option<IgvmAgentTestSetting>,
 
@@ -234,6 +255,9 @@ pub struct GuestEmulationDevice {
     #[inspect(skip)]
     igvm_agent: TestIgvmAgent,
 
+    #[inspect(debug)]
+    vtl2_settings_test_config: Option<Vtl2SettingsTestConfig>,
+
     test_gsp_by_id: bool,
 }
 
@@ -255,6 +279,7 @@ pub fn new(
         framebuffer_control: Option<Box<dyn FramebufferControl>>,
         vmgs_disk: Option<Disk>,
         igvm_agent_setting: Option<IgvmAgentTestSetting>,
+        vtl2_settings_test_config: Option<Vtl2SettingsTestConfig>,
         test_gsp_by_id: bool,
     ) -> Self {
         Self {
@@ -268,14 +293,45 @@ pub fn new(
                 mem: GuestMemory::allocate(MAX_PAYLOAD_SIZE),
             }),
             save_restore_buf: None,
+            sent_restore_buf: None,
+            failed_restore_buf: None,
             waiting_for_vtl0_start: Vec::new(),
+            waiting_for_vpci_binding: Vec::new(),
+            vpci_bound: HashMap::new(),
             last_save_restore_buf_len: 0,
+            servicing_fault: None,
             igvm_agent_setting,
             igvm_agent: TestIgvmAgent::new("openvmm"),
+            vtl2_settings_test_config,
             test_gsp_by_id,
         }
     }
 
+    /// Sets the source of certificates for AK cert requests from the guest,
+    /// replacing the test CA.
+    pub fn set_ak_cert_issuer(&mut self, issuer: Arc<dyn AkCertIssuer>) {
+        self.igvm_agent.set_ak_cert_issuer(issuer);
+    }
+
+    /// Clears the armed servicing fault and returns true if it is `fault`.
+    fn take_servicing_fault(&mut self, fault: ServicingFault) -> bool {
+        if self.servicing_fault == Some(fault) {
+            tracing::info!(?fault, "injecting servicing fault");
+            self.servicing_fault = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Clears any servicing fault that did not apply to the servicing
+    /// operation that just ended, so that it does not carry over.
+    fn disarm_servicing_fault(&mut self) {
+        if let Some(fault) = self.servicing_fault.take() {
+            tracing::info!(?fault, "disarming unused servicing fault");
+        }
+    }
+
     fn send_event(&self, event: FirmwareEvent) {
         if let Some(sender) = &self.firmware_event_send {
             sender.send(event);
@@ -367,6 +423,16 @@ enum GedState {
     SendingRestore { written: usize },
 }
 
+/// Waits for `delay`, to simulate a slow host in test configurations.
+///
+/// The GED does not process other messages in the meantime.
+async fn test_delay(delay: Duration) {
+    mesh::CancelContext::new()
+        .with_timeout(delay)
+        .cancelled()
+        .await;
+}
+
 impl<T: RingMem + Unpin> GedChannel<T> {
     fn new(channel: MessagePipe<T>, guest_memory: GuestMemory) -> Self {
         Self {
@@ -433,7 +499,7 @@ async fn process(
                             self.handle_pipe_input(&message_buf[..bytes_read], state).await?;
                         },
                         guest_request = state.guest_request_recv.select_next_some() => {
-                            self.handle_guest_request_input(state, guest_request)?;
+                            self.handle_guest_request_input(state, guest_request).await?;
                         }
                         _ = stop.fuse() => {
                             return Err(Error::Cancelled(task_control::Cancelled));
@@ -450,7 +516,8 @@ async fn process(
                     if *written >= saved_state_size {
                         self.state = GedState::Ready;
                         state.last_save_restore_buf_len = saved_state_size;
-                        state.save_restore_buf = None;
+                        state.sent_restore_buf = state.save_restore_buf.take();
+                        state.disarm_servicing_fault();
                         continue;
                     }
 
@@ -522,7 +589,7 @@ async fn handle_pipe_input(
         Ok(())
     }
 
-    fn handle_guest_request_input(
+    async fn handle_guest_request_input(
         &mut self,
         state: &mut GuestEmulationDevice,
         guest_request: GuestEmulationRequest,
@@ -549,6 +616,20 @@ fn handle_guest_request_input(
                     return Ok(());
                 }
 
+                let mut data = data;
+                match state.vtl2_settings_test_config {
+                    Some(Vtl2SettingsTestConfig::Delay(delay)) => {
+                        tracing::info!(?delay, "test config: delaying VTL2 settings modification");
+                        test_delay(delay).await;
+                    }
+                    Some(Vtl2SettingsTestConfig::TruncateFirstModify) => {
+                        tracing::info!("test config: truncating VTL2 settings modification");
+                        data.pop();
+                        state.vtl2_settings_test_config = None;
+                    }
+                    None => {}
+                }
+
                 let header = get_protocol::ModifyVtl2SettingsRev1Notification {
                     message_header: HeaderGeneric::new(
                         get_protocol::GuestNotifications::MODIFY_VTL2_SETTINGS_REV1,
@@ -563,6 +644,43 @@ fn handle_guest_request_input(
 
                 self.modify = Some(response);
             }
+            GuestEmulationRequest::InjectServicingFault(rpc) => rpc.handle_sync(|fault| {
+                tracing::info!(?fault, "arming servicing fault");
+                state.servicing_fault = fault;
+            }),
+            GuestEmulationRequest::TakeServicingFault(rpc) => {
+                rpc.handle_sync(|fault| state.take_servicing_fault(fault))
+            }
+            GuestEmulationRequest::PrepareVpciDeviceRemoval(rpc) => {
+                let (bus_instance_id, response) = rpc.split();
+                let notification = get_protocol::VpciDeviceNotification {
+                    message_header: HeaderGeneric::new(
+                        get_protocol::GuestNotifications::VPCI_DEVICE_NOTIFICATION,
+                    ),
+                    bus_instance_id,
+                    code: get_protocol::VpciDeviceNotificationCode::PREPARE_FOR_REMOVAL,
+                };
+                self.channel
+                    .try_send(notification.as_bytes())
+                    .map_err(Error::Vmbus)?;
+                response.complete(());
+            }
+            GuestEmulationRequest::RearmFailedRestore(rpc) => rpc.handle_sync(|()| {
+                let Some(buffer) = state.failed_restore_buf.take() else {
+                    return false;
+                };
+                tracing::info!("offering saved state again after failed restore");
+                state.save_restore_buf = Some(buffer);
+                true
+            }),
+            GuestEmulationRequest::WaitForVpciBindingState(rpc) => {
+                let (bus_instance_id, bound) = *rpc.input();
+                if state.vpci_bound.get(&bus_instance_id) == Some(&bound) {
+                    rpc.complete(());
+                } else {
+                    state.waiting_for_vpci_binding.push(rpc);
+                }
+            }
             GuestEmulationRequest::SaveGuestVtl2State(rpc) => {
                 let r = (|| {
                     if self.save.is_some() {
@@ -622,20 +740,30 @@ async fn handle_host_request(
             HostRequests::GUEST_STATE_PROTECTION_BY_ID => {
                 self.handle_guest_state_protection_by_id(state.test_gsp_by_id)?;
             }
-            HostRequests::IGVM_ATTEST => self.handle_igvm_attest(message_buf, state)?,
+            HostRequests::IGVM_ATTEST => self.handle_igvm_attest(message_buf, state).await?,
             HostRequests::DEVICE_PLATFORM_SETTINGS_V2 => {
+                if let Some(Vtl2SettingsTestConfig::Delay(delay)) = state.vtl2_settings_test_config
+                {
+                    tracing::info!(?delay, "test config: delaying device platform settings");
+                    test_delay(delay).await;
+                }
                 self.handle_device_platform_settings_v2(state)?
             }
             HostRequests::SAVE_GUEST_VTL2_STATE => {
                 self.handle_save_guest_vtl2_state(message_buf, state)?
             }
-            HostRequests::RESTORE_GUEST_VTL2_STATE => self.handle_restore_guest_vtl2_state(),
+            HostRequests::RESTORE_GUEST_VTL2_STATE => {
+                self.handle_restore_guest_vtl2_state(state)?
+            }
             HostRequests::MAP_FRAMEBUFFER => {
                 self.handle_map_framebuffer(state, message_buf).await?
             }
             HostRequests::UNMAP_FRAMEBUFFER => self.handle_unmap_framebuffer(state).await?,
             HostRequests::CREATE_RAM_GPA_RANGE => self.handle_create_ram_gpa_range(message_buf)?,
             HostRequests::RESET_RAM_GPA_RANGE => self.handle_reset_ram_gpa_range(message_buf)?,
+            HostRequests::VPCI_DEVICE_BINDING_CHANGE => {
+                self.handle_vpci_device_binding_change(message_buf, state)?
+            }
             _ => {
                 tracing::error!(message_id = ?header.message_id(), "unexpected message");
                 return Err(Error::InvalidSequence);
@@ -658,6 +786,39 @@ fn handle_bios_boot_finalize(&mut self, message_buf: &[u8]) -> Result<(), Error>
         Ok(())
     }
 
+    fn handle_vpci_device_binding_change(
+        &mut self,
+        message_buf: &[u8],
+        state: &mut GuestEmulationDevice,
+    ) -> Result<(), Error> {
+        let msg = get_protocol::VpciDeviceBindingChangeRequest::read_from_prefix(message_buf)
+            .map_err(|_| Error::MessageTooSmall)?
+            .0; // TODO: zerocopy: map_err (https://github.com/microsoft/openvmm/issues/759)
+
+        let bus_instance_id =
+            Guid::read_from_bytes(&msg.bus_instance_id).expect("guid is 16 bytes");
+        let bound = msg.binding_state != 0;
+        tracing::info!(%bus_instance_id, bound, "vpci device binding change");
+
+        state.vpci_bound.insert(bus_instance_id, bound);
+        let (ready, waiting) = std::mem::take(&mut state.waiting_for_vpci_binding)
+            .into_iter()
+            .partition(|rpc| *rpc.input() == (bus_instance_id, bound));
+        state.waiting_for_vpci_binding = waiting;
+        for rpc in ready {
+            rpc.complete(());
+        }
+
+        let response = get_protocol::VpciDeviceBindingChangeResponse::new(
+            bus_instance_id,
+            get_protocol::VpciDeviceControlStatus::SUCCESS,
+        );
+        self.channel
+            .try_send(response.as_bytes())
+            .map_err(Error::Vmbus)?;
+        Ok(())
+    }
+
     fn handle_time(&mut self) -> Result<(), Error> {
         const WINDOWS_EPOCH: DateTime = date(1601, 1, 1).at(0, 0, 0, 0);
 
@@ -875,7 +1036,7 @@ fn handle_guest_state_protection_by_id(&mut self, test_gsp_by_id: bool) -> Resul
 
     /// Stub implementation that simulates the behavior of GED and the host agent.
     /// Used only for test scenarios such as VMM tests.
-    fn handle_igvm_attest(
+    async fn handle_igvm_attest(
         &mut self,
         message_buf: &[u8],
         state: &mut GuestEmulationDevice,
@@ -894,16 +1055,20 @@ fn handle_igvm_attest(
             Err(Error::InvalidIgvmAttestRequest)?
         }
 
-        let (response_payload, length) = {
-            if let Some(setting) = &state.igvm_agent_setting {
-                state.igvm_agent.install_plan_from_setting(setting);
-            }
+        let report = &request.report[..request.report_length as usize];
+        if let Some(setting) = &state.igvm_agent_setting {
+            state.igvm_agent.install_plan_from_setting(setting);
+        }
 
-            state
-                .igvm_agent
-                .handle_request(&request.report[..request.report_length as usize])
-                .map_err(Error::TestIgvmAgent)?
-        };
+        if let Some(delay) = state.igvm_agent.take_response_delay(report) {
+            tracing::info!(?delay, "Test plan: delaying IGVM Attest response");
+            test_delay(delay).await;
+        }
+
+        let (response_payload, length) = state
+            .igvm_agent
+            .handle_request(report)
+            .map_err(Error::TestIgvmAgent)?;
 
         // Write the response payload to the guest's shared memory
         self.gm
@@ -946,17 +1111,25 @@ fn handle_save_guest_vtl2_state(
 
                 tracing::debug!("Received all guest VTL2 save state");
 
+                let reject = state.take_servicing_fault(ServicingFault::RejectSave);
+
                 // Send response and then notify completion.
-                let response = get_protocol::SaveGuestVtl2StateResponse::new(
-                    get_protocol::GuestVtl2SaveRestoreStatus::SUCCESS,
-                );
+                let response = get_protocol::SaveGuestVtl2StateResponse::new(if reject {
+                    get_protocol::GuestVtl2SaveRestoreStatus::FAILURE
+                } else {
+                    get_protocol::GuestVtl2SaveRestoreStatus::SUCCESS
+                });
                 self.channel
                     .try_send(response.as_bytes())
                     .map_err(Error::Vmbus)?;
 
                 tracing::debug!("Notifying completion channel that save guest VTL2 op is complete");
 
-                Some(Ok(()))
+                Some(if reject {
+                    Err(SaveRestoreError::InjectedFault)
+                } else {
+                    Ok(())
+                })
             }
             get_protocol::GuestVtl2SaveRestoreStatus::FAILURE => {
                 Some(Err(SaveRestoreError::GuestError))
@@ -968,15 +1141,36 @@ fn handle_save_guest_vtl2_state(
         if let Some(r) = r {
             let save = self.save.take().unwrap();
             if r.is_ok() {
-                state.save_restore_buf = Some(save.buffer);
+                let mut buffer = save.buffer;
+                if state.take_servicing_fault(ServicingFault::CorruptSavedState) {
+                    buffer.iter_mut().for_each(|b| *b = !*b);
+                }
+                state.save_restore_buf = Some(buffer);
+            } else {
+                state.disarm_servicing_fault();
             }
             save.rpc.complete(r);
         }
         Ok(())
     }
 
-    fn handle_restore_guest_vtl2_state(&mut self) {
+    fn handle_restore_guest_vtl2_state(
+        &mut self,
+        state: &mut GuestEmulationDevice,
+    ) -> Result<(), Error> {
+        if state.take_servicing_fault(ServicingFault::FailRestore) {
+            // Keep the saved state, so that the guest can retry.
+            let response = get_protocol::RestoreGuestVtl2StateResponse::new(
+                0,
+                get_protocol::GuestVtl2SaveRestoreStatus::FAILURE,
+            );
+            self.channel
+                .try_send(response.as_bytes())
+                .map_err(Error::Vmbus)?;
+            return Ok(());
+        }
         self.state = GedState::SendingRestore { written: 0 };
+        Ok(())
     }
 
     async fn handle_map_framebuffer(
@@ -1072,13 +1266,13 @@ fn handle_host_notification(
                 self.handle_event_log(state, message_buf)?;
             }
             HostNotifications::RESTORE_GUEST_VTL2_STATE_COMPLETED => {
-                self.handle_restore_guest_vtl2_state_completed(message_buf)?;
+                self.handle_restore_guest_vtl2_state_completed(state, message_buf)?;
             }
             HostNotifications::START_VTL0_COMPLETED => {
                 self.handle_start_vtl0_completed(state, message_buf)?;
             }
             HostNotifications::VTL_CRASH => {
-                self.handle_vtl_crash(message_buf)?;
+                self.handle_vtl_crash(state, message_buf)?;
             }
             HostNotifications::TRIPLE_FAULT => {
                 self.handle_triple_fault(state, message_buf)?;
@@ -1153,6 +1347,7 @@ fn handle_event_log(
 
     fn handle_restore_guest_vtl2_state_completed(
         &mut self,
+        state: &mut GuestEmulationDevice,
         message_buf: &[u8],
     ) -> Result<(), Error> {
         let message =
@@ -1165,6 +1360,18 @@ fn handle_restore_guest_vtl2_state_completed(
             _ => return Err(Error::InvalidFieldValue),
         };
         tracing::info!(success, "restore vtl2 complete");
+        let buffer = state.sent_restore_buf.take();
+        if !success {
+            // Keep the saved state so that the host can roll back to the
+            // previous VTL2 firmware, and fail anyone waiting for VTL0 to
+            // start, since it never will.
+            state.failed_restore_buf = buffer;
+            let result = Err(Vtl0StartError("VTL2 failed to restore saved state".into()));
+            for response in state.waiting_for_vtl0_start.drain(..) {
+                response.complete(result.clone());
+            }
+            self.vtl0_start_report = Some(result);
+        }
         Ok(())
     }
 
@@ -1203,11 +1410,21 @@ fn handle_start_vtl0_completed(
         Ok(())
     }
 
-    fn handle_vtl_crash(&mut self, message_buf: &[u8]) -> Result<(), Error> {
+    fn handle_vtl_crash(
+        &mut self,
+        state: &mut GuestEmulationDevice,
+        message_buf: &[u8],
+    ) -> Result<(), Error> {
         let msg = get_protocol::VtlCrashNotification::read_from_prefix(message_buf)
             .map_err(|_| Error::MessageTooSmall)?
             .0; // TODO: zerocopy: map_err (https://github.com/microsoft/openvmm/issues/759)
         tracing::info!("Guest has reported a system crash {msg:x?}");
+        if hvdef::GuestCrashCtl::from(msg.control).crash_notify() {
+            state.power_client.power_request(PowerRequest::GuestCrash {
+                vp: msg.vp_index,
+                parameters: msg.parameters,
+            });
+        }
         Ok(())
     }
 
@@ -217,6 +217,7 @@ async fn resolve(
             resource
                 .igvm_attest_test_config
                 .map(IgvmAgentTestSetting::TestConfig),
+            resource.vtl2_settings_test_config,
             resource.test_gsp_by_id,
         );
         Ok(SimpleDeviceWrapper::new(input.driver_source.simple(), device).into())
@@ -15,6 +15,11 @@
 use get_protocol::test_utilities::TEST_VMGS_CAPACITY;
 use get_resources::ged::GuestEmulationRequest;
 use get_resources::ged::GuestServicingFlags;
+use get_resources::ged::ModifyVtl2SettingsError;
+use get_resources::ged::SaveRestoreError;
+use get_resources::ged::ServicingFault;
+use get_resources::ged::Vtl0StartError;
+use get_resources::ged::Vtl2SettingsTestConfig;
 use guestmem::GuestMemory;
 use mesh::rpc::RpcSend;
 use pal_async::task::Spawn;
@@ -239,6 +244,7 @@ pub fn create_host_channel(
     version: get_protocol::ProtocolVersion,
     guest_memory: Option<GuestMemory>,
     igvm_agent_plan: Option<IgvmAgentTestPlan>,
+    vtl2_settings_test_config: Option<Vtl2SettingsTestConfig>,
 ) -> TestGedClient {
     let guest_config = GuestConfig {
         firmware: GuestFirmwareConfig::Uefi {
@@ -286,6 +292,7 @@ pub fn create_host_channel(
         None,
         Some(disklayer_ram::ram_disk(TEST_VMGS_CAPACITY as u64, false).unwrap()),
         igvm_agent_plan.map(IgvmAgentTestSetting::TestPlan),
+        vtl2_settings_test_config,
         false,
     );
 
@@ -366,4 +373,57 @@ pub async fn test_save_guest_vtl2_state(&mut self) {
             .await
             .expect("no failure");
     }
+
+    /// Saves guest VTL2 state, returning the host's view of the result.
+    pub async fn test_try_save_guest_vtl2_state(&self) -> Result<(), SaveRestoreError> {
+        self.sender
+            .call(
+                GuestEmulationRequest::SaveGuestVtl2State,
+                GuestServicingFlags::default(),
+            )
+            .await
+            .unwrap()
+    }
+
+    /// Sends modified VTL2 settings to the guest, returning the result.
+    pub async fn test_modify_vtl2_settings(
+        &self,
+        settings: Vec<u8>,
+    ) -> Result<(), ModifyVtl2SettingsError> {
+        self.sender
+            .call(GuestEmulationRequest::ModifyVtl2Settings, settings)
+            .await
+            .unwrap()
+    }
+
+    pub async fn test_inject_servicing_fault(&mut self, fault: ServicingFault) {
+        self.sender
+            .call(GuestEmulationRequest::InjectServicingFault, Some(fault))
+            .await
+            .unwrap();
+    }
+
+    /// Waits for the guest to report whether VTL0 started.
+    pub async fn test_wait_for_vtl0_start(&self) -> Result<(), Vtl0StartError> {
+        self.sender
+            .call(GuestEmulationRequest::WaitForVtl0Start, ())
+            .await
+            .unwrap()
+    }
+
+    /// Offers the saved state again after a failed restore, returning whether
+    /// there was one.
+    pub async fn test_rearm_failed_restore(&self) -> bool {
+        self.sender
+            .call(GuestEmulationRequest::RearmFailedRestore, ())
+            .await
+            .unwrap()
+    }
+
+    pub async fn test_clear_servicing_fault(&mut self) {
+        self.sender
+            .call(GuestEmulationRequest::InjectServicingFault, None)
+            .await
+            .unwrap();
+    }
 }
@@ -56,6 +56,7 @@ pub mod test_utilities {
     use crate::worker::GuestEmulationTransportWorker;
     use client::GuestEmulationTransportClient;
     use get_protocol::ProtocolVersion;
+    use get_resources::ged::Vtl2SettingsTestConfig;
     use guest_emulation_device::IgvmAgentTestPlan;
     use guest_emulation_device::test_utilities::TestGedClient;
     use guest_emulation_device::test_utilities::TestGetResponses;
@@ -84,6 +85,28 @@ pub async fn new_transport_pair(
         version: ProtocolVersion,
         guest_memory: Option<guestmem::GuestMemory>,
         igvm_agent_script: Option<IgvmAgentTestPlan>,
+    ) -> TestGet {
+        new_transport_pair_with_vtl2_settings_test_config(
+            spawn,
+            ged_responses,
+            version,
+            guest_memory,
+            igvm_agent_script,
+            None,
+        )
+        .await
+    }
+
+    /// Creates a new host guest transport pair like [`new_transport_pair`],
+    /// with a GED that injects the faults in `vtl2_settings_test_config`
+    /// into the VTL2 settings exchanges.
+    pub async fn new_transport_pair_with_vtl2_settings_test_config(
+        spawn: impl Spawn,
+        ged_responses: Option<Vec<TestGetResponses>>,
+        version: ProtocolVersion,
+        guest_memory: Option<guestmem::GuestMemory>,
+        igvm_agent_script: Option<IgvmAgentTestPlan>,
+        vtl2_settings_test_config: Option<Vtl2SettingsTestConfig>,
     ) -> TestGet {
         let (host_vmbus, guest_vmbus) = vmbus_async::pipe::connected_message_pipes(
             get_protocol::MAX_MESSAGE_SIZE + vmbus_ring::PAGE_SIZE,
@@ -96,6 +119,7 @@ pub async fn new_transport_pair(
             version,
             guest_memory,
             igvm_agent_script,
+            vtl2_settings_test_config,
         );
 
         // Create the GET
@@ -119,10 +143,15 @@ pub async fn new_transport_pair(
 mod tests {
     use super::test_utilities::*;
     use super::worker::GuestEmulationTransportWorker;
+    use crate::api::GuestSaveRequest;
     use crate::process_loop::FatalError;
     use get_protocol::ProtocolVersion;
     use get_protocol::VmgsIoStatus;
     use get_protocol::test_utilities::TEST_VMGS_SECTOR_SIZE;
+    use get_resources::ged::ModifyVtl2SettingsError;
+    use get_resources::ged::SaveRestoreError;
+    use get_resources::ged::ServicingFault;
+    use get_resources::ged::Vtl2SettingsTestConfig;
     use guest_emulation_device::test_utilities::Event;
     use guest_emulation_device::test_utilities::TestGetResponses;
     use pal_async::DefaultDriver;
@@ -777,4 +806,206 @@ async fn test_save_guest_vtl2_state(driver: DefaultDriver) {
 
         get.test_ged_client.test_save_guest_vtl2_state().await;
     }
+
+    /// Runs a servicing save in which the guest sends `data`, and returns
+    /// whether the guest saw it succeed along with the host's result.
+    async fn save_servicing_state(
+        get: &TestGet,
+        save_recv: &mut mesh::Receiver<GuestSaveRequest>,
+        data: Vec<u8>,
+    ) -> (bool, Result<(), SaveRestoreError>) {
+        let guest = async {
+            save_recv.recv().await.unwrap();
+            get.client.send_servicing_state(data).await.is_ok()
+        };
+        futures::join!(guest, get.test_ged_client.test_try_save_guest_vtl2_state())
+    }
+
+    #[async_test]
+    async fn test_restore_guest_vtl2_state_fault(driver: DefaultDriver) {
+        let mut get =
+            new_transport_pair(driver, None, ProtocolVersion::NICKEL_REV2, None, None).await;
+
+        get.test_ged_client
+            .test_inject_servicing_fault(ServicingFault::FailRestore)
+            .await;
+        get.client.get_saved_state_from_host().await.unwrap_err();
+    }
+
+    #[async_test]
+    async fn test_rearm_failed_restore(driver: DefaultDriver) {
+        let mut get =
+            new_transport_pair(driver, None, ProtocolVersion::NICKEL_REV2, None, None).await;
+        let mut save_recv = get.client.take_save_request_recv().await.unwrap();
+
+        let (guest, host) = save_servicing_state(&get, &mut save_recv, vec![1, 2, 3]).await;
+        assert!(guest);
+        host.unwrap();
+        let saved_state = get.client.get_saved_state_from_host().await.unwrap();
+        assert_eq!(saved_state, [1, 2, 3]);
+
+        // A failed restore fails the wait for VTL0 to start, and the saved
+        // state can then be offered again, once.
+        get.client.report_restore_result_to_host(false).await;
+        get.test_ged_client
+            .test_wait_for_vtl0_start()
+            .await
+            .unwrap_err();
+        assert!(get.test_ged_client.test_rearm_failed_restore().await);
+        assert!(!get.test_ged_client.test_rearm_failed_restore().await);
+        let saved_state = get.client.get_saved_state_from_host().await.unwrap();
+        assert_eq!(saved_state, [1, 2, 3]);
+    }
+
+    #[async_test]
+    async fn test_reject_save_fault(driver: DefaultDriver) {
+        let mut get =
+            new_transport_pair(driver, None, ProtocolVersion::NICKEL_REV2, None, None).await;
+        let mut save_recv = get.client.take_save_request_recv().await.unwrap();
+
+        get.test_ged_client
+            .test_inject_servicing_fault(ServicingFault::RejectSave)
+            .await;
+        let (guest, host) = save_servicing_state(&get, &mut save_recv, vec![1, 2, 3]).await;
+        assert!(!guest);
+        assert!(matches!(host, Err(SaveRestoreError::InjectedFault)));
+
+        // The fault only applies once.
+        let (guest, host) = save_servicing_state(&get, &mut save_recv, vec![1, 2, 3]).await;
+        assert!(guest);
+        host.unwrap();
+    }
+
+    #[async_test]
+    async fn test_corrupt_saved_state_fault(driver: DefaultDriver) {
+        let mut get =
+            new_transport_pair(driver, None, ProtocolVersion::NICKEL_REV2, None, None).await;
+        let mut save_recv = get.client.take_save_request_recv().await.unwrap();
+
+        get.test_ged_client
+            .test_inject_servicing_fault(ServicingFault::CorruptSavedState)
+            .await;
+        let (guest, host) = save_servicing_state(&get, &mut save_recv, vec![1, 2, 3]).await;
+        assert!(guest);
+        host.unwrap();
+
+        let saved_state = get.client.get_saved_state_from_host().await.unwrap();
+        assert_eq!(saved_state, [!1, !2, !3]);
+    }
+
+    #[async_test]
+    async fn test_clear_servicing_fault(driver: DefaultDriver) {
+        let mut get =
+            new_transport_pair(driver, None, ProtocolVersion::NICKEL_REV2, None, None).await;
+        let mut save_recv = get.client.take_save_request_recv().await.unwrap();
+
+        get.test_ged_client
+            .test_inject_servicing_fault(ServicingFault::FailRestore)
+            .await;
+        get.test_ged_client.test_clear_servicing_fault().await;
+
+        let (guest, host) = save_servicing_state(&get, &mut save_recv, vec![1, 2, 3]).await;
+        assert!(guest);
+        host.unwrap();
+        let saved_state = get.client.get_saved_state_from_host().await.unwrap();
+        assert_eq!(saved_state, [1, 2, 3]);
+    }
+
+    #[async_test]
+    async fn test_servicing_fault_not_carried_over(driver: DefaultDriver) {
+        let mut get =
+            new_transport_pair(driver, None, ProtocolVersion::NICKEL_REV2, None, None).await;
+        let mut save_recv = get.client.take_save_request_recv().await.unwrap();
+
+        // Arm a fault for the restore step, then have the guest abort the
+        // save so that the restore never happens.
+        get.test_ged_client
+            .test_inject_servicing_fault(ServicingFault::FailRestore)
+            .await;
+        let guest = async {
+            save_recv.recv().await.unwrap();
+            get.client.send_servicing_failure("aborted").await.unwrap();
+        };
+        let ((), host) =
+            futures::join!(guest, get.test_ged_client.test_try_save_guest_vtl2_state());
+        assert!(matches!(host, Err(SaveRestoreError::GuestError)));
+
+        // The next servicing operation does not see the fault.
+        let (guest, host) = save_servicing_state(&get, &mut save_recv, vec![1, 2, 3]).await;
+        assert!(guest);
+        host.unwrap();
+        let saved_state = get.client.get_saved_state_from_host().await.unwrap();
+        assert_eq!(saved_state, [1, 2, 3]);
+    }
+
+    /// Completes the next VTL2 settings modification in the guest, failing it
+    /// if the settings are not `expected`.
+    async fn complete_vtl2_settings_modification(
+        recv: &mut mesh::Receiver<crate::client::ModifyVtl2SettingsRequest>,
+        expected: &[u8],
+    ) {
+        let crate::client::ModifyVtl2SettingsRequest(rpc) = recv.recv().await.unwrap();
+        rpc.handle_sync(|settings| {
+            if settings == expected {
+                Ok(())
+            } else {
+                Err(Vec::new())
+            }
+        });
+    }
+
+    #[async_test]
+    async fn test_truncate_first_vtl2_settings_modification(driver: DefaultDriver) {
+        let get = new_transport_pair_with_vtl2_settings_test_config(
+            driver,
+            None,
+            ProtocolVersion::NICKEL_REV2,
+            None,
+            None,
+            Some(Vtl2SettingsTestConfig::TruncateFirstModify),
+        )
+        .await;
+        let mut recv = get.client.take_vtl2_settings_recv().await.unwrap();
+        let settings = vec![1, 2, 3];
+
+        let ((), host) = futures::join!(
+            complete_vtl2_settings_modification(&mut recv, &settings),
+            get.test_ged_client
+                .test_modify_vtl2_settings(settings.clone())
+        );
+        assert!(matches!(host, Err(ModifyVtl2SettingsError::Guest(_))));
+
+        // Only the first modification is truncated.
+        let ((), host) = futures::join!(
+            complete_vtl2_settings_modification(&mut recv, &settings),
+            get.test_ged_client
+                .test_modify_vtl2_settings(settings.clone())
+        );
+        host.unwrap();
+    }
+
+    #[async_test]
+    async fn test_delay_vtl2_settings_modification(driver: DefaultDriver) {
+        const DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+        let get = new_transport_pair_with_vtl2_settings_test_config(
+            driver,
+            None,
+            ProtocolVersion::NICKEL_REV2,
+            None,
+            None,
+            Some(Vtl2SettingsTestConfig::Delay(DELAY)),
+        )
+        .await;
+        let mut recv = get.client.take_vtl2_settings_recv().await.unwrap();
+        let settings = vec![1, 2, 3];
+
+        let start = std::time::Instant::now();
+        let ((), host) = futures::join!(
+            complete_vtl2_settings_modification(&mut recv, &settings),
+            get.test_ged_client
+                .test_modify_vtl2_settings(settings.clone())
+        );
+        host.unwrap();
+        assert!(start.elapsed() >= DELAY);
+    }
 }
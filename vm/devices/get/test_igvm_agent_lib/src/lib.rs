@@ -10,8 +10,15 @@
 
 //! NOTE: This is a test implementation and should not be used in production.
 
+mod test_ca;
 mod test_crypto;
 
+pub use crate::test_ca::TestCaAkCertIssuer;
+pub use crate::test_ca::TestCaError;
+pub use crate::test_ca::issue_test_ak_cert;
+pub use crate::test_ca::verify_quote;
+pub use crate::test_ca::verify_test_ak_cert;
+
 use crate::test_crypto::DummyRng;
 use crate::test_crypto::TestSha1;
 use crate::test_crypto::aes_key_wrap_with_padding;
@@ -38,10 +45,29 @@
 use sha2::Sha256;
 use std::collections::HashMap;
 use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 use zerocopy::FromBytes;
 use zerocopy::IntoBytes;
 
+/// The error code in failure responses scripted by
+/// [`IgvmAgentAction::RespondTransientFailure`].
+const TRANSIENT_FAILURE_ERROR_CODE: u32 = 0x2345;
+/// The error code in failure responses scripted by
+/// [`IgvmAgentAction::RespondFailure`].
+const FAILURE_ERROR_CODE: u32 = 0x1234;
+/// The error code in failure responses scripted by
+/// [`IgvmAgentAction::RespondFailureSkipHwUnsealing`].
+const FAILURE_SKIP_HW_UNSEALING_ERROR_CODE: u32 = 0x5678;
+/// The error code in responses to AK cert requests that the
+/// [`AkCertIssuer`] failed to issue a certificate for.
+const AK_CERT_ISSUER_FAILURE_ERROR_CODE: u32 = 0x9abc;
+/// How long [`IgvmAttestTestConfig::AkCertRequestTransientFaults`] delays the
+/// successful response, simulating a slow host.
+const AK_CERT_SLOW_RESPONSE_DELAY: Duration = Duration::from_secs(5);
+
 #[expect(missing_docs)] // self-explanatory fields
 #[derive(Debug, Error)]
 pub enum Error {
@@ -96,8 +122,44 @@ pub enum KeyReleaseError {
     JsonSerializeError(#[source] serde_json::Error),
 }
 
+/// A source of AK certificates for AK cert requests.
+///
+/// This allows the agent to hand out certificates from somewhere other than
+/// the built-in test CA ([`TestCaAkCertIssuer`]), such as a fixed certificate
+/// captured offline or a host-side service.
+pub trait AkCertIssuer: Debug + Send + Sync {
+    /// Returns the AK certificate to send in response to an AK cert request
+    /// with the given runtime claims.
+    ///
+    /// On failure, the agent responds with a retryable error.
+    fn issue_ak_cert(
+        &self,
+        runtime_claims: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>;
+}
+
+/// An [`AkCertIssuer`] that always returns the same certificate.
+#[derive(Debug, Clone)]
+pub struct FixedAkCertIssuer(Vec<u8>);
+
+impl FixedAkCertIssuer {
+    /// Returns an issuer that always returns `cert`.
+    pub fn new(cert: Vec<u8>) -> Self {
+        Self(cert)
+    }
+}
+
+impl AkCertIssuer for FixedAkCertIssuer {
+    fn issue_ak_cert(
+        &self,
+        _runtime_claims: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.0.clone())
+    }
+}
+
 /// Test IGVM agent includes states that need to be persisted.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct TestIgvmAgent {
     /// VM name for log correlation.
     vm_name: String,
@@ -109,6 +171,14 @@ pub struct TestIgvmAgent {
     plan: Option<IgvmAgentTestPlan>,
     /// Track whether the plan has been installed to prevent multiple installations.
     plan_installed: bool,
+    /// The source of certificates for successful AK cert requests.
+    ak_cert_issuer: Arc<dyn AkCertIssuer>,
+}
+
+impl Default for TestIgvmAgent {
+    fn default() -> Self {
+        Self::new(String::new())
+    }
 }
 
 /// Possible actions for the IGVM agent to take in response to a request.
@@ -120,6 +190,19 @@ pub enum IgvmAgentAction {
     RespondFailure,
     /// Emit a response that indicates a protocol error with skip_hw_unsealing signal.
     RespondFailureSkipHwUnsealing,
+    /// Emit a response that indicates a transient error, signaling that the
+    /// request should be retried.
+    RespondTransientFailure,
+    /// Emit a successful response that has been cut short, so that it is
+    /// smaller than the size its header claims.
+    RespondTruncated,
+    /// Delay the response to this request by the given time, then take the
+    /// next action, or respond normally if there is none.
+    ///
+    /// The delay is returned by [`TestIgvmAgent::take_response_delay`], for
+    /// the caller to wait out before handling the request. Callers that do
+    /// not wait respond without a delay.
+    Delay(Duration),
     /// Skip responding to simulate a timeout (consumed once).
     NoResponse,
     /// Skip responding for this and all subsequent requests of the same type.
@@ -226,6 +309,18 @@ fn test_config_to_plan(test_config: &IgvmAttestTestConfig) -> IgvmAgentTestPlan
                 ]),
             );
         }
+        IgvmAttestTestConfig::AkCertRequestTransientFaults => {
+            plan.insert(
+                IgvmAttestRequestType::AK_CERT_REQUEST,
+                VecDeque::from([
+                    IgvmAgentAction::RespondTransientFailure,
+                    IgvmAgentAction::RespondTruncated,
+                    IgvmAgentAction::NoResponse,
+                    IgvmAgentAction::Delay(AK_CERT_SLOW_RESPONSE_DELAY),
+                    IgvmAgentAction::RespondSuccess,
+                ]),
+            );
+        }
         IgvmAttestTestConfig::KeyReleaseFailure => {
             // Hyper-V VMs go through an `initial_reboot`, consuming two
             // KEY_RELEASE requests (one during initial boot, one during
@@ -257,9 +352,16 @@ pub fn new(vm_name: impl Into<String>) -> Self {
             des_key: None,
             plan: None,
             plan_installed: false,
+            ak_cert_issuer: Arc::new(TestCaAkCertIssuer),
         }
     }
 
+    /// Sets the source of certificates for successful AK cert requests,
+    /// replacing the test CA.
+    pub fn set_ak_cert_issuer(&mut self, issuer: Arc<dyn AkCertIssuer>) {
+        self.ak_cert_issuer = issuer;
+    }
+
     /// Install a scripted plan used by tests based on the setting.
     /// Can be called multiple times but will only install the plan once per instance.
     pub fn install_plan_from_setting(&mut self, setting: &IgvmAgentTestSetting) {
@@ -286,7 +388,8 @@ pub fn install_plan_from_setting(&mut self, setting: &IgvmAgentTestSetting) {
     ///
     /// [`IgvmAgentAction::AlwaysNoResponse`] is sticky: it is returned but
     /// never removed from the queue, so every subsequent call for the same
-    /// request type will keep returning it.
+    /// request type will keep returning it. [`IgvmAgentAction::Delay`] actions
+    /// that have not been taken by [`Self::take_response_delay`] are skipped.
     pub fn take_next_action(
         &mut self,
         request_type: IgvmAttestRequestType,
@@ -294,12 +397,36 @@ pub fn take_next_action(
         // Fast path: no plan installed.
         let plan = self.plan.as_mut()?;
         let queue = plan.get_mut(&request_type)?;
-        match queue.front()? {
-            IgvmAgentAction::AlwaysNoResponse => Some(IgvmAgentAction::AlwaysNoResponse),
-            _ => queue.pop_front(),
+        loop {
+            match queue.front()? {
+                IgvmAgentAction::AlwaysNoResponse => {
+                    return Some(IgvmAgentAction::AlwaysNoResponse);
+                }
+                IgvmAgentAction::Delay(_) => {
+                    queue.pop_front();
+                }
+                _ => return queue.pop_front(),
+            }
         }
     }
 
+    /// Take the scripted delay for the response to `request_bytes`, if any.
+    ///
+    /// This consumes the [`IgvmAgentAction::Delay`] actions at the front of
+    /// the queue for the request's type and returns their total. The caller
+    /// should wait that long before passing the request to
+    /// [`Self::handle_request`].
+    pub fn take_response_delay(&mut self, request_bytes: &[u8]) -> Option<Duration> {
+        let (request, _) = IgvmAttestRequestBase::read_from_prefix(request_bytes).ok()?;
+        let queue = self.plan.as_mut()?.get_mut(&request.header.request_type)?;
+        let mut delay = None;
+        while let Some(&IgvmAgentAction::Delay(d)) = queue.front() {
+            *delay.get_or_insert(Duration::ZERO) += d;
+            queue.pop_front();
+        }
+        delay
+    }
+
     /// Build a failure response for any request type with the given
     /// `error_code` and `igvm_signal`.
     fn build_failure_response(
@@ -384,73 +511,36 @@ pub fn handle_request(&mut self, request_bytes: &[u8]) -> Result<(Vec<u8>, u32),
             // If a plan is installed and has a queued action for this request type,
             // execute it. This allows tests to force success/no-response, etc.
             match action {
+                IgvmAgentAction::Delay(_) => unreachable!("delays are never taken as actions"),
                 IgvmAgentAction::NoResponse | IgvmAgentAction::AlwaysNoResponse => {
                     tracing::info!(?request.header.request_type, "Test plan: NoResponse");
                     (vec![], 0)
                 }
                 IgvmAgentAction::RespondSuccess => {
                     tracing::info!(?request.header.request_type, "Test plan: RespondSuccess");
-                    match request.header.request_type {
-                        IgvmAttestRequestType::WRAPPED_KEY_REQUEST => {
-                            self.initialize_keys()?;
-                            let data = self
-                                .generate_mock_wrapped_key_response()
-                                .map_err(Error::WrappedKeyError)?;
-                            let header = IgvmAttestWrappedKeyResponseHeader {
-                                data_size: (data.len()
-                                    + size_of::<IgvmAttestWrappedKeyResponseHeader>())
-                                    as u32,
-                                version: IGVM_ATTEST_RESPONSE_CURRENT_VERSION,
-                                error_info: IgvmErrorInfo::default(),
-                            };
-                            let payload = [header.as_bytes(), &data].concat();
-                            let payload_len = payload.len() as u32;
-
-                            (payload, payload_len)
-                        }
-                        IgvmAttestRequestType::KEY_RELEASE_REQUEST => {
-                            if self.secret_key.is_none() {
-                                // Ensure keys exist so we can generate a valid JWT response
-                                self.initialize_keys()?;
-                            }
-                            let jwt = self
-                                .generate_mock_key_release_response(runtime_claims_bytes)
-                                .map_err(Error::KeyReleaseError)?;
-                            let data = jwt.as_bytes().to_vec();
-                            let header = IgvmAttestKeyReleaseResponseHeader {
-                                data_size: (data.len()
-                                    + size_of::<IgvmAttestKeyReleaseResponseHeader>())
-                                    as u32,
-                                version: IGVM_ATTEST_RESPONSE_CURRENT_VERSION,
-                                error_info: IgvmErrorInfo::default(),
-                            };
-                            let payload = [header.as_bytes(), &data].concat();
-                            let payload_len = payload.len() as u32;
-
-                            (payload, payload_len)
-                        }
-                        IgvmAttestRequestType::AK_CERT_REQUEST => {
-                            let data = vec![0xab; 2500];
-                            let header = IgvmAttestAkCertResponseHeader {
-                                data_size: (data.len()
-                                    + size_of::<IgvmAttestAkCertResponseHeader>())
-                                    as u32,
-                                version: IGVM_ATTEST_RESPONSE_CURRENT_VERSION,
-                                error_info: IgvmErrorInfo::default(),
-                            };
-                            let payload = [header.as_bytes(), &data].concat();
-                            let payload_len = payload.len() as u32;
-
-                            (payload, payload_len)
-                        }
-                        ty => return Err(Error::UnsupportedIgvmAttestRequestType(ty.0)),
-                    }
+                    self.success_response(request.header.request_type, runtime_claims_bytes)?
+                }
+                IgvmAgentAction::RespondTransientFailure => {
+                    tracing::info!(?request.header.request_type, "Test plan: RespondTransientFailure");
+                    Self::build_failure_response(
+                        request.header.request_type,
+                        TRANSIENT_FAILURE_ERROR_CODE,
+                        IgvmSignal::default().with_retry(true),
+                    )?
+                }
+                IgvmAgentAction::RespondTruncated => {
+                    tracing::info!(?request.header.request_type, "Test plan: RespondTruncated");
+                    let (mut payload, _) =
+                        self.success_response(request.header.request_type, runtime_claims_bytes)?;
+                    payload.truncate(payload.len() / 2);
+                    let payload_len = payload.len() as u32;
+                    (payload, payload_len)
                 }
                 IgvmAgentAction::RespondFailure => {
                     tracing::info!(?request.header.request_type, "Test plan: RespondFailure");
                     Self::build_failure_response(
                         request.header.request_type,
-                        0x1234,
+                        FAILURE_ERROR_CODE,
                         IgvmSignal::default().with_retry(false),
                     )?
                 }
@@ -458,7 +548,7 @@ pub fn handle_request(&mut self, request_bytes: &[u8]) -> Result<(Vec<u8>, u32),
                     tracing::info!(?request.header.request_type, "Test plan: RespondFailureSkipHwUnsealing");
                     Self::build_failure_response(
                         request.header.request_type,
-                        0x5678,
+                        FAILURE_SKIP_HW_UNSEALING_ERROR_CODE,
                         IgvmSignal::default()
                             .with_retry(false)
                             .with_skip_hw_unsealing(true),
@@ -473,18 +563,7 @@ pub fn handle_request(&mut self, request_bytes: &[u8]) -> Result<(Vec<u8>, u32),
             match request.header.request_type {
                 IgvmAttestRequestType::AK_CERT_REQUEST => {
                     tracing::info!("Send a response for AK_CERT_REQUEST");
-
-                    let data = vec![0xab; 2500];
-                    let header = IgvmAttestAkCertResponseHeader {
-                        data_size: (data.len() + size_of::<IgvmAttestAkCertResponseHeader>())
-                            as u32,
-                        version: IGVM_ATTEST_RESPONSE_CURRENT_VERSION,
-                        error_info: IgvmErrorInfo::default(),
-                    };
-                    let payload = [header.as_bytes(), &data].concat();
-                    let payload_len = payload.len() as u32;
-
-                    (payload, payload_len)
+                    self.ak_cert_response(runtime_claims_bytes)?
                 }
                 IgvmAttestRequestType::WRAPPED_KEY_REQUEST => {
                     tracing::info!("Send a response for WRAPPED_KEY_REQUEST");
@@ -548,6 +627,82 @@ pub fn handle_request(&mut self, request_bytes: &[u8]) -> Result<(Vec<u8>, u32),
         Ok((response, length))
     }
 
+    /// Build a successful response for the given request type.
+    fn success_response(
+        &mut self,
+        request_type: IgvmAttestRequestType,
+        runtime_claims_bytes: &[u8],
+    ) -> Result<(Vec<u8>, u32), Error> {
+        Ok(match request_type {
+            IgvmAttestRequestType::WRAPPED_KEY_REQUEST => {
+                self.initialize_keys()?;
+                let data = self
+                    .generate_mock_wrapped_key_response()
+                    .map_err(Error::WrappedKeyError)?;
+                let header = IgvmAttestWrappedKeyResponseHeader {
+                    data_size: (data.len() + size_of::<IgvmAttestWrappedKeyResponseHeader>())
+                        as u32,
+                    version: IGVM_ATTEST_RESPONSE_CURRENT_VERSION,
+                    error_info: IgvmErrorInfo::default(),
+                };
+                let payload = [header.as_bytes(), &data].concat();
+                let payload_len = payload.len() as u32;
+
+                (payload, payload_len)
+            }
+            IgvmAttestRequestType::KEY_RELEASE_REQUEST => {
+                if self.secret_key.is_none() {
+                    // Ensure keys exist so we can generate a valid JWT response
+                    self.initialize_keys()?;
+                }
+                let jwt = self
+                    .generate_mock_key_release_response(runtime_claims_bytes)
+                    .map_err(Error::KeyReleaseError)?;
+                let data = jwt.as_bytes().to_vec();
+                let header = IgvmAttestKeyReleaseResponseHeader {
+                    data_size: (data.len() + size_of::<IgvmAttestKeyReleaseResponseHeader>())
+                        as u32,
+                    version: IGVM_ATTEST_RESPONSE_CURRENT_VERSION,
+                    error_info: IgvmErrorInfo::default(),
+                };
+                let payload = [header.as_bytes(), &data].concat();
+                let payload_len = payload.len() as u32;
+
+                (payload, payload_len)
+            }
+            IgvmAttestRequestType::AK_CERT_REQUEST => {
+                self.ak_cert_response(runtime_claims_bytes)?
+            }
+            ty => return Err(Error::UnsupportedIgvmAttestRequestType(ty.0)),
+        })
+    }
+
+    /// Build a response to an AK cert request using the AK cert issuer.
+    fn ak_cert_response(&self, runtime_claims: &[u8]) -> Result<(Vec<u8>, u32), Error> {
+        let data = match self.ak_cert_issuer.issue_ak_cert(runtime_claims) {
+            Ok(data) => data,
+            Err(err) => {
+                tracing::error!(
+                    error = err.as_ref() as &dyn std::error::Error,
+                    "AK cert issuer failed"
+                );
+                return Self::build_failure_response(
+                    IgvmAttestRequestType::AK_CERT_REQUEST,
+                    AK_CERT_ISSUER_FAILURE_ERROR_CODE,
+                    IgvmSignal::default().with_retry(true),
+                );
+            }
+        };
+        let header = IgvmAttestAkCertResponseHeader {
+            data_size: (data.len() + size_of::<IgvmAttestAkCertResponseHeader>()) as u32,
+            version: IGVM_ATTEST_RESPONSE_CURRENT_VERSION,
+            error_info: IgvmErrorInfo::default(),
+        };
+        let payload = [header.as_bytes(), &data].concat();
+        let payload_len = payload.len() as u32;
+        Ok((payload, payload_len))
+    }
+
     fn initialize_keys(&mut self) -> Result<(), Error> {
         if self.secret_key.is_some() && self.des_key.is_some() {
             // Keys are already initialized, nothing to do.
@@ -740,3 +895,126 @@ fn generate_jwt_with_rsa_key(
         Ok(format!("{}.{}.{}", header_b64, body_b64, signature_b64))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zerocopy::FromZeros;
+
+    #[derive(Debug)]
+    struct FailingAkCertIssuer;
+
+    impl AkCertIssuer for FailingAkCertIssuer {
+        fn issue_ak_cert(
+            &self,
+            _runtime_claims: &[u8],
+        ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+            Err("no certificate".into())
+        }
+    }
+
+    fn ak_cert_request_bytes() -> Vec<u8> {
+        let mut request = IgvmAttestRequestBase::new_zeroed();
+        request.header.request_type = IgvmAttestRequestType::AK_CERT_REQUEST;
+        request.request_data.version = IGVM_ATTEST_REQUEST_CURRENT_VERSION;
+        [
+            request.as_bytes(),
+            IgvmAttestRequestDataExt::new_zeroed().as_bytes(),
+        ]
+        .concat()
+    }
+
+    fn ak_cert_request(agent: &mut TestIgvmAgent) -> (IgvmAttestAkCertResponseHeader, Vec<u8>) {
+        let (response, len) = agent.handle_request(&ak_cert_request_bytes()).unwrap();
+        assert_eq!(response.len(), len as usize);
+        let (header, data) = IgvmAttestAkCertResponseHeader::read_from_prefix(&response).unwrap();
+        assert_eq!(header.data_size as usize, response.len());
+        (header, data.to_vec())
+    }
+
+    #[test]
+    fn ak_cert_from_issuer() {
+        let mut agent = TestIgvmAgent::new("test");
+        agent.set_ak_cert_issuer(Arc::new(FixedAkCertIssuer::new(vec![1, 2, 3])));
+
+        let (header, data) = ak_cert_request(&mut agent);
+        assert_eq!(header.error_info.error_code, 0);
+        assert_eq!(data, [1, 2, 3]);
+    }
+
+    #[test]
+    fn ak_cert_issuer_failure_is_retryable() {
+        let mut agent = TestIgvmAgent::new("test");
+        agent.set_ak_cert_issuer(Arc::new(FailingAkCertIssuer));
+
+        let (header, data) = ak_cert_request(&mut agent);
+        assert_eq!(
+            header.error_info.error_code,
+            AK_CERT_ISSUER_FAILURE_ERROR_CODE
+        );
+        assert!(header.error_info.igvm_signal.retry());
+        assert!(data.is_empty());
+    }
+
+    #[test]
+    fn ak_cert_transient_faults() {
+        let mut agent = TestIgvmAgent::new("test");
+        agent.set_ak_cert_issuer(Arc::new(FixedAkCertIssuer::new(vec![1, 2, 3])));
+        agent.install_plan_from_setting(&IgvmAgentTestSetting::TestConfig(
+            IgvmAttestTestConfig::AkCertRequestTransientFaults,
+        ));
+        let request = ak_cert_request_bytes();
+
+        assert_eq!(agent.take_response_delay(&request), None);
+        let (header, _) = ak_cert_request(&mut agent);
+        assert_eq!(header.error_info.error_code, TRANSIENT_FAILURE_ERROR_CODE);
+        assert!(header.error_info.igvm_signal.retry());
+
+        assert_eq!(agent.take_response_delay(&request), None);
+        let (response, len) = agent.handle_request(&request).unwrap();
+        assert_eq!(response.len(), len as usize);
+        let (header, _) = IgvmAttestAkCertResponseHeader::read_from_prefix(&response).unwrap();
+        assert!((header.data_size as usize) > response.len());
+
+        assert_eq!(agent.take_response_delay(&request), None);
+        assert_eq!(agent.handle_request(&request).unwrap(), (Vec::new(), 0));
+
+        assert_eq!(
+            agent.take_response_delay(&request),
+            Some(AK_CERT_SLOW_RESPONSE_DELAY)
+        );
+        let (header, data) = ak_cert_request(&mut agent);
+        assert_eq!(header.error_info.error_code, 0);
+        assert_eq!(data, [1, 2, 3]);
+    }
+
+    #[test]
+    fn delays_are_summed_or_skipped() {
+        let request = ak_cert_request_bytes();
+        let plan = IgvmAgentTestPlan::from([(
+            IgvmAttestRequestType::AK_CERT_REQUEST,
+            VecDeque::from([
+                IgvmAgentAction::Delay(Duration::from_secs(1)),
+                IgvmAgentAction::Delay(Duration::from_secs(2)),
+                IgvmAgentAction::RespondFailure,
+                IgvmAgentAction::Delay(Duration::from_secs(3)),
+                IgvmAgentAction::RespondFailure,
+            ]),
+        )]);
+        let mut agent = TestIgvmAgent::new("test");
+        agent.install_plan_from_setting(&IgvmAgentTestSetting::TestPlan(plan));
+
+        assert_eq!(
+            agent.take_response_delay(&request),
+            Some(Duration::from_secs(3))
+        );
+        let (header, _) = ak_cert_request(&mut agent);
+        assert_eq!(header.error_info.error_code, FAILURE_ERROR_CODE);
+
+        // A delay that the caller does not take does not consume the action
+        // after it.
+        let (header, _) = ak_cert_request(&mut agent);
+        assert_eq!(header.error_info.error_code, FAILURE_ERROR_CODE);
+        assert_eq!(agent.take_response_delay(&request), None);
+    }
+}
@@ -0,0 +1,418 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! A deterministic test CA that issues AK certificates, along with helpers
+//! for tests to verify AK certificates and TPM quotes against it.
+//!
+//! NOTE: This is a test implementation and should not be used in production.
+//! The CA key is derived from a fixed seed, so anyone can issue certificates
+//! from it.
+
+use crate::AkCertIssuer;
+use crate::test_crypto::DummyRng;
+use openhcl_attestation_protocol::igvm_attest::get::runtime_claims::RuntimeClaims;
+use rsa::Pkcs1v15Sign;
+use rsa::RsaPrivateKey;
+use rsa::RsaPublicKey;
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::pkcs8::EncodePublicKey;
+use rsa::rand_core::SeedableRng;
+use rsa::traits::PublicKeyParts;
+use sha2::Digest;
+use sha2::Sha256;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
+use thiserror::Error;
+use x509_cert::Certificate;
+use x509_cert::TbsCertificate;
+use x509_cert::builder::Builder;
+use x509_cert::builder::CertificateBuilder;
+use x509_cert::builder::profile::BuilderProfile;
+use x509_cert::der::Decode;
+use x509_cert::der::Encode;
+use x509_cert::der::SliceReader;
+use x509_cert::der::asn1::GeneralizedTime;
+use x509_cert::ext::Extension;
+use x509_cert::name::Name;
+use x509_cert::serial_number::SerialNumber;
+use x509_cert::spki::SubjectPublicKeyInfoOwned;
+use x509_cert::spki::SubjectPublicKeyInfoRef;
+use x509_cert::time::Time;
+use x509_cert::time::Validity;
+
+const TEST_CA_NAME: &str = "CN=OpenVMM Test AK CA";
+const TEST_AK_NAME: &str = "CN=OpenVMM Test AK";
+const TEST_CA_KEY_SEED: u64 = 5678;
+
+/// `TPM_GENERATED_VALUE`, the magic at the start of every `TPMS_ATTEST`.
+const TPM_GENERATED_VALUE: u32 = 0xff544347;
+/// `TPM_ST_ATTEST_QUOTE`
+const TPM_ST_ATTEST_QUOTE: u16 = 0x8018;
+
+#[expect(missing_docs)] // self-explanatory fields
+#[derive(Debug, Error)]
+pub enum TestCaError {
+    #[error("invalid runtime claims")]
+    InvalidRuntimeClaims(#[source] serde_json::Error),
+    #[error("missing AK public key in runtime claims")]
+    MissingAkPubInRuntimeClaims,
+    #[error("invalid RSA public key")]
+    InvalidRsaPublicKey(#[source] rsa::Error),
+    #[error("failed to encode or decode an RSA public key")]
+    PublicKeyDer(#[source] rsa::pkcs8::spki::Error),
+    #[error("failed to build the AK certificate")]
+    BuildCertificate(#[source] x509_cert::builder::Error),
+    #[error("failed to encode or decode the AK certificate")]
+    CertificateDer(#[source] x509_cert::der::Error),
+    #[error("AK certificate was not issued by the test CA")]
+    WrongIssuer,
+    #[error("AK certificate signature is invalid")]
+    InvalidCertificateSignature(#[source] rsa::Error),
+    #[error("AK certificate does not certify the AK public key")]
+    AkPubMismatch,
+    #[error("quote is malformed")]
+    MalformedQuote,
+    #[error("quote does not contain the nonce")]
+    NonceMismatch,
+    #[error("quote signature is invalid")]
+    InvalidQuoteSignature(#[source] rsa::Error),
+}
+
+/// An [`AkCertIssuer`] that issues certificates from the test CA for the AK
+/// public key in the request's runtime claims.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TestCaAkCertIssuer;
+
+impl AkCertIssuer for TestCaAkCertIssuer {
+    fn issue_ak_cert(
+        &self,
+        runtime_claims: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let runtime_claims: RuntimeClaims =
+            serde_json::from_slice(runtime_claims).map_err(TestCaError::InvalidRuntimeClaims)?;
+        let ak_pub = runtime_claims
+            .keys
+            .iter()
+            .find(|key| key.kid == "HCLAkPub")
+            .ok_or(TestCaError::MissingAkPubInRuntimeClaims)?;
+
+        Ok(issue_test_ak_cert(&ak_pub.n, &ak_pub.e)?)
+    }
+}
+
+/// Profile for AK certificates: issued by the test CA, with no extensions.
+struct AkCertProfile;
+
+impl BuilderProfile for AkCertProfile {
+    fn get_issuer(&self, _subject: &Name) -> Name {
+        test_ca_name()
+    }
+
+    fn get_subject(&self) -> Name {
+        Name::from_str(TEST_AK_NAME).expect("valid name")
+    }
+
+    fn build_extensions(
+        &self,
+        _spk: SubjectPublicKeyInfoRef<'_>,
+        _issuer_spk: SubjectPublicKeyInfoRef<'_>,
+        _tbs: &TbsCertificate,
+    ) -> x509_cert::builder::Result<Vec<Extension>> {
+        Ok(Vec::new())
+    }
+}
+
+fn test_ca_name() -> Name {
+    Name::from_str(TEST_CA_NAME).expect("valid name")
+}
+
+/// Returns the test CA's key, which is the same on every call and in every
+/// process.
+fn test_ca_key() -> &'static RsaPrivateKey {
+    static KEY: OnceLock<RsaPrivateKey> = OnceLock::new();
+    KEY.get_or_init(|| {
+        let mut rng = DummyRng::from_seed(TEST_CA_KEY_SEED.to_le_bytes());
+        RsaPrivateKey::new(&mut rng, 2048).expect("failed to generate test CA key")
+    })
+}
+
+fn rsa_public_key(modulus: &[u8], exponent: &[u8]) -> Result<RsaPublicKey, TestCaError> {
+    RsaPublicKey::new(
+        rsa::BoxedUint::from_be_slice_vartime(modulus),
+        rsa::BoxedUint::from_be_slice_vartime(exponent),
+    )
+    .map_err(TestCaError::InvalidRsaPublicKey)
+}
+
+/// Issues a DER-encoded AK certificate from the test CA for the RSA public key
+/// with the given big-endian `modulus` and `exponent`.
+pub fn issue_test_ak_cert(modulus: &[u8], exponent: &[u8]) -> Result<Vec<u8>, TestCaError> {
+    let ak_pub = rsa_public_key(modulus, exponent)?;
+    let spki_der = ak_pub
+        .to_public_key_der()
+        .map_err(TestCaError::PublicKeyDer)?;
+    let spki = SubjectPublicKeyInfoOwned::from_der(spki_der.as_bytes())
+        .map_err(TestCaError::CertificateDer)?;
+
+    // Use a fixed serial number and validity so that the certificate only
+    // depends on the AK.
+    let validity = Validity::new(
+        GeneralizedTime::from_unix_duration(Duration::ZERO)
+            .map_err(TestCaError::CertificateDer)?
+            .into(),
+        Time::INFINITY,
+    );
+    let builder = CertificateBuilder::new(AkCertProfile, SerialNumber::from(1u32), validity, spki)
+        .map_err(TestCaError::BuildCertificate)?;
+    let cert = builder
+        .build(&SigningKey::<Sha256>::new(test_ca_key().clone()))
+        .map_err(TestCaError::BuildCertificate)?;
+
+    cert.to_der().map_err(TestCaError::CertificateDer)
+}
+
+/// Verifies that `cert` was issued by the test CA for the RSA public key with
+/// the given big-endian `modulus` and `exponent`.
+///
+/// `cert` may be followed by padding, as it is when read from the AK cert NV
+/// index.
+pub fn verify_test_ak_cert(
+    cert: &[u8],
+    modulus: &[u8],
+    exponent: &[u8],
+) -> Result<(), TestCaError> {
+    let mut reader = SliceReader::new(cert).map_err(TestCaError::CertificateDer)?;
+    let cert = Certificate::decode(&mut reader).map_err(TestCaError::CertificateDer)?;
+    let tbs = cert.tbs_certificate();
+
+    if tbs.issuer() != &test_ca_name() {
+        return Err(TestCaError::WrongIssuer);
+    }
+
+    let tbs_der = tbs.to_der().map_err(TestCaError::CertificateDer)?;
+    test_ca_key()
+        .to_public_key()
+        .verify(
+            Pkcs1v15Sign::new::<Sha256>(),
+            &Sha256::digest(&tbs_der),
+            cert.signature().raw_bytes(),
+        )
+        .map_err(TestCaError::InvalidCertificateSignature)?;
+
+    let spki_der = tbs
+        .subject_public_key_info()
+        .to_der()
+        .map_err(TestCaError::CertificateDer)?;
+    let cert_pub =
+        RsaPublicKey::from_public_key_der(&spki_der).map_err(TestCaError::PublicKeyDer)?;
+    let ak_pub = rsa_public_key(modulus, exponent)?;
+    if cert_pub.n().to_be_bytes_trimmed_vartime() != ak_pub.n().to_be_bytes_trimmed_vartime()
+        || cert_pub.e().to_be_bytes_trimmed_vartime() != ak_pub.e().to_be_bytes_trimmed_vartime()
+    {
+        return Err(TestCaError::AkPubMismatch);
+    }
+
+    Ok(())
+}
+
+/// Verifies a `TPM2_Quote` made with the AK whose RSA public key has the
+/// given big-endian `modulus` and `exponent`.
+///
+/// `attest` is the marshaled `TPMS_ATTEST` and `signature` is its
+/// RSASSA-PKCS1-v1_5 SHA-256 signature. The quote must have been made with
+/// `nonce` as its qualifying data.
+pub fn verify_quote(
+    attest: &[u8],
+    signature: &[u8],
+    nonce: &[u8],
+    modulus: &[u8],
+    exponent: &[u8],
+) -> Result<(), TestCaError> {
+    rsa_public_key(modulus, exponent)?
+        .verify(
+            Pkcs1v15Sign::new::<Sha256>(),
+            &Sha256::digest(attest),
+            signature,
+        )
+        .map_err(TestCaError::InvalidQuoteSignature)?;
+
+    // `TPMS_ATTEST` starts with the magic, the type, the signer's name, and
+    // then the qualifying data.
+    let (magic, rest) = attest
+        .split_first_chunk::<4>()
+        .ok_or(TestCaError::MalformedQuote)?;
+    let (attest_type, rest) = rest
+        .split_first_chunk::<2>()
+        .ok_or(TestCaError::MalformedQuote)?;
+    if u32::from_be_bytes(*magic) != TPM_GENERATED_VALUE
+        || u16::from_be_bytes(*attest_type) != TPM_ST_ATTEST_QUOTE
+    {
+        return Err(TestCaError::MalformedQuote);
+    }
+
+    let (_qualified_signer, rest) = split_tpm2b(rest)?;
+    let (extra_data, _) = split_tpm2b(rest)?;
+    if extra_data != nonce {
+        return Err(TestCaError::NonceMismatch);
+    }
+
+    Ok(())
+}
+
+/// Splits a `TPM2B` structure off the front of `data`, returning its contents
+/// and the remaining bytes.
+fn split_tpm2b(data: &[u8]) -> Result<(&[u8], &[u8]), TestCaError> {
+    let (size, rest) = data
+        .split_first_chunk::<2>()
+        .ok_or(TestCaError::MalformedQuote)?;
+    rest.split_at_checked(u16::from_be_bytes(*size).into())
+        .ok_or(TestCaError::MalformedQuote)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_key_components() -> (Vec<u8>, Vec<u8>) {
+        let key = test_ca_key();
+        (
+            key.n().to_be_bytes_trimmed_vartime().to_vec(),
+            key.e().to_be_bytes_trimmed_vartime().to_vec(),
+        )
+    }
+
+    fn sign(data: &[u8]) -> Vec<u8> {
+        test_ca_key()
+            .sign(Pkcs1v15Sign::new::<Sha256>(), &Sha256::digest(data))
+            .unwrap()
+    }
+
+    fn quote_attest(nonce: &[u8]) -> Vec<u8> {
+        let mut attest = Vec::new();
+        attest.extend_from_slice(&TPM_GENERATED_VALUE.to_be_bytes());
+        attest.extend_from_slice(&TPM_ST_ATTEST_QUOTE.to_be_bytes());
+        attest.extend_from_slice(&[0x00, 0x02, 0xaa, 0xbb]);
+        attest.extend_from_slice(&(nonce.len() as u16).to_be_bytes());
+        attest.extend_from_slice(nonce);
+        attest.extend_from_slice(&[0; 17]);
+        attest
+    }
+
+    #[test]
+    fn ak_cert_round_trip() {
+        let (n, e) = test_key_components();
+        let cert = issue_test_ak_cert(&n, &e).unwrap();
+
+        // Issuing is deterministic.
+        assert_eq!(cert, issue_test_ak_cert(&n, &e).unwrap());
+
+        // Padding after the certificate is ignored.
+        let mut padded = cert.clone();
+        padded.resize(4096, 0);
+        verify_test_ak_cert(&padded, &n, &e).unwrap();
+    }
+
+    #[test]
+    fn ak_cert_rejects_other_key() {
+        let (n, e) = test_key_components();
+        let cert = issue_test_ak_cert(&n, &e).unwrap();
+
+        let mut other_n = n.clone();
+        *other_n.last_mut().unwrap() ^= 2;
+        assert!(matches!(
+            verify_test_ak_cert(&cert, &other_n, &e),
+            Err(TestCaError::AkPubMismatch)
+        ));
+    }
+
+    #[test]
+    fn ak_cert_rejects_bad_signature() {
+        let (n, e) = test_key_components();
+        let mut cert = issue_test_ak_cert(&n, &e).unwrap();
+
+        *cert.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            verify_test_ak_cert(&cert, &n, &e),
+            Err(TestCaError::InvalidCertificateSignature(_))
+        ));
+    }
+
+    #[test]
+    fn ak_cert_rejects_placeholder() {
+        let (n, e) = test_key_components();
+        verify_test_ak_cert(&[0xab; 2500], &n, &e).unwrap_err();
+    }
+
+    #[test]
+    fn issuer_uses_runtime_claims_ak_pub() {
+        use openhcl_attestation_protocol::igvm_attest::get::runtime_claims::AttestationVmConfig;
+
+        let (n, e) = test_key_components();
+        let vm_config = AttestationVmConfig {
+            current_time: None,
+            root_cert_thumbprint: String::new(),
+            console_enabled: false,
+            interactive_console_enabled: false,
+            secure_boot: false,
+            tpm_enabled: true,
+            tpm_persisted: true,
+            filtered_vpci_devices_allowed: false,
+            vm_unique_id: String::new(),
+        };
+        // Use a different key for the EK to check that the AK is certified.
+        let runtime_claims =
+            RuntimeClaims::ak_cert_runtime_claims(&e, &n, &e, &[0xff; 256], &vm_config, &[]);
+
+        let cert = TestCaAkCertIssuer
+            .issue_ak_cert(&serde_json::to_vec(&runtime_claims).unwrap())
+            .unwrap();
+        verify_test_ak_cert(&cert, &n, &e).unwrap();
+
+        TestCaAkCertIssuer.issue_ak_cert(b"{}").unwrap_err();
+    }
+
+    #[test]
+    fn quote_round_trip() {
+        let (n, e) = test_key_components();
+        let nonce = [0x5a; 16];
+        let attest = quote_attest(&nonce);
+        let signature = sign(&attest);
+
+        verify_quote(&attest, &signature, &nonce, &n, &e).unwrap();
+        assert!(matches!(
+            verify_quote(&attest, &signature, &[0x5a; 15], &n, &e),
+            Err(TestCaError::NonceMismatch)
+        ));
+    }
+
+    #[test]
+    fn quote_rejects_bad_signature() {
+        let (n, e) = test_key_components();
+        let nonce = [0x5a; 16];
+        let mut attest = quote_attest(&nonce);
+        let signature = sign(&attest);
+
+        attest[attest.len() - 1] ^= 1;
+        assert!(matches!(
+            verify_quote(&attest, &signature, &nonce, &n, &e),
+            Err(TestCaError::InvalidQuoteSignature(_))
+        ));
+    }
+
+    #[test]
+    fn quote_rejects_other_structures() {
+        let (n, e) = test_key_components();
+        let nonce = [0x5a; 16];
+        let mut attest = quote_attest(&nonce);
+        // TPM_ST_ATTEST_CERTIFY
+        attest[4..6].copy_from_slice(&0x8017u16.to_be_bytes());
+        let signature = sign(&attest);
+
+        assert!(matches!(
+            verify_quote(&attest, &signature, &nonce, &n, &e),
+            Err(TestCaError::MalformedQuote)
+        ));
+    }
+}
@@ -142,6 +142,23 @@ pub struct ScsiDisk {
     pub physical_devices: PhysicalDevices,
     pub ntfs_guid: Option<Guid>,
     pub is_dvd: bool,
+    pub telemetry: DiskTelemetry,
+}
+
+/// Opt-in diagnostics for a disk, which can be changed at runtime.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, MeshPayload, Inspect)]
+pub struct DiskTelemetry {
+    pub latency_histogram: bool,
+    pub trace_level: IoTraceLevel,
+}
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, MeshPayload, Inspect)]
+pub enum IoTraceLevel {
+    #[default]
+    Off,
+    Info,
+    Debug,
+    Trace,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, MeshPayload, Inspect)]
@@ -157,6 +174,7 @@ pub struct NvmeNamespace {
     pub nsid: u32,
     pub disk_params: DiskParameters,
     pub physical_devices: PhysicalDevices,
+    pub telemetry: DiskTelemetry,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, MeshPayload, Inspect)]
@@ -181,6 +199,33 @@ pub struct Vtl2SettingsFixed {
     pub io_ring_size: u32,
     /// Max bounce buffer pages active per cpu
     pub max_bounce_buffer_pages: Option<u32>,
+    /// Namespace allow-lists for assigned NVMe controllers
+    #[inspect(iter_by_index)]
+    pub nvme_namespace_filters: Vec<NvmeNamespaceFilter>,
+    /// PCI IDs of assigned NVMe controllers to reset rather than keep alive
+    /// across servicing
+    #[inspect(iter_by_index)]
+    pub nvme_keepalive_disabled: Vec<String>,
+}
+
+/// Restricts which namespaces of an assigned NVMe controller may be used.
+#[derive(Debug, Clone, Eq, PartialEq, MeshPayload, Inspect)]
+pub struct NvmeNamespaceFilter {
+    /// PCI ID of the controller
+    pub pci_id: String,
+    /// Allowed namespace IDs
+    #[inspect(iter_by_index)]
+    pub allowed_nsids: Vec<u32>,
+    /// Allowed namespace globally unique identifiers
+    #[inspect(with = "|x| inspect::iter_by_index(x.iter().map(inspect::AsBytes))")]
+    pub allowed_nguids: Vec<[u8; 16]>,
+}
+
+impl NvmeNamespaceFilter {
+    /// Returns whether the namespace with the given ID and NGUID may be used.
+    pub fn allows(&self, nsid: u32, nguid: &[u8; 16]) -> bool {
+        self.allowed_nsids.contains(&nsid) || self.allowed_nguids.contains(nguid)
+    }
 }
 
 #[derive(Debug, Clone, MeshPayload, Inspect)]
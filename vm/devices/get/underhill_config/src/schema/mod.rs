@@ -496,4 +496,64 @@ fn namespace_test_empty_nic_devices_protobuf() {
         let settings = crate::Vtl2Settings::read_from(&buf, old_settings).unwrap();
         assert_eq!(0, settings.dynamic.nic_devices.len());
     }
+
+    #[test]
+    fn nvme_keepalive_disabled() {
+        let json = br#"{
+            "version": "V1",
+            "fixed": {
+                "nvme_keepalive_disabled": ["0000:00:01.0"]
+            }
+        }"#;
+        let settings = crate::Vtl2Settings::read_from(json, Default::default()).unwrap();
+        assert_eq!(settings.fixed.nvme_keepalive_disabled, ["0000:00:01.0"]);
+    }
+
+    #[test]
+    fn storage_telemetry_inherits_controller_settings() {
+        let settings = crate::Vtl2Settings::read_from(
+            include_bytes!("vtl2s_test_storage_telemetry.json"),
+            Default::default(),
+        )
+        .unwrap();
+
+        let telemetry = settings.dynamic.scsi_controllers[0]
+            .disks
+            .iter()
+            .map(|disk| disk.telemetry)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            telemetry,
+            [
+                crate::DiskTelemetry {
+                    latency_histogram: true,
+                    trace_level: crate::IoTraceLevel::Debug,
+                },
+                crate::DiskTelemetry {
+                    latency_histogram: false,
+                    trace_level: crate::IoTraceLevel::Info,
+                },
+                crate::DiskTelemetry {
+                    latency_histogram: true,
+                    trace_level: crate::IoTraceLevel::Info,
+                },
+            ]
+        );
+
+        let telemetry = settings.dynamic.nvme_controllers[0]
+            .namespaces
+            .iter()
+            .map(|namespace| namespace.telemetry)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            telemetry,
+            [
+                crate::DiskTelemetry {
+                    latency_histogram: true,
+                    trace_level: crate::IoTraceLevel::Trace,
+                },
+                crate::DiskTelemetry::default(),
+            ]
+        );
+    }
 }
@@ -69,6 +69,8 @@ pub(crate) enum Error<'a> {
     StorageProtocolUnknown,
     #[error("invalid device type")]
     StorageInvalidDeviceType,
+    #[error("invalid NVMe namespace NGUID '{0}'")]
+    StorageInvalidNvmeNguid(&'a str),
 }
 
 impl Error<'_> {
@@ -120,6 +122,7 @@ fn code(&self) -> Vtl2SettingsErrorCode {
             }
             Error::StorageProtocolUnknown => Vtl2SettingsErrorCode::StorageInvalidControllerType,
             Error::StorageInvalidDeviceType => Vtl2SettingsErrorCode::StorageUnsupportedDeviceType,
+            Error::StorageInvalidNvmeNguid(_) => Vtl2SettingsErrorCode::StorageInvalidDeviceId,
         }
     }
 }
@@ -161,6 +164,18 @@ fn parse_instance_id(instance_id: &str) -> Result<Guid, Error<'_>> {
         .map_err(|err| Error::InvalidInstanceId(instance_id, err))
 }
 
+fn parse_nguid(nguid: &str) -> Result<[u8; 16], Error<'_>> {
+    let err = || Error::StorageInvalidNvmeNguid(nguid);
+    if nguid.len() != 32 || !nguid.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return Err(err());
+    }
+    let mut bytes = [0; 16];
+    for (i, b) in bytes.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&nguid[i * 2..i * 2 + 2], 16).map_err(|_| err())?;
+    }
+    Ok(bytes)
+}
+
 fn parse_ntfs_guid(ntfs_guid: Option<&str>) -> Result<Option<Guid>, Error<'_>> {
     ntfs_guid
         .map(|guid| {
@@ -330,6 +345,33 @@ fn parse_schema(&self, errors: &mut ParseErrors<'_>) -> Result<crate::IdeDisk, P
     }
 }
 
+/// Parses the telemetry settings for a disk, with unset LUN settings falling
+/// back to the controller's.
+fn parse_telemetry(
+    controller: Option<&StorageTelemetry>,
+    lun: Option<&StorageTelemetry>,
+) -> crate::DiskTelemetry {
+    fn setting<T>(
+        controller: Option<&StorageTelemetry>,
+        lun: Option<&StorageTelemetry>,
+        f: fn(&StorageTelemetry) -> Option<T>,
+    ) -> Option<T> {
+        lun.and_then(f).or_else(|| controller.and_then(f))
+    }
+    let trace_level = setting(controller, lun, |t| {
+        t.trace_level.is_some().then(|| t.trace_level())
+    });
+    crate::DiskTelemetry {
+        latency_histogram: setting(controller, lun, |t| t.latency_histogram).unwrap_or(false),
+        trace_level: match trace_level.unwrap_or(storage_telemetry::TraceLevel::Off) {
+            storage_telemetry::TraceLevel::Off => crate::IoTraceLevel::Off,
+            storage_telemetry::TraceLevel::Info => crate::IoTraceLevel::Info,
+            storage_telemetry::TraceLevel::Debug => crate::IoTraceLevel::Debug,
+            storage_telemetry::TraceLevel::Trace => crate::IoTraceLevel::Trace,
+        },
+    }
+}
+
 impl ParseSchema<crate::ScsiDisk> for Lun {
     fn parse_schema(
         &self,
@@ -347,6 +389,7 @@ fn parse_schema(
                 physical_devices: self.parse(errors)?,
                 ntfs_guid: parse_ntfs_guid(self.ntfs_guid.as_deref())?,
                 is_dvd: self.is_dvd,
+                telemetry: parse_telemetry(None, self.telemetry.as_ref()),
             })
         })
     }
@@ -369,6 +412,7 @@ fn parse_schema(
                 nsid: self.location,
                 disk_params: self.parse(errors)?,
                 physical_devices: self.parse(errors)?,
+                telemetry: parse_telemetry(None, self.telemetry.as_ref()),
             })
         })
     }
@@ -420,7 +464,12 @@ fn parse_schema(
             let disks = self
                 .luns
                 .iter()
-                .flat_map(|lun| lun.parse(errors).collect_error(errors))
+                .flat_map(|lun| {
+                    let mut disk: crate::ScsiDisk = lun.parse(errors).collect_error(errors)?;
+                    disk.telemetry =
+                        parse_telemetry(self.telemetry.as_ref(), lun.telemetry.as_ref());
+                    Some(disk)
+                })
                 .collect::<Vec<crate::ScsiDisk>>();
 
             check_dups(errors, disks.iter().map(|disk| disk.location.into()));
@@ -447,7 +496,13 @@ fn parse_schema(
             let namespaces = self
                 .luns
                 .iter()
-                .flat_map(|lun| lun.parse(errors).collect_error(errors))
+                .flat_map(|lun| {
+                    let mut namespace: crate::NvmeNamespace =
+                        lun.parse(errors).collect_error(errors)?;
+                    namespace.telemetry =
+                        parse_telemetry(self.telemetry.as_ref(), lun.telemetry.as_ref());
+                    Some(namespace)
+                })
                 .collect::<Vec<crate::NvmeNamespace>>();
 
             check_dups(errors, namespaces.iter().map(|ns| ns.nsid));
@@ -522,12 +577,39 @@ fn parse_schema(
 impl ParseSchema<crate::Vtl2SettingsFixed> for Vtl2SettingsFixed {
     fn parse_schema(
         &self,
-        _errors: &mut ParseErrors<'_>,
+        errors: &mut ParseErrors<'_>,
     ) -> Result<crate::Vtl2SettingsFixed, ParsingStopped> {
         Ok(crate::Vtl2SettingsFixed {
             scsi_sub_channels: self.scsi_sub_channels.map_or(0, |x| x as u16),
             io_ring_size: self.io_ring_size.unwrap_or(256),
             max_bounce_buffer_pages: self.max_bounce_buffer_pages,
+            nvme_namespace_filters: self
+                .nvme_namespace_filters
+                .iter()
+                .flat_map(|filter| filter.parse(errors).collect_error(errors))
+                .collect(),
+            nvme_keepalive_disabled: self.nvme_keepalive_disabled.clone(),
+        })
+    }
+}
+
+impl ParseSchema<crate::NvmeNamespaceFilter> for NvmeNamespaceFilter {
+    fn parse_schema(
+        &self,
+        errors: &mut ParseErrors<'_>,
+    ) -> Result<crate::NvmeNamespaceFilter, ParsingStopped> {
+        let mut allowed_nguids = Vec::new();
+        for nguid in &self.allowed_nguids {
+            match parse_nguid(nguid) {
+                Ok(nguid) => allowed_nguids.push(nguid),
+                Err(err) => errors.push(err),
+            }
+        }
+
+        Ok(crate::NvmeNamespaceFilter {
+            pci_id: self.pci_id.clone(),
+            allowed_nsids: self.allowed_nsids.clone(),
+            allowed_nguids,
         })
     }
 }
@@ -0,0 +1,1238 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Hyper-V dynamic memory device.
+//!
+//! This implements the host side of the Hyper-V dynamic memory protocol,
+//! which is supported by Windows guests and by the Linux `hv_balloon` driver.
+//! After the guest negotiates a protocol version and reports its
+//! capabilities, the host can:
+//!
+//! * Balloon memory out of the guest. The device asks the guest for pages
+//!   until the balloon reaches the target set via [`DynamicMemoryRpc`], and
+//!   releases the backing of the page ranges the guest gives up via
+//!   [`DiscardRam`]. Lowering the target returns ranges to the guest.
+//! * Hot-add memory. The device adds RAM to the VM via [`HotAddRam`] and asks
+//!   the guest to add it. Hot-added RAM is offered to the guest again each
+//!   time it connects, since it is not in the firmware's memory map.
+//!
+//! The device sends one request to the guest at a time. The guest's periodic
+//! memory status reports are kept for the host to query.
+
+#![forbid(unsafe_code)]
+
+mod protocol;
+pub mod resolver;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use futures_concurrency::future::Race as _;
+use guestmem::DiscardRam;
+use guestmem::GuestMemory;
+use guestmem::HotAddRam;
+use guestmem::MemoryRead;
+use hyperv_dm_resources::DynamicMemoryRpc;
+use hyperv_dm_resources::DynamicMemoryStatus;
+use hyperv_dm_resources::GuestMemoryStatus;
+use inspect::Inspect;
+use inspect::InspectMut;
+use mesh::rpc::FailableRpc;
+use mesh::rpc::Rpc;
+use pal_async::task::Spawn;
+use pal_async::task::Task;
+use parking_lot::Mutex;
+use protocol::PAGE_SIZE;
+use protocol::PageRange;
+use std::collections::VecDeque;
+use std::future::pending;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use task_control::Cancelled;
+use task_control::StopTask;
+use thiserror::Error;
+use vmbus_async::queue;
+use vmbus_async::queue::IncomingPacket;
+use vmbus_async::queue::OutgoingPacket;
+use vmbus_async::queue::Queue;
+use vmbus_channel::RawAsyncChannel;
+use vmbus_channel::bus::ChannelType;
+use vmbus_channel::bus::OfferParams;
+use vmbus_channel::channel::ChannelOpenError;
+use vmbus_channel::gpadl_ring::GpadlRingMem;
+use vmbus_channel::simple::SaveRestoreSimpleVmbusDevice;
+use vmbus_channel::simple::SimpleVmbusDevice;
+use vmbus_ring::OutgoingPacketType;
+use vmbus_ring::PacketSize;
+use vmbus_ring::RingMem;
+use vmcore::save_restore::SavedStateNotSupported;
+use vmcore::vm_task::VmTaskDriverSource;
+use zerocopy::FromBytes;
+use zerocopy::IntoBytes;
+
+/// The most pages to ask the guest for in one balloon request (1GB), so that
+/// target changes and hot-add requests are handled in between.
+const MAX_BALLOON_REQUEST_PAGES: u64 = 0x40000;
+
+/// The most ranges to return to the guest in one unballoon request, keeping
+/// the message well within [`protocol::MAX_MESSAGE_SIZE`].
+const MAX_UNBALLOON_RANGES: usize = 256;
+
+/// A Hyper-V dynamic memory device.
+#[derive(InspectMut)]
+pub struct DynamicMemoryDevice {
+    #[inspect(flatten)]
+    state: Arc<DmState>,
+    #[inspect(skip)]
+    _control_task: Task<()>,
+    #[inspect(skip)]
+    target_changed: mesh::Receiver<()>,
+    #[inspect(skip)]
+    hot_add_requests: mesh::Receiver<FailableRpc<u64, u64>>,
+    #[inspect(skip)]
+    discard_ram: Option<Arc<dyn DiscardRam>>,
+    #[inspect(skip)]
+    hot_add_ram: Option<Arc<dyn HotAddRam>>,
+    /// The RAM added to the VM so far.
+    #[inspect(with = "|x| x.len()")]
+    hot_added_ranges: Vec<PageRange>,
+}
+
+/// State shared between the device, its channel, and the host control task.
+#[derive(Inspect)]
+struct DmState {
+    /// The balloon target set by the host, in 4KB pages.
+    target_pages: AtomicU64,
+    /// Whether the guest has negotiated the protocol on the open channel.
+    connected: AtomicBool,
+    /// The number of pages the guest has given up to the balloon.
+    ballooned_pages: AtomicU64,
+    /// The number of hot-added pages the guest has accepted.
+    hot_added_pages: AtomicU64,
+    /// The number of ballooned pages whose backing was released.
+    discarded_pages: AtomicU64,
+    /// The number of ballooned pages whose backing could not be released.
+    discard_failures: AtomicU64,
+    #[inspect(skip)]
+    guest_status: Mutex<Option<GuestMemoryStatus>>,
+}
+
+impl DmState {
+    /// Resets the state for a new connection from the guest.
+    fn reset_connection(&self) {
+        self.connected.store(false, Ordering::Relaxed);
+        self.ballooned_pages.store(0, Ordering::Relaxed);
+        self.hot_added_pages.store(0, Ordering::Relaxed);
+        *self.guest_status.lock() = None;
+    }
+}
+
+/// An error adding memory to the guest.
+#[derive(Debug, Error)]
+enum HotAddError {
+    #[error("the guest has not connected")]
+    NotConnected,
+    #[error("the guest does not support memory hot-add")]
+    NotSupported,
+    #[error("memory hot-add is not configured for this VM")]
+    NotConfigured,
+    #[error("invalid hot-add size {0:#x}")]
+    InvalidSize(u64),
+    #[error("failed to add RAM to the VM")]
+    Ram(#[source] std::io::Error),
+    #[error("the guest did not add the memory")]
+    Rejected,
+}
+
+impl DynamicMemoryDevice {
+    /// Creates a new dynamic memory device, controlled by the host via
+    /// `recv`.
+    ///
+    /// If `discard_ram` is provided, it is used to release the backing of
+    /// ballooned pages. If `hot_add_ram` is provided, the host can add memory
+    /// to the guest.
+    pub fn new(
+        driver_source: &VmTaskDriverSource,
+        recv: mesh::Receiver<DynamicMemoryRpc>,
+        discard_ram: Option<Arc<dyn DiscardRam>>,
+        hot_add_ram: Option<Arc<dyn HotAddRam>>,
+    ) -> Self {
+        let state = Arc::new(DmState {
+            target_pages: 0.into(),
+            connected: false.into(),
+            ballooned_pages: 0.into(),
+            hot_added_pages: 0.into(),
+            discarded_pages: 0.into(),
+            discard_failures: 0.into(),
+            guest_status: Mutex::new(None),
+        });
+        let (target_send, target_recv) = mesh::channel();
+        let (hot_add_send, hot_add_recv) = mesh::channel();
+        let control_task = driver_source.simple().spawn(
+            "hyperv-dm-control",
+            run_control(state.clone(), recv, target_send, hot_add_send),
+        );
+        Self {
+            state,
+            _control_task: control_task,
+            target_changed: target_recv,
+            hot_add_requests: hot_add_recv,
+            discard_ram,
+            hot_add_ram,
+            hot_added_ranges: Vec::new(),
+        }
+    }
+
+    /// Releases the backing of a range the guest has given up.
+    fn discard(&self, range: PageRange) {
+        let Some(discard_ram) = &self.discard_ram else {
+            return;
+        };
+        let count = range.page_count();
+        let gpa = range.start_page() * PAGE_SIZE;
+        match discard_ram.discard_ram(gpa, count * PAGE_SIZE) {
+            Ok(()) => {
+                self.state
+                    .discarded_pages
+                    .fetch_add(count, Ordering::Relaxed);
+            }
+            Err(err) => {
+                self.state
+                    .discard_failures
+                    .fetch_add(count, Ordering::Relaxed);
+                tracelimit::warn_ratelimited!(
+                    error = &err as &dyn std::error::Error,
+                    gpa,
+                    count,
+                    "failed to release ballooned pages"
+                );
+            }
+        }
+    }
+
+    /// Adds at least `len` bytes of RAM to the VM, rounded up to the
+    /// guest's hot-add alignment, and returns the new range.
+    fn add_ram(
+        &mut self,
+        capabilities: protocol::Capabilities,
+        len: u64,
+    ) -> Result<PageRange, HotAddError> {
+        if !capabilities.hot_add() {
+            return Err(HotAddError::NotSupported);
+        }
+        let hot_add_ram = self
+            .hot_add_ram
+            .as_ref()
+            .ok_or(HotAddError::NotConfigured)?;
+        // The guest adds memory in blocks of its reported alignment. The
+        // hot-add range starts aligned, so aligning each size keeps every
+        // added range aligned.
+        let alignment = 1 << (20 + capabilities.hot_add_alignment());
+        let aligned_len = len
+            .checked_next_multiple_of(alignment)
+            .filter(|&len| len != 0 && len / PAGE_SIZE <= protocol::MAX_RANGE_PAGES)
+            .ok_or(HotAddError::InvalidSize(len))?;
+        let gpa = hot_add_ram
+            .hot_add_ram(aligned_len)
+            .map_err(HotAddError::Ram)?;
+        let range = PageRange::new()
+            .with_start_page(gpa / PAGE_SIZE)
+            .with_page_count(aligned_len / PAGE_SIZE);
+        self.hot_added_ranges.push(range);
+        Ok(range)
+    }
+}
+
+/// Handles host requests.
+async fn run_control(
+    state: Arc<DmState>,
+    mut recv: mesh::Receiver<DynamicMemoryRpc>,
+    target_changed: mesh::Sender<()>,
+    hot_add: mesh::Sender<FailableRpc<u64, u64>>,
+) {
+    while let Some(rpc) = recv.next().await {
+        match rpc {
+            DynamicMemoryRpc::SetTarget(rpc) => rpc.handle_sync(|target_pages| {
+                tracing::info!(target_pages, "setting dynamic memory balloon target");
+                state.target_pages.store(target_pages, Ordering::Relaxed);
+                target_changed.send(());
+            }),
+            DynamicMemoryRpc::HotAdd(rpc) => {
+                if state.connected.load(Ordering::Relaxed) {
+                    hot_add.send(rpc);
+                } else {
+                    rpc.fail(HotAddError::NotConnected);
+                }
+            }
+            DynamicMemoryRpc::Status(rpc) => rpc.handle_sync(|()| DynamicMemoryStatus {
+                connected: state.connected.load(Ordering::Relaxed),
+                target_pages: state.target_pages.load(Ordering::Relaxed),
+                ballooned_pages: state.ballooned_pages.load(Ordering::Relaxed),
+                hot_added_pages: state.hot_added_pages.load(Ordering::Relaxed),
+                guest: *state.guest_status.lock(),
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl SimpleVmbusDevice for DynamicMemoryDevice {
+    type SavedState = SavedStateNotSupported;
+    type Runner = DmChannel;
+
+    fn offer(&self) -> OfferParams {
+        OfferParams {
+            interface_name: "dynamic_memory".to_owned(),
+            instance_id: protocol::INSTANCE_ID,
+            interface_id: protocol::INTERFACE_ID,
+            channel_type: ChannelType::Device {
+                pipe_packets: false,
+            },
+            ..Default::default()
+        }
+    }
+
+    fn inspect(&mut self, req: inspect::Request<'_>, runner: Option<&mut Self::Runner>) {
+        req.respond().merge(self).merge(runner);
+    }
+
+    fn open(
+        &mut self,
+        channel: RawAsyncChannel<GpadlRingMem>,
+        _guest_memory: GuestMemory,
+    ) -> Result<Self::Runner, ChannelOpenError> {
+        // The guest starts over with all of its memory each time it connects.
+        self.state.reset_connection();
+        Ok(DmChannel::new(Queue::new(channel)?))
+    }
+
+    async fn run(
+        &mut self,
+        stop: &mut StopTask<'_>,
+        runner: &mut Self::Runner,
+    ) -> Result<(), Cancelled> {
+        stop.until_stopped(runner.process(self)).await
+    }
+
+    async fn close(&mut self) {
+        self.state.connected.store(false, Ordering::Relaxed);
+    }
+
+    fn supports_save_restore(
+        &mut self,
+    ) -> Option<
+        &mut dyn SaveRestoreSimpleVmbusDevice<SavedState = Self::SavedState, Runner = Self::Runner>,
+    > {
+        None
+    }
+}
+
+#[doc(hidden)] // used as an associated type in a trait but not part of the public API
+#[derive(InspectMut)]
+pub struct DmChannel<T: RingMem = GpadlRingMem> {
+    queue: Queue<T>,
+    state: ChannelState,
+    #[inspect(skip)]
+    next_transaction_id: u32,
+}
+
+#[derive(Inspect)]
+#[inspect(external_tag)]
+enum ChannelState {
+    Version,
+    Capabilities {
+        #[inspect(debug)]
+        version: protocol::Version,
+    },
+    Ready(#[inspect(rename = "state")] ReadyState),
+    Failed,
+}
+
+#[derive(Inspect)]
+struct ReadyState {
+    #[inspect(debug)]
+    version: protocol::Version,
+    #[inspect(debug)]
+    capabilities: protocol::Capabilities,
+    /// The ranges the guest has given up, in the order they were ballooned.
+    #[inspect(with = "|x| x.len()")]
+    ballooned: Vec<PageRange>,
+    ballooned_pages: u64,
+    /// Set when the guest gave up fewer pages than requested, so that the
+    /// device does not ask again until the target changes.
+    balloon_stalled: bool,
+    /// The outstanding request to the guest.
+    #[inspect(with = "Option::is_some")]
+    operation: Option<Operation>,
+    /// Hot-added RAM waiting to be offered to the guest.
+    #[inspect(with = "|x| x.len()")]
+    hot_add_queue: VecDeque<PendingHotAdd>,
+}
+
+/// A request to the guest that is waiting for a response.
+enum Operation {
+    Balloon { requested: u64, received: u64 },
+    Unballoon { pages: u64 },
+    HotAdd(PendingHotAdd),
+}
+
+struct PendingHotAdd {
+    range: PageRange,
+    /// The host request to complete, if this is not RAM that is being
+    /// offered again to a reconnected guest.
+    rpc: Option<Rpc<(), Result<u64, mesh::error::RemoteError>>>,
+}
+
+/// The next request to send to the guest.
+enum Request {
+    HotAdd,
+    Balloon(u64),
+    Unballoon(u64),
+}
+
+#[derive(Debug)]
+enum Message {
+    VersionRequest(protocol::VersionRequest),
+    CapabilitiesReport(protocol::CapabilitiesReport),
+    StatusReport(protocol::StatusReport),
+    BalloonResponse {
+        more_pages: bool,
+        ranges: Vec<PageRange>,
+    },
+    UnballoonResponse,
+    HotAddResponse(protocol::HotAddResponse),
+    Info,
+}
+
+enum Event {
+    Message(Message),
+    TargetChanged,
+    HotAdd(FailableRpc<u64, u64>),
+}
+
+#[derive(Debug, Error)]
+enum Error {
+    #[error("queue error")]
+    Queue(#[source] queue::Error),
+    #[error("memory access error")]
+    Access(#[source] guestmem::AccessError),
+    #[error("unexpectedly out of ring space")]
+    OutOfSpace,
+    #[error("invalid packet type")]
+    InvalidPacketType,
+    #[error("message is too large")]
+    MessageTooLarge,
+    #[error("message is too short")]
+    TooShort,
+    #[error("invalid message size {0}")]
+    InvalidSize(u16),
+    #[error("invalid message type: {0:?}")]
+    InvalidMessageType(protocol::MessageType),
+    #[error("unexpected message")]
+    UnexpectedMessage,
+    #[error("guest ballooned more pages than requested")]
+    TooManyPages,
+    #[error("no supported protocol version")]
+    NoSupportedVersion,
+    #[error("guest rejected the capabilities response")]
+    CapabilitiesRejected,
+}
+
+impl<T: RingMem> DmChannel<T> {
+    fn new(queue: Queue<T>) -> Self {
+        Self {
+            queue,
+            state: ChannelState::Version,
+            next_transaction_id: 1,
+        }
+    }
+
+    async fn process(&mut self, dev: &mut DynamicMemoryDevice) -> ! {
+        loop {
+            if let Err(err) = self.process_state_machine(dev).await {
+                tracing::error!(
+                    error = &err as &dyn std::error::Error,
+                    "dynamic memory error"
+                );
+                dev.state.connected.store(false, Ordering::Relaxed);
+                self.state = ChannelState::Failed;
+            }
+        }
+    }
+
+    async fn process_state_machine(&mut self, dev: &mut DynamicMemoryDevice) -> Result<(), Error> {
+        match &mut self.state {
+            ChannelState::Version => {
+                // Make sure there is space for the response before consuming
+                // the request.
+                wait_ready(&mut self.queue).await?;
+                let Message::VersionRequest(request) = read_message(&mut self.queue).await? else {
+                    return Err(Error::UnexpectedMessage);
+                };
+                let accepted = matches!(
+                    request.version,
+                    protocol::Version::WIN8 | protocol::Version::WIN10
+                );
+                try_send(
+                    &mut self.queue,
+                    &[protocol::VersionResponse {
+                        header: header::<protocol::VersionResponse>(
+                            protocol::MessageType::VERSION_RESPONSE,
+                            request.header.transaction_id,
+                        ),
+                        flags: protocol::ResponseFlags::new().with_is_accepted(accepted),
+                    }
+                    .as_bytes()],
+                )?;
+                if accepted {
+                    self.state = ChannelState::Capabilities {
+                        version: request.version,
+                    };
+                } else if request.flags.is_last_attempt() {
+                    return Err(Error::NoSupportedVersion);
+                } else {
+                    tracing::debug!(version = ?request.version, "unsupported version");
+                }
+            }
+            ChannelState::Capabilities { version } => {
+                let version = *version;
+                wait_ready(&mut self.queue).await?;
+                let Message::CapabilitiesReport(report) = read_message(&mut self.queue).await?
+                else {
+                    return Err(Error::UnexpectedMessage);
+                };
+                // Mark the guest connected before it sees the response, so that
+                // it is not reported disconnected once it has finished
+                // negotiating.
+                dev.state.connected.store(true, Ordering::Relaxed);
+                try_send(
+                    &mut self.queue,
+                    &[protocol::CapabilitiesResponse {
+                        header: header::<protocol::CapabilitiesResponse>(
+                            protocol::MessageType::CAPABILITIES_RESPONSE,
+                            report.header.transaction_id,
+                        ),
+                        flags: protocol::CapabilitiesResponseFlags::new().with_is_accepted(true),
+                    }
+                    .as_bytes()],
+                )?;
+                let capabilities = report.capabilities;
+                tracelimit::info_ratelimited!(
+                    ?version,
+                    ?capabilities,
+                    "dynamic memory guest connected"
+                );
+                // Offer any RAM that was added before the guest connected,
+                // such as before it rebooted.
+                let hot_add_queue = if capabilities.hot_add() {
+                    dev.hot_added_ranges
+                        .iter()
+                        .map(|&range| PendingHotAdd { range, rpc: None })
+                        .collect()
+                } else {
+                    VecDeque::new()
+                };
+                self.state = ChannelState::Ready(ReadyState {
+                    version,
+                    capabilities,
+                    ballooned: Vec::new(),
+                    ballooned_pages: 0,
+                    balloon_stalled: false,
+                    operation: None,
+                    hot_add_queue,
+                });
+            }
+            ChannelState::Ready(ready) => {
+                if ready.operation.is_none() {
+                    let target_pages = dev.state.target_pages.load(Ordering::Relaxed);
+                    if let Some(request) = ready.next_request(target_pages) {
+                        // Wait for space before starting the operation, so
+                        // that it is not lost if the device is stopped.
+                        wait_ready(&mut self.queue).await?;
+                        let transaction_id = self.next_transaction_id;
+                        self.next_transaction_id = self.next_transaction_id.wrapping_add(1);
+                        return ready.send_request(&mut self.queue, request, transaction_id);
+                    }
+                }
+                let queue = &mut self.queue;
+                let target_changed = &mut dev.target_changed;
+                let hot_add_requests = &mut dev.hot_add_requests;
+                let event = (
+                    async { read_message(queue).await.map(Event::Message) },
+                    async {
+                        match target_changed.next().await {
+                            Some(()) => Ok(Event::TargetChanged),
+                            None => pending().await,
+                        }
+                    },
+                    async {
+                        match hot_add_requests.next().await {
+                            Some(rpc) => Ok(Event::HotAdd(rpc)),
+                            None => pending().await,
+                        }
+                    },
+                )
+                    .race()
+                    .await?;
+                match event {
+                    Event::Message(message) => ready.handle_message(message, dev)?,
+                    Event::TargetChanged => ready.balloon_stalled = false,
+                    Event::HotAdd(rpc) => {
+                        let (len, rpc) = rpc.split();
+                        match dev.add_ram(ready.capabilities, len) {
+                            Ok(range) => ready.hot_add_queue.push_back(PendingHotAdd {
+                                range,
+                                rpc: Some(rpc),
+                            }),
+                            Err(err) => rpc.fail(err),
+                        }
+                    }
+                }
+            }
+            ChannelState::Failed => pending().await,
+        }
+        Ok(())
+    }
+}
+
+impl ReadyState {
+    /// Returns the next request to send to the guest, if any.
+    fn next_request(&self, target_pages: u64) -> Option<Request> {
+        if !self.hot_add_queue.is_empty() {
+            Some(Request::HotAdd)
+        } else if target_pages > self.ballooned_pages
+            && self.capabilities.balloon()
+            && !self.balloon_stalled
+        {
+            Some(Request::Balloon(
+                (target_pages - self.ballooned_pages).min(MAX_BALLOON_REQUEST_PAGES),
+            ))
+        } else if target_pages < self.ballooned_pages {
+            Some(Request::Unballoon(self.ballooned_pages - target_pages))
+        } else {
+            None
+        }
+    }
+
+    /// Sends `request` to the guest. The caller must have waited for ring
+    /// space.
+    fn send_request<T: RingMem>(
+        &mut self,
+        queue: &mut Queue<T>,
+        request: Request,
+        transaction_id: u32,
+    ) -> Result<(), Error> {
+        let operation = match request {
+            Request::HotAdd => {
+                let hot_add = self.hot_add_queue.pop_front().unwrap();
+                try_send(
+                    queue,
+                    &[protocol::HotAddRequest {
+                        header: header::<protocol::HotAddRequest>(
+                            protocol::MessageType::MEM_HOT_ADD_REQUEST,
+                            transaction_id,
+                        ),
+                        range: hot_add.range,
+                    }
+                    .as_bytes()],
+                )?;
+                Operation::HotAdd(hot_add)
+            }
+            Request::Balloon(pages) => {
+                try_send(
+                    queue,
+                    &[protocol::BalloonRequest {
+                        header: header::<protocol::BalloonRequest>(
+                            protocol::MessageType::BALLOON_REQUEST,
+                            transaction_id,
+                        ),
+                        page_count: pages as u32,
+                        reserved: 0,
+                    }
+                    .as_bytes()],
+                )?;
+                Operation::Balloon {
+                    requested: pages,
+                    received: 0,
+                }
+            }
+            Request::Unballoon(pages) => {
+                let ranges = self.take_ballooned(pages);
+                let pages = ranges.iter().map(|r| r.page_count()).sum();
+                let mut header = header::<protocol::UnballoonRequest>(
+                    protocol::MessageType::UNBALLOON_REQUEST,
+                    transaction_id,
+                );
+                header.size += (ranges.len() * size_of::<PageRange>()) as u16;
+                try_send(
+                    queue,
+                    &[
+                        protocol::UnballoonRequest {
+                            header,
+                            flags: protocol::MorePagesFlags::new(),
+                            range_count: ranges.len() as u32,
+                        }
+                        .as_bytes(),
+                        ranges.as_bytes(),
+                    ],
+                )?;
+                Operation::Unballoon { pages }
+            }
+        };
+        self.operation = Some(operation);
+        Ok(())
+    }
+
+    /// Removes up to `pages` pages of the most recently ballooned ranges, to
+    /// return to the guest.
+    fn take_ballooned(&mut self, mut pages: u64) -> Vec<PageRange> {
+        let mut ranges = Vec::new();
+        while pages > 0 && ranges.len() < MAX_UNBALLOON_RANGES {
+            let Some(last) = self.ballooned.last_mut() else {
+                break;
+            };
+            let count = last.page_count();
+            if count <= pages {
+                ranges.push(self.ballooned.pop().unwrap());
+                pages -= count;
+            } else {
+                // Split the range, returning its tail.
+                last.set_page_count(count - pages);
+                ranges.push(
+                    PageRange::new()
+                        .with_start_page(last.start_page() + count - pages)
+                        .with_page_count(pages),
+                );
+                pages = 0;
+            }
+        }
+        ranges
+    }
+
+    fn handle_message(
+        &mut self,
+        message: Message,
+        dev: &mut DynamicMemoryDevice,
+    ) -> Result<(), Error> {
+        match message {
+            Message::StatusReport(report) => {
+                *dev.state.guest_status.lock() = Some(GuestMemoryStatus {
+                    available_pages: report.available_pages,
+                    committed_pages: report.committed_pages,
+                    page_file_size: report.page_file_size,
+                    zero_free_pages: report.zero_free_pages,
+                    page_file_writes: report.page_file_writes,
+                    io_diff: report.io_diff,
+                });
+            }
+            Message::BalloonResponse { more_pages, ranges } => {
+                let Some(Operation::Balloon {
+                    requested,
+                    received,
+                }) = &mut self.operation
+                else {
+                    return Err(Error::UnexpectedMessage);
+                };
+                for range in ranges {
+                    let count = range.page_count();
+                    if count > *requested - *received {
+                        return Err(Error::TooManyPages);
+                    }
+                    if count == 0 {
+                        continue;
+                    }
+                    *received += count;
+                    self.ballooned_pages += count;
+                    dev.discard(range);
+                    self.ballooned.push(range);
+                }
+                dev.state
+                    .ballooned_pages
+                    .store(self.ballooned_pages, Ordering::Relaxed);
+                if !more_pages {
+                    if *received < *requested {
+                        tracelimit::info_ratelimited!(
+                            requested = *requested,
+                            received = *received,
+                            "guest ballooned fewer pages than requested"
+                        );
+                        self.balloon_stalled = true;
+                    }
+                    self.operation = None;
+                }
+            }
+            Message::UnballoonResponse => {
+                let Some(Operation::Unballoon { pages }) = self.operation else {
+                    return Err(Error::UnexpectedMessage);
+                };
+                self.ballooned_pages -= pages;
+                dev.state
+                    .ballooned_pages
+                    .store(self.ballooned_pages, Ordering::Relaxed);
+                self.operation = None;
+            }
+            Message::HotAddResponse(response) => {
+                let Some(Operation::HotAdd(hot_add)) = self
+                    .operation
+                    .take_if(|op| matches!(op, Operation::HotAdd(_)))
+                else {
+                    return Err(Error::UnexpectedMessage);
+                };
+                let pages = (response.page_count as u64).min(hot_add.range.page_count());
+                let result = if response.result == protocol::HOT_ADD_SUCCESS && pages != 0 {
+                    dev.state
+                        .hot_added_pages
+                        .fetch_add(pages, Ordering::Relaxed);
+                    Ok(pages * PAGE_SIZE)
+                } else {
+                    tracelimit::warn_ratelimited!(
+                        start_page = hot_add.range.start_page(),
+                        page_count = hot_add.range.page_count(),
+                        result = response.result,
+                        "guest did not add memory"
+                    );
+                    Err(HotAddError::Rejected)
+                };
+                if let Some(rpc) = hot_add.rpc {
+                    match result {
+                        Ok(len) => rpc.complete(Ok(len)),
+                        Err(err) => rpc.fail(err),
+                    }
+                }
+            }
+            Message::Info => {}
+            Message::VersionRequest(_) | Message::CapabilitiesReport(_) => {
+                return Err(Error::UnexpectedMessage);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns a header for a message of type `T` with no trailing data.
+fn header<T>(message_type: protocol::MessageType, transaction_id: u32) -> protocol::Header {
+    protocol::Header {
+        message_type,
+        size: size_of::<T>() as u16,
+        transaction_id,
+    }
+}
+
+async fn wait_ready<T: RingMem>(queue: &mut Queue<T>) -> Result<(), Error> {
+    let (_, mut write) = queue.split();
+    let len = PacketSize::in_band(protocol::MAX_MESSAGE_SIZE).min(write.capacity());
+    write.wait_ready(len).await.map_err(Error::Queue)
+}
+
+fn try_send<T: RingMem>(queue: &mut Queue<T>, payload: &[&[u8]]) -> Result<(), Error> {
+    queue
+        .split()
+        .1
+        .try_write(&OutgoingPacket {
+            transaction_id: 0,
+            packet_type: OutgoingPacketType::InBandNoCompletion,
+            payload,
+        })
+        .map_err(|err| match err {
+            queue::TryWriteError::Full(_) => Error::OutOfSpace,
+            queue::TryWriteError::Queue(err) => Error::Queue(err),
+        })
+}
+
+async fn read_message<T: RingMem>(queue: &mut Queue<T>) -> Result<Message, Error> {
+    let (mut read, _) = queue.split();
+    let packet = read.read().await.map_err(Error::Queue)?;
+    let IncomingPacket::Data(data) = &*packet else {
+        return Err(Error::InvalidPacketType);
+    };
+    let mut reader = data.reader();
+    if reader.len() > protocol::MAX_MESSAGE_SIZE {
+        return Err(Error::MessageTooLarge);
+    }
+    let buf = reader.read_all().map_err(Error::Access)?;
+    let message = parse_message(&buf)?;
+    tracing::trace!(?message, "message");
+    Ok(message)
+}
+
+fn read<T: FromBytes>(buf: &[u8]) -> Result<T, Error> {
+    Ok(T::read_from_prefix(buf).map_err(|_| Error::TooShort)?.0) // TODO: zerocopy: map_err (https://github.com/microsoft/openvmm/issues/759)
+}
+
+fn parse_message(buf: &[u8]) -> Result<Message, Error> {
+    let header = read::<protocol::Header>(buf)?;
+    // The packet may be padded beyond the message.
+    let buf = buf
+        .get(..header.size as usize)
+        .filter(|buf| buf.len() >= size_of::<protocol::Header>())
+        .ok_or(Error::InvalidSize(header.size))?;
+    let message = match header.message_type {
+        protocol::MessageType::VERSION_REQUEST => Message::VersionRequest(read(buf)?),
+        protocol::MessageType::CAPABILITIES_REPORT => Message::CapabilitiesReport(read(buf)?),
+        protocol::MessageType::STATUS_REPORT => Message::StatusReport(read(buf)?),
+        protocol::MessageType::BALLOON_RESPONSE => {
+            let response = read::<protocol::BalloonResponse>(buf)?;
+            let ranges = buf[size_of::<protocol::BalloonResponse>()..]
+                .chunks_exact(size_of::<PageRange>())
+                .map(|range| PageRange::read_from_bytes(range).unwrap())
+                .take(response.range_count as usize)
+                .collect::<Vec<_>>();
+            if ranges.len() != response.range_count as usize {
+                return Err(Error::TooShort);
+            }
+            Message::BalloonResponse {
+                more_pages: response.flags.more_pages(),
+                ranges,
+            }
+        }
+        protocol::MessageType::UNBALLOON_RESPONSE => Message::UnballoonResponse,
+        protocol::MessageType::MEM_HOT_ADD_RESPONSE => Message::HotAddResponse(read(buf)?),
+        protocol::MessageType::INFO_MESSAGE => Message::Info,
+        ty => return Err(Error::InvalidMessageType(ty)),
+    };
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::Either;
+    use mesh::rpc::RpcSend;
+    use pal_async::DefaultDriver;
+    use pal_async::async_test;
+    use std::future::Future;
+    use std::pin::pin;
+    use vmbus_async::queue::connected_queues;
+    use vmbus_ring::FlatRingMem;
+    use vmcore::vm_task::SingleDriverBackend;
+
+    const HOT_ADD_BASE: u64 = 0x1_0000_0000;
+
+    #[derive(Default)]
+    struct TestRam {
+        discarded: Mutex<Vec<(u64, u64)>>,
+        added: Mutex<Vec<(u64, u64)>>,
+    }
+
+    impl DiscardRam for TestRam {
+        fn discard_ram(&self, gpa: u64, len: u64) -> std::io::Result<()> {
+            self.discarded.lock().push((gpa, len));
+            Ok(())
+        }
+    }
+
+    impl HotAddRam for TestRam {
+        fn hot_add_ram(&self, len: u64) -> std::io::Result<u64> {
+            let mut added = self.added.lock();
+            let gpa = added.last().map_or(HOT_ADD_BASE, |&(gpa, len)| gpa + len);
+            added.push((gpa, len));
+            Ok(gpa)
+        }
+    }
+
+    fn new_device(
+        driver: &DefaultDriver,
+        ram: &Arc<TestRam>,
+    ) -> (DynamicMemoryDevice, mesh::Sender<DynamicMemoryRpc>) {
+        let driver_source = VmTaskDriverSource::new(SingleDriverBackend::new(driver.clone()));
+        let (send, recv) = mesh::channel();
+        let dev =
+            DynamicMemoryDevice::new(&driver_source, recv, Some(ram.clone()), Some(ram.clone()));
+        (dev, send)
+    }
+
+    fn connect() -> (DmChannel<FlatRingMem>, TestGuest) {
+        let (host, guest) = connected_queues(16384);
+        (DmChannel::new(host), TestGuest(guest))
+    }
+
+    /// Runs the device on `channel` until `guest` completes.
+    async fn run_with_guest<F: Future>(
+        dev: &mut DynamicMemoryDevice,
+        channel: &mut DmChannel<FlatRingMem>,
+        guest: F,
+    ) -> F::Output {
+        match futures::future::select(pin!(channel.process(dev)), pin!(guest)).await {
+            Either::Left((never, _)) => never,
+            Either::Right((output, _)) => output,
+        }
+    }
+
+    fn range(start_page: u64, page_count: u64) -> PageRange {
+        PageRange::new()
+            .with_start_page(start_page)
+            .with_page_count(page_count)
+    }
+
+    /// The guest side of the channel.
+    struct TestGuest(Queue<FlatRingMem>);
+
+    impl TestGuest {
+        async fn send(&mut self, payload: &[&[u8]]) {
+            self.0
+                .split()
+                .1
+                .write(OutgoingPacket {
+                    transaction_id: 0,
+                    packet_type: OutgoingPacketType::InBandNoCompletion,
+                    payload,
+                })
+                .await
+                .unwrap();
+        }
+
+        /// Receives a message of type `T`, and returns it with the data that
+        /// follows it.
+        async fn recv<T: FromBytes>(
+            &mut self,
+            message_type: protocol::MessageType,
+        ) -> (T, Vec<u8>) {
+            let (mut read, _) = self.0.split();
+            let packet = read.read().await.unwrap();
+            let IncomingPacket::Data(data) = &*packet else {
+                panic!("unexpected completion packet");
+            };
+            let buf = data.reader().read_all().unwrap();
+            let header = protocol::Header::read_from_prefix(&buf).unwrap().0;
+            assert_eq!(header.message_type, message_type);
+            let buf = &buf[..header.size as usize];
+            let (message, rest) = T::read_from_prefix(buf).unwrap();
+            (message, rest.to_vec())
+        }
+
+        async fn negotiate(&mut self, capabilities: protocol::Capabilities) {
+            self.send(&[protocol::VersionRequest {
+                header: header::<protocol::VersionRequest>(
+                    protocol::MessageType::VERSION_REQUEST,
+                    1,
+                ),
+                version: protocol::Version::WIN10,
+                flags: protocol::VersionRequestFlags::new().with_is_last_attempt(true),
+            }
+            .as_bytes()])
+                .await;
+            let (response, _) = self
+                .recv::<protocol::VersionResponse>(protocol::MessageType::VERSION_RESPONSE)
+                .await;
+            assert!(response.flags.is_accepted());
+            self.send(&[protocol::CapabilitiesReport {
+                header: header::<protocol::CapabilitiesReport>(
+                    protocol::MessageType::CAPABILITIES_REPORT,
+                    2,
+                ),
+                capabilities,
+                min_page_count: 0x1000,
+                max_page_number: u64::MAX,
+            }
+            .as_bytes()])
+                .await;
+            let (response, _) = self
+                .recv::<protocol::CapabilitiesResponse>(
+                    protocol::MessageType::CAPABILITIES_RESPONSE,
+                )
+                .await;
+            assert!(response.flags.is_accepted());
+        }
+
+        async fn send_balloon_response(&mut self, more_pages: bool, ranges: &[PageRange]) {
+            let mut header =
+                header::<protocol::BalloonResponse>(protocol::MessageType::BALLOON_RESPONSE, 0);
+            header.size += size_of_val(ranges) as u16;
+            self.send(&[
+                protocol::BalloonResponse {
+                    header,
+                    reserved: 0,
+                    flags: protocol::MorePagesFlags::new().with_more_pages(more_pages),
+                    range_count: ranges.len() as u32,
+                }
+                .as_bytes(),
+                ranges.as_bytes(),
+            ])
+            .await;
+        }
+
+        async fn recv_unballoon_request(&mut self) -> Vec<PageRange> {
+            let (request, rest) = self
+                .recv::<protocol::UnballoonRequest>(protocol::MessageType::UNBALLOON_REQUEST)
+                .await;
+            let ranges = <[PageRange]>::ref_from_bytes(&rest).unwrap();
+            assert_eq!(ranges.len(), request.range_count as usize);
+            ranges.to_vec()
+        }
+    }
+
+    #[async_test]
+    async fn test_negotiate(driver: DefaultDriver) {
+        let ram = Arc::new(TestRam::default());
+        let (mut dev, send) = new_device(&driver, &ram);
+        let (mut channel, mut guest) = connect();
+        run_with_guest(&mut dev, &mut channel, async {
+            // Older versions are rejected.
+            guest
+                .send(&[protocol::VersionRequest {
+                    header: header::<protocol::VersionRequest>(
+                        protocol::MessageType::VERSION_REQUEST,
+                        7,
+                    ),
+                    version: protocol::Version::WIN7,
+                    flags: protocol::VersionRequestFlags::new(),
+                }
+                .as_bytes()])
+                .await;
+            let (response, _) = guest
+                .recv::<protocol::VersionResponse>(protocol::MessageType::VERSION_RESPONSE)
+                .await;
+            assert_eq!(response.header.transaction_id, 7);
+            assert!(!response.flags.is_accepted());
+            let status = send.call(DynamicMemoryRpc::Status, ()).await.unwrap();
+            assert!(!status.connected);
+
+            guest
+                .negotiate(protocol::Capabilities::new().with_balloon(true))
+                .await;
+            let status = send.call(DynamicMemoryRpc::Status, ()).await.unwrap();
+            assert!(status.connected);
+            assert!(status.guest.is_none());
+        })
+        .await;
+    }
+
+    #[async_test]
+    async fn test_balloon(driver: DefaultDriver) {
+        let ram = Arc::new(TestRam::default());
+        let (mut dev, send) = new_device(&driver, &ram);
+        let (mut channel, mut guest) = connect();
+        run_with_guest(&mut dev, &mut channel, async {
+            guest
+                .negotiate(protocol::Capabilities::new().with_balloon(true))
+                .await;
+            send.call(DynamicMemoryRpc::SetTarget, 300).await.unwrap();
+            let (request, _) = guest
+                .recv::<protocol::BalloonRequest>(protocol::MessageType::BALLOON_REQUEST)
+                .await;
+            assert_eq!(request.page_count, 300);
+
+            guest
+                .send(&[protocol::StatusReport {
+                    header: header::<protocol::StatusReport>(
+                        protocol::MessageType::STATUS_REPORT,
+                        0,
+                    ),
+                    available_pages: 1000,
+                    committed_pages: 500,
+                    page_file_size: 0,
+                    zero_free_pages: 10,
+                    page_file_writes: 0,
+                    io_diff: 0,
+                }
+                .as_bytes()])
+                .await;
+
+            // The guest gives up fewer pages than requested, so the device
+            // waits for a new target.
+            guest
+                .send_balloon_response(true, &[range(0x100, 100)])
+                .await;
+            guest
+                .send_balloon_response(false, &[range(0x300, 150)])
+                .await;
+
+            // Lowering the target returns the most recently ballooned pages,
+            // splitting a range if needed.
+            send.call(DynamicMemoryRpc::SetTarget, 50).await.unwrap();
+            assert_eq!(
+                guest.recv_unballoon_request().await,
+                [range(0x300, 150), range(0x132, 50)]
+            );
+            assert_eq!(
+                *ram.discarded.lock(),
+                [(0x100000, 100 * PAGE_SIZE), (0x300000, 150 * PAGE_SIZE)]
+            );
+            let status = send.call(DynamicMemoryRpc::Status, ()).await.unwrap();
+            assert_eq!(status.target_pages, 50);
+            assert_eq!(status.ballooned_pages, 250);
+            assert_eq!(status.guest.unwrap().available_pages, 1000);
+
+            guest
+                .send(&[protocol::Header {
+                    message_type: protocol::MessageType::UNBALLOON_RESPONSE,
+                    size: size_of::<protocol::Header>() as u16,
+                    transaction_id: 0,
+                }
+                .as_bytes()])
+                .await;
+            send.call(DynamicMemoryRpc::SetTarget, 0).await.unwrap();
+            assert_eq!(guest.recv_unballoon_request().await, [range(0x100, 50)]);
+        })
+        .await;
+    }
+
+    #[async_test]
+    async fn test_hot_add(driver: DefaultDriver) {
+        let ram = Arc::new(TestRam::default());
+        let (mut dev, send) = new_device(&driver, &ram);
+        // 128MB alignment.
+        let capabilities = protocol::Capabilities::new()
+            .with_hot_add(true)
+            .with_hot_add_alignment(7);
+        let expected = range(HOT_ADD_BASE / PAGE_SIZE, (128 << 20) / PAGE_SIZE);
+
+        let (mut channel, mut guest) = connect();
+        run_with_guest(&mut dev, &mut channel, async {
+            guest.negotiate(capabilities).await;
+            let rpc = send.call_failable(DynamicMemoryRpc::HotAdd, 1 << 20);
+            let (request, _) = guest
+                .recv::<protocol::HotAddRequest>(protocol::MessageType::MEM_HOT_ADD_REQUEST)
+                .await;
+            assert_eq!(request.range, expected);
+            guest
+                .send(&[protocol::HotAddResponse {
+                    header: header::<protocol::HotAddResponse>(
+                        protocol::MessageType::MEM_HOT_ADD_RESPONSE,
+                        request.header.transaction_id,
+                    ),
+                    page_count: expected.page_count() as u32,
+                    result: protocol::HOT_ADD_SUCCESS,
+                }
+                .as_bytes()])
+                .await;
+            assert_eq!(rpc.await.unwrap(), 128 << 20);
+        })
+        .await;
+        assert_eq!(*ram.added.lock(), [(HOT_ADD_BASE, 128 << 20)]);
+
+        // The memory is offered again when the guest reconnects.
+        let (mut channel, mut guest) = connect();
+        run_with_guest(&mut dev, &mut channel, async {
+            guest.negotiate(capabilities).await;
+            let (request, _) = guest
+                .recv::<protocol::HotAddRequest>(protocol::MessageType::MEM_HOT_ADD_REQUEST)
+                .await;
+            assert_eq!(request.range, expected);
+        })
+        .await;
+        assert_eq!(ram.added.lock().len(), 1);
+    }
+
+    #[async_test]
+    async fn test_hot_add_errors(driver: DefaultDriver) {
+        let ram = Arc::new(TestRam::default());
+        let (mut dev, send) = new_device(&driver, &ram);
+        send.call_failable(DynamicMemoryRpc::HotAdd, 1 << 20)
+            .await
+            .unwrap_err();
+
+        let (mut channel, mut guest) = connect();
+        run_with_guest(&mut dev, &mut channel, async {
+            guest
+                .negotiate(protocol::Capabilities::new().with_balloon(true))
+                .await;
+            send.call_failable(DynamicMemoryRpc::HotAdd, 1 << 20)
+                .await
+                .unwrap_err();
+        })
+        .await;
+        assert!(ram.added.lock().is_empty());
+    }
+}
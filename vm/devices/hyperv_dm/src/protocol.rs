@@ -0,0 +1,224 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Hyper-V dynamic memory protocol definitions.
+//!
+//! Messages are sent as in-band vmbus packets, each starting with a
+//! [`Header`].
+
+use bitfield_struct::bitfield;
+use guid::Guid;
+use open_enum::open_enum;
+use zerocopy::FromBytes;
+use zerocopy::Immutable;
+use zerocopy::IntoBytes;
+use zerocopy::KnownLayout;
+
+pub const INTERFACE_ID: Guid = guid::guid!("525074dc-8985-46e2-8057-a307dc18a502");
+pub const INSTANCE_ID: Guid = guid::guid!("d1da3a1a-6e4e-4e0b-9b55-05f1ae2d4bc1");
+
+/// The largest message the guest expects to receive, and the largest message
+/// the device accepts from the guest.
+pub const MAX_MESSAGE_SIZE: usize = 4096;
+
+/// The size of the pages referred to by page numbers and counts.
+pub const PAGE_SIZE: u64 = 4096;
+
+open_enum! {
+    #[derive(IntoBytes, Immutable, KnownLayout, FromBytes)]
+    pub enum Version: u32 {
+        WIN7 = 0x00000003,
+        WIN8 = 0x00010000,
+        WIN10 = 0x00020000,
+    }
+}
+
+open_enum! {
+    #[derive(IntoBytes, Immutable, KnownLayout, FromBytes)]
+    pub enum MessageType: u16 {
+        ERROR = 0,
+        VERSION_REQUEST = 1,
+        VERSION_RESPONSE = 2,
+        CAPABILITIES_REPORT = 3,
+        CAPABILITIES_RESPONSE = 4,
+        STATUS_REPORT = 5,
+        BALLOON_REQUEST = 6,
+        BALLOON_RESPONSE = 7,
+        UNBALLOON_REQUEST = 8,
+        UNBALLOON_RESPONSE = 9,
+        MEM_HOT_ADD_REQUEST = 10,
+        MEM_HOT_ADD_RESPONSE = 11,
+        INFO_MESSAGE = 12,
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct Header {
+    pub message_type: MessageType,
+    /// The size of the message, including the header.
+    pub size: u16,
+    pub transaction_id: u32,
+}
+
+/// A range of pages, packed into 64 bits.
+#[bitfield(u64)]
+#[derive(IntoBytes, Immutable, KnownLayout, FromBytes, PartialEq, Eq)]
+pub struct PageRange {
+    #[bits(40)]
+    pub start_page: u64,
+    #[bits(24)]
+    pub page_count: u64,
+}
+
+/// The largest page count that fits in a [`PageRange`].
+pub const MAX_RANGE_PAGES: u64 = (1 << 24) - 1;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct VersionRequest {
+    pub header: Header,
+    pub version: Version,
+    pub flags: VersionRequestFlags,
+}
+
+#[bitfield(u32)]
+#[derive(IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct VersionRequestFlags {
+    /// The guest has no older version to try if this one is rejected.
+    pub is_last_attempt: bool,
+    #[bits(31)]
+    _reserved: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct VersionResponse {
+    pub header: Header,
+    pub flags: ResponseFlags,
+}
+
+#[bitfield(u64)]
+#[derive(IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct ResponseFlags {
+    pub is_accepted: bool,
+    #[bits(63)]
+    _reserved: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct CapabilitiesReport {
+    pub header: Header,
+    pub capabilities: Capabilities,
+    /// The minimum number of pages the guest needs to run.
+    pub min_page_count: u64,
+    /// The highest page number the guest can use.
+    pub max_page_number: u64,
+}
+
+#[bitfield(u64)]
+#[derive(IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct Capabilities {
+    pub balloon: bool,
+    pub hot_add: bool,
+    /// The required alignment of hot-added memory, as a power of two number
+    /// of megabytes.
+    #[bits(4)]
+    pub hot_add_alignment: u8,
+    #[bits(58)]
+    _reserved: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct CapabilitiesResponse {
+    pub header: Header,
+    pub flags: CapabilitiesResponseFlags,
+}
+
+#[bitfield(u64)]
+#[derive(IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct CapabilitiesResponseFlags {
+    pub is_accepted: bool,
+    pub hot_remove: bool,
+    pub suppress_pressure_reports: bool,
+    #[bits(61)]
+    _reserved: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct StatusReport {
+    pub header: Header,
+    pub available_pages: u64,
+    pub committed_pages: u64,
+    pub page_file_size: u64,
+    pub zero_free_pages: u64,
+    pub page_file_writes: u32,
+    pub io_diff: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct BalloonRequest {
+    pub header: Header,
+    pub page_count: u32,
+    pub reserved: u32,
+}
+
+/// Followed by `range_count` [`PageRange`]s.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct BalloonResponse {
+    pub header: Header,
+    pub reserved: u32,
+    pub flags: MorePagesFlags,
+    pub range_count: u32,
+}
+
+#[bitfield(u32)]
+#[derive(IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct MorePagesFlags {
+    /// More messages follow for the same request.
+    pub more_pages: bool,
+    #[bits(31)]
+    _reserved: u32,
+}
+
+/// Followed by `range_count` [`PageRange`]s.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct UnballoonRequest {
+    pub header: Header,
+    pub flags: MorePagesFlags,
+    pub range_count: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct HotAddRequest {
+    pub header: Header,
+    pub range: PageRange,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct HotAddResponse {
+    pub header: Header,
+    /// The number of pages the guest added.
+    pub page_count: u32,
+    pub result: u32,
+}
+
+/// [`HotAddResponse::result`] when the guest added memory.
+pub const HOT_ADD_SUCCESS: u32 = 1;
+
+/// Followed by `info_size` bytes of information.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct InfoMessage {
+    pub header: Header,
+    pub reserved: u32,
+    pub info_size: u32,
+}
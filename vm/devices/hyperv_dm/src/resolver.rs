@@ -0,0 +1,44 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Resource resolver for the dynamic memory device.
+
+use crate::DynamicMemoryDevice;
+use hyperv_dm_resources::DynamicMemoryHandle;
+use std::convert::Infallible;
+use vm_resource::ResolveResource;
+use vm_resource::declare_static_resolver;
+use vm_resource::kind::VmbusDeviceHandleKind;
+use vmbus_channel::resources::ResolveVmbusDeviceHandleParams;
+use vmbus_channel::resources::ResolvedVmbusDevice;
+use vmbus_channel::simple::SimpleDeviceWrapper;
+
+/// Resource resolver for the dynamic memory device.
+pub struct DynamicMemoryResolver;
+
+declare_static_resolver! {
+    DynamicMemoryResolver,
+    (VmbusDeviceHandleKind, DynamicMemoryHandle),
+}
+
+impl ResolveResource<VmbusDeviceHandleKind, DynamicMemoryHandle> for DynamicMemoryResolver {
+    type Output = ResolvedVmbusDevice;
+    type Error = Infallible;
+
+    fn resolve(
+        &self,
+        resource: DynamicMemoryHandle,
+        input: ResolveVmbusDeviceHandleParams<'_>,
+    ) -> Result<Self::Output, Self::Error> {
+        Ok(SimpleDeviceWrapper::new(
+            input.driver_source.simple(),
+            DynamicMemoryDevice::new(
+                input.driver_source,
+                resource.recv,
+                input.discard_ram,
+                input.hot_add_ram,
+            ),
+        )
+        .into())
+    }
+}
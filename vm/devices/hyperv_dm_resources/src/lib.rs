@@ -0,0 +1,67 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Resources for the Hyper-V dynamic memory device.
+
+#![forbid(unsafe_code)]
+
+use mesh::MeshPayload;
+use mesh::rpc::FailableRpc;
+use mesh::rpc::Rpc;
+use vm_resource::ResourceId;
+use vm_resource::kind::VmbusDeviceHandleKind;
+
+/// A handle to a Hyper-V dynamic memory device.
+#[derive(MeshPayload)]
+pub struct DynamicMemoryHandle {
+    /// The channel by which the host controls the device.
+    pub recv: mesh::Receiver<DynamicMemoryRpc>,
+}
+
+impl ResourceId<VmbusDeviceHandleKind> for DynamicMemoryHandle {
+    const ID: &'static str = "hyperv_dm";
+}
+
+/// An RPC request to the dynamic memory device.
+#[derive(MeshPayload)]
+pub enum DynamicMemoryRpc {
+    /// Set the balloon target, in 4KB pages of memory to take from the guest.
+    SetTarget(Rpc<u64, ()>),
+    /// Add at least the given number of bytes of RAM to the VM and offer it
+    /// to the guest. Returns the number of bytes the guest accepted.
+    HotAdd(FailableRpc<u64, u64>),
+    /// Get the current status of the device.
+    Status(Rpc<(), DynamicMemoryStatus>),
+}
+
+/// The state of the dynamic memory device.
+#[derive(Debug, MeshPayload)]
+pub struct DynamicMemoryStatus {
+    /// Whether the guest has connected and negotiated the protocol.
+    pub connected: bool,
+    /// The balloon target, in 4KB pages.
+    pub target_pages: u64,
+    /// The number of 4KB pages the guest has given up to the balloon.
+    pub ballooned_pages: u64,
+    /// The number of hot-added 4KB pages the guest has accepted.
+    pub hot_added_pages: u64,
+    /// The memory status last reported by the guest.
+    pub guest: Option<GuestMemoryStatus>,
+}
+
+/// The memory status periodically reported by the guest.
+#[derive(Debug, Copy, Clone, MeshPayload)]
+pub struct GuestMemoryStatus {
+    /// The number of 4KB pages available to the guest.
+    pub available_pages: u64,
+    /// The number of 4KB pages committed by the guest.
+    pub committed_pages: u64,
+    /// The size of the guest's page file, in 4KB pages.
+    pub page_file_size: u64,
+    /// The number of free, zeroed 4KB pages.
+    pub zero_free_pages: u64,
+    /// The number of page file writes.
+    pub page_file_writes: u32,
+    /// The amount of IO since the last report.
+    pub io_diff: u32,
+}
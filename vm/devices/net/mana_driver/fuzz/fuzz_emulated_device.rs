@@ -0,0 +1,113 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! A shim layer to fuzz responses from an emulated device.
+//! This is the primary fuzzer for the host (a.k.a device) ->
+//! openhcl attack surface. Do not sanitize any arbitrary data
+//! responses in this routine.
+use crate::arbitrary_data;
+
+use chipset_device::mmio::MmioIntercept;
+use chipset_device::pci::PciConfigSpace;
+use inspect::Inspect;
+use inspect::InspectMut;
+use pci_core::msi::MsiConnection;
+use std::sync::Arc;
+use user_driver::DeviceBacking;
+use user_driver::DeviceRegisterIo;
+use user_driver::DmaClient;
+use user_driver::DmaPool;
+use user_driver::interrupt::DeviceInterrupt;
+use user_driver_emulated_mock::EmulatedDevice;
+use user_driver_emulated_mock::Mapping;
+
+/// An EmulatedDevice fuzzer that requires a working EmulatedDevice backend.
+#[derive(Inspect)]
+pub struct FuzzEmulatedDevice<T: InspectMut, U> {
+    device: EmulatedDevice<T, U>,
+}
+
+impl<T: PciConfigSpace + MmioIntercept + InspectMut, U: DmaClient> FuzzEmulatedDevice<T, U> {
+    /// Creates a new emulated device, wrapping `device`, using the provided MSI controller.
+    pub fn new(device: T, msi_conn: MsiConnection, dma_client: Arc<U>) -> Self {
+        let device = EmulatedDevice::new(device, msi_conn, dma_client);
+
+        Self { device }
+    }
+}
+
+/// Implementation for DeviceBacking trait.
+impl<T: 'static + Send + InspectMut + MmioIntercept, U: 'static + DmaClient> DeviceBacking
+    for FuzzEmulatedDevice<T, U>
+{
+    type Registers = FuzzMapping<T>;
+
+    fn id(&self) -> &str {
+        self.device.id()
+    }
+
+    fn map_bar(&mut self, n: u8) -> anyhow::Result<Self::Registers> {
+        Ok(FuzzMapping {
+            mapping: self.device.map_bar(n)?,
+        })
+    }
+
+    fn dma_client(&self) -> Arc<dyn DmaClient> {
+        self.device.dma_client()
+    }
+
+    fn dma_client_for(&self, pool: DmaPool) -> anyhow::Result<Arc<dyn DmaClient>> {
+        self.device.dma_client_for(pool)
+    }
+
+    fn max_interrupt_count(&self) -> u32 {
+        self.device.max_interrupt_count()
+    }
+
+    fn map_interrupt(&mut self, msix: u32, cpu: u32) -> anyhow::Result<DeviceInterrupt> {
+        self.device.map_interrupt(msix, cpu)
+    }
+}
+
+/// A BAR mapping that replaces register reads with arbitrary values, as
+/// directed by the fuzz input.
+///
+/// Once the fuzz input is exhausted, reads are passed through to the
+/// emulated device so that the driver can still make progress.
+#[derive(Inspect)]
+#[inspect(transparent)]
+pub struct FuzzMapping<T> {
+    mapping: Mapping<T>,
+}
+
+impl<T: MmioIntercept + Send> DeviceRegisterIo for FuzzMapping<T> {
+    fn len(&self) -> usize {
+        self.mapping.len()
+    }
+
+    fn read_u32(&self, offset: usize) -> u32 {
+        let value = self.mapping.read_u32(offset);
+        if arbitrary_data::<bool>().unwrap_or(false) {
+            arbitrary_data().unwrap_or(value)
+        } else {
+            value
+        }
+    }
+
+    fn read_u64(&self, offset: usize) -> u64 {
+        let value = self.mapping.read_u64(offset);
+        if arbitrary_data::<bool>().unwrap_or(false) {
+            arbitrary_data().unwrap_or(value)
+        } else {
+            value
+        }
+    }
+
+    fn write_u32(&self, offset: usize, data: u32) {
+        self.mapping.write_u32(offset, data)
+    }
+
+    fn write_u64(&self, offset: usize, data: u64) {
+        self.mapping.write_u64(offset, data)
+    }
+}
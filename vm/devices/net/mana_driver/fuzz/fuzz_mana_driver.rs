@@ -0,0 +1,299 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! An interface to fuzz the MANA driver with arbitrary actions
+use crate::arbitrary_data;
+use crate::fuzz_emulated_device::FuzzEmulatedDevice;
+
+use arbitrary::Arbitrary;
+use chipset_device::mmio::ExternallyManagedMmioIntercepts;
+use gdma::GdmaDevice;
+use gdma::VportConfig;
+use gdma_defs::Sge;
+use guestmem::GuestMemory;
+use mana_driver::mana::BnicEq;
+use mana_driver::mana::BnicWq;
+use mana_driver::mana::ManaDevice;
+use mana_driver::mana::ResourceArena;
+use mana_driver::mana::Vport;
+use mana_driver::queues;
+use net_backend::null::NullEndpoint;
+use page_pool_alloc::PagePoolAllocator;
+use pal_async::DefaultDriver;
+use pci_core::bus_range::AssignedBusRange;
+use pci_core::msi::MsiConnection;
+use std::collections::VecDeque;
+use user_driver::memory::PAGE_SIZE;
+use user_driver_emulated_mock::DeviceTestMemory;
+use vmcore::vm_task::SingleDriverBackend;
+use vmcore::vm_task::VmTaskDriverSource;
+
+/// A work queue and its completion queue, along with the lengths of the
+/// posted entries that the device has not yet completed.
+struct FuzzWq {
+    _bnic_wq: BnicWq,
+    wq: queues::Wq,
+    cq: queues::Cq,
+    posted: VecDeque<u32>,
+}
+
+type FuzzDevice = FuzzEmulatedDevice<GdmaDevice, PagePoolAllocator>;
+
+/// The most queues of each type to create, to bound DMA memory usage.
+const MAX_QUEUES: usize = 8;
+
+/// The most CPUs to report to the driver.
+const MAX_CPUS: u8 = 64;
+
+/// The most scatter-gather entries in a single WQE. A WQE is at most 512 bytes,
+/// which leaves room for 31 entries after the header and 8-byte out-of-band
+/// data.
+const MAX_WQE_SGL_ENTRIES: usize = 31;
+
+/// MANA driver fuzzer
+pub struct FuzzManaDriver {
+    device: Option<ManaDevice<FuzzDevice>>,
+    vport: Option<Vport<FuzzDevice>>,
+    arena: ResourceArena,
+    eqs: Vec<(BnicEq, queues::Eq)>,
+    wqs: Vec<FuzzWq>,
+    dma_mem: GuestMemory,
+    dma_len: u64,
+    cpu_count: u32,
+}
+
+impl FuzzManaDriver {
+    /// Setup a new MANA driver with a fuzz-enabled backend device.
+    pub async fn new(driver: DefaultDriver) -> Result<Self, anyhow::Error> {
+        let cpu_count = u32::from(arbitrary_data::<u8>()? % MAX_CPUS) + 1;
+        let pages = 512; // 2MB
+        let mem = DeviceTestMemory::new(pages, false, "fuzz_mana_driver");
+
+        // GDMA device and driver setup
+        let driver_source = VmTaskDriverSource::new(SingleDriverBackend::new(driver.clone()));
+        let msi_conn = MsiConnection::new(AssignedBusRange::new(), 0);
+
+        let gdma = GdmaDevice::new(
+            &driver_source,
+            mem.guest_memory(),
+            msi_conn.target(),
+            vec![VportConfig {
+                mac_address: arbitrary_data::<[u8; 6]>()?.into(),
+                endpoint: Box::new(NullEndpoint::new()),
+            }],
+            &mut ExternallyManagedMmioIntercepts,
+        );
+
+        let device = FuzzEmulatedDevice::new(gdma, msi_conn, mem.dma_client());
+        let mana_device = ManaDevice::new(&driver, device, cpu_count, 1, None).await?;
+        let vport = mana_device
+            .new_vport(0, None, mana_device.dev_config())
+            .await?;
+
+        Ok(Self {
+            device: Some(mana_device),
+            vport: Some(vport),
+            arena: ResourceArena::new(),
+            eqs: Vec::new(),
+            wqs: Vec::new(),
+            // DMA allocations come from the first half of the test memory.
+            dma_mem: mem.guest_memory(),
+            dma_len: pages / 2 * PAGE_SIZE as u64,
+            cpu_count,
+        })
+    }
+
+    /// Clean up fuzzing infrastructure.
+    pub async fn shutdown(&mut self) {
+        self.wqs.clear();
+        self.eqs.clear();
+        let vport = self.vport.take().unwrap();
+        vport.destroy(std::mem::take(&mut self.arena)).await;
+        drop(vport);
+        let (_result, _device) = self.device.take().unwrap().shutdown().await;
+    }
+
+    /// Generates and executes an arbitrary ManaDriverAction. As with the
+    /// nvme driver fuzzer, arbitrary data is only sanitized where needed to
+    /// get past contract-checking `assert!`s in the driver, which imply
+    /// programmer error rather than a malicious device.
+    pub async fn execute_arbitrary_action(&mut self) -> Result<(), anyhow::Error> {
+        let action = arbitrary_data::<ManaDriverAction>()?;
+        let vport = self.vport.as_ref().unwrap();
+
+        match action {
+            ManaDriverAction::CreateEq { size_shift, cpu } => {
+                if self.eqs.len() < MAX_QUEUES {
+                    let size = queue_size(size_shift);
+                    // The device may fail the request, which ends this input
+                    // rather than being treated as a driver bug.
+                    let eq = vport
+                        .new_eq(&mut self.arena, size, cpu % self.cpu_count)
+                        .await?;
+                    let queue = eq.queue();
+                    self.eqs.push((eq, queue));
+                }
+            }
+
+            ManaDriverAction::CreateWq {
+                is_send,
+                wq_size_shift,
+                cq_size_shift,
+                eq_index,
+            } => {
+                if self.wqs.len() < MAX_QUEUES && !self.eqs.is_empty() {
+                    let eq_id = self.eqs[eq_index as usize % self.eqs.len()].0.id();
+                    let wq = vport
+                        .new_wq(
+                            &mut self.arena,
+                            is_send,
+                            queue_size(wq_size_shift),
+                            queue_size(cq_size_shift),
+                            eq_id,
+                        )
+                        .await?;
+                    self.wqs.push(FuzzWq {
+                        wq: wq.wq(),
+                        cq: wq.cq(),
+                        _bnic_wq: wq,
+                        posted: VecDeque::new(),
+                    });
+                }
+            }
+
+            ManaDriverAction::PostWqe {
+                wq_index,
+                oob,
+                sgl,
+                commit,
+            } => {
+                if !self.wqs.is_empty() {
+                    let index = wq_index as usize % self.wqs.len();
+                    let wq = &mut self.wqs[index];
+                    let sgl = sgl.into_iter().take(MAX_WQE_SGL_ENTRIES).map(
+                        |(address, mem_key, size)| Sge {
+                            address,
+                            mem_key,
+                            size,
+                        },
+                    );
+                    // A full queue is expected, not a failure.
+                    if let Ok(len) = wq.wq.push(oob, sgl) {
+                        wq.posted.push_back(len);
+                    }
+                    if commit {
+                        wq.wq.commit();
+                    }
+                }
+            }
+
+            ManaDriverAction::PollCq { wq_index, arm } => {
+                if !self.wqs.is_empty() {
+                    let index = wq_index as usize % self.wqs.len();
+                    let wq = &mut self.wqs[index];
+                    // Completions arrive in order, so each one releases the
+                    // oldest posted entry, as in the net_mana backend.
+                    while wq.cq.pop().is_some() {
+                        if let Some(len) = wq.posted.pop_front() {
+                            wq.wq.advance_head(len);
+                        }
+                    }
+                    if arm { wq.cq.arm() } else { wq.cq.ack() }
+                }
+            }
+
+            ManaDriverAction::ProcessEq { eq_index, arm } => {
+                if !self.eqs.is_empty() {
+                    let index = eq_index as usize % self.eqs.len();
+                    let (_, eq) = &mut self.eqs[index];
+                    while eq.pop().is_some() {}
+                    if arm { eq.arm() } else { eq.ack() }
+                }
+            }
+
+            ManaDriverAction::RetargetInterrupt { eq_index, cpu } => {
+                if !self.eqs.is_empty() {
+                    let index = eq_index as usize % self.eqs.len();
+                    let (eq, _) = &self.eqs[index];
+                    vport
+                        .retarget_interrupt(eq.id(), cpu % self.cpu_count)
+                        .await?;
+                }
+            }
+
+            ManaDriverAction::DeviceWrite { offset, data } => {
+                // Simulate the device writing arbitrary EQ/CQ entries (or
+                // anything else) into DMA memory.
+                let offset = offset % self.dma_len;
+                let len = data.len().min((self.dma_len - offset) as usize);
+                self.dma_mem.write_at(offset, &data[..len])?;
+            }
+
+            ManaDriverAction::QueryStats => {
+                vport.query_stats().await?;
+            }
+
+            ManaDriverAction::QueryFilterState => {
+                vport.query_filter_state(vport.id().into()).await?;
+            }
+
+            ManaDriverAction::MoveFilter { direction_to_vtl0 } => {
+                vport.move_filter(direction_to_vtl0).await?;
+            }
+
+            ManaDriverAction::ConfigTx => {
+                vport.config_tx().await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns a power-of-two queue size of at least a page, as required by the
+/// driver.
+fn queue_size(shift: u8) -> u32 {
+    (PAGE_SIZE as u32) << (shift % 3)
+}
+
+#[derive(Debug, Arbitrary)]
+pub enum ManaDriverAction {
+    CreateEq {
+        size_shift: u8,
+        cpu: u32,
+    },
+    CreateWq {
+        is_send: bool,
+        wq_size_shift: u8,
+        cq_size_shift: u8,
+        eq_index: u8,
+    },
+    PostWqe {
+        wq_index: u8,
+        oob: [u8; 8],
+        sgl: Vec<(u64, u32, u32)>,
+        commit: bool,
+    },
+    PollCq {
+        wq_index: u8,
+        arm: bool,
+    },
+    ProcessEq {
+        eq_index: u8,
+        arm: bool,
+    },
+    RetargetInterrupt {
+        eq_index: u8,
+        cpu: u32,
+    },
+    DeviceWrite {
+        offset: u64,
+        data: Vec<u8>,
+    },
+    QueryStats,
+    QueryFilterState,
+    MoveFilter {
+        direction_to_vtl0: u8,
+    },
+    ConfigTx,
+}
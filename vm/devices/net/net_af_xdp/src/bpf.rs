@@ -0,0 +1,236 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! The XDP program that steers received packets to the AF_XDP sockets.
+//!
+//! The program redirects each packet to the socket in an `XSKMAP` slot
+//! matching the packet's receive queue, or passes it to the host network
+//! stack if there is no socket bound to that queue. It is small enough to be
+//! assembled by hand here rather than requiring a BPF toolchain.
+
+// UNSAFETY: Calling the bpf syscall.
+#![expect(unsafe_code)]
+
+use std::io;
+use std::os::fd::AsRawFd;
+use std::os::fd::FromRawFd;
+use std::os::fd::OwnedFd;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to create the XSKMAP")]
+    CreateMap(#[source] io::Error),
+    #[error("failed to load the XDP program")]
+    LoadProgram(#[source] io::Error),
+    #[error("failed to attach the XDP program to the interface")]
+    Attach(#[source] io::Error),
+    #[error("failed to update the XSKMAP")]
+    UpdateMap(#[source] io::Error),
+}
+
+const BPF_MAP_CREATE: libc::c_int = 0;
+const BPF_MAP_UPDATE_ELEM: libc::c_int = 2;
+const BPF_PROG_LOAD: libc::c_int = 5;
+const BPF_LINK_CREATE: libc::c_int = 28;
+
+const BPF_MAP_TYPE_XSKMAP: u32 = 17;
+const BPF_PROG_TYPE_XDP: u32 = 6;
+const BPF_XDP: u32 = 37;
+
+const BPF_FUNC_REDIRECT_MAP: i32 = 51;
+const BPF_PSEUDO_MAP_FD: u8 = 1;
+const XDP_PASS: i32 = 2;
+
+/// The offset of `rx_queue_index` in `struct xdp_md`.
+const XDP_MD_RX_QUEUE_INDEX: i16 = 16;
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+struct BpfInsn {
+    code: u8,
+    /// The destination register in the low nibble, the source in the high.
+    regs: u8,
+    off: i16,
+    imm: i32,
+}
+
+impl BpfInsn {
+    const fn new(code: u8, dst: u8, src: u8, off: i16, imm: i32) -> Self {
+        Self {
+            code,
+            regs: dst | (src << 4),
+            off,
+            imm,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct MapCreateAttr {
+    map_type: u32,
+    key_size: u32,
+    value_size: u32,
+    max_entries: u32,
+    map_flags: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct MapElemAttr {
+    map_fd: u32,
+    _pad: u32,
+    key: u64,
+    value: u64,
+    flags: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ProgLoadAttr {
+    prog_type: u32,
+    insn_cnt: u32,
+    insns: u64,
+    license: u64,
+    log_level: u32,
+    log_size: u32,
+    log_buf: u64,
+    kern_version: u32,
+    prog_flags: u32,
+    prog_name: [u8; 16],
+    prog_ifindex: u32,
+    expected_attach_type: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct LinkCreateAttr {
+    prog_fd: u32,
+    target_ifindex: u32,
+    attach_type: u32,
+    flags: u32,
+}
+
+/// Issues a bpf syscall that returns a new file descriptor.
+///
+/// # Safety
+/// `attr` must be the attribute structure for `cmd`, and any pointers in it
+/// must be valid for the duration of the call.
+unsafe fn bpf_fd<T>(cmd: libc::c_int, attr: &mut T) -> io::Result<OwnedFd> {
+    // SAFETY: guaranteed by the caller.
+    let fd = unsafe {
+        libc::syscall(
+            libc::SYS_bpf,
+            cmd,
+            std::ptr::from_mut(attr),
+            size_of::<T>() as u32,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: the syscall returned a new file descriptor that nothing else
+    // owns.
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as i32) })
+}
+
+/// An XDP program attached to an interface, along with the map of sockets it
+/// redirects to.
+///
+/// The program is detached when this is dropped.
+#[derive(Debug)]
+pub struct XdpProgram {
+    map: OwnedFd,
+    _prog: OwnedFd,
+    _link: OwnedFd,
+}
+
+impl XdpProgram {
+    /// Loads the program and attaches it to interface `ifindex`, with room for
+    /// sockets on `queue_count` queues.
+    ///
+    /// The program is attached in driver mode if the interface supports it,
+    /// falling back to generic mode otherwise.
+    pub fn attach(ifindex: u32, queue_count: u32) -> Result<Self, Error> {
+        let mut attr = MapCreateAttr {
+            map_type: BPF_MAP_TYPE_XSKMAP,
+            key_size: 4,
+            value_size: 4,
+            max_entries: queue_count,
+            ..Default::default()
+        };
+        // SAFETY: passing a valid map create attribute.
+        let map = unsafe { bpf_fd(BPF_MAP_CREATE, &mut attr) }.map_err(Error::CreateMap)?;
+
+        let insns = [
+            // r2 = ctx->rx_queue_index
+            BpfInsn::new(0x61, 2, 1, XDP_MD_RX_QUEUE_INDEX, 0),
+            // r1 = map (a 16-byte load spanning two instructions)
+            BpfInsn::new(0x18, 1, BPF_PSEUDO_MAP_FD, 0, map.as_raw_fd()),
+            BpfInsn::default(),
+            // r3 = XDP_PASS, the action if the map slot is empty
+            BpfInsn::new(0xb7, 3, 0, 0, XDP_PASS),
+            // r0 = bpf_redirect_map(r1, r2, r3)
+            BpfInsn::new(0x85, 0, 0, 0, BPF_FUNC_REDIRECT_MAP),
+            // return r0
+            BpfInsn::new(0x95, 0, 0, 0, 0),
+        ];
+        let license = c"MIT";
+        let mut prog_name = [0; 16];
+        prog_name[..7].copy_from_slice(b"openvmm");
+        let mut attr = ProgLoadAttr {
+            prog_type: BPF_PROG_TYPE_XDP,
+            insn_cnt: insns.len() as u32,
+            insns: insns.as_ptr() as u64,
+            license: license.as_ptr() as u64,
+            prog_name,
+            ..Default::default()
+        };
+        // SAFETY: passing a valid program load attribute, whose pointers
+        // reference the local instructions and license.
+        let prog = unsafe { bpf_fd(BPF_PROG_LOAD, &mut attr) }.map_err(Error::LoadProgram)?;
+
+        let mut attr = LinkCreateAttr {
+            prog_fd: prog.as_raw_fd() as u32,
+            target_ifindex: ifindex,
+            attach_type: BPF_XDP,
+            flags: 0,
+        };
+        // SAFETY: passing a valid link create attribute.
+        let link = unsafe { bpf_fd(BPF_LINK_CREATE, &mut attr) }.map_err(Error::Attach)?;
+
+        Ok(Self {
+            map,
+            _prog: prog,
+            _link: link,
+        })
+    }
+
+    /// Steers packets received on `queue_id` to the AF_XDP socket `socket`.
+    ///
+    /// The kernel removes the socket from the map when it is closed.
+    pub fn set_socket(&self, queue_id: u32, socket: &impl AsRawFd) -> Result<(), Error> {
+        let value = socket.as_raw_fd() as u32;
+        let mut attr = MapElemAttr {
+            map_fd: self.map.as_raw_fd() as u32,
+            key: std::ptr::from_ref(&queue_id) as u64,
+            value: std::ptr::from_ref(&value) as u64,
+            ..Default::default()
+        };
+        // SAFETY: passing a valid map element attribute, whose pointers
+        // reference the locals.
+        let r = unsafe {
+            libc::syscall(
+                libc::SYS_bpf,
+                BPF_MAP_UPDATE_ELEM,
+                std::ptr::from_mut(&mut attr),
+                size_of::<MapElemAttr>() as u32,
+            )
+        };
+        if r < 0 {
+            return Err(Error::UpdateMap(io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+}
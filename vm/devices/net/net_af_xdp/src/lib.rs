@@ -0,0 +1,293 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! An endpoint that attaches directly to a host NIC's queues via AF_XDP
+//! sockets.
+//!
+//! Each guest queue is backed by an AF_XDP socket bound to the NIC queue with
+//! the same index, and an XDP program steers the packets received on those
+//! queues to the sockets, bypassing the host network stack. Packets received
+//! on other queues are passed to the host network stack as usual.
+//!
+//! Packets are copied between guest memory and each socket's UMEM, so this
+//! avoids the host network stack but not copies. Zero-copy mode only refers to
+//! the NIC driver placing packets directly in the UMEM.
+//!
+//! The NIC's own RSS configuration decides which queue receives each packet,
+//! so the guest's RSS configuration is ignored. The NIC should be configured
+//! (e.g. with `ethtool -L <interface> combined <n>`) to use no more queues
+//! than the guest, or some traffic will go to the host instead.
+//!
+//! This requires `CAP_NET_ADMIN` and `CAP_BPF` (or `CAP_SYS_ADMIN`), and
+//! enough `RLIMIT_MEMLOCK` for each queue's UMEM on older kernels.
+
+#![cfg(target_os = "linux")]
+#![expect(missing_docs)]
+
+mod bpf;
+pub mod resolver;
+mod xsk;
+
+use anyhow::Context as _;
+use async_trait::async_trait;
+use inspect::InspectMut;
+use inspect_counters::Counter;
+use net_backend::BufferAccess;
+use net_backend::Endpoint;
+use net_backend::MultiQueueSupport;
+use net_backend::Queue;
+use net_backend::QueueConfig;
+use net_backend::RssConfig;
+use net_backend::RxId;
+use net_backend::RxMetadata;
+use net_backend::TxError;
+use net_backend::TxId;
+use net_backend::TxSegment;
+use net_backend::next_packet;
+use pal_async::driver::PollImpl;
+use pal_async::fd::PollFdReady;
+use pal_async::interest::InterestSlot;
+use pal_async::interest::PollEvents;
+use std::collections::VecDeque;
+use std::os::fd::AsRawFd;
+use std::task::Context;
+use std::task::Poll;
+
+/// An endpoint backed by AF_XDP sockets on a host interface.
+pub struct AfXdpEndpoint {
+    interface: String,
+    ifindex: u32,
+    queue_count: u16,
+    require_zero_copy: bool,
+    program: bpf::XdpProgram,
+}
+
+impl AfXdpEndpoint {
+    /// Attaches to host interface `interface`.
+    ///
+    /// If `require_zero_copy` is set, creating queues fails if the
+    /// interface's driver does not support zero-copy AF_XDP. Otherwise, the
+    /// kernel falls back to copying packets into the UMEM.
+    pub fn new(interface: &str, require_zero_copy: bool) -> anyhow::Result<Self> {
+        let ifindex = xsk::interface_index(interface)
+            .with_context(|| format!("failed to find interface {interface}"))?;
+        let queue_count = std::fs::read_dir(format!("/sys/class/net/{interface}/queues"))
+            .context("failed to enumerate interface queues")?
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .is_ok_and(|entry| entry.file_name().to_string_lossy().starts_with("rx-"))
+            })
+            .count()
+            .clamp(1, u16::MAX.into()) as u16;
+        let program = bpf::XdpProgram::attach(ifindex, queue_count.into())
+            .with_context(|| format!("failed to attach XDP program to {interface}"))?;
+        Ok(Self {
+            interface: interface.to_owned(),
+            ifindex,
+            queue_count,
+            require_zero_copy,
+            program,
+        })
+    }
+}
+
+impl InspectMut for AfXdpEndpoint {
+    fn inspect_mut(&mut self, req: inspect::Request<'_>) {
+        req.respond()
+            .field("interface", &self.interface)
+            .field("ifindex", self.ifindex)
+            .field("queue_count", self.queue_count)
+            .field("require_zero_copy", self.require_zero_copy);
+    }
+}
+
+#[async_trait]
+impl Endpoint for AfXdpEndpoint {
+    fn endpoint_type(&self) -> &'static str {
+        "af_xdp"
+    }
+
+    async fn get_queues(
+        &mut self,
+        config: Vec<QueueConfig>,
+        _rss: Option<&RssConfig<'_>>,
+        queues: &mut Vec<Box<dyn Queue>>,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            config.len() <= self.queue_count.into(),
+            "{} queues requested, but {} only has {}",
+            config.len(),
+            self.interface,
+            self.queue_count
+        );
+        for (queue_id, config) in (0..).zip(config) {
+            let xsk = xsk::XskSocket::new(self.ifindex, queue_id, self.require_zero_copy)
+                .with_context(|| format!("failed to create AF_XDP socket on {}", self.interface))?;
+            self.program.set_socket(queue_id, &xsk)?;
+            let fd_ready = config.driver.new_dyn_fd_ready(xsk.as_raw_fd())?;
+            queues.push(Box::new(AfXdpQueue {
+                xsk,
+                fd_ready,
+                rx_free: VecDeque::new(),
+                rx_ready: VecDeque::new(),
+                stats: Default::default(),
+            }));
+        }
+        Ok(())
+    }
+
+    async fn stop(&mut self) {}
+
+    fn multiqueue_support(&self) -> MultiQueueSupport {
+        MultiQueueSupport {
+            max_queues: self.queue_count,
+            // The NIC's indirection table is used instead, but the guest
+            // needs a table to enable RSS at all.
+            indirection_table_size: 128,
+        }
+    }
+
+    fn tx_fast_completions(&self) -> bool {
+        true
+    }
+}
+
+struct AfXdpQueue {
+    xsk: xsk::XskSocket,
+    fd_ready: PollImpl<dyn PollFdReady>,
+    rx_free: VecDeque<RxId>,
+    rx_ready: VecDeque<RxId>,
+    stats: Stats,
+}
+
+#[derive(inspect::Inspect, Default)]
+struct Stats {
+    rx_packets: Counter,
+    tx_packets: Counter,
+    tx_dropped: Counter,
+}
+
+impl InspectMut for AfXdpQueue {
+    fn inspect_mut(&mut self, req: inspect::Request<'_>) {
+        req.respond()
+            .field("rx_free", self.rx_free.len())
+            .field("rx_ready", self.rx_ready.len())
+            .field("stats", &self.stats)
+            .field("xdp", self.xsk.statistics().ok());
+    }
+}
+
+impl AfXdpQueue {
+    /// Copies received packets into free guest buffers.
+    fn process_rx(&mut self, pool: &mut dyn BufferAccess) {
+        let Self {
+            xsk,
+            rx_free,
+            rx_ready,
+            stats,
+            ..
+        } = self;
+        let n = xsk.receive(rx_free.len(), |data| {
+            let rx = rx_free.pop_front().unwrap();
+            pool.write_packet(
+                rx,
+                &RxMetadata {
+                    offset: 0,
+                    len: data.len(),
+                    ..Default::default()
+                },
+                data,
+            );
+            rx_ready.push_back(rx);
+        });
+        stats.rx_packets.add(n as u64);
+    }
+}
+
+impl Queue for AfXdpQueue {
+    fn poll_ready(&mut self, cx: &mut Context<'_>, pool: &mut dyn BufferAccess) -> Poll<()> {
+        loop {
+            self.process_rx(pool);
+            if !self.rx_ready.is_empty() {
+                return Poll::Ready(());
+            }
+            if self.rx_free.is_empty() {
+                return Poll::Pending;
+            }
+            // Clear readiness before checking the ring again, so that a
+            // packet arriving after the check wakes this up.
+            if self
+                .fd_ready
+                .poll_fd_ready(cx, InterestSlot::Read, PollEvents::IN)
+                .is_pending()
+            {
+                return Poll::Pending;
+            }
+            self.fd_ready.clear_fd_ready(InterestSlot::Read);
+        }
+    }
+
+    fn rx_avail(&mut self, _pool: &mut dyn BufferAccess, done: &[RxId]) {
+        self.rx_free.extend(done);
+    }
+
+    fn rx_poll(
+        &mut self,
+        _pool: &mut dyn BufferAccess,
+        packets: &mut [RxId],
+    ) -> anyhow::Result<usize> {
+        let n = packets.len().min(self.rx_ready.len());
+        for (done, id) in packets.iter_mut().zip(self.rx_ready.drain(..n)) {
+            *done = id;
+        }
+        Ok(n)
+    }
+
+    fn tx_avail(
+        &mut self,
+        pool: &mut dyn BufferAccess,
+        mut segments: &[TxSegment],
+    ) -> anyhow::Result<(bool, usize)> {
+        let n = segments.len();
+        let mem = pool.guest_memory();
+        while !segments.is_empty() {
+            let (meta, this, rest) = next_packet(segments);
+            segments = rest;
+            // Copy the packet straight from guest memory into the UMEM frame.
+            // Transmit offloads are not supported, so the guest has already
+            // segmented the packet and computed its checksums.
+            let sent = self.xsk.transmit(meta.len as usize, |frame| {
+                let mut offset = 0;
+                for segment in this {
+                    let len = segment.len as usize;
+                    let dest = frame
+                        .get_mut(offset..offset + len)
+                        .context("packet segments longer than packet")?;
+                    mem.read_at(segment.gpa, dest)?;
+                    offset += len;
+                }
+                anyhow::Ok(())
+            })?;
+            if sent {
+                self.stats.tx_packets.increment();
+            } else {
+                // Dropped: the packet is too large or the TX ring is full.
+                self.stats.tx_dropped.increment();
+            }
+        }
+        self.xsk.flush_tx();
+        Ok((true, n))
+    }
+
+    fn tx_poll(
+        &mut self,
+        _pool: &mut dyn BufferAccess,
+        _done: &mut [TxId],
+    ) -> Result<usize, TxError> {
+        // Packets are copied into the UMEM synchronously, so just reclaim the
+        // frames the kernel has finished with.
+        self.xsk.reclaim_tx();
+        Ok(0)
+    }
+}
@@ -0,0 +1,30 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+use crate::AfXdpEndpoint;
+use net_backend::resolve::ResolveEndpointParams;
+use net_backend::resolve::ResolvedEndpoint;
+use net_backend_resources::af_xdp::AfXdpHandle;
+use vm_resource::ResolveResource;
+use vm_resource::declare_static_resolver;
+use vm_resource::kind::NetEndpointHandleKind;
+
+pub struct AfXdpResolver;
+
+declare_static_resolver! {
+    AfXdpResolver,
+    (NetEndpointHandleKind, AfXdpHandle),
+}
+
+impl ResolveResource<NetEndpointHandleKind, AfXdpHandle> for AfXdpResolver {
+    type Output = ResolvedEndpoint;
+    type Error = anyhow::Error;
+
+    fn resolve(
+        &self,
+        resource: AfXdpHandle,
+        _input: ResolveEndpointParams,
+    ) -> Result<Self::Output, Self::Error> {
+        Ok(AfXdpEndpoint::new(&resource.interface, resource.require_zero_copy)?.into())
+    }
+}
@@ -0,0 +1,561 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! An AF_XDP socket bound to a single NIC queue, with its own UMEM.
+//!
+//! The UMEM is split in two: the first half of the frames are owned by the
+//! kernel via the fill and RX rings, and the second half are used for
+//! transmits via the TX and completion rings.
+
+// UNSAFETY: Creating AF_XDP sockets and accessing their memory-mapped rings.
+#![expect(unsafe_code)]
+
+use std::io;
+use std::os::fd::AsFd;
+use std::os::fd::AsRawFd;
+use std::os::fd::BorrowedFd;
+use std::os::fd::FromRawFd;
+use std::os::fd::OwnedFd;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use thiserror::Error;
+
+/// The size of each UMEM frame. This bounds the largest packet that can be
+/// sent or received, so jumbo frames are not supported.
+pub const FRAME_SIZE: u32 = 2048;
+
+/// The number of frames in each half of the UMEM, and the size of each ring.
+pub const RING_SIZE: u32 = 1024;
+
+const FRAME_COUNT: u32 = RING_SIZE * 2;
+
+const XDP_MMAP_OFFSETS: libc::c_int = 1;
+const XDP_RX_RING: libc::c_int = 2;
+const XDP_TX_RING: libc::c_int = 3;
+const XDP_UMEM_REG: libc::c_int = 4;
+const XDP_UMEM_FILL_RING: libc::c_int = 5;
+const XDP_UMEM_COMPLETION_RING: libc::c_int = 6;
+const XDP_STATISTICS: libc::c_int = 7;
+
+const XDP_PGOFF_RX_RING: libc::off_t = 0;
+const XDP_PGOFF_TX_RING: libc::off_t = 0x80000000;
+const XDP_UMEM_PGOFF_FILL_RING: libc::off_t = 0x100000000;
+const XDP_UMEM_PGOFF_COMPLETION_RING: libc::off_t = 0x180000000;
+
+const XDP_ZEROCOPY: u16 = 1 << 2;
+const XDP_USE_NEED_WAKEUP: u16 = 1 << 3;
+
+const XDP_RING_NEED_WAKEUP: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to create AF_XDP socket")]
+    Socket(#[source] io::Error),
+    #[error("failed to allocate UMEM")]
+    AllocateUmem(#[source] io::Error),
+    #[error("failed to register UMEM, check RLIMIT_MEMLOCK")]
+    RegisterUmem(#[source] io::Error),
+    #[error("failed to configure rings")]
+    ConfigureRings(#[source] io::Error),
+    #[error("failed to map rings")]
+    MapRings(#[source] io::Error),
+    #[error("failed to bind to queue {queue_id}")]
+    Bind {
+        queue_id: u32,
+        #[source]
+        err: io::Error,
+    },
+}
+
+#[repr(C)]
+struct XdpUmemReg {
+    addr: u64,
+    len: u64,
+    chunk_size: u32,
+    headroom: u32,
+    flags: u32,
+    tx_metadata_len: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct XdpRingOffset {
+    producer: u64,
+    consumer: u64,
+    desc: u64,
+    flags: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct XdpMmapOffsets {
+    rx: XdpRingOffset,
+    tx: XdpRingOffset,
+    fr: XdpRingOffset,
+    cr: XdpRingOffset,
+}
+
+#[repr(C)]
+struct SockaddrXdp {
+    family: u16,
+    flags: u16,
+    ifindex: u32,
+    queue_id: u32,
+    shared_umem_fd: u32,
+}
+
+/// A packet descriptor in the RX and TX rings.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct XdpDesc {
+    pub addr: u64,
+    pub len: u32,
+    pub options: u32,
+}
+
+/// Socket statistics, as reported by the kernel.
+#[repr(C)]
+#[derive(Debug, Default, inspect::Inspect)]
+pub struct XdpStatistics {
+    pub rx_dropped: u64,
+    pub rx_invalid_descs: u64,
+    pub tx_invalid_descs: u64,
+    pub rx_ring_full: u64,
+    pub rx_fill_ring_empty_descs: u64,
+    pub tx_ring_empty_descs: u64,
+}
+
+/// Returns the index of the interface named `name`.
+pub fn interface_index(name: &str) -> io::Result<u32> {
+    let name = std::ffi::CString::new(name).map_err(io::Error::other)?;
+    // SAFETY: passing a valid C string.
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    if index == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(index)
+}
+
+/// A memory mapping, unmapped on drop.
+struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+}
+
+// SAFETY: the mapping is just memory.
+unsafe impl Send for Mapping {}
+// SAFETY: the mapping is just memory.
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    fn new(
+        len: usize,
+        flags: libc::c_int,
+        fd: Option<BorrowedFd<'_>>,
+        offset: libc::off_t,
+    ) -> io::Result<Self> {
+        // SAFETY: creating a new mapping, which does not alias any existing
+        // memory.
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                flags,
+                fd.map_or(-1, |fd| fd.as_raw_fd()),
+                offset,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            ptr: ptr.cast(),
+            len,
+        })
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        // SAFETY: unmapping memory this object owns.
+        unsafe {
+            libc::munmap(self.ptr.cast(), self.len);
+        }
+    }
+}
+
+/// A single-producer, single-consumer ring shared with the kernel.
+struct Ring<T> {
+    mapping: Mapping,
+    producer: usize,
+    consumer: usize,
+    flags: usize,
+    desc: usize,
+    /// The local copy of the index this side owns (the producer index for
+    /// fill and TX rings, the consumer index for RX and completion rings).
+    index: u32,
+    _phantom: std::marker::PhantomData<T>,
+}
+
+impl<T: Copy> Ring<T> {
+    fn map(fd: BorrowedFd<'_>, offsets: &XdpRingOffset, pgoff: libc::off_t) -> io::Result<Self> {
+        let len = offsets.desc as usize + RING_SIZE as usize * size_of::<T>();
+        let mapping = Mapping::new(len, libc::MAP_SHARED | libc::MAP_POPULATE, Some(fd), pgoff)?;
+        Ok(Self {
+            mapping,
+            producer: offsets.producer as usize,
+            consumer: offsets.consumer as usize,
+            flags: offsets.flags as usize,
+            desc: offsets.desc as usize,
+            index: 0,
+            _phantom: std::marker::PhantomData,
+        })
+    }
+
+    fn atomic(&self, offset: usize) -> &AtomicU32 {
+        // SAFETY: the kernel reported this offset as an aligned u32 within
+        // the mapping, and it is only accessed atomically.
+        unsafe { &*self.mapping.ptr.add(offset).cast::<AtomicU32>() }
+    }
+
+    fn producer(&self) -> &AtomicU32 {
+        self.atomic(self.producer)
+    }
+
+    fn consumer(&self) -> &AtomicU32 {
+        self.atomic(self.consumer)
+    }
+
+    fn slot(&self, index: u32) -> *mut T {
+        let i = (index % RING_SIZE) as usize;
+        // SAFETY: the ring has RING_SIZE entries of T starting at `desc`.
+        unsafe { self.mapping.ptr.add(self.desc).cast::<T>().add(i) }
+    }
+
+    fn needs_wakeup(&self) -> bool {
+        self.atomic(self.flags).load(Ordering::Relaxed) & XDP_RING_NEED_WAKEUP != 0
+    }
+
+    /// Returns the number of entries that can be produced.
+    fn free(&self) -> u32 {
+        RING_SIZE
+            - self
+                .index
+                .wrapping_sub(self.consumer().load(Ordering::Acquire))
+    }
+
+    /// Writes an entry. The caller must have checked there is space with
+    /// [`Self::free`].
+    fn push(&mut self, value: T) {
+        // SAFETY: the slot is owned by the producer until it is submitted.
+        unsafe { self.slot(self.index).write_volatile(value) };
+        self.index = self.index.wrapping_add(1);
+    }
+
+    /// Makes the pushed entries visible to the kernel.
+    fn submit(&self) {
+        self.producer().store(self.index, Ordering::Release);
+    }
+
+    /// Returns the number of entries available to consume.
+    fn available(&self) -> u32 {
+        self.producer()
+            .load(Ordering::Acquire)
+            .wrapping_sub(self.index)
+    }
+
+    /// Reads the `n`th available entry.
+    fn peek(&self, n: u32) -> T {
+        // SAFETY: the slot is owned by the consumer until it is released.
+        unsafe { self.slot(self.index.wrapping_add(n)).read_volatile() }
+    }
+
+    /// Returns `n` consumed entries to the kernel.
+    fn release(&mut self, n: u32) {
+        self.index = self.index.wrapping_add(n);
+        self.consumer().store(self.index, Ordering::Release);
+    }
+}
+
+/// An AF_XDP socket bound to a NIC queue.
+pub struct XskSocket {
+    fd: OwnedFd,
+    umem: Mapping,
+    fill: Ring<u64>,
+    completion: Ring<u64>,
+    rx: Ring<XdpDesc>,
+    tx: Ring<XdpDesc>,
+    /// The free transmit frames.
+    tx_frames: Vec<u64>,
+}
+
+fn setsockopt<T>(fd: BorrowedFd<'_>, name: libc::c_int, value: &T) -> io::Result<()> {
+    // SAFETY: passing a valid pointer and length.
+    let r = unsafe {
+        libc::setsockopt(
+            fd.as_raw_fd(),
+            libc::SOL_XDP,
+            name,
+            std::ptr::from_ref(value).cast(),
+            size_of::<T>() as libc::socklen_t,
+        )
+    };
+    if r < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn getsockopt<T: Default>(fd: BorrowedFd<'_>, name: libc::c_int) -> io::Result<T> {
+    let mut value = T::default();
+    let mut len = size_of::<T>() as libc::socklen_t;
+    // SAFETY: passing a valid pointer and length.
+    let r = unsafe {
+        libc::getsockopt(
+            fd.as_raw_fd(),
+            libc::SOL_XDP,
+            name,
+            std::ptr::from_mut(&mut value).cast(),
+            &mut len,
+        )
+    };
+    if r < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(value)
+}
+
+impl XskSocket {
+    /// Creates a socket bound to queue `queue_id` of interface `ifindex`.
+    ///
+    /// If `require_zero_copy` is false, the kernel falls back to copy mode if
+    /// the driver does not support zero-copy.
+    pub fn new(ifindex: u32, queue_id: u32, require_zero_copy: bool) -> Result<Self, Error> {
+        // SAFETY: creating a new socket.
+        let fd = unsafe { libc::socket(libc::AF_XDP, libc::SOCK_RAW | libc::SOCK_CLOEXEC, 0) };
+        if fd < 0 {
+            return Err(Error::Socket(io::Error::last_os_error()));
+        }
+        // SAFETY: the socket is newly created and owned by nothing else.
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let umem = Mapping::new(
+            (FRAME_COUNT * FRAME_SIZE) as usize,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_POPULATE,
+            None,
+            0,
+        )
+        .map_err(Error::AllocateUmem)?;
+
+        let reg = XdpUmemReg {
+            addr: umem.ptr as u64,
+            len: umem.len as u64,
+            chunk_size: FRAME_SIZE,
+            headroom: 0,
+            flags: 0,
+            tx_metadata_len: 0,
+        };
+        setsockopt(fd.as_fd(), XDP_UMEM_REG, &reg).map_err(Error::RegisterUmem)?;
+        for opt in [
+            XDP_UMEM_FILL_RING,
+            XDP_UMEM_COMPLETION_RING,
+            XDP_RX_RING,
+            XDP_TX_RING,
+        ] {
+            setsockopt(fd.as_fd(), opt, &RING_SIZE).map_err(Error::ConfigureRings)?;
+        }
+
+        let offsets: XdpMmapOffsets =
+            getsockopt(fd.as_fd(), XDP_MMAP_OFFSETS).map_err(Error::MapRings)?;
+        let fill = Ring::map(fd.as_fd(), &offsets.fr, XDP_UMEM_PGOFF_FILL_RING)
+            .map_err(Error::MapRings)?;
+        let completion = Ring::map(fd.as_fd(), &offsets.cr, XDP_UMEM_PGOFF_COMPLETION_RING)
+            .map_err(Error::MapRings)?;
+        let rx = Ring::map(fd.as_fd(), &offsets.rx, XDP_PGOFF_RX_RING).map_err(Error::MapRings)?;
+        let tx = Ring::map(fd.as_fd(), &offsets.tx, XDP_PGOFF_TX_RING).map_err(Error::MapRings)?;
+
+        let mut this = Self {
+            fd,
+            umem,
+            fill,
+            completion,
+            rx,
+            tx,
+            tx_frames: (RING_SIZE..FRAME_COUNT)
+                .map(|i| (i * FRAME_SIZE).into())
+                .collect(),
+        };
+
+        // Give the receive frames to the kernel before binding so that
+        // packets can be received immediately.
+        for i in 0..RING_SIZE {
+            this.fill.push((i * FRAME_SIZE).into());
+        }
+        this.fill.submit();
+
+        let mut flags = XDP_USE_NEED_WAKEUP;
+        if require_zero_copy {
+            flags |= XDP_ZEROCOPY;
+        }
+        let addr = SockaddrXdp {
+            family: libc::AF_XDP as u16,
+            flags,
+            ifindex,
+            queue_id,
+            shared_umem_fd: 0,
+        };
+        // SAFETY: passing a valid address and length.
+        let r = unsafe {
+            libc::bind(
+                this.fd.as_raw_fd(),
+                std::ptr::from_ref(&addr).cast(),
+                size_of::<SockaddrXdp>() as libc::socklen_t,
+            )
+        };
+        if r < 0 {
+            return Err(Error::Bind {
+                queue_id,
+                err: io::Error::last_os_error(),
+            });
+        }
+        Ok(this)
+    }
+
+    /// Calls `f` with each received packet, up to `max` packets, and returns
+    /// the frames to the kernel afterwards. Returns the number of packets.
+    pub fn receive(&mut self, max: usize, mut f: impl FnMut(&[u8])) -> usize {
+        let n = self.rx.available().min(max as u32);
+        for i in 0..n {
+            let desc = self.rx.peek(i);
+            let data = self.frame(desc.addr, desc.len);
+            // SAFETY: the kernel has handed this frame to user mode until it
+            // is returned via the fill ring, so nothing else is writing it.
+            f(unsafe { &*data });
+            // The fill ring is as large as the receive half of the UMEM, so
+            // there is always room.
+            self.fill
+                .push(desc.addr - desc.addr % u64::from(FRAME_SIZE));
+        }
+        if n != 0 {
+            self.rx.release(n);
+            self.fill.submit();
+            if self.fill.needs_wakeup() {
+                self.wakeup_rx();
+            }
+        }
+        n as usize
+    }
+
+    /// Queues a packet of `len` bytes for transmit, calling `f` to fill in
+    /// the packet data.
+    ///
+    /// Returns `Ok(false)` without calling `f` if the packet is too large or
+    /// there is no room to transmit it. The packet is not sent until
+    /// [`Self::flush_tx`] is called.
+    pub fn transmit<E>(
+        &mut self,
+        len: usize,
+        f: impl FnOnce(&mut [u8]) -> Result<(), E>,
+    ) -> Result<bool, E> {
+        if len > FRAME_SIZE as usize {
+            return Ok(false);
+        }
+        if self.tx_frames.is_empty() {
+            self.reclaim_tx();
+        }
+        if self.tx.free() == 0 {
+            return Ok(false);
+        }
+        let Some(addr) = self.tx_frames.pop() else {
+            return Ok(false);
+        };
+        let data = self.frame(addr, len as u32);
+        // SAFETY: the frame is on the free list, so the kernel is not
+        // accessing it.
+        if let Err(err) = f(unsafe { &mut *data }) {
+            self.tx_frames.push(addr);
+            return Err(err);
+        }
+        self.tx.push(XdpDesc {
+            addr,
+            len: len as u32,
+            options: 0,
+        });
+        Ok(true)
+    }
+
+    /// Submits the queued transmits to the kernel.
+    pub fn flush_tx(&mut self) {
+        self.tx.submit();
+        if self.tx.needs_wakeup() {
+            // SAFETY: sending nothing, which just kicks the kernel to process
+            // the TX ring.
+            let r = unsafe {
+                libc::sendto(
+                    self.fd.as_raw_fd(),
+                    std::ptr::null(),
+                    0,
+                    libc::MSG_DONTWAIT,
+                    std::ptr::null(),
+                    0,
+                )
+            };
+            if r < 0 {
+                let err = io::Error::last_os_error();
+                // These mean the kernel is already busy with the ring and
+                // will process the new entries later.
+                if !matches!(
+                    err.raw_os_error(),
+                    Some(libc::EAGAIN | libc::EBUSY | libc::ENOBUFS | libc::ENETDOWN)
+                ) {
+                    tracing::warn!(error = &err as &dyn std::error::Error, "xsk tx kick failed");
+                }
+            }
+        }
+    }
+
+    /// Returns completed transmit frames to the free list.
+    pub fn reclaim_tx(&mut self) {
+        let n = self.completion.available();
+        for i in 0..n {
+            self.tx_frames.push(self.completion.peek(i));
+        }
+        if n != 0 {
+            self.completion.release(n);
+        }
+    }
+
+    /// Returns the socket statistics.
+    pub fn statistics(&self) -> io::Result<XdpStatistics> {
+        getsockopt(self.fd.as_fd(), XDP_STATISTICS)
+    }
+
+    fn wakeup_rx(&self) {
+        // SAFETY: receiving nothing, which just kicks the kernel to refill
+        // the driver from the fill ring.
+        unsafe {
+            libc::recvfrom(
+                self.fd.as_raw_fd(),
+                std::ptr::null_mut(),
+                0,
+                libc::MSG_DONTWAIT,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            );
+        }
+    }
+
+    fn frame(&self, addr: u64, len: u32) -> *mut [u8] {
+        assert!(addr + u64::from(len) <= self.umem.len as u64);
+        // SAFETY: the range is within the UMEM mapping.
+        let ptr = unsafe { self.umem.ptr.add(addr as usize) };
+        std::ptr::slice_from_raw_parts_mut(ptr, len as usize)
+    }
+}
+
+impl AsRawFd for XskSocket {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        self.fd.as_raw_fd()
+    }
+}
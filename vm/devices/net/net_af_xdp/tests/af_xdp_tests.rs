@@ -0,0 +1,272 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Integration tests for net_af_xdp over a veth pair, using a custom test
+//! harness (libtest-mimic) so that no threads are spawned before we call
+//! `unshare`.
+
+// AF_XDP and network namespaces are Linux-only. Provide a no-op main for other
+// targets since this binary uses `harness = false`.
+#[cfg(not(target_os = "linux"))]
+fn main() {}
+
+#[cfg(target_os = "linux")]
+fn main() {
+    af_xdp_tests::main();
+}
+
+#[cfg(target_os = "linux")]
+mod af_xdp_tests {
+    // UNSAFETY: Calling libc functions for namespace setup.
+    #![expect(unsafe_code)]
+
+    use libtest_mimic::Arguments;
+    use libtest_mimic::Trial;
+    use net_af_xdp::AfXdpEndpoint;
+    use net_backend::Endpoint;
+    use net_backend::Queue;
+    use net_backend::QueueConfig;
+    use net_backend::RxId;
+    use net_backend::TxId;
+    use net_backend::TxMetadata;
+    use net_backend::TxSegment;
+    use net_backend::TxSegmentType;
+    use pal_async::DefaultDriver;
+    use pal_async::timer::PolledTimer;
+    use std::future::poll_fn;
+    use std::process::Command;
+    use std::time::Duration;
+
+    /// Enter an isolated network and mount namespace, with sysfs remounted so
+    /// that `/sys/class/net` shows the new namespace's interfaces. Must be
+    /// called while the process is still single-threaded.
+    ///
+    /// Unlike the TAP tests, this cannot use a user namespace, since loading
+    /// XDP programs requires `CAP_BPF` and `CAP_NET_ADMIN` in the initial user
+    /// namespace. Returns `Err` with a message if the process lacks them.
+    fn enter_test_netns() -> Result<(), String> {
+        // SAFETY: unshare() only affects the calling process's namespace
+        // membership.
+        let ret = unsafe { libc::unshare(libc::CLONE_NEWNET | libc::CLONE_NEWNS) };
+        if ret != 0 {
+            let err = std::io::Error::last_os_error();
+            if err.raw_os_error() == Some(libc::EPERM) {
+                return Err(format!("unshare(CLONE_NEWNET | CLONE_NEWNS) failed: {err}"));
+            }
+            panic!("unshare(CLONE_NEWNET | CLONE_NEWNS) failed unexpectedly: {err}");
+        }
+
+        // Keep the sysfs mount below from propagating to the host.
+        //
+        // SAFETY: passing valid nul-terminated strings and null data.
+        let ret = unsafe {
+            libc::mount(
+                std::ptr::null(),
+                c"/".as_ptr(),
+                std::ptr::null(),
+                libc::MS_REC | libc::MS_PRIVATE,
+                std::ptr::null(),
+            )
+        };
+        assert_eq!(ret, 0, "mount /: {}", std::io::Error::last_os_error());
+        // SAFETY: passing valid nul-terminated strings and null data.
+        let ret = unsafe {
+            libc::mount(
+                c"sysfs".as_ptr(),
+                c"/sys".as_ptr(),
+                c"sysfs".as_ptr(),
+                0,
+                std::ptr::null(),
+            )
+        };
+        assert_eq!(ret, 0, "mount /sys: {}", std::io::Error::last_os_error());
+
+        // Each test gets its own veth pair, since only one XDP program can be
+        // attached to an interface at a time.
+        for (a, b) in [("xdp0", "xdp1"), ("xdp2", "xdp3")] {
+            ip(&["link", "add", a, "type", "veth", "peer", "name", b])
+                .map_err(|e| format!("failed to create veth pair: {e}"))?;
+            for name in [a, b] {
+                ip(&["link", "set", name, "up"])?;
+            }
+        }
+
+        // Verify that XDP programs can actually be loaded. Some environments
+        // run as root but restrict BPF.
+        if let Err(e) = AfXdpEndpoint::new("xdp0", false) {
+            return Err(format!("AF_XDP not available: {e:#}"));
+        }
+
+        Ok(())
+    }
+
+    /// Runs `ip` with `args`.
+    fn ip(args: &[&str]) -> Result<(), String> {
+        let output = Command::new("ip")
+            .args(args)
+            .output()
+            .map_err(|e| format!("failed to run ip: {e}"))?;
+        if !output.status.success() {
+            return Err(format!(
+                "ip {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Create a buffer pool and guest memory following the pattern from net_backend tests.
+    fn make_pool() -> (net_backend::tests::Bufs, guestmem::GuestMemory) {
+        let layout = net_backend::tests::test_layout();
+        let mem = guestmem::GuestMemory::allocate(layout.end_of_ram() as usize);
+        let pool = net_backend::tests::Bufs::new(mem.clone());
+        (pool, mem)
+    }
+
+    /// Wrap an async test function into a [`Trial`] that runs on a
+    /// single-threaded event loop.
+    fn async_trial(name: &str, f: impl AsyncFnOnce(DefaultDriver) + Send + 'static) -> Trial {
+        Trial::test(name, move || {
+            pal_async::DefaultPool::run_with(f);
+            Ok(())
+        })
+    }
+
+    /// Create an endpoint on `interface` and get its single queue.
+    async fn new_queue(driver: &DefaultDriver, interface: &str) -> (AfXdpEndpoint, Box<dyn Queue>) {
+        let mut endpoint = AfXdpEndpoint::new(interface, false).unwrap();
+        let config = vec![QueueConfig {
+            driver: Box::new(driver.clone()),
+        }];
+        let mut queues = Vec::new();
+        endpoint
+            .get_queues(config, None, &mut queues)
+            .await
+            .unwrap();
+        assert_eq!(queues.len(), 1);
+        (endpoint, queues.pop().unwrap())
+    }
+
+    // ---------------------------------------------------------------------------
+    // Test implementations
+    // ---------------------------------------------------------------------------
+
+    /// Validates that asking for more queues than the interface has fails
+    /// instead of panicking.
+    async fn test_af_xdp_too_many_queues(driver: DefaultDriver) {
+        let mut endpoint = AfXdpEndpoint::new("xdp2", false).unwrap();
+        // veth interfaces have a single queue by default.
+        assert_eq!(endpoint.multiqueue_support().max_queues, 1);
+        let config = (0..2)
+            .map(|_| QueueConfig {
+                driver: Box::new(driver.clone()),
+            })
+            .collect();
+        let mut queues = Vec::new();
+        let err = endpoint
+            .get_queues(config, None, &mut queues)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("2 queues requested"), "{err:#}");
+    }
+
+    /// Validates that a frame transmitted on one end of the veth pair is
+    /// received on the other.
+    async fn test_af_xdp_veth_loopback(driver: DefaultDriver) {
+        let (_rx_endpoint, mut rx_queue) = new_queue(&driver, "xdp0").await;
+        let (_tx_endpoint, mut tx_queue) = new_queue(&driver, "xdp1").await;
+
+        let (mut pool, mem) = make_pool();
+        rx_queue.rx_avail(&mut pool, &(1..64).map(RxId).collect::<Vec<_>>());
+
+        // Build a minimal Ethernet frame with a local experimental ethertype,
+        // so that it cannot be confused with traffic the kernel generates.
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff]); // dst: broadcast
+        frame.extend_from_slice(&[0x02, 0x00, 0x00, 0x00, 0x00, 0x01]); // src
+        frame.extend_from_slice(&[0x88, 0xb5]); // ethertype: local experimental
+        frame.extend_from_slice(&[0xa5; 46]); // payload (minimum Ethernet frame size)
+        let frame_len = frame.len() as u32;
+
+        // Write the frame into guest memory past the receive buffers, split
+        // across two segments.
+        let tx_gpa = 64 * 2048;
+        mem.write_at(tx_gpa, &frame).unwrap();
+        let segments = [
+            TxSegment {
+                ty: TxSegmentType::Head(TxMetadata {
+                    id: TxId(0),
+                    segment_count: 2,
+                    len: frame_len,
+                    ..Default::default()
+                }),
+                gpa: tx_gpa,
+                len: 14,
+            },
+            TxSegment {
+                ty: TxSegmentType::Tail,
+                gpa: tx_gpa + 14,
+                len: frame_len - 14,
+            },
+        ];
+
+        // Retransmit periodically in case the receive side was not ready.
+        let mut timer = PolledTimer::new(&driver);
+        let mut packets = [RxId(0); 64];
+        for _ in 0..50 {
+            let (completed, count) = tx_queue.tx_avail(&mut pool, &segments).unwrap();
+            assert!(completed, "tx should complete synchronously");
+            assert_eq!(count, 2);
+
+            let ready =
+                futures::FutureExt::now_or_never(poll_fn(|cx| rx_queue.poll_ready(cx, &mut pool)));
+            if ready.is_some() {
+                let n = rx_queue.rx_poll(&mut pool, &mut packets).unwrap();
+                for &rx_id in &packets[..n] {
+                    let len = pool.rx_metadata(rx_id).unwrap().len;
+                    let mut buf = vec![0; len];
+                    mem.read_at(rx_id.0 as u64 * 2048, &mut buf).unwrap();
+                    if buf == frame {
+                        return;
+                    }
+                }
+                rx_queue.rx_avail(&mut pool, &packets[..n]);
+            }
+            timer.sleep(Duration::from_millis(20)).await;
+        }
+        panic!("frame was not received");
+    }
+
+    // ---------------------------------------------------------------------------
+    // Harness
+    // ---------------------------------------------------------------------------
+
+    pub(crate) fn main() {
+        let args = Arguments::from_args();
+
+        // Only enter the namespace when actually running tests—not when
+        // nextest calls `--list` to discover them.
+        let ns_available = if args.list {
+            true // assume available; will be checked on actual run
+        } else {
+            match enter_test_netns() {
+                Ok(()) => true,
+                Err(msg) => {
+                    eprintln!("note: skipping AF_XDP tests — {msg}");
+                    false
+                }
+            }
+        };
+
+        let ignored = !ns_available;
+        let tests = [
+            async_trial("af_xdp_too_many_queues", test_af_xdp_too_many_queues),
+            async_trial("af_xdp_veth_loopback", test_af_xdp_veth_loopback),
+        ]
+        .map(|t| t.with_ignored_flag(ignored))
+        .into();
+
+        libtest_mimic::run(&args, tests).exit();
+    }
+} // mod af_xdp_tests
@@ -85,8 +85,18 @@ pub struct HostPortConfig {
     pub struct ConsommeHandle {
         /// The CIDR of the network to use.
         pub cidr: Option<String>,
+        /// The IPv4 address to assign the guest via DHCP, or `None` to use the
+        /// second host address of the network.
+        pub guest_ip: Option<std::net::Ipv4Addr>,
         /// Ports to forward from the host into the guest.
         pub ports: Vec<HostPortConfig>,
+        /// DNS servers to forward guest DNS traffic to. If empty, the
+        /// built-in DNS resolver is used.
+        pub nameservers: Vec<HostIpAddress>,
+        /// A host directory to serve to the guest over TFTP, for network boot.
+        pub tftp_root: Option<String>,
+        /// The boot file name to advertise to the guest via DHCP.
+        pub boot_file: Option<String>,
     }
 
     impl ResourceId<NetEndpointHandleKind> for ConsommeHandle {
@@ -141,3 +151,25 @@ impl ResourceId<NetEndpointHandleKind> for TapHandle {
         const ID: &'static str = "tap";
     }
 }
+
+/// Linux AF_XDP backend.
+#[cfg(target_os = "linux")]
+pub mod af_xdp {
+    use mesh::MeshPayload;
+    use vm_resource::ResourceId;
+    use vm_resource::kind::NetEndpointHandleKind;
+
+    /// A handle to a host interface to attach to via AF_XDP sockets.
+    #[derive(MeshPayload)]
+    pub struct AfXdpHandle {
+        /// The name of the host interface.
+        pub interface: String,
+        /// Fail if the interface's driver does not support zero-copy AF_XDP,
+        /// rather than falling back to copy mode.
+        pub require_zero_copy: bool,
+    }
+
+    impl ResourceId<NetEndpointHandleKind> for AfXdpHandle {
+        const ID: &'static str = "af_xdp";
+    }
+}
@@ -10,6 +10,7 @@
 use smoltcp::phy::ChecksumCapabilities;
 use smoltcp::wire::DHCP_MAX_DNS_SERVER_COUNT;
 use smoltcp::wire::DhcpMessageType;
+use smoltcp::wire::DhcpOption;
 use smoltcp::wire::DhcpPacket;
 use smoltcp::wire::DhcpRepr;
 use smoltcp::wire::EthernetFrame;
@@ -26,6 +27,9 @@
 pub const DHCP_SERVER: u16 = 67;
 pub const DHCP_CLIENT: u16 = 68;
 
+const DHCP_OPTION_TFTP_SERVER_NAME: u8 = 66;
+const DHCP_OPTION_BOOT_FILE_NAME: u8 = 67;
+
 impl<T: Client> Access<'_, T> {
     pub(crate) fn handle_dhcp(&mut self, payload: &[u8]) -> Result<(), DropReason> {
         let dhcp_packet = DhcpPacket::new_checked(payload)?;
@@ -63,6 +67,26 @@ pub(crate) fn handle_dhcp(&mut self, payload: &[u8]) -> Result<(), DropReason> {
                 .take(DHCP_MAX_DNS_SERVER_COUNT),
         );
 
+        // Point network boot clients at the built-in TFTP server.
+        let tftp_server_name = self.inner.state.params.gateway_ip.to_string();
+        let boot_options;
+        let additional_options: &[DhcpOption<'_>] =
+            if let Some(boot_file) = &self.inner.state.params.boot_file {
+                boot_options = [
+                    DhcpOption {
+                        kind: DHCP_OPTION_TFTP_SERVER_NAME,
+                        data: tftp_server_name.as_bytes(),
+                    },
+                    DhcpOption {
+                        kind: DHCP_OPTION_BOOT_FILE_NAME,
+                        data: boot_file.as_bytes(),
+                    },
+                ];
+                &boot_options
+            } else {
+                &[]
+            };
+
         let resp_dhcp = if let Some(your_ip) = your_ip {
             DhcpRepr {
                 message_type,
@@ -85,7 +109,7 @@ pub(crate) fn handle_dhcp(&mut self, payload: &[u8]) -> Result<(), DropReason> {
                 lease_duration: Some(86400),
                 renew_duration: None,
                 rebind_duration: None,
-                additional_options: &[],
+                additional_options,
             }
         } else {
             DhcpRepr {
@@ -1,19 +1,22 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT License.
 
-//! DHCPv6 (Dynamic Host Configuration Protocol for IPv6) implementation for
-//! IPv6 SLAAC (Stateless Address Autoconfiguration)
+//! DHCPv6 (Dynamic Host Configuration Protocol for IPv6) implementation
 //!
 //! This module implements a subset of RFC 8415 (DHCPv6) to compliment our NDP
-//! implementation for SLAAC.  
-//! We only support the Information Request message type, to configure DNS
-//! servers for clients that have autoconfigured their own addresses via SLAAC.
+//! implementation for SLAAC. Information Request messages configure DNS
+//! servers for clients that have autoconfigured their own addresses via SLAAC,
+//! and stateful clients are leased a single non-temporary address (IA_NA) from
+//! the advertised prefix. Prefix delegation and temporary addresses are not
+//! supported.
 
 use super::Access;
 use super::Client;
 use super::DropReason;
 use crate::ChecksumState;
 use crate::MIN_MTU;
+use crate::is_same_ipv6_subnet;
+use crate::ndp::NETWORK_PREFIX_BASE;
 use smoltcp::phy::ChecksumCapabilities;
 use smoltcp::wire::EthernetFrame;
 use smoltcp::wire::EthernetProtocol;
@@ -41,12 +44,32 @@
 pub const DHCPV6_SERVER: u16 = 547;
 pub const DHCPV6_CLIENT: u16 = 546;
 
+/// The interface identifier of the address leased to the guest. This is
+/// distinct from any EUI-64 identifier, so the leased address does not
+/// collide with a SLAAC address the guest configures from the same prefix.
+const LEASE_INTERFACE_ID: u128 = 0x100;
+
+// Lease lifetimes in seconds, matching the lifetimes of the prefix advertised
+// for SLAAC. T1 and T2 are the RFC 8415 recommended 0.5 and 0.8 times the
+// preferred lifetime.
+const LEASE_PREFERRED_LIFETIME: u32 = 604800;
+const LEASE_VALID_LIFETIME: u32 = 2592000;
+const LEASE_T1: u32 = LEASE_PREFERRED_LIFETIME / 2;
+const LEASE_T2: u32 = LEASE_PREFERRED_LIFETIME / 5 * 4;
+
 open_enum::open_enum! {
     #[derive(IntoBytes, FromBytes, Immutable, KnownLayout)]
     /// DHCPv6 message types (RFC 8415)
     pub enum MessageType: u8 {
-        INFORMATION_REQUEST = 11,
+        SOLICIT = 1,
+        ADVERTISE = 2,
+        REQUEST = 3,
+        CONFIRM = 4,
+        RENEW = 5,
+        REBIND = 6,
         REPLY = 7,
+        RELEASE = 8,
+        INFORMATION_REQUEST = 11,
     }
 }
 
@@ -56,10 +79,40 @@ pub enum MessageType: u8 {
     pub enum OptionCode: u16 {
         CLIENT_ID = 1,
         SERVER_ID = 2,
+        IA_NA = 3,
+        IA_ADDR = 5,
+        STATUS_CODE = 13,
+        RAPID_COMMIT = 14,
         DNS_SERVERS = 23,
     }
 }
 
+open_enum::open_enum! {
+    /// DHCPv6 status codes (RFC 8415)
+    pub enum StatusCode: u16 {
+        SUCCESS = 0,
+        NO_ADDRS_AVAIL = 2,
+        NOT_ON_LINK = 4,
+    }
+}
+
+/// Identity Association for Non-temporary Addresses option
+#[derive(Debug, Clone, PartialEq)]
+struct IaNa {
+    iaid: u32,
+    t1: u32,
+    t2: u32,
+    addresses: Vec<IaAddress>,
+}
+
+/// IA Address option, nested in an IA_NA option
+#[derive(Debug, Clone, PartialEq)]
+struct IaAddress {
+    addr: std::net::Ipv6Addr,
+    preferred_lifetime: u32,
+    valid_lifetime: u32,
+}
+
 /// DHCPv6 message
 struct Message {
     msg_type: MessageType,
@@ -67,6 +120,9 @@ struct Message {
     client_id: Option<Vec<u8>>,
     server_id: Option<Vec<u8>>,
     dns_servers: Option<Vec<std::net::Ipv6Addr>>,
+    ia_na: Option<IaNa>,
+    status_code: Option<StatusCode>,
+    rapid_commit: bool,
 }
 
 #[derive(Debug, Error)]
@@ -77,6 +133,10 @@ enum DhcpV6Error {
     MalformedOption(usize),
     #[error("invalid DNS Server option length {0:#x}")]
     InvalidDnsServerOption(usize),
+    #[error("invalid IA_NA option length {0:#x}")]
+    InvalidIaNaOption(usize),
+    #[error("invalid IA Address option length {0:#x}")]
+    InvalidIaAddressOption(usize),
 }
 
 #[repr(C)]
@@ -101,22 +161,22 @@ fn new(msg_type: MessageType) -> Self {
             client_id: None,
             server_id: None,
             dns_servers: None,
+            ia_na: None,
+            status_code: None,
+            rapid_commit: false,
         }
     }
 
-    fn decode(message_bytes: &[u8]) -> Result<Self, DhcpV6Error> {
-        let (header, mut unparsed_bytes) = Ref::<_, DhcpV6Header>::from_prefix(message_bytes)
-            .map_err(|_| DhcpV6Error::MessageTooShort(message_bytes.len()))?;
-
-        let msg_type = MessageType(header.msg_type);
-        let transaction_id = header.transaction_id;
-
-        let mut client_id = None;
-        let mut server_id = None;
-        let mut dns_servers = None;
-
+    /// Calls `f` with the code and value of each option in `bytes`, which
+    /// starts at `offset` in the message.
+    fn parse_options(
+        bytes: &[u8],
+        offset: usize,
+        mut f: impl FnMut(OptionCode, &[u8]) -> Result<(), DhcpV6Error>,
+    ) -> Result<(), DhcpV6Error> {
+        let mut unparsed_bytes = bytes;
         while unparsed_bytes.len() >= size_of::<DhcpV6Option>() {
-            let option_offset = message_bytes.len() - unparsed_bytes.len();
+            let option_offset = offset + bytes.len() - unparsed_bytes.len();
             let (option_header, after_option_header) =
                 Ref::<_, DhcpV6Option>::from_prefix(unparsed_bytes)
                     .map_err(|_| DhcpV6Error::MalformedOption(option_offset))?;
@@ -126,14 +186,67 @@ fn decode(message_bytes: &[u8]) -> Result<Self, DhcpV6Error> {
 
             if option_len > after_option_header.len() {
                 return Err(DhcpV6Error::MalformedOption(
-                    message_bytes.len() - after_option_header.len(),
+                    offset + bytes.len() - after_option_header.len(),
                 ));
             }
 
             let option_value = &after_option_header[..option_len];
             unparsed_bytes = &after_option_header[option_len..];
+            f(OptionCode(option_code), option_value)?;
+        }
+        Ok(())
+    }
+
+    fn decode_ia_na(option_value: &[u8]) -> Result<IaNa, DhcpV6Error> {
+        let invalid = || DhcpV6Error::InvalidIaNaOption(option_value.len());
+        let (fields, options) = option_value.split_at_checked(12).ok_or_else(invalid)?;
+        let field = |i: usize| u32::from_be_bytes(fields[i * 4..][..4].try_into().unwrap());
+        let mut addresses = Vec::new();
+        Self::parse_options(options, 12, |code, value| {
+            if code == OptionCode::IA_ADDR {
+                // The address and lifetimes may be followed by IA Address
+                // options, such as a status code, which are ignored.
+                let value = value
+                    .get(..24)
+                    .ok_or(DhcpV6Error::InvalidIaAddressOption(value.len()))?;
+                addresses.push(IaAddress {
+                    addr: std::net::Ipv6Addr::from(<[u8; 16]>::try_from(&value[..16]).unwrap()),
+                    preferred_lifetime: u32::from_be_bytes(value[16..20].try_into().unwrap()),
+                    valid_lifetime: u32::from_be_bytes(value[20..24].try_into().unwrap()),
+                });
+            }
+            Ok(())
+        })
+        .map_err(|e| match e {
+            DhcpV6Error::MalformedOption(_) => invalid(),
+            e => e,
+        })?;
+        Ok(IaNa {
+            iaid: field(0),
+            t1: field(1),
+            t2: field(2),
+            addresses,
+        })
+    }
+
+    fn decode(message_bytes: &[u8]) -> Result<Self, DhcpV6Error> {
+        let (header, options) = Ref::<_, DhcpV6Header>::from_prefix(message_bytes)
+            .map_err(|_| DhcpV6Error::MessageTooShort(message_bytes.len()))?;
+
+        let msg_type = MessageType(header.msg_type);
+        let transaction_id = header.transaction_id;
 
-            match OptionCode(option_code) {
+        let mut client_id = None;
+        let mut server_id = None;
+        let mut dns_servers = None;
+        let mut ia_na = None;
+        let mut status_code = None;
+        let mut rapid_commit = false;
+
+        let options_offset = size_of::<DhcpV6Header>();
+        Self::parse_options(options, options_offset, |option_code, option_value| {
+            let option_len = option_value.len();
+            match option_code {
                 OptionCode::CLIENT_ID => {
                     client_id = Some(option_value.to_vec());
                 }
@@ -153,11 +266,28 @@ fn decode(message_bytes: &[u8]) -> Result<Self, DhcpV6Error> {
                     }
                     dns_servers = Some(servers);
                 }
+                OptionCode::IA_NA => {
+                    // Only the first IA_NA is served; a client asking for
+                    // several addresses gets one.
+                    if ia_na.is_none() {
+                        ia_na = Some(Self::decode_ia_na(option_value)?);
+                    }
+                }
+                OptionCode::STATUS_CODE => {
+                    if let Some(code) = option_value.get(..2) {
+                        status_code =
+                            Some(StatusCode(u16::from_be_bytes(code.try_into().unwrap())));
+                    }
+                }
+                OptionCode::RAPID_COMMIT => {
+                    rapid_commit = true;
+                }
                 _ => {
                     // Skip unknown options
                 }
             }
-        }
+            Ok(())
+        })?;
 
         Ok(Self {
             msg_type,
@@ -165,6 +295,9 @@ fn decode(message_bytes: &[u8]) -> Result<Self, DhcpV6Error> {
             client_id,
             server_id,
             dns_servers,
+            ia_na,
+            status_code,
+            rapid_commit,
         })
     }
 
@@ -177,15 +310,37 @@ fn encode(&self) -> Vec<u8> {
 
         // Encode options
         if let Some(data) = &self.client_id {
-            buffer.extend_from_slice(&OptionCode::CLIENT_ID.0.to_be_bytes());
-            buffer.extend_from_slice(&(data.len() as u16).to_be_bytes());
-            buffer.extend_from_slice(data);
+            push_option(&mut buffer, OptionCode::CLIENT_ID, data);
         }
 
         if let Some(data) = &self.server_id {
-            buffer.extend_from_slice(&OptionCode::SERVER_ID.0.to_be_bytes());
-            buffer.extend_from_slice(&(data.len() as u16).to_be_bytes());
-            buffer.extend_from_slice(data);
+            push_option(&mut buffer, OptionCode::SERVER_ID, data);
+        }
+
+        if let Some(ia_na) = &self.ia_na {
+            let mut data = Vec::new();
+            data.extend_from_slice(&ia_na.iaid.to_be_bytes());
+            data.extend_from_slice(&ia_na.t1.to_be_bytes());
+            data.extend_from_slice(&ia_na.t2.to_be_bytes());
+            for address in &ia_na.addresses {
+                let mut addr_data = address.addr.octets().to_vec();
+                addr_data.extend_from_slice(&address.preferred_lifetime.to_be_bytes());
+                addr_data.extend_from_slice(&address.valid_lifetime.to_be_bytes());
+                push_option(&mut data, OptionCode::IA_ADDR, &addr_data);
+            }
+            push_option(&mut buffer, OptionCode::IA_NA, &data);
+        }
+
+        if let Some(status_code) = self.status_code {
+            push_option(
+                &mut buffer,
+                OptionCode::STATUS_CODE,
+                &status_code.0.to_be_bytes(),
+            );
+        }
+
+        if self.rapid_commit {
+            push_option(&mut buffer, OptionCode::RAPID_COMMIT, &[]);
         }
 
         if let Some(servers) = &self.dns_servers {
@@ -200,6 +355,12 @@ fn encode(&self) -> Vec<u8> {
     }
 }
 
+fn push_option(buffer: &mut Vec<u8>, code: OptionCode, data: &[u8]) {
+    buffer.extend_from_slice(&code.0.to_be_bytes());
+    buffer.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    buffer.extend_from_slice(data);
+}
+
 impl<T: Client> Access<'_, T> {
     pub(crate) fn handle_dhcpv6(
         &mut self,
@@ -212,87 +373,295 @@ pub(crate) fn handle_dhcpv6(
             DropReason::MalformedPacket
         })?;
 
-        match msg.msg_type {
-            MessageType::INFORMATION_REQUEST => {
-                // Build DHCPv6 Reply response
-                let mut reply = Message::new(MessageType::REPLY);
-                reply.transaction_id = msg.transaction_id;
-
-                // Add Client Identifier option (echo back from the InformationRequest)
-                reply.client_id = msg.client_id.clone();
+        let reply = match msg.msg_type {
+            MessageType::INFORMATION_REQUEST => self.dhcpv6_reply(MessageType::REPLY, &msg),
+            MessageType::SOLICIT => {
+                // RFC 8415 Section 18.3.1: with Rapid Commit, skip the
+                // Advertise and commit the lease immediately.
+                let mut reply = if msg.rapid_commit {
+                    let mut reply = self.dhcpv6_reply(MessageType::REPLY, &msg);
+                    reply.rapid_commit = true;
+                    reply
+                } else {
+                    self.dhcpv6_reply(MessageType::ADVERTISE, &msg)
+                };
+                self.dhcpv6_lease(&msg, &mut reply);
+                reply
+            }
+            MessageType::REQUEST | MessageType::RENEW | MessageType::REBIND => {
+                // Request and Renew are addressed to a specific server.
+                if msg.msg_type != MessageType::REBIND && !self.dhcpv6_is_our_server_id(&msg) {
+                    return Ok(());
+                }
+                let mut reply = self.dhcpv6_reply(MessageType::REPLY, &msg);
+                self.dhcpv6_lease(&msg, &mut reply);
+                reply
+            }
+            MessageType::CONFIRM => {
+                // RFC 8415 Section 18.3.3: report whether the client's
+                // addresses are still appropriate for the link.
+                let lease_address = self.dhcpv6_lease_address();
+                let prefix_len = self.inner.state.params.prefix_len_ipv6;
+                let on_link = msg
+                    .ia_na
+                    .iter()
+                    .flat_map(|ia| &ia.addresses)
+                    .all(|address| is_same_ipv6_subnet(address.addr, lease_address, prefix_len));
+                let mut reply = self.dhcpv6_reply(MessageType::REPLY, &msg);
+                reply.status_code = Some(if on_link {
+                    StatusCode::SUCCESS
+                } else {
+                    StatusCode::NOT_ON_LINK
+                });
+                reply
+            }
+            MessageType::RELEASE => {
+                if !self.dhcpv6_is_our_server_id(&msg) {
+                    return Ok(());
+                }
+                let lease_address = self.dhcpv6_lease_address();
+                let params = &mut self.inner.state.params;
+                if params.client_ip_ipv6_routable == Some(lease_address) {
+                    params.client_ip_ipv6_routable = None;
+                }
+                let mut reply = self.dhcpv6_reply(MessageType::REPLY, &msg);
+                reply.status_code = Some(StatusCode::SUCCESS);
+                reply
+            }
+            _ => return Err(DropReason::UnsupportedDhcpv6(msg.msg_type)),
+        };
 
-                // Add Server Identifier option
-                // Use DUID-LL (type 3: Link-layer address)
-                let gateway_mac = self.inner.state.params.gateway_mac_ipv6.0;
-                let mut duid_bytes = vec![0x00, 0x03, 0x00, 0x01]; // Type 3 (LL), Hardware type 1 (Ethernet)
-                duid_bytes.extend_from_slice(&gateway_mac);
-                reply.server_id = Some(duid_bytes);
+        self.send_dhcpv6(&reply, client_ip);
+        Ok(())
+    }
 
-                // Add DNS Name Server option if we have nameservers
-                let dns_servers = self.inner.state.params.filtered_ipv6_nameservers();
+    /// The server DUID, a DUID-LL (type 3: Link-layer address) built from the
+    /// gateway MAC.
+    fn dhcpv6_server_id(&self) -> Vec<u8> {
+        let gateway_mac = self.inner.state.params.gateway_mac_ipv6.0;
+        let mut duid_bytes = vec![0x00, 0x03, 0x00, 0x01]; // Type 3 (LL), Hardware type 1 (Ethernet)
+        duid_bytes.extend_from_slice(&gateway_mac);
+        duid_bytes
+    }
 
-                if !dns_servers.is_empty() {
-                    reply.dns_servers = Some(dns_servers);
-                }
+    fn dhcpv6_is_our_server_id(&self, msg: &Message) -> bool {
+        msg.server_id
+            .as_ref()
+            .is_some_and(|id| *id == self.dhcpv6_server_id())
+    }
 
-                let dhcpv6_buffer = reply.encode();
+    /// The address leased to stateful DHCPv6 clients, in the prefix that is
+    /// advertised for SLAAC.
+    fn dhcpv6_lease_address(&self) -> Ipv6Address {
+        let prefix = self
+            .compute_network_prefix(NETWORK_PREFIX_BASE, self.inner.state.params.prefix_len_ipv6);
+        Ipv6Address::from_bits(prefix.to_bits() | LEASE_INTERFACE_ID)
+    }
 
-                let resp_udp = UdpRepr {
-                    src_port: DHCPV6_SERVER,
-                    dst_port: DHCPV6_CLIENT,
-                };
+    /// Builds a reply to `msg` carrying the client and server identifiers and
+    /// the DNS servers.
+    fn dhcpv6_reply(&self, msg_type: MessageType, msg: &Message) -> Message {
+        let mut reply = Message::new(msg_type);
+        reply.transaction_id = msg.transaction_id;
 
-                let client_link_local = client_ip.unwrap_or(DHCPV6_ALL_AGENTS_MULTICAST);
-                let resp_ipv6 = Ipv6Repr {
-                    src_addr: self.inner.state.params.gateway_link_local_ipv6,
-                    dst_addr: client_link_local,
-                    next_header: IpProtocol::Udp,
-                    payload_len: resp_udp.header_len() + dhcpv6_buffer.len(),
-                    hop_limit: 64,
-                };
-                let resp_eth = EthernetRepr {
-                    src_addr: self.inner.state.params.gateway_mac_ipv6,
-                    dst_addr: self.inner.state.params.client_mac,
-                    ethertype: EthernetProtocol::Ipv6,
-                };
+        // Echo back the Client Identifier option
+        reply.client_id = msg.client_id.clone();
+        reply.server_id = Some(self.dhcpv6_server_id());
 
-                // Construct the complete packet
-                let mut buffer = [0; MIN_MTU];
-                let mut eth_frame = EthernetFrame::new_unchecked(&mut buffer);
-                resp_eth.emit(&mut eth_frame);
-
-                let mut ipv6_packet = Ipv6Packet::new_unchecked(eth_frame.payload_mut());
-                resp_ipv6.emit(&mut ipv6_packet);
-
-                let mut udp_packet = UdpPacket::new_unchecked(ipv6_packet.payload_mut());
-                resp_udp.emit(
-                    &mut udp_packet,
-                    &IpAddress::Ipv6(resp_ipv6.src_addr),
-                    &IpAddress::Ipv6(resp_ipv6.dst_addr),
-                    dhcpv6_buffer.len(),
-                    |udp_payload| {
-                        udp_payload[..dhcpv6_buffer.len()].copy_from_slice(&dhcpv6_buffer);
-                    },
-                    &ChecksumCapabilities::default(),
-                );
-
-                let total_len = resp_eth.buffer_len()
-                    + resp_ipv6.buffer_len()
-                    + resp_udp.header_len()
-                    + dhcpv6_buffer.len();
-
-                self.client.recv(&buffer[..total_len], &ChecksumState::NONE);
-            }
-            _ => return Err(DropReason::UnsupportedDhcpv6(msg.msg_type)),
+        // Add DNS Name Server option if we have nameservers
+        let dns_servers = self.inner.state.params.filtered_ipv6_nameservers();
+        if !dns_servers.is_empty() {
+            reply.dns_servers = Some(dns_servers);
         }
+        reply
+    }
 
-        Ok(())
+    /// Adds the leased address to `reply`, for the IA_NA requested in `msg`.
+    fn dhcpv6_lease(&self, msg: &Message, reply: &mut Message) {
+        let Some(ia_na) = &msg.ia_na else {
+            reply.status_code = Some(StatusCode::NO_ADDRS_AVAIL);
+            return;
+        };
+        reply.ia_na = Some(IaNa {
+            iaid: ia_na.iaid,
+            t1: LEASE_T1,
+            t2: LEASE_T2,
+            addresses: vec![IaAddress {
+                addr: self.dhcpv6_lease_address(),
+                preferred_lifetime: LEASE_PREFERRED_LIFETIME,
+                valid_lifetime: LEASE_VALID_LIFETIME,
+            }],
+        });
+    }
+
+    fn send_dhcpv6(&mut self, reply: &Message, client_ip: Option<Ipv6Address>) {
+        let dhcpv6_buffer = reply.encode();
+
+        let resp_udp = UdpRepr {
+            src_port: DHCPV6_SERVER,
+            dst_port: DHCPV6_CLIENT,
+        };
+
+        let client_link_local = client_ip.unwrap_or(DHCPV6_ALL_AGENTS_MULTICAST);
+        let resp_ipv6 = Ipv6Repr {
+            src_addr: self.inner.state.params.gateway_link_local_ipv6,
+            dst_addr: client_link_local,
+            next_header: IpProtocol::Udp,
+            payload_len: resp_udp.header_len() + dhcpv6_buffer.len(),
+            hop_limit: 64,
+        };
+        let resp_eth = EthernetRepr {
+            src_addr: self.inner.state.params.gateway_mac_ipv6,
+            dst_addr: self.inner.state.params.client_mac,
+            ethertype: EthernetProtocol::Ipv6,
+        };
+
+        // Construct the complete packet
+        let mut buffer = [0; MIN_MTU];
+        let mut eth_frame = EthernetFrame::new_unchecked(&mut buffer);
+        resp_eth.emit(&mut eth_frame);
+
+        let mut ipv6_packet = Ipv6Packet::new_unchecked(eth_frame.payload_mut());
+        resp_ipv6.emit(&mut ipv6_packet);
+
+        let mut udp_packet = UdpPacket::new_unchecked(ipv6_packet.payload_mut());
+        resp_udp.emit(
+            &mut udp_packet,
+            &IpAddress::Ipv6(resp_ipv6.src_addr),
+            &IpAddress::Ipv6(resp_ipv6.dst_addr),
+            dhcpv6_buffer.len(),
+            |udp_payload| {
+                udp_payload[..dhcpv6_buffer.len()].copy_from_slice(&dhcpv6_buffer);
+            },
+            &ChecksumCapabilities::default(),
+        );
+
+        let total_len = resp_eth.buffer_len()
+            + resp_ipv6.buffer_len()
+            + resp_udp.header_len()
+            + dhcpv6_buffer.len();
+
+        self.client.recv(&buffer[..total_len], &ChecksumState::NONE);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Consomme;
+    use crate::ConsommeParams;
+    use pal_async::DefaultDriver;
+    use pal_async::driver::Driver;
+    use smoltcp::wire::ETHERNET_HEADER_LEN;
+    use smoltcp::wire::EthernetAddress;
+    use smoltcp::wire::IPV6_HEADER_LEN;
+    use smoltcp::wire::UDP_HEADER_LEN;
+
+    struct TestClient {
+        driver: DefaultDriver,
+        received: Vec<Vec<u8>>,
+    }
+
+    impl Client for TestClient {
+        fn driver(&self) -> &dyn Driver {
+            &self.driver
+        }
+
+        fn recv(&mut self, data: &[u8], _checksum: &ChecksumState) {
+            self.received.push(data.to_vec());
+        }
+
+        fn rx_mtu(&mut self) -> usize {
+            1514
+        }
+    }
+
+    /// Sends `msg` from the guest to the DHCPv6 multicast address and returns
+    /// the decoded responses.
+    fn exchange(consomme: &mut Consomme, client: &mut TestClient, msg: &Message) -> Vec<Message> {
+        let params = consomme.params_mut();
+        let guest_mac = params.client_mac;
+        let gateway_mac = params.gateway_mac_ipv6;
+        let guest_ip = ConsommeParams::compute_link_local_address(guest_mac);
+
+        let payload = msg.encode();
+        let udp_repr = UdpRepr {
+            src_port: DHCPV6_CLIENT,
+            dst_port: DHCPV6_SERVER,
+        };
+        let ipv6_repr = Ipv6Repr {
+            src_addr: guest_ip,
+            dst_addr: DHCPV6_ALL_AGENTS_MULTICAST,
+            next_header: IpProtocol::Udp,
+            payload_len: UDP_HEADER_LEN + payload.len(),
+            hop_limit: 1,
+        };
+        let mut buf = vec![0u8; ETHERNET_HEADER_LEN + IPV6_HEADER_LEN + ipv6_repr.payload_len];
+        let mut eth = EthernetFrame::new_unchecked(&mut buf[..]);
+        EthernetRepr {
+            src_addr: guest_mac,
+            dst_addr: gateway_mac,
+            ethertype: EthernetProtocol::Ipv6,
+        }
+        .emit(&mut eth);
+        let mut ipv6 = Ipv6Packet::new_unchecked(eth.payload_mut());
+        ipv6_repr.emit(&mut ipv6);
+        udp_repr.emit(
+            &mut UdpPacket::new_unchecked(ipv6.payload_mut()),
+            &IpAddress::Ipv6(ipv6_repr.src_addr),
+            &IpAddress::Ipv6(ipv6_repr.dst_addr),
+            payload.len(),
+            |udp_payload| udp_payload[..payload.len()].copy_from_slice(&payload),
+            &ChecksumCapabilities::default(),
+        );
+
+        consomme
+            .access(client)
+            .send(&buf, &ChecksumState::NONE)
+            .unwrap();
+
+        client
+            .received
+            .drain(..)
+            .map(|frame| {
+                let eth = EthernetFrame::new_unchecked(&frame[..]);
+                let ipv6 = Ipv6Packet::new_unchecked(eth.payload());
+                assert_eq!(ipv6.dst_addr(), guest_ip);
+                let udp = UdpPacket::new_unchecked(ipv6.payload());
+                assert_eq!(udp.src_port(), DHCPV6_SERVER);
+                assert_eq!(udp.dst_port(), DHCPV6_CLIENT);
+                Message::decode(udp.payload()).unwrap()
+            })
+            .collect()
+    }
+
+    fn new_consomme(driver: DefaultDriver) -> (Consomme, TestClient) {
+        let mut params = ConsommeParams::new().unwrap();
+        params.skip_ipv6_checks = true;
+        params.client_mac = EthernetAddress([0x00, 0x15, 0x5d, 0x00, 0x00, 0x01]);
+        let client = TestClient {
+            driver,
+            received: Vec::new(),
+        };
+        (Consomme::new(params), client)
+    }
+
+    fn client_message(msg_type: MessageType) -> Message {
+        let mut msg = Message::new(msg_type);
+        msg.transaction_id = [0x12, 0x34, 0x56];
+        msg.client_id = Some(hex_to_bytes("0001000130adec9800155d000001"));
+        msg.ia_na = Some(IaNa {
+            iaid: 0x5d000001,
+            t1: 0,
+            t2: 0,
+            addresses: Vec::new(),
+        });
+        msg
+    }
+
+    fn expected_lease() -> std::net::Ipv6Addr {
+        "2001:abcd::100".parse().unwrap()
+    }
 
     /// Helper function to convert a hex string to bytes
     fn hex_to_bytes(hex: &str) -> Vec<u8> {
@@ -362,4 +731,113 @@ fn test_message_encode() {
         let servers = decoded.dns_servers.as_ref().expect("DnsServers not found");
         assert_eq!(servers, &dns_servers);
     }
+
+    #[test]
+    fn test_ia_na_round_trip() {
+        let ia_na = IaNa {
+            iaid: 0x5d000001,
+            t1: LEASE_T1,
+            t2: LEASE_T2,
+            addresses: vec![IaAddress {
+                addr: expected_lease(),
+                preferred_lifetime: LEASE_PREFERRED_LIFETIME,
+                valid_lifetime: LEASE_VALID_LIFETIME,
+            }],
+        };
+        let mut msg = Message::new(MessageType::REPLY);
+        msg.ia_na = Some(ia_na.clone());
+        msg.status_code = Some(StatusCode::SUCCESS);
+        msg.rapid_commit = true;
+
+        let decoded = Message::decode(&msg.encode()).expect("Failed to decode encoded message");
+        assert_eq!(decoded.ia_na, Some(ia_na));
+        assert_eq!(decoded.status_code, Some(StatusCode::SUCCESS));
+        assert!(decoded.rapid_commit);
+    }
+
+    #[test]
+    fn test_ia_na_truncated() {
+        let mut bytes = vec![MessageType::SOLICIT.0, 0, 0, 0];
+        push_option(&mut bytes, OptionCode::IA_NA, &[0; 8]);
+        assert!(matches!(
+            Message::decode(&bytes),
+            Err(DhcpV6Error::InvalidIaNaOption(8))
+        ));
+    }
+
+    #[pal_async::async_test]
+    async fn test_stateful_lease(driver: DefaultDriver) {
+        let (mut consomme, mut client) = new_consomme(driver);
+
+        // Solicit is answered with an Advertise offering the lease.
+        let solicit = client_message(MessageType::SOLICIT);
+        let [advertise]: [Message; 1] = exchange(&mut consomme, &mut client, &solicit)
+            .try_into()
+            .ok()
+            .unwrap();
+        assert_eq!(advertise.msg_type, MessageType::ADVERTISE);
+        assert_eq!(advertise.transaction_id, solicit.transaction_id);
+        assert_eq!(advertise.client_id, solicit.client_id);
+        let ia_na = advertise.ia_na.as_ref().unwrap();
+        assert_eq!(ia_na.iaid, 0x5d000001);
+        assert_eq!(ia_na.addresses.len(), 1);
+        assert_eq!(ia_na.addresses[0].addr, expected_lease());
+
+        // Request commits the lease with the server that advertised it.
+        let mut request = client_message(MessageType::REQUEST);
+        request.server_id = advertise.server_id.clone();
+        let [reply]: [Message; 1] = exchange(&mut consomme, &mut client, &request)
+            .try_into()
+            .ok()
+            .unwrap();
+        assert_eq!(reply.msg_type, MessageType::REPLY);
+        assert_eq!(reply.ia_na, advertise.ia_na);
+
+        // A Request for another server is ignored.
+        request.server_id = Some(hex_to_bytes("00030001001122334455"));
+        assert!(exchange(&mut consomme, &mut client, &request).is_empty());
+
+        // Confirm reports whether the address is still on the link.
+        let mut confirm = client_message(MessageType::CONFIRM);
+        confirm.ia_na.as_mut().unwrap().addresses.push(IaAddress {
+            addr: expected_lease(),
+            preferred_lifetime: 0,
+            valid_lifetime: 0,
+        });
+        let [reply]: [Message; 1] = exchange(&mut consomme, &mut client, &confirm)
+            .try_into()
+            .ok()
+            .unwrap();
+        assert_eq!(reply.status_code, Some(StatusCode::SUCCESS));
+        confirm.ia_na.as_mut().unwrap().addresses[0].addr = "2001:db8::1".parse().unwrap();
+        let [reply]: [Message; 1] = exchange(&mut consomme, &mut client, &confirm)
+            .try_into()
+            .ok()
+            .unwrap();
+        assert_eq!(reply.status_code, Some(StatusCode::NOT_ON_LINK));
+    }
+
+    #[pal_async::async_test]
+    async fn test_rapid_commit(driver: DefaultDriver) {
+        let (mut consomme, mut client) = new_consomme(driver);
+
+        let mut solicit = client_message(MessageType::SOLICIT);
+        solicit.rapid_commit = true;
+        let [reply]: [Message; 1] = exchange(&mut consomme, &mut client, &solicit)
+            .try_into()
+            .ok()
+            .unwrap();
+        assert_eq!(reply.msg_type, MessageType::REPLY);
+        assert!(reply.rapid_commit);
+        assert_eq!(reply.ia_na.unwrap().addresses[0].addr, expected_lease());
+
+        // Without an IA_NA, there is no address to offer.
+        solicit.ia_na = None;
+        let [reply]: [Message; 1] = exchange(&mut consomme, &mut client, &solicit)
+            .try_into()
+            .ok()
+            .unwrap();
+        assert!(reply.ia_na.is_none());
+        assert_eq!(reply.status_code, Some(StatusCode::NO_ADDRS_AVAIL));
+    }
 }
@@ -0,0 +1,414 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! ICMPv6 echo (ping) support.
+//!
+//! Echo requests to the gateway's link-local address are answered directly.
+//! Echo requests to any other address are sent through a host ICMPv6 socket
+//! (one per guest source address), and the replies are wrapped back into IPv6
+//! packets for the guest.
+
+// UNSAFETY: needed to cast the socket buffer to `MaybeUninit`.
+#![expect(unsafe_code)]
+
+use super::Access;
+use super::Client;
+use super::ConsommeState;
+use super::DropReason;
+use crate::ChecksumState;
+use crate::Ipv6Addresses;
+use crate::MIN_MTU;
+
+use inspect::Inspect;
+use inspect_counters::Counter;
+use pal_async::interest::InterestSlot;
+use pal_async::interest::PollEvents;
+use pal_async::socket::PolledSocket;
+use smoltcp::wire::ETHERNET_HEADER_LEN;
+use smoltcp::wire::EthernetAddress;
+use smoltcp::wire::EthernetFrame;
+use smoltcp::wire::EthernetProtocol;
+use smoltcp::wire::EthernetRepr;
+use smoltcp::wire::IPV6_HEADER_LEN;
+use smoltcp::wire::Icmpv6Message;
+use smoltcp::wire::Icmpv6Packet;
+use smoltcp::wire::IpProtocol;
+use smoltcp::wire::Ipv6Address;
+use smoltcp::wire::Ipv6Packet;
+use smoltcp::wire::Ipv6Repr;
+use socket2::Domain;
+use socket2::Protocol;
+use socket2::SockAddr;
+use socket2::Socket;
+use socket2::Type;
+use std::collections::HashMap;
+use std::collections::hash_map;
+use std::io::ErrorKind;
+use std::mem::MaybeUninit;
+use std::net::SocketAddr;
+use std::net::SocketAddrV6;
+use std::task::Context;
+use std::task::Poll;
+
+/// The length of the ICMPv6 echo header (type, code, checksum, identifier,
+/// sequence number).
+const ICMPV6_ECHO_HEADER_LEN: usize = 8;
+
+pub(crate) struct Icmpv6 {
+    connections: HashMap<Ipv6Address, Icmpv6Connection>,
+}
+
+impl Icmpv6 {
+    pub fn new() -> Self {
+        Self {
+            connections: HashMap::new(),
+        }
+    }
+}
+
+impl Inspect for Icmpv6 {
+    fn inspect(&self, req: inspect::Request<'_>) {
+        let mut resp = req.respond();
+        for (addr, conn) in &self.connections {
+            resp.field(&addr.to_string(), conn);
+        }
+    }
+}
+
+#[derive(Inspect)]
+struct Icmpv6Connection {
+    #[inspect(skip)]
+    socket: PolledSocket<Socket>,
+    #[inspect(display)]
+    guest_mac: EthernetAddress,
+    /// The echo identifier most recently used by the guest. Unprivileged
+    /// host ping sockets replace the identifier with their own, so it is
+    /// restored on replies.
+    #[inspect(hex)]
+    echo_ident: u16,
+    stats: Stats,
+}
+
+#[derive(Inspect, Default)]
+struct Stats {
+    tx_packets: Counter,
+    tx_dropped: Counter,
+    tx_errors: Counter,
+    rx_packets: Counter,
+    rx_dropped: Counter,
+}
+
+impl Icmpv6Connection {
+    fn poll_conn(
+        &mut self,
+        cx: &mut Context<'_>,
+        guest_addr: &Ipv6Address,
+        state: &mut ConsommeState,
+        client: &mut impl Client,
+    ) {
+        const HEADERS_LEN: usize = ETHERNET_HEADER_LEN + IPV6_HEADER_LEN;
+        loop {
+            let (n, src_addr) =
+                match self
+                    .socket
+                    .poll_io(cx, InterestSlot::Read, PollEvents::IN, |socket| {
+                        Self::recv_from(socket.get(), &mut state.buffer[HEADERS_LEN..MIN_MTU])
+                    }) {
+                    Poll::Ready(Ok(r)) => r,
+                    Poll::Ready(Err(err)) => {
+                        tracelimit::error_ratelimited!(
+                            error = &err as &dyn std::error::Error,
+                            guest_ip = %guest_addr,
+                            "icmpv6 recv error"
+                        );
+                        break;
+                    }
+                    Poll::Pending => break,
+                };
+
+            // Host ICMPv6 sockets deliver just the ICMPv6 message, without
+            // the IPv6 header. Only relay echo replies to the guest.
+            let Some(src_addr) = src_addr.as_socket_ipv6() else {
+                self.stats.rx_dropped.increment();
+                continue;
+            };
+            let icmp = &mut state.buffer[HEADERS_LEN..HEADERS_LEN + n];
+            if n < ICMPV6_ECHO_HEADER_LEN
+                || Icmpv6Packet::new_unchecked(&*icmp).msg_type() != Icmpv6Message::EchoReply
+            {
+                self.stats.rx_dropped.increment();
+                continue;
+            }
+
+            let mut icmp = Icmpv6Packet::new_unchecked(icmp);
+            icmp.set_echo_ident(self.echo_ident);
+            icmp.fill_checksum(src_addr.ip(), guest_addr);
+
+            let eth_repr = EthernetRepr {
+                src_addr: state.params.gateway_mac_ipv6,
+                dst_addr: self.guest_mac,
+                ethertype: EthernetProtocol::Ipv6,
+            };
+            let ipv6_repr = Ipv6Repr {
+                src_addr: *src_addr.ip(),
+                dst_addr: *guest_addr,
+                next_header: IpProtocol::Icmpv6,
+                payload_len: n,
+                // The real hop limit is not available from the host socket.
+                hop_limit: 64,
+            };
+            let mut eth = EthernetFrame::new_unchecked(&mut state.buffer[..]);
+            eth_repr.emit(&mut eth);
+            ipv6_repr.emit(&mut Ipv6Packet::new_unchecked(eth.payload_mut()));
+
+            client.recv(&state.buffer[..HEADERS_LEN + n], &ChecksumState::NONE);
+            self.stats.rx_packets.increment();
+        }
+    }
+
+    fn recv_from(socket: &Socket, buffer: &mut [u8]) -> std::io::Result<(usize, SockAddr)> {
+        // SAFETY: The underlying socket `recv` implementation promises
+        //   not to write uninitialized bytes into the buffer.
+        let buf = unsafe {
+            std::slice::from_raw_parts_mut(
+                buffer.as_mut_ptr().cast::<MaybeUninit<u8>>(),
+                buffer.len(),
+            )
+        };
+        socket.recv_from(buf)
+    }
+
+    fn send_to(&mut self, dest: Ipv6Address, buffer: &[u8], hop_limit: u8) -> std::io::Result<()> {
+        let socket = self.socket.get();
+        let dest = SocketAddr::V6(SocketAddrV6::new(dest, 0, 0, 0));
+        socket.set_unicast_hops_v6(hop_limit.into())?;
+        socket.send_to(buffer, &dest.into())?;
+        Ok(())
+    }
+}
+
+impl<T: Client> Access<'_, T> {
+    pub(crate) fn poll_icmpv6(&mut self, cx: &mut Context<'_>) {
+        for (guest_addr, conn) in &mut self.inner.icmpv6.connections {
+            conn.poll_conn(cx, guest_addr, &mut self.inner.state, self.client);
+        }
+    }
+
+    /// Handle an ICMPv6 echo request destined for the gateway's link-local
+    /// address by generating a reply directly.
+    fn handle_icmpv6_gateway_echo(
+        &mut self,
+        frame: &EthernetRepr,
+        addresses: &Ipv6Addresses,
+        payload: &[u8],
+    ) -> Result<(), DropReason> {
+        let icmp_len = payload.len();
+        let eth_total_len = ETHERNET_HEADER_LEN + IPV6_HEADER_LEN + icmp_len;
+        if eth_total_len > MIN_MTU {
+            return Err(DropReason::MalformedPacket);
+        }
+
+        let mut buffer = [0u8; MIN_MTU];
+        let eth_repr = EthernetRepr {
+            src_addr: self.inner.state.params.gateway_mac_ipv6,
+            dst_addr: frame.src_addr,
+            ethertype: EthernetProtocol::Ipv6,
+        };
+        let mut eth = EthernetFrame::new_unchecked(&mut buffer[..]);
+        eth_repr.emit(&mut eth);
+
+        let ipv6_repr = Ipv6Repr {
+            src_addr: addresses.dst_addr,
+            dst_addr: addresses.src_addr,
+            next_header: IpProtocol::Icmpv6,
+            payload_len: icmp_len,
+            hop_limit: 64,
+        };
+        let mut ipv6 = Ipv6Packet::new_unchecked(eth.payload_mut());
+        ipv6_repr.emit(&mut ipv6);
+
+        // ICMPv6 echo reply — copy the request payload and change the type.
+        let icmp_buf = &mut ipv6.payload_mut()[..icmp_len];
+        icmp_buf.copy_from_slice(payload);
+        let mut icmp_reply = Icmpv6Packet::new_unchecked(icmp_buf);
+        icmp_reply.set_msg_type(Icmpv6Message::EchoReply);
+        icmp_reply.fill_checksum(&ipv6_repr.src_addr, &ipv6_repr.dst_addr);
+
+        self.client
+            .recv(&buffer[..eth_total_len], &ChecksumState::NONE);
+        Ok(())
+    }
+
+    /// Handle an ICMPv6 echo request from the guest.
+    pub(crate) fn handle_icmpv6_echo(
+        &mut self,
+        frame: &EthernetRepr,
+        addresses: &Ipv6Addresses,
+        payload: &[u8],
+        hop_limit: u8,
+    ) -> Result<(), DropReason> {
+        if payload.len() < ICMPV6_ECHO_HEADER_LEN {
+            return Err(DropReason::MalformedPacket);
+        }
+
+        if addresses.dst_addr == self.inner.state.params.gateway_link_local_ipv6 {
+            return self.handle_icmpv6_gateway_echo(frame, addresses, payload);
+        }
+
+        let entry = self.inner.icmpv6.connections.entry(addresses.src_addr);
+        let conn = match entry {
+            hash_map::Entry::Occupied(conn) => conn.into_mut(),
+            hash_map::Entry::Vacant(e) => {
+                // As with ICMPv4, Linux only allows unprivileged DGRAM ICMPv6
+                // sockets (subject to 'net.ipv4.ping_group_range').
+                let socket_type = if cfg!(windows) {
+                    Type::RAW
+                } else {
+                    Type::DGRAM
+                };
+                let socket = match Socket::new(Domain::IPV6, socket_type, Some(Protocol::ICMPV6)) {
+                    Err(e) => {
+                        tracelimit::error_ratelimited!(
+                            error = &e as &dyn std::error::Error,
+                            guest_ip = %addresses.src_addr,
+                            dst_ip = %addresses.dst_addr,
+                            "icmpv6 socket creation failed",
+                        );
+                        return Err(DropReason::Io(e));
+                    }
+                    Ok(s) => s,
+                };
+                let bind_addr =
+                    SocketAddr::V6(SocketAddrV6::new(Ipv6Address::UNSPECIFIED, 0, 0, 0));
+                socket.bind(&bind_addr.into()).map_err(DropReason::Io)?;
+                let socket =
+                    PolledSocket::new(self.client.driver(), socket).map_err(DropReason::Io)?;
+                e.insert(Icmpv6Connection {
+                    socket,
+                    guest_mac: frame.src_addr,
+                    echo_ident: 0,
+                    stats: Default::default(),
+                })
+            }
+        };
+
+        conn.echo_ident = Icmpv6Packet::new_unchecked(payload).echo_ident();
+        match conn.send_to(addresses.dst_addr, payload, hop_limit) {
+            Ok(_) => {
+                conn.stats.tx_packets.increment();
+                Ok(())
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                conn.stats.tx_dropped.increment();
+                Err(DropReason::SendBufferFull)
+            }
+            Err(err) => {
+                conn.stats.tx_errors.increment();
+                Err(DropReason::Io(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ChecksumState;
+    use crate::Client;
+    use crate::Consomme;
+    use crate::ConsommeParams;
+    use pal_async::DefaultDriver;
+    use pal_async::driver::Driver;
+    use smoltcp::wire::ETHERNET_HEADER_LEN;
+    use smoltcp::wire::EthernetAddress;
+    use smoltcp::wire::EthernetFrame;
+    use smoltcp::wire::EthernetProtocol;
+    use smoltcp::wire::EthernetRepr;
+    use smoltcp::wire::IPV6_HEADER_LEN;
+    use smoltcp::wire::Icmpv6Message;
+    use smoltcp::wire::Icmpv6Packet;
+    use smoltcp::wire::IpProtocol;
+    use smoltcp::wire::Ipv6Packet;
+    use smoltcp::wire::Ipv6Repr;
+
+    struct TestClient {
+        driver: DefaultDriver,
+        received: Vec<Vec<u8>>,
+    }
+
+    impl Client for TestClient {
+        fn driver(&self) -> &dyn Driver {
+            &self.driver
+        }
+
+        fn recv(&mut self, data: &[u8], _checksum: &ChecksumState) {
+            self.received.push(data.to_vec());
+        }
+
+        fn rx_mtu(&mut self) -> usize {
+            1514
+        }
+    }
+
+    #[pal_async::async_test]
+    async fn test_gateway_echo(driver: DefaultDriver) {
+        let mut params = ConsommeParams::new().unwrap();
+        params.skip_ipv6_checks = true;
+        let guest_mac = params.client_mac;
+        let gateway_mac = params.gateway_mac_ipv6;
+        let gateway_ip = params.gateway_link_local_ipv6;
+        let guest_ip = ConsommeParams::compute_link_local_address(EthernetAddress([
+            0x00, 0x15, 0x5d, 0x00, 0x00, 0x01,
+        ]));
+        let mut consomme = Consomme::new(params);
+        let mut client = TestClient {
+            driver,
+            received: Vec::new(),
+        };
+
+        let echo_data = b"consomme ping";
+        let icmp_len = 8 + echo_data.len();
+        let mut buf = vec![0u8; ETHERNET_HEADER_LEN + IPV6_HEADER_LEN + icmp_len];
+        let mut eth = EthernetFrame::new_unchecked(&mut buf[..]);
+        EthernetRepr {
+            src_addr: guest_mac,
+            dst_addr: gateway_mac,
+            ethertype: EthernetProtocol::Ipv6,
+        }
+        .emit(&mut eth);
+        let ipv6_repr = Ipv6Repr {
+            src_addr: guest_ip,
+            dst_addr: gateway_ip,
+            next_header: IpProtocol::Icmpv6,
+            payload_len: icmp_len,
+            hop_limit: 64,
+        };
+        let mut ipv6 = Ipv6Packet::new_unchecked(eth.payload_mut());
+        ipv6_repr.emit(&mut ipv6);
+        let mut icmp = Icmpv6Packet::new_unchecked(ipv6.payload_mut());
+        icmp.set_msg_type(Icmpv6Message::EchoRequest);
+        icmp.set_msg_code(0);
+        icmp.set_echo_ident(0x1234);
+        icmp.set_echo_seq_no(7);
+        icmp.payload_mut().copy_from_slice(echo_data);
+        icmp.fill_checksum(&guest_ip, &gateway_ip);
+
+        consomme
+            .access(&mut client)
+            .send(&buf, &ChecksumState::NONE)
+            .unwrap();
+
+        assert_eq!(client.received.len(), 1);
+        let eth = EthernetFrame::new_unchecked(&client.received[0][..]);
+        assert_eq!(eth.dst_addr(), guest_mac);
+        assert_eq!(eth.ethertype(), EthernetProtocol::Ipv6);
+        let ipv6 = Ipv6Packet::new_unchecked(eth.payload());
+        assert_eq!(ipv6.src_addr(), gateway_ip);
+        assert_eq!(ipv6.dst_addr(), guest_ip);
+        let reply = Icmpv6Packet::new_unchecked(ipv6.payload());
+        assert_eq!(reply.msg_type(), Icmpv6Message::EchoReply);
+        assert_eq!(reply.echo_ident(), 0x1234);
+        assert_eq!(reply.echo_seq_no(), 7);
+        assert_eq!(reply.payload(), echo_data);
+        assert!(reply.verify_checksum(&gateway_ip, &guest_ip));
+    }
+}
@@ -12,7 +12,8 @@
 //! essentially causing this stack to act as a NAT implementation, providing
 //! guest OS networking by leveraging the host's network stack.
 //!
-//! This implementation includes a small DHCP server for address assignment.
+//! This implementation includes a small DHCP server for address assignment,
+//! and optionally a read-only TFTP server for network boot.
 
 mod arp;
 mod dhcp;
@@ -22,9 +23,11 @@
 mod dns;
 mod dns_resolver;
 mod icmp;
+mod icmpv6;
 mod local_addr_map;
 mod ndp;
 mod tcp;
+mod tftp;
 mod udp;
 
 mod unix;
@@ -55,6 +58,7 @@
 use std::net::SocketAddr;
 use std::net::SocketAddrV4;
 use std::net::SocketAddrV6;
+use std::path::PathBuf;
 use std::task::Context;
 use std::time::Duration;
 use thiserror::Error;
@@ -80,6 +84,8 @@ pub struct Consomme {
     #[inspect(mut)]
     udp: udp::Udp,
     icmp: icmp::Icmp,
+    icmpv6: icmpv6::Icmpv6,
+    tftp: tftp::Tftp,
     dns: Option<dns_resolver::DnsResolver>,
     host_has_ipv6: bool,
 }
@@ -113,6 +119,11 @@ pub struct ConsommeParams {
     /// Current list of DNS resolvers.
     #[inspect(with = "|x| inspect::iter_by_index(x).map_value(inspect::AsDisplay)")]
     pub nameservers: Vec<IpAddress>,
+    /// If true, the built-in DNS resolver is not used. Instead, `nameservers`
+    /// is advertised to the guest as-is, and guest DNS traffic is forwarded to
+    /// those servers through the NAT.
+    #[inspect(display)]
+    pub forward_dns: bool,
     /// Current IPv6 network mask (if any).
     #[inspect(display)]
     pub prefix_len_ipv6: u8,
@@ -144,6 +155,14 @@ pub struct ConsommeParams {
     /// routable IPv6 address.
     #[inspect(display)]
     pub skip_ipv6_checks: bool,
+    /// The host directory to serve to the guest over TFTP, for network boot.
+    /// If `None`, TFTP requests to the gateway are forwarded like any other
+    /// UDP traffic.
+    #[inspect(with = "|x| x.as_ref().map(|p| p.display().to_string())")]
+    pub tftp_root: Option<PathBuf>,
+    /// The boot file name to advertise to the guest via DHCP, for network
+    /// boot.
+    pub boot_file: Option<String>,
 }
 
 /// An error indicating that the CIDR is invalid.
@@ -151,6 +170,12 @@ pub struct ConsommeParams {
 #[error("invalid CIDR")]
 pub struct InvalidCidr;
 
+/// An error indicating that the client IP address is not a usable host address
+/// on the network.
+#[derive(Debug, Error)]
+#[error("client IP address {0} is not a host address on the network")]
+pub struct InvalidClientIp(Ipv4Address);
+
 impl ConsommeParams {
     /// Create default dynamic network state. The default state is
     ///     IP address: 10.0.0.2 / 24
@@ -169,6 +194,7 @@ pub fn new() -> Result<Self, Error> {
             client_mac: EthernetAddress([0x0, 0x0, 0x0, 0x0, 0x1, 0x0]),
             net_mask: Ipv4Address::new(255, 255, 255, 0),
             nameservers,
+            forward_dns: false,
             prefix_len_ipv6: 64,
             gateway_mac_ipv6,
             gateway_link_local_ipv6: Self::compute_link_local_address(gateway_mac_ipv6),
@@ -177,6 +203,8 @@ pub fn new() -> Result<Self, Error> {
             // Per RFC 4787, UDP NAT bindings, by default, should timeout after 5 minutes, but can be configured.
             udp_timeout: Duration::from_secs(300),
             skip_ipv6_checks: false,
+            tftp_root: None,
+            boot_file: None,
         })
     }
 
@@ -197,6 +225,23 @@ pub fn set_cidr(&mut self, cidr: &str) -> Result<(), InvalidCidr> {
         Ok(())
     }
 
+    /// Sets the IPv4 address that DHCP assigns to the client, instead of the
+    /// network's second host address.
+    ///
+    /// This must be called after [`Self::set_cidr`], since that resets the
+    /// client IP.
+    pub fn set_client_ip(&mut self, client_ip: Ipv4Address) -> Result<(), InvalidClientIp> {
+        let mask = self.net_mask.to_bits();
+        let network = self.gateway_ip.to_bits() & mask;
+        let ip = client_ip.to_bits();
+        let is_host_address = (ip & mask) == network && ip != network && (ip | mask) != u32::MAX;
+        if !is_host_address || client_ip == self.gateway_ip {
+            return Err(InvalidClientIp(client_ip));
+        }
+        self.client_ip = client_ip;
+        Ok(())
+    }
+
     /// Compute a link-local IPv6 address from a MAC address using EUI-64 format.
     ///
     /// RFC 4291 Section 2.5.6: Link-local addresses are formed by combining
@@ -654,7 +699,10 @@ pub fn new(mut params: ConsommeParams) -> Self {
                 }
             }
         };
-        let dns =
+        let dns = if params.forward_dns {
+            // The caller explicitly chose the nameservers to forward to.
+            None
+        } else {
             match dns_resolver::DnsResolver::new(dns_resolver::DEFAULT_MAX_PENDING_DNS_REQUESTS) {
                 Ok(dns) => {
                     // When the DNS resolver is available, use the default internal nameserver.
@@ -667,7 +715,8 @@ pub fn new(mut params: ConsommeParams) -> Self {
                     );
                     None
                 }
-            };
+            }
+        };
         let timeout = params.udp_timeout;
         Self {
             state: ConsommeState {
@@ -678,6 +727,8 @@ pub fn new(mut params: ConsommeParams) -> Self {
             tcp: tcp::Tcp::new(),
             udp: udp::Udp::new(timeout),
             icmp: icmp::Icmp::new(),
+            icmpv6: icmpv6::Icmpv6::new(),
+            tftp: tftp::Tftp::new(),
             dns,
             host_has_ipv6,
         }
@@ -726,6 +777,8 @@ pub fn poll(&mut self, cx: &mut Context<'_>) {
         self.poll_udp(cx);
         self.poll_tcp(cx);
         self.poll_icmp(cx);
+        self.poll_icmpv6(cx);
+        self.poll_tftp(cx);
     }
 
     /// Update all sockets to use the new client's IO driver. This must be
@@ -902,6 +955,8 @@ fn handle_ipv6(
                     || msg_type == smoltcp::wire::Icmpv6Message::RouterAdvert
                 {
                     self.handle_ndp(frame, inner, ipv6.src_addr())?;
+                } else if msg_type == smoltcp::wire::Icmpv6Message::EchoRequest {
+                    self.handle_icmpv6_echo(frame, &addresses, inner, ipv6.hop_limit())?;
                 } else {
                     return Err(DropReason::UnsupportedIpProtocol(next_header));
                 }
@@ -929,6 +984,29 @@ mod tests {
     use super::*;
     use smoltcp::wire::Ipv6Address;
 
+    #[test]
+    fn test_set_client_ip() {
+        let mut params = ConsommeParams::new().unwrap();
+        params.set_cidr("192.168.0.0/24").unwrap();
+        params
+            .set_client_ip(Ipv4Address::new(192, 168, 0, 100))
+            .unwrap();
+        assert_eq!(params.client_ip, Ipv4Address::new(192, 168, 0, 100));
+
+        for invalid in [
+            // Gateway.
+            Ipv4Address::new(192, 168, 0, 1),
+            // Network and broadcast addresses.
+            Ipv4Address::new(192, 168, 0, 0),
+            Ipv4Address::new(192, 168, 0, 255),
+            // Outside the network.
+            Ipv4Address::new(192, 168, 1, 2),
+        ] {
+            assert!(params.set_client_ip(invalid).is_err(), "{invalid}");
+        }
+        assert_eq!(params.client_ip, Ipv4Address::new(192, 168, 0, 100));
+    }
+
     #[test]
     fn test_is_same_ipv6_subnet_basic() {
         let a = Ipv6Address::new(0x2001, 0x0db8, 0x0001, 0, 0, 0, 0, 1);
@@ -7,7 +7,8 @@
 //! This module implements RFC 4861 (Neighbor Discovery) and RFC 4862 (IPv6
 //! Stateless Address Autoconfiguration).  The implementation is stateless - we
 //! advertise prefixes via Router Advertisements and let clients autoconfigure
-//! their own addresses using SLAAC.
+//! their own addresses using SLAAC. The advertisements also point clients at
+//! DHCPv6 for a stateful address and other configuration.
 
 use super::Access;
 use super::Client;
@@ -33,7 +34,8 @@
 use smoltcp::wire::NdiscRouterFlags;
 use smoltcp::wire::RawHardwareAddress;
 
-const NETWORK_PREFIX_BASE: Ipv6Address = Ipv6Address::new(0x2001, 0xabcd, 0, 0, 0, 0, 0, 0);
+pub(crate) const NETWORK_PREFIX_BASE: Ipv6Address =
+    Ipv6Address::new(0x2001, 0xabcd, 0, 0, 0, 0, 0, 0);
 const LINK_LOCAL_ALL_NODES: Ipv6Address =
     Ipv6Address::from_octets([0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
 
@@ -181,9 +183,13 @@ fn send_router_advertisement(
         // We set the ADDRCONF flag to enable SLAAC. We intentionally omit ON_LINK
         // so the guest treats global addresses as off-link and routes all traffic
         // through the gateway rather than attempting on-link NDP resolution.
+        //
+        // The MANAGED and OTHER flags tell clients that DHCPv6 is available for
+        // a stateful address (RFC 8415) and for DNS configuration, for guests
+        // that do not configure addresses via SLAAC.
         let ndp_repr = NdiscRepr::RouterAdvert {
             hop_limit: 255,
-            flags: NdiscRouterFlags::empty(),
+            flags: NdiscRouterFlags::MANAGED | NdiscRouterFlags::OTHER,
             router_lifetime: smoltcp::time::Duration::from_secs(9000), // https://www.rfc-editor.org/rfc/rfc4861#section-4.2
             reachable_time: smoltcp::time::Duration::from_millis(30000), // https://www.rfc-editor.org/rfc/rfc4861#section-6
             retrans_time: smoltcp::time::Duration::from_millis(1000), // https://www.rfc-editor.org/rfc/rfc4861#section-6
@@ -415,7 +421,7 @@ fn send_neighbor_advertisement(
     ///
     /// This extracts the network portion of an IPv6 address by applying
     /// a mask based on the prefix length.
-    fn compute_network_prefix(&self, addr: Ipv6Address, prefix_len: u8) -> Ipv6Address {
+    pub(crate) fn compute_network_prefix(&self, addr: Ipv6Address, prefix_len: u8) -> Ipv6Address {
         if prefix_len >= 128 {
             return addr;
         }
@@ -0,0 +1,694 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! A minimal read-only TFTP server (RFC 1350) on the gateway address, used to
+//! serve network boot artifacts from a host directory.
+//!
+//! The blksize (RFC 2348) and tsize (RFC 2349) options are supported, since
+//! UEFI PXE clients use them to size their download buffers. There is no
+//! retransmit timer: transfers are driven entirely by the guest, which
+//! retransmits its ACK on timeout, at which point the current block is resent.
+//!
+//! Files are read in full on a blocking thread pool when a transfer starts, so
+//! that the packet path never waits on the host filesystem.
+
+use super::Access;
+use super::Client;
+use super::DropReason;
+use crate::ChecksumState;
+use crate::ConsommeState;
+use crate::Ipv4Addresses;
+use crate::MIN_MTU;
+use crate::udp::build_udp_packet;
+use inspect::Inspect;
+use mesh_channel_core::Receiver;
+use smoltcp::wire::ETHERNET_HEADER_LEN;
+use smoltcp::wire::EthernetAddress;
+use smoltcp::wire::EthernetFrame;
+use smoltcp::wire::EthernetRepr;
+use smoltcp::wire::IPV4_HEADER_LEN;
+use smoltcp::wire::Ipv4Address;
+use smoltcp::wire::UDP_HEADER_LEN;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Instant;
+
+pub const TFTP_PORT: u16 = 69;
+
+const OP_RRQ: u16 = 1;
+const OP_WRQ: u16 = 2;
+const OP_DATA: u16 = 3;
+const OP_ACK: u16 = 4;
+const OP_ERROR: u16 = 5;
+const OP_OACK: u16 = 6;
+
+const ERR_NOT_DEFINED: u16 = 0;
+const ERR_FILE_NOT_FOUND: u16 = 1;
+const ERR_ACCESS_VIOLATION: u16 = 2;
+const ERR_ILLEGAL_OPERATION: u16 = 4;
+
+const DEFAULT_BLOCK_SIZE: usize = 512;
+const MIN_BLOCK_SIZE: usize = 8;
+/// The largest block size that fits in a single frame.
+const MAX_BLOCK_SIZE: usize = MIN_MTU - ETHERNET_HEADER_LEN - IPV4_HEADER_LEN - UDP_HEADER_LEN - 4;
+
+/// The maximum number of concurrent transfers. When exceeded, the least
+/// recently active transfer is abandoned.
+const MAX_TRANSFERS: usize = 8;
+
+/// The maximum number of files being read at once. Further requests are
+/// rejected until a read completes.
+const MAX_PENDING_READS: usize = MAX_TRANSFERS;
+
+/// The first port used for transfer IDs.
+const FIRST_TID: u16 = 49152;
+
+#[derive(Inspect)]
+pub(crate) struct Tftp {
+    #[inspect(iter_by_key)]
+    transfers: HashMap<u16, Transfer>,
+    #[inspect(skip)]
+    next_tid: u16,
+    /// The client ports of requests whose files are being read.
+    #[inspect(with = "HashSet::len")]
+    pending_reads: HashSet<u16>,
+    #[inspect(skip)]
+    completed_reads: Receiver<CompletedRead>,
+}
+
+/// A read request waiting for its file to be read.
+struct PendingRead {
+    dst_mac: EthernetAddress,
+    dst_addr: Ipv4Address,
+    client_port: u16,
+    filename: String,
+    block_size: Option<usize>,
+    tsize: bool,
+}
+
+struct CompletedRead {
+    request: PendingRead,
+    result: Result<Vec<u8>, TftpError>,
+}
+
+#[derive(Inspect)]
+struct Transfer {
+    client_port: u16,
+    path: String,
+    #[inspect(with = "Vec::len")]
+    data: Vec<u8>,
+    block_size: usize,
+    /// The most recently sent block, or 0 if waiting for the client to
+    /// acknowledge the option acknowledgement.
+    block: u64,
+    #[inspect(skip)]
+    oack: Vec<u8>,
+    #[inspect(skip)]
+    last_activity: Instant,
+}
+
+impl Transfer {
+    fn final_block(&self) -> u64 {
+        (self.data.len() / self.block_size) as u64 + 1
+    }
+
+    fn block_data(&self, block: u64) -> &[u8] {
+        let start = (block as usize - 1) * self.block_size;
+        &self.data[start..(start + self.block_size).min(self.data.len())]
+    }
+}
+
+impl Tftp {
+    pub fn new() -> Self {
+        Self {
+            transfers: HashMap::new(),
+            next_tid: FIRST_TID,
+            pending_reads: HashSet::new(),
+            completed_reads: Receiver::new(),
+        }
+    }
+
+    fn insert(&mut self, transfer: Transfer) -> u16 {
+        // A new request from the same client port supersedes any previous
+        // transfer, since the client has evidently given up on it.
+        self.transfers
+            .retain(|_, t| t.client_port != transfer.client_port);
+        if self.transfers.len() >= MAX_TRANSFERS {
+            let oldest = self
+                .transfers
+                .iter()
+                .min_by_key(|(_, t)| t.last_activity)
+                .map(|(&tid, _)| tid)
+                .unwrap();
+            self.transfers.remove(&oldest);
+        }
+        let tid = loop {
+            let tid = self.next_tid;
+            self.next_tid = self.next_tid.checked_add(1).unwrap_or(FIRST_TID);
+            if !self.transfers.contains_key(&tid) {
+                break tid;
+            }
+        };
+        self.transfers.insert(tid, transfer);
+        tid
+    }
+}
+
+/// A TFTP error to report to the client.
+#[derive(Debug, PartialEq, Eq)]
+struct TftpError {
+    code: u16,
+    message: &'static str,
+}
+
+impl TftpError {
+    const fn new(code: u16, message: &'static str) -> Self {
+        Self { code, message }
+    }
+}
+
+/// A parsed read request.
+#[derive(Debug, PartialEq, Eq)]
+struct ReadRequest<'a> {
+    filename: &'a str,
+    block_size: Option<usize>,
+    tsize: bool,
+}
+
+impl<'a> ReadRequest<'a> {
+    fn parse(payload: &'a [u8]) -> Result<Self, TftpError> {
+        let (opcode, rest) = payload
+            .split_first_chunk::<2>()
+            .ok_or(TftpError::new(ERR_ILLEGAL_OPERATION, "malformed request"))?;
+        match u16::from_be_bytes(*opcode) {
+            OP_RRQ => {}
+            OP_WRQ => {
+                return Err(TftpError::new(
+                    ERR_ACCESS_VIOLATION,
+                    "write requests are not supported",
+                ));
+            }
+            _ => return Err(TftpError::new(ERR_ILLEGAL_OPERATION, "expected a request")),
+        }
+
+        // The request is a sequence of NUL-terminated strings: the filename,
+        // the mode, and then option name/value pairs.
+        let rest = rest
+            .strip_suffix(&[0])
+            .ok_or(TftpError::new(ERR_ILLEGAL_OPERATION, "malformed request"))?;
+        let mut strings = rest.split(|&b| b == 0).map(str::from_utf8);
+        let (Some(Ok(filename)), Some(Ok(mode))) = (strings.next(), strings.next()) else {
+            return Err(TftpError::new(ERR_ILLEGAL_OPERATION, "malformed request"));
+        };
+        if !mode.eq_ignore_ascii_case("octet") {
+            return Err(TftpError::new(
+                ERR_ILLEGAL_OPERATION,
+                "only octet mode is supported",
+            ));
+        }
+
+        let mut request = Self {
+            filename,
+            block_size: None,
+            tsize: false,
+        };
+        while let (Some(Ok(name)), Some(Ok(value))) = (strings.next(), strings.next()) {
+            if name.eq_ignore_ascii_case("blksize") {
+                // Ignore invalid sizes, which falls back to the default.
+                request.block_size = value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&size| size >= MIN_BLOCK_SIZE)
+                    .map(|size| size.min(MAX_BLOCK_SIZE));
+            } else if name.eq_ignore_ascii_case("tsize") {
+                request.tsize = true;
+            }
+        }
+        Ok(request)
+    }
+}
+
+/// Returns the path of `filename` under `root`, or `None` if the filename
+/// would escape `root`.
+///
+/// Both `/` and `\` are accepted as separators, and leading separators are
+/// ignored, since boot loaders are inconsistent about both.
+fn resolve_path(root: &Path, filename: &str) -> Option<PathBuf> {
+    let mut path = root.to_owned();
+    for component in filename.split(['/', '\\']).filter(|c| !c.is_empty()) {
+        let mut components = Path::new(component).components();
+        match (components.next(), components.next()) {
+            (Some(Component::CurDir), None) => {}
+            (Some(Component::Normal(c)), None) => path.push(c),
+            _ => return None,
+        }
+    }
+    Some(path)
+}
+
+fn read_error(err: std::io::Error) -> TftpError {
+    match err.kind() {
+        ErrorKind::NotFound => TftpError::new(ERR_FILE_NOT_FOUND, "file not found"),
+        ErrorKind::PermissionDenied => TftpError::new(ERR_ACCESS_VIOLATION, "access denied"),
+        _ => TftpError::new(ERR_NOT_DEFINED, "read failed"),
+    }
+}
+
+/// Reads the file at `path`, which must still be under `root` once symlinks
+/// are resolved.
+fn read_file(root: &Path, path: &Path) -> Result<Vec<u8>, TftpError> {
+    let root = root.canonicalize().map_err(read_error)?;
+    let path = path.canonicalize().map_err(read_error)?;
+    if !path.starts_with(&root) {
+        return Err(TftpError::new(ERR_ACCESS_VIOLATION, "invalid path"));
+    }
+    std::fs::read(path).map_err(read_error)
+}
+
+impl<T: Client> Access<'_, T> {
+    /// Handles a UDP packet from the guest to the gateway, returning `false`
+    /// if it is not TFTP traffic.
+    pub(crate) fn handle_tftp(
+        &mut self,
+        frame: &EthernetRepr,
+        addresses: &Ipv4Addresses,
+        src_port: u16,
+        dst_port: u16,
+        payload: &[u8],
+    ) -> Result<bool, DropReason> {
+        let state = &mut self.inner.state;
+        if addresses.dst_addr != state.params.gateway_ip {
+            return Ok(false);
+        }
+        let tftp = &mut self.inner.tftp;
+        let mut reply = Reply {
+            state,
+            client: &mut *self.client,
+            dst_mac: frame.src_addr,
+            dst_addr: addresses.src_addr,
+            dst_port: src_port,
+        };
+
+        if dst_port == TFTP_PORT {
+            let Some(root) = reply.state.params.tftp_root.clone() else {
+                return Ok(false);
+            };
+            let request = match ReadRequest::parse(payload) {
+                Ok(request) => request,
+                Err(err) => {
+                    reply.send_error(TFTP_PORT, &err);
+                    return Ok(true);
+                }
+            };
+            let Some(path) = resolve_path(&root, request.filename) else {
+                tracing::debug!(filename = request.filename, "tftp request for invalid path");
+                reply.send_error(
+                    TFTP_PORT,
+                    &TftpError::new(ERR_ACCESS_VIOLATION, "invalid path"),
+                );
+                return Ok(true);
+            };
+            if tftp.pending_reads.contains(&src_port) {
+                // A retransmit of a request that is already being read.
+                return Ok(true);
+            }
+            if tftp.pending_reads.len() >= MAX_PENDING_READS {
+                tracelimit::warn_ratelimited!("tftp read limit reached");
+                reply.send_error(TFTP_PORT, &TftpError::new(ERR_NOT_DEFINED, "server busy"));
+                return Ok(true);
+            }
+            tftp.pending_reads.insert(src_port);
+            let request = PendingRead {
+                dst_mac: frame.src_addr,
+                dst_addr: addresses.src_addr,
+                client_port: src_port,
+                filename: request.filename.to_owned(),
+                block_size: request.block_size,
+                tsize: request.tsize,
+            };
+            let sender = tftp.completed_reads.sender();
+            blocking::unblock(move || {
+                let result = read_file(&root, &path);
+                sender.send(CompletedRead { request, result });
+            })
+            .detach();
+            return Ok(true);
+        }
+
+        let Some(transfer) = tftp.transfers.get_mut(&dst_port) else {
+            return Ok(false);
+        };
+        if transfer.client_port != src_port {
+            return Ok(true);
+        }
+        let Some((opcode, rest)) = payload.split_first_chunk::<2>() else {
+            return Ok(true);
+        };
+        match u16::from_be_bytes(*opcode) {
+            OP_ACK => {
+                let Some(acked) = rest.first_chunk::<2>() else {
+                    return Ok(true);
+                };
+                let acked = u16::from_be_bytes(*acked);
+                transfer.last_activity = Instant::now();
+                // Block numbers wrap, so compare only the low 16 bits.
+                if acked == transfer.block as u16 {
+                    if transfer.block == transfer.final_block() {
+                        tracing::debug!(
+                            filename = transfer.path.as_str(),
+                            "tftp transfer complete"
+                        );
+                        tftp.transfers.remove(&dst_port);
+                        return Ok(true);
+                    }
+                    transfer.block += 1;
+                } else if acked != transfer.block.wrapping_sub(1) as u16 {
+                    // Not a duplicate of the previous ACK either, so ignore it.
+                    return Ok(true);
+                }
+                reply.send_block(dst_port, transfer);
+            }
+            OP_ERROR => {
+                tracing::debug!(
+                    filename = transfer.path.as_str(),
+                    "tftp transfer aborted by client"
+                );
+                tftp.transfers.remove(&dst_port);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+}
+
+impl<T: Client> Access<'_, T> {
+    /// Starts transfers for read requests whose files have been read.
+    pub(crate) fn poll_tftp(&mut self, cx: &mut Context<'_>) {
+        let tftp = &mut self.inner.tftp;
+        while let Poll::Ready(Ok(CompletedRead { request, result })) =
+            tftp.completed_reads.poll_recv(cx)
+        {
+            tftp.pending_reads.remove(&request.client_port);
+            let mut reply = Reply {
+                state: &mut self.inner.state,
+                client: &mut *self.client,
+                dst_mac: request.dst_mac,
+                dst_addr: request.dst_addr,
+                dst_port: request.client_port,
+            };
+            let data = match result {
+                Ok(data) => data,
+                Err(err) => {
+                    tracing::debug!(
+                        filename = request.filename.as_str(),
+                        message = err.message,
+                        "tftp request failed"
+                    );
+                    reply.send_error(TFTP_PORT, &err);
+                    continue;
+                }
+            };
+            tracing::debug!(
+                filename = request.filename.as_str(),
+                len = data.len(),
+                "tftp transfer starting"
+            );
+
+            let mut oack = Vec::new();
+            if request.block_size.is_some() || request.tsize {
+                oack.extend_from_slice(&OP_OACK.to_be_bytes());
+                let options = request
+                    .block_size
+                    .map(|size| ("blksize", size))
+                    .into_iter()
+                    .chain(request.tsize.then_some(("tsize", data.len())));
+                for (name, value) in options {
+                    oack.extend_from_slice(name.as_bytes());
+                    oack.push(0);
+                    oack.extend_from_slice(value.to_string().as_bytes());
+                    oack.push(0);
+                }
+            }
+            let mut transfer = Transfer {
+                client_port: request.client_port,
+                path: request.filename,
+                data,
+                block_size: request.block_size.unwrap_or(DEFAULT_BLOCK_SIZE),
+                block: 0,
+                oack,
+                last_activity: Instant::now(),
+            };
+            // Without options, the transfer starts with the first block
+            // rather than an option acknowledgement.
+            if transfer.oack.is_empty() {
+                transfer.block = 1;
+            }
+            let tid = tftp.insert(transfer);
+            reply.send_block(tid, &tftp.transfers[&tid]);
+        }
+    }
+}
+
+/// The destination for TFTP packets to the guest.
+struct Reply<'a, T> {
+    state: &'a mut ConsommeState,
+    client: &'a mut T,
+    dst_mac: EthernetAddress,
+    dst_addr: Ipv4Address,
+    dst_port: u16,
+}
+
+impl<T: Client> Reply<'_, T> {
+    /// Sends the current block of `transfer`, or the option acknowledgement
+    /// if no block has been sent yet.
+    fn send_block(&mut self, tid: u16, transfer: &Transfer) {
+        if transfer.block == 0 {
+            self.send(tid, &[&transfer.oack]);
+        } else {
+            let mut header = [0; 4];
+            header[..2].copy_from_slice(&OP_DATA.to_be_bytes());
+            header[2..].copy_from_slice(&(transfer.block as u16).to_be_bytes());
+            self.send(tid, &[&header, transfer.block_data(transfer.block)]);
+        }
+    }
+
+    fn send_error(&mut self, tid: u16, err: &TftpError) {
+        let mut header = [0; 4];
+        header[..2].copy_from_slice(&OP_ERROR.to_be_bytes());
+        header[2..].copy_from_slice(&err.code.to_be_bytes());
+        self.send(tid, &[&header, err.message.as_bytes(), &[0]]);
+    }
+
+    fn send(&mut self, src_port: u16, parts: &[&[u8]]) {
+        let buffer = &mut self.state.buffer;
+        let mut offset = ETHERNET_HEADER_LEN + IPV4_HEADER_LEN + UDP_HEADER_LEN;
+        let payload_offset = offset;
+        for part in parts {
+            buffer[offset..offset + part.len()].copy_from_slice(part);
+            offset += part.len();
+        }
+        let mut eth_frame = EthernetFrame::new_unchecked(&mut buffer[..]);
+        let frame_len = build_udp_packet(
+            &mut eth_frame,
+            self.state.params.gateway_ip.into(),
+            self.dst_addr.into(),
+            src_port,
+            self.dst_port,
+            offset - payload_offset,
+            self.state.params.gateway_mac,
+            self.dst_mac,
+        );
+        self.client
+            .recv(&self.state.buffer[..frame_len], &ChecksumState::UDP4);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Consomme;
+    use crate::ConsommeParams;
+    use pal_async::DefaultDriver;
+    use pal_async::timer::PolledTimer;
+    use smoltcp::wire::Ipv4Packet;
+    use smoltcp::wire::UdpPacket;
+    use std::time::Duration;
+
+    struct TestClient {
+        driver: DefaultDriver,
+        received_packets: Vec<Vec<u8>>,
+    }
+
+    impl Client for TestClient {
+        fn driver(&self) -> &dyn pal_async::driver::Driver {
+            &self.driver
+        }
+
+        fn recv(&mut self, data: &[u8], _checksum: &ChecksumState) {
+            self.received_packets.push(data.to_vec());
+        }
+
+        fn rx_mtu(&mut self) -> usize {
+            1514
+        }
+    }
+
+    fn request(parts: &[&str]) -> Vec<u8> {
+        let mut payload = OP_RRQ.to_be_bytes().to_vec();
+        for part in parts {
+            payload.extend_from_slice(part.as_bytes());
+            payload.push(0);
+        }
+        payload
+    }
+
+    #[test]
+    fn parse_read_request() {
+        assert_eq!(
+            ReadRequest::parse(&request(&["bootx64.efi", "octet"])).unwrap(),
+            ReadRequest {
+                filename: "bootx64.efi",
+                block_size: None,
+                tsize: false,
+            }
+        );
+        assert_eq!(
+            ReadRequest::parse(&request(&[
+                "bootx64.efi",
+                "OCTET",
+                "tsize",
+                "0",
+                "blksize",
+                "65464"
+            ]))
+            .unwrap(),
+            ReadRequest {
+                filename: "bootx64.efi",
+                block_size: Some(MAX_BLOCK_SIZE),
+                tsize: true,
+            }
+        );
+        assert_eq!(
+            ReadRequest::parse(&request(&["bootx64.efi", "octet", "blksize", "4"]))
+                .unwrap()
+                .block_size,
+            None
+        );
+        assert!(ReadRequest::parse(&request(&["bootx64.efi", "netascii"])).is_err());
+        assert!(ReadRequest::parse(&request(&["bootx64.efi"])).is_err());
+        assert!(ReadRequest::parse(&OP_WRQ.to_be_bytes()).is_err());
+        assert!(ReadRequest::parse(&[]).is_err());
+    }
+
+    #[test]
+    fn resolve_paths() {
+        let root = Path::new("root");
+        assert_eq!(
+            resolve_path(root, "/efi\\boot/./bootx64.efi"),
+            Some(root.join("efi").join("boot").join("bootx64.efi"))
+        );
+        assert_eq!(resolve_path(root, "../secret"), None);
+        assert_eq!(resolve_path(root, "efi/../../secret"), None);
+    }
+
+    #[test]
+    fn read_files() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("bootx64.efi"), b"boot").unwrap();
+        assert_eq!(
+            read_file(root.path(), &root.path().join("bootx64.efi")).unwrap(),
+            b"boot"
+        );
+        assert_eq!(
+            read_file(root.path(), &root.path().join("missing.efi")).unwrap_err(),
+            TftpError::new(ERR_FILE_NOT_FOUND, "file not found")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn read_file_rejects_symlink_escape() {
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret"), b"secret").unwrap();
+        let root = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path().join("secret"), root.path().join("link"))
+            .unwrap();
+        std::os::unix::fs::symlink(outside.path(), root.path().join("dir")).unwrap();
+
+        let invalid_path = TftpError::new(ERR_ACCESS_VIOLATION, "invalid path");
+        assert_eq!(
+            read_file(root.path(), &root.path().join("link")).unwrap_err(),
+            invalid_path
+        );
+        assert_eq!(
+            read_file(root.path(), &root.path().join("dir").join("secret")).unwrap_err(),
+            invalid_path
+        );
+    }
+
+    #[pal_async::async_test]
+    async fn read_request(driver: DefaultDriver) {
+        let root = tempfile::tempdir().unwrap();
+        let data = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+        std::fs::write(root.path().join("bootx64.efi"), &data).unwrap();
+
+        let mut params = ConsommeParams::new().unwrap();
+        params.tftp_root = Some(root.path().to_owned());
+        let mut consomme = Consomme::new(params);
+        let mut client = TestClient {
+            driver: driver.clone(),
+            received_packets: Vec::new(),
+        };
+
+        let payload = request(&["bootx64.efi", "octet"]);
+        let params = consomme.params_mut();
+        let mut buffer =
+            vec![0; ETHERNET_HEADER_LEN + IPV4_HEADER_LEN + UDP_HEADER_LEN + payload.len()];
+        buffer[ETHERNET_HEADER_LEN + IPV4_HEADER_LEN + UDP_HEADER_LEN..].copy_from_slice(&payload);
+        let len = build_udp_packet(
+            &mut EthernetFrame::new_unchecked(&mut buffer[..]),
+            params.client_ip.into(),
+            params.gateway_ip.into(),
+            1234,
+            TFTP_PORT,
+            payload.len(),
+            params.client_mac,
+            params.gateway_mac,
+        );
+
+        let mut access = consomme.access(&mut client);
+        access.send(&buffer[..len], &ChecksumState::NONE).unwrap();
+
+        // The file is read off-thread, so poll until the first block arrives.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            std::future::poll_fn(|cx| {
+                access.poll(cx);
+                Poll::Ready(())
+            })
+            .await;
+            if !access.client.received_packets.is_empty() {
+                break;
+            }
+            assert!(Instant::now() < deadline, "timed out waiting for tftp data");
+            PolledTimer::new(&driver)
+                .sleep(Duration::from_millis(10))
+                .await;
+        }
+
+        let packet = &access.client.received_packets[0];
+        let eth = EthernetFrame::new_unchecked(packet.as_slice());
+        let ipv4 = Ipv4Packet::new_unchecked(eth.payload());
+        let udp = UdpPacket::new_unchecked(ipv4.payload());
+        assert_eq!(udp.dst_port(), 1234);
+        let (header, block) = udp.payload().split_at(4);
+        assert_eq!(header, [0, OP_DATA as u8, 0, 1]);
+        assert_eq!(block, &data[..DEFAULT_BLOCK_SIZE]);
+    }
+}
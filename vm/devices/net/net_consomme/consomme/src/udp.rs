@@ -520,7 +520,7 @@ fn handle_gateway_udp(
                 addresses.dst_addr.into(),
                 udp,
             ),
-            _ => Ok(false),
+            dst_port => self.handle_tftp(frame, addresses, udp.src_port(), dst_port, udp.payload()),
         }
     }
 
@@ -671,7 +671,7 @@ pub fn udp_connection_count(&self) -> usize {
 /// the UDP payload is already present in the buffer at the correct offset.
 ///
 /// Returns the total length of the constructed frame.
-fn build_udp_packet<T: AsRef<[u8]> + AsMut<[u8]> + ?Sized>(
+pub(crate) fn build_udp_packet<T: AsRef<[u8]> + AsMut<[u8]> + ?Sized>(
     eth_frame: &mut EthernetFrame<&mut T>,
     src_ip: IpAddress,
     dst_ip: IpAddress,
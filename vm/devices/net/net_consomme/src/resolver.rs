@@ -28,6 +28,8 @@ pub enum ResolveConsommeError {
     Consomme(consomme::Error),
     #[error(transparent)]
     InvalidCidr(consomme::InvalidCidr),
+    #[error(transparent)]
+    InvalidGuestIp(consomme::InvalidClientIp),
     #[error("failed to create socket for port forward ({details})")]
     SocketCreation {
         #[source]
@@ -52,6 +54,21 @@ fn resolve(
                 .set_cidr(cidr)
                 .map_err(ResolveConsommeError::InvalidCidr)?;
         }
+        if let Some(guest_ip) = resource.guest_ip {
+            state
+                .set_client_ip(guest_ip)
+                .map_err(ResolveConsommeError::InvalidGuestIp)?;
+        }
+        if !resource.nameservers.is_empty() {
+            state.nameservers = resource
+                .nameservers
+                .into_iter()
+                .map(|addr| std::net::IpAddr::from(addr).into())
+                .collect();
+            state.forward_dns = true;
+        }
+        state.tftp_root = resource.tftp_root.map(Into::into);
+        state.boot_file = resource.boot_file;
         let port_forwards: Vec<PortForwardConfig> = resource
             .ports
             .into_iter()
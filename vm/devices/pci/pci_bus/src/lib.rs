@@ -31,6 +31,8 @@
 use chipset_device::poll_device::PollDevice;
 use inspect::Inspect;
 use inspect::InspectMut;
+use pci_core::capabilities::extended::aer::AerError;
+use pci_core::capabilities::extended::aer::AerSeverity;
 use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::task::Context;
@@ -151,6 +153,18 @@ fn pci_cfg_write_with_routing(
             Some(IoResult::Ok)
         }
     }
+
+    /// Injects an AER error into the device, as if the device had detected
+    /// it.
+    ///
+    /// Returns the severity of the error message the device sends upstream,
+    /// or `None` if no message is sent, because the device does not support
+    /// AER or the error is masked or its reporting is disabled.
+    ///
+    /// The default implementation does not support AER.
+    fn inject_aer_error(&mut self, _error: AerError) -> Option<AerSeverity> {
+        None
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Inspect)]
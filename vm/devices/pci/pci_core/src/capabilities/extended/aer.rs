@@ -0,0 +1,619 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! PCIe Advanced Error Reporting (AER) extended capability.
+//!
+//! The emulated device does not detect any errors on its own. Instead, errors
+//! are injected via [`AerExtendedCapability::inject_correctable`] and
+//! [`AerExtendedCapability::inject_uncorrectable`], which update the AER
+//! registers as the hardware would, so that guest error handling paths can be
+//! exercised.
+//!
+//! Root ports additionally collect the error messages sent by devices below
+//! them, via [`AerExtendedCapability::receive_error_message`], and record them
+//! in the Root Error Status and Error Source Identification registers.
+
+use super::PciExtendedCapability;
+use crate::spec::caps::ExtendedCapabilityId;
+use crate::spec::caps::aer::AER_ENDPOINT_LEN;
+use crate::spec::caps::aer::AER_ROOT_PORT_LEN;
+use crate::spec::caps::aer::AerCapsControl;
+use crate::spec::caps::aer::AerExtendedCapabilityHeader;
+use crate::spec::caps::aer::CorrectableErrors;
+use crate::spec::caps::aer::DEFAULT_CORRECTABLE_MASK;
+use crate::spec::caps::aer::DEFAULT_UNCORRECTABLE_SEVERITY;
+use crate::spec::caps::aer::ErrorSourceId;
+use crate::spec::caps::aer::RootErrorCommand;
+use crate::spec::caps::aer::RootErrorStatus;
+use crate::spec::caps::aer::SUPPORTED_CORRECTABLE_ERRORS;
+use crate::spec::caps::aer::SUPPORTED_UNCORRECTABLE_ERRORS;
+use crate::spec::caps::aer::UncorrectableErrors;
+use inspect::Inspect;
+
+/// The severity of an injected error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Inspect)]
+pub enum AerSeverity {
+    /// A correctable error.
+    Correctable,
+    /// An uncorrectable, non-fatal error.
+    NonFatal,
+    /// An uncorrectable, fatal error.
+    Fatal,
+}
+
+/// An error to inject into an [`AerExtendedCapability`].
+#[derive(Debug, Copy, Clone)]
+pub enum AerError {
+    /// Correctable errors.
+    Correctable(CorrectableErrors),
+    /// Uncorrectable errors, along with the header of the failing TLP.
+    Uncorrectable {
+        /// The errors.
+        errors: UncorrectableErrors,
+        /// The TLP header to record in the header log.
+        header_log: [u32; 4],
+    },
+}
+
+/// The result of injecting an error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AerErrorReport {
+    /// The severity of the error, which should be reflected in the PCI
+    /// Express capability's Device Status register.
+    pub severity: AerSeverity,
+    /// Whether no error message should be signaled, because all the injected
+    /// errors are masked or the guest has not enabled reporting of errors of
+    /// this severity.
+    pub masked: bool,
+}
+
+/// PCIe Advanced Error Reporting (AER) extended capability emulator.
+#[derive(Debug, Inspect)]
+pub struct AerExtendedCapability {
+    uncorrectable_status: UncorrectableErrors,
+    uncorrectable_mask: UncorrectableErrors,
+    uncorrectable_severity: UncorrectableErrors,
+    correctable_status: CorrectableErrors,
+    correctable_mask: CorrectableErrors,
+    caps_control: AerCapsControl,
+    #[inspect(hex, iter_by_index)]
+    header_log: [u32; 4],
+    root_port: bool,
+    root_error_command: RootErrorCommand,
+    root_error_status: RootErrorStatus,
+    error_source_id: ErrorSourceId,
+}
+
+impl AerExtendedCapability {
+    /// Creates an AER capability with the spec-defined default masks and
+    /// severities.
+    pub fn new() -> Self {
+        Self {
+            uncorrectable_status: UncorrectableErrors::new(),
+            uncorrectable_mask: UncorrectableErrors::new(),
+            uncorrectable_severity: UncorrectableErrors::from_bits(DEFAULT_UNCORRECTABLE_SEVERITY),
+            correctable_status: CorrectableErrors::new(),
+            correctable_mask: CorrectableErrors::from_bits(DEFAULT_CORRECTABLE_MASK),
+            caps_control: AerCapsControl::new(),
+            header_log: [0; 4],
+            root_port: false,
+            root_error_command: RootErrorCommand::new(),
+            root_error_status: RootErrorStatus::new(),
+            error_source_id: ErrorSourceId::new(),
+        }
+    }
+
+    /// Creates an AER capability for a root port, which includes the root
+    /// error registers.
+    pub fn new_root_port() -> Self {
+        Self {
+            root_port: true,
+            ..Self::new()
+        }
+    }
+
+    /// Records an error message of `severity` received from the device with
+    /// `requester_id`, as a root port does for messages from its secondary
+    /// side.
+    ///
+    /// Returns whether the guest has enabled reporting of errors of this
+    /// severity in the Root Error Command register, in which case the caller
+    /// should signal the root port's interrupt.
+    pub fn receive_error_message(&mut self, severity: AerSeverity, requester_id: u16) -> bool {
+        assert!(
+            self.root_port,
+            "error messages are only received by root ports"
+        );
+        let status = &mut self.root_error_status;
+        match severity {
+            AerSeverity::Correctable => {
+                if status.err_cor_received() {
+                    status.set_multiple_err_cor_received(true);
+                } else {
+                    status.set_err_cor_received(true);
+                    self.error_source_id.set_err_cor_source_id(requester_id);
+                }
+                self.root_error_command.correctable_error_reporting_enable()
+            }
+            AerSeverity::NonFatal | AerSeverity::Fatal => {
+                if status.err_fatal_nonfatal_received() {
+                    status.set_multiple_err_fatal_nonfatal_received(true);
+                } else {
+                    status.set_err_fatal_nonfatal_received(true);
+                    status.set_first_uncorrectable_fatal(severity == AerSeverity::Fatal);
+                    self.error_source_id
+                        .set_err_fatal_nonfatal_source_id(requester_id);
+                }
+                if severity == AerSeverity::Fatal {
+                    status.set_fatal_error_messages_received(true);
+                    self.root_error_command.fatal_error_reporting_enable()
+                } else {
+                    status.set_non_fatal_error_messages_received(true);
+                    self.root_error_command.non_fatal_error_reporting_enable()
+                }
+            }
+        }
+    }
+
+    /// Records `error` in the AER registers.
+    ///
+    /// Returns `None` if `error` contains no supported errors.
+    pub fn inject(&mut self, error: AerError) -> Option<AerErrorReport> {
+        match error {
+            AerError::Correctable(errors) => self.inject_correctable(errors),
+            AerError::Uncorrectable { errors, header_log } => {
+                self.inject_uncorrectable(errors, header_log)
+            }
+        }
+    }
+
+    /// Records the correctable `errors` in the Correctable Error Status
+    /// register.
+    ///
+    /// Returns `None` if `errors` contains no supported errors.
+    pub fn inject_correctable(&mut self, errors: CorrectableErrors) -> Option<AerErrorReport> {
+        let errors = errors.into_bits() & SUPPORTED_CORRECTABLE_ERRORS;
+        if errors == 0 {
+            return None;
+        }
+        self.correctable_status =
+            CorrectableErrors::from_bits(self.correctable_status.into_bits() | errors);
+        Some(AerErrorReport {
+            severity: AerSeverity::Correctable,
+            masked: errors & !self.correctable_mask.into_bits() == 0,
+        })
+    }
+
+    /// Records the uncorrectable `errors` in the Uncorrectable Error Status
+    /// register, logging `header_log` as the header of the failing TLP if
+    /// this is the first unmasked error.
+    ///
+    /// Returns `None` if `errors` contains no supported errors.
+    pub fn inject_uncorrectable(
+        &mut self,
+        errors: UncorrectableErrors,
+        header_log: [u32; 4],
+    ) -> Option<AerErrorReport> {
+        let errors = errors.into_bits() & SUPPORTED_UNCORRECTABLE_ERRORS;
+        if errors == 0 {
+            return None;
+        }
+        let unmasked = errors & !self.uncorrectable_mask.into_bits();
+
+        // The first error pointer and header log are only updated if they
+        // don't already refer to an error that the guest has not cleared.
+        let status = self.uncorrectable_status.into_bits();
+        let first_error = 1 << self.caps_control.first_error_pointer();
+        if unmasked != 0 && status & first_error == 0 {
+            self.caps_control
+                .set_first_error_pointer(unmasked.trailing_zeros() as u8);
+            self.header_log = header_log;
+        }
+        self.uncorrectable_status = UncorrectableErrors::from_bits(status | errors);
+
+        // Masked errors don't contribute to the severity of the error.
+        let severity_bits = if unmasked != 0 { unmasked } else { errors };
+        let severity = if severity_bits & self.uncorrectable_severity.into_bits() != 0 {
+            AerSeverity::Fatal
+        } else {
+            AerSeverity::NonFatal
+        };
+        Some(AerErrorReport {
+            severity,
+            masked: unmasked == 0,
+        })
+    }
+}
+
+impl PciExtendedCapability for AerExtendedCapability {
+    fn label(&self) -> &str {
+        "aer"
+    }
+
+    fn extended_capability_id(&self) -> u16 {
+        ExtendedCapabilityId::AER.0
+    }
+
+    fn capability_version(&self) -> u8 {
+        2
+    }
+
+    fn len(&self) -> usize {
+        if self.root_port {
+            AER_ROOT_PORT_LEN
+        } else {
+            AER_ENDPOINT_LEN
+        }
+    }
+
+    fn read_u32(&self, offset: u16) -> u32 {
+        match AerExtendedCapabilityHeader(offset) {
+            AerExtendedCapabilityHeader::HEADER => {
+                u32::from(self.extended_capability_id())
+                    | (u32::from(self.capability_version()) << 16)
+            }
+            AerExtendedCapabilityHeader::UNCORRECTABLE_STATUS => {
+                self.uncorrectable_status.into_bits()
+            }
+            AerExtendedCapabilityHeader::UNCORRECTABLE_MASK => self.uncorrectable_mask.into_bits(),
+            AerExtendedCapabilityHeader::UNCORRECTABLE_SEVERITY => {
+                self.uncorrectable_severity.into_bits()
+            }
+            AerExtendedCapabilityHeader::CORRECTABLE_STATUS => self.correctable_status.into_bits(),
+            AerExtendedCapabilityHeader::CORRECTABLE_MASK => self.correctable_mask.into_bits(),
+            AerExtendedCapabilityHeader::CAPS_CONTROL => self.caps_control.into_bits(),
+            AerExtendedCapabilityHeader::ROOT_ERROR_COMMAND if self.root_port => {
+                self.root_error_command.into_bits()
+            }
+            AerExtendedCapabilityHeader::ROOT_ERROR_STATUS if self.root_port => {
+                self.root_error_status.into_bits()
+            }
+            AerExtendedCapabilityHeader::ERROR_SOURCE_ID if self.root_port => {
+                self.error_source_id.into_bits()
+            }
+            _ if (AerExtendedCapabilityHeader::HEADER_LOG.0..AER_ENDPOINT_LEN as u16)
+                .contains(&offset) =>
+            {
+                self.header_log[(offset - AerExtendedCapabilityHeader::HEADER_LOG.0) as usize / 4]
+            }
+            _ => !0,
+        }
+    }
+
+    fn write_u32(&mut self, offset: u16, val: u32) {
+        match AerExtendedCapabilityHeader(offset) {
+            AerExtendedCapabilityHeader::UNCORRECTABLE_STATUS => {
+                // Write-1-to-clear.
+                self.uncorrectable_status =
+                    UncorrectableErrors::from_bits(self.uncorrectable_status.into_bits() & !val);
+            }
+            AerExtendedCapabilityHeader::UNCORRECTABLE_MASK => {
+                self.uncorrectable_mask =
+                    UncorrectableErrors::from_bits(val & SUPPORTED_UNCORRECTABLE_ERRORS);
+            }
+            AerExtendedCapabilityHeader::UNCORRECTABLE_SEVERITY => {
+                self.uncorrectable_severity =
+                    UncorrectableErrors::from_bits(val & SUPPORTED_UNCORRECTABLE_ERRORS);
+            }
+            AerExtendedCapabilityHeader::CORRECTABLE_STATUS => {
+                // Write-1-to-clear.
+                self.correctable_status =
+                    CorrectableErrors::from_bits(self.correctable_status.into_bits() & !val);
+            }
+            AerExtendedCapabilityHeader::CORRECTABLE_MASK => {
+                self.correctable_mask =
+                    CorrectableErrors::from_bits(val & SUPPORTED_CORRECTABLE_ERRORS);
+            }
+            AerExtendedCapabilityHeader::CAPS_CONTROL => {
+                // None of the optional ECRC or multiple header recording
+                // features are supported, so there is nothing to enable.
+            }
+            AerExtendedCapabilityHeader::ROOT_ERROR_COMMAND if self.root_port => {
+                self.root_error_command = RootErrorCommand::from_bits(val & 0x7);
+            }
+            AerExtendedCapabilityHeader::ROOT_ERROR_STATUS if self.root_port => {
+                // Write-1-to-clear, except for the interrupt message number.
+                let status = self.root_error_status.into_bits();
+                self.root_error_status = RootErrorStatus::from_bits(status & !(val & 0x7f));
+            }
+            _ => {
+                tracelimit::warn_ratelimited!(
+                    offset,
+                    value = val,
+                    "write to read-only AER extended capability register"
+                );
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self {
+            root_port: self.root_port,
+            ..Self::new()
+        };
+    }
+
+    fn as_aer_mut(&mut self) -> Option<&mut AerExtendedCapability> {
+        Some(self)
+    }
+}
+
+mod save_restore {
+    use super::*;
+    use vmcore::save_restore::RestoreError;
+    use vmcore::save_restore::SaveError;
+    use vmcore::save_restore::SaveRestore;
+
+    mod state {
+        use mesh::payload::Protobuf;
+        use vmcore::save_restore::SavedStateRoot;
+
+        #[derive(Debug, Protobuf, SavedStateRoot)]
+        #[mesh(package = "pci.capabilities.extended.aer")]
+        pub struct SavedState {
+            #[mesh(1)]
+            pub uncorrectable_status: u32,
+            #[mesh(2)]
+            pub uncorrectable_mask: u32,
+            #[mesh(3)]
+            pub uncorrectable_severity: u32,
+            #[mesh(4)]
+            pub correctable_status: u32,
+            #[mesh(5)]
+            pub correctable_mask: u32,
+            #[mesh(6)]
+            pub caps_control: u32,
+            #[mesh(7)]
+            pub header_log: [u32; 4],
+            #[mesh(8)]
+            pub root_error_command: u32,
+            #[mesh(9)]
+            pub root_error_status: u32,
+            #[mesh(10)]
+            pub error_source_id: u32,
+        }
+    }
+
+    impl SaveRestore for AerExtendedCapability {
+        type SavedState = state::SavedState;
+
+        fn save(&mut self) -> Result<Self::SavedState, SaveError> {
+            Ok(state::SavedState {
+                uncorrectable_status: self.uncorrectable_status.into_bits(),
+                uncorrectable_mask: self.uncorrectable_mask.into_bits(),
+                uncorrectable_severity: self.uncorrectable_severity.into_bits(),
+                correctable_status: self.correctable_status.into_bits(),
+                correctable_mask: self.correctable_mask.into_bits(),
+                caps_control: self.caps_control.into_bits(),
+                header_log: self.header_log,
+                root_error_command: self.root_error_command.into_bits(),
+                root_error_status: self.root_error_status.into_bits(),
+                error_source_id: self.error_source_id.into_bits(),
+            })
+        }
+
+        fn restore(&mut self, state: Self::SavedState) -> Result<(), RestoreError> {
+            let state::SavedState {
+                uncorrectable_status,
+                uncorrectable_mask,
+                uncorrectable_severity,
+                correctable_status,
+                correctable_mask,
+                caps_control,
+                header_log,
+                root_error_command,
+                root_error_status,
+                error_source_id,
+            } = state;
+            self.uncorrectable_status = UncorrectableErrors::from_bits(uncorrectable_status);
+            self.uncorrectable_mask = UncorrectableErrors::from_bits(uncorrectable_mask);
+            self.uncorrectable_severity = UncorrectableErrors::from_bits(uncorrectable_severity);
+            self.correctable_status = CorrectableErrors::from_bits(correctable_status);
+            self.correctable_mask = CorrectableErrors::from_bits(correctable_mask);
+            self.caps_control = AerCapsControl::from_bits(caps_control);
+            self.header_log = header_log;
+            self.root_error_command = RootErrorCommand::from_bits(root_error_command);
+            self.root_error_status = RootErrorStatus::from_bits(root_error_status);
+            self.error_source_id = ErrorSourceId::from_bits(error_source_id);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capabilities::extended::assert_extended_header_contract;
+    use vmcore::save_restore::SaveRestore;
+
+    #[test]
+    fn test_aer_defaults() {
+        let cap = AerExtendedCapability::new();
+
+        assert_eq!(cap.label(), "aer");
+        assert_eq!(cap.extended_capability_id(), ExtendedCapabilityId::AER.0);
+        assert_eq!(cap.len(), AER_ENDPOINT_LEN);
+        assert_extended_header_contract(&cap);
+
+        assert_eq!(
+            cap.read_u32(AerExtendedCapabilityHeader::UNCORRECTABLE_SEVERITY.0),
+            DEFAULT_UNCORRECTABLE_SEVERITY
+        );
+        assert_eq!(
+            cap.read_u32(AerExtendedCapabilityHeader::CORRECTABLE_MASK.0),
+            DEFAULT_CORRECTABLE_MASK
+        );
+    }
+
+    #[test]
+    fn test_aer_inject_correctable() {
+        let mut cap = AerExtendedCapability::new();
+
+        let report = cap
+            .inject_correctable(CorrectableErrors::new().with_bad_tlp(true))
+            .unwrap();
+        assert_eq!(report.severity, AerSeverity::Correctable);
+        assert!(!report.masked);
+
+        // Advisory non-fatal errors are masked by default.
+        let report = cap
+            .inject_correctable(CorrectableErrors::new().with_advisory_non_fatal(true))
+            .unwrap();
+        assert!(report.masked);
+
+        let status = cap.read_u32(AerExtendedCapabilityHeader::CORRECTABLE_STATUS.0);
+        assert_eq!(
+            status,
+            CorrectableErrors::new()
+                .with_bad_tlp(true)
+                .with_advisory_non_fatal(true)
+                .into_bits()
+        );
+
+        // Write-1-to-clear.
+        cap.write_u32(AerExtendedCapabilityHeader::CORRECTABLE_STATUS.0, status);
+        assert_eq!(
+            cap.read_u32(AerExtendedCapabilityHeader::CORRECTABLE_STATUS.0),
+            0
+        );
+
+        assert!(cap.inject_correctable(CorrectableErrors::new()).is_none());
+    }
+
+    #[test]
+    fn test_aer_inject_uncorrectable() {
+        let mut cap = AerExtendedCapability::new();
+        let header = [1, 2, 3, 4];
+
+        let report = cap
+            .inject_uncorrectable(
+                UncorrectableErrors::new().with_completion_timeout(true),
+                header,
+            )
+            .unwrap();
+        assert_eq!(report.severity, AerSeverity::NonFatal);
+        assert!(!report.masked);
+
+        let caps_control =
+            AerCapsControl::from(cap.read_u32(AerExtendedCapabilityHeader::CAPS_CONTROL.0));
+        assert_eq!(caps_control.first_error_pointer(), 14);
+        for (i, &dw) in header.iter().enumerate() {
+            assert_eq!(
+                cap.read_u32(AerExtendedCapabilityHeader::HEADER_LOG.0 + i as u16 * 4),
+                dw
+            );
+        }
+
+        // A second error doesn't overwrite the first error pointer or the
+        // header log until the first error is cleared.
+        let report = cap
+            .inject_uncorrectable(UncorrectableErrors::new().with_malformed_tlp(true), [5; 4])
+            .unwrap();
+        assert_eq!(report.severity, AerSeverity::Fatal);
+        let caps_control =
+            AerCapsControl::from(cap.read_u32(AerExtendedCapabilityHeader::CAPS_CONTROL.0));
+        assert_eq!(caps_control.first_error_pointer(), 14);
+        assert_eq!(cap.read_u32(AerExtendedCapabilityHeader::HEADER_LOG.0), 1);
+
+        // Masked errors are recorded but not reported.
+        cap.write_u32(
+            AerExtendedCapabilityHeader::UNCORRECTABLE_MASK.0,
+            UncorrectableErrors::new()
+                .with_unsupported_request(true)
+                .into_bits(),
+        );
+        let report = cap
+            .inject_uncorrectable(
+                UncorrectableErrors::new().with_unsupported_request(true),
+                [0; 4],
+            )
+            .unwrap();
+        assert!(report.masked);
+        assert!(
+            UncorrectableErrors::from(
+                cap.read_u32(AerExtendedCapabilityHeader::UNCORRECTABLE_STATUS.0)
+            )
+            .unsupported_request()
+        );
+    }
+
+    #[test]
+    fn test_aer_root_port() {
+        let mut cap = AerExtendedCapability::new_root_port();
+        assert_eq!(cap.len(), AER_ROOT_PORT_LEN);
+        assert_extended_header_contract(&cap);
+
+        // Reporting is disabled by default, so messages are only recorded.
+        assert!(!cap.receive_error_message(AerSeverity::Correctable, 0x100));
+        cap.write_u32(AerExtendedCapabilityHeader::ROOT_ERROR_COMMAND.0, 0x7);
+        assert!(cap.receive_error_message(AerSeverity::NonFatal, 0x200));
+        assert!(cap.receive_error_message(AerSeverity::Fatal, 0x300));
+
+        let status =
+            RootErrorStatus::from(cap.read_u32(AerExtendedCapabilityHeader::ROOT_ERROR_STATUS.0));
+        assert!(status.err_cor_received());
+        assert!(!status.multiple_err_cor_received());
+        assert!(status.err_fatal_nonfatal_received());
+        assert!(status.multiple_err_fatal_nonfatal_received());
+        assert!(!status.first_uncorrectable_fatal());
+        assert!(status.non_fatal_error_messages_received());
+        assert!(status.fatal_error_messages_received());
+
+        // The source of the first message of each kind is recorded.
+        let source =
+            ErrorSourceId::from(cap.read_u32(AerExtendedCapabilityHeader::ERROR_SOURCE_ID.0));
+        assert_eq!(source.err_cor_source_id(), 0x100);
+        assert_eq!(source.err_fatal_nonfatal_source_id(), 0x200);
+
+        // Write-1-to-clear.
+        cap.write_u32(
+            AerExtendedCapabilityHeader::ROOT_ERROR_STATUS.0,
+            status.into_bits(),
+        );
+        assert_eq!(
+            cap.read_u32(AerExtendedCapabilityHeader::ROOT_ERROR_STATUS.0),
+            0
+        );
+
+        // Reset keeps the root port registers but clears them.
+        cap.receive_error_message(AerSeverity::Fatal, 0x300);
+        cap.reset();
+        assert_eq!(cap.len(), AER_ROOT_PORT_LEN);
+        assert_eq!(
+            cap.read_u32(AerExtendedCapabilityHeader::ROOT_ERROR_COMMAND.0),
+            0
+        );
+        assert_eq!(
+            cap.read_u32(AerExtendedCapabilityHeader::ROOT_ERROR_STATUS.0),
+            0
+        );
+
+        // Endpoints don't implement the root registers.
+        let cap = AerExtendedCapability::new();
+        assert_eq!(
+            cap.read_u32(AerExtendedCapabilityHeader::ROOT_ERROR_STATUS.0),
+            !0
+        );
+    }
+
+    #[test]
+    fn test_aer_save_restore() {
+        let mut cap = AerExtendedCapability::new();
+        cap.inject_uncorrectable(UncorrectableErrors::new().with_poisoned_tlp(true), [7; 4]);
+        let status = cap.read_u32(AerExtendedCapabilityHeader::UNCORRECTABLE_STATUS.0);
+
+        let saved = cap.save().expect("save should succeed");
+        cap.reset();
+        assert_eq!(
+            cap.read_u32(AerExtendedCapabilityHeader::UNCORRECTABLE_STATUS.0),
+            0
+        );
+
+        cap.restore(saved).expect("restore should succeed");
+        assert_eq!(
+            cap.read_u32(AerExtendedCapabilityHeader::UNCORRECTABLE_STATUS.0),
+            status
+        );
+        assert_eq!(cap.read_u32(AerExtendedCapabilityHeader::HEADER_LOG.0), 7);
+    }
+}
@@ -7,6 +7,7 @@
 use vmcore::save_restore::ProtobufSaveRestore;
 
 pub mod acs;
+pub mod aer;
 
 /// A generic PCIe extended capability structure.
 pub trait PciExtendedCapability: Send + Sync + Inspect + ProtobufSaveRestore {
@@ -37,6 +38,11 @@ pub trait PciExtendedCapability: Send + Sync + Inspect + ProtobufSaveRestore {
 
     /// Reset the capability.
     fn reset(&mut self);
+
+    /// Downcast to AER capability (mutable)
+    fn as_aer_mut(&mut self) -> Option<&mut aer::AerExtendedCapability> {
+        None
+    }
 }
 
 #[cfg(test)]
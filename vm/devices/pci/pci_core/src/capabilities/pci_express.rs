@@ -4,6 +4,7 @@
 //! PCI Express Capability with Function Level Reset (FLR) support.
 
 use super::PciCapability;
+use super::extended::aer::AerSeverity;
 use crate::spec::caps::CapabilityId;
 use crate::spec::caps::pci_express;
 use crate::spec::caps::pci_express::{
@@ -311,8 +312,17 @@ pub fn with_hotplug_support(mut self, slot_number: u32) -> Self {
         // A correct implementation for ports with real delay would need to
         // diff old vs new Slot Control values and only signal completion
         // when control bits actually change, not on RW1C status clears.
+        //
+        // The attention button, power controller, and indicators let the VMM
+        // request an orderly removal: the guest's pciehp driver handles a
+        // button press by blinking the power indicator, quiescing the device,
+        // and then turning both the slot power and the power indicator off.
         self.slot_capabilities = self
             .slot_capabilities
+            .with_attention_button_present(true)
+            .with_power_controller_present(true)
+            .with_attention_indicator_present(true)
+            .with_power_indicator_present(true)
             .with_hot_plug_surprise(true)
             .with_hot_plug_capable(true)
             .with_no_command_completed_support(true)
@@ -397,6 +407,74 @@ pub fn hot_plug_interrupt_enabled(&self) -> bool {
         self.state.lock().slot_control.hot_plug_interrupt_enable()
     }
 
+    /// Sets the Attention Button Pressed bit in Slot Status, to request that
+    /// the guest release the device in the slot.
+    ///
+    /// Returns whether the guest has enabled the attention button interrupt,
+    /// in which case the caller should notify the guest via MSI. Returns
+    /// false without changing any state if the slot has no attention button.
+    pub fn press_attention_button(&self) -> bool {
+        if !self.slot_capabilities.attention_button_present() {
+            return false;
+        }
+
+        let mut state = self.state.lock();
+        state.slot_status.set_attention_button_pressed(true);
+        state.slot_control.hot_plug_interrupt_enable()
+            && state.slot_control.attention_button_pressed_enable()
+    }
+
+    /// Sets the error detected bit in Device Status for an error of
+    /// `severity`.
+    ///
+    /// Returns whether the guest has enabled reporting of errors of this
+    /// severity in Device Control, in which case the caller should signal
+    /// the error.
+    pub fn report_error(&self, severity: AerSeverity) -> bool {
+        let mut state = self.state.lock();
+        match severity {
+            AerSeverity::Correctable => {
+                state.device_status.set_correctable_error_detected(true);
+                state.device_control.correctable_error_reporting_enable()
+            }
+            AerSeverity::NonFatal => {
+                state.device_status.set_non_fatal_error_detected(true);
+                state.device_control.non_fatal_error_reporting_enable()
+            }
+            AerSeverity::Fatal => {
+                state.device_status.set_fatal_error_detected(true);
+                state.device_control.fatal_error_reporting_enable()
+            }
+        }
+    }
+
+    /// Returns the attention indicator state last programmed by the guest.
+    pub fn attention_indicator(&self) -> pci_express::IndicatorControl {
+        pci_express::IndicatorControl(self.state.lock().slot_control.attention_indicator_control())
+    }
+
+    /// Returns the power indicator state last programmed by the guest.
+    pub fn power_indicator(&self) -> pci_express::IndicatorControl {
+        pci_express::IndicatorControl(self.state.lock().slot_control.power_indicator_control())
+    }
+
+    /// Returns whether the guest has released the device in the slot.
+    ///
+    /// This requires a device to be present and the guest to have turned off
+    /// both the slot power (if the slot has a power controller) and the power
+    /// indicator, which is what pciehp does once it has quiesced the device.
+    /// The power indicator alone is not enough, since the guest also turns it
+    /// off for slots it never brought up.
+    pub fn device_released(&self) -> bool {
+        let state = self.state.lock();
+        let powered_off = !self.slot_capabilities.power_controller_present()
+            || state.slot_control.power_controller_control();
+        state.slot_status.presence_detect_state() != 0
+            && powered_off
+            && pci_express::IndicatorControl(state.slot_control.power_indicator_control())
+                == pci_express::IndicatorControl::OFF
+    }
+
     /// Returns a reference to the slot capabilities register.
     pub fn slot_capabilities(&self) -> &pci_express::SlotCapabilities {
         &self.slot_capabilities
@@ -1135,6 +1213,67 @@ fn test_link_control_retrain_link_behavior() {
         );
     }
 
+    #[test]
+    fn test_attention_button_and_indicators() {
+        let mut cap =
+            PciExpressCapability::new(DevicePortType::RootPort, None).with_hotplug_support(1);
+        assert!(cap.slot_capabilities.attention_button_present());
+        assert!(cap.slot_capabilities.attention_indicator_present());
+        assert!(cap.slot_capabilities.power_indicator_present());
+        assert!(cap.slot_capabilities.power_controller_present());
+
+        let slot_ctl_sts_offset = 0x18; // SLOT_CTL_STS offset
+
+        // The button press is latched, but no interrupt is requested until
+        // the guest enables it.
+        assert!(!cap.press_attention_button());
+        let slot_status =
+            pci_express::SlotStatus::from_bits((cap.read_u32(slot_ctl_sts_offset) >> 16) as u16);
+        assert!(slot_status.attention_button_pressed());
+
+        let slot_control = pci_express::SlotControl::new()
+            .with_attention_button_pressed_enable(true)
+            .with_hot_plug_interrupt_enable(true)
+            .with_attention_indicator_control(pci_express::IndicatorControl::OFF.0)
+            .with_power_indicator_control(pci_express::IndicatorControl::BLINK.0);
+        cap.write_u32(slot_ctl_sts_offset, slot_control.into_bits() as u32);
+        assert_eq!(
+            cap.attention_indicator(),
+            pci_express::IndicatorControl::OFF
+        );
+        assert_eq!(cap.power_indicator(), pci_express::IndicatorControl::BLINK);
+        assert!(cap.press_attention_button());
+
+        // The device only counts as released once it is present, and the
+        // guest has turned off both the slot power and the power indicator.
+        let released_control = slot_control
+            .with_power_indicator_control(pci_express::IndicatorControl::OFF.0)
+            .with_power_controller_control(true);
+        cap.write_u32(slot_ctl_sts_offset, released_control.into_bits() as u32);
+        assert!(!cap.device_released());
+        cap.set_presence_detect_state(true);
+        assert!(cap.device_released());
+        cap.write_u32(
+            slot_ctl_sts_offset,
+            released_control
+                .with_power_controller_control(false)
+                .into_bits() as u32,
+        );
+        assert!(!cap.device_released());
+        cap.write_u32(
+            slot_ctl_sts_offset,
+            released_control
+                .with_power_indicator_control(pci_express::IndicatorControl::ON.0)
+                .into_bits() as u32,
+        );
+        assert!(!cap.device_released());
+
+        // Without an attention button, presses are ignored.
+        let cap = PciExpressCapability::new(DevicePortType::RootPort, None);
+        assert!(!cap.press_attention_button());
+        assert_eq!(cap.read_u32(slot_ctl_sts_offset), 0);
+    }
+
     #[test]
     fn test_hotplug_link_capabilities() {
         // Test that Data Link Layer Link Active Reporting is enabled with hotplug
@@ -10,6 +10,9 @@
 use crate::bar_mapping::BarMappings;
 use crate::capabilities::PciCapability;
 use crate::capabilities::extended::PciExtendedCapability;
+use crate::capabilities::extended::aer::AerError;
+use crate::capabilities::extended::aer::AerErrorReport;
+use crate::capabilities::extended::aer::AerSeverity;
 use crate::spec::caps::{COMMON_HEADER_END, CapabilityId, EXT_CAP_END, EXT_CAP_START};
 use crate::spec::cfg_space;
 use crate::spec::hwid::HardwareIds;
@@ -1098,6 +1101,33 @@ pub fn is_pcie_device(&self) -> bool {
         self.common.is_pcie_device()
     }
 
+    /// Injects `error` into the AER extended capability, and reflects it in
+    /// the Device Status register of the PCI Express capability.
+    ///
+    /// The report is masked if the guest has not enabled reporting for errors
+    /// of its severity. Otherwise, the caller is responsible for sending the
+    /// error message to the root port, e.g. via
+    /// [`ConfigSpaceType1Emulator::receive_aer_error_message`].
+    ///
+    /// Returns `None` if the device has no AER capability or `error` contains
+    /// no supported errors.
+    pub fn inject_aer_error(&mut self, error: AerError) -> Option<AerErrorReport> {
+        let mut report = self
+            .common
+            .extended_capabilities
+            .iter_mut()
+            .find_map(|cap| cap.as_aer_mut())?
+            .inject(error)?;
+        let reporting_enabled = self
+            .common
+            .capabilities()
+            .iter()
+            .find_map(|cap| cap.as_pci_express())
+            .is_some_and(|pcie| pcie.report_error(report.severity));
+        report.masked |= !reporting_enabled;
+        Some(report)
+    }
+
     /// Set the presence detect state for a hotplug-capable slot.
     /// This method finds the PCIe Express capability and calls its set_presence_detect_state method.
     /// If the PCIe Express capability is not found, the call is silently ignored.
@@ -1451,6 +1481,21 @@ pub fn set_presence_detect_state(&mut self, present: bool) {
         // If no PCIe Express capability is found, silently ignore the call
     }
 
+    /// Records an error message of `severity` from the device with
+    /// `requester_id` on the secondary side of this port, in the root port
+    /// AER extended capability.
+    ///
+    /// Returns whether the guest has enabled reporting of errors of this
+    /// severity, in which case the caller should signal the port's interrupt.
+    /// Returns `false` if the port has no AER capability.
+    pub fn receive_aer_error_message(&mut self, severity: AerSeverity, requester_id: u16) -> bool {
+        self.common
+            .extended_capabilities
+            .iter_mut()
+            .find_map(|cap| cap.as_aer_mut())
+            .is_some_and(|aer| aer.receive_error_message(severity, requester_id))
+    }
+
     /// Get the list of PCI capabilities.
     pub fn capabilities(&self) -> &[Box<dyn PciCapability>] {
         self.common.capabilities()
@@ -1833,8 +1878,11 @@ fn restore(&mut self, state: Self::SavedState) -> Result<(), RestoreError> {
 mod tests {
     use super::*;
     use crate::capabilities::extended::acs::AcsExtendedCapability;
+    use crate::capabilities::extended::aer::AerExtendedCapability;
     use crate::capabilities::pci_express::PciExpressCapability;
     use crate::capabilities::read_only::ReadOnlyCapability;
+    use crate::spec::caps::aer::CorrectableErrors;
+    use crate::spec::caps::pci_express;
     use crate::spec::caps::pci_express::DevicePortType;
     use crate::spec::hwid::ClassCode;
     use crate::spec::hwid::ProgrammingInterface;
@@ -2450,6 +2498,65 @@ fn test_type1_acs_extended_capability() {
         assert_eq!((value >> 16) as u16, 0x005f);
     }
 
+    #[test]
+    fn test_type0_inject_aer_error() {
+        let mut emu = ConfigSpaceType0Emulator::new(
+            HardwareIds {
+                vendor_id: 0x1111,
+                device_id: 0x2222,
+                revision_id: 1,
+                prog_if: ProgrammingInterface::NONE,
+                sub_class: Subclass::NONE,
+                base_class: ClassCode::UNCLASSIFIED,
+                type0_sub_vendor_id: 0,
+                type0_sub_system_id: 0,
+            },
+            vec![Box::new(PciExpressCapability::new(
+                DevicePortType::Endpoint,
+                None,
+            ))],
+            vec![Box::new(AerExtendedCapability::new())],
+            DeviceBars::new(),
+        );
+        let error = AerError::Correctable(CorrectableErrors::new().with_bad_tlp(true));
+
+        // Reporting is disabled in Device Control, so the error is only
+        // recorded.
+        assert_eq!(
+            emu.inject_aer_error(error),
+            Some(AerErrorReport {
+                severity: AerSeverity::Correctable,
+                masked: true,
+            })
+        );
+        let mut value = 0;
+        emu.read_u32(0x48, &mut value).unwrap();
+        let status = pci_express::DeviceStatus::from_bits((value >> 16) as u16);
+        assert!(status.correctable_error_detected());
+        emu.read_u32(EXT_CAP_START + 0x10, &mut value).unwrap();
+        assert_eq!(
+            value,
+            CorrectableErrors::new().with_bad_tlp(true).into_bits()
+        );
+
+        // Enable correctable error reporting.
+        emu.write_u32(0x48, 0x1).unwrap();
+        assert_eq!(
+            emu.inject_aer_error(error),
+            Some(AerErrorReport {
+                severity: AerSeverity::Correctable,
+                masked: false,
+            })
+        );
+
+        // Devices without AER don't report anything.
+        let mut emu = create_type0_emulator(vec![Box::new(PciExpressCapability::new(
+            DevicePortType::Endpoint,
+            None,
+        ))]);
+        assert_eq!(emu.inject_aer_error(error), None);
+    }
+
     #[test]
     fn test_type0_emulator_save_restore() {
         use vmcore::save_restore::SaveRestore;
@@ -450,6 +450,7 @@ pub enum CapabilityId: u8 {
         /// variants on an as-needed basis!
         pub enum ExtendedCapabilityId: u16 {
             #![expect(missing_docs)] // self explanatory variants
+            AER   = 0x01,
             ACS   = 0x0D,
             ARI   = 0x0E,
             SRIOV = 0x10,
@@ -860,6 +861,16 @@ pub struct LinkStatus {
             pub link_autonomous_bandwidth_status: bool,
         }
 
+        open_enum::open_enum! {
+            /// Attention Indicator Control and Power Indicator Control
+            /// encodings in the Slot Control register.
+            pub enum IndicatorControl: u16 {
+                ON = 0b01,
+                BLINK = 0b10,
+                OFF = 0b11,
+            }
+        }
+
         /// Slot Capabilities Register
         #[bitfield(u32)]
         #[derive(IntoBytes, Immutable, KnownLayout, FromBytes, Inspect)]
@@ -1171,6 +1182,169 @@ pub struct AcsControl {
         }
     }
 
+    /// Advanced Error Reporting (AER) extended capability
+    #[expect(missing_docs)] // primarily enums/structs with self-explanatory variants
+    pub mod aer {
+        use bitfield_struct::bitfield;
+        use inspect::Inspect;
+        use zerocopy::FromBytes;
+        use zerocopy::Immutable;
+        use zerocopy::IntoBytes;
+        use zerocopy::KnownLayout;
+
+        open_enum::open_enum! {
+            /// Offsets into the AER Extended Capability structure.
+            ///
+            /// Based on PCI Express Base Specification, Section 7.8.4. The
+            /// registers from 0x2C on are only present on root ports.
+            ///
+            /// | Offset     | Bits 31-16             | Bits 15-0              |
+            /// |------------|------------------------|------------------------|
+            /// | Ext + 0x00 | Next Cap Ptr + Version | Extended Capability ID |
+            /// | Ext + 0x04 | Uncorrectable Error Status                      |
+            /// | Ext + 0x08 | Uncorrectable Error Mask                        |
+            /// | Ext + 0x0C | Uncorrectable Error Severity                    |
+            /// | Ext + 0x10 | Correctable Error Status                        |
+            /// | Ext + 0x14 | Correctable Error Mask                          |
+            /// | Ext + 0x18 | Advanced Error Capabilities and Control         |
+            /// | Ext + 0x1C | Header Log (4 DWORDs)                           |
+            /// | Ext + 0x2C | Root Error Command                              |
+            /// | Ext + 0x30 | Root Error Status                               |
+            /// | Ext + 0x34 | Error Source Identification                     |
+            pub enum AerExtendedCapabilityHeader: u16 {
+                HEADER = 0x00,
+                UNCORRECTABLE_STATUS = 0x04,
+                UNCORRECTABLE_MASK = 0x08,
+                UNCORRECTABLE_SEVERITY = 0x0C,
+                CORRECTABLE_STATUS = 0x10,
+                CORRECTABLE_MASK = 0x14,
+                CAPS_CONTROL = 0x18,
+                HEADER_LOG = 0x1C,
+                ROOT_ERROR_COMMAND = 0x2C,
+                ROOT_ERROR_STATUS = 0x30,
+                ERROR_SOURCE_ID = 0x34,
+            }
+        }
+
+        /// Length of the AER extended capability structure for an endpoint.
+        pub const AER_ENDPOINT_LEN: usize = 0x2C;
+        /// Length of the AER extended capability structure for a root port.
+        pub const AER_ROOT_PORT_LEN: usize = 0x38;
+
+        /// The uncorrectable errors that can be reported by the emulator.
+        pub const SUPPORTED_UNCORRECTABLE_ERRORS: u32 = 0x007f_f030;
+        /// The correctable errors that can be reported by the emulator.
+        pub const SUPPORTED_CORRECTABLE_ERRORS: u32 = 0x0000_f1c1;
+        /// Default Uncorrectable Error Severity register value.
+        pub const DEFAULT_UNCORRECTABLE_SEVERITY: u32 = 0x0046_2030;
+        /// Default Correctable Error Mask register value (Advisory Non-Fatal
+        /// Error masked).
+        pub const DEFAULT_CORRECTABLE_MASK: u32 = 0x0000_2000;
+
+        /// Uncorrectable Error Status, Mask, and Severity registers.
+        #[bitfield(u32)]
+        #[derive(IntoBytes, Immutable, KnownLayout, FromBytes, Inspect, PartialEq, Eq)]
+        pub struct UncorrectableErrors {
+            #[bits(4)]
+            _reserved: u32,
+            pub data_link_protocol: bool,
+            pub surprise_down: bool,
+            #[bits(6)]
+            _reserved2: u32,
+            pub poisoned_tlp: bool,
+            pub flow_control_protocol: bool,
+            pub completion_timeout: bool,
+            pub completer_abort: bool,
+            pub unexpected_completion: bool,
+            pub receiver_overflow: bool,
+            pub malformed_tlp: bool,
+            pub ecrc: bool,
+            pub unsupported_request: bool,
+            pub acs_violation: bool,
+            pub uncorrectable_internal: bool,
+            pub mc_blocked_tlp: bool,
+            pub atomic_op_egress_blocked: bool,
+            pub tlp_prefix_blocked: bool,
+            pub poisoned_tlp_egress_blocked: bool,
+            #[bits(5)]
+            _reserved3: u32,
+        }
+
+        /// Correctable Error Status and Mask registers.
+        #[bitfield(u32)]
+        #[derive(IntoBytes, Immutable, KnownLayout, FromBytes, Inspect, PartialEq, Eq)]
+        pub struct CorrectableErrors {
+            pub receiver_error: bool,
+            #[bits(5)]
+            _reserved: u32,
+            pub bad_tlp: bool,
+            pub bad_dllp: bool,
+            pub replay_num_rollover: bool,
+            #[bits(3)]
+            _reserved2: u32,
+            pub replay_timer_timeout: bool,
+            pub advisory_non_fatal: bool,
+            pub corrected_internal: bool,
+            pub header_log_overflow: bool,
+            #[bits(16)]
+            _reserved3: u32,
+        }
+
+        /// Advanced Error Capabilities and Control register.
+        #[bitfield(u32)]
+        #[derive(IntoBytes, Immutable, KnownLayout, FromBytes, Inspect)]
+        pub struct AerCapsControl {
+            #[bits(5)]
+            pub first_error_pointer: u8,
+            pub ecrc_generation_capable: bool,
+            pub ecrc_generation_enable: bool,
+            pub ecrc_check_capable: bool,
+            pub ecrc_check_enable: bool,
+            pub multiple_header_recording_capable: bool,
+            pub multiple_header_recording_enable: bool,
+            pub tlp_prefix_log_present: bool,
+            pub completion_timeout_prefix_header_log_capable: bool,
+            #[bits(19)]
+            _reserved: u32,
+        }
+
+        /// Root Error Command register.
+        #[bitfield(u32)]
+        #[derive(IntoBytes, Immutable, KnownLayout, FromBytes, Inspect)]
+        pub struct RootErrorCommand {
+            pub correctable_error_reporting_enable: bool,
+            pub non_fatal_error_reporting_enable: bool,
+            pub fatal_error_reporting_enable: bool,
+            #[bits(29)]
+            _reserved: u32,
+        }
+
+        /// Root Error Status register.
+        #[bitfield(u32)]
+        #[derive(IntoBytes, Immutable, KnownLayout, FromBytes, Inspect)]
+        pub struct RootErrorStatus {
+            pub err_cor_received: bool,
+            pub multiple_err_cor_received: bool,
+            pub err_fatal_nonfatal_received: bool,
+            pub multiple_err_fatal_nonfatal_received: bool,
+            pub first_uncorrectable_fatal: bool,
+            pub non_fatal_error_messages_received: bool,
+            pub fatal_error_messages_received: bool,
+            #[bits(20)]
+            _reserved: u32,
+            #[bits(5)]
+            pub advanced_error_interrupt_message_number: u8,
+        }
+
+        /// Error Source Identification register.
+        #[bitfield(u32)]
+        #[derive(IntoBytes, Immutable, KnownLayout, FromBytes, Inspect)]
+        pub struct ErrorSourceId {
+            pub err_cor_source_id: u16,
+            pub err_fatal_nonfatal_source_id: u16,
+        }
+    }
+
     /// Designated Vendor-Specific Extended Capability (DVSEC)
     #[expect(missing_docs)] // primarily enums/structs with self-explanatory variants
     pub mod dvsec {
@@ -20,6 +20,9 @@
 use pci_core::bus_range::AssignedBusRange;
 use pci_core::capabilities::extended::PciExtendedCapability;
 use pci_core::capabilities::extended::acs::AcsExtendedCapability;
+use pci_core::capabilities::extended::aer::AerError;
+use pci_core::capabilities::extended::aer::AerExtendedCapability;
+use pci_core::capabilities::extended::aer::AerSeverity;
 use pci_core::capabilities::msi_cap::MsiCapability;
 use pci_core::capabilities::pci_express::PciExpressCapability;
 use pci_core::cfg_space_emu::BarMemoryKind;
@@ -67,6 +70,11 @@ pub struct PciePortSettings {
     /// CXL DVSECs are added only when this is `Some` and either `cache_capable`
     /// or `mem_capable` is set.
     pub cxl_flex_bus_port_capability: Option<CxlFlexBusPortDvsecCapability>,
+
+    /// Whether to expose the root port AER extended capability, which
+    /// collects error messages from the device below the port. Only
+    /// supported on root ports.
+    pub aer: bool,
 }
 
 /// Generic PCIe port BAR definition.
@@ -342,6 +350,12 @@ pub fn new(
         let msi_capability = MsiCapability::new(0, true, false, msi_target);
         let acs_supported =
             filter_acs_capabilities_for_bridge(&port_type, settings.acs_capabilities_supported);
+        let root_port_aer = settings.aer && matches!(port_type, DevicePortType::RootPort);
+        if settings.aer && !root_port_aer {
+            tracelimit::warn_ratelimited!(
+                "ignoring AER setting; only root ports collect error messages"
+            );
+        }
 
         let pcie_cap = if hotplug {
             let slot_num = slot_number.unwrap_or(0);
@@ -361,6 +375,10 @@ pub fn new(
 
         let mut extended_capabilities = extended_capabilities;
 
+        if root_port_aer {
+            extended_capabilities.push(Box::new(AerExtendedCapability::new_root_port()));
+        }
+
         if cxl_enabled {
             // CXL Spec mandates that a CXL root port or downstream switch port must have CXL Port DVSEC
             // and CXL Flex Bus Port DVSEC.
@@ -634,15 +652,20 @@ fn fire_hotplug_msi(&self) {
             .is_some_and(|pcie| pcie.hot_plug_interrupt_enabled());
 
         if hotplug_enabled {
-            if let Some(interrupt) = self
-                .cfg_space
-                .capabilities()
-                .iter()
-                .find_map(|cap| cap.as_msi_cap())
-                .and_then(|msi| msi.interrupt())
-            {
-                interrupt.deliver();
-            }
+            self.deliver_msi();
+        }
+    }
+
+    /// Deliver the port's MSI, if the guest has configured it.
+    fn deliver_msi(&self) {
+        if let Some(interrupt) = self
+            .cfg_space
+            .capabilities()
+            .iter()
+            .find_map(|cap| cap.as_msi_cap())
+            .and_then(|msi| msi.interrupt())
+        {
+            interrupt.deliver();
         }
     }
 
@@ -823,6 +846,70 @@ pub fn hotplug_remove_device(&mut self) -> anyhow::Result<()> {
         self.fire_hotplug_msi();
         Ok(())
     }
+
+    /// Request that the guest release the device in this port, by pressing
+    /// the slot's attention button.
+    ///
+    /// The guest's pciehp driver responds by blinking the power indicator,
+    /// quiescing the device, and then turning the power indicator off. The
+    /// caller can poll [`Self::hotplug_device_released`] to determine when it
+    /// is safe to call [`Self::hotplug_remove_device`].
+    pub fn hotplug_request_removal(&mut self) -> anyhow::Result<()> {
+        let pcie = self
+            .cfg_space
+            .capabilities()
+            .iter()
+            .find_map(|cap| cap.as_pci_express())
+            .filter(|pcie| pcie.slot_capabilities().attention_button_present());
+
+        let Some(pcie) = pcie else {
+            bail!("port '{}' has no attention button", self.name);
+        };
+        if self.link.is_none() {
+            bail!("port '{}' is empty", self.name);
+        }
+
+        if pcie.press_attention_button() {
+            self.deliver_msi();
+        }
+        Ok(())
+    }
+
+    /// Returns whether the guest has released the device in this port, as
+    /// indicated by it turning off the slot's power and power indicator.
+    pub fn hotplug_device_released(&self) -> bool {
+        self.link.is_some()
+            && self
+                .cfg_space
+                .capabilities()
+                .iter()
+                .find_map(|cap| cap.as_pci_express())
+                .is_some_and(|pcie| pcie.device_released())
+    }
+
+    /// Injects an AER error into the device in this port, and delivers the
+    /// resulting error message to the port, signaling the port's MSI if the
+    /// guest has enabled reporting for it.
+    ///
+    /// Returns the severity of the error message sent by the device, or
+    /// `None` if the device did not send one.
+    pub fn inject_aer_error(&mut self, error: AerError) -> anyhow::Result<Option<AerSeverity>> {
+        let Some((_, device)) = &mut self.link else {
+            bail!("port '{}' is empty", self.name);
+        };
+        let Some(severity) = device.inject_aer_error(error) else {
+            return Ok(None);
+        };
+        // The device is function 0 of the port's secondary bus.
+        let requester_id = u16::from(*self.cfg_space.assigned_bus_range().start()) << 8;
+        if self
+            .cfg_space
+            .receive_aer_error_message(severity, requester_id)
+        {
+            self.deliver_msi();
+        }
+        Ok(Some(severity))
+    }
 }
 
 #[cfg(test)]
@@ -864,6 +951,7 @@ fn make_cxl_bar_port() -> PcieDownstreamPort {
                 cxl_flex_bus_port_capability: Some(
                     CxlFlexBusPortDvsecCapability::new().with_mem_capable(true),
                 ),
+                aer: false,
             },
             Some(&mut mmio),
             Some(PortBarDefinition {
@@ -1038,6 +1126,142 @@ fn test_add_pcie_device_without_hotplug() {
         );
     }
 
+    #[test]
+    fn test_hotplug_request_removal() {
+        use pci_core::spec::caps::pci_express::IndicatorControl;
+        use pci_core::spec::caps::pci_express::SlotControl;
+        use pci_core::spec::caps::pci_express::SlotStatus;
+        use pci_core::spec::hwid::{ClassCode, ProgrammingInterface, Subclass};
+
+        let hardware_ids = HardwareIds {
+            vendor_id: 0x1234,
+            device_id: 0x5678,
+            revision_id: 0,
+            prog_if: ProgrammingInterface::NONE,
+            sub_class: Subclass::BRIDGE_PCI_TO_PCI,
+            base_class: ClassCode::BRIDGE,
+            type0_sub_vendor_id: 0,
+            type0_sub_system_id: 0,
+        };
+
+        let msi_conn = pci_core::msi::MsiConnection::new(AssignedBusRange::new(), 0);
+        let mut port = PcieDownstreamPort::new(
+            "test-port",
+            hardware_ids,
+            DevicePortType::RootPort,
+            false,
+            Some(1), // Enable hotplug with slot number 1
+            msi_conn.target(),
+            PciePortSettings::default(),
+            None,
+            None,
+        );
+
+        // 0x40 (cap start) + 0x18 (slot control/status)
+        let slot_ctl_sts_offset = 0x58;
+        let read_slot_status = |port: &PcieDownstreamPort| {
+            let mut val = 0u32;
+            port.cfg_space
+                .read_u32(slot_ctl_sts_offset, &mut val)
+                .unwrap();
+            SlotStatus::from_bits((val >> 16) as u16)
+        };
+
+        // An empty slot has nothing to release, even if the guest turned its
+        // power and power indicator off.
+        let released_control = SlotControl::new()
+            .with_power_indicator_control(IndicatorControl::OFF.0)
+            .with_power_controller_control(true);
+        port.cfg_space
+            .write_u32(slot_ctl_sts_offset, released_control.into_bits() as u32)
+            .unwrap();
+        assert!(port.hotplug_request_removal().is_err());
+        assert!(!port.hotplug_device_released());
+
+        // The guest powers the slot back on when a device is added.
+        port.hotplug_add_device("mock-device", Box::new(MockDevice))
+            .unwrap();
+        let powered_on = SlotControl::new()
+            .with_attention_button_pressed_enable(true)
+            .with_hot_plug_interrupt_enable(true)
+            .with_power_indicator_control(IndicatorControl::ON.0);
+        port.cfg_space
+            .write_u32(slot_ctl_sts_offset, powered_on.into_bits() as u32)
+            .unwrap();
+        assert!(!port.hotplug_device_released());
+
+        port.hotplug_request_removal().unwrap();
+        assert!(read_slot_status(&port).attention_button_pressed());
+        assert!(!port.hotplug_device_released());
+
+        // The guest acknowledges the button press. Turning off the power
+        // indicator while the slot is still powered is not a release.
+        let ack = SlotStatus::new().with_attention_button_pressed(true);
+        port.cfg_space
+            .write_u32(
+                slot_ctl_sts_offset,
+                powered_on
+                    .with_power_indicator_control(IndicatorControl::OFF.0)
+                    .into_bits() as u32
+                    | (ack.into_bits() as u32) << 16,
+            )
+            .unwrap();
+        assert!(!read_slot_status(&port).attention_button_pressed());
+        assert!(!port.hotplug_device_released());
+
+        // Once the slot is powered off as well, the device can be removed.
+        port.cfg_space
+            .write_u32(
+                slot_ctl_sts_offset,
+                powered_on
+                    .with_power_indicator_control(IndicatorControl::OFF.0)
+                    .with_power_controller_control(true)
+                    .into_bits() as u32,
+            )
+            .unwrap();
+        assert!(port.hotplug_device_released());
+
+        port.hotplug_remove_device().unwrap();
+        assert!(!port.hotplug_device_released());
+        assert_eq!(read_slot_status(&port).presence_detect_state(), 0);
+    }
+
+    #[test]
+    fn test_hotplug_request_removal_without_hotplug() {
+        use pci_core::spec::hwid::{ClassCode, ProgrammingInterface, Subclass};
+
+        let hardware_ids = HardwareIds {
+            vendor_id: 0x1234,
+            device_id: 0x5678,
+            revision_id: 0,
+            prog_if: ProgrammingInterface::NONE,
+            sub_class: Subclass::BRIDGE_PCI_TO_PCI,
+            base_class: ClassCode::BRIDGE,
+            type0_sub_vendor_id: 0,
+            type0_sub_system_id: 0,
+        };
+
+        let msi_conn = pci_core::msi::MsiConnection::new(AssignedBusRange::new(), 0);
+        let mut port = PcieDownstreamPort::new(
+            "test-port",
+            hardware_ids,
+            DevicePortType::RootPort,
+            false,
+            None, // No hotplug
+            msi_conn.target(),
+            PciePortSettings::default(),
+            None,
+            None,
+        );
+        port.add_pcie_device("test-port", "mock-device", Box::new(MockDevice))
+            .unwrap();
+
+        // Without an attention button, the guest cannot be asked to release
+        // the device.
+        assert!(port.hotplug_request_removal().is_err());
+        assert!(!port.hotplug_device_released());
+    }
+
     #[test]
     fn test_direct_child_bus_reads_use_forward_for_multifunction_devices() {
         use pci_core::spec::hwid::{ClassCode, ProgrammingInterface, Subclass};
@@ -1300,6 +1524,7 @@ fn test_invalid_cxl_component_register_locator_disables_cxl_exposure() {
                 cxl_flex_bus_port_capability: Some(
                     CxlFlexBusPortDvsecCapability::new().with_mem_capable(true),
                 ),
+                aer: false,
             },
             None,
             Some(PortBarDefinition {
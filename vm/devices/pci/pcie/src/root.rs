@@ -26,6 +26,8 @@
 use memory_range::MemoryRange;
 use pci_bus::GenericPciBusDevice;
 use pci_core::bus_range::AssignedBusRange;
+use pci_core::capabilities::extended::aer::AerError;
+use pci_core::capabilities::extended::aer::AerSeverity;
 use pci_core::msi::MsiTarget;
 use pci_core::spec::caps::pci_express::DevicePortType;
 use pci_core::spec::hwid::ClassCode;
@@ -291,6 +293,42 @@ pub fn hotplug_remove_device(&mut self, port_name: &str) -> anyhow::Result<()> {
         root_port.port.hotplug_remove_device()
     }
 
+    /// Request that the guest release the device in a named port. See
+    /// [`crate::port::PcieDownstreamPort::hotplug_request_removal`].
+    pub fn hotplug_request_removal(&mut self, port_name: &str) -> anyhow::Result<()> {
+        let (_, (_, root_port)) = self
+            .ports
+            .iter_mut()
+            .find(|(_, (name, _))| name.as_ref() == port_name)
+            .ok_or_else(|| anyhow::anyhow!("port '{}' not found", port_name))?;
+        root_port.port.hotplug_request_removal()
+    }
+
+    /// Returns whether the guest has released the device in a named port.
+    pub fn hotplug_device_released(&self, port_name: &str) -> anyhow::Result<bool> {
+        let (_, (_, root_port)) = self
+            .ports
+            .iter()
+            .find(|(_, (name, _))| name.as_ref() == port_name)
+            .ok_or_else(|| anyhow::anyhow!("port '{}' not found", port_name))?;
+        Ok(root_port.port.hotplug_device_released())
+    }
+
+    /// Injects an AER error into the device in a named port. See
+    /// [`crate::port::PcieDownstreamPort::inject_aer_error`].
+    pub fn inject_aer_error(
+        &mut self,
+        port_name: &str,
+        error: AerError,
+    ) -> anyhow::Result<Option<AerSeverity>> {
+        let (_, (_, root_port)) = self
+            .ports
+            .iter_mut()
+            .find(|(_, (name, _))| name.as_ref() == port_name)
+            .ok_or_else(|| anyhow::anyhow!("port '{}' not found", port_name))?;
+        root_port.port.inject_aer_error(error)
+    }
+
     /// Returns the size of the ECAM MMIO region this root complex is emulating.
     pub fn ecam_size(&self) -> u64 {
         ecam_size_from_bus_numbers(self.start_bus, self.end_bus)
@@ -1210,6 +1248,169 @@ async fn test_reset() {
         assert_eq!(value_16, COMMAND_REG_VALUE);
     }
 
+    /// An endpoint with the AER capability, backed by the real config space
+    /// emulator.
+    struct AerTestEndpoint(pci_core::cfg_space_emu::ConfigSpaceType0Emulator);
+
+    impl GenericPciBusDevice for AerTestEndpoint {
+        fn pci_cfg_read(&mut self, offset: u16, value: &mut u32) -> Option<IoResult> {
+            Some(self.0.read_u32(offset, value))
+        }
+
+        fn pci_cfg_write(&mut self, offset: u16, value: u32) -> Option<IoResult> {
+            Some(self.0.write_u32(offset, value))
+        }
+
+        fn inject_aer_error(&mut self, error: AerError) -> Option<AerSeverity> {
+            let report = self.0.inject_aer_error(error)?;
+            (!report.masked).then_some(report.severity)
+        }
+    }
+
+    #[test]
+    fn test_inject_aer_error() {
+        use pci_core::capabilities::PciCapability;
+        use pci_core::capabilities::extended::aer::AerExtendedCapability;
+        use pci_core::capabilities::pci_express::PciExpressCapability;
+        use pci_core::cfg_space_emu::ConfigSpaceType0Emulator;
+        use pci_core::cfg_space_emu::DeviceBars;
+        use pci_core::spec::caps::aer::AerExtendedCapabilityHeader;
+        use pci_core::spec::caps::aer::ErrorSourceId;
+        use pci_core::spec::caps::aer::RootErrorStatus;
+        use pci_core::spec::caps::aer::UncorrectableErrors;
+        use pci_core::test_helpers::TestPciInterruptController;
+
+        const PORT_AER: u64 = 0x100;
+        const ENDPOINT_ECAM: u64 = 256 * 4096;
+        const ENDPOINT_DEVICE_CONTROL: u64 = 0x48;
+
+        let mut register_mmio = TestPcieMmioRegistration {};
+        let rc_bus_range = AssignedBusRange::new();
+        rc_bus_range.set_bus_range(0, 255);
+        let msi_conn = pci_core::msi::MsiConnection::new(rc_bus_range, 0);
+        let msi_controller = TestPciInterruptController::new();
+        msi_conn.connect(msi_controller.signal_msi());
+        let mut rc = GenericPcieRootComplex::new(
+            &mut register_mmio,
+            0,
+            255,
+            None,
+            MemoryRange::new(0..ecam_size_from_bus_numbers(0, 255)),
+            vec![GenericPcieRootPortDefinition {
+                name: "aer-port".into(),
+                hotplug: false,
+                settings: PciePortSettings {
+                    aer: true,
+                    ..Default::default()
+                },
+            }],
+            msi_conn.target(),
+        );
+
+        let endpoint = AerTestEndpoint(ConfigSpaceType0Emulator::new(
+            HardwareIds {
+                vendor_id: 0x1111,
+                device_id: 0x2222,
+                revision_id: 0,
+                prog_if: ProgrammingInterface::NONE,
+                sub_class: Subclass::NONE,
+                base_class: ClassCode::UNCLASSIFIED,
+                type0_sub_vendor_id: 0,
+                type0_sub_system_id: 0,
+            },
+            vec![Box::new(PciExpressCapability::new(
+                DevicePortType::Endpoint,
+                None,
+            ))],
+            vec![Box::new(AerExtendedCapability::new())],
+            DeviceBars::new(),
+        ));
+        rc.add_pcie_device(0, "aer-ep", Box::new(endpoint)).unwrap();
+
+        // Assign bus 1 to the port and enable the port's MSI.
+        rc.mmio_write(0x19, &[1]).unwrap();
+        rc.mmio_write(0x1A, &[1]).unwrap();
+        {
+            let (_, root_port) = rc.ports.get_mut(&0).unwrap();
+            let msi = root_port
+                .port
+                .cfg_space
+                .capabilities_mut()
+                .iter_mut()
+                .find_map(|cap| cap.as_msi_cap_mut())
+                .unwrap();
+            msi.write_u32(4, 0xfee0_0000);
+            msi.write_u32(12, 0x42);
+            msi.write_u32(0, 0x0001_0000);
+        }
+
+        let error = AerError::Uncorrectable {
+            errors: UncorrectableErrors::new().with_malformed_tlp(true),
+            header_log: [0; 4],
+        };
+
+        // The endpoint has not enabled error reporting, so no message is
+        // sent.
+        assert_eq!(rc.inject_aer_error("aer-port", error).unwrap(), None);
+        let mut value = 0u32;
+        rc.mmio_read(
+            PORT_AER + AerExtendedCapabilityHeader::ROOT_ERROR_STATUS.0 as u64,
+            value.as_mut_bytes(),
+        )
+        .unwrap();
+        assert_eq!(value, 0);
+
+        // Enable reporting on the endpoint. The port records the message but
+        // doesn't interrupt until the guest enables it in Root Error Command.
+        rc.mmio_write(ENDPOINT_ECAM + ENDPOINT_DEVICE_CONTROL, 0x7u32.as_bytes())
+            .unwrap();
+        assert_eq!(
+            rc.inject_aer_error("aer-port", error).unwrap(),
+            Some(AerSeverity::Fatal)
+        );
+        assert_eq!(msi_controller.get_next_interrupt(), None);
+        rc.mmio_read(
+            PORT_AER + AerExtendedCapabilityHeader::ROOT_ERROR_STATUS.0 as u64,
+            value.as_mut_bytes(),
+        )
+        .unwrap();
+        let status = RootErrorStatus::from(value);
+        assert!(status.err_fatal_nonfatal_received());
+        assert!(status.first_uncorrectable_fatal());
+        assert!(status.fatal_error_messages_received());
+        rc.mmio_read(
+            PORT_AER + AerExtendedCapabilityHeader::ERROR_SOURCE_ID.0 as u64,
+            value.as_mut_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            ErrorSourceId::from(value).err_fatal_nonfatal_source_id(),
+            0x100
+        );
+
+        // Clear the status and enable fatal error interrupts.
+        rc.mmio_write(
+            PORT_AER + AerExtendedCapabilityHeader::ROOT_ERROR_STATUS.0 as u64,
+            status.into_bits().as_bytes(),
+        )
+        .unwrap();
+        rc.mmio_write(
+            PORT_AER + AerExtendedCapabilityHeader::ROOT_ERROR_COMMAND.0 as u64,
+            0x4u32.as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            rc.inject_aer_error("aer-port", error).unwrap(),
+            Some(AerSeverity::Fatal)
+        );
+        assert_eq!(
+            msi_controller.get_next_interrupt(),
+            Some((0xfee0_0000, 0x42))
+        );
+
+        assert!(rc.inject_aer_error("missing-port", error).is_err());
+    }
+
     #[test]
     fn test_root_port_hotplug_options() {
         // Test with hotplug disabled (None)
@@ -50,8 +50,11 @@
 pub struct VpciBus {
     #[inspect(mut, flatten)]
     bus_device: VpciBusDevice,
+    /// The offered channel, or `None` while the channel is being offered
+    /// again.
     #[inspect(flatten)]
-    channel: SimpleDeviceHandle<VpciChannel>,
+    channel: Option<SimpleDeviceHandle<VpciChannel>>,
+    running: bool,
 }
 
 /// The chipset device portion of the VPCI bus.
@@ -106,6 +109,20 @@ pub enum CreateBusError {
     Offer(#[source] anyhow::Error),
 }
 
+/// An error offering the channel of a VPCI bus again.
+#[derive(Debug, Error)]
+pub enum ReofferBusError {
+    /// The channel is already being offered again.
+    #[error("vpci vmbus channel is not offered")]
+    NotOffered,
+    /// The vmbus server stopped while the channel was being revoked.
+    #[error("vmbus server stopped while revoking vpci vmbus channel")]
+    ServerStopped,
+    /// The vmbus channel offer failed.
+    #[error("failed to offer vpci vmbus channel")]
+    Offer(#[source] anyhow::Error),
+}
+
 impl VpciBusDevice {
     /// Returns a new VPCI bus device, along with the vmbus channel used for bus
     /// communications.
@@ -170,22 +187,62 @@ pub async fn new(
 
         Ok(Self {
             bus_device: bus,
-            channel,
+            channel: Some(channel),
+            running: false,
         })
     }
+
+    /// Revokes the bus's vmbus channel and then offers it again, as when the
+    /// device is hot-removed from the guest and added back.
+    ///
+    /// This takes the bus's mutex rather than `&mut self` so that the lock is
+    /// not held while the guest releases the channel, since the guest may
+    /// access the bus's config space in the meantime.
+    pub async fn reoffer(
+        this: &CloseableMutex<Self>,
+        driver_source: &VmTaskDriverSource,
+        vmbus: &dyn vmbus_channel::bus::ParentBus,
+    ) -> Result<(), ReofferBusError> {
+        let channel = this
+            .lock()
+            .channel
+            .take()
+            .ok_or(ReofferBusError::NotOffered)?;
+        let channel = channel
+            .revoke()
+            .await
+            .ok_or(ReofferBusError::ServerStopped)?;
+        let channel = offer_simple_device(driver_source, vmbus, channel)
+            .await
+            .map_err(ReofferBusError::Offer)?;
+        let mut this = this.lock();
+        if this.running {
+            channel.start();
+        }
+        this.channel = Some(channel);
+        Ok(())
+    }
 }
 
 impl ChangeDeviceState for VpciBus {
     fn start(&mut self) {
-        self.channel.start();
+        self.running = true;
+        if let Some(channel) = &self.channel {
+            channel.start();
+        }
     }
 
     async fn stop(&mut self) {
-        self.channel.stop().await;
+        self.running = false;
+        if let Some(channel) = &self.channel {
+            channel.stop().await;
+        }
     }
 
     async fn reset(&mut self) {
-        self.channel.reset().await;
+        if let Some(channel) = &self.channel {
+            channel.reset().await;
+        }
     }
 }
 
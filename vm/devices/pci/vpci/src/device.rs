@@ -38,7 +38,6 @@
 use vmbus_channel::simple::SimpleVmbusDevice;
 use vmbus_ring as ring;
 use vmbus_ring::RingMem;
-use vmcore::save_restore::NoSavedState;
 use vmcore::vpci_msi::MapVpciInterrupt;
 use vmcore::vpci_msi::MsiAddressData;
 use vmcore::vpci_msi::RegisterInterruptError;
@@ -1359,9 +1358,9 @@ pub(crate) fn new(
 }
 
 #[async_trait]
-impl<M: 'static + Send + Sync + RingMem> SimpleVmbusDevice<M> for VpciChannel {
-    type SavedState = NoSavedState;
-    type Runner = VpciChannelState<M>;
+impl SimpleVmbusDevice for VpciChannel {
+    type SavedState = save_restore::state::SavedState;
+    type Runner = VpciChannelState;
 
     fn offer(&self) -> OfferParams {
         OfferParams {
@@ -1379,7 +1378,7 @@ fn inspect(&mut self, req: inspect::Request<'_>, runner: Option<&mut Self::Runne
 
     fn open(
         &mut self,
-        channel: RawAsyncChannel<M>,
+        channel: RawAsyncChannel<GpadlRingMem>,
         _guest_memory: guestmem::GuestMemory,
     ) -> Result<Self::Runner, ChannelOpenError> {
         Ok(VpciChannelState {
@@ -1414,7 +1413,147 @@ fn supports_save_restore(
     ) -> Option<
         &mut dyn SaveRestoreSimpleVmbusDevice<SavedState = Self::SavedState, Runner = Self::Runner>,
     > {
-        None
+        // The interrupts can only be restored if the mapper can put them back
+        // at the same addresses, since those are already programmed into the
+        // device. Otherwise, revoke and reoffer the channel.
+        if self.msi_mapper.supports_save_restore() {
+            Some(self)
+        } else {
+            None
+        }
+    }
+}
+
+impl SaveRestoreSimpleVmbusDevice for VpciChannel {
+    fn save_open(&mut self, runner: &Self::Runner) -> Self::SavedState {
+        self.save_state(&runner.state)
+    }
+
+    fn restore_open(
+        &mut self,
+        state: Self::SavedState,
+        channel: RawAsyncChannel<GpadlRingMem>,
+    ) -> Result<Self::Runner, ChannelOpenError> {
+        let conn = Connection {
+            queue: Queue::new(channel)?,
+        };
+        let state = self.restore_state(state)?;
+        Ok(VpciChannelState { conn, state })
+    }
+}
+
+mod save_restore {
+    use super::*;
+    use anyhow::Context as _;
+
+    pub mod state {
+        use mesh::payload::Protobuf;
+        use vmcore::save_restore::SavedStateRoot;
+        use vmcore::vpci_msi::SavedInterrupt;
+
+        #[derive(Protobuf, SavedStateRoot)]
+        #[mesh(package = "vpci.channel")]
+        pub struct SavedState {
+            /// The negotiated protocol state, or `None` if version negotiation
+            /// had not completed.
+            #[mesh(1)]
+            pub ready: Option<SavedReadyState>,
+            #[mesh(2)]
+            pub config_space_offset: Option<u64>,
+            #[mesh(3)]
+            pub bars_set: bool,
+            /// The guest's interrupts, as remapped by the interrupt mapper.
+            #[mesh(4)]
+            pub interrupts: Vec<SavedInterrupt>,
+        }
+
+        #[derive(Protobuf)]
+        #[mesh(package = "vpci.channel")]
+        pub struct SavedReadyState {
+            #[mesh(1)]
+            pub vpci_version: u32,
+            #[mesh(2)]
+            pub send_device: bool,
+            #[mesh(3)]
+            pub send_completion: Option<u64>,
+        }
+    }
+
+    impl VpciChannel {
+        pub(super) fn save_state(&self, protocol_state: &ProtocolState) -> state::SavedState {
+            let ready = match protocol_state {
+                ProtocolState::Init => None,
+                ProtocolState::Ready(ReadyState {
+                    send_device,
+                    send_completion,
+                    vpci_version,
+                }) => Some(state::SavedReadyState {
+                    vpci_version: vpci_version.0,
+                    send_device: *send_device,
+                    send_completion: *send_completion,
+                }),
+            };
+            // Only the interrupts registered by this channel are saved, but
+            // with the targets tracked by the mapper, since the guest may have
+            // retargeted them since.
+            let interrupts = self
+                .msi_mapper
+                .save_interrupts()
+                .into_iter()
+                .filter(|saved| {
+                    self.interrupts.contains(&MsiAddressData {
+                        address: saved.address,
+                        data: saved.data,
+                    })
+                })
+                .collect();
+            state::SavedState {
+                ready,
+                config_space_offset: self.config_space.offset().get(),
+                bars_set: self.bars_set,
+                interrupts,
+            }
+        }
+
+        pub(super) fn restore_state(
+            &mut self,
+            state: state::SavedState,
+        ) -> anyhow::Result<ProtocolState> {
+            let state::SavedState {
+                ready,
+                config_space_offset,
+                bars_set,
+                interrupts,
+            } = state;
+
+            self.msi_mapper
+                .restore_interrupts(&interrupts)
+                .context("failed to restore interrupts")?;
+            self.interrupts = interrupts
+                .iter()
+                .map(|saved| MsiAddressData {
+                    address: saved.address,
+                    data: saved.data,
+                })
+                .collect();
+            if let Some(offset) = config_space_offset {
+                self.config_space.map(offset);
+            }
+            self.bars_set = bars_set;
+
+            Ok(match ready {
+                None => ProtocolState::Init,
+                Some(state::SavedReadyState {
+                    vpci_version,
+                    send_device,
+                    send_completion,
+                }) => ProtocolState::Ready(ReadyState {
+                    send_device,
+                    send_completion,
+                    vpci_version: protocol::ProtocolVersion(vpci_version),
+                }),
+            })
+        }
     }
 }
 
@@ -1426,7 +1565,10 @@ fn supports_save_restore(
 #[cfg(test)]
 mod tests {
     use super::Connection;
+    use super::InterruptResourceRequest;
+    use super::InterruptType;
     use super::ProtocolState;
+    use super::ReadyState;
     use super::VpciChannel;
     use super::VpciChannelState;
     use super::VpciConfigSpace;
@@ -1481,7 +1623,9 @@ mod tests {
     use vmbus_async::queue::Queue;
     use vmbus_async::queue::connected_queues;
     use vmbus_ring as ring;
+    use vmcore::vpci_msi::MsiAddressData;
     use vmcore::vpci_msi::VpciInterruptMapper;
+    use vmcore::vpci_msi::VpciInterruptParameters;
     use vpci_protocol as protocol;
     use vpci_protocol::SlotNumber;
     use zerocopy::FromBytes;
@@ -1516,12 +1660,10 @@ struct MockVpciGuestDevice {
         protocol_version: protocol::ProtocolVersion,
     }
 
-    fn connected_device(
-        driver: &impl SpawnDriver,
+    fn test_channel(
         device: Arc<CloseableMutex<dyn ChipsetDevice>>,
         msi_mapper: Arc<TestVpciInterruptController>,
-    ) -> MockVpciGuestDevice {
-        let (host, guest) = connected_queues(16384);
+    ) -> VpciChannel {
         let (hardware_ids, bar_masks);
         {
             let mut device = device.lock();
@@ -1533,7 +1675,7 @@ fn connected_device(
             ExternallyManagedMmioIntercepts.new_io_region("test", 2 * HV_PAGE_SIZE),
             None,
         );
-        let mut state = VpciChannel {
+        VpciChannel {
             msi_mapper: VpciInterruptMapper::new(msi_mapper),
             config_space,
             instance_id: Guid::new_random(),
@@ -1543,7 +1685,17 @@ fn connected_device(
             device,
             bars_set: false,
             interrupts: Vec::new(),
-        };
+        }
+    }
+
+    fn connected_device(
+        driver: &impl SpawnDriver,
+        device: Arc<CloseableMutex<dyn ChipsetDevice>>,
+        msi_mapper: Arc<TestVpciInterruptController>,
+    ) -> MockVpciGuestDevice {
+        let (host, guest) = connected_queues(16384);
+        let mut state = test_channel(device, msi_mapper);
+        let hardware_ids = state.hardware_ids;
         let mut worker = VpciChannelState {
             conn: Connection { queue: host },
             state: ProtocolState::Init,
@@ -2303,4 +2455,80 @@ async fn verify_simple_device_interrupt(driver: DefaultDriver) {
         assert_ne!(addr, 0);
         assert_eq!(data, 0);
     }
+
+    #[async_test]
+    async fn verify_save_restore_interrupts() {
+        let pci_config = HardwareIds {
+            vendor_id: 0x123,
+            device_id: 0x789,
+            revision_id: 1,
+            prog_if: ProgrammingInterface::NONE,
+            base_class: ClassCode::BASE_SYSTEM_PERIPHERAL,
+            sub_class: Subclass::BASE_SYSTEM_PERIPHERAL_OTHER,
+            type0_sub_vendor_id: 0x456,
+            type0_sub_system_id: 0x1,
+        };
+        let pci = Arc::new(CloseableMutex::new(NullDevice {
+            config_space: ConfigSpaceType0Emulator::new(
+                pci_config,
+                Vec::new(),
+                Vec::new(),
+                DeviceBars::new(),
+            ),
+        }));
+
+        let msi_controller = TestVpciInterruptController::new();
+        let mut channel = test_channel(pci.clone(), msi_controller.clone());
+        let base_address = 0x1000000;
+        channel.config_space.map(base_address);
+        let mut remapped = Vec::new();
+        channel
+            .map_interrupts(
+                &[InterruptResourceRequest {
+                    vector: 0x21,
+                    vector_count: 1,
+                    delivery_mode: InterruptType::Fixed,
+                    target_processors: vec![1],
+                }],
+                &mut |resource| remapped.push(resource),
+            )
+            .await
+            .unwrap();
+        let address = remapped[0].address;
+
+        // Retarget the interrupt, as the guest would when changing its
+        // affinity, before saving.
+        msi_controller.retarget_interrupt(
+            address,
+            0,
+            &VpciInterruptParameters {
+                vector: 0x22,
+                multicast: false,
+                target_processors: &[3],
+            },
+        );
+        let state = channel.save_state(&ProtocolState::Ready(ReadyState {
+            send_device: false,
+            send_completion: None,
+            vpci_version: protocol::ProtocolVersion::VB,
+        }));
+
+        // Restore into a new channel and interrupt controller.
+        let msi_controller = TestVpciInterruptController::new();
+        let mut channel = test_channel(pci, msi_controller.clone());
+        let ProtocolState::Ready(ready) = channel.restore_state(state).unwrap() else {
+            panic!("expected ready protocol state");
+        };
+        assert_eq!(ready.vpci_version, protocol::ProtocolVersion::VB);
+        assert_eq!(channel.config_space.offset().get(), Some(base_address));
+        assert_eq!(channel.interrupts, [MsiAddressData { address, data: 0 }]);
+
+        // The address already programmed into the device still reaches the
+        // retargeted processor.
+        msi_controller.deliver_interrupt(address, 0);
+        let delivered = msi_controller.get_next_interrupt().unwrap();
+        assert_eq!(delivered.vector, 0x22);
+        assert_eq!(delivered.destination, 3);
+        assert!(msi_controller.get_next_interrupt().is_none());
+    }
 }
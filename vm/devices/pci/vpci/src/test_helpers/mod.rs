@@ -13,6 +13,7 @@
 use vmcore::vpci_msi::MapVpciInterrupt;
 use vmcore::vpci_msi::MsiAddressData;
 use vmcore::vpci_msi::RegisterInterruptError;
+use vmcore::vpci_msi::SavedInterrupt;
 use vmcore::vpci_msi::VpciInterruptParameters;
 
 #[derive(Debug, Clone)]
@@ -99,6 +100,21 @@ async fn unregister_interrupt(&self, address: u64, data: u32) {
             .lock()
             .unregister_interrupt(address, data)
     }
+
+    fn supports_save_restore(&self) -> bool {
+        true
+    }
+
+    fn save_interrupts(&self) -> Vec<SavedInterrupt> {
+        self.inner.mapping_table.lock().save()
+    }
+
+    fn restore_interrupts(
+        &self,
+        interrupts: &[SavedInterrupt],
+    ) -> Result<(), RegisterInterruptError> {
+        self.inner.mapping_table.lock().restore(interrupts)
+    }
 }
 
 #[derive(Debug)]
@@ -106,7 +122,7 @@ struct MsiInterrupt {
     _address: u64,
     _data: u32,
     base_vector: u32,
-    _vector_count: u32,
+    vector_count: u32,
     multicast: bool,
     target_processor: Arc<Mutex<u32>>,
 }
@@ -185,7 +201,7 @@ fn register_interrupt(
                     _address: address,
                     _data: 0,
                     base_vector: params.vector,
-                    _vector_count: vector_count,
+                    vector_count,
                     multicast: params.multicast,
                     target_processor: Arc::new(Mutex::new(params.target_processors[0])),
                 });
@@ -201,6 +217,42 @@ fn unregister_interrupt(&mut self, address: u64, _data: u32) {
         let index = Self::interrupt_index_from_address(address);
         self.interrupts.remove(&index);
     }
+
+    fn save(&self) -> Vec<SavedInterrupt> {
+        self.interrupts
+            .iter()
+            .map(|(&index, interrupt)| SavedInterrupt {
+                address: Self::interrupt_address_from_index(index),
+                data: 0,
+                vector: interrupt.base_vector,
+                vector_count: interrupt.vector_count,
+                multicast: interrupt.multicast,
+                target_processors: vec![*interrupt.target_processor.lock()],
+            })
+            .collect()
+    }
+
+    fn restore(&mut self, interrupts: &[SavedInterrupt]) -> Result<(), RegisterInterruptError> {
+        for saved in interrupts {
+            let index = Self::interrupt_index_from_address(saved.address);
+            let std::collections::btree_map::Entry::Vacant(e) = self.interrupts.entry(index) else {
+                return Err(RegisterInterruptError::new("interrupt already registered"));
+            };
+            let target_processor = *saved
+                .target_processors
+                .first()
+                .ok_or_else(|| RegisterInterruptError::new("invalid input"))?;
+            e.insert(MsiInterrupt {
+                _address: saved.address,
+                _data: saved.data,
+                base_vector: saved.vector,
+                vector_count: saved.vector_count,
+                multicast: saved.multicast,
+                target_processor: Arc::new(Mutex::new(target_processor)),
+            });
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -119,6 +119,17 @@ pub enum DiskError {
     UnsupportedEject,
 }
 
+impl DiskError {
+    /// Converts an error from a host file or device backing the disk, mapping
+    /// the error kinds that have a more specific guest-visible error.
+    pub fn from_io_error(err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::ReadOnlyFilesystem => Self::ReadOnly,
+            _ => Self::Io(err),
+        }
+    }
+}
+
 /// Failure details for [`DiskError::MediumError`].
 #[derive(Debug)]
 pub enum MediumErrorDetails {
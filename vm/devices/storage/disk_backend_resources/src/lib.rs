@@ -9,6 +9,7 @@
 
 use mesh::Cell;
 use mesh::MeshPayload;
+use mesh::rpc::Rpc;
 use std::time::Duration;
 use vm_resource::IntoResource;
 use vm_resource::Resource;
@@ -61,6 +62,73 @@ impl ResourceId<DiskHandleKind> for DelayDiskHandle {
     const ID: &'static str = "delay";
 }
 
+/// Disk handle for a disk that tracks which blocks have been changed, for use
+/// by incremental backup tools.
+#[derive(MeshPayload)]
+pub struct ChangeTrackingDiskHandle {
+    /// The underlying disk resource.
+    pub disk: Resource<DiskHandleKind>,
+    /// The granularity at which to track changes, in bytes. Must be a power
+    /// of two and at least the sector size.
+    pub block_size: u32,
+    /// Channel for querying and resetting the changed blocks.
+    pub requests: mesh::Receiver<ChangeTrackingRequest>,
+    /// Changed blocks to restore, as previously queried from a change
+    /// tracking disk for the same underlying disk.
+    pub saved_state: Option<ChangedBlocks>,
+}
+
+impl ResourceId<DiskHandleKind> for ChangeTrackingDiskHandle {
+    const ID: &'static str = "change_tracking";
+}
+
+/// Requests to a change tracking disk.
+#[derive(MeshPayload)]
+pub enum ChangeTrackingRequest {
+    /// Get the blocks changed in the current epoch.
+    Query(Rpc<(), ChangedBlocks>),
+    /// Get the blocks changed in the current epoch and atomically start a new
+    /// epoch with no changed blocks.
+    Reset(Rpc<(), ChangedBlocks>),
+}
+
+/// The blocks changed during an epoch of a change tracking disk.
+#[derive(Debug, Clone, MeshPayload)]
+pub struct ChangedBlocks {
+    /// The epoch, which starts at zero and is incremented on each reset.
+    pub epoch: u64,
+    /// The size of each block in bytes.
+    pub block_size: u32,
+    /// The changed blocks. Block `n` changed if bit `n % 64` of
+    /// `bitmap[n / 64]` is set. Blocks past the end of the bitmap have not
+    /// changed.
+    pub bitmap: Vec<u64>,
+}
+
+/// Opt-in diagnostics for a disk attached to a storage controller, which can
+/// be changed while the disk is in use.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, MeshPayload)]
+pub struct DiskTelemetry {
+    /// Record a histogram of IO latencies, available via inspect.
+    pub latency_histogram: bool,
+    /// The level at which to trace each IO as it completes.
+    pub trace_level: IoTraceLevel,
+}
+
+/// The tracing level for per-IO diagnostics.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, MeshPayload)]
+pub enum IoTraceLevel {
+    /// Do not trace IOs.
+    #[default]
+    Off,
+    /// Trace IOs at info level.
+    Info,
+    /// Trace IOs at debug level.
+    Debug,
+    /// Trace IOs at trace level.
+    Trace,
+}
+
 /// Disk handle for a fixed VHD1 disk.
 #[derive(MeshPayload)]
 pub struct FixedVhd1DiskHandle(pub std::fs::File);
@@ -339,7 +339,7 @@ fn map_io_error(&self, err: std::io::Error) -> DiskError {
                 _ => {}
             }
         }
-        DiskError::Io(err)
+        DiskError::from_io_error(err)
     }
 }
 
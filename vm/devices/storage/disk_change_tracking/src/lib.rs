@@ -0,0 +1,371 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! A disk wrapper that tracks which blocks have been written, so that
+//! incremental backup tools can copy only the changed blocks of a disk,
+//! regardless of the format of the underlying disk.
+//!
+//! Changes are recorded in a bitmap, one bit per block, for the current
+//! epoch. Resetting the tracker returns the changes for the current epoch and
+//! atomically starts a new one, so that a backup tool can reset the tracker,
+//! copy the changed blocks, and be sure that any write racing with the copy
+//! will be reported in the next epoch.
+//!
+//! The disk does not persist the changes itself. To carry them across a save
+//! and restore, query them when saving and pass them to
+//! [`ChangeTracker::restore`] when restoring.
+
+#![forbid(unsafe_code)]
+
+/// Resolver for change tracking disks.
+pub mod resolver;
+
+use disk_backend::Disk;
+use disk_backend::DiskError;
+use disk_backend::DiskIo;
+use disk_backend::UnmapBehavior;
+use disk_backend_resources::ChangedBlocks;
+use inspect::Inspect;
+use parking_lot::Mutex;
+use scsi_buffers::RequestBuffers;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// A disk that tracks which blocks have been changed.
+#[derive(Inspect)]
+pub struct ChangeTrackingDisk {
+    inner: Disk,
+    #[inspect(flatten)]
+    tracker: Arc<ChangeTracker>,
+}
+
+/// An error returned when creating a [`ChangeTrackingDisk`] with an invalid
+/// block size.
+#[derive(Debug, Error)]
+#[error(
+    "block size {block_size:#x} must be a power of two and a multiple of the sector size {sector_size:#x}"
+)]
+pub struct InvalidBlockSize {
+    block_size: u32,
+    sector_size: u32,
+}
+
+/// An error returned when restoring changed blocks that were tracked with a
+/// different block size.
+#[derive(Debug, Error)]
+#[error("saved block size {saved:#x} does not match the block size {block_size:#x}")]
+pub struct BlockSizeMismatch {
+    saved: u32,
+    block_size: u32,
+}
+
+impl ChangeTrackingDisk {
+    /// Creates a new disk that tracks changes to `inner` at a granularity of
+    /// `block_size` bytes.
+    pub fn new(inner: Disk, block_size: u32) -> Result<Self, InvalidBlockSize> {
+        let sector_size = inner.sector_size();
+        if !block_size.is_power_of_two() || block_size < sector_size {
+            return Err(InvalidBlockSize {
+                block_size,
+                sector_size,
+            });
+        }
+        let tracker = ChangeTracker {
+            block_size,
+            block_shift: (block_size / sector_size).trailing_zeros(),
+            sector_shift: sector_size.trailing_zeros(),
+            epoch: Mutex::new(Epoch {
+                epoch: 0,
+                bitmap: Vec::new(),
+            }),
+        };
+        Ok(Self {
+            inner,
+            tracker: Arc::new(tracker),
+        })
+    }
+
+    /// Returns the change tracker, which can be used to query and reset the
+    /// changed blocks.
+    pub fn tracker(&self) -> &Arc<ChangeTracker> {
+        &self.tracker
+    }
+}
+
+/// Tracks the blocks changed in the current epoch.
+#[derive(Debug, Inspect)]
+pub struct ChangeTracker {
+    block_size: u32,
+    /// The log2 of the number of sectors in a block.
+    #[inspect(skip)]
+    block_shift: u32,
+    /// The log2 of the sector size.
+    #[inspect(skip)]
+    sector_shift: u32,
+    #[inspect(flatten)]
+    epoch: Mutex<Epoch>,
+}
+
+#[derive(Debug, Inspect)]
+struct Epoch {
+    epoch: u64,
+    #[inspect(
+        rename = "changed_blocks",
+        with = "|x| x.iter().map(|b| b.count_ones() as u64).sum::<u64>()"
+    )]
+    bitmap: Vec<u64>,
+}
+
+impl ChangeTracker {
+    /// Returns the blocks changed in the current epoch.
+    pub fn query(&self) -> ChangedBlocks {
+        let epoch = self.epoch.lock();
+        ChangedBlocks {
+            epoch: epoch.epoch,
+            block_size: self.block_size,
+            bitmap: epoch.bitmap.clone(),
+        }
+    }
+
+    /// Returns the blocks changed in the current epoch and starts a new
+    /// epoch with no changed blocks.
+    pub fn reset(&self) -> ChangedBlocks {
+        let mut epoch = self.epoch.lock();
+        let bitmap = std::mem::take(&mut epoch.bitmap);
+        let changed = ChangedBlocks {
+            epoch: epoch.epoch,
+            block_size: self.block_size,
+            bitmap,
+        };
+        epoch.epoch += 1;
+        changed
+    }
+
+    /// Replaces the current epoch and changed blocks with `changed`, which
+    /// was previously returned by [`Self::query`] or [`Self::reset`] on a
+    /// tracker for the same disk.
+    pub fn restore(&self, changed: ChangedBlocks) -> Result<(), BlockSizeMismatch> {
+        let ChangedBlocks {
+            epoch,
+            block_size,
+            bitmap,
+        } = changed;
+        if block_size != self.block_size {
+            return Err(BlockSizeMismatch {
+                saved: block_size,
+                block_size: self.block_size,
+            });
+        }
+        *self.epoch.lock() = Epoch { epoch, bitmap };
+        Ok(())
+    }
+
+    /// Marks the blocks containing sectors `sector..sector + count` as
+    /// changed, ignoring any sectors at or past `sector_count`.
+    fn mark(&self, sector: u64, count: u64, sector_count: u64) {
+        let end = sector.saturating_add(count).min(sector_count);
+        if sector >= end {
+            return;
+        }
+        let first = sector >> self.block_shift;
+        let last = (end - 1) >> self.block_shift;
+        let mut epoch = self.epoch.lock();
+        let len = (last / 64 + 1) as usize;
+        if epoch.bitmap.len() < len {
+            epoch.bitmap.resize(len, 0);
+        }
+        for block in first..=last {
+            epoch.bitmap[(block / 64) as usize] |= 1 << (block % 64);
+        }
+    }
+}
+
+impl DiskIo for ChangeTrackingDisk {
+    fn disk_type(&self) -> &str {
+        "change_tracking"
+    }
+
+    fn sector_count(&self) -> u64 {
+        self.inner.sector_count()
+    }
+
+    fn sector_size(&self) -> u32 {
+        self.inner.sector_size()
+    }
+
+    fn disk_id(&self) -> Option<[u8; 16]> {
+        self.inner.disk_id()
+    }
+
+    fn physical_sector_size(&self) -> u32 {
+        self.inner.physical_sector_size()
+    }
+
+    fn is_fua_respected(&self) -> bool {
+        self.inner.is_fua_respected()
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.inner.is_read_only()
+    }
+
+    fn pr(&self) -> Option<&dyn disk_backend::pr::PersistentReservation> {
+        self.inner.pr()
+    }
+
+    async fn read_vectored(
+        &self,
+        buffers: &RequestBuffers<'_>,
+        sector: u64,
+    ) -> Result<(), DiskError> {
+        self.inner.read_vectored(buffers, sector).await
+    }
+
+    async fn write_vectored(
+        &self,
+        buffers: &RequestBuffers<'_>,
+        sector: u64,
+        fua: bool,
+    ) -> Result<(), DiskError> {
+        self.inner.write_vectored(buffers, sector, fua).await?;
+        // Mark the blocks only after the write has completed, so that a reset
+        // that races with the write leaves the blocks marked in the new
+        // epoch.
+        self.tracker.mark(
+            sector,
+            buffers.len() as u64 >> self.tracker.sector_shift,
+            self.sector_count(),
+        );
+        Ok(())
+    }
+
+    async fn sync_cache(&self) -> Result<(), DiskError> {
+        self.inner.sync_cache().await
+    }
+
+    async fn wait_resize(&self, sector_count: u64) -> u64 {
+        self.inner.wait_resize(sector_count).await
+    }
+
+    async fn unmap(
+        &self,
+        sector: u64,
+        count: u64,
+        block_level_only: bool,
+    ) -> Result<(), DiskError> {
+        self.inner.unmap(sector, count, block_level_only).await?;
+        self.tracker.mark(sector, count, self.sector_count());
+        Ok(())
+    }
+
+    fn unmap_behavior(&self) -> UnmapBehavior {
+        self.inner.unmap_behavior()
+    }
+
+    fn optimal_unmap_sectors(&self) -> u32 {
+        self.inner.optimal_unmap_sectors()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChangeTrackingDisk;
+    use disk_backend::DiskIo;
+    use guestmem::GuestMemory;
+    use pal_async::async_test;
+    use scsi_buffers::OwnedRequestBuffers;
+
+    #[async_test]
+    async fn track_and_reset() {
+        // 1MB disk with 64KB blocks.
+        let inner = disklayer_ram::ram_disk(0x100000, false).unwrap();
+        let disk = ChangeTrackingDisk::new(inner, 0x10000).unwrap();
+        let gm = GuestMemory::allocate(0x2000);
+
+        // Two sectors straddling the boundary between blocks 1 and 2, and one
+        // sector in block 15.
+        disk.write_vectored(
+            &OwnedRequestBuffers::linear(0, 0x400, false).buffer(&gm),
+            0x10000 / 512 * 2 - 1,
+            false,
+        )
+        .await
+        .unwrap();
+        disk.write_vectored(
+            &OwnedRequestBuffers::linear(0, 0x200, false).buffer(&gm),
+            0x10000 / 512 * 15,
+            false,
+        )
+        .await
+        .unwrap();
+        // Reads don't change anything.
+        disk.read_vectored(&OwnedRequestBuffers::linear(0, 0x2000, true).buffer(&gm), 0)
+            .await
+            .unwrap();
+
+        let tracker = disk.tracker();
+        let changed = tracker.query();
+        assert_eq!(changed.epoch, 0);
+        assert_eq!(changed.block_size, 0x10000);
+        assert_eq!(changed.bitmap, [(1 << 1) | (1 << 2) | (1 << 15)]);
+
+        let changed = tracker.reset();
+        assert_eq!(changed.epoch, 0);
+        assert_eq!(changed.bitmap, [(1 << 1) | (1 << 2) | (1 << 15)]);
+
+        let changed = tracker.query();
+        assert_eq!(changed.epoch, 1);
+        assert!(changed.bitmap.iter().all(|&b| b == 0));
+    }
+
+    #[async_test]
+    async fn failed_and_out_of_range_writes() {
+        let inner = disklayer_ram::ram_disk(0x100000, false).unwrap();
+        let disk = ChangeTrackingDisk::new(inner, 0x10000).unwrap();
+        let gm = GuestMemory::allocate(0x1000);
+        let sector_count = disk.sector_count();
+
+        // A write past the end of the disk fails and marks nothing.
+        disk.write_vectored(
+            &OwnedRequestBuffers::linear(0, 0x400, false).buffer(&gm),
+            sector_count - 1,
+            false,
+        )
+        .await
+        .unwrap_err();
+        // Unmaps are clamped to the disk and must not overflow.
+        disk.tracker().mark(u64::MAX - 1, 4, sector_count);
+        disk.tracker().mark(sector_count - 1, 0x1000, sector_count);
+        assert_eq!(disk.tracker().query().bitmap, [1 << 15]);
+    }
+
+    #[test]
+    fn restore() {
+        let inner = disklayer_ram::ram_disk(0x100000, false).unwrap();
+        let disk = ChangeTrackingDisk::new(inner, 0x10000).unwrap();
+        let tracker = disk.tracker();
+        tracker.mark(0, 1, disk.sector_count());
+        tracker.reset();
+        tracker.mark(0x80, 1, disk.sector_count());
+        let saved = tracker.query();
+
+        let inner = disklayer_ram::ram_disk(0x100000, false).unwrap();
+        let disk = ChangeTrackingDisk::new(inner, 0x10000).unwrap();
+        disk.tracker().restore(saved.clone()).unwrap();
+        let restored = disk.tracker().query();
+        assert_eq!(restored.epoch, 1);
+        assert_eq!(restored.bitmap, saved.bitmap);
+
+        let inner = disklayer_ram::ram_disk(0x100000, false).unwrap();
+        let disk = ChangeTrackingDisk::new(inner, 0x20000).unwrap();
+        assert!(disk.tracker().restore(saved).is_err());
+    }
+
+    #[test]
+    fn invalid_block_size() {
+        let inner = disklayer_ram::ram_disk(0x100000, false).unwrap();
+        assert!(ChangeTrackingDisk::new(inner, 0x3000).is_err());
+        let inner = disklayer_ram::ram_disk(0x100000, false).unwrap();
+        assert!(ChangeTrackingDisk::new(inner, 0x100).is_err());
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+use crate::ChangeTracker;
+use crate::ChangeTrackingDisk;
+use anyhow::Context;
+use async_trait::async_trait;
+use disk_backend::resolve::ResolveDiskParameters;
+use disk_backend::resolve::ResolvedDisk;
+use disk_backend_resources::ChangeTrackingDiskHandle;
+use disk_backend_resources::ChangeTrackingRequest;
+use futures::StreamExt;
+use pal_async::task::Spawn;
+use std::sync::Arc;
+use std::sync::Weak;
+use vm_resource::AsyncResolveResource;
+use vm_resource::ResourceResolver;
+use vm_resource::declare_static_async_resolver;
+use vm_resource::kind::DiskHandleKind;
+
+/// A resolver for [`ChangeTrackingDisk`].
+pub struct ChangeTrackingDiskResolver;
+declare_static_async_resolver!(
+    ChangeTrackingDiskResolver,
+    (DiskHandleKind, ChangeTrackingDiskHandle)
+);
+
+#[async_trait]
+impl AsyncResolveResource<DiskHandleKind, ChangeTrackingDiskHandle> for ChangeTrackingDiskResolver {
+    type Output = ResolvedDisk;
+    type Error = anyhow::Error;
+
+    async fn resolve(
+        &self,
+        resolver: &ResourceResolver,
+        rsrc: ChangeTrackingDiskHandle,
+        input: ResolveDiskParameters<'_>,
+    ) -> Result<Self::Output, Self::Error> {
+        let inner = resolver.resolve(rsrc.disk, input).await?;
+        let disk = ChangeTrackingDisk::new(inner.0, rsrc.block_size)
+            .context("failed to create the change tracking disk")?;
+        if let Some(saved_state) = rsrc.saved_state {
+            disk.tracker()
+                .restore(saved_state)
+                .context("failed to restore the changed blocks")?;
+        }
+
+        // Start a task to handle incoming query and reset requests.
+        input
+            .driver_source
+            .simple()
+            .spawn(
+                "disk-change-tracking",
+                handle_requests(Arc::downgrade(disk.tracker()), rsrc.requests),
+            )
+            .detach();
+
+        ResolvedDisk::new(disk).context("failed to create the change tracking disk")
+    }
+}
+
+async fn handle_requests(
+    tracker: Weak<ChangeTracker>,
+    mut requests: mesh::Receiver<ChangeTrackingRequest>,
+) {
+    while let Some(req) = requests.next().await {
+        let Some(tracker) = tracker.upgrade() else {
+            break;
+        };
+        match req {
+            ChangeTrackingRequest::Query(rpc) => rpc.handle_sync(|()| tracker.query()),
+            ChangeTrackingRequest::Reset(rpc) => rpc.handle_sync(|()| tracker.reset()),
+        }
+    }
+}
@@ -111,7 +111,7 @@ pub async fn read(&self, buffers: &RequestBuffers<'_>, sector: u64) -> Result<()
             Ok(buffer)
         })
         .await
-        .map_err(DiskError::Io)?;
+        .map_err(DiskError::from_io_error)?;
         buffers.writer().write(&buffer)?;
         Ok(())
     }
@@ -131,7 +131,7 @@ pub async fn write(
         let offset = sector << self.sector_shift;
         unblock(move || file.write_at(&buffer, offset))
             .await
-            .map_err(DiskError::Io)?;
+            .map_err(DiskError::from_io_error)?;
         Ok(())
     }
 
@@ -139,7 +139,7 @@ pub async fn flush(&self) -> Result<(), DiskError> {
         let file = self.file.clone();
         unblock(move || file.sync_all())
             .await
-            .map_err(DiskError::Io)?;
+            .map_err(DiskError::from_io_error)?;
         Ok(())
     }
 }
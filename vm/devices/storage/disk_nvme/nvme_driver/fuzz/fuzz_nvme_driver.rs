@@ -56,6 +56,7 @@ pub async fn new(driver: DefaultDriver) -> Result<Self, anyhow::Error> {
                 msix_count: 2,
                 max_io_queues: 64,
                 subsystem_id: guid,
+                boot_partitions: None,
             },
         );
 
@@ -658,6 +658,50 @@ pub async fn namespace(&mut self, nsid: u32) -> Result<NamespaceHandle, Namespac
         Ok(NamespaceHandle::new(namespace))
     }
 
+    /// Attaches the allocated namespace `nsid` to this controller.
+    ///
+    /// Once attached, the namespace can be opened with
+    /// [`namespace`](Self::namespace).
+    ///
+    /// This does not check OACS for namespace management support, since
+    /// controllers that provision namespaces externally may support
+    /// attachment alone. Controllers without support fail the command.
+    pub async fn attach_namespace(&self, nsid: u32) -> Result<(), RequestError> {
+        self.namespace_attachment(nsid, spec::NamespaceAttachmentSelect::ATTACH)
+            .await
+    }
+
+    /// Detaches namespace `nsid` from this controller, leaving it allocated in
+    /// the subsystem.
+    pub async fn detach_namespace(&self, nsid: u32) -> Result<(), RequestError> {
+        self.namespace_attachment(nsid, spec::NamespaceAttachmentSelect::DETACH)
+            .await
+    }
+
+    async fn namespace_attachment(
+        &self,
+        nsid: u32,
+        sel: spec::NamespaceAttachmentSelect,
+    ) -> Result<(), RequestError> {
+        let identify = self.identify.as_ref().unwrap();
+        let mut list = spec::ControllerList::new_zeroed();
+        list.count = 1;
+        list.ids[0] = identify.cntlid;
+        self.admin
+            .as_ref()
+            .unwrap()
+            .issue_in(
+                spec::Command {
+                    nsid,
+                    cdw10: spec::Cdw10NamespaceAttachment::new().with_sel(sel.0).into(),
+                    ..admin_cmd(spec::AdminOpcode::NAMESPACE_ATTACHMENT)
+                },
+                list.as_bytes(),
+            )
+            .await?;
+        Ok(())
+    }
+
     /// Returns the number of CPUs that are in fallback mode (that are using a
     /// remote CPU's queue due to a failure or resource limitation).
     pub fn fallback_cpu_count(&self) -> usize {
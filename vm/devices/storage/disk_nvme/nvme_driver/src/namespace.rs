@@ -556,6 +556,11 @@ pub fn nsid(&self) -> u32 {
         self.nsid
     }
 
+    /// Return the namespace globally unique identifier (NGUID).
+    pub fn nguid(&self) -> [u8; 16] {
+        self.state.identify.lock().nguid
+    }
+
     /// Save namespace object data for servicing.
     /// Initially we will re-query namespace state after restore
     /// to avoid possible contention if namespace was changed
@@ -24,6 +24,8 @@
 use nvme_resources::fault::FaultConfiguration;
 use nvme_resources::fault::IoQueueFaultBehavior;
 use nvme_resources::fault::IoQueueFaultConfig;
+use nvme_resources::fault::PciFaultBehavior;
+use nvme_resources::fault::PciFaultConfig;
 use nvme_spec::AdminOpcode;
 use nvme_spec::AsynchronousEventRequestDw0;
 use nvme_spec::Cap;
@@ -35,7 +37,9 @@
 use pal_async::async_test;
 use parking_lot::Mutex;
 use pci_core::bus_range::AssignedBusRange;
+use pci_core::capabilities::extended::aer::AerError;
 use pci_core::msi::MsiConnection;
+use pci_core::spec::caps::aer::UncorrectableErrors;
 use scsi_buffers::OwnedRequestBuffers;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
@@ -182,6 +186,74 @@ async fn test_nvme_driver_bounce_buffer(driver: DefaultDriver) {
     .await;
 }
 
+#[async_test]
+async fn test_nvme_init_fault_cc_enable(driver: DefaultDriver) {
+    // The fatal status stays latched in the controller, so the driver cannot
+    // recover from this fault.
+    let err = nvme_driver_init_with_fault(
+        driver,
+        |fault_configuration| {
+            fault_configuration
+                .with_pci_fault(PciFaultConfig::new().with_cc_enable_fault(PciFaultBehavior::Fail))
+        },
+        false,
+    )
+    .await;
+    assert!(format!("{err:#}").contains("device had fatal error"));
+}
+
+#[async_test]
+async fn test_nvme_init_fault_identify_controller(driver: DefaultDriver) {
+    let err = nvme_driver_init_with_fault(
+        driver,
+        |fault_configuration| {
+            fault_configuration.with_admin_queue_fault(
+                AdminQueueFaultConfig::new().with_submission_queue_fault(
+                    CommandMatchBuilder::new()
+                        .match_cdw0_opcode(AdminOpcode::IDENTIFY.0)
+                        .match_cdw10(
+                            nvme_spec::Cdw10Identify::new()
+                                .with_cns(nvme_spec::Cns::CONTROLLER.0)
+                                .into(),
+                            nvme_spec::Cdw10Identify::new().with_cns(u8::MAX).into(),
+                        )
+                        .build(),
+                    AdminQueueFaultBehavior::Fail(nvme_spec::Status::INVALID_FIELD_IN_COMMAND.0),
+                ),
+            )
+        },
+        true,
+    )
+    .await;
+    assert!(format!("{err:#}").contains("failed to identify controller"));
+}
+
+#[async_test]
+async fn test_nvme_init_fault_set_number_of_queues(driver: DefaultDriver) {
+    let err = nvme_driver_init_with_fault(
+        driver,
+        |fault_configuration| {
+            fault_configuration.with_admin_queue_fault(
+                AdminQueueFaultConfig::new().with_completion_queue_fault(
+                    CommandMatchBuilder::new()
+                        .match_cdw0_opcode(AdminOpcode::SET_FEATURES.0)
+                        .match_cdw10(
+                            nvme_spec::Cdw10SetFeatures::new()
+                                .with_fid(nvme_spec::Feature::NUMBER_OF_QUEUES.0)
+                                .into(),
+                            nvme_spec::Cdw10SetFeatures::new().with_fid(u8::MAX).into(),
+                        )
+                        .build(),
+                    AdminQueueFaultBehavior::Fail(nvme_spec::Status::INTERNAL_ERROR.0),
+                ),
+            )
+        },
+        true,
+    )
+    .await;
+    assert!(format!("{err:#}").contains("failed to set number of queues"));
+}
+
 #[async_test]
 async fn test_nvme_driver_fails_to_create_dma_alloc_failure(driver: DefaultDriver) {
     test_nvme_driver(
@@ -232,6 +304,7 @@ async fn test_nvme_ioqueue_max_mqes(driver: DefaultDriver) {
             msix_count: MSIX_COUNT,
             max_io_queues: IO_QUEUE_COUNT,
             subsystem_id: Guid::new_random(),
+            boot_partitions: None,
         },
     );
 
@@ -269,6 +342,7 @@ async fn test_nvme_ioqueue_invalid_mqes(driver: DefaultDriver) {
             msix_count: MSIX_COUNT,
             max_io_queues: IO_QUEUE_COUNT,
             subsystem_id: Guid::new_random(),
+            boot_partitions: None,
         },
     );
 
@@ -282,6 +356,148 @@ async fn test_nvme_ioqueue_invalid_mqes(driver: DefaultDriver) {
     assert!(driver.is_err());
 }
 
+#[async_test]
+async fn test_nvme_namespace_attachment(driver: DefaultDriver) {
+    const MSIX_COUNT: u16 = 2;
+    const IO_QUEUE_COUNT: u16 = 64;
+    const CPU_COUNT: u32 = 64;
+
+    let pages = 1000;
+    let device_test_memory = DeviceTestMemory::new(pages, false, "test_nvme_namespace_attachment");
+    let guest_mem = device_test_memory.guest_memory();
+    let dma_client = device_test_memory.dma_client();
+
+    let driver_source = VmTaskDriverSource::new(SingleDriverBackend::new(driver));
+    let msi_conn = MsiConnection::new(AssignedBusRange::new(), 0);
+    let nvme = nvme::NvmeController::new(
+        &driver_source,
+        guest_mem,
+        msi_conn.target(),
+        &mut ExternallyManagedMmioIntercepts,
+        NvmeControllerCaps {
+            msix_count: MSIX_COUNT,
+            max_io_queues: IO_QUEUE_COUNT,
+            subsystem_id: Guid::new_random(),
+            boot_partitions: None,
+        },
+    );
+    nvme.client()
+        .add_namespace(1, disklayer_ram::ram_disk(2 << 20, false).unwrap())
+        .await
+        .unwrap();
+
+    let device = NvmeTestEmulatedDevice::new(nvme, msi_conn, dma_client.clone());
+    let mut driver = NvmeDriver::new(&driver_source, CPU_COUNT, device, false)
+        .await
+        .unwrap();
+
+    // A detached namespace is still allocated but is no longer active.
+    driver.detach_namespace(1).await.unwrap();
+    assert!(driver.detach_namespace(1).await.is_err());
+    assert!(matches!(
+        driver.namespace(1).await,
+        Err(crate::NamespaceError::NotFound)
+    ));
+
+    driver.attach_namespace(1).await.unwrap();
+    assert!(driver.attach_namespace(1).await.is_err());
+    driver.namespace(1).await.unwrap();
+
+    driver.shutdown().await;
+}
+
+#[async_test]
+async fn test_nvme_driver_recovers_from_fatal_aer_error(driver: DefaultDriver) {
+    const MSIX_COUNT: u16 = 2;
+    const IO_QUEUE_COUNT: u16 = 64;
+    const CPU_COUNT: u32 = 64;
+
+    let pages = 1024;
+    let device_test_memory = DeviceTestMemory::new(pages * 2, false, "test_nvme_driver");
+    let guest_mem = device_test_memory.guest_memory();
+    let payload_mem = device_test_memory.payload_mem();
+    let dma_client = device_test_memory.dma_client();
+
+    let driver_source = VmTaskDriverSource::new(SingleDriverBackend::new(driver));
+    let msi_conn = MsiConnection::new(AssignedBusRange::new(), 0);
+    let nvme = nvme::NvmeController::new(
+        &driver_source,
+        guest_mem,
+        msi_conn.target(),
+        &mut ExternallyManagedMmioIntercepts,
+        NvmeControllerCaps {
+            msix_count: MSIX_COUNT,
+            max_io_queues: IO_QUEUE_COUNT,
+            subsystem_id: Guid::new_random(),
+            boot_partitions: None,
+        },
+    );
+    nvme.client()
+        .add_namespace(1, disklayer_ram::ram_disk(2 << 20, false).unwrap())
+        .await
+        .unwrap();
+
+    let device = NvmeTestEmulatedDevice::new(nvme, msi_conn, dma_client);
+    let controller = device.device();
+    let mut nvme_driver = NvmeDriver::new(&driver_source, CPU_COUNT, device.clone(), false)
+        .await
+        .unwrap();
+    let namespace = nvme_driver.namespace(1).await.unwrap();
+    let buf_range = OwnedRequestBuffers::linear(0, 4096, true);
+    payload_mem.write_at(0, &[0xcc; 4096]).unwrap();
+    namespace
+        .write(
+            0,
+            1,
+            8,
+            false,
+            &payload_mem,
+            buf_range.buffer(&payload_mem).range(),
+        )
+        .await
+        .unwrap();
+    drop(namespace);
+
+    // The guest has not enabled error reporting, so no message is sent to the
+    // root port, but the controller still fails.
+    let error = AerError::Uncorrectable {
+        errors: UncorrectableErrors::new().with_malformed_tlp(true),
+        header_log: [0; 4],
+    };
+    assert_eq!(controller.lock().inject_aer_error(error), None);
+    let mut csts = 0u32;
+    controller
+        .lock()
+        .read_bar0(0x1c, csts.as_mut_bytes())
+        .unwrap();
+    assert!(nvme_spec::Csts::from(csts).cfs());
+
+    // Recover the way the driver's owner does: reset the controller by
+    // shutting the driver down, then bring up a new driver.
+    nvme_driver.shutdown().await;
+    let mut nvme_driver = NvmeDriver::new(&driver_source, CPU_COUNT, device, false)
+        .await
+        .unwrap();
+    let namespace = nvme_driver.namespace(1).await.unwrap();
+    payload_mem.fill_at(0, 0, 4096).unwrap();
+    namespace
+        .read(
+            0,
+            1,
+            8,
+            &payload_mem,
+            buf_range.buffer(&payload_mem).range(),
+        )
+        .await
+        .unwrap();
+    let mut data = [0; 4096];
+    payload_mem.read_at(0, &mut data).unwrap();
+    assert_eq!(data, [0xcc; 4096]);
+    drop(namespace);
+
+    nvme_driver.shutdown().await;
+}
+
 struct NvmeTestConfig {
     allow_dma: bool,
     fail_at_driver_create: bool,
@@ -325,6 +541,7 @@ async fn test_nvme_driver(driver: DefaultDriver, config: NvmeTestConfig) {
             msix_count: MSIX_COUNT,
             max_io_queues: IO_QUEUE_COUNT,
             subsystem_id: Guid::new_random(),
+            boot_partitions: None,
         },
     );
 
@@ -509,6 +726,69 @@ async fn test_nvme_fault_injection(driver: DefaultDriver, fault_configuration: F
     driver.shutdown().await;
 }
 
+// This helper function creates a NVMe fault controller with the fault
+// configuration returned by `configure` and checks that initializing the NVMe
+// driver attached to it fails, leaving the controller disabled. If
+// `expect_recovery` is set, the fault is then turned off and a new driver must
+// initialize successfully on the same controller, showing that the failed
+// driver released the controller and its DMA memory. Returns the
+// initialization error.
+async fn nvme_driver_init_with_fault(
+    driver: DefaultDriver,
+    configure: impl FnOnce(FaultConfiguration) -> FaultConfiguration,
+    expect_recovery: bool,
+) -> anyhow::Error {
+    const MSIX_COUNT: u16 = 2;
+    const IO_QUEUE_COUNT: u16 = 64;
+    const CPU_COUNT: u32 = 64;
+
+    let pages = 1024; // 4MB
+    let device_test_memory = DeviceTestMemory::new(pages, false, "nvme_driver_init_with_fault");
+    let driver_source = VmTaskDriverSource::new(SingleDriverBackend::new(driver));
+    let msi_conn = MsiConnection::new(AssignedBusRange::new(), 0);
+    let mut fault_active = CellUpdater::new(true);
+    let nvme = nvme_test::NvmeFaultController::new(
+        &driver_source,
+        device_test_memory.guest_memory(),
+        msi_conn.target(),
+        &mut ExternallyManagedMmioIntercepts,
+        nvme_test::NvmeFaultControllerCaps {
+            msix_count: MSIX_COUNT,
+            max_io_queues: IO_QUEUE_COUNT,
+            subsystem_id: Guid::new_random(),
+        },
+        configure(FaultConfiguration::new(fault_active.cell())),
+        None,
+    );
+
+    let device = NvmeTestEmulatedDevice::new(nvme, msi_conn, device_test_memory.dma_client());
+    let controller = device.device();
+    let Err(err) = NvmeDriver::new(&driver_source, CPU_COUNT, device.clone(), false).await else {
+        panic!("driver initialization should have failed");
+    };
+
+    // The failed driver must have disabled the controller on its way out.
+    let read_register = |register: nvme_spec::Register| {
+        let mut n = [0; 4];
+        controller.lock().mmio_read(register.0, &mut n).unwrap();
+        u32::from_ne_bytes(n)
+    };
+    let cc = nvme_spec::Cc::from(read_register(nvme_spec::Register::CC));
+    let csts = nvme_spec::Csts::from(read_register(nvme_spec::Register::CSTS));
+    assert!(!cc.en(), "controller left enabled: {cc:?}");
+    assert!(!csts.rdy(), "controller left ready: {csts:?}");
+
+    if expect_recovery {
+        fault_active.set(false).await;
+        let driver = NvmeDriver::new(&driver_source, CPU_COUNT, device, false)
+            .await
+            .unwrap();
+        driver.shutdown().await;
+    }
+
+    err
+}
+
 #[derive(Inspect)]
 pub struct NvmeTestEmulatedDevice<T: InspectMut, U: DmaClient> {
     device: EmulatedDevice<T, U>,
@@ -537,6 +817,11 @@ pub fn new(device: T, msi_conn: MsiConnection, dma_client: Arc<U>) -> Self {
         }
     }
 
+    /// Returns the emulated device.
+    pub fn device(&self) -> Arc<Mutex<T>> {
+        self.device.device()
+    }
+
     // TODO: set_mock_response_u32 is intentionally not implemented to avoid dead code.
     pub fn set_mock_response_u64(&mut self, mapping: Option<(usize, u64)>) {
         let mut mock_response = self.mocked_response_u64.lock();
@@ -544,6 +829,18 @@ pub fn set_mock_response_u64(&mut self, mapping: Option<(usize, u64)>) {
     }
 }
 
+impl<T: PciConfigSpace + MmioIntercept + InspectMut, U: DmaClient> Clone
+    for NvmeTestEmulatedDevice<T, U>
+{
+    fn clone(&self) -> Self {
+        Self {
+            device: self.device.clone(),
+            mocked_response_u32: self.mocked_response_u32.clone(),
+            mocked_response_u64: self.mocked_response_u64.clone(),
+        }
+    }
+}
+
 /// Implementation of DeviceBacking trait for NvmeTestEmulatedDevice
 impl<T: 'static + Send + InspectMut + MmioIntercept, U: 'static + DmaClient> DeviceBacking
     for NvmeTestEmulatedDevice<T, U>
@@ -10,11 +10,9 @@
 use async_trait::async_trait;
 use disk_backend::DiskError;
 use disk_backend::DiskIo;
-use disk_backend::MediumErrorDetails;
 use disk_backend::pr;
 use inspect::Inspect;
 use nvme_common::from_nvme_reservation_report;
-use nvme_spec::Status;
 use nvme_spec::nvm;
 #[cfg(target_os = "linux")]
 use pal::unix::affinity::get_cpu_number;
@@ -314,43 +312,7 @@ fn map_nvme_error(err: nvme_driver::RequestError) -> DiskError {
             DiskError::Io(io::Error::new(io::ErrorKind::NotConnected, err))
         }
         nvme_driver::RequestError::Nvme(err) => {
-            match err.status() {
-                Status::RESERVATION_CONFLICT => DiskError::ReservationConflict,
-
-                Status::INVALID_FIELD_IN_COMMAND => DiskError::InvalidInput,
-
-                Status::LBA_OUT_OF_RANGE => DiskError::IllegalBlock,
-
-                // MediumError
-                Status::DATA_TRANSFER_ERROR | Status::CAPACITY_EXCEEDED => {
-                    DiskError::Io(io::Error::other(err))
-                }
-                Status::MEDIA_WRITE_FAULT => {
-                    DiskError::MediumError(io::Error::other(err), MediumErrorDetails::WriteFault)
-                }
-                Status::MEDIA_UNRECOVERED_READ_ERROR => DiskError::MediumError(
-                    io::Error::other(err),
-                    MediumErrorDetails::UnrecoveredReadError,
-                ),
-                Status::MEDIA_END_TO_END_GUARD_CHECK_ERROR => DiskError::MediumError(
-                    io::Error::other(err),
-                    MediumErrorDetails::GuardCheckFailed,
-                ),
-                Status::MEDIA_END_TO_END_APPLICATION_TAG_CHECK_ERROR => DiskError::MediumError(
-                    io::Error::other(err),
-                    MediumErrorDetails::ApplicationTagCheckFailed,
-                ),
-                Status::MEDIA_END_TO_END_REFERENCE_TAG_CHECK_ERROR => DiskError::MediumError(
-                    io::Error::other(err),
-                    MediumErrorDetails::ReferenceTagCheckFailed,
-                ),
-
-                Status::COMMAND_ABORTED_DUE_TO_PREEMPT_AND_ABORT => {
-                    DiskError::AbortDueToPreemptAndAbort
-                }
-
-                _ => DiskError::Io(io::Error::other(err)),
-            }
+            nvme_common::nvme_status_to_disk_error(err.status(), io::Error::other(err))
         }
         nvme_driver::RequestError::Memory(err) => DiskError::MemoryAccess(err.into()),
         err @ nvme_driver::RequestError::TooLarge => {
@@ -17,6 +17,7 @@
 use mesh::MeshPayload;
 use scsi_buffers::RequestBuffers;
 use std::fs;
+use std::ops::Range;
 use std::os::windows::prelude::*;
 use std::path::Path;
 use thiserror::Error;
@@ -335,6 +336,7 @@ pub union GET_VIRTUAL_DISK_INFO_u {
         pub SmallestSafeVirtualSize: u64,
         pub FragmentationPercentage: u32,
         pub VirtualDiskId: GUID,
+        pub ChangeTrackingState: GET_VIRTUAL_DISK_INFO_ChangeTrackingState,
     }
 
     #[repr(C)]
@@ -346,6 +348,65 @@ pub struct GET_VIRTUAL_DISK_INFO_Size {
         pub SectorSize: u32,
     }
 
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    pub struct GET_VIRTUAL_DISK_INFO_ChangeTrackingState {
+        pub Enabled: BOOL,
+        pub NewerChanges: BOOL,
+        // Variable length, null terminated.
+        pub MostRecentId: [u16; 1],
+    }
+
+    pub const SET_VIRTUAL_DISK_INFO_UNSPECIFIED: u32 = 0;
+    pub const SET_VIRTUAL_DISK_INFO_PARENT_PATH: u32 = 1;
+    pub const SET_VIRTUAL_DISK_INFO_IDENTIFIER: u32 = 2;
+    pub const SET_VIRTUAL_DISK_INFO_PARENT_PATH_WITH_DEPTH: u32 = 3;
+    pub const SET_VIRTUAL_DISK_INFO_PHYSICAL_SECTOR_SIZE: u32 = 4;
+    pub const SET_VIRTUAL_DISK_INFO_VIRTUAL_DISK_ID: u32 = 5;
+    pub const SET_VIRTUAL_DISK_INFO_CHANGE_TRACKING_STATE: u32 = 6;
+    pub const SET_VIRTUAL_DISK_INFO_PARENT_LOCATOR: u32 = 7;
+
+    #[repr(C)]
+    pub struct SET_VIRTUAL_DISK_INFO {
+        pub Version: u32,
+        pub u: SET_VIRTUAL_DISK_INFO_u,
+    }
+
+    #[repr(C)]
+    pub union SET_VIRTUAL_DISK_INFO_u {
+        pub ParentFilePath: PCWSTR,
+        pub UniqueIdentifier: GUID,
+        pub ParentPathWithDepthInfo: SET_VIRTUAL_DISK_INFO_ParentPathWithDepthInfo,
+        pub VhdPhysicalSectorSize: ULONG,
+        pub VirtualDiskId: GUID,
+        pub ChangeTrackingEnabled: BOOL,
+        pub ParentLocator: SET_VIRTUAL_DISK_INFO_ParentLocator,
+    }
+
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    pub struct SET_VIRTUAL_DISK_INFO_ParentPathWithDepthInfo {
+        pub ChildDepth: ULONG,
+        pub ParentFilePath: PCWSTR,
+    }
+
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    pub struct SET_VIRTUAL_DISK_INFO_ParentLocator {
+        pub LinkageId: GUID,
+        pub ParentFilePath: PCWSTR,
+    }
+
+    pub const QUERY_CHANGES_VIRTUAL_DISK_FLAG_NONE: u32 = 0;
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Default)]
+    pub struct QUERY_CHANGES_VIRTUAL_DISK_RANGE {
+        pub ByteOffset: ULONGLONG,
+        pub ByteLength: ULONGLONG,
+        pub Reserved: ULONGLONG,
+    }
+
     #[link(name = "virtdisk")]
     unsafe extern "system" {
         pub fn OpenVirtualDisk(
@@ -373,6 +434,22 @@ pub fn GetVirtualDiskInformation(
             size_use: Option<&mut u32>,
         ) -> u32;
 
+        pub fn SetVirtualDiskInformation(
+            virtual_disk_handle: RawHandle,
+            virtual_disk_info: &mut SET_VIRTUAL_DISK_INFO,
+        ) -> u32;
+
+        pub fn QueryChangesVirtualDisk(
+            virtual_disk_handle: RawHandle,
+            change_tracking_id: PCWSTR,
+            byte_offset: ULONGLONG,
+            byte_length: ULONGLONG,
+            flags: u32,
+            ranges: *mut QUERY_CHANGES_VIRTUAL_DISK_RANGE,
+            range_count: &mut ULONG,
+            processed_length: &mut ULONGLONG,
+        ) -> u32;
+
         pub fn CreateVirtualDisk(
             virtual_storage_type: &mut VIRTUAL_STORAGE_TYPE,
             path: *const u16,
@@ -406,6 +483,7 @@ pub struct OpenOptions {
     flags: u32,
     read_only: bool,
     attach: bool,
+    change_tracking: Option<bool>,
 }
 
 impl OpenOptions {
@@ -425,6 +503,16 @@ pub fn cached_io(mut self, cached: bool) -> Self {
         self
     }
 
+    /// Sets whether resilient change tracking (RCT) should be enabled or
+    /// disabled on the disk when it is opened. If not set, the disk's
+    /// existing change tracking state is left unchanged.
+    ///
+    /// Change tracking is only supported for VHDX files.
+    pub fn change_tracking(mut self, enabled: bool) -> Self {
+        self.change_tracking = Some(enabled);
+        self
+    }
+
     fn open_raw(&self, path: &Path) -> std::io::Result<Vhd> {
         let mut storage_type = virtdisk::VIRTUAL_STORAGE_TYPE::default();
         // Use a unique ID for each open to avoid virtual disk sharing
@@ -464,6 +552,11 @@ fn open_raw(&self, path: &Path) -> std::io::Result<Vhd> {
     pub fn open(&self, path: &Path) -> Result<Vhd, Error> {
         let vhd = self.open_raw(path).map_err(Error::Open)?;
 
+        if let Some(enabled) = self.change_tracking {
+            vhd.set_change_tracking(enabled)
+                .map_err(Error::ChangeTracking)?;
+        }
+
         if self.attach {
             // N.B. This must be attached here and not later in a worker process
             //      since this operation may require impersonation, which is
@@ -646,6 +739,131 @@ fn get_physical_sector_size(&self) -> std::io::Result<u32> {
         }
     }
 
+    /// Enables or disables resilient change tracking (RCT).
+    pub fn set_change_tracking(&self, enabled: bool) -> std::io::Result<()> {
+        let mut info = virtdisk::SET_VIRTUAL_DISK_INFO {
+            Version: virtdisk::SET_VIRTUAL_DISK_INFO_CHANGE_TRACKING_STATE,
+            u: virtdisk::SET_VIRTUAL_DISK_INFO_u {
+                ChangeTrackingEnabled: enabled.into(),
+            },
+        };
+        // SAFETY: We are guaranteed to be holding an open handle, and we
+        // validate the result immediately.
+        unsafe {
+            chk_win32(virtdisk::SetVirtualDiskInformation(
+                self.0.as_raw_handle(),
+                &mut info,
+            ))
+        }
+    }
+
+    /// Queries the resilient change tracking (RCT) state.
+    pub fn change_tracking_state(&self) -> std::io::Result<ChangeTrackingState> {
+        const ERROR_INSUFFICIENT_BUFFER: u32 = 122;
+
+        // The most recent ID is returned inline as a variable-length string,
+        // so the info may not fit in `GET_VIRTUAL_DISK_INFO`. Use a u64 buffer
+        // to get the right alignment.
+        let mut buf = vec![0u64; size_of::<virtdisk::GET_VIRTUAL_DISK_INFO>().div_ceil(8) + 32];
+        loop {
+            let mut size = (buf.len() * 8) as u32;
+            // SAFETY: the buffer is suitably aligned and at least as large as
+            // `GET_VIRTUAL_DISK_INFO`, and all zeroes is a valid bit pattern
+            // for it.
+            let info = unsafe { &mut *buf.as_mut_ptr().cast::<virtdisk::GET_VIRTUAL_DISK_INFO>() };
+            info.Version = virtdisk::GET_VIRTUAL_DISK_INFO_CHANGE_TRACKING_STATE;
+            // SAFETY: We are guaranteed to be holding an open handle, and
+            // `size` is the size of the buffer backing `info`.
+            let err = unsafe {
+                virtdisk::GetVirtualDiskInformation(
+                    self.0.as_raw_handle(),
+                    &mut size,
+                    Some(info),
+                    None,
+                )
+            };
+            if err == ERROR_INSUFFICIENT_BUFFER && size as usize > buf.len() * 8 {
+                buf.resize((size as usize).div_ceil(8), 0);
+                continue;
+            }
+            chk_win32(err)?;
+            break;
+        }
+
+        let base = buf.as_ptr().cast::<u8>();
+        let info = base.cast::<virtdisk::GET_VIRTUAL_DISK_INFO>();
+        // SAFETY: Accessing the right union field for this call, and the
+        // buffer is large enough for the fixed portion of the state.
+        let (state, id_ptr) = unsafe {
+            (
+                (*info).u.ChangeTrackingState,
+                (&raw const (*info).u.ChangeTrackingState.MostRecentId).cast::<u16>(),
+            )
+        };
+        // SAFETY: The ID is within the buffer, and the remainder of the
+        // buffer is initialized.
+        let id = unsafe {
+            let offset = id_ptr.cast::<u8>().offset_from(base) as usize;
+            std::slice::from_raw_parts(id_ptr, (buf.len() * 8 - offset) / 2)
+        };
+        let id = &id[..id.iter().position(|&c| c == 0).unwrap_or(id.len())];
+
+        Ok(ChangeTrackingState {
+            enabled: state.Enabled != 0,
+            newer_changes: state.NewerChanges != 0,
+            most_recent_id: String::from_utf16_lossy(id),
+        })
+    }
+
+    /// Returns the byte ranges within `range` that have changed since the
+    /// resilient change tracking (RCT) ID `change_tracking_id` was current.
+    ///
+    /// Backup applications use this to copy only the changed parts of a disk.
+    pub fn query_changes(
+        &self,
+        change_tracking_id: &str,
+        range: Range<u64>,
+    ) -> std::io::Result<Vec<Range<u64>>> {
+        let id16: Vec<u16> = change_tracking_id
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .collect();
+        let mut buf = [virtdisk::QUERY_CHANGES_VIRTUAL_DISK_RANGE::default(); 256];
+        let mut changes: Vec<Range<u64>> = Vec::new();
+        let mut offset = range.start;
+        while offset < range.end {
+            let mut count = buf.len() as u32;
+            let mut processed = 0;
+            // SAFETY: We are guaranteed to be holding an open handle, the ID
+            // is null terminated, and `count` is the length of `buf`.
+            unsafe {
+                chk_win32(virtdisk::QueryChangesVirtualDisk(
+                    self.0.as_raw_handle(),
+                    id16.as_ptr(),
+                    offset,
+                    range.end - offset,
+                    virtdisk::QUERY_CHANGES_VIRTUAL_DISK_FLAG_NONE,
+                    buf.as_mut_ptr(),
+                    &mut count,
+                    &mut processed,
+                ))?;
+            }
+            for r in &buf[..count as usize] {
+                let r = r.ByteOffset..r.ByteOffset + r.ByteLength;
+                // Merge ranges that were split across calls.
+                match changes.last_mut() {
+                    Some(last) if last.end == r.start => last.end = r.end,
+                    _ => changes.push(r),
+                }
+            }
+            if processed == 0 {
+                break;
+            }
+            offset += processed;
+        }
+        Ok(changes)
+    }
+
     fn get_disk_id(&self) -> std::io::Result<Guid> {
         // SAFETY: Accessing the right union field for this call.
         unsafe {
@@ -658,6 +876,18 @@ fn get_disk_id(&self) -> std::io::Result<Guid> {
     }
 }
 
+/// The resilient change tracking (RCT) state of a virtual disk.
+#[derive(Debug, Clone, Inspect)]
+pub struct ChangeTrackingState {
+    /// Whether change tracking is enabled.
+    pub enabled: bool,
+    /// Whether there are changes newer than `most_recent_id`.
+    pub newer_changes: bool,
+    /// The most recent change tracking ID. Backup applications use this to
+    /// query the ranges that have changed since this point.
+    pub most_recent_id: String,
+}
+
 #[derive(MeshPayload)]
 /// Configuration to open a VHDMP disk.
 pub struct OpenVhdmpDiskConfig(pub Vhd);
@@ -709,6 +939,16 @@ pub struct VhdmpDisk {
     #[inspect(skip)]
     io_lock: futures::lock::Mutex<()>,
     disk_id: Guid,
+    /// A second handle to the VHD, for metadata queries.
+    #[inspect(rename = "change_tracking", with = "inspect_change_tracking")]
+    control: Vhd,
+}
+
+fn inspect_change_tracking(vhd: &Vhd) -> impl '_ + Inspect {
+    inspect::adhoc(move |req| match vhd.change_tracking_state() {
+        Ok(state) => state.inspect(req),
+        Err(err) => req.value(err.to_string()),
+    })
 }
 
 #[derive(Debug, Error)]
@@ -726,6 +966,9 @@ pub enum Error {
     #[error("failed to query VHD metadata")]
     /// Error querying disk metadata
     Query(#[source] std::io::Error),
+    #[error("failed to set VHD change tracking state")]
+    /// Error enabling or disabling change tracking
+    ChangeTracking(#[source] std::io::Error),
 }
 
 impl VhdmpDisk {
@@ -736,6 +979,7 @@ pub fn options() -> OpenOptions {
             read_only: false,
             // The VHD must be attached to allow raw access.
             attach: true,
+            change_tracking: None,
         }
     }
 
@@ -749,14 +993,33 @@ pub fn new(vhd: Vhd, read_only: bool) -> Result<Self, Error> {
             physical_sector_size: vhd.get_physical_sector_size().map_err(Error::Query)?,
             read_only,
         };
+        let control = Vhd(vhd.0.try_clone().map_err(Error::Query)?);
         let vhd = FileDisk::with_metadata(vhd.0, metadata);
 
         Ok(Self {
             vhd,
             io_lock: Default::default(),
             disk_id,
+            control,
         })
     }
+
+    /// Returns the current resilient change tracking (RCT) state of the disk.
+    pub fn change_tracking_state(&self) -> Result<ChangeTrackingState, Error> {
+        self.control.change_tracking_state().map_err(Error::Query)
+    }
+
+    /// Returns the byte ranges within `range` that have changed since the
+    /// resilient change tracking (RCT) ID `change_tracking_id` was current.
+    pub fn query_changes(
+        &self,
+        change_tracking_id: &str,
+        range: Range<u64>,
+    ) -> Result<Vec<Range<u64>>, Error> {
+        self.control
+            .query_changes(change_tracking_id, range)
+            .map_err(Error::Query)
+    }
 }
 
 impl DiskIo for VhdmpDisk {
@@ -828,6 +1091,7 @@ fn unmap_behavior(&self) -> disk_backend::UnmapBehavior {
 
 #[cfg(test)]
 mod tests {
+    use super::Vhd;
     use super::VhdmpDisk;
     use disk_backend::DiskError;
     use disk_backend::DiskIo;
@@ -863,6 +1127,54 @@ fn open_readonly() {
             .unwrap_err();
     }
 
+    #[async_test]
+    async fn change_tracking() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.vhdx");
+        drop(Vhd::create_dynamic(&path, 16, true).unwrap());
+
+        let vhd = VhdmpDisk::options()
+            .change_tracking(true)
+            .open(&path)
+            .unwrap();
+        let disk = VhdmpDisk::new(vhd, false).unwrap();
+        let state = disk.change_tracking_state().unwrap();
+        assert!(state.enabled);
+        assert!(!state.most_recent_id.is_empty());
+
+        // Only the written sector is reported as changed.
+        let size = disk.sector_count() * disk.sector_size() as u64;
+        assert!(
+            disk.query_changes(&state.most_recent_id, 0..size)
+                .unwrap()
+                .is_empty()
+        );
+        let gm = GuestMemory::allocate(512);
+        gm.fill_at(0, 0xcc, 512).unwrap();
+        disk.write_vectored(
+            &OwnedRequestBuffers::linear(0, 512, false).buffer(&gm),
+            8,
+            false,
+        )
+        .await
+        .unwrap();
+        let changes = disk.query_changes(&state.most_recent_id, 0..size).unwrap();
+        assert!(!changes.is_empty());
+        assert!(
+            changes
+                .iter()
+                .all(|r| r.start <= 8 * 512 && r.end >= 9 * 512)
+        );
+        drop(disk);
+
+        let vhd = VhdmpDisk::options()
+            .change_tracking(false)
+            .open(&path)
+            .unwrap();
+        let disk = VhdmpDisk::new(vhd, false).unwrap();
+        assert!(!disk.change_tracking_state().unwrap().enabled);
+    }
+
     #[async_test]
     async fn test_invalid_lba() {
         let path = make_test_vhd();
@@ -0,0 +1,194 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Breaks down the latency of each command in an NVMe IO trace, optionally
+//! merged with the guest's `blkparse` output for the same disk.
+//!
+//! ```text
+//! cargo run -p nvme --example nvme_io_trace -- <trace> [<blkparse.txt>] [--lba-size <bytes>]
+//! ```
+//!
+//! Without `blkparse` output, prints the time each command spent queued in the
+//! emulator, in the backend, and waiting to post its completion.
+//!
+//! With `blkparse` output (in its default text format), each read and write is
+//! matched to the guest's dispatch (`D`) and completion (`C`) events for the
+//! same sectors, and the time spent between the guest and the emulator is
+//! reported too. The guest and host clocks are aligned by assuming that the
+//! fastest dispatch-to-submit transition took no time, so those columns are
+//! relative to the fastest IO.
+
+use anyhow::Context as _;
+use nvme::io_trace::IoTraceRecord;
+use nvme::io_trace::parse_io_trace;
+use nvme_spec::nvm::NvmOpcode;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+/// The sector size used by blktrace.
+const BLKTRACE_SECTOR_SIZE: u64 = 512;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct IoKey {
+    write: bool,
+    sector: u64,
+    sectors: u64,
+}
+
+#[derive(Default)]
+struct GuestEvents {
+    dispatched: HashMap<IoKey, VecDeque<u64>>,
+    completed: HashMap<IoKey, VecDeque<u64>>,
+}
+
+/// Parses the dispatch and completion events from `blkparse` output, with
+/// timestamps in nanoseconds.
+fn parse_blkparse(text: &str) -> GuestEvents {
+    let mut events = GuestEvents::default();
+    for line in text.lines() {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        // <dev> <cpu> <seq> <time> <pid> <action> <rwbs> <sector> + <sectors> ...
+        if fields.len() < 10 || fields[8] != "+" {
+            continue;
+        }
+        let (time, action, rwbs, sector, sectors) =
+            (fields[3], fields[5], fields[6], fields[7], fields[9]);
+        let write = if rwbs.contains('W') {
+            true
+        } else if rwbs.contains('R') {
+            false
+        } else {
+            continue;
+        };
+        let (Ok(time), Ok(sector), Ok(sectors)) = (
+            time.parse::<f64>(),
+            sector.parse::<u64>(),
+            sectors.parse::<u64>(),
+        ) else {
+            continue;
+        };
+        let list = match action {
+            "D" => &mut events.dispatched,
+            "C" => &mut events.completed,
+            _ => continue,
+        };
+        list.entry(IoKey {
+            write,
+            sector,
+            sectors,
+        })
+        .or_default()
+        .push_back((time * 1e9) as u64);
+    }
+    events
+}
+
+fn us(ns: i128) -> f64 {
+    ns as f64 / 1000.0
+}
+
+fn main() -> anyhow::Result<()> {
+    let mut paths = Vec::new();
+    let mut lba_size = 512;
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--lba-size" {
+            lba_size = args
+                .next()
+                .and_then(|v| v.to_str()?.parse().ok())
+                .context("--lba-size requires a size in bytes")?;
+        } else {
+            paths.push(PathBuf::from(arg));
+        }
+    }
+    let (trace_path, blkparse_path) = match paths.as_slice() {
+        [trace] => (trace, None),
+        [trace, blkparse] => (trace, Some(blkparse)),
+        _ => anyhow::bail!("usage: nvme_io_trace <trace> [<blkparse.txt>] [--lba-size <bytes>]"),
+    };
+
+    let data = fs_err::read(trace_path)?;
+    let (_, mut records) = parse_io_trace(&data).context("failed to parse trace")?;
+    records.sort_by_key(|r| r.submit_ns);
+
+    let Some(blkparse_path) = blkparse_path else {
+        println!("sqid,cid,opcode,slba,nlb,status,queued_us,backend_us,post_us");
+        for r in &records {
+            println!(
+                "{},{},{:?},{},{},{:#x},{:.1},{:.1},{:.1}",
+                r.sqid,
+                r.cid,
+                NvmOpcode(r.opcode),
+                r.slba,
+                r.nlb,
+                r.status,
+                us(r.start_ns as i128 - r.submit_ns as i128),
+                us(r.complete_ns as i128 - r.start_ns as i128),
+                us(r.post_ns as i128 - r.complete_ns as i128),
+            );
+        }
+        return Ok(());
+    };
+
+    let mut guest = parse_blkparse(&fs_err::read_to_string(blkparse_path)?);
+    let sectors_per_lba = (lba_size / BLKTRACE_SECTOR_SIZE).max(1);
+    let matched: Vec<(&IoTraceRecord, u64, Option<u64>)> = records
+        .iter()
+        .filter_map(|r| {
+            let write = match NvmOpcode(r.opcode) {
+                NvmOpcode::READ => false,
+                NvmOpcode::WRITE => true,
+                _ => return None,
+            };
+            let key = IoKey {
+                write,
+                sector: r.slba * sectors_per_lba,
+                sectors: r.nlb as u64 * sectors_per_lba,
+            };
+            let dispatched = guest.dispatched.get_mut(&key)?.pop_front()?;
+            let completed = guest
+                .completed
+                .get_mut(&key)
+                .and_then(|list| list.pop_front());
+            Some((r, dispatched, completed))
+        })
+        .collect();
+
+    // Host time minus guest time, for the IO that reached the emulator
+    // fastest.
+    let offset = matched
+        .iter()
+        .map(|&(r, dispatched, _)| r.submit_ns as i128 - dispatched as i128)
+        .min()
+        .context("no IOs matched the guest trace")?;
+
+    println!(
+        "sqid,cid,opcode,slba,nlb,status,to_emulator_us,queued_us,backend_us,post_us,to_guest_us"
+    );
+    for &(r, dispatched, completed) in &matched {
+        let to_guest = completed.map_or(String::new(), |completed| {
+            format!("{:.1}", us(completed as i128 + offset - r.post_ns as i128))
+        });
+        println!(
+            "{},{},{:?},{},{},{:#x},{:.1},{:.1},{:.1},{:.1},{}",
+            r.sqid,
+            r.cid,
+            NvmOpcode(r.opcode),
+            r.slba,
+            r.nlb,
+            r.status,
+            us(r.submit_ns as i128 - (dispatched as i128 + offset)),
+            us(r.start_ns as i128 - r.submit_ns as i128),
+            us(r.complete_ns as i128 - r.start_ns as i128),
+            us(r.post_ns as i128 - r.complete_ns as i128),
+            to_guest,
+        );
+    }
+    eprintln!(
+        "matched {} of {} traced commands",
+        matched.len(),
+        records.len()
+    );
+    Ok(())
+}
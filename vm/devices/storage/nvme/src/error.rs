@@ -20,6 +20,11 @@ pub fn new(status: spec::Status, source: impl Into<Box<dyn Error + Send + Sync>>
             source: Some(source.into()),
         }
     }
+
+    /// Returns the status to complete the command with.
+    pub fn status(&self) -> spec::Status {
+        self.status
+    }
 }
 
 impl Error for NvmeError {
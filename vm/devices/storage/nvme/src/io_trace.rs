@@ -0,0 +1,258 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Per-command IO latency tracing.
+//!
+//! When enabled via [`NvmeControllerClient::set_io_trace`], the IO workers
+//! write one fixed-size [`IoTraceRecord`] per completed command to a trace
+//! file, after an [`IoTraceHeader`]. Each record has the time the command was
+//! read from its submission queue, when the backend started and completed it,
+//! and when its completion was posted, so that the latency of each stage can
+//! be separated.
+//!
+//! The `nvme_io_trace` example merges a trace with guest `blkparse` output to
+//! break down each IO's end-to-end latency between the guest, the emulator,
+//! and the backend disk.
+//!
+//! [`NvmeControllerClient::set_io_trace`]: crate::NvmeControllerClient::set_io_trace
+
+use parking_lot::Mutex;
+use parking_lot::RwLock;
+use std::io::BufWriter;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Instant;
+use std::time::SystemTime;
+use thiserror::Error;
+use zerocopy::FromBytes;
+use zerocopy::Immutable;
+use zerocopy::IntoBytes;
+use zerocopy::KnownLayout;
+
+/// The magic number at the start of a trace file.
+pub const IO_TRACE_MAGIC: [u8; 8] = *b"NVMEIOTR";
+
+/// The current trace format version.
+pub const IO_TRACE_VERSION: u32 = 1;
+
+/// The header at the start of a trace file.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, IntoBytes, FromBytes, Immutable, KnownLayout)]
+pub struct IoTraceHeader {
+    /// [`IO_TRACE_MAGIC`].
+    pub magic: [u8; 8],
+    /// [`IO_TRACE_VERSION`].
+    pub version: u32,
+    /// The size of each record, in bytes.
+    pub record_size: u32,
+    /// The wall clock time the trace started, in nanoseconds since the Unix
+    /// epoch. Record timestamps are relative to this.
+    pub start_unix_ns: u64,
+}
+
+/// A single command's trace record.
+///
+/// Timestamps are in nanoseconds since the trace started.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, IntoBytes, FromBytes, Immutable, KnownLayout)]
+pub struct IoTraceRecord {
+    /// When the command was read from its submission queue.
+    pub submit_ns: u64,
+    /// When the backend started processing the command.
+    pub start_ns: u64,
+    /// When the backend completed the command.
+    pub complete_ns: u64,
+    /// When the command's completion was posted to its completion queue.
+    pub post_ns: u64,
+    /// The starting LBA, for reads and writes.
+    pub slba: u64,
+    /// The namespace ID.
+    pub nsid: u32,
+    /// The number of logical blocks, for reads and writes.
+    pub nlb: u32,
+    /// The submission queue ID.
+    pub sqid: u16,
+    /// The command ID.
+    pub cid: u16,
+    /// The completion status field.
+    pub status: u16,
+    /// The NVM command set opcode.
+    pub opcode: u8,
+    /// Reserved, zero.
+    pub reserved: u8,
+}
+
+/// An error parsing a trace file.
+#[derive(Debug, Error)]
+pub enum IoTraceParseError {
+    /// The file is too short for the header.
+    #[error("trace is too short for the header")]
+    TruncatedHeader,
+    /// The file does not start with [`IO_TRACE_MAGIC`].
+    #[error("not an nvme io trace")]
+    BadMagic,
+    /// The file is from an unsupported version of the format.
+    #[error("unsupported trace version {0}")]
+    UnsupportedVersion(u32),
+    /// The header's record size does not match this version of the format.
+    #[error("unexpected record size {0}")]
+    BadRecordSize(u32),
+}
+
+/// Parses a trace file, ignoring any partially written record at the end.
+pub fn parse_io_trace(
+    data: &[u8],
+) -> Result<(IoTraceHeader, Vec<IoTraceRecord>), IoTraceParseError> {
+    let (header, data) =
+        IoTraceHeader::read_from_prefix(data).map_err(|_| IoTraceParseError::TruncatedHeader)?;
+    if header.magic != IO_TRACE_MAGIC {
+        return Err(IoTraceParseError::BadMagic);
+    }
+    if header.version != IO_TRACE_VERSION {
+        return Err(IoTraceParseError::UnsupportedVersion(header.version));
+    }
+    if header.record_size as usize != size_of::<IoTraceRecord>() {
+        return Err(IoTraceParseError::BadRecordSize(header.record_size));
+    }
+    let records = data
+        .chunks_exact(size_of::<IoTraceRecord>())
+        .map(|chunk| IoTraceRecord::read_from_bytes(chunk).unwrap())
+        .collect();
+    Ok((header, records))
+}
+
+/// A trace file that IO workers write records to.
+#[derive(Clone)]
+pub struct IoTrace(Arc<IoTraceInner>);
+
+struct IoTraceInner {
+    start: Instant,
+    writer: Mutex<BufWriter<Box<dyn Write + Send>>>,
+}
+
+impl IoTrace {
+    /// Starts a trace, writing the header to `writer`.
+    pub fn new(writer: impl 'static + Write + Send) -> std::io::Result<Self> {
+        let start = Instant::now();
+        let start_unix_ns = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        let mut writer = BufWriter::new(Box::new(writer) as Box<dyn Write + Send>);
+        writer.write_all(
+            IoTraceHeader {
+                magic: IO_TRACE_MAGIC,
+                version: IO_TRACE_VERSION,
+                record_size: size_of::<IoTraceRecord>() as u32,
+                start_unix_ns,
+            }
+            .as_bytes(),
+        )?;
+        Ok(Self(Arc::new(IoTraceInner {
+            start,
+            writer: Mutex::new(writer),
+        })))
+    }
+
+    /// Returns the trace timestamp for `instant`.
+    pub(crate) fn timestamp(&self, instant: Instant) -> u64 {
+        instant.saturating_duration_since(self.0.start).as_nanos() as u64
+    }
+
+    pub(crate) fn write(&self, record: &IoTraceRecord) {
+        if let Err(err) = self.0.writer.lock().write_all(record.as_bytes()) {
+            tracelimit::warn_ratelimited!(
+                error = &err as &dyn std::error::Error,
+                "failed to write io trace record"
+            );
+        }
+    }
+}
+
+/// The trace shared by a controller's IO workers, which can be replaced at
+/// runtime.
+#[derive(Clone, Default)]
+pub(crate) struct SharedIoTrace(Arc<RwLock<Option<IoTrace>>>);
+
+impl SharedIoTrace {
+    pub fn get(&self) -> Option<IoTrace> {
+        self.0.read().clone()
+    }
+
+    pub fn set(&self, trace: Option<IoTrace>) {
+        *self.0.write() = trace;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(cid: u16) -> IoTraceRecord {
+        IoTraceRecord {
+            submit_ns: 100,
+            start_ns: 200,
+            complete_ns: 300,
+            post_ns: 400,
+            slba: 0x1000,
+            nsid: 1,
+            nlb: 8,
+            sqid: 1,
+            cid,
+            status: 0,
+            opcode: 2,
+            reserved: 0,
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let buf = SharedBuf::default();
+        let trace = IoTrace::new(buf.clone()).unwrap();
+        trace.write(&record(1));
+        trace.write(&record(2));
+        drop(trace);
+
+        let mut data = buf.0.lock().clone();
+        // A partially written record is ignored.
+        data.extend_from_slice(&[0; 10]);
+        let (header, records) = parse_io_trace(&data).unwrap();
+        assert_eq!(header.magic, IO_TRACE_MAGIC);
+        assert_eq!(records, [record(1), record(2)]);
+    }
+
+    #[test]
+    fn test_bad_header() {
+        assert!(matches!(
+            parse_io_trace(&[0; 4]),
+            Err(IoTraceParseError::TruncatedHeader)
+        ));
+        assert!(matches!(
+            parse_io_trace(&[0; 64]),
+            Err(IoTraceParseError::BadMagic)
+        ));
+        let header = IoTraceHeader {
+            magic: IO_TRACE_MAGIC,
+            version: IO_TRACE_VERSION + 1,
+            record_size: size_of::<IoTraceRecord>() as u32,
+            start_unix_ns: 0,
+        };
+        assert!(matches!(
+            parse_io_trace(header.as_bytes()),
+            Err(IoTraceParseError::UnsupportedVersion(_))
+        ));
+    }
+}
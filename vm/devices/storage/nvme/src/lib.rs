@@ -41,6 +41,7 @@
 #![forbid(unsafe_code)]
 
 mod error;
+pub mod io_trace;
 mod namespace;
 mod pci;
 mod prp;
@@ -51,6 +52,7 @@
 #[cfg(test)]
 mod tests;
 
+pub use pci::BootPartitions;
 pub use pci::NvmeController;
 pub use pci::NvmeControllerCaps;
 pub use workers::NsidConflict;
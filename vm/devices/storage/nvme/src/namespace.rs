@@ -11,9 +11,18 @@
 use crate::spec;
 use crate::spec::nvm;
 use disk_backend::Disk;
+use disk_backend_resources::DiskTelemetry;
+use disk_backend_resources::IoTraceLevel;
 use guestmem::GuestMemory;
 use inspect::Inspect;
+use inspect_counters::Histogram;
+use inspect_counters::SharedCounter;
+use parking_lot::Mutex;
 use scsi_buffers::RequestBuffers;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
 use zerocopy::FromBytes;
 use zerocopy::FromZeros;
 use zerocopy::IntoBytes;
@@ -26,6 +35,32 @@ pub struct Namespace {
     mem: GuestMemory,
     block_shift: u32,
     pr: bool,
+    /// Fail dataset management commands with malformed range lists, rather
+    /// than ignoring the malformed parts.
+    #[inspect(with = "inspect::AtomicMut")]
+    strict_dsm: AtomicBool,
+    dsm: DsmStats,
+    telemetry: IoTelemetry,
+}
+
+/// Statistics on the dataset management commands and hints sent by the
+/// guest.
+#[derive(Inspect, Default)]
+struct DsmStats {
+    commands: SharedCounter,
+    ranges: SharedCounter,
+    deallocate: SharedCounter,
+    integral_read: SharedCounter,
+    integral_write: SharedCounter,
+    sequential_read: SharedCounter,
+    sequential_write: SharedCounter,
+    write_prepare: SharedCounter,
+    #[inspect(iter_by_index)]
+    access_frequency: [SharedCounter; 16],
+    #[inspect(iter_by_index)]
+    access_latency: [SharedCounter; 4],
+    /// Commands with reserved fields set or out of range LBAs.
+    malformed: SharedCounter,
 }
 
 impl Namespace {
@@ -33,6 +68,9 @@ pub fn new(mem: GuestMemory, nsid: u32, disk: Disk) -> Self {
         Self {
             block_shift: disk.sector_size().trailing_zeros(),
             pr: disk.pr().is_some(),
+            strict_dsm: AtomicBool::new(false),
+            dsm: DsmStats::default(),
+            telemetry: IoTelemetry::default(),
             mem,
             disk,
             nsid,
@@ -114,10 +152,28 @@ pub async fn wait_change(&self, token: Option<u64>) -> u64 {
         self.disk.wait_resize(sector_count).await
     }
 
+    /// Changes the opt-in diagnostics for IOs to this namespace.
+    pub fn set_telemetry(&self, telemetry: DiskTelemetry) {
+        self.telemetry.set(telemetry);
+    }
+
     pub async fn nvm_command(
         &self,
         max_data_transfer_size: usize,
         command: &spec::Command,
+    ) -> Result<CommandResult, NvmeError> {
+        let start = self.telemetry.start();
+        let result = self
+            .execute_nvm_command(max_data_transfer_size, command)
+            .await;
+        self.telemetry.complete(self.nsid, command, start, &result);
+        result
+    }
+
+    async fn execute_nvm_command(
+        &self,
+        max_data_transfer_size: usize,
+        command: &spec::Command,
     ) -> Result<CommandResult, NvmeError> {
         let opcode = nvm::NvmOpcode(command.cdw0.opcode());
         tracing::trace!(nsid = self.nsid, ?opcode, ?command, "nvm command");
@@ -190,6 +246,18 @@ pub async fn nvm_command(
                     PrpRange::parse(&self.mem, size_of_val(dsm_ranges.as_ref()), command.dptr)?;
                 prp.read(&self.mem, dsm_ranges.as_mut_bytes())?;
                 tracing::debug!(nsid = self.nsid, ?cdw11, ?dsm_ranges, "dsm");
+                if let Err(status) = self.account_dsm(command, &dsm_ranges) {
+                    self.dsm.malformed.increment();
+                    if self.strict_dsm.load(Ordering::Relaxed) {
+                        tracelimit::warn_ratelimited!(
+                            nsid = self.nsid,
+                            ?status,
+                            ?dsm_ranges,
+                            "malformed dsm command"
+                        );
+                        return Err(status.into());
+                    }
+                }
                 if cdw11.ad() {
                     for range in dsm_ranges.as_ref() {
                         self.disk
@@ -224,36 +292,292 @@ pub async fn nvm_command(
     }
 }
 
-fn map_disk_error(err: disk_backend::DiskError) -> NvmeError {
-    match err {
-        disk_backend::DiskError::ReservationConflict => spec::Status::RESERVATION_CONFLICT.into(),
-        disk_backend::DiskError::MemoryAccess(err) => {
-            NvmeError::new(spec::Status::DATA_TRANSFER_ERROR, err)
+impl Namespace {
+    /// Accounts the hints in a dataset management command, returning the
+    /// status to fail the command with in strict mode if it is malformed.
+    fn account_dsm(
+        &self,
+        command: &spec::Command,
+        ranges: &[nvm::DsmRange],
+    ) -> Result<(), spec::Status> {
+        let cdw11 = nvm::Cdw11Dsm::from(command.cdw11);
+        let stats = &self.dsm;
+        stats.commands.increment();
+        stats.ranges.add(ranges.len() as u64);
+        if cdw11.ad() {
+            stats.deallocate.increment();
         }
-        disk_backend::DiskError::AbortDueToPreemptAndAbort => {
-            NvmeError::new(spec::Status::COMMAND_ABORTED_DUE_TO_PREEMPT_AND_ABORT, err)
+        if cdw11.idr() {
+            stats.integral_read.increment();
         }
-        disk_backend::DiskError::IllegalBlock => spec::Status::LBA_OUT_OF_RANGE.into(),
-        disk_backend::DiskError::InvalidInput => spec::Status::INVALID_FIELD_IN_COMMAND.into(),
-        disk_backend::DiskError::Io(err) => NvmeError::new(spec::Status::DATA_TRANSFER_ERROR, err),
-        disk_backend::DiskError::MediumError(_, details) => match details {
-            disk_backend::MediumErrorDetails::ApplicationTagCheckFailed => {
-                spec::Status::MEDIA_END_TO_END_APPLICATION_TAG_CHECK_ERROR.into()
+        if cdw11.idw() {
+            stats.integral_write.increment();
+        }
+
+        let mut result = Ok(());
+        if command.cdw10 & !0xff != 0 || u32::from(cdw11) & !0x7 != 0 {
+            result = Err(spec::Status::INVALID_FIELD_IN_COMMAND);
+        }
+
+        let sector_count = self.disk.sector_count();
+        for range in ranges {
+            let attributes = nvm::DsmContextAttributes::from(range.context_attributes);
+            stats.access_frequency[attributes.access_frequency() as usize].increment();
+            stats.access_latency[attributes.access_latency() as usize].increment();
+            if attributes.sequential_read() {
+                stats.sequential_read.increment();
             }
-            disk_backend::MediumErrorDetails::GuardCheckFailed => {
-                spec::Status::MEDIA_END_TO_END_GUARD_CHECK_ERROR.into()
+            if attributes.sequential_write() {
+                stats.sequential_write.increment();
             }
-            disk_backend::MediumErrorDetails::ReferenceTagCheckFailed => {
-                spec::Status::MEDIA_END_TO_END_REFERENCE_TAG_CHECK_ERROR.into()
+            if attributes.write_prepare() {
+                stats.write_prepare.increment();
             }
-            disk_backend::MediumErrorDetails::UnrecoveredReadError => {
-                spec::Status::MEDIA_UNRECOVERED_READ_ERROR.into()
+
+            if result.is_ok() {
+                if range.context_attributes & nvm::DsmContextAttributes::RESERVED_MASK != 0
+                    || attributes.access_frequency()
+                        > nvm::DsmContextAttributes::MAX_ACCESS_FREQUENCY
+                {
+                    result = Err(spec::Status::INVALID_FIELD_IN_COMMAND);
+                } else if range
+                    .starting_lba
+                    .checked_add(range.lba_count.into())
+                    .is_none_or(|end| end > sector_count)
+                {
+                    result = Err(spec::Status::LBA_OUT_OF_RANGE);
+                }
             }
-            disk_backend::MediumErrorDetails::WriteFault => spec::Status::MEDIA_WRITE_FAULT.into(),
-        },
-        disk_backend::DiskError::ReadOnly => {
-            spec::Status::ATTEMPTED_WRITE_TO_READ_ONLY_RANGE.into()
         }
-        disk_backend::DiskError::UnsupportedEject => spec::Status::INVALID_COMMAND_OPCODE.into(),
+        result
+    }
+}
+
+/// Opt-in per-namespace diagnostics, which can be changed while the namespace
+/// is in use.
+#[derive(Default)]
+struct IoTelemetry {
+    latency_histogram: AtomicBool,
+    /// The [`IoTraceLevel`], as a `u8`.
+    trace_level: AtomicU8,
+    /// IO latencies in microseconds.
+    latency_us: Mutex<Histogram<16>>,
+}
+
+impl IoTelemetry {
+    fn set(&self, telemetry: DiskTelemetry) {
+        let DiskTelemetry {
+            latency_histogram,
+            trace_level,
+        } = telemetry;
+        self.latency_histogram
+            .store(latency_histogram, Ordering::Relaxed);
+        self.trace_level.store(trace_level as u8, Ordering::Relaxed);
+    }
+
+    fn trace_level(&self) -> IoTraceLevel {
+        match self.trace_level.load(Ordering::Relaxed) {
+            x if x == IoTraceLevel::Info as u8 => IoTraceLevel::Info,
+            x if x == IoTraceLevel::Debug as u8 => IoTraceLevel::Debug,
+            x if x == IoTraceLevel::Trace as u8 => IoTraceLevel::Trace,
+            _ => IoTraceLevel::Off,
+        }
+    }
+
+    /// Returns the start time of an IO, if it is needed.
+    fn start(&self) -> Option<Instant> {
+        (self.latency_histogram.load(Ordering::Relaxed) || self.trace_level() != IoTraceLevel::Off)
+            .then(Instant::now)
+    }
+
+    fn complete(
+        &self,
+        nsid: u32,
+        command: &spec::Command,
+        start: Option<Instant>,
+        result: &Result<CommandResult, NvmeError>,
+    ) {
+        let Some(start) = start else {
+            return;
+        };
+        let latency_us = start.elapsed().as_micros() as u64;
+        if self.latency_histogram.load(Ordering::Relaxed) {
+            self.latency_us.lock().add_sample(latency_us);
+        }
+        let opcode = nvm::NvmOpcode(command.cdw0.opcode());
+        let status = match result {
+            Ok(result) => result.status,
+            Err(err) => err.status(),
+        };
+        macro_rules! trace_io {
+            ($level:expr) => {
+                tracing::event!(
+                    $level,
+                    nsid,
+                    ?opcode,
+                    ?status,
+                    latency_us,
+                    "nvme io completed"
+                )
+            };
+        }
+        match self.trace_level() {
+            IoTraceLevel::Off => {}
+            IoTraceLevel::Info => trace_io!(tracing::Level::INFO),
+            IoTraceLevel::Debug => trace_io!(tracing::Level::DEBUG),
+            IoTraceLevel::Trace => trace_io!(tracing::Level::TRACE),
+        }
+    }
+}
+
+impl Inspect for IoTelemetry {
+    fn inspect(&self, req: inspect::Request<'_>) {
+        req.respond()
+            .field("latency_histogram", &self.latency_histogram)
+            .field("trace_level", format!("{:?}", self.trace_level()))
+            .field("latency_us", &*self.latency_us.lock());
+    }
+}
+
+fn map_disk_error(err: disk_backend::DiskError) -> NvmeError {
+    NvmeError::new(nvme_common::disk_error_to_nvme_status(&err), err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Namespace;
+    use crate::error::CommandResult;
+    use crate::spec;
+    use crate::spec::nvm;
+    use disk_backend_resources::DiskTelemetry;
+    use disk_backend_resources::IoTraceLevel;
+    use guestmem::GuestMemory;
+    use pal_async::async_test;
+    use std::sync::atomic::Ordering;
+    use zerocopy::FromZeros;
+    use zerocopy::IntoBytes;
+
+    async fn deallocate(
+        namespace: &Namespace,
+        mem: &GuestMemory,
+        range: nvm::DsmRange,
+    ) -> spec::Status {
+        mem.write_at(0, range.as_bytes()).unwrap();
+        let mut command = spec::Command::new_zeroed();
+        command.cdw0.set_opcode(nvm::NvmOpcode::DSM.0);
+        command.cdw11 = nvm::Cdw11Dsm::new().with_ad(true).into();
+        match namespace.nvm_command(0x10000, &command).await {
+            Ok(result) => result.status,
+            Err(err) => CommandResult::from(err).status,
+        }
+    }
+
+    #[async_test]
+    async fn test_dsm_hints_and_strict_mode() {
+        let mem = GuestMemory::allocate(0x1000);
+        let disk = disklayer_ram::ram_disk(0x100000, false).unwrap();
+        let namespace = Namespace::new(mem.clone(), 1, disk);
+        let sector_count = 0x100000 / 512;
+
+        let hinted = nvm::DsmRange {
+            context_attributes: nvm::DsmContextAttributes::new()
+                .with_access_frequency(6)
+                .with_access_latency(3)
+                .with_sequential_read(true)
+                .into(),
+            lba_count: 8,
+            starting_lba: 0,
+        };
+        let out_of_range = nvm::DsmRange {
+            context_attributes: 0,
+            lba_count: 8,
+            starting_lba: sector_count - 4,
+        };
+        let reserved = nvm::DsmRange {
+            context_attributes: nvm::DsmContextAttributes::new()
+                .with_access_frequency(nvm::DsmContextAttributes::MAX_ACCESS_FREQUENCY + 1)
+                .into(),
+            lba_count: 8,
+            starting_lba: 0,
+        };
+
+        assert_eq!(
+            deallocate(&namespace, &mem, hinted).await,
+            spec::Status::SUCCESS
+        );
+        assert_eq!(namespace.dsm.access_frequency[6].get(), 1);
+        assert_eq!(namespace.dsm.access_latency[3].get(), 1);
+        assert_eq!(namespace.dsm.sequential_read.get(), 1);
+        assert_eq!(namespace.dsm.malformed.get(), 0);
+
+        // Malformed ranges are only counted by default...
+        assert_eq!(
+            deallocate(&namespace, &mem, reserved).await,
+            spec::Status::SUCCESS
+        );
+        assert_eq!(namespace.dsm.malformed.get(), 1);
+
+        // ...but fail in strict mode.
+        namespace.strict_dsm.store(true, Ordering::Relaxed);
+        assert_eq!(
+            deallocate(&namespace, &mem, out_of_range).await,
+            spec::Status::LBA_OUT_OF_RANGE
+        );
+        assert_eq!(
+            deallocate(&namespace, &mem, reserved).await,
+            spec::Status::INVALID_FIELD_IN_COMMAND
+        );
+        assert_eq!(
+            deallocate(&namespace, &mem, hinted).await,
+            spec::Status::SUCCESS
+        );
+        assert_eq!(namespace.dsm.commands.get(), 5);
+        assert_eq!(namespace.dsm.malformed.get(), 3);
+    }
+    #[async_test]
+    async fn test_telemetry() {
+        let mem = GuestMemory::allocate(0x1000);
+        let disk = disklayer_ram::ram_disk(0x100000, false).unwrap();
+        let namespace = Namespace::new(mem, 1, disk);
+
+        let latency_samples = || {
+            let mut inspection = inspect::inspect("telemetry/latency_us", &namespace);
+            futures::executor::block_on(inspection.resolve());
+            let inspect::Node::Dir(buckets) = inspection.results() else {
+                panic!("latency histogram missing");
+            };
+            buckets
+                .iter()
+                .map(|bucket| match &bucket.node {
+                    inspect::Node::Value(inspect::Value {
+                        kind: inspect::ValueKind::Unsigned(n),
+                        ..
+                    }) => *n,
+                    node => panic!("unexpected bucket {node:?}"),
+                })
+                .sum::<u64>()
+        };
+
+        let mut flush = spec::Command::new_zeroed();
+        flush.cdw0.set_opcode(nvm::NvmOpcode::FLUSH.0);
+        let mut unsupported = spec::Command::new_zeroed();
+        unsupported.cdw0.set_opcode(0x7f);
+
+        // Nothing is recorded by default.
+        namespace.nvm_command(0x10000, &flush).await.unwrap();
+        assert_eq!(latency_samples(), 0);
+
+        namespace.set_telemetry(DiskTelemetry {
+            latency_histogram: true,
+            trace_level: IoTraceLevel::Info,
+        });
+        namespace.nvm_command(0x10000, &flush).await.unwrap();
+        // Failed commands are recorded too.
+        assert!(namespace.nvm_command(0x10000, &unsupported).await.is_err());
+        assert_eq!(latency_samples(), 2);
+
+        namespace.set_telemetry(DiskTelemetry::default());
+        namespace.nvm_command(0x10000, &flush).await.unwrap();
+        assert_eq!(latency_samples(), 2);
     }
 }
@@ -30,6 +30,9 @@
 use inspect::Inspect;
 use inspect::InspectMut;
 use parking_lot::Mutex;
+use pci_core::capabilities::extended::aer::AerError;
+use pci_core::capabilities::extended::aer::AerExtendedCapability;
+use pci_core::capabilities::extended::aer::AerSeverity;
 use pci_core::capabilities::msix::MsixEmulator;
 use pci_core::capabilities::pci_express::PciExpressCapability;
 use pci_core::cfg_space_emu::BarMemoryKind;
@@ -42,6 +45,7 @@
 use pci_core::spec::hwid::Subclass;
 use std::sync::Arc;
 use vmcore::device_state::ChangeDeviceState;
+use vmcore::interrupt_latency::InterruptLatency;
 use vmcore::save_restore::SaveError;
 use vmcore::save_restore::SaveRestore;
 use vmcore::save_restore::SavedStateNotSupported;
@@ -59,6 +63,12 @@ pub struct NvmeController {
     qe_sizes: Arc<Mutex<IoQueueEntrySizes>>,
     #[inspect(flatten, mut)]
     workers: NvmeWorkers,
+    #[inspect(skip)]
+    guest_memory: GuestMemory,
+    boot_partitions: Option<BootPartitions>,
+    subsystem_reset_pending: bool,
+    /// Latency from completion to interrupt delivery, by MSI-X vector.
+    interrupt_latency: InterruptLatency,
 }
 
 #[derive(Inspect)]
@@ -72,6 +82,11 @@ struct RegState {
     asq: u64,
     #[inspect(hex)]
     acq: u64,
+    bprsel: spec::Bprsel,
+    #[inspect(hex)]
+    bpmbl: u64,
+    #[inspect(debug)]
+    brs: spec::BootReadStatus,
 }
 
 impl RegState {
@@ -83,6 +98,9 @@ fn new() -> Self {
             aqa: spec::Aqa::new(),
             asq: 0,
             acq: 0,
+            bprsel: spec::Bprsel::new(),
+            bpmbl: 0,
+            brs: spec::BootReadStatus::NO_READ,
         }
     }
 }
@@ -91,11 +109,12 @@ fn new() -> Self {
     .with_dstrd(DOORBELL_STRIDE_BITS - 2)
     .with_mqes_z(MAX_QES - 1)
     .with_cqr(true)
+    .with_nssrs(true)
     .with_css_nvm(true)
     .with_to(!0);
 
 /// The NVMe controller's capabilities.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct NvmeControllerCaps {
     /// The number of entries in the MSI-X table.
     pub msix_count: u16,
@@ -104,6 +123,61 @@ pub struct NvmeControllerCaps {
     /// The subsystem ID, used as part of the subnqn field of the identify
     /// controller response.
     pub subsystem_id: Guid,
+    /// The boot partitions to expose, if any.
+    pub boot_partitions: Option<BootPartitions>,
+}
+
+/// The contents of an NVMe controller's two boot partitions.
+///
+/// Boot partitions can be read through the BPINFO, BPRSEL, and BPMBL
+/// registers, without enabling the controller, so that firmware can load boot
+/// code from them.
+#[derive(Debug, Clone, Inspect)]
+pub struct BootPartitions {
+    #[inspect(skip)]
+    partitions: Arc<[Vec<u8>; 2]>,
+    /// The active boot partition ID.
+    active: u8,
+}
+
+impl BootPartitions {
+    /// Creates boot partitions with the given contents, with partition 0
+    /// active.
+    ///
+    /// The partitions are zero-padded to a common size that is a multiple of
+    /// 128KB.
+    pub fn new(partitions: [Vec<u8>; 2]) -> Self {
+        let len = partitions
+            .iter()
+            .map(|p| p.len())
+            .max()
+            .unwrap()
+            .next_multiple_of(spec::BOOT_PARTITION_SIZE_UNIT as usize);
+        assert!(
+            len as u64 / spec::BOOT_PARTITION_SIZE_UNIT < 1 << 15,
+            "boot partitions too large"
+        );
+        Self {
+            partitions: Arc::new(partitions.map(|mut p| {
+                p.resize(len, 0);
+                p
+            })),
+            active: 0,
+        }
+    }
+
+    /// Sets the boot partition reported as active.
+    pub fn with_active(mut self, id: u8) -> Self {
+        assert!(id < 2, "invalid boot partition id");
+        self.active = id;
+        self
+    }
+
+    fn bpinfo(&self) -> spec::Bpinfo {
+        spec::Bpinfo::new()
+            .with_bpsz((self.partitions[0].len() as u64 / spec::BOOT_PARTITION_SIZE_UNIT) as u16)
+            .with_abpid(self.active != 0)
+    }
 }
 
 impl NvmeController {
@@ -144,18 +218,23 @@ pub fn new(
                     None,
                 )),
             ],
-            Vec::new(),
+            vec![Box::new(AerExtendedCapability::new())],
             bars,
         );
 
+        let interrupt_latency = InterruptLatency::new();
         let interrupts = (0..caps.msix_count)
-            .map(|i| msix.interrupt(i).unwrap())
+            .map(|i| {
+                msix.interrupt(i)
+                    .unwrap()
+                    .with_latency(interrupt_latency.clone(), i.into())
+            })
             .collect();
 
         let qe_sizes = Arc::new(Default::default());
         let admin = NvmeWorkers::new(
             driver_source,
-            guest_memory,
+            guest_memory.clone(),
             interrupts,
             caps.max_io_queues,
             caps.max_io_queues,
@@ -169,6 +248,10 @@ pub fn new(
             registers: RegState::new(),
             workers: admin,
             qe_sizes,
+            guest_memory,
+            boot_partitions: caps.boot_partitions,
+            subsystem_reset_pending: false,
+            interrupt_latency,
         }
     }
 
@@ -188,10 +271,10 @@ pub fn read_bar0(&mut self, addr: u64, data: &mut [u8]) -> IoResult {
 
         // Check for 64-bit registers.
         let d: Option<u64> = match spec::Register(addr & !7) {
-            spec::Register::CAP => Some(CAP.into()),
+            spec::Register::CAP => Some(CAP.with_bps(self.boot_partitions.is_some()).into()),
             spec::Register::ASQ => Some(self.registers.asq),
             spec::Register::ACQ => Some(self.registers.acq),
-            spec::Register::BPMBL => Some(0),
+            spec::Register::BPMBL => Some(self.registers.bpmbl),
             _ => None,
         };
         if let Some(d) = d {
@@ -221,8 +304,8 @@ pub fn read_bar0(&mut self, addr: u64, data: &mut [u8]) -> IoResult {
             spec::Register::AQA => self.registers.aqa.into(),
             spec::Register::CMBLOC => 0,
             spec::Register::CMBSZ => 0,
-            spec::Register::BPINFO => 0,
-            spec::Register::BPRSEL => 0,
+            spec::Register::BPINFO => self.bpinfo().into(),
+            spec::Register::BPRSEL => self.registers.bprsel.into(),
             _ => return IoResult::Err(InvalidRegister),
         };
         data.copy_from_slice(&d.to_ne_bytes());
@@ -288,6 +371,10 @@ pub fn write_bar0(&mut self, addr: u64, data: &[u8]) -> IoResult {
                 }
                 true
             }
+            spec::Register::BPMBL if self.boot_partitions.is_some() => {
+                self.registers.bpmbl = update_reg(self.registers.bpmbl) & PAGE_MASK;
+                true
+            }
             _ => false,
         };
         if handled {
@@ -304,7 +391,22 @@ pub fn write_bar0(&mut self, addr: u64, data: &[u8]) -> IoResult {
             spec::Register::INTMS => self.registers.interrupt_mask |= data,
             spec::Register::INTMC => self.registers.interrupt_mask &= !data,
             spec::Register::CC => self.set_cc(data.into()),
+            spec::Register::CSTS => {
+                // NSSRO is cleared by writing 1 to it. The other bits are
+                // read-only.
+                if spec::Csts::from(data).nssro() {
+                    self.registers.csts.set_nssro(false);
+                }
+            }
+            spec::Register::NSSR => {
+                if data == spec::NSSR_RESET_VALUE {
+                    self.subsystem_reset();
+                }
+            }
             spec::Register::AQA => self.registers.aqa = data.into(),
+            spec::Register::BPRSEL if self.boot_partitions.is_some() => {
+                self.read_boot_partition(data.into())
+            }
             _ => return IoResult::Err(InvalidRegister),
         }
         IoResult::Ok
@@ -400,12 +502,52 @@ fn set_cc(&mut self, cc: spec::Cc) {
         };
     }
 
+    fn bpinfo(&self) -> spec::Bpinfo {
+        self.boot_partitions
+            .as_ref()
+            .map_or(spec::Bpinfo::new(), |bp| bp.bpinfo())
+            .with_brs(self.registers.brs.0)
+    }
+
+    /// Copies the boot partition range selected by `bprsel` to the buffer at
+    /// BPMBL.
+    ///
+    /// The copy is completed synchronously, so the guest never observes the
+    /// in-progress read status.
+    fn read_boot_partition(&mut self, bprsel: spec::Bprsel) {
+        tracing::debug!(?bprsel, "boot partition read");
+        self.registers.bprsel = bprsel;
+        let Some(boot_partitions) = &self.boot_partitions else {
+            return;
+        };
+        let partition = &boot_partitions.partitions[bprsel.bpid() as usize];
+        let offset = bprsel.bprof() as usize * spec::BOOT_PARTITION_READ_UNIT as usize;
+        let len = bprsel.bprsz() as usize * spec::BOOT_PARTITION_READ_UNIT as usize;
+        self.registers.brs = match partition.get(offset..offset + len) {
+            Some(data) => match self.guest_memory.write_at(self.registers.bpmbl, data) {
+                Ok(()) => spec::BootReadStatus::COMPLETED,
+                Err(err) => {
+                    tracelimit::warn_ratelimited!(
+                        error = &err as &dyn std::error::Error,
+                        "failed to write boot partition data"
+                    );
+                    spec::BootReadStatus::ERROR
+                }
+            },
+            None => {
+                tracelimit::warn_ratelimited!(offset, len, "boot partition read out of range");
+                spec::BootReadStatus::ERROR
+            }
+        };
+    }
+
     fn get_csts(&mut self) -> u32 {
         if !self.registers.cc.en() && self.registers.csts.rdy() {
             // Keep trying to disable.
             if self.workers.poll_controller_reset() {
-                // AQA, ASQ, and ACQ are not reset by controller reset.
-                self.registers.csts = 0.into();
+                // AQA, ASQ, and ACQ are not reset by controller reset, and
+                // neither is the record of a previous subsystem reset.
+                self.registers.csts = spec::Csts::new().with_nssro(self.registers.csts.nssro());
                 self.registers.cc = 0.into();
                 self.registers.interrupt_mask = 0;
             }
@@ -414,17 +556,69 @@ fn get_csts(&mut self) -> u32 {
                 self.registers.csts.set_rdy(true);
             }
         }
+        self.poll_subsystem_reset();
 
         let csts = self.registers.csts;
         tracing::debug!(?csts, "get csts");
         csts.into()
     }
 
+    /// Starts an NVM subsystem reset.
+    ///
+    /// Unlike a controller reset, this resets all the controller's registers,
+    /// including the admin queue registers, and the guest can tell that it
+    /// occurred via CSTS.NSSRO. This emulator models a subsystem with a
+    /// single controller, so only this controller is affected.
+    fn subsystem_reset(&mut self) {
+        tracing::info!("nvm subsystem reset");
+        self.subsystem_reset_pending = true;
+        self.poll_subsystem_reset();
+    }
+
+    /// Advances a pending subsystem reset.
+    ///
+    /// An enabled controller is reset first, and an enable or controller reset
+    /// that is already in progress is allowed to finish. The subsystem reset
+    /// completes once the controller is idle.
+    fn poll_subsystem_reset(&mut self) {
+        if !self.subsystem_reset_pending {
+            return;
+        }
+        match (self.registers.cc.en(), self.registers.csts.rdy()) {
+            (true, true) => {
+                self.workers.controller_reset();
+                self.registers.cc.set_en(false);
+            }
+            (false, false) => {
+                self.subsystem_reset_pending = false;
+                self.registers = RegState::new();
+                self.registers.csts.set_nssro(true);
+                *self.qe_sizes.lock() = Default::default();
+            }
+            // Wait for the enable or controller reset to finish.
+            (true, false) | (false, true) => {}
+        }
+    }
+
     /// Sets the CFS bit in the controller status register (CSTS), indicating
     /// that the controller has experienced "undefined" behavior.
     pub fn fatal_error(&mut self) {
         self.registers.csts.set_cfs(true);
     }
+
+    /// Injects a PCIe error, as if the controller had detected it on the
+    /// link. A fatal error also fails the controller, so that the driver
+    /// has to reset it to recover.
+    ///
+    /// Returns the severity of the error message sent to the root port, or
+    /// `None` if the error is masked or its reporting is disabled.
+    pub fn inject_aer_error(&mut self, error: AerError) -> Option<AerSeverity> {
+        let report = self.cfg_space.inject_aer_error(error)?;
+        if report.severity == AerSeverity::Fatal {
+            self.fatal_error();
+        }
+        (!report.masked).then_some(report.severity)
+    }
 }
 
 impl ChangeDeviceState for NvmeController {
@@ -439,10 +633,15 @@ async fn reset(&mut self) {
             registers,
             qe_sizes,
             workers,
+            guest_memory: _,
+            boot_partitions: _,
+            subsystem_reset_pending,
+            interrupt_latency: _,
         } = self;
         workers.reset().await;
         cfg_space.reset();
         *registers = RegState::new();
+        *subsystem_reset_pending = false;
         *qe_sizes.lock() = Default::default();
     }
 }
@@ -3,15 +3,18 @@
 
 //! Resource resolver for the nvme controller.
 
+use crate::BootPartitions;
 use crate::NsidConflict;
 use crate::NvmeController;
 use crate::NvmeControllerCaps;
 use crate::NvmeControllerClient;
+use crate::io_trace::IoTrace;
 use anyhow::Context;
 use async_trait::async_trait;
 use disk_backend::resolve::ResolveDiskParameters;
 use futures::StreamExt;
 use nvme_resources::NamespaceDefinition;
+use nvme_resources::NvmeBootPartitions;
 use nvme_resources::NvmeControllerHandle;
 use nvme_resources::NvmeControllerRequest;
 use pal_async::task::Spawn;
@@ -45,6 +48,8 @@ pub enum Error {
     },
     #[error(transparent)]
     NsidConflict(NsidConflict),
+    #[error("invalid active boot partition {0}")]
+    InvalidActiveBootPartition(u8),
 }
 
 #[async_trait]
@@ -58,6 +63,15 @@ async fn resolve(
         resource: NvmeControllerHandle,
         input: ResolvePciDeviceHandleParams<'_>,
     ) -> Result<Self::Output, Self::Error> {
+        let boot_partitions = resource
+            .boot_partitions
+            .map(|NvmeBootPartitions { partitions, active }| {
+                if active >= 2 {
+                    return Err(Error::InvalidActiveBootPartition(active));
+                }
+                Ok(BootPartitions::new(partitions).with_active(active))
+            })
+            .transpose()?;
         let controller = NvmeController::new(
             input.driver_source,
             input.guest_memory.clone(),
@@ -67,6 +81,7 @@ async fn resolve(
                 msix_count: resource.msix_count,
                 max_io_queues: resource.max_io_queues,
                 subsystem_id: resource.subsystem_id,
+                boot_partitions,
             },
         );
         for NamespaceDefinition {
@@ -157,6 +172,26 @@ async fn handle_requests(
                 })
                 .await
             }
+            NvmeControllerRequest::SetTelemetry(rpc) => {
+                rpc.handle_failable(async |(nsid, telemetry)| {
+                    if !client.set_namespace_telemetry(nsid, telemetry).await {
+                        anyhow::bail!("namespace {nsid} not found");
+                    }
+                    anyhow::Ok(())
+                })
+                .await
+            }
+            NvmeControllerRequest::SetIoTrace(rpc) => {
+                rpc.handle_failable(async |file| {
+                    let trace = file
+                        .map(IoTrace::new)
+                        .transpose()
+                        .context("failed to start io trace")?;
+                    client.set_io_trace(trace).await;
+                    anyhow::Ok(())
+                })
+                .await
+            }
         }
     }
 }
@@ -3,6 +3,7 @@
 
 use super::test_helpers::TestNvmeMmioRegistration;
 use crate::BAR0_LEN;
+use crate::BootPartitions;
 use crate::NvmeController;
 use crate::NvmeControllerCaps;
 use crate::PAGE_SIZE64;
@@ -20,7 +21,11 @@
 use pal_async::DefaultDriver;
 use pal_async::async_test;
 use pci_core::bus_range::AssignedBusRange;
+use pci_core::capabilities::extended::aer::AerError;
+use pci_core::capabilities::extended::aer::AerSeverity;
 use pci_core::msi::MsiConnection;
+use pci_core::spec::caps::aer::CorrectableErrors;
+use pci_core::spec::caps::aer::UncorrectableErrors;
 use pci_core::test_helpers::TestPciInterruptController;
 use user_driver::backoff::Backoff;
 use vmcore::vm_task::SingleDriverBackend;
@@ -45,6 +50,7 @@ fn instantiate_controller(
             msix_count: 64,
             max_io_queues: 64,
             subsystem_id: Guid::new_random(),
+            boot_partitions: None,
         },
     );
 
@@ -207,7 +213,7 @@ async fn test_basic_registers(driver: DefaultDriver) {
     assert_eq!(dword, 0xFF0100FF);
     let mut qword = 0u64;
     nvmec.read_bar0(0, qword.as_mut_bytes()).unwrap();
-    assert_eq!(qword, 0x20FF0100FF);
+    assert_eq!(qword, 0x30FF0100FF);
     nvmec.read_bar0(8, dword.as_mut_bytes()).unwrap();
     assert_eq!(dword, 0x20000);
 
@@ -220,6 +226,67 @@ async fn test_basic_registers(driver: DefaultDriver) {
     assert_eq!(qword, 0x1000);
 }
 
+#[async_test]
+async fn test_boot_partition_read(driver: DefaultDriver) {
+    let gm = test_memory();
+    let vm_task_driver = &VmTaskDriverSource::new(SingleDriverBackend::new(driver));
+    let msi_conn = MsiConnection::new(AssignedBusRange::new(), 0);
+    let partition1 = (0..0x3000).map(|i| (i / 0x1000) as u8 + 1).collect();
+    let mut nvmec = NvmeController::new(
+        vm_task_driver,
+        gm.clone(),
+        msi_conn.target(),
+        &mut TestNvmeMmioRegistration {},
+        NvmeControllerCaps {
+            msix_count: 64,
+            max_io_queues: 64,
+            subsystem_id: Guid::new_random(),
+            boot_partitions: Some(BootPartitions::new([Vec::new(), partition1]).with_active(1)),
+        },
+    );
+
+    let mut qword = 0u64;
+    nvmec.read_bar0(0, qword.as_mut_bytes()).unwrap();
+    assert!(spec::Cap::from(qword).bps());
+
+    let mut dword = 0u32;
+    nvmec.read_bar0(0x40, dword.as_mut_bytes()).unwrap();
+    let bpinfo = spec::Bpinfo::from(dword);
+    assert_eq!(bpinfo.bpsz(), 1);
+    assert!(bpinfo.abpid());
+    assert_eq!(bpinfo.brs(), spec::BootReadStatus::NO_READ.0);
+
+    // Read the second and third pages of partition 1 into guest memory.
+    nvmec.write_bar0(0x48, 0x4000u64.as_bytes()).unwrap();
+    let bprsel = spec::Bprsel::new()
+        .with_bpid(true)
+        .with_bprof(1)
+        .with_bprsz(2);
+    nvmec
+        .write_bar0(0x44, u32::from(bprsel).as_bytes())
+        .unwrap();
+    nvmec.read_bar0(0x40, dword.as_mut_bytes()).unwrap();
+    assert_eq!(
+        spec::Bpinfo::from(dword).brs(),
+        spec::BootReadStatus::COMPLETED.0
+    );
+    let mut data = [0; 0x2000];
+    gm.read_at(0x4000, &mut data).unwrap();
+    assert!(data[..0x1000].iter().all(|&b| b == 2));
+    assert!(data[0x1000..].iter().all(|&b| b == 3));
+
+    // Reads past the end of the partition fail.
+    let bprsel = bprsel.with_bprof(0x20);
+    nvmec
+        .write_bar0(0x44, u32::from(bprsel).as_bytes())
+        .unwrap();
+    nvmec.read_bar0(0x40, dword.as_mut_bytes()).unwrap();
+    assert_eq!(
+        spec::Bpinfo::from(dword).brs(),
+        spec::BootReadStatus::ERROR.0
+    );
+}
+
 #[async_test]
 async fn test_invalid_configuration(driver: DefaultDriver) {
     let gm = test_memory();
@@ -234,6 +301,55 @@ async fn test_invalid_configuration(driver: DefaultDriver) {
     assert!(dword & 2 != 0);
 }
 
+#[async_test]
+async fn test_inject_aer_error(driver: DefaultDriver) {
+    let gm = test_memory();
+    let mut nvmec = instantiate_controller(driver, &gm, None);
+
+    // Find the PCI Express cap struct.
+    let mut cap_ptr = 0;
+    nvmec.pci_cfg_read(0x34, &mut cap_ptr).unwrap();
+    cap_ptr &= 0xff;
+    loop {
+        assert_ne!(cap_ptr, 0, "no PCI Express capability");
+        let mut cap_header = 0;
+        nvmec.pci_cfg_read(cap_ptr as u16, &mut cap_header).unwrap();
+        if cap_header & 0xff == 0x10 {
+            break;
+        }
+        cap_ptr = (cap_header >> 8) & 0xff;
+    }
+
+    // Reporting is disabled by default, so nothing is sent upstream, but the
+    // error is still logged in the AER capability.
+    let correctable = AerError::Correctable(CorrectableErrors::new().with_bad_tlp(true));
+    assert_eq!(nvmec.inject_aer_error(correctable), None);
+    let mut dword = 0u32;
+    nvmec.pci_cfg_read(0x110, &mut dword).unwrap();
+    assert_eq!(
+        dword,
+        CorrectableErrors::new().with_bad_tlp(true).into_bits()
+    );
+
+    // Enable reporting of all error severities in Device Control.
+    nvmec.pci_cfg_write(cap_ptr as u16 + 8, 0x7).unwrap();
+    assert_eq!(
+        nvmec.inject_aer_error(correctable),
+        Some(AerSeverity::Correctable)
+    );
+
+    // A fatal error also fails the controller.
+    nvmec.read_bar0(0x1c, dword.as_mut_bytes()).unwrap();
+    assert!(dword & 2 == 0);
+    let fatal = AerError::Uncorrectable {
+        errors: UncorrectableErrors::new().with_malformed_tlp(true),
+        header_log: [0; 4],
+    };
+    assert_eq!(nvmec.inject_aer_error(fatal), Some(AerSeverity::Fatal));
+    nvmec.read_bar0(0x1c, dword.as_mut_bytes()).unwrap();
+    assert!(dword & 2 != 0);
+}
+
 #[async_test]
 async fn test_enable_controller(driver: DefaultDriver) {
     let gm = test_memory();
@@ -288,6 +404,65 @@ async fn test_multi_page_admin_queues(driver: DefaultDriver) {
     assert!(dword & 2 == 0);
 }
 
+#[async_test]
+async fn test_subsystem_reset(driver: DefaultDriver) {
+    let dm1 = PrpRange::new(vec![0], 0, PAGE_SIZE64).unwrap();
+    let dm2 = PrpRange::new(vec![0x1000], 0, PAGE_SIZE64).unwrap();
+    let gm = test_memory();
+    let mut nvmec =
+        instantiate_and_build_admin_queue(&dm1, 64, &dm2, 64, false, None, driver.clone(), &gm)
+            .await;
+
+    let mut qword = 0u64;
+    nvmec.read_bar0(0, qword.as_mut_bytes()).unwrap();
+    assert!(spec::Cap::from(qword).nssrs());
+
+    // Writes of anything other than "NVMe" are ignored.
+    let mut dword = 0u32;
+    nvmec.write_bar0(0x20, 1u32.as_bytes()).unwrap();
+    nvmec.read_bar0(0x1c, dword.as_mut_bytes()).unwrap();
+    let csts = spec::Csts::from(dword);
+    assert!(csts.rdy());
+    assert!(!csts.nssro());
+
+    nvmec
+        .write_bar0(0x20, spec::NSSR_RESET_VALUE.as_bytes())
+        .unwrap();
+
+    // Wait for the controller to become idle.
+    let mut backoff = Backoff::new(&driver);
+    let mut csts = spec::Csts::new();
+    for _ in 0..5 {
+        nvmec.read_bar0(0x1c, dword.as_mut_bytes()).unwrap();
+        csts = dword.into();
+        if !csts.rdy() {
+            break;
+        }
+        backoff.back_off().await;
+    }
+    assert!(!csts.rdy());
+    assert!(csts.nssro());
+
+    // Unlike a controller reset, a subsystem reset clears the admin queue
+    // registers.
+    nvmec.read_bar0(0x14, dword.as_mut_bytes()).unwrap();
+    assert_eq!(dword, 0);
+    nvmec.read_bar0(0x24, dword.as_mut_bytes()).unwrap();
+    assert_eq!(dword, 0);
+    nvmec.read_bar0(0x28, qword.as_mut_bytes()).unwrap();
+    assert_eq!(qword, 0);
+
+    // NSSRO is cleared by writing 1 to it.
+    nvmec
+        .write_bar0(
+            0x1c,
+            u32::from(spec::Csts::new().with_nssro(true)).as_bytes(),
+        )
+        .unwrap();
+    nvmec.read_bar0(0x1c, dword.as_mut_bytes()).unwrap();
+    assert!(!spec::Csts::from(dword).nssro());
+}
+
 #[async_test]
 async fn test_send_identify(driver: DefaultDriver) {
     let dm1 = PrpRange::new(vec![0], 0, PAGE_SIZE64).unwrap();
@@ -330,6 +505,133 @@ async fn test_send_identify(driver: DefaultDriver) {
     assert_eq!(cqe.status.status(), spec::Status::SUCCESS.0);
 }
 
+#[async_test]
+async fn test_interrupt_latency(driver: DefaultDriver) {
+    let dm1 = PrpRange::new(vec![0], 0, PAGE_SIZE64).unwrap();
+    let dm2 = PrpRange::new(vec![0x1000], 0, PAGE_SIZE64).unwrap();
+    let gm = test_memory();
+    let int_controller = TestPciInterruptController::new();
+
+    let mut nvmec = instantiate_and_build_admin_queue(
+        &dm1,
+        64,
+        &dm2,
+        64,
+        true,
+        Some(&int_controller),
+        driver.clone(),
+        &gm,
+    )
+    .await;
+
+    inspect::update("interrupt_latency/enabled", "true", &mut nvmec)
+        .await
+        .unwrap();
+
+    let mut entry = spec::Command::new_zeroed();
+    entry.cdw0.set_opcode(spec::AdminOpcode::IDENTIFY.0);
+    let cdw10 = spec::Cdw10Identify::new().with_cns(spec::Cns::CONTROLLER.0);
+    entry.cdw10 = u32::from(cdw10);
+    entry.dptr[0] = 1;
+    write_command_to_queue(&gm, &dm2, 0, &entry);
+    nvmec.write_bar0(0x1000, 1u32.as_bytes()).unwrap();
+
+    wait_for_msi(driver.clone(), &int_controller, 1000, 0xfeed0000, 0x1111).await;
+
+    // The completion interrupt was measured from signal to delivery.
+    let mut inspection =
+        inspect::InspectionBuilder::new("interrupt_latency/delivered/count").inspect(&mut nvmec);
+    inspection.resolve().await;
+    match inspection.results() {
+        inspect::Node::Value(v) => {
+            assert!(matches!(v.kind, inspect::ValueKind::Unsigned(1)), "{v:?}")
+        }
+        other => panic!("unexpected inspect node: {other:?}"),
+    }
+}
+
+#[async_test]
+async fn test_get_log_page_offset(driver: DefaultDriver) {
+    let admin_cq_buf = PrpRange::new(vec![0], 0, PAGE_SIZE64).unwrap();
+    let admin_sq_buf = PrpRange::new(vec![0x1000], 0, PAGE_SIZE64).unwrap();
+    let gm = test_memory();
+    let int_controller = TestPciInterruptController::new();
+
+    let mut nvmec = instantiate_and_build_admin_queue(
+        &admin_cq_buf,
+        64,
+        &admin_sq_buf,
+        64,
+        true,
+        Some(&int_controller),
+        driver.clone(),
+        &gm,
+    )
+    .await;
+
+    // Attach namespaces so that the changed namespace list is not empty.
+    for nsid in 1..=3 {
+        let disk = ram_disk(1 << 20, /* read_only = */ false).unwrap();
+        nvmec.client().add_namespace(nsid, disk).await.unwrap();
+    }
+
+    // Read 8 bytes of a log page at various offsets. On success, check the
+    // data that was written and that nothing past the end of the log page
+    // was.
+    let firmware = spec::LogPageIdentifier::FIRMWARE_SLOT_INFORMATION;
+    let changed = spec::LogPageIdentifier::CHANGED_NAMESPACE_LIST;
+    let cases: [(_, u64, _, &[u32]); 7] = [
+        (changed, 4, spec::Status::SUCCESS, &[2, 3]),
+        (firmware, 256, spec::Status::SUCCESS, &[0, 0]),
+        // The final dword of the log page.
+        (firmware, 508, spec::Status::SUCCESS, &[0]),
+        // Not dword aligned.
+        (firmware, 2, spec::Status::INVALID_FIELD_IN_COMMAND, &[]),
+        // At or past the end of the log page.
+        (firmware, 512, spec::Status::INVALID_FIELD_IN_COMMAND, &[]),
+        (changed, 4096, spec::Status::INVALID_FIELD_IN_COMMAND, &[]),
+        (
+            firmware,
+            1 << 32,
+            spec::Status::INVALID_FIELD_IN_COMMAND,
+            &[],
+        ),
+    ];
+
+    for (slot, (lid, offset, status, expected)) in cases.into_iter().enumerate() {
+        gm.write_at(0x2000, [!0u32; 2].as_bytes()).unwrap();
+
+        let mut command = spec::Command::new_zeroed();
+        command.cdw0.set_opcode(spec::AdminOpcode::GET_LOG_PAGE.0);
+        command.cdw0.set_cid(slot as u16);
+        command.cdw10 = spec::Cdw10GetLogPage::new()
+            .with_lid(lid.0)
+            .with_numdl_z(8 / 4 - 1)
+            .into();
+        command.cdw12 = offset as u32;
+        command.cdw13 = (offset >> 32) as u32;
+        command.dptr[0] = 0x2000;
+
+        write_command_to_queue(&gm, &admin_sq_buf, slot, &command);
+        nvmec
+            .write_bar0(0x1000, (slot as u32 + 1).as_bytes())
+            .unwrap();
+        wait_for_msi(driver.clone(), &int_controller, 1000, 0xfeed0000, 0x1111).await;
+
+        let cqe = read_completion_from_queue(&gm, &admin_cq_buf, slot);
+        assert_eq!(cqe.cid, slot as u16);
+        assert_eq!(cqe.status.status(), status.0, "offset {offset:#x}");
+
+        let mut data = [0u32; 2];
+        gm.read_at(0x2000, data.as_mut_bytes()).unwrap();
+        assert_eq!(&data[..expected.len()], expected, "offset {offset:#x}");
+        assert!(
+            data[expected.len()..].iter().all(|&x| x == !0),
+            "offset {offset:#x}"
+        );
+    }
+}
+
 // =============================================================================
 // Regression tests for the I/O worker `io_count` accounting.
 //
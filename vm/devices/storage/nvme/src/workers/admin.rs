@@ -15,6 +15,8 @@
 use crate::VENDOR_ID;
 use crate::error::CommandResult;
 use crate::error::NvmeError;
+use crate::io_trace::IoTrace;
+use crate::io_trace::SharedIoTrace;
 use crate::namespace::Namespace;
 use crate::prp::PrpRange;
 use crate::queue::CompletionQueue;
@@ -23,6 +25,7 @@
 use crate::queue::SubmissionQueue;
 use crate::spec;
 use disk_backend::Disk;
+use disk_backend_resources::DiskTelemetry;
 use futures::FutureExt;
 use futures::SinkExt;
 use futures::StreamExt;
@@ -58,6 +61,9 @@
 const IOCQES: u8 = 4;
 const MAX_ASYNC_EVENT_REQUESTS: u8 = 4; // minimum recommended by spec
 const ERROR_LOG_PAGE_ENTRIES: u8 = 1;
+const CHANGED_NAMESPACE_LIST_LEN: usize = 4096; // 1024 namespace IDs
+/// The ID of the single controller in the emulated subsystem.
+const CONTROLLER_ID: u16 = 0;
 
 #[derive(Inspect)]
 pub struct AdminConfig {
@@ -74,6 +80,8 @@ pub struct AdminConfig {
     pub max_sqs: u16,
     pub max_cqs: u16,
     pub qe_sizes: Arc<Mutex<IoQueueEntrySizes>>,
+    #[inspect(skip)]
+    pub io_trace: SharedIoTrace,
 }
 
 #[derive(Inspect)]
@@ -82,6 +90,10 @@ pub struct AdminHandler {
     config: AdminConfig,
     #[inspect(iter_by_key)]
     namespaces: BTreeMap<u32, Arc<Namespace>>,
+    /// Namespaces the guest has detached from the controller. These remain
+    /// allocated in the subsystem but are not active.
+    #[inspect(iter_by_key)]
+    detached_namespaces: BTreeMap<u32, Arc<Namespace>>,
 }
 
 #[derive(Inspect)]
@@ -211,6 +223,7 @@ fn set_max_queues(&mut self, handler: &AdminHandler, num_sqs: u16, num_cqs: u16)
                 task: TaskControl::new(IoHandler::new(
                     handler.config.mem.clone(),
                     self.sq_delete_response.sender(),
+                    handler.config.io_trace.clone(),
                 )),
             }
         });
@@ -338,6 +351,7 @@ pub fn new(driver: VmTaskDriver, config: AdminConfig) -> Self {
             driver,
             config,
             namespaces: Default::default(),
+            detached_namespaces: Default::default(),
         }
     }
 
@@ -347,6 +361,9 @@ pub async fn add_namespace(
         nsid: u32,
         disk: Disk,
     ) -> Result<(), NsidConflict> {
+        if self.detached_namespaces.contains_key(&nsid) {
+            return Err(NsidConflict(nsid));
+        }
         let namespace = &*match self.namespaces.entry(nsid) {
             btree_map::Entry::Vacant(entry) => entry.insert(Arc::new(Namespace::new(
                 self.config.mem.clone(),
@@ -364,6 +381,10 @@ pub async fn add_namespace(
     }
 
     pub async fn remove_namespace(&mut self, state: Option<&mut AdminState>, nsid: u32) -> bool {
+        // A detached namespace is not active, so there is nothing to update.
+        if self.detached_namespaces.remove(&nsid).is_some() {
+            return true;
+        }
         if self.namespaces.remove(&nsid).is_none() {
             return false;
         }
@@ -375,6 +396,25 @@ pub async fn remove_namespace(&mut self, state: Option<&mut AdminState>, nsid: u
         true
     }
 
+    /// Changes the telemetry settings of a namespace, returning false if it
+    /// does not exist.
+    pub fn set_namespace_telemetry(&self, nsid: u32, telemetry: DiskTelemetry) -> bool {
+        let Some(namespace) = self
+            .namespaces
+            .get(&nsid)
+            .or_else(|| self.detached_namespaces.get(&nsid))
+        else {
+            return false;
+        };
+        namespace.set_telemetry(telemetry);
+        true
+    }
+
+    /// Replaces the IO trace that the IO workers write to.
+    pub fn set_io_trace(&self, trace: Option<IoTrace>) {
+        self.config.io_trace.set(trace);
+    }
+
     async fn next_event(&mut self, state: &mut AdminState) -> Result<Event, QueueError> {
         let event = loop {
             // Wait for there to be room for a completion for the next
@@ -468,6 +508,10 @@ async fn process_event(
                     spec::AdminOpcode::GET_LOG_PAGE => self
                         .handle_get_log_page(state, &command)
                         .map(|()| Some(Default::default())),
+                    spec::AdminOpcode::NAMESPACE_ATTACHMENT => self
+                        .handle_namespace_attachment(state, &command)
+                        .await
+                        .map(|()| Some(Default::default())),
                     spec::AdminOpcode::DOORBELL_BUFFER_CONFIG
                         if self.supports_shadow_doorbells(state) =>
                     {
@@ -572,6 +616,42 @@ fn handle_identify(
                     tracelimit::warn_ratelimited!(nsid = command.nsid, "unknown namespace id");
                 }
             }
+            spec::Cns::ALLOCATED_NAMESPACE_LIST => {
+                if command.nsid >= 0xfffffffe {
+                    return Err(spec::Status::INVALID_NAMESPACE_OR_FORMAT.into());
+                }
+                let nsids = <[u32]>::mut_from_bytes(buf).unwrap();
+                for (ns, nsid) in self
+                    .allocated_nsids()
+                    .filter(|&ns| ns > command.nsid)
+                    .zip(nsids)
+                {
+                    *nsid = ns;
+                }
+            }
+            spec::Cns::ALLOCATED_NAMESPACE => {
+                if let Some(ns) = self
+                    .namespaces
+                    .get(&command.nsid)
+                    .or_else(|| self.detached_namespaces.get(&command.nsid))
+                {
+                    ns.identify(buf);
+                } else {
+                    tracelimit::warn_ratelimited!(nsid = command.nsid, "unknown namespace id");
+                }
+            }
+            spec::Cns::CONTROLLER_LIST_OF_NSID | spec::Cns::CONTROLLER_LIST_OF_NVM_SUBSYSTEM => {
+                let list = spec::ControllerList::mut_from_bytes(buf).unwrap();
+                let attached = spec::Cns(cdw10.cns())
+                    == spec::Cns::CONTROLLER_LIST_OF_NVM_SUBSYSTEM
+                    || self.namespaces.contains_key(&command.nsid);
+                // The list starts at `cntid`, so it is empty unless the
+                // request starts at the only controller.
+                if attached && cdw10.cntid() == CONTROLLER_ID {
+                    list.count = 1;
+                    list.ids[0] = CONTROLLER_ID;
+                }
+            }
             cns => {
                 tracelimit::warn_ratelimited!(?cns, "unsupported cns");
                 return Err(spec::Status::INVALID_FIELD_IN_COMMAND.into());
@@ -596,13 +676,15 @@ fn identify_controller(&self, state: &AdminState) -> spec::IdentifyController {
                 .with_min(IOCQES)
                 .with_max(IOCQES),
             frmw: spec::FirmwareUpdates::new().with_ffsro(true).with_nofs(1),
-            nn: self.namespaces.keys().copied().max().unwrap_or(0),
+            cntlid: CONTROLLER_ID,
+            nn: self.allocated_nsids().max().unwrap_or(0),
             ieee: [0x74, 0xe2, 0x8c], // Microsoft
             fr: (*b"v1.00000").into(),
             mn: (*b"MSFT NVMe Accelerator v1.0              ").into(),
             sn: (*b"SN: 000001          ").into(),
             aerl: MAX_ASYNC_EVENT_REQUESTS - 1,
             elpe: ERROR_LOG_PAGE_ENTRIES - 1,
+            lpa: spec::LogPageAttributes::new().with_extended_data(true),
             oaes: spec::Oaes::new().with_namespace_attribute(true),
             oncs: spec::Oncs::new()
                 .with_dataset_management(true)
@@ -925,52 +1007,130 @@ fn handle_get_log_page(
         let numd =
             ((cdw10.numdl_z() as u32) | ((cdw11.numdu() as u32) << 16)).saturating_add(1) as usize;
         let len = numd * 4;
+        // The log page offset is in bytes and must be dword aligned.
+        let offset = (command.cdw12 as u64) | ((command.cdw13 as u64) << 32);
+        if !offset.is_multiple_of(4) {
+            return Err(spec::Status::INVALID_FIELD_IN_COMMAND.into());
+        }
         let prp = PrpRange::parse(&self.config.mem, len, command.dptr)?;
 
-        match spec::LogPageIdentifier(cdw10.lid()) {
+        // Build the full log page, then return the requested window of it.
+        let log = match spec::LogPageIdentifier(cdw10.lid()) {
             spec::LogPageIdentifier::ERROR_INFORMATION => {
-                // Write empty log entries.
-                prp.zero(
-                    &self.config.mem,
-                    len.min(ERROR_LOG_PAGE_ENTRIES as usize * 64),
-                )?;
+                // Empty log entries.
+                vec![0; ERROR_LOG_PAGE_ENTRIES as usize * 64]
             }
             spec::LogPageIdentifier::HEALTH_INFORMATION => {
                 if command.nsid != !0 {
                     return Err(spec::Status::INVALID_FIELD_IN_COMMAND.into());
                 }
-                // Write an empty page.
-                prp.zero(&self.config.mem, len.min(512))?;
+                // An empty page.
+                vec![0; 512]
             }
             spec::LogPageIdentifier::FIRMWARE_SLOT_INFORMATION => {
-                // Write an empty page.
-                prp.zero(&self.config.mem, len.min(512))?;
+                // An empty page.
+                vec![0; 512]
             }
             spec::LogPageIdentifier::CHANGED_NAMESPACE_LIST => {
-                // Zero the whole list.
-                prp.zero(&self.config.mem, len.min(4096))?;
-                // Now write in the changed namespaces.
-                if state.changed_namespaces.len() > 1024 {
+                let mut log = vec![0; CHANGED_NAMESPACE_LIST_LEN];
+                if state.changed_namespaces.len() > CHANGED_NAMESPACE_LIST_LEN / 4 {
                     // Too many to fit, write !0 so the driver scans everything.
-                    prp.write(&self.config.mem, (!0u32).as_bytes())?;
+                    log[..4].copy_from_slice((!0u32).as_bytes());
                 } else {
-                    let count = state.changed_namespaces.len().min(numd);
-                    prp.write(
-                        &self.config.mem,
-                        state.changed_namespaces[..count].as_bytes(),
-                    )?;
-                }
-                state.changed_namespaces.clear();
-                if !cdw10.rae() {
-                    state.notified_changed_namespaces = false;
+                    let changed = state.changed_namespaces.as_bytes();
+                    log[..changed.len()].copy_from_slice(changed);
                 }
+                log
             }
             lid => {
                 tracelimit::warn_ratelimited!(?lid, "unsupported log page");
                 return Err(spec::Status::INVALID_LOG_PAGE.into());
             }
+        };
+
+        // The offset must be within the log page, so a read of at least one
+        // dword always returns data.
+        let Some(data) = usize::try_from(offset)
+            .ok()
+            .filter(|&offset| offset < log.len())
+            .map(|offset| &log[offset..])
+        else {
+            return Err(spec::Status::INVALID_FIELD_IN_COMMAND.into());
+        };
+        let count = len.min(data.len());
+        prp.write(&self.config.mem, &data[..count])?;
+
+        // Only consume the changed namespace list once the guest has read
+        // through to the end of it, so that a log retrieved in multiple pieces
+        // is consistent.
+        if spec::LogPageIdentifier(cdw10.lid()) == spec::LogPageIdentifier::CHANGED_NAMESPACE_LIST
+            && data.len() <= len
+        {
+            state.changed_namespaces.clear();
+            if !cdw10.rae() {
+                state.notified_changed_namespaces = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the IDs of all namespaces in the subsystem, attached or not, in
+    /// ascending order.
+    fn allocated_nsids(&self) -> impl Iterator<Item = u32> + '_ {
+        let mut attached = self.namespaces.keys().copied().peekable();
+        let mut detached = self.detached_namespaces.keys().copied().peekable();
+        std::iter::from_fn(move || match (attached.peek(), detached.peek()) {
+            (Some(a), Some(d)) if d < a => detached.next(),
+            (Some(_), _) => attached.next(),
+            (None, _) => detached.next(),
+        })
+    }
+
+    /// Handles Namespace Attachment. Namespaces are provisioned by the host,
+    /// so Namespace Management is not supported and is not advertised in
+    /// OACS, but the guest can still detach and reattach them.
+    async fn handle_namespace_attachment(
+        &mut self,
+        state: &mut AdminState,
+        command: &spec::Command,
+    ) -> Result<(), NvmeError> {
+        let cdw10 = spec::Cdw10NamespaceAttachment::from(command.cdw10);
+        let nsid = command.nsid;
+        if !self.namespaces.contains_key(&nsid) && !self.detached_namespaces.contains_key(&nsid) {
+            return Err(spec::Status::INVALID_NAMESPACE_OR_FORMAT.into());
         }
 
+        let mut list = spec::ControllerList::new_zeroed();
+        PrpRange::parse(&self.config.mem, size_of_val(&list), command.dptr)?
+            .read(&self.config.mem, list.as_mut_bytes())?;
+        let ids = list
+            .ids
+            .get(..list.count as usize)
+            .ok_or(spec::Status::CONTROLLER_LIST_INVALID)?;
+        if ids != [CONTROLLER_ID] {
+            return Err(spec::Status::CONTROLLER_LIST_INVALID.into());
+        }
+
+        match spec::NamespaceAttachmentSelect(cdw10.sel()) {
+            spec::NamespaceAttachmentSelect::ATTACH => {
+                let namespace = self
+                    .detached_namespaces
+                    .remove(&nsid)
+                    .ok_or(spec::Status::NAMESPACE_ALREADY_ATTACHED)?;
+                state.add_namespace(&self.driver, nsid, &namespace).await;
+                self.namespaces.insert(nsid, namespace);
+            }
+            spec::NamespaceAttachmentSelect::DETACH => {
+                let namespace = self
+                    .namespaces
+                    .remove(&nsid)
+                    .ok_or(spec::Status::NAMESPACE_NOT_ATTACHED)?;
+                state.remove_namespace(nsid).await;
+                self.detached_namespaces.insert(nsid, namespace);
+            }
+            _ => return Err(spec::Status::INVALID_FIELD_IN_COMMAND.into()),
+        }
         Ok(())
     }
 
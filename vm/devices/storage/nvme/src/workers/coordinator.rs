@@ -8,9 +8,12 @@
 use super::admin::AdminHandler;
 use super::admin::AdminState;
 use super::admin::NsidConflict;
+use crate::io_trace::IoTrace;
+use crate::io_trace::SharedIoTrace;
 use crate::queue::DoorbellMemory;
 use crate::queue::InvalidDoorbell;
 use disk_backend::Disk;
+use disk_backend_resources::DiskTelemetry;
 use futures::FutureExt;
 use futures::StreamExt;
 use futures_concurrency::future::Race;
@@ -76,6 +79,7 @@ pub fn new(
                 max_sqs,
                 max_cqs,
                 qe_sizes,
+                io_trace: SharedIoTrace::default(),
             },
         );
         let coordinator = Coordinator {
@@ -199,6 +203,24 @@ pub async fn remove_namespace(&self, nsid: u32) -> bool {
             .await
             .unwrap()
     }
+
+    /// Changes the telemetry settings of a namespace. Returns false if the
+    /// namespace does not exist.
+    pub async fn set_namespace_telemetry(&self, nsid: u32, telemetry: DiskTelemetry) -> bool {
+        self.send
+            .call(CoordinatorRequest::SetNamespaceTelemetry, (nsid, telemetry))
+            .await
+            .unwrap()
+    }
+
+    /// Starts writing a per-command latency record to `trace` for each
+    /// completed IO, or stops if `trace` is `None`.
+    pub async fn set_io_trace(&self, trace: Option<IoTrace>) {
+        self.send
+            .call(CoordinatorRequest::SetIoTrace, trace)
+            .await
+            .unwrap()
+    }
 }
 
 #[derive(Inspect)]
@@ -214,6 +236,8 @@ enum CoordinatorRequest {
     EnableAdmin(Rpc<EnableAdminParams, ()>),
     AddNamespace(Rpc<(u32, Disk), Result<(), NsidConflict>>),
     RemoveNamespace(Rpc<u32, bool>),
+    SetNamespaceTelemetry(Rpc<(u32, DiskTelemetry), bool>),
+    SetIoTrace(Rpc<Option<IoTrace>, ()>),
     Inspect(inspect::Deferred),
     ControllerReset(Rpc<(), ()>),
 }
@@ -295,6 +319,21 @@ enum Event {
                         })
                         .await
                     }
+                    CoordinatorRequest::SetNamespaceTelemetry(rpc) => {
+                        rpc.handle(async |(nsid, telemetry)| {
+                            let running = self.admin.stop().await;
+                            let (admin, _) = self.admin.get_mut();
+                            let r = admin.set_namespace_telemetry(nsid, telemetry);
+                            if running {
+                                self.admin.start();
+                            }
+                            r
+                        })
+                        .await
+                    }
+                    CoordinatorRequest::SetIoTrace(rpc) => {
+                        rpc.handle_sync(|trace| self.admin.task().set_io_trace(trace))
+                    }
                     CoordinatorRequest::ControllerReset(rpc) => {
                         assert!(self.reset.is_none());
                         self.reset = Some(rpc);
@@ -13,6 +13,9 @@
 //! increase throughput.
 
 use crate::error::CommandResult;
+use crate::io_trace::IoTrace;
+use crate::io_trace::IoTraceRecord;
+use crate::io_trace::SharedIoTrace;
 use crate::namespace::Namespace;
 use crate::queue::CompletionQueue;
 use crate::queue::DoorbellMemory;
@@ -33,6 +36,7 @@
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::Poll;
+use std::time::Instant;
 use task_control::AsyncRun;
 use task_control::Cancelled;
 use task_control::InspectTask;
@@ -46,6 +50,8 @@ pub struct IoHandler {
     mem: GuestMemory,
     #[inspect(skip)]
     admin_response: mesh::Sender<u16>,
+    #[inspect(skip)]
+    io_trace: SharedIoTrace,
 }
 
 #[derive(Inspect)]
@@ -143,6 +149,59 @@ struct IoResult {
     sq_idx: usize,
     cid: u16,
     result: CommandResult,
+    trace: Option<CommandTrace>,
+}
+
+/// A command's IO trace record, filled in as the command progresses and
+/// written when its completion is posted.
+struct CommandTrace {
+    trace: IoTrace,
+    record: IoTraceRecord,
+}
+
+impl CommandTrace {
+    fn new(trace: IoTrace, sqid: u16, command: &spec::Command) -> Self {
+        let opcode = nvm::NvmOpcode(command.cdw0.opcode());
+        let (slba, nlb) = if matches!(opcode, nvm::NvmOpcode::READ | nvm::NvmOpcode::WRITE) {
+            let cdw12 = nvm::Cdw12ReadWrite::from(command.cdw12);
+            (
+                command.cdw10 as u64 | ((command.cdw11 as u64) << 32),
+                cdw12.nlb_z() as u32 + 1,
+            )
+        } else {
+            (0, 0)
+        };
+        let now = trace.timestamp(Instant::now());
+        Self {
+            trace,
+            record: IoTraceRecord {
+                submit_ns: now,
+                start_ns: now,
+                complete_ns: now,
+                post_ns: now,
+                slba,
+                nsid: command.nsid,
+                nlb,
+                sqid,
+                cid: command.cdw0.cid(),
+                status: 0,
+                opcode: opcode.0,
+                reserved: 0,
+            },
+        }
+    }
+
+    fn now(&self) -> u64 {
+        self.trace.timestamp(Instant::now())
+    }
+
+    fn finish(&self, status: spec::Status) {
+        self.trace.write(&IoTraceRecord {
+            post_ns: self.now(),
+            status: status.0,
+            ..self.record
+        });
+    }
 }
 
 impl AsyncRun<IoState> for IoHandler {
@@ -171,10 +230,15 @@ enum HandlerError {
 }
 
 impl IoHandler {
-    pub fn new(mem: GuestMemory, admin_response: mesh::Sender<u16>) -> Self {
+    pub fn new(
+        mem: GuestMemory,
+        admin_response: mesh::Sender<u16>,
+        io_trace: SharedIoTrace,
+    ) -> Self {
         Self {
             mem,
             admin_response,
+            io_trace,
         }
     }
 
@@ -223,10 +287,17 @@ enum Event {
                     let command = r?;
                     let cid = command.cdw0.cid();
                     state.sqs[sq_idx].io_count += 1;
+                    let mut trace = self
+                        .io_trace
+                        .get()
+                        .map(|trace| CommandTrace::new(trace, state.sqs[sq_idx].sqid, &command));
 
                     if let Some(ns) = state.namespaces.get(&command.nsid) {
                         let ns = ns.clone();
                         let io = Box::pin(async move {
+                            if let Some(trace) = &mut trace {
+                                trace.record.start_ns = trace.now();
+                            }
                             let result = ns
                                 .nvm_command(MAX_DATA_TRANSFER_SIZE, &command)
                                 .await
@@ -240,10 +311,14 @@ enum Event {
                                     );
                                     err.into()
                                 });
+                            if let Some(trace) = &mut trace {
+                                trace.record.complete_ns = trace.now();
+                            }
                             IoResult {
                                 sq_idx,
                                 cid,
                                 result,
+                                trace,
                             }
                         });
                         state.ios.push(io);
@@ -258,6 +333,7 @@ enum Event {
                         cid,
                         sq_idx,
                         result: spec::Status::INVALID_NAMESPACE_OR_FORMAT.into(),
+                        trace,
                     }
                 }
             };
@@ -273,7 +349,11 @@ enum Event {
             };
 
             match state.cq.write(completion) {
-                Ok(true) => {}
+                Ok(true) => {
+                    if let Some(trace) = &io_result.trace {
+                        trace.finish(io_result.result.status);
+                    }
+                }
                 Ok(false) => {
                     if !sq.deleting {
                         state.completions.push_back(io_result);
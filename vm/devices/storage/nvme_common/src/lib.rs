@@ -3,12 +3,16 @@
 
 //! Conversion routines between [`nvme_spec`] and [`disk_backend`] types,
 //! primarily for persistent reservation mapping between NVMe and SCSI PR
-//! models.
+//! models, and for mapping errors between NVMe statuses and [`DiskError`].
 
 #![forbid(unsafe_code)]
 
+use disk_backend::DiskError;
+use disk_backend::MediumErrorDetails;
 use disk_backend::pr;
+use nvme_spec::Status;
 use nvme_spec::nvm;
+use std::io;
 use thiserror::Error;
 
 /// Converts a `disk_backend` reservation type to an NVMe reservation type.
@@ -105,3 +109,90 @@ pub fn from_nvme_reservation_report(
 
     Ok(report)
 }
+
+/// Returns the NVMe status to complete a command with when the backing disk
+/// fails with `err`.
+pub fn disk_error_to_nvme_status(err: &DiskError) -> Status {
+    match err {
+        DiskError::ReservationConflict => Status::RESERVATION_CONFLICT,
+        DiskError::MemoryAccess(_) | DiskError::Io(_) => Status::DATA_TRANSFER_ERROR,
+        DiskError::AbortDueToPreemptAndAbort => Status::COMMAND_ABORTED_DUE_TO_PREEMPT_AND_ABORT,
+        DiskError::IllegalBlock => Status::LBA_OUT_OF_RANGE,
+        DiskError::InvalidInput => Status::INVALID_FIELD_IN_COMMAND,
+        DiskError::MediumError(_, details) => match details {
+            MediumErrorDetails::ApplicationTagCheckFailed => {
+                Status::MEDIA_END_TO_END_APPLICATION_TAG_CHECK_ERROR
+            }
+            MediumErrorDetails::GuardCheckFailed => Status::MEDIA_END_TO_END_GUARD_CHECK_ERROR,
+            MediumErrorDetails::ReferenceTagCheckFailed => {
+                Status::MEDIA_END_TO_END_REFERENCE_TAG_CHECK_ERROR
+            }
+            MediumErrorDetails::UnrecoveredReadError => Status::MEDIA_UNRECOVERED_READ_ERROR,
+            MediumErrorDetails::WriteFault => Status::MEDIA_WRITE_FAULT,
+        },
+        DiskError::ReadOnly => Status::ATTEMPTED_WRITE_TO_READ_ONLY_RANGE,
+        DiskError::UnsupportedEject => Status::INVALID_COMMAND_OPCODE,
+    }
+}
+
+/// Converts an NVMe command failure `status` from a backing NVMe device to a
+/// [`DiskError`], so that it can be reported accurately to the guest by any
+/// storage emulator.
+///
+/// `err` is the error to report for statuses without a more specific
+/// [`DiskError`] variant.
+pub fn nvme_status_to_disk_error(status: Status, err: io::Error) -> DiskError {
+    let medium_error = |details| DiskError::MediumError(err, details);
+    match status {
+        Status::RESERVATION_CONFLICT => DiskError::ReservationConflict,
+        Status::INVALID_FIELD_IN_COMMAND => DiskError::InvalidInput,
+        Status::LBA_OUT_OF_RANGE => DiskError::IllegalBlock,
+        Status::ATTEMPTED_WRITE_TO_READ_ONLY_RANGE => DiskError::ReadOnly,
+        Status::COMMAND_ABORTED_DUE_TO_PREEMPT_AND_ABORT => DiskError::AbortDueToPreemptAndAbort,
+        Status::MEDIA_WRITE_FAULT => medium_error(MediumErrorDetails::WriteFault),
+        Status::MEDIA_UNRECOVERED_READ_ERROR => {
+            medium_error(MediumErrorDetails::UnrecoveredReadError)
+        }
+        Status::MEDIA_END_TO_END_GUARD_CHECK_ERROR => {
+            medium_error(MediumErrorDetails::GuardCheckFailed)
+        }
+        Status::MEDIA_END_TO_END_APPLICATION_TAG_CHECK_ERROR => {
+            medium_error(MediumErrorDetails::ApplicationTagCheckFailed)
+        }
+        Status::MEDIA_END_TO_END_REFERENCE_TAG_CHECK_ERROR => {
+            medium_error(MediumErrorDetails::ReferenceTagCheckFailed)
+        }
+        _ => DiskError::Io(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nvme_status_round_trip() {
+        for status in [
+            Status::RESERVATION_CONFLICT,
+            Status::INVALID_FIELD_IN_COMMAND,
+            Status::LBA_OUT_OF_RANGE,
+            Status::ATTEMPTED_WRITE_TO_READ_ONLY_RANGE,
+            Status::COMMAND_ABORTED_DUE_TO_PREEMPT_AND_ABORT,
+            Status::MEDIA_WRITE_FAULT,
+            Status::MEDIA_UNRECOVERED_READ_ERROR,
+            Status::MEDIA_END_TO_END_GUARD_CHECK_ERROR,
+            Status::MEDIA_END_TO_END_APPLICATION_TAG_CHECK_ERROR,
+            Status::MEDIA_END_TO_END_REFERENCE_TAG_CHECK_ERROR,
+            Status::DATA_TRANSFER_ERROR,
+        ] {
+            let err = nvme_status_to_disk_error(status, io::Error::other("test"));
+            assert_eq!(disk_error_to_nvme_status(&err), status, "{err:?}");
+        }
+    }
+
+    #[test]
+    fn unknown_nvme_status_is_io_error() {
+        let err = nvme_status_to_disk_error(Status::CAPACITY_EXCEEDED, io::Error::other("test"));
+        assert!(matches!(err, DiskError::Io(_)));
+    }
+}
@@ -31,6 +31,11 @@ pub enum AdminQueueFaultBehavior<T> {
     CustomPayload(Vec<u8>),
     /// Verify that a command was seen.
     Verify(Option<OneshotSender<()>>),
+    /// Fail the command with the given status code (see
+    /// [`nvme_spec::Status`]). For submission queue faults, the command is
+    /// completed without being processed. For completion queue faults, the
+    /// command is processed and only the completion status is replaced.
+    Fail(u16),
 }
 
 /// Supported fault behaviour for NVMe IO queues
@@ -58,6 +63,8 @@ pub enum PciFaultBehavior {
     Default,
     /// Verify that the fault was triggered.
     Verify(Option<OneshotSender<()>>),
+    /// Fail the operation by reporting a controller fatal status (CSTS.CFS).
+    Fail,
 }
 
 /// A notification to the test confirming namespace change processing.
@@ -69,8 +76,8 @@ pub enum NamespaceChange {
 
 /// A fault configuration to apply [`PciFaultBehavior`] to the controller management layer.
 ///
-/// Currently the only supported fault is to delay enabling the controller via
-/// cc.en().
+/// Currently the only supported faults are to delay or fail enabling the
+/// controller via cc.en().
 ///
 /// # Example
 /// Delay enabling the controller by 500ms.
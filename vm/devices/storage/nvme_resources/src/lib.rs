@@ -5,11 +5,12 @@
 //!
 //! [`NvmeControllerHandle`] configures the controller with its initial
 //! namespaces, MSI-X count, and queue limits. [`NvmeControllerRequest`] enables
-//! runtime namespace add/remove.
+//! runtime namespace add/remove, telemetry changes, and IO latency tracing.
 
 #![forbid(unsafe_code)]
 
 use crate::fault::FaultConfiguration;
+use disk_backend_resources::DiskTelemetry;
 use guid::Guid;
 use mesh::MeshPayload;
 use mesh::rpc::FailableRpc;
@@ -31,14 +32,27 @@ pub struct NvmeControllerHandle {
     pub max_io_queues: u16,
     /// The initial set of namespaces.
     pub namespaces: Vec<NamespaceDefinition>,
-    /// Runtime request channel for hot add/remove of namespaces.
+    /// Runtime request channel for hot add/remove of namespaces and
+    /// telemetry changes.
     pub requests: Option<mesh::Receiver<NvmeControllerRequest>>,
+    /// The boot partitions to expose, if any.
+    pub boot_partitions: Option<NvmeBootPartitions>,
 }
 
 impl ResourceId<PciDeviceHandleKind> for NvmeControllerHandle {
     const ID: &'static str = "nvme";
 }
 
+/// The contents of an NVMe controller's boot partitions.
+#[derive(MeshPayload)]
+pub struct NvmeBootPartitions {
+    /// The contents of boot partitions 0 and 1. These are zero-padded to a
+    /// common size.
+    pub partitions: [Vec<u8>; 2],
+    /// The active boot partition ID, 0 or 1.
+    pub active: u8,
+}
+
 /// A runtime request to the NVMe controller.
 #[derive(MeshPayload)]
 pub enum NvmeControllerRequest {
@@ -46,6 +60,11 @@ pub enum NvmeControllerRequest {
     AddNamespace(FailableRpc<NamespaceDefinition, ()>),
     /// Remove a namespace by its NSID.
     RemoveNamespace(FailableRpc<u32, ()>),
+    /// Change the telemetry settings of a namespace, by its NSID.
+    SetTelemetry(FailableRpc<(u32, DiskTelemetry), ()>),
+    /// Start writing a per-command IO latency trace to a file, replacing any
+    /// current trace, or stop tracing if `None`.
+    SetIoTrace(FailableRpc<Option<std::fs::File>, ()>),
 }
 
 /// A handle to a NVMe fault controller.
@@ -120,6 +120,10 @@ pub struct Csts {
     pub reserved: u32,
 }
 
+/// The value written to the NSSR register to initiate an NVM subsystem reset
+/// ("NVMe" in ASCII).
+pub const NSSR_RESET_VALUE: u32 = 0x4e564d65;
+
 #[derive(Inspect)]
 #[bitfield(u32)]
 pub struct Aqa {
@@ -133,6 +137,52 @@ pub struct Aqa {
     pub reserved2: u8,
 }
 
+#[derive(Inspect)]
+#[bitfield(u32)]
+pub struct Bpinfo {
+    /// Boot partition size, in units of [`BOOT_PARTITION_SIZE_UNIT`].
+    #[bits(15)]
+    pub bpsz: u16,
+    #[bits(9)]
+    pub reserved: u16,
+    /// Boot read status.
+    #[bits(2)]
+    pub brs: u8,
+    #[bits(5)]
+    pub reserved2: u8,
+    /// Active boot partition ID.
+    pub abpid: bool,
+}
+
+/// The unit of [`Bpinfo::bpsz`], in bytes.
+pub const BOOT_PARTITION_SIZE_UNIT: u64 = 128 * 1024;
+
+/// The unit of [`Bprsel::bprsz`] and [`Bprsel::bprof`], in bytes.
+pub const BOOT_PARTITION_READ_UNIT: u64 = 4096;
+
+open_enum! {
+    pub enum BootReadStatus: u8 {
+        NO_READ = 0,
+        IN_PROGRESS = 1,
+        COMPLETED = 2,
+        ERROR = 3,
+    }
+}
+
+#[derive(Inspect)]
+#[bitfield(u32)]
+pub struct Bprsel {
+    /// Boot partition read size, in units of [`BOOT_PARTITION_READ_UNIT`].
+    #[bits(10)]
+    pub bprsz: u16,
+    /// Boot partition read offset, in units of [`BOOT_PARTITION_READ_UNIT`].
+    #[bits(20)]
+    pub bprof: u32,
+    pub reserved: bool,
+    /// Boot partition identifier.
+    pub bpid: bool,
+}
+
 #[repr(C)]
 #[derive(
     Copy,
@@ -473,7 +523,7 @@ pub struct IdentifyController {
     pub acl: u8,
     pub aerl: u8,
     pub frmw: FirmwareUpdates,
-    pub lpa: u8,
+    pub lpa: LogPageAttributes,
     pub elpe: u8,
     pub npss: u8,
     pub avscc: u8,
@@ -576,6 +626,25 @@ pub struct FirmwareUpdates {
     pub rsvd: u8,
 }
 
+/// Log page attributes
+#[derive(Inspect)]
+#[bitfield(u8)]
+#[derive(IntoBytes, Immutable, KnownLayout, FromBytes)]
+pub struct LogPageAttributes {
+    /// SMART / health information log page on a per-namespace basis
+    pub smart_per_namespace: bool,
+    /// Commands supported and effects log page
+    pub commands_effects: bool,
+    /// Extended data for Get Log Page (extended NUMD and log page offset)
+    pub extended_data: bool,
+    /// Telemetry host-initiated and controller-initiated log pages
+    pub telemetry: bool,
+    /// Persistent event log
+    pub persistent_event_log: bool,
+    #[bits(3)]
+    pub rsvd: u8,
+}
+
 /// Optional asynchronous events supported
 #[derive(Inspect)]
 #[bitfield(u32)]
@@ -748,6 +817,34 @@ pub struct Cdw10DeleteIoQueue {
     pub rsvd: u16,
 }
 
+#[bitfield(u32)]
+pub struct Cdw10NamespaceAttachment {
+    /// One of [`NamespaceAttachmentSelect`].
+    #[bits(4)]
+    pub sel: u8,
+    #[bits(28)]
+    pub rsvd: u32,
+}
+
+open_enum! {
+    pub enum NamespaceAttachmentSelect: u8 {
+        ATTACH = 0,
+        DETACH = 1,
+    }
+}
+
+/// A list of controller identifiers, used by Namespace Attachment and by
+/// Identify.
+#[repr(C)]
+#[derive(Debug, IntoBytes, Immutable, KnownLayout, FromBytes, Clone)]
+pub struct ControllerList {
+    /// The number of valid entries in `ids`.
+    pub count: u16,
+    pub ids: [u16; 2047],
+}
+
+static_assertions::assert_eq_size!(ControllerList, [u8; 4096]);
+
 #[bitfield(u32)]
 pub struct Cdw10GetLogPage {
     /// Log page identifier
@@ -225,6 +225,36 @@ pub struct DsmRange {
     pub starting_lba: u64,
 }
 
+/// The context attributes of a [`DsmRange`].
+#[bitfield(u32)]
+pub struct DsmContextAttributes {
+    /// Access frequency hint.
+    #[bits(4)]
+    pub access_frequency: u8,
+    /// Access latency hint.
+    #[bits(2)]
+    pub access_latency: u8,
+    #[bits(2)]
+    _rsvd: u8,
+    /// Sequential read range.
+    pub sequential_read: bool,
+    /// Sequential write range.
+    pub sequential_write: bool,
+    /// Write prepare.
+    pub write_prepare: bool,
+    #[bits(13)]
+    _rsvd2: u16,
+    /// Command access size, in logical blocks.
+    pub command_access_size: u8,
+}
+
+impl DsmContextAttributes {
+    /// The reserved bits.
+    pub const RESERVED_MASK: u32 = 0x00ff_f8c0;
+    /// The highest access frequency value that is not reserved.
+    pub const MAX_ACCESS_FREQUENCY: u8 = 8;
+}
+
 #[bitfield(u32)]
 pub struct Cdw10ReservationRegister {
     /// Reservation register action
@@ -225,35 +225,5 @@ pub async fn nvm_command(
 }
 
 fn map_disk_error(err: disk_backend::DiskError) -> NvmeError {
-    match err {
-        disk_backend::DiskError::ReservationConflict => spec::Status::RESERVATION_CONFLICT.into(),
-        disk_backend::DiskError::MemoryAccess(err) => {
-            NvmeError::new(spec::Status::DATA_TRANSFER_ERROR, err)
-        }
-        disk_backend::DiskError::AbortDueToPreemptAndAbort => {
-            NvmeError::new(spec::Status::COMMAND_ABORTED_DUE_TO_PREEMPT_AND_ABORT, err)
-        }
-        disk_backend::DiskError::IllegalBlock => spec::Status::LBA_OUT_OF_RANGE.into(),
-        disk_backend::DiskError::InvalidInput => spec::Status::INVALID_FIELD_IN_COMMAND.into(),
-        disk_backend::DiskError::Io(err) => NvmeError::new(spec::Status::DATA_TRANSFER_ERROR, err),
-        disk_backend::DiskError::MediumError(_, details) => match details {
-            disk_backend::MediumErrorDetails::ApplicationTagCheckFailed => {
-                spec::Status::MEDIA_END_TO_END_APPLICATION_TAG_CHECK_ERROR.into()
-            }
-            disk_backend::MediumErrorDetails::GuardCheckFailed => {
-                spec::Status::MEDIA_END_TO_END_GUARD_CHECK_ERROR.into()
-            }
-            disk_backend::MediumErrorDetails::ReferenceTagCheckFailed => {
-                spec::Status::MEDIA_END_TO_END_REFERENCE_TAG_CHECK_ERROR.into()
-            }
-            disk_backend::MediumErrorDetails::UnrecoveredReadError => {
-                spec::Status::MEDIA_UNRECOVERED_READ_ERROR.into()
-            }
-            disk_backend::MediumErrorDetails::WriteFault => spec::Status::MEDIA_WRITE_FAULT.into(),
-        },
-        disk_backend::DiskError::ReadOnly => {
-            spec::Status::ATTEMPTED_WRITE_TO_READ_ONLY_RANGE.into()
-        }
-        disk_backend::DiskError::UnsupportedEject => spec::Status::INVALID_COMMAND_OPCODE.into(),
-    }
+    NvmeError::new(nvme_common::disk_error_to_nvme_status(&err), err)
 }
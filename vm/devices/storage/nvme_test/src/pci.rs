@@ -394,6 +394,11 @@ fn set_cc(&mut self, cc: spec::Cc) {
                                 send.send(());
                             }
                         }
+                        PciFaultBehavior::Fail => {
+                            tracing::info!("configured fault: failing cc.en with a fatal error");
+                            self.fatal_error();
+                            return;
+                        }
                     }
                 }
 
@@ -515,6 +515,7 @@ async fn process_event(
             Event::Command(command) => {
                 let mut command = command?;
                 let opcode = spec::AdminOpcode(command.cdw0.opcode());
+                let mut injected_status = None;
 
                 if self.config.fault_configuration.fault_active.get()
                     && let Some(fault) = Self::get_configured_fault_behavior_mut::<nvme_spec::Command>(
@@ -568,48 +569,60 @@ async fn process_event(
                                 send.send(());
                             }
                         }
+                        AdminQueueFaultBehavior::Fail(status) => {
+                            tracing::info!(
+                                "configured fault: admin command failed in sq with status {:#x}. command: {:?}",
+                                status,
+                                &command
+                            );
+                            injected_status = Some(spec::Status(*status));
+                        }
                     }
                 }
 
-                let result = match opcode {
-                    spec::AdminOpcode::IDENTIFY => self
-                        .handle_identify(state, &command)
-                        .map(|()| Some(Default::default())),
-                    spec::AdminOpcode::GET_FEATURES => {
-                        self.handle_get_features(state, &command).await.map(Some)
-                    }
-                    spec::AdminOpcode::SET_FEATURES => {
-                        self.handle_set_features(state, &command).map(Some)
-                    }
-                    spec::AdminOpcode::CREATE_IO_COMPLETION_QUEUE => self
-                        .handle_create_io_completion_queue(state, &command)
-                        .map(|()| Some(Default::default())),
-                    spec::AdminOpcode::CREATE_IO_SUBMISSION_QUEUE => self
-                        .handle_create_io_submission_queue(state, &command)
-                        .map(|()| Some(Default::default())),
-                    spec::AdminOpcode::DELETE_IO_COMPLETION_QUEUE => self
-                        .handle_delete_io_completion_queue(state, &command)
-                        .map(|()| Some(Default::default())),
-                    spec::AdminOpcode::DELETE_IO_SUBMISSION_QUEUE => {
-                        self.handle_delete_io_submission_queue(state, &command)
-                    }
-                    spec::AdminOpcode::ASYNCHRONOUS_EVENT_REQUEST => {
-                        self.handle_asynchronous_event_request(state, &command)
-                    }
-                    spec::AdminOpcode::ABORT => self.handle_abort(),
-                    spec::AdminOpcode::GET_LOG_PAGE => self
-                        .handle_get_log_page(state, &command)
-                        .map(|()| Some(Default::default())),
-                    spec::AdminOpcode::DOORBELL_BUFFER_CONFIG
-                        if self.supports_shadow_doorbells(state) =>
-                    {
-                        self.handle_doorbell_buffer_config(state, &command)
-                            .await
-                            .map(|()| Some(Default::default()))
-                    }
-                    opcode => {
-                        tracelimit::warn_ratelimited!(?opcode, "unsupported opcode");
-                        Err(spec::Status::INVALID_COMMAND_OPCODE.into())
+                let result = if let Some(status) = injected_status {
+                    Err(status.into())
+                } else {
+                    match opcode {
+                        spec::AdminOpcode::IDENTIFY => self
+                            .handle_identify(state, &command)
+                            .map(|()| Some(Default::default())),
+                        spec::AdminOpcode::GET_FEATURES => {
+                            self.handle_get_features(state, &command).await.map(Some)
+                        }
+                        spec::AdminOpcode::SET_FEATURES => {
+                            self.handle_set_features(state, &command).map(Some)
+                        }
+                        spec::AdminOpcode::CREATE_IO_COMPLETION_QUEUE => self
+                            .handle_create_io_completion_queue(state, &command)
+                            .map(|()| Some(Default::default())),
+                        spec::AdminOpcode::CREATE_IO_SUBMISSION_QUEUE => self
+                            .handle_create_io_submission_queue(state, &command)
+                            .map(|()| Some(Default::default())),
+                        spec::AdminOpcode::DELETE_IO_COMPLETION_QUEUE => self
+                            .handle_delete_io_completion_queue(state, &command)
+                            .map(|()| Some(Default::default())),
+                        spec::AdminOpcode::DELETE_IO_SUBMISSION_QUEUE => {
+                            self.handle_delete_io_submission_queue(state, &command)
+                        }
+                        spec::AdminOpcode::ASYNCHRONOUS_EVENT_REQUEST => {
+                            self.handle_asynchronous_event_request(state, &command)
+                        }
+                        spec::AdminOpcode::ABORT => self.handle_abort(),
+                        spec::AdminOpcode::GET_LOG_PAGE => self
+                            .handle_get_log_page(state, &command)
+                            .map(|()| Some(Default::default())),
+                        spec::AdminOpcode::DOORBELL_BUFFER_CONFIG
+                            if self.supports_shadow_doorbells(state) =>
+                        {
+                            self.handle_doorbell_buffer_config(state, &command)
+                                .await
+                                .map(|()| Some(Default::default()))
+                        }
+                        opcode => {
+                            tracelimit::warn_ratelimited!(?opcode, "unsupported opcode");
+                            Err(spec::Status::INVALID_COMMAND_OPCODE.into())
+                        }
                     }
                 };
 
@@ -742,6 +755,18 @@ async fn apply_completion_queue_fault(
                         send.send(());
                     }
                 }
+                AdminQueueFaultBehavior::Fail(status) => {
+                    tracing::info!(
+                        "configured fault: admin completion failed in cq with status {:#x}. command: {:?}, completion: {:?}",
+                        status,
+                        &command,
+                        &completion
+                    );
+                    updated_completion = Some(spec::Completion {
+                        status: completion.status.with_status(*status),
+                        ..completion.clone()
+                    });
+                }
             }
         }
         updated_completion
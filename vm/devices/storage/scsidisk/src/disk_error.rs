@@ -0,0 +1,113 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Mapping from disk backend errors to SCSI status and sense data, shared by
+//! the hard disk and DVD emulators so that guests see the same errors for the
+//! same backend failures.
+
+use disk_backend::DiskError;
+use disk_backend::MediumErrorDetails;
+use scsi::AdditionalSenseCode;
+use scsi::ScsiStatus;
+use scsi::SenseData;
+use scsi::SenseKey;
+use scsi::srb::SrbStatus;
+use scsi_core::ScsiResult;
+use scsi_defs as scsi;
+
+/// Returns the SCSI result to report to the guest for a failed disk request.
+///
+/// `illegal_request_sense` builds the sense data for illegal requests, since
+/// the hard disk and DVD emulators accept different sense codes.
+pub(crate) fn disk_error_result(
+    err: &DiskError,
+    illegal_request_sense: fn(AdditionalSenseCode) -> SenseData,
+) -> ScsiResult {
+    let (scsi_status, srb_status, sense_data) = match err {
+        DiskError::InvalidInput | DiskError::MemoryAccess(_) => (
+            ScsiStatus::CHECK_CONDITION,
+            SrbStatus::INVALID_REQUEST,
+            Some(illegal_request_sense(AdditionalSenseCode::INVALID_CDB)),
+        ),
+        DiskError::IllegalBlock => (
+            ScsiStatus::CHECK_CONDITION,
+            SrbStatus::ERROR,
+            Some(illegal_request_sense(AdditionalSenseCode::ILLEGAL_BLOCK)),
+        ),
+        DiskError::UnsupportedEject => (
+            ScsiStatus::CHECK_CONDITION,
+            SrbStatus::INVALID_REQUEST,
+            Some(illegal_request_sense(AdditionalSenseCode::ILLEGAL_COMMAND)),
+        ),
+        DiskError::ReadOnly => (
+            ScsiStatus::CHECK_CONDITION,
+            SrbStatus::ERROR,
+            Some(SenseData::new(
+                SenseKey::DATA_PROTECT,
+                AdditionalSenseCode::WRITE_PROTECT,
+                0,
+            )),
+        ),
+        DiskError::AbortDueToPreemptAndAbort => (
+            ScsiStatus::TASK_ABORTED,
+            SrbStatus::ABORTED,
+            Some(SenseData::new(
+                SenseKey::ABORTED_COMMAND,
+                AdditionalSenseCode::NO_SENSE,
+                0,
+            )),
+        ),
+        // Reservation conflicts are reported by status alone, with no sense
+        // data.
+        DiskError::ReservationConflict => {
+            (ScsiStatus::RESERVATION_CONFLICT, SrbStatus::ERROR, None)
+        }
+        DiskError::Io(_) => (
+            ScsiStatus::CHECK_CONDITION,
+            SrbStatus::ERROR,
+            Some(SenseData::new(
+                SenseKey::MEDIUM_ERROR,
+                AdditionalSenseCode::NO_SENSE,
+                0,
+            )),
+        ),
+        DiskError::MediumError(_, details) => {
+            let (sense_code, qualifier) = medium_error_sense(details);
+            (
+                ScsiStatus::CHECK_CONDITION,
+                SrbStatus::ERROR,
+                Some(SenseData::new(
+                    SenseKey::MEDIUM_ERROR,
+                    sense_code,
+                    qualifier,
+                )),
+            )
+        }
+    };
+    ScsiResult {
+        scsi_status,
+        srb_status,
+        tx: 0,
+        sense_data,
+    }
+}
+
+/// Returns the additional sense code and qualifier for a medium error.
+fn medium_error_sense(details: &MediumErrorDetails) -> (AdditionalSenseCode, u8) {
+    match details {
+        MediumErrorDetails::ApplicationTagCheckFailed => (
+            AdditionalSenseCode::UNRECOVERED_ERROR,
+            scsi::SCSI_SENSEQ_LOGICAL_BLOCK_TAG_CHECK_FAILED,
+        ),
+        MediumErrorDetails::GuardCheckFailed => (
+            AdditionalSenseCode::CRC_OR_ECC_ERROR,
+            scsi::SCSI_SENSEQ_LOGICAL_BLOCK_GUARD_CHECK_FAILED,
+        ),
+        MediumErrorDetails::ReferenceTagCheckFailed => (
+            AdditionalSenseCode::CRC_OR_ECC_ERROR,
+            scsi::SCSI_SENSEQ_LOGICAL_BLOCK_REF_TAG_CHECK_FAILED,
+        ),
+        MediumErrorDetails::UnrecoveredReadError => (AdditionalSenseCode::UNRECOVERED_ERROR, 0),
+        MediumErrorDetails::WriteFault => (AdditionalSenseCode::WRITE, 0),
+    }
+}
@@ -30,6 +30,7 @@
 #![forbid(unsafe_code)]
 
 pub mod atapi_scsi;
+mod disk_error;
 mod getlbastatus;
 mod inquiry;
 mod reservation;
@@ -76,6 +77,7 @@
 use thiserror::Error;
 use tracing::Instrument;
 use tracing_helpers::ErrorValueExt;
+use unmap::UnmapStats;
 use unmap::validate_lba_range;
 use vmcore::save_restore::RestoreError;
 use vmcore::save_restore::SaveError;
@@ -140,6 +142,7 @@ pub struct SimpleScsiDisk {
     scsi_parameters: ScsiParameters,
     support_pr: bool,
     last_sector_count: AtomicU64,
+    unmap_stats: UnmapStats,
 }
 
 #[derive(Debug, Clone, Inspect)]
@@ -229,6 +232,7 @@ pub fn new(disk: Disk, disk_parameters: DiskParameters) -> Self {
             scsi_parameters,
             support_pr,
             last_sector_count: AtomicU64::new(sector_count),
+            unmap_stats: UnmapStats::default(),
         }
     }
 }
@@ -979,141 +983,51 @@ fn process_result(&self, result: Result<usize, ScsiError>, op: ScsiOp) -> ScsiRe
                 tx,
                 sense_data: None,
             },
-            Err(err) => {
-                match err {
-                    ScsiError::MemoryAccess(_)
-                    | ScsiError::UnsupportedModePageCode(..)
-                    | ScsiError::UnsupportedServiceAction(_)
-                    | ScsiError::UnsupportedVpdPageCode(_)
-                    | ScsiError::SrbError
-                    | ScsiError::Disk(DiskError::InvalidInput)
-                    | ScsiError::Disk(DiskError::MemoryAccess(_)) => ScsiResult {
-                        scsi_status: ScsiStatus::CHECK_CONDITION,
-                        srb_status: SrbStatus::INVALID_REQUEST,
-                        tx: 0,
-                        sense_data: Some(illegal_request_sense(AdditionalSenseCode::INVALID_CDB)),
-                    },
-                    ScsiError::IllegalRequest(sense_code) => ScsiResult {
-                        scsi_status: ScsiStatus::CHECK_CONDITION,
-                        srb_status: SrbStatus::INVALID_REQUEST,
-                        tx: 0,
-                        sense_data: Some(illegal_request_sense(sense_code)),
-                    },
-                    ScsiError::DataOverrun => ScsiResult {
-                        scsi_status: ScsiStatus::CHECK_CONDITION,
-                        srb_status: SrbStatus::DATA_OVERRUN,
-                        tx: 0,
-                        sense_data: Some(illegal_request_sense(AdditionalSenseCode::INVALID_CDB)),
-                    },
-                    ScsiError::UnitAttention => ScsiResult {
-                        scsi_status: ScsiStatus::CHECK_CONDITION,
-                        srb_status: SrbStatus::ERROR,
-                        tx: 0,
-                        sense_data: Some(scsi::SenseData::new(
-                            SenseKey::UNIT_ATTENTION,
-                            AdditionalSenseCode::PARAMETERS_CHANGED,
-                            scsi::SCSI_SENSEQ_CAPACITY_DATA_CHANGED,
-                        )),
-                    },
-                    ScsiError::WriteProtected | ScsiError::Disk(DiskError::ReadOnly) => {
-                        ScsiResult {
-                            scsi_status: ScsiStatus::CHECK_CONDITION,
-                            srb_status: SrbStatus::ERROR,
-                            tx: 0,
-                            sense_data: Some(scsi::SenseData::new(
-                                SenseKey::DATA_PROTECT,
-                                AdditionalSenseCode::WRITE_PROTECT,
-                                0,
-                            )),
-                        }
-                    }
-                    ScsiError::Disk(err) => {
-                        match err {
-                            DiskError::AbortDueToPreemptAndAbort => ScsiResult {
-                                scsi_status: ScsiStatus::TASK_ABORTED,
-                                srb_status: SrbStatus::ABORTED,
-                                tx: 0,
-                                sense_data: Some(scsi::SenseData::new(
-                                    SenseKey::ABORTED_COMMAND,
-                                    AdditionalSenseCode::NO_SENSE,
-                                    0,
-                                )),
-                            },
-                            DiskError::IllegalBlock => ScsiResult {
-                                scsi_status: ScsiStatus::CHECK_CONDITION,
-                                srb_status: SrbStatus::ERROR,
-                                tx: 0,
-                                sense_data: Some(scsi::SenseData::new(
-                                    SenseKey::ILLEGAL_REQUEST,
-                                    AdditionalSenseCode::ILLEGAL_BLOCK,
-                                    0,
-                                )),
-                            },
-                            DiskError::Io(_) => ScsiResult {
-                                scsi_status: ScsiStatus::CHECK_CONDITION,
-                                srb_status: SrbStatus::ERROR,
-                                tx: 0,
-                                sense_data: Some(scsi::SenseData::new(
-                                    SenseKey::MEDIUM_ERROR,
-                                    AdditionalSenseCode::NO_SENSE,
-                                    0,
-                                )),
-                            },
-                            DiskError::MediumError(_, details) => {
-                                let (sense_code, qualifier) = match details {
-                                    disk_backend::MediumErrorDetails::ApplicationTagCheckFailed => {
-                                        (
-                                            AdditionalSenseCode::UNRECOVERED_ERROR,
-                                            scsi::SCSI_SENSEQ_LOGICAL_BLOCK_TAG_CHECK_FAILED,
-                                        )
-                                    }
-                                    disk_backend::MediumErrorDetails::GuardCheckFailed => (
-                                        AdditionalSenseCode::CRC_OR_ECC_ERROR,
-                                        scsi::SCSI_SENSEQ_LOGICAL_BLOCK_GUARD_CHECK_FAILED,
-                                    ),
-                                    disk_backend::MediumErrorDetails::ReferenceTagCheckFailed => (
-                                        AdditionalSenseCode::CRC_OR_ECC_ERROR,
-                                        scsi::SCSI_SENSEQ_LOGICAL_BLOCK_REF_TAG_CHECK_FAILED,
-                                    ),
-                                    disk_backend::MediumErrorDetails::UnrecoveredReadError => {
-                                        (AdditionalSenseCode::UNRECOVERED_ERROR, 0)
-                                    }
-                                    disk_backend::MediumErrorDetails::WriteFault => {
-                                        (AdditionalSenseCode::WRITE, 0)
-                                    }
-                                };
-                                ScsiResult {
-                                    scsi_status: ScsiStatus::CHECK_CONDITION,
-                                    srb_status: SrbStatus::ERROR,
-                                    tx: 0,
-                                    sense_data: Some(scsi::SenseData::new(
-                                        SenseKey::MEDIUM_ERROR,
-                                        sense_code,
-                                        qualifier,
-                                    )),
-                                }
-                            }
-                            DiskError::ReservationConflict => ScsiResult {
-                                scsi_status: ScsiStatus::RESERVATION_CONFLICT,
-                                srb_status: SrbStatus::ERROR,
-                                tx: 0,
-                                sense_data: None,
-                            },
-                            DiskError::UnsupportedEject => ScsiResult {
-                                scsi_status: ScsiStatus::CHECK_CONDITION,
-                                srb_status: SrbStatus::INVALID_REQUEST,
-                                tx: 0,
-                                sense_data: Some(illegal_request_sense(
-                                    AdditionalSenseCode::ILLEGAL_COMMAND,
-                                )),
-                            },
-                            DiskError::InvalidInput
-                            | DiskError::MemoryAccess(_)
-                            | DiskError::ReadOnly => unreachable!(), //handled above
-                        }
-                    }
-                }
-            }
+            Err(err) => match err {
+                ScsiError::MemoryAccess(_)
+                | ScsiError::UnsupportedModePageCode(..)
+                | ScsiError::UnsupportedServiceAction(_)
+                | ScsiError::UnsupportedVpdPageCode(_)
+                | ScsiError::SrbError => ScsiResult {
+                    scsi_status: ScsiStatus::CHECK_CONDITION,
+                    srb_status: SrbStatus::INVALID_REQUEST,
+                    tx: 0,
+                    sense_data: Some(illegal_request_sense(AdditionalSenseCode::INVALID_CDB)),
+                },
+                ScsiError::IllegalRequest(sense_code) => ScsiResult {
+                    scsi_status: ScsiStatus::CHECK_CONDITION,
+                    srb_status: SrbStatus::INVALID_REQUEST,
+                    tx: 0,
+                    sense_data: Some(illegal_request_sense(sense_code)),
+                },
+                ScsiError::DataOverrun => ScsiResult {
+                    scsi_status: ScsiStatus::CHECK_CONDITION,
+                    srb_status: SrbStatus::DATA_OVERRUN,
+                    tx: 0,
+                    sense_data: Some(illegal_request_sense(AdditionalSenseCode::INVALID_CDB)),
+                },
+                ScsiError::UnitAttention => ScsiResult {
+                    scsi_status: ScsiStatus::CHECK_CONDITION,
+                    srb_status: SrbStatus::ERROR,
+                    tx: 0,
+                    sense_data: Some(scsi::SenseData::new(
+                        SenseKey::UNIT_ATTENTION,
+                        AdditionalSenseCode::PARAMETERS_CHANGED,
+                        scsi::SCSI_SENSEQ_CAPACITY_DATA_CHANGED,
+                    )),
+                },
+                ScsiError::WriteProtected => ScsiResult {
+                    scsi_status: ScsiStatus::CHECK_CONDITION,
+                    srb_status: SrbStatus::ERROR,
+                    tx: 0,
+                    sense_data: Some(scsi::SenseData::new(
+                        SenseKey::DATA_PROTECT,
+                        AdditionalSenseCode::WRITE_PROTECT,
+                        0,
+                    )),
+                },
+                ScsiError::Disk(err) => disk_error::disk_error_result(&err, illegal_request_sense),
+            },
         };
 
         self.sense_data.set(result.sense_data.as_ref());
@@ -1342,6 +1256,7 @@ fn inspect(&self, req: inspect::Request<'_>) {
             )
             .field("scsi_parameters", &self.scsi_parameters)
             .field("pr", self.support_pr)
+            .field("unmap_stats", &self.unmap_stats)
             .field("backend", &self.disk);
     }
 }
@@ -2312,33 +2312,11 @@ fn process_result(&self, result: Result<usize, ScsiDvdError>, op: ScsiOp) -> Scs
                         sense_qualifier,
                     )),
                 },
-                ScsiDvdError::IoError(err) => match err {
-                    DiskError::UnsupportedEject => ScsiResult {
-                        scsi_status: ScsiStatus::CHECK_CONDITION,
-                        srb_status: SrbStatus::INVALID_REQUEST,
-                        tx: 0,
-                        sense_data: Some(illegal_request_sense_iso(
-                            AdditionalSenseCode::ILLEGAL_COMMAND,
-                            0,
-                        )),
-                    },
-                    DiskError::ReservationConflict => ScsiResult {
-                        scsi_status: ScsiStatus::RESERVATION_CONFLICT,
-                        srb_status: SrbStatus::ERROR,
-                        tx: 0,
-                        sense_data: Some(SenseData::new(
-                            SenseKey::ILLEGAL_REQUEST,
-                            AdditionalSenseCode::COMMAND_SEQUENCE_ERROR,
-                            scsi::SCSI_SENSEQ_CAPACITY_DATA_CHANGED,
-                        )),
-                    },
-                    _ => ScsiResult {
-                        scsi_status: ScsiStatus::CHECK_CONDITION,
-                        srb_status: SrbStatus::ERROR,
-                        tx: 0,
-                        sense_data: None,
-                    },
-                },
+                ScsiDvdError::IoError(err) => {
+                    crate::disk_error::disk_error_result(&err, |sense_code| {
+                        illegal_request_sense_iso(sense_code, 0)
+                    })
+                }
                 ScsiDvdError::IllegalRequestNoSenseData => ScsiResult {
                     scsi_status: ScsiStatus::CHECK_CONDITION,
                     srb_status: SrbStatus::INVALID_REQUEST,
@@ -5,10 +5,14 @@
 use super::SimpleScsiDisk;
 use crate::UNMAP_RANGE_DESCRIPTOR_COUNT_MAX;
 use crate::scsi;
+use futures::StreamExt;
 use guestmem::MemoryRead;
+use inspect::Inspect;
 use scsi::AdditionalSenseCode;
 use scsi_buffers::RequestBuffers;
 use scsi_core::Request;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use zerocopy::FromBytes;
 
 #[derive(Debug, Default)]
@@ -161,24 +165,53 @@ fn process_unmap_request(
     }
 }
 
+/// The maximum number of backend unmap operations in flight for a single UNMAP
+/// command.
+const MAX_CONCURRENT_UNMAPS: usize = 8;
+
+/// Counters for UNMAP processing.
+#[derive(Debug, Default)]
+pub(crate) struct UnmapStats {
+    /// UNMAP commands that were passed to the backend.
+    requests: AtomicU64,
+    /// Backend unmap operations issued.
+    ranges: AtomicU64,
+    /// Backend unmap operations that failed (and were ignored).
+    failed_ranges: AtomicU64,
+    /// Sectors passed to the backend for unmapping.
+    sectors: AtomicU64,
+}
+
+impl Inspect for UnmapStats {
+    fn inspect(&self, req: inspect::Request<'_>) {
+        req.respond()
+            .counter("requests", self.requests.load(Ordering::Relaxed))
+            .counter("ranges", self.ranges.load(Ordering::Relaxed))
+            .counter("failed_ranges", self.failed_ranges.load(Ordering::Relaxed))
+            .counter("sectors", self.sectors.load(Ordering::Relaxed));
+    }
+}
+
 impl SimpleScsiDisk {
-    async fn perform_unmap(
+    /// Splits the UNMAP descriptors into the sub-ranges to pass to the
+    /// backend, validating all of them before any are issued.
+    fn unmap_sub_ranges(
         &self,
         buffer: &[u8],
         unmap_info: &mut UnmapInfo,
-        block_level_only: bool,
         sector_count: u64,
-    ) -> Result<(), ScsiError> {
+    ) -> Result<Vec<(u64, u64)>, ScsiError> {
         let sector_shift = self.sector_shift;
         let max_lba_count_per_sub_range = (u32::MAX >> sector_shift) as u64;
         let max_lba_count_per_block = (self.sector_size >> sector_shift) as u64; //TODO: get block_size
 
+        let mut sub_ranges = Vec::new();
         loop {
             // Determine if there is more work to do on the current descriptor.
             if unmap_info.lba_count == 0 {
                 unmap_info.descriptor_index += 1;
                 if unmap_info.descriptor_index >= unmap_info.total_descriptors {
-                    return Ok(());
+                    return Ok(sub_ranges);
                 }
 
                 unmap_info.offset += size_of::<scsi::UnmapBlockDescriptor>();
@@ -204,25 +237,44 @@ async fn perform_unmap(
 
             unmap_info.start_lba += lba_count;
             unmap_info.lba_count -= lba_count;
-
-            tracing::debug!(
-                start_lba,
-                lba_count,
-                start_lba,
-                lba_count,
-                "dispatching inner unmap"
-            );
-
-            if let Err(e) = self
-                .disk
-                .unmap(start_lba, lba_count, block_level_only)
-                .await
-            {
-                tracing::debug!(error = ?e, "Unmap failures ignored")
-            }
+            sub_ranges.push((start_lba, lba_count));
         }
     }
 
+    /// Issues the UNMAP's sub-ranges to the backend, keeping several in
+    /// flight at once, and completes when all of them have.
+    async fn perform_unmap(
+        &self,
+        buffer: &[u8],
+        unmap_info: &mut UnmapInfo,
+        block_level_only: bool,
+        sector_count: u64,
+    ) -> Result<(), ScsiError> {
+        let sub_ranges = self.unmap_sub_ranges(buffer, unmap_info, sector_count)?;
+        let stats = &self.unmap_stats;
+        stats.requests.fetch_add(1, Ordering::Relaxed);
+
+        futures::stream::iter(sub_ranges)
+            .map(|(start_lba, lba_count)| async move {
+                tracing::debug!(start_lba, lba_count, "dispatching inner unmap");
+                stats.ranges.fetch_add(1, Ordering::Relaxed);
+                stats.sectors.fetch_add(lba_count, Ordering::Relaxed);
+                if let Err(e) = self
+                    .disk
+                    .unmap(start_lba, lba_count, block_level_only)
+                    .await
+                {
+                    stats.failed_ranges.fetch_add(1, Ordering::Relaxed);
+                    tracing::debug!(error = ?e, "Unmap failures ignored")
+                }
+            })
+            .buffer_unordered(MAX_CONCURRENT_UNMAPS)
+            .collect::<()>()
+            .await;
+
+        Ok(())
+    }
+
     pub(crate) async fn handle_unmap(
         &self,
         external_data: &RequestBuffers<'_>,
@@ -66,6 +66,7 @@ async fn new(
                 msix_count: MSIX_COUNT,
                 max_io_queues: IO_QUEUE_COUNT,
                 subsystem_id: Guid::new_random(),
+                boot_partitions: None,
             },
         );
 
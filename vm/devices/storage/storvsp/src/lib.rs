@@ -32,7 +32,8 @@
 //!   `SaveRestoreVmbusDevice`.
 //! - [`ScsiController`] — manages attached disks by [`ScsiPath`]. Supports
 //!   runtime attach/remove.
-//! - [`ScsiControllerDisk`] — wraps `Arc<dyn AsyncScsiDisk>`.
+//! - [`ScsiControllerDisk`] — wraps `Arc<dyn AsyncScsiDisk>`, along with its
+//!   runtime-configurable telemetry.
 //!
 //! # Performance
 //!
@@ -59,6 +60,8 @@
 use crate::ring::gparange::MultiPagedRangeBuf;
 use anyhow::Context as _;
 use async_trait::async_trait;
+use disk_backend_resources::DiskTelemetry;
+use disk_backend_resources::IoTraceLevel;
 use fast_select::FastSelect;
 use futures::FutureExt;
 use futures::StreamExt;
@@ -96,11 +99,16 @@
 use std::future::poll_fn;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU8;
 use std::sync::atomic::AtomicU32;
 use std::sync::atomic::Ordering::Relaxed;
 use std::task::Context;
 use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
 use storvsp_resources::ScsiPath;
+use storvsp_resources::ScsiProtocolVersion;
 use task_control::AsyncRun;
 use task_control::InspectTask;
 use task_control::StopTask;
@@ -145,6 +153,10 @@
 /// new IO requests.
 const DEFAULT_POLL_MODE_QUEUE_DEPTH: u32 = 1;
 
+/// The interrupt latency to use when the guest requests monitored
+/// notifications (MNF) for the channel.
+const MNF_INTERRUPT_LATENCY: Duration = Duration::from_micros(100);
+
 pub struct StorageDevice {
     instance_id: Guid,
     ide_path: Option<ScsiPath>,
@@ -173,7 +185,9 @@ fn inspect_mut(&mut self, req: inspect::Request<'_>) {
         let disks = self.controller.disks.read();
         for (path, controller_disk) in disks.iter() {
             resp.child(&format!("disks/{}", path), |req| {
-                controller_disk.disk.inspect(req);
+                req.respond()
+                    .merge(&*controller_disk.disk)
+                    .field("telemetry", &*controller_disk.telemetry);
             });
         }
 
@@ -202,6 +216,8 @@ struct Protocol {
     state: RwLock<ProtocolState>,
     /// Signaled when `state` transitions to `ProtocolState::Ready`.
     ready: event_listener::Event,
+    /// The newest version to accept from the guest.
+    max_version: Version,
 }
 
 struct WorkerInner {
@@ -255,6 +271,15 @@ fn parse(major_minor: u16) -> Result<Self, UnsupportedVersion> {
         Ok(version)
     }
 
+    fn from_resource(version: ScsiProtocolVersion) -> Self {
+        match version {
+            ScsiProtocolVersion::Win6 => Self::Win6,
+            ScsiProtocolVersion::Win7 => Self::Win7,
+            ScsiProtocolVersion::Win8 => Self::Win8,
+            ScsiProtocolVersion::Blue => Self::Blue,
+        }
+    }
+
     fn max_request_size(&self) -> usize {
         match self {
             Version::Win8 | Version::Blue => storvsp_protocol::SCSI_REQUEST_LEN_V2,
@@ -596,18 +621,13 @@ async fn execute_scsi(&self, full_request: &ScsiRequestAndRange) -> ScsiResult {
             "execute_scsi start...",
         );
 
-        let path_id = self.force_path_id.unwrap_or(request.path_id);
+        let path = ScsiPath {
+            path: self.force_path_id.unwrap_or(request.path_id),
+            target: request.target_id,
+            lun: request.lun,
+        };
 
-        let controller_disk = self
-            .controller
-            .disks
-            .read()
-            .get(&ScsiPath {
-                path: path_id,
-                target: request.target_id,
-                lun: request.lun,
-            })
-            .cloned();
+        let controller_disk = self.controller.disks.read().get(&path).cloned();
 
         let result = match op {
             ScsiOp::REPORT_LUNS => {
@@ -665,10 +685,11 @@ async fn execute_scsi(&self, full_request: &ScsiRequestAndRange) -> ScsiResult {
                 }
             }
             _ if controller_disk.is_some() => {
+                let controller_disk = controller_disk.as_ref().unwrap();
                 let mut cdb = [0; 16];
                 cdb.copy_from_slice(&request.payload[0..storvsp_protocol::CDB16GENERIC_LENGTH]);
-                controller_disk
-                    .unwrap()
+                let start = controller_disk.telemetry.start();
+                let result = controller_disk
                     .disk
                     .execute_scsi(
                         &external_data,
@@ -677,7 +698,9 @@ async fn execute_scsi(&self, full_request: &ScsiRequestAndRange) -> ScsiResult {
                             srb_flags: request.srb_flags,
                         },
                     )
-                    .await
+                    .await;
+                controller_disk.telemetry.complete(path, op, start, &result);
+                result
             }
             ScsiOp::INQUIRY => {
                 let cdb = scsi::CdbInquiry::ref_from_prefix(&request.payload)
@@ -973,7 +996,9 @@ async fn process_primary(&mut self) -> Result<(), WorkerError> {
                         InitState::QueryVersion => {
                             let packet = self.inner.next_packet(&mut reader).await?;
                             if let PacketData::QueryProtocolVersion(major_minor) = packet.data {
-                                if let Ok(version) = Version::parse(major_minor) {
+                                if let Ok(version) = Version::parse(major_minor)
+                                    && version <= self.inner.protocol.max_version
+                                {
                                     self.inner.send_completion(
                                         &mut writer,
                                         &packet,
@@ -1468,12 +1493,16 @@ fn inspect(&self, req: inspect::Request<'_>) {
 
 impl StorageDevice {
     /// Returns a new SCSI device.
+    ///
+    /// If `max_protocol_version` is set, guests requesting a newer protocol
+    /// version are refused, so that they fall back to an older one.
     pub fn build_scsi(
         driver_source: &VmTaskDriverSource,
         controller: &ScsiController,
         instance_id: Guid,
         max_sub_channel_count: u16,
         io_queue_depth: u32,
+        max_protocol_version: Option<ScsiProtocolVersion>,
     ) -> Self {
         Self::build_inner(
             driver_source,
@@ -1482,6 +1511,7 @@ pub fn build_scsi(
             None,
             max_sub_channel_count,
             io_queue_depth,
+            max_protocol_version.map_or(Version::Blue, Version::from_resource),
         )
     }
 
@@ -1518,6 +1548,7 @@ pub fn build_ide(
             Some(path),
             0,
             io_queue_depth,
+            Version::Blue,
         )
     }
 
@@ -1528,6 +1559,7 @@ fn build_inner(
         ide_path: Option<ScsiPath>,
         max_sub_channel_count: u16,
         io_queue_depth: u32,
+        max_version: Version,
     ) -> Self {
         let workers = (0..max_sub_channel_count + 1)
             .map(|channel_index| WorkerAndDriver {
@@ -1551,6 +1583,7 @@ fn build_inner(
             protocol: Arc::new(Protocol {
                 state: RwLock::new(ProtocolState::Init(InitState::Begin)),
                 ready: Default::default(),
+                max_version,
             }),
             io_queue_depth,
         }
@@ -1620,12 +1653,92 @@ fn new_worker(
 #[derive(Clone)]
 pub struct ScsiControllerDisk {
     disk: Arc<dyn AsyncScsiDisk>,
+    telemetry: Arc<IoTelemetry>,
 }
 
 impl ScsiControllerDisk {
     /// Creates a new controller disk from an async SCSI disk.
     pub fn new(disk: Arc<dyn AsyncScsiDisk>) -> Self {
-        Self { disk }
+        Self {
+            disk,
+            telemetry: Default::default(),
+        }
+    }
+}
+
+/// Opt-in per-disk diagnostics, which can be changed while the disk is in
+/// use.
+#[derive(Default)]
+struct IoTelemetry {
+    latency_histogram: AtomicBool,
+    /// The [`IoTraceLevel`], as a `u8`.
+    trace_level: AtomicU8,
+    /// IO latencies in microseconds.
+    latency_us: Mutex<Histogram<16>>,
+}
+
+impl IoTelemetry {
+    fn set(&self, telemetry: DiskTelemetry) {
+        let DiskTelemetry {
+            latency_histogram,
+            trace_level,
+        } = telemetry;
+        self.latency_histogram.store(latency_histogram, Relaxed);
+        self.trace_level.store(trace_level as u8, Relaxed);
+    }
+
+    fn trace_level(&self) -> IoTraceLevel {
+        match self.trace_level.load(Relaxed) {
+            x if x == IoTraceLevel::Info as u8 => IoTraceLevel::Info,
+            x if x == IoTraceLevel::Debug as u8 => IoTraceLevel::Debug,
+            x if x == IoTraceLevel::Trace as u8 => IoTraceLevel::Trace,
+            _ => IoTraceLevel::Off,
+        }
+    }
+
+    /// Returns the start time of an IO, if it is needed.
+    fn start(&self) -> Option<Instant> {
+        (self.latency_histogram.load(Relaxed) || self.trace_level() != IoTraceLevel::Off)
+            .then(Instant::now)
+    }
+
+    fn complete(&self, path: ScsiPath, op: ScsiOp, start: Option<Instant>, result: &ScsiResult) {
+        let Some(start) = start else {
+            return;
+        };
+        let latency_us = start.elapsed().as_micros() as u64;
+        if self.latency_histogram.load(Relaxed) {
+            self.latency_us.lock().add_sample(latency_us);
+        }
+        macro_rules! trace_io {
+            ($level:expr) => {
+                tracing::event!(
+                    $level,
+                    %path,
+                    ?op,
+                    scsi_status = ?result.scsi_status,
+                    srb_status = ?result.srb_status,
+                    tx = result.tx,
+                    latency_us,
+                    "scsi io completed"
+                )
+            };
+        }
+        match self.trace_level() {
+            IoTraceLevel::Off => {}
+            IoTraceLevel::Info => trace_io!(tracing::Level::INFO),
+            IoTraceLevel::Debug => trace_io!(tracing::Level::DEBUG),
+            IoTraceLevel::Trace => trace_io!(tracing::Level::TRACE),
+        }
+    }
+}
+
+impl Inspect for IoTelemetry {
+    fn inspect(&self, req: inspect::Request<'_>) {
+        req.respond()
+            .field("latency_histogram", &self.latency_histogram)
+            .field("trace_level", format!("{:?}", self.trace_level()))
+            .field("latency_us", &*self.latency_us.lock());
     }
 }
 
@@ -1669,6 +1782,22 @@ pub fn attach(&self, path: ScsiPath, disk: ScsiControllerDisk) -> Result<(), Scs
         Ok(())
     }
 
+    /// Changes the telemetry settings of the disk at `path`.
+    pub fn set_telemetry(
+        &self,
+        path: ScsiPath,
+        telemetry: DiskTelemetry,
+    ) -> Result<(), ScsiPathNotInUse> {
+        self.state
+            .disks
+            .read()
+            .get(&path)
+            .ok_or(ScsiPathNotInUse(path))?
+            .telemetry
+            .set(telemetry);
+        Ok(())
+    }
+
     pub fn remove(&self, path: ScsiPath) -> Result<(), ScsiPathNotInUse> {
         match self.state.disks.write().entry(path) {
             Entry::Vacant(_) => return Err(ScsiPathNotInUse(path)),
@@ -1720,6 +1849,7 @@ fn offer(&self) -> OfferParams {
                 instance_id: self.instance_id,
                 interface_id: storvsp_protocol::IDE_ACCELERATOR_INTERFACE_ID,
                 channel_type: ChannelType::Interface { user_defined },
+                mnf_interrupt_latency: Some(MNF_INTERRUPT_LATENCY),
                 ..Default::default()
             }
         } else {
@@ -1727,6 +1857,7 @@ fn offer(&self) -> OfferParams {
                 interface_name: "scsi".to_owned(),
                 instance_id: self.instance_id,
                 interface_id: storvsp_protocol::SCSI_INTERFACE_ID,
+                mnf_interrupt_latency: Some(MNF_INTERRUPT_LATENCY),
                 ..Default::default()
             }
         }
@@ -1826,6 +1957,7 @@ mod tests {
     use scsi::srb::SrbStatus;
     use test_with_tracing::test;
     use vmbus_channel::connected_async_channels;
+    use vmcore::vm_task::SingleDriverBackend;
 
     // Discourage `Clone` for `ScsiController` outside the crate, but it is
     // necessary for testing. The fuzzer also uses `TestWorker`, which needs
@@ -1907,6 +2039,110 @@ async fn test_channel_working(driver: DefaultDriver) {
         guest.verify_graceful_close(test_worker).await;
     }
 
+    #[async_test]
+    async fn test_disk_telemetry(driver: DefaultDriver) {
+        let (host, guest) = connected_async_channels(16 * 1024);
+        let guest_queue = Queue::new(guest).unwrap();
+
+        let test_guest_mem = GuestMemory::allocate(16384);
+        let controller = ScsiController::new();
+        let disk = scsidisk::SimpleScsiDisk::new(
+            disklayer_ram::ram_disk(10 * 1024 * 1024, false).unwrap(),
+            Default::default(),
+        );
+        controller
+            .attach(ScsiPath::default(), ScsiControllerDisk::new(Arc::new(disk)))
+            .unwrap();
+
+        let telemetry = DiskTelemetry {
+            latency_histogram: true,
+            trace_level: IoTraceLevel::Info,
+        };
+        let missing = ScsiPath {
+            lun: 1,
+            ..Default::default()
+        };
+        controller.set_telemetry(missing, telemetry).unwrap_err();
+        controller
+            .set_telemetry(ScsiPath::default(), telemetry)
+            .unwrap();
+
+        let test_worker = TestWorker::start(
+            controller.clone(),
+            driver.clone(),
+            test_guest_mem.clone(),
+            host,
+            None,
+        );
+
+        let mut guest = test_helpers::TestGuest {
+            queue: guest_queue,
+            transaction_id: 0,
+        };
+
+        guest.perform_protocol_negotiation().await;
+
+        const IO_LEN: usize = 4 * 1024;
+        let gpa = 4 * 1024u64;
+        guest
+            .send_write_packet(ScsiPath::default(), gpa, 1, IO_LEN)
+            .await;
+        guest
+            .verify_completion(|p| test_helpers::parse_guest_completed_io(p, SrbStatus::SUCCESS))
+            .await;
+        guest
+            .send_read_packet(ScsiPath::default(), gpa, 1, IO_LEN)
+            .await;
+        guest
+            .verify_completion(|p| test_helpers::parse_guest_completed_io(p, SrbStatus::SUCCESS))
+            .await;
+
+        let inspect_telemetry = |path: &str| {
+            let disks = controller.state.disks.read();
+            let telemetry = &disks[&ScsiPath::default()].telemetry;
+            let mut inspection = inspect::inspect(path, &**telemetry);
+            futures::executor::block_on(inspection.resolve());
+            inspection.results()
+        };
+        let latency_samples = || {
+            let inspect::Node::Dir(buckets) = inspect_telemetry("latency_us") else {
+                panic!("latency histogram missing");
+            };
+            buckets
+                .iter()
+                .map(|bucket| match &bucket.node {
+                    inspect::Node::Value(inspect::Value {
+                        kind: inspect::ValueKind::Unsigned(n),
+                        ..
+                    }) => *n,
+                    node => panic!("unexpected bucket {node:?}"),
+                })
+                .sum::<u64>()
+        };
+        let trace_level = || match inspect_telemetry("trace_level") {
+            inspect::Node::Value(v) => v.kind,
+            node => panic!("unexpected trace level {node:?}"),
+        };
+
+        assert_eq!(latency_samples(), 2);
+        assert_eq!(trace_level(), "Info".into());
+
+        // Turning telemetry off stops recording without detaching the disk.
+        controller
+            .set_telemetry(ScsiPath::default(), DiskTelemetry::default())
+            .unwrap();
+        guest
+            .send_read_packet(ScsiPath::default(), gpa, 1, IO_LEN)
+            .await;
+        guest
+            .verify_completion(|p| test_helpers::parse_guest_completed_io(p, SrbStatus::SUCCESS))
+            .await;
+        assert_eq!(latency_samples(), 2);
+        assert_eq!(trace_level(), "Off".into());
+
+        guest.verify_graceful_close(test_worker).await;
+    }
+
     #[async_test]
     async fn test_packet_sizes(driver: DefaultDriver) {
         // set up the channels and worker
@@ -2069,6 +2305,107 @@ async fn test_unrecognized_operation(driver: DefaultDriver) {
         }
     }
 
+    #[async_test]
+    async fn test_version_negotiation_matrix(driver: DefaultDriver) {
+        let guest_versions = [
+            storvsp_protocol::VERSION_WIN6,
+            storvsp_protocol::VERSION_WIN7,
+            storvsp_protocol::VERSION_WIN8,
+            storvsp_protocol::VERSION_BLUE,
+            storvsp_protocol::VERSION_THRESHOLD,
+        ];
+        for max_version in [Version::Win6, Version::Win7, Version::Win8, Version::Blue] {
+            for guest_version in guest_versions {
+                let (host, guest) = connected_async_channels(16384);
+                let guest_queue = Queue::new(guest).unwrap();
+                let worker = TestWorker::start_with_max_version(
+                    ScsiController::new(),
+                    driver.clone(),
+                    GuestMemory::allocate(1024),
+                    host,
+                    None,
+                    max_version,
+                );
+                let mut guest = test_helpers::TestGuest {
+                    queue: guest_queue,
+                    transaction_id: 0,
+                };
+
+                let negotiate_packet = storvsp_protocol::Packet {
+                    operation: storvsp_protocol::Operation::BEGIN_INITIALIZATION,
+                    flags: 0,
+                    status: storvsp_protocol::NtStatus::SUCCESS,
+                };
+                guest
+                    .send_data_packet_sync(&[negotiate_packet.as_bytes()])
+                    .await;
+                guest.verify_completion(parse_guest_completion).await;
+
+                let version_packet = storvsp_protocol::Packet {
+                    operation: storvsp_protocol::Operation::QUERY_PROTOCOL_VERSION,
+                    flags: 0,
+                    status: storvsp_protocol::NtStatus::SUCCESS,
+                };
+                let version = storvsp_protocol::ProtocolVersion {
+                    major_minor: guest_version,
+                    reserved: 0,
+                };
+                guest
+                    .send_data_packet_sync(&[version_packet.as_bytes(), version.as_bytes()])
+                    .await;
+
+                let negotiated = Version::parse(guest_version)
+                    .ok()
+                    .filter(|&v| v <= max_version);
+                let expected_status = if negotiated.is_some() {
+                    storvsp_protocol::NtStatus::SUCCESS
+                } else {
+                    storvsp_protocol::NtStatus::REVISION_MISMATCH
+                };
+                guest
+                    .verify_completion(|p| {
+                        parse_guest_completion_check_flags_status(p, 0, expected_status)
+                    })
+                    .await;
+
+                if let Some(negotiated) = negotiated {
+                    let properties_packet = storvsp_protocol::Packet {
+                        operation: storvsp_protocol::Operation::QUERY_PROPERTIES,
+                        flags: 0,
+                        status: storvsp_protocol::NtStatus::SUCCESS,
+                    };
+                    guest
+                        .send_data_packet_sync(&[properties_packet.as_bytes()])
+                        .await;
+                    guest
+                        .verify_completion(|packet| {
+                            let IncomingPacket::Completion(packet) = packet else {
+                                unreachable!()
+                            };
+                            let mut reader = packet.reader();
+                            let header = reader.read_plain::<storvsp_protocol::Packet>().unwrap();
+                            assert_eq!(header.status, storvsp_protocol::NtStatus::SUCCESS);
+                            let properties = reader
+                                .read_plain::<storvsp_protocol::ChannelProperties>()
+                                .unwrap();
+                            // Multi-channel support is only offered from Win8.
+                            assert_eq!(
+                                properties.flags
+                                    & storvsp_protocol::STORAGE_CHANNEL_SUPPORTS_MULTI_CHANNEL
+                                    != 0,
+                                negotiated >= Version::Win8,
+                                "guest {guest_version:#x}, max {max_version:?}"
+                            );
+                            Ok(())
+                        })
+                        .await;
+                }
+
+                guest.verify_graceful_close(worker).await;
+            }
+        }
+    }
+
     #[async_test]
     async fn test_too_many_subchannels(driver: DefaultDriver) {
         // set up the channels and worker
@@ -2468,4 +2805,37 @@ pub async fn test_async_disk(driver: DefaultDriver) {
 
         guest.verify_graceful_close(worker).await;
     }
+
+    #[async_test]
+    async fn test_offer_requests_mnf(driver: DefaultDriver) {
+        let driver_source = VmTaskDriverSource::new(SingleDriverBackend::new(driver));
+
+        let scsi = StorageDevice::build_scsi(
+            &driver_source,
+            &ScsiController::new(),
+            Guid::new_random(),
+            0,
+            256,
+            None,
+        );
+        assert_eq!(
+            scsi.offer().mnf_interrupt_latency,
+            Some(MNF_INTERRUPT_LATENCY)
+        );
+
+        let ide = StorageDevice::build_ide(
+            &driver_source,
+            0,
+            0,
+            ScsiControllerDisk::new(Arc::new(scsidisk::SimpleScsiDisk::new(
+                disklayer_ram::ram_disk(1024 * 1024, false).unwrap(),
+                Default::default(),
+            ))),
+            256,
+        );
+        assert_eq!(
+            ide.offer().mnf_interrupt_latency,
+            Some(MNF_INTERRUPT_LATENCY)
+        );
+    }
 }
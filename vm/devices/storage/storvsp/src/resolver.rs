@@ -69,6 +69,7 @@ async fn resolve(
             resource.instance_id,
             resource.max_sub_channel_count,
             resource.io_queue_depth.unwrap_or(256),
+            resource.max_protocol_version,
         );
 
         for ScsiDeviceAndPath { path, device } in resource.devices {
@@ -143,6 +144,16 @@ async fn handle_requests(
                 }
                 anyhow::Ok(())
             }),
+            ScsiControllerRequest::SetTelemetry(rpc) => {
+                rpc.handle_failable_sync(|(path, telemetry)| {
+                    if let Some(state) = state.upgrade() {
+                        ScsiController { state }
+                            .set_telemetry(path, telemetry)
+                            .context("failed to set device telemetry")?;
+                    }
+                    anyhow::Ok(())
+                })
+            }
         }
     }
 }
@@ -14,6 +14,7 @@
 use crate::ProtocolState;
 use crate::ScsiController;
 use crate::ScsiPath;
+use crate::Version;
 use crate::Worker;
 use crate::WorkerError;
 use guestmem::GuestMemory;
@@ -73,6 +74,24 @@ pub fn start<T: ring::RingMem + 'static + Sync>(
         mem: GuestMemory,
         channel: RawAsyncChannel<T>,
         io_queue_depth: Option<u32>,
+    ) -> Self {
+        Self::start_with_max_version(
+            controller,
+            spawner,
+            mem,
+            channel,
+            io_queue_depth,
+            Version::Blue,
+        )
+    }
+
+    pub(crate) fn start_with_max_version<T: ring::RingMem + 'static + Sync>(
+        controller: ScsiController,
+        spawner: impl Spawn,
+        mem: GuestMemory,
+        channel: RawAsyncChannel<T>,
+        io_queue_depth: Option<u32>,
+        max_version: Version,
     ) -> Self {
         let task = spawner.spawn("test", async move {
             let mut worker = Worker::new(
@@ -85,6 +104,7 @@ pub fn start<T: ring::RingMem + 'static + Sync>(
                 Arc::new(Protocol {
                     state: RwLock::new(ProtocolState::Init(InitState::Begin)),
                     ready: Default::default(),
+                    max_version,
                 }),
                 None,
             )
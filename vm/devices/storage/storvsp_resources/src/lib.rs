@@ -5,10 +5,11 @@
 //!
 //! [`ScsiControllerHandle`] configures the controller with its initial devices,
 //! instance ID, and queue depth. [`ScsiControllerRequest`] enables runtime
-//! device add/remove.
+//! device add/remove and telemetry changes.
 
 #![forbid(unsafe_code)]
 
+use disk_backend_resources::DiskTelemetry;
 use guid::Guid;
 use mesh::MeshPayload;
 use mesh::payload::Protobuf;
@@ -58,6 +59,24 @@ pub struct ScsiControllerHandle {
     /// Higher numbers mean that there must be _more_ IOs outstanding to backing storage devices before storvsp
     /// decides to keep interrupts masked.
     pub poll_mode_queue_depth: Option<u32>,
+    /// The newest protocol version to negotiate with the guest, to emulate an
+    /// older host. `None` allows all supported versions.
+    pub max_protocol_version: Option<ScsiProtocolVersion>,
+}
+
+/// A storvsp protocol version, named after the Windows release that
+/// introduced it.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, MeshPayload)]
+pub enum ScsiProtocolVersion {
+    /// Version 2.0. No multi-channel support, and the original (smaller)
+    /// SCSI request size.
+    Win6,
+    /// Version 4.2. Adds bus rescan notifications.
+    Win7,
+    /// Version 5.1. Adds sub-channels and the larger SCSI request size.
+    Win8,
+    /// Version 6.0.
+    Blue,
 }
 
 impl ResourceId<VmbusDeviceHandleKind> for ScsiControllerHandle {
@@ -80,4 +99,6 @@ pub enum ScsiControllerRequest {
     AddDevice(FailableRpc<ScsiDeviceAndPath, ()>),
     /// Remove a device.
     RemoveDevice(FailableRpc<ScsiPath, ()>),
+    /// Change the telemetry settings of a device.
+    SetTelemetry(FailableRpc<(ScsiPath, DiskTelemetry), ()>),
 }
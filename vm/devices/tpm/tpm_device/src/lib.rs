@@ -25,6 +25,8 @@
 use tpm_lib::TpmRsa2kPublic;
 
 use self::io_port_interface::PpiOperation;
+use self::io_port_interface::PpiSetOperationResult;
+use self::io_port_interface::PpiUserConfirmation;
 use self::io_port_interface::TpmIoCommand;
 use crate::ak_cert::TpmAkCertType;
 use base64::Engine;
@@ -822,7 +824,13 @@ fn hyperv_port_read(&mut self, data: &mut [u8]) -> IoResult {
                 TpmIoCommand::PPI_GET_LAST_OPERATION => self.ppi_state.last_ppi_operation.0,
                 TpmIoCommand::PPI_GET_LAST_RESULT => self.ppi_state.last_ppi_state,
                 TpmIoCommand::PPI_SET_OPERATION => self.ppi_state.set_ppi_operation_state,
-                TpmIoCommand::PPI_GET_USER_CONFIRMATION => 4,
+                TpmIoCommand::PPI_GET_USER_CONFIRMATION => {
+                    if self.ppi_state.in_query_ppi_operation.is_supported() {
+                        PpiUserConfirmation::ALLOWED_USER_NOT_REQUIRED.0
+                    } else {
+                        PpiUserConfirmation::NOT_IMPLEMENTED.0
+                    }
+                }
                 TpmIoCommand::GET_TCG_PROTOCOL_VERSION => {
                     io_port_interface::TcgProtocol::Tcg2 as u32
                 }
@@ -886,8 +894,19 @@ fn hyperv_port_write(&mut self, control_port: bool, data: &[u8]) -> IoResult {
                     self.ppi_state.ppi_set_operation_arg3_integer2 = val;
                 }
                 TpmIoCommand::PPI_SET_OPERATION => {
-                    self.ppi_state.pending_ppi_operation = PpiOperation(val);
-                    self.ppi_state.set_ppi_operation_state = 0;
+                    let operation = PpiOperation(val);
+                    self.ppi_state.set_ppi_operation_state = if operation.is_supported() {
+                        self.ppi_state.pending_ppi_operation = operation;
+                        PpiSetOperationResult::SUCCESS.0
+                    } else {
+                        // Leave any previously requested operation pending.
+                        tracelimit::warn_ratelimited!(
+                            CVM_ALLOWED,
+                            ?operation,
+                            "unsupported PPI operation requested"
+                        );
+                        PpiSetOperationResult::NOT_IMPLEMENTED.0
+                    };
                 }
                 TpmIoCommand::PPI_GET_USER_CONFIRMATION => {
                     self.ppi_state.in_query_ppi_operation = PpiOperation(val);
@@ -946,6 +965,9 @@ fn execute_pending_ppi(&mut self) -> Result<(), TpmError> {
                 self.ppi_state.tpm_capability_hash_alg_bitmap,
                 self.ppi_state.ppi_set_operation_arg3_integer2,
             )?,
+            // The TPM is always enabled and activated, so there is nothing to
+            // do.
+            PpiOperation::ENABLE | PpiOperation::ACTIVATE | PpiOperation::ENABLE_ACTIVATE => 0,
             other => {
                 tracelimit::warn_ratelimited!(CVM_ALLOWED, ?other, "unknown pending PPI operation");
                 0
@@ -1663,6 +1685,48 @@ pub enum PpiOperation: u32 {
             SET_PCR_BANKS = 23,
         }
     }
+
+    impl PpiOperation {
+        /// Returns whether the operation can be requested by the guest and
+        /// executed on the next boot.
+        pub fn is_supported(self) -> bool {
+            matches!(
+                self,
+                Self::NO_OP
+                    | Self::ENABLE
+                    | Self::ACTIVATE
+                    | Self::CLEAR
+                    | Self::ENABLE_ACTIVATE
+                    | Self::CLEAR_ENABLE_ACTIVATE
+                    | Self::ENABLE_ACTIVATE_CLEAR
+                    | Self::ENABLE_ACTIVATE_CLEAR_ENABLE_ACTIVATE
+                    | Self::SET_PCR_BANKS
+            )
+        }
+    }
+
+    open_enum::open_enum! {
+        /// Return values for Get User Confirmation Status for Operation (PPI
+        /// function 8).
+        pub enum PpiUserConfirmation: u32 {
+            NOT_IMPLEMENTED = 0,
+            BIOS_ONLY = 1,
+            BLOCKED = 2,
+            ALLOWED_USER_REQUIRED = 3,
+            ALLOWED_USER_NOT_REQUIRED = 4,
+        }
+    }
+
+    open_enum::open_enum! {
+        /// Return values for Submit TPM Operation Request to Pre-OS
+        /// Environment (PPI function 7).
+        pub enum PpiSetOperationResult: u32 {
+            SUCCESS = 0,
+            NOT_IMPLEMENTED = 1,
+            GENERAL_FAILURE = 2,
+            BLOCKED = 3,
+        }
+    }
 }
 
 mod persist_restore {
@@ -2100,4 +2164,101 @@ async fn test_fix_corrupted_vmgs() {
             .expect("find_nv_index should succeed")
             .expect("mitigation marker NV index present");
     }
+
+    fn ppi_write(tpm: &mut Tpm, command: TpmIoCommand, val: u32) {
+        tpm.hyperv_port_write(true, &command.0.to_le_bytes())
+            .unwrap();
+        tpm.hyperv_port_write(false, &val.to_le_bytes()).unwrap();
+    }
+
+    fn ppi_read(tpm: &mut Tpm, command: TpmIoCommand) -> u32 {
+        tpm.hyperv_port_write(true, &command.0.to_le_bytes())
+            .unwrap();
+        let mut data = [0; 4];
+        tpm.hyperv_port_read(&mut data).unwrap();
+        u32::from_le_bytes(data)
+    }
+
+    #[async_test]
+    async fn test_ppi_operations() {
+        let ppi_store = EphemeralNonVolatileStore::new_boxed();
+        let store = EphemeralNonVolatileStore::new_boxed();
+        let gm = GuestMemory::allocate(0x10000);
+        let monotonic_timer = Box::new(|| std::time::Duration::new(0, 0));
+
+        let mut tpm = Tpm::new(
+            TpmRegisterLayout::IoPort,
+            gm,
+            ppi_store,
+            store,
+            None,
+            monotonic_timer,
+            false,
+            false,
+            TpmAkCertType::None,
+            None,
+            None,
+            false,
+            guid::guid!("00000000-0000-0000-0000-000000000000"),
+        )
+        .await
+        .unwrap();
+
+        // Supported operations don't require user confirmation.
+        ppi_write(
+            &mut tpm,
+            TpmIoCommand::PPI_GET_USER_CONFIRMATION,
+            PpiOperation::CLEAR.0,
+        );
+        assert_eq!(
+            ppi_read(&mut tpm, TpmIoCommand::PPI_GET_USER_CONFIRMATION),
+            PpiUserConfirmation::ALLOWED_USER_NOT_REQUIRED.0
+        );
+        ppi_write(
+            &mut tpm,
+            TpmIoCommand::PPI_GET_USER_CONFIRMATION,
+            PpiOperation::DISABLE.0,
+        );
+        assert_eq!(
+            ppi_read(&mut tpm, TpmIoCommand::PPI_GET_USER_CONFIRMATION),
+            PpiUserConfirmation::NOT_IMPLEMENTED.0
+        );
+
+        // A supported operation becomes pending.
+        ppi_write(
+            &mut tpm,
+            TpmIoCommand::PPI_SET_OPERATION,
+            PpiOperation::CLEAR.0,
+        );
+        assert_eq!(
+            ppi_read(&mut tpm, TpmIoCommand::PPI_SET_OPERATION),
+            PpiSetOperationResult::SUCCESS.0
+        );
+        assert_eq!(
+            ppi_read(&mut tpm, TpmIoCommand::PPI_GET_PENDING_OPERATION),
+            PpiOperation::CLEAR.0
+        );
+
+        // An unsupported operation is rejected without replacing it.
+        ppi_write(
+            &mut tpm,
+            TpmIoCommand::PPI_SET_OPERATION,
+            PpiOperation::DISABLE.0,
+        );
+        assert_eq!(
+            ppi_read(&mut tpm, TpmIoCommand::PPI_SET_OPERATION),
+            PpiSetOperationResult::NOT_IMPLEMENTED.0
+        );
+        assert_eq!(
+            ppi_read(&mut tpm, TpmIoCommand::PPI_GET_PENDING_OPERATION),
+            PpiOperation::CLEAR.0
+        );
+
+        // The pending operation is persisted for the next boot.
+        let persisted = persist_restore::deserialize_ppi_state(
+            tpm.rt.ppi_store.restore().await.unwrap().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(persisted.pending_ppi_operation, PpiOperation::CLEAR);
+    }
 }
@@ -2,8 +2,8 @@
 // Licensed under the MIT License.
 
 //! Command-line utility for interacting with a physical TPM during guest attestation tests.
-//! Supports reading the AK certificate NV index and producing attestation reports with
-//! optional user-provided payloads.
+//! Supports reading the AK certificate NV index, producing attestation reports with
+//! optional user-provided payloads, and quoting PCRs with the AK.
 
 mod report;
 mod tpm;
@@ -23,7 +23,10 @@
 
 use tpm_lib::TpmEngine;
 use tpm_lib::TpmEngineHelper;
+use tpm_protocol::TPM_AZURE_AIK_HANDLE;
+use tpm_protocol::tpm20proto::AlgIdEnum;
 use tpm_protocol::tpm20proto::TPM20_RH_OWNER;
+use tpm_protocol::tpm20proto::protocol::PcrSelection;
 
 use report::IGVM_ATTEST_REQUEST_VERSION_1;
 use report::IGVM_ATTESTATION_SIGNATURE;
@@ -51,9 +54,11 @@ struct Config {
     ak_cert: bool,
     ak_cert_expected: Option<Vec<u8>>,
     ak_cert_retry_attempts: u32,
+    ak_cert_show_hex: bool,
     report: bool,
     user_data: Option<Vec<u8>>,
     show_runtime_claims: bool,
+    quote_nonce: Option<Vec<u8>>,
 }
 
 #[derive(Parser, Debug)]
@@ -77,6 +82,9 @@ enum Command {
     /// Write guest input and read the attestation report
     #[command(name = "report")]
     Report(ReportArgs),
+    /// Print the AK public key and quote PCRs with the AK
+    #[command(name = "quote")]
+    Quote(QuoteArgs),
 }
 
 #[derive(Args, Debug, Default)]
@@ -89,9 +97,14 @@ struct AkCertArgs {
     #[arg(long, value_name = "HEX", conflicts_with = "expected_data")]
     expected_data_hex: Option<String>,
 
-    /// Retry AK certificate comparison up to COUNT times
+    /// Retry up to COUNT times until the AK certificate matches the expected
+    /// contents or, without expected contents, until it is present
     #[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u32).range(1..))]
     retry: Option<u32>,
+
+    /// Print the full AK certificate contents as hex
+    #[arg(long)]
+    show_hex: bool,
 }
 
 #[derive(Args, Debug, Default)]
@@ -109,6 +122,13 @@ struct ReportArgs {
     show_runtime_claims: bool,
 }
 
+#[derive(Args, Debug, Default)]
+struct QuoteArgs {
+    /// Nonce to include in the quote (hex)
+    #[arg(long, value_name = "HEX")]
+    nonce_hex: String,
+}
+
 fn main() {
     let cli = Cli::parse();
     let config = match config_from_cli(cli) {
@@ -141,6 +161,7 @@ fn run(config: &Config) -> Result<(), Box<dyn Error>> {
             &mut helper,
             config.ak_cert_expected.as_deref(),
             config.ak_cert_retry_attempts,
+            config.ak_cert_show_hex,
         )?;
     }
 
@@ -152,6 +173,10 @@ fn run(config: &Config) -> Result<(), Box<dyn Error>> {
         }
     }
 
+    if let Some(nonce) = &config.quote_nonce {
+        handle_quote(&mut helper, nonce)?;
+    }
+
     Ok(())
 }
 
@@ -159,11 +184,12 @@ fn handle_ak_cert<E: TpmEngine>(
     helper: &mut TpmEngineHelper<E>,
     expected: Option<&[u8]>,
     retry_attempts: u32,
+    show_hex: bool,
 ) -> Result<(), Box<dyn Error>> {
     for attempt in 0..=retry_attempts {
         if attempt > 0 {
             println!(
-                "AK certificate mismatch; retrying after {} ms ({}/{})…",
+                "AK certificate not ready; retrying after {} ms ({}/{})…",
                 AK_CERT_RETRY_DELAY_MS, attempt, retry_attempts
             );
             thread::sleep(Duration::from_millis(AK_CERT_RETRY_DELAY_MS));
@@ -198,14 +224,22 @@ fn handle_ak_cert<E: TpmEngine>(
                     "AK certificate matches expected value ({} bytes).",
                     data.len()
                 );
+                if show_hex {
+                    println!("AK certificate hex: {}", to_hex(&data));
+                }
                 return Ok(());
             }
 
             if attempt == retry_attempts {
                 return Err("AK certificate contents did not match expected value".into());
             }
-        } else {
+        } else if data.iter().any(|&b| b != 0) {
+            if show_hex {
+                println!("AK certificate hex: {}", to_hex(&data));
+            }
             return Ok(());
+        } else if attempt == retry_attempts {
+            return Err("AK certificate is empty".into());
         }
     }
 
@@ -230,11 +264,10 @@ fn config_from_cli(cli: Cli) -> Result<Config, String> {
             }
 
             if let Some(retry) = args.retry {
-                if config.ak_cert_expected.is_none() {
-                    return Err("--retry requires expected AK certificate data".into());
-                }
                 config.ak_cert_retry_attempts = retry;
             }
+
+            config.ak_cert_show_hex = args.show_hex;
         }
         Command::Report(args) => {
             config.report = true;
@@ -249,6 +282,11 @@ fn config_from_cli(cli: Cli) -> Result<Config, String> {
                 config.user_data = Some(bytes);
             }
         }
+        Command::Quote(args) => {
+            let bytes =
+                parse_hex_bytes(&args.nonce_hex).map_err(|e| format!("--nonce-hex: {e}"))?;
+            config.quote_nonce = Some(bytes);
+        }
     }
 
     Ok(config)
@@ -286,6 +324,44 @@ fn handle_report<E: TpmEngine>(
     Ok(att_report)
 }
 
+/// Returns the PCRs included in a quote: PCRs 0-7 of the SHA-256 bank.
+fn quote_pcr_selection() -> PcrSelection {
+    PcrSelection {
+        hash: AlgIdEnum::SHA256.into(),
+        size_of_select: 3,
+        bitmap: [0xff, 0x00, 0x00],
+    }
+}
+
+fn handle_quote<E: TpmEngine>(
+    helper: &mut TpmEngineHelper<E>,
+    nonce: &[u8],
+) -> Result<(), Box<dyn Error>> {
+    let ak_pub = helper.read_public(TPM_AZURE_AIK_HANDLE)?;
+    let public_area = &ak_pub.out_public.public_area;
+    let modulus = &public_area.unique.buffer[..public_area.unique.size.get() as usize];
+    // An exponent of zero encodes the default exponent, 2^16 + 1.
+    let exponent = match public_area.parameters.exponent.get() {
+        0 => 0x10001,
+        exponent => exponent,
+    };
+    println!("AK public modulus: {}", to_hex(modulus));
+    println!("AK public exponent: {}", to_hex(&exponent.to_be_bytes()));
+
+    println!(
+        "Quoting PCRs with the AK at handle {:#x}…",
+        TPM_AZURE_AIK_HANDLE.0.get()
+    );
+    let reply = helper.quote(TPM_AZURE_AIK_HANDLE, nonce, &[quote_pcr_selection()])?;
+
+    let quoted = &reply.quoted.buffer[..reply.quoted.size.get() as usize];
+    let signature = &reply.signature.buffer[..reply.signature.size.get() as usize];
+    println!("Quote attest: {}", to_hex(quoted));
+    println!("Quote signature: {}", to_hex(signature));
+
+    Ok(())
+}
+
 fn print_runtime_claims(attestation_report: &[u8]) -> Result<(), Box<dyn Error>> {
     match runtime_claims_json(attestation_report)? {
         Some(json) => {
@@ -544,6 +620,18 @@ fn parse_hex_bytes(value: &str) -> Result<Vec<u8>, String> {
     Ok(bytes)
 }
 
+fn to_hex(data: &[u8]) -> String {
+    use std::fmt::Write as _;
+
+    let mut hex = String::with_capacity(data.len() * 2 + 2);
+    hex.push_str("0x");
+    for byte in data {
+        write!(&mut hex, "{byte:02x}").expect("write! to String should not fail");
+    }
+
+    hex
+}
+
 fn print_nv_summary(label: &str, data: &[u8]) {
     println!("{label}");
     if data.is_empty() {
@@ -669,11 +757,23 @@ fn cli_requires_action() {
     }
 
     #[test]
-    fn cli_retry_requires_expected_data() {
-        let cli =
-            Cli::try_parse_from(["tpm_guest_tests", "ak_cert", "--retry", "2"]).expect("parse CLI");
-        let err = config_from_cli(cli).expect_err("retry should require expected data");
-        assert!(err.contains("--retry"));
+    fn cli_retry_without_expected_data() {
+        let cli = Cli::try_parse_from(["tpm_guest_tests", "ak_cert", "--retry", "2", "--show-hex"])
+            .expect("parse CLI");
+        let config = config_from_cli(cli).expect("build config");
+        assert!(config.ak_cert_expected.is_none());
+        assert_eq!(config.ak_cert_retry_attempts, 2);
+        assert!(config.ak_cert_show_hex);
+    }
+
+    #[test]
+    fn cli_quote_requires_nonce() {
+        assert!(Cli::try_parse_from(["tpm_guest_tests", "quote"]).is_err());
+
+        let cli = Cli::try_parse_from(["tpm_guest_tests", "quote", "--nonce-hex", "0x0102"])
+            .expect("parse CLI");
+        let config = config_from_cli(cli).expect("build config");
+        assert_eq!(config.quote_nonce.as_deref(), Some(&[1u8, 2][..]));
     }
 
     #[test]
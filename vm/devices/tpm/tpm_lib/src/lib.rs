@@ -41,6 +41,7 @@
 use tpm_protocol::tpm20proto::protocol::LoadReply;
 use tpm_protocol::tpm20proto::protocol::NvReadPublicReply;
 use tpm_protocol::tpm20proto::protocol::PcrSelection;
+use tpm_protocol::tpm20proto::protocol::QuoteReply;
 use tpm_protocol::tpm20proto::protocol::ReadPublicReply;
 use tpm_protocol::tpm20proto::protocol::StartupType;
 use tpm_protocol::tpm20proto::protocol::Tpm2bBuffer;
@@ -1559,6 +1560,47 @@ pub fn read_public(
         }
     }
 
+    /// Helper function to send Quote command.
+    ///
+    /// # Arguments
+    /// * `sign_handle` - The handle of the key that signs the quote.
+    /// * `qualifying_data` - Caller-provided data, such as a nonce, that is
+    ///   included in the signed attestation structure.
+    /// * `pcr_selections` - The PCRs to quote.
+    ///
+    /// Returns Ok(QuoteReply) if the command succeeds. Returns
+    /// Err(TpmCommandError) otherwise.
+    pub fn quote(
+        &mut self,
+        sign_handle: ReservedHandle,
+        qualifying_data: &[u8],
+        pcr_selections: &[PcrSelection],
+    ) -> Result<QuoteReply, TpmCommandError> {
+        use tpm20proto::protocol::QuoteCmd;
+
+        let session_tag = SessionTagEnum::Sessions;
+        let cmd = QuoteCmd::new(
+            session_tag.into(),
+            sign_handle,
+            CmdAuth::new(TPM20_RS_PW, 0, 0, 0),
+            qualifying_data,
+            pcr_selections,
+        )
+        .map_err(TpmCommandError::TpmCommandCreationFailed)?;
+
+        self.tpm_engine
+            .execute_command(&mut cmd.serialize(), &mut self.reply_buffer)
+            .map_err(TpmCommandError::TpmExecuteCommand)?;
+
+        match QuoteCmd::base_validate_reply(&self.reply_buffer, session_tag) {
+            Err(error) => Err(TpmCommandError::InvalidResponse(error))?,
+            Ok((res, false)) => Err(TpmCommandError::TpmCommandFailed {
+                response_code: res.header.response_code.get(),
+            })?,
+            Ok((res, true)) => Ok(res),
+        }
+    }
+
     /// Helper function to send FlushContext command.
     ///
     /// # Arguments
@@ -3412,6 +3454,47 @@ fn test_read_public() {
         }
     }
 
+    #[test]
+    fn test_quote() {
+        const TPM_GENERATED_VALUE: [u8; 4] = [0xff, 0x54, 0x43, 0x47];
+        const TPM_ST_ATTEST_QUOTE: [u8; 2] = [0x80, 0x18];
+
+        let mut tpm_engine_helper = create_tpm_engine_helper();
+        restart_tpm_engine(&mut tpm_engine_helper, false, true);
+
+        let result = tpm_engine_helper.create_ak_pub(false);
+        assert!(result.is_ok());
+
+        let nonce = [0x5a; 32];
+        let pcr_selection = PcrSelection {
+            hash: AlgIdEnum::SHA256.into(),
+            size_of_select: 3,
+            bitmap: [0xff, 0x00, 0x00],
+        };
+
+        // Positive test
+        let result = tpm_engine_helper.quote(TPM_AZURE_AIK_HANDLE, &nonce, &[pcr_selection]);
+        assert!(result.is_ok());
+        let response = result.unwrap();
+        assert_eq!(response.hash, AlgId::from(AlgIdEnum::SHA256));
+        assert_eq!(response.signature.size.get() as usize, RSA_2K_MODULUS_SIZE);
+
+        // The `TPMS_ATTEST` starts with the magic and type, followed by the
+        // signer's name and then the nonce as `extraData`.
+        let quoted = &response.quoted.buffer[..response.quoted.size.get() as usize];
+        assert_eq!(quoted[..4], TPM_GENERATED_VALUE);
+        assert_eq!(quoted[4..6], TPM_ST_ATTEST_QUOTE);
+        let name_size = u16::from_be_bytes([quoted[6], quoted[7]]) as usize;
+        let extra_data = &quoted[8 + name_size..];
+        assert_eq!(extra_data[..2], (nonce.len() as u16).to_be_bytes());
+        assert_eq!(extra_data[2..2 + nonce.len()], nonce);
+
+        // Negative test
+        let invalid_sign_handle = ReservedHandle((TPM_AZURE_AIK_HANDLE.0.get() + 10).into()); // pick an unallocated handle
+        let result = tpm_engine_helper.quote(invalid_sign_handle, &nonce, &[pcr_selection]);
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_nv_define_space() {
         let nv_index = TPM_NV_INDEX_AIK_CERT;
@@ -59,6 +59,10 @@ pub enum TpmProtoError {
     NvDefineSpacePublicInfo(#[source] InvalidInput),
     #[error("input data to NvWriteCmd is invalid")]
     NvWriteData(#[source] InvalidInput),
+    #[error("input qualifying_data to QuoteCmd is invalid")]
+    QuoteQualifyingData(#[source] InvalidInput),
+    #[error("input PCR selection to QuoteCmd is invalid")]
+    QuotePcrSelection(#[source] InvalidInput),
 }
 
 /// Errors surfaced when validating TPM replies against expected invariants.
@@ -3761,6 +3765,217 @@ fn payload_size(&self) -> usize {
             size
         }
     }
+
+    // === Quote === //
+
+    /// Command payload for `TPM2_Quote`.
+    ///
+    /// The quote is always signed with the signing key's own scheme.
+    #[repr(C)]
+    #[derive(IntoBytes, Immutable, KnownLayout, FromBytes)]
+    pub struct QuoteCmd {
+        header: CmdHeader,
+        sign_handle: ReservedHandle,
+        // Authorization area
+        auth_size: u32_be,
+        auth: common::CmdAuth,
+        // Parameters
+        // `TPM2B_DATA`
+        qualifying_data: Tpm2bBuffer,
+        // `TPMT_SIG_SCHEME`
+        in_scheme: AlgId,
+        pcr_select: TpmlPcrSelection,
+    }
+
+    impl QuoteCmd {
+        /// Creates a quote command over the selected PCRs, signed by the key
+        /// at `sign_handle` and bound to `qualifying_data`.
+        pub fn new(
+            session: SessionTag,
+            sign_handle: ReservedHandle,
+            auth: common::CmdAuth,
+            qualifying_data: &[u8],
+            pcr_selections: &[PcrSelection],
+        ) -> Result<Self, TpmProtoError> {
+            let qualifying_data =
+                Tpm2bBuffer::new(qualifying_data).map_err(TpmProtoError::QuoteQualifyingData)?;
+            let pcr_select =
+                TpmlPcrSelection::new(pcr_selections).map_err(TpmProtoError::QuotePcrSelection)?;
+
+            let mut cmd = Self {
+                header: CmdHeader::new::<Self>(session, CommandCodeEnum::Quote.into()),
+                sign_handle,
+                auth_size: (size_of::<common::CmdAuth>() as u32).into(),
+                auth,
+                qualifying_data,
+                in_scheme: AlgIdEnum::NULL.into(),
+                pcr_select,
+            };
+
+            cmd.header.size = new_u32_be(cmd.payload_size() as u32);
+
+            Ok(cmd)
+        }
+
+        /// Serializes the command into TPM wire format.
+        pub fn serialize(&self) -> Vec<u8> {
+            let mut buffer = Vec::new();
+
+            buffer.extend_from_slice(self.header.as_bytes());
+            buffer.extend_from_slice(self.sign_handle.as_bytes());
+            buffer.extend_from_slice(self.auth_size.as_bytes());
+            buffer.extend_from_slice(self.auth.as_bytes());
+            buffer.extend_from_slice(&self.qualifying_data.serialize());
+            buffer.extend_from_slice(self.in_scheme.as_bytes());
+            buffer.extend_from_slice(&self.pcr_select.serialize());
+
+            buffer
+        }
+
+        /// Returns the number of bytes produced by [`Self::serialize`].
+        pub fn payload_size(&self) -> usize {
+            let mut payload_size = 0;
+
+            payload_size += size_of_val(&self.header);
+            payload_size += size_of_val(&self.sign_handle);
+            payload_size += size_of_val(&self.auth_size);
+            payload_size += size_of_val(&self.auth);
+            payload_size += self.qualifying_data.payload_size();
+            payload_size += size_of_val(&self.in_scheme);
+            payload_size += self.pcr_select.payload_size();
+
+            payload_size
+        }
+    }
+
+    /// Reply payload for `TPM2_Quote`.
+    ///
+    /// Only RSASSA signatures are supported.
+    #[repr(C)]
+    #[derive(Debug, FromBytes, IntoBytes, Immutable, KnownLayout)]
+    pub struct QuoteReply {
+        /// Standard TPM reply header and status.
+        pub header: ReplyHeader,
+        /// Size in bytes of the parameter area contained in the reply.
+        pub parameter_size: u32_be,
+        // Parameters
+        // `TPM2B_ATTEST`
+        /// The marshaled `TPMS_ATTEST` structure that was signed.
+        pub quoted: Tpm2bBuffer,
+        // `TPMT_SIGNATURE`
+        /// Signature algorithm.
+        pub sig_alg: AlgId,
+        /// Hash algorithm used for the signature.
+        pub hash: AlgId,
+        // `TPM2B_PUBLIC_KEY_RSA`
+        /// The signature over `quoted`.
+        pub signature: Tpm2bBuffer,
+        // Authorization area
+        /// Authorization data returned alongside the reply.
+        pub auth: common::ReplyAuth,
+    }
+
+    impl TpmCommand for QuoteCmd {
+        type Reply = QuoteReply;
+    }
+
+    impl TpmReply for QuoteReply {
+        type Command = QuoteCmd;
+
+        fn deserialize(bytes: &[u8]) -> Option<Self> {
+            let mut start = 0;
+            let mut end = size_of::<ReplyHeader>();
+            if bytes.len() < end {
+                return None;
+            }
+
+            let header = ReplyHeader::read_from_prefix(&bytes[start..end]).ok()?.0; // TODO: zerocopy: use-rest-of-range, option-to-error (https://github.com/microsoft/openvmm/issues/759)
+
+            // Handle the command failure.
+            if header.size.get() as usize == end {
+                return Some(Self {
+                    header,
+                    parameter_size: 0.into(),
+                    quoted: Tpm2bBuffer::new_zeroed(),
+                    sig_alg: AlgId::new_zeroed(),
+                    hash: AlgId::new_zeroed(),
+                    signature: Tpm2bBuffer::new_zeroed(),
+                    auth: common::ReplyAuth::new_zeroed(),
+                });
+            }
+
+            start = end;
+            end += size_of::<u32_be>();
+            if bytes.len() < end {
+                return None;
+            }
+            let parameter_size = u32_be::read_from_prefix(&bytes[start..end]).ok()?.0; // TODO: zerocopy: use-rest-of-range, option-to-error (https://github.com/microsoft/openvmm/issues/759)
+            let expected_auth_start = end + parameter_size.get() as usize;
+
+            start = end;
+            let quoted = Tpm2bBuffer::deserialize(&bytes[start..])?;
+            end += quoted.payload_size();
+
+            start = end;
+            end += size_of::<AlgId>();
+            if bytes.len() < end {
+                return None;
+            }
+            let sig_alg = AlgId::read_from_prefix(&bytes[start..end]).ok()?.0; // TODO: zerocopy: use-rest-of-range, option-to-error (https://github.com/microsoft/openvmm/issues/759)
+            if sig_alg != AlgIdEnum::RSASSA.into() {
+                return None;
+            }
+
+            start = end;
+            end += size_of::<AlgId>();
+            if bytes.len() < end {
+                return None;
+            }
+            let hash = AlgId::read_from_prefix(&bytes[start..end]).ok()?.0; // TODO: zerocopy: use-rest-of-range, option-to-error (https://github.com/microsoft/openvmm/issues/759)
+
+            start = end;
+            let signature = Tpm2bBuffer::deserialize(&bytes[start..])?;
+            end += signature.payload_size();
+
+            start = end;
+            if start != expected_auth_start {
+                return None;
+            }
+            end += size_of::<common::ReplyAuth>();
+            if bytes.len() < end {
+                return None;
+            }
+            let auth = common::ReplyAuth::read_from_prefix(&bytes[start..end])
+                .ok()?
+                .0; // TODO: zerocopy: use-rest-of-range, option-to-error (https://github.com/microsoft/openvmm/issues/759)
+
+            if header.size.get() as usize != end {
+                return None;
+            }
+
+            Some(Self {
+                header,
+                parameter_size,
+                quoted,
+                sig_alg,
+                hash,
+                signature,
+                auth,
+            })
+        }
+
+        fn payload_size(&self) -> usize {
+            let mut size = 0;
+
+            size += size_of::<ReplyHeader>();
+            size += self.quoted.payload_size();
+            size += size_of::<AlgId>();
+            size += size_of::<AlgId>();
+            size += self.signature.payload_size();
+
+            size
+        }
+    }
 }
 
 #[cfg(test)]
@@ -4234,4 +4449,54 @@ fn test_nv_read() {
         assert_eq!(response.header.response_code.get(), 0x0);
         assert_eq!(response.data.buffer[..EXPECTED_DATA.len()], EXPECTED_DATA);
     }
+
+    #[test]
+    fn test_quote() {
+        const EXPECTED_CMD: [u8; 45] = [
+            0x80, 0x02, 0x00, 0x00, 0x00, 0x2d, 0x00, 0x00, 0x01, 0x58, 0x81, 0x00, 0x00, 0x03,
+            0x00, 0x00, 0x00, 0x09, 0x40, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x04, 0x01, 0x02, 0x03, 0x04, 0x00, 0x10, 0x00, 0x00, 0x00, 0x01, 0x00, 0x0b, 0x03,
+            0xff, 0x00, 0x00,
+        ];
+
+        const REPLY_SUCCEED: [u8; 33] = [
+            0x80, 0x02, 0x00, 0x00, 0x00, 0x21, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0e,
+            0x00, 0x04, 0xaa, 0xbb, 0xcc, 0xdd, 0x00, 0x14, 0x00, 0x0b, 0x00, 0x02, 0x11, 0x22,
+            0x00, 0x00, 0x01, 0x00, 0x00,
+        ];
+
+        let pcr_selection = PcrSelection {
+            hash: AlgIdEnum::SHA256.into(),
+            size_of_select: 3,
+            bitmap: [0xff, 0x00, 0x00],
+        };
+        let result = QuoteCmd::new(
+            SessionTagEnum::Sessions.into(),
+            crate::TPM_AZURE_AIK_HANDLE,
+            CmdAuth::new(TPM20_RS_PW, 0, 0, 0),
+            &[0x01, 0x02, 0x03, 0x04],
+            &[pcr_selection],
+        );
+        assert!(result.is_ok());
+        let cmd = result.unwrap();
+        assert_eq!(cmd.serialize(), EXPECTED_CMD);
+
+        let mut reply = [0u8; 4096];
+        reply[..REPLY_SUCCEED.len()].copy_from_slice(&REPLY_SUCCEED);
+
+        let response = QuoteReply::deserialize(&reply);
+        assert!(response.is_some());
+        let response = response.unwrap();
+        assert_eq!(response.header.response_code.get(), 0x0);
+        assert_eq!(
+            response.quoted.buffer[..response.quoted.size.get() as usize],
+            [0xaa, 0xbb, 0xcc, 0xdd]
+        );
+        assert_eq!(response.sig_alg, AlgId::from(AlgIdEnum::RSASSA));
+        assert_eq!(response.hash, AlgId::from(AlgIdEnum::SHA256));
+        assert_eq!(
+            response.signature.buffer[..response.signature.size.get() as usize],
+            [0x11, 0x22]
+        );
+    }
 }
@@ -129,6 +129,12 @@ pub fn new(mut device: T, msi_conn: MsiConnection, dma_client: Arc<U>) -> Self {
             bar0_len,
         }
     }
+
+    /// Returns the wrapped device, so that tests can act on it directly, e.g.
+    /// to inject errors.
+    pub fn device(&self) -> Arc<Mutex<T>> {
+        self.device.clone()
+    }
 }
 
 /// A memory mapping for an [`EmulatedDevice`].
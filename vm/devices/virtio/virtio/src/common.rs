@@ -457,3 +457,24 @@ pub struct QueueResources {
     pub event: Event,
     pub guest_memory: GuestMemory,
 }
+
+/// Used by a device to tell the driver that its device-specific config
+/// registers have changed.
+///
+/// The transport bumps the config generation and raises a config change
+/// interrupt if the driver has set DRIVER_OK.
+#[derive(Debug, Clone)]
+pub struct ConfigChangeNotifier(mesh::Sender<()>);
+
+impl ConfigChangeNotifier {
+    /// Returns a notifier that sends on `send` for each change. Used by
+    /// transports and by device tests.
+    pub fn new(send: mesh::Sender<()>) -> Self {
+        Self(send)
+    }
+
+    /// Signals a config change.
+    pub fn notify(&self) {
+        self.0.send(());
+    }
+}
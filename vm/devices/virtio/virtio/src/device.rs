@@ -4,6 +4,7 @@
 //! Per-queue virtio device trait (`VirtioDevice`) and object-safe wrapper
 //! (`DynVirtioDevice`).
 
+use crate::ConfigChangeNotifier;
 use crate::DEFAULT_QUEUE_SIZE;
 use crate::DeviceTraits;
 use crate::QueueResources;
@@ -105,6 +106,12 @@ fn reset(&mut self) -> impl Future<Output = ()> + Send {
         async {}
     }
 
+    /// Provide a notifier the device can use to signal that its
+    /// device-specific config registers have changed.
+    ///
+    /// Called once, before any queue is started. Default: no-op.
+    fn set_config_change_notifier(&mut self, _notifier: ConfigChangeNotifier) {}
+
     /// Whether the device supports save/restore.
     ///
     /// Devices that return `false` will cause the transport's `save()` to
@@ -164,6 +171,9 @@ fn stop_queue(
     /// Reset device-internal state.
     fn reset(&mut self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>>;
 
+    /// Provide the config change notifier to the device.
+    fn set_config_change_notifier(&mut self, notifier: ConfigChangeNotifier);
+
     /// Whether the device supports save/restore.
     fn supports_save_restore(&self) -> bool;
 }
@@ -226,6 +236,10 @@ fn reset(&mut self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
         Box::pin(VirtioDevice::reset(self))
     }
 
+    fn set_config_change_notifier(&mut self, notifier: ConfigChangeNotifier) {
+        VirtioDevice::set_config_change_notifier(self, notifier)
+    }
+
     fn supports_save_restore(&self) -> bool {
         VirtioDevice::supports_save_restore(self)
     }
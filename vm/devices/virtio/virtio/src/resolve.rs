@@ -5,6 +5,8 @@
 
 use crate::DynVirtioDevice;
 use crate::VirtioDevice;
+use guestmem::DiscardRam;
+use std::sync::Arc;
 use vm_resource::CanResolveTo;
 use vm_resource::kind::VirtioDeviceHandle;
 use vmcore::vm_task::VmTaskDriverSource;
@@ -26,4 +28,7 @@ fn from(value: T) -> Self {
 pub struct VirtioResolveInput<'a> {
     /// The VM driver source.
     pub driver_source: &'a VmTaskDriverSource,
+    /// Used to release the backing of guest RAM, if supported by the VM's
+    /// memory configuration.
+    pub discard_ram: Option<Arc<dyn DiscardRam>>,
 }
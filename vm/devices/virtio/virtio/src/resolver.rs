@@ -49,6 +49,7 @@ async fn resolve(
                 resource.0,
                 VirtioResolveInput {
                     driver_source: input.driver_source,
+                    discard_ram: None,
                 },
             )
             .await
@@ -6,6 +6,7 @@
 #![expect(unsafe_code)]
 #![cfg(test)]
 
+use crate::ConfigChangeNotifier;
 use crate::DeviceTraits;
 use crate::DynVirtioDevice;
 use crate::PciInterruptModel;
@@ -1257,6 +1258,7 @@ struct TestDevice {
     queue_work: Option<TestDeviceQueueWorkFn>,
     driver: vmcore::vm_task::VmTaskDriver,
     workers: Vec<TaskControl<TestDeviceTask, TestDeviceQueue>>,
+    config_change: Arc<Mutex<Option<ConfigChangeNotifier>>>,
 }
 
 impl TestDevice {
@@ -1270,6 +1272,7 @@ fn new(
             queue_work,
             driver: driver_source.simple(),
             workers: Vec::new(),
+            config_change: Default::default(),
         }
     }
 }
@@ -1342,6 +1345,10 @@ async fn reset(&mut self) {
         self.workers.clear();
     }
 
+    fn set_config_change_notifier(&mut self, notifier: ConfigChangeNotifier) {
+        *self.config_change.lock() = Some(notifier);
+    }
+
     fn supports_save_restore(&self) -> bool {
         true
     }
@@ -2627,6 +2634,63 @@ async fn verify_device_split_queue_simple(driver: DefaultDriver) {
         .with_bank(1, VIRTIO_F_VERSION_1);
     verify_device_queue_simple_inner(test_mem, guest, features).await;
 }
+#[async_test]
+async fn verify_device_config_change(driver: DefaultDriver) {
+    let test_mem = VirtioTestMemoryAccess::new();
+    let guest = VirtioTestGuest::new_split(&driver, &test_mem, 1, 2, true);
+    let features = VirtioDeviceFeatures::new()
+        .with_bank(0, VIRTIO_F_RING_EVENT_IDX | 2)
+        .with_bank(1, VIRTIO_F_VERSION_1);
+    let target = TestLineInterruptTarget::new_arc();
+    let interrupt = LineInterrupt::new_with_target("test", target.clone(), 0);
+    let driver_source = VmTaskDriverSource::new(SingleDriverBackend::new(guest.driver()));
+    let device = TestDevice::new(
+        &driver_source,
+        DeviceTraits {
+            device_id: VirtioDeviceType::CONSOLE,
+            device_features: features,
+            max_queues: 1,
+            device_register_length: 0,
+            ..Default::default()
+        },
+        None,
+    );
+    let config_change = device.config_change.clone();
+    let mut dev = VirtioMmioDevice::new(
+        Box::new(device),
+        &driver_source.simple(),
+        guest.mem(),
+        interrupt,
+        None,
+        0,
+        1,
+    )
+    .unwrap();
+    let notifier = config_change.lock().clone().unwrap();
+
+    guest.setup_chipset_device(&mut dev, features).await;
+    expect_mmio_interrupt(
+        &mut dev,
+        &target,
+        VIRTIO_MMIO_INTERRUPT_STATUS_CONFIG_CHANGE,
+        false,
+    )
+    .await;
+
+    // A device-initiated change bumps the generation and interrupts the
+    // driver.
+    notifier.notify();
+    yield_and_poll_device(&mut dev).await;
+    assert_eq!(dev.read_u32(0xfc), 3);
+    expect_mmio_interrupt(
+        &mut dev,
+        &target,
+        VIRTIO_MMIO_INTERRUPT_STATUS_CONFIG_CHANGE,
+        false,
+    )
+    .await;
+}
+
 #[async_test]
 async fn verify_device_packed_queue_simple(driver: DefaultDriver) {
     let test_mem = VirtioTestMemoryAccess::new();
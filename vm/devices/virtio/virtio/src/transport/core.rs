@@ -9,6 +9,7 @@
 use super::task::TransportState;
 use super::task::TransportStateResult;
 use super::task::run_device_task;
+use crate::ConfigChangeNotifier;
 use crate::DynVirtioDevice;
 use crate::QueueResources;
 use crate::VirtioDoorbells;
@@ -17,6 +18,7 @@
 use crate::spec::VirtioDeviceFeatures;
 use crate::spec::VirtioDeviceStatus;
 use chipset_device::io::deferred::DeferredWrite;
+use futures::StreamExt;
 use guestmem::DoorbellRegistration;
 use guestmem::GuestMemory;
 use inspect::Inspect;
@@ -85,6 +87,8 @@ pub(crate) struct VirtioTransportCore {
     pub poll_waker: Option<std::task::Waker>,
     pub config_generation: u32,
     #[inspect(skip)]
+    pub config_change_recv: mesh::Receiver<()>,
+    #[inspect(skip)]
     pub doorbells: VirtioDoorbells,
     pub supports_save_restore: bool,
     #[inspect(skip)]
@@ -98,7 +102,7 @@ pub(crate) struct VirtioTransportCore {
 impl VirtioTransportCore {
     /// Create a new transport core, spawning the device task.
     pub fn new(
-        device: Box<dyn DynVirtioDevice>,
+        mut device: Box<dyn DynVirtioDevice>,
         driver: &impl Spawn,
         guest_memory: GuestMemory,
         doorbell_registration: Option<Arc<dyn DoorbellRegistration>>,
@@ -130,6 +134,9 @@ pub fn new(
             .with_access_platform(true);
         let supports_save_restore = device.supports_save_restore();
 
+        let (config_change_send, config_change_recv) = mesh::channel();
+        device.set_config_change_notifier(ConfigChangeNotifier::new(config_change_send));
+
         let (sender, receiver) = mesh::channel();
         let _device_task = driver.spawn("virtio-device-task", async move {
             run_device_task(device, receiver).await;
@@ -148,6 +155,7 @@ pub fn new(
             device_status: VirtioDeviceStatus::new(),
             poll_waker: None,
             config_generation: 0,
+            config_change_recv,
             doorbells: VirtioDoorbells::new(doorbell_registration),
             supports_save_restore,
             guest_memory,
@@ -187,6 +195,7 @@ pub fn reset_status(&mut self, ops: &mut dyn TransportOps) {
             // Immutable / long-lived — not reset.
             device_sender: _,
             _device_task: _,
+            config_change_recv: _,
             device_feature: _,
             supports_save_restore: _,
             guest_memory: _,
@@ -346,6 +355,10 @@ pub fn write_device_status(&mut self, ops: &mut dyn TransportOps, val: u8) {
     pub fn poll_device(&mut self, ops: &mut dyn TransportOps, cx: &mut std::task::Context<'_>) {
         self.poll_waker = Some(cx.waker().clone());
 
+        while let Poll::Ready(Some(())) = self.config_change_recv.poll_next_unpin(cx) {
+            self.update_config_generation(ops);
+        }
+
         if let Poll::Ready(result) = self.state.poll(cx) {
             // Complete the deferred STATUS write before applying the
             // result, since apply_transport_result may call reset_status
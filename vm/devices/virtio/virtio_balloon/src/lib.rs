@@ -0,0 +1,717 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Virtio memory balloon device.
+//!
+//! This crate implements virtio device ID 5 (memory balloon) as defined in
+//! the [virtio spec §5.5](https://docs.oasis-open.org/virtio/virtio/v1.2/virtio-v1.2.html).
+//! The host sets a target balloon size via [`BalloonRpc`], and the guest
+//! inflates or deflates the balloon towards that target, reporting the pages
+//! it gives up or takes back on the inflate and deflate queues.
+//!
+//! # Queues
+//!
+//! | Queue | Purpose |
+//! |-------|---------|
+//! | 0 — inflateq | PFNs of pages given up by the guest |
+//! | 1 — deflateq | PFNs of pages taken back by the guest |
+//! | 2 — statsq | Guest memory statistics, if `F_STATS_VQ` is negotiated |
+//!
+//! The guest keeps a single buffer on the statistics queue. The device holds
+//! on to it until the host asks for the balloon status, and then returns it
+//! to ask the guest to report fresh statistics.
+//!
+//! When the host changes the target, the device raises a config change
+//! interrupt so that the guest starts inflating or deflating right away.
+//!
+//! Pages on the inflate queue have their backing released via [`DiscardRam`],
+//! when the VM's memory configuration supports it. Deflated pages need no
+//! action, since the host allocates new backing when the guest next touches
+//! them.
+
+#![forbid(unsafe_code)]
+
+pub mod resolver;
+mod spec;
+
+use futures::StreamExt;
+use futures_concurrency::future::Race as _;
+use guestmem::DiscardRam;
+use guestmem::GuestMemory;
+use inspect::Inspect;
+use inspect::InspectMut;
+use pal_async::task::Spawn;
+use pal_async::task::Task;
+use parking_lot::Mutex;
+use spec::CONFIG_LEN;
+use spec::CONFIG_OFFSET_ACTUAL;
+use spec::CONFIG_OFFSET_NUM_PAGES;
+use spec::DEFLATE_QUEUE;
+use spec::INFLATE_QUEUE;
+use spec::STATS_QUEUE;
+use spec::VIRTIO_BALLOON_F_STATS_VQ;
+use spec::VIRTIO_BALLOON_PFN_LEN;
+use spec::VIRTIO_BALLOON_STAT_SIZE;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use task_control::AsyncRun;
+use task_control::Cancelled;
+use task_control::InspectTaskMut;
+use task_control::TaskControl;
+use virtio::ConfigChangeNotifier;
+use virtio::DeviceTraits;
+use virtio::DeviceTraitsSharedMemory;
+use virtio::QueueResources;
+use virtio::VirtioDevice;
+use virtio::VirtioQueue;
+use virtio::queue::QueueState;
+use virtio::spec::VirtioDeviceFeatures;
+use virtio_resources::balloon::BalloonRpc;
+use virtio_resources::balloon::BalloonStat;
+use virtio_resources::balloon::BalloonStatus;
+use vmcore::vm_task::VmTaskDriver;
+use vmcore::vm_task::VmTaskDriverSource;
+
+/// The maximum number of statistics to read from a single statistics buffer.
+const MAX_STATS: usize = 64;
+
+/// The number of PFNs to read from a descriptor at a time.
+const PFN_CHUNK: usize = 256;
+
+/// The size of the pages referred to by balloon PFNs.
+const BALLOON_PAGE_SIZE: u64 = 4096;
+
+/// A virtio memory balloon device.
+#[derive(InspectMut)]
+pub struct VirtioBalloonDevice {
+    driver: VmTaskDriver,
+    #[inspect(flatten)]
+    state: Arc<BalloonState>,
+    #[inspect(skip)]
+    _control_task: Task<()>,
+    #[inspect(mut)]
+    worker: TaskControl<BalloonWorker, BalloonQueues>,
+}
+
+/// Balloon state shared between the device, its worker, and the host control
+/// task.
+#[derive(Inspect)]
+struct BalloonState {
+    /// The target set by the host, in 4KB pages.
+    target_pages: AtomicU32,
+    /// The balloon size written by the guest to the config space.
+    actual_pages: AtomicU32,
+    /// The number of pages reported on the inflate queue, less those reported
+    /// on the deflate queue.
+    inflated_pages: AtomicU64,
+    /// The number of inflated pages whose backing was released.
+    discarded_pages: AtomicU64,
+    /// The number of inflated pages whose backing could not be released.
+    discard_failures: AtomicU64,
+    #[inspect(skip)]
+    stats: Mutex<Vec<BalloonStat>>,
+    #[inspect(skip)]
+    config_change: Mutex<Option<ConfigChangeNotifier>>,
+}
+
+impl VirtioBalloonDevice {
+    /// Creates a new balloon device with an initial target of `target_pages`
+    /// 4KB pages, which the host can adjust via `recv`.
+    ///
+    /// If `discard_ram` is provided, it is used to release the backing of
+    /// inflated pages.
+    pub fn new(
+        driver_source: &VmTaskDriverSource,
+        target_pages: u32,
+        recv: mesh::Receiver<BalloonRpc>,
+        discard_ram: Option<Arc<dyn DiscardRam>>,
+    ) -> Self {
+        let driver = driver_source.simple();
+        let state = Arc::new(BalloonState {
+            target_pages: target_pages.into(),
+            actual_pages: 0.into(),
+            inflated_pages: 0.into(),
+            discarded_pages: 0.into(),
+            discard_failures: 0.into(),
+            stats: Mutex::new(Vec::new()),
+            config_change: Mutex::new(None),
+        });
+        let (refresh_send, refresh_recv) = mesh::channel();
+        let control_task = driver.spawn(
+            "virtio-balloon-control",
+            run_control(state.clone(), recv, refresh_send),
+        );
+        Self {
+            driver,
+            state: state.clone(),
+            _control_task: control_task,
+            worker: TaskControl::new(BalloonWorker {
+                state,
+                refresh_stats: refresh_recv,
+                discard_ram,
+            }),
+        }
+    }
+}
+
+impl VirtioDevice for VirtioBalloonDevice {
+    fn traits(&self) -> DeviceTraits {
+        DeviceTraits {
+            device_id: virtio::spec::VirtioDeviceType::BALLOON,
+            device_features: VirtioDeviceFeatures::new()
+                .with_device_specific_low(1 << VIRTIO_BALLOON_F_STATS_VQ)
+                .with_ring_event_idx(true)
+                .with_ring_indirect_desc(true)
+                .with_ring_packed(true),
+            max_queues: 3, // inflateq (0) + deflateq (1) + statsq (2)
+            device_register_length: CONFIG_LEN,
+            shared_memory: DeviceTraitsSharedMemory::default(),
+        }
+    }
+
+    async fn read_registers_u32(&mut self, offset: u16) -> u32 {
+        match offset {
+            CONFIG_OFFSET_NUM_PAGES => self.state.target_pages.load(Ordering::Relaxed),
+            CONFIG_OFFSET_ACTUAL => self.state.actual_pages.load(Ordering::Relaxed),
+            _ => {
+                tracelimit::warn_ratelimited!(offset, "invalid config read offset");
+                0
+            }
+        }
+    }
+
+    async fn write_registers_u32(&mut self, offset: u16, val: u32) {
+        match offset {
+            CONFIG_OFFSET_ACTUAL => self.state.actual_pages.store(val, Ordering::Relaxed),
+            _ => {
+                tracelimit::warn_ratelimited!(offset, "invalid config write offset");
+            }
+        }
+    }
+
+    async fn start_queue(
+        &mut self,
+        idx: u16,
+        resources: QueueResources,
+        features: &VirtioDeviceFeatures,
+        initial_state: Option<QueueState>,
+    ) -> anyhow::Result<()> {
+        let guest_memory = resources.guest_memory.clone();
+        let queue = VirtioQueue::new(
+            *features,
+            resources.params,
+            resources.guest_memory,
+            resources.notify,
+            pal_async::wait::PolledWait::new(&self.driver, resources.event)?,
+            initial_state,
+        )?;
+
+        if self.worker.has_state() {
+            // The worker is already running with another queue, so inject
+            // this one.
+            self.worker.update_with(move |_worker, state| {
+                if let Some(state) = state {
+                    *state.queue_mut(idx) = Some(queue);
+                }
+            });
+        } else {
+            let mut state = BalloonQueues {
+                inflateq: None,
+                deflateq: None,
+                statsq: None,
+                mem: guest_memory,
+            };
+            *state.queue_mut(idx) = Some(queue);
+            self.worker.insert(&self.driver, "virtio-balloon", state);
+            self.worker.start();
+        }
+        Ok(())
+    }
+
+    async fn stop_queue(&mut self, idx: u16) -> Option<QueueState> {
+        if !self.worker.has_state() {
+            return None;
+        }
+
+        // Stop the worker (shared by all queues). Once stopped, we can reach
+        // into the state to take the requested queue.
+        self.worker.stop().await;
+
+        let state = self.worker.state_mut().unwrap();
+        let queue = state.queue_mut(idx).take();
+
+        // Remove the worker state once all queues have been taken. Otherwise,
+        // restart the worker so the remaining queues stay active.
+        if state.inflateq.is_none() && state.deflateq.is_none() && state.statsq.is_none() {
+            self.worker.remove();
+        } else {
+            self.worker.start();
+        }
+
+        queue.map(|q| q.queue_state())
+    }
+
+    async fn reset(&mut self) {
+        // A reset returns the whole balloon to the guest.
+        self.state.actual_pages.store(0, Ordering::Relaxed);
+        self.state.inflated_pages.store(0, Ordering::Relaxed);
+        self.state.stats.lock().clear();
+    }
+
+    fn set_config_change_notifier(&mut self, notifier: ConfigChangeNotifier) {
+        *self.state.config_change.lock() = Some(notifier);
+    }
+}
+
+/// Handles host requests to adjust the balloon.
+async fn run_control(
+    state: Arc<BalloonState>,
+    mut recv: mesh::Receiver<BalloonRpc>,
+    refresh_stats: mesh::Sender<()>,
+) {
+    while let Some(rpc) = recv.next().await {
+        match rpc {
+            BalloonRpc::SetTarget(rpc) => rpc.handle_sync(|target_pages| {
+                tracing::info!(target_pages, "setting balloon target");
+                let old = state.target_pages.swap(target_pages, Ordering::Relaxed);
+                if old != target_pages {
+                    if let Some(notifier) = &*state.config_change.lock() {
+                        notifier.notify();
+                    }
+                }
+            }),
+            BalloonRpc::Status(rpc) => rpc.handle_sync(|()| {
+                refresh_stats.send(());
+                BalloonStatus {
+                    target_pages: state.target_pages.load(Ordering::Relaxed),
+                    actual_pages: state.actual_pages.load(Ordering::Relaxed),
+                    stats: state.stats.lock().clone(),
+                }
+            }),
+        }
+    }
+}
+
+#[derive(InspectMut)]
+struct BalloonWorker {
+    #[inspect(skip)]
+    state: Arc<BalloonState>,
+    #[inspect(skip)]
+    refresh_stats: mesh::Receiver<()>,
+    #[inspect(skip)]
+    discard_ram: Option<Arc<dyn DiscardRam>>,
+}
+
+#[derive(InspectMut)]
+struct BalloonQueues {
+    inflateq: Option<VirtioQueue>,
+    deflateq: Option<VirtioQueue>,
+    statsq: Option<VirtioQueue>,
+    mem: GuestMemory,
+}
+
+impl BalloonQueues {
+    fn queue_mut(&mut self, idx: u16) -> &mut Option<VirtioQueue> {
+        match idx {
+            INFLATE_QUEUE => &mut self.inflateq,
+            DEFLATE_QUEUE => &mut self.deflateq,
+            STATS_QUEUE => &mut self.statsq,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl InspectTaskMut<BalloonQueues> for BalloonWorker {
+    fn inspect_mut(&mut self, req: inspect::Request<'_>, state: Option<&mut BalloonQueues>) {
+        req.respond().merge(self).merge(state);
+    }
+}
+
+impl AsyncRun<BalloonQueues> for BalloonWorker {
+    async fn run(
+        &mut self,
+        stop: &mut task_control::StopTask<'_>,
+        state: &mut BalloonQueues,
+    ) -> Result<(), Cancelled> {
+        stop.until_stopped(self.run_loop(state)).await.map(|r| {
+            if let Err(err) = r {
+                tracing::error!(
+                    error = &err as &dyn std::error::Error,
+                    "virtio-balloon worker loop failed"
+                );
+            }
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum WorkerError {
+    #[error("virtio queue error")]
+    Virtio(#[source] std::io::Error),
+    #[error("guest memory error")]
+    GuestMemory(#[source] guestmem::GuestMemoryError),
+}
+
+impl BalloonWorker {
+    /// Core worker loop.
+    ///
+    /// This must be cancel safe, since it could be stopped at any await
+    /// point. Descriptors are only consumed once they have been fully
+    /// processed.
+    async fn run_loop(&mut self, queues: &mut BalloonQueues) -> Result<(), WorkerError> {
+        let BalloonQueues {
+            inflateq,
+            deflateq,
+            statsq,
+            mem,
+        } = queues;
+        let state = &*self.state;
+        let discard_ram = self.discard_ram.as_deref();
+        (
+            process_pfns(
+                inflateq.as_mut(),
+                mem,
+                state,
+                PfnQueue::Inflate(discard_ram),
+            ),
+            process_pfns(deflateq.as_mut(), mem, state, PfnQueue::Deflate),
+            process_stats(statsq.as_mut(), mem, state, &mut self.refresh_stats),
+        )
+            .race()
+            .await
+    }
+}
+
+#[derive(Copy, Clone)]
+enum PfnQueue<'a> {
+    /// The inflate queue, with the object used to release the backing of
+    /// inflated pages, if any.
+    Inflate(Option<&'a dyn DiscardRam>),
+    /// The deflate queue.
+    Deflate,
+}
+
+/// Processes PFN arrays on the inflate or deflate queue.
+async fn process_pfns(
+    queue: Option<&mut VirtioQueue>,
+    mem: &GuestMemory,
+    state: &BalloonState,
+    kind: PfnQueue<'_>,
+) -> Result<(), WorkerError> {
+    let Some(queue) = queue else {
+        return std::future::pending().await;
+    };
+    loop {
+        let work = queue.peek().await.map_err(WorkerError::Virtio)?;
+        let len = work.readable_length();
+        let mut pages = 0;
+        let mut buf = vec![0; PFN_CHUNK * VIRTIO_BALLOON_PFN_LEN as usize];
+        let mut offset = 0;
+        while offset < len {
+            let n = work
+                .read_at_offset(offset, mem, &mut buf)
+                .map_err(WorkerError::GuestMemory)?;
+            if n == 0 {
+                break;
+            }
+            let pfns = parse_pfns(&buf[..n]);
+            pages += pfns.len() as u64;
+            if let PfnQueue::Inflate(Some(discard_ram)) = kind {
+                discard_pfns(discard_ram, state, &pfns);
+            }
+            offset += n as u64;
+        }
+        match kind {
+            PfnQueue::Inflate(_) => {
+                state.inflated_pages.fetch_add(pages, Ordering::Relaxed);
+            }
+            PfnQueue::Deflate => {
+                let _ =
+                    state
+                        .inflated_pages
+                        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                            Some(n.saturating_sub(pages))
+                        });
+            }
+        }
+        let work = work.consume();
+        queue.complete(work, 0);
+    }
+}
+
+/// Parses a buffer of little-endian u32 PFNs, ignoring any trailing partial
+/// PFN.
+fn parse_pfns(buf: &[u8]) -> Vec<u32> {
+    buf.chunks_exact(VIRTIO_BALLOON_PFN_LEN as usize)
+        .map(|pfn| u32::from_le_bytes(pfn.try_into().unwrap()))
+        .collect()
+}
+
+/// Releases the backing of the pages in `pfns`, coalescing runs of
+/// contiguous pages.
+fn discard_pfns(discard_ram: &dyn DiscardRam, state: &BalloonState, pfns: &[u32]) {
+    let mut discard = |start: u32, count: u64| {
+        let gpa = start as u64 * BALLOON_PAGE_SIZE;
+        match discard_ram.discard_ram(gpa, count * BALLOON_PAGE_SIZE) {
+            Ok(()) => {
+                state.discarded_pages.fetch_add(count, Ordering::Relaxed);
+            }
+            Err(err) => {
+                state.discard_failures.fetch_add(count, Ordering::Relaxed);
+                tracelimit::warn_ratelimited!(
+                    error = &err as &dyn std::error::Error,
+                    gpa,
+                    count,
+                    "failed to release inflated balloon pages"
+                );
+            }
+        }
+    };
+    let mut run: Option<(u32, u64)> = None;
+    for &pfn in pfns {
+        run = match run {
+            Some((start, count)) if start as u64 + count == pfn as u64 => Some((start, count + 1)),
+            Some((start, count)) => {
+                discard(start, count);
+                Some((pfn, 1))
+            }
+            None => Some((pfn, 1)),
+        };
+    }
+    if let Some((start, count)) = run {
+        discard(start, count);
+    }
+}
+
+/// Processes the statistics queue.
+async fn process_stats(
+    queue: Option<&mut VirtioQueue>,
+    mem: &GuestMemory,
+    state: &BalloonState,
+    refresh_stats: &mut mesh::Receiver<()>,
+) -> Result<(), WorkerError> {
+    let Some(queue) = queue else {
+        return std::future::pending().await;
+    };
+    loop {
+        let work = queue.peek().await.map_err(WorkerError::Virtio)?;
+        let len = (work.readable_length() as usize).min(MAX_STATS * VIRTIO_BALLOON_STAT_SIZE);
+        let mut buf = vec![0; len];
+        let n = work.read(mem, &mut buf).map_err(WorkerError::GuestMemory)?;
+        *state.stats.lock() = parse_stats(&buf[..n]);
+        // Hold on to the buffer until the host asks for fresh statistics.
+        // Returning it is the request for the guest to report them.
+        if refresh_stats.next().await.is_none() {
+            std::future::pending::<()>().await;
+        }
+        let work = work.consume();
+        queue.complete(work, 0);
+    }
+}
+
+/// Parses the packed statistics in a statistics queue buffer.
+fn parse_stats(buf: &[u8]) -> Vec<BalloonStat> {
+    buf.chunks_exact(VIRTIO_BALLOON_STAT_SIZE)
+        .map(|stat| BalloonStat {
+            tag: u16::from_le_bytes(stat[..2].try_into().unwrap()),
+            value: u64::from_le_bytes(stat[2..].try_into().unwrap()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mesh::rpc::RpcSend;
+    use pal_async::DefaultDriver;
+    use pal_async::async_test;
+    use pal_event::Event;
+    use test_with_tracing::test;
+    use virtio::queue::QueueParams;
+    use virtio::spec::queue::DescriptorFlags;
+    use virtio::test_helpers::init_avail_ring;
+    use virtio::test_helpers::init_used_ring;
+    use virtio::test_helpers::make_available;
+    use virtio::test_helpers::wait_for_used;
+    use virtio::test_helpers::write_descriptor;
+    use vmcore::interrupt::Interrupt;
+    use vmcore::vm_task::SingleDriverBackend;
+
+    const QUEUE_SIZE: u16 = 16;
+
+    /// Records discarded ranges.
+    #[derive(Default)]
+    struct RecordDiscard(Mutex<Vec<(u64, u64)>>);
+
+    impl DiscardRam for RecordDiscard {
+        fn discard_ram(&self, gpa: u64, len: u64) -> std::io::Result<()> {
+            self.0.lock().push((gpa, len));
+            Ok(())
+        }
+    }
+
+    /// The guest side of a PFN queue.
+    struct TestQueue {
+        desc_addr: u64,
+        avail_addr: u64,
+        used_addr: u64,
+        data_addr: u64,
+        event: Event,
+        interrupt: Event,
+        avail_idx: u16,
+        used_idx: u16,
+    }
+
+    impl TestQueue {
+        async fn start(
+            device: &mut VirtioBalloonDevice,
+            mem: &GuestMemory,
+            idx: u16,
+            base: u64,
+        ) -> Self {
+            let queue = Self {
+                desc_addr: base,
+                avail_addr: base + 0x1000,
+                used_addr: base + 0x2000,
+                data_addr: base + 0x3000,
+                event: Event::new(),
+                interrupt: Event::new(),
+                avail_idx: 0,
+                used_idx: 0,
+            };
+            init_avail_ring(mem, queue.avail_addr);
+            init_used_ring(mem, queue.used_addr);
+            device
+                .start_queue(
+                    idx,
+                    QueueResources {
+                        params: QueueParams {
+                            size: QUEUE_SIZE,
+                            enable: true,
+                            desc_addr: queue.desc_addr,
+                            avail_addr: queue.avail_addr,
+                            used_addr: queue.used_addr,
+                        },
+                        notify: Interrupt::from_event(queue.interrupt.clone()),
+                        event: queue.event.clone(),
+                        guest_memory: mem.clone(),
+                    },
+                    &VirtioDeviceFeatures::new(),
+                    None,
+                )
+                .await
+                .unwrap();
+            queue
+        }
+
+        /// Posts `pfns` as a single descriptor and waits for the device to
+        /// complete it.
+        async fn post_pfns(&mut self, driver: &DefaultDriver, mem: &GuestMemory, pfns: &[u32]) {
+            let data: Vec<u8> = pfns.iter().flat_map(|pfn| pfn.to_le_bytes()).collect();
+            mem.write_at(self.data_addr, &data).unwrap();
+            write_descriptor(
+                mem,
+                self.desc_addr,
+                0,
+                self.data_addr,
+                data.len() as u32,
+                DescriptorFlags::new(),
+                0,
+            );
+            make_available(mem, self.avail_addr, QUEUE_SIZE, 0, &mut self.avail_idx);
+            self.event.signal();
+            wait_for_used(
+                driver,
+                &self.interrupt,
+                mem,
+                self.used_addr,
+                QUEUE_SIZE,
+                &mut self.used_idx,
+            )
+            .await;
+        }
+    }
+
+    #[test]
+    fn parse_packed_stats() {
+        let mut buf = Vec::new();
+        for (tag, value) in [(4u16, 0x1000u64), (5, 0x2000)] {
+            buf.extend_from_slice(&tag.to_le_bytes());
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+        // A trailing partial statistic is ignored.
+        buf.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(
+            parse_stats(&buf),
+            [
+                BalloonStat {
+                    tag: 4,
+                    value: 0x1000
+                },
+                BalloonStat {
+                    tag: 5,
+                    value: 0x2000
+                },
+            ]
+        );
+    }
+
+    #[async_test]
+    async fn host_sets_target(driver: DefaultDriver) {
+        let driver_source = VmTaskDriverSource::new(SingleDriverBackend::new(driver));
+        let (send, recv) = mesh::channel();
+        let mut device = VirtioBalloonDevice::new(&driver_source, 16, recv, None);
+        let (config_send, mut config_recv) = mesh::channel();
+        device.set_config_change_notifier(ConfigChangeNotifier::new(config_send));
+        assert_eq!(device.read_registers_u32(CONFIG_OFFSET_NUM_PAGES).await, 16);
+
+        // A new target signals a config change, but setting the same target
+        // again does not.
+        send.call(BalloonRpc::SetTarget, 256).await.unwrap();
+        send.call(BalloonRpc::SetTarget, 256).await.unwrap();
+        assert_eq!(
+            device.read_registers_u32(CONFIG_OFFSET_NUM_PAGES).await,
+            256
+        );
+        config_recv.try_recv().unwrap();
+        config_recv.try_recv().unwrap_err();
+
+        device.write_registers_u32(CONFIG_OFFSET_ACTUAL, 128).await;
+        let status = send.call(BalloonRpc::Status, ()).await.unwrap();
+        assert_eq!(status.target_pages, 256);
+        assert_eq!(status.actual_pages, 128);
+        assert!(status.stats.is_empty());
+
+        device.reset().await;
+        assert_eq!(device.read_registers_u32(CONFIG_OFFSET_ACTUAL).await, 0);
+    }
+
+    #[async_test]
+    async fn inflate_and_deflate(driver: DefaultDriver) {
+        let driver_source = VmTaskDriverSource::new(SingleDriverBackend::new(driver.clone()));
+        let mem = GuestMemory::allocate(0x10000);
+        let discard = Arc::new(RecordDiscard::default());
+        let (_send, recv) = mesh::channel();
+        let mut device = VirtioBalloonDevice::new(&driver_source, 0, recv, Some(discard.clone()));
+        let mut inflateq = TestQueue::start(&mut device, &mem, INFLATE_QUEUE, 0).await;
+        let mut deflateq = TestQueue::start(&mut device, &mem, DEFLATE_QUEUE, 0x8000).await;
+
+        // Contiguous pages are released together.
+        inflateq
+            .post_pfns(&driver, &mem, &[0x105, 0x106, 0x109])
+            .await;
+        assert_eq!(device.state.inflated_pages.load(Ordering::Relaxed), 3);
+        assert_eq!(device.state.discarded_pages.load(Ordering::Relaxed), 3);
+        assert_eq!(*discard.0.lock(), [(0x105000, 0x2000), (0x109000, 0x1000)]);
+
+        // Deflated pages are returned without releasing anything.
+        deflateq.post_pfns(&driver, &mem, &[0x105]).await;
+        assert_eq!(device.state.inflated_pages.load(Ordering::Relaxed), 2);
+        assert_eq!(discard.0.lock().len(), 2);
+
+        assert!(device.stop_queue(INFLATE_QUEUE).await.is_some());
+        assert!(device.stop_queue(DEFLATE_QUEUE).await.is_some());
+    }
+}
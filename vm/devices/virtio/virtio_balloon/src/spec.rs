@@ -0,0 +1,29 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Virtio balloon spec constants and configuration types.
+
+/// Feature bit: the device has a statistics queue.
+pub const VIRTIO_BALLOON_F_STATS_VQ: u64 = 1;
+
+/// The size of a single PFN on the inflate and deflate queues. Each PFN
+/// refers to a 4KB page, regardless of the guest page size.
+pub const VIRTIO_BALLOON_PFN_LEN: u64 = 4;
+
+/// The size of a single statistic on the statistics queue: a little-endian
+/// u16 tag followed by a little-endian u64 value, packed.
+pub const VIRTIO_BALLOON_STAT_SIZE: usize = 10;
+
+/// Queue indices.
+pub const INFLATE_QUEUE: u16 = 0;
+pub const DEFLATE_QUEUE: u16 = 1;
+pub const STATS_QUEUE: u16 = 2;
+
+/// Virtio balloon configuration space layout.
+///
+/// From the virtio spec §5.5.4:
+/// - num_pages: u32 at offset 0, the target set by the device
+/// - actual: u32 at offset 4, the balloon size written by the driver
+pub const CONFIG_OFFSET_NUM_PAGES: u16 = 0;
+pub const CONFIG_OFFSET_ACTUAL: u16 = 4;
+pub const CONFIG_LEN: u32 = 8;
@@ -4,9 +4,11 @@
 //! Virtio network device implementation.
 //!
 //! This crate implements a virtio-net device that connects a guest's virtual
-//! NIC to a pluggable [`net_backend::Endpoint`]. It currently operates with a
-//! single queue pair (one RX, one TX) and supports synchronous and asynchronous
-//! TX completion modes depending on the backend.
+//! NIC to a pluggable [`net_backend::Endpoint`]. Each queue pair (one RX, one
+//! TX) is processed by its own worker task. When the endpoint supports
+//! multiple queues, the device offers `VIRTIO_NET_F_MQ` and the guest chooses
+//! how many pairs to use via the control queue. The device supports
+//! synchronous and asynchronous TX completion modes depending on the backend.
 
 #![expect(missing_docs)]
 #![forbid(unsafe_code)]
@@ -28,6 +30,7 @@
 use net_backend::Endpoint;
 use net_backend::EndpointAction;
 use net_backend::QueueConfig;
+use net_backend::RssConfig;
 use net_backend::RxId;
 use net_backend::TxFlags;
 use net_backend::TxId;
@@ -138,8 +141,24 @@ struct NetStatus {
 
 const DEFAULT_MTU: u16 = 1514;
 
-#[expect(dead_code)]
-const VIRTIO_NET_MAX_QUEUES: u16 = 0x8000;
+/// The maximum number of queue pairs allowed by the spec.
+const VIRTIO_NET_MAX_QUEUE_PAIRS: u16 = 0x8000;
+
+// Control queue command classes and commands.
+const VIRTIO_NET_CTRL_MQ: u8 = 4;
+const VIRTIO_NET_CTRL_MQ_VQ_PAIRS_SET: u8 = 0;
+
+// Control queue command acknowledgements.
+const VIRTIO_NET_OK: u8 = 0;
+const VIRTIO_NET_ERR: u8 = 1;
+
+/// The Toeplitz hash key used to spread received packets across queues. The
+/// guest cannot configure RSS, so this is the well-known default key.
+const DEFAULT_RSS_KEY: [u8; 40] = [
+    0x6d, 0x5a, 0x56, 0xda, 0x25, 0x5b, 0x0e, 0xc2, 0x41, 0x67, 0x25, 0x3d, 0x43, 0xa3, 0x8f, 0xb0,
+    0xd0, 0xca, 0x2b, 0xcb, 0xae, 0x7b, 0x30, 0xb4, 0x77, 0xcb, 0x2d, 0xa3, 0x80, 0x30, 0xf2, 0x0c,
+    0x6a, 0x42, 0xb7, 0x3b, 0xbe, 0xac, 0x01, 0xfa,
+];
 
 #[repr(C)]
 struct NetConfig {
@@ -212,6 +231,13 @@ struct VirtioNetHeader {
     pub padding_reserved: u16, // Only if VIRTIO_NET_F_HASH_REPORT negotiated
 }
 
+#[derive(IntoBytes, Immutable, KnownLayout, FromBytes)]
+#[repr(C)]
+struct VirtioNetCtrlHeader {
+    pub class: u8,
+    pub command: u8,
+}
+
 const fn header_size() -> usize {
     // TODO: Verify hash flags are not set, since header size would be larger in that case.
     offset_of!(VirtioNetHeader, hash_value)
@@ -223,6 +249,16 @@ struct Adapter {
     tx_fast_completions: bool,
     mac_address: MacAddress,
     tx_offload_support: TxOffloadSupport,
+    indirection_table_size: u16,
+    busy_poll: bool,
+}
+
+impl Adapter {
+    /// Returns whether the device offers the control queue, which it only
+    /// needs for the guest to enable additional queue pairs.
+    fn has_ctrl_queue(&self) -> bool {
+        self.max_queue_pairs > 1
+    }
 }
 
 pub struct Device {
@@ -232,6 +268,10 @@ pub struct Device {
     driver_source: VmTaskDriverSource,
     /// Per-pair state tracking.
     pairs: Vec<QueuePairState>,
+    /// The index of the control queue, once started.
+    ctrl_queue_index: Option<u16>,
+    /// The control queue, if started before the coordinator exists.
+    pending_ctrl_queue: Option<ControlQueue>,
 }
 
 /// Tracks the state of a queue pair through the start_queue lifecycle.
@@ -264,12 +304,16 @@ fn traits(&self) -> DeviceTraits {
         // Linux kernels.
         let host_uso = offloads.uso && offloads.udp;
 
+        let mq = self.adapter.has_ctrl_queue();
+
         let features_bank0 = NetworkFeaturesBank0::new()
             .with_mac(true)
             .with_csum(csum)
             .with_guest_csum(true)
             .with_host_tso4(host_tso)
-            .with_host_tso6(host_tso);
+            .with_host_tso6(host_tso)
+            .with_ctrl_vq(mq)
+            .with_mq(mq);
 
         let features_bank1 = NetworkFeaturesBank1::new().with_host_uso(host_uso);
 
@@ -281,7 +325,7 @@ fn traits(&self) -> DeviceTraits {
                 .with_ring_event_idx(true)
                 .with_ring_indirect_desc(true)
                 .with_ring_packed(true),
-            max_queues: 2 * self.registers.max_virtqueue_pairs,
+            max_queues: 2 * self.registers.max_virtqueue_pairs + u16::from(mq),
             device_register_length: size_of::<NetConfig>() as u32,
             shared_memory: DeviceTraitsSharedMemory { id: 0, size: 0 },
         }
@@ -331,7 +375,35 @@ async fn start_queue(
 
         let negotiated_features = NetworkFeaturesBank0::from(features.bank(0));
         let negotiated_features_bank1 = NetworkFeaturesBank1::from(features.bank(1));
+
+        // The control queue follows the last queue pair the guest can use.
+        if negotiated_features.ctrl_vq() {
+            let ctrl_idx = if negotiated_features.mq() {
+                2 * self.adapter.max_queue_pairs
+            } else {
+                2
+            };
+            if idx == ctrl_idx {
+                let ctrl = ControlQueue {
+                    queue,
+                    mem: guest_memory,
+                };
+                if self.coordinator.has_state() {
+                    self.coordinator.stop().await;
+                    self.coordinator.state_mut().unwrap().ctrl = Some(ctrl);
+                    self.coordinator.start();
+                } else {
+                    self.pending_ctrl_queue = Some(ctrl);
+                }
+                self.ctrl_queue_index = Some(idx);
+                return Ok(());
+            }
+        }
+
         let pair_idx = (idx / 2) as usize;
+        if pair_idx >= self.pairs.len() {
+            anyhow::bail!("invalid queue index {idx}");
+        }
         let is_rx = idx.is_multiple_of(2);
 
         match &self.pairs[pair_idx] {
@@ -355,11 +427,6 @@ async fn start_queue(
                 }
 
                 // Second queue — extract the first, form the pair.
-                let first_pair = !self
-                    .pairs
-                    .iter()
-                    .any(|p| matches!(p, QueuePairState::Active));
-
                 let prev = std::mem::replace(&mut self.pairs[pair_idx], QueuePairState::Active);
                 let QueuePairState::HalfOpen {
                     queue: pending_queue,
@@ -376,8 +443,10 @@ async fn start_queue(
                     (queue, queue_size, pending_queue, pending_queue_size)
                 };
 
-                if first_pair {
-                    self.insert_coordinator(self.pairs.len() as u16);
+                if self.coordinator.has_state() {
+                    self.coordinator.stop().await;
+                } else {
+                    self.insert_coordinator();
                 }
 
                 let virtio_state = VirtioState {
@@ -394,9 +463,10 @@ async fn start_queue(
                     negotiated_features_bank1,
                 );
 
-                if first_pair {
-                    self.coordinator.start();
-                }
+                // Restart the endpoint queues so that the new worker gets
+                // one, if the guest has enabled its pair.
+                self.coordinator.state_mut().unwrap().restart = true;
+                self.coordinator.start();
             }
             QueuePairState::Active => {
                 anyhow::bail!("queue pair {pair_idx} already active");
@@ -406,6 +476,17 @@ async fn start_queue(
     }
 
     async fn stop_queue(&mut self, idx: u16) -> Option<QueueState> {
+        if self.ctrl_queue_index == Some(idx) {
+            self.ctrl_queue_index = None;
+            self.pending_ctrl_queue = None;
+            if self.coordinator.has_state() {
+                self.coordinator.stop().await;
+                self.coordinator.state_mut().unwrap().ctrl = None;
+                self.coordinator.start();
+            }
+            return None;
+        }
+
         let pair_idx = (idx / 2) as usize;
 
         if pair_idx < self.pairs.len() {
@@ -427,7 +508,14 @@ async fn stop_queue(&mut self, idx: u16) -> Option<QueueState> {
                     }
                 }
                 let _ = self.coordinator.remove();
-                self.pairs[pair_idx] = QueuePairState::Empty;
+                // The workers for all the pairs, and the control queue, were
+                // removed with the coordinator.
+                self.ctrl_queue_index = None;
+                for pair in &mut self.pairs {
+                    if matches!(pair, QueuePairState::Active) {
+                        *pair = QueuePairState::Empty;
+                    }
+                }
             }
         }
 
@@ -437,6 +525,8 @@ async fn stop_queue(&mut self, idx: u16) -> Option<QueueState> {
 
     async fn reset(&mut self) {
         self.pairs.fill_with(|| QueuePairState::Empty);
+        self.ctrl_queue_index = None;
+        self.pending_ctrl_queue = None;
     }
 
     fn supports_save_restore(&self) -> bool {
@@ -522,6 +612,7 @@ struct PendingTxPacket {
 
 pub struct NicBuilder {
     max_queue_pairs: u16,
+    busy_poll: bool,
 }
 
 impl NicBuilder {
@@ -530,6 +621,16 @@ pub fn max_queues(mut self, max_queue_pairs: u16) -> Self {
         self
     }
 
+    /// Sets whether queue workers poll for work continuously instead of
+    /// waiting for guest notifications and endpoint wakeups.
+    ///
+    /// This reduces latency at the cost of keeping a host thread busy per
+    /// queue pair.
+    pub fn busy_poll(mut self, busy_poll: bool) -> Self {
+        self.busy_poll = busy_poll;
+        self
+    }
+
     /// Creates a new NIC.
     pub fn build(
         self,
@@ -537,10 +638,11 @@ pub fn build(
         endpoint: Box<dyn Endpoint>,
         mac_address: MacAddress,
     ) -> Device {
-        // TODO: Implement VIRTIO_NET_F_MQ and VIRTIO_NET_F_RSS logic based on mulitqueue support.
-        // let multiqueue = endpoint.multiqueue_support();
-        // let max_queue_pairs = self.max_queue_pairs.clamp(1, multiqueue.max_queues.min(VIRTIO_NET_MAX_QUEUES));
-        let max_queue_pairs = 1;
+        let multiqueue = endpoint.multiqueue_support();
+        let max_queue_pairs = self
+            .max_queue_pairs
+            .min(multiqueue.max_queues)
+            .clamp(1, VIRTIO_NET_MAX_QUEUE_PAIRS);
 
         let driver = driver_source.simple();
         let tx_offload_support = endpoint.tx_offload_support();
@@ -550,6 +652,8 @@ pub fn build(
             tx_fast_completions: endpoint.tx_fast_completions(),
             mac_address,
             tx_offload_support,
+            indirection_table_size: multiqueue.indirection_table_size,
+            busy_poll: self.busy_poll,
         });
 
         let coordinator = TaskControl::new(CoordinatorState {
@@ -577,6 +681,8 @@ pub fn build(
             pairs: (0..max_queue_pairs)
                 .map(|_| QueuePairState::Empty)
                 .collect(),
+            ctrl_queue_index: None,
+            pending_ctrl_queue: None,
         }
     }
 }
@@ -585,6 +691,7 @@ impl Device {
     pub fn builder() -> NicBuilder {
         NicBuilder {
             max_queue_pairs: !0,
+            busy_poll: false,
         }
     }
 }
@@ -596,7 +703,7 @@ fn inspect_mut(&mut self, req: inspect::Request<'_>) {
 }
 
 impl Device {
-    fn insert_coordinator(&mut self, num_queues: u16) {
+    fn insert_coordinator(&mut self) {
         self.coordinator.insert(
             &self.adapter.driver,
             "virtio-net-coordinator".to_string(),
@@ -604,7 +711,10 @@ fn insert_coordinator(&mut self, num_queues: u16) {
                 workers: (0..self.adapter.max_queue_pairs)
                     .map(|_| TaskControl::new(NetQueue { state: None }))
                     .collect(),
-                num_queues,
+                // Only the first pair is enabled until the guest enables more
+                // via the control queue.
+                active_pairs: 1,
+                ctrl: self.pending_ctrl_queue.take(),
                 restart: true,
             },
         );
@@ -641,6 +751,7 @@ fn insert_worker(
             active_state,
             negotiated_features,
             negotiated_features_bank1,
+            busy_poll: self.adapter.busy_poll,
         };
         let coordinator = self.coordinator.state_mut().unwrap();
         let worker_task = &mut coordinator.workers[idx];
@@ -651,10 +762,108 @@ fn insert_worker(
 
 struct Coordinator {
     workers: Vec<TaskControl<NetQueue, Worker>>,
-    num_queues: u16,
+    /// The number of queue pairs the guest has enabled.
+    active_pairs: u16,
+    ctrl: Option<ControlQueue>,
     restart: bool,
 }
 
+/// The control queue, used by the guest to configure the device.
+struct ControlQueue {
+    queue: VirtioQueue,
+    mem: GuestMemory,
+}
+
+#[derive(Debug, Error)]
+enum ControlCommandError {
+    #[error("failed to read control command")]
+    Read(#[source] guestmem::GuestMemoryError),
+    #[error("control command too short")]
+    TooShort,
+    #[error("unsupported control command class {class}, command {command}")]
+    Unsupported { class: u8, command: u8 },
+    #[error("invalid queue pair count {0}")]
+    InvalidPairCount(u16),
+}
+
+impl ControlQueue {
+    /// Processes the available control commands, returning the number of
+    /// queue pairs the guest most recently asked to enable, if any.
+    fn process(&mut self, max_queue_pairs: u16) -> Option<u16> {
+        let mut active_pairs = None;
+        loop {
+            let work = match self.queue.try_next() {
+                Ok(Some(work)) => work,
+                Ok(None) => break,
+                Err(err) => {
+                    tracelimit::error_ratelimited!(
+                        error = &err as &dyn std::error::Error,
+                        "failed to read control queue"
+                    );
+                    break;
+                }
+            };
+            let ack = match self.handle_command(&work, max_queue_pairs) {
+                Ok(pairs) => {
+                    active_pairs = Some(pairs);
+                    VIRTIO_NET_OK
+                }
+                Err(err) => {
+                    tracelimit::warn_ratelimited!(
+                        error = &err as &dyn std::error::Error,
+                        "failed control command"
+                    );
+                    VIRTIO_NET_ERR
+                }
+            };
+            let written = match work.write(&self.mem, &[ack]) {
+                Ok(()) => 1,
+                Err(err) => {
+                    tracelimit::warn_ratelimited!(
+                        error = &err as &dyn std::error::Error,
+                        "failed to write control command ack"
+                    );
+                    0
+                }
+            };
+            self.queue.complete(work, written);
+        }
+        active_pairs
+    }
+
+    /// Handles a single control command. Only the command to set the number
+    /// of queue pairs is supported, so on success this returns the new count.
+    fn handle_command(
+        &self,
+        work: &VirtioQueueCallbackWork,
+        max_queue_pairs: u16,
+    ) -> Result<u16, ControlCommandError> {
+        let mut buf = [0; size_of::<VirtioNetCtrlHeader>() + 2];
+        let n = work
+            .read(&self.mem, &mut buf)
+            .map_err(ControlCommandError::Read)?;
+        let (header, data) = VirtioNetCtrlHeader::read_from_prefix(&buf[..n])
+            .map_err(|_| ControlCommandError::TooShort)?;
+        if (header.class, header.command) != (VIRTIO_NET_CTRL_MQ, VIRTIO_NET_CTRL_MQ_VQ_PAIRS_SET) {
+            return Err(ControlCommandError::Unsupported {
+                class: header.class,
+                command: header.command,
+            });
+        }
+        let pairs = u16::from_le_bytes(data.try_into().map_err(|_| ControlCommandError::TooShort)?);
+        if !(1..=max_queue_pairs).contains(&pairs) {
+            return Err(ControlCommandError::InvalidPairCount(pairs));
+        }
+        Ok(pairs)
+    }
+}
+
+/// The reason the coordinator woke up.
+enum CoordinatorEvent {
+    Endpoint(EndpointAction),
+    ControlQueue,
+}
+
 struct CoordinatorState {
     endpoint: Box<dyn Endpoint>,
     adapter: Arc<Adapter>,
@@ -676,12 +885,15 @@ fn inspect_mut(&mut self, req: inspect::Request<'_>, coordinator: Option<&mut Co
             .field_mut("endpoint", self.endpoint.as_mut());
 
         if let Some(coordinator) = coordinator {
-            resp.fields_mut(
-                "queues",
-                coordinator.workers[..coordinator.num_queues as usize]
-                    .iter_mut()
-                    .enumerate(),
-            );
+            resp.field("active_pairs", coordinator.active_pairs)
+                .fields_mut(
+                    "queues",
+                    coordinator
+                        .workers
+                        .iter_mut()
+                        .enumerate()
+                        .filter(|(_, worker)| worker.has_state()),
+                );
         }
     }
 }
@@ -716,14 +928,37 @@ async fn process(
                 self.restart = false;
             }
             self.start_workers();
-            match stop
-                .until_stopped(state.endpoint.wait_for_endpoint_action())
-                .await?
-            {
-                EndpointAction::RestartRequired => self.restart = true,
-                EndpointAction::LinkStatusNotify(_) => {
+            let mut action = state.endpoint.wait_for_endpoint_action();
+            let ctrl = &mut self.ctrl;
+            let event = stop
+                .until_stopped(std::future::poll_fn(|cx| {
+                    if let Poll::Ready(action) = action.as_mut().poll(cx) {
+                        return Poll::Ready(CoordinatorEvent::Endpoint(action));
+                    }
+                    if let Some(ctrl) = ctrl.as_mut()
+                        && let Poll::Ready(()) = ctrl.queue.poll_kick(cx)
+                    {
+                        return Poll::Ready(CoordinatorEvent::ControlQueue);
+                    }
+                    Poll::Pending
+                }))
+                .await?;
+            drop(action);
+            match event {
+                CoordinatorEvent::Endpoint(EndpointAction::RestartRequired) => self.restart = true,
+                CoordinatorEvent::Endpoint(EndpointAction::LinkStatusNotify(_)) => {
                     tracing::error!("unexpected link status notification")
                 }
+                CoordinatorEvent::ControlQueue => {
+                    let max_queue_pairs = state.adapter.max_queue_pairs;
+                    if let Some(pairs) = self.ctrl.as_mut().unwrap().process(max_queue_pairs)
+                        && pairs != self.active_pairs
+                    {
+                        tracing::debug!(pairs, "guest changed active queue pairs");
+                        self.active_pairs = pairs;
+                        self.restart = true;
+                    }
+                }
             }
         }
     }
@@ -740,20 +975,41 @@ async fn restart_queues(&mut self, c_state: &mut CoordinatorState) -> Result<(),
             worker.task_mut().state = None;
         }
 
-        let queue_config = (0..self.workers.len())
+        // Only use the pairs that the guest has both started and enabled.
+        let queue_count = self
+            .workers
+            .iter()
+            .take(self.active_pairs.into())
+            .take_while(|worker| worker.has_state())
+            .count();
+        if queue_count == 0 {
+            return Ok(());
+        }
+
+        let queue_config = (0..queue_count)
             .map(|_| QueueConfig {
                 driver: Box::new(c_state.adapter.driver.clone()),
             })
             .collect::<Vec<_>>();
 
+        // Spread received packets evenly across the queues.
+        let indirection_table = (0..c_state.adapter.indirection_table_size)
+            .map(|i| i % queue_count as u16)
+            .collect::<Vec<_>>();
+        let rss = (queue_count > 1).then(|| RssConfig {
+            key: &DEFAULT_RSS_KEY,
+            indirection_table: &indirection_table,
+            flags: 0,
+        });
+
         let mut queues = Vec::new();
         c_state
             .endpoint
-            .get_queues(queue_config, None, &mut queues)
+            .get_queues(queue_config, rss.as_ref(), &mut queues)
             .await
             .map_err(WorkerError::Endpoint)?;
 
-        assert_eq!(queues.len(), self.workers.len());
+        assert_eq!(queues.len(), queue_count);
 
         for (worker, mut queue) in self.workers.iter_mut().zip(queues) {
             let state = &mut worker.state_mut().unwrap().active_state;
@@ -835,6 +1091,7 @@ struct Worker {
     negotiated_features: NetworkFeaturesBank0,
     #[inspect(skip)]
     negotiated_features_bank1: NetworkFeaturesBank1,
+    busy_poll: bool,
 }
 
 impl Worker {
@@ -868,7 +1125,7 @@ async fn main_loop(
                 | self.process_virtio_tx(epqueue_state)?
                 | self.process_endpoint_tx(epqueue_state.queue.as_mut())?;
 
-            if !did_some_work {
+            if !did_some_work && !self.busy_poll {
                 self.active_state.stats.spurious_wakes.increment();
             }
 
@@ -879,11 +1136,24 @@ async fn main_loop(
             let tx_segments = &self.active_state.data.tx_segments;
             let tx_queue = &mut self.virtio_state.tx_queue;
             let rx_queue = &mut self.virtio_state.rx_queue;
+            let busy_poll = self.busy_poll;
+            let mut yielded = false;
             stop.until_stopped(std::future::poll_fn(|cx| {
                 if let Poll::Ready(()) = epqueue_state.queue.poll_ready(cx, pending_rx_packets) {
                     return Poll::Ready(());
                 }
 
+                if busy_poll {
+                    // Give other tasks a chance to run, then check the queues
+                    // again without arming guest notifications.
+                    if yielded {
+                        return Poll::Ready(());
+                    }
+                    yielded = true;
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+
                 if tx_segments.is_empty()
                     && let Poll::Ready(()) = tx_queue.poll_kick(cx)
                 {
@@ -46,6 +46,7 @@
 use vmcore::vm_task::VmTaskDriverSource;
 
 use crate::Device;
+use crate::NicBuilder;
 
 use crate::NetworkFeaturesBank0;
 use crate::NetworkFeaturesBank1;
@@ -72,7 +73,23 @@
 
 // Data area for TX packet headers and payloads
 const DATA_BASE: u64 = 0x20000;
-const TOTAL_MEM_SIZE: usize = 0x30000;
+const DATA_END: u64 = 0x30000;
+
+// Memory layout for queues 2 and 3 (the second pair's RX and TX)
+const RX1_DESC_ADDR: u64 = 0x30000;
+const RX1_AVAIL_ADDR: u64 = 0x31000;
+const RX1_USED_ADDR: u64 = 0x32000;
+const TX1_DESC_ADDR: u64 = 0x33000;
+const TX1_AVAIL_ADDR: u64 = 0x34000;
+const TX1_USED_ADDR: u64 = 0x35000;
+
+// Memory layout for the control queue
+const CTRL_DESC_ADDR: u64 = 0x36000;
+const CTRL_AVAIL_ADDR: u64 = 0x37000;
+const CTRL_USED_ADDR: u64 = 0x38000;
+const CTRL_DATA_ADDR: u64 = 0x39000;
+
+const TOTAL_MEM_SIZE: usize = 0x40000;
 
 // Virtio-net header size, derived from the actual layout.
 const NET_HEADER_SIZE: u32 = header_size() as u32;
@@ -336,6 +353,9 @@ fn new_mock_queue() -> (MockQueue, MockQueueHandle) {
 
 struct MockEndpoint {
     queue_tx: mesh::Sender<MockQueueHandle>,
+    max_queues: u16,
+    /// The queue count and RSS indirection table of each `get_queues` call.
+    get_queues_log: Arc<Mutex<Vec<(usize, Option<Vec<u16>>)>>>,
 }
 
 impl InspectMut for MockEndpoint {
@@ -352,13 +372,18 @@ fn endpoint_type(&self) -> &'static str {
 
     async fn get_queues(
         &mut self,
-        _config: Vec<QueueConfig>,
-        _rss: Option<&RssConfig<'_>>,
+        config: Vec<QueueConfig>,
+        rss: Option<&RssConfig<'_>>,
         queues: &mut Vec<Box<dyn net_backend::Queue>>,
     ) -> anyhow::Result<()> {
-        let (queue, handle) = new_mock_queue();
-        self.queue_tx.send(handle);
-        queues.push(Box::new(queue));
+        self.get_queues_log
+            .lock()
+            .push((config.len(), rss.map(|rss| rss.indirection_table.to_vec())));
+        for _ in &config {
+            let (queue, handle) = new_mock_queue();
+            self.queue_tx.send(handle);
+            queues.push(Box::new(queue));
+        }
         Ok(())
     }
 
@@ -374,8 +399,8 @@ fn tx_offload_support(&self) -> TxOffloadSupport {
 
     fn multiqueue_support(&self) -> MultiQueueSupport {
         MultiQueueSupport {
-            max_queues: 1,
-            indirection_table_size: 0,
+            max_queues: self.max_queues,
+            indirection_table_size: 8,
         }
     }
 
@@ -432,6 +457,7 @@ struct TestHarness {
     mem: GuestMemory,
     driver: DefaultDriver,
     queue_handle_rx: mesh::Receiver<MockQueueHandle>,
+    get_queues_log: Arc<Mutex<Vec<(usize, Option<Vec<u16>>)>>>,
     rx_event: Event,
     rx_interrupt_event: Event,
     tx_event: Event,
@@ -445,6 +471,12 @@ struct TestHarness {
 
 impl TestHarness {
     fn new(driver: &DefaultDriver) -> Self {
+        Self::with_builder(driver, Device::builder(), 1)
+    }
+
+    /// Create a harness whose device is built by `builder`, backed by an
+    /// endpoint supporting `max_queues` queues.
+    fn with_builder(driver: &DefaultDriver, builder: NicBuilder, max_queues: u16) -> Self {
         let mem = GuestMemory::allocate(TOTAL_MEM_SIZE);
 
         // Initialize RX queue rings
@@ -457,11 +489,16 @@ fn new(driver: &DefaultDriver) -> Self {
 
         // Create mock endpoint with channel
         let (queue_tx, queue_handle_rx) = mesh::channel();
-        let endpoint = MockEndpoint { queue_tx };
+        let get_queues_log = Arc::new(Mutex::new(Vec::new()));
+        let endpoint = MockEndpoint {
+            queue_tx,
+            max_queues,
+            get_queues_log: get_queues_log.clone(),
+        };
 
         let driver_source = VmTaskDriverSource::new(SingleDriverBackend::new(driver.clone()));
         let mac = MacAddress::new([0x00, 0x15, 0x5d, 0xaa, 0xbb, 0xcc]);
-        let device = Device::builder().build(&driver_source, Box::new(endpoint), mac);
+        let device = builder.build(&driver_source, Box::new(endpoint), mac);
 
         let rx_event = Event::new();
         let rx_interrupt_event = Event::new();
@@ -473,6 +510,7 @@ fn new(driver: &DefaultDriver) -> Self {
             mem,
             driver: driver.clone(),
             queue_handle_rx,
+            get_queues_log,
             rx_event,
             rx_interrupt_event,
             tx_event,
@@ -496,54 +534,66 @@ async fn enable_and_get_handle_with_features(
         &mut self,
         features: VirtioDeviceFeatures,
     ) -> MockQueueHandle {
-        let rx_interrupt = Interrupt::from_event(self.rx_interrupt_event.clone());
-        let tx_interrupt = Interrupt::from_event(self.tx_interrupt_event.clone());
-
         // Queue 0: RX
-        self.device
-            .start_queue(
-                0,
-                QueueResources {
-                    params: QueueParams {
-                        size: QUEUE_SIZE,
-                        enable: true,
-                        desc_addr: RX_DESC_ADDR,
-                        avail_addr: RX_AVAIL_ADDR,
-                        used_addr: RX_USED_ADDR,
-                    },
-                    notify: rx_interrupt,
-                    event: self.rx_event.clone(),
-                    guest_memory: self.mem.clone(),
-                },
-                &features,
-                None,
-            )
-            .await
-            .unwrap();
+        let (event, interrupt_event) = (self.rx_event.clone(), self.rx_interrupt_event.clone());
+        self.start_queue(
+            0,
+            [RX_DESC_ADDR, RX_AVAIL_ADDR, RX_USED_ADDR],
+            &event,
+            &interrupt_event,
+            &features,
+        )
+        .await;
 
         // Queue 1: TX
+        let (event, interrupt_event) = (self.tx_event.clone(), self.tx_interrupt_event.clone());
+        self.start_queue(
+            1,
+            [TX_DESC_ADDR, TX_AVAIL_ADDR, TX_USED_ADDR],
+            &event,
+            &interrupt_event,
+            &features,
+        )
+        .await;
+
+        self.next_queue_handle().await
+    }
+
+    /// Start queue `idx` with its descriptor table, avail ring, and used ring
+    /// at `addrs`.
+    async fn start_queue(
+        &mut self,
+        idx: u16,
+        addrs: [u64; 3],
+        event: &Event,
+        interrupt_event: &Event,
+        features: &VirtioDeviceFeatures,
+    ) {
+        let [desc_addr, avail_addr, used_addr] = addrs;
         self.device
             .start_queue(
-                1,
+                idx,
                 QueueResources {
                     params: QueueParams {
                         size: QUEUE_SIZE,
                         enable: true,
-                        desc_addr: TX_DESC_ADDR,
-                        avail_addr: TX_AVAIL_ADDR,
-                        used_addr: TX_USED_ADDR,
+                        desc_addr,
+                        avail_addr,
+                        used_addr,
                     },
-                    notify: tx_interrupt,
-                    event: self.tx_event.clone(),
+                    notify: Interrupt::from_event(interrupt_event.clone()),
+                    event: event.clone(),
                     guest_memory: self.mem.clone(),
                 },
-                &features,
+                features,
                 None,
             )
             .await
             .unwrap();
+    }
 
-        // Wait for the mock endpoint to provide a queue handle
+    /// Wait for the mock endpoint to provide a queue handle.
+    async fn next_queue_handle(&mut self) -> MockQueueHandle {
         mesh::CancelContext::new()
             .with_timeout(Duration::from_secs(5))
             .until_cancelled(self.queue_handle_rx.next())
@@ -556,15 +606,19 @@ async fn enable_and_get_handle_with_features(
     fn alloc_data(&mut self, size: u32) -> u64 {
         let gpa = self.next_data_offset;
         self.next_data_offset += size as u64;
-        assert!(
-            self.next_data_offset <= TOTAL_MEM_SIZE as u64,
-            "ran out of test memory"
-        );
+        assert!(self.next_data_offset <= DATA_END, "ran out of test memory");
         gpa
     }
 
     /// Post a TX packet with a header + one data segment, make it available, and signal.
     fn post_tx_and_signal(&mut self, desc_index: u16, data_len: u32) {
+        self.post_tx(desc_index, data_len);
+        self.tx_event.signal();
+    }
+
+    /// Post a TX packet with a header + one data segment and make it
+    /// available, without signaling the device.
+    fn post_tx(&mut self, desc_index: u16, data_len: u32) {
         let header_gpa = self.alloc_data(NET_HEADER_SIZE);
         let data_gpa = self.alloc_data(data_len);
 
@@ -591,7 +645,6 @@ fn post_tx_and_signal(&mut self, desc_index: u16, data_len: u32) {
             desc_index,
             &mut self.tx_avail_idx,
         );
-        self.tx_event.signal();
     }
 
     /// Wait for the next TX used ring entry with a timeout.
@@ -1693,6 +1746,7 @@ async fn feature_negotiation_with_offloads(driver: DefaultDriver) {
             tso: true,
             uso: true,
         },
+        max_queues: 1,
     };
 
     let device = Device::builder().build(&driver_source, Box::new(endpoint), mac);
@@ -1730,6 +1784,7 @@ async fn feature_negotiation_no_offloads(driver: DefaultDriver) {
 
     let endpoint = MockEndpointWithOffloads {
         offloads: TxOffloadSupport::default(),
+        max_queues: 1,
     };
 
     let device = Device::builder().build(&driver_source, Box::new(endpoint), mac);
@@ -1753,11 +1808,49 @@ async fn feature_negotiation_no_offloads(driver: DefaultDriver) {
         !bank1.host_uso(),
         "HOST_USO should not be set without USO offloads"
     );
+    assert!(!bank0.mq(), "MQ should not be set for a single queue");
+    assert!(
+        !bank0.ctrl_vq(),
+        "CTRL_VQ should not be set for a single queue"
+    );
+    assert_eq!(traits.max_queues, 2);
+}
+
+/// Verify that the device advertises multiqueue support, limited by both the
+/// builder and the endpoint.
+#[async_test]
+async fn feature_negotiation_multiqueue(driver: DefaultDriver) {
+    let driver_source = VmTaskDriverSource::new(SingleDriverBackend::new(driver));
+    let mac = MacAddress::new([0x00, 0x15, 0x5d, 0x01, 0x02, 0x03]);
+
+    for (builder_max, endpoint_max, expected_pairs) in [(!0, 4, 4), (2, 4, 2), (8, 3, 3)] {
+        let endpoint = MockEndpointWithOffloads {
+            offloads: TxOffloadSupport::default(),
+            max_queues: endpoint_max,
+        };
+        let mut device = Device::builder().max_queues(builder_max).build(
+            &driver_source,
+            Box::new(endpoint),
+            mac,
+        );
+        let traits = device.traits();
+
+        let bank0 = NetworkFeaturesBank0::from(traits.device_features.bank(0));
+        assert!(bank0.mq(), "MQ should be set for multiple queues");
+        assert!(bank0.ctrl_vq(), "CTRL_VQ is required for MQ");
+        // The queue pairs plus the control queue.
+        assert_eq!(traits.max_queues, 2 * expected_pairs + 1);
+        assert_eq!(
+            device.read_registers_u32(8).await & 0xffff,
+            expected_pairs as u32
+        );
+    }
 }
 
 // Mock endpoint that reports specific offload support.
 struct MockEndpointWithOffloads {
     offloads: TxOffloadSupport,
+    max_queues: u16,
 }
 
 impl InspectMut for MockEndpointWithOffloads {
@@ -1787,7 +1880,215 @@ fn tx_offload_support(&self) -> TxOffloadSupport {
         self.offloads
     }
 
+    fn multiqueue_support(&self) -> MultiQueueSupport {
+        MultiQueueSupport {
+            max_queues: self.max_queues,
+            indirection_table_size: 128,
+        }
+    }
+
     async fn wait_for_endpoint_action(&mut self) -> EndpointAction {
         pending().await
     }
 }
+
+/// With busy polling, the worker picks up transmits without the guest
+/// signaling the queue.
+#[async_test]
+async fn busy_poll_tx_without_kick(driver: DefaultDriver) {
+    let mut harness = TestHarness::with_builder(&driver, Device::builder().busy_poll(true), 1);
+    let handle = harness.enable_and_get_handle().await;
+
+    harness.post_tx(0, 64);
+    let (used_id, _) = harness.wait_for_used().await;
+    assert_eq!(used_id, 0);
+    assert_eq!(handle.take_tx_avail_log().len(), 1);
+}
+
+/// Send a control queue command and return the device's acknowledgement.
+async fn send_ctrl_command(
+    harness: &TestHarness,
+    event: &Event,
+    interrupt_event: &Event,
+    command: &[u8],
+    avail_idx: &mut u16,
+    used_idx: &mut u16,
+) -> u8 {
+    let ack_gpa = CTRL_DATA_ADDR + 0x100;
+    harness.mem.write_at(CTRL_DATA_ADDR, command).unwrap();
+    harness.mem.write_at(ack_gpa, &[0xff]).unwrap();
+    write_descriptor(
+        &harness.mem,
+        CTRL_DESC_ADDR,
+        0,
+        CTRL_DATA_ADDR,
+        command.len() as u32,
+        DescriptorFlags::new().with_next(true),
+        1,
+    );
+    write_descriptor(
+        &harness.mem,
+        CTRL_DESC_ADDR,
+        1,
+        ack_gpa,
+        1,
+        DescriptorFlags::new().with_write(true),
+        0,
+    );
+    make_available(&harness.mem, CTRL_AVAIL_ADDR, QUEUE_SIZE, 0, avail_idx);
+    event.signal();
+
+    let (used_id, used_len) = wait_for_used(
+        &harness.driver,
+        interrupt_event,
+        &harness.mem,
+        CTRL_USED_ADDR,
+        QUEUE_SIZE,
+        used_idx,
+    )
+    .await;
+    assert_eq!((used_id, used_len), (0, 1));
+    let mut ack = [0];
+    harness.mem.read_at(ack_gpa, &mut ack).unwrap();
+    ack[0]
+}
+
+/// Enable a second queue pair through the control queue and verify that the
+/// endpoint is asked for both queues, with received packets spread across
+/// them, and that the second pair transmits through its own queue.
+#[async_test]
+async fn multiqueue_ctrl_vq_pairs_set(driver: DefaultDriver) {
+    let mut harness = TestHarness::with_builder(&driver, Device::builder().max_queues(2), 2);
+    let features = VirtioDeviceFeatures::new().with_bank(
+        0,
+        NetworkFeaturesBank0::new()
+            .with_mq(true)
+            .with_ctrl_vq(true)
+            .into_bits(),
+    );
+    for addr in [RX1_AVAIL_ADDR, TX1_AVAIL_ADDR, CTRL_AVAIL_ADDR] {
+        init_avail_ring(&harness.mem, addr);
+    }
+    for addr in [RX1_USED_ADDR, TX1_USED_ADDR, CTRL_USED_ADDR] {
+        init_used_ring(&harness.mem, addr);
+    }
+
+    let _handle0 = harness.enable_and_get_handle_with_features(features).await;
+
+    // Start the second pair. Only the first pair is enabled until the guest
+    // asks for more, so this restarts the endpoint with a single queue again.
+    let rx1_event = Event::new();
+    let tx1_event = Event::new();
+    let tx1_interrupt_event = Event::new();
+    harness
+        .start_queue(
+            2,
+            [RX1_DESC_ADDR, RX1_AVAIL_ADDR, RX1_USED_ADDR],
+            &rx1_event,
+            &Event::new(),
+            &features,
+        )
+        .await;
+    harness
+        .start_queue(
+            3,
+            [TX1_DESC_ADDR, TX1_AVAIL_ADDR, TX1_USED_ADDR],
+            &tx1_event,
+            &tx1_interrupt_event,
+            &features,
+        )
+        .await;
+    let _ = harness.next_queue_handle().await;
+
+    // The control queue follows the last pair.
+    let ctrl_event = Event::new();
+    let ctrl_interrupt_event = Event::new();
+    harness
+        .start_queue(
+            4,
+            [CTRL_DESC_ADDR, CTRL_AVAIL_ADDR, CTRL_USED_ADDR],
+            &ctrl_event,
+            &ctrl_interrupt_event,
+            &features,
+        )
+        .await;
+    assert_eq!(harness.device.ctrl_queue_index, Some(4));
+
+    let mut ctrl_avail_idx = 0;
+    let mut ctrl_used_idx = 0;
+
+    // VIRTIO_NET_CTRL_MQ, VIRTIO_NET_CTRL_MQ_VQ_PAIRS_SET, 2 pairs.
+    let ack = send_ctrl_command(
+        &harness,
+        &ctrl_event,
+        &ctrl_interrupt_event,
+        &[4, 0, 2, 0],
+        &mut ctrl_avail_idx,
+        &mut ctrl_used_idx,
+    )
+    .await;
+    assert_eq!(ack, crate::VIRTIO_NET_OK);
+
+    let _handle0 = harness.next_queue_handle().await;
+    let handle1 = harness.next_queue_handle().await;
+    assert_eq!(
+        harness.get_queues_log.lock().last().unwrap(),
+        &(2, Some(vec![0, 1, 0, 1, 0, 1, 0, 1]))
+    );
+
+    // Transmit on the second pair.
+    let header_gpa = harness.alloc_data(NET_HEADER_SIZE + 64);
+    harness
+        .mem
+        .write_at(header_gpa, &[0; NET_HEADER_SIZE as usize])
+        .unwrap();
+    write_descriptor(
+        &harness.mem,
+        TX1_DESC_ADDR,
+        0,
+        header_gpa,
+        NET_HEADER_SIZE + 64,
+        DescriptorFlags::new(),
+        0,
+    );
+    let mut tx1_avail_idx = 0;
+    let mut tx1_used_idx = 0;
+    make_available(
+        &harness.mem,
+        TX1_AVAIL_ADDR,
+        QUEUE_SIZE,
+        0,
+        &mut tx1_avail_idx,
+    );
+    tx1_event.signal();
+    let (used_id, _) = wait_for_used(
+        &harness.driver,
+        &tx1_interrupt_event,
+        &harness.mem,
+        TX1_USED_ADDR,
+        QUEUE_SIZE,
+        &mut tx1_used_idx,
+    )
+    .await;
+    assert_eq!(used_id, 0);
+    let log = handle1.take_tx_avail_log();
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0][0].tx_id, Some(0));
+
+    // More pairs than the device supports is rejected.
+    let ack = send_ctrl_command(
+        &harness,
+        &ctrl_event,
+        &ctrl_interrupt_event,
+        &[4, 0, 3, 0],
+        &mut ctrl_avail_idx,
+        &mut ctrl_used_idx,
+    )
+    .await;
+    assert_eq!(ack, crate::VIRTIO_NET_ERR);
+
+    // Stopping a data queue tears down the control queue with the
+    // coordinator.
+    harness.device.stop_queue(2).await;
+    assert_eq!(harness.device.ctrl_queue_index, None);
+}
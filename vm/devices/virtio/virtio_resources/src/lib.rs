@@ -95,6 +95,53 @@ impl ResourceId<VirtioDeviceHandle> for VirtioRngHandle {
     }
 }
 
+pub mod balloon {
+    use mesh::MeshPayload;
+    use mesh::rpc::Rpc;
+    use vm_resource::ResourceId;
+    use vm_resource::kind::VirtioDeviceHandle;
+
+    #[derive(MeshPayload)]
+    pub struct VirtioBalloonHandle {
+        /// The initial balloon target, in 4KB pages.
+        pub target_pages: u32,
+        /// The channel by which the host adjusts the balloon.
+        pub recv: mesh::Receiver<BalloonRpc>,
+    }
+
+    impl ResourceId<VirtioDeviceHandle> for VirtioBalloonHandle {
+        const ID: &'static str = "virtio-balloon";
+    }
+
+    /// An RPC request to the balloon device.
+    #[derive(MeshPayload)]
+    pub enum BalloonRpc {
+        /// Set the balloon target, in 4KB pages.
+        SetTarget(Rpc<u32, ()>),
+        /// Get the current balloon status and the last statistics reported
+        /// by the guest. This also asks the guest to refresh its statistics.
+        Status(Rpc<(), BalloonStatus>),
+    }
+
+    /// The state of the balloon.
+    #[derive(Debug, MeshPayload)]
+    pub struct BalloonStatus {
+        /// The balloon target, in 4KB pages.
+        pub target_pages: u32,
+        /// The balloon size reported by the guest, in 4KB pages.
+        pub actual_pages: u32,
+        /// The memory statistics last reported by the guest.
+        pub stats: Vec<BalloonStat>,
+    }
+
+    /// A guest memory statistic, as defined in the virtio spec §5.5.6.3.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, MeshPayload)]
+    pub struct BalloonStat {
+        pub tag: u16,
+        pub value: u64,
+    }
+}
+
 pub mod blk {
     use mesh::MeshPayload;
     use vm_resource::Resource;
@@ -126,6 +173,8 @@ pub struct VirtioNetHandle {
         pub max_queues: Option<u16>,
         pub mac_address: MacAddress,
         pub endpoint: Resource<NetEndpointHandleKind>,
+        /// Poll the queues continuously instead of waiting for notifications.
+        pub busy_poll: bool,
     }
 
     impl ResourceId<VirtioDeviceHandle> for VirtioNetHandle {
@@ -115,6 +115,7 @@ pub enum VirtioDeviceType: u16 {
         BLK = 2,
         CONSOLE = 3,
         RNG = 4,
+        BALLOON = 5,
         P9 = 9,
         VSOCK = 19,
         FS = 26,
@@ -3,6 +3,7 @@
 
 //! Internal types for performing asynchronous channel IO.
 
+use crate::trace::PacketTrace;
 use inspect::Inspect;
 use inspect_counters::Counter;
 use pal_async::multi_waker::MultiWaker;
@@ -89,6 +90,7 @@ pub struct ReadState {
     pub signals: Counter,
     ready: bool,
     masked: bool,
+    pub trace: PacketTrace,
 }
 
 impl ReadState {
@@ -102,6 +104,7 @@ pub fn new(ptrs: ring::IncomingOffset) -> Self {
             // setting the mask is an optimization but clearing it is required
             // to avoid missing notifications.
             masked: true,
+            trace: PacketTrace::default(),
         }
     }
 
@@ -173,6 +176,7 @@ pub struct WriteState {
     pub polls: Counter,
     ready: bool,
     pending_size: usize,
+    pub trace: PacketTrace,
 }
 
 impl WriteState {
@@ -183,6 +187,7 @@ pub fn new(ptrs: OutgoingOffset) -> Self {
             polls: Counter::new(),
             ready: false,
             pending_size: 0,
+            trace: PacketTrace::default(),
         }
     }
 
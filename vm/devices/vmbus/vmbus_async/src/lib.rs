@@ -9,3 +9,4 @@
 mod core;
 pub mod pipe;
 pub mod queue;
+mod trace;
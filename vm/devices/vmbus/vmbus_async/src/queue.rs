@@ -127,6 +127,14 @@ fn next_priv(&mut self) -> Result<Option<IncomingPacket<'a, M>>, Error> {
         let mut ptrs = self.read.ptrs.clone();
         match self.core.in_ring().read(&mut ptrs) {
             Ok(packet) => {
+                self.read.trace.incoming(
+                    &packet.typ,
+                    packet.transaction_id,
+                    packet.payload.len(),
+                    |buf| {
+                        let _ = packet.payload.reader(self.core.in_ring()).read(buf);
+                    },
+                );
                 let packet = IncomingPacket::parse(self.core.in_ring(), packet)?;
                 self.read.ptrs = ptrs;
                 Ok(Some(packet))
@@ -639,6 +647,19 @@ pub fn try_write(&mut self, packet: &OutgoingPacket<'_, '_>) -> Result<(), TryWr
                 .map_err(|err| TryWriteError::Queue(ErrorInner::Access(err).into()))?;
         }
         builder.finish();
+        self.write.trace.outgoing(
+            &packet.packet_type,
+            packet.transaction_id,
+            size,
+            |mut buf| {
+                for p in packet.payload {
+                    let n = p.len().min(buf.len());
+                    let (head, rest) = buf.split_at_mut(n);
+                    head.copy_from_slice(&p[..n]);
+                    buf = rest;
+                }
+            },
+        );
         Ok(())
     }
 
@@ -662,6 +683,11 @@ pub fn try_write_aligned(
         let mut builder = self.try_start_write(&ring_packet)?;
         builder.write_aligned_full(data);
         builder.finish();
+        self.write
+            .trace
+            .outgoing(&packet_type, transaction_id, size, |buf| {
+                buf.copy_from_slice(&data.as_bytes()[..buf.len()]);
+            });
         Ok(())
     }
 
@@ -1068,4 +1094,50 @@ async fn test_ring_full(driver: DefaultDriver) {
             })
             .unwrap();
     }
+
+    #[async_test]
+    async fn test_packet_trace() {
+        let (mut host_queue, mut guest_queue) = connected_queues(16384);
+
+        inspect::update("incoming_ring/trace/max_packets", "2", &host_queue)
+            .await
+            .unwrap();
+        inspect::update("incoming_ring/trace/max_payload_bytes", "4", &host_queue)
+            .await
+            .unwrap();
+
+        for i in 0..3u8 {
+            guest_queue
+                .split()
+                .1
+                .write(OutgoingPacket {
+                    transaction_id: i.into(),
+                    packet_type: OutgoingPacketType::InBandWithCompletion,
+                    payload: &[&[i; 16]],
+                })
+                .await
+                .unwrap();
+        }
+        let mut batch = host_queue.split().0.read_batch().await.unwrap();
+        assert_eq!(batch.packets().count(), 3);
+        drop(batch);
+
+        // Only the two most recent packets are kept.
+        let value = |path: &str| {
+            let mut inspection =
+                inspect::inspect(&format!("incoming_ring/trace/packets/{path}"), &host_queue);
+            futures::executor::block_on(inspection.resolve());
+            match inspection.results() {
+                inspect::Node::Value(v) => Some(v.kind),
+                _ => None,
+            }
+        };
+        assert!(value("0/kind").is_none());
+        assert_eq!(value("1/kind"), Some("in_band".into()));
+        assert_eq!(value("2/len"), Some(16usize.into()));
+        assert_eq!(
+            value("2/payload"),
+            Some(inspect::ValueKind::Bytes(vec![2; 4]))
+        );
+    }
 }
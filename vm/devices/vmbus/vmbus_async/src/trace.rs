@@ -0,0 +1,151 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! A rotating in-memory log of the packets that pass through a queue, for
+//! debugging device protocols.
+//!
+//! Tracing is disabled by default. Setting the `max_packets` inspect node to a
+//! non-zero value records the headers of up to that many of the most recent
+//! packets in one direction; setting `max_payload_bytes` also captures the
+//! start of each packet's payload. Since the log is exposed via inspect, it can
+//! be read with `ohcldiag-dev inspect` or the openvmm console.
+
+use inspect::Inspect;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use vmbus_ring::IncomingPacketType;
+use vmbus_ring::OutgoingPacketType;
+
+/// The largest payload capture per packet, to bound the log's memory usage.
+const MAX_PAYLOAD_BYTES: usize = 4096;
+
+#[derive(Debug, Default)]
+pub(crate) struct PacketTrace {
+    /// Whether `state.max_packets` is non-zero, to avoid taking the lock for
+    /// each packet when tracing is disabled.
+    enabled: AtomicBool,
+    state: Mutex<TraceState>,
+}
+
+#[derive(Debug, Default)]
+struct TraceState {
+    max_packets: usize,
+    max_payload_bytes: usize,
+    next_seq: u64,
+    packets: VecDeque<TracedPacket>,
+}
+
+#[derive(Debug, Inspect)]
+struct TracedPacket {
+    #[inspect(skip)]
+    seq: u64,
+    kind: &'static str,
+    #[inspect(with = "|x| x.map(inspect::AsHex)")]
+    transaction_id: Option<u64>,
+    len: usize,
+    #[inspect(with = "inspect::AsBytes")]
+    payload: Vec<u8>,
+}
+
+impl PacketTrace {
+    /// Records an incoming packet.
+    ///
+    /// `read_payload` is called with a buffer to fill with the start of the
+    /// payload, if payloads are being captured.
+    pub fn incoming(
+        &self,
+        typ: &IncomingPacketType,
+        transaction_id: Option<u64>,
+        len: usize,
+        read_payload: impl FnOnce(&mut [u8]),
+    ) {
+        let kind = match typ {
+            IncomingPacketType::InBand => "in_band",
+            IncomingPacketType::Completion => "completion",
+            IncomingPacketType::GpaDirect(..) => "gpa_direct",
+            IncomingPacketType::TransferPages(..) => "transfer_pages",
+        };
+        self.record(kind, transaction_id, len, read_payload);
+    }
+
+    /// Records an outgoing packet.
+    ///
+    /// `read_payload` is called with a buffer to fill with the start of the
+    /// payload, if payloads are being captured.
+    pub fn outgoing(
+        &self,
+        typ: &OutgoingPacketType<'_>,
+        transaction_id: u64,
+        len: usize,
+        read_payload: impl FnOnce(&mut [u8]),
+    ) {
+        let (kind, transaction_id) = match typ {
+            OutgoingPacketType::InBandNoCompletion => ("in_band", None),
+            OutgoingPacketType::InBandWithCompletion => ("in_band", Some(transaction_id)),
+            OutgoingPacketType::Completion => ("completion", Some(transaction_id)),
+            OutgoingPacketType::GpaDirect(_) => ("gpa_direct", Some(transaction_id)),
+            OutgoingPacketType::TransferPages(..) => ("transfer_pages", Some(transaction_id)),
+        };
+        self.record(kind, transaction_id, len, read_payload);
+    }
+
+    fn record(
+        &self,
+        kind: &'static str,
+        transaction_id: Option<u64>,
+        len: usize,
+        read_payload: impl FnOnce(&mut [u8]),
+    ) {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut state = self.state.lock();
+        if state.max_packets == 0 {
+            return;
+        }
+        let mut payload = vec![0; len.min(state.max_payload_bytes)];
+        read_payload(&mut payload);
+        while state.packets.len() >= state.max_packets {
+            state.packets.pop_front();
+        }
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        state.packets.push_back(TracedPacket {
+            seq,
+            kind,
+            transaction_id,
+            len,
+            payload,
+        });
+    }
+}
+
+impl Inspect for PacketTrace {
+    fn inspect(&self, req: inspect::Request<'_>) {
+        let mut state = self.state.lock();
+        let state = &mut *state;
+        req.respond()
+            .field_mut_with("max_packets", |v| {
+                if let Some(v) = v {
+                    state.max_packets = v.parse()?;
+                    let excess = state.packets.len().saturating_sub(state.max_packets);
+                    state.packets.drain(..excess);
+                    self.enabled
+                        .store(state.max_packets != 0, Ordering::Relaxed);
+                }
+                Ok::<_, std::num::ParseIntError>(state.max_packets)
+            })
+            .field_mut_with("max_payload_bytes", |v| {
+                if let Some(v) = v {
+                    state.max_payload_bytes = v.parse::<usize>()?.min(MAX_PAYLOAD_BYTES);
+                }
+                Ok::<_, std::num::ParseIntError>(state.max_payload_bytes)
+            })
+            .field(
+                "packets",
+                inspect::iter_by_key(state.packets.iter().map(|p| (p.seq, p))),
+            );
+    }
+}
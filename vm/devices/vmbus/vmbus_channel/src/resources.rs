@@ -4,6 +4,9 @@
 //! Resource definitions related to vmbus.
 
 use crate::channel::VmbusDevice;
+use guestmem::DiscardRam;
+use guestmem::HotAddRam;
+use std::sync::Arc;
 use vm_resource::CanResolveTo;
 use vm_resource::kind::VmbusDeviceHandleKind;
 use vmcore::vm_task::VmTaskDriverSource;
@@ -16,6 +19,12 @@ impl CanResolveTo<ResolvedVmbusDevice> for VmbusDeviceHandleKind {
 pub struct ResolveVmbusDeviceHandleParams<'a> {
     /// The driver source to use for spawning tasks and IO.
     pub driver_source: &'a VmTaskDriverSource,
+    /// Used to release the backing of guest RAM, if supported by the VM's
+    /// memory configuration.
+    pub discard_ram: Option<Arc<dyn DiscardRam>>,
+    /// Used to add RAM to the VM, if the VM's memory configuration reserves
+    /// space for it.
+    pub hot_add_ram: Option<Arc<dyn HotAddRam>>,
 }
 
 /// A resolved vmbus device.
@@ -35,9 +35,14 @@
 use vmcore::notify::Notify;
 use vmcore::notify::PolledNotify;
 
+/// The maximum number of pages in a channel's ring buffer, since the ring
+/// buffer is described to the host with a single gpadl.
+pub const MAX_RING_PAGES: u16 = (crate::MAX_GPADL_VALUES - 1) as u16;
+
 /// Input parameters when opening a vmbus channel.
 pub struct OpenParams {
-    /// The number of pages to use for the ring buffer.
+    /// The number of pages to use for the ring buffer, including both rings.
+    /// At most [`MAX_RING_PAGES`].
     pub ring_pages: u16,
     /// The offset in pages where the downstream ring starts.
     pub ring_offset_in_pages: u16,
@@ -53,6 +58,20 @@ pub async fn open_channel(
     params: OpenParams,
     dma_client: &dyn DmaClient,
 ) -> anyhow::Result<RawAsyncChannel<MemoryBlockRingMem>> {
+    if params.ring_pages > MAX_RING_PAGES {
+        anyhow::bail!(
+            "ring buffer of {} pages exceeds the maximum of {MAX_RING_PAGES}",
+            params.ring_pages
+        );
+    }
+    if params.ring_offset_in_pages == 0 || params.ring_offset_in_pages >= params.ring_pages {
+        anyhow::bail!(
+            "invalid ring offset {} for ring buffer of {} pages",
+            params.ring_offset_in_pages,
+            params.ring_pages
+        );
+    }
+
     let gpadl =
         dma_client.allocate_dma_buffer(vmbus_ring::PAGE_SIZE * params.ring_pages as usize)?;
 
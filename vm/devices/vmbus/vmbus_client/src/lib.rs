@@ -76,6 +76,10 @@
     .with_client_id(true)
     .with_pause_resume(true);
 
+/// The maximum number of 64-bit values (range headers and page numbers) in a
+/// single gpadl, limited by the 16-bit byte length in the gpadl header.
+pub const MAX_GPADL_VALUES: usize = u16::MAX as usize / size_of::<u64>();
+
 /// The client interface synic events.
 pub trait SynicEventClient: Send + Sync {
     /// Maps an incoming event signal on SINT7 to `event`.
@@ -1242,6 +1246,16 @@ fn handle_restore_channel(
     fn handle_gpadl(&mut self, channel_id: ChannelId, rpc: FailableRpc<GpadlRequest, ()>) {
         let (request, rpc) = rpc.split();
         let mut channel = self.channels.get_mut(channel_id);
+        // The protocol limits the size of a gpadl's range descriptors.
+        let Ok(len) = u16::try_from(request.buf.len() * size_of::<u64>()) else {
+            rpc.fail(anyhow::anyhow!(
+                "gpadl {:#x} is too large: {} values, maximum is {}",
+                request.id.0,
+                request.buf.len(),
+                MAX_GPADL_VALUES
+            ));
+            return;
+        };
         if channel
             .gpadls
             .insert(request.id, GpadlState::Offered(rpc))
@@ -1272,9 +1286,7 @@ fn handle_gpadl(&mut self, channel_id: ChannelId, rpc: FailableRpc<GpadlRequest,
         let message = protocol::GpadlHeader {
             channel_id,
             gpadl_id: request.id,
-            len: (request.buf.len() * size_of::<u64>())
-                .try_into()
-                .expect("Too many GPA values"),
+            len,
             count: request.count,
         };
 
@@ -2683,6 +2695,22 @@ async fn test_hot_add_remove(driver: DefaultDriver) {
         );
     }
 
+    #[async_test]
+    async fn test_gpadl_too_large(driver: DefaultDriver) {
+        let (mut server, mut client) = test_init(&driver);
+        let channel = server.get_channel(&mut client).await;
+        let recv = channel.request_send.call(
+            ChannelRequest::Gpadl,
+            GpadlRequest {
+                id: GpadlId(1),
+                count: 1,
+                buf: vec![0; MAX_GPADL_VALUES + 1],
+            },
+        );
+
+        recv.await.unwrap().unwrap_err();
+    }
+
     #[async_test]
     async fn test_gpadl_success(driver: DefaultDriver) {
         let (mut server, mut client) = test_init(&driver);
@@ -2740,6 +2768,61 @@ async fn test_gpadl_success(driver: DefaultDriver) {
         rpc.await.unwrap();
     }
 
+    #[async_test]
+    async fn test_gpadl_multi_range(driver: DefaultDriver) {
+        let (mut server, mut client) = test_init(&driver);
+        let channel = server.get_channel(&mut client).await;
+
+        // Two ranges whose descriptors do not fit in the header, so the rest
+        // are sent in body messages.
+        let pages = protocol::GpadlHeader::MAX_DATA_VALUES as u64;
+        let mut buf = vec![pages * 4096];
+        buf.extend(0..pages);
+        buf.push(pages * 4096);
+        buf.extend(pages..pages * 2);
+        let recv = channel.request_send.call(
+            ChannelRequest::Gpadl,
+            GpadlRequest {
+                id: GpadlId(1),
+                count: 2,
+                buf: buf.clone(),
+            },
+        );
+
+        let (first, remaining) = buf.split_at(protocol::GpadlHeader::MAX_DATA_VALUES);
+        check_message_with_data(
+            server.next().await.unwrap(),
+            protocol::GpadlHeader {
+                channel_id: ChannelId(0),
+                gpadl_id: GpadlId(1),
+                len: (buf.len() * size_of::<u64>()) as u16,
+                count: 2,
+            },
+            first.as_bytes(),
+        );
+        for chunk in remaining.chunks(protocol::GpadlBody::MAX_DATA_VALUES) {
+            check_message_with_data(
+                server.next().await.unwrap(),
+                protocol::GpadlBody {
+                    rsvd: 0,
+                    gpadl_id: GpadlId(1),
+                },
+                chunk.as_bytes(),
+            );
+        }
+
+        server.send(in_msg(
+            MessageType::GPADL_CREATED,
+            protocol::GpadlCreated {
+                channel_id: ChannelId(0),
+                gpadl_id: GpadlId(1),
+                status: protocol::STATUS_SUCCESS,
+            },
+        ));
+
+        recv.await.unwrap().unwrap();
+    }
+
     #[async_test]
     async fn test_gpadl_fail(driver: DefaultDriver) {
         let (mut server, mut client) = test_init(&driver);
@@ -36,6 +36,7 @@
 use pal_async::task::Task;
 use pal_event::Event;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::future::Future;
 use std::pin::Pin;
@@ -105,6 +106,7 @@ pub async fn new(
         vmbus_client: client::VmbusClientAccess,
         connection: client::ConnectResult,
         intercept_list: Vec<(Guid, mesh::Sender<InterceptChannelRequest>)>,
+        blocked_channels: HashSet<Guid>,
     ) -> Result<Self> {
         if connection.version.feature_flags & REQUIRED_FEATURE_FLAGS != REQUIRED_FEATURE_FLAGS {
             anyhow::bail!(
@@ -124,6 +126,7 @@ pub async fn new(
         );
 
         relay_task.intercept_channels.extend(intercept_list);
+        relay_task.blocked_channels = blocked_channels;
 
         for offer in connection.offers {
             relay_task.handle_offer(offer).await?;
@@ -550,6 +553,9 @@ struct RelayTask {
     channel_workers: FuturesUnordered<Task<ChannelId>>,
     #[inspect(with = "|x| inspect::iter_by_key(x).map_value(|_| ())")]
     intercept_channels: HashMap<Guid, mesh::Sender<InterceptChannelRequest>>,
+    /// Interface and instance IDs of channels that are not relayed.
+    #[inspect(with = "|x| inspect::iter_by_key(x).map_value(|_| ())")]
+    blocked_channels: HashSet<Guid>,
     use_interrupt_relay: Arc<AtomicBool>,
     #[inspect(skip)]
     server_response_send: mesh::Sender<ModifyRelayResponse>,
@@ -580,6 +586,7 @@ fn new(
             channels: HashMap::new(),
             channel_workers: FuturesUnordered::new(),
             intercept_channels: HashMap::new(),
+            blocked_channels: HashSet::new(),
             use_interrupt_relay: Arc::new(AtomicBool::new(false)),
             server_response_send,
             hvsock_relay,
@@ -652,6 +659,20 @@ async fn handle_offer(&mut self, offer: client::OfferInfo) -> Result<()> {
             return Ok(());
         }
 
+        if self.blocked_channels.contains(&offer.offer.interface_id)
+            || self.blocked_channels.contains(&offer.offer.instance_id)
+        {
+            // Dropping the offer releases the channel back to the client, so
+            // it can be released to the host when the host revokes it.
+            tracing::info!(
+                channel_id,
+                interface_id = %offer.offer.interface_id,
+                instance_id = %offer.offer.instance_id,
+                "not relaying blocked channel"
+            );
+            return Ok(());
+        }
+
         if self.channels.contains_key(&ChannelId(channel_id)) {
             anyhow::bail!("channel {channel_id} already exists");
         }
@@ -56,6 +56,10 @@
 use vmcore::save_restore::SavedStateRoot;
 use zerocopy::FromZeros;
 
+/// The minimum number of pages in each ring: one for the control bytes and at
+/// least one for the data.
+pub const MIN_RING_PAGES: u16 = 2;
+
 pub enum OfferResponse {
     Ignore,
     Open,
@@ -77,6 +81,15 @@ pub trait SimpleVmbusClientDevice {
     /// Respond to a new channel offer for a device matching instance_id().
     fn offer(&self, offer: &vmbus_core::protocol::OfferChannel) -> OfferResponse;
 
+    /// Returns the number of pages to use for each of the incoming and
+    /// outgoing rings of an opened offer, including each ring's control page.
+    ///
+    /// High-throughput devices can return more than the minimum of two pages.
+    /// Both rings together must fit in [`vmbus_client::driver::MAX_RING_PAGES`].
+    fn ring_pages(&self, _offer: &vmbus_core::protocol::OfferChannel) -> u16 {
+        MIN_RING_PAGES
+    }
+
     /// Open successful for the channel number `channel_idx`.
     ///
     /// When the channel is closed, the runner will be dropped.
@@ -306,17 +319,29 @@ async fn handle_offer(
             return Ok(());
         }
 
+        let ring_pages = self.device.task_mut().0.ring_pages(&offer.offer);
+        if ring_pages < MIN_RING_PAGES
+            || ring_pages as usize * 2 > vmbus_client::driver::MAX_RING_PAGES as usize
+        {
+            anyhow::bail!("invalid ring size of {ring_pages} pages");
+        }
+
         let interrupt_event = pal_event::Event::new();
         let (memory, ring_gpadl_id) = self
-            .reserve_memory(state, &offer.request_send, 4)
+            .reserve_memory(state, &offer.request_send, ring_pages as usize * 2)
             .await
             .context("reserve memory")?;
         let guest_to_host_interrupt = offer.guest_to_host_interrupt.clone();
         state.offer = Some(offer);
         let offer = state.offer.as_ref().unwrap();
-        self.open_channel(&offer.request_send, ring_gpadl_id, &interrupt_event)
-            .await
-            .context("open channel")?;
+        self.open_channel(
+            &offer.request_send,
+            ring_gpadl_id,
+            ring_pages,
+            &interrupt_event,
+        )
+        .await
+        .context("open channel")?;
         let channel = self
             .create_vmbus_channel(&memory, &interrupt_event, guest_to_host_interrupt)
             .context("create vmbus queue")?;
@@ -436,9 +461,7 @@ async fn reserve_memory(
         request_send: &mesh::Sender<ChannelRequest>,
         page_count: usize,
     ) -> Result<(MemoryBlock, GpadlId)> {
-        // Incoming and outgoing rings require a minimum of two pages apiece:
-        // one for the control bytes and at least one for the ring.
-        assert!(page_count >= 4);
+        assert!(page_count >= MIN_RING_PAGES as usize * 2);
 
         let mem = self
             .dma_alloc
@@ -471,12 +494,13 @@ async fn open_channel(
         &self,
         request_send: &mesh::Sender<ChannelRequest>,
         ring_gpadl_id: GpadlId,
+        ring_pages: u16,
         event: &pal_event::Event,
     ) -> Result<OpenOutput> {
         let open_request = OpenRequest {
             open_data: OpenData {
                 target_vp: Some(0),
-                ring_offset: 2,
+                ring_offset: ring_pages.into(),
                 ring_gpadl_id,
                 event_flag: !0,
                 connection_id: !0,
@@ -502,10 +526,11 @@ fn create_vmbus_channel(
         host_to_guest_event: &pal_event::Event,
         guest_to_host_interrupt: Interrupt,
     ) -> Result<RawAsyncChannel<MemoryBlockRingBuffer>> {
-        let (out_ring_mem, in_ring_mem) = (
-            mem.subblock(0, 2 * PAGE_SIZE),
-            mem.subblock(2 * PAGE_SIZE, 2 * PAGE_SIZE),
-        );
+        // The outgoing ring is first, followed by the incoming ring, each
+        // taking half of the memory.
+        let ring_len = mem.len() / 2;
+        let (out_ring_mem, in_ring_mem) =
+            (mem.subblock(0, ring_len), mem.subblock(ring_len, ring_len));
         let (in_ring, out_ring) = (
             IncomingRing::new(in_ring_mem.into()).unwrap(),
             OutgoingRing::new(out_ring_mem.into()).unwrap(),
@@ -28,6 +28,8 @@
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
 use virt::x86::MsrError;
+use virt::x86::vp::SynicTimer;
+use virt::x86::vp::SynicTimers;
 use vm_topology::processor::VpIndex;
 use zerocopy::IntoBytes;
 
@@ -190,14 +192,16 @@ fn evaluate(
             sints.post_message(sint_index, &message, &mut *interrupt);
         }
 
-        // One-shot timers must be disabled upon expiration.
-        if !self.config.periodic() {
-            self.config.set_enabled(false);
-        }
-
         self.due_time = if self.config.periodic() {
-            Some(ref_time_now.wrapping_add(self.count))
+            // Keep periodic timers on their original schedule so that they
+            // do not drift by the delivery latency. Expirations that were
+            // missed entirely (e.g. because the message slot was full) are
+            // coalesced into this one.
+            let missed = ref_time_now.wrapping_sub(due_time) / self.count;
+            Some(due_time.wrapping_add(self.count.wrapping_mul(missed.wrapping_add(1))))
         } else {
+            // One-shot timers must be disabled upon expiration.
+            self.config.set_enabled(false);
             None
         };
 
@@ -333,6 +337,60 @@ pub fn stimer_count(&self, n: usize) -> u64 {
         self.timers[n].count
     }
 
+    /// Returns the synthetic timer state, for save/restore.
+    ///
+    /// For an armed periodic timer, `adjustment` is the time already elapsed
+    /// in the current period, so that the restored timer next expires `count -
+    /// adjustment` after the restore. A timer that has expired but whose
+    /// message could not be delivered yet reports its expiration time in
+    /// `undelivered_message_expiration_time`.
+    pub fn timers(&self, ref_time_now: u64) -> SynicTimers {
+        SynicTimers {
+            timers: self.timers.each_ref().map(|timer| {
+                let mut saved = SynicTimer {
+                    config: timer.config.into(),
+                    count: timer.count,
+                    adjustment: 0,
+                    undelivered_message_expiration_time: None,
+                };
+                if let (false, Some(due_time)) = (timer.reevaluate, timer.due_time) {
+                    let remaining = due_time.wrapping_sub(ref_time_now) as i64;
+                    if remaining <= 0 {
+                        saved.undelivered_message_expiration_time = Some(due_time);
+                    } else if timer.config.periodic() {
+                        saved.adjustment = timer.count.saturating_sub(remaining as u64);
+                    }
+                }
+                saved
+            }),
+        }
+    }
+
+    /// Restores the synthetic timer state saved by [`Self::timers`].
+    pub fn set_timers(&mut self, state: &SynicTimers, ref_time_now: u64) {
+        for (timer, saved) in self.timers.iter_mut().zip(&state.timers) {
+            let config = HvSynicStimerConfig::from(saved.config);
+            *timer = Timer {
+                config,
+                count: saved.count,
+                reevaluate: true,
+                due_time: None,
+            };
+            if !config.enabled() || saved.count == 0 {
+                continue;
+            }
+            if let Some(expiration_time) = saved.undelivered_message_expiration_time {
+                // Retry delivery on the next scan.
+                timer.reevaluate = false;
+                timer.due_time = Some(expiration_time);
+            } else if config.periodic() && saved.adjustment != 0 {
+                timer.reevaluate = false;
+                timer.due_time =
+                    Some(ref_time_now.wrapping_add(saved.count.saturating_sub(saved.adjustment)));
+            }
+        }
+    }
+
     /// Returns the value of the VINA register.
     pub fn vina(&self) -> HvRegisterVsmVina {
         self.vina
@@ -403,7 +461,12 @@ pub fn set_sint(&mut self, n: usize, v: u64) {
 
     /// Sets the specified synthetic timer configuration register.
     pub fn set_stimer_config(&mut self, n: usize, v: u64) {
-        let config = v.into();
+        let mut config = HvSynicStimerConfig::from(v);
+        // A timer cannot deliver messages to SINT 0, so it cannot be enabled
+        // unless it is in direct mode.
+        if !config.direct_mode() && config.sint() == 0 {
+            config.set_enabled(false);
+        }
         self.timers[n].config = config;
         self.timers[n].reevaluate = true;
     }
@@ -411,7 +474,10 @@ pub fn set_stimer_config(&mut self, n: usize, v: u64) {
     /// Sets the specified synthetic timer count register.
     pub fn set_stimer_count(&mut self, n: usize, v: u64) {
         self.timers[n].count = v;
-        if self.timers[n].config.auto_enable() && self.timers[n].count != 0 {
+        // Writing a zero count disables the timer.
+        if v == 0 {
+            self.timers[n].config.set_enabled(false);
+        } else if self.timers[n].config.auto_enable() {
             self.timers[n].config.set_enabled(true);
         }
 
@@ -721,3 +787,149 @@ fn check_sint_ready(&mut self, sint: u8) -> bool {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::GlobalSynic;
+    use hvdef::HvSynicStimerConfig;
+    use vm_topology::processor::VpIndex;
+
+    #[test]
+    fn test_periodic_timer_schedule() {
+        let synic = GlobalSynic::new(1);
+        let mut vp = synic.add_vp(VpIndex::BSP);
+        let vector = 0x40;
+        vp.set_stimer_count(0, 100);
+        vp.set_stimer_config(
+            0,
+            HvSynicStimerConfig::new()
+                .with_enabled(true)
+                .with_periodic(true)
+                .with_direct_mode(true)
+                .with_apic_vector(vector)
+                .into(),
+        );
+
+        let mut fired = 0;
+        let mut interrupt = |v: u32, _auto_eoi: bool| {
+            assert_eq!(v, vector.into());
+            fired += 1;
+        };
+
+        // Arms relative to the time of the write.
+        assert_eq!(vp.scan(1000, &mut interrupt), (0, Some(1100)));
+        // Late delivery does not shift the schedule.
+        assert_eq!(vp.scan(1130, &mut interrupt), (0, Some(1200)));
+        // Missed expirations are coalesced.
+        assert_eq!(vp.scan(1450, &mut interrupt), (0, Some(1500)));
+        assert_eq!(fired, 2);
+
+        // Writing a zero count disables the timer.
+        vp.set_stimer_count(0, 0);
+        assert_eq!(vp.scan(1500, &mut |_: u32, _: bool| panic!()), (0, None));
+        assert!(!HvSynicStimerConfig::from(vp.stimer_config(0)).enabled());
+    }
+
+    #[test]
+    fn test_one_shot_timer() {
+        let synic = GlobalSynic::new(1);
+        let mut vp = synic.add_vp(VpIndex::BSP);
+        vp.set_stimer_config(
+            0,
+            HvSynicStimerConfig::new()
+                .with_auto_enable(true)
+                .with_direct_mode(true)
+                .with_apic_vector(0x40)
+                .into(),
+        );
+        // Auto-enable arms the timer on the count write, with the count as
+        // an absolute expiration time.
+        vp.set_stimer_count(0, 2000);
+
+        let mut fired = 0;
+        let mut interrupt = |_: u32, _: bool| fired += 1;
+        assert_eq!(vp.scan(1000, &mut interrupt), (0, Some(2000)));
+        assert_eq!(vp.scan(2010, &mut interrupt), (0, None));
+        assert_eq!(fired, 1);
+        assert!(!HvSynicStimerConfig::from(vp.stimer_config(0)).enabled());
+    }
+
+    #[test]
+    fn test_timer_save_restore() {
+        let synic = GlobalSynic::new(2);
+        let mut vp = synic.add_vp(VpIndex::BSP);
+        vp.set_stimer_count(0, 100);
+        vp.set_stimer_config(
+            0,
+            HvSynicStimerConfig::new()
+                .with_enabled(true)
+                .with_periodic(true)
+                .with_direct_mode(true)
+                .with_apic_vector(0x40)
+                .into(),
+        );
+        assert_eq!(
+            vp.scan(1000, &mut |_: u32, _: bool| panic!()),
+            (0, Some(1100))
+        );
+
+        // The saved adjustment is the time elapsed in the current period, not
+        // an absolute time.
+        let saved = vp.timers(1030);
+        assert_eq!(saved.timers[0].adjustment, 30);
+        assert_eq!(saved.timers[0].undelivered_message_expiration_time, None);
+
+        // The restored timer keeps its phase relative to the new reference
+        // time, rather than rearming for a full period.
+        let mut restored = synic.add_vp(VpIndex::new(1));
+        restored.set_timers(&saved, 5030);
+        assert_eq!(restored.stimer_config(0), vp.stimer_config(0));
+        assert_eq!(restored.stimer_count(0), 100);
+        assert_eq!(restored.timers(5030), saved);
+        let mut fired = 0;
+        let mut interrupt = |_: u32, _: bool| fired += 1;
+        assert_eq!(restored.scan(5050, &mut interrupt), (0, Some(5100)));
+        assert_eq!(restored.scan(5100, &mut interrupt), (0, Some(5200)));
+        assert_eq!(fired, 1);
+    }
+
+    #[test]
+    fn test_timer_save_restore_undelivered() {
+        let synic = GlobalSynic::new(2);
+        let mut vp = synic.add_vp(VpIndex::BSP);
+        // SINT 2 is masked, so the timer message cannot be delivered.
+        vp.set_stimer_count(0, 1000);
+        vp.set_stimer_config(
+            0,
+            HvSynicStimerConfig::new()
+                .with_enabled(true)
+                .with_sint(2)
+                .into(),
+        );
+        assert_eq!(vp.scan(1200, &mut |_: u32, _: bool| panic!()), (0, None));
+
+        let saved = vp.timers(1300);
+        assert_eq!(
+            saved.timers[0].undelivered_message_expiration_time,
+            Some(1000)
+        );
+        assert_eq!(
+            SynicTimers::from_hv(saved.as_hv()).timers[0].undelivered_message_expiration_time,
+            Some(1000)
+        );
+
+        let mut restored = synic.add_vp(VpIndex::new(1));
+        restored.set_timers(&saved, 1400);
+        assert_eq!(restored.timers(1400), saved);
+    }
+
+    #[test]
+    fn test_timer_sint_zero() {
+        let synic = GlobalSynic::new(1);
+        let mut vp = synic.add_vp(VpIndex::BSP);
+        vp.set_stimer_count(0, 100);
+        vp.set_stimer_config(0, HvSynicStimerConfig::new().with_enabled(true).into());
+        assert!(!HvSynicStimerConfig::from(vp.stimer_config(0)).enabled());
+        assert_eq!(vp.scan(0, &mut |_: u32, _: bool| panic!()), (0, None));
+    }
+}
@@ -1639,6 +1639,7 @@ pub enum HvInterceptType: u32 {
             HvInterceptTypeX64ApicEoi = 0x0000000E,
             HvInterceptTypeRetargetInterruptWithUnknownDeviceId = 0x0000000F,
             HvInterceptTypeX64IoPortRange = 0x00000011,
+            HvInterceptTypeX64MsrIndex = 0x00000012,
         }
     }
 
@@ -1661,6 +1662,10 @@ pub fn new_exception(vector: u16) -> Self {
             Self(vector as u64)
         }
 
+        pub fn new_msr_index(msr: u32) -> Self {
+            Self(msr as u64)
+        }
+
         pub fn io_port(&self) -> u16 {
             self.0 as u16
         }
@@ -1678,6 +1683,10 @@ pub fn cpuid_index(&self) -> u32 {
         pub fn exception(&self) -> u16 {
             self.0 as u16
         }
+
+        pub fn msr_index(&self) -> u32 {
+            self.0 as u32
+        }
     }
 
     #[repr(C)]
@@ -2928,6 +2937,39 @@ fn from(name: HvAllArchRegisterName) -> Self {
         Cpsr = 0x00020023,
         SpsrEl2 = 0x00021002,
 
+        Q0 = 0x00030000,
+        Q1 = 0x00030001,
+        Q2 = 0x00030002,
+        Q3 = 0x00030003,
+        Q4 = 0x00030004,
+        Q5 = 0x00030005,
+        Q6 = 0x00030006,
+        Q7 = 0x00030007,
+        Q8 = 0x00030008,
+        Q9 = 0x00030009,
+        Q10 = 0x0003000A,
+        Q11 = 0x0003000B,
+        Q12 = 0x0003000C,
+        Q13 = 0x0003000D,
+        Q14 = 0x0003000E,
+        Q15 = 0x0003000F,
+        Q16 = 0x00030010,
+        Q17 = 0x00030011,
+        Q18 = 0x00030012,
+        Q19 = 0x00030013,
+        Q20 = 0x00030014,
+        Q21 = 0x00030015,
+        Q22 = 0x00030016,
+        Q23 = 0x00030017,
+        Q24 = 0x00030018,
+        Q25 = 0x00030019,
+        Q26 = 0x0003001A,
+        Q27 = 0x0003001B,
+        Q28 = 0x0003001C,
+        Q29 = 0x0003001D,
+        Q30 = 0x0003001E,
+        Q31 = 0x0003001F,
+
         SctlrEl1 = 0x00040002,
         Ttbr0El1 = 0x00040005,
         Ttbr1El1 = 0x00040006,
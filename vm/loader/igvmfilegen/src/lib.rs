@@ -0,0 +1,712 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Builds IGVM files from an [`igvmfilegen_config::Config`].
+//!
+//! This is used by the `igvmfilegen` tool, and by tests and fuzzers that need
+//! to build small custom IGVM files in memory.
+
+#![forbid(unsafe_code)]
+
+mod file_loader;
+mod identity_mapping;
+mod signed_measurement;
+mod vp_context_builder;
+
+use crate::file_loader::IgvmLoader;
+use crate::file_loader::LoaderIsolationType;
+use anyhow::Context;
+use anyhow::bail;
+use file_loader::IgvmLoaderRegister;
+use file_loader::IgvmVtlLoader;
+use igvm::IgvmFile;
+use igvm_defs::SnpPolicy;
+use igvm_defs::TdxPolicy;
+use igvmfilegen_config::Config;
+use igvmfilegen_config::ConfigIsolationType;
+use igvmfilegen_config::Image;
+use igvmfilegen_config::LinuxImage;
+use igvmfilegen_config::MissingResourcesError;
+use igvmfilegen_config::ResourceType;
+use igvmfilegen_config::Resources;
+use igvmfilegen_config::SecureAvicType;
+use igvmfilegen_config::SnpInjectionType;
+use igvmfilegen_config::UefiConfigType;
+use loader::common::ReadSeek;
+use loader::importer::Aarch64Register;
+use loader::importer::GuestArch;
+use loader::importer::GuestArchKind;
+use loader::importer::ImageLoad;
+use loader::importer::X86Register;
+use loader::linux::InitrdConfig;
+use loader::paravisor::CommandLineType;
+use loader::paravisor::Vtl0Config;
+use loader::paravisor::Vtl0Linux;
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::io::Read;
+use std::io::Seek;
+
+/// The binary resources to build an IGVM file from.
+#[derive(Debug, Clone, Copy)]
+pub enum ImageResources<'a> {
+    /// Read each resource from the file at its path.
+    Files(&'a Resources),
+    /// Use the in-memory contents of each resource, such as a hand-built or
+    /// deliberately malformed image.
+    Memory(&'a HashMap<ResourceType, Vec<u8>>),
+}
+
+impl<'a> ImageResources<'a> {
+    fn check_required(&self, required: &[ResourceType]) -> Result<(), MissingResourcesError> {
+        match *self {
+            ImageResources::Files(resources) => resources.check_required(required),
+            ImageResources::Memory(resources) => {
+                let missing: Vec<_> = required
+                    .iter()
+                    .copied()
+                    .filter(|resource| !resources.contains_key(resource))
+                    .collect();
+                if missing.is_empty() {
+                    Ok(())
+                } else {
+                    Err(MissingResourcesError(missing))
+                }
+            }
+        }
+    }
+
+    /// Opens `resource`, if present. `name` describes the resource in errors.
+    fn open(
+        &self,
+        resource: ResourceType,
+        name: &str,
+    ) -> anyhow::Result<Option<Box<dyn ReadSeek + 'a>>> {
+        let file: Box<dyn ReadSeek + 'a> = match *self {
+            ImageResources::Files(resources) => {
+                let Some(path) = resources.get(resource) else {
+                    return Ok(None);
+                };
+                Box::new(
+                    fs_err::File::open(path)
+                        .with_context(|| format!("reading {name} at {}", path.display()))?,
+                )
+            }
+            ImageResources::Memory(resources) => {
+                let Some(data) = resources.get(&resource) else {
+                    return Ok(None);
+                };
+                Box::new(Cursor::new(data.as_slice()))
+            }
+        };
+        Ok(Some(file))
+    }
+
+    /// Opens `resource`, which has been validated to be present.
+    fn open_required(
+        &self,
+        resource: ResourceType,
+        name: &str,
+    ) -> anyhow::Result<Box<dyn ReadSeek + 'a>> {
+        Ok(self.open(resource, name)?.expect("validated present"))
+    }
+}
+
+/// An IGVM file built by [`build_igvm_file`].
+pub struct BuiltIgvmFile {
+    /// The IGVM file.
+    pub file: IgvmFile,
+    /// The serialized IGVM file.
+    pub binary: Vec<u8>,
+    /// The human-readable memory map for each guest config, in order.
+    pub maps: Vec<String>,
+    /// The JSON measurement documents for each guest config that produced
+    /// one, along with the name of the guest config's isolation type (such as
+    /// `snp`).
+    pub measurement_docs: Vec<(&'static str, String)>,
+}
+
+/// Builds an IGVM file in memory from `config`, using the binary resources it
+/// requires from `resources`.
+///
+/// If `debug_validation` is set, the serialized file is additionally parsed
+/// back and compared against the in-memory file.
+pub fn build_igvm_file(
+    config: Config,
+    resources: ImageResources<'_>,
+    debug_validation: bool,
+) -> anyhow::Result<BuiltIgvmFile> {
+    resources
+        .check_required(&config.required_resources())
+        .context("required resources not specified")?;
+
+    match config.guest_arch {
+        igvmfilegen_config::GuestArch::X64 => {
+            create_igvm_file::<X86Register>(config, resources, debug_validation)
+        }
+        igvmfilegen_config::GuestArch::Aarch64 => {
+            create_igvm_file::<Aarch64Register>(config, resources, debug_validation)
+        }
+    }
+}
+
+/// Create an IGVM file from the specified config
+fn create_igvm_file<R: IgvmfilegenRegister + GuestArch + 'static>(
+    igvm_config: Config,
+    resources: ImageResources<'_>,
+    debug_validation: bool,
+) -> anyhow::Result<BuiltIgvmFile> {
+    tracing::debug!(?igvm_config, "Creating IGVM file",);
+
+    let mut igvm_file: Option<IgvmFile> = None;
+    let mut maps = Vec::new();
+    let mut measurement_docs = Vec::new();
+    for config in igvm_config.guest_configs {
+        // Max VTL must be 2 or 0.
+        if config.max_vtl != 2 && config.max_vtl != 0 {
+            bail!("max_vtl must be 2 or 0");
+        }
+
+        let isolation_string = match config.isolation_type {
+            ConfigIsolationType::None => "none",
+            ConfigIsolationType::Vbs { .. } => "vbs",
+            ConfigIsolationType::Snp { .. } => "snp",
+            ConfigIsolationType::Tdx { .. } => "tdx",
+        };
+        let loader_isolation_type = match config.isolation_type {
+            ConfigIsolationType::None => LoaderIsolationType::None,
+            ConfigIsolationType::Vbs { enable_debug } => LoaderIsolationType::Vbs { enable_debug },
+            ConfigIsolationType::Snp {
+                shared_gpa_boundary_bits,
+                policy,
+                enable_debug,
+                injection_type,
+                secure_avic,
+            } => LoaderIsolationType::Snp {
+                shared_gpa_boundary_bits,
+                policy: SnpPolicy::from(policy).with_debug(enable_debug as u8),
+                injection_type: match injection_type {
+                    SnpInjectionType::Normal => vp_context_builder::snp::InjectionType::Normal,
+                    SnpInjectionType::Restricted => {
+                        vp_context_builder::snp::InjectionType::Restricted
+                    }
+                },
+                secure_avic: match secure_avic {
+                    SecureAvicType::Enabled => vp_context_builder::snp::SecureAvic::Enabled,
+                    SecureAvicType::Disabled => vp_context_builder::snp::SecureAvic::Disabled,
+                },
+            },
+            ConfigIsolationType::Tdx {
+                enable_debug,
+                sept_ve_disable,
+            } => LoaderIsolationType::Tdx {
+                policy: TdxPolicy::new()
+                    .with_debug_allowed(enable_debug as u8)
+                    .with_sept_ve_disable(sept_ve_disable as u8),
+            },
+        };
+
+        // Max VTL of 2 implies paravisor.
+        let with_paravisor = config.max_vtl == 2;
+
+        let mut loader = IgvmLoader::<R>::new(with_paravisor, loader_isolation_type);
+
+        load_image(&mut loader.loader(), &config.image, &resources)?;
+
+        let igvm_output = loader
+            .finalize(config.guest_svn)
+            .context("finalizing loader")?;
+
+        // Merge the loaded guest into the overall IGVM file.
+        match &mut igvm_file {
+            Some(file) => file
+                .merge_simple(igvm_output.guest)
+                .context("merging guest into overall igvm file")?,
+            None => igvm_file = Some(igvm_output.guest),
+        }
+
+        maps.push(igvm_output.map.to_string());
+
+        if let Some(doc) = igvm_output.doc {
+            measurement_docs.push((
+                isolation_string,
+                serde_json::to_string(&doc).expect("json string"),
+            ));
+        }
+    }
+
+    let mut binary = Vec::new();
+    let file = igvm_file.context("no guest configs specified")?;
+    file.serialize(&mut binary).context("serializing igvm")?;
+
+    // If enabled, perform additional validation.
+    if debug_validation {
+        debug_validate_igvm_file(&file, &binary);
+    }
+
+    Ok(BuiltIgvmFile {
+        file,
+        binary,
+        maps,
+        measurement_docs,
+    })
+}
+
+/// Validate an in-memory IGVM file and the binary repr are equivalent.
+// TODO: should live in the igvm crate
+fn debug_validate_igvm_file(igvm_file: &IgvmFile, binary_file: &[u8]) {
+    use igvm::IgvmDirectiveHeader;
+    tracing::info!("Debug validation of serialized IGVM file.");
+
+    let igvm_reserialized = IgvmFile::new_from_binary(binary_file, None).expect("should be valid");
+
+    for (a, b) in igvm_file
+        .platforms()
+        .iter()
+        .zip(igvm_reserialized.platforms().iter())
+    {
+        assert_eq!(a, b);
+    }
+
+    for (a, b) in igvm_file
+        .initializations()
+        .iter()
+        .zip(igvm_reserialized.initializations().iter())
+    {
+        assert_eq!(a, b);
+    }
+
+    for (a, b) in igvm_file
+        .directives()
+        .iter()
+        .zip(igvm_reserialized.directives().iter())
+    {
+        match (a, b) {
+            (
+                IgvmDirectiveHeader::PageData {
+                    gpa: a_gpa,
+                    flags: a_flags,
+                    data_type: a_data_type,
+                    data: a_data,
+                    compatibility_mask: a_compmask,
+                },
+                IgvmDirectiveHeader::PageData {
+                    gpa: b_gpa,
+                    flags: b_flags,
+                    data_type: b_data_type,
+                    data: b_data,
+                    compatibility_mask: b_compmask,
+                },
+            ) => {
+                assert!(
+                    a_gpa == b_gpa
+                        && a_flags == b_flags
+                        && a_data_type == b_data_type
+                        && a_compmask == b_compmask
+                );
+
+                // data might not be the same length, as it gets padded out.
+                for i in 0..b_data.len() {
+                    if i < a_data.len() {
+                        assert_eq!(a_data[i], b_data[i]);
+                    } else {
+                        assert_eq!(0, b_data[i]);
+                    }
+                }
+            }
+            (
+                IgvmDirectiveHeader::ParameterArea {
+                    number_of_bytes: a_number_of_bytes,
+                    parameter_area_index: a_parameter_area_index,
+                    initial_data: a_initial_data,
+                },
+                IgvmDirectiveHeader::ParameterArea {
+                    number_of_bytes: b_number_of_bytes,
+                    parameter_area_index: b_parameter_area_index,
+                    initial_data: b_initial_data,
+                },
+            ) => {
+                assert!(
+                    a_number_of_bytes == b_number_of_bytes
+                        && a_parameter_area_index == b_parameter_area_index
+                );
+
+                // initial_data might be padded out just like page data.
+                for i in 0..b_initial_data.len() {
+                    if i < a_initial_data.len() {
+                        assert_eq!(a_initial_data[i], b_initial_data[i]);
+                    } else {
+                        assert_eq!(0, b_initial_data[i]);
+                    }
+                }
+            }
+            _ => assert_eq!(a, b),
+        }
+    }
+}
+
+/// A trait to specialize behavior of the file builder based on different
+/// register types for different architectures. Different methods may need to be
+/// called depending on the register type that represents the given architecture.
+trait IgvmfilegenRegister: IgvmLoaderRegister + 'static {
+    fn load_uefi(
+        importer: &mut dyn ImageLoad<Self>,
+        image: &[u8],
+        config: loader::uefi::ConfigType,
+    ) -> Result<loader::uefi::LoadInfo, loader::uefi::Error>;
+
+    fn load_linux_kernel_and_initrd<F>(
+        importer: &mut impl ImageLoad<Self>,
+        kernel_image: &mut F,
+        kernel_minimum_start_address: u64,
+        initrd: Option<InitrdConfig<'_>>,
+        device_tree_blob: Option<&[u8]>,
+    ) -> Result<loader::linux::LoadInfo, loader::linux::Error>
+    where
+        F: std::io::Read + Seek,
+        Self: GuestArch;
+
+    fn load_openhcl<F>(
+        importer: &mut dyn ImageLoad<Self>,
+        kernel_image: &mut F,
+        shim: &mut F,
+        sidecar: Option<&mut F>,
+        command_line: CommandLineType<'_>,
+        initrd: Option<(&mut dyn loader::common::ReadSeek, u64)>,
+        memory_page_base: Option<u64>,
+        memory_page_count: u64,
+        vtl0_config: Vtl0Config<'_>,
+    ) -> Result<(), loader::paravisor::Error>
+    where
+        F: std::io::Read + Seek;
+}
+
+impl IgvmfilegenRegister for X86Register {
+    fn load_uefi(
+        importer: &mut dyn ImageLoad<Self>,
+        image: &[u8],
+        config: loader::uefi::ConfigType,
+    ) -> Result<loader::uefi::LoadInfo, loader::uefi::Error> {
+        loader::uefi::x86_64::load(importer, image, config)
+    }
+
+    fn load_linux_kernel_and_initrd<F>(
+        importer: &mut impl ImageLoad<Self>,
+        kernel_image: &mut F,
+        kernel_minimum_start_address: u64,
+        initrd: Option<InitrdConfig<'_>>,
+        _device_tree_blob: Option<&[u8]>,
+    ) -> Result<loader::linux::LoadInfo, loader::linux::Error>
+    where
+        F: std::io::Read + Seek,
+    {
+        loader::linux::load_kernel_and_initrd_x64(
+            importer,
+            kernel_image,
+            kernel_minimum_start_address,
+            initrd,
+        )
+    }
+
+    fn load_openhcl<F>(
+        importer: &mut dyn ImageLoad<Self>,
+        kernel_image: &mut F,
+        shim: &mut F,
+        sidecar: Option<&mut F>,
+        command_line: CommandLineType<'_>,
+        initrd: Option<(&mut dyn loader::common::ReadSeek, u64)>,
+        memory_page_base: Option<u64>,
+        memory_page_count: u64,
+        vtl0_config: Vtl0Config<'_>,
+    ) -> Result<(), loader::paravisor::Error>
+    where
+        F: std::io::Read + Seek,
+    {
+        loader::paravisor::load_openhcl_x64(
+            importer,
+            kernel_image,
+            shim,
+            sidecar,
+            command_line,
+            initrd,
+            memory_page_base,
+            memory_page_count,
+            vtl0_config,
+        )
+    }
+}
+
+impl IgvmfilegenRegister for Aarch64Register {
+    fn load_uefi(
+        importer: &mut dyn ImageLoad<Self>,
+        image: &[u8],
+        config: loader::uefi::ConfigType,
+    ) -> Result<loader::uefi::LoadInfo, loader::uefi::Error> {
+        loader::uefi::aarch64::load(importer, image, config)
+    }
+
+    fn load_linux_kernel_and_initrd<F>(
+        importer: &mut impl ImageLoad<Self>,
+        kernel_image: &mut F,
+        kernel_minimum_start_address: u64,
+        initrd: Option<InitrdConfig<'_>>,
+        device_tree_blob: Option<&[u8]>,
+    ) -> Result<loader::linux::LoadInfo, loader::linux::Error>
+    where
+        F: std::io::Read + Seek,
+    {
+        loader::linux::load_kernel_and_initrd_arm64(
+            importer,
+            kernel_image,
+            kernel_minimum_start_address,
+            initrd,
+            device_tree_blob,
+        )
+    }
+
+    fn load_openhcl<F>(
+        importer: &mut dyn ImageLoad<Self>,
+        kernel_image: &mut F,
+        shim: &mut F,
+        _sidecar: Option<&mut F>,
+        command_line: CommandLineType<'_>,
+        initrd: Option<(&mut dyn loader::common::ReadSeek, u64)>,
+        memory_page_base: Option<u64>,
+        memory_page_count: u64,
+        vtl0_config: Vtl0Config<'_>,
+    ) -> Result<(), loader::paravisor::Error>
+    where
+        F: std::io::Read + Seek,
+    {
+        loader::paravisor::load_openhcl_arm64(
+            importer,
+            kernel_image,
+            shim,
+            command_line,
+            initrd,
+            memory_page_base,
+            memory_page_count,
+            vtl0_config,
+        )
+    }
+}
+
+/// Load an image.
+fn load_image<R: IgvmfilegenRegister + GuestArch + 'static>(
+    loader: &mut IgvmVtlLoader<'_, R>,
+    config: &Image,
+    resources: &ImageResources<'_>,
+) -> anyhow::Result<()> {
+    tracing::debug!(?config, "loading into VTL0");
+
+    match *config {
+        Image::None => {
+            // Nothing is loaded.
+        }
+        Image::Uefi { config_type } => {
+            load_uefi(loader, resources, config_type)?;
+        }
+        Image::Linux(ref linux) => {
+            load_linux(loader, linux, resources)?;
+        }
+        Image::Openhcl {
+            ref command_line,
+            static_command_line,
+            memory_page_base,
+            memory_page_count,
+            uefi,
+            ref linux,
+        } => {
+            if uefi && linux.is_some() {
+                anyhow::bail!("cannot include both UEFI and Linux images in OpenHCL image");
+            }
+
+            let mut kernel =
+                resources.open_required(ResourceType::UnderhillKernel, "underhill kernel image")?;
+            let mut initrd =
+                resources.open_required(ResourceType::UnderhillInitrd, "underhill initrd")?;
+            let mut shim = resources.open_required(ResourceType::OpenhclBoot, "underhill shim")?;
+            let mut sidecar = resources.open(ResourceType::UnderhillSidecar, "AP kernel")?;
+
+            let initrd_size = initrd.seek(std::io::SeekFrom::End(0))?;
+            initrd.rewind()?;
+            let initrd_info = Some((&mut *initrd as &mut dyn ReadSeek, initrd_size));
+
+            // TODO: While the paravisor supports multiple things that can be
+            // loaded in VTL0, we don't yet have updated file builder config for
+            // that.
+            //
+            // Since the host performs PCAT loading, each image that supports
+            // UEFI also supports PCAT boot. A future file builder config change
+            // will make this more explicit.
+            let vtl0_load_config = if uefi {
+                let mut inner_loader = loader.nested_loader();
+                let load_info = load_uefi(&mut inner_loader, resources, UefiConfigType::None)?;
+                let vp_context = inner_loader.take_vp_context();
+                Vtl0Config {
+                    supports_pcat: loader.loader().arch() == GuestArchKind::X86_64,
+                    supports_uefi: Some((load_info, vp_context)),
+                    supports_linux: None,
+                }
+            } else if let Some(linux) = linux {
+                let load_info = load_linux(&mut loader.nested_loader(), linux, resources)?;
+                Vtl0Config {
+                    supports_pcat: false,
+                    supports_uefi: None,
+                    supports_linux: Some(Vtl0Linux {
+                        command_line: &linux.command_line,
+                        load_info,
+                    }),
+                }
+            } else {
+                Vtl0Config {
+                    supports_pcat: false,
+                    supports_uefi: None,
+                    supports_linux: None,
+                }
+            };
+
+            let command_line = if static_command_line {
+                CommandLineType::Static(command_line)
+            } else {
+                CommandLineType::HostAppendable(command_line)
+            };
+
+            R::load_openhcl(
+                loader,
+                &mut kernel,
+                &mut shim,
+                sidecar.as_mut(),
+                command_line,
+                initrd_info,
+                memory_page_base,
+                memory_page_count,
+                vtl0_load_config,
+            )
+            .context("underhill kernel loader")?;
+        }
+    };
+
+    Ok(())
+}
+
+fn load_uefi<R: IgvmfilegenRegister + GuestArch + 'static>(
+    loader: &mut IgvmVtlLoader<'_, R>,
+    resources: &ImageResources<'_>,
+    config_type: UefiConfigType,
+) -> Result<loader::uefi::LoadInfo, anyhow::Error> {
+    let mut image = Vec::new();
+    resources
+        .open_required(ResourceType::Uefi, "uefi image")?
+        .read_to_end(&mut image)
+        .context("reading uefi image")?;
+    let config = match config_type {
+        UefiConfigType::None => loader::uefi::ConfigType::None,
+        UefiConfigType::Igvm => loader::uefi::ConfigType::Igvm,
+    };
+    let load_info = R::load_uefi(loader, &image, config).context("uefi loader")?;
+    Ok(load_info)
+}
+
+fn load_linux<R: IgvmfilegenRegister + GuestArch + 'static>(
+    loader: &mut IgvmVtlLoader<'_, R>,
+    config: &LinuxImage,
+    resources: &ImageResources<'_>,
+) -> Result<loader::linux::LoadInfo, anyhow::Error> {
+    let LinuxImage {
+        use_initrd,
+        command_line: _,
+    } = *config;
+    let mut kernel = resources.open_required(ResourceType::LinuxKernel, "vtl0 kernel image")?;
+    let mut initrd_file = if use_initrd {
+        Some(resources.open_required(ResourceType::LinuxInitrd, "vtl0 initrd")?)
+    } else {
+        None
+    };
+    let initrd = if let Some(ref mut f) = initrd_file {
+        let size = f.seek(std::io::SeekFrom::End(0))?;
+        f.rewind()?;
+        Some(InitrdConfig {
+            initrd_address: loader::linux::InitrdAddressType::AfterKernel,
+            initrd: &mut **f,
+            size,
+        })
+    } else {
+        None
+    };
+    let load_info = R::load_linux_kernel_and_initrd(loader, &mut kernel, 0, initrd, None)
+        .context("loading linux kernel and initrd")?;
+    Ok(load_info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ImageResources;
+    use super::build_igvm_file;
+    use igvmfilegen_config::Config;
+    use igvmfilegen_config::ConfigIsolationType;
+    use igvmfilegen_config::GuestArch;
+    use igvmfilegen_config::GuestConfig;
+    use igvmfilegen_config::Image;
+    use igvmfilegen_config::LinuxImage;
+    use igvmfilegen_config::ResourceType;
+    use igvmfilegen_config::UefiConfigType;
+    use std::collections::HashMap;
+
+    fn config(image: Image) -> Config {
+        Config {
+            guest_arch: GuestArch::X64,
+            guest_configs: vec![GuestConfig {
+                guest_svn: 0,
+                max_vtl: 0,
+                isolation_type: ConfigIsolationType::None,
+                image,
+            }],
+        }
+    }
+
+    #[test]
+    fn missing_memory_resource() {
+        let Err(err) = build_igvm_file(
+            config(Image::Uefi {
+                config_type: UefiConfigType::None,
+            }),
+            ImageResources::Memory(&HashMap::new()),
+            false,
+        ) else {
+            panic!("build should fail");
+        };
+        assert!(format!("{err:#}").contains("Uefi"), "{err:#}");
+    }
+
+    #[test]
+    fn corrupted_memory_kernel() {
+        let resources = HashMap::from([(ResourceType::LinuxKernel, vec![0xff; 4096])]);
+        let Err(err) = build_igvm_file(
+            config(Image::Linux(LinuxImage {
+                use_initrd: false,
+                command_line: c"".into(),
+            })),
+            ImageResources::Memory(&resources),
+            false,
+        ) else {
+            panic!("build should fail");
+        };
+        assert!(
+            format!("{err:#}").contains("loading linux kernel and initrd"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn empty_image() {
+        let built = build_igvm_file(
+            config(Image::None),
+            ImageResources::Memory(&HashMap::new()),
+            true,
+        )
+        .unwrap();
+        assert_eq!(built.maps.len(), 1);
+        assert!(built.measurement_docs.is_empty());
+    }
+}
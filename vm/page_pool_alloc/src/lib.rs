@@ -3,6 +3,10 @@
 
 //! This module implements a page memory allocator for allocating pages from a
 //! given portion of the guest address space.
+//!
+//! A pool can also return unused pages to the host through a
+//! [`PoolReleaser`], and takes them back when an allocation needs them. This
+//! lets a pool sized for the worst case give its idle memory back.
 
 mod device_dma;
 
@@ -57,6 +61,8 @@ enum InnerSlotState {
             #[mesh(2)]
             tag: String,
         },
+        #[mesh(4)]
+        Released,
     }
 
     #[derive(Protobuf)]
@@ -93,6 +99,7 @@ fn save(&mut self) -> Result<Self::SavedState, vmcore::save_restore::SaveError>
                         let slot = slot.resolve(&state.device_ids);
                         let inner_state = match slot.state {
                             ResolvedSlotState::Free => InnerSlotState::Free,
+                            ResolvedSlotState::Released => InnerSlotState::Released,
                             ResolvedSlotState::Allocated { device_id, tag } => {
                                 InnerSlotState::Allocated {
                                     device_id: device_id.to_string(),
@@ -160,6 +167,7 @@ fn restore(
                 .map(|slot| {
                     let inner = match slot.state {
                         InnerSlotState::Free => SlotState::Free,
+                        InnerSlotState::Released => SlotState::Released,
                         InnerSlotState::Allocated { device_id, tag } => {
                             SlotState::AllocatedPendingRestore { device_id, tag }
                         }
@@ -207,6 +215,9 @@ pub enum Error {
     /// No matching allocation found for restore.
     #[error("no matching allocation found for restore")]
     NoMatchingAllocation,
+    /// Unable to reclaim released pages from the host for the allocation.
+    #[error("failed to reclaim released pages for allocation")]
+    Reclaim(#[source] anyhow::Error),
 }
 
 /// Error returned when unrestored allocations are found.
@@ -225,6 +236,9 @@ struct Slot {
 #[derive(Clone, Debug, PartialEq, Eq)]
 enum SlotState {
     Free,
+    /// These pages are free and have been returned to the host via the pool's
+    /// [`PoolReleaser`]. They must be reclaimed before they are allocated.
+    Released,
     Allocated {
         /// This is an index into the outer [`PagePoolInner`]'s device_ids
         /// vector.
@@ -252,6 +266,7 @@ fn resolve<'a>(&'a self, device_ids: &'a [DeviceId]) -> ResolvedSlot<'a> {
             size_pages: self.size_pages,
             state: match self.state {
                 SlotState::Free => ResolvedSlotState::Free,
+                SlotState::Released => ResolvedSlotState::Released,
                 SlotState::Allocated { device_id, ref tag } => ResolvedSlotState::Allocated {
                     device_id: device_ids[device_id].name(),
                     tag,
@@ -299,6 +314,7 @@ struct ResolvedSlot<'a> {
 #[inspect(external_tag)]
 enum ResolvedSlotState<'a> {
     Free,
+    Released,
     Allocated { device_id: &'a str, tag: &'a str },
     AllocatedPendingRestore { device_id: &'a str, tag: &'a str },
     Leaked { device_id: &'a str, tag: &'a str },
@@ -333,6 +349,47 @@ struct PagePoolInner {
     source: Box<dyn PoolSource>,
     #[inspect(skip)]
     mapping: SparseMapping,
+    /// Used to return free pages to the host. Always locked after `state`.
+    #[inspect(skip)]
+    releaser: Mutex<Option<Box<dyn PoolReleaser>>>,
+}
+
+impl PagePoolInner {
+    fn biased_range(&self, base_pfn: u64, size_pages: u64) -> MemoryRange {
+        let base_pfn = base_pfn + self.pfn_bias;
+        MemoryRange::from_4k_gpn_range(base_pfn..base_pfn + size_pages)
+    }
+
+    /// Reclaims `size_pages` pages from the start of a released slot, leaving
+    /// them free. Returns the index of the free slot.
+    fn reclaim(&self, state: &mut PagePoolState, size_pages: u64) -> Result<Option<usize>, Error> {
+        let Some(index) = state.slots.iter().position(|slot| {
+            matches!(slot.state, SlotState::Released) && slot.size_pages >= size_pages
+        }) else {
+            return Ok(None);
+        };
+
+        let releaser = self.releaser.lock();
+        let releaser = releaser
+            .as_ref()
+            .context("no releaser set for pool")
+            .map_err(Error::Reclaim)?;
+        let slot = &mut state.slots[index];
+        releaser
+            .reclaim(self.biased_range(slot.base_pfn, size_pages))
+            .map_err(Error::Reclaim)?;
+
+        let remaining_slot = (slot.size_pages > size_pages).then(|| Slot {
+            base_pfn: slot.base_pfn + size_pages,
+            mapping_offset: slot.mapping_offset + (size_pages * PAGE_SIZE) as usize,
+            size_pages: slot.size_pages - size_pages,
+            state: SlotState::Released,
+        });
+        slot.size_pages = size_pages;
+        slot.state = SlotState::Free;
+        state.slots.extend(remaining_slot);
+        Ok(Some(index))
+    }
 }
 
 impl Debug for PagePoolInner {
@@ -357,10 +414,33 @@ struct PagePoolState {
 impl Inspect for PagePoolState {
     fn inspect(&self, req: inspect::Request<'_>) {
         let Self { slots, device_ids } = self;
-        req.respond().field(
-            "slots",
-            inspect::iter_by_index(slots).map_value(|s| s.resolve(device_ids)),
-        );
+        let mut total_pages = 0;
+        let mut free_pages = 0;
+        let mut largest_free_pages = 0;
+        let mut leaked_pages = 0;
+        let mut released_pages = 0;
+        for slot in slots {
+            total_pages += slot.size_pages;
+            match slot.state {
+                SlotState::Free => {
+                    free_pages += slot.size_pages;
+                    largest_free_pages = largest_free_pages.max(slot.size_pages);
+                }
+                SlotState::Leaked { .. } => leaked_pages += slot.size_pages,
+                SlotState::Released => released_pages += slot.size_pages,
+                SlotState::Allocated { .. } | SlotState::AllocatedPendingRestore { .. } => {}
+            }
+        }
+        req.respond()
+            .field("total_pages", total_pages)
+            .field("free_pages", free_pages)
+            .field("largest_free_pages", largest_free_pages)
+            .field("leaked_pages", leaked_pages)
+            .field("released_pages", released_pages)
+            .field(
+                "slots",
+                inspect::iter_by_index(slots).map_value(|s| s.resolve(device_ids)),
+            );
     }
 }
 
@@ -439,6 +519,17 @@ pub trait PoolSource: Inspect + Send + Sync {
     fn mappable(&self) -> MappableRef<'_>;
 }
 
+/// A host interface for returning free pool pages while they are not needed.
+///
+/// Ranges are physical addresses including the pool's address bias.
+pub trait PoolReleaser: Send + Sync {
+    /// Returns the pages in `range` to the host. The pool will not access them
+    /// until they are passed to [`Self::reclaim`].
+    fn release(&self, range: MemoryRange) -> anyhow::Result<()>;
+    /// Takes back pages previously passed to [`Self::release`].
+    fn reclaim(&self, range: MemoryRange) -> anyhow::Result<()>;
+}
+
 /// A mapper that uses an internal buffer to map pages. This is meant to be used
 /// for tests that use [`PagePool`].
 #[derive(Inspect)]
@@ -555,6 +646,7 @@ fn new_internal(memory: &[MemoryRange], source: Box<dyn PoolSource>) -> anyhow::
                 pfn_bias: source.address_bias() / PAGE_SIZE,
                 source,
                 mapping,
+                releaser: Mutex::new(None),
             }),
             ranges: memory.to_vec(),
         })
@@ -576,6 +668,72 @@ pub fn allocator_spawner(&self) -> PagePoolAllocatorSpawner {
         }
     }
 
+    /// Sets the interface used to return free pages to the host, enabling
+    /// [`Self::release_free_pages`].
+    ///
+    /// This must also be set after restoring a pool that had released pages,
+    /// since they must be reclaimed before they can be allocated again.
+    pub fn set_releaser(&self, releaser: Box<dyn PoolReleaser>) {
+        *self.inner.releaser.lock() = Some(releaser);
+    }
+
+    /// Returns free pages to the host, keeping at least `keep_free_pages`
+    /// pages free in the pool for allocations that cannot wait for a reclaim.
+    /// Returns the number of pages released.
+    ///
+    /// The caller decides when to call this and how many pages to keep, for
+    /// example on a timer or when VTL2 is under memory pressure. Released pages
+    /// are reclaimed automatically when an allocation cannot be satisfied from
+    /// the remaining free pages.
+    pub fn release_free_pages(&self, keep_free_pages: u64) -> anyhow::Result<u64> {
+        let mut state = self.inner.state.lock();
+        let releaser = self.inner.releaser.lock();
+        let releaser = releaser.as_ref().context("no releaser set for pool")?;
+
+        let mut free_pages: u64 = state
+            .slots
+            .iter()
+            .filter(|slot| matches!(slot.state, SlotState::Free))
+            .map(|slot| slot.size_pages)
+            .sum();
+
+        let mut released_pages = 0;
+        for index in 0..state.slots.len() {
+            if free_pages <= keep_free_pages {
+                break;
+            }
+            let slot = &state.slots[index];
+            if !matches!(slot.state, SlotState::Free) {
+                continue;
+            }
+
+            // Release the tail of the slot if the whole slot would leave too
+            // few free pages.
+            let size_pages = slot.size_pages.min(free_pages - keep_free_pages);
+            let keep_pages = slot.size_pages - size_pages;
+            let base_pfn = slot.base_pfn + keep_pages;
+            let mapping_offset = slot.mapping_offset + (keep_pages * PAGE_SIZE) as usize;
+            releaser.release(self.inner.biased_range(base_pfn, size_pages))?;
+
+            let released_slot = Slot {
+                base_pfn,
+                mapping_offset,
+                size_pages,
+                state: SlotState::Released,
+            };
+            if keep_pages > 0 {
+                state.slots[index].size_pages = keep_pages;
+                state.slots.push(released_slot);
+            } else {
+                state.slots[index] = released_slot;
+            }
+            free_pages -= size_pages;
+            released_pages += size_pages;
+        }
+
+        Ok(released_pages)
+    }
+
     /// Validate that all allocations have been restored. This should be called
     /// after all devices have been restored.
     ///
@@ -592,7 +750,10 @@ pub fn validate_restore(&self, leak_unrestored: bool) -> Result<(), UnrestoredAl
         // Mark unrestored allocations as leaked.
         for slot in inner.slots.iter_mut() {
             match &slot.state {
-                SlotState::Free | SlotState::Allocated { .. } | SlotState::Leaked { .. } => {}
+                SlotState::Free
+                | SlotState::Released
+                | SlotState::Allocated { .. }
+                | SlotState::Leaked { .. } => {}
                 SlotState::AllocatedPendingRestore { device_id, tag } => {
                     tracing::warn!(
                         base_pfn = slot.base_pfn,
@@ -702,19 +863,23 @@ fn alloc_inner(&self, size_pages: NonZeroU64, tag: String) -> Result<PagePoolHan
         let mut inner = self.inner.state.lock();
         let size_pages = size_pages.get();
 
-        let index = inner
-            .slots
-            .iter()
-            .position(|slot| match slot.state {
-                SlotState::Free => slot.size_pages >= size_pages,
-                SlotState::Allocated { .. }
-                | SlotState::AllocatedPendingRestore { .. }
-                | SlotState::Leaked { .. } => false,
-            })
-            .ok_or(Error::PagePoolOutOfMemory {
-                size: size_pages,
-                tag: tag.clone(),
-            })?;
+        let index = inner.slots.iter().position(|slot| match slot.state {
+            SlotState::Free => slot.size_pages >= size_pages,
+            SlotState::Released
+            | SlotState::Allocated { .. }
+            | SlotState::AllocatedPendingRestore { .. }
+            | SlotState::Leaked { .. } => false,
+        });
+
+        // Fall back to reclaiming pages that were returned to the host.
+        let index = match index {
+            Some(index) => Some(index),
+            None => self.inner.reclaim(&mut inner, size_pages)?,
+        }
+        .ok_or(Error::PagePoolOutOfMemory {
+            size: size_pages,
+            tag: tag.clone(),
+        })?;
 
         // Track which slots we should append if the mapping creation succeeds.
         // If the mapping creation fails, we instead commit the original free
@@ -889,12 +1054,15 @@ fn attach_pending_buffers(&self) -> anyhow::Result<Vec<user_driver::memory::Memo
 mod test {
     use crate::PAGE_SIZE;
     use crate::PagePool;
+    use crate::PoolReleaser;
     use crate::PoolSource;
     use crate::TestMapper;
     use inspect::Inspect;
     use memory_range::MemoryRange;
+    use parking_lot::Mutex;
     use safeatomic::AtomicSliceOps;
     use sparse_mmap::MappableRef;
+    use std::sync::Arc;
     use vmcore::save_restore::SaveRestore;
 
     #[derive(Inspect)]
@@ -928,6 +1096,25 @@ fn big_test_mapper() -> TestMapper {
         TestMapper::new(1024 * 1024).unwrap()
     }
 
+    /// Records released and reclaimed ranges.
+    #[derive(Clone, Default)]
+    struct TestReleaser {
+        released: Arc<Mutex<Vec<MemoryRange>>>,
+        reclaimed: Arc<Mutex<Vec<MemoryRange>>>,
+    }
+
+    impl PoolReleaser for TestReleaser {
+        fn release(&self, range: MemoryRange) -> anyhow::Result<()> {
+            self.released.lock().push(range);
+            Ok(())
+        }
+
+        fn reclaim(&self, range: MemoryRange) -> anyhow::Result<()> {
+            self.reclaimed.lock().push(range);
+            Ok(())
+        }
+    }
+
     #[test]
     fn test_basic_alloc() {
         let pfn_bias = 15;
@@ -1152,6 +1339,73 @@ fn test_restore_other_allocator() {
         );
     }
 
+    #[test]
+    fn test_release_and_reclaim() {
+        let pool =
+            PagePool::new(&[MemoryRange::from_4k_gpn_range(10..30)], big_test_mapper()).unwrap();
+        let alloc = pool.allocator("test".into()).unwrap();
+        assert!(pool.release_free_pages(0).is_err());
+
+        let releaser = TestReleaser::default();
+        pool.set_releaser(Box::new(releaser.clone()));
+
+        let _a1 = alloc.alloc(5.try_into().unwrap(), "alloc1".into()).unwrap();
+        assert_eq!(pool.release_free_pages(4).unwrap(), 11);
+        assert_eq!(
+            *releaser.released.lock(),
+            [MemoryRange::from_4k_gpn_range(19..30)]
+        );
+        // Nothing more to release.
+        assert_eq!(pool.release_free_pages(4).unwrap(), 0);
+
+        // Fits in the remaining free pages.
+        let a2 = alloc.alloc(4.try_into().unwrap(), "alloc2".into()).unwrap();
+        assert_eq!(a2.base_pfn, 15);
+        assert!(releaser.reclaimed.lock().is_empty());
+
+        // Requires reclaiming from the host.
+        let a3 = alloc.alloc(6.try_into().unwrap(), "alloc3".into()).unwrap();
+        assert_eq!(a3.base_pfn, 19);
+        assert_eq!(
+            *releaser.reclaimed.lock(),
+            [MemoryRange::from_4k_gpn_range(19..25)]
+        );
+
+        assert!(alloc.alloc(6.try_into().unwrap(), "failed".into()).is_err());
+        let _a4 = alloc.alloc(5.try_into().unwrap(), "alloc4".into()).unwrap();
+        assert_eq!(releaser.reclaimed.lock().len(), 2);
+    }
+
+    #[test]
+    fn test_save_restore_released() {
+        let mut pool =
+            PagePool::new(&[MemoryRange::from_4k_gpn_range(10..30)], big_test_mapper()).unwrap();
+        pool.set_releaser(Box::new(TestReleaser::default()));
+        assert_eq!(pool.release_free_pages(10).unwrap(), 10);
+
+        let state = pool.save().unwrap();
+
+        let mut pool =
+            PagePool::new(&[MemoryRange::from_4k_gpn_range(10..30)], big_test_mapper()).unwrap();
+        pool.restore(state).unwrap();
+        pool.validate_restore(false).unwrap();
+        let alloc = pool.allocator("test".into()).unwrap();
+        let _a1 = alloc
+            .alloc(10.try_into().unwrap(), "alloc1".into())
+            .unwrap();
+
+        // The released pages stay with the host until reclaimed.
+        assert!(alloc.alloc(1.try_into().unwrap(), "failed".into()).is_err());
+        let releaser = TestReleaser::default();
+        pool.set_releaser(Box::new(releaser.clone()));
+        let a2 = alloc.alloc(1.try_into().unwrap(), "alloc2".into()).unwrap();
+        assert_eq!(a2.base_pfn, 20);
+        assert_eq!(
+            *releaser.reclaimed.lock(),
+            [MemoryRange::from_4k_gpn_range(20..21)]
+        );
+    }
+
     #[test]
     fn test_mapping() {
         let pool = PagePool::new(
@@ -52,4 +52,11 @@ pub enum PowerRequest {
         /// The VP that caused the triple fault.
         vp: u32,
     },
+    /// The guest reported a crash.
+    GuestCrash {
+        /// The VP that reported the crash.
+        vp: u32,
+        /// The guest crash parameters (P0 through P4).
+        parameters: [u64; hvdef::HV_X64_GUEST_CRASH_PARAMETER_MSRS],
+    },
 }
@@ -8,6 +8,7 @@
 #![expect(missing_docs)]
 
 pub mod ranges;
+pub mod tracking;
 
 use self::ranges::PagedRange;
 use inspect::Inspect;
@@ -2628,6 +2629,26 @@ pub trait UnmapRom: Send + Sync {
     fn unmap_rom(self);
 }
 
+/// Trait to release the host backing of guest RAM, such as pages the guest
+/// has given up to a balloon.
+///
+/// The guest reads zeros from discarded RAM, and the host allocates new
+/// backing for it on the next access.
+pub trait DiscardRam: Send + Sync {
+    /// Discards the backing of `len` bytes of RAM at `gpa`. Both must be
+    /// page aligned.
+    fn discard_ram(&self, gpa: u64, len: u64) -> io::Result<()>;
+}
+
+/// Trait to add RAM to a running VM.
+pub trait HotAddRam: Send + Sync {
+    /// Backs `len` bytes of new RAM, directly after any previously added RAM,
+    /// and returns its guest physical address. `len` must be page aligned.
+    ///
+    /// The caller is responsible for telling the guest about the new RAM.
+    fn hot_add_ram(&self, len: u64) -> io::Result<u64>;
+}
+
 #[cfg(test)]
 #[expect(clippy::undocumented_unsafe_blocks)]
 mod tests {
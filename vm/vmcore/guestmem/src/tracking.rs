@@ -0,0 +1,563 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Guest memory access tracking, for detecting stray device accesses in
+//! tests.
+//!
+//! An [`AccessTracker`] hands out [`GuestMemory`] objects tagged with a device
+//! name. Every access through these objects is recorded by the tracker, so that
+//! a test can assert that no device touched a range of guest memory during some
+//! operation (for example, that a device does not DMA into a buffer after it
+//! has been unmapped or across servicing).
+//!
+//! When the `bitmap` feature is enabled and the underlying guest memory has a
+//! single mapping, tracked memory exposes that mapping with an access bitmap
+//! that denies every page. Each access therefore faults into the tracker, which
+//! records it and then performs it on the underlying memory. Pages can still be
+//! locked for direct access; the lock is recorded as an access, and the range
+//! counts as touched until the device unlocks it, since the device can access
+//! locked pages without going through the tracker. Otherwise, tracked memory
+//! has no mapping, and locking fails.
+//!
+//! Every access marks its pages in a bitmap, which is never lost. The journal
+//! of individual accesses merges contiguous accesses and is bounded; when it is
+//! full, the oldest entries are dropped. This mode is only intended for tests.
+
+use crate::GuestMemory;
+use crate::GuestMemoryAccess;
+use crate::GuestMemoryBackingError;
+use crate::GuestMemoryError;
+use crate::LockedPages;
+use crate::PAGE_SIZE64;
+use crate::PageFaultAction;
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::ptr::NonNull;
+use std::sync::Arc;
+use std::sync::Mutex;
+use zerocopy::FromBytes;
+use zerocopy::Immutable;
+use zerocopy::IntoBytes;
+use zerocopy::KnownLayout;
+
+/// The default maximum number of entries in the journal.
+const DEFAULT_JOURNAL_LIMIT: usize = 0x10000;
+
+/// The kind of a recorded guest memory access.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AccessKind {
+    /// A read.
+    Read,
+    /// A write (including fills and compare-exchanges).
+    Write,
+    /// Pages were locked for direct access.
+    Lock,
+}
+
+/// A recorded guest memory access.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Access {
+    /// The name of the device that performed the access.
+    pub device: Arc<str>,
+    /// The guest physical address of the access.
+    pub gpa: u64,
+    /// The length of the access in bytes.
+    pub len: u64,
+    /// The kind of access.
+    pub kind: AccessKind,
+}
+
+impl Access {
+    fn end(&self) -> u64 {
+        self.gpa.saturating_add(self.len)
+    }
+
+    fn overlaps(&self, range: &Range<u64>) -> bool {
+        self.gpa < range.end && range.start < self.end()
+    }
+}
+
+/// Pages that a device currently holds locked.
+#[derive(Debug)]
+struct HeldLock {
+    device: Arc<str>,
+    gpns: Box<[u64]>,
+    /// Keeps the pages locked in the inner guest memory.
+    _pages: LockedPages,
+}
+
+#[derive(Debug)]
+struct State {
+    journal: VecDeque<Access>,
+    journal_limit: usize,
+    dropped: u64,
+    /// One bit per page accessed since the tracker was last cleared.
+    touched: Vec<u64>,
+    locks: Vec<HeldLock>,
+}
+
+impl State {
+    fn record(&mut self, device: &Arc<str>, gpa: u64, len: u64, kind: AccessKind) {
+        if len == 0 {
+            return;
+        }
+        let end = gpa.saturating_add(len);
+        for gpn in gpa / PAGE_SIZE64..end.div_ceil(PAGE_SIZE64) {
+            let word = (gpn / 64) as usize;
+            if word >= self.touched.len() {
+                self.touched.resize(word + 1, 0);
+            }
+            self.touched[word] |= 1 << (gpn % 64);
+        }
+
+        // Merge sequential accesses, such as a device streaming through a
+        // buffer, into one entry.
+        if let Some(last) = self.journal.back_mut() {
+            if last.kind == kind && last.end() == gpa && last.device == *device {
+                last.len += len;
+                return;
+            }
+        }
+        if self.journal.len() == self.journal_limit {
+            self.journal.pop_front();
+            self.dropped += 1;
+        }
+        self.journal.push_back(Access {
+            device: device.clone(),
+            gpa,
+            len,
+            kind,
+        });
+    }
+
+    fn any_touched(&self, range: &Range<u64>) -> bool {
+        (range.start / PAGE_SIZE64..range.end.div_ceil(PAGE_SIZE64)).any(|gpn| {
+            self.touched
+                .get((gpn / 64) as usize)
+                .is_some_and(|word| word & (1 << (gpn % 64)) != 0)
+        })
+    }
+
+    fn locks_in(&self, range: &Range<u64>) -> Vec<Access> {
+        self.locks
+            .iter()
+            .flat_map(|lock| {
+                lock.gpns.iter().map(|&gpn| Access {
+                    device: lock.device.clone(),
+                    gpa: gpn * PAGE_SIZE64,
+                    len: PAGE_SIZE64,
+                    kind: AccessKind::Lock,
+                })
+            })
+            .filter(|access| access.overlaps(range))
+            .collect()
+    }
+}
+
+/// Records accesses to guest memory by device.
+#[derive(Debug, Clone)]
+pub struct AccessTracker {
+    inner: GuestMemory,
+    state: Arc<Mutex<State>>,
+    /// A bitmap with no pages accessible, covering the inner mapping.
+    #[cfg(feature = "bitmap")]
+    deny_bitmap: Option<Arc<[u8]>>,
+}
+
+impl AccessTracker {
+    /// Returns a new tracker for accesses to `inner`.
+    pub fn new(inner: GuestMemory) -> Self {
+        #[cfg(feature = "bitmap")]
+        let deny_bitmap = inner
+            .full_mapping()
+            .map(|(_, len)| vec![0; (len as u64).div_ceil(PAGE_SIZE64 * 8) as usize].into());
+        Self {
+            inner,
+            state: Arc::new(Mutex::new(State {
+                journal: VecDeque::new(),
+                journal_limit: DEFAULT_JOURNAL_LIMIT,
+                dropped: 0,
+                touched: Vec::new(),
+                locks: Vec::new(),
+            })),
+            #[cfg(feature = "bitmap")]
+            deny_bitmap,
+        }
+    }
+
+    /// Sets the maximum number of entries kept in the journal. Once it is
+    /// full, the oldest entries are dropped.
+    pub fn with_journal_limit(self, limit: usize) -> Self {
+        assert!(limit > 0, "journal must hold at least one entry");
+        {
+            let mut state = self.state.lock().unwrap();
+            state.journal_limit = limit;
+            while state.journal.len() > limit {
+                state.journal.pop_front();
+                state.dropped += 1;
+            }
+        }
+        self
+    }
+
+    /// Returns a guest memory object for use by `device`. Accesses through it
+    /// are forwarded to the underlying guest memory and recorded.
+    pub fn guest_memory(&self, device: impl Into<Arc<str>>) -> GuestMemory {
+        let device = device.into();
+        #[cfg(feature = "bitmap")]
+        let mapping = self.deny_bitmap.clone().map(|bitmap| {
+            let (ptr, len) = self.inner.full_mapping().expect("mapping was present");
+            DenyMapping {
+                ptr: NonNull::new(ptr).expect("mapping is not null"),
+                len: len as u64,
+                bitmap,
+            }
+        });
+        GuestMemory::new(
+            format!("tracked-{device}"),
+            TrackedMemory {
+                inner: self.inner.clone(),
+                device,
+                state: self.state.clone(),
+                #[cfg(feature = "bitmap")]
+                mapping,
+            },
+        )
+    }
+
+    /// Returns the accesses in the journal, in order.
+    pub fn accesses(&self) -> Vec<Access> {
+        self.state.lock().unwrap().journal.iter().cloned().collect()
+    }
+
+    /// Returns the accesses in the journal that overlap `range`, in order,
+    /// followed by any pages in `range` that are currently locked.
+    pub fn accesses_in(&self, range: Range<u64>) -> Vec<Access> {
+        let state = self.state.lock().unwrap();
+        state
+            .journal
+            .iter()
+            .filter(|access| access.overlaps(&range))
+            .cloned()
+            .chain(state.locks_in(&range))
+            .collect()
+    }
+
+    /// Returns the number of journal entries dropped because the journal was
+    /// full.
+    pub fn dropped(&self) -> u64 {
+        self.state.lock().unwrap().dropped
+    }
+
+    /// Returns whether any device has accessed `range` since the tracker was
+    /// last cleared, or currently holds pages in `range` locked.
+    pub fn is_touched(&self, range: Range<u64>) -> bool {
+        let state = self.state.lock().unwrap();
+        if !state.locks_in(&range).is_empty() {
+            return true;
+        }
+        if !state.any_touched(&range) {
+            return false;
+        }
+        // The bitmap is per page, so use the journal to check the exact
+        // range, unless entries that might have overlapped were dropped.
+        state.dropped > 0 || state.journal.iter().any(|access| access.overlaps(&range))
+    }
+
+    /// Clears the journal and the record of touched pages, so that subsequent
+    /// queries only report accesses from this point on. Pages that are still
+    /// locked remain touched.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.journal.clear();
+        state.dropped = 0;
+        state.touched.clear();
+    }
+
+    /// Panics if any device has accessed `range` since the tracker was last
+    /// cleared, or currently holds pages in `range` locked.
+    #[track_caller]
+    pub fn assert_untouched(&self, range: Range<u64>) {
+        if self.is_touched(range.clone()) {
+            let accesses = self.accesses_in(range.clone());
+            panic!(
+                "guest memory {:#x}-{:#x} was accessed ({} journal entries dropped): {accesses:#x?}",
+                range.start,
+                range.end,
+                self.dropped(),
+            );
+        }
+    }
+}
+
+/// The inner mapping, exposed with a bitmap that denies access to every
+/// page.
+#[cfg(feature = "bitmap")]
+struct DenyMapping {
+    ptr: NonNull<u8>,
+    len: u64,
+    bitmap: Arc<[u8]>,
+}
+
+// SAFETY: the mapping is owned by the inner guest memory, which is kept alive
+// and can be accessed from any thread, and the bitmap is immutable.
+#[cfg(feature = "bitmap")]
+unsafe impl Send for DenyMapping {}
+// SAFETY: see above.
+#[cfg(feature = "bitmap")]
+unsafe impl Sync for DenyMapping {}
+
+struct TrackedMemory {
+    inner: GuestMemory,
+    device: Arc<str>,
+    state: Arc<Mutex<State>>,
+    #[cfg(feature = "bitmap")]
+    mapping: Option<DenyMapping>,
+}
+
+impl TrackedMemory {
+    fn record(&self, gpa: u64, len: usize, kind: AccessKind) {
+        self.state
+            .lock()
+            .unwrap()
+            .record(&self.device, gpa, len as u64, kind);
+    }
+}
+
+fn compare_exchange<T: IntoBytes + FromBytes + Immutable + KnownLayout + Copy>(
+    gm: &GuestMemory,
+    gpa: u64,
+    current: &mut [u8],
+    new: &[u8],
+) -> Result<bool, GuestMemoryError> {
+    let old = T::read_from_bytes(current).unwrap();
+    let new = T::read_from_bytes(new).unwrap();
+    match gm.compare_exchange(gpa, old, new)? {
+        Ok(_) => Ok(true),
+        Err(actual) => {
+            current.copy_from_slice(actual.as_bytes());
+            Ok(false)
+        }
+    }
+}
+
+// SAFETY: the mapping, if any, is the inner guest memory's mapping, which the
+// inner guest memory keeps reserved, and the bitmap covers all of it. Every
+// page is denied in the bitmap, so all accesses other than through locked
+// pages go through the fallback methods, which delegate to the inner
+// `GuestMemory`.
+unsafe impl GuestMemoryAccess for TrackedMemory {
+    fn mapping(&self) -> Option<NonNull<u8>> {
+        #[cfg(feature = "bitmap")]
+        if let Some(mapping) = &self.mapping {
+            return Some(mapping.ptr);
+        }
+        // Force all accesses through the fallback path so that they are
+        // recorded.
+        None
+    }
+
+    fn max_address(&self) -> u64 {
+        #[cfg(feature = "bitmap")]
+        if let Some(mapping) = &self.mapping {
+            return mapping.len;
+        }
+        // The inner guest memory enforces its own bounds.
+        u64::MAX
+    }
+
+    #[cfg(feature = "bitmap")]
+    fn access_bitmap(&self) -> Option<crate::BitmapInfo> {
+        self.mapping.as_ref().map(|mapping| {
+            let bitmap = NonNull::from(&mapping.bitmap[..]).cast();
+            crate::BitmapInfo {
+                read_bitmap: bitmap,
+                write_bitmap: bitmap,
+                bit_offset: 0,
+            }
+        })
+    }
+
+    fn page_fault(
+        &self,
+        _address: u64,
+        _len: usize,
+        _write: bool,
+        bitmap_failure: bool,
+    ) -> PageFaultAction {
+        if bitmap_failure {
+            // Every page is denied in the bitmap; record the access in the
+            // fallback.
+            PageFaultAction::Fallback
+        } else {
+            PageFaultAction::Fail(crate::PageFaultError::other(crate::NotMapped))
+        }
+    }
+
+    unsafe fn read_fallback(
+        &self,
+        addr: u64,
+        dest: *mut u8,
+        len: usize,
+    ) -> Result<(), GuestMemoryBackingError> {
+        self.record(addr, len, AccessKind::Read);
+        // SAFETY: dest is valid for len bytes per the trait contract.
+        let dest = unsafe { std::slice::from_raw_parts_mut(dest, len) };
+        self.inner
+            .read_at(addr, dest)
+            .map_err(|e| GuestMemoryBackingError::other(addr, e))
+    }
+
+    unsafe fn write_fallback(
+        &self,
+        addr: u64,
+        src: *const u8,
+        len: usize,
+    ) -> Result<(), GuestMemoryBackingError> {
+        self.record(addr, len, AccessKind::Write);
+        // SAFETY: src is valid for len bytes per the trait contract.
+        let src = unsafe { std::slice::from_raw_parts(src, len) };
+        self.inner
+            .write_at(addr, src)
+            .map_err(|e| GuestMemoryBackingError::other(addr, e))
+    }
+
+    fn fill_fallback(&self, addr: u64, val: u8, len: usize) -> Result<(), GuestMemoryBackingError> {
+        self.record(addr, len, AccessKind::Write);
+        self.inner
+            .fill_at(addr, val, len)
+            .map_err(|e| GuestMemoryBackingError::other(addr, e))
+    }
+
+    fn compare_exchange_fallback(
+        &self,
+        addr: u64,
+        current: &mut [u8],
+        new: &[u8],
+    ) -> Result<bool, GuestMemoryBackingError> {
+        self.record(addr, new.len(), AccessKind::Write);
+        match new.len() {
+            1 => compare_exchange::<u8>(&self.inner, addr, current, new),
+            2 => compare_exchange::<u16>(&self.inner, addr, current, new),
+            4 => compare_exchange::<u32>(&self.inner, addr, current, new),
+            8 => compare_exchange::<u64>(&self.inner, addr, current, new),
+            len => unreachable!("invalid compare exchange size {len}"),
+        }
+        .map_err(|e| GuestMemoryBackingError::other(addr, e))
+    }
+
+    fn lock_gpns(&self, gpns: &[u64]) -> Result<bool, GuestMemoryBackingError> {
+        let pages = self.inner.lock_gpns(false, gpns).map_err(|e| {
+            GuestMemoryBackingError::other(gpns.first().map_or(0, |gpn| gpn * PAGE_SIZE64), e)
+        })?;
+        let mut state = self.state.lock().unwrap();
+        for &gpn in gpns {
+            state.record(
+                &self.device,
+                gpn * PAGE_SIZE64,
+                PAGE_SIZE64,
+                AccessKind::Lock,
+            );
+        }
+        state.locks.push(HeldLock {
+            device: self.device.clone(),
+            gpns: gpns.into(),
+            _pages: pages,
+        });
+        Ok(true)
+    }
+
+    fn unlock_gpns(&self, gpns: &[u64]) {
+        let mut state = self.state.lock().unwrap();
+        let index = state
+            .locks
+            .iter()
+            .position(|lock| lock.device == self.device && *lock.gpns == *gpns)
+            .expect("unlocking pages that were not locked");
+        state.locks.swap_remove(index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AccessKind;
+    use super::AccessTracker;
+    use crate::GuestMemory;
+
+    #[test]
+    fn track_accesses() {
+        let tracker = AccessTracker::new(GuestMemory::allocate(0x4000));
+        let a = tracker.guest_memory("a");
+        let b = tracker.guest_memory("b");
+
+        a.write_at(0x1000, &[1; 0x10]).unwrap();
+        let mut buf = [0; 0x10];
+        b.read_at(0x1008, &mut buf).unwrap();
+        assert_eq!(buf[..8], [1; 8]);
+        assert_eq!(b.compare_exchange(0x3000, 0u32, 1).unwrap(), Ok(1));
+
+        let accesses = tracker.accesses_in(0x1000..0x1001);
+        assert_eq!(accesses.len(), 1);
+        assert_eq!(&*accesses[0].device, "a");
+        assert_eq!(accesses[0].kind, AccessKind::Write);
+        assert_eq!(tracker.accesses_in(0x1010..0x1018).len(), 1);
+        assert_eq!(tracker.accesses_in(0x3000..0x3004).len(), 1);
+        tracker.assert_untouched(0x2000..0x3000);
+        // The page was touched, but not this part of it.
+        tracker.assert_untouched(0x1018..0x2000);
+
+        tracker.clear();
+        tracker.assert_untouched(0..0x4000);
+    }
+
+    #[test]
+    #[should_panic(expected = "was accessed")]
+    fn assert_untouched_panics() {
+        let tracker = AccessTracker::new(GuestMemory::allocate(0x1000));
+        tracker.guest_memory("a").fill_at(0x800, 0, 1).unwrap();
+        tracker.assert_untouched(0x800..0x801);
+    }
+
+    #[test]
+    fn journal_limit() {
+        let tracker = AccessTracker::new(GuestMemory::allocate(0x4000)).with_journal_limit(2);
+        let gm = tracker.guest_memory("a");
+
+        // Sequential accesses are merged into one entry.
+        for i in 0..0x10 {
+            gm.write_at(i * 0x10, &[0; 0x10]).unwrap();
+        }
+        assert_eq!(tracker.accesses().len(), 1);
+        assert_eq!(tracker.accesses()[0].len, 0x100);
+
+        gm.read_plain::<u8>(0x1000).unwrap();
+        gm.read_plain::<u8>(0x2000).unwrap();
+        assert_eq!(tracker.accesses().len(), 2);
+        assert_eq!(tracker.dropped(), 1);
+
+        // The dropped access is still known to have touched its page.
+        assert!(tracker.is_touched(0x80..0x81));
+        tracker.assert_untouched(0x3000..0x4000);
+    }
+
+    #[cfg(feature = "bitmap")]
+    #[test]
+    fn locked_pages() {
+        let tracker = AccessTracker::new(GuestMemory::allocate(0x4000));
+        let gm = tracker.guest_memory("a");
+
+        let locked = gm.lock_gpns(false, &[1, 2]).unwrap();
+        tracker.clear();
+        // Accesses through the locked pages are not seen, so the locked
+        // range counts as touched until it is unlocked.
+        assert!(tracker.is_touched(0x1000..0x3000));
+        tracker.assert_untouched(0x3000..0x4000);
+
+        drop(locked);
+        tracker.assert_untouched(0..0x4000);
+
+        // Accesses through the mapping are still recorded.
+        gm.write_plain(0x3000, &1u32).unwrap();
+        assert!(tracker.is_touched(0x3000..0x3004));
+    }
+}
@@ -5,6 +5,8 @@
 
 #![forbid(unsafe_code)]
 
+use crate::interrupt_latency::InterruptLatency;
+use crate::interrupt_latency::Stage;
 use mesh::MeshPayload;
 use mesh::payload::DefaultEncoding;
 use mesh::payload::FieldDecode;
@@ -89,6 +91,22 @@ pub fn from_target(target: impl InterruptTarget + 'static) -> Self {
         }
     }
 
+    /// Returns an interrupt that delivers this one, recording in `latency`
+    /// under `vector` the time from signaling to delivery to the target.
+    ///
+    /// Injection is not visible from here, so the later stages are left to
+    /// the component that injects the interrupt, such as the partition.
+    ///
+    /// The returned interrupt is not backed by an event, so it cannot be sent
+    /// to a remote process.
+    pub fn with_latency(&self, latency: InterruptLatency, vector: u32) -> Self {
+        Self::from_target(LatencyTarget {
+            interrupt: self.clone(),
+            latency,
+            vector,
+        })
+    }
+
     /// Delivers the interrupt.
     pub fn deliver(&self) {
         self.inner.t.deliver();
@@ -179,6 +197,22 @@ fn deliver(&self) {
     }
 }
 
+/// Target that records delivery latency for another interrupt.
+struct LatencyTarget {
+    interrupt: Interrupt,
+    latency: InterruptLatency,
+    vector: u32,
+}
+
+impl InterruptTarget for LatencyTarget {
+    fn deliver(&self) {
+        self.latency.signal(self.vector);
+        self.interrupt.deliver();
+        self.latency.record(self.vector, Stage::Delivered);
+        self.latency.discard(self.vector);
+    }
+}
+
 /// Target for null interrupts that lazily creates an event on demand.
 struct NullEventTarget;
 
@@ -282,6 +316,8 @@ async fn run(mut wait: PolledWait<Event>, interrupt: Interrupt) {
 mod tests {
     use super::Interrupt;
     use super::InterruptTarget;
+    use crate::interrupt_latency::InterruptLatency;
+    use crate::interrupt_latency::Stage;
     use pal_async::DefaultDriver;
     use pal_async::async_test;
     use pal_event::Event;
@@ -383,6 +419,23 @@ fn event(&self) -> Option<Arc<Event>> {
         assert!(event.try_wait());
     }
 
+    #[test]
+    fn test_with_latency() {
+        let event = Event::new();
+        let latency = InterruptLatency::new();
+        latency.set_enabled(true);
+        let interrupt = Interrupt::from_event(event.clone()).with_latency(latency.clone(), 5);
+        interrupt.deliver();
+        assert!(event.try_wait());
+        interrupt.deliver();
+        assert!(event.try_wait());
+        // Each delivery is measured from its own signal.
+        assert_eq!(latency.count(Stage::Delivered), 2);
+        // The wrapper cannot see injection.
+        latency.record(5, Stage::Injected);
+        assert!(latency.percentile(Stage::Injected, 50).is_none());
+    }
+
     #[test]
     fn test_clone_shares_state() {
         let count = Arc::new(AtomicUsize::new(0));
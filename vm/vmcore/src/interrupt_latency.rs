@@ -0,0 +1,286 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Optional instrumentation for measuring interrupt delivery latency.
+//!
+//! An [`InterruptLatency`] timestamps an interrupt when it is signaled and
+//! records the time until it reaches each later stage of delivery. Each
+//! interrupt source is identified by a vector, whose meaning is up to the
+//! owner: an MSI-X table index for a device, or an APIC vector for a
+//! partition. Latencies for all vectors are kept in power-of-two microsecond
+//! buckets and summarized as percentiles via `Inspect`.
+//!
+//! Measurement is disabled by default, in which case each signal or stage
+//! costs a single atomic load. Enable it with
+//! [`InterruptLatency::set_enabled`] or by writing `true` to the `enabled`
+//! inspect node.
+
+use inspect::Inspect;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+/// A stage of interrupt delivery, after the interrupt is signaled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Stage {
+    /// The interrupt has been handed to its target, such as an interrupt
+    /// controller, a synic event port, or a relay to another VTL.
+    Delivered,
+    /// The interrupt is pending in the target processor's interrupt
+    /// controller, waiting to be injected.
+    Queued,
+    /// The interrupt has been injected into the guest.
+    Injected,
+}
+
+impl Stage {
+    const ALL: [Stage; 3] = [Stage::Delivered, Stage::Queued, Stage::Injected];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Stage::Delivered => "delivered",
+            Stage::Queued => "queued",
+            Stage::Injected => "injected",
+        }
+    }
+}
+
+/// The number of latency buckets. Bucket `i` holds latencies of less than
+/// `2^i` microseconds, with the last bucket holding everything larger.
+const BUCKETS: usize = 32;
+
+/// Measures delivery latency for the vectors of one or more interrupt sources.
+///
+/// Clones share the same measurements, so the signaling and injecting sides
+/// of the delivery path can each hold a copy.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptLatency {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    enabled: AtomicBool,
+    /// The time of the oldest signal that has not yet been injected, by
+    /// vector.
+    signaled: Mutex<HashMap<u32, Instant>>,
+    stages: [LatencyBuckets; Stage::ALL.len()],
+}
+
+#[derive(Debug, Default)]
+struct LatencyBuckets([AtomicU64; BUCKETS]);
+
+impl LatencyBuckets {
+    fn add(&self, micros: u64) {
+        let bucket = (64 - micros.leading_zeros() as usize).min(BUCKETS - 1);
+        self.0[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the upper bound in microseconds of the bucket containing the
+    /// `percent`th percentile sample, or `None` if there are no samples.
+    fn percentile(&self, percent: u64) -> Option<u64> {
+        let counts = self.0.each_ref().map(|n| n.load(Ordering::Relaxed));
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let target = (total * percent).div_ceil(100).max(1);
+        let mut seen = 0;
+        let bucket = counts
+            .iter()
+            .position(|&n| {
+                seen += n;
+                seen >= target
+            })
+            .unwrap();
+        Some((1u64 << bucket) - 1)
+    }
+
+    fn count(&self) -> u64 {
+        self.0.iter().map(|n| n.load(Ordering::Relaxed)).sum()
+    }
+
+    fn reset(&self) {
+        for n in &self.0 {
+            n.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Inspect for LatencyBuckets {
+    fn inspect(&self, req: inspect::Request<'_>) {
+        let mut resp = req.respond();
+        resp.counter("count", self.count());
+        for percent in [50, 90, 99] {
+            resp.field(&format!("p{percent}_us"), self.percentile(percent));
+        }
+    }
+}
+
+impl InterruptLatency {
+    /// Returns a new, disabled latency tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns whether measurement is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.inner.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Enables or disables measurement. Disabling discards any pending
+    /// signals but keeps the latencies recorded so far.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.inner.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.inner.signaled.lock().clear();
+        }
+    }
+
+    /// Discards all recorded latencies.
+    pub fn reset(&self) {
+        for stage in &self.inner.stages {
+            stage.reset();
+        }
+    }
+
+    /// Records that `vector` was signaled.
+    ///
+    /// If an earlier signal of `vector` has not been injected yet, the signals
+    /// coalesce, as they would for an edge-triggered interrupt, and latency
+    /// continues to be measured from the earlier signal.
+    pub fn signal(&self, vector: u32) {
+        if !self.is_enabled() {
+            return;
+        }
+        self.inner
+            .signaled
+            .lock()
+            .entry(vector)
+            .or_insert_with(Instant::now);
+    }
+
+    /// Records that `vector` reached `stage`. Reaching [`Stage::Injected`]
+    /// completes the pending signal. Does nothing if `vector` has no pending
+    /// signal.
+    pub fn record(&self, vector: u32, stage: Stage) {
+        if !self.is_enabled() {
+            return;
+        }
+        let signaled = {
+            let mut signaled = self.inner.signaled.lock();
+            if stage == Stage::Injected {
+                signaled.remove(&vector)
+            } else {
+                signaled.get(&vector).copied()
+            }
+        };
+        if let Some(signaled) = signaled {
+            self.inner.stages[stage as usize].add(signaled.elapsed().as_micros() as u64);
+        }
+    }
+
+    /// Discards the pending signal of `vector`, if any.
+    ///
+    /// Use this when the remaining stages cannot be observed, such as when the
+    /// interrupt is injected by the hypervisor or by another component that
+    /// tracks its own latency, so that the next signal is measured afresh.
+    pub fn discard(&self, vector: u32) {
+        if !self.is_enabled() {
+            return;
+        }
+        self.inner.signaled.lock().remove(&vector);
+    }
+
+    /// Returns the number of latencies recorded to `stage`.
+    pub fn count(&self, stage: Stage) -> u64 {
+        self.inner.stages[stage as usize].count()
+    }
+
+    /// Returns the upper bound in microseconds of the `percent`th percentile
+    /// latency to `stage`, or `None` if no latencies have been recorded.
+    pub fn percentile(&self, stage: Stage, percent: u64) -> Option<u64> {
+        self.inner.stages[stage as usize].percentile(percent)
+    }
+}
+
+impl Inspect for InterruptLatency {
+    fn inspect(&self, req: inspect::Request<'_>) {
+        let mut resp = req.respond();
+        resp.field_mut_with("enabled", |value| {
+            if let Some(value) = value {
+                self.set_enabled(value.parse()?);
+            }
+            Ok::<_, std::str::ParseBoolError>(self.is_enabled())
+        });
+        resp.field("pending", self.inner.signaled.lock().len());
+        for stage in Stage::ALL {
+            resp.field(stage.name(), &self.inner.stages[stage as usize]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InterruptLatency;
+    use super::LatencyBuckets;
+    use super::Stage;
+
+    #[test]
+    fn percentiles() {
+        let buckets = LatencyBuckets::default();
+        assert_eq!(buckets.percentile(50), None);
+        for micros in [0, 1, 3, 5, 100, 100, 100, 100, 100, 5000] {
+            buckets.add(micros);
+        }
+        assert_eq!(buckets.count(), 10);
+        assert_eq!(buckets.percentile(10), Some(0));
+        assert_eq!(buckets.percentile(30), Some(3));
+        assert_eq!(buckets.percentile(50), Some(127));
+        assert_eq!(buckets.percentile(99), Some(8191));
+    }
+
+    #[test]
+    fn signals_coalesce_until_injected() {
+        let latency = InterruptLatency::new();
+        // Nothing is recorded while disabled.
+        latency.signal(0);
+        latency.record(0, Stage::Injected);
+        assert_eq!(latency.percentile(Stage::Injected, 50), None);
+
+        latency.set_enabled(true);
+        latency.signal(0);
+        latency.signal(0);
+        latency.record(0, Stage::Delivered);
+        latency.record(0, Stage::Queued);
+        latency.record(0, Stage::Injected);
+        // No pending signal, so this is not recorded.
+        latency.record(0, Stage::Injected);
+        assert_eq!(latency.inner.stages[Stage::Delivered as usize].count(), 1);
+        assert_eq!(latency.inner.stages[Stage::Queued as usize].count(), 1);
+        assert_eq!(latency.inner.stages[Stage::Injected as usize].count(), 1);
+
+        latency.reset();
+        assert_eq!(latency.percentile(Stage::Delivered, 50), None);
+    }
+
+    #[test]
+    fn vectors_are_independent() {
+        let latency = InterruptLatency::new();
+        latency.set_enabled(true);
+        latency.signal(1);
+        latency.signal(2);
+        latency.record(1, Stage::Injected);
+        // Injecting vector 1 leaves vector 2 pending.
+        assert_eq!(latency.inner.signaled.lock().len(), 1);
+        latency.record(3, Stage::Injected);
+        assert_eq!(latency.inner.stages[Stage::Injected as usize].count(), 1);
+        latency.record(2, Stage::Injected);
+        assert_eq!(latency.inner.stages[Stage::Injected as usize].count(), 2);
+        assert!(latency.inner.signaled.lock().is_empty());
+    }
+}
@@ -11,6 +11,7 @@
 
 pub mod device_state;
 pub mod interrupt;
+pub mod interrupt_latency;
 pub mod irqfd;
 pub mod isa_dma_channel;
 pub mod line_interrupt;
@@ -8,6 +8,7 @@
 
 use async_trait::async_trait;
 use inspect::Inspect;
+use mesh::payload::Protobuf;
 use std::future::Future;
 use std::sync::Arc;
 use thiserror::Error;
@@ -29,6 +30,26 @@ pub struct VpciInterruptParameters<'a> {
     pub target_processors: &'a [u32],
 }
 
+/// The saved state of a registered interrupt, including its current target.
+#[derive(Debug, Clone, PartialEq, Eq, Protobuf)]
+#[mesh(package = "vmcore.vpci_msi")]
+pub struct SavedInterrupt {
+    /// The MSI address returned at registration.
+    #[mesh(1)]
+    pub address: u64,
+    /// The MSI data returned at registration.
+    #[mesh(2)]
+    pub data: u32,
+    #[mesh(3)]
+    pub vector: u32,
+    #[mesh(4)]
+    pub vector_count: u32,
+    #[mesh(5)]
+    pub multicast: bool,
+    #[mesh(6)]
+    pub target_processors: Vec<u32>,
+}
+
 /// Trait to model host-assisted MSI/MSI-X configuration when using VPCI.
 ///
 /// The VPCI model allows the guest to register interrupts with the host, and
@@ -42,6 +63,32 @@ fn register_interrupt(
     ) -> impl Future<Output = Result<MsiAddressData, RegisterInterruptError>> + Send;
 
     fn unregister_interrupt(&self, address: u64, data: u32) -> impl Future<Output = ()> + Send;
+
+    /// Returns whether the mapper can save its interrupts and restore them at
+    /// the same addresses.
+    fn supports_save_restore(&self) -> bool {
+        false
+    }
+
+    /// Returns the registered interrupts, including any retargeting since
+    /// they were registered.
+    ///
+    /// Only called if [`Self::supports_save_restore`] returns true.
+    fn save_interrupts(&self) -> Vec<SavedInterrupt> {
+        Vec::new()
+    }
+
+    /// Reregisters previously saved interrupts at their saved addresses, so
+    /// that the MSI address/data pairs already programmed into the device
+    /// remain valid.
+    fn restore_interrupts(
+        &self,
+        _interrupts: &[SavedInterrupt],
+    ) -> Result<(), RegisterInterruptError> {
+        Err(RegisterInterruptError::new(
+            "interrupt restore is not supported",
+        ))
+    }
 }
 
 #[async_trait]
@@ -53,6 +100,15 @@ async fn register_interrupt(
     ) -> Result<MsiAddressData, RegisterInterruptError>;
 
     async fn unregister_interrupt(&self, address: u64, data: u32);
+
+    fn supports_save_restore(&self) -> bool;
+
+    fn save_interrupts(&self) -> Vec<SavedInterrupt>;
+
+    fn restore_interrupts(
+        &self,
+        interrupts: &[SavedInterrupt],
+    ) -> Result<(), RegisterInterruptError>;
 }
 
 #[async_trait]
@@ -68,6 +124,21 @@ async fn register_interrupt(
     async fn unregister_interrupt(&self, address: u64, data: u32) {
         self.unregister_interrupt(address, data).await
     }
+
+    fn supports_save_restore(&self) -> bool {
+        self.supports_save_restore()
+    }
+
+    fn save_interrupts(&self) -> Vec<SavedInterrupt> {
+        self.save_interrupts()
+    }
+
+    fn restore_interrupts(
+        &self,
+        interrupts: &[SavedInterrupt],
+    ) -> Result<(), RegisterInterruptError> {
+        self.restore_interrupts(interrupts)
+    }
 }
 
 /// A type-erased [`MapVpciInterrupt`] trait object.
@@ -93,6 +164,21 @@ async fn register_interrupt(
     async fn unregister_interrupt(&self, address: u64, data: u32) {
         self.0.unregister_interrupt(address, data).await
     }
+
+    fn supports_save_restore(&self) -> bool {
+        self.0.supports_save_restore()
+    }
+
+    fn save_interrupts(&self) -> Vec<SavedInterrupt> {
+        self.0.save_interrupts()
+    }
+
+    fn restore_interrupts(
+        &self,
+        interrupts: &[SavedInterrupt],
+    ) -> Result<(), RegisterInterruptError> {
+        self.0.restore_interrupts(interrupts)
+    }
 }
 
 #[derive(Debug, Error)]
@@ -89,6 +89,10 @@ pub enum Error {
     /// Cryptographic error
     #[error("Cryptographic error: {0}")]
     Crypto(#[source] crypto::aes_256_gcm::Aes256GcmError),
+    #[cfg(feature = "encryption")]
+    /// Invalid sealed vTPM state
+    #[error("Invalid vTPM state: {0}")]
+    InvalidTpmState(&'static str),
 
     /// Serde JSON error
     #[error("Serde JSON error: {0}")]
@@ -27,6 +27,8 @@
 mod encrypt;
 mod error;
 mod storage;
+#[cfg(feature = "encryption")]
+pub mod tpm_state;
 mod vmgs_impl;
 
 pub use error::Error;
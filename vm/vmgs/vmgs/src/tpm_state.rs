@@ -0,0 +1,147 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Sealing of the vTPM state (the TPM_NVRAM and TPM_PPI files) for moving a
+//! vTPM to a VM on another host.
+//!
+//! The state is sealed into a single blob with AES-256-GCM under a
+//! caller-provided transport key, which is independent of the key used to
+//! encrypt the VMGS file itself. The blob header is authenticated as
+//! additional data, so it cannot be altered without failing to unseal.
+
+use crate::error::Error;
+use crypto::aes_256_gcm::Aes256Gcm;
+use crypto::aes_256_gcm::IV_LEN;
+
+/// The length of the transport key.
+pub const TRANSPORT_KEY_LEN: usize = crypto::aes_256_gcm::KEY_LEN;
+
+const MAGIC: [u8; 8] = *b"VTPMSTAT";
+const VERSION: u32 = 1;
+const TAG_LEN: usize = 16;
+/// The authenticated part of the header: the magic, version, and nonce.
+const AAD_LEN: usize = MAGIC.len() + size_of::<u32>() + IV_LEN;
+const HEADER_LEN: usize = AAD_LEN + TAG_LEN;
+
+/// The vTPM state stored in a VMGS file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TpmState {
+    /// The contents of the TPM_NVRAM file.
+    pub nvram: Vec<u8>,
+    /// The contents of the TPM_PPI file, or empty if the guest never
+    /// requested a physical presence operation.
+    pub ppi: Vec<u8>,
+}
+
+/// Seals `state` into a blob encrypted under `transport_key`.
+pub fn seal(state: &TpmState, transport_key: &[u8; TRANSPORT_KEY_LEN]) -> Result<Vec<u8>, Error> {
+    let mut plaintext =
+        Vec::with_capacity(2 * size_of::<u32>() + state.nvram.len() + state.ppi.len());
+    for data in [&state.nvram, &state.ppi] {
+        plaintext.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        plaintext.extend_from_slice(data);
+    }
+
+    let mut nonce = [0; IV_LEN];
+    getrandom::fill(&mut nonce).expect("rng failure");
+
+    let mut blob = Vec::with_capacity(HEADER_LEN + plaintext.len());
+    blob.extend_from_slice(&MAGIC);
+    blob.extend_from_slice(&VERSION.to_le_bytes());
+    blob.extend_from_slice(&nonce);
+
+    let mut tag = [0; TAG_LEN];
+    let ciphertext = Aes256Gcm::new(transport_key)
+        .map_err(Error::Crypto)?
+        .encrypt()
+        .map_err(Error::Crypto)?
+        .cipher_with_aad(&nonce, &blob, &plaintext, &mut tag)
+        .map_err(Error::Crypto)?;
+
+    blob.extend_from_slice(&tag);
+    blob.extend_from_slice(&ciphertext);
+    Ok(blob)
+}
+
+/// Unseals a blob created by [`seal`].
+pub fn unseal(blob: &[u8], transport_key: &[u8; TRANSPORT_KEY_LEN]) -> Result<TpmState, Error> {
+    let (header, ciphertext) = blob
+        .split_at_checked(HEADER_LEN)
+        .ok_or(Error::InvalidTpmState("truncated header"))?;
+    let (aad, tag) = header.split_at(AAD_LEN);
+    let (magic, rest) = aad.split_at(MAGIC.len());
+    let (version, nonce) = rest.split_at(size_of::<u32>());
+    if magic != MAGIC {
+        return Err(Error::InvalidTpmState("bad magic"));
+    }
+    if u32::from_le_bytes(version.try_into().unwrap()) != VERSION {
+        return Err(Error::InvalidTpmState("unsupported version"));
+    }
+
+    let plaintext = Aes256Gcm::new(transport_key)
+        .map_err(Error::Crypto)?
+        .decrypt()
+        .map_err(Error::Crypto)?
+        .cipher_with_aad(nonce.try_into().unwrap(), aad, ciphertext, tag)
+        .map_err(Error::Crypto)?;
+
+    let mut rest = plaintext.as_slice();
+    let nvram = split_entry(&mut rest)?.to_vec();
+    let ppi = split_entry(&mut rest)?.to_vec();
+    if !rest.is_empty() {
+        return Err(Error::InvalidTpmState("trailing data"));
+    }
+    if nvram.is_empty() {
+        return Err(Error::InvalidTpmState("missing TPM NVRAM"));
+    }
+    Ok(TpmState { nvram, ppi })
+}
+
+/// Splits a length-prefixed entry off the front of `rest`.
+fn split_entry<'a>(rest: &mut &'a [u8]) -> Result<&'a [u8], Error> {
+    let data: &'a [u8] = rest;
+    let (len, data) = data
+        .split_first_chunk::<4>()
+        .ok_or(Error::InvalidTpmState("truncated length"))?;
+    let (entry, remaining) = data
+        .split_at_checked(u32::from_le_bytes(*len) as usize)
+        .ok_or(Error::InvalidTpmState("truncated data"))?;
+    *rest = remaining;
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seal_round_trip() {
+        let state = TpmState {
+            nvram: b"nvram".to_vec(),
+            ppi: b"ppi".to_vec(),
+        };
+        let blob = seal(&state, &[7; TRANSPORT_KEY_LEN]).unwrap();
+        assert_eq!(unseal(&blob, &[7; TRANSPORT_KEY_LEN]).unwrap(), state);
+
+        // The wrong transport key must fail authentication.
+        assert!(matches!(
+            unseal(&blob, &[8; TRANSPORT_KEY_LEN]),
+            Err(Error::Crypto(_))
+        ));
+
+        // So must any change to the authenticated header or the ciphertext.
+        for offset in [MAGIC.len() + size_of::<u32>(), HEADER_LEN] {
+            let mut tampered = blob.clone();
+            tampered[offset] ^= 1;
+            assert!(matches!(
+                unseal(&tampered, &[7; TRANSPORT_KEY_LEN]),
+                Err(Error::Crypto(_))
+            ));
+        }
+
+        assert!(matches!(
+            unseal(&blob[..HEADER_LEN - 1], &[7; TRANSPORT_KEY_LEN]),
+            Err(Error::InvalidTpmState("truncated header"))
+        ));
+    }
+}
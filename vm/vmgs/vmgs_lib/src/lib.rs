@@ -20,6 +20,9 @@
 use std::path::PathBuf;
 use vmgs::EncryptionAlgorithm;
 use vmgs::Vmgs;
+use vmgs::tpm_state;
+use vmgs::tpm_state::TRANSPORT_KEY_LEN;
+use vmgs::tpm_state::TpmState;
 use vmgs_format::FileId;
 use vmgs_format::VMGS_BYTES_PER_BLOCK;
 use vmgs_format::VMGS_DEFAULT_CAPACITY;
@@ -42,6 +45,7 @@ pub enum VmgsError {
     EncryptionFailed = 12,
     WriteFailed = 13,
     FileExists = 14,
+    InvalidTpmState = 15,
 }
 
 /// Read the contents of a `FileId` in a VMGS file
@@ -389,3 +393,241 @@ async fn do_query_size(file_path: &str, file_id: FileId) -> Result<u64, VmgsErro
 
     Ok(info.valid_bytes)
 }
+
+/// Parses the `encryption_key` argument, which must be null-terminated and
+/// nonnull if `use_encryption` is set.
+///
+/// # Safety
+///
+/// `encryption_key` must point to a valid null-terminated string if
+/// `use_encryption` is set.
+unsafe fn encryption_key_arg<'a>(
+    encryption_key: *const c_char,
+    use_encryption: bool,
+) -> Result<Option<&'a [u8; VMGS_ENCRYPTION_KEY_SIZE]>, VmgsError> {
+    if !use_encryption {
+        return Ok(None);
+    }
+    if encryption_key.is_null() {
+        return Err(VmgsError::NullParam);
+    }
+    // SAFETY: `encryption_key` guaranteed by caller to be null-terminated and nonnull if using encryption
+    let bytes = unsafe { CStr::from_ptr(encryption_key) }
+        .to_str()
+        .map_err(|_| VmgsError::InvalidString)?
+        .as_bytes();
+    bytes
+        .try_into()
+        .map(Some)
+        .map_err(|_| VmgsError::InvalidString)
+}
+
+/// Export the vTPM state of a VMGS file to `data_path`, sealed under
+/// `transport_key`, so that it can be imported into a VMGS file on another
+/// host with `import_tpm_state_vmgs`
+///
+/// # Safety
+///
+/// `file_path` and `data_path` must point to valid null-terminated utf-8 strings.
+/// `encryption_key` must be null-terminated and nonnull if using encryption
+/// `transport_key` must point to `TRANSPORT_KEY_LEN` bytes
+// SAFETY: In this library this function name is unique.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn export_tpm_state_vmgs(
+    file_path: *const c_char,
+    data_path: *const c_char,
+    encryption_key: *const c_char,
+    use_encryption: bool,
+    transport_key: *const u8,
+) -> VmgsError {
+    // SAFETY: all passed pointers are checked to be null-terminated and nonnull before access
+    let (file_path, data_path) = unsafe {
+        if file_path.is_null() || data_path.is_null() {
+            return VmgsError::NullParam;
+        }
+
+        let file = CStr::from_ptr(file_path).to_str();
+        let data = CStr::from_ptr(data_path).to_str();
+        match (file, data) {
+            (Ok(f), Ok(d)) => (f, d),
+            _ => return VmgsError::InvalidString,
+        }
+    };
+
+    // SAFETY: `transport_key` is a pointer to `TRANSPORT_KEY_LEN` bytes
+    let transport_key = unsafe {
+        if transport_key.is_null() {
+            return VmgsError::NullParam;
+        }
+        &*transport_key.cast::<[u8; TRANSPORT_KEY_LEN]>()
+    };
+
+    // SAFETY: `encryption_key` must be null-terminated and nonnull if using encryption
+    let key = match unsafe { encryption_key_arg(encryption_key, use_encryption) } {
+        Ok(key) => key,
+        Err(err) => return err,
+    };
+
+    match block_on(do_export_tpm_state(
+        file_path,
+        data_path,
+        key,
+        transport_key,
+    )) {
+        Ok(()) => VmgsError::Ok,
+        Err(err) => err,
+    }
+}
+
+async fn do_export_tpm_state(
+    file_path: &str,
+    data_path: &str,
+    key: Option<&[u8; VMGS_ENCRYPTION_KEY_SIZE]>,
+    transport_key: &[u8; TRANSPORT_KEY_LEN],
+) -> Result<(), VmgsError> {
+    let mut vmgs = Vmgs::open(open_disk(file_path, true)?, None)
+        .await
+        .map_err(|_| VmgsError::InvalidVmgs)?;
+
+    if let Some(encryption_key) = key {
+        vmgs.unlock_with_encryption_key(encryption_key)
+            .await
+            .map_err(|_| VmgsError::DecryptionFailed)?;
+    }
+
+    let nvram = read_optional(&mut vmgs, FileId::TPM_NVRAM)
+        .await?
+        .ok_or(VmgsError::FileInfoNotAllocated)?;
+    // The PPI file is only written once the guest requests a PPI operation,
+    // so it may legitimately be missing.
+    let ppi = read_optional(&mut vmgs, FileId::TPM_PPI)
+        .await?
+        .unwrap_or_default();
+
+    let blob = tpm_state::seal(&TpmState { nvram, ppi }, transport_key)
+        .map_err(|_| VmgsError::EncryptionFailed)?;
+    std::fs::write(data_path, blob).map_err(|_| VmgsError::CantOpenFile)
+}
+
+/// Reads `file_id`, returning `None` if it is not allocated.
+async fn read_optional(vmgs: &mut Vmgs, file_id: FileId) -> Result<Option<Vec<u8>>, VmgsError> {
+    match vmgs.read_file(file_id).await {
+        Ok(data) => Ok(Some(data)),
+        Err(vmgs::Error::FileInfoNotAllocated(_)) => Ok(None),
+        Err(_) => Err(VmgsError::CantReadFile),
+    }
+}
+
+/// Import the vTPM state from a blob at `data_path` created by
+/// `export_tpm_state_vmgs`
+///
+/// Import will fail if the VMGS file already contains vTPM state unless the
+/// `allow_overwrite` flag is set.
+///
+/// # Safety
+///
+/// `file_path` and `data_path` must point to valid null-terminated utf-8 strings.
+/// `encryption_key` must be null-terminated and nonnull if using encryption
+/// `transport_key` must point to `TRANSPORT_KEY_LEN` bytes
+// SAFETY: In this library this function name is unique.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn import_tpm_state_vmgs(
+    file_path: *const c_char,
+    data_path: *const c_char,
+    encryption_key: *const c_char,
+    use_encryption: bool,
+    transport_key: *const u8,
+    allow_overwrite: bool,
+) -> VmgsError {
+    // SAFETY: all passed pointers are checked to be null-terminated and nonnull before access
+    let (file_path, data_path) = unsafe {
+        if file_path.is_null() || data_path.is_null() {
+            return VmgsError::NullParam;
+        }
+
+        let file = CStr::from_ptr(file_path).to_str();
+        let data = CStr::from_ptr(data_path).to_str();
+        match (file, data) {
+            (Ok(f), Ok(d)) => (f, d),
+            _ => return VmgsError::InvalidString,
+        }
+    };
+
+    // SAFETY: `transport_key` is a pointer to `TRANSPORT_KEY_LEN` bytes
+    let transport_key = unsafe {
+        if transport_key.is_null() {
+            return VmgsError::NullParam;
+        }
+        &*transport_key.cast::<[u8; TRANSPORT_KEY_LEN]>()
+    };
+
+    // SAFETY: `encryption_key` must be null-terminated and nonnull if using encryption
+    let key = match unsafe { encryption_key_arg(encryption_key, use_encryption) } {
+        Ok(key) => key,
+        Err(err) => return err,
+    };
+
+    match block_on(do_import_tpm_state(
+        file_path,
+        data_path,
+        key,
+        transport_key,
+        allow_overwrite,
+    )) {
+        Ok(()) => VmgsError::Ok,
+        Err(err) => err,
+    }
+}
+
+async fn do_import_tpm_state(
+    file_path: &str,
+    data_path: &str,
+    key: Option<&[u8; VMGS_ENCRYPTION_KEY_SIZE]>,
+    transport_key: &[u8; TRANSPORT_KEY_LEN],
+    allow_overwrite: bool,
+) -> Result<(), VmgsError> {
+    let mut blob = Vec::new();
+    let mut file = File::open(data_path).map_err(|_| VmgsError::CantOpenFile)?;
+    // manually allow, since we want to differentiate between the file not being
+    // accessible, and a read operation failing
+    #[expect(clippy::verbose_file_reads)]
+    file.read_to_end(&mut blob)
+        .map_err(|_| VmgsError::CantReadFile)?;
+
+    let TpmState { nvram, ppi } =
+        tpm_state::unseal(&blob, transport_key).map_err(|err| match err {
+            vmgs::Error::InvalidTpmState(_) => VmgsError::InvalidTpmState,
+            _ => VmgsError::DecryptionFailed,
+        })?;
+
+    let mut vmgs = Vmgs::open(open_disk(file_path, false)?, None)
+        .await
+        .map_err(|_| VmgsError::InvalidVmgs)?;
+
+    if !allow_overwrite
+        && [FileId::TPM_NVRAM, FileId::TPM_PPI]
+            .into_iter()
+            .any(|file_id| vmgs.check_file_allocated(file_id))
+    {
+        return Err(VmgsError::FileExists);
+    }
+
+    if let Some(encryption_key) = key {
+        vmgs.unlock_with_encryption_key(encryption_key)
+            .await
+            .map_err(|_| VmgsError::DecryptionFailed)?;
+    }
+
+    for (file_id, data) in [(FileId::TPM_NVRAM, nvram), (FileId::TPM_PPI, ppi)] {
+        if data.is_empty() {
+            continue;
+        }
+        if key.is_some() {
+            vmgs.write_file_encrypted(file_id, &data).await
+        } else {
+            vmgs.write_file(file_id, &data).await
+        }
+        .map_err(|_| VmgsError::WriteFailed)?;
+    }
+    Ok(())
+}
@@ -26,6 +26,7 @@ enum VmgsError
     VmgsEncryptionFailed = 12,
     VmgsWriteFailed = 13,
     VmgsFileExists = 14,
+    VmgsInvalidTpmState = 15,
 };
 
 enum FileId
@@ -95,6 +96,38 @@ enum VmgsError query_size_vmgs(
     enum FileId file_id,
     int64_t *out_size);
 
+// Export the vTPM state of `file_path` to `data_path`, sealed under
+// `transport_key`
+//
+// If reading encrypted data, `use_encryption` must be true
+// and `encryption_key` must point to a valid null-terminated utf-8 string
+//
+// `file_path` and `data_path` must point to valid null-terminated utf-8 strings
+// `transport_key` must point to a 32 byte key
+enum VmgsError export_tpm_state_vmgs(
+    char *file_path,
+    char *data_path,
+    char *encryption_key,
+    bool use_encryption,
+    const uint8_t *transport_key);
+
+// Import the vTPM state into `file_path` from a blob at `data_path` created
+// by export_tpm_state_vmgs with the same `transport_key`
+//
+// If writing encrypted data, `use_encryption` must be true
+// and `encryption_key` must point to a valid null-terminated utf-8 string
+//
+// `file_path` and `data_path` must point to valid null-terminated utf-8 strings
+// `transport_key` must point to a 32 byte key
+// fails with VmgsFileExists if vTPM state exists, unless `allow_overwrite`
+enum VmgsError import_tpm_state_vmgs(
+    char *file_path,
+    char *data_path,
+    char *encryption_key,
+    bool use_encryption,
+    const uint8_t *transport_key,
+    bool allow_overwrite);
+
 #ifdef __cplusplus
 }
 #endif
@@ -11,6 +11,8 @@
 mod storage_backend;
 #[cfg(feature = "test_helpers")]
 mod test;
+#[cfg(feature = "encryption")]
+mod tpm_state;
 mod uefi_nvram;
 mod vmgs_json;
 
@@ -28,6 +30,8 @@
 use std::path::Path;
 use std::path::PathBuf;
 use thiserror::Error;
+#[cfg(feature = "encryption")]
+use tpm_state::TpmStateOperation;
 use uefi_nvram::UefiNvramOperation;
 use vmgs::Error as VmgsError;
 use vmgs::GspType;
@@ -312,6 +316,13 @@ enum Options {
         #[clap(subcommand)]
         operation: UefiNvramOperation,
     },
+    /// vTPM state export/import operations, for moving a vTPM to a VM on
+    /// another host
+    #[cfg(feature = "encryption")]
+    TpmState {
+        #[clap(subcommand)]
+        operation: TpmStateOperation,
+    },
     #[cfg(feature = "test_helpers")]
     /// Create a test VMGS file
     Test {
@@ -589,6 +600,8 @@ async fn do_main() -> Result<(), Error> {
             key_path,
         } => vmgs_file_dump_file_table(file_path.file_path, key_path.key_path).await,
         Options::UefiNvram { operation } => uefi_nvram::do_command(operation).await,
+        #[cfg(feature = "encryption")]
+        Options::TpmState { operation } => tpm_state::do_command(operation).await,
         #[cfg(feature = "test_helpers")]
         Options::Test { operation } => test::do_command(operation).await,
     }
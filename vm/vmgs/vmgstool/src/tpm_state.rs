@@ -0,0 +1,246 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Functions for exporting and importing the vTPM state (the TPM_NVRAM and
+//! TPM_PPI files) of a VMGS file, so that it can be moved to a VM on another
+//! host.
+//!
+//! The blob format is defined by [`vmgs::tpm_state`].
+
+use crate::Error;
+use crate::FilePathArg;
+use crate::KeyPathArg;
+use crate::OpenMode;
+use crate::read_key_path;
+use crate::vmgs_file_open;
+use crate::vmgs_read;
+use crate::vmgs_write;
+use clap::Subcommand;
+use fs_err::File;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use vmgs::Error as VmgsError;
+use vmgs::Vmgs;
+use vmgs::tpm_state;
+use vmgs::tpm_state::TRANSPORT_KEY_LEN;
+use vmgs::tpm_state::TpmState;
+use vmgs_format::FileId;
+
+#[derive(Subcommand)]
+pub(crate) enum TpmStateOperation {
+    /// Export the vTPM state to an encrypted blob
+    Export {
+        #[command(flatten)]
+        file_path: FilePathArg,
+        #[command(flatten)]
+        key_path: KeyPathArg,
+        /// Output file path for the exported state
+        #[clap(short = 'd', long, alias = "datapath")]
+        data_path: PathBuf,
+        /// Transport key file path. The file must contain a key that is 32
+        /// bytes long.
+        #[clap(short = 't', long, alias = "transportkeypath")]
+        transport_key_path: PathBuf,
+    },
+    /// Import the vTPM state from a blob created by `export`
+    Import {
+        #[command(flatten)]
+        file_path: FilePathArg,
+        #[command(flatten)]
+        key_path: KeyPathArg,
+        /// Input file path for the exported state
+        #[clap(short = 'd', long, alias = "datapath")]
+        data_path: PathBuf,
+        /// Transport key file path. The file must contain a key that is 32
+        /// bytes long.
+        #[clap(short = 't', long, alias = "transportkeypath")]
+        transport_key_path: PathBuf,
+        /// Overwrite any existing vTPM state in the VMGS file
+        #[clap(long, alias = "allowoverwrite")]
+        allow_overwrite: bool,
+    },
+}
+
+pub(crate) async fn do_command(operation: TpmStateOperation) -> Result<(), Error> {
+    match operation {
+        TpmStateOperation::Export {
+            file_path,
+            key_path,
+            data_path,
+            transport_key_path,
+        } => {
+            vmgs_file_export_tpm_state(
+                file_path.file_path,
+                key_path.key_path,
+                data_path,
+                transport_key_path,
+            )
+            .await
+        }
+        TpmStateOperation::Import {
+            file_path,
+            key_path,
+            data_path,
+            transport_key_path,
+            allow_overwrite,
+        } => {
+            vmgs_file_import_tpm_state(
+                file_path.file_path,
+                key_path.key_path,
+                data_path,
+                transport_key_path,
+                allow_overwrite,
+            )
+            .await
+        }
+    }
+}
+
+async fn vmgs_file_export_tpm_state(
+    file_path: impl AsRef<Path>,
+    key_path: Option<impl AsRef<Path>>,
+    data_path: impl AsRef<Path>,
+    transport_key_path: impl AsRef<Path>,
+) -> Result<(), Error> {
+    let transport_key = read_key_path(transport_key_path)?;
+    let decrypt = key_path.is_some();
+    let mut vmgs = vmgs_file_open(file_path, key_path, OpenMode::ReadOnlyIgnore).await?;
+    if !decrypt && vmgs.encrypted() {
+        return Err(Error::EncryptedNoKey);
+    }
+
+    let blob = vmgs_export_tpm_state(&mut vmgs, decrypt, &transport_key).await?;
+
+    tracing::info!("Writing vTPM state to {}", data_path.as_ref().display());
+    let mut file = File::create(data_path.as_ref()).map_err(Error::DataFile)?;
+    file.write_all(&blob).map_err(Error::DataFile)?;
+
+    Ok(())
+}
+
+async fn vmgs_file_import_tpm_state(
+    file_path: impl AsRef<Path>,
+    key_path: Option<impl AsRef<Path>>,
+    data_path: impl AsRef<Path>,
+    transport_key_path: impl AsRef<Path>,
+    allow_overwrite: bool,
+) -> Result<(), Error> {
+    let transport_key = read_key_path(transport_key_path)?;
+
+    tracing::info!("Reading vTPM state from {}", data_path.as_ref().display());
+    let mut file = File::open(data_path.as_ref()).map_err(Error::DataFile)?;
+    let mut blob = Vec::new();
+    file.read_to_end(&mut blob).map_err(Error::DataFile)?;
+
+    let encrypt = key_path.is_some();
+    let mut vmgs = vmgs_file_open(file_path, key_path, OpenMode::ReadWriteRequire).await?;
+
+    vmgs_import_tpm_state(&mut vmgs, &blob, encrypt, &transport_key, allow_overwrite).await
+}
+
+async fn vmgs_export_tpm_state(
+    vmgs: &mut Vmgs,
+    decrypt: bool,
+    transport_key: &[u8; TRANSPORT_KEY_LEN],
+) -> Result<Vec<u8>, Error> {
+    let nvram = vmgs_read(vmgs, FileId::TPM_NVRAM, decrypt).await?;
+    // The PPI file is only written once the guest requests a PPI operation,
+    // so it may legitimately be missing.
+    let ppi = match vmgs.get_file_info(FileId::TPM_PPI) {
+        Ok(_) => vmgs_read(vmgs, FileId::TPM_PPI, decrypt).await?,
+        Err(VmgsError::FileInfoNotAllocated(_)) => Vec::new(),
+        Err(err) => return Err(err.into()),
+    };
+    tracing::info!(
+        "Exporting {} bytes of TPM NVRAM and {} bytes of TPM PPI state",
+        nvram.len(),
+        ppi.len()
+    );
+
+    Ok(tpm_state::seal(&TpmState { nvram, ppi }, transport_key)?)
+}
+
+async fn vmgs_import_tpm_state(
+    vmgs: &mut Vmgs,
+    blob: &[u8],
+    encrypt: bool,
+    transport_key: &[u8; TRANSPORT_KEY_LEN],
+    allow_overwrite: bool,
+) -> Result<(), Error> {
+    let TpmState { nvram, ppi } = tpm_state::unseal(blob, transport_key)?;
+
+    vmgs_write(vmgs, FileId::TPM_NVRAM, &nvram, encrypt, allow_overwrite).await?;
+    if !ppi.is_empty() {
+        vmgs_write(vmgs, FileId::TPM_PPI, &ppi, encrypt, allow_overwrite).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vhdfiledisk_create;
+    use crate::vmgs_create;
+    use pal_async::async_test;
+    use tempfile::TempDir;
+    use tempfile::tempdir;
+
+    async fn test_vmgs() -> (TempDir, Vmgs) {
+        let dir = tempdir().unwrap();
+        let disk = vhdfiledisk_create(dir.path().join("test.vmgs"), None, false).unwrap();
+        let vmgs = vmgs_create(disk, None).await.unwrap();
+        (dir, vmgs)
+    }
+
+    #[async_test]
+    async fn tpm_state_round_trip() {
+        let transport_key = [7; 32];
+        let (_src, mut src) = test_vmgs().await;
+        vmgs_write(&mut src, FileId::TPM_NVRAM, b"nvram", false, false)
+            .await
+            .unwrap();
+        vmgs_write(&mut src, FileId::TPM_PPI, b"ppi", false, false)
+            .await
+            .unwrap();
+
+        let blob = vmgs_export_tpm_state(&mut src, false, &transport_key)
+            .await
+            .unwrap();
+
+        let (_dst, mut dst) = test_vmgs().await;
+        vmgs_import_tpm_state(&mut dst, &blob, false, &transport_key, false)
+            .await
+            .unwrap();
+        assert_eq!(dst.read_file(FileId::TPM_NVRAM).await.unwrap(), b"nvram");
+        assert_eq!(dst.read_file(FileId::TPM_PPI).await.unwrap(), b"ppi");
+
+        // A missing PPI file is exported as empty and not imported.
+        let (_src, mut src) = test_vmgs().await;
+        vmgs_write(&mut src, FileId::TPM_NVRAM, b"nvram", false, false)
+            .await
+            .unwrap();
+        let blob_no_ppi = vmgs_export_tpm_state(&mut src, false, &transport_key)
+            .await
+            .unwrap();
+        let (_dst2, mut dst2) = test_vmgs().await;
+        vmgs_import_tpm_state(&mut dst2, &blob_no_ppi, false, &transport_key, false)
+            .await
+            .unwrap();
+        assert!(!dst2.check_file_allocated(FileId::TPM_PPI));
+
+        // Importing again must not silently clobber the existing state.
+        assert!(matches!(
+            vmgs_import_tpm_state(&mut dst, &blob, false, &transport_key, false).await,
+            Err(Error::FileIdExists(FileId::TPM_NVRAM))
+        ));
+
+        // The wrong transport key must fail authentication.
+        assert!(matches!(
+            vmgs_import_tpm_state(&mut dst, &blob, false, &[8; 32], true).await,
+            Err(Error::Vmgs(VmgsError::Crypto(_)))
+        ));
+    }
+}
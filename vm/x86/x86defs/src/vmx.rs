@@ -32,6 +32,8 @@ pub enum VmxExitBasic: u16 {
         MSR_READ = 0x1F,
         MSR_WRITE = 0x20,
         BAD_GUEST_STATE = 0x21,
+        MWAIT_INSTRUCTION = 0x24,
+        MONITOR_INSTRUCTION = 0x27,
         TPR_BELOW_THRESHOLD = 0x2B,
         GDTR_OR_IDTR = 0x2E,
         LDTR_OR_TR = 0x2F,
@@ -47,6 +47,17 @@ fn compare_and_write_memory(
         is_user_mode: bool,
     ) -> impl Future<Output = Result<bool, Self::Error>>;
 
+    /// Hints that upcoming memory accesses will touch the pages containing
+    /// `gvas`, so that the implementation can translate them together rather
+    /// than one access at a time.
+    ///
+    /// This is only a hint: the accesses themselves still go through
+    /// [`Cpu::read_memory`] and [`Cpu::write_memory`], which report any
+    /// translation failure.
+    fn prefetch_translations(&mut self, gvas: &[u64], write: bool, is_user_mode: bool) {
+        let _ = (gvas, write, is_user_mode);
+    }
+
     /// Performs an io read of 1, 2, or 4 bytes.
     fn read_io(
         &mut self,
@@ -65,6 +76,26 @@ fn write_io(
     fn set_gp(&mut self, reg: RegisterIndex, v: u64);
     fn xmm(&mut self, index: usize) -> u128;
     fn set_xmm(&mut self, index: usize, v: u128);
+
+    /// Gets the upper 128 bits of YMM register `index`.
+    ///
+    /// Returns `None` if the backend does not provide access to AVX register
+    /// state, in which case VEX-encoded instructions that need it are not
+    /// emulated.
+    fn ymm_upper(&mut self, index: usize) -> Option<u128> {
+        let _ = index;
+        None
+    }
+
+    /// Sets the upper 128 bits of YMM register `index`.
+    ///
+    /// Returns `None` if the backend does not provide access to AVX register
+    /// state.
+    fn set_ymm_upper(&mut self, index: usize, v: u128) -> Option<()> {
+        let _ = (index, v);
+        None
+    }
+
     fn rip(&mut self) -> u64;
     fn set_rip(&mut self, v: u64);
     fn segment(&mut self, index: Segment) -> SegmentRegister;
@@ -105,6 +136,10 @@ fn compare_and_write_memory(
         (*self).compare_and_write_memory(gva, current, new, is_user_mode)
     }
 
+    fn prefetch_translations(&mut self, gvas: &[u64], write: bool, is_user_mode: bool) {
+        (*self).prefetch_translations(gvas, write, is_user_mode)
+    }
+
     fn read_io(
         &mut self,
         io_port: u16,
@@ -141,6 +176,14 @@ fn set_xmm(&mut self, index: usize, v: u128) {
         (*self).set_xmm(index, v)
     }
 
+    fn ymm_upper(&mut self, index: usize) -> Option<u128> {
+        (*self).ymm_upper(index)
+    }
+
+    fn set_ymm_upper(&mut self, index: usize, v: u128) -> Option<()> {
+        (*self).set_ymm_upper(index, v)
+    }
+
     fn rip(&mut self) -> u64 {
         (*self).rip()
     }
@@ -24,6 +24,7 @@
 mod bt;
 mod cmpxchg816;
 mod cond;
+mod decode_cache;
 pub mod fast_path;
 mod instruction;
 mod mov;
@@ -31,7 +32,9 @@
 mod rep;
 mod rflags;
 mod shift_rotate;
+mod vex;
 
+pub use decode_cache::DecodeCache;
 pub use rep::MAX_REP_LOOPS;
 
 // Trait to allow operating over u64 and u128 in some functions.
@@ -129,6 +132,7 @@ pub struct Emulator<'a, T> {
     cpu: T,
     decoder_options: u32,
     bytes: &'a [u8],
+    decode_cache: Option<&'a mut DecodeCache>,
 }
 
 #[derive(Debug, Error)]
@@ -204,9 +208,17 @@ pub fn new(cpu: T, vendor: x86defs::cpuid::Vendor, bytes: &'a [u8]) -> Self {
             cpu,
             decoder_options,
             bytes,
+            decode_cache: None,
         }
     }
 
+    /// Uses `cache` to avoid decoding instructions that have been decoded
+    /// recently.
+    pub fn with_decode_cache(mut self, cache: &'a mut DecodeCache) -> Self {
+        self.decode_cache = Some(cache);
+        self
+    }
+
     /// Gets the linear IP of the CPU, taking into account the code segment.
     ///
     /// Returns None if IP/EIP does not fit into the code segment.
@@ -723,23 +735,35 @@ pub async fn run(&mut self) -> Result<(), Box<Error<T::Error>>> {
         let efer = self.cpu.efer();
         let cs = self.cpu.segment(Segment::CS);
         let bitness = bitness(cr0, efer, cs);
-        let mut decoder = Decoder::new(bitness.into(), self.bytes, self.decoder_options);
-        decoder.set_ip(self.cpu.rip());
-        let instr = decoder.decode();
-        if instr.code() == Code::INVALID {
-            match decoder.last_error() {
-                DecoderError::None => unreachable!(),
-                DecoderError::NoMoreBytes => return Err(Box::new(Error::NotEnoughBytes)),
-                err => {
-                    tracing::warn!(
-                        error = ?err,
-                        bytes = ?self.bytes,
-                        "could not decode instruction"
-                    );
-                    return Err(Box::new(Error::DecodeFailure));
-                }
+        let rip = self.cpu.rip();
+        let mut vex_l256 = None;
+        let instr = if let Some(instr) = self
+            .decode_cache
+            .as_ref()
+            .and_then(|cache| cache.lookup(rip, bitness, self.decoder_options, self.bytes))
+        {
+            instr
+        } else if let Some(vex) = (cr0 & x86defs::X64_CR0_PE != 0)
+            .then(|| vex::decode(bitness, self.bytes, rip, self.decoder_options))
+            .flatten()
+        {
+            // VEX instructions are not cached, since the cache does not keep
+            // the vector length.
+            let vex = vex.map_err(|err| self.decode_error(err))?;
+            vex_l256 = Some(vex.l256);
+            vex.instr
+        } else {
+            let mut decoder = Decoder::new(bitness.into(), self.bytes, self.decoder_options);
+            decoder.set_ip(rip);
+            let instr = decoder.decode();
+            if instr.code() == Code::INVALID {
+                return Err(self.decode_error(decoder.last_error()));
             }
-        }
+            if let Some(cache) = &mut self.decode_cache {
+                cache.insert(rip, bitness, self.decoder_options, self.bytes, instr);
+            }
+            instr
+        };
         tracing::trace!(
             bytes = ?self.bytes[..instr.len()],
             cs = ?self.cpu.segment(Segment::CS),
@@ -747,7 +771,11 @@ pub async fn run(&mut self) -> Result<(), Box<Error<T::Error>>> {
             ?bitness,
             "Emulating instruction",
         );
-        match self.emulate(&instr).await {
+        let result = match vex_l256 {
+            Some(l256) => self.emulate_vex(&instr, l256).await,
+            None => self.emulate(&instr).await,
+        };
+        match result {
             // If `Retry` is returned, then the RIP has not been advanced, but
             // some register and memory state may have changed. The processor is
             // in a consistent, observable state. The caller should resume
@@ -759,6 +787,21 @@ pub async fn run(&mut self) -> Result<(), Box<Error<T::Error>>> {
         Ok(())
     }
 
+    fn decode_error(&self, err: DecoderError) -> Box<Error<T::Error>> {
+        match err {
+            DecoderError::None => unreachable!(),
+            DecoderError::NoMoreBytes => Box::new(Error::NotEnoughBytes),
+            err => {
+                tracing::warn!(
+                    error = ?err,
+                    bytes = ?self.bytes,
+                    "could not decode instruction"
+                );
+                Box::new(Error::DecodeFailure)
+            }
+        }
+    }
+
     // DEVNOTE: The error type is boxed as a codesize optimization. See the comment on
     //          `run()` above for more information.
     /// Emulates the effects of an instruction.
@@ -1354,6 +1397,58 @@ async fn emulate(&mut self, instr: &Instruction) -> Result<(), InternalError<T::
             | _ => Err(self.unsupported_instruction(instr).into()),
         }?;
 
+        self.complete_instruction(instr)
+    }
+
+    /// Emulates the effects of a VEX instruction, given its legacy form as
+    /// decoded by [`vex::decode`] and its vector length.
+    async fn emulate_vex(
+        &mut self,
+        instr: &Instruction,
+        l256: bool,
+    ) -> Result<(), InternalError<T::Error>> {
+        if !instr.op_kinds().any(|x| x == OpKind::Memory) {
+            Err(Error::NonMemoryOrPortInstruction(
+                self.bytes[..instr.len()].into(),
+            ))?;
+        }
+
+        match instr.code() {
+            // vmovups
+            // vmovupd
+            // vmovdqu
+            // vmovntdq
+            // vmovntps
+            // vmovntpd
+            Code::Movups_xmm_xmmm128
+            | Code::Movups_xmmm128_xmm
+            | Code::Movupd_xmm_xmmm128
+            | Code::Movupd_xmmm128_xmm
+            | Code::Movdqu_xmm_xmmm128
+            | Code::Movdqu_xmmm128_xmm
+            | Code::Movntdq_m128_xmm
+            | Code::Movntps_m128_xmm
+            | Code::Movntpd_m128_xmm => self.mov_avx(instr, l256, false).await,
+
+            // vmovaps
+            // vmovapd
+            // vmovdqa
+            Code::Movaps_xmm_xmmm128
+            | Code::Movaps_xmmm128_xmm
+            | Code::Movapd_xmm_xmmm128
+            | Code::Movapd_xmmm128_xmm
+            | Code::Movdqa_xmm_xmmm128
+            | Code::Movdqa_xmmm128_xmm => self.mov_avx(instr, l256, true).await,
+
+            _ => Err(self.unsupported_instruction(instr).into()),
+        }?;
+
+        self.complete_instruction(instr)
+    }
+
+    /// Completes an emulated instruction by advancing the RIP and checking for
+    /// traps.
+    fn complete_instruction(&mut self, instr: &Instruction) -> Result<(), InternalError<T::Error>> {
         // The instruction is complete. Update the RIP and check for traps.
         self.cpu.set_rip(instr.next_ip());
         let mut rflags = self.cpu.rflags();
@@ -0,0 +1,80 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! A cache of decoded instructions.
+
+use crate::registers::Bitness;
+use iced_x86::Instruction;
+
+/// The number of cache entries. Must be a power of two.
+const ENTRIES: usize = 16;
+
+/// A cache of decoded instructions, to avoid repeatedly decoding the same
+/// instruction when the guest accesses an intercepted address in a tight
+/// loop, such as when polling a device register.
+///
+/// Entries are keyed by RIP and processor mode, and an entry is only used if
+/// the instruction bytes being emulated still match the cached bytes. So if
+/// the guest modifies the code, the stale entry is simply replaced, and the
+/// cache never needs to be explicitly invalidated.
+#[derive(Default)]
+pub struct DecodeCache {
+    entries: [Option<Entry>; ENTRIES],
+}
+
+struct Entry {
+    rip: u64,
+    bitness: Bitness,
+    decoder_options: u32,
+    bytes: [u8; 15],
+    instr: Instruction,
+}
+
+impl DecodeCache {
+    /// Returns a new, empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn index(rip: u64) -> usize {
+        rip as usize & (ENTRIES - 1)
+    }
+
+    /// Returns the cached instruction at `rip`, if its bytes are a prefix of
+    /// `bytes`.
+    pub(super) fn lookup(
+        &self,
+        rip: u64,
+        bitness: Bitness,
+        decoder_options: u32,
+        bytes: &[u8],
+    ) -> Option<Instruction> {
+        let entry = self.entries[Self::index(rip)].as_ref()?;
+        (entry.rip == rip
+            && entry.bitness == bitness
+            && entry.decoder_options == decoder_options
+            && bytes.get(..entry.instr.len()) == Some(&entry.bytes[..entry.instr.len()]))
+        .then_some(entry.instr)
+    }
+
+    /// Inserts the decoded instruction `instr`, whose encoding is at the start
+    /// of `bytes`.
+    pub(super) fn insert(
+        &mut self,
+        rip: u64,
+        bitness: Bitness,
+        decoder_options: u32,
+        bytes: &[u8],
+        instr: Instruction,
+    ) {
+        let mut entry = Entry {
+            rip,
+            bitness,
+            decoder_options,
+            bytes: [0; 15],
+            instr,
+        };
+        entry.bytes[..instr.len()].copy_from_slice(&bytes[..instr.len()]);
+        self.entries[Self::index(rip)] = Some(entry);
+    }
+}
@@ -54,6 +54,79 @@ pub(super) async fn mov_sse(
         Ok(())
     }
 
+    /// Emulates a VEX-encoded move of an XMM or YMM register, given the
+    /// legacy form of the instruction and whether it is the 256-bit form.
+    ///
+    /// The VEX.128 forms zero the upper half of a destination YMM register,
+    /// so loads to a register need AVX register state even when they only
+    /// touch 16 bytes of memory.
+    pub(super) async fn mov_avx(
+        &mut self,
+        instr: &Instruction,
+        l256: bool,
+        aligned: bool,
+    ) -> Result<(), InternalError<T::Error>> {
+        let len = if l256 { 32 } else { 16 };
+        let alignment = if aligned {
+            AlignmentMode::Aligned(len as u64)
+        } else {
+            AlignmentMode::Unaligned
+        };
+
+        let mut value = [0; 32];
+        match instr.op1_kind() {
+            OpKind::Memory => {
+                let offset = self.memory_op_offset(instr, 1);
+                self.read_memory(
+                    instr.memory_segment().into(),
+                    offset,
+                    alignment,
+                    &mut value[..len],
+                )
+                .await?;
+            }
+            OpKind::Register => {
+                let reg = instr.op1_register().number();
+                value[..16].copy_from_slice(&self.cpu.xmm(reg).to_le_bytes());
+                if len == 32 {
+                    let upper = self
+                        .cpu
+                        .ymm_upper(reg)
+                        .ok_or_else(|| self.unsupported_instruction(instr))?;
+                    value[16..].copy_from_slice(&upper.to_le_bytes());
+                }
+            }
+            _ => Err(self.unsupported_instruction(instr))?,
+        }
+
+        match instr.op0_kind() {
+            OpKind::Memory => {
+                let offset = self.memory_op_offset(instr, 0);
+                self.write_memory(
+                    instr.memory_segment().into(),
+                    offset,
+                    alignment,
+                    &value[..len],
+                )
+                .await?;
+            }
+            OpKind::Register => {
+                let reg = instr.op0_register().number();
+                let (lower, upper) = value.split_at(16);
+                // Set the upper half first, so that nothing is modified if
+                // AVX register state is not available.
+                self.cpu
+                    .set_ymm_upper(reg, u128::from_le_bytes(upper.try_into().unwrap()))
+                    .ok_or_else(|| self.unsupported_instruction(instr))?;
+                self.cpu
+                    .set_xmm(reg, u128::from_le_bytes(lower.try_into().unwrap()));
+            }
+            _ => Err(self.unsupported_instruction(instr))?,
+        }
+
+        Ok(())
+    }
+
     pub(super) async fn movdir64b(
         &mut self,
         instr: &Instruction,
@@ -4,6 +4,7 @@
 use super::AlignmentMode;
 use super::Emulator;
 use super::InternalError;
+use super::OperationKind;
 use super::arith::ArithOp;
 use crate::Cpu;
 use crate::Segment;
@@ -16,6 +17,8 @@
 /// or other events. However we won't increment RIP, so we'll be re-entered where we left off.
 pub const MAX_REP_LOOPS: u64 = 1024;
 
+const PAGE_SHIFT: u64 = 12;
+
 /// State for rep ops. See [`Emulator::rep_op`].
 struct RepState {
     pub count_reg: Register,
@@ -139,6 +142,50 @@ fn rep_op(
         })
     }
 
+    /// Hints to the CPU which pages the iterations of this run will access
+    /// through `segment:offset`.
+    ///
+    /// Instructions with two memory operands alternate between them on every
+    /// iteration, so translating both ranges up front avoids translating each
+    /// operand's page again and again.
+    fn prefetch_rep_translations(
+        &mut self,
+        rep_state: &RepState,
+        segment: Segment,
+        offset: u64,
+        op: OperationKind,
+    ) {
+        let count = (rep_state.requested - rep_state.done).min(MAX_REP_LOOPS);
+        if count <= 1 {
+            return;
+        }
+        // Any failure is reported by the access itself.
+        let Ok(gva) = self.compute_and_validate_gva(
+            segment,
+            offset,
+            rep_state.size,
+            op,
+            AlignmentMode::Standard,
+        ) else {
+            return;
+        };
+        let span = (count - 1) * rep_state.size as u64;
+        let (start, end) = if (rep_state.delta as i64) > 0 {
+            (gva, gva.wrapping_add(span + rep_state.size as u64 - 1))
+        } else {
+            (
+                gva.wrapping_sub(span),
+                gva.wrapping_add(rep_state.size as u64 - 1),
+            )
+        };
+        let gvas: Vec<u64> = (start >> PAGE_SHIFT..=end >> PAGE_SHIFT)
+            .map(|page| page << PAGE_SHIFT)
+            .collect();
+        let is_user_mode = self.is_user_mode();
+        self.cpu
+            .prefetch_translations(&gvas, op == OperationKind::Write, is_user_mode);
+    }
+
     fn rep_again(&mut self, rep_state: &mut RepState) -> bool {
         if rep_state.rep.is_some() {
             self.cpu.set_gp(
@@ -267,6 +314,15 @@ pub(super) async fn movs(
         let mut rep = self.rep_op(instr, instr.op0_kind(), false)?;
         let rdi = sized_rdi(instr.op0_kind());
         let rsi = sized_rsi(instr.op1_kind());
+        let si_offset = self.memory_op_offset(instr, 1);
+        self.prefetch_rep_translations(
+            &rep,
+            instr.memory_segment().into(),
+            si_offset,
+            OperationKind::Read,
+        );
+        let di_offset = self.memory_op_offset(instr, 0);
+        self.prefetch_rep_translations(&rep, Segment::ES, di_offset, OperationKind::Write);
         while self.rep_again(&mut rep) {
             let data = &mut [0; 8][..rep.size];
 
@@ -302,6 +358,15 @@ pub(super) async fn cmps(
         let mut rep = self.rep_op(instr, instr.op0_kind(), true)?;
         let rsi = sized_rsi(instr.op0_kind());
         let rdi = sized_rdi(instr.op1_kind());
+        let si_offset = self.memory_op_offset(instr, 0);
+        self.prefetch_rep_translations(
+            &rep,
+            instr.memory_segment().into(),
+            si_offset,
+            OperationKind::Read,
+        );
+        let di_offset = self.memory_op_offset(instr, 1);
+        self.prefetch_rep_translations(&rep, Segment::ES, di_offset, OperationKind::Read);
         let mut left = 0;
         let mut right = 0;
         while self.rep_again(&mut rep) {
@@ -0,0 +1,134 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Decoding of the VEX-encoded instructions the emulator supports.
+//!
+//! iced is built without VEX support to keep every binary that decodes or
+//! formats instructions small. Instead, the emulator decodes the few VEX
+//! instructions it supports by rewriting them to the legacy SSE instruction
+//! with the same opcode and operands, decoding that with iced, and keeping the
+//! vector length on the side.
+
+use crate::registers::Bitness;
+use iced_x86::Code;
+use iced_x86::Decoder;
+use iced_x86::DecoderError;
+use iced_x86::Instruction;
+
+/// The maximum length of an x86 instruction.
+const MAX_INSTRUCTION_LEN: usize = 15;
+
+/// A decoded VEX instruction.
+pub(super) struct VexInstruction {
+    /// The legacy SSE form of the instruction, with the length, next IP, and
+    /// any RIP-relative address of the VEX form.
+    pub instr: Instruction,
+    /// Whether the instruction operates on 256-bit YMM registers (VEX.L).
+    pub l256: bool,
+}
+
+/// Decodes a supported VEX instruction at the start of `bytes`.
+///
+/// Returns `None` if `bytes` does not start with a supported VEX
+/// instruction, in which case it should be decoded normally.
+pub(super) fn decode(
+    bitness: Bitness,
+    bytes: &[u8],
+    rip: u64,
+    decoder_options: u32,
+) -> Option<Result<VexInstruction, DecoderError>> {
+    // Segment and address size overrides may precede the VEX prefix. Any other
+    // legacy prefix makes the instruction invalid, so leave that to iced.
+    let prefix_len = bytes
+        .iter()
+        .take_while(|b| matches!(b, 0x26 | 0x2e | 0x36 | 0x3e | 0x64 | 0x65 | 0x67))
+        .count();
+    let (prefixes, vex) = bytes.split_at(prefix_len);
+
+    // Outside of 64-bit mode, C4 and C5 are LES and LDS unless the top two
+    // bits of the next byte are set.
+    let long_mode = bitness == Bitness::Bit64;
+    match *vex {
+        [0xc4 | 0xc5, b1, ..] if !long_mode && b1 & 0xc0 != 0xc0 => return None,
+        [0xc4] | [0xc4, _] | [0xc5] => return Some(Err(DecoderError::NoMoreBytes)),
+        _ => {}
+    }
+    // The R, X, B, and vvvv fields are stored inverted.
+    let (rxb, wvvvvlpp, vex_len) = match *vex {
+        [0xc5, b1, ..] => ((!b1 >> 5) & 0b100, b1, 2),
+        // Only the 0F opcode map has legacy equivalents.
+        [0xc4, b1, b2, ..] if b1 & 0x1f == 1 => ((!b1 >> 5) & 0b111, b2, 3),
+        _ => return None,
+    };
+    // Outside of 64-bit mode, only eight registers are addressable.
+    let (rxb, vvvv_mask) = if long_mode { (rxb, 0xf) } else { (0, 0x7) };
+    let vvvv = (!wvvvvlpp >> 3) & vvvv_mask;
+    let l256 = wvvvvlpp & 0b100 != 0;
+    let pp = wvvvvlpp & 0b11;
+
+    let Some(&opcode) = vex.get(vex_len) else {
+        return Some(Err(DecoderError::NoMoreBytes));
+    };
+    // The supported moves have no second source register.
+    if vvvv != 0 {
+        return None;
+    }
+    let supported = match (pp, opcode) {
+        // vmovups, vmovaps, vmovntps and their 66 (pd) forms.
+        (0 | 1, 0x10 | 0x11 | 0x28 | 0x29 | 0x2b) => true,
+        // vmovdqa, vmovntdq
+        (1, 0x6f | 0x7f | 0xe7) => true,
+        // vmovdqu
+        (2, 0x6f | 0x7f) => true,
+        _ => false,
+    };
+    if !supported {
+        return None;
+    }
+
+    // Build the legacy form: the prefixes, the mandatory prefix from pp, a
+    // REX prefix for the extended register bits, then 0F and the rest.
+    let mut legacy = Vec::with_capacity(MAX_INSTRUCTION_LEN + 1);
+    legacy.extend_from_slice(prefixes);
+    match pp {
+        1 => legacy.push(0x66),
+        2 => legacy.push(0xf3),
+        _ => {}
+    }
+    if rxb != 0 {
+        legacy.push(0x40 | rxb);
+    }
+    legacy.push(0x0f);
+    let overhead = legacy.len() - prefix_len;
+    let rest = &vex[vex_len..];
+    legacy.extend_from_slice(&rest[..rest.len().min(MAX_INSTRUCTION_LEN)]);
+
+    let mut decoder = Decoder::new(bitness.into(), &legacy, decoder_options);
+    decoder.set_ip(rip);
+    let mut instr = decoder.decode();
+    if instr.code() == Code::INVALID {
+        return Some(Err(decoder.last_error()));
+    }
+
+    // Fix up the instruction to have the length of the VEX form.
+    let len = instr.len() - overhead + vex_len;
+    if len > MAX_INSTRUCTION_LEN {
+        return Some(Err(DecoderError::InvalidInstruction));
+    }
+    let next_ip = instr
+        .next_ip()
+        .wrapping_add(len as u64)
+        .wrapping_sub(instr.len() as u64);
+    if instr.is_ip_rel_memory_operand() {
+        // iced stores the target address, which is relative to the next IP.
+        instr.set_memory_displacement64(
+            instr
+                .memory_displacement64()
+                .wrapping_add(next_ip)
+                .wrapping_sub(instr.next_ip()),
+        );
+    }
+    instr.set_len(len);
+    instr.set_next_ip(next_ip);
+    Some(Ok(VexInstruction { instr, l256 }))
+}
@@ -10,6 +10,7 @@
 
 pub use cpu::Cpu;
 pub use emulator::AlignmentMode;
+pub use emulator::DecodeCache;
 pub use emulator::Emulator;
 pub use emulator::Error;
 pub use emulator::MAX_REP_LOOPS;
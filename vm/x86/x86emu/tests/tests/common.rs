@@ -424,6 +424,11 @@ pub struct MultipleCellCpu {
     pub read_mem_offset: usize,
     pub write_mem_offset: usize,
 
+    pub xmm: [u128; 16],
+    pub ymm_upper: [u128; 16],
+    /// Whether to report AVX register state as unavailable.
+    pub avx_unavailable: bool,
+
     pub state: CpuState,
 }
 
@@ -538,12 +543,20 @@ fn set_rflags(&mut self, v: RFlags) {
         self.state.rflags = v
     }
 
-    fn set_xmm(&mut self, _reg: usize, _value: u128) {
-        todo!()
+    fn set_xmm(&mut self, reg: usize, value: u128) {
+        self.xmm[reg] = value;
+    }
+
+    fn xmm(&mut self, reg: usize) -> u128 {
+        self.xmm[reg]
+    }
+
+    fn ymm_upper(&mut self, reg: usize) -> Option<u128> {
+        (!self.avx_unavailable).then(|| self.ymm_upper[reg])
     }
 
-    fn xmm(&mut self, _reg: usize) -> u128 {
-        todo!()
+    fn set_ymm_upper(&mut self, reg: usize, value: u128) -> Option<()> {
+        (!self.avx_unavailable).then(|| self.ymm_upper[reg] = value)
     }
 }
 
@@ -671,6 +684,9 @@ fn new(rflags: RFlags) -> Self {
             io_val: Vec::<u8>::default(),
             read_mem_offset: 0,
             write_mem_offset: 0,
+            xmm: [0; 16],
+            ymm_upper: [0; 16],
+            avx_unavailable: false,
             state,
         }
     }
@@ -696,6 +712,8 @@ fn eq(&self, other: &Self) -> bool {
             && self.io_val == other.io_val
             && self.read_mem_offset == other.read_mem_offset
             && self.write_mem_offset == other.write_mem_offset
+            && self.xmm == other.xmm
+            && self.ymm_upper == other.ymm_upper
     }
 }
 
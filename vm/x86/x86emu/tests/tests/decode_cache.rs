@@ -0,0 +1,50 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+use crate::tests::common::SingleCellCpu;
+use crate::tests::common::TestCpu;
+use futures::FutureExt;
+use iced_x86::code_asm::*;
+use x86defs::cpuid::Vendor;
+use x86emu::Cpu;
+use x86emu::DecodeCache;
+use x86emu::Emulator;
+use x86emu::Gp;
+
+fn run_cached(cache: &mut DecodeCache, asm: impl Fn(&mut CodeAssembler)) -> SingleCellCpu<u64> {
+    let mut cpu = SingleCellCpu::<u64>::new(0.into());
+    cpu.valid_gva = 0x200;
+    cpu.set_gp(Gp::RAX.into(), 0x1111);
+    cpu.set_gp(Gp::RCX.into(), 0x2222);
+
+    let mut assembler = CodeAssembler::new(64).unwrap();
+    asm(&mut assembler);
+    let bytes = assembler.assemble(0).unwrap();
+
+    Emulator::new(&mut cpu, Vendor::INTEL, &bytes)
+        .with_decode_cache(cache)
+        .run()
+        .now_or_never()
+        .unwrap()
+        .unwrap();
+    cpu
+}
+
+#[test]
+fn decode_cache_reuse() {
+    let mut cache = DecodeCache::new();
+    for _ in 0..2 {
+        let cpu = run_cached(&mut cache, |asm| asm.mov(qword_ptr(0x200), rax).unwrap());
+        assert_eq!(cpu.mem_val, 0x1111);
+    }
+}
+
+#[test]
+fn decode_cache_modified_code() {
+    // A different instruction at the same RIP must not use the stale entry.
+    let mut cache = DecodeCache::new();
+    let cpu = run_cached(&mut cache, |asm| asm.mov(qword_ptr(0x200), rax).unwrap());
+    assert_eq!(cpu.mem_val, 0x1111);
+    let cpu = run_cached(&mut cache, |asm| asm.mov(qword_ptr(0x200), rcx).unwrap());
+    assert_eq!(cpu.mem_val, 0x2222);
+}
@@ -6,6 +6,7 @@
 mod cmpxchg816;
 mod common;
 mod cond;
+mod decode_cache;
 mod mov;
 mod muldiv;
 mod rep;
@@ -9,6 +9,7 @@
 use x86emu::Gp;
 use x86emu::Segment;
 
+mod avx;
 mod others;
 mod sse;
 mod xchg;
@@ -68,6 +68,12 @@ pub struct AcpiTablesBuilder<'a, T: AcpiTopology> {
     ///
     /// If and only if this has root complexes, then an MCFG will be generated.
     pub pcie_host_bridges: &'a Vec<PcieHostBridge>,
+    /// Additional raw ACPI tables, such as SSDTs, to append to the generated
+    /// tables and reference from the XSDT.
+    ///
+    /// Each table must already have been validated with
+    /// [`acpi::builder::finalize_raw_table`].
+    pub extra_tables: &'a [Vec<u8>],
     /// Architecture-specific ACPI configuration.
     pub arch: AcpiArchConfig,
 }
@@ -962,6 +968,10 @@ fn build_acpi_tables_inner(&self, gpa: u64, dsdt: &[u8]) -> BuiltAcpiTables {
             self.with_gtdt(|t| b.append(t));
         }
 
+        for table in self.extra_tables {
+            b.append_raw(table);
+        }
+
         let (rdsp, tables) = b.build();
 
         BuiltAcpiTables { rdsp, tables }
@@ -1060,6 +1070,7 @@ fn new_builder<'a>(
             mem_layout,
             cache_topology: None,
             pcie_host_bridges,
+            extra_tables: &[],
             arch: AcpiArchConfig::X86 {
                 with_ioapic: true,
                 with_pic: false,
@@ -1196,6 +1207,7 @@ fn new_aarch64_builder<'a>(
             mem_layout,
             cache_topology: None,
             pcie_host_bridges,
+            extra_tables: &[],
             arch: AcpiArchConfig::Aarch64 {
                 hypervisor_vendor_identity: 0,
                 virt_timer_ppi: 20,
@@ -1351,6 +1363,7 @@ fn new_aarch64_builder_with_smmu<'a>(
             mem_layout,
             cache_topology: None,
             pcie_host_bridges,
+            extra_tables: &[],
             arch: AcpiArchConfig::Aarch64 {
                 hypervisor_vendor_identity: 0,
                 virt_timer_ppi: 20,
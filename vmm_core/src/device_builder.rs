@@ -5,6 +5,7 @@
 
 use anyhow::Context as _;
 use chipset_device_resources::ErasedChipsetDevice;
+use closeable_mutex::CloseableMutex;
 use guestmem::DoorbellRegistration;
 use guestmem::GuestMemory;
 use pci_core::msi::MsiConnection;
@@ -19,6 +20,7 @@
 use vmcore::vpci_msi::VpciInterruptMapper;
 use vmotherboard::ArcMutexChipsetDeviceBuilder;
 use vmotherboard::ChipsetBuilder;
+use vpci::bus::VpciBus;
 
 /// Common context for resolving and building a PCI device. These parameters
 /// are shared across PCIe and VPCI device construction.
@@ -42,7 +44,7 @@ pub struct PciDeviceResolveContext<'a> {
 }
 
 /// Resolves a PCI device resource, builds the corresponding device, and builds
-/// a VPCI bus to host it.
+/// a VPCI bus to host it. Returns the bus.
 pub async fn build_vpci_device(
     ctx: PciDeviceResolveContext<'_>,
     vmbus: &VmbusServerControl,
@@ -50,7 +52,7 @@ pub async fn build_vpci_device(
     chipset_builder: &ChipsetBuilder<'_>,
     new_virtual_device: impl FnOnce(u64) -> anyhow::Result<(Arc<dyn SignalMsi>, VpciInterruptMapper)>,
     vtom: Option<u64>,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<Arc<CloseableMutex<VpciBus>>> {
     let device_name = format!("{}:vpci-{instance_id}", ctx.resource.id());
     let driver_source = ctx.driver_source;
 
@@ -62,38 +64,36 @@ pub async fn build_vpci_device(
 
     let device = resolve_and_add_pci_device(device_builder, ctx, msi_conn.target()).await?;
 
-    {
-        let device_id = (instance_id.data2 as u64) << 16 | (instance_id.data3 as u64 & 0xfff8);
-        let vpci_bus_name = format!("vpci:{instance_id}");
-        chipset_builder
-            .arc_mutex_device(vpci_bus_name)
-            .try_add_async(async |services| {
-                let (msi_controller, interrupt_mapper) =
-                    new_virtual_device(device_id).context(format!(
-                        "failed to create virtual device, device_id {device_id} = {} | {}",
-                        instance_id.data2,
-                        instance_id.data3 as u64 & 0xfff8
-                    ))?;
-
-                msi_conn.connect(msi_controller);
-
-                let bus = vpci::bus::VpciBus::new(
-                    driver_source,
-                    instance_id,
-                    device,
-                    &mut services.register_mmio(),
-                    vmbus,
-                    interrupt_mapper,
-                    vtom,
-                )
-                .await?;
-
-                anyhow::Ok(bus)
-            })
+    let device_id = (instance_id.data2 as u64) << 16 | (instance_id.data3 as u64 & 0xfff8);
+    let vpci_bus_name = format!("vpci:{instance_id}");
+    let bus = chipset_builder
+        .arc_mutex_device(vpci_bus_name)
+        .try_add_async(async |services| {
+            let (msi_controller, interrupt_mapper) =
+                new_virtual_device(device_id).context(format!(
+                    "failed to create virtual device, device_id {device_id} = {} | {}",
+                    instance_id.data2,
+                    instance_id.data3 as u64 & 0xfff8
+                ))?;
+
+            msi_conn.connect(msi_controller);
+
+            let bus = VpciBus::new(
+                driver_source,
+                instance_id,
+                device,
+                &mut services.register_mmio(),
+                vmbus,
+                interrupt_mapper,
+                vtom,
+            )
             .await?;
-    }
 
-    Ok(())
+            anyhow::Ok(bus)
+        })
+        .await?;
+
+    Ok(bus)
 }
 
 /// Resolves a PCI device resource, builds the corresponding device, and attaches
@@ -37,7 +37,6 @@
 use thiserror::Error;
 use virt::InitialRegs;
 use virt::PageVisibility;
-#[cfg(feature = "dump")]
 use virt::VpIndex;
 use vm_topology::processor::ProcessorTopology;
 use vmcore::save_restore::ProtobufSaveRestore;
@@ -133,6 +132,7 @@ enum PartitionRequest {
     ),
     StopVps(Rpc<(), ()>),
     StartVps,
+    InjectMachineCheck(Rpc<VpIndex, anyhow::Result<()>>),
     /// Build the partition state blob for a dump file.
     #[cfg(feature = "dump")]
     BuildDumpPartitionState(Rpc<(), anyhow::Result<Vec<u8>>>),
@@ -301,6 +301,14 @@ pub async fn set_initial_page_visibility(
             .unwrap()
     }
 
+    /// Injects a machine check exception into a VP.
+    pub async fn inject_machine_check(&mut self, vp: VpIndex) -> anyhow::Result<()> {
+        self.req_send
+            .call(PartitionRequest::InjectMachineCheck, vp)
+            .await
+            .unwrap()
+    }
+
     /// Builds the partition state blob for a `.vmrs` dump file.
     ///
     /// Stops VPs internally for a consistent snapshot and resumes them
@@ -382,6 +390,10 @@ enum Event {
                     PartitionRequest::StartVps => {
                         self.resume_vps();
                     }
+                    PartitionRequest::InjectMachineCheck(rpc) => {
+                        rpc.handle(async |vp| self.vp_set.inject_machine_check(vp).await)
+                            .await
+                    }
                     #[cfg(feature = "dump")]
                     PartitionRequest::BuildDumpPartitionState(rpc) => {
                         rpc.handle(async |()| self.build_dump_partition_state().await)
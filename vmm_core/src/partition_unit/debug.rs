@@ -56,6 +56,7 @@ pub fn report_halt_to_debugger(&mut self, reason: &HaltReason) -> bool {
                 HaltReason::PowerOff | HaltReason::Hibernate => DebugStopReason::PowerOff,
                 HaltReason::Reset => DebugStopReason::Reset,
                 HaltReason::TripleFault { vp, .. } => DebugStopReason::TripleFault { vp: *vp },
+                HaltReason::GuestCrash { .. } => DebugStopReason::Break,
                 HaltReason::DebugBreak { .. } => DebugStopReason::Break,
                 HaltReason::SingleStep { vp } => DebugStopReason::SingleStep { vp: *vp },
                 HaltReason::HwBreakpoint { vp, breakpoint } => DebugStopReason::HwBreakpoint {
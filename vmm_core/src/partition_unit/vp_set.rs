@@ -78,6 +78,9 @@ fn set_initial_regs(
     /// Scrub per-VP state for a VTL.
     fn scrub(&mut self, vtl: Vtl) -> anyhow::Result<()>;
 
+    /// Inject a machine check exception into a VTL.
+    fn inject_machine_check(&mut self, vtl: Vtl) -> anyhow::Result<()>;
+
     #[cfg(feature = "gdb")]
     fn debug(&mut self) -> &mut dyn DebugVp;
 
@@ -172,6 +175,18 @@ async fn run_vp(
                     breakpoint,
                 })
             }
+            VpHaltReason::GuestCrash { vtl, parameters } => {
+                tracing::error!(
+                    ?vtl,
+                    vp = self.vp_index.index(),
+                    parameters = ?format_args!("{parameters:#x?}"),
+                    "guest crash"
+                );
+                Err(HaltReason::GuestCrash {
+                    vp: self.vp_index.index(),
+                    parameters,
+                })
+            }
         }
     }
 
@@ -206,6 +221,28 @@ fn scrub(&mut self, vtl: Vtl) -> anyhow::Result<()> {
         self.vp.scrub(vtl).map_err(Into::into)
     }
 
+    #[cfg(guest_arch = "x86_64")]
+    fn inject_machine_check(&mut self, vtl: Vtl) -> anyhow::Result<()> {
+        let mut access = self.vp.access_state(vtl);
+        let mut activity = access.activity()?;
+        if activity.pending_event.is_some() {
+            anyhow::bail!("an event is already pending");
+        }
+        activity.pending_event = Some(virt::x86::vp::PendingEvent::Exception {
+            vector: x86defs::Exception::MACHINE_CHECK.0,
+            error_code: None,
+            parameter: 0,
+        });
+        access.set_activity(&activity)?;
+        access.commit()?;
+        Ok(())
+    }
+
+    #[cfg(guest_arch = "aarch64")]
+    fn inject_machine_check(&mut self, _vtl: Vtl) -> anyhow::Result<()> {
+        anyhow::bail!("machine check injection is not supported on aarch64")
+    }
+
     fn set_initial_regs(
         &mut self,
         vtl: Vtl,
@@ -879,6 +916,20 @@ pub async fn scrub(&mut self, vtl: Vtl) -> anyhow::Result<()> {
         Ok(())
     }
 
+    /// Injects a machine check exception into VTL0 of a VP.
+    pub async fn inject_machine_check(&self, vp: VpIndex) -> anyhow::Result<()> {
+        self.vps
+            .get(vp.index() as usize)
+            .context("invalid vp index")?
+            .send
+            .call_failable(
+                |x| VpEvent::State(StateEvent::InjectMachineCheck(x)),
+                Vtl::Vtl0,
+            )
+            .await
+            .with_context(|| format!("vp{} machine check", vp.index()))
+    }
+
     pub async fn save(&mut self) -> Result<Vec<(VpIndex, SavedStateBlob)>, SaveError> {
         assert!(!self.started);
         self.vps
@@ -1101,6 +1152,7 @@ enum StateEvent {
     Restore(Rpc<SavedStateBlob, Result<(), RestoreError>>),
     Reset(mesh::rpc::FailableRpc<(), ()>),
     Scrub(mesh::rpc::FailableRpc<Vtl, ()>),
+    InjectMachineCheck(mesh::rpc::FailableRpc<Vtl, ()>),
     #[cfg(feature = "dump")]
     GetDumpVpState(Rpc<Vtl, anyhow::Result<hyperv_dump::VpState>>),
     #[cfg(feature = "gdb")]
@@ -1347,6 +1399,9 @@ fn state_event(&mut self, vp: &mut dyn ControlVp, event: StateEvent) {
             StateEvent::Restore(rpc) => rpc.handle_sync(|data| vp.restore(data)),
             StateEvent::Reset(rpc) => rpc.handle_failable_sync(|()| vp.reset()),
             StateEvent::Scrub(rpc) => rpc.handle_failable_sync(|vtl| vp.scrub(vtl)),
+            StateEvent::InjectMachineCheck(rpc) => {
+                rpc.handle_failable_sync(|vtl| vp.inject_machine_check(vtl))
+            }
             #[cfg(feature = "dump")]
             StateEvent::GetDumpVpState(rpc) => rpc.handle_sync(|vtl| vp.get_dump_vp_state(vtl)),
             #[cfg(feature = "gdb")]
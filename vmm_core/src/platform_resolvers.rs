@@ -13,7 +13,13 @@
 use vmm_core_defs::HaltReason;
 
 /// Platform power request resolver over [`Halt`].
-pub struct HaltResolver(pub Arc<Halt>);
+pub struct HaltResolver {
+    /// The halt object to issue requests to.
+    pub halt: Arc<Halt>,
+    /// Whether to halt when a device reports a guest crash, rather than just
+    /// logging it.
+    pub halt_on_guest_crash: bool,
+}
 
 impl ResolveResource<PowerRequestHandleKind, PlatformResource> for HaltResolver {
     type Output = PowerRequestClient;
@@ -24,7 +30,8 @@ fn resolve(
         _resource: PlatformResource,
         _input: (),
     ) -> Result<Self::Output, Self::Error> {
-        let halt = self.0.clone();
+        let halt = self.halt.clone();
+        let halt_on_guest_crash = self.halt_on_guest_crash;
         Ok((move |request: PowerRequest| match request {
             PowerRequest::PowerOff => halt.halt(HaltReason::PowerOff),
             PowerRequest::Reset => halt.halt(HaltReason::Reset),
@@ -33,6 +40,16 @@ fn resolve(
                 vp,
                 registers: None,
             }),
+            PowerRequest::GuestCrash { vp, parameters } => {
+                tracing::error!(
+                    vp,
+                    parameters = ?format_args!("{parameters:#x?}"),
+                    "guest crash"
+                );
+                if halt_on_guest_crash {
+                    halt.halt(HaltReason::GuestCrash { vp, parameters })
+                }
+            }
         })
         .into())
     }
@@ -132,6 +132,13 @@ pub async fn revoke(self) -> T {
     }
 }
 
+impl ChannelUnit<dyn VmbusDevice> {
+    /// Revokes a channel.
+    pub async fn revoke(self) -> Box<dyn VmbusDevice> {
+        self.0.revoke().await.unwrap()
+    }
+}
+
 impl<T: 'static + VmbusDevice + ?Sized> StateUnit for &'_ ChannelUnit<T> {
     async fn start(&mut self) {
         self.0.start();
@@ -226,12 +233,30 @@ pub async fn offer_vmbus_device_handle_unit(
     resource: Resource<VmbusDeviceHandleKind>,
 ) -> anyhow::Result<SpawnedUnit<ChannelUnit<dyn VmbusDevice>>> {
     let channel = resolver
-        .resolve(resource, ResolveVmbusDeviceHandleParams { driver_source })
+        .resolve(
+            resource,
+            ResolveVmbusDeviceHandleParams {
+                driver_source,
+                discard_ram: None,
+                hot_add_ram: None,
+            },
+        )
         .await?;
-    let offer = channel.0.offer();
+    offer_vmbus_device_unit(driver_source, state_units, vmbus, channel.0).await
+}
+
+/// Offers an already-resolved vmbus device (such as one that was previously
+/// revoked), creates a unit for it, and adds it to `state_units`.
+pub async fn offer_vmbus_device_unit(
+    driver_source: &VmTaskDriverSource,
+    state_units: &StateUnits,
+    vmbus: &VmbusServerHandle,
+    device: Box<dyn VmbusDevice>,
+) -> anyhow::Result<SpawnedUnit<ChannelUnit<dyn VmbusDevice>>> {
+    let offer = device.offer();
     let name = format!("{}:{}", offer.interface_name, offer.instance_id);
     let handle =
-        offer_generic_channel(&driver_source.simple(), vmbus.control.as_ref(), channel.0).await?;
+        offer_generic_channel(&driver_source.simple(), vmbus.control.as_ref(), device).await?;
     let unit = state_units
         .add(name)
         .depends_on(vmbus.unit.handle())
@@ -47,8 +47,15 @@ pub fn indexed(self, index: u32) -> Self {
         Self { mask, ..self }
     }
 
+    /// Orders leaves by function, with indexed leaves before the unindexed
+    /// leaf for the same function so that the more specific leaf is found
+    /// first.
     fn cmp_key(&self, other: &Self) -> std::cmp::Ordering {
-        (self.function, self.index).cmp(&(other.function, other.index))
+        (self.function, self.index.is_none(), self.index).cmp(&(
+            other.function,
+            other.index.is_none(),
+            other.index,
+        ))
     }
 
     /// Returns true if this result is intended for the given `eax` and `ecx`
@@ -124,6 +131,40 @@ pub fn new(mut leaves: Vec<CpuidLeaf>) -> Self {
         Self { leaves }
     }
 
+    /// Returns a new result set from `leaves`, with `overrides` applied on
+    /// top of them.
+    ///
+    /// Unlike a leaf passed to [`Self::new`], an override is not shadowed by a
+    /// leaf with a different index specificity. An unindexed override for a
+    /// function that has indexed leaves applies to each of those indexes, and
+    /// an indexed override for a function that only has an unindexed leaf
+    /// applies on top of that leaf's result.
+    pub fn with_overrides(mut leaves: Vec<CpuidLeaf>, overrides: &[CpuidLeaf]) -> Self {
+        for &leaf in overrides {
+            let matching = leaves
+                .iter()
+                .filter(|x| x.function == leaf.function)
+                .copied()
+                .collect::<Vec<_>>();
+            match leaf.index {
+                None if matching.iter().any(|x| x.index.is_some()) => {
+                    leaves.extend(matching.iter().filter_map(|x| Some(leaf.indexed(x.index?))));
+                }
+                Some(index) if !matching.iter().any(|x| x.index == Some(index)) => {
+                    leaves.extend(
+                        matching
+                            .iter()
+                            .filter(|x| x.index.is_none())
+                            .map(|x| x.indexed(index)),
+                    );
+                    leaves.push(leaf);
+                }
+                _ => leaves.push(leaf),
+            }
+        }
+        Self::new(leaves)
+    }
+
     /// Returns the merged leaves.
     pub fn into_leaves(self) -> Vec<CpuidLeaf> {
         self.leaves
@@ -146,3 +187,44 @@ pub fn leaves(&self) -> &[CpuidLeaf] {
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::CpuidLeaf;
+    use super::CpuidLeafSet;
+
+    #[test]
+    fn overrides_win() {
+        let leaves = vec![
+            CpuidLeaf::new(1, [1, 1, 1, 1]),
+            CpuidLeaf::new(7, [2, 2, 2, 2]).indexed(0),
+            CpuidLeaf::new(7, [3, 3, 3, 3]).indexed(1),
+            CpuidLeaf::new(0xd, [5, 5, 5, 5]),
+        ];
+        let overrides = [
+            CpuidLeaf::new(1, [0, 0x10, 0, 0]).masked([0, 0x10, 0, 0]),
+            CpuidLeaf::new(7, [0, 0, 0, 0]).masked([0, 0, !0, 0]),
+            CpuidLeaf::new(7, [4, 0, 0, 0])
+                .indexed(1)
+                .masked([!0, 0, 0, 0]),
+            CpuidLeaf::new(0xd, [6, 0, 0, 0])
+                .indexed(1)
+                .masked([!0, 0, 0, 0]),
+        ];
+        let set = CpuidLeafSet::with_overrides(leaves, &overrides);
+        assert_eq!(set.result(1, 0, &[0; 4]), [1, 0x11, 1, 1]);
+        // The unindexed override applies to each indexed leaf, and a later
+        // indexed override applies on top of it.
+        assert_eq!(set.result(7, 0, &[0; 4]), [2, 2, 0, 2]);
+        assert_eq!(set.result(7, 1, &[0; 4]), [4, 3, 0, 3]);
+        // The indexed override applies on top of the unindexed leaf, without
+        // affecting other indexes.
+        assert_eq!(set.result(0xd, 1, &[0; 4]), [6, 5, 5, 5]);
+        assert_eq!(set.result(0xd, 2, &[0; 4]), [5, 5, 5, 5]);
+        assert!(
+            set.leaves()
+                .iter()
+                .all(|x| x.function != 7 || x.index.is_some())
+        );
+    }
+}
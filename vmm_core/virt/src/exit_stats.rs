@@ -0,0 +1,221 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Per-VP exit reason histograms.
+
+use crate::VpIndex;
+use inspect::Inspect;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::Duration;
+use std::time::Instant;
+
+/// How often to emit a snapshot to tracing.
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The exit rate above which snapshots are logged as warnings rather than
+/// at debug level.
+const STORM_EXITS_PER_SEC: u64 = 50_000;
+
+/// How many exits to record between checks of the snapshot timer, to keep
+/// the cost of [`ExitHistogram::record`] down.
+const CHECK_INTERVAL_EXITS: u64 = 1024;
+
+/// The number of reasons to include in a snapshot.
+const SNAPSHOT_TOP_REASONS: usize = 5;
+
+/// A histogram of VP exits by reason.
+///
+/// Backends define their own set of exit reasons, identified by index into
+/// the `names` passed to [`ExitHistogram::new`]. In addition to the running
+/// totals, which are available via `Inspect`, the histogram periodically
+/// logs the exits since the previous snapshot, so that exit storms (such as a
+/// guest busy-looping on an IO port) can be identified from logs alone.
+pub struct ExitHistogram<const N: usize> {
+    vp_index: VpIndex,
+    names: &'static [&'static str; N],
+    total: [u64; N],
+    interval: [u64; N],
+    interval_exits: u64,
+    io_ports: HashMap<u16, u64>,
+    last_snapshot: Instant,
+}
+
+impl<const N: usize> ExitHistogram<N> {
+    /// Returns a new histogram for VP `vp_index` with the exit reasons
+    /// `names`.
+    pub fn new(vp_index: VpIndex, names: &'static [&'static str; N]) -> Self {
+        Self {
+            vp_index,
+            names,
+            total: [0; N],
+            interval: [0; N],
+            interval_exits: 0,
+            io_ports: HashMap::new(),
+            last_snapshot: Instant::now(),
+        }
+    }
+
+    /// Records an exit for reason `reason`, an index into the reason names.
+    pub fn record(&mut self, reason: usize) {
+        self.total[reason] += 1;
+        self.interval[reason] += 1;
+        self.interval_exits += 1;
+        if self.interval_exits.is_multiple_of(CHECK_INTERVAL_EXITS) {
+            self.poll_snapshot();
+        }
+    }
+
+    /// Records an IO port exit for reason `reason`, additionally tracking
+    /// the port so that the busiest port is reported in snapshots.
+    pub fn record_io_port(&mut self, reason: usize, port: u16) {
+        *self.io_ports.entry(port).or_default() += 1;
+        self.record(reason);
+    }
+
+    /// Emits a snapshot to tracing if the snapshot interval has elapsed.
+    ///
+    /// This is called periodically by [`ExitHistogram::record`], but the run
+    /// loop should also call it when it is otherwise idle so that snapshots
+    /// are not delayed indefinitely when there are few exits.
+    pub fn poll_snapshot(&mut self) {
+        let Some(snapshot) = self.take_snapshot(Instant::now()) else {
+            return;
+        };
+        if snapshot.exits_per_sec >= STORM_EXITS_PER_SEC {
+            tracing::warn!(
+                vp = self.vp_index.index(),
+                exits_per_sec = snapshot.exits_per_sec,
+                top_reasons = snapshot.top_reasons.as_str(),
+                busiest_port = snapshot.busiest_port.as_deref(),
+                "high VP exit rate"
+            );
+        } else {
+            tracing::debug!(
+                vp = self.vp_index.index(),
+                exits_per_sec = snapshot.exits_per_sec,
+                top_reasons = snapshot.top_reasons.as_str(),
+                busiest_port = snapshot.busiest_port.as_deref(),
+                "VP exit snapshot"
+            );
+        }
+    }
+
+    /// Returns the exits since the previous snapshot and starts a new
+    /// interval, if the snapshot interval has elapsed by `now` and there were
+    /// any exits.
+    fn take_snapshot(&mut self, now: Instant) -> Option<Snapshot> {
+        let elapsed = now.saturating_duration_since(self.last_snapshot);
+        if elapsed < SNAPSHOT_INTERVAL {
+            return None;
+        }
+        self.last_snapshot = now;
+        if self.interval_exits == 0 {
+            return None;
+        }
+
+        let mut reasons = self
+            .names
+            .iter()
+            .zip(&self.interval)
+            .filter(|&(_, &count)| count != 0)
+            .collect::<Vec<_>>();
+        reasons.sort_by_key(|&(_, &count)| std::cmp::Reverse(count));
+        let mut top_reasons = String::new();
+        for (name, count) in reasons.iter().take(SNAPSHOT_TOP_REASONS) {
+            let _ = write!(
+                top_reasons,
+                "{}{name}={count}",
+                if top_reasons.is_empty() { "" } else { " " }
+            );
+        }
+        let busiest_port = self
+            .io_ports
+            .iter()
+            .max_by_key(|&(_, &count)| count)
+            .map(|(&port, &count)| format!("{port:#x}={count}"));
+
+        let snapshot = Snapshot {
+            exits_per_sec: self.interval_exits / elapsed.as_secs().max(1),
+            top_reasons,
+            busiest_port,
+        };
+
+        self.interval = [0; N];
+        self.interval_exits = 0;
+        self.io_ports.clear();
+        Some(snapshot)
+    }
+}
+
+/// The exits over one snapshot interval.
+#[derive(Debug, PartialEq, Eq)]
+struct Snapshot {
+    exits_per_sec: u64,
+    /// The most frequent exit reasons, as `name=count` pairs.
+    top_reasons: String,
+    /// The most frequently accessed IO port, as `port=count`.
+    busiest_port: Option<String>,
+}
+
+impl<const N: usize> Inspect for ExitHistogram<N> {
+    fn inspect(&self, req: inspect::Request<'_>) {
+        let mut resp = req.respond();
+        for (name, &count) in self.names.iter().zip(&self.total) {
+            resp.counter(name, count);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExitHistogram;
+    use super::SNAPSHOT_INTERVAL;
+    use super::Snapshot;
+    use crate::VpIndex;
+    use std::time::Duration;
+
+    const NAMES: [&str; 3] = ["io", "mmio", "msr"];
+
+    #[test]
+    fn snapshot_interval() {
+        let mut exits = ExitHistogram::new(VpIndex::BSP, &NAMES);
+        let start = exits.last_snapshot;
+        exits.record(1);
+        exits.record_io_port(0, 0x60);
+        exits.record_io_port(0, 0x60);
+        exits.record_io_port(0, 0x64);
+
+        // Nothing is reported until the interval has elapsed.
+        assert_eq!(exits.take_snapshot(start + Duration::from_secs(1)), None);
+
+        let now = start + SNAPSHOT_INTERVAL;
+        assert_eq!(
+            exits.take_snapshot(now),
+            Some(Snapshot {
+                exits_per_sec: 0,
+                top_reasons: "io=3 mmio=1".into(),
+                busiest_port: Some("0x60=2".into()),
+            })
+        );
+
+        // The interval restarts, but the totals are kept.
+        assert_eq!(exits.take_snapshot(now + SNAPSHOT_INTERVAL), None);
+        assert_eq!(exits.total, [3, 1, 0]);
+        assert_eq!(exits.interval, [0; 3]);
+        assert!(exits.io_ports.is_empty());
+    }
+
+    #[test]
+    fn snapshot_rate() {
+        let mut exits = ExitHistogram::new(VpIndex::BSP, &NAMES);
+        let start = exits.last_snapshot;
+        for _ in 0..200 {
+            exits.record(2);
+        }
+        let snapshot = exits.take_snapshot(start + SNAPSHOT_INTERVAL * 2).unwrap();
+        assert_eq!(snapshot.exits_per_sec, 10);
+        assert_eq!(snapshot.top_reasons, "msr=200");
+        assert_eq!(snapshot.busiest_port, None);
+    }
+}
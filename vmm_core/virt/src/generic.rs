@@ -171,6 +171,10 @@ pub struct PartitionConfig<'a> {
     pub guest_memory: &'a GuestMemory,
     /// Cpuid leaves to add to the default CPUID results.
     pub cpuid: &'a [CpuidLeaf],
+    /// User-specified CPUID overrides. Backends apply these after all other
+    /// leaves, including the ones they compute themselves, so that they take
+    /// precedence on every backend.
+    pub cpuid_overrides: &'a [CpuidLeaf],
     /// The offset of the VTL0 alias map. This maps VTL0's view of memory into
     /// VTL2 at the specified offset (which must be a power of 2).
     pub vtl0_alias_map: Option<u64>,
@@ -268,6 +272,10 @@ pub struct HvConfig {
     /// Enable VTL2 support if set. Additional options are described by
     /// [Vtl2Config].
     pub vtl2: Option<Vtl2Config>,
+    /// Halt with [`VpHaltReason::GuestCrash`] when the guest reports a crash
+    /// through the guest crash MSRs. Otherwise the crash is only logged and
+    /// the guest keeps running, so that it can write a crash dump.
+    pub halt_on_guest_crash: bool,
 }
 
 /// Methods for manipulating a VM partition.
@@ -637,6 +645,13 @@ pub enum VpHaltReason {
     SingleStep,
     /// Debugger hardware breakpoint.
     HwBreak(HardwareBreakpoint),
+    /// The guest reported a crash through the Hyper-V guest crash registers.
+    GuestCrash {
+        /// The crashing VTL.
+        vtl: Vtl,
+        /// The guest crash parameter registers (P0 through P4).
+        parameters: [u64; hvdef::HV_X64_GUEST_CRASH_PARAMETER_MSRS],
+    },
 }
 
 impl From<VpStopped> for VpHaltReason {
@@ -5,6 +5,7 @@
 
 pub mod aarch64;
 mod cpuid;
+pub mod exit_stats;
 mod generic;
 pub mod io;
 pub mod irqcon;
@@ -17,6 +17,7 @@
 use vmcore::vpci_msi::MapVpciInterrupt;
 use vmcore::vpci_msi::MsiAddressData;
 use vmcore::vpci_msi::RegisterInterruptError;
+use vmcore::vpci_msi::SavedInterrupt;
 use vmcore::vpci_msi::VpciInterruptParameters;
 use x86defs::msi::MSI_ADDRESS;
 use x86defs::msi::MsiAddress;
@@ -147,10 +148,32 @@ struct InterruptEntry {
     base_vector: u32,
     vector_count: u32,
     multicast: bool,
+    #[inspect(iter_by_index)]
+    target_processors: Vec<u32>,
     target_apic_id: u32,
 }
 
 impl InterruptEntry {
+    fn new(
+        apic_id_map: &[u32],
+        vector_count: u32,
+        params: &VpciInterruptParameters<'_>,
+    ) -> Result<Self, InvalidInterruptParams> {
+        if vector_count == 0 || params.target_processors.is_empty() {
+            return Err(InvalidInterruptParams::InvalidHypercallInput);
+        }
+        let vp = params.target_processors[0];
+        Ok(Self {
+            base_vector: params.vector,
+            vector_count,
+            multicast: params.multicast,
+            target_processors: params.target_processors.to_vec(),
+            target_apic_id: *apic_id_map
+                .get(vp as usize)
+                .ok_or(InvalidInterruptParams::InvalidVirtualProcessor(vp))?,
+        })
+    }
+
     fn msi_params(&self) -> MsiAddressData {
         let address = MsiAddress::new()
             .with_address(MSI_ADDRESS)
@@ -169,6 +192,10 @@ enum InvalidInterruptParams {
     InvalidHypercallInput,
     #[error("invalid virtual processor index {0}")]
     InvalidVirtualProcessor(u32),
+    #[error("invalid interrupt address {0:#x}")]
+    InvalidAddress(u64),
+    #[error("interrupt address {0:#x} is already in use")]
+    AddressInUse(u64),
 }
 
 #[derive(Debug, Error)]
@@ -221,6 +248,7 @@ fn retarget_interrupt(
         });
         if let Some(target_apic_id) = iter.next() {
             interrupt.target_apic_id = target_apic_id?;
+            interrupt.target_processors = params.target_processors.to_vec();
         }
 
         // Check the rest of the VPs.
@@ -235,24 +263,61 @@ fn register_interrupt(
         vector_count: u32,
         params: &VpciInterruptParameters<'_>,
     ) -> Result<MsiAddressData, InvalidInterruptParams> {
-        if vector_count == 0 || params.target_processors.is_empty() {
-            return Err(InvalidInterruptParams::InvalidHypercallInput);
-        }
-
-        // TODO: the caller should specify the interrupt ID (needed for save/restore)
-        let vp = params.target_processors[0];
-        let i = self.entries.insert(InterruptEntry {
-            base_vector: params.vector,
-            vector_count,
-            multicast: params.multicast,
-            target_apic_id: *apic_id_map
-                .get(vp as usize)
-                .ok_or(InvalidInterruptParams::InvalidVirtualProcessor(vp))?,
-        });
+        let entry = InterruptEntry::new(apic_id_map, vector_count, params)?;
+        let i = self.entries.insert(entry);
         let address = Self::interrupt_address_from_index(i);
         Ok(MsiAddressData { address, data: 0 })
     }
 
+    fn save(&self) -> Vec<SavedInterrupt> {
+        self.entries
+            .iter()
+            .map(|(i, entry)| SavedInterrupt {
+                address: Self::interrupt_address_from_index(i),
+                data: 0,
+                vector: entry.base_vector,
+                vector_count: entry.vector_count,
+                multicast: entry.multicast,
+                target_processors: entry.target_processors.clone(),
+            })
+            .collect()
+    }
+
+    /// Restores saved interrupts at the indexes encoded in their addresses,
+    /// so that the addresses handed out before the save remain valid.
+    fn restore(
+        &mut self,
+        apic_id_map: &[u32],
+        interrupts: &[SavedInterrupt],
+    ) -> Result<(), InvalidInterruptParams> {
+        let mut entries = Vec::with_capacity(interrupts.len());
+        for interrupt in interrupts {
+            if interrupt.address
+                != Self::interrupt_address_from_index(Self::interrupt_index_from_address(
+                    interrupt.address,
+                ))
+            {
+                return Err(InvalidInterruptParams::InvalidAddress(interrupt.address));
+            }
+            let index = Self::interrupt_index_from_address(interrupt.address);
+            if self.entries.contains(index) || entries.iter().any(|&(i, _)| i == index) {
+                return Err(InvalidInterruptParams::AddressInUse(interrupt.address));
+            }
+            let params = VpciInterruptParameters {
+                vector: interrupt.vector,
+                multicast: interrupt.multicast,
+                target_processors: &interrupt.target_processors,
+            };
+            entries.push((
+                index,
+                InterruptEntry::new(apic_id_map, interrupt.vector_count, &params)?,
+            ));
+        }
+        entries.extend(std::mem::take(&mut self.entries));
+        self.entries = entries.into_iter().collect();
+        Ok(())
+    }
+
     fn unregister_interrupt(&mut self, address: u64, _data: u32) {
         let index = Self::interrupt_index_from_address(address);
         self.entries.remove(index);
@@ -286,4 +351,22 @@ async fn register_interrupt(
     async fn unregister_interrupt(&self, address: u64, data: u32) {
         self.table.lock().unregister_interrupt(address, data)
     }
+
+    fn supports_save_restore(&self) -> bool {
+        true
+    }
+
+    fn save_interrupts(&self) -> Vec<SavedInterrupt> {
+        self.table.lock().save()
+    }
+
+    fn restore_interrupts(
+        &self,
+        interrupts: &[SavedInterrupt],
+    ) -> Result<(), RegisterInterruptError> {
+        self.table
+            .lock()
+            .restore(&self.devices.apic_id_map, interrupts)
+            .map_err(RegisterInterruptError::new)
+    }
 }
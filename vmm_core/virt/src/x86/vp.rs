@@ -872,6 +872,45 @@ pub fn fxsave(&self) -> Fxsave {
         fxsave
     }
 
+    /// Returns the upper 128 bits of YMM register `index`, or `None` if the
+    /// state does not include AVX.
+    pub fn ymm_upper(&self, index: usize) -> Option<u128> {
+        let header = self.xsave_header();
+        if header.xcomp_bv & XFEATURE_YMM == 0 {
+            return None;
+        }
+        if header.xstate_bv & XFEATURE_YMM == 0 {
+            return Some(0);
+        }
+        // YMM_Hi128 is the first component of the compact format.
+        let offset = XSAVE_VARIABLE_OFFSET + index * 16;
+        Some(u128::from_le_bytes(
+            self.data.as_bytes()[offset..offset + 16]
+                .try_into()
+                .unwrap(),
+        ))
+    }
+
+    /// Sets the upper 128 bits of YMM register `index`. Returns `None` if the
+    /// state does not include AVX.
+    pub fn set_ymm_upper(&mut self, index: usize, value: u128) -> Option<()> {
+        let (mut fxsave, data) = Ref::<_, Fxsave>::from_prefix(self.data.as_mut_bytes()).unwrap();
+        let (header, data) = XsaveHeader::mut_from_prefix(data).unwrap();
+        if header.xcomp_bv & XFEATURE_YMM == 0 {
+            return None;
+        }
+        if header.xstate_bv & XFEATURE_YMM == 0 {
+            // The other registers are in their init state.
+            data[..16 * 16].fill(0);
+            if header.xstate_bv & XFEATURE_SSE == 0 {
+                fxsave.mxcsr = DEFAULT_MXCSR;
+            }
+            header.xstate_bv |= XFEATURE_YMM;
+        }
+        data[index * 16..index * 16 + 16].copy_from_slice(&value.to_le_bytes());
+        Some(())
+    }
+
     fn xsave_header(&self) -> &XsaveHeader {
         XsaveHeader::ref_from_prefix(&self.data.as_bytes()[XSAVE_LEGACY_LEN..])
             .unwrap()
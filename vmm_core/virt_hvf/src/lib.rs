@@ -58,6 +58,7 @@
 use virt::VpIndex;
 use virt::aarch64::Aarch64PartitionCapabilities;
 use virt::aarch64::vm::AccessVmState;
+use virt::exit_stats::ExitHistogram;
 use virt::io::CpuIo;
 use virt::state::StateElement;
 use virt::vp::AccessVpState;
@@ -587,6 +588,7 @@ fn bind(&mut self) -> Result<Self::Processor<'_>, Self::Error> {
             gicr: state.gicr,
             hv1: state.hv1,
             vmtime: state.vmtime,
+            exits: ExitHistogram::new(self.vp_index, &EXIT_REASON_NAMES),
         };
 
         // Set initial register state.
@@ -615,6 +617,38 @@ pub struct HvfProcessor<'a> {
     vcpu: HvfVcpu,
     wfi: bool,
     on: bool,
+    exits: ExitHistogram<{ EXIT_REASON_NAMES.len() }>,
+}
+
+/// The names of the exit reasons tracked in [`HvfProcessor::exits`], indexed
+/// by [`exit_reason`].
+const EXIT_REASON_NAMES: [&str; 9] = [
+    "canceled",
+    "data_abort",
+    "system",
+    "hvc",
+    "smc",
+    "wfi",
+    "other_exception",
+    "vtimer_activated",
+    "other",
+];
+
+/// Returns the index into [`EXIT_REASON_NAMES`] for `exit`.
+fn exit_reason(exit: &abi::HvVcpuExit) -> usize {
+    match exit.reason {
+        abi::HvExitReason::CANCELED => 0,
+        abi::HvExitReason::EXCEPTION => match ExceptionClass(exit.exception.syndrome.ec()) {
+            ExceptionClass::DATA_ABORT_LOWER => 1,
+            ExceptionClass::SYSTEM => 2,
+            ExceptionClass::HVC => 3,
+            ExceptionClass::SMC => 4,
+            ExceptionClass::WFI => 5,
+            _ => 6,
+        },
+        abi::HvExitReason::VTIMER_ACTIVATED => 7,
+        _ => 8,
+    }
 }
 
 #[derive(Debug, Inspect)]
@@ -882,6 +916,7 @@ async fn run_vp(
         let mut last_waker = None;
         loop {
             self.inner.needs_yield.maybe_yield().await;
+            self.exits.poll_snapshot();
 
             poll_fn(|cx| {
                 loop {
@@ -983,6 +1018,7 @@ async fn run_vp(
                 .chk()
                 .map_err(|err| dev.fatal_error(err.into()))?;
 
+            self.exits.record(exit_reason(&self.vcpu.exit));
             match self.vcpu.exit.reason {
                 abi::HvExitReason::CANCELED => {
                     continue;
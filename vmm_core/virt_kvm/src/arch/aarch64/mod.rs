@@ -51,6 +51,7 @@
 use virt::StopVp;
 use virt::VpHaltReason;
 use virt::VpIndex;
+use virt::exit_stats::ExitHistogram;
 use virt::io::CpuIo;
 use virt::vp::Registers;
 use virt::vp::SystemRegisters;
@@ -283,6 +284,26 @@ pub struct KvmProcessor<'a> {
     kvm: kvm::Processor<'a>,
     vpindex: VpIndex,
     vmtime: &'a mut VmTimeAccess,
+    exits: &'a mut KvmExitHistogram,
+}
+
+/// The exit histogram for a VP, kept in the binder so that it spans the VP's
+/// lifetime rather than being reset by each bind.
+pub(crate) type KvmExitHistogram = ExitHistogram<{ EXIT_REASON_NAMES.len() }>;
+
+/// The names of the exit reasons tracked in [`KvmProcessor::exits`], indexed
+/// by [`exit_reason`].
+const EXIT_REASON_NAMES: [&str; 5] = ["interrupted", "mmio", "shutdown", "system_event", "error"];
+
+/// Returns the index into [`EXIT_REASON_NAMES`] for `exit`.
+fn exit_reason(exit: &kvm::Exit<'_>) -> usize {
+    match exit {
+        kvm::Exit::Interrupted => 0,
+        kvm::Exit::MmioRead { .. } | kvm::Exit::MmioWrite { .. } => 1,
+        kvm::Exit::Shutdown => 2,
+        kvm::Exit::SystemEvent { .. } => 3,
+        _ => 4,
+    }
 }
 
 impl virt::vp::AccessVpState for &'_ mut KvmProcessor<'_> {
@@ -431,11 +452,11 @@ impl virt::vm::AccessVmState for &KvmPartition {
     type Error = KvmError;
 
     fn caps(&self) -> &PartitionCapabilities {
-        unimplemented!()
+        &self.inner.caps
     }
 
     fn commit(&mut self) -> Result<(), Self::Error> {
-        unimplemented!()
+        Ok(())
     }
 }
 
@@ -448,9 +469,14 @@ impl virt::Processor for KvmProcessor<'_> {
     fn set_debug_state(
         &mut self,
         _vtl: Vtl,
-        _state: Option<&DebugState>,
+        state: Option<&DebugState>,
     ) -> Result<(), <&mut Self as virt::vp::AccessVpState>::Error> {
-        unimplemented!()
+        // Guest debugging is not yet wired up on aarch64, but clearing the
+        // debug state (e.g. when a debugger detaches) is trivially supported.
+        match state {
+            None => Ok(()),
+            Some(_) => Err(KvmError::NotSupported),
+        }
     }
 
     async fn run_vp(
@@ -461,6 +487,7 @@ async fn run_vp(
         loop {
             self.inner.needs_yield.maybe_yield().await;
             stop.check()?;
+            self.exits.poll_snapshot();
 
             // Run the VP and handle exits until `evaluate_vp` is called or the
             // thread is otherwise interrupted.
@@ -484,6 +511,7 @@ async fn run_vp(
 
                 let exit = exit.map_err(|err| dev.fatal_error(KvmRunVpError::Run(err).into()))?;
                 pending_exit = true;
+                self.exits.record(exit_reason(&exit));
                 match exit {
                     kvm::Exit::Interrupted => {
                         pending_exit = false;
@@ -546,6 +574,7 @@ pub struct KvmProcessorBinder {
     partition: Arc<KvmPartitionInner>,
     vpindex: VpIndex,
     vmtime: VmTimeAccess,
+    exits: KvmExitHistogram,
 }
 
 impl virt::BindProcessor for KvmProcessorBinder {
@@ -564,6 +593,7 @@ fn bind(&mut self) -> Result<Self::Processor<'_>, Self::Error> {
             kvm,
             vpindex: self.vpindex,
             vmtime: &mut self.vmtime,
+            exits: &mut self.exits,
         };
 
         Ok(vp)
@@ -874,6 +904,7 @@ fn build(
                     .config
                     .vmtime
                     .access(format!("vp-{}", vp.vp_index.index())),
+                exits: ExitHistogram::new(vp.vp_index, &EXIT_REASON_NAMES),
             })
             .collect::<Vec<_>>();
 
@@ -892,8 +923,12 @@ fn caps(&self) -> &PartitionCapabilities {
         &self.inner.caps
     }
 
-    fn request_msi(&self, _vtl: Vtl, _request: virt::irqcon::MsiRequest) {
-        tracelimit::warn_ratelimited!("msis not supported");
+    fn request_msi(&self, vtl: Vtl, request: virt::irqcon::MsiRequest) {
+        let Some(signal_msi) = self.as_signal_msi(vtl) else {
+            tracelimit::warn_ratelimited!("no MSI controller configured");
+            return;
+        };
+        signal_msi.signal_msi(None, request.address, request.data);
     }
 
     fn as_signal_msi(&self, _minimum_vtl: Vtl) -> Option<Arc<dyn pci_core::msi::SignalMsi>> {
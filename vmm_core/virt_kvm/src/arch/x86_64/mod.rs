@@ -64,6 +64,7 @@
 use virt::StopVp;
 use virt::VpHaltReason;
 use virt::VpIndex;
+use virt::exit_stats::ExitHistogram;
 use virt::io::CpuIo;
 use virt::irqcon::DeliveryMode;
 use virt::irqcon::IoApicRouting;
@@ -242,7 +243,8 @@ fn new_partition<'a>(
                     split_u128(u128::from(
                         HvFeatures::new()
                             .with_privileges(privileges)
-                            .with_frequency_regs_available(true),
+                            .with_frequency_regs_available(true)
+                            .with_guest_crash_regs_available(hv_config.halt_on_guest_crash),
                     )),
                 ),
                 CpuidLeaf::new(
@@ -303,17 +305,12 @@ fn build(
         )
         .map_err(KvmError::TopologyCpuid)?;
 
-        // Work around a KVM bug where PSFD is advertised in guest CPUID
-        // but the SPEC_CTRL MSR is not accessible. Check the KVM-reported
-        // CPUID (before user overrides) since that determines what KVM
-        // will allow.
-        let psfd_fixup = strip_psfd_leaf(&self.cpuid);
-
-        let mut cpuid = self.cpuid.into_leaves();
-        cpuid.extend(config.cpuid);
-        cpuid.extend(topology_leaves);
-        cpuid.extend(psfd_fixup);
-        let cpuid = CpuidLeafSet::new(cpuid);
+        let cpuid = partition_cpuid(
+            self.cpuid,
+            config.cpuid,
+            topology_leaves,
+            config.cpuid_overrides,
+        );
 
         let bsp_apic_id = self.config.processor_topology.vp_arch(VpIndex::BSP).apic_id;
         if bsp_apic_id != 0 {
@@ -343,6 +340,20 @@ fn build(
                     ),
                     (hvdef::HV_X64_MSR_SCONTROL, 1),
                 ])?;
+
+                // KVM only reports guest crashes with a system event exit if
+                // the notify bit is set by the host.
+                if self
+                    .config
+                    .hv_config
+                    .as_ref()
+                    .is_some_and(|cfg| cfg.halt_on_guest_crash)
+                {
+                    vp.set_msrs(&[(
+                        hvdef::HV_X64_MSR_GUEST_CRASH_CTL,
+                        hvdef::GuestCrashCtl::new().with_crash_notify(true).into(),
+                    )])?;
+                }
             }
 
             // Unlike the Microsoft hypervisor, KVM allows this MSR to be set and
@@ -483,6 +494,7 @@ fn build(
                     .config
                     .vmtime
                     .access(format!("vp-{}", vp.vp_index.index())),
+                exits: ExitHistogram::new(vp.vp_index, &EXIT_REASON_NAMES),
             })
             .collect::<Vec<_>>();
 
@@ -501,6 +513,28 @@ fn build(
 /// guest that sees PSFD and infers SPEC_CTRL MSR support (as Hyper-V
 /// does) will #GP when writing the MSR.
 ///
+/// Returns the partition's CPUID leaves, built from the KVM-reported `base`
+/// leaves, the VMM's `config` leaves, and the `topology` leaves, in increasing
+/// order of precedence. User `overrides` are applied last, on top of KVM's own
+/// fixups.
+fn partition_cpuid(
+    base: CpuidLeafSet,
+    config: &[CpuidLeaf],
+    topology: Vec<CpuidLeaf>,
+    overrides: &[CpuidLeaf],
+) -> CpuidLeafSet {
+    // Work around a KVM bug where PSFD is advertised in guest CPUID but the
+    // SPEC_CTRL MSR is not accessible. Check the KVM-reported CPUID since
+    // that determines what KVM will allow.
+    let psfd_fixup = strip_psfd_leaf(&base);
+
+    let mut cpuid = base.into_leaves();
+    cpuid.extend(config);
+    cpuid.extend(topology);
+    cpuid.extend(psfd_fixup);
+    CpuidLeafSet::with_overrides(cpuid, overrides)
+}
+
 /// Returns a leaf that strips PSFD when it should not be advertised.
 fn strip_psfd_leaf(cpuid: &CpuidLeafSet) -> Option<CpuidLeaf> {
     use x86defs::cpuid::ExtendedAddressSpaceSizesEbx;
@@ -692,6 +726,7 @@ fn bind(&mut self) -> Result<Self::Processor<'_>, Self::Error> {
             siefp: 0.into(),
             simp: 0.into(),
             vmtime: &mut self.vmtime,
+            exits: &mut self.exits,
         };
 
         // 1. Reset the APIC state to clear the directed EOI bit, which is
@@ -738,6 +773,48 @@ pub struct KvmProcessor<'a> {
     siefp: HvSynicSimpSiefp,
     #[inspect(hex, with = "|&x| u64::from(x)")]
     simp: HvSynicSimpSiefp,
+    exits: &'a mut KvmExitHistogram,
+}
+
+/// The exit histogram for a VP, kept in the binder so that it spans the VP's
+/// lifetime rather than being reset by each bind.
+pub(crate) type KvmExitHistogram = ExitHistogram<{ EXIT_REASON_NAMES.len() }>;
+
+/// The names of the exit reasons tracked in [`KvmProcessor::exits`], indexed
+/// by [`exit_reason`].
+const EXIT_REASON_NAMES: [&str; 12] = [
+    "interrupted",
+    "interrupt_window",
+    "io",
+    "mmio",
+    "msr",
+    "synic_update",
+    "hypercall",
+    "debug",
+    "eoi",
+    "shutdown",
+    "system_event",
+    "error",
+];
+
+/// Returns the index into [`EXIT_REASON_NAMES`] for `exit`.
+fn exit_reason(exit: &kvm::Exit<'_>) -> usize {
+    match exit {
+        kvm::Exit::Interrupted => 0,
+        kvm::Exit::InterruptWindow => 1,
+        kvm::Exit::IoIn { .. } | kvm::Exit::IoOut { .. } => 2,
+        kvm::Exit::MmioRead { .. } | kvm::Exit::MmioWrite { .. } => 3,
+        kvm::Exit::MsrRead { .. } | kvm::Exit::MsrWrite { .. } => 4,
+        kvm::Exit::SynicUpdate { .. } => 5,
+        kvm::Exit::HvHypercall { .. } => 6,
+        kvm::Exit::Debug { .. } => 7,
+        kvm::Exit::Eoi { .. } => 8,
+        kvm::Exit::Shutdown => 9,
+        kvm::Exit::SystemEvent { .. } => 10,
+        kvm::Exit::InternalError { .. }
+        | kvm::Exit::EmulationFailure { .. }
+        | kvm::Exit::FailEntry { .. } => 11,
+    }
 }
 
 impl KvmProcessor<'_> {
@@ -1218,6 +1295,8 @@ async fn run_vp(
                 }
             }
 
+            self.exits.poll_snapshot();
+
             // Arm the timer. If it has expired, then loop around to scan for
             // synic messages again.
             if poll_fn(|cx| Poll::Ready(self.vmtime.poll_timeout(cx).is_ready())).await {
@@ -1246,6 +1325,12 @@ async fn run_vp(
 
                 let exit = exit.map_err(|err| dev.fatal_error(KvmRunVpError::Run(err).into()))?;
                 pending_exit = true;
+                match exit {
+                    kvm::Exit::IoIn { port, .. } | kvm::Exit::IoOut { port, .. } => {
+                        self.exits.record_io_port(exit_reason(&exit), port)
+                    }
+                    _ => self.exits.record(exit_reason(&exit)),
+                }
                 match exit {
                     kvm::Exit::Interrupted => {
                         tracing::trace!("interrupted");
@@ -1359,6 +1444,38 @@ async fn run_vp(
                         tracing::error!(hardware_entry_failure_reason, "VP entry failed");
                         return Err(dev.fatal_error(KvmRunVpError::InvalidVpState.into()));
                     }
+                    kvm::Exit::SystemEvent {
+                        event_type,
+                        event_flags,
+                    } => {
+                        tracing::info!(event_type, event_flags, "system event");
+                        if event_type != kvm::KVM_SYSTEM_EVENT_CRASH {
+                            return Err(dev.fatal_error(
+                                KvmRunVpError::UnhandledSystemEvent(event_type).into(),
+                            ));
+                        }
+                        let mut parameters = [0; hvdef::HV_X64_GUEST_CRASH_PARAMETER_MSRS];
+                        self.partition
+                            .vp_state_access(self.vpindex)
+                            .kvm()
+                            .get_msrs(
+                                &[
+                                    hvdef::HV_X64_MSR_GUEST_CRASH_P0,
+                                    hvdef::HV_X64_MSR_GUEST_CRASH_P1,
+                                    hvdef::HV_X64_MSR_GUEST_CRASH_P2,
+                                    hvdef::HV_X64_MSR_GUEST_CRASH_P3,
+                                    hvdef::HV_X64_MSR_GUEST_CRASH_P4,
+                                ],
+                                &mut parameters,
+                            )
+                            .map_err(|err| {
+                                dev.fatal_error(KvmRunVpError::CrashParameters(err).into())
+                            })?;
+                        return Err(VpHaltReason::GuestCrash {
+                            vtl: Vtl::Vtl0,
+                            parameters,
+                        });
+                    }
                 }
             }
         }
@@ -1505,3 +1622,34 @@ fn signal_msi(&self, _devid: Option<u32>, address: u64, data: u32) {
         self.request_msi(MsiRequest { address, data });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::partition_cpuid;
+    use virt::CpuidLeaf;
+    use virt::CpuidLeafSet;
+    use x86defs::cpuid::CpuidFunction;
+    use x86defs::cpuid::ExtendedAddressSpaceSizesEbx;
+
+    #[test]
+    fn overrides_apply_after_fixups() {
+        let function = CpuidFunction::ExtendedAddressSpaceSizes.0;
+        let psfd = u32::from(ExtendedAddressSpaceSizesEbx::new().with_psfd(true));
+        let base = || CpuidLeafSet::new(vec![CpuidLeaf::new(function, [0, psfd, 0, 0])]);
+
+        // Without SPEC_CTRL support, KVM's PSFD is stripped...
+        let cpuid = partition_cpuid(base(), &[], Vec::new(), &[]);
+        assert_eq!(cpuid.result(function, 0, &[0; 4])[1], 0);
+
+        // ...unless the user overrides it back on.
+        let overrides = [CpuidLeaf::new(function, [0, psfd, 0, 0]).masked([0, psfd, 0, 0])];
+        let cpuid = partition_cpuid(base(), &[], Vec::new(), &overrides);
+        assert_eq!(cpuid.result(function, 0, &[0; 4])[1], psfd);
+
+        // Overrides also take precedence over the topology leaves.
+        let topology = vec![CpuidLeaf::new(0xb, [1, 2, 3, 4]).indexed(0)];
+        let overrides = [CpuidLeaf::new(0xb, [0, 0, 0, 0]).masked([!0, 0, 0, 0])];
+        let cpuid = partition_cpuid(base(), &[], topology, &overrides);
+        assert_eq!(cpuid.result(0xb, 0, &[0; 4]), [0, 2, 3, 4]);
+    }
+}
@@ -133,10 +133,12 @@ enum KvmRunVpError {
     InvalidVpState,
     #[error("failed to run VP")]
     Run(#[source] kvm::Error),
-    #[cfg_attr(guest_arch = "x86_64", expect(dead_code))]
     #[error("unhandled system event type: {0:#x}")]
     UnhandledSystemEvent(u32),
     #[cfg(guest_arch = "x86_64")]
+    #[error("failed to read the guest crash parameters")]
+    CrashParameters(#[source] kvm::Error),
+    #[cfg(guest_arch = "x86_64")]
     #[error("failed to inject an extint interrupt")]
     ExtintInterrupt(#[source] kvm::Error),
 }
@@ -146,6 +148,7 @@ pub struct KvmProcessorBinder {
     partition: Arc<KvmPartitionInner>,
     vpindex: VpIndex,
     vmtime: VmTimeAccess,
+    exits: arch::KvmExitHistogram,
 }
 
 impl KvmPartitionInner {
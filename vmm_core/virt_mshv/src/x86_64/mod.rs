@@ -237,7 +237,8 @@ fn build(
         self,
         config: PartitionConfig<'_>,
     ) -> Result<(Self::Partition, Vec<Self::ProcessorBinder>), Self::Error> {
-        let cpuid = virt::CpuidLeafSet::new(config.cpuid.to_vec());
+        let cpuid =
+            virt::CpuidLeafSet::with_overrides(config.cpuid.to_vec(), config.cpuid_overrides);
 
         // Apply CPUID overrides partition-wide.
         for leaf in cpuid.leaves().iter() {
@@ -277,6 +278,26 @@ fn build(
                 .map_err(|e| ErrorInner::RegisterCpuid(e.into()))?;
         }
 
+        // Intercept writes to the crash control MSR so that guest crashes
+        // halt the VM. The crash parameter MSRs are still handled by the
+        // hypervisor.
+        if self
+            .config
+            .hv_config
+            .as_ref()
+            .is_some_and(|cfg| cfg.halt_on_guest_crash)
+        {
+            self.vmfd
+                .install_intercept(mshv_bindings::mshv_install_intercept {
+                    access_type_mask: hvdef::hypercall::HV_INTERCEPT_ACCESS_MASK_WRITE,
+                    intercept_type: hvdef::hypercall::HvInterceptType::HvInterceptTypeX64MsrIndex.0,
+                    intercept_parameter: mshv_bindings::hv_intercept_parameters {
+                        as_uint64: hvdef::HV_X64_MSR_GUEST_CRASH_CTL.into(),
+                    },
+                })
+                .map_err(|e| ErrorInner::InstallIntercept(e.into()))?;
+        }
+
         let caps = {
             let mut caps = match self.bsp.get_cpuid_values(0, 0, 0, 0) {
                 Ok(_) => virt::PartitionCapabilities::from_cpuid(
@@ -617,6 +638,9 @@ pub(crate) async fn handle_exit(
                 let msg = exit.as_message::<hvdef::HvX64ApicEoiMessage>();
                 dev.handle_eoi(msg.interrupt_vector);
             }
+            HvMessageType::HvMessageTypeX64MsrIntercept => {
+                self.handle_msr_intercept(exit, dev)?;
+            }
             exit_type => {
                 panic!("Unhandled vcpu exit code {exit_type:?}");
             }
@@ -669,6 +693,47 @@ async fn handle_mmio_intercept(
         self.emulate(message, devices, interruption_pending).await
     }
 
+    /// Handles a write to the crash control MSR, the only MSR intercepted.
+    fn handle_msr_intercept(
+        &mut self,
+        message: &HvMessage,
+        dev: &impl CpuIo,
+    ) -> Result<(), VpHaltReason> {
+        let info = message.as_message::<hvdef::HvX64MsrInterceptMessage>();
+        let rp = self.runner.reg_page();
+        rp.rip = info.header.rip + info.header.instruction_len() as u64;
+        rp.dirty.set_instruction_pointer(true);
+
+        if info.header.intercept_access_type != hvdef::HvInterceptAccessType::WRITE
+            || info.msr_number != hvdef::HV_X64_MSR_GUEST_CRASH_CTL
+        {
+            tracelimit::warn_ratelimited!(msr = info.msr_number, "unexpected msr intercept");
+            return Ok(());
+        }
+
+        let value = (info.rax & 0xffff_ffff) | (info.rdx << 32);
+        if !hvdef::GuestCrashCtl::from(value).crash_notify() {
+            return Ok(());
+        }
+
+        let mut assoc = [
+            HvX64RegisterName::GuestCrashP0,
+            HvX64RegisterName::GuestCrashP1,
+            HvX64RegisterName::GuestCrashP2,
+            HvX64RegisterName::GuestCrashP3,
+            HvX64RegisterName::GuestCrashP4,
+        ]
+        .map(|name| HvRegisterAssoc::from((name, 0u64)));
+        self.runner
+            .vcpufd
+            .get_hvdef_regs(&mut assoc)
+            .map_err(|err| dev.fatal_error(ErrorInner::Register(err).into()))?;
+        Err(VpHaltReason::GuestCrash {
+            vtl: Vtl::Vtl0,
+            parameters: assoc.map(|assoc| assoc.value.as_u64()),
+        })
+    }
+
     fn handle_hypercall_intercept(&mut self, message: &HvMessage, _devices: &impl CpuIo) {
         let info = message.as_message::<hvdef::HvX64HypercallInterceptMessage>();
         let is_64bit =
@@ -738,6 +803,10 @@ fn cr0(&mut self) -> u64 {
         self.reg_page.cr0
     }
 
+    fn cr3(&mut self) -> Option<u64> {
+        Some(self.reg_page.cr3)
+    }
+
     fn rflags(&mut self) -> RFlags {
         RFlags::from(self.reg_page.rflags)
     }
@@ -771,6 +840,18 @@ fn set_xmm(&mut self, reg: usize, value: u128) {
         }
     }
 
+    fn ymm_upper(&mut self, reg: usize) -> Option<u128> {
+        vp_state::get_xsave(self.vcpufd, &self.partition.caps)
+            .ok()?
+            .ymm_upper(reg)
+    }
+
+    fn set_ymm_upper(&mut self, reg: usize, value: u128) -> Option<()> {
+        let mut xsave = vp_state::get_xsave(self.vcpufd, &self.partition.caps).ok()?;
+        xsave.set_ymm_upper(reg, value)?;
+        vp_state::set_xsave(self.vcpufd, &xsave).ok()
+    }
+
     fn flush(&mut self) {}
 
     fn instruction_bytes(&self) -> &[u8] {
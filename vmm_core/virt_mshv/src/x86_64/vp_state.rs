@@ -11,6 +11,7 @@
 use mshv_bindings::MSHV_VP_STATE_SIMP;
 use mshv_bindings::MSHV_VP_STATE_SYNTHETIC_TIMERS;
 use mshv_bindings::mshv_get_set_vp_state;
+use mshv_ioctls::VcpuFd;
 use std::ptr::NonNull;
 use std::sync::OnceLock;
 use virt::state::HvRegisterState;
@@ -62,24 +63,7 @@ pub(crate) fn get_register_state<T, const N: usize>(&self) -> Result<T, Error>
     }
 
     fn set_state(&self, ty: u32, data: &[u8]) -> Result<(), Error> {
-        // The kernel requires a page-aligned buffer for VP state operations.
-        let mut buf = PageAlignedBuffer::new(data.len());
-        buf.as_mut_bytes().copy_from_slice(data);
-
-        let vp_state = mshv_get_set_vp_state {
-            type_: ty as u8,
-            buf_sz: buf.aligned_len() as u32,
-            buf_ptr: buf.as_ptr() as u64,
-            ..Default::default()
-        };
-        self.runner
-            .vcpufd
-            .set_vp_state_ioctl(&vp_state)
-            .map_err(|e| ErrorInner::SetVpState {
-                error: e.into(),
-                ty: ty as u8,
-            })?;
-        Ok(())
+        set_vp_state(self.runner.vcpufd, ty, data)
     }
 
     fn get_fixed_state<T: zerocopy::FromBytes>(&self, ty: u32) -> Result<T, Error> {
@@ -88,22 +72,7 @@ fn get_fixed_state<T: zerocopy::FromBytes>(&self, ty: u32) -> Result<T, Error> {
     }
 
     fn get_state(&self, ty: u32, size: usize) -> Result<PageAlignedBuffer, Error> {
-        // The kernel requires a page-aligned buffer for VP state operations.
-        let mut buf = PageAlignedBuffer::new(size);
-        let mut vp_state = mshv_get_set_vp_state {
-            type_: ty as u8,
-            buf_sz: buf.aligned_len() as u32,
-            buf_ptr: buf.as_mut_ptr() as u64,
-            ..Default::default()
-        };
-        self.runner
-            .vcpufd
-            .get_vp_state_ioctl(&mut vp_state)
-            .map_err(|e| ErrorInner::GetVpState {
-                error: e.into(),
-                ty: ty as u8,
-            })?;
-        Ok(buf)
+        get_vp_state(self.runner.vcpufd, ty, size)
     }
 
     fn get_lapic(&self) -> Result<ApicRegisters, Error> {
@@ -119,6 +88,62 @@ fn set_lapic(&self, lapic: &ApicRegisters) -> Result<(), Error> {
     }
 }
 
+fn set_vp_state(vcpufd: &VcpuFd, ty: u32, data: &[u8]) -> Result<(), Error> {
+    // The kernel requires a page-aligned buffer for VP state operations.
+    let mut buf = PageAlignedBuffer::new(data.len());
+    buf.as_mut_bytes().copy_from_slice(data);
+
+    let vp_state = mshv_get_set_vp_state {
+        type_: ty as u8,
+        buf_sz: buf.aligned_len() as u32,
+        buf_ptr: buf.as_ptr() as u64,
+        ..Default::default()
+    };
+    vcpufd
+        .set_vp_state_ioctl(&vp_state)
+        .map_err(|e| ErrorInner::SetVpState {
+            error: e.into(),
+            ty: ty as u8,
+        })?;
+    Ok(())
+}
+
+fn get_vp_state(vcpufd: &VcpuFd, ty: u32, size: usize) -> Result<PageAlignedBuffer, Error> {
+    // The kernel requires a page-aligned buffer for VP state operations.
+    let mut buf = PageAlignedBuffer::new(size);
+    let mut vp_state = mshv_get_set_vp_state {
+        type_: ty as u8,
+        buf_sz: buf.aligned_len() as u32,
+        buf_ptr: buf.as_mut_ptr() as u64,
+        ..Default::default()
+    };
+    vcpufd
+        .get_vp_state_ioctl(&mut vp_state)
+        .map_err(|e| ErrorInner::GetVpState {
+            error: e.into(),
+            ty: ty as u8,
+        })?;
+    Ok(buf)
+}
+
+/// Gets the xsave state of the VP behind `vcpufd`.
+pub(crate) fn get_xsave(
+    vcpufd: &VcpuFd,
+    caps: &virt::PartitionCapabilities,
+) -> Result<vp::Xsave, Error> {
+    let xsave = get_vp_state(
+        vcpufd,
+        mshv_bindings::MSHV_VP_STATE_XSAVE,
+        caps.xsave.compact_len as usize,
+    )?;
+    Ok(vp::Xsave::from_compact(xsave.as_bytes(), caps))
+}
+
+/// Sets the xsave state of the VP behind `vcpufd`.
+pub(crate) fn set_xsave(vcpufd: &VcpuFd, value: &vp::Xsave) -> Result<(), Error> {
+    set_vp_state(vcpufd, mshv_bindings::MSHV_VP_STATE_XSAVE, value.compact())
+}
+
 struct PageAlignedBuffer {
     ptr: NonNull<u8>,
     len: usize,
@@ -217,19 +242,11 @@ fn set_activity(&mut self, value: &vp::Activity) -> Result<(), Self::Error> {
     }
 
     fn xsave(&mut self) -> Result<vp::Xsave, Self::Error> {
-        let xsave = self.get_state(
-            mshv_bindings::MSHV_VP_STATE_XSAVE,
-            self.partition.caps.xsave.compact_len as usize,
-        )?;
-        Ok(vp::Xsave::from_compact(
-            xsave.as_bytes(),
-            &self.partition.caps,
-        ))
+        get_xsave(self.runner.vcpufd, &self.partition.caps)
     }
 
     fn set_xsave(&mut self, value: &vp::Xsave) -> Result<(), Self::Error> {
-        self.set_state(mshv_bindings::MSHV_VP_STATE_XSAVE, value.compact())?;
-        Ok(())
+        set_xsave(self.runner.vcpufd, value)
     }
 
     fn apic(&mut self) -> Result<vp::Apic, Self::Error> {
@@ -422,7 +422,11 @@ pub fn add_apic(&self, vp: &X86VpInfo, software_enabled_on_reset: bool) -> Local
     ///
     /// Calls `wake` for each processor that should be woken up for APIC
     /// handling.
-    pub fn request_interrupt(&self, address: u64, data: u32, wake: impl FnMut(VpIndex)) {
+    ///
+    /// Returns false if no APIC accepted the interrupt, because there is no
+    /// APIC with the destination ID or because the destination APICs are
+    /// disabled.
+    pub fn request_interrupt(&self, address: u64, data: u32, wake: impl FnMut(VpIndex)) -> bool {
         let address = MsiAddress::from(address as u32);
         let data = MsiData::from(data);
         self.global.request_interrupt(
@@ -435,7 +439,7 @@ pub fn request_interrupt(&self, address: u64, data: u32, wake: impl FnMut(VpInde
             data.vector(),
             data.trigger_mode_level(),
             wake,
-        );
+        )
     }
 
     /// Pulses the specified LINT.
@@ -488,6 +492,7 @@ pub fn synic_interrupt(
 }
 
 impl GlobalState {
+    /// Returns true if any APIC accepted the interrupt.
     fn request_interrupt(
         &self,
         destination: Destination,
@@ -495,28 +500,22 @@ fn request_interrupt(
         vector: u8,
         level: bool,
         mut wake: impl FnMut(VpIndex),
-    ) {
+    ) -> bool {
         let mutable = self.mutable.read();
         match destination {
-            Destination::Physical(id) => {
-                if let Some(slot) = mutable.by_apic_id.get(id as usize) {
-                    slot.request_interrupt(delivery_mode, vector, level, false, &mut wake);
-                }
-            }
+            Destination::Physical(id) => mutable.by_apic_id.get(id as usize).is_some_and(|slot| {
+                slot.request_interrupt(delivery_mode, vector, level, false, &mut wake)
+            }),
             Destination::Logical(id) => {
                 if mutable.x2apic_enabled > 0 {
                     // X2APIC cluster mode.
                     if id == !0 {
-                        mutable.request_broadcast_interrupt(
-                            delivery_mode,
-                            vector,
-                            level,
-                            &mut wake,
-                        );
+                        mutable.request_broadcast_interrupt(delivery_mode, vector, level, &mut wake)
                     } else {
                         let lowest_priority = delivery_mode == DeliveryMode::LOWEST_PRIORITY;
                         let id = X2ApicLogicalId::from(id);
                         let base = (id.cluster_id() as u32) << 4;
+                        let mut accepted = false;
                         for i in 0..16 {
                             if id.logical_id() & (1 << i) == 0 {
                                 continue;
@@ -525,7 +524,7 @@ fn request_interrupt(
                             if let Some(slot) = mutable.by_apic_id.get(phys_id as usize) {
                                 // For now, just pick the first enabled APIC in the set for lowest priority.
                                 if !lowest_priority || slot.software_enabled {
-                                    slot.request_interrupt(
+                                    accepted |= slot.request_interrupt(
                                         delivery_mode,
                                         vector,
                                         level,
@@ -538,15 +537,11 @@ fn request_interrupt(
                                 }
                             }
                         }
+                        accepted
                     }
                 } else if mutable.logical_cluster_mode > 0 {
                     if id as u8 == !0 {
-                        mutable.request_broadcast_interrupt(
-                            delivery_mode,
-                            vector,
-                            level,
-                            &mut wake,
-                        );
+                        mutable.request_broadcast_interrupt(delivery_mode, vector, level, &mut wake)
                     } else {
                         // XAPIC cluster mode. Easy and fast to iterate through the APICs.
                         let id = XApicClusterLogicalId::from(id as u8);
@@ -560,7 +555,7 @@ fn request_interrupt(
                                 ldr.cluster_id() == id.cluster_id()
                                     && ldr.logical_id() & id.logical_id() != 0
                             },
-                        );
+                        )
                     }
                 } else {
                     // APIC flat mode. Just iterate through all the VPs.
@@ -570,21 +565,19 @@ fn request_interrupt(
                         level,
                         &mut wake,
                         |_, slot| slot.logical_id & id as u8 != 0,
-                    );
+                    )
                 }
             }
             Destination::Broadcast => {
-                mutable.request_broadcast_interrupt(delivery_mode, vector, level, &mut wake);
-            }
-            Destination::AllExcept(except) => {
-                mutable.request_set_interrupt(
-                    delivery_mode,
-                    vector,
-                    level,
-                    &mut wake,
-                    |apic_id, _| apic_id != except,
-                );
-            }
+                mutable.request_broadcast_interrupt(delivery_mode, vector, level, &mut wake)
+            }
+            Destination::AllExcept(except) => mutable.request_set_interrupt(
+                delivery_mode,
+                vector,
+                level,
+                &mut wake,
+                |apic_id, _| apic_id != except,
+            ),
         }
     }
 }
@@ -1171,7 +1164,7 @@ fn request_interrupt(
     ) -> bool {
         match delivery_mode {
             DeliveryMode::FIXED | DeliveryMode::LOWEST_PRIORITY => {
-                if !software_enabled || !(16..=255).contains(&vector) {
+                if !accepts_interrupt(software_enabled, delivery_mode, vector) {
                     return false;
                 }
                 let (bank, mask) = bank_mask(vector);
@@ -1240,6 +1233,18 @@ fn request_interrupt(
     }
 }
 
+/// Returns whether an APIC in the given software enable state accepts an
+/// interrupt, rather than dropping it.
+fn accepts_interrupt(software_enabled: bool, delivery_mode: DeliveryMode, vector: u8) -> bool {
+    match delivery_mode {
+        DeliveryMode::FIXED | DeliveryMode::LOWEST_PRIORITY => {
+            software_enabled && (16..=255).contains(&vector)
+        }
+        DeliveryMode::NMI | DeliveryMode::INIT | DeliveryMode::SIPI | DeliveryMode::EXTINT => true,
+        _ => false,
+    }
+}
+
 impl MutableGlobalState {
     fn request_broadcast_interrupt(
         &self,
@@ -1247,8 +1252,8 @@ fn request_broadcast_interrupt(
         vector: u8,
         level_triggered: bool,
         wake: impl FnMut(VpIndex),
-    ) {
-        self.request_set_interrupt(delivery_mode, vector, level_triggered, wake, |_, _| true);
+    ) -> bool {
+        self.request_set_interrupt(delivery_mode, vector, level_triggered, wake, |_, _| true)
     }
 
     fn request_set_interrupt(
@@ -1258,24 +1263,33 @@ fn request_set_interrupt(
         level_triggered: bool,
         mut wake: impl FnMut(VpIndex),
         mut filter: impl FnMut(u32, &ApicSlot) -> bool,
-    ) {
+    ) -> bool {
         let lowest_priority = delivery_mode == DeliveryMode::LOWEST_PRIORITY;
+        let mut accepted = false;
         for (apic_id, slot) in self.by_apic_id.iter().enumerate() {
             if !filter(apic_id as u32, slot) {
                 continue;
             }
             // For now, just pick the first enabled APIC in the set for lowest priority.
             if !lowest_priority || slot.software_enabled {
-                slot.request_interrupt(delivery_mode, vector, level_triggered, false, &mut wake);
+                accepted |= slot.request_interrupt(
+                    delivery_mode,
+                    vector,
+                    level_triggered,
+                    false,
+                    &mut wake,
+                );
                 if lowest_priority {
                     break;
                 }
             }
         }
+        accepted
     }
 }
 
 impl ApicSlot {
+    /// Returns true if the APIC accepted the interrupt.
     fn request_interrupt(
         &self,
         delivery_mode: DeliveryMode,
@@ -1283,20 +1297,25 @@ fn request_interrupt(
         level_triggered: bool,
         auto_eoi: bool,
         wake: impl FnOnce(VpIndex),
-    ) {
-        if let Some(shared) = &self.shared {
-            if self.hardware_enabled
-                && shared.request_interrupt(
-                    self.software_enabled,
-                    delivery_mode,
-                    vector,
-                    level_triggered,
-                    auto_eoi,
-                )
-            {
-                wake(shared.vp_index);
-            }
+    ) -> bool {
+        let Some(shared) = &self.shared else {
+            return false;
+        };
+        if !self.hardware_enabled
+            || !accepts_interrupt(self.software_enabled, delivery_mode, vector)
+        {
+            return false;
         }
+        if shared.request_interrupt(
+            self.software_enabled,
+            delivery_mode,
+            vector,
+            level_triggered,
+            auto_eoi,
+        ) {
+            wake(shared.vp_index);
+        }
+        true
     }
 }
 
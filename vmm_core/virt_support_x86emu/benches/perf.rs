@@ -0,0 +1,276 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Performance tests.
+
+#![expect(missing_docs)]
+
+use futures::FutureExt;
+use guestmem::GuestMemory;
+use iced_x86::code_asm::CodeAssembler;
+use virt::VpHaltReason;
+use virt::VpIndex;
+use virt::io::CpuIo;
+use virt_support_x86emu::emulate::EmuCheckVtlAccessError;
+use virt_support_x86emu::emulate::EmuTranslateError;
+use virt_support_x86emu::emulate::EmuTranslateResult;
+use virt_support_x86emu::emulate::EmulatorMemoryAccess;
+use virt_support_x86emu::emulate::EmulatorSupport;
+use virt_support_x86emu::emulate::InitialTranslation;
+use virt_support_x86emu::emulate::TranslateGvaSupport;
+use virt_support_x86emu::emulate::TranslateMode;
+use virt_support_x86emu::emulate::emulate;
+use virt_support_x86emu::emulate::emulate_translate_gva;
+use virt_support_x86emu::translate::EncryptionMode;
+use virt_support_x86emu::translate::TranslationRegisters;
+use x86defs::RFlags;
+use x86defs::SegmentAttributes;
+use x86defs::SegmentRegister;
+use x86defs::cpuid::Vendor;
+use x86emu::Gp;
+use x86emu::Segment;
+
+criterion::criterion_main!(benches);
+
+criterion::criterion_group!(benches, bench_emulate);
+
+const PML4_ADDRESS: u64 = 0x1000;
+const PDPT_ADDRESS: u64 = 0x2000;
+const PD_ADDRESS: u64 = 0x3000;
+const CODE_ADDRESS: u64 = 0x10000;
+const DATA_ADDRESS: u64 = 0x20000;
+const MEMORY_SIZE: usize = 0x200000;
+
+/// Emulates `mov rax, [DATA_ADDRESS]` as if from a memory intercept that did
+/// not provide the instruction bytes, so that each emulation must fetch them
+/// through a four-level page table walk.
+fn bench_emulate(c: &mut criterion::Criterion) {
+    let gm = GuestMemory::allocate(MEMORY_SIZE);
+    // Identity map the first 2MB with a large page.
+    gm.write_plain(PML4_ADDRESS, &(PDPT_ADDRESS | 0x3)).unwrap();
+    gm.write_plain(PDPT_ADDRESS, &(PD_ADDRESS | 0x3)).unwrap();
+    gm.write_plain(PD_ADDRESS, &0x83u64).unwrap();
+
+    let mut asm = CodeAssembler::new(64).unwrap();
+    asm.mov(
+        iced_x86::code_asm::rax,
+        iced_x86::code_asm::ptr(DATA_ADDRESS),
+    )
+    .unwrap();
+    gm.write_at(CODE_ADDRESS, &asm.assemble(CODE_ADDRESS).unwrap())
+        .unwrap();
+
+    let emu_mem = EmulatorMemoryAccess {
+        gm: &gm,
+        kx_gm: &gm,
+        ux_gm: &gm,
+    };
+
+    let mut group = c.benchmark_group("emulate-fetch");
+    for (name, cache_fetches) in [("uncached", false), ("cached", true)] {
+        let mut support = BenchSupport::new(&gm, cache_fetches);
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                support.rip = CODE_ADDRESS;
+                emulate(&mut support, &emu_mem, &BenchCpu)
+                    .now_or_never()
+                    .unwrap()
+                    .unwrap();
+            });
+        });
+    }
+    group.finish();
+}
+
+struct BenchSupport<'a> {
+    gm: &'a GuestMemory,
+    gps: [u64; 16],
+    rip: u64,
+    rflags: RFlags,
+    segment: SegmentRegister,
+    cache_fetches: bool,
+}
+
+impl<'a> BenchSupport<'a> {
+    fn new(gm: &'a GuestMemory, cache_fetches: bool) -> Self {
+        Self {
+            gm,
+            gps: [0; 16],
+            rip: CODE_ADDRESS,
+            rflags: RFlags::at_reset(),
+            segment: SegmentRegister {
+                base: 0,
+                limit: 0,
+                attributes: SegmentAttributes::new().with_long(true),
+                selector: 0,
+            },
+            cache_fetches,
+        }
+    }
+}
+
+impl TranslateGvaSupport for BenchSupport<'_> {
+    fn guest_memory(&self) -> &GuestMemory {
+        self.gm
+    }
+
+    fn acquire_tlb_lock(&mut self) {}
+
+    fn registers(&mut self) -> TranslationRegisters {
+        TranslationRegisters {
+            cr0: self.cr0(),
+            cr4: x86defs::X64_CR4_PAE,
+            efer: self.efer(),
+            cr3: PML4_ADDRESS,
+            rflags: self.rflags.into(),
+            ss: self.segment,
+            encryption_mode: EncryptionMode::None,
+        }
+    }
+}
+
+impl EmulatorSupport for BenchSupport<'_> {
+    fn vp_index(&self) -> VpIndex {
+        VpIndex::BSP
+    }
+
+    fn vendor(&self) -> Vendor {
+        Vendor::INTEL
+    }
+
+    fn gp(&mut self, reg: Gp) -> u64 {
+        self.gps[reg as usize]
+    }
+
+    fn set_gp(&mut self, reg: Gp, v: u64) {
+        self.gps[reg as usize] = v;
+    }
+
+    fn rip(&mut self) -> u64 {
+        self.rip
+    }
+
+    fn set_rip(&mut self, v: u64) {
+        self.rip = v;
+    }
+
+    fn segment(&mut self, _index: Segment) -> SegmentRegister {
+        self.segment
+    }
+
+    fn efer(&mut self) -> u64 {
+        x86defs::X64_EFER_LMA | x86defs::X64_EFER_LME
+    }
+
+    fn cr0(&mut self) -> u64 {
+        x86defs::X64_CR0_PE | x86defs::X64_CR0_PG
+    }
+
+    fn cr3(&mut self) -> Option<u64> {
+        self.cache_fetches.then_some(PML4_ADDRESS)
+    }
+
+    fn rflags(&mut self) -> RFlags {
+        self.rflags
+    }
+
+    fn set_rflags(&mut self, v: RFlags) {
+        self.rflags = v;
+    }
+
+    fn xmm(&mut self, _reg: usize) -> u128 {
+        unreachable!()
+    }
+
+    fn set_xmm(&mut self, _reg: usize, _value: u128) {
+        unreachable!()
+    }
+
+    fn flush(&mut self) {}
+
+    fn instruction_bytes(&self) -> &[u8] {
+        &[]
+    }
+
+    fn physical_address(&self) -> Option<u64> {
+        Some(DATA_ADDRESS)
+    }
+
+    fn initial_gva_translation(&mut self) -> Option<InitialTranslation> {
+        None
+    }
+
+    fn interruption_pending(&self) -> bool {
+        false
+    }
+
+    fn check_vtl_access(
+        &mut self,
+        _gpa: u64,
+        _mode: TranslateMode,
+    ) -> Result<(), EmuCheckVtlAccessError> {
+        Ok(())
+    }
+
+    fn translate_gva(
+        &mut self,
+        gva: u64,
+        mode: TranslateMode,
+    ) -> Result<EmuTranslateResult, EmuTranslateError> {
+        emulate_translate_gva(self, gva, mode)
+    }
+
+    fn inject_pending_event(&mut self, _event_info: hvdef::HvX64PendingEvent) {
+        unreachable!()
+    }
+
+    fn is_gpa_mapped(&self, _gpa: u64, _write: bool) -> bool {
+        true
+    }
+
+    fn lapic_base_address(&self) -> Option<u64> {
+        None
+    }
+
+    fn lapic_read(&mut self, _address: u64, _data: &mut [u8]) {
+        unreachable!()
+    }
+
+    fn lapic_write(&mut self, _address: u64, _data: &[u8]) {
+        unreachable!()
+    }
+}
+
+struct BenchCpu;
+
+impl CpuIo for BenchCpu {
+    fn is_mmio(&self, _address: u64) -> bool {
+        false
+    }
+
+    fn acknowledge_pic_interrupt(&self) -> Option<u8> {
+        None
+    }
+
+    fn handle_eoi(&self, _irq: u32) {}
+
+    async fn read_mmio(&self, _vp: VpIndex, _address: u64, _data: &mut [u8]) {
+        unreachable!()
+    }
+
+    async fn write_mmio(&self, _vp: VpIndex, _address: u64, _data: &[u8]) {
+        unreachable!()
+    }
+
+    async fn read_io(&self, _vp: VpIndex, _port: u16, _data: &mut [u8]) {
+        unreachable!()
+    }
+
+    async fn write_io(&self, _vp: VpIndex, _port: u16, _data: &[u8]) {
+        unreachable!()
+    }
+
+    fn fatal_error(&self, error: Box<dyn std::error::Error + Send + Sync>) -> VpHaltReason {
+        panic!("{error}")
+    }
+}
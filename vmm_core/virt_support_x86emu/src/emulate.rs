@@ -3,6 +3,12 @@
 
 //! Wrapper around x86emu for emulating single instructions to handle VM exits.
 
+use crate::instruction_cache::FetchKey;
+use crate::instruction_cache::FetchedInstruction;
+use crate::instruction_cache::FetchedRange;
+use crate::instruction_cache::InstructionCache;
+use crate::instruction_cache::InstructionCacheGuard;
+use crate::instruction_cache::MAX_INSTRUCTION_BYTES;
 use crate::translate::TranslateFlags;
 use crate::translate::TranslatePrivilegeCheck;
 use crate::translate::translate_gva_to_gpa;
@@ -57,6 +63,14 @@ pub trait EmulatorSupport {
     /// Read cr0
     fn cr0(&mut self) -> u64;
 
+    /// Read cr3, or `None` if it is not available.
+    ///
+    /// Instruction fetches are only cached across emulations when this is
+    /// available, since the cache is keyed on the address space.
+    fn cr3(&mut self) -> Option<u64> {
+        None
+    }
+
     /// Read rflags
     fn rflags(&mut self) -> RFlags;
 
@@ -69,6 +83,20 @@ pub trait EmulatorSupport {
     /// Sets the value of an XMM* register.
     fn set_xmm(&mut self, reg: usize, value: u128);
 
+    /// Gets the upper 128 bits of a YMM* register, or `None` if AVX register
+    /// state is not available.
+    fn ymm_upper(&mut self, reg: usize) -> Option<u128> {
+        let _ = reg;
+        None
+    }
+
+    /// Sets the upper 128 bits of a YMM* register, or returns `None` if AVX
+    /// register state is not available.
+    fn set_ymm_upper(&mut self, reg: usize, value: u128) -> Option<()> {
+        let _ = (reg, value);
+        None
+    }
+
     /// Flush registers in the emulation cache to the backing
     fn flush(&mut self);
 
@@ -99,6 +127,19 @@ fn translate_gva(
         mode: TranslateMode,
     ) -> Result<EmuTranslateResult, EmuTranslateError>;
 
+    /// Translates a batch of GVAs to GPAs, returning one result per GVA.
+    ///
+    /// The default implementation calls [`Self::translate_gva`] for each GVA.
+    fn translate_gvas(
+        &mut self,
+        gvas: &[u64],
+        mode: TranslateMode,
+    ) -> Vec<Result<EmuTranslateResult, EmuTranslateError>> {
+        gvas.iter()
+            .map(|&gva| self.translate_gva(gva, mode))
+            .collect()
+    }
+
     /// Generates an event (exception, guest nested page fault, etc.) in the guest.
     fn inject_pending_event(&mut self, event_info: hvdef::HvX64PendingEvent);
 
@@ -342,6 +383,47 @@ pub async fn emulate<T: EmulatorSupport>(
     })
 }
 
+/// Returns the instruction previously fetched with `key`, if the guest has
+/// not modified it since.
+///
+/// This skips the page table walk, but still checks VTL permissions and
+/// compares the cached bytes against guest memory. Comparing the bytes also
+/// covers page table changes that were not reflected in the key: if the old
+/// physical page still holds the same bytes, emulating them is equivalent.
+fn cached_instruction<T: EmulatorSupport, U>(
+    cache: &mut InstructionCache,
+    key: &FetchKey,
+    cpu: &mut EmulatorCpu<'_, T, U>,
+    emu_mem: &EmulatorMemoryAccess<'_>,
+    provided_bytes: &[u8],
+) -> Option<FetchedInstruction> {
+    let cached = cache.lookup(key)?;
+    let instruction_gm = emu_mem.gm(EmulatorMemoryAccessType::InstructionRead {
+        is_user_mode: key.is_user_mode,
+    });
+    let valid = cached.bytes().starts_with(provided_bytes)
+        && cached
+            .ranges()
+            .next()
+            .is_some_and(|range| range.offset == provided_bytes.len())
+        && cached.ranges().all(|range| {
+            let expected = &cached.bytes()[range.offset..][..range.len];
+            let mut current = [0; MAX_INSTRUCTION_BYTES];
+            let current = &mut current[..range.len];
+            cpu.check_vtl_access(range.gpa, TranslateMode::Execute)
+                .is_ok()
+                && instruction_gm.read_at(range.gpa, current).is_ok()
+                && current == expected
+        });
+    if valid {
+        Some(cached)
+    } else {
+        // Fall back to a full fetch, which will also report any failure.
+        cache.invalidate(key);
+        None
+    }
+}
+
 async fn emulate_core<T: EmulatorSupport>(
     support: &mut T,
     emu_mem: &EmulatorMemoryAccess<'_>,
@@ -349,7 +431,7 @@ async fn emulate_core<T: EmulatorSupport>(
 ) -> Result<(), EmulationError> {
     let vendor = support.vendor();
 
-    let mut bytes = [0; 16];
+    let mut bytes = [0; MAX_INSTRUCTION_BYTES];
     let mut valid_bytes;
     {
         let instruction_bytes = support.instruction_bytes();
@@ -383,14 +465,43 @@ async fn emulate_core<T: EmulatorSupport>(
 
     let initial_alignment_check = support.rflags().alignment_check();
 
+    let fetch_key = support.cr3().map(|cr3| FetchKey {
+        rip: support.rip(),
+        cr3,
+        cr0: support.cr0(),
+        efer: support.efer(),
+        cs: support.segment(Segment::CS),
+        is_user_mode: support
+            .segment(Segment::SS)
+            .attributes
+            .descriptor_privilege_level()
+            == x86defs::USER_MODE_DPL,
+        physical_address: support.physical_address(),
+    });
+
     let mut cpu = EmulatorCpu::new(
         emu_mem.gm(EmulatorMemoryAccessType::ReadWrite),
         dev,
         support,
     );
+    // Take the cache for the duration of the emulation, since this future may
+    // yield to another one that is emulating on the same thread.
+    let mut cache = InstructionCacheGuard::take();
+    let mut fetched = None;
+    if let Some(key) = &fetch_key {
+        if let Some(cached) =
+            cached_instruction(&mut cache, key, &mut cpu, emu_mem, &bytes[..valid_bytes])
+        {
+            valid_bytes = cached.bytes().len();
+            bytes[..valid_bytes].copy_from_slice(cached.bytes());
+        } else {
+            fetched = Some(FetchedInstruction::new(*key));
+        }
+    }
     let result = loop {
         let instruction_bytes = &bytes[..valid_bytes];
-        let mut emu = x86emu::Emulator::new(&mut cpu, vendor, instruction_bytes);
+        let mut emu = x86emu::Emulator::new(&mut cpu, vendor, instruction_bytes)
+            .with_decode_cache(&mut cache.decode);
         let res = emu.run().await;
 
         if let Err(e) = &res {
@@ -444,13 +555,28 @@ async fn emulate_core<T: EmulatorSupport>(
                     return Ok(());
                 }
 
+                let range = FetchedRange {
+                    offset: valid_bytes,
+                    gpa: phys_ip,
+                    len,
+                };
                 valid_bytes += len;
+                if let Some(f) = &mut fetched {
+                    if !f.push_range(&bytes[..valid_bytes], range) {
+                        fetched = None;
+                    }
+                }
                 continue;
             }
         }
 
         break res;
     };
+    if let Some(f) = fetched {
+        if f.ranges().next().is_some() {
+            cache.insert(f);
+        }
+    }
 
     cpu.support.flush();
 
@@ -585,13 +711,15 @@ pub fn new(gva: u64, gpa: u64, translate_mode: TranslateMode) -> Self {
     }
 }
 
+/// The maximum number of translations cached by [`EmulatorCpu`]. A rep string
+/// instruction touches at most three pages per operand in a single run.
+const MAX_CACHED_TRANSLATIONS: usize = 8;
+
 struct EmulatorCpu<'a, T, U> {
     gm: &'a GuestMemory,
     support: &'a mut T,
     dev: &'a U,
-    // TODO: this should be able to hold at least two entries for effective use for
-    // rep move instructions.
-    cached_translation: Option<GvaGpaCacheEntry>,
+    cached_translations: Vec<GvaGpaCacheEntry>,
 }
 
 #[derive(Debug, Error)]
@@ -641,9 +769,9 @@ pub fn new<'a>(gm: &'a GuestMemory, dev: &'a U, support: &'a mut T) -> EmulatorC
                     ?translate_mode,
                     "adding initial translation to cache"
                 );
-                Some(GvaGpaCacheEntry::new(gva, gpa, translate_mode))
+                vec![GvaGpaCacheEntry::new(gva, gpa, translate_mode)]
             } else {
-                None
+                Vec::new()
             }
         };
 
@@ -651,10 +779,24 @@ pub fn new<'a>(gm: &'a GuestMemory, dev: &'a U, support: &'a mut T) -> EmulatorC
             gm,
             dev,
             support,
-            cached_translation: init_cache,
+            cached_translations: init_cache,
         }
     }
 
+    fn cache_translation(&mut self, gva: u64, gpa: u64, mode: TranslateMode) {
+        if self.cached_translations.len() == MAX_CACHED_TRANSLATIONS {
+            self.cached_translations.remove(0);
+        }
+        self.cached_translations
+            .push(GvaGpaCacheEntry::new(gva, gpa, mode));
+    }
+
+    fn cached_translation(&self, gva: u64, mode: TranslateMode) -> Option<&GvaGpaCacheEntry> {
+        self.cached_translations.iter().find(|entry| {
+            entry.gva_page == gva >> hvdef::HV_PAGE_SHIFT && entry.translate_mode == mode
+        })
+    }
+
     pub fn translate_gva(
         &mut self,
         gva: u64,
@@ -663,22 +805,20 @@ pub fn translate_gva(
     ) -> Result<u64, Error> {
         type TranslateCode = hvdef::hypercall::TranslateGvaResultCode;
 
-        if let Some(GvaGpaCacheEntry {
+        if let Some(&GvaGpaCacheEntry {
             gva_page: cached_gva_page,
             gpa_page: cached_gpa_page,
             translate_mode: cached_mode,
-        }) = self.cached_translation
+        }) = self.cached_translation(gva, mode)
         {
-            if ((gva >> hvdef::HV_PAGE_SHIFT) == cached_gva_page) && (cached_mode == mode) {
-                tracing::trace!(
-                    ?gva,
-                    ?cached_gva_page,
-                    cached_gpa_page,
-                    ?cached_mode,
-                    "using cached entry"
-                );
-                return Ok((cached_gpa_page << hvdef::HV_PAGE_SHIFT) + (gva & (HV_PAGE_SIZE - 1)));
-            }
+            tracing::trace!(
+                ?gva,
+                ?cached_gva_page,
+                cached_gpa_page,
+                ?cached_mode,
+                "using cached entry"
+            );
+            return Ok((cached_gpa_page << hvdef::HV_PAGE_SHIFT) + (gva & (HV_PAGE_SIZE - 1)));
         };
 
         match self.support.translate_gva(gva, mode) {
@@ -708,9 +848,7 @@ pub fn translate_gva(
                     ));
                 }
 
-                let new_cache_entry = GvaGpaCacheEntry::new(gva, gpa, mode);
-
-                self.cached_translation = Some(new_cache_entry);
+                self.cache_translation(gva, gpa, mode);
                 Ok(gpa)
             }
             Err(EmuTranslateError { code, event_info }) => {
@@ -940,6 +1078,33 @@ async fn compare_and_write_memory(
         Ok(success)
     }
 
+    fn prefetch_translations(&mut self, gvas: &[u64], write: bool, _is_user_mode: bool) {
+        let mode = if write {
+            TranslateMode::Write
+        } else {
+            TranslateMode::Read
+        };
+        let gvas: Vec<u64> = gvas
+            .iter()
+            .copied()
+            .filter(|&gva| self.cached_translation(gva, mode).is_none())
+            .collect();
+        if gvas.is_empty() {
+            return;
+        }
+        // Only cache successful translations; failures are reported when the
+        // page is actually accessed. Overlay pages are left out for the same
+        // reason, since writes to them fail.
+        let results = self.support.translate_gvas(&gvas, mode);
+        for (gva, result) in gvas.into_iter().zip(results) {
+            if let Ok(EmuTranslateResult { gpa, overlay_page }) = result {
+                if !(write && overlay_page == Some(true)) {
+                    self.cache_translation(gva, gpa, mode);
+                }
+            }
+        }
+    }
+
     async fn read_io(&mut self, io_port: u16, bytes: &mut [u8]) -> Result<(), Self::Error> {
         self.dev
             .read_io(self.support.vp_index(), io_port, bytes)
@@ -1008,6 +1173,14 @@ fn xmm(&mut self, reg: usize) -> u128 {
     fn set_xmm(&mut self, reg: usize, value: u128) {
         self.support.set_xmm(reg, value)
     }
+
+    fn ymm_upper(&mut self, reg: usize) -> Option<u128> {
+        self.support.ymm_upper(reg)
+    }
+
+    fn set_ymm_upper(&mut self, reg: usize, value: u128) -> Option<()> {
+        self.support.set_ymm_upper(reg, value)
+    }
 }
 
 /// Emulates an IO port instruction.
@@ -0,0 +1,163 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! A per-thread cache of recently emulated instructions.
+
+use std::cell::Cell;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use x86defs::SegmentRegister;
+use x86emu::DecodeCache;
+
+/// The number of cached instruction fetches. Must be a power of two.
+const FETCH_ENTRIES: usize = 8;
+
+/// The maximum number of instruction bytes fetched for one instruction.
+pub(crate) const MAX_INSTRUCTION_BYTES: usize = 16;
+
+thread_local! {
+    /// VPs generally run on dedicated threads, so this is effectively per-VP.
+    /// Nothing in the cache is VP-specific, so sharing it is still safe.
+    static INSTRUCTION_CACHE: Cell<Option<Box<InstructionCache>>> = const { Cell::new(None) };
+}
+
+/// Caches the fetch and decode of recently emulated instructions, so that
+/// repeated intercepts at the same instruction, such as a driver polling a
+/// device register, skip the guest page table walk and the decode.
+#[derive(Default)]
+pub(crate) struct InstructionCache {
+    pub decode: DecodeCache,
+    fetched: [Option<FetchedInstruction>; FETCH_ENTRIES],
+}
+
+/// The processor state that determines which instruction bytes a fetch at
+/// RIP returns.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct FetchKey {
+    pub rip: u64,
+    pub cr3: u64,
+    pub cr0: u64,
+    pub efer: u64,
+    pub cs: SegmentRegister,
+    pub is_user_mode: bool,
+    /// The physical address of the intercepted access. This narrows a cached
+    /// fetch to the access that caused it, so an instruction is only reused
+    /// for the intercept pattern it was fetched for.
+    pub physical_address: Option<u64>,
+}
+
+/// Instruction bytes that were fetched from a single guest page.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct FetchedRange {
+    /// The offset of the range within the instruction bytes.
+    pub offset: usize,
+    pub gpa: u64,
+    pub len: usize,
+}
+
+/// Instruction bytes fetched from guest memory, and where they came from.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct FetchedInstruction {
+    key: FetchKey,
+    bytes: [u8; MAX_INSTRUCTION_BYTES],
+    len: usize,
+    ranges: [Option<FetchedRange>; 2],
+}
+
+impl FetchedInstruction {
+    /// Returns an empty record for a fetch with `key`.
+    pub fn new(key: FetchKey) -> Self {
+        Self {
+            key,
+            bytes: [0; MAX_INSTRUCTION_BYTES],
+            len: 0,
+            ranges: [None; 2],
+        }
+    }
+
+    /// Records that `bytes[range.offset..][..range.len]` was fetched from
+    /// `range.gpa`. Returns false if too many ranges were fetched to cache the
+    /// instruction.
+    pub fn push_range(&mut self, bytes: &[u8], range: FetchedRange) -> bool {
+        let Some(slot) = self.ranges.iter_mut().find(|r| r.is_none()) else {
+            return false;
+        };
+        *slot = Some(range);
+        self.len = bytes.len();
+        self.bytes[..bytes.len()].copy_from_slice(bytes);
+        true
+    }
+
+    /// The instruction bytes, including any that were provided with the
+    /// intercept rather than fetched.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// The ranges that were fetched from guest memory.
+    pub fn ranges(&self) -> impl Iterator<Item = FetchedRange> + '_ {
+        self.ranges.iter().flatten().copied()
+    }
+}
+
+impl InstructionCache {
+    fn index(rip: u64) -> usize {
+        rip as usize & (FETCH_ENTRIES - 1)
+    }
+
+    /// Returns the instruction last fetched with `key`.
+    ///
+    /// The caller must check that the guest has not modified the code since,
+    /// by comparing the bytes against guest memory, and call
+    /// [`Self::invalidate`] if it has.
+    pub fn lookup(&self, key: &FetchKey) -> Option<FetchedInstruction> {
+        self.fetched[Self::index(key.rip)].filter(|entry| entry.key == *key)
+    }
+
+    /// Caches a fetched instruction, replacing any other instruction cached
+    /// in the same slot.
+    pub fn insert(&mut self, fetched: FetchedInstruction) {
+        self.fetched[Self::index(fetched.key.rip)] = Some(fetched);
+    }
+
+    /// Drops the instruction fetched with `key`, if cached.
+    pub fn invalidate(&mut self, key: &FetchKey) {
+        let slot = &mut self.fetched[Self::index(key.rip)];
+        if slot.is_some_and(|entry| entry.key == *key) {
+            *slot = None;
+        }
+    }
+}
+
+/// Holds the thread's instruction cache, putting it back when dropped.
+///
+/// The cache is taken out of the thread-local storage for the duration of an
+/// emulation, since the emulation may yield to another one on the same
+/// thread.
+pub(crate) struct InstructionCacheGuard(Option<Box<InstructionCache>>);
+
+impl InstructionCacheGuard {
+    pub fn take() -> Self {
+        Self(Some(INSTRUCTION_CACHE.take().unwrap_or_default()))
+    }
+}
+
+impl Deref for InstructionCacheGuard {
+    type Target = InstructionCache;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for InstructionCacheGuard {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.as_mut().unwrap()
+    }
+}
+
+impl Drop for InstructionCacheGuard {
+    fn drop(&mut self) {
+        INSTRUCTION_CACHE.set(self.0.take());
+    }
+}
@@ -7,4 +7,5 @@
 #![forbid(unsafe_code)]
 
 pub mod emulate;
+mod instruction_cache;
 pub mod translate;
@@ -19,6 +19,8 @@ struct MockSupport {
     state: CpuState,
     instruction_bytes: Vec<u8>,
     interruption_pending: bool,
+    translations: usize,
+    cr3: Option<u64>,
 }
 
 impl EmulatorSupport for MockSupport {
@@ -53,6 +55,9 @@ fn efer(&mut self) -> u64 {
     fn cr0(&mut self) -> u64 {
         self.state.cr0
     }
+    fn cr3(&mut self) -> Option<u64> {
+        self.cr3
+    }
     fn rflags(&mut self) -> RFlags {
         self.state.rflags
     }
@@ -84,6 +89,7 @@ fn translate_gva(
         gva: u64,
         _mode: virt_support_x86emu::emulate::TranslateMode,
     ) -> Result<EmuTranslateResult, EmuTranslateError> {
+        self.translations += 1;
         Ok(EmuTranslateResult {
             gpa: gva,
             overlay_page: None,
@@ -91,7 +97,7 @@ fn translate_gva(
     }
 
     fn physical_address(&self) -> Option<u64> {
-        todo!()
+        None
     }
 
     /// The gva translation included in the intercept message header, if valid.
@@ -154,6 +160,8 @@ async fn basic_mov() {
         state: long_protected_mode(false),
         instruction_bytes,
         interruption_pending: false,
+        translations: 0,
+        cr3: None,
     };
 
     emulate(&mut support, &emu_mem, &MockCpu).await.unwrap();
@@ -189,6 +197,8 @@ async fn not_enough_bytes() {
         state: long_protected_mode(false),
         instruction_bytes: instruction_bytes[..2].into(),
         interruption_pending: false,
+        translations: 0,
+        cr3: None,
     };
 
     gm.write_at(support.state.rip, &instruction_bytes).unwrap();
@@ -226,6 +236,8 @@ async fn trap_from_interrupt() {
         state: long_protected_mode(false),
         instruction_bytes,
         interruption_pending: true,
+        translations: 0,
+        cr3: None,
     };
 
     emulate(&mut support, &emu_mem, &MockCpu).await.unwrap();
@@ -262,7 +274,122 @@ async fn trap_from_debug() {
         state,
         instruction_bytes,
         interruption_pending: false,
+        translations: 0,
+        cr3: None,
+    };
+
+    emulate(&mut support, &emu_mem, &MockCpu).await.unwrap();
+}
+
+#[async_test]
+async fn rep_movs_translates_each_page_once() {
+    const SRC_ADDRESS: u64 = 0xf00;
+    const DST_ADDRESS: u64 = 0x2f00;
+    const LEN: u64 = 0x400;
+
+    let gm = GuestMemory::allocate(0x4000);
+    let emu_mem = virt_support_x86emu::emulate::EmulatorMemoryAccess {
+        gm: &gm,
+        kx_gm: &gm,
+        ux_gm: &gm,
+    };
+    gm.write_at(SRC_ADDRESS, &[0xcc; LEN as usize]).unwrap();
+
+    let mut asm = CodeAssembler::new(64).unwrap();
+    asm.rep().movsb().unwrap();
+
+    let instruction_bytes = asm.assemble(0).unwrap();
+
+    let mut state = long_protected_mode(false);
+    state.gps[Gp::RSI as usize] = SRC_ADDRESS;
+    state.gps[Gp::RDI as usize] = DST_ADDRESS;
+    state.gps[Gp::RCX as usize] = LEN;
+
+    let mut support = MockSupport {
+        state,
+        instruction_bytes,
+        interruption_pending: false,
+        translations: 0,
+        cr3: None,
     };
 
     emulate(&mut support, &emu_mem, &MockCpu).await.unwrap();
+
+    let mut data = [0; LEN as usize];
+    gm.read_at(DST_ADDRESS, &mut data).unwrap();
+    assert_eq!(data, [0xcc; LEN as usize]);
+    assert_eq!(support.gp(Gp::RCX), 0);
+    // Both operands span two pages, and each page is translated only once
+    // even though the accesses alternate between source and destination.
+    assert_eq!(support.translations, 4);
+}
+
+#[async_test]
+async fn fetched_instruction_cached() {
+    const CODE_ADDRESS: u64 = 0x800;
+    const TEST_ADDRESS: u64 = 0x100;
+    const TEST_VALUE: u64 = 0x123456789abcdef0;
+    const OTHER_ADDRESS: u64 = 0x108;
+    const OTHER_VALUE: u64 = 0xfedcba9876543210;
+
+    let gm = GuestMemory::allocate(4096);
+    let emu_mem = virt_support_x86emu::emulate::EmulatorMemoryAccess {
+        gm: &gm,
+        kx_gm: &gm,
+        ux_gm: &gm,
+    };
+    gm.write_at(TEST_ADDRESS, &TEST_VALUE.to_le_bytes())
+        .unwrap();
+    gm.write_at(OTHER_ADDRESS, &OTHER_VALUE.to_le_bytes())
+        .unwrap();
+
+    let assemble = |address| {
+        let mut asm = CodeAssembler::new(64).unwrap();
+        asm.mov(iced_x86::code_asm::rax, iced_x86::code_asm::ptr(address))
+            .unwrap();
+        asm.assemble(CODE_ADDRESS).unwrap()
+    };
+    gm.write_at(CODE_ADDRESS, &assemble(TEST_ADDRESS)).unwrap();
+
+    let mut support = MockSupport {
+        state: long_protected_mode(false),
+        instruction_bytes: Vec::new(),
+        interruption_pending: false,
+        translations: 0,
+        cr3: Some(0x1000),
+    };
+
+    let run = async |support: &mut MockSupport| {
+        support.state.rip = CODE_ADDRESS;
+        support.translations = 0;
+        emulate(support, &emu_mem, &MockCpu).await.unwrap();
+    };
+
+    // The first emulation translates both the instruction and the data.
+    run(&mut support).await;
+    assert_eq!(support.gp(Gp::RAX), TEST_VALUE);
+    assert_eq!(support.translations, 2);
+
+    // The second reuses the fetched instruction bytes.
+    run(&mut support).await;
+    assert_eq!(support.gp(Gp::RAX), TEST_VALUE);
+    assert_eq!(support.translations, 1);
+
+    // Modified code is refetched.
+    gm.write_at(CODE_ADDRESS, &assemble(OTHER_ADDRESS)).unwrap();
+    run(&mut support).await;
+    assert_eq!(support.gp(Gp::RAX), OTHER_VALUE);
+    assert_eq!(support.translations, 2);
+
+    // So is code in a different address space.
+    support.cr3 = Some(0x2000);
+    run(&mut support).await;
+    assert_eq!(support.gp(Gp::RAX), OTHER_VALUE);
+    assert_eq!(support.translations, 2);
+
+    // Without cr3, nothing is cached.
+    support.cr3 = None;
+    run(&mut support).await;
+    run(&mut support).await;
+    assert_eq!(support.translations, 2);
 }
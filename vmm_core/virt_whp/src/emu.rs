@@ -9,6 +9,7 @@
 use hvdef::Vtl;
 use virt::VpIndex;
 use virt::io::CpuIo;
+use virt::x86::vp;
 use virt_support_x86emu::emulate::EmuTranslateError;
 use virt_support_x86emu::emulate::EmuTranslateResult;
 use virt_support_x86emu::emulate::TranslateGvaSupport;
@@ -122,6 +123,18 @@ fn set_xmm(&mut self, reg: usize, value: u128) {
         self.vp.current_whp().set_registers(&[reg], &value).unwrap();
     }
 
+    fn ymm_upper(&mut self, reg: usize) -> Option<u128> {
+        let data = self.vp.current_whp().get_xsave().ok()?;
+        vp::Xsave::from_compact(&data, &self.vp.vp.partition.caps).ymm_upper(reg)
+    }
+
+    fn set_ymm_upper(&mut self, reg: usize, value: u128) -> Option<()> {
+        let data = self.vp.current_whp().get_xsave().ok()?;
+        let mut xsave = vp::Xsave::from_compact(&data, &self.vp.vp.partition.caps);
+        xsave.set_ymm_upper(reg, value)?;
+        self.vp.current_whp().set_xsave(xsave.compact()).ok()
+    }
+
     fn flush(&mut self) {
         self.vp.set_emulator_state(&self.cache);
     }
@@ -121,6 +121,7 @@ struct WhpPartitionInner {
     monitor_page: MonitorPage,
     hvstate: Hv1State,
     isolation: IsolationType,
+    halt_on_guest_crash: bool,
     #[cfg(guest_arch = "aarch64")]
     #[inspect(skip)]
     gic_msi: vm_topology::processor::aarch64::GicMsiController,
@@ -204,6 +205,13 @@ struct RunState {
     vtl2_wakeup_vmtime: Option<VmTimeAccess>,
     crash_msg_address: Option<u64>,
     crash_msg_len: Option<usize>,
+    #[inspect(hex, iter_by_index)]
+    crash_parameters: [u64; hvdef::HV_X64_GUEST_CRASH_PARAMETER_MSRS],
+    /// Set when the guest has reported a crash and the partition is
+    /// configured to halt on guest crashes, to halt the VP with the crash
+    /// parameters once the exit has been handled.
+    #[inspect(skip)]
+    pending_crash: bool,
     #[inspect(flatten)]
     vtls: RunStateVtls,
     #[inspect(mut)]
@@ -302,6 +310,8 @@ fn reset(&mut self, vtl2_scrub: bool, is_bsp: bool) {
             enabled_vtls,
             ref mut crash_msg_address,
             ref mut crash_msg_len,
+            ref mut crash_parameters,
+            ref mut pending_crash,
             ref mut vtls,
             ref mut halted,
             exits: _,
@@ -314,6 +324,8 @@ fn reset(&mut self, vtl2_scrub: bool, is_bsp: bool) {
         *vtl2_deliverability_notifications = Default::default();
         *crash_msg_address = None;
         *crash_msg_len = None;
+        *crash_parameters = [0; hvdef::HV_X64_GUEST_CRASH_PARAMETER_MSRS];
+        *pending_crash = false;
         if !vtl2_scrub {
             vtls.vtl0.reset(is_bsp);
         }
@@ -935,6 +947,8 @@ fn build(
                         vtl2_deliverability_notifications: Default::default(),
                         crash_msg_address: None,
                         crash_msg_len: None,
+                        crash_parameters: [0; hvdef::HV_X64_GUEST_CRASH_PARAMETER_MSRS],
+                        pending_crash: false,
                         halted: false,
                         vtls: RunStateVtls {
                             vtl0: PerVtlRunState::new(
@@ -1032,7 +1046,9 @@ fn new(
             )
             .map_err(Error::TopologyCpuid)?;
 
-            virt::CpuidLeafSet::new(cpuid)
+            // Apply user overrides last so that they take precedence over the
+            // leaves above.
+            virt::CpuidLeafSet::with_overrides(cpuid, config.cpuid_overrides)
         };
 
         let mut vtl0_alias_map_offset = None;
@@ -1166,6 +1182,10 @@ fn new(
             monitor_page: MonitorPage::new(),
             hvstate,
             isolation: proto_config.isolation,
+            halt_on_guest_crash: proto_config
+                .hv_config
+                .as_ref()
+                .is_some_and(|cfg| cfg.halt_on_guest_crash),
             #[cfg(guest_arch = "aarch64")]
             gic_msi: proto_config.processor_topology.gic_msi(),
             synic_ports: Default::default(),
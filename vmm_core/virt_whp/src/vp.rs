@@ -615,6 +615,12 @@ pub(super) async fn handle_exit(
                 }
             };
             stat.increment();
+            if std::mem::take(&mut self.state.pending_crash) {
+                return Err(VpHaltReason::GuestCrash {
+                    vtl: self.state.active_vtl,
+                    parameters: self.state.crash_parameters,
+                });
+            }
             Ok(())
         }
 
@@ -1220,6 +1226,13 @@ fn msr_write(
                         if !self.send_unknown_msrs_to_vtl2() =>
                     {
                         tracing::warn!(msr, v, "Guest signaled crash register");
+                        if let Some(parameter) = self
+                            .state
+                            .crash_parameters
+                            .get_mut((msr - hvdef::HV_X64_MSR_GUEST_CRASH_P0) as usize)
+                        {
+                            *parameter = v;
+                        }
                         match msr {
                             hvdef::HV_X64_MSR_GUEST_CRASH_P3 => {
                                 self.state.crash_msg_address = Some(v)
@@ -1229,6 +1242,8 @@ fn msr_write(
                                     Some(std::cmp::min(v as usize, hvdef::HV_PAGE_SIZE_USIZE))
                             }
                             hvdef::HV_X64_MSR_GUEST_CRASH_CTL => {
+                                self.state.pending_crash = self.vp.partition.halt_on_guest_crash
+                                    && hvdef::GuestCrashCtl::from(v).crash_notify();
                                 if let (Some(addr), Some(len)) = (
                                     self.state.crash_msg_address.take(),
                                     self.state.crash_msg_len.take(),
@@ -60,4 +60,13 @@ pub enum HaltReason {
         #[inspect(skip)]
         breakpoint: virt::x86::HardwareBreakpoint,
     },
+    /// The guest reported a crash through the Hyper-V guest crash registers.
+    /// For Windows guests, `parameters[0]` is the bugcheck code and the
+    /// remaining parameters are the bugcheck parameters.
+    GuestCrash {
+        #[inspect(rename = "failing_vp")]
+        vp: u32,
+        #[inspect(hex, iter_by_index)]
+        parameters: [u64; hvdef::HV_X64_GUEST_CRASH_PARAMETER_MSRS],
+    },
 }
@@ -93,8 +93,9 @@ fn resolve(&self, id: ErasedArtifactHandle) -> anyhow::Result<PathBuf> {
             _ if id == openhcl_igvm::um_bin::LATEST_LINUX_DIRECT_TEST_X64 => openhcl_extras_path(OpenhclVersion::Latest,OpenhclFlavor::LinuxDirect,OpenhclExtras::UmBin),
             _ if id == openhcl_igvm::um_dbg::LATEST_LINUX_DIRECT_TEST_X64 => openhcl_extras_path(OpenhclVersion::Latest,OpenhclFlavor::LinuxDirect,OpenhclExtras::UmDbg),
 
-            _ if id == test_vhd::GUEST_TEST_UEFI_X64 => guest_test_uefi_disk_path(MachineArch::X86_64),
-            _ if id == test_vhd::GUEST_TEST_UEFI_AARCH64 => guest_test_uefi_disk_path(MachineArch::Aarch64),
+            _ if id == test_vhd::GUEST_TEST_UEFI_X64 => guest_test_uefi_path(MachineArch::X86_64, "guest_test_uefi.img"),
+            _ if id == test_vhd::GUEST_TEST_UEFI_AARCH64 => guest_test_uefi_path(MachineArch::Aarch64, "guest_test_uefi.img"),
+            _ if id == test_vhd::GUEST_TEST_UEFI_EFI_X64 => guest_test_uefi_path(MachineArch::X86_64, "guest_test_uefi.efi"),
 
             _ if id == test_vhd::GEN2_WINDOWS_DATA_CENTER_CORE2025_X64_PREPPED => {
                 let base_filename = test_vhd::GEN2_WINDOWS_DATA_CENTER_CORE2025_X64::FILENAME;
@@ -269,12 +270,13 @@ fn get_test_artifact_path(artifact: KnownTestArtifacts) -> Result<PathBuf, anyho
     )
 }
 
-/// Path to the output location of our guest-test image for UEFI.
-fn guest_test_uefi_disk_path(arch: MachineArch) -> anyhow::Result<PathBuf> {
+/// Path to the output location of `file_name` from our guest-test image build
+/// for UEFI.
+fn guest_test_uefi_path(arch: MachineArch, file_name: &str) -> anyhow::Result<PathBuf> {
     // `guest_test_uefi` is always at `{arch}-unknown-uefi/debug`
     get_path(
         format!("target/{}-unknown-uefi/debug", target_arch_path(arch)),
-        "guest_test_uefi.img",
+        file_name,
         MissingCommand::Xtask {
             xtask_args: &[
                 "guest-test",
@@ -320,6 +320,12 @@ impl IsTestVhd for GUEST_TEST_UEFI_AARCH64 {
             const ARCH: MachineArch = MachineArch::Aarch64;
         }
 
+        declare_artifacts! {
+            /// guest_test_uefi.efi, the EFI application inside
+            /// [`GUEST_TEST_UEFI_X64`], for network boot.
+            GUEST_TEST_UEFI_EFI_X64,
+        }
+
         // NOTE: GUEST_TEST_UEFI is not hosted on the HvLite Azure Blob Store. It is
         // built just-in-time, using the code that is present in-tree, under
         // `guest_test_uefi`.
@@ -115,6 +115,7 @@ fn run(
             openvmm_helpers::disk::OpenDiskOptions {
                 read_only: false,
                 direct: false,
+                change_tracking: false,
             },
         )
         .await
@@ -157,6 +158,7 @@ fn run(
                         }],
                         requests: None,
                         poll_mode_queue_depth: None,
+                        max_protocol_version: None,
                     }
                     .into_resource(),
                 ))
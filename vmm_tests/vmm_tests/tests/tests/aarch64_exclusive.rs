@@ -3,12 +3,27 @@
 
 //! Integration tests for aarch64 guests.
 
+#[cfg(windows)]
+use crate::utils::ExpectedGuestDevice;
+#[cfg(windows)]
+use crate::utils::get_device_paths;
+use anyhow::Context;
+#[cfg(windows)]
+use guid::Guid;
+use openvmm_defs::config::GicMsiConfig;
 use petri::PetriVmBuilder;
 use petri::PetriVmmBackend;
 use petri::openvmm::OpenVmmPetriBackend;
 use petri::pipette::cmd;
-// TODO: re-enable when boot_dt test is re-enabled
-// use vmm_test_macros::openvmm_test;
+#[cfg(windows)]
+use petri::vtl2_settings::ControllerType;
+#[cfg(windows)]
+use petri::vtl2_settings::Vtl2LunBuilder;
+#[cfg(windows)]
+use petri::vtl2_settings::Vtl2StorageBackingDeviceBuilder;
+#[cfg(windows)]
+use petri::vtl2_settings::Vtl2StorageControllerBuilder;
+use vmm_test_macros::openvmm_test;
 use vmm_test_macros::vmm_test;
 
 /// Boot Linux and verify the PMU interrupt is available.
@@ -79,3 +94,193 @@ async fn boot_dt(config: PetriVmBuilder<OpenVmmPetriBackend>) -> Result<(), anyh
     vm.wait_for_clean_teardown().await?;
     Ok(())
 }
+
+/// Exercise MSI-X delivery through the GICv3 ITS (as LPIs) for an NVMe
+/// device on an emulated PCIe root port.
+#[openvmm_test(linux_direct_aarch64)]
+async fn gic_its_msi(config: PetriVmBuilder<OpenVmmPetriBackend>) -> anyhow::Result<()> {
+    gic_msi_nvme_io(config, GicMsiConfig::Its, "ITS-PCI-MSIX").await
+}
+
+/// Exercise MSI-X delivery through GICv2m (as SPIs), for comparison with the
+/// ITS path.
+#[openvmm_test(linux_direct_aarch64)]
+async fn gic_v2m_msi(config: PetriVmBuilder<OpenVmmPetriBackend>) -> anyhow::Result<()> {
+    gic_msi_nvme_io(
+        config,
+        GicMsiConfig::V2m { spi_count: None },
+        "GICv2m-PCI-MSIX",
+    )
+    .await
+}
+
+/// Boots with the given MSI controller and an NVMe device, performs I/O to
+/// the device, and verifies that its queue interrupts were delivered through
+/// the MSI domain named `msi_domain` in `/proc/interrupts`.
+async fn gic_msi_nvme_io(
+    config: PetriVmBuilder<OpenVmmPetriBackend>,
+    gic_msi: GicMsiConfig,
+    msi_domain: &str,
+) -> anyhow::Result<()> {
+    let is_its = matches!(gic_msi, GicMsiConfig::Its);
+    let (vm, agent) = config
+        .modify_backend(|b| {
+            b.with_gic_msi(gic_msi)
+                .with_pcie_root_topology(1, 1, 1)
+                .with_pcie_nvme(
+                    "s0rc0rp0",
+                    guid::guid!("3a1e2f7c-5b4d-4c8e-9f0a-6d2b8c4e1a37"),
+                )
+        })
+        .run()
+        .await?;
+
+    let sh = agent.unix_shell();
+    cmd!(
+        sh,
+        "dd if=/dev/nvme0n1 of=/dev/null bs=4096 count=256 iflag=direct"
+    )
+    .read()
+    .await?;
+
+    let interrupts = cmd!(sh, "cat /proc/interrupts").read().await?;
+    let nvme_lines = interrupt_lines(&interrupts, "nvme0q");
+    for line in &nvme_lines {
+        assert!(
+            line.contains(msi_domain),
+            "NVMe interrupt not routed through {msi_domain}: {line}"
+        );
+    }
+    assert_interrupts_delivered(&nvme_lines);
+
+    if is_its {
+        // The ITS driver logs its probe as `ITS@<base>: ...`.
+        let dmesg = cmd!(sh, "dmesg").read().await?;
+        dmesg
+            .lines()
+            .find(|line| line.contains("ITS@"))
+            .context("guest did not probe the ITS")?;
+    }
+
+    agent.power_off().await?;
+    vm.wait_for_clean_teardown().await?;
+    Ok(())
+}
+
+/// Exercise MSI-X delivery for a VPCI NVMe device assigned to OpenHCL.
+///
+/// The device is Hyper-V's NVMe emulator, assigned to VTL2 over VPCI and
+/// relayed to VTL0 as a SCSI disk. I/O from VTL0 is serviced by OpenHCL's
+/// user-mode NVMe driver, so the device's queue interrupts must be delivered
+/// to VTL2 through its VFIO MSI-X vectors.
+#[cfg(windows)]
+#[vmm_test(unstable_hyperv_openhcl_uefi_aarch64(vhd(ubuntu_2404_server_aarch64)))]
+async fn vpci_nvme_msi_openhcl<T: PetriVmmBackend>(
+    config: PetriVmBuilder<T>,
+) -> anyhow::Result<()> {
+    let vtl0_nvme_lun = 0;
+    let nvme_nsid = 1;
+    let nvme_vsid = Guid::new_random();
+    let scsi_instance = Guid::new_random();
+    const NVME_DISK_SECTORS: u64 = 0x5_0000;
+    const SECTOR_SIZE: u64 = 512;
+
+    let mut vhd =
+        tempfile::NamedTempFile::with_suffix("nvme.vhd").context("create temp nvme vhd")?;
+    vhd.as_file()
+        .set_len(NVME_DISK_SECTORS * SECTOR_SIZE)
+        .context("set file length")?;
+    disk_vhd1::Vhd1Disk::make_fixed(vhd.as_file_mut()).context("make fixed")?;
+
+    // Close the handle without deleting the file, so Hyper-V can open it.
+    let vhd_path = vhd.into_temp_path();
+
+    let (mut vm, agent) = config
+        .with_vmbus_redirect(true)
+        .add_vmbus_storage_controller(&nvme_vsid, petri::Vtl::Vtl2, petri::VmbusStorageType::Nvme)
+        .add_vmbus_drive(
+            petri::Drive::new(Some(petri::Disk::Persistent(vhd_path.to_path_buf())), false),
+            &nvme_vsid,
+            Some(nvme_nsid),
+        )
+        .add_vtl2_storage_controller(
+            Vtl2StorageControllerBuilder::new(ControllerType::Scsi)
+                .with_instance_id(scsi_instance)
+                .add_lun(
+                    Vtl2LunBuilder::disk()
+                        .with_location(vtl0_nvme_lun)
+                        .with_physical_device(Vtl2StorageBackingDeviceBuilder::new(
+                            ControllerType::Nvme,
+                            nvme_vsid,
+                            nvme_nsid,
+                        )),
+                )
+                .build(),
+        )
+        .run()
+        .await?;
+
+    let device_paths = get_device_paths(
+        &agent,
+        scsi_instance,
+        vec![ExpectedGuestDevice {
+            lun: vtl0_nvme_lun,
+            disk_size_sectors: NVME_DISK_SECTORS as usize,
+            friendly_name: "nvme".to_string(),
+        }],
+    )
+    .await?;
+    let disk = &device_paths[0];
+
+    let sh = agent.unix_shell();
+    cmd!(
+        sh,
+        "dd if={disk} of=/dev/null bs=4096 count=256 iflag=direct"
+    )
+    .read()
+    .await?;
+
+    let vtl2_agent = vm.wait_for_vtl2_agent().await?;
+    let interrupts = vtl2_agent
+        .unix_shell()
+        .read_file("/proc/interrupts")
+        .await?;
+    assert_interrupts_delivered(&interrupt_lines(&interrupts, "vfio-msix"));
+
+    agent.power_off().await?;
+    vm.wait_for_clean_teardown().await?;
+    Ok(())
+}
+
+/// Returns the lines of `/proc/interrupts` for the interrupts whose name
+/// contains `name`, asserting that there is at least one.
+fn interrupt_lines<'a>(interrupts: &'a str, name: &str) -> Vec<&'a str> {
+    let lines = interrupts
+        .lines()
+        .filter(|line| line.contains(name))
+        .collect::<Vec<_>>();
+    assert!(
+        !lines.is_empty(),
+        "no {name} interrupts in /proc/interrupts:\n{interrupts}"
+    );
+    lines
+}
+
+/// Asserts that at least one of the interrupts in the given `/proc/interrupts`
+/// lines was delivered.
+fn assert_interrupts_delivered(lines: &[&str]) {
+    // The per-CPU counts follow the IRQ number.
+    let delivered = lines
+        .iter()
+        .flat_map(|line| {
+            line.split_whitespace()
+                .skip(1)
+                .map_while(|count| count.parse::<u64>().ok())
+        })
+        .sum::<u64>();
+    assert!(
+        delivered > 0,
+        "no interrupts were delivered:\n{}",
+        lines.join("\n")
+    );
+}
@@ -4,7 +4,16 @@
 //! Integration tests that run on more than one architecture.
 
 use anyhow::Context;
+use firmware_uefi_custom_vars::Sha256Digest;
+use firmware_uefi_custom_vars::Signature;
+use firmware_uefi_custom_vars::delta::CustomVarsDelta;
+use firmware_uefi_custom_vars::delta::SignatureDelta;
+use firmware_uefi_custom_vars::delta::SignatureDeltaVec;
+use firmware_uefi_custom_vars::delta::SignaturesDelta;
+use firmware_uefi_custom_vars::delta::SignaturesReplace;
+use futures::AsyncReadExt;
 use futures::StreamExt;
+use get_resources::ged::FirmwareEvent;
 use petri::EfiDiagnosticsLogLevel;
 use petri::MemoryConfig;
 use petri::PetriHaltReason;
@@ -13,6 +22,8 @@
 use petri::ProcessorTopology;
 use petri::SIZE_1_GB;
 use petri::ShutdownKind;
+use petri::UefiTestResults;
+use petri::UefiTestStatus;
 use petri::openvmm::OpenVmmPetriBackend;
 use petri::pipette::cmd;
 use petri_artifacts_common::tags::MachineArch;
@@ -93,6 +104,23 @@ async fn boot<T: PetriVmmBackend>(config: PetriVmBuilder<T>) -> anyhow::Result<(
     Ok(())
 }
 
+/// Validate that a guest crash reported through the Hyper-V guest crash
+/// registers halts the VM with the bugcheck code.
+#[vmm_test(
+    openvmm_uefi_x64(vhd(windows_datacenter_core_2022_x64)),
+    openvmm_openhcl_uefi_x64(vhd(windows_datacenter_core_2022_x64)),
+    hyperv_openhcl_uefi_x64(vhd(windows_datacenter_core_2022_x64))
+)]
+async fn guest_crash<T: PetriVmmBackend>(config: PetriVmBuilder<T>) -> anyhow::Result<()> {
+    let (mut vm, agent) = config.run().await?;
+    // Killing wininit bugchecks with CRITICAL_PROCESS_DIED.
+    agent.kernel_crash().await?;
+    let crash = vm.wait_for_crash().await?;
+    assert_eq!(crash.bugcheck_code, Some(0xef));
+    vm.teardown().await?;
+    Ok(())
+}
+
 /// Basic boot test using virtio vsock instead of vmbus hvsocket.
 /// N.B. Because this requires kernel support, it's only done for Linux direct boot since the test
 ///      kernel is guaranteed to include it.
@@ -108,6 +136,67 @@ async fn boot_virtio_vsock(config: PetriVmBuilder<OpenVmmPetriBackend>) -> anyho
     Ok(())
 }
 
+/// Validate that the host can listen for and dial guest vsock connections.
+#[openvmm_test(uefi_x64(vhd(ubuntu_2404_server_x64)))]
+async fn guest_vsock(config: PetriVmBuilder<OpenVmmPetriBackend>) -> anyhow::Result<()> {
+    const HOST_PORT: u32 = 5000;
+    const GUEST_PORT: u32 = 5001;
+
+    let (mut vm, agent) = config.run().await?;
+    let sh = agent.unix_shell();
+
+    let mut listener = vm.backend().listen_guest_vsock(HOST_PORT)?;
+    let listener_path = listener.path().to_owned();
+
+    // The guest only connects to the host once it is listening, so the host
+    // knows when it can dial the guest.
+    let script = format!(
+        "import socket
+l = socket.socket(socket.AF_VSOCK, socket.SOCK_STREAM)
+l.bind((socket.VMADDR_CID_ANY, {GUEST_PORT}))
+l.listen(1)
+s = socket.socket(socket.AF_VSOCK, socket.SOCK_STREAM)
+s.connect((socket.VMADDR_CID_HOST, {HOST_PORT}))
+s.sendall(b'ready')
+s.close()
+c, _ = l.accept()
+c.sendall(b'world')
+c.close()
+"
+    );
+    let guest = cmd!(sh, "python3 -c {script}").run();
+    let host = async {
+        let mut buf = Vec::new();
+        listener
+            .accept()
+            .await?
+            .read_to_end(&mut buf)
+            .await
+            .context("failed to read from guest connection")?;
+        assert_eq!(buf, b"ready");
+
+        buf.clear();
+        vm.backend()
+            .connect_guest_vsock(GUEST_PORT)
+            .await?
+            .read_to_end(&mut buf)
+            .await
+            .context("failed to read from guest listener")?;
+        assert_eq!(buf, b"world");
+        anyhow::Ok(())
+    };
+    let (guest_result, host_result) = futures::join!(guest, host);
+    guest_result.context("guest vsock script failed")?;
+    host_result?;
+
+    drop(listener);
+    assert!(!listener_path.exists());
+
+    agent.power_off().await?;
+    vm.wait_for_clean_teardown().await?;
+    Ok(())
+}
+
 /// Boot with private anonymous memory instead of shared memory sections.
 #[openvmm_test(
     linux_direct_x64,
@@ -466,6 +555,98 @@ async fn secure_boot_mismatched_template<T: PetriVmmBackend>(
     Ok(())
 }
 
+/// Verify that custom secure boot variables are applied, by replacing db with
+/// a single hash that matches no bootloader, so that boot fails.
+#[vmm_test_with(noagent(
+    openvmm_uefi_aarch64(vhd(ubuntu_2404_server_aarch64)),
+    openvmm_uefi_x64(vhd(windows_datacenter_core_2022_x64)),
+    openvmm_uefi_x64(vhd(ubuntu_2504_server_x64))
+))]
+async fn secure_boot_custom_db<T: PetriVmmBackend>(
+    config: PetriVmBuilder<T>,
+) -> anyhow::Result<()> {
+    let vm = config
+        .with_expect_boot_failure()
+        .with_secure_boot()
+        .with_uefi_frontpage(false)
+        .with_custom_secure_boot_vars(CustomVarsDelta {
+            signatures: SignaturesDelta::Replace(SignaturesReplace {
+                pk: SignatureDelta::Default,
+                kek: SignatureDeltaVec::Default,
+                db: SignatureDeltaVec::Sigs(vec![Signature::Sha256(vec![Sha256Digest([0; 32])])]),
+                dbx: SignatureDeltaVec::Default,
+                moklist: None,
+                moklistx: None,
+            }),
+            custom_vars: Vec::new(),
+        })
+        .run_without_agent()
+        .await?;
+    vm.wait_for_clean_teardown().await?;
+    Ok(())
+}
+
+/// Verify that UEFI reports each boot phase on the way to a successful boot.
+#[vmm_test_with(noagent(
+    openvmm_uefi_aarch64(vhd(ubuntu_2404_server_aarch64)),
+    openvmm_uefi_x64(vhd(ubuntu_2504_server_x64))
+))]
+async fn uefi_boot_phases<T: PetriVmmBackend>(config: PetriVmBuilder<T>) -> anyhow::Result<()> {
+    let mut vm = config.run_without_agent().await?;
+    vm.wait_for_boot_phase(FirmwareEvent::BdsStarted).await?;
+    vm.wait_for_boot_phase(FirmwareEvent::OsLoaderHandoff)
+        .await?;
+    vm.send_enlightened_shutdown(ShutdownKind::Shutdown).await?;
+    vm.wait_for_clean_teardown().await?;
+    Ok(())
+}
+
+/// Verify that a guest request to boot into the firmware UI, made by setting
+/// `EFI_OS_INDICATIONS_BOOT_TO_FW_UI` in `OsIndications`, is honored on the
+/// next boot even though the frontpage is otherwise disabled.
+#[openvmm_test(uefi_x64(vhd(ubuntu_2504_server_x64)))]
+async fn boot_to_firmware_ui(config: PetriVmBuilder<OpenVmmPetriBackend>) -> anyhow::Result<()> {
+    const OS_INDICATIONS_PATH: &str =
+        "/sys/firmware/efi/efivars/OsIndications-8be4df61-93ca-11d2-aa0d-00e098032b8c";
+
+    let (mut vm, agent) = config.run().await?;
+    let shell = agent.unix_shell();
+
+    // efivarfs expects the variable attributes (NV | BS | RT) followed by the
+    // data, here just EFI_OS_INDICATIONS_BOOT_TO_FW_UI.
+    cmd!(shell, "sudo")
+        .args([
+            "sh",
+            "-c",
+            &format!(
+                "chattr -i {OS_INDICATIONS_PATH} 2>/dev/null; \
+                 printf '\\007\\000\\000\\000\\001\\000\\000\\000\\000\\000\\000\\000' \
+                 > {OS_INDICATIONS_PATH}"
+            ),
+        ])
+        .run()
+        .await?;
+
+    agent.reboot().await?;
+    let halt_reason = vm.wait_for_halt().await?;
+    assert_eq!(halt_reason.reason, PetriHaltReason::Reset);
+    vm.backend().reset().await?;
+
+    // The firmware should stop in its UI rather than hand off to the OS.
+    vm.wait_for_boot_phase(FirmwareEvent::BdsStarted).await?;
+    let handoff = mesh::CancelContext::new()
+        .with_timeout(std::time::Duration::from_secs(60))
+        .until_cancelled(vm.wait_for_boot_phase(FirmwareEvent::OsLoaderHandoff))
+        .await;
+    assert!(
+        handoff.is_err(),
+        "the OS was loaded instead of the firmware UI"
+    );
+
+    vm.teardown().await?;
+    Ok(())
+}
+
 /// Test EFI diagnostics with no boot devices.
 /// TODO:
 ///   - uefi_x64 + uefi_aarch64 trace searching support
@@ -538,13 +719,15 @@ async fn efi_diagnostics_info_level<T: PetriVmmBackend>(
 
 /// Boot our guest-test UEFI image, which will run some tests,
 /// and then purposefully triple fault itself via an expiring
-/// watchdog timer.
+/// watchdog timer. The results the image reports over serial are
+/// checked after teardown.
 #[vmm_test_with(noagent(
     openvmm_uefi_x64(guest_test_uefi_x64),
     openvmm_uefi_aarch64(guest_test_uefi_aarch64),
     openvmm_openhcl_uefi_x64(guest_test_uefi_x64)
 ))]
 async fn guest_test_uefi<T: PetriVmmBackend>(config: PetriVmBuilder<T>) -> anyhow::Result<()> {
+    let log_source = config.log_source().clone();
     let vm = config
         .with_windows_secure_boot_template()
         .run_without_agent()
@@ -553,16 +736,26 @@ async fn guest_test_uefi<T: PetriVmmBackend>(config: PetriVmBuilder<T>) -> anyho
     // No boot event check, UEFI watchdog gets fired before ExitBootServices
     let halt_reason = vm.wait_for_teardown().await?;
     tracing::debug!("vm halt reason: {halt_reason:?}");
-    let check_reason = |expected| {
-        if halt_reason.reason != expected {
-            anyhow::bail!("Expected {expected:?}, got {halt_reason:?}");
-        }
-        Ok(())
+    let expected = match arch {
+        MachineArch::X86_64 => PetriHaltReason::TripleFault,
+        MachineArch::Aarch64 => PetriHaltReason::Reset,
     };
-    match arch {
-        MachineArch::X86_64 => check_reason(PetriHaltReason::TripleFault),
-        MachineArch::Aarch64 => check_reason(PetriHaltReason::Reset),
+    if halt_reason.reason != expected {
+        anyhow::bail!("Expected {expected:?}, got {halt_reason:?}");
+    }
+
+    // The watchdog test ends the VM, so it never reports that it passed.
+    let results = UefiTestResults::from_log(&log_source)?;
+    tracing::info!(?results, "guest test results");
+    if results.status("watchdog") != Some(UefiTestStatus::Running) {
+        anyhow::bail!("watchdog test did not run: {results:?}");
+    }
+    for (name, status) in results.tests() {
+        if name != "watchdog" && status != UefiTestStatus::Passed {
+            anyhow::bail!("guest test '{name}' did not pass: {status:?}");
+        }
     }
+    Ok(())
 }
 
 /// Test that unauthenticated deletion of PK and KEK is rejected by the firmware.
@@ -847,3 +1040,33 @@ fn drop(&mut self) {
 
     Ok(())
 }
+
+/// Restart the Hyper-V host services while an OpenHCL VM is running, and
+/// verify that the VM is re-adopted and the guest is unaffected.
+///
+/// This disrupts any other Hyper-V VM on the host, so nextest runs it on its
+/// own (see `.config/nextest.toml`).
+#[cfg(windows)]
+#[vmm_test(
+    hyperv_openhcl_uefi_x64(vhd(ubuntu_2504_server_x64)),
+    hyperv_openhcl_uefi_aarch64(vhd(ubuntu_2404_server_aarch64))
+)]
+async fn host_service_restart(
+    config: PetriVmBuilder<petri::hyperv::HyperVPetriBackend>,
+) -> anyhow::Result<()> {
+    use petri::hyperv::powershell::HyperVHostService;
+
+    let (mut vm, agent) = config.run().await?;
+
+    for service in [HyperVHostService::Vmms, HyperVHostService::HostCompute] {
+        vm.backend().restart_host_service(service).await?;
+        agent
+            .ping()
+            .await
+            .with_context(|| format!("guest unresponsive after restarting {service:?}"))?;
+    }
+
+    agent.power_off().await?;
+    vm.wait_for_clean_teardown().await?;
+    Ok(())
+}
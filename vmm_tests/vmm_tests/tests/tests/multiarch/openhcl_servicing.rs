@@ -29,10 +29,14 @@
 use nvme_test::command_match::CommandMatchBuilder;
 use openvmm_defs::config::DeviceVtl;
 use openvmm_defs::config::VpciDeviceConfig;
+use pal_async::DefaultPool;
+use petri::Firmware;
 use petri::MemoryConfig;
 use petri::OpenHclServicingFlags;
 use petri::PetriGuestStateLifetime;
+use petri::PetriTestParams;
 use petri::PetriVm;
+use petri::PetriVmArtifacts;
 use petri::PetriVmBuilder;
 use petri::PetriVmmBackend;
 use petri::ProcessorTopology;
@@ -43,6 +47,7 @@
 use petri::vtl2_settings::Vtl2LunBuilder;
 use petri::vtl2_settings::Vtl2StorageBackingDeviceBuilder;
 use petri::vtl2_settings::Vtl2StorageControllerBuilder;
+use petri_artifacts_common::tags::MachineArch;
 use petri_artifacts_vmm_test::artifacts::openhcl_igvm::LATEST_LINUX_DIRECT_TEST_X64;
 use petri_artifacts_vmm_test::artifacts::openhcl_igvm::LATEST_RELEASE_LINUX_DIRECT_X64;
 #[cfg(windows)]
@@ -115,6 +120,88 @@ async fn basic_servicing<T: PetriVmmBackend>(
     openhcl_servicing_core(config, igvm_file, flags, DEFAULT_SERVICING_COUNT).await
 }
 
+petri::stress_test!(
+    openvmm_openhcl_linux_direct_x64_servicing_stress,
+    |resolver| {
+        let firmware = Firmware::openhcl_linux_direct(resolver, MachineArch::X86_64);
+        let artifacts = PetriVmArtifacts::<OpenVmmPetriBackend>::new(
+            resolver,
+            firmware,
+            MachineArch::X86_64,
+            true,
+        )?;
+        Some((artifacts, resolver.require(LATEST_LINUX_DIRECT_TEST_X64)))
+    },
+    iterations = 10,
+    max_duration = Duration::from_secs(30 * 60)
+);
+
+/// Soak servicing an OpenHCL VM from the current version to itself, with a
+/// fresh VM for each iteration, to catch servicing races that only reproduce
+/// occasionally.
+fn openvmm_openhcl_linux_direct_x64_servicing_stress(
+    params: PetriTestParams<'_>,
+    (artifacts, igvm_file): (
+        PetriVmArtifacts<OpenVmmPetriBackend>,
+        ResolvedArtifact<impl petri_artifacts_common::tags::IsOpenhclIgvm>,
+    ),
+) -> anyhow::Result<()> {
+    DefaultPool::run_with(async |driver| {
+        let config = PetriVmBuilder::new(params, artifacts, &driver)?;
+        let mut flags = config.default_servicing_flags();
+        flags.override_version_checks = true;
+        openhcl_servicing_core(config, igvm_file, flags, DEFAULT_SERVICING_COUNT).await
+    })
+}
+
+/// Test that a disarmed servicing fault does not affect servicing.
+#[openvmm_test(openhcl_linux_direct_x64 [LATEST_LINUX_DIRECT_TEST_X64])]
+async fn servicing_fault_cleared(
+    config: PetriVmBuilder<OpenVmmPetriBackend>,
+    (igvm_file,): (ResolvedArtifact<impl petri_artifacts_common::tags::IsOpenhclIgvm>,),
+) -> anyhow::Result<()> {
+    let mut flags = config.default_servicing_flags();
+    flags.override_version_checks = true;
+    let (mut vm, agent) = config.run().await?;
+    agent.ping().await?;
+
+    vm.backend()
+        .inject_servicing_fault(get_resources::ged::ServicingFault::FailRestore)
+        .await?;
+    vm.backend().clear_servicing_fault().await?;
+    vm.restart_openhcl(igvm_file, flags).await?;
+    agent.ping().await?;
+
+    agent.power_off().await?;
+    vm.wait_for_clean_teardown().await?;
+    Ok(())
+}
+
+/// Test that servicing fails cleanly when the host fails to activate the new
+/// OpenHCL image.
+#[openvmm_test(openhcl_linux_direct_x64 [LATEST_LINUX_DIRECT_TEST_X64])]
+async fn servicing_fault_fail_activation(
+    config: PetriVmBuilder<OpenVmmPetriBackend>,
+    (igvm_file,): (ResolvedArtifact<impl petri_artifacts_common::tags::IsOpenhclIgvm>,),
+) -> anyhow::Result<()> {
+    let mut flags = config.default_servicing_flags();
+    flags.override_version_checks = true;
+    let (mut vm, agent) = config.run().await?;
+    agent.ping().await?;
+
+    vm.backend()
+        .inject_servicing_fault(get_resources::ged::ServicingFault::FailActivation)
+        .await?;
+    vm.restart_openhcl(igvm_file, flags)
+        .await
+        .expect_err("servicing should fail to activate the new image");
+
+    // The old OpenHCL has already saved its state and the new one never
+    // started, so the VM cannot continue.
+    vm.teardown().await?;
+    Ok(())
+}
+
 /// Test servicing an OpenHCL VM from the current version to itself, with a tpm.
 #[vmm_test(
     openvmm_openhcl_uefi_x64(vhd(ubuntu_2504_server_x64))[LATEST_STANDARD_X64],
@@ -426,6 +513,7 @@ async fn servicing_shutdown_ic(
                         io_queue_depth: None,
                         requests: None,
                         poll_mode_queue_depth: None,
+                        max_protocol_version: None,
                     }
                     .into_resource(),
                 ));
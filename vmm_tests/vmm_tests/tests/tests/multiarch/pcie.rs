@@ -363,6 +363,7 @@ async fn pcie_hotplug(
         max_io_queues: 1,
         namespaces: vec![],
         requests: None,
+        boot_partitions: None,
     });
     vm.add_pcie_device("s0rc0rp0".into(), nvme_resource).await?;
 
@@ -468,6 +469,56 @@ async fn pcie_save_restore(config: PetriVmBuilder<OpenVmmPetriBackend>) -> anyho
     Ok(())
 }
 
+/// Test that device memory tracking attributes NVMe DMA to the device's port,
+/// and that clearing the tracker forgets everything but held locks.
+#[openvmm_test(unstable_linux_direct_x64)]
+async fn pcie_nvme_memory_tracking(
+    config: PetriVmBuilder<OpenVmmPetriBackend>,
+) -> anyhow::Result<()> {
+    let (mut vm, agent) = config
+        .modify_backend(|b| {
+            b.with_pcie_root_topology(1, 1, 1)
+                .with_pcie_nvme("s0rc0rp0", PCIE_NVME_SUBSYSTEM_IDS[0])
+                .with_device_memory_tracking()
+        })
+        .run()
+        .await?;
+
+    let sh = agent.unix_shell();
+    cmd!(
+        sh,
+        "dd if=/dev/zero of=/dev/nvme0n1 bs=4096 count=16 oflag=direct"
+    )
+    .run()
+    .await?;
+
+    let accesses = vm.backend().device_memory_accesses(0..u64::MAX).await?;
+    assert!(accesses.touched, "NVMe I/O should touch guest memory");
+    assert!(
+        accesses.accesses.iter().all(|a| a.device == "s0rc0rp0"),
+        "unexpected device in {:?}",
+        accesses.accesses
+    );
+
+    // Pause so that no new I/O arrives between clearing and querying.
+    vm.backend().pause().await?;
+    vm.backend().clear_device_memory_accesses().await?;
+    let accesses = vm.backend().device_memory_accesses(0..u64::MAX).await?;
+    assert!(
+        accesses
+            .accesses
+            .iter()
+            .all(|a| a.kind == openvmm_defs::rpc::DeviceMemoryAccessKind::Lock),
+        "only held locks should remain after clearing: {:?}",
+        accesses.accesses
+    );
+    vm.backend().resume().await?;
+
+    agent.power_off().await?;
+    vm.wait_for_clean_teardown().await?;
+    Ok(())
+}
+
 /// Boot a guest through UEFI from an NVMe device on an emulated PCIe root port.
 /// Validates that UEFI's driver stack correctly enumerates and uses the NVMe
 /// device to load the guest OS.
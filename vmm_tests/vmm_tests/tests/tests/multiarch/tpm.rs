@@ -3,6 +3,8 @@
 
 use anyhow::Context;
 use anyhow::ensure;
+#[cfg(windows)]
+use openhcl_attestation_protocol::igvm_attest::get::runtime_claims::RsaJwk;
 use petri::PetriGuestStateLifetime;
 #[cfg(windows)]
 use petri::PetriHaltReason;
@@ -30,11 +32,12 @@
 #[cfg(windows)]
 use vmm_test_macros::vmm_test_with;
 
-const AK_CERT_NONZERO_BYTES: usize = 2500;
-const AK_CERT_TOTAL_BYTES: usize = 4096;
-
 const TPM_GUEST_TESTS_LINUX_GUEST_PATH: &str = "/tmp/tpm_guest_tests";
 const TPM_GUEST_TESTS_WINDOWS_GUEST_PATH: &str = "C:\\tpm_guest_tests.exe";
+/// The size of the guest attestation input NV index written by
+/// `tpm_guest_tests report`.
+#[cfg(windows)]
+const GUEST_INPUT_SIZE: usize = 64;
 
 #[cfg(windows)]
 fn ensure_rpc_server_running(
@@ -59,12 +62,9 @@ fn ensure_rpc_server_running(
         .context("failed to start test_igvm_agent_rpc_server")
 }
 
-fn expected_ak_cert_hex() -> String {
+fn to_hex(data: &[u8]) -> String {
     use std::fmt::Write as _;
 
-    let mut data = vec![0xab; AK_CERT_NONZERO_BYTES];
-    data.resize(AK_CERT_TOTAL_BYTES, 0);
-
     let mut hex = String::with_capacity(data.len() * 2 + 2);
     hex.push_str("0x");
     for byte in data {
@@ -74,6 +74,33 @@ fn expected_ak_cert_hex() -> String {
     hex
 }
 
+fn from_hex(hex: &str) -> anyhow::Result<Vec<u8>> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    ensure!(hex.len() % 2 == 0, "odd-length hex string");
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).context("invalid hex string"))
+        .collect()
+}
+
+/// Parses the hex value of the `label: 0x...` line in `tpm_guest_tests`
+/// output.
+fn output_hex(output: &str, label: &str) -> anyhow::Result<Vec<u8>> {
+    let value = output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix(label)?.strip_prefix(':'))
+        .with_context(|| format!("no {label} in output: {output}"))?;
+    from_hex(value.trim()).with_context(|| format!("failed to parse {label}"))
+}
+
+/// Returns a nonce that has not been used by an earlier check.
+fn fresh_nonce() -> anyhow::Result<[u8; 16]> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_nanos()
+        .to_le_bytes())
+}
+
 struct TpmGuestTests<'a> {
     os_flavor: OsFlavor,
     guest_binary_path: String,
@@ -135,61 +162,175 @@ async fn read_ak_cert(&self) -> anyhow::Result<String> {
         }
     }
 
-    async fn read_ak_cert_with_expected_hex(&self, expected_hex: &str) -> anyhow::Result<String> {
+    /// Reads the AK certificate NV index, retrying until the certificate has
+    /// been provisioned, and returns its contents.
+    async fn read_ak_cert_with_retry(&self) -> anyhow::Result<Vec<u8>> {
         let guest_binary_path = &self.guest_binary_path;
+        let args = ["ak_cert", "--show-hex", "--retry", "10"];
+        let output = match self.os_flavor {
+            OsFlavor::Linux => {
+                let sh = self.agent.unix_shell();
+                cmd!(sh, "{guest_binary_path}").args(args).read().await?
+            }
+            OsFlavor::Windows => {
+                let sh = self.agent.windows_shell();
+                cmd!(sh, "{guest_binary_path}").args(args).read().await?
+            }
+            _ => unreachable!(),
+        };
 
-        match self.os_flavor {
+        output_hex(&output, "AK certificate hex")
+    }
+
+    /// Quotes the guest's PCRs with the AK, using `nonce` as the qualifying
+    /// data.
+    async fn quote(&self, nonce: &[u8]) -> anyhow::Result<GuestQuote> {
+        let guest_binary_path = &self.guest_binary_path;
+        let nonce_hex = to_hex(nonce);
+        let args = ["quote", "--nonce-hex", nonce_hex.as_str()];
+        let output = match self.os_flavor {
             OsFlavor::Linux => {
                 let sh = self.agent.unix_shell();
-                cmd!(sh, "{guest_binary_path}")
-                    .args([
-                        "ak_cert",
-                        "--expected-data-hex",
-                        expected_hex,
-                        "--retry",
-                        "10",
-                    ])
-                    .read()
-                    .await
+                cmd!(sh, "{guest_binary_path}").args(args).read().await?
             }
             OsFlavor::Windows => {
                 let sh = self.agent.windows_shell();
-                cmd!(sh, "{guest_binary_path}")
-                    .args([
-                        "ak_cert",
-                        "--expected-data-hex",
-                        expected_hex,
-                        "--retry",
-                        "10",
-                    ])
-                    .read()
-                    .await
+                cmd!(sh, "{guest_binary_path}").args(args).read().await?
             }
             _ => unreachable!(),
-        }
+        };
+
+        Ok(GuestQuote {
+            ak_modulus: output_hex(&output, "AK public modulus")?,
+            ak_exponent: output_hex(&output, "AK public exponent")?,
+            attest: output_hex(&output, "Quote attest")?,
+            signature: output_hex(&output, "Quote signature")?,
+        })
+    }
+
+    /// Verifies the guest's AK from inside the guest.
+    ///
+    /// Checks that the AK signs a quote bound to a fresh nonce, and that the
+    /// AK certificate in the guest was issued for that AK by the test IGVM
+    /// agent's CA. Returns the AK's RSA modulus.
+    async fn verify_ak(&self) -> anyhow::Result<Vec<u8>> {
+        // Wait for the AK certificate first, so that the AK has been
+        // provisioned before it is used.
+        let ak_cert = self
+            .read_ak_cert_with_retry()
+            .await
+            .context("failed to read the AK certificate inside the guest")?;
+
+        let nonce = fresh_nonce()?;
+        let quote = self
+            .quote(&nonce)
+            .await
+            .context("failed to quote with the AK inside the guest")?;
+        test_igvm_agent_lib::verify_quote(
+            &quote.attest,
+            &quote.signature,
+            &nonce,
+            &quote.ak_modulus,
+            &quote.ak_exponent,
+        )
+        .context("invalid quote")?;
+        test_igvm_agent_lib::verify_test_ak_cert(&ak_cert, &quote.ak_modulus, &quote.ak_exponent)
+            .context("invalid AK certificate")?;
+
+        Ok(quote.ak_modulus)
     }
 
     #[cfg(windows)]
-    async fn read_report(&self) -> anyhow::Result<String> {
+    async fn read_report(&self, user_data_hex: &str) -> anyhow::Result<String> {
         let guest_binary_path = &self.guest_binary_path;
+        let args = [
+            "report",
+            "--show-runtime-claims",
+            "--user-data-hex",
+            user_data_hex,
+        ];
         match self.os_flavor {
             OsFlavor::Linux => {
                 let sh = self.agent.unix_shell();
-                cmd!(sh, "{guest_binary_path}")
-                    .args(["report", "--show-runtime-claims"])
-                    .read()
-                    .await
+                cmd!(sh, "{guest_binary_path}").args(args).read().await
             }
             OsFlavor::Windows => {
                 let sh = self.agent.windows_shell();
-                cmd!(sh, "{guest_binary_path}")
-                    .args(["report", "--show-runtime-claims"])
-                    .read()
-                    .await
+                cmd!(sh, "{guest_binary_path}").args(args).read().await
             }
             _ => unreachable!(),
         }
     }
+
+    /// Attests the guest's vTPM from inside the guest.
+    ///
+    /// Verifies the AK with [`Self::verify_ak`], then requests an attestation
+    /// report bound to a fresh nonce and verifies that the report's runtime
+    /// claims echo the nonce, so that a stale report cannot satisfy the
+    /// check, and carry the verified AK.
+    #[cfg(windows)]
+    async fn attest(&self) -> anyhow::Result<GuestAttestation> {
+        let ak_modulus = self.verify_ak().await?;
+
+        let nonce = fresh_nonce()?;
+        let report_output = self
+            .read_report(&to_hex(&nonce))
+            .await
+            .context("failed to execute tpm_guest_tests report inside the guest")?;
+        let (_, claims) = report_output
+            .split_once("Runtime claims JSON:")
+            .with_context(|| format!("no runtime claims in report: {report_output}"))?;
+        let runtime_claims: serde_json::Value =
+            serde_json::from_str(claims.trim()).context("failed to parse runtime claims")?;
+
+        // The guest input is zero-padded to its NV index size before being
+        // reported.
+        let mut guest_input = nonce.to_vec();
+        guest_input.resize(GUEST_INPUT_SIZE, 0);
+        let user_data = runtime_claims["user-data"].as_str().unwrap_or_default();
+        ensure!(
+            user_data.eq_ignore_ascii_case(&to_hex(&guest_input)[2..]),
+            "runtime claims do not contain the nonce: {user_data}"
+        );
+
+        let ak_pub = runtime_claims["keys"]
+            .as_array()
+            .and_then(|keys| keys.iter().find(|key| key["kid"] == "HCLAkPub"))
+            .context("runtime claims are missing the AK public key")?;
+        let ak_pub: RsaJwk =
+            serde_json::from_value(ak_pub.clone()).context("failed to parse the AK public key")?;
+        ensure!(
+            ak_pub.n == ak_modulus,
+            "runtime claims AK public key does not match the TPM's AK"
+        );
+        let vm_unique_id = runtime_claims["vm-configuration"]["vmUniqueId"]
+            .as_str()
+            .context("runtime claims are missing the VM unique ID")?
+            .to_owned();
+
+        Ok(GuestAttestation { vm_unique_id })
+    }
+}
+
+/// A quote made with the guest's AK, from [`TpmGuestTests::quote`].
+struct GuestQuote {
+    /// The AK's big-endian RSA modulus.
+    ak_modulus: Vec<u8>,
+    /// The AK's big-endian RSA public exponent.
+    ak_exponent: Vec<u8>,
+    /// The marshaled `TPMS_ATTEST`.
+    attest: Vec<u8>,
+    /// The signature over `attest`.
+    signature: Vec<u8>,
+}
+
+/// The results of attesting the guest's vTPM, from
+/// [`TpmGuestTests::attest`].
+#[cfg(windows)]
+#[derive(Debug)]
+struct GuestAttestation {
+    /// The VM unique ID from the attestation report's runtime claims.
+    vm_unique_id: String,
 }
 
 /// Basic boot tests with TPM enabled.
@@ -251,21 +392,19 @@ async fn tpm_ak_cert_persisted<T>(
         .run()
         .await?;
 
-    let guest_binary_path = match os_flavor {
-        // Ubuntu automatically reboots when the TPM is enabled
-        OsFlavor::Linux => {
-            // First boot - AK cert request will be served by GED.
-            // Second boot - Ak cert request will be bypassed by GED.
-            TPM_GUEST_TESTS_LINUX_GUEST_PATH
-        }
-        OsFlavor::Windows => {
-            // First boot - AK cert request will be served by GED
-            // Second boot - Ak cert request will be bypassed by GED.
-            agent.reboot().await?;
-            agent = vm.wait_for_reset().await?;
+    // First boot - AK cert request will be served by GED.
+    // Second boot - Ak cert request will be bypassed by GED.
+    //
+    // Some guests (e.g., Ubuntu) automatically reboot when the TPM is
+    // enabled, as declared by their quirks. Only reboot the others.
+    if !vm.reset_on_startup() {
+        agent.reboot().await?;
+        agent = vm.wait_for_reset().await?;
+    }
 
-            TPM_GUEST_TESTS_WINDOWS_GUEST_PATH
-        }
+    let guest_binary_path = match os_flavor {
+        OsFlavor::Linux => TPM_GUEST_TESTS_LINUX_GUEST_PATH,
+        OsFlavor::Windows => TPM_GUEST_TESTS_WINDOWS_GUEST_PATH,
         _ => unreachable!(),
     };
 
@@ -275,15 +414,7 @@ async fn tpm_ak_cert_persisted<T>(
         TpmGuestTests::send_tpm_guest_tests(&agent, host_binary_path, guest_binary_path, os_flavor)
             .await?;
 
-    let expected_hex = expected_ak_cert_hex();
-    let output = tpm_guest_tests
-        .read_ak_cert_with_expected_hex(expected_hex.as_str())
-        .await?;
-
-    ensure!(
-        output.contains("AK certificate matches expected value"),
-        format!("{output}")
-    );
+    tpm_guest_tests.verify_ak().await?;
 
     agent.power_off().await?;
     vm.wait_for_clean_teardown().await?;
@@ -341,15 +472,52 @@ async fn tpm_ak_cert_retry<T>(
         "AK certificate read unexpectedly succeeded"
     );
 
-    let expected_hex = expected_ak_cert_hex();
-    let output = tpm_guest_tests
-        .read_ak_cert_with_expected_hex(expected_hex.as_str())
+    tpm_guest_tests.verify_ak().await?;
+
+    agent.power_off().await?;
+    vm.wait_for_clean_teardown().await?;
+
+    Ok(())
+}
+
+/// Test that the AK cert is provisioned when the host fails the first AK cert
+/// requests in different ways and then responds slowly.
+#[openvmm_test(
+    openhcl_uefi_x64(vhd(ubuntu_2504_server_x64))[TPM_GUEST_TESTS_LINUX_X64],
+    openhcl_uefi_x64(vhd(windows_datacenter_core_2022_x64))[TPM_GUEST_TESTS_WINDOWS_X64]
+)]
+async fn tpm_ak_cert_transient_faults<T>(
+    config: PetriVmBuilder<OpenVmmPetriBackend>,
+    extra_deps: (ResolvedArtifact<T>,),
+) -> anyhow::Result<()> {
+    let os_flavor = config.os_flavor();
+    let (vm, agent) = config
+        .with_guest_state_lifetime(PetriGuestStateLifetime::Disk)
+        .with_tpm(true)
+        .with_tpm_state_persistence(true)
+        .modify_backend(|b| {
+            b.with_igvm_attest_test_config(
+                get_resources::ged::IgvmAttestTestConfig::AkCertRequestTransientFaults,
+            )
+        })
+        .run()
         .await?;
 
-    ensure!(
-        output.contains("AK certificate matches expected value"),
-        format!("{output}")
-    );
+    let guest_binary_path = match os_flavor {
+        OsFlavor::Linux => TPM_GUEST_TESTS_LINUX_GUEST_PATH,
+        OsFlavor::Windows => TPM_GUEST_TESTS_WINDOWS_GUEST_PATH,
+        _ => unreachable!(),
+    };
+
+    let (artifact,) = extra_deps;
+    let host_binary_path = artifact.get();
+    let tpm_guest_tests =
+        TpmGuestTests::send_tpm_guest_tests(&agent, host_binary_path, guest_binary_path, os_flavor)
+            .await?;
+
+    // Each failed read triggers another AK cert request, until the delayed
+    // success provisions the certificate.
+    tpm_guest_tests.verify_ak().await?;
 
     agent.power_off().await?;
     vm.wait_for_clean_teardown().await?;
@@ -411,15 +579,7 @@ async fn ak_cert_cache<T, S, U: PetriVmmBackend>(
         TpmGuestTests::send_tpm_guest_tests(&agent, host_binary_path, guest_binary_path, os_flavor)
             .await?;
 
-    let expected_hex = expected_ak_cert_hex();
-    let output = tpm_guest_tests
-        .read_ak_cert_with_expected_hex(expected_hex.as_str())
-        .await?;
-
-    ensure!(
-        output.contains("AK certificate matches expected value"),
-        format!("{output}")
-    );
+    tpm_guest_tests.verify_ak().await?;
 
     agent.power_off().await?;
     vm.wait_for_clean_teardown().await?;
@@ -487,15 +647,7 @@ async fn ak_cert_retry<T, S, U: PetriVmmBackend>(
         "AK certificate read unexpectedly succeeded"
     );
 
-    let expected_hex = expected_ak_cert_hex();
-    let output = tpm_guest_tests
-        .read_ak_cert_with_expected_hex(expected_hex.as_str())
-        .await?;
-
-    ensure!(
-        output.contains("AK certificate matches expected value"),
-        format!("{output}")
-    );
+    tpm_guest_tests.verify_ak().await?;
 
     agent.power_off().await?;
     vm.wait_for_clean_teardown().await?;
@@ -555,72 +707,52 @@ async fn tpm_test_platform_hierarchy_disabled(
     Ok(())
 }
 
-// VBS attestation test with agent
-// TODO: Enable windows test when prep run dependency is supported for openvmm-based vbs tests and
-// remove `vbs_boot_with_attestation` test.
-// TODO: Enable Linux test when boot failure is resolved.
-// #[openvmm_test(
-//     openhcl_uefi_x64[vbs](vhd(ubuntu_2504_server_x64))[TPM_GUEST_TESTS_LINUX_X64],
-//     openhcl_uefi_x64[vbs](vhd(windows_datacenter_core_2025_x64_prepped))[TPM_GUEST_TESTS_WINDOWS_X64],
-// )]
-// async fn vbs_attestation_with_agent<T>(
-//     config: PetriVmBuilder<OpenVmmPetriBackend>,
-//     extra_deps: (ResolvedArtifact<T>,),
-// ) -> anyhow::Result<()> {
-//     let os_flavor = config.os_flavor();
-//     let config = config
-//         .with_guest_state_lifetime(PetriGuestStateLifetime::Disk)
-//         .modify_backend(|b| b.with_tpm().with_tpm_state_persistence(true));
-
-//     let (vm, agent, guest_binary_path) = match os_flavor {
-//         OsFlavor::Linux => {
-//             let (vm, agent) = config.with_expect_reset().run().await?;
-
-//             (vm, agent, TPM_GUEST_TESTS_LINUX_GUEST_PATH)
-//         }
-//         OsFlavor::Windows => {
-//             let (vm, agent) = config.run().await?;
-
-//             (vm, agent, TPM_GUEST_TESTS_WINDOWS_GUEST_PATH)
-//         }
-//         _ => unreachable!(),
-//     };
-
-//     let (artifact,) = extra_deps;
-//     let host_binary_path = artifact.get();
-//     let tpm_guest_tests =
-//         TpmGuestTests::send_tpm_guest_tests(&agent, host_binary_path, guest_binary_path, os_flavor)
-//             .await?;
-
-//     let expected_hex = expected_ak_cert_hex();
-//     let ak_cert_output = tpm_guest_tests
-//         .read_ak_cert_with_expected_hex(expected_hex.as_str())
-//         .await?;
-
-//     ensure!(
-//         ak_cert_output.contains("AK certificate matches expected value"),
-//         format!("{ak_cert_output}")
-//     );
-
-//     let report_output = tpm_guest_tests
-//         .read_report()
-//         .await
-//         .context("failed to execute tpm_guest_tests report inside the guest")?;
-
-//     ensure!(
-//         report_output.contains("Runtime claims JSON"),
-//         format!("{report_output}")
-//     );
-//     ensure!(
-//         report_output.contains("\"vmUniqueId\""),
-//         format!("{report_output}")
-//     );
-
-//     agent.power_off().await?;
-//     vm.wait_for_clean_teardown().await?;
-
-//     Ok(())
-// }
+/// VBS attestation test with agent.
+///
+/// Verifies a quote made with the AK and the AK certificate chain against
+/// the GED's test CA, then checks the attestation report's runtime claims.
+// TODO: Enable Linux test when boot failure is resolved, and remove
+// `vbs_boot_with_attestation` once this test is stable.
+#[cfg(windows)]
+#[openvmm_test(
+    unstable_openhcl_uefi_x64[vbs](vhd(windows_datacenter_core_2025_x64_prepped))[TPM_GUEST_TESTS_WINDOWS_X64],
+    // openhcl_uefi_x64[vbs](vhd(ubuntu_2504_server_x64))[TPM_GUEST_TESTS_LINUX_X64],
+)]
+async fn vbs_attestation_with_agent<T>(
+    config: PetriVmBuilder<OpenVmmPetriBackend>,
+    extra_deps: (ResolvedArtifact<T>,),
+) -> anyhow::Result<()> {
+    let os_flavor = config.os_flavor();
+    let (vm, agent) = config
+        .with_guest_state_lifetime(PetriGuestStateLifetime::Disk)
+        .with_tpm(true)
+        .with_tpm_state_persistence(true)
+        .run()
+        .await?;
+
+    let guest_binary_path = match os_flavor {
+        OsFlavor::Linux => TPM_GUEST_TESTS_LINUX_GUEST_PATH,
+        OsFlavor::Windows => TPM_GUEST_TESTS_WINDOWS_GUEST_PATH,
+        _ => unreachable!(),
+    };
+
+    let (artifact,) = extra_deps;
+    let host_binary_path = artifact.get();
+    let tpm_guest_tests =
+        TpmGuestTests::send_tpm_guest_tests(&agent, host_binary_path, guest_binary_path, os_flavor)
+            .await?;
+
+    let attestation = tpm_guest_tests.attest().await?;
+    ensure!(
+        !attestation.vm_unique_id.is_empty(),
+        "empty VM unique ID in runtime claims"
+    );
+
+    agent.power_off().await?;
+    vm.wait_for_clean_teardown().await?;
+
+    Ok(())
+}
 
 /// CVM with guest tpm tests on Hyper-V.
 ///
@@ -665,29 +797,12 @@ async fn cvm_tpm_guest_tests<T, S, U: PetriVmmBackend>(
         TpmGuestTests::send_tpm_guest_tests(&agent, host_binary_path, guest_binary_path, os_flavor)
             .await?;
 
-    // Verify AK cert with the test IGVM agent RPC server
-    let expected_hex = expected_ak_cert_hex();
-    let ak_cert_output = tpm_guest_tests
-        .read_ak_cert_with_expected_hex(expected_hex.as_str())
-        .await?;
-
+    // Verify the AK and its certificate from the test IGVM agent RPC server
+    let attestation = tpm_guest_tests.attest().await?;
+    tracing::info!(?attestation, "guest attestation");
     ensure!(
-        ak_cert_output.contains("AK certificate matches expected value"),
-        format!("{ak_cert_output}")
-    );
-
-    let report_output = tpm_guest_tests
-        .read_report()
-        .await
-        .context("failed to execute tpm_guest_tests report inside the guest")?;
-
-    ensure!(
-        report_output.contains("Runtime claims JSON"),
-        format!("{report_output}")
-    );
-    ensure!(
-        report_output.contains("\"vmUniqueId\""),
-        format!("{report_output}")
+        !attestation.vm_unique_id.is_empty(),
+        "empty VM unique ID in runtime claims"
     );
 
     agent.power_off().await?;
@@ -831,15 +946,7 @@ async fn use_hw_unseal<T, S, U: PetriVmmBackend>(
             .await?;
 
     // First boot: KEY_RELEASE succeeds. Verify AK cert is present.
-    let expected_hex = expected_ak_cert_hex();
-    let ak_cert_output = tpm_guest_tests
-        .read_ak_cert_with_expected_hex(expected_hex.as_str())
-        .await?;
-
-    ensure!(
-        ak_cert_output.contains("AK certificate matches expected value"),
-        format!("{ak_cert_output}")
-    );
+    tpm_guest_tests.verify_ak().await?;
 
     // Reboot: triggers second KEY_RELEASE which fails (plain failure,
     // no skip_hw_unsealing signal).  Hardware unsealing fallback kicks
@@ -853,14 +960,10 @@ async fn use_hw_unseal<T, S, U: PetriVmmBackend>(
         TpmGuestTests::send_tpm_guest_tests(&agent, host_binary_path, guest_binary_path, os_flavor)
             .await?;
 
-    let ak_cert_output = tpm_guest_tests
-        .read_ak_cert_with_expected_hex(expected_hex.as_str())
-        .await?;
-
-    ensure!(
-        ak_cert_output.contains("AK certificate matches expected value"),
-        "AK cert should still be accessible after hw unsealing fallback: {ak_cert_output}"
-    );
+    tpm_guest_tests
+        .verify_ak()
+        .await
+        .context("AK cert should still be accessible after hw unsealing fallback")?;
 
     agent.power_off().await?;
     vm.wait_for_clean_teardown().await?;
@@ -80,7 +80,7 @@ fn test_ttrpc_interface(
             &driver,
             mesh_rpc::client::UnixDialier::new(driver.clone(), ttrpc_path),
         );
-        for i in 0..3 {
+        for i in 0..4 {
             let mut com1_path = std::env::temp_dir();
             com1_path.push(Guid::new_random().to_string());
 
@@ -223,21 +223,17 @@ fn test_ttrpc_interface(
 
                     waiter.await.unwrap();
 
-                    if i == 0 {
-                        client
-                            .call()
-                            .start(vmservice::Vm::TeardownVm, ())
-                            .await
-                            .unwrap();
-
-                        client
-                            .call()
-                            .start(vmservice::Vm::WaitVm, ())
-                            .await
-                            .unwrap_err();
-                    } else {
-                        let _ = client.call().start(vmservice::Vm::Quit, ()).await;
-                    }
+                    client
+                        .call()
+                        .start(vmservice::Vm::TeardownVm, ())
+                        .await
+                        .unwrap();
+
+                    client
+                        .call()
+                        .start(vmservice::Vm::WaitVm, ())
+                        .await
+                        .unwrap_err();
                 }
                 1 => {
                     client
@@ -248,6 +244,37 @@ fn test_ttrpc_interface(
 
                     waiter.await.unwrap_err();
                 }
+                3 => {
+                    // The VM was never resumed, so the guest cannot answer
+                    // the shutdown request and the VM is torn down once the
+                    // timeout expires.
+                    let response = client
+                        .call()
+                        .start(
+                            vmservice::Vm::ShutdownVm,
+                            vmservice::ShutdownVmRequest {
+                                timeout_secs: 1,
+                                force: false,
+                            },
+                        )
+                        .await
+                        .unwrap();
+                    assert_eq!(
+                        response.result,
+                        vmservice::shutdown_vm_response::ShutdownResult::TimedOut as i32
+                    );
+
+                    // Powering off the VM completes the pending wait.
+                    waiter.await.unwrap();
+
+                    client
+                        .call()
+                        .start(vmservice::Vm::WaitVm, ())
+                        .await
+                        .unwrap_err();
+
+                    let _ = client.call().start(vmservice::Vm::Quit, ()).await;
+                }
                 _ => unreachable!(),
             }
 
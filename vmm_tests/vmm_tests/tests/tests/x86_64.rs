@@ -18,16 +18,20 @@
 use openvmm_defs::config::DeviceVtl;
 use openvmm_defs::config::VpciDeviceConfig;
 use petri::ApicMode;
+use petri::PetriHaltReason;
 use petri::PetriVmBuilder;
 use petri::PetriVmmBackend;
 use petri::ProcessorTopology;
+use petri::ResolvedArtifact;
 use petri::openvmm::OpenVmmPetriBackend;
 use petri::pipette::cmd;
 use petri_artifacts_common::tags::OsFlavor;
+use petri_artifacts_vmm_test::artifacts::test_vhd::GUEST_TEST_UEFI_EFI_X64;
 use virtio_resources::VirtioPciDeviceHandle;
 use virtio_resources::net::VirtioNetHandle;
 use vm_resource::IntoResource;
 use vmm_test_macros::openvmm_test;
+use vmm_test_macros::openvmm_test_no_agent;
 use vmm_test_macros::vmm_test;
 use vmm_test_macros::vmm_test_with;
 
@@ -183,6 +187,7 @@ async fn vpci_filter(config: PetriVmBuilder<OpenVmmPetriBackend>) -> anyhow::Res
                             max_io_queues: 1,
                             namespaces: Vec::new(),
                             requests: None,
+                            boot_partitions: None,
                         }
                         .into_resource(),
                     },
@@ -194,6 +199,7 @@ async fn vpci_filter(config: PetriVmBuilder<OpenVmmPetriBackend>) -> anyhow::Res
                                 max_queues: None,
                                 mac_address: MacAddress::new([0x00, 0x15, 0x5D, 0x12, 0x12, 0x12]),
                                 endpoint: NullHandle.into_resource(),
+                                busy_poll: false,
                             }
                             .into_resource(),
                         )
@@ -455,6 +461,56 @@ async fn virtio_rng_device(config: PetriVmBuilder<OpenVmmPetriBackend>) -> anyho
     Ok(())
 }
 
+/// Inject an NMI into the guest and verify that it is counted on the target
+/// processor.
+#[openvmm_test(linux_direct_x64)]
+async fn inject_nmi(config: PetriVmBuilder<OpenVmmPetriBackend>) -> anyhow::Result<()> {
+    let (mut vm, agent) = config
+        .with_processor_topology(ProcessorTopology {
+            vp_count: 2,
+            ..Default::default()
+        })
+        .run()
+        .await?;
+
+    let sh = agent.unix_shell();
+    // Returns the NMI count for VP 1 from /proc/interrupts.
+    let nmi_count = async || -> anyhow::Result<u64> {
+        let line = cmd!(sh, "grep NMI: /proc/interrupts").read().await?;
+        line.split_whitespace()
+            .nth(2)
+            .context("missing NMI count")?
+            .parse()
+            .context("invalid NMI count")
+    };
+
+    let before = nmi_count().await?;
+    vm.backend().inject_nmi(1).await?;
+    // The NMI is delivered asynchronously, so poll for it.
+    let mut after = before;
+    for _ in 0..10 {
+        after = nmi_count().await?;
+        if after > before {
+            break;
+        }
+        cmd!(sh, "sleep 1").run().await?;
+    }
+    assert!(after > before, "NMI was not delivered to VP 1");
+
+    vm.backend()
+        .inject_nmi(2)
+        .await
+        .expect_err("NMI to a nonexistent VP should fail");
+    vm.backend()
+        .inject_machine_check(2)
+        .await
+        .expect_err("machine check to a nonexistent VP should fail");
+
+    agent.power_off().await?;
+    vm.wait_for_clean_teardown().await?;
+    Ok(())
+}
+
 /// Boot Linux with guest memory backed by a file instead of anonymous RAM.
 ///
 /// This validates that the file-backed memory plumbing through petri works
@@ -565,3 +621,28 @@ async fn snapshot_save_to_disk(
 
     Ok(())
 }
+
+/// Network boot our guest-test UEFI image over PXE, from a host directory
+/// served by consomme's TFTP server.
+#[openvmm_test_no_agent(uefi_x64(none)[GUEST_TEST_UEFI_EFI_X64])]
+async fn guest_test_uefi_network_boot(
+    config: PetriVmBuilder<OpenVmmPetriBackend>,
+    (efi,): (ResolvedArtifact<GUEST_TEST_UEFI_EFI_X64>,),
+) -> anyhow::Result<()> {
+    let tftp_root = tempfile::tempdir()?;
+    std::fs::copy(efi.get(), tftp_root.path().join("bootx64.efi"))?;
+
+    let vm = config
+        .with_windows_secure_boot_template()
+        .modify_backend(|b| b.with_network_boot_nic(tftp_root.path(), "bootx64.efi"))
+        .run_without_agent()
+        .await?;
+
+    // As when booting it from disk, the image runs its tests and then lets
+    // its watchdog triple fault the VM, which only happens if it was loaded.
+    let halt_reason = vm.wait_for_teardown().await?;
+    if halt_reason.reason != PetriHaltReason::TripleFault {
+        anyhow::bail!("Expected TripleFault, got {halt_reason:?}");
+    }
+    Ok(())
+}
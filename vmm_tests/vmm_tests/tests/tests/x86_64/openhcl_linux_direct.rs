@@ -4,6 +4,7 @@
 //! Integration tests for x86_64 Linux direct boot with OpenHCL.
 
 use crate::x86_64::storage::new_test_vtl2_nvme_device;
+use get_resources::ged::Vtl2SettingsTestConfig;
 use guid::Guid;
 use memory_range::MemoryRange;
 use openvmm_defs::config::Vtl2BaseAddressType;
@@ -12,6 +13,7 @@
 use petri::PetriVmBuilder;
 use petri::ProcessorTopology;
 use petri::ResolvedArtifact;
+use petri::openvmm::MANA_INSTANCE;
 use petri::openvmm::OpenVmmPetriBackend;
 use petri::pipette::PipetteClient;
 use petri::pipette::cmd;
@@ -20,6 +22,7 @@
 use petri::vtl2_settings::Vtl2StorageBackingDeviceBuilder;
 use petri::vtl2_settings::Vtl2StorageControllerBuilder;
 use petri_artifacts_vmm_test::artifacts::openhcl_igvm::LATEST_LINUX_DIRECT_TEST_X64;
+use std::time::Duration;
 use vmm_test_macros::openvmm_test;
 use zerocopy::FromBytes;
 
@@ -79,6 +82,51 @@ async fn mana_nic_shared_pool(
     Ok(())
 }
 
+/// Test revoking and re-offering the VF of an OpenHCL MANA nic while the
+/// guest is generating traffic on it.
+///
+/// OpenHCL must move the guest off the VF when the host revokes it and bring
+/// the VF back when it is offered again, without the guest losing its
+/// network connection.
+#[openvmm_test(openhcl_linux_direct_x64)]
+async fn mana_nic_vf_failover(
+    config: PetriVmBuilder<OpenVmmPetriBackend>,
+) -> Result<(), anyhow::Error> {
+    let (mut vm, agent) = config
+        .with_vmbus_redirect(true)
+        .modify_backend(|b| b.with_nic())
+        .run()
+        .await?;
+
+    validate_mana_nic(&agent).await?;
+
+    // Repeatedly renew the DHCP lease (served by consomme) while the VF is
+    // revoked and offered again. Requests that are sent while the VF is gone
+    // are retried, but every renewal must eventually succeed.
+    let sh = agent.unix_shell();
+    let traffic = cmd!(
+        sh,
+        "sh -c 'for i in $(seq 50); do udhcpc -n -q -t 10 -T 1 -i eth0 || exit 1; done'"
+    )
+    .run();
+    let failover = async {
+        for _ in 0..2 {
+            vm.revoke_and_reoffer_openhcl_vf(MANA_INSTANCE).await?;
+        }
+        anyhow::Ok(())
+    };
+    let (traffic, failover) = futures::join!(traffic, failover);
+    failover?;
+    traffic?;
+
+    validate_mana_nic(&agent).await?;
+
+    agent.power_off().await?;
+    vm.wait_for_clean_teardown().await?;
+
+    Ok(())
+}
+
 /// Test an OpenHCL Linux direct VM with many NVMe devices assigned to VTL2 and vmbus relay.
 #[openvmm_test(openhcl_linux_direct_x64 [LATEST_LINUX_DIRECT_TEST_X64])]
 async fn many_nvme_devices_servicing_very_heavy(
@@ -490,3 +538,50 @@ async fn openhcl_linux_vtl2_mmio_self_allocate(
 
     Ok(())
 }
+
+/// Test that OpenHCL boots when the host is slow to deliver the initial VTL2
+/// settings, and applies later modifications after the host delays them.
+#[openvmm_test(openhcl_linux_direct_x64)]
+async fn vtl2_settings_delayed(
+    config: PetriVmBuilder<OpenVmmPetriBackend>,
+) -> Result<(), anyhow::Error> {
+    let (mut vm, agent) = config
+        .modify_backend(|b| {
+            b.with_vtl2_settings_test_config(Vtl2SettingsTestConfig::Delay(Duration::from_secs(5)))
+        })
+        .run()
+        .await?;
+
+    vm.modify_vtl2_settings(|_| {}).await?;
+
+    agent.power_off().await?;
+    vm.wait_for_clean_teardown().await?;
+
+    Ok(())
+}
+
+/// Test that OpenHCL rejects a truncated VTL2 settings modification and
+/// applies the modification when the host sends it again.
+#[openvmm_test(openhcl_linux_direct_x64)]
+async fn vtl2_settings_truncated(
+    config: PetriVmBuilder<OpenVmmPetriBackend>,
+) -> Result<(), anyhow::Error> {
+    let (mut vm, agent) = config
+        .modify_backend(|b| {
+            b.with_vtl2_settings_test_config(Vtl2SettingsTestConfig::TruncateFirstModify)
+        })
+        .run()
+        .await?;
+
+    let attempt = vm.modify_vtl2_settings(|_| {}).await;
+    assert!(
+        attempt.is_err(),
+        "truncated VTL2 settings were unexpectedly accepted"
+    );
+    vm.modify_vtl2_settings(|_| {}).await?;
+
+    agent.power_off().await?;
+    vm.wait_for_clean_teardown().await?;
+
+    Ok(())
+}
@@ -74,6 +74,7 @@ pub(crate) fn new_test_vtl2_nvme_device(
                 read_only: false,
             }],
             requests: None,
+            boot_partitions: None,
         }
         .into_resource(),
     }
@@ -230,6 +231,7 @@ async fn storvsp(config: PetriVmBuilder<OpenVmmPetriBackend>) -> Result<(), anyh
                         io_queue_depth: None,
                         requests: None,
                         poll_mode_queue_depth: None,
+                        max_protocol_version: None,
                     }
                     .into_resource(),
                 ));
@@ -579,6 +581,7 @@ async fn openhcl_linux_storvsp_dvd(
                         io_queue_depth: None,
                         requests: None,
                         poll_mode_queue_depth: None,
+                        max_protocol_version: None,
                     }
                     .into_resource(),
                 ));
@@ -805,6 +808,7 @@ const fn disk_sectors(index: u32) -> u64 {
                             })
                             .collect(),
                         requests: None,
+                        boot_partitions: None,
                     }
                     .into_resource(),
                 });
@@ -878,3 +882,115 @@ const fn disk_sectors(index: u32) -> u64 {
 
     Ok(())
 }
+
+/// Test a Linux direct VM with a VTL0 storvsp controller that is rescinded
+/// and re-offered while the guest has IO outstanding to it. The guest must
+/// tear down the old channel and rediscover the disk on the new one.
+#[openvmm_test(linux_direct_x64)]
+async fn storvsp_reoffer_during_io(
+    config: PetriVmBuilder<OpenVmmPetriBackend>,
+) -> Result<(), anyhow::Error> {
+    const SCSI_DISK_SECTORS: u64 = 0x4_0000;
+    const SECTOR_SIZE: u64 = 512;
+    const NUM_ITERATIONS: u32 = 3;
+    let vtl0_scsi_lun = 0;
+    let scsi_instance = Guid::new_random();
+
+    // See the assumptions in [`storvsp`].
+    static_assertions::const_assert!(SCSI_DISK_SECTORS * SECTOR_SIZE > 105 * 1024 * 1024);
+
+    let (mut vm, agent) = config
+        .modify_backend(move |b| {
+            b.with_custom_config(|c| {
+                c.vmbus_devices.push((
+                    DeviceVtl::Vtl0,
+                    ScsiControllerHandle {
+                        instance_id: scsi_instance,
+                        max_sub_channel_count: 1,
+                        devices: vec![ScsiDeviceAndPath {
+                            path: ScsiPath {
+                                path: 0,
+                                target: 0,
+                                lun: vtl0_scsi_lun as u8,
+                            },
+                            device: SimpleScsiDiskHandle {
+                                disk: LayeredDiskHandle::single_layer(RamDiskLayerHandle {
+                                    len: Some(SCSI_DISK_SECTORS * SECTOR_SIZE),
+                                    sector_size: None,
+                                })
+                                .into_resource(),
+                                read_only: false,
+                                parameters: Default::default(),
+                            }
+                            .into_resource(),
+                        }],
+                        io_queue_depth: None,
+                        requests: None,
+                        poll_mode_queue_depth: None,
+                        max_protocol_version: None,
+                    }
+                    .into_resource(),
+                ));
+            })
+        })
+        .run()
+        .await?;
+
+    let expected_devices = vec![ExpectedGuestDevice {
+        lun: vtl0_scsi_lun,
+        disk_size_sectors: SCSI_DISK_SECTORS as usize,
+        friendly_name: "scsi".to_string(),
+    }];
+
+    test_storage_linux(&agent, scsi_instance, expected_devices.clone()).await?;
+
+    let sh = agent.unix_shell();
+    for i in 0..NUM_ITERATIONS {
+        tracing::info!(iteration = i, "re-offering storvsp during IO");
+        let device = get_device_paths(&agent, scsi_instance, expected_devices.clone())
+            .await?
+            .remove(0);
+
+        // Read the disk in a loop until told to stop, so that IO is still
+        // outstanding whenever the re-offer lands. `io_started` is created
+        // once the first read completes, and `io_done` once the loop exits
+        // for any reason, so the waiter below cannot hang.
+        //
+        // The IO is expected to fail when the channel is rescinded out from
+        // under it; what matters is that the guest does not hang and that
+        // the disk comes back.
+        cmd!(sh, "rm -f /tmp/io_started /tmp/io_stop /tmp/io_done")
+            .run()
+            .await?;
+        let io_script = format!(
+            "while [ ! -e /tmp/io_stop ] && dd if={device} of=/dev/null bs=4k count=256 iflag=direct 2>/dev/null; \
+             do touch /tmp/io_started; done; touch /tmp/io_done"
+        );
+        let io = cmd!(sh, "sh -c {io_script}").run();
+        let reoffer = async {
+            cmd!(
+                sh,
+                "sh -c 'until [ -e /tmp/io_started ] || [ -e /tmp/io_done ]; do sleep 0.1; done'"
+            )
+            .run()
+            .await?;
+            cmd!(sh, "test -e /tmp/io_started")
+                .run()
+                .await
+                .context("guest IO failed before the re-offer")?;
+            let result = vm.backend().reoffer_vmbus_device(scsi_instance).await;
+            cmd!(sh, "touch /tmp/io_stop").run().await?;
+            result
+        };
+        let (io_result, reoffer_result) = futures::join!(io, reoffer);
+        reoffer_result.context("failed to re-offer storvsp")?;
+        tracing::info!(?io_result, "IO during re-offer completed");
+
+        test_storage_linux(&agent, scsi_instance, expected_devices.clone()).await?;
+    }
+
+    agent.power_off().await?;
+    vm.wait_for_clean_teardown().await?;
+
+    Ok(())
+}